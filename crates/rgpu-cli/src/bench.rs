@@ -0,0 +1,728 @@
+//! `rgpu bench`: end-to-end performance against a server.
+//!
+//! Connects the way the client daemon does and times what applications
+//! feel: round trips for a ping and a CUDA call, HtoD and DtoH throughput at
+//! a few sizes, kernel launches per second when pipelined the way the daemon
+//! sends them, and a Vulkan compute dispatch from submit to its fence. Every
+//! transport and compression codec asked for gets its own column, so
+//! settings can be compared side by side.
+
+use std::collections::BTreeSet;
+use std::time::{Duration, Instant};
+
+use rand::RngCore;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::messages::{Message, RequestId, PROTOCOL_VERSION};
+use rgpu_protocol::vulkan_commands::{
+    DeviceQueueCreateInfo, RecordedCommand, SerializedComputePipelineCreateInfo,
+    SerializedPipelineShaderStageCreateInfo, SerializedSubmitInfo, VulkanCommand, VulkanResponse,
+};
+use rgpu_protocol::wire::{self, Codec, CompressionSettings, CompressionStats};
+use rgpu_transport::quic::QuicConnection;
+
+/// Transfer sizes timed in each direction.
+const SIZES: [u64; 4] = [64 << 10, 1 << 20, 16 << 20, 64 << 20];
+
+/// Kernel launches sent per pipelined batch, as the daemon does between
+/// synchronizing calls.
+const LAUNCH_BATCH: usize = 100;
+
+/// An empty kernel, JIT-compiled by the server.
+const EMPTY_KERNEL_PTX: &str = "\
+.version 6.0
+.target sm_50
+.address_size 64
+
+.visible .entry rgpu_bench_empty()
+{
+\tret;
+}
+";
+
+/// An empty compute shader (`main`, local size 1x1x1) as SPIR-V words.
+const EMPTY_SHADER_SPIRV: &[u32] = &[
+    0x0723_0203, 0x0001_0000, 0, 5, 0,
+    0x0002_0011, 1, // OpCapability Shader
+    0x0003_000e, 0, 1, // OpMemoryModel Logical GLSL450
+    0x0005_000f, 5, 1, 0x6e69_616d, 0, // OpEntryPoint GLCompute %1 "main"
+    0x0006_0010, 1, 17, 1, 1, 1, // OpExecutionMode %1 LocalSize 1 1 1
+    0x0002_0013, 2, // %2 = OpTypeVoid
+    0x0003_0021, 3, 2, // %3 = OpTypeFunction %2
+    0x0005_0036, 2, 1, 0, 3, // %1 = OpFunction %2 None %3
+    0x0002_00f8, 4, // %4 = OpLabel
+    0x0001_00fd, // OpReturn
+    0x0001_0038, // OpFunctionEnd
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Transport {
+    Tcp,
+    Quic,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Compression {
+    Lz4,
+    Zstd,
+    None,
+}
+
+/// What the transferred buffers hold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Data {
+    /// Random bytes, which don't compress
+    Random,
+    /// Zeros, which compress as well as anything can
+    Zeros,
+}
+
+pub struct BenchOptions {
+    /// Round trips and dispatches timed per measurement
+    pub iterations: u32,
+    /// CUDA device ordinal and Vulkan physical device index
+    pub device: u32,
+    pub data: Data,
+}
+
+/// Rows of a column that succeed or fail together.
+struct Section {
+    name: &'static str,
+    labels: Vec<String>,
+    values: Result<Vec<String>, String>,
+}
+
+impl Section {
+    fn new(name: &'static str, labels: Vec<String>, values: anyhow::Result<Vec<String>>) -> Self {
+        Self {
+            name,
+            labels,
+            values: values.map_err(|e| e.to_string()),
+        }
+    }
+
+    fn failed(name: &'static str, labels: Vec<String>, error: String) -> Self {
+        Self {
+            name,
+            labels,
+            values: Err(error),
+        }
+    }
+}
+
+/// Run the benchmark once per transport and codec and print the results
+/// as a table.
+pub async fn run(
+    server: String,
+    token: String,
+    transports: Vec<Transport>,
+    compressions: Vec<Compression>,
+    options: BenchOptions,
+) -> anyhow::Result<()> {
+    let data = match options.data {
+        Data::Random => {
+            let mut data = vec![0u8; SIZES[SIZES.len() - 1] as usize];
+            rand::thread_rng().fill_bytes(&mut data);
+            data
+        }
+        Data::Zeros => vec![0u8; SIZES[SIZES.len() - 1] as usize],
+    };
+
+    let mut columns = Vec::new();
+    for &transport in &transports {
+        for &compression in &compressions {
+            let label = format!("{}/{}", name(transport), codec_name(compression));
+            eprintln!("running {} ...", label);
+            let sections = match BenchConnection::open(&server, &token, transport, compression).await {
+                Ok(mut conn) => measure(&mut conn, &options, &data).await,
+                Err(e) => vec![Section::failed("connect", vec!["Connect".to_string()], e.to_string())],
+            };
+            columns.push((label, sections));
+        }
+    }
+
+    println!();
+    println!(
+        "rgpu bench: {}, {} iterations, {} data",
+        server,
+        options.iterations,
+        match options.data {
+            Data::Random => "random",
+            Data::Zeros => "zero",
+        }
+    );
+    println!();
+    print_table(&columns);
+    println!();
+    println!("  The codec applies to what the benchmark sends (HtoD); responses are");
+    println!("  compressed as the server's [server.compression] says.");
+    Ok(())
+}
+
+fn name(transport: Transport) -> &'static str {
+    match transport {
+        Transport::Tcp => "tcp",
+        Transport::Quic => "quic",
+    }
+}
+
+fn codec_name(compression: Compression) -> &'static str {
+    match compression {
+        Compression::Lz4 => "lz4",
+        Compression::Zstd => "zstd",
+        Compression::None => "none",
+    }
+}
+
+fn print_table(columns: &[(String, Vec<Section>)]) {
+    // Rows in the order they were first measured; a column that failed to
+    // connect has none of its own.
+    let mut labels: Vec<String> = Vec::new();
+    for (_, sections) in columns {
+        for label in sections.iter().flat_map(|s| &s.labels) {
+            if !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
+    }
+    let width = labels.iter().map(|l| l.len()).max().unwrap_or(0) + 2;
+
+    print!("  {:<width$}", "", width = width);
+    for (column, _) in columns {
+        print!("{:>14}", column);
+    }
+    println!();
+    for label in &labels {
+        print!("  {:<width$}", label, width = width);
+        for (_, sections) in columns {
+            let cell = sections
+                .iter()
+                .find_map(|s| {
+                    let row = s.labels.iter().position(|l| l == label)?;
+                    Some(match &s.values {
+                        Ok(values) => values[row].clone(),
+                        Err(_) => "failed".to_string(),
+                    })
+                })
+                .unwrap_or_else(|| "-".to_string());
+            print!("{:>14}", cell);
+        }
+        println!();
+    }
+
+    let mut errors = BTreeSet::new();
+    for (column, sections) in columns {
+        for section in sections {
+            if let Err(e) = &section.values {
+                errors.insert(format!("{}: {}: {}", column, section.name, e));
+            }
+        }
+    }
+    if !errors.is_empty() {
+        println!();
+        for error in errors {
+            println!("  {}", error);
+        }
+    }
+}
+
+/// All measurements over one connection.
+async fn measure(conn: &mut BenchConnection, options: &BenchOptions, data: &[u8]) -> Vec<Section> {
+    let iterations = options.iterations.max(1);
+    let mut sections = Vec::new();
+
+    let ping = conn.round_trips(iterations, |_| Message::Ping).await;
+    sections.push(Section::new("ping", latency_labels("Ping"), ping.map(latency_values)));
+
+    let call = conn
+        .round_trips(iterations, |request_id| Message::CudaCommand {
+            request_id,
+            command: CudaCommand::DeviceGetCount,
+            deadline_ms: None,
+        })
+        .await;
+    sections.push(Section::new("CUDA call", latency_labels("CUDA call"), call.map(latency_values)));
+
+    let transfer_labels: Vec<String> = ["HtoD", "DtoH"]
+        .iter()
+        .flat_map(|direction| SIZES.map(|size| format!("{} {}", direction, size_label(size))))
+        .collect();
+    let launch_labels = vec!["Kernel launches".to_string()];
+    match conn.cuda_setup(options.device).await {
+        Ok(buffer) => {
+            let transfers = conn.transfers(buffer, data).await;
+            sections.push(Section::new("transfers", transfer_labels, transfers));
+            let launches = conn.launch_rate(iterations).await;
+            sections.push(Section::new("kernel launches", launch_labels, launches.map(|rate| vec![format!("{:.0}/s", rate)])));
+        }
+        Err(e) => {
+            sections.push(Section::failed("transfers", transfer_labels, e.to_string()));
+            sections.push(Section::failed("kernel launches", launch_labels, e.to_string()));
+        }
+    }
+
+    let dispatch = conn.vulkan_dispatches(options.device, iterations).await;
+    sections.push(Section::new("Vulkan dispatch", latency_labels("Vulkan dispatch"), dispatch.map(latency_values)));
+    sections
+}
+
+fn latency_labels(what: &str) -> Vec<String> {
+    vec![format!("{} (median)", what), format!("{} (p99)", what)]
+}
+
+fn latency_values(mut times: Vec<Duration>) -> Vec<String> {
+    times.sort();
+    let at = |fraction: f64| times[((times.len() - 1) as f64 * fraction).round() as usize];
+    vec![duration_label(at(0.5)), duration_label(at(0.99))]
+}
+
+fn duration_label(duration: Duration) -> String {
+    match duration.as_micros() {
+        micros if micros < 1000 => format!("{} µs", micros),
+        _ => format!("{:.2} ms", duration.as_secs_f64() * 1000.0),
+    }
+}
+
+fn size_label(size: u64) -> String {
+    match size {
+        size if size >= 1 << 20 => format!("{} MiB", size >> 20),
+        size => format!("{} KiB", size >> 10),
+    }
+}
+
+/// Times a size is transferred: enough for about 64 MiB, within limits.
+fn repetitions(size: u64) -> u32 {
+    ((64 << 20) / size).clamp(3, 256) as u32
+}
+
+enum Link {
+    Tcp {
+        reader: OwnedReadHalf,
+        writer: OwnedWriteHalf,
+    },
+    Quic(QuicConnection),
+}
+
+/// An authenticated session with the server.
+struct BenchConnection {
+    link: Link,
+    /// Compression of what the benchmark sends
+    compression: CompressionStats,
+    next_request_id: u64,
+}
+
+impl BenchConnection {
+    async fn open(address: &str, token: &str, transport: Transport, compression: Compression) -> anyhow::Result<Self> {
+        let link = match transport {
+            Transport::Tcp => {
+                let (reader, writer) = TcpStream::connect(address).await?.into_split();
+                Link::Tcp { reader, writer }
+            }
+            Transport::Quic => Link::Quic(rgpu_transport::quic::connect_quic_client(address).await?),
+        };
+        let stats = CompressionStats::default();
+        stats.configure(CompressionSettings {
+            codec: match compression {
+                Compression::Lz4 => Codec::Lz4,
+                Compression::Zstd => Codec::Zstd,
+                Compression::None => Codec::None,
+            },
+            ..Default::default()
+        });
+        let mut conn = Self {
+            link,
+            compression: stats,
+            next_request_id: 1,
+        };
+
+        let hello = Message::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            name: "RGPU Bench".to_string(),
+            challenge: None,
+        };
+        let challenge = match conn.exchange(&hello).await? {
+            Message::Hello { challenge, .. } => challenge.unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        let auth_msg = Message::Authenticate {
+            token: token.to_string(),
+            challenge_response: rgpu_transport::auth::compute_challenge_response(token, &challenge),
+        };
+        match conn.exchange(&auth_msg).await? {
+            Message::AuthResult { success: true, .. } => Ok(conn),
+            Message::AuthResult { error_message, .. } => {
+                anyhow::bail!("authentication failed: {}", error_message.unwrap_or_default())
+            }
+            _ => anyhow::bail!("unexpected response during auth"),
+        }
+    }
+
+    async fn exchange(&mut self, msg: &Message) -> anyhow::Result<Message> {
+        match &mut self.link {
+            Link::Tcp { reader, writer } => {
+                let frame = wire::encode_message_tracked(msg, wire::request_tag(msg), &self.compression)?;
+                writer.write_all(&frame).await?;
+                crate::read_message(reader).await
+            }
+            Link::Quic(conn) => Ok(conn.send_and_receive_tracked(msg, None, &self.compression).await?),
+        }
+    }
+
+    fn request_id(&mut self) -> RequestId {
+        self.next_request_id += 1;
+        RequestId(self.next_request_id)
+    }
+
+    async fn cuda(&mut self, command: CudaCommand) -> anyhow::Result<CudaResponse> {
+        let request_id = self.request_id();
+        let msg = Message::CudaCommand {
+            request_id,
+            command,
+            deadline_ms: None,
+        };
+        cuda_result(self.exchange(&msg).await?)
+    }
+
+    async fn vulkan(&mut self, command: VulkanCommand) -> anyhow::Result<VulkanResponse> {
+        let request_id = self.request_id();
+        let msg = Message::VulkanCommand {
+            request_id,
+            command,
+            deadline_ms: None,
+        };
+        match self.exchange(&msg).await? {
+            Message::VulkanResponse {
+                response: VulkanResponse::Error { code, message },
+                ..
+            } => anyhow::bail!("Vulkan error {}: {}", code, message),
+            Message::VulkanResponse { response, .. } => Ok(response),
+            other => unexpected(&other),
+        }
+    }
+
+    /// Time `iterations` round trips of the message `make` builds.
+    async fn round_trips(
+        &mut self,
+        iterations: u32,
+        make: impl Fn(RequestId) -> Message,
+    ) -> anyhow::Result<Vec<Duration>> {
+        let mut times = Vec::with_capacity(iterations as usize);
+        for _ in 0..iterations {
+            let msg = make(self.request_id());
+            let started = Instant::now();
+            let response = self.exchange(&msg).await?;
+            times.push(started.elapsed());
+            if !matches!(response, Message::Pong) {
+                cuda_result(response)?;
+            }
+        }
+        Ok(times)
+    }
+
+    /// Create a context on `device` and a buffer for the largest transfer.
+    async fn cuda_setup(&mut self, device: u32) -> anyhow::Result<NetworkHandle> {
+        let device = match self.cuda(CudaCommand::DeviceGet { ordinal: device as i32 }).await? {
+            CudaResponse::Device(device) => device,
+            other => return unexpected(&other),
+        };
+        self.cuda(CudaCommand::CtxCreate { flags: 0, device }).await?;
+        match self
+            .cuda(CudaCommand::MemAlloc {
+                byte_size: SIZES[SIZES.len() - 1],
+            })
+            .await?
+        {
+            CudaResponse::MemAllocated(buffer) => Ok(buffer),
+            other => unexpected(&other),
+        }
+    }
+
+    /// Throughput of HtoD copies of each size, then of DtoH copies.
+    async fn transfers(&mut self, buffer: NetworkHandle, data: &[u8]) -> anyhow::Result<Vec<String>> {
+        let mut rates = Vec::new();
+        for size in SIZES {
+            let msg = Message::CudaCommand {
+                request_id: self.request_id(),
+                command: CudaCommand::MemcpyHtoD {
+                    dst: buffer,
+                    src_data: data[..size as usize].to_vec(),
+                    byte_count: size,
+                },
+                deadline_ms: None,
+            };
+            rates.push(self.throughput(&msg, size).await?);
+        }
+        for size in SIZES {
+            let msg = Message::CudaCommand {
+                request_id: self.request_id(),
+                command: CudaCommand::MemcpyDtoH {
+                    src: buffer,
+                    byte_count: size,
+                },
+                deadline_ms: None,
+            };
+            rates.push(self.throughput(&msg, size).await?);
+        }
+        Ok(rates)
+    }
+
+    async fn throughput(&mut self, msg: &Message, size: u64) -> anyhow::Result<String> {
+        let repetitions = repetitions(size);
+        let started = Instant::now();
+        for _ in 0..repetitions {
+            cuda_result(self.exchange(msg).await?)?;
+        }
+        let bytes = size as f64 * repetitions as f64;
+        Ok(format!("{:.0} MB/s", bytes / started.elapsed().as_secs_f64() / 1e6))
+    }
+
+    /// Kernel launches per second, sent in pipelined batches that each end
+    /// with a synchronize.
+    async fn launch_rate(&mut self, iterations: u32) -> anyhow::Result<f64> {
+        let mut image = EMPTY_KERNEL_PTX.as_bytes().to_vec();
+        image.push(0);
+        let module = match self.cuda(CudaCommand::ModuleLoadData { image }).await? {
+            CudaResponse::Module(module) => module,
+            other => return unexpected(&other),
+        };
+        let func = match self
+            .cuda(CudaCommand::ModuleGetFunction {
+                module,
+                name: "rgpu_bench_empty".to_string(),
+            })
+            .await?
+        {
+            CudaResponse::Function(handle) | CudaResponse::FunctionWithParams { handle, .. } => handle,
+            other => return unexpected(&other),
+        };
+        let launch = CudaCommand::LaunchKernel {
+            func,
+            grid_dim: [1, 1, 1],
+            block_dim: [1, 1, 1],
+            shared_mem_bytes: 0,
+            stream: NetworkHandle::null_stream(),
+            kernel_params: Vec::new(),
+        };
+
+        let batches = iterations.div_ceil(10).max(1);
+        let started = Instant::now();
+        for _ in 0..batches {
+            let msg = Message::CudaPipelined {
+                request_id: self.request_id(),
+                batch: vec![launch.clone(); LAUNCH_BATCH],
+                command: CudaCommand::CtxSynchronize,
+            };
+            cuda_result(self.exchange(&msg).await?)?;
+        }
+        Ok((batches as usize * LAUNCH_BATCH) as f64 / started.elapsed().as_secs_f64())
+    }
+
+    /// Times from submitting an empty compute dispatch to its fence
+    /// signalling.
+    async fn vulkan_dispatches(&mut self, device: u32, iterations: u32) -> anyhow::Result<Vec<Duration>> {
+        let instance = match self
+            .vulkan(VulkanCommand::CreateInstance {
+                app_name: Some("rgpu bench".to_string()),
+                app_version: 0,
+                engine_name: None,
+                engine_version: 0,
+                api_version: 1 << 22,
+                enabled_extensions: Vec::new(),
+                enabled_layers: Vec::new(),
+            })
+            .await?
+        {
+            VulkanResponse::InstanceCreated { handle } => handle,
+            other => return unexpected(&other),
+        };
+        let physical_device = match self.vulkan(VulkanCommand::EnumeratePhysicalDevices { instance }).await? {
+            VulkanResponse::PhysicalDevices { handles } => match handles.get(device as usize) {
+                Some(&handle) => handle,
+                None => anyhow::bail!("no Vulkan device {}", device),
+            },
+            other => return unexpected(&other),
+        };
+        let family = match self
+            .vulkan(VulkanCommand::GetPhysicalDeviceQueueFamilyProperties { physical_device })
+            .await?
+        {
+            // VK_QUEUE_COMPUTE_BIT
+            VulkanResponse::QueueFamilyProperties { families } => match families.iter().position(|f| f.queue_flags & 0x2 != 0) {
+                Some(family) => family as u32,
+                None => anyhow::bail!("no compute queue"),
+            },
+            other => return unexpected(&other),
+        };
+        let device = match self
+            .vulkan(VulkanCommand::CreateDevice {
+                physical_device,
+                queue_create_infos: vec![DeviceQueueCreateInfo {
+                    queue_family_index: family,
+                    queue_priorities: vec![1.0],
+                }],
+                enabled_extensions: Vec::new(),
+                enabled_features: None,
+                p_next: Vec::new(),
+            })
+            .await?
+        {
+            VulkanResponse::DeviceCreated { handle } => handle,
+            other => return unexpected(&other),
+        };
+        let queue = match self
+            .vulkan(VulkanCommand::GetDeviceQueue {
+                device,
+                queue_family_index: family,
+                queue_index: 0,
+            })
+            .await?
+        {
+            VulkanResponse::QueueRetrieved { handle } => handle,
+            other => return unexpected(&other),
+        };
+
+        let code = EMPTY_SHADER_SPIRV
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        let module = match self.vulkan(VulkanCommand::CreateShaderModule { device, code }).await? {
+            VulkanResponse::ShaderModuleCreated { handle } => handle,
+            other => return unexpected(&other),
+        };
+        let layout = match self
+            .vulkan(VulkanCommand::CreatePipelineLayout {
+                device,
+                set_layouts: Vec::new(),
+                push_constant_ranges: Vec::new(),
+            })
+            .await?
+        {
+            VulkanResponse::PipelineLayoutCreated { handle } => handle,
+            other => return unexpected(&other),
+        };
+        let pipeline = match self
+            .vulkan(VulkanCommand::CreateComputePipelines {
+                device,
+                pipeline_cache: None,
+                create_infos: vec![SerializedComputePipelineCreateInfo {
+                    stage: SerializedPipelineShaderStageCreateInfo {
+                        module,
+                        entry_point: "main".to_string(),
+                        // VK_SHADER_STAGE_COMPUTE_BIT
+                        stage: 0x20,
+                    },
+                    layout,
+                    flags: 0,
+                }],
+            })
+            .await?
+        {
+            VulkanResponse::PipelinesCreated { handles } if !handles.is_empty() => handles[0],
+            other => return unexpected(&other),
+        };
+        let command_pool = match self
+            .vulkan(VulkanCommand::CreateCommandPool {
+                device,
+                queue_family_index: family,
+                flags: 0,
+            })
+            .await?
+        {
+            VulkanResponse::CommandPoolCreated { handle } => handle,
+            other => return unexpected(&other),
+        };
+        let command_buffer = match self
+            .vulkan(VulkanCommand::AllocateCommandBuffers {
+                device,
+                command_pool,
+                level: 0,
+                count: 1,
+            })
+            .await?
+        {
+            VulkanResponse::CommandBuffersAllocated { handles } if !handles.is_empty() => handles[0],
+            other => return unexpected(&other),
+        };
+        self.vulkan(VulkanCommand::SubmitRecordedCommands {
+            command_buffer,
+            secondary: None,
+            commands: vec![
+                RecordedCommand::BindPipeline {
+                    // VK_PIPELINE_BIND_POINT_COMPUTE
+                    pipeline_bind_point: 1,
+                    pipeline,
+                },
+                RecordedCommand::Dispatch {
+                    group_count_x: 1,
+                    group_count_y: 1,
+                    group_count_z: 1,
+                },
+            ],
+        })
+        .await?;
+        let fence = match self.vulkan(VulkanCommand::CreateFence { device, signaled: false }).await? {
+            VulkanResponse::FenceCreated { handle } => handle,
+            other => return unexpected(&other),
+        };
+
+        let mut times = Vec::with_capacity(iterations as usize);
+        for _ in 0..iterations {
+            let started = Instant::now();
+            self.vulkan(VulkanCommand::QueueSubmit {
+                queue,
+                submits: vec![SerializedSubmitInfo {
+                    wait_semaphores: Vec::new(),
+                    wait_dst_stage_masks: Vec::new(),
+                    command_buffers: vec![command_buffer],
+                    signal_semaphores: Vec::new(),
+                    wait_semaphore_values: Vec::new(),
+                    signal_semaphore_values: Vec::new(),
+                    p_next: Vec::new(),
+                }],
+                fence: Some(fence),
+            })
+            .await?;
+            match self
+                .vulkan(VulkanCommand::WaitForFences {
+                    device,
+                    fences: vec![fence],
+                    wait_all: true,
+                    timeout_ns: 5_000_000_000,
+                })
+                .await?
+            {
+                VulkanResponse::FenceWaitResult { result: 0 } | VulkanResponse::Success => {}
+                other => return unexpected(&other),
+            }
+            times.push(started.elapsed());
+            self.vulkan(VulkanCommand::ResetFences {
+                device,
+                fences: vec![fence],
+            })
+            .await?;
+        }
+        Ok(times)
+    }
+}
+
+fn cuda_result(msg: Message) -> anyhow::Result<CudaResponse> {
+    match msg {
+        Message::CudaResponse {
+            response: CudaResponse::Error { code, message },
+            ..
+        } => anyhow::bail!("CUDA error {}: {}", code, message),
+        Message::CudaResponse { response, .. } => Ok(response),
+        Message::Unsupported { reason, .. } => anyhow::bail!("not supported by the server: {}", reason),
+        other => unexpected(&other),
+    }
+}
+
+fn unexpected<T>(response: &impl std::fmt::Debug) -> anyhow::Result<T> {
+    let response = format!("{:?}", response);
+    let name = response.split(|c: char| !c.is_alphanumeric()).next().unwrap_or_default();
+    anyhow::bail!("unexpected response {}", name)
+}
@@ -4,6 +4,10 @@
 use clap::{Parser, Subcommand};
 use tracing::info;
 
+mod bench;
+mod shell;
+mod stats;
+mod user_service;
 mod verify;
 
 #[cfg(windows)]
@@ -51,6 +55,11 @@ enum Commands {
         #[arg(long)]
         key: Option<String>,
 
+        /// Also accept WebSocket connections on this port, for clients
+        /// behind a reverse proxy
+        #[arg(long)]
+        websocket_port: Option<u16>,
+
         /// Configuration file path (auto-discovers from system location if not specified)
         #[arg(short, long)]
         config: Option<String>,
@@ -66,7 +75,8 @@ enum Commands {
 
     /// Start the RGPU client daemon (connects to servers and exposes remote GPUs locally)
     Client {
-        /// Server address(es) to connect to (host:port)
+        /// Server address(es) to connect to (host:port, or a ws:// or wss://
+        /// URL to go through a WebSocket proxy)
         #[arg(short, long)]
         server: Vec<String>,
 
@@ -85,6 +95,11 @@ enum Commands {
         /// Run as a Windows service (used by the service control manager)
         #[arg(long, hide = true)]
         service: bool,
+
+        /// Install or remove a per-user service that keeps the daemon available
+        /// (systemd user unit on Linux, logon scheduled task on Windows)
+        #[arg(long, value_enum)]
+        user_service: Option<user_service::UserServiceAction>,
     },
 
     /// Generate an authentication token
@@ -103,6 +118,61 @@ enum Commands {
         /// Authentication token
         #[arg(short, long, default_value = "")]
         token: String,
+
+        /// Also show PCIe/NVLink connections between GPUs and their NUMA nodes
+        #[arg(long)]
+        topology: bool,
+    },
+
+    /// Show server metrics, including connected sessions with their names and labels
+    Stats {
+        /// Server address(es) to query (default: the servers in the config file)
+        #[arg(short, long)]
+        server: Vec<String>,
+
+        /// Authentication token for --server
+        #[arg(short, long, default_value = "")]
+        token: String,
+
+        /// Configuration file path (auto-discovers from system location if not specified)
+        #[arg(short, long)]
+        config: Option<String>,
+
+        /// Print in the Prometheus text exposition format
+        #[arg(long)]
+        prometheus: bool,
+    },
+
+    /// Measure latency, transfer throughput, kernel launch rate and Vulkan
+    /// dispatch latency against a server, per transport and compression codec
+    Bench {
+        /// Server address (host:port)
+        #[arg(short, long)]
+        server: String,
+
+        /// Authentication token
+        #[arg(short, long, default_value = "")]
+        token: String,
+
+        /// Transports to compare, comma-separated
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "tcp")]
+        transport: Vec<bench::Transport>,
+
+        /// Compression codecs to compare for what the benchmark sends, comma-separated
+        #[arg(long, value_enum, value_delimiter = ',', default_value = "lz4")]
+        compression: Vec<bench::Compression>,
+
+        /// Round trips and dispatches timed per measurement
+        #[arg(short = 'n', long, default_value_t = 100)]
+        iterations: u32,
+
+        /// CUDA device ordinal and Vulkan device index on the server
+        #[arg(long, default_value_t = 0)]
+        device: u32,
+
+        /// Contents of the transferred buffers
+        #[arg(long, value_enum, default_value = "random")]
+        data: bench::Data,
     },
 
     /// Verify the RGPU client installation (config, daemon, drivers, connectivity)
@@ -116,6 +186,36 @@ enum Commands {
         json: bool,
     },
 
+    /// Start a shell (or run one command) with RGPU interposition configured
+    Shell {
+        /// Run this command instead of an interactive shell
+        #[arg(long)]
+        run: Option<String>,
+
+        /// Print the environment as shell `export` lines instead of spawning
+        #[arg(long, conflicts_with = "run")]
+        print_env: bool,
+    },
+
+    /// Run a program with RGPU interposition configured
+    Run {
+        /// Set an environment variable for the program (repeatable)
+        #[arg(short, long = "env", value_name = "KEY=VALUE", value_parser = shell::parse_env)]
+        env: Vec<(String, String)>,
+
+        /// Session name shown in `rgpu stats` and the UI
+        #[arg(short, long)]
+        name: Option<String>,
+
+        /// Also interpose the CUDA runtime, for programs whose runtime bypasses the driver
+        #[arg(long)]
+        cudart: bool,
+
+        /// The program and its arguments
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "PROGRAM")]
+        program: Vec<String>,
+    },
+
     /// Launch the RGPU desktop GUI
     Ui {
         /// Server address(es) to monitor (host:port)
@@ -153,6 +253,7 @@ async fn main() -> anyhow::Result<()> {
             bind,
             cert,
             key,
+            websocket_port,
             config,
             pid_file,
             service,
@@ -172,11 +273,14 @@ async fn main() -> anyhow::Result<()> {
             let config = config.unwrap_or_else(rgpu_core::config::default_config_path);
             info!("starting RGPU server on {}:{} (config: {})", bind, port, config);
 
-            let mut server_config = rgpu_core::config::ServerConfig::default();
-            server_config.port = port;
-            server_config.bind = bind;
-            server_config.cert_path = cert;
-            server_config.key_path = key;
+            let server_config = rgpu_core::config::ServerConfig {
+                port,
+                bind,
+                cert_path: cert,
+                key_path: key,
+                websocket_port,
+                ..Default::default()
+            };
 
             // Load tokens from config if available
             let rgpu_config = rgpu_core::config::RgpuConfig::load_or_default(&config);
@@ -201,6 +305,7 @@ async fn main() -> anyhow::Result<()> {
             config,
             pid_file,
             service,
+            user_service,
         }) => {
             #[cfg(windows)]
             if service {
@@ -210,6 +315,11 @@ async fn main() -> anyhow::Result<()> {
             #[cfg(not(windows))]
             let _ = service;
 
+            if let Some(action) = user_service {
+                user_service::run(action, config)?;
+                return Ok(());
+            }
+
             if let Some(ref path) = pid_file {
                 std::fs::write(path, std::process::id().to_string())?;
             }
@@ -222,10 +332,11 @@ async fn main() -> anyhow::Result<()> {
             // Add servers from CLI args
             for addr in server {
                 client_config.servers.push(rgpu_core::config::ServerEndpoint {
+                    transport: rgpu_core::config::TransportMode::for_address(&addr),
                     address: addr,
                     token: token.clone(),
                     ca_cert: None,
-                    transport: rgpu_core::config::TransportMode::default(),
+                    rdma: Default::default(),
                 });
             }
 
@@ -234,6 +345,23 @@ async fn main() -> anyhow::Result<()> {
             client_config.servers.extend(rgpu_config.client.servers);
             client_config.include_local_gpus = rgpu_config.client.include_local_gpus;
             client_config.gpu_ordering = rgpu_config.client.gpu_ordering;
+            client_config.gpu_uuids = rgpu_config.client.gpu_uuids;
+            client_config.stable_gpu_indices = rgpu_config.client.stable_gpu_indices;
+            client_config.breadcrumb_depth = rgpu_config.client.breadcrumb_depth;
+            client_config.breadcrumb_dir = rgpu_config.client.breadcrumb_dir;
+            client_config.mirror = rgpu_config.client.mirror;
+            client_config.readback_diff = rgpu_config.client.readback_diff;
+            client_config.readback_diff_cache_mb = rgpu_config.client.readback_diff_cache_mb;
+            client_config.dtoh_prefetch = rgpu_config.client.dtoh_prefetch;
+            client_config.spill_threshold_mb = rgpu_config.client.spill_threshold_mb;
+            client_config.spill_dir = rgpu_config.client.spill_dir;
+            client_config.session_name = rgpu_config.client.session_name;
+            client_config.session_labels = rgpu_config.client.session_labels;
+            client_config.compression = rgpu_config.client.compression;
+            client_config.ipc_shared_memory_mb = rgpu_config.client.ipc_shared_memory_mb;
+            client_config.failover = rgpu_config.client.failover;
+            client_config.health_check_secs = rgpu_config.client.health_check_secs;
+            client_config.placement = rgpu_config.client.placement;
 
             if client_config.servers.is_empty() && !client_config.include_local_gpus {
                 anyhow::bail!("no servers configured and include_local_gpus is false. Use --server or add servers to rgpu.toml");
@@ -295,12 +423,47 @@ async fn main() -> anyhow::Result<()> {
             rgpu_ui::launch_ui(all_servers, config, poll_interval)?;
         }
 
+        Some(Commands::Bench {
+            server,
+            token,
+            transport,
+            compression,
+            iterations,
+            device,
+            data,
+        }) => {
+            let options = bench::BenchOptions {
+                iterations,
+                device,
+                data,
+            };
+            bench::run(server, token, transport, compression, options).await?;
+        }
+
         Some(Commands::Verify { config, json }) => {
             let config_path = config.unwrap_or_else(rgpu_core::config::default_config_path);
             verify::run_verify(&config_path, json).await?;
         }
 
-        Some(Commands::Info { server, token }) => {
+        Some(Commands::Shell { run, print_env }) => {
+            shell::run_shell(run, print_env)?;
+        }
+
+        Some(Commands::Run {
+            env,
+            name,
+            cudart,
+            program,
+        }) => {
+            let options = shell::RunOptions {
+                env,
+                session_name: name,
+                cudart,
+            };
+            shell::run_program(program, options)?;
+        }
+
+        Some(Commands::Info { server, token, topology }) => {
             info!("querying GPU info from {}", server);
 
             use tokio::io::AsyncWriteExt;
@@ -352,6 +515,9 @@ async fn main() -> anyhow::Result<()> {
                     for (i, gpu) in available_gpus.iter().enumerate() {
                         println!("  GPU {}: {}", i, gpu.device_name);
                         println!("    Type:     {:?}", gpu.device_type);
+                        if let Some(uuid) = gpu.uuid_string() {
+                            println!("    UUID:     {}", uuid);
+                        }
                         println!(
                             "    VRAM:     {} MB",
                             gpu.total_memory / (1024 * 1024)
@@ -361,8 +527,20 @@ async fn main() -> anyhow::Result<()> {
                         if let Some((maj, min)) = gpu.cuda_compute_capability {
                             println!("    Compute:  {}.{}", maj, min);
                         }
+                        println!("    Sharing:  {}", gpu.scheduling.describe());
+                        if topology {
+                            if let Some(bus_id) = &gpu.topology.pci_bus_id {
+                                println!("    PCI:      {}", bus_id);
+                            }
+                            if let Some(node) = gpu.topology.numa_node {
+                                println!("    NUMA:     {}", node);
+                            }
+                        }
                         println!();
                     }
+                    if topology {
+                        print_topology(&available_gpus);
+                    }
                 }
                 rgpu_protocol::messages::Message::AuthResult {
                     success: false,
@@ -380,6 +558,26 @@ async fn main() -> anyhow::Result<()> {
             }
         }
 
+        Some(Commands::Stats {
+            server,
+            token,
+            config,
+            prometheus,
+        }) => {
+            let servers: Vec<(String, String)> = if server.is_empty() {
+                let config = config.unwrap_or_else(rgpu_core::config::default_config_path);
+                rgpu_core::config::RgpuConfig::load_or_default(&config)
+                    .client
+                    .servers
+                    .iter()
+                    .map(|s| (s.address.clone(), s.token.clone()))
+                    .collect()
+            } else {
+                server.into_iter().map(|s| (s, token.clone())).collect()
+            };
+            stats::run(servers, prometheus).await?;
+        }
+
         None => {
             // Default: launch UI when no subcommand is given (e.g. double-click on Windows/macOS)
             let config = rgpu_core::config::default_config_path();
@@ -398,6 +596,56 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Connection matrix between GPUs, like `nvidia-smi topo -m`.
+fn print_topology(gpus: &[rgpu_protocol::gpu_info::GpuInfo]) {
+    if gpus.len() < 2 {
+        return;
+    }
+    println!("Topology:");
+    print!("        ");
+    for i in 0..gpus.len() {
+        print!("{:>8}", format!("GPU{}", i));
+    }
+    println!("{:>8}", "NUMA");
+    for (i, gpu) in gpus.iter().enumerate() {
+        print!("  {:<6}", format!("GPU{}", i));
+        for peer in gpus {
+            let cell = if peer.server_device_index == gpu.server_device_index {
+                "X".to_string()
+            } else {
+                match gpu.topology.link_to(peer.server_device_index) {
+                    Some(link) if link.nvlink_count > 0 => format!("NV{}", link.nvlink_count),
+                    Some(link) => link.path.label().to_string(),
+                    None => "?".to_string(),
+                }
+            };
+            print!("{:>8}", cell);
+        }
+        let numa = gpu.topology.numa_node.map(|n| n.to_string()).unwrap_or_else(|| "-".to_string());
+        println!("{:>8}", numa);
+    }
+    println!();
+    println!("  NV#  = connected by # NVLink links");
+    println!("  PIX  = behind the same PCIe switch");
+    println!("  PHB  = through a PCIe host bridge");
+    println!("  NODE = across host bridges within a NUMA node");
+    println!("  SYS  = across NUMA nodes");
+    println!();
+    for (i, gpu) in gpus.iter().enumerate() {
+        for (j, peer) in gpus.iter().enumerate().skip(i + 1) {
+            if let Some(link) = gpu.topology.link_to(peer.server_device_index).filter(|l| l.p2p_access) {
+                println!(
+                    "  GPU{} <-> GPU{}: peer access{}, performance rank {}",
+                    i,
+                    j,
+                    if link.p2p_atomics { " with native atomics" } else { "" },
+                    link.performance_rank
+                );
+            }
+        }
+    }
+}
+
 async fn read_message<R: tokio::io::AsyncRead + Unpin>(
     reader: &mut R,
 ) -> anyhow::Result<rgpu_protocol::messages::Message> {
@@ -0,0 +1,298 @@
+//! `rgpu shell` — run a shell or a single command with RGPU interposition enabled.
+//!
+//! Locates the CUDA and NVML interpose libraries and the Vulkan ICD manifest,
+//! then spawns the child with `LD_PRELOAD` (or `DYLD_INSERT_LIBRARIES` on macOS) and
+//! `VK_ICD_FILENAMES` set, so individual apps don't need hand-crafted
+//! environment variables. `--print-env` emits the same settings as shell
+//! `export` lines for activation scripts (`eval "$(rgpu shell --print-env)"`).
+//!
+//! `rgpu run -- <program>` runs a program directly under the same settings,
+//! with per-run additions (session name, extra variables) and its exit
+//! status passed through. On Windows, where there is no preloading, the
+//! libraries are put first on `PATH` under the names applications load.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(target_os = "linux")]
+const INTERPOSE_LIB: &str = "librgpu_cuda_interpose.so";
+#[cfg(target_os = "macos")]
+const INTERPOSE_LIB: &str = "librgpu_cuda_interpose.dylib";
+#[cfg(windows)]
+const INTERPOSE_LIB: &str = "rgpu_cuda_interpose.dll";
+
+#[cfg(target_os = "linux")]
+const NVML_INTERPOSE_LIB: &str = "librgpu_nvml_interpose.so";
+#[cfg(target_os = "macos")]
+const NVML_INTERPOSE_LIB: &str = "librgpu_nvml_interpose.dylib";
+#[cfg(windows)]
+const NVML_INTERPOSE_LIB: &str = "rgpu_nvml_interpose.dll";
+
+#[cfg(target_os = "linux")]
+const ICD_LIB: &str = "librgpu_vk_icd.so";
+#[cfg(target_os = "macos")]
+const ICD_LIB: &str = "librgpu_vk_icd.dylib";
+#[cfg(windows)]
+const ICD_LIB: &str = "rgpu_vk_icd.dll";
+
+#[cfg(target_os = "linux")]
+const CUDART_INTERPOSE_LIB: &str = "librgpu_cudart_interpose.so";
+#[cfg(target_os = "macos")]
+const CUDART_INTERPOSE_LIB: &str = "librgpu_cudart_interpose.dylib";
+#[cfg(windows)]
+const CUDART_INTERPOSE_LIB: &str = "rgpu_cudart_interpose.dll";
+
+/// Preload variable understood by the platform's dynamic loader.
+#[cfg(target_os = "macos")]
+const PRELOAD_VAR: &str = "DYLD_INSERT_LIBRARIES";
+#[cfg(all(unix, not(target_os = "macos")))]
+const PRELOAD_VAR: &str = "LD_PRELOAD";
+
+/// Installed ICD manifest locations, checked before generating one.
+#[cfg(not(windows))]
+const ICD_MANIFEST_PATHS: &[&str] = &[
+    "/usr/share/vulkan/icd.d/rgpu_icd.json",
+    "/etc/vulkan/icd.d/rgpu_icd.json",
+    "/usr/local/share/vulkan/icd.d/rgpu_icd.json",
+];
+#[cfg(windows)]
+const ICD_MANIFEST_PATHS: &[&str] = &[];
+
+/// Per-run settings of `rgpu run`.
+pub struct RunOptions {
+    /// Extra variables, set after (and so overriding) the interposition ones
+    pub env: Vec<(String, String)>,
+    /// `RGPU_SESSION_NAME` for the program's session
+    pub session_name: Option<String>,
+    /// Also interpose the CUDA runtime, for programs that bypass the driver
+    pub cudart: bool,
+}
+
+pub fn run_shell(run: Option<String>, print_env: bool) -> anyhow::Result<()> {
+    let env = interpose_env(false)?;
+
+    if print_env {
+        for (key, value) in &env {
+            #[cfg(not(windows))]
+            println!("export {}=\"{}\"", key, value);
+            #[cfg(windows)]
+            println!("set {}={}", key, value);
+        }
+        return Ok(());
+    }
+
+    let mut command = match run {
+        Some(cmd) => one_shot_command(&cmd),
+        None => interactive_shell(),
+    };
+    command.envs(env);
+    warn_without_daemon();
+
+    let status = command.status()?;
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// Run `program` (a path or name on `PATH`, then its arguments) with
+/// interposition, exiting with its status.
+pub fn run_program(program: Vec<String>, options: RunOptions) -> anyhow::Result<()> {
+    let Some((name, args)) = program.split_first() else {
+        anyhow::bail!("no program given; usage: rgpu run [OPTIONS] -- <PROGRAM> [ARGS]...");
+    };
+    let mut env = interpose_env(options.cudart)?;
+    if let Some(session_name) = options.session_name {
+        env.push(("RGPU_SESSION_NAME".to_string(), session_name));
+    }
+    env.extend(options.env);
+
+    let mut command = Command::new(name);
+    command.args(args).envs(env);
+    warn_without_daemon();
+
+    #[cfg(unix)]
+    {
+        // Replace this process, so signals and the exit status are the program's own.
+        use std::os::unix::process::CommandExt;
+        let e = command.exec();
+        anyhow::bail!("can't run {}: {}", name, e);
+    }
+    #[cfg(not(unix))]
+    {
+        let status = command
+            .status()
+            .map_err(|e| anyhow::anyhow!("can't run {}: {}", name, e))?;
+        std::process::exit(status.code().unwrap_or(1));
+    }
+}
+
+/// Parse a `KEY=VALUE` argument.
+pub fn parse_env(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got {:?}", arg)),
+    }
+}
+
+fn warn_without_daemon() {
+    let ipc_path = rgpu_common::platform::default_ipc_path();
+    #[cfg(unix)]
+    if !Path::new(&ipc_path).exists() {
+        eprintln!(
+            "warning: client daemon socket {} not found; start it with `rgpu client`",
+            ipc_path
+        );
+    }
+    #[cfg(not(unix))]
+    let _ = ipc_path;
+}
+
+/// Environment variables to set in the child, in a stable order. With
+/// `cudart`, the CUDA runtime is interposed as well.
+fn interpose_env(cudart: bool) -> anyhow::Result<Vec<(String, String)>> {
+    let mut env = Vec::new();
+
+    let mut preload = Vec::new();
+    if cudart {
+        match find_library(CUDART_INTERPOSE_LIB) {
+            Some(lib) => preload.push(lib.display().to_string()),
+            None => eprintln!("warning: {} not found, the CUDA runtime will not be interposed", CUDART_INTERPOSE_LIB),
+        }
+    }
+    match find_library(INTERPOSE_LIB) {
+        Some(lib) => preload.push(lib.display().to_string()),
+        None => eprintln!("warning: {} not found, CUDA apps will not be interposed", INTERPOSE_LIB),
+    }
+    match find_library(NVML_INTERPOSE_LIB) {
+        Some(lib) => preload.push(lib.display().to_string()),
+        None => eprintln!("warning: {} not found, NVML will report local GPUs only", NVML_INTERPOSE_LIB),
+    }
+    if !preload.is_empty() {
+        #[cfg(not(windows))]
+        {
+            // Keep anything the user already preloads.
+            if let Ok(existing) = std::env::var(PRELOAD_VAR) {
+                if !existing.is_empty() {
+                    preload.push(existing);
+                }
+            }
+            env.push((PRELOAD_VAR.to_string(), preload.join(":")));
+        }
+        #[cfg(windows)]
+        env.extend(windows_dll_path(&preload)?);
+    }
+
+    match find_icd_manifest()? {
+        Some(manifest) => env.push(("VK_ICD_FILENAMES".to_string(), manifest.display().to_string())),
+        None => eprintln!("warning: RGPU Vulkan ICD not found, Vulkan apps will not see remote GPUs"),
+    }
+
+    if let Ok(log) = std::env::var("RGPU_LOG") {
+        env.push(("RGPU_LOG".to_string(), log));
+    }
+    env.push(("RGPU_SHELL".to_string(), "1".to_string()));
+
+    Ok(env)
+}
+
+/// Search next to the `rgpu` binary (development builds and portable
+/// installs), then the packaged library directories.
+fn find_library(name: &str) -> Option<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+    {
+        dirs.push(exe_dir.join("../lib/rgpu"));
+        dirs.push(exe_dir);
+    }
+    #[cfg(not(windows))]
+    {
+        dirs.push(PathBuf::from("/usr/lib/rgpu"));
+        dirs.push(PathBuf::from("/usr/local/lib/rgpu"));
+    }
+
+    dirs.into_iter()
+        .map(|dir| dir.join(name))
+        .find(|path| path.exists())
+        .and_then(|path| path.canonicalize().ok())
+}
+
+/// Use an installed manifest if there is one; otherwise write a manifest for
+/// the ICD library found next to `rgpu` into the temp directory.
+fn find_icd_manifest() -> anyhow::Result<Option<PathBuf>> {
+    if let Some(path) = ICD_MANIFEST_PATHS
+        .iter()
+        .map(PathBuf::from)
+        .find(|path| path.exists())
+    {
+        return Ok(Some(path));
+    }
+
+    let Some(icd_lib) = find_library(ICD_LIB) else {
+        return Ok(None);
+    };
+    let manifest = std::env::temp_dir().join("rgpu_icd.json");
+    let content = format!(
+        "{{\n    \"file_format_version\": \"1.0.1\",\n    \"ICD\": {{\n        \"library_path\": {:?},\n        \"api_version\": \"1.3.0\",\n        \"is_portability_driver\": false\n    }}\n}}\n",
+        icd_lib.display().to_string()
+    );
+    std::fs::write(&manifest, content)?;
+    Ok(Some(manifest))
+}
+
+/// Windows has no preloading: copy the interpose DLLs into a directory under
+/// the names applications load (`nvcuda.dll`, `nvml.dll`, `cudart64_12.dll`)
+/// and put it first on `PATH`. The system directory is searched before
+/// `PATH`, so this only takes effect where the NVIDIA driver isn't installed
+/// or the system-wide interposer already is.
+#[cfg(windows)]
+fn windows_dll_path(libraries: &[String]) -> anyhow::Result<Vec<(String, String)>> {
+    let dir = std::env::temp_dir().join("rgpu").join("bin");
+    std::fs::create_dir_all(&dir)?;
+    for library in libraries {
+        let target = match Path::new(library).file_name().and_then(|name| name.to_str()) {
+            Some(INTERPOSE_LIB) => "nvcuda.dll",
+            Some(NVML_INTERPOSE_LIB) => "nvml.dll",
+            Some(CUDART_INTERPOSE_LIB) => "cudart64_12.dll",
+            _ => continue,
+        };
+        std::fs::copy(library, dir.join(target))?;
+    }
+
+    let system32 = Path::new(&std::env::var("SystemRoot").unwrap_or_default()).join("System32");
+    // The system-wide interposer keeps the driver's DLL as nvcuda_real.dll.
+    if system32.join("nvcuda.dll").exists() && !system32.join("nvcuda_real.dll").exists() {
+        eprintln!(
+            "warning: the NVIDIA driver's nvcuda.dll is loaded before PATH; copy {} next to the application as nvcuda.dll",
+            dir.join("nvcuda.dll").display()
+        );
+    }
+
+    let path = std::env::var("PATH").unwrap_or_default();
+    Ok(vec![("PATH".to_string(), format!("{};{}", dir.display(), path))])
+}
+
+#[cfg(not(windows))]
+fn interactive_shell() -> Command {
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+    Command::new(shell)
+}
+
+#[cfg(windows)]
+fn interactive_shell() -> Command {
+    let shell = std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string());
+    Command::new(shell)
+}
+
+#[cfg(not(windows))]
+fn one_shot_command(cmd: &str) -> Command {
+    let mut command = Command::new("/bin/sh");
+    command.arg("-c").arg(cmd);
+    command
+}
+
+#[cfg(windows)]
+fn one_shot_command(cmd: &str) -> Command {
+    let mut command = interactive_shell();
+    command.arg("/C").arg(cmd);
+    command
+}
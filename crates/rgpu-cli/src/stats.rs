@@ -0,0 +1,314 @@
+use std::fmt::Write as _;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use rgpu_protocol::messages::{CompressionSummary, Message, SessionSummary, PROTOCOL_VERSION};
+use rgpu_protocol::wire;
+
+/// Metrics of one server, as returned by `QueryMetrics`.
+struct ServerStats {
+    address: String,
+    metrics: Message,
+}
+
+/// Query each server's metrics and print them, either as a table or in the
+/// Prometheus text exposition format.
+pub async fn run(servers: Vec<(String, String)>, prometheus: bool) -> anyhow::Result<()> {
+    if servers.is_empty() {
+        anyhow::bail!("no servers to query. Use --server or add servers to rgpu.toml");
+    }
+
+    let mut stats = Vec::new();
+    for (address, token) in servers {
+        match query_metrics(&address, &token).await {
+            Ok(metrics) => stats.push(ServerStats { address, metrics }),
+            Err(e) => eprintln!("{}: {}", address, e),
+        }
+    }
+
+    if prometheus {
+        print!("{}", prometheus_text(&stats));
+    } else {
+        for server in &stats {
+            print_human(server);
+        }
+    }
+    Ok(())
+}
+
+async fn query_metrics(address: &str, token: &str) -> anyhow::Result<Message> {
+    let stream = TcpStream::connect(address).await?;
+    let (mut reader, mut writer) = stream.into_split();
+
+    let hello = Message::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        name: "RGPU Stats".to_string(),
+        challenge: None,
+    };
+    writer.write_all(&wire::encode_message(&hello, 0)?).await?;
+    let challenge = match crate::read_message(&mut reader).await? {
+        Message::Hello { challenge, .. } => challenge.unwrap_or_default(),
+        _ => Vec::new(),
+    };
+
+    let auth_msg = Message::Authenticate {
+        token: token.to_string(),
+        challenge_response: rgpu_transport::auth::compute_challenge_response(token, &challenge),
+    };
+    writer.write_all(&wire::encode_message(&auth_msg, 0)?).await?;
+    match crate::read_message(&mut reader).await? {
+        Message::AuthResult { success: true, .. } => {}
+        Message::AuthResult { error_message, .. } => {
+            anyhow::bail!("authentication failed: {}", error_message.unwrap_or_default())
+        }
+        _ => anyhow::bail!("unexpected response during auth"),
+    }
+
+    writer.write_all(&wire::encode_message(&Message::QueryMetrics, 0)?).await?;
+    match crate::read_message(&mut reader).await? {
+        metrics @ Message::MetricsData { .. } => Ok(metrics),
+        _ => anyhow::bail!("unexpected response to metrics query"),
+    }
+}
+
+fn print_human(server: &ServerStats) {
+    let Message::MetricsData {
+        connections_active,
+        requests_total,
+        errors_total,
+        cuda_commands,
+        vulkan_commands,
+        uptime_secs,
+        server_id,
+        sessions,
+        ..
+    } = &server.metrics
+    else {
+        return;
+    };
+
+    println!("Server {} (id={}, up {}s)", server.address, server_id, uptime_secs);
+    println!(
+        "  Requests: {} ({} CUDA, {} Vulkan, {} errors)",
+        requests_total, cuda_commands, vulkan_commands, errors_total
+    );
+    println!("  Sessions: {}", connections_active);
+    for session in sessions {
+        let labels = session
+            .labels
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join(",");
+        println!(
+            "    #{:<4} {:<24} {:>10} req  {:>7}s  {:>10.1} MiB VRAM  {:<28} {}",
+            session.session_id,
+            session.name,
+            session.requests,
+            session.connected_secs,
+            session.vram.iter().map(|d| d.total()).sum::<u64>() as f64 / (1024.0 * 1024.0),
+            compression_text(&session.compression),
+            labels
+        );
+        if session.vram_quota > 0 || session.vram_refused > 0 {
+            println!(
+                "          quota {}, {} allocation(s) refused",
+                quota_text(session.vram_quota),
+                session.vram_refused
+            );
+        }
+        for device in &session.vram {
+            println!(
+                "          GPU {}: {:.1} MiB CUDA, {:.1} MiB Vulkan",
+                device_label(device.device_index),
+                device.cuda_bytes as f64 / (1024.0 * 1024.0),
+                device.vulkan_bytes as f64 / (1024.0 * 1024.0)
+            );
+        }
+    }
+    println!();
+}
+
+fn quota_text(bytes: u64) -> String {
+    if bytes == 0 {
+        "unlimited".to_string()
+    } else {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+/// Server device index, or `?` for a device the server couldn't identify.
+fn device_label(index: u32) -> String {
+    if index == u32::MAX {
+        "?".to_string()
+    } else {
+        index.to_string()
+    }
+}
+
+/// Compression ratio and savings, or a hint that it was turned off.
+fn compression_text(compression: &CompressionSummary) -> String {
+    if compression.disabled {
+        format!("compression off ({:.2}x)", compression.ratio())
+    } else {
+        format!(
+            "{:.2}x, {:.1} MiB saved",
+            compression.ratio(),
+            compression.bytes_saved() as f64 / (1024.0 * 1024.0)
+        )
+    }
+}
+
+/// Server-wide series: name, type and help text, in [`server_values`] order.
+const SERVER_METRICS: [(&str, &str, &str); 6] = [
+    ("rgpu_requests_total", "counter", "Messages handled by the server."),
+    ("rgpu_errors_total", "counter", "Requests that failed."),
+    ("rgpu_cuda_commands_total", "counter", "CUDA commands executed."),
+    ("rgpu_vulkan_commands_total", "counter", "Vulkan commands executed."),
+    ("rgpu_connections_active", "gauge", "Connected sessions."),
+    ("rgpu_uptime_seconds", "gauge", "Seconds since the server started."),
+];
+
+fn server_values(metrics: &Message) -> [u64; 6] {
+    match metrics {
+        Message::MetricsData {
+            requests_total,
+            errors_total,
+            cuda_commands,
+            vulkan_commands,
+            connections_active,
+            uptime_secs,
+            ..
+        } => [
+            *requests_total,
+            *errors_total,
+            *cuda_commands,
+            *vulkan_commands,
+            *connections_active as u64,
+            *uptime_secs,
+        ],
+        _ => [0; 6],
+    }
+}
+
+/// Per-session series: name, type and help text, in [`session_values`] order.
+const SESSION_METRICS: [(&str, &str, &str); 9] = [
+    ("rgpu_session_requests_total", "counter", "Messages handled for the session."),
+    ("rgpu_session_connected_seconds", "gauge", "Seconds since the session connected."),
+    ("rgpu_session_compression_input_bytes_total", "counter", "Response bytes large enough to compress."),
+    ("rgpu_session_compression_output_bytes_total", "counter", "Bytes sent for those responses."),
+    ("rgpu_session_compression_saved_bytes_total", "counter", "Bytes saved by compression."),
+    ("rgpu_session_compression_cpu_microseconds_total", "counter", "CPU time spent compressing."),
+    ("rgpu_session_compression_disabled", "gauge", "1 if compression was turned off for a low ratio."),
+    ("rgpu_session_vram_quota_bytes", "gauge", "VRAM the session may hold on all GPUs together (0 = unlimited)."),
+    ("rgpu_session_vram_refused_total", "counter", "Allocations refused for going over a VRAM quota."),
+];
+
+fn session_values(session: &SessionSummary) -> [u64; 9] {
+    let compression = &session.compression;
+    [
+        session.requests,
+        session.connected_secs,
+        compression.bytes_in,
+        compression.bytes_out,
+        compression.bytes_saved(),
+        compression.cpu_micros,
+        compression.disabled as u64,
+        session.vram_quota,
+        session.vram_refused,
+    ]
+}
+
+fn prometheus_text(stats: &[ServerStats]) -> String {
+    let mut out = String::new();
+
+    for (i, (name, kind, help)) in SERVER_METRICS.iter().enumerate() {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for server in stats {
+            let _ = writeln!(
+                out,
+                "{}{{server=\"{}\"}} {}",
+                name,
+                escape_label_value(&server.address),
+                server_values(&server.metrics)[i]
+            );
+        }
+    }
+
+    for (i, (name, kind, help)) in SESSION_METRICS.iter().enumerate() {
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} {}", name, kind);
+        for server in stats {
+            let Message::MetricsData { sessions, .. } = &server.metrics else {
+                continue;
+            };
+            for session in sessions {
+                let _ = writeln!(
+                    out,
+                    "{}{{{}}} {}",
+                    name,
+                    session_labels(&server.address, session),
+                    session_values(session)[i]
+                );
+            }
+        }
+    }
+
+    let _ = writeln!(out, "# HELP rgpu_session_vram_bytes VRAM held by the session on a GPU.");
+    let _ = writeln!(out, "# TYPE rgpu_session_vram_bytes gauge");
+    for server in stats {
+        let Message::MetricsData { sessions, .. } = &server.metrics else {
+            continue;
+        };
+        for session in sessions {
+            let labels = session_labels(&server.address, session);
+            for device in &session.vram {
+                let device_index = device_label(device.device_index);
+                for (api, bytes) in [("cuda", device.cuda_bytes), ("vulkan", device.vulkan_bytes)] {
+                    let _ = writeln!(
+                        out,
+                        "rgpu_session_vram_bytes{{{},device=\"{}\",api=\"{}\"}} {}",
+                        labels, device_index, api, bytes
+                    );
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Label set for a session's series. User labels are prefixed with `label_`
+/// so they can't collide with the built-in ones.
+fn session_labels(address: &str, session: &SessionSummary) -> String {
+    let mut labels = format!(
+        "server=\"{}\",session_id=\"{}\",session=\"{}\"",
+        escape_label_value(address),
+        session.session_id,
+        escape_label_value(&session.name)
+    );
+    for (key, value) in &session.labels {
+        let _ = write!(
+            labels,
+            ",label_{}=\"{}\"",
+            sanitize_label_name(key),
+            escape_label_value(value)
+        );
+    }
+    labels
+}
+
+/// Label names may only contain `[a-zA-Z0-9_]`.
+fn sanitize_label_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
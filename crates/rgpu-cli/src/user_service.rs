@@ -0,0 +1,174 @@
+//! Per-user client daemon service (`rgpu client --user-service ...`).
+//!
+//! Installs the daemon so it is running whenever the user is logged in,
+//! without administrator rights:
+//! - Linux: a systemd user unit plus a socket unit on `$XDG_RUNTIME_DIR/rgpu.sock`,
+//!   so the first interposed app to connect starts the daemon.
+//! - Windows: a scheduled task that runs `rgpu client` at logon.
+
+use rgpu_common::autostart::USER_SERVICE_NAME;
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum UserServiceAction {
+    Install,
+    Uninstall,
+}
+
+pub fn run(action: UserServiceAction, config: Option<String>) -> anyhow::Result<()> {
+    match action {
+        UserServiceAction::Install => install(config),
+        UserServiceAction::Uninstall => uninstall(),
+    }
+}
+
+/// Command line the service runs, with the config path made absolute.
+fn daemon_command(config: Option<String>) -> anyhow::Result<(String, Vec<String>)> {
+    let exe = std::env::current_exe()?.display().to_string();
+    let mut args = vec!["client".to_string()];
+    if let Some(config) = config {
+        let config = std::fs::canonicalize(&config)
+            .map_err(|e| anyhow::anyhow!("config file {}: {}", config, e))?;
+        args.push("--config".to_string());
+        args.push(config.display().to_string());
+    }
+    Ok((exe, args))
+}
+
+#[cfg(target_os = "linux")]
+fn unit_dir() -> anyhow::Result<std::path::PathBuf> {
+    let base = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => std::path::PathBuf::from(dir),
+        _ => {
+            let home = std::env::var("HOME").map_err(|_| anyhow::anyhow!("HOME is not set"))?;
+            std::path::PathBuf::from(home).join(".config")
+        }
+    };
+    Ok(base.join("systemd").join("user"))
+}
+
+#[cfg(target_os = "linux")]
+fn systemctl(args: &[&str]) -> anyhow::Result<()> {
+    let status = std::process::Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("systemctl --user {} failed ({})", args.join(" "), status);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn install(config: Option<String>) -> anyhow::Result<()> {
+    let (exe, args) = daemon_command(config)?;
+    let dir = unit_dir()?;
+    std::fs::create_dir_all(&dir)?;
+
+    let service = format!(
+        "[Unit]\n\
+         Description=RGPU Remote GPU Client Daemon (user)\n\
+         Documentation=https://github.com/Fill84/RGPU\n\
+         Requires={name}.socket\n\
+         After=network-online.target {name}.socket\n\
+         \n\
+         [Service]\n\
+         Type=simple\n\
+         ExecStart={exe} {args}\n\
+         Restart=on-failure\n\
+         RestartSec=5\n\
+         Environment=RGPU_LOG=info\n\
+         \n\
+         [Install]\n\
+         WantedBy=default.target\n",
+        name = USER_SERVICE_NAME,
+        exe = exe,
+        args = args.join(" "),
+    );
+    // Matches rgpu_common::platform::default_ipc_path() under a user session.
+    let socket = "[Unit]\n\
+         Description=RGPU client daemon IPC socket (user)\n\
+         \n\
+         [Socket]\n\
+         ListenStream=%t/rgpu.sock\n\
+         SocketMode=0600\n\
+         \n\
+         [Install]\n\
+         WantedBy=sockets.target\n";
+
+    let service_path = dir.join(format!("{}.service", USER_SERVICE_NAME));
+    let socket_path = dir.join(format!("{}.socket", USER_SERVICE_NAME));
+    std::fs::write(&service_path, service)?;
+    std::fs::write(&socket_path, socket)?;
+    println!("Wrote {}", service_path.display());
+    println!("Wrote {}", socket_path.display());
+
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", "--now", &format!("{}.socket", USER_SERVICE_NAME)])?;
+    systemctl(&["enable", &format!("{}.service", USER_SERVICE_NAME)])?;
+    println!("User service installed; the daemon starts on login or on first connect.");
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn uninstall() -> anyhow::Result<()> {
+    let socket = format!("{}.socket", USER_SERVICE_NAME);
+    let service = format!("{}.service", USER_SERVICE_NAME);
+    // Units may already be stopped or disabled; only the file removal matters.
+    let _ = systemctl(&["disable", "--now", &socket, &service]);
+
+    let dir = unit_dir()?;
+    for unit in [&socket, &service] {
+        let path = dir.join(unit);
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+            println!("Removed {}", path.display());
+        }
+    }
+    systemctl(&["daemon-reload"])?;
+    println!("User service uninstalled.");
+    Ok(())
+}
+
+#[cfg(windows)]
+fn schtasks(args: &[&str]) -> anyhow::Result<()> {
+    let status = std::process::Command::new("schtasks").args(args).status()?;
+    if !status.success() {
+        anyhow::bail!("schtasks {} failed ({})", args.join(" "), status);
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn install(config: Option<String>) -> anyhow::Result<()> {
+    let (exe, args) = daemon_command(config)?;
+    let args: Vec<String> = args.iter().map(|arg| format!("\"{}\"", arg)).collect();
+    let task = format!("\"{}\" {}", exe, args.join(" "));
+
+    schtasks(&[
+        "/Create", "/F", "/TN", USER_SERVICE_NAME, "/SC", "ONLOGON", "/RL", "LIMITED", "/TR",
+        &task,
+    ])?;
+    schtasks(&["/Run", "/TN", USER_SERVICE_NAME])?;
+    println!("Scheduled task '{}' installed; the daemon starts at logon.", USER_SERVICE_NAME);
+    Ok(())
+}
+
+#[cfg(windows)]
+fn uninstall() -> anyhow::Result<()> {
+    // Stop a running instance first; it's fine if none is running.
+    let _ = schtasks(&["/End", "/TN", USER_SERVICE_NAME]);
+    schtasks(&["/Delete", "/F", "/TN", USER_SERVICE_NAME])?;
+    println!("Scheduled task '{}' removed.", USER_SERVICE_NAME);
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn install(config: Option<String>) -> anyhow::Result<()> {
+    let _ = daemon_command(config)?;
+    anyhow::bail!("--user-service is not supported on this platform")
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn uninstall() -> anyhow::Result<()> {
+    anyhow::bail!("--user-service is not supported on this platform")
+}
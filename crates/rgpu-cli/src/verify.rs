@@ -271,7 +271,7 @@ fn check_gpu_pool(gpus: Option<&[GpuInfo]>, results: &mut Vec<CheckResult>) {
                 "Daemon not connected, cannot query GPU pool",
             ));
         }
-        Some(gpus) if gpus.is_empty() => {
+        Some([]) => {
             results.push(
                 CheckResult::warn("GPU pool", "No GPUs in pool")
                     .detail("Check server connectivity or enable include_local_gpus in config"),
@@ -0,0 +1,324 @@
+//! Crash breadcrumbs for interposed applications.
+//!
+//! Each IPC connection keeps a ring buffer of the last few commands it sent,
+//! with timings and outcomes. If the application goes away abnormally —
+//! the connection is reset, it disconnects with a request still in flight,
+//! or it disconnects right after receiving an error — the buffer is written
+//! to a breadcrumb file so "my app just died under RGPU" reports come with
+//! the commands that led up to it.
+
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use tracing::{info, warn};
+
+use rgpu_protocol::messages::Message;
+
+/// Where and how much to record. A depth of 0 disables breadcrumbs.
+#[derive(Debug, Clone)]
+pub struct BreadcrumbSettings {
+    pub depth: usize,
+    pub dir: PathBuf,
+    /// Report handles an application leaves behind (see [`crate::leaks`])
+    pub leak_warnings: bool,
+}
+
+impl BreadcrumbSettings {
+    pub fn from_config(config: &rgpu_core::config::ClientConfig) -> Self {
+        let dir = match &config.breadcrumb_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::temp_dir().join("rgpu-crashes"),
+        };
+        Self {
+            depth: config.breadcrumb_depth,
+            dir,
+            leak_warnings: config.leak_warnings,
+        }
+    }
+}
+
+/// Why the reader side of an IPC connection stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Clean end-of-stream between frames.
+    Closed,
+    /// Read failed or the stream ended part-way through a frame.
+    Broken(String),
+}
+
+struct Breadcrumb {
+    /// Offset from connection start.
+    at_ms: u128,
+    command: String,
+    request_id: Option<u64>,
+    duration_ms: u128,
+    error: Option<String>,
+}
+
+/// A request handed to the message handler, awaiting its response.
+pub struct PendingRequest {
+    command: String,
+    request_id: Option<u64>,
+    started: Instant,
+}
+
+/// Per-connection command history.
+pub struct Breadcrumbs {
+    settings: BreadcrumbSettings,
+    peer_pid: Option<u32>,
+    peer_name: Option<String>,
+    connected_at: Instant,
+    connected_wall: SystemTime,
+    entries: VecDeque<Breadcrumb>,
+    last_error: Option<String>,
+    /// Set when the peer was already gone by the time a response was ready.
+    in_flight_at_disconnect: Option<String>,
+    /// Summary of the handles the application left behind
+    leaked: Option<String>,
+}
+
+impl Breadcrumbs {
+    pub fn new(settings: BreadcrumbSettings, peer_pid: Option<u32>) -> Self {
+        Self {
+            peer_name: peer_pid.and_then(process_name),
+            settings,
+            peer_pid,
+            connected_at: Instant::now(),
+            connected_wall: SystemTime::now(),
+            entries: VecDeque::new(),
+            last_error: None,
+            in_flight_at_disconnect: None,
+            leaked: None,
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.settings.depth > 0
+    }
+
+    pub fn leak_warnings(&self) -> bool {
+        self.settings.leak_warnings
+    }
+
+    /// Note a request as it is handed to the message handler.
+    pub fn begin(&self, request: &Message) -> Option<PendingRequest> {
+        if !self.enabled() {
+            return None;
+        }
+        Some(PendingRequest {
+            command: describe(request),
+            request_id: request_id(request),
+            started: Instant::now(),
+        })
+    }
+
+    /// Record the outcome of a request started with [`Breadcrumbs::begin`].
+    /// `peer_gone` says whether the application had disconnected by the time
+    /// the response was ready.
+    pub fn record(&mut self, pending: PendingRequest, response: &Message, peer_gone: bool) {
+        let error = response_error(response);
+        if let Some(ref e) = error {
+            self.last_error = Some(format!("{}: {}", pending.command, e));
+        }
+        if peer_gone && self.in_flight_at_disconnect.is_none() {
+            self.in_flight_at_disconnect = Some(pending.command.clone());
+        }
+
+        if self.entries.len() == self.settings.depth {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(Breadcrumb {
+            at_ms: pending.started.duration_since(self.connected_at).as_millis(),
+            command: pending.command,
+            request_id: pending.request_id,
+            duration_ms: pending.started.elapsed().as_millis(),
+            error,
+        });
+    }
+
+    /// The application, e.g. `train.py (pid 4242)`.
+    pub fn peer(&self) -> String {
+        format!(
+            "{} (pid {})",
+            self.peer_name.as_deref().unwrap_or("application"),
+            self.peer_pid.map_or_else(|| "unknown".to_string(), |p| p.to_string()),
+        )
+    }
+
+    /// Note the handles the application left behind; the breadcrumb file is
+    /// written even on a clean disconnect then.
+    pub fn set_leaked(&mut self, summary: String) {
+        self.leaked = Some(summary);
+    }
+
+    /// Decide whether the disconnect was abnormal and, if so, write the
+    /// breadcrumb file. Returns the file written, if any.
+    pub fn finish(self, reason: &DisconnectReason) -> Option<PathBuf> {
+        if !self.enabled() {
+            return None;
+        }
+        let last_failed = self.entries.back().is_some_and(|e| e.error.is_some());
+        let why = match (reason, &self.in_flight_at_disconnect) {
+            (DisconnectReason::Broken(e), _) => format!("connection broken: {}", e),
+            (_, Some(cmd)) => format!("disconnected while {} was in flight", cmd),
+            _ if last_failed => "disconnected after an error response".to_string(),
+            _ if self.leaked.is_some() => "exited with live handles".to_string(),
+            _ => return None,
+        };
+
+        let path = self.settings.dir.join(format!(
+            "rgpu-crash-{}-{}.txt",
+            self.peer_pid.map_or_else(|| "unknown".to_string(), |p| p.to_string()),
+            unix_secs(SystemTime::now()),
+        ));
+        let report = self.render(&why);
+        let written = std::fs::create_dir_all(&self.settings.dir)
+            .and_then(|_| std::fs::write(&path, report));
+        match written {
+            Ok(()) => {
+                info!(
+                    "abnormal IPC disconnect ({}), breadcrumbs written to {}",
+                    why,
+                    path.display()
+                );
+                Some(path)
+            }
+            Err(e) => {
+                warn!(
+                    "abnormal IPC disconnect ({}), cannot write {}: {}",
+                    why,
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    fn render(&self, why: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "RGPU crash breadcrumbs");
+        let _ = writeln!(
+            out,
+            "process:   {} ({})",
+            self.peer_pid.map_or_else(|| "unknown".to_string(), |p| p.to_string()),
+            self.peer_name.as_deref().unwrap_or("unknown"),
+        );
+        let _ = writeln!(out, "connected: {} (unix time)", unix_secs(self.connected_wall));
+        let _ = writeln!(
+            out,
+            "duration:  {:.3}s",
+            self.connected_at.elapsed().as_secs_f64()
+        );
+        let _ = writeln!(out, "reason:    {}", why);
+        let _ = writeln!(
+            out,
+            "last error: {}",
+            self.last_error.as_deref().unwrap_or("none")
+        );
+        if let Some(leaked) = &self.leaked {
+            let _ = writeln!(out, "leaked:    {} (freed automatically)", leaked);
+        }
+        let _ = writeln!(out);
+        let _ = writeln!(out, "last {} command(s), oldest first:", self.entries.len());
+        for entry in &self.entries {
+            let _ = writeln!(
+                out,
+                "  +{:>9}ms  req {:>8}  {:<40} {:>7}ms  {}",
+                entry.at_ms,
+                entry.request_id.map_or_else(|| "-".to_string(), |id| id.to_string()),
+                entry.command,
+                entry.duration_ms,
+                entry.error.as_deref().unwrap_or("ok"),
+            );
+        }
+        out
+    }
+}
+
+/// Short name for a request, e.g. `Cuda::LaunchKernel`.
+fn describe(msg: &Message) -> String {
+    match msg {
+        Message::CudaCommand { command, .. } => format!("Cuda::{}", variant_name(command)),
+        Message::CudaCommandStreamed { command, .. } => {
+            format!("Cuda::{} (streamed)", variant_name(command))
+        }
+        Message::VulkanCommand { command, .. } => format!("Vulkan::{}", variant_name(command)),
+        Message::CudaBatch(commands) => format!("CudaBatch[{}]", commands.len()),
+        Message::CudaPipelined { batch, command, .. } => {
+            format!("CudaBatch[{}] + Cuda::{}", batch.len(), variant_name(command))
+        }
+        other => variant_name(other),
+    }
+}
+
+fn request_id(msg: &Message) -> Option<u64> {
+    match msg {
+        Message::CudaCommand { request_id, .. }
+        | Message::CudaCommandStreamed { request_id, .. }
+        | Message::CudaPipelined { request_id, .. }
+        | Message::VulkanCommand { request_id, .. } => Some(request_id.0),
+        _ => None,
+    }
+}
+
+fn response_error(msg: &Message) -> Option<String> {
+    use rgpu_protocol::cuda_commands::CudaResponse;
+    use rgpu_protocol::vulkan_commands::VulkanResponse;
+
+    match msg {
+        Message::CudaResponse {
+            response: CudaResponse::Error { code, message },
+            ..
+        }
+        | Message::VulkanResponse {
+            response: VulkanResponse::Error { code, message },
+            ..
+        } => Some(format!("error {}: {}", code, message)),
+        Message::Error(e) => Some(e.to_string()),
+        _ => None,
+    }
+}
+
+/// Enum variant name from the derived `Debug` output, without formatting
+/// the fields (which may hold megabytes of buffer data).
+pub(crate) fn variant_name(value: &impl std::fmt::Debug) -> String {
+    struct NameOnly(String);
+
+    impl std::fmt::Write for NameOnly {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            match s.find(|c: char| !(c.is_alphanumeric() || c == '_')) {
+                Some(end) => {
+                    self.0.push_str(&s[..end]);
+                    Err(std::fmt::Error)
+                }
+                None => {
+                    self.0.push_str(s);
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    let mut name = NameOnly(String::new());
+    let _ = write!(name, "{:?}", value);
+    name.0
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(target_os = "linux")]
+fn process_name(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .map(|name| name.trim().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_name(_pid: u32) -> Option<String> {
+    None
+}
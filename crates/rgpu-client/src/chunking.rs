@@ -0,0 +1,202 @@
+//! Chunked uploads, sized to the link.
+//!
+//! A large `MemcpyHtoD` sent as one frame holds the server connection for
+//! as long as it takes to transfer, and both ends build the whole frame in
+//! memory. Over a byte stream the daemon sends such data ahead of the
+//! command as `TransferChunk`s instead, a window of them unacknowledged at
+//! a time, and lets other requests use the connection between rounds of
+//! chunks. Chunk size and window follow the round-trip time and bandwidth
+//! measured on the connection: a chunk takes about `CHUNK_TIME` to send,
+//! and the window covers the bandwidth-delay product.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rgpu_protocol::messages::Message;
+
+/// Uploads smaller than this are sent whole.
+pub const MIN_CHUNKED_BYTES: usize = 4 << 20;
+
+/// How long an upload keeps the connection before other requests get a
+/// turn.
+pub const ROUND: Duration = Duration::from_millis(50);
+
+const CHUNK_TIME: Duration = Duration::from_millis(20);
+const MIN_CHUNK: usize = 256 << 10;
+const MAX_CHUNK: usize = 16 << 20;
+const MIN_WINDOW: usize = 2;
+const MAX_WINDOW: usize = 16;
+
+/// Chunk size and window before anything is measured.
+const INITIAL_CHUNK: usize = 1 << 20;
+const INITIAL_WINDOW: usize = 4;
+
+#[derive(Default)]
+struct Estimate {
+    rtt: Option<Duration>,
+    /// Bytes per second
+    bandwidth: Option<f64>,
+}
+
+/// What the daemon has measured of its connection to a server.
+#[derive(Default)]
+pub struct Link {
+    estimate: Mutex<Estimate>,
+    next_transfer: AtomicU64,
+}
+
+impl Link {
+    /// Id for a new upload on this connection.
+    pub fn transfer_id(&self) -> u64 {
+        self.next_transfer.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Bytes to put in each chunk.
+    pub fn chunk_size(&self) -> usize {
+        match self.estimate.lock().unwrap().bandwidth {
+            Some(bandwidth) => ((bandwidth * CHUNK_TIME.as_secs_f64()) as usize).clamp(MIN_CHUNK, MAX_CHUNK),
+            None => INITIAL_CHUNK,
+        }
+    }
+
+    /// Chunks to have unacknowledged at once. One more than fit in the
+    /// bandwidth-delay product, so a window that limits the measured
+    /// bandwidth grows until it doesn't.
+    pub fn window(&self) -> usize {
+        let chunk = self.chunk_size() as f64;
+        let estimate = self.estimate.lock().unwrap();
+        match (estimate.rtt, estimate.bandwidth) {
+            (Some(rtt), Some(bandwidth)) => {
+                ((bandwidth * rtt.as_secs_f64() / chunk).ceil() as usize + 1).clamp(MIN_WINDOW, MAX_WINDOW)
+            }
+            _ => INITIAL_WINDOW,
+        }
+    }
+
+    /// Record a round of chunks: the shortest wait for a chunk's
+    /// acknowledgement, and `bytes` acknowledged in `elapsed`.
+    pub fn observe(&self, rtt: Duration, bytes: u64, elapsed: Duration) {
+        if bytes == 0 || elapsed.is_zero() {
+            return;
+        }
+        let mut estimate = self.estimate.lock().unwrap();
+        estimate.rtt = Some(match estimate.rtt {
+            Some(previous) => (previous * 7 + rtt) / 8,
+            None => rtt,
+        });
+        let bandwidth = bytes as f64 / elapsed.as_secs_f64();
+        estimate.bandwidth = Some(match estimate.bandwidth {
+            Some(previous) => (previous * 3.0 + bandwidth) / 4.0,
+            None => bandwidth,
+        });
+    }
+}
+
+/// Take the data out of `msg` if it is an upload worth chunking.
+pub fn take_upload(msg: &mut Message) -> Option<Vec<u8>> {
+    match msg {
+        Message::CudaCommand { command, .. } => command
+            .upload_data_mut()
+            .filter(|data| data.len() >= MIN_CHUNKED_BYTES)
+            .map(std::mem::take),
+        _ => None,
+    }
+}
+
+/// Put data taken by [`take_upload`] back.
+pub fn restore_upload(msg: &mut Message, data: Vec<u8>) {
+    if let Message::CudaCommand { command, .. } = msg {
+        if let Some(src_data) = command.upload_data_mut() {
+            *src_data = data;
+        }
+    }
+}
+
+/// `msg`, taken by [`take_upload`], as a command on upload `transfer`.
+pub fn chunked(msg: &Message, transfer: u64) -> Option<Message> {
+    match msg {
+        Message::CudaCommand {
+            request_id,
+            command,
+            deadline_ms,
+        } => Some(Message::CudaCommandChunked {
+            request_id: *request_id,
+            command: command.clone(),
+            deadline_ms: *deadline_ms,
+            transfer,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::cuda_commands::CudaCommand;
+    use rgpu_protocol::handle::{NetworkHandle, ResourceType};
+    use rgpu_protocol::messages::RequestId;
+
+    fn upload(len: usize) -> Message {
+        Message::CudaCommand {
+            request_id: RequestId(1),
+            command: CudaCommand::MemcpyHtoD {
+                dst: NetworkHandle {
+                    server_id: 1,
+                    session_id: 1,
+                    resource_id: 1,
+                    resource_type: ResourceType::CuDevicePtr,
+                },
+                src_data: vec![9; len],
+                byte_count: len as u64,
+            },
+            deadline_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_only_large_uploads_are_chunked() {
+        assert!(take_upload(&mut upload(MIN_CHUNKED_BYTES - 1)).is_none());
+
+        let mut msg = upload(MIN_CHUNKED_BYTES);
+        let data = take_upload(&mut msg).unwrap();
+        assert_eq!(data.len(), MIN_CHUNKED_BYTES);
+        match chunked(&msg, 3) {
+            Some(Message::CudaCommandChunked {
+                command: CudaCommand::MemcpyHtoD { src_data, .. },
+                transfer: 3,
+                ..
+            }) => assert!(src_data.is_empty()),
+            other => panic!("expected CudaCommandChunked, got {:?}", other),
+        }
+        restore_upload(&mut msg, data);
+        match msg {
+            Message::CudaCommand {
+                command: CudaCommand::MemcpyHtoD { src_data, .. },
+                ..
+            } => assert_eq!(src_data, vec![9; MIN_CHUNKED_BYTES]),
+            other => panic!("expected CudaCommand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_chunks_follow_the_link() {
+        let link = Link::default();
+        assert_eq!((link.chunk_size(), link.window()), (INITIAL_CHUNK, INITIAL_WINDOW));
+
+        // 1 GB/s with 1 ms round trips: 20 MB would take a chunk time, so
+        // chunks are capped; one fits in the 1 MB bandwidth-delay product.
+        link.observe(Duration::from_millis(1), 1 << 30, Duration::from_secs(1));
+        assert_eq!((link.chunk_size(), link.window()), (MAX_CHUNK, MIN_WINDOW));
+
+        // 50 MB/s with 100 ms round trips: 1 MB chunks, 5 MB in flight
+        let link = Link::default();
+        link.observe(Duration::from_millis(100), 50_000_000, Duration::from_secs(1));
+        assert_eq!((link.chunk_size(), link.window()), (1_000_000, 6));
+
+        // Slow links still send reasonable chunks
+        let link = Link::default();
+        link.observe(Duration::from_millis(100), 1_000_000, Duration::from_secs(1));
+        assert_eq!(link.chunk_size(), MIN_CHUNK);
+    }
+}
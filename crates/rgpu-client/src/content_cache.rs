@@ -0,0 +1,78 @@
+//! Uploads by content hash.
+//!
+//! Apps create the same shader modules and load the same CUDA modules on
+//! every run, each time sending the full SPIR-V or module image over the
+//! network. The daemon keeps a manifest of the SHA-256 of what each server
+//! has been sent, and the next time the same code goes there sends only
+//! the hash (`CreateShaderModuleByHash`, `ModuleLoadByHash`). A server that
+//! no longer has the code (it restarted, or dropped it for space) answers
+//! that it's missing, and the code goes in full after all.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Mutex;
+
+use sha2::{Digest, Sha256};
+
+/// SPIR-V sent with `CreateShaderModule`.
+pub static SHADERS: Manifest = Manifest::new();
+
+/// Module images sent with `ModuleLoadData`.
+pub static MODULES: Manifest = Manifest::new();
+
+/// The hash uploads are named by.
+pub fn hash(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Hashes of what each server, by address, has been sent.
+pub struct Manifest {
+    known: Mutex<BTreeMap<String, HashSet<[u8; 32]>>>,
+}
+
+impl Manifest {
+    pub const fn new() -> Self {
+        Self {
+            known: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Whether the server at `address` should have what `hash` names.
+    pub fn known(&self, address: &str, hash: &[u8; 32]) -> bool {
+        self.known.lock().unwrap().get(address).is_some_and(|hashes| hashes.contains(hash))
+    }
+
+    /// Note that the server at `address` was sent what `hash` names.
+    pub fn remember(&self, address: &str, hash: [u8; 32]) {
+        self.known.lock().unwrap().entry(address.to_string()).or_default().insert(hash);
+    }
+
+    /// Note that the server at `address` doesn't have what `hash` names.
+    pub fn forget(&self, address: &str, hash: &[u8; 32]) {
+        if let Some(hashes) = self.known.lock().unwrap().get_mut(address) {
+            hashes.remove(hash);
+        }
+    }
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_per_server() {
+        let manifest = Manifest::new();
+        let h = hash(&[0x03, 0x02, 0x23, 0x07]);
+        assert!(!manifest.known("a:1", &h));
+        manifest.remember("a:1", h);
+        assert!(manifest.known("a:1", &h));
+        assert!(!manifest.known("b:1", &h));
+        manifest.forget("a:1", &h);
+        assert!(!manifest.known("a:1", &h));
+    }
+}
@@ -0,0 +1,207 @@
+//! The context an application has current, for routing.
+//!
+//! Most CUDA commands name the object they act on, and its handle says which
+//! server owns it. Context-scoped commands (allocations, creating streams,
+//! events and modules, `cuCtxSynchronize`, ...) don't, so with GPUs from
+//! several servers in the pool they have to go to the server of the
+//! application's current context. Each IPC connection keeps a
+//! [`ContextStack`] that follows the application's `cuCtxCreate`,
+//! `cuCtxSetCurrent`, `cuCtxPushCurrent`, `cuCtxPopCurrent` and
+//! `cuCtxDestroy` calls. The daemon can't tell an application's threads
+//! apart, so there is one stack per connection rather than one per thread.
+
+use std::sync::{Arc, Mutex};
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
+
+use crate::daemon::extract_cuda_routing_handle;
+
+/// The context stack of one IPC connection, shared by the requests on it.
+pub type Contexts = Arc<Mutex<ContextStack>>;
+
+/// Contexts made current through one IPC connection, innermost last.
+#[derive(Debug, Clone, Default)]
+pub struct ContextStack {
+    stack: Vec<NetworkHandle>,
+}
+
+/// How a command changes the stack once it has succeeded.
+#[derive(Debug, Clone, Copy)]
+pub enum Switch {
+    /// Push the context in the response (`cuCtxCreate`)
+    Created,
+    Push(NetworkHandle),
+    /// Replace the current context
+    Set(NetworkHandle),
+    Pop,
+    /// Drop a destroyed context wherever it is on the stack
+    Destroy(NetworkHandle),
+}
+
+impl ContextStack {
+    pub fn current(&self) -> Option<NetworkHandle> {
+        self.stack.last().copied()
+    }
+
+    /// The handle `command` routes by: the object it names, or the current
+    /// context for commands that name none (or only the null stream).
+    pub fn routing_handle(&self, command: &CudaCommand) -> Option<NetworkHandle> {
+        match extract_cuda_routing_handle(command) {
+            Some(handle) if handle != NetworkHandle::null() && handle != NetworkHandle::null_stream() => {
+                Some(handle)
+            }
+            _ => self.current(),
+        }
+    }
+
+    /// The change `command` makes to the stack, applied by [`Self::apply`]
+    /// once its response is in.
+    pub fn switch(command: &CudaCommand) -> Option<Switch> {
+        match command {
+            CudaCommand::CtxCreate { .. } => Some(Switch::Created),
+            CudaCommand::CtxPushCurrent { ctx } => Some(Switch::Push(*ctx)),
+            CudaCommand::CtxSetCurrent { ctx } => Some(Switch::Set(*ctx)),
+            CudaCommand::CtxPopCurrent => Some(Switch::Pop),
+            CudaCommand::CtxDestroy { ctx } => Some(Switch::Destroy(*ctx)),
+            _ => None,
+        }
+    }
+
+    /// Apply a switch unless the command failed.
+    pub fn apply(&mut self, switch: Switch, response: &CudaResponse) {
+        if matches!(response, CudaResponse::Error { .. }) {
+            return;
+        }
+        match switch {
+            Switch::Created => {
+                if let CudaResponse::Context(ctx) = response {
+                    self.stack.push(*ctx);
+                }
+            }
+            Switch::Push(ctx) => self.stack.push(ctx),
+            Switch::Set(ctx) if ctx.is_null() => {
+                self.stack.pop();
+            }
+            Switch::Set(ctx) => match self.stack.last_mut() {
+                Some(top) => *top = ctx,
+                None => self.stack.push(ctx),
+            },
+            Switch::Pop => {
+                self.stack.pop();
+            }
+            Switch::Destroy(ctx) => self.stack.retain(|current| *current != ctx),
+        }
+    }
+
+    /// Route `command` and apply its switch as if it succeeded, for void
+    /// commands whose results aren't seen one by one.
+    pub fn route_void(&mut self, command: &CudaCommand) -> Option<NetworkHandle> {
+        let handle = self.routing_handle(command);
+        if let Some(switch) = Self::switch(command) {
+            self.apply(switch, &CudaResponse::Success);
+        }
+        handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::ResourceType;
+
+    fn handle(server_id: u16, resource_id: u64, resource_type: ResourceType) -> NetworkHandle {
+        NetworkHandle {
+            server_id,
+            session_id: 1,
+            resource_id,
+            resource_type,
+        }
+    }
+
+    fn ctx(server_id: u16) -> NetworkHandle {
+        handle(server_id, 100 + server_id as u64, ResourceType::CuContext)
+    }
+
+    fn run(stack: &mut ContextStack, command: CudaCommand, response: CudaResponse) -> Option<NetworkHandle> {
+        let routed = stack.routing_handle(&command);
+        if let Some(switch) = ContextStack::switch(&command) {
+            stack.apply(switch, &response);
+        }
+        routed
+    }
+
+    fn server(handle: Option<NetworkHandle>) -> Option<u16> {
+        handle.map(|h| h.server_id)
+    }
+
+    #[test]
+    fn context_scoped_commands_follow_the_current_context() {
+        let mut stack = ContextStack::default();
+        let alloc = || CudaCommand::MemAlloc { byte_size: 1024 };
+        assert_eq!(stack.routing_handle(&alloc()), None);
+
+        // One context on each of three servers, created on their devices.
+        for id in 1..=3 {
+            let device = handle(id, 0, ResourceType::CuDevice);
+            let created = CudaCommand::CtxCreate { flags: 0, device };
+            assert_eq!(server(run(&mut stack, created, CudaResponse::Context(ctx(id)))), Some(id));
+            assert_eq!(server(stack.routing_handle(&alloc())), Some(id));
+        }
+
+        run(&mut stack, CudaCommand::CtxSetCurrent { ctx: ctx(1) }, CudaResponse::Success);
+        assert_eq!(server(stack.routing_handle(&CudaCommand::StreamCreate { flags: 0 })), Some(1));
+        assert_eq!(server(stack.routing_handle(&CudaCommand::CtxSynchronize)), Some(1));
+
+        // Work on the null stream goes where the context is.
+        let null_sync = CudaCommand::StreamSynchronize {
+            stream: NetworkHandle::null_stream(),
+        };
+        assert_eq!(server(stack.routing_handle(&null_sync)), Some(1));
+    }
+
+    #[test]
+    fn push_pop_and_destroy() {
+        let mut stack = ContextStack::default();
+        run(&mut stack, CudaCommand::CtxPushCurrent { ctx: ctx(1) }, CudaResponse::Success);
+        run(&mut stack, CudaCommand::CtxPushCurrent { ctx: ctx(2) }, CudaResponse::Success);
+        assert_eq!(server(stack.current()), Some(2));
+
+        // The pop goes to the server whose context is popped.
+        let popped = run(&mut stack, CudaCommand::CtxPopCurrent, CudaResponse::Context(ctx(2)));
+        assert_eq!(server(popped), Some(2));
+        assert_eq!(server(stack.current()), Some(1));
+
+        // A failed switch leaves the stack alone.
+        let failed = CudaResponse::Error {
+            code: 201,
+            message: "invalid context".to_string(),
+        };
+        run(&mut stack, CudaCommand::CtxSetCurrent { ctx: ctx(3) }, failed);
+        assert_eq!(server(stack.current()), Some(1));
+
+        run(&mut stack, CudaCommand::CtxPushCurrent { ctx: ctx(3) }, CudaResponse::Success);
+        run(&mut stack, CudaCommand::CtxDestroy { ctx: ctx(1) }, CudaResponse::Success);
+        run(&mut stack, CudaCommand::CtxDestroy { ctx: ctx(3) }, CudaResponse::Success);
+        assert_eq!(stack.current(), None);
+    }
+
+    #[test]
+    fn mixed_server_batches_keep_each_handle_on_its_server() {
+        let mut stack = ContextStack::default();
+        let mem = |server_id| handle(server_id, 7, ResourceType::CuDevicePtr);
+        let stream = handle(3, 8, ResourceType::CuStream);
+
+        let batch = [
+            CudaCommand::CtxSetCurrent { ctx: ctx(2) },
+            CudaCommand::MemsetD8 { dst: mem(1), value: 0, count: 16 },
+            CudaCommand::MemAlloc { byte_size: 64 },
+            CudaCommand::StreamSynchronize { stream },
+            CudaCommand::CtxPushCurrent { ctx: ctx(3) },
+            CudaCommand::MemAlloc { byte_size: 64 },
+        ];
+        let routes: Vec<Option<u16>> = batch.iter().map(|cmd| server(stack.route_void(cmd))).collect();
+        assert_eq!(routes, [Some(2), Some(1), Some(2), Some(3), Some(3), Some(3)]);
+        assert_eq!(server(stack.current()), Some(3));
+    }
+}
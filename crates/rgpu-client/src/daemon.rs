@@ -1,56 +1,605 @@
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 
-use rgpu_core::config::{ClientConfig, ServerEndpoint, TransportMode};
-use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_core::config::{ClientConfig, PlacementPolicy, ServerEndpoint, TransportMode, VersionCheck};
+use rgpu_protocol::compat;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse, TextureResource};
+use rgpu_protocol::error::ProtocolError;
 use rgpu_protocol::gpu_info::GpuInfo;
 use rgpu_protocol::handle::NetworkHandle;
-use rgpu_protocol::messages::{Message, RequestId, PROTOCOL_VERSION};
+use rgpu_protocol::messages::{Message, RequestId, PLACEMENT_LABEL, PROTOCOL_VERSION};
+use rgpu_protocol::version::{BuildInfo, Compatibility};
 use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
-use rgpu_protocol::wire;
+use rgpu_protocol::wire::{self, CompressionSettings, CompressionStats};
 use rgpu_transport::auth;
+use rgpu_transport::health;
 use rgpu_transport::quic::QuicConnection;
-
-use crate::pool_manager::{ConnectionStatus, GpuPoolManager, LOCAL_SERVER_ID};
+use rgpu_transport::rdma::{self, RdmaLink};
+use rgpu_transport::websocket;
+
+use crate::chunking;
+use crate::content_cache::{self, MODULES, SHADERS};
+use crate::current_context::{ContextStack, Contexts};
+use crate::failover;
+use crate::ipc::PeerGone;
+use crate::mirror::Mirror;
+use crate::placement::{self, Candidate, Placement};
+use crate::prefetch::{Observation, PrefetchSlot, Prefetcher, Read};
+use crate::readback::ReadbackCache;
+use crate::relocation::{self, Relocations};
+use crate::spill::Spill;
+use crate::pool_manager::{ConnectionStatus, GpuPoolEntry, GpuPoolManager, LOCAL_SERVER_ID};
+use crate::transport_probe;
+
+type StreamReader = Box<dyn AsyncRead + Send + Unpin>;
+type StreamWriter = Box<dyn AsyncWrite + Send + Unpin>;
 
 /// Transport-specific connection variant.
 enum TransportConn {
-    Tcp {
-        reader: OwnedReadHalf,
-        writer: OwnedWriteHalf,
+    /// TCP, or the same byte stream tunnelled over WebSocket
+    Stream {
+        reader: StreamReader,
+        writer: StreamWriter,
+        /// Bulk data goes over RDMA once this is set
+        rdma: Option<Box<RdmaLink>>,
     },
     Quic(QuicConnection),
 }
 
+/// The local application waiting on a forwarded request.
+#[derive(Clone)]
+struct IpcCaller {
+    /// Flips to true if the application disconnects
+    peer_gone: PeerGone,
+    /// When the application's IPC read times out and it stops waiting
+    deadline: Instant,
+    /// The application's current context, for commands that name no object
+    contexts: Contexts,
+}
+
+/// Shared table of per-server connection slots, indexed by server index.
+type ServerConns = Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>;
+
 /// A persistent, authenticated connection to an RGPU server.
-struct ServerConn {
+pub(crate) struct ServerConn {
     transport: TransportConn,
-    _address: String,
+    address: String,
     _token: String,
+    /// Protocol version pinned during the Hello exchange
+    version: u32,
+    /// Session the server assigned this connection
+    session_id: Option<u32>,
+    /// Secret for resuming that session from a later connection
+    resume_token: Option<u64>,
+    /// Objects of the previous session this connection took over on a
+    /// reconnect; 0 if there were none or they were lost
+    pub(crate) restored: u32,
+    /// Compression of what this daemon sends the server, shared with the
+    /// connection's concurrent handles
+    compression: Arc<CompressionStats>,
+    /// Measured round trips and bandwidth for chunked uploads; also tells
+    /// this connection from a later one in the same slot
+    link: Arc<chunking::Link>,
 }
 
 impl ServerConn {
+    /// A handle for sending on this connection without holding its slot, if
+    /// requests on it can be in flight side by side: QUIC connections.
+    /// Requests on one CUDA stream still go out in order on that stream's
+    /// lane.
+    fn concurrent(&self) -> Option<ServerConn> {
+        match &self.transport {
+            TransportConn::Quic(quic) => Some(ServerConn {
+                transport: TransportConn::Quic(quic.clone()),
+                address: self.address.clone(),
+                _token: self._token.clone(),
+                version: self.version,
+                session_id: self.session_id,
+                resume_token: self.resume_token,
+                restored: self.restored,
+                compression: self.compression.clone(),
+                link: self.link.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Whether `other` is a handle of this connection.
+    fn same_connection(&self, other: &ServerConn) -> bool {
+        match (&self.transport, &other.transport) {
+            (TransportConn::Quic(a), TransportConn::Quic(b)) => a.same_connection(b),
+            _ => false,
+        }
+    }
+
+    /// Whether large uploads go to this server in chunks: over a byte
+    /// stream to a server that reassembles them. Requests on a QUIC
+    /// connection don't wait for each other anyway, and RDMA moves bulk
+    /// data past the stream.
+    fn chunks_uploads(&self) -> bool {
+        matches!(self.transport, TransportConn::Stream { rdma: None, .. })
+    }
+
+    /// Send chunks of upload `transfer` from `offset` for up to a round,
+    /// keeping a window of them unacknowledged. Returns the bytes the
+    /// server holds afterwards, or None if it refused the upload.
+    async fn send_chunks(
+        &mut self,
+        transfer: u64,
+        data: &[u8],
+        offset: u64,
+    ) -> Result<Option<u64>, Box<dyn std::error::Error + Send + Sync>> {
+        let TransportConn::Stream { reader, writer, .. } = &mut self.transport else {
+            return Err("chunked upload on a connection without a byte stream".into());
+        };
+        let (chunk, window) = (self.link.chunk_size() as u64, self.link.window());
+        let total = data.len() as u64;
+        let started = Instant::now();
+        let (mut sent, mut acked) = (offset, offset);
+        let mut unacked = std::collections::VecDeque::with_capacity(window);
+        let mut rtt = Duration::MAX;
+        loop {
+            while unacked.len() < window && sent < total && started.elapsed() < chunking::ROUND {
+                let end = (sent + chunk).min(total);
+                let msg = Message::TransferChunk {
+                    transfer,
+                    offset: sent,
+                    total,
+                    data: data[sent as usize..end as usize].to_vec(),
+                };
+                let frame = wire::encode_message_tracked(&msg, wire::request_tag(&msg), &self.compression)?;
+                writer.write_all(&frame).await?;
+                writer.flush().await?;
+                unacked.push_back(Instant::now());
+                sent = end;
+            }
+            let Some(sent_at) = unacked.pop_front() else {
+                break;
+            };
+            match read_response(reader, None, &self.address).await? {
+                Message::TransferAck { transfer: acked_transfer, received }
+                    if acked_transfer == transfer && received > acked =>
+                {
+                    acked = received;
+                    rtt = rtt.min(sent_at.elapsed());
+                }
+                // Refused; the acknowledgements of chunks still unacked are
+                // read so the connection stays in sync.
+                Message::TransferAck { .. } => {
+                    for _ in unacked.drain(..) {
+                        read_response(reader, None, &self.address).await?;
+                    }
+                    return Ok(None);
+                }
+                other => return Err(format!("expected TransferAck, got {:?}", other).into()),
+            }
+        }
+        self.link.observe(rtt, acked - offset, started.elapsed());
+        Ok(Some(acked))
+    }
+
+    /// The QUIC lane `msg` goes on: its CUDA stream's, so work on different
+    /// streams doesn't queue behind each other.
+    fn lane(&self, msg: &Message) -> Option<u64> {
+        match msg {
+            Message::CudaCommand { command, .. } => command.stream().map(|stream| stream.resource_id),
+            _ => None,
+        }
+    }
+
     /// Send a message and wait for the response on this connection.
-    async fn send_and_receive(
+    pub(crate) async fn send_and_receive(
+        &mut self,
+        msg: &Message,
+    ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+        let response = self.transmit(msg).await?;
+        Ok(self.answer_unsupported(msg, response))
+    }
+
+    /// Turn the server's `Unsupported` answer to `request` into the error
+    /// response of the request's kind.
+    fn answer_unsupported(&self, request: &Message, response: Message) -> Message {
+        match response {
+            Message::Unsupported { reason, .. } => {
+                warn!("{} can't handle a request: {}", self.address, reason);
+                compat::unsupported_response(request, reason)
+            }
+            response => response,
+        }
+    }
+
+    /// Send a message and wait for the server's response as it comes.
+    async fn transmit(
         &mut self,
         msg: &Message,
     ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+        let lane = self.lane(msg);
         match &mut self.transport {
-            TransportConn::Tcp { reader, writer } => {
-                let frame = wire::encode_message(msg, 0)?;
+            TransportConn::Stream { reader, writer, rdma } => {
+                let frame = wire::encode_message_tracked(msg, wire::request_tag(msg), &self.compression)?;
+                auto_tune_compression(&self.compression, &self.address);
+                let frame = match rdma {
+                    Some(link) => link.offload(frame).await,
+                    None => frame,
+                };
+                writer.write_all(&frame).await?;
+                writer.flush().await?;
+                read_response(reader, rdma.as_deref(), &self.address).await
+            }
+            TransportConn::Quic(quic) => {
+                let response = quic.send_and_receive_tracked(msg, lane, &self.compression).await?;
+                auto_tune_compression(&self.compression, &self.address);
+                close_lane(quic, msg);
+                Ok(response)
+            }
+        }
+    }
+
+    /// Like `send_and_receive`, but if the local application goes away before
+    /// the response arrives, tell the server to abandon the request. The
+    /// response to the cancelled request is still read so the connection
+    /// stays in sync.
+    async fn send_and_receive_cancellable(
+        &mut self,
+        msg: &Message,
+        request_id: RequestId,
+        mut peer_gone: PeerGone,
+    ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+        let cancel = Message::Cancel { request_id };
+        let lane = self.lane(msg);
+        let response = match &mut self.transport {
+            TransportConn::Stream { reader, writer, rdma } => {
+                let frame = wire::encode_message_tracked(msg, wire::request_tag(msg), &self.compression)?;
+                auto_tune_compression(&self.compression, &self.address);
+                let frame = match rdma {
+                    Some(link) => link.offload(frame).await,
+                    None => frame,
+                };
                 writer.write_all(&frame).await?;
-                read_message(reader).await
+                writer.flush().await?;
+                let response = read_response(reader, rdma.as_deref(), &self.address);
+                tokio::pin!(response);
+                let finished = tokio::select! {
+                    result = &mut response => Some(result),
+                    _ = peer_gone.wait_for(|gone| *gone) => None,
+                };
+                match finished {
+                    Some(result) => result?,
+                    None => {
+                        debug!("IPC peer gone, cancelling request {:?}", request_id);
+                        writer.write_all(&wire::encode_message(&cancel, 0)?).await?;
+                        writer.flush().await?;
+                        response.await?
+                    }
+                }
             }
             TransportConn::Quic(quic) => {
-                Ok(quic.send_and_receive(msg).await?)
+                let response = quic.send_and_receive_tracked(msg, lane, &self.compression);
+                tokio::pin!(response);
+                let finished = tokio::select! {
+                    result = &mut response => Some(result),
+                    _ = peer_gone.wait_for(|gone| *gone) => None,
+                };
+                let response = match finished {
+                    Some(result) => result?,
+                    None => {
+                        debug!("IPC peer gone, cancelling request {:?}", request_id);
+                        quic.send_oneway(&cancel).await?;
+                        response.await?
+                    }
+                };
+                close_lane(quic, msg);
+                response
+            }
+        };
+        Ok(self.answer_unsupported(msg, response))
+    }
+}
+
+/// Close the lane of a destroyed stream.
+fn close_lane(quic: &QuicConnection, msg: &Message) {
+    if let Message::CudaCommand {
+        command: CudaCommand::StreamDestroy { stream },
+        ..
+    } = msg
+    {
+        quic.close_lane(stream.resource_id);
+    }
+}
+
+/// Stop compressing what is sent to a server once it's clear it isn't paying
+/// off for this session's data.
+fn auto_tune_compression(stats: &CompressionStats, address: &str) {
+    if stats.auto_tune() {
+        info!(
+            "compression ratio to {} is {:.2}, below {}; sending uncompressed",
+            address,
+            stats.summary().ratio(),
+            wire::AUTO_DISABLE_RATIO
+        );
+    }
+}
+
+/// Session name and labels announced to every server this daemon connects
+/// to. Seeded from the config, updated by applications via `SetSessionInfo`.
+static SESSION_TAGS: std::sync::Mutex<(Option<String>, BTreeMap<String, String>)> =
+    std::sync::Mutex::new((None, BTreeMap::new()));
+
+/// Merge a name and labels into [`SESSION_TAGS`].
+fn update_session_tags(name: Option<String>, labels: Vec<(String, String)>) {
+    let mut tags = SESSION_TAGS.lock().unwrap();
+    if name.is_some() {
+        tags.0 = name;
+    }
+    tags.1.extend(labels);
+}
+
+/// The current tags as a `SetSessionInfo`, or `None` if there are none.
+fn session_info_message() -> Option<Message> {
+    let tags = SESSION_TAGS.lock().unwrap();
+    if tags.0.is_none() && tags.1.is_empty() {
+        return None;
+    }
+    Some(Message::SetSessionInfo {
+        name: tags.0.clone(),
+        labels: tags.1.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+    })
+}
+
+/// Send the current session tags on a freshly authenticated connection.
+async fn announce_session(conn: &mut ServerConn) {
+    let Some(msg) = session_info_message() else {
+        return;
+    };
+    if let Err(e) = conn.send_and_receive(&msg).await {
+        warn!("failed to send session info to {}: {}", conn.address, e);
+    }
+}
+
+/// With `transport = "rdma"`, move the bulk data of a fresh TCP connection
+/// onto RDMA. If either side can't, it stays on TCP.
+async fn attach_rdma(conn: &mut ServerConn, endpoint: &ServerEndpoint) {
+    if endpoint.transport != TransportMode::Rdma {
+        return;
+    }
+    let config = &endpoint.rdma;
+    let mut link = match RdmaLink::open(config.device.as_deref(), config.port, config.gid_index) {
+        Ok(link) => link,
+        Err(e) => {
+            warn!("can't open RDMA for {}, using TCP: {}", conn.address, e);
+            return;
+        }
+    };
+    // If the server connected its side but this one fails, its writes fail
+    // too, and it sends over TCP instead.
+    match conn.send_and_receive(&Message::RdmaConnect(link.local())).await {
+        Ok(Message::RdmaConnect(remote)) => match link.connect(&remote) {
+            Ok(()) => {
+                info!("bulk data to {} over RDMA on {}", conn.address, link.device());
+                if let TransportConn::Stream { rdma, .. } = &mut conn.transport {
+                    *rdma = Some(Box::new(link));
+                }
+            }
+            Err(e) => warn!("can't connect RDMA to {}, using TCP: {}", conn.address, e),
+        },
+        Ok(Message::Error(e)) => warn!("{} can't do RDMA, using TCP: {}", conn.address, e),
+        Ok(other) => warn!("unexpected response to RdmaConnect from {}: {:?}", conn.address, other),
+        Err(e) => warn!("failed to set up RDMA with {}: {}", conn.address, e),
+    }
+}
+
+/// Session this daemon last had on each server and its resume token, by
+/// address.
+static SESSIONS: std::sync::Mutex<BTreeMap<String, (u32, Option<u64>)>> = std::sync::Mutex::new(BTreeMap::new());
+
+fn remember_session(conn: &ServerConn) {
+    if let Some(session_id) = conn.session_id {
+        SESSIONS
+            .lock()
+            .unwrap()
+            .insert(conn.address.clone(), (session_id, conn.resume_token));
+    }
+}
+
+/// On a reconnect, take the previous session over if the server still keeps
+/// it, so the handles applications hold stay valid. Otherwise ask the server
+/// to give the new session the GPUs the previous one had, and warn about
+/// those it couldn't: state tuned for them no longer applies.
+async fn resume_session(conn: &mut ServerConn) {
+    let previous = SESSIONS.lock().unwrap().get(&conn.address).copied();
+    remember_session(conn);
+    let Some((session_id, resume_token)) = previous else {
+        return;
+    };
+    match conn.send_and_receive(&Message::ResumeSession { session_id, resume_token }).await {
+        Ok(Message::SessionResumed { resumed: true, restored, changed }) => {
+            conn.restored = restored;
+            if restored > 0 {
+                info!("resumed session {} on {} with {} object(s)", session_id, conn.address, restored);
+            } else {
+                info!("resumed session {} on {}", session_id, conn.address);
+            }
+            for change in changed {
+                warn!(
+                    "device changed on {}: CUDA device {} was {} and is now {}",
+                    conn.address,
+                    change.ordinal,
+                    format_uuid(&change.previous),
+                    change.current.map_or("gone".to_string(), |uuid| format_uuid(&uuid))
+                );
+            }
+        }
+        Ok(Message::SessionResumed { resumed: false, .. }) => {
+            debug!("{} can't resume session {}", conn.address, session_id);
+        }
+        Ok(other) => warn!("unexpected response to ResumeSession from {}: {:?}", conn.address, other),
+        Err(e) => warn!("failed to resume session on {}: {}", conn.address, e),
+    }
+}
+
+/// A GPU UUID as `nvidia-smi -L` prints it.
+fn format_uuid(uuid: &[u8; 16]) -> String {
+    let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("GPU-{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// GPU lists servers reported since the reconnection loop last put them in
+/// the pool, by address.
+static TOPOLOGY: std::sync::Mutex<BTreeMap<String, Vec<GpuInfo>>> = std::sync::Mutex::new(BTreeMap::new());
+
+/// Note the GPUs the server at `address` has now.
+fn note_topology(address: &str, gpus: Vec<GpuInfo>) {
+    TOPOLOGY.lock().unwrap().insert(address.to_string(), gpus);
+}
+
+/// Note the GPU lists a QUIC server pushes, until the connection closes.
+fn watch_pushed(quic: &QuicConnection, address: String) {
+    let quic = quic.clone();
+    tokio::spawn(async move {
+        loop {
+            match quic.accept_pushed().await {
+                Ok(Message::ServerTopologyChanged { gpus }) => note_topology(&address, gpus),
+                Ok(other) => debug!("unexpected push from {}: {:?}", address, other),
+                Err(e) => {
+                    debug!("no more pushes from {}: {}", address, e);
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Put the GPU lists servers reported into the pool.
+async fn apply_topology(
+    endpoints: &[ServerEndpoint],
+    pool_manager: &GpuPoolManager,
+    cached_gpus: &tokio::sync::RwLock<Vec<GpuInfo>>,
+) {
+    let reported = std::mem::take(&mut *TOPOLOGY.lock().unwrap());
+    let mut changed = false;
+    for (address, gpus) in reported {
+        for (i, _) in endpoints.iter().enumerate().filter(|(_, endpoint)| endpoint.address == address) {
+            if pool_manager.replace_server_gpus(i, gpus.clone()).await {
+                info!("server {} now has {} GPU(s)", address, gpus.len());
+                changed = true;
             }
         }
     }
+    if changed {
+        pool_manager.apply_ordering().await;
+        *cached_gpus.write().await = pool_manager.listed_gpus().await;
+    }
+}
+
+/// Compression of what the daemon sends, from the config.
+static COMPRESSION: std::sync::OnceLock<CompressionSettings> = std::sync::OnceLock::new();
+
+/// Compression for a server connection.
+fn compression_stats() -> Arc<CompressionStats> {
+    let stats = CompressionStats::default();
+    stats.configure(COMPRESSION.get().copied().unwrap_or_default());
+    Arc::new(stats)
+}
+
+/// Interval of the health checks on server connections, from the config.
+static HEALTH_CHECK: std::sync::OnceLock<Duration> = std::sync::OnceLock::new();
+
+fn health_check() -> Duration {
+    HEALTH_CHECK.get().copied().unwrap_or(Duration::from_secs(5))
+}
+
+/// Connect to a server over TCP, with keepalive probes at the health check
+/// interval.
+async fn connect_tcp(address: &str) -> std::io::Result<TcpStream> {
+    let stream = TcpStream::connect(address).await?;
+    if let Err(e) = health::tcp_keepalive(&stream, health_check()) {
+        debug!("no TCP keepalive for {}: {}", address, e);
+    }
+    Ok(stream)
+}
+
+/// Open the byte stream to a server: TCP, or a WebSocket tunnel with
+/// `transport = "websocket"`.
+async fn connect_stream(
+    endpoint: &ServerEndpoint,
+) -> Result<(StreamReader, StreamWriter), Box<dyn std::error::Error + Send + Sync>> {
+    if endpoint.transport == TransportMode::WebSocket {
+        let ws = websocket::connect(&endpoint.address, endpoint.ca_cert.as_deref(), Some(health_check())).await?;
+        let (reader, writer) = tokio::io::split(ws);
+        return Ok((Box::new(reader), Box::new(writer)));
+    }
+    let (reader, writer) = connect_tcp(&endpoint.address).await?.into_split();
+    Ok((Box::new(reader), Box::new(writer)))
+}
+
+/// What to do about incompatible builds, from the config.
+static VERSION_CHECK: std::sync::OnceLock<VersionCheck> = std::sync::OnceLock::new();
+
+/// Build of each connected server by address, reported to applications
+/// that send `QueryBuildInfo`.
+static SERVER_BUILDS: std::sync::Mutex<BTreeMap<String, BuildInfo>> =
+    std::sync::Mutex::new(BTreeMap::new());
+
+fn daemon_build() -> BuildInfo {
+    BuildInfo::current("rgpu-client")
+}
+
+/// Log how a peer's build relates to this one. `Err` if they can't work
+/// together and the config says to refuse.
+fn report_compatibility(peer: &BuildInfo, bridged: bool) -> Result<(), ProtocolError> {
+    match daemon_build().check(peer, bridged) {
+        Compatibility::Same => Ok(()),
+        Compatibility::Differs(reason) => {
+            warn!("version difference: {}", reason);
+            Ok(())
+        }
+        Compatibility::Incompatible(reason) => {
+            if VERSION_CHECK.get().copied().unwrap_or_default() == VersionCheck::Refuse {
+                error!("refusing incompatible build: {}", reason);
+                Err(ProtocolError::VersionMismatch(reason))
+            } else {
+                warn!("incompatible build, expect decode errors: {}", reason);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Swap builds with a freshly authenticated server and check its build.
+/// Servers that predate the exchange are identified by protocol version.
+async fn exchange_build_info(
+    conn: &mut ServerConn,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let reply = conn
+        .send_and_receive(&Message::QueryBuildInfo(daemon_build()))
+        .await;
+    let build = match reply {
+        Ok(Message::BuildInfo(builds)) => builds.into_iter().next(),
+        Ok(_) => None,
+        Err(e) => {
+            warn!("failed to query build of {}: {}", conn.address, e);
+            None
+        }
+    };
+    let build = build
+        .unwrap_or_else(|| BuildInfo {
+            component: "rgpu-server".to_string(),
+            version: "unknown".to_string(),
+            git_hash: None,
+            protocol_version: conn.version,
+            address: None,
+        })
+        .with_address(conn.address.clone());
+    info!("server build: {}", build);
+    report_compatibility(&build, true)?;
+    SERVER_BUILDS.lock().unwrap().insert(conn.address.clone(), build);
+    Ok(())
 }
 
 /// The RGPU client daemon. Connects to servers, manages the GPU pool,
@@ -61,7 +610,7 @@ pub struct ClientDaemon {
     /// Cached GPU info from connected servers (and local GPUs)
     cached_gpus: Arc<tokio::sync::RwLock<Vec<GpuInfo>>>,
     /// Persistent server connections (one per server, behind Mutex for exclusive access)
-    server_conns: Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: ServerConns,
     /// Server endpoints for reconnection
     endpoints: Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     /// Local CUDA executor for include_local_gpus (executes CUDA commands on the client's own GPU)
@@ -74,7 +623,11 @@ pub struct ClientDaemon {
 
 impl ClientDaemon {
     pub fn new(config: ClientConfig) -> Self {
-        let ordering = config.gpu_ordering.clone();
+        let pool_manager = GpuPoolManager::new(
+            config.gpu_ordering.clone(),
+            config.gpu_uuids.clone(),
+            config.stable_gpu_indices,
+        );
 
         // If include_local_gpus is enabled, discover and initialize local GPU executors
         let (local_cuda, local_vulkan, local_session) = if config.include_local_gpus {
@@ -99,7 +652,7 @@ impl ClientDaemon {
 
         Self {
             config,
-            pool_manager: Arc::new(GpuPoolManager::new(ordering)),
+            pool_manager: Arc::new(pool_manager),
             cached_gpus: Arc::new(tokio::sync::RwLock::new(Vec::new())),
             server_conns: Arc::new(tokio::sync::RwLock::new(Vec::new())),
             endpoints: Arc::new(tokio::sync::RwLock::new(Vec::new())),
@@ -111,7 +664,14 @@ impl ClientDaemon {
 
     /// Start the client daemon: connect to servers and start IPC listener.
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        info!("RGPU client daemon starting");
+        info!("RGPU client daemon starting ({})", daemon_build());
+        let _ = VERSION_CHECK.set(self.config.version_check);
+        let _ = COMPRESSION.set(self.config.compression.settings());
+        let _ = HEALTH_CHECK.set(Duration::from_secs(self.config.health_check_secs.max(1)));
+        update_session_tags(
+            self.config.session_name.clone(),
+            self.config.session_labels.clone().into_iter().collect(),
+        );
 
         // Connect to all configured servers and discover GPUs
         for (server_index, server) in self.config.servers.iter().enumerate() {
@@ -180,8 +740,9 @@ impl ClientDaemon {
         let reconnect_conns = self.server_conns.clone();
         let reconnect_endpoints = self.endpoints.clone();
         let reconnect_pool = self.pool_manager.clone();
+        let reconnect_gpus = self.cached_gpus.clone();
         tokio::spawn(async move {
-            reconnection_loop(reconnect_conns, reconnect_endpoints, reconnect_pool).await;
+            reconnection_loop(reconnect_conns, reconnect_endpoints, reconnect_pool, reconnect_gpus).await;
         });
 
         // Start IPC listener for local applications
@@ -193,14 +754,30 @@ impl ClientDaemon {
         let local_cuda = self.local_cuda_executor.clone();
         let local_vulkan = self.local_vulkan_executor.clone();
         let local_session = self.local_session.clone();
+        let mirror = self.config.mirror.clone().map(|config| Arc::new(Mirror::start(config)));
+        let readback = self
+            .config
+            .readback_diff
+            .then(|| Arc::new(ReadbackCache::new(self.config.readback_diff_cache_mb)));
+        let prefetcher = self
+            .config
+            .dtoh_prefetch
+            .then(|| Arc::new(Prefetcher::new(Spill::from_config(&self.config))));
+        let placement = (self.config.placement.policy != PlacementPolicy::None)
+            .then(|| Placement::new(self.config.placement.clone()));
+        let relocations = (self.config.failover || placement.is_some())
+            .then(|| Arc::new(Relocations::new(self.config.failover, placement)));
 
         info!("starting IPC listener on {}", ipc_path);
 
-        let ipc_future = crate::ipc::start_ipc_listener(&ipc_path, move |msg| {
+        let breadcrumbs = crate::breadcrumbs::BreadcrumbSettings::from_config(&self.config);
+        let shared_memory_mb = self.config.ipc_shared_memory_mb;
+        let ipc_future = crate::ipc::start_ipc_listener(&ipc_path, breadcrumbs, shared_memory_mb, move |msg, peer_gone, contexts| {
             handle_ipc_message(
                 &cached_gpus, &server_conns, &endpoints, &pool_manager,
                 &local_cuda, &local_vulkan, &local_session,
-                msg,
+                &mirror, &readback, &prefetcher, &relocations,
+                msg, peer_gone, contexts,
             )
         });
 
@@ -221,19 +798,30 @@ impl ClientDaemon {
     ) -> Result<(Vec<GpuInfo>, ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
         info!("connecting to server: {} ({:?})", endpoint.address, endpoint.transport);
 
-        match endpoint.transport {
+        let connected = match transport_probe::resolve(endpoint).await {
             TransportMode::Quic => self.connect_and_discover_quic(endpoint).await,
-            TransportMode::Tcp => self.connect_and_discover_tcp(endpoint).await,
+            _ => self.connect_and_discover_tcp(endpoint).await,
+        };
+        if connected.is_err() {
+            transport_probe::forget(endpoint).await;
+        }
+        let (gpus, mut conn, server_id) = connected?;
+        if let TransportConn::Quic(quic) = &conn.transport {
+            watch_pushed(quic, conn.address.clone());
         }
+        attach_rdma(&mut conn, endpoint).await;
+        remember_session(&conn);
+        announce_session(&mut conn).await;
+        exchange_build_info(&mut conn).await?;
+        Ok((gpus, conn, server_id))
     }
 
-    /// TCP connect + handshake.
+    /// TCP or WebSocket connect + handshake.
     async fn connect_and_discover_tcp(
         &self,
         endpoint: &ServerEndpoint,
     ) -> Result<(Vec<GpuInfo>, ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
-        let stream = TcpStream::connect(&endpoint.address).await?;
-        let (mut reader, mut writer) = stream.into_split();
+        let (mut reader, mut writer) = connect_stream(endpoint).await?;
 
         // Send Hello
         let hello = Message::Hello {
@@ -243,14 +831,14 @@ impl ClientDaemon {
         };
         let frame = wire::encode_message(&hello, 0)?;
         writer.write_all(&frame).await?;
+        writer.flush().await?;
 
         // Read server Hello
-        let server_hello = read_message(&mut reader).await?;
+        let server_hello = read_message(&mut reader, None)
+            .await
+            .map_err(|e| unreadable_hello(endpoint, e))?;
 
-        let challenge = match &server_hello {
-            Message::Hello { challenge, .. } => challenge.clone().unwrap_or_default(),
-            _ => Vec::new(),
-        };
+        let (challenge, version) = parse_server_hello(&server_hello, endpoint)?;
 
         // Send auth
         let challenge_response = auth::compute_challenge_response(&endpoint.token, &challenge);
@@ -260,11 +848,12 @@ impl ClientDaemon {
         };
         let frame = wire::encode_message(&auth_msg, 0)?;
         writer.write_all(&frame).await?;
+        writer.flush().await?;
 
         // Read auth result
-        let auth_result = read_message(&mut reader).await?;
+        let auth_result = read_message(&mut reader, None).await?;
 
-        parse_auth_result(auth_result, endpoint, TransportConn::Tcp { reader, writer })
+        parse_auth_result(auth_result, endpoint, TransportConn::Stream { reader, writer, rdma: None }, version)
     }
 
     /// QUIC connect + handshake.
@@ -272,7 +861,7 @@ impl ClientDaemon {
         &self,
         endpoint: &ServerEndpoint,
     ) -> Result<(Vec<GpuInfo>, ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
-        let quic_conn = rgpu_transport::quic::connect_quic_client(&endpoint.address).await?;
+        let quic_conn = rgpu_transport::quic::connect_quic_client_checked(&endpoint.address, Some(health_check())).await?;
 
         // Perform handshake over QUIC
         let hello = Message::Hello {
@@ -282,10 +871,7 @@ impl ClientDaemon {
         };
         let server_hello = quic_conn.send_and_receive(&hello).await?;
 
-        let challenge = match &server_hello {
-            Message::Hello { challenge, .. } => challenge.clone().unwrap_or_default(),
-            _ => Vec::new(),
-        };
+        let (challenge, version) = parse_server_hello(&server_hello, endpoint)?;
 
         let challenge_response = auth::compute_challenge_response(&endpoint.token, &challenge);
         let auth_msg = Message::Authenticate {
@@ -294,32 +880,96 @@ impl ClientDaemon {
         };
         let auth_result = quic_conn.send_and_receive(&auth_msg).await?;
 
-        parse_auth_result(auth_result, endpoint, TransportConn::Quic(quic_conn))
+        parse_auth_result(auth_result, endpoint, TransportConn::Quic(quic_conn), version)
     }
 }
 
-/// Read a single framed message from a reader.
+/// Read a single framed message from a reader, with its payload out of the
+/// RDMA ring if it was sent there.
 async fn read_message<R: tokio::io::AsyncRead + Unpin>(
     reader: &mut R,
+    rdma: Option<&RdmaLink>,
 ) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
     let mut header_buf = [0u8; wire::HEADER_SIZE];
     reader.read_exact(&mut header_buf).await?;
     let (flags, _, payload_len) = wire::decode_header(&header_buf)?;
     let mut payload = vec![0u8; payload_len as usize];
     reader.read_exact(&mut payload).await?;
+    let (flags, payload) = rdma::unshare(rdma, flags, payload)?;
     let msg = wire::decode_message(&payload, flags)?;
     Ok(msg)
 }
 
+/// Read the response to a request, noting the GPU lists the server at
+/// `address` pushed ahead of it.
+async fn read_response<R: tokio::io::AsyncRead + Unpin>(
+    reader: &mut R,
+    rdma: Option<&RdmaLink>,
+    address: &str,
+) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+    loop {
+        match read_message(reader, rdma).await? {
+            Message::ServerTopologyChanged { gpus } => note_topology(address, gpus),
+            msg => return Ok(msg),
+        }
+    }
+}
+
+/// Challenge and negotiated protocol version from the server's Hello.
+fn parse_server_hello(
+    server_hello: &Message,
+    endpoint: &ServerEndpoint,
+) -> Result<(Vec<u8>, u32), Box<dyn std::error::Error + Send + Sync>> {
+    let (challenge, peer_version) = match server_hello {
+        Message::Hello {
+            challenge,
+            protocol_version,
+            ..
+        } => (challenge.clone().unwrap_or_default(), *protocol_version),
+        Message::Error(ProtocolError::VersionMismatch(reason)) => {
+            return Err(format!("{}: {}", endpoint.address, reason).into());
+        }
+        other => return Err(format!("{}: expected Hello, got {:?}", endpoint.address, other).into()),
+    };
+    let version = compat::negotiate(peer_version)
+        .map_err(|e| format!("{}: {}", endpoint.address, e))?;
+    if version < PROTOCOL_VERSION {
+        info!(
+            "server {} speaks protocol v{}, bridging from v{}",
+            endpoint.address, version, PROTOCOL_VERSION
+        );
+    }
+    Ok((challenge, version))
+}
+
+/// `error` from reading a server's Hello, explaining a payload that doesn't
+/// decode: the server speaks a protocol version with another encoding.
+fn unreadable_hello(
+    endpoint: &ServerEndpoint,
+    error: Box<dyn std::error::Error + Send + Sync>,
+) -> Box<dyn std::error::Error + Send + Sync> {
+    match error.downcast_ref::<wire::WireError>() {
+        Some(wire::WireError::Serialization(_)) => format!(
+            "{}: can't decode the server's Hello ({}); it speaks a protocol version this v{} daemon can't bridge to",
+            endpoint.address, error, PROTOCOL_VERSION
+        )
+        .into(),
+        _ => error,
+    }
+}
+
 /// Parse an AuthResult message into GPUs + ServerConn.
 fn parse_auth_result(
     auth_result: Message,
     endpoint: &ServerEndpoint,
     transport: TransportConn,
+    version: u32,
 ) -> Result<(Vec<GpuInfo>, ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
     match auth_result {
         Message::AuthResult {
             success: true,
+            session_id,
+            resume_token,
             server_id,
             available_gpus,
             ..
@@ -337,8 +987,14 @@ fn parse_auth_result(
             );
             let conn = ServerConn {
                 transport,
-                _address: endpoint.address.clone(),
+                address: endpoint.address.clone(),
                 _token: endpoint.token.clone(),
+                version,
+                session_id,
+                resume_token,
+                restored: 0,
+                compression: compression_stats(),
+                link: Arc::default(),
             };
             Ok((available_gpus, conn, sid))
         }
@@ -356,23 +1012,34 @@ fn parse_auth_result(
 }
 
 /// Establish a new authenticated connection to a server.
-async fn reconnect(
+pub(crate) async fn reconnect(
     endpoint: &ServerEndpoint,
 ) -> Result<(ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
     info!("reconnecting to server: {} ({:?})", endpoint.address, endpoint.transport);
 
-    match endpoint.transport {
+    let connected = match transport_probe::resolve(endpoint).await {
         TransportMode::Quic => reconnect_quic(endpoint).await,
-        TransportMode::Tcp => reconnect_tcp(endpoint).await,
+        _ => reconnect_tcp(endpoint).await,
+    };
+    if connected.is_err() {
+        transport_probe::forget(endpoint).await;
+    }
+    let (mut conn, server_id) = connected?;
+    if let TransportConn::Quic(quic) = &conn.transport {
+        watch_pushed(quic, conn.address.clone());
     }
+    attach_rdma(&mut conn, endpoint).await;
+    resume_session(&mut conn).await;
+    announce_session(&mut conn).await;
+    exchange_build_info(&mut conn).await?;
+    Ok((conn, server_id))
 }
 
-/// TCP reconnect.
-async fn reconnect_tcp(
+/// TCP or WebSocket reconnect.
+pub(crate) async fn reconnect_tcp(
     endpoint: &ServerEndpoint,
 ) -> Result<(ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
-    let stream = TcpStream::connect(&endpoint.address).await?;
-    let (mut reader, mut writer) = stream.into_split();
+    let (mut reader, mut writer) = connect_stream(endpoint).await?;
 
     // Hello
     let hello = Message::Hello {
@@ -382,12 +1049,12 @@ async fn reconnect_tcp(
     };
     let frame = wire::encode_message(&hello, 0)?;
     writer.write_all(&frame).await?;
+    writer.flush().await?;
 
-    let server_hello = read_message(&mut reader).await?;
-    let challenge = match &server_hello {
-        Message::Hello { challenge, .. } => challenge.clone().unwrap_or_default(),
-        _ => Vec::new(),
-    };
+    let server_hello = read_message(&mut reader, None)
+        .await
+        .map_err(|e| unreadable_hello(endpoint, e))?;
+    let (challenge, version) = parse_server_hello(&server_hello, endpoint)?;
 
     // Auth
     let challenge_response = auth::compute_challenge_response(&endpoint.token, &challenge);
@@ -397,21 +1064,33 @@ async fn reconnect_tcp(
     };
     let frame = wire::encode_message(&auth_msg, 0)?;
     writer.write_all(&frame).await?;
+    writer.flush().await?;
 
-    let auth_result = read_message(&mut reader).await?;
+    let auth_result = read_message(&mut reader, None).await?;
     match auth_result {
         Message::AuthResult {
             success: true,
+            session_id,
+            resume_token,
             server_id,
+            available_gpus,
             ..
         } => {
             let sid = server_id.unwrap_or(0);
+            // A server down at startup has no GPUs in the pool yet
+            note_topology(&endpoint.address, available_gpus);
             info!("reconnected to server {} (id={})", endpoint.address, sid);
             Ok((
                 ServerConn {
-                    transport: TransportConn::Tcp { reader, writer },
-                    _address: endpoint.address.clone(),
+                    transport: TransportConn::Stream { reader, writer, rdma: None },
+                    address: endpoint.address.clone(),
                     _token: endpoint.token.clone(),
+                    version,
+                    session_id,
+                    resume_token,
+                    restored: 0,
+                    compression: compression_stats(),
+                    link: Arc::default(),
                 },
                 sid,
             ))
@@ -421,10 +1100,10 @@ async fn reconnect_tcp(
 }
 
 /// QUIC reconnect.
-async fn reconnect_quic(
+pub(crate) async fn reconnect_quic(
     endpoint: &ServerEndpoint,
 ) -> Result<(ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
-    let quic_conn = rgpu_transport::quic::connect_quic_client(&endpoint.address).await?;
+    let quic_conn = rgpu_transport::quic::connect_quic_client_checked(&endpoint.address, Some(health_check())).await?;
 
     // Hello
     let hello = Message::Hello {
@@ -433,10 +1112,7 @@ async fn reconnect_quic(
         challenge: None,
     };
     let server_hello = quic_conn.send_and_receive(&hello).await?;
-    let challenge = match &server_hello {
-        Message::Hello { challenge, .. } => challenge.clone().unwrap_or_default(),
-        _ => Vec::new(),
-    };
+    let (challenge, version) = parse_server_hello(&server_hello, endpoint)?;
 
     // Auth
     let challenge_response = auth::compute_challenge_response(&endpoint.token, &challenge);
@@ -449,16 +1125,27 @@ async fn reconnect_quic(
     match auth_result {
         Message::AuthResult {
             success: true,
+            session_id,
+            resume_token,
             server_id,
+            available_gpus,
             ..
         } => {
             let sid = server_id.unwrap_or(0);
+            // A server down at startup has no GPUs in the pool yet
+            note_topology(&endpoint.address, available_gpus);
             info!("reconnected to server {} via QUIC (id={})", endpoint.address, sid);
             Ok((
                 ServerConn {
                     transport: TransportConn::Quic(quic_conn),
-                    _address: endpoint.address.clone(),
+                    address: endpoint.address.clone(),
                     _token: endpoint.token.clone(),
+                    version,
+                    session_id,
+                    resume_token,
+                    restored: 0,
+                    compression: compression_stats(),
+                    link: Arc::default(),
                 },
                 sid,
             ))
@@ -471,7 +1158,7 @@ async fn reconnect_quic(
 
 /// Extract the primary NetworkHandle from a CUDA command for routing.
 /// Returns None for creation/global commands that don't target a specific server.
-fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
+pub(crate) fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
     match cmd {
         // Global / creation commands — no routing handle
         CudaCommand::Init { .. }
@@ -491,7 +1178,10 @@ fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
         | CudaCommand::CtxGetFlags
         | CudaCommand::CtxSetFlags { .. }
         | CudaCommand::CtxResetPersistingL2Cache
+        | CudaCommand::CtxSetSharedMemConfig { .. }
+        | CudaCommand::CtxGetSharedMemConfig
         | CudaCommand::ModuleLoadData { .. }
+        | CudaCommand::ModuleLoadByHash { .. }
         | CudaCommand::ModuleLoad { .. }
         | CudaCommand::ModuleLoadDataEx { .. }
         | CudaCommand::ModuleLoadFatBinary { .. }
@@ -505,7 +1195,9 @@ fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
         | CudaCommand::MemGetInfo
         | CudaCommand::StreamCreate { .. }
         | CudaCommand::StreamCreateWithPriority { .. }
-        | CudaCommand::EventCreate { .. } => None,
+        | CudaCommand::EventCreate { .. }
+        | CudaCommand::GraphCreate { .. }
+        | CudaCommand::ArrayCreate { .. } => None,
 
         // Device management — route via device handle
         CudaCommand::DeviceGetName { device, .. }
@@ -514,6 +1206,7 @@ fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
         | CudaCommand::DeviceComputeCapability { device, .. }
         | CudaCommand::DeviceGetUuid { device }
         | CudaCommand::DeviceGetPCIBusId { device }
+        | CudaCommand::DeviceGetUsage { device }
         | CudaCommand::DeviceGetDefaultMemPool { device }
         | CudaCommand::DeviceGetMemPool { device }
         | CudaCommand::DeviceSetMemPool { device, .. }
@@ -557,6 +1250,13 @@ fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
         CudaCommand::MemcpyDtoD { dst, .. } => Some(*dst),
         CudaCommand::MemcpyHtoDAsync { dst, .. } => Some(*dst),
         CudaCommand::MemcpyDtoHAsync { src, .. } => Some(*src),
+        CudaCommand::MemcpyDtoHDiff { src, .. } => Some(*src),
+        CudaCommand::MemSetTransferCodec { dptr, .. } => Some(*dptr),
+        CudaCommand::MemcpyHtoDEncoded { dst, .. } => Some(*dst),
+        CudaCommand::FillBufferTyped { dst, .. } => Some(*dst),
+        CudaCommand::Memcpy3D { params, .. } | CudaCommand::Memcpy3DAsync { params, .. } => {
+            params.dst.handle.or(params.src.handle)
+        }
         CudaCommand::MemcpyDtoDAsync { dst, .. } => Some(*dst),
         CudaCommand::MemsetD8 { dst, .. } => Some(*dst),
         CudaCommand::MemsetD16 { dst, .. } => Some(*dst),
@@ -564,11 +1264,19 @@ fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
         CudaCommand::MemsetD8Async { dst, .. } => Some(*dst),
         CudaCommand::MemsetD16Async { dst, .. } => Some(*dst),
         CudaCommand::MemsetD32Async { dst, .. } => Some(*dst),
+        CudaCommand::MemsetD2D8 { dst, .. }
+        | CudaCommand::MemsetD2D16 { dst, .. }
+        | CudaCommand::MemsetD2D32 { dst, .. }
+        | CudaCommand::MemsetD2D8Async { dst, .. }
+        | CudaCommand::MemsetD2D16Async { dst, .. }
+        | CudaCommand::MemsetD2D32Async { dst, .. } => Some(*dst),
         CudaCommand::MemGetAddressRange { dptr } => Some(*dptr),
         CudaCommand::MemFreeHost { ptr } => Some(*ptr),
         CudaCommand::MemHostGetDevicePointer { host_ptr, .. } => Some(*host_ptr),
         CudaCommand::MemHostGetFlags { host_ptr } => Some(*host_ptr),
         CudaCommand::MemHostUnregister { ptr } => Some(*ptr),
+        CudaCommand::HostMemWrite { ptr, .. } => Some(*ptr),
+        CudaCommand::HostMemRead { ptr, .. } => Some(*ptr),
         CudaCommand::MemPrefetchAsync { dptr, .. } => Some(*dptr),
         CudaCommand::MemAdvise { dptr, .. } => Some(*dptr),
         CudaCommand::MemRangeGetAttribute { dptr, .. } => Some(*dptr),
@@ -595,6 +1303,16 @@ fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
         CudaCommand::StreamGetPriority { stream } => Some(*stream),
         CudaCommand::StreamGetFlags { stream } => Some(*stream),
         CudaCommand::StreamGetCtx { stream } => Some(*stream),
+        CudaCommand::StreamBeginCapture { stream, .. } => Some(*stream),
+        CudaCommand::StreamEndCapture { stream } => Some(*stream),
+        CudaCommand::StreamIsCapturing { stream } => Some(*stream),
+
+        // Graphs — route via graph or executable graph handle
+        CudaCommand::GraphDestroy { graph } => Some(*graph),
+        CudaCommand::GraphInstantiate { graph, .. } => Some(*graph),
+        CudaCommand::GraphExecDestroy { exec } => Some(*exec),
+        CudaCommand::GraphLaunch { exec, .. } => Some(*exec),
+        CudaCommand::GraphUpload { exec, .. } => Some(*exec),
 
         // Event management — route via event handle
         CudaCommand::EventDestroy { event, .. } => Some(*event),
@@ -604,6 +1322,30 @@ fn extract_cuda_routing_handle(cmd: &CudaCommand) -> Option<NetworkHandle> {
         CudaCommand::EventQuery { event, .. } => Some(*event),
         CudaCommand::EventElapsedTime { start, .. } => Some(*start),
 
+        // Texture and surface objects — route via the memory read or the object
+        CudaCommand::TexObjectCreate { resource, .. } | CudaCommand::SurfObjectCreate { resource } => {
+            Some(match &resource.resource {
+                TextureResource::Linear { dptr, .. } | TextureResource::Pitch2D { dptr, .. } => *dptr,
+                TextureResource::Array { array } => *array,
+            })
+        }
+        CudaCommand::TexObjectDestroy { tex_object } => Some(*tex_object),
+        CudaCommand::SurfObjectDestroy { surf_object } => Some(*surf_object),
+
+        // CUDA arrays — route via array handle
+        CudaCommand::ArrayDestroy { array } => Some(*array),
+
+        // Texture and surface references — route via module or reference
+        CudaCommand::ModuleGetTexRef { module, .. } | CudaCommand::ModuleGetSurfRef { module, .. } => {
+            Some(*module)
+        }
+        CudaCommand::TexRefSetAddress { texref, .. } | CudaCommand::TexRefSet { texref, .. } => Some(*texref),
+        CudaCommand::SurfRefSetArray { surfref, .. } => Some(*surfref),
+
+        // IPC memory handles — route via the exported allocation
+        CudaCommand::IpcGetMemHandle { dptr } | CudaCommand::IpcCloseMemHandle { dptr } => Some(*dptr),
+        CudaCommand::IpcOpenMemHandle { handle, .. } => Some(handle.memory),
+
         // Pointer queries — route via memory handle
         CudaCommand::PointerGetAttribute { ptr, .. } => Some(*ptr),
         CudaCommand::PointerGetAttributes { ptr, .. } => Some(*ptr),
@@ -642,7 +1384,8 @@ fn extract_vulkan_routing_handle(cmd: &VulkanCommand) -> Option<NetworkHandle> {
         | VulkanCommand::GetPhysicalDeviceMemoryProperties2 { physical_device, .. }
         | VulkanCommand::GetPhysicalDeviceQueueFamilyProperties { physical_device, .. }
         | VulkanCommand::GetPhysicalDeviceQueueFamilyProperties2 { physical_device, .. }
-        | VulkanCommand::GetPhysicalDeviceFormatProperties { physical_device, .. } => {
+        | VulkanCommand::GetPhysicalDeviceFormatProperties { physical_device, .. }
+        | VulkanCommand::GetPhysicalDeviceImageFormatProperties { physical_device, .. } => {
             Some(*physical_device)
         }
 
@@ -664,6 +1407,7 @@ fn extract_vulkan_routing_handle(cmd: &VulkanCommand) -> Option<NetworkHandle> {
         | VulkanCommand::BindBufferMemory { device, .. }
         | VulkanCommand::GetBufferMemoryRequirements { device, .. }
         | VulkanCommand::CreateShaderModule { device, .. }
+        | VulkanCommand::CreateShaderModuleByHash { device, .. }
         | VulkanCommand::DestroyShaderModule { device, .. }
         | VulkanCommand::CreateDescriptorSetLayout { device, .. }
         | VulkanCommand::DestroyDescriptorSetLayout { device, .. }
@@ -698,11 +1442,35 @@ fn extract_vulkan_routing_handle(cmd: &VulkanCommand) -> Option<NetworkHandle> {
         | VulkanCommand::DestroyFramebuffer { device, .. }
         | VulkanCommand::CreateGraphicsPipelines { device, .. }
         | VulkanCommand::CreateSemaphore { device, .. }
-        | VulkanCommand::DestroySemaphore { device, .. } => Some(*device),
+        | VulkanCommand::DestroySemaphore { device, .. }
+        | VulkanCommand::CreateSwapchain { device, .. }
+        | VulkanCommand::DestroySwapchain { device, .. }
+        | VulkanCommand::AcquireNextImage { device, .. }
+        | VulkanCommand::WaitSemaphores { device, .. }
+        | VulkanCommand::SignalSemaphore { device, .. }
+        | VulkanCommand::GetSemaphoreCounterValue { device, .. }
+        | VulkanCommand::CreateQueryPool { device, .. }
+        | VulkanCommand::DestroyQueryPool { device, .. }
+        | VulkanCommand::GetQueryPoolResults { device, .. }
+        | VulkanCommand::CreateSampler { device, .. }
+        | VulkanCommand::DestroySampler { device, .. }
+        | VulkanCommand::CreatePipelineCache { device, .. }
+        | VulkanCommand::DestroyPipelineCache { device, .. }
+        | VulkanCommand::GetPipelineCacheData { device, .. }
+        | VulkanCommand::MergePipelineCaches { device, .. }
+        | VulkanCommand::CreateBufferView { device, .. }
+        | VulkanCommand::DestroyBufferView { device, .. }
+        | VulkanCommand::CreateEvent { device, .. }
+        | VulkanCommand::DestroyEvent { device, .. }
+        | VulkanCommand::GetEventStatus { device, .. }
+        | VulkanCommand::SetEvent { device, .. }
+        | VulkanCommand::ResetEvent { device, .. } => Some(*device),
 
         // Queue commands
         VulkanCommand::QueueSubmit { queue, .. }
-        | VulkanCommand::QueueWaitIdle { queue, .. } => Some(*queue),
+        | VulkanCommand::QueueSubmit2 { queue, .. }
+        | VulkanCommand::QueueWaitIdle { queue, .. }
+        | VulkanCommand::QueuePresent { queue, .. } => Some(*queue),
 
         // Recorded commands route via command buffer
         VulkanCommand::SubmitRecordedCommands { command_buffer, .. } => Some(*command_buffer),
@@ -729,16 +1497,29 @@ async fn resolve_server_index(
 
 /// Handle an IPC message from a local application (Vulkan ICD or CUDA interpose lib).
 /// Always returns a valid response Message - never returns None which would hang the app.
+#[allow(clippy::too_many_arguments)]
 fn handle_ipc_message(
     cached_gpus: &Arc<tokio::sync::RwLock<Vec<GpuInfo>>>,
-    server_conns: &Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: &ServerConns,
     endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     pool_manager: &Arc<GpuPoolManager>,
     local_cuda_executor: &Option<Arc<rgpu_server::cuda_executor::CudaExecutor>>,
     local_vulkan_executor: &Option<Arc<rgpu_server::vulkan_executor::VulkanExecutor>>,
     local_session: &Option<Arc<rgpu_server::session::Session>>,
+    mirror: &Option<Arc<Mirror>>,
+    readback: &Option<Arc<ReadbackCache>>,
+    prefetcher: &Option<Arc<Prefetcher>>,
+    relocations: &Option<Arc<Relocations>>,
     msg: Message,
+    peer_gone: PeerGone,
+    contexts: Contexts,
 ) -> Option<Message> {
+    let caller = IpcCaller {
+        peer_gone,
+        deadline: Instant::now() + rgpu_common::platform::IPC_READ_TIMEOUT,
+        contexts,
+    };
+
     // Use block_in_place to bridge sync IPC to async forwarding without deadlocks
     match msg {
         Message::QueryGpus => {
@@ -754,6 +1535,12 @@ fn handle_ipc_message(
         Message::CudaCommand {
             request_id,
             command,
+            ..
+        }
+        | Message::CudaCommandStreamed {
+            request_id,
+            command,
+            ..
         } => {
             let conns = server_conns.clone();
             let eps = endpoints.clone();
@@ -766,7 +1553,8 @@ fn handle_ipc_message(
                     forward_cuda_command_pooled(
                         &conns, &eps, &pm,
                         &local_cuda, &local_sess,
-                        request_id, command,
+                        mirror, readback, prefetcher, relocations,
+                        request_id, command, caller,
                     ).await
                 })
             });
@@ -776,6 +1564,7 @@ fn handle_ipc_message(
         Message::VulkanCommand {
             request_id,
             command,
+            ..
         } => {
             let conns = server_conns.clone();
             let eps = endpoints.clone();
@@ -788,7 +1577,7 @@ fn handle_ipc_message(
                     forward_vulkan_command_pooled(
                         &conns, &eps, &pm,
                         &local_vk, &local_sess,
-                        request_id, command,
+                        request_id, command, caller,
                     ).await
                 })
             });
@@ -804,40 +1593,60 @@ fn handle_ipc_message(
 
             let response = tokio::task::block_in_place(|| {
                 tokio::runtime::Handle::current().block_on(async {
-                    // Determine server from first command in batch
-                    let routing_handle = commands.first()
-                        .and_then(|cmd| extract_cuda_routing_handle(cmd));
-                    let server_idx = resolve_server_index(&pm, routing_handle).await;
-
-                    // Check if this batch targets local GPU
-                    if server_idx == crate::pool_manager::LOCAL_SERVER_INDEX {
-                        if let (Some(executor), Some(session)) = (&local_cuda, &local_sess) {
-                            let mut last_error = None;
-                            for cmd in commands {
-                                let response = executor.execute(session, cmd);
-                                if let CudaResponse::Error { .. } = &response {
-                                    last_error = Some(Message::CudaResponse {
-                                        request_id: RequestId(0),
-                                        response,
-                                    });
-                                }
-                            }
-                            return last_error.unwrap_or_else(|| Message::CudaResponse {
-                                request_id: RequestId(0),
-                                response: CudaResponse::Success,
-                            });
-                        }
-                        return make_error_response(RequestId(0), true, "local GPU not available");
-                    }
+                    forward_cuda_batch(
+                        &conns, &eps, &pm,
+                        &local_cuda, &local_sess,
+                        mirror, relocations, &caller.contexts, commands,
+                    ).await
+                })
+            });
+            Some(response)
+        }
 
-                    let batch_msg = Message::CudaBatch(commands);
-                    let request_id = RequestId(0);
-                    forward_to_server(&conns, &eps, server_idx, request_id, &batch_msg, true).await
+        Message::CudaPipelined {
+            request_id,
+            batch,
+            command,
+        } => {
+            let conns = server_conns.clone();
+            let eps = endpoints.clone();
+            let pm = pool_manager.clone();
+            let local_cuda = local_cuda_executor.clone();
+            let local_sess = local_session.clone();
+
+            let response = tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(async {
+                    forward_cuda_pipelined(
+                        &conns, &eps, &pm,
+                        &local_cuda, &local_sess,
+                        mirror, readback, prefetcher, relocations,
+                        request_id, batch, command, caller,
+                    ).await
                 })
             });
             Some(response)
         }
 
+        Message::SetSessionInfo { name, labels } => {
+            info!("application set session info: name={:?} labels={:?}", name, labels);
+            update_session_tags(name, labels);
+            let conns = server_conns.clone();
+            tokio::task::block_in_place(|| {
+                tokio::runtime::Handle::current().block_on(announce_to_all(&conns))
+            });
+            Some(Message::Pong)
+        }
+
+        Message::QueryBuildInfo(app) => {
+            info!("application build: {}", app);
+            if let Err(e) = report_compatibility(&app, false) {
+                return Some(Message::Error(e));
+            }
+            let mut builds = vec![daemon_build()];
+            builds.extend(SERVER_BUILDS.lock().unwrap().values().cloned());
+            Some(Message::BuildInfo(builds))
+        }
+
         Message::Ping => Some(Message::Pong),
 
         _ => {
@@ -853,20 +1662,44 @@ fn handle_ipc_message(
     }
 }
 
+/// Re-send the session tags on every connected server.
+async fn announce_to_all(server_conns: &ServerConns) {
+    let slots: Vec<_> = server_conns.read().await.iter().cloned().collect();
+    for slot in slots {
+        if let Some(conn) = slot.lock().await.as_mut() {
+            announce_session(conn).await;
+        }
+    }
+}
+
 // ── CUDA Forwarding ──────────────────────────────────────────────────
 
 /// Forward a CUDA command using the pooled persistent connection.
 /// Routes to the correct server based on handle's server_id.
 /// If the target is the local GPU, executes directly via the local executor.
+#[allow(clippy::too_many_arguments)]
 async fn forward_cuda_command_pooled(
-    server_conns: &Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: &ServerConns,
     endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     pool_manager: &Arc<GpuPoolManager>,
     local_cuda_executor: &Option<Arc<rgpu_server::cuda_executor::CudaExecutor>>,
     local_session: &Option<Arc<rgpu_server::session::Session>>,
+    mirror: &Option<Arc<Mirror>>,
+    readback: &Option<Arc<ReadbackCache>>,
+    prefetcher: &Option<Arc<Prefetcher>>,
+    relocations: &Option<Arc<Relocations>>,
     request_id: RequestId,
-    command: CudaCommand,
+    mut command: CudaCommand,
+    caller: IpcCaller,
 ) -> Message {
+    if let Some(relocations) = relocations {
+        if let Err(response) =
+            relocate_context(server_conns, endpoints, pool_manager, relocations, request_id, &mut command).await
+        {
+            return response;
+        }
+    }
+
     // Special handling for DeviceGetCount: return pool total
     if matches!(command, CudaCommand::DeviceGetCount) {
         let count = pool_manager.cuda_device_count().await;
@@ -878,10 +1711,18 @@ async fn forward_cuda_command_pooled(
 
     // Special handling for DeviceGet: map pool ordinal to server-local ordinal
     if let CudaCommand::DeviceGet { ordinal } = &command {
-        if let Some((server_idx, server_local_ordinal)) = pool_manager
-            .server_for_pool_ordinal(*ordinal as u32)
-            .await
-        {
+        let ordinal = *ordinal as u32;
+        let target = match relocations {
+            Some(relocations) if relocations.failover => failover_target(server_conns, pool_manager, ordinal)
+                .await
+                .map(|gpu| (gpu.server_index, gpu.server_device_index)),
+            _ => None,
+        };
+        let target = match target {
+            Some(target) => Some(target),
+            None => pool_manager.server_for_pool_ordinal(ordinal).await,
+        };
+        if let Some((server_idx, server_local_ordinal)) = target {
             // Check if this is a local GPU
             if server_idx == crate::pool_manager::LOCAL_SERVER_INDEX {
                 if let (Some(executor), Some(session)) = (local_cuda_executor, local_session) {
@@ -897,7 +1738,8 @@ async fn forward_cuda_command_pooled(
             let remapped_cmd = CudaCommand::DeviceGet {
                 ordinal: server_local_ordinal as i32,
             };
-            return forward_cuda_to_server(
+            let mirrored = mirror.as_ref().map(|_| remapped_cmd.clone());
+            let response = forward_cuda_to_server(
                 server_conns,
                 endpoints,
                 server_idx,
@@ -905,34 +1747,558 @@ async fn forward_cuda_command_pooled(
                 remapped_cmd,
             )
             .await;
+            if let (Some(relocations), Message::CudaResponse { response: CudaResponse::Device(device), .. }) =
+                (relocations, &response)
+            {
+                relocations.note_device(*device, ordinal);
+            }
+            if let (Some(mirror), Some(command)) = (mirror, mirrored) {
+                mirror.submit(request_id, command, &response).await;
+            }
+            return response;
         }
         // Fallback: forward as-is to default server
     }
 
-    // Determine target server from handle
-    let routing_handle = extract_cuda_routing_handle(&command);
+    // GPUs on different servers (or a server and the local GPU) can't reach
+    // each other, which no single server could answer.
+    if let CudaCommand::DeviceGetP2PAttribute { src_device: a, dst_device: b, .. }
+    | CudaCommand::DeviceCanAccessPeer { device: a, peer_device: b } = &command
+    {
+        if pool_manager.server_index_for_handle(a).await != pool_manager.server_index_for_handle(b).await {
+            let response = match command {
+                CudaCommand::DeviceCanAccessPeer { .. } => CudaResponse::BoolResult(false),
+                _ => CudaResponse::P2PAttribute(0),
+            };
+            return Message::CudaResponse { request_id, response };
+        }
+    }
+
+    // Determine target server from handle, or the current context's server
+    let (routing_handle, switch) = {
+        let contexts = caller.contexts.lock().unwrap();
+        (contexts.routing_handle(&command), ContextStack::switch(&command))
+    };
     let server_idx = resolve_server_index(pool_manager, routing_handle).await;
 
     // Check if this targets the local GPU
     if server_idx == crate::pool_manager::LOCAL_SERVER_INDEX {
         if let (Some(executor), Some(session)) = (local_cuda_executor, local_session) {
             let response = executor.execute(session, command);
+            if let Some(switch) = switch {
+                caller.contexts.lock().unwrap().apply(switch, &response);
+            }
             return Message::CudaResponse { request_id, response };
         }
         return make_error_response(request_id, true, "local GPU not available");
     }
 
+    let mirrored = mirror.as_ref().map(|_| command.clone());
+    let observation = match prefetcher {
+        Some(prefetcher) => prefetcher.observe(&command),
+        None => Observation::Forward,
+    };
+    let trigger = match observation {
+        Observation::Forward => None,
+        Observation::Trigger(trigger) => Some(trigger),
+        Observation::Prefetched(rx) => {
+            // Fall through to a normal read if the speculative one failed.
+            let prefetched = match rx.await {
+                Ok(held) => held.load().await,
+                Err(_) => None,
+            };
+            if let Some(
+                prefetched @ (CudaResponse::MemoryData(_) | CudaResponse::EncodedMemoryData { .. }),
+            ) = prefetched
+            {
+                let response = Message::CudaResponse {
+                    request_id,
+                    response: prefetched,
+                };
+                if let (Some(mirror), Some(command)) = (mirror, mirrored) {
+                    mirror.submit(request_id, command, &response).await;
+                }
+                return response;
+            }
+            None
+        }
+    };
+
+    let (command, pending_readback) = match readback {
+        Some(cache) => cache.begin(command),
+        None => (command, None),
+    };
+    let mut response = match command {
+        CudaCommand::ModuleLoadData { image } => {
+            forward_module_image(server_conns, endpoints, server_idx, request_id, image, &caller).await
+        }
+        command => {
+            let msg = Message::CudaCommand {
+                request_id,
+                command,
+                deadline_ms: None,
+            };
+            forward_to_server(server_conns, endpoints, server_idx, request_id, msg, true, Some(&caller)).await
+        }
+    };
+    if let (Some(cache), Some(pending)) = (readback, pending_readback) {
+        if let Message::CudaResponse { request_id, response: diff } = response {
+            response = Message::CudaResponse {
+                request_id,
+                response: cache.finish(pending, diff),
+            };
+        }
+    }
+    if let (Some(prefetcher), Some(trigger)) = (prefetcher, trigger) {
+        if matches!(response, Message::CudaResponse { response: CudaResponse::Success, .. }) {
+            spawn_prefetches(server_conns, endpoints, pool_manager, prefetcher.plan(trigger));
+        }
+    }
+    if let (Some(switch), Message::CudaResponse { response, .. }) = (switch, &response) {
+        caller.contexts.lock().unwrap().apply(switch, response);
+    }
+    if let (Some(mirror), Some(command)) = (mirror, mirrored) {
+        mirror.submit(request_id, command, &response).await;
+    }
+    response
+}
+
+/// Load a module on a server, by hash if the server was sent the same image
+/// before.
+async fn forward_module_image(
+    server_conns: &ServerConns,
+    endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
+    server_idx: usize,
+    request_id: RequestId,
+    image: Vec<u8>,
+    caller: &IpcCaller,
+) -> Message {
+    let address = endpoints.read().await.get(server_idx).map(|ep| ep.address.clone());
+    let hash = content_cache::hash(&image);
+
+    if let Some(address) = address.as_deref().filter(|address| MODULES.known(address, &hash)) {
+        let msg = Message::CudaCommand {
+            request_id,
+            command: CudaCommand::ModuleLoadByHash { hash },
+            deadline_ms: None,
+        };
+        match forward_to_server(server_conns, endpoints, server_idx, request_id, msg, true, Some(caller)).await {
+            Message::CudaResponse {
+                response: CudaResponse::ModuleImageMissing,
+                ..
+            } => {
+                debug!("{} no longer has module image {:02x?}, sending it", address, &hash[..4]);
+                MODULES.forget(address, &hash);
+            }
+            response => return response,
+        }
+    }
+
     let msg = Message::CudaCommand {
+        request_id,
+        command: CudaCommand::ModuleLoadData { image },
+        deadline_ms: None,
+    };
+    let response = forward_to_server(server_conns, endpoints, server_idx, request_id, msg, true, Some(caller)).await;
+    if let (
+        Some(address),
+        Message::CudaResponse {
+            response: CudaResponse::Module(_),
+            ..
+        },
+    ) = (address, &response)
+    {
+        MODULES.remember(&address, hash);
+    }
+    response
+}
+
+/// Forward batched void commands. Consecutive commands for the same server
+/// go out together as one batch. Returns the last error, or `Success`.
+#[allow(clippy::too_many_arguments)]
+async fn forward_cuda_batch(
+    server_conns: &ServerConns,
+    endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
+    pool_manager: &Arc<GpuPoolManager>,
+    local_cuda_executor: &Option<Arc<rgpu_server::cuda_executor::CudaExecutor>>,
+    local_session: &Option<Arc<rgpu_server::session::Session>>,
+    mirror: &Option<Arc<Mirror>>,
+    relocations: &Option<Arc<Relocations>>,
+    contexts: &Contexts,
+    commands: Vec<CudaCommand>,
+) -> Message {
+    let mut runs: Vec<(usize, Vec<CudaCommand>)> = Vec::new();
+    for mut cmd in commands {
+        if let Some(relocations) = relocations {
+            relocations.rewrite(&mut cmd);
+        }
+        let routing_handle = contexts.lock().unwrap().route_void(&cmd);
+        let server_idx = resolve_server_index(pool_manager, routing_handle).await;
+        match runs.last_mut() {
+            Some((idx, run)) if *idx == server_idx => run.push(cmd),
+            _ => runs.push((server_idx, vec![cmd])),
+        }
+    }
+
+    let request_id = RequestId(0);
+    let mut last_error = None;
+    for (server_idx, commands) in runs {
+        let response = if server_idx == crate::pool_manager::LOCAL_SERVER_INDEX {
+            match (local_cuda_executor, local_session) {
+                (Some(executor), Some(session)) => {
+                    let mut response = CudaResponse::Success;
+                    for cmd in commands {
+                        let result = executor.execute(session, cmd);
+                        if let CudaResponse::Error { .. } = &result {
+                            response = result;
+                        }
+                    }
+                    Message::CudaResponse { request_id, response }
+                }
+                _ => make_error_response(request_id, true, "local GPU not available"),
+            }
+        } else {
+            let mirrored = mirror.as_ref().map(|_| commands.clone());
+            let response = forward_to_server(
+                server_conns,
+                endpoints,
+                server_idx,
+                request_id,
+                Message::CudaBatch(commands),
+                true,
+                None,
+            )
+            .await;
+            if let (Some(mirror), Some(commands)) = (mirror, mirrored) {
+                mirror.submit_batch(commands).await;
+            }
+            response
+        };
+        if matches!(response, Message::CudaResponse { response: CudaResponse::Error { .. }, .. }) {
+            last_error = Some(response);
+        }
+    }
+    last_error.unwrap_or(Message::CudaResponse {
+        request_id,
+        response: CudaResponse::Success,
+    })
+}
+
+/// Forward the void commands an application queued since its last sync
+/// point together with the command that flushed them. If everything goes to
+/// one remote server and the daemon has no reason to look at the command
+/// itself, they travel as a single `CudaPipelined` round trip; otherwise the
+/// batch is forwarded first and the command after it.
+#[allow(clippy::too_many_arguments)]
+async fn forward_cuda_pipelined(
+    server_conns: &ServerConns,
+    endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
+    pool_manager: &Arc<GpuPoolManager>,
+    local_cuda_executor: &Option<Arc<rgpu_server::cuda_executor::CudaExecutor>>,
+    local_session: &Option<Arc<rgpu_server::session::Session>>,
+    mirror: &Option<Arc<Mirror>>,
+    readback: &Option<Arc<ReadbackCache>>,
+    prefetcher: &Option<Arc<Prefetcher>>,
+    relocations: &Option<Arc<Relocations>>,
+    request_id: RequestId,
+    mut batch: Vec<CudaCommand>,
+    mut command: CudaCommand,
+    caller: IpcCaller,
+) -> Message {
+    if let Some(relocations) = relocations {
+        batch.iter_mut().chain(std::iter::once(&mut command)).for_each(|cmd| relocations.rewrite(cmd));
+    }
+    // Route on a copy of the context stack: if the commands split across
+    // servers, forwarding them one run at a time updates the real one.
+    let mut preview = caller.contexts.lock().unwrap().clone();
+    let batch_handles: Vec<Option<NetworkHandle>> = batch.iter().map(|cmd| preview.route_void(cmd)).collect();
+    let server_idx = resolve_server_index(pool_manager, preview.routing_handle(&command)).await;
+    let mut single_server = server_idx != crate::pool_manager::LOCAL_SERVER_INDEX
+        && readback.is_none()
+        && prefetcher.is_none()
+        && !handled_by_daemon(&command, relocations);
+    for handle in batch_handles {
+        if !single_server {
+            break;
+        }
+        single_server = resolve_server_index(pool_manager, handle).await == server_idx;
+    }
+
+    if single_server {
+        let switch = ContextStack::switch(&command);
+        let mirrored = mirror.as_ref().map(|_| (batch.clone(), command.clone()));
+        let msg = Message::CudaPipelined {
+            request_id,
+            batch,
+            command,
+        };
+        let response =
+            forward_to_server(server_conns, endpoints, server_idx, request_id, msg, true, Some(&caller)).await;
+        if let (Some(switch), Message::CudaResponse { response, .. }) = (switch, &response) {
+            preview.apply(switch, response);
+        }
+        *caller.contexts.lock().unwrap() = preview;
+        if let (Some(mirror), Some((batch, command))) = (mirror, mirrored) {
+            mirror.submit_batch(batch).await;
+            mirror.submit(request_id, command, &response).await;
+        }
+        return response;
+    }
+
+    let flushed = forward_cuda_batch(
+        server_conns,
+        endpoints,
+        pool_manager,
+        local_cuda_executor,
+        local_session,
+        mirror,
+        relocations,
+        &caller.contexts,
+        batch,
+    )
+    .await;
+    if let Message::CudaResponse {
+        response: error @ CudaResponse::Error { .. },
+        ..
+    } = flushed
+    {
+        return Message::CudaResponse {
+            request_id,
+            response: error,
+        };
+    }
+    forward_cuda_command_pooled(
+        server_conns,
+        endpoints,
+        pool_manager,
+        local_cuda_executor,
+        local_session,
+        mirror,
+        readback,
+        prefetcher,
+        relocations,
         request_id,
         command,
+        caller,
+    )
+    .await
+}
+
+/// Commands `forward_cuda_command_pooled` answers or rewrites itself.
+fn handled_by_daemon(command: &CudaCommand, relocations: &Option<Arc<Relocations>>) -> bool {
+    matches!(
+        command,
+        CudaCommand::DeviceGetCount
+            | CudaCommand::DeviceGet { .. }
+            | CudaCommand::DeviceGetP2PAttribute { .. }
+            | CudaCommand::DeviceCanAccessPeer { .. }
+    ) || (relocations.is_some() && relocation::context_device(command).is_some())
+}
+
+/// Whether the server at `server_idx` is down: marked so by the heartbeat,
+/// or its connection failed since.
+async fn server_down(server_conns: &ServerConns, pool_manager: &GpuPoolManager, server_idx: usize) -> bool {
+    if !pool_manager.is_connected(server_idx).await {
+        return true;
+    }
+    let slot = server_conns.read().await.get(server_idx).cloned();
+    // A connection in use is taken to be alive
+    slot.is_some_and(|slot| slot.try_lock().is_ok_and(|conn| conn.is_none()))
+}
+
+/// With failover, the GPU to use for pool GPU `ordinal`: the GPU itself
+/// while its server is up, else an equivalent one on the next connected
+/// server.
+async fn failover_target(
+    server_conns: &ServerConns,
+    pool_manager: &GpuPoolManager,
+    ordinal: u32,
+) -> Option<GpuPoolEntry> {
+    let gpu = pool_manager.get_gpu(ordinal).await?;
+    if gpu.is_local || !server_down(server_conns, pool_manager, gpu.server_index).await {
+        return Some(gpu);
+    }
+    let connected = pool_manager.all_connected_server_indices().await;
+    let pool = pool_manager.get_all_gpus().await;
+    let Some(standby) = failover::standby(&pool, &gpu, &connected) else {
+        warn!("server {} is down and no other server has a GPU like its {}", gpu.server_index, gpu.info.device_name);
+        return None;
     };
+    warn!(
+        "server {} is down, failing GPU {} ({}) over to server {}",
+        gpu.server_index, ordinal, gpu.info.device_name, standby.server_index
+    );
+    Some(standby.clone())
+}
 
-    forward_to_server(server_conns, endpoints, server_idx, request_id, &msg, true).await
+/// Swap relocated device handles in a command, and if it creates a context
+/// on a device, move it first: to a stand-in while the device's server is
+/// down, or with placement, to the GPU the policy picks for the device's
+/// first context. `Err` is the response to give the application if getting
+/// the new GPU's device fails.
+async fn relocate_context(
+    server_conns: &ServerConns,
+    endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
+    pool_manager: &GpuPoolManager,
+    relocations: &Relocations,
+    request_id: RequestId,
+    command: &mut CudaCommand,
+) -> Result<(), Message> {
+    relocations.rewrite(command);
+    let Some(device) = relocation::context_device(command) else {
+        return Ok(());
+    };
+    let Some(ordinal) = relocations.ordinal(&device) else {
+        return Ok(());
+    };
+    let Some(server_idx) = pool_manager.server_index_for_handle(&device).await else {
+        return Ok(());
+    };
+    let target = if relocations.failover && server_down(server_conns, pool_manager, server_idx).await {
+        failover_target(server_conns, pool_manager, ordinal)
+            .await
+            .filter(|target| target.server_index != server_idx)
+    } else if let Some(placement) = relocations.placement.as_ref().filter(|_| relocations.settle(device)) {
+        place_context(server_conns, endpoints, pool_manager, placement, ordinal, server_idx).await
+    } else {
+        None
+    };
+    let Some(target) = target.filter(|target| !target.is_local) else {
+        return Ok(());
+    };
+    let device_get = CudaCommand::DeviceGet { ordinal: target.server_device_index as i32 };
+    match forward_cuda_to_server(server_conns, endpoints, target.server_index, request_id, device_get).await {
+        Message::CudaResponse { response: CudaResponse::Device(replacement), .. } => {
+            relocations.swap(device, replacement);
+            relocations.note_device(replacement, ordinal);
+            relocations.rewrite(command);
+            Ok(())
+        }
+        response => Err(response),
+    }
+}
+
+/// With placement, the GPU the policy picks for the first context on pool
+/// GPU `ordinal`, got on server `server_idx`; `None` leaves it there.
+async fn place_context(
+    server_conns: &ServerConns,
+    endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
+    pool_manager: &GpuPoolManager,
+    placement: &Placement,
+    ordinal: u32,
+    server_idx: usize,
+) -> Option<GpuPoolEntry> {
+    let asked = pool_manager.get_gpu(ordinal).await?;
+    // Local GPUs stay local, and failed-over devices where they are
+    if asked.is_local || asked.server_index != server_idx {
+        return None;
+    }
+    let connected = pool_manager.all_connected_server_indices().await;
+    let pool = pool_manager.get_all_gpus().await;
+    let gpus = placement.candidates(&pool, &asked, &connected);
+    let vram_used = match placement.policy() {
+        PlacementPolicy::LeastVramUsed => vram_in_use(server_conns, &gpus).await,
+        _ => HashMap::new(),
+    };
+    let candidates: Vec<Candidate> = {
+        let endpoints = endpoints.read().await;
+        gpus.into_iter()
+            .map(|gpu| Candidate {
+                address: endpoints.get(gpu.server_index).map(|e| e.address.clone()).unwrap_or_default(),
+                vram_used: vram_used.get(&(gpu.server_index, gpu.server_device_index)).copied().unwrap_or(0),
+                gpu: gpu.clone(),
+            })
+            .collect()
+    };
+    let chosen = placement.choose(&candidates)?.gpu.clone();
+    info!(
+        "placing context for GPU {} on server {} GPU {} ({}, {})",
+        ordinal,
+        chosen.server_index,
+        chosen.server_device_index,
+        chosen.info.device_name,
+        placement::policy_name(placement.policy())
+    );
+    let placed = placement.note(&chosen);
+    announce_placement(server_conns, chosen.server_index, placed).await;
+    (chosen.pool_index != asked.pool_index).then_some(chosen)
+}
+
+/// VRAM allocated by all sessions on the servers of `gpus`, by server index
+/// and device index.
+async fn vram_in_use(server_conns: &ServerConns, gpus: &[&GpuPoolEntry]) -> HashMap<(usize, u32), u64> {
+    let mut servers: Vec<usize> = gpus.iter().map(|gpu| gpu.server_index).collect();
+    servers.sort_unstable();
+    servers.dedup();
+    let mut used = HashMap::new();
+    for server_idx in servers {
+        let Some(slot) = server_conns.read().await.get(server_idx).cloned() else {
+            continue;
+        };
+        let mut guard = slot.lock().await;
+        let Some(conn) = guard.as_mut() else {
+            continue;
+        };
+        match tokio::time::timeout(health_check() * 2, conn.send_and_receive(&Message::QueryMetrics)).await {
+            Ok(Ok(Message::MetricsData { sessions, .. })) => {
+                for usage in sessions.iter().flat_map(|session| &session.vram) {
+                    *used.entry((server_idx, usage.device_index)).or_default() += usage.total();
+                }
+            }
+            _ => warn!("couldn't get the VRAM in use on {}", conn.address),
+        }
+    }
+    used
+}
+
+/// Tell server `server_idx` the contexts placed on its GPUs, as a session
+/// label.
+async fn announce_placement(server_conns: &ServerConns, server_idx: usize, placed: String) {
+    let Some(slot) = server_conns.read().await.get(server_idx).cloned() else {
+        return;
+    };
+    let mut guard = slot.lock().await;
+    if let Some(conn) = guard.as_mut() {
+        let msg = Message::SetSessionInfo {
+            name: None,
+            labels: vec![(PLACEMENT_LABEL.to_string(), placed)],
+        };
+        if let Err(e) = conn.send_and_receive(&msg).await {
+            warn!("failed to send placement to {}: {}", conn.address, e);
+        }
+    }
+}
+
+/// Issue speculative reads in the background. Each response is delivered to
+/// the prefetcher, which hands it to the app's read if nothing else came first.
+fn spawn_prefetches(
+    server_conns: &ServerConns,
+    endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
+    pool_manager: &Arc<GpuPoolManager>,
+    plan: Vec<(Read, PrefetchSlot)>,
+) {
+    for ((src, byte_count), slot) in plan {
+        let conns = server_conns.clone();
+        let eps = endpoints.clone();
+        let pm = pool_manager.clone();
+        tokio::spawn(async move {
+            let server_idx = resolve_server_index(&pm, Some(src)).await;
+            if server_idx == crate::pool_manager::LOCAL_SERVER_INDEX {
+                return;
+            }
+            let command = CudaCommand::MemcpyDtoH { src, byte_count };
+            if let Message::CudaResponse { response, .. } =
+                forward_cuda_to_server(&conns, &eps, server_idx, RequestId(0), command).await
+            {
+                slot.fill(response).await;
+            }
+        });
+    }
 }
 
 /// Forward a CUDA command to a specific server by index.
 async fn forward_cuda_to_server(
-    server_conns: &Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: &ServerConns,
     endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     server_idx: usize,
     request_id: RequestId,
@@ -941,8 +2307,9 @@ async fn forward_cuda_to_server(
     let msg = Message::CudaCommand {
         request_id,
         command,
+        deadline_ms: None,
     };
-    forward_to_server(server_conns, endpoints, server_idx, request_id, &msg, true).await
+    forward_to_server(server_conns, endpoints, server_idx, request_id, msg, true, None).await
 }
 
 // ── Vulkan Forwarding ────────────────────────────────────────────────
@@ -950,14 +2317,16 @@ async fn forward_cuda_to_server(
 /// Forward a Vulkan command using the pooled persistent connection.
 /// Routes to the correct server based on handle's server_id.
 /// If the target is the local GPU, executes directly via the local executor.
+#[allow(clippy::too_many_arguments)]
 async fn forward_vulkan_command_pooled(
-    server_conns: &Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: &ServerConns,
     endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     pool_manager: &Arc<GpuPoolManager>,
     local_vulkan_executor: &Option<Arc<rgpu_server::vulkan_executor::VulkanExecutor>>,
     local_session: &Option<Arc<rgpu_server::session::Session>>,
     request_id: RequestId,
     command: VulkanCommand,
+    caller: IpcCaller,
 ) -> Message {
     // Broadcast commands: CreateInstance goes to all servers AND local executor
     if matches!(command, VulkanCommand::CreateInstance { .. }) {
@@ -991,12 +2360,68 @@ async fn forward_vulkan_command_pooled(
         return make_error_response(request_id, false, "local Vulkan not available");
     }
 
+    if let VulkanCommand::CreateShaderModule { device, code } = command {
+        return forward_shader_module(server_conns, endpoints, server_idx, request_id, device, code, &caller).await;
+    }
+
     let msg = Message::VulkanCommand {
         request_id,
         command,
+        deadline_ms: None,
     };
 
-    forward_to_server(server_conns, endpoints, server_idx, request_id, &msg, false).await
+    forward_to_server(server_conns, endpoints, server_idx, request_id, msg, false, Some(&caller)).await
+}
+
+/// Create a shader module on a server, by hash if the server was sent the
+/// same code before.
+async fn forward_shader_module(
+    server_conns: &ServerConns,
+    endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
+    server_idx: usize,
+    request_id: RequestId,
+    device: NetworkHandle,
+    code: Vec<u8>,
+    caller: &IpcCaller,
+) -> Message {
+    let address = endpoints.read().await.get(server_idx).map(|ep| ep.address.clone());
+    let hash = content_cache::hash(&code);
+
+    if let Some(address) = address.as_deref().filter(|address| SHADERS.known(address, &hash)) {
+        let msg = Message::VulkanCommand {
+            request_id,
+            command: VulkanCommand::CreateShaderModuleByHash { device, hash },
+            deadline_ms: None,
+        };
+        match forward_to_server(server_conns, endpoints, server_idx, request_id, msg, false, Some(caller)).await {
+            Message::VulkanResponse {
+                response: VulkanResponse::ShaderCodeMissing,
+                ..
+            } => {
+                debug!("{} no longer has shader code {:02x?}, sending it", address, &hash[..4]);
+                SHADERS.forget(address, &hash);
+            }
+            response => return response,
+        }
+    }
+
+    let msg = Message::VulkanCommand {
+        request_id,
+        command: VulkanCommand::CreateShaderModule { device, code },
+        deadline_ms: None,
+    };
+    let response = forward_to_server(server_conns, endpoints, server_idx, request_id, msg, false, Some(caller)).await;
+    if let (
+        Some(address),
+        Message::VulkanResponse {
+            response: VulkanResponse::ShaderModuleCreated { .. },
+            ..
+        },
+    ) = (address, &response)
+    {
+        SHADERS.remember(&address, hash);
+    }
+    response
 }
 
 // ── Broadcast Vulkan Commands ────────────────────────────────────────
@@ -1006,7 +2431,7 @@ async fn forward_vulkan_command_pooled(
 /// In multi-server mode, each server creates its own VkInstance.
 /// The client tracks which instance handle belongs to which server via the NetworkHandle.
 async fn broadcast_vulkan_create_instance(
-    server_conns: &Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: &ServerConns,
     endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     pool_manager: &Arc<GpuPoolManager>,
     local_vulkan_executor: &Option<Arc<rgpu_server::vulkan_executor::VulkanExecutor>>,
@@ -1045,8 +2470,9 @@ async fn broadcast_vulkan_create_instance(
         let msg = Message::VulkanCommand {
             request_id,
             command: command.clone(),
+            deadline_ms: None,
         };
-        let resp = forward_to_server(server_conns, endpoints, idx, request_id, &msg, false).await;
+        let resp = forward_to_server(server_conns, endpoints, idx, request_id, msg, false, None).await;
 
         if let Message::VulkanResponse {
             response: VulkanResponse::InstanceCreated { handle },
@@ -1077,7 +2503,7 @@ async fn broadcast_vulkan_create_instance(
 
 /// Enumerate physical devices from all servers and local executor, merge into single response.
 async fn broadcast_vulkan_enumerate_physical_devices(
-    server_conns: &Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: &ServerConns,
     endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     pool_manager: &Arc<GpuPoolManager>,
     local_vulkan_executor: &Option<Arc<rgpu_server::vulkan_executor::VulkanExecutor>>,
@@ -1103,8 +2529,9 @@ async fn broadcast_vulkan_enumerate_physical_devices(
         let msg = Message::VulkanCommand {
             request_id,
             command: VulkanCommand::EnumeratePhysicalDevices { instance },
+            deadline_ms: None,
         };
-        let resp = forward_to_server(server_conns, endpoints, idx, request_id, &msg, false).await;
+        let resp = forward_to_server(server_conns, endpoints, idx, request_id, msg, false, None).await;
 
         if let Message::VulkanResponse {
             response: VulkanResponse::PhysicalDevices { handles },
@@ -1131,13 +2558,17 @@ async fn broadcast_vulkan_enumerate_physical_devices(
 // ── Generic Forwarding with Reconnect ────────────────────────────────
 
 /// Forward a message to a specific server, with reconnection on failure.
+/// When forwarding for an IPC `caller`, the command carries the caller's
+/// remaining time budget, and is cancelled on the server if the local
+/// application disconnects while waiting for the response.
 async fn forward_to_server(
-    server_conns: &Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: &ServerConns,
     endpoints: &Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     server_idx: usize,
     request_id: RequestId,
-    msg: &Message,
+    mut msg: Message,
     is_cuda: bool,
+    caller: Option<&IpcCaller>,
 ) -> Message {
     let conns = server_conns.read().await;
     let eps = endpoints.read().await;
@@ -1153,9 +2584,66 @@ async fn forward_to_server(
 
     let mut conn_guard = conn_slot.lock().await;
 
+    // Budget is measured after waiting for the connection, which may have
+    // been busy with other applications' requests.
+    let peer_gone = caller.map(|c| c.peer_gone.clone());
+    if let Some(caller) = caller {
+        let remaining = caller.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            warn!("request {:?} expired before it was sent to server {}", request_id, server_idx);
+            return make_error_response(request_id, is_cuda, "request deadline expired");
+        }
+        set_deadline(&mut msg, remaining);
+    }
+
+    // Over QUIC the request goes on a stream of its own, so other requests
+    // needn't wait for it to finish with the connection.
+    if let Some(mut conn) = conn_guard.as_ref().and_then(ServerConn::concurrent) {
+        drop(conn_guard);
+        match send_on(&mut conn, &msg, request_id, peer_gone.clone()).await {
+            Ok(response) => {
+                debug!("forwarded command to server {} via pooled connection", server_idx);
+                return response;
+            }
+            Err(e) => {
+                warn!(
+                    "pooled connection to server {} failed: {} - reconnecting",
+                    server_idx, e
+                );
+            }
+        }
+        conn_guard = conn_slot.lock().await;
+        if conn_guard.as_ref().is_some_and(|current| current.same_connection(&conn)) {
+            *conn_guard = None;
+        }
+    }
+
+    // A large upload goes to the server in chunks ahead of its command,
+    // leaving the connection to other requests between rounds of them.
+    if conn_guard.as_ref().is_some_and(ServerConn::chunks_uploads) {
+        if let Some(data) = chunking::take_upload(&mut msg) {
+            let transfer;
+            (conn_guard, transfer) = upload_chunked(&conn_slot, conn_guard, &data, server_idx).await;
+            if let (Some(transfer), Some(conn)) = (transfer, conn_guard.as_mut()) {
+                if let Some(caller) = caller {
+                    set_deadline(&mut msg, caller.deadline.saturating_duration_since(Instant::now()));
+                }
+                let chunked = chunking::chunked(&msg, transfer).expect("upload is a CudaCommand");
+                match send_on(conn, &chunked, request_id, peer_gone.clone()).await {
+                    Ok(response) => return response,
+                    Err(e) => {
+                        warn!("pooled connection to server {} failed: {} - reconnecting", server_idx, e);
+                        *conn_guard = None;
+                    }
+                }
+            }
+            chunking::restore_upload(&mut msg, data);
+        }
+    }
+
     // Try existing connection
     if let Some(ref mut conn) = *conn_guard {
-        match conn.send_and_receive(msg).await {
+        match send_on(conn, &msg, request_id, peer_gone.clone()).await {
             Ok(response) => {
                 debug!("forwarded command to server {} via pooled connection", server_idx);
                 return response;
@@ -1170,10 +2658,13 @@ async fn forward_to_server(
         }
     }
 
-    // Connection is None or failed - try to reconnect
-    match reconnect(&endpoint).await {
+    // Connection is None or failed - try to reconnect, resuming the session
+    match reconnect_with_backoff(&endpoint, caller.map(|c| c.deadline)).await {
         Ok((mut new_conn, _sid)) => {
-            match new_conn.send_and_receive(msg).await {
+            if let Some(caller) = caller {
+                set_deadline(&mut msg, caller.deadline.saturating_duration_since(Instant::now()));
+            }
+            match send_on(&mut new_conn, &msg, request_id, peer_gone).await {
                 Ok(response) => {
                     *conn_guard = Some(new_conn);
                     return response;
@@ -1192,6 +2683,95 @@ async fn forward_to_server(
     make_error_response(request_id, is_cuda, "failed to communicate with server")
 }
 
+/// Send `data` to the server in chunks, a round at a time, unlocking the
+/// connection's slot in between so requests waiting for it go first.
+/// Returns the slot locked again, with the upload's id if the connection
+/// now holds all of it. Without one the upload was refused, or the
+/// connection failed (and was cleared) or was replaced meanwhile.
+async fn upload_chunked<'a>(
+    conn_slot: &'a Mutex<Option<ServerConn>>,
+    mut conn_guard: tokio::sync::MutexGuard<'a, Option<ServerConn>>,
+    data: &[u8],
+    server_idx: usize,
+) -> (tokio::sync::MutexGuard<'a, Option<ServerConn>>, Option<u64>) {
+    let Some(link) = conn_guard.as_ref().map(|conn| conn.link.clone()) else {
+        return (conn_guard, None);
+    };
+    let transfer = link.transfer_id();
+    let mut offset = 0;
+    loop {
+        let Some(conn) = conn_guard.as_mut().filter(|conn| Arc::ptr_eq(&conn.link, &link)) else {
+            return (conn_guard, None);
+        };
+        match conn.send_chunks(transfer, data, offset).await {
+            Ok(Some(received)) if received == data.len() as u64 => return (conn_guard, Some(transfer)),
+            Ok(Some(received)) => offset = received,
+            Ok(None) => {
+                debug!("server {} refused upload {}, sending it whole", server_idx, transfer);
+                return (conn_guard, None);
+            }
+            Err(e) => {
+                warn!("chunked upload to server {} failed: {} - reconnecting", server_idx, e);
+                *conn_guard = None;
+                return (conn_guard, None);
+            }
+        }
+        // Tokio's mutex is fair: requests already waiting get it first.
+        drop(conn_guard);
+        conn_guard = conn_slot.lock().await;
+    }
+}
+
+/// Tries a request makes to reconnect before it fails.
+const RECONNECT_ATTEMPTS: u32 = 5;
+
+/// Reconnect for a request whose connection dropped, backing off from
+/// 250 ms up to 4 s between tries, so an outage of a few seconds doesn't
+/// fail it. Gives up after `RECONNECT_ATTEMPTS` tries or when the caller's
+/// deadline would pass.
+async fn reconnect_with_backoff(
+    endpoint: &ServerEndpoint,
+    deadline: Option<Instant>,
+) -> Result<(ServerConn, u16), Box<dyn std::error::Error + Send + Sync>> {
+    let mut delay = Duration::from_millis(250);
+    let mut attempt = 1;
+    loop {
+        match reconnect(endpoint).await {
+            Ok(connected) => return Ok(connected),
+            Err(e) if attempt >= RECONNECT_ATTEMPTS || deadline.is_some_and(|d| Instant::now() + delay >= d) => {
+                return Err(e);
+            }
+            Err(e) => {
+                debug!("reconnection to {} failed: {} - retrying in {:?}", endpoint.address, e, delay);
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(Duration::from_secs(4));
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Stamp the remaining time budget onto a command message.
+fn set_deadline(msg: &mut Message, remaining: Duration) {
+    let ms = remaining.as_millis().min(u32::MAX as u128) as u32;
+    if let Message::CudaCommand { deadline_ms, .. } | Message::VulkanCommand { deadline_ms, .. } = msg {
+        *deadline_ms = Some(ms);
+    }
+}
+
+/// Send on a server connection, cancellable if `peer_gone` is given.
+async fn send_on(
+    conn: &mut ServerConn,
+    msg: &Message,
+    request_id: RequestId,
+    peer_gone: Option<PeerGone>,
+) -> Result<Message, Box<dyn std::error::Error + Send + Sync>> {
+    match peer_gone {
+        Some(peer_gone) => conn.send_and_receive_cancellable(msg, request_id, peer_gone).await,
+        None => conn.send_and_receive(msg).await,
+    }
+}
+
 /// Create an appropriate error response message.
 fn make_error_response(request_id: RequestId, is_cuda: bool, msg: &str) -> Message {
     if is_cuda {
@@ -1215,16 +2795,19 @@ fn make_error_response(request_id: RequestId, is_cuda: bool, msg: &str) -> Messa
 
 // ── Reconnection Loop ────────────────────────────────────────────────
 
-/// Background task that periodically checks for disconnected servers and reconnects.
+/// Background task that periodically checks for disconnected servers and
+/// reconnects, and puts the GPU lists servers report into the pool.
 async fn reconnection_loop(
-    server_conns: Arc<tokio::sync::RwLock<Vec<Arc<Mutex<Option<ServerConn>>>>>>,
+    server_conns: ServerConns,
     endpoints: Arc<tokio::sync::RwLock<Vec<ServerEndpoint>>>,
     pool_manager: Arc<GpuPoolManager>,
+    cached_gpus: Arc<tokio::sync::RwLock<Vec<GpuInfo>>>,
 ) {
     let mut backoff_secs: Vec<u64> = Vec::new();
+    let mut retry_at: Vec<Instant> = Vec::new();
 
     loop {
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        tokio::time::sleep(health_check()).await;
 
         // Snapshot the connection slots and endpoints to avoid holding locks during reconnect
         let (conn_slots, endpoint_list): (Vec<_>, Vec<_>) = {
@@ -1240,6 +2823,7 @@ async fn reconnection_loop(
         // Initialize backoff array if needed
         if backoff_secs.len() < server_count {
             backoff_secs.resize(server_count, 1);
+            retry_at.resize(server_count, Instant::now());
         }
 
         for i in 0..server_count {
@@ -1257,7 +2841,7 @@ async fn reconnection_loop(
                 let mut guard = conn_slot.lock().await;
                 if let Some(ref mut conn) = *guard {
                     let ping_result = tokio::time::timeout(
-                        tokio::time::Duration::from_secs(10),
+                        health_check() * 2,
                         conn.send_and_receive(&Message::Ping),
                     )
                     .await;
@@ -1267,7 +2851,7 @@ async fn reconnection_loop(
                             // Server is alive
                         }
                         _ => {
-                            warn!("server {} failed heartbeat, marking disconnected", i);
+                            warn!("server {} ({}) failed heartbeat, marking disconnected", i, endpoint.address);
                             *guard = None;
                             drop(guard);
                             pool_manager
@@ -1282,7 +2866,10 @@ async fn reconnection_loop(
                 continue;
             }
 
-            // Needs reconnect
+            // Needs reconnect, once the backoff has passed
+            if Instant::now() < retry_at[i] {
+                continue;
+            }
             debug!("attempting reconnection to server {} (backoff={}s)", i, backoff_secs[i]);
 
             match reconnect(endpoint).await {
@@ -1305,10 +2892,13 @@ async fn reconnection_loop(
                         )
                         .await;
                     // Exponential backoff: double up to 60s max
+                    retry_at[i] = Instant::now() + Duration::from_secs(backoff_secs[i]);
                     backoff_secs[i] = (backoff_secs[i] * 2).min(60);
                 }
             }
         }
+
+        apply_topology(&endpoint_list, &pool_manager, &cached_gpus).await;
     }
 }
 
@@ -0,0 +1,68 @@
+//! Failover between servers with equivalent GPUs.
+//!
+//! With `failover` enabled, a CUDA device whose server is down is stood in
+//! for by an equivalent GPU (same model, memory and compute capability) on
+//! the next connected server, counting on from the failed one. cuDeviceGet
+//! on the GPU's ordinal gets the stand-in, and so does a context created on
+//! a device handle got before the server went down (cuCtxCreate,
+//! cuDevicePrimaryCtxRetain): the handle is swapped for one of the stand-in,
+//! and every later command naming it goes there too (see
+//! [`crate::relocation`]). Contexts and allocations made on the dead server
+//! are lost with it.
+
+use crate::pool_manager::GpuPoolEntry;
+
+/// The GPU to stand in for `failed`: an equivalent one on the first server
+/// in `connected` after the failed server's index, wrapping around.
+pub fn standby<'a>(pool: &'a [GpuPoolEntry], failed: &GpuPoolEntry, connected: &[usize]) -> Option<&'a GpuPoolEntry> {
+    let mut servers: Vec<usize> = connected.iter().copied().filter(|&s| s != failed.server_index).collect();
+    servers.sort_by_key(|&s| (s < failed.server_index, s));
+    servers.into_iter().find_map(|server| {
+        pool.iter()
+            .find(|g| g.server_index == server && !g.is_local && g.info.is_equivalent(&failed.info))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::gpu_info::{GpuDeviceType, GpuInfo};
+
+    fn entry(server_index: usize, name: &str) -> GpuPoolEntry {
+        GpuPoolEntry {
+            pool_index: 0,
+            server_index,
+            server_device_index: 0,
+            info: GpuInfo {
+                device_name: name.to_string(),
+                vendor_id: 0x10de,
+                device_id: 0,
+                device_type: GpuDeviceType::DiscreteGpu,
+                total_memory: 24 << 30,
+                supports_vulkan: true,
+                supports_cuda: true,
+                vulkan_api_version: None,
+                vulkan_driver_version: None,
+                cuda_compute_capability: Some((8, 9)),
+                queue_family_count: 1,
+                memory_heaps: Vec::new(),
+                server_device_index: 0,
+                server_id: server_index as u16,
+                topology: Default::default(),
+                scheduling: Default::default(),
+                uuid: None,
+            },
+            is_local: false,
+        }
+    }
+
+    #[test]
+    fn next_equivalent_server_stands_in() {
+        let pool = [entry(0, "RTX 4090"), entry(1, "A100"), entry(2, "RTX 4090"), entry(3, "RTX 4090")];
+        let chosen = standby(&pool, &pool[2], &[0, 1, 3]).unwrap();
+        assert_eq!(chosen.server_index, 3);
+        let chosen = standby(&pool, &pool[3], &[0, 1]).unwrap();
+        assert_eq!(chosen.server_index, 0);
+        assert!(standby(&pool, &pool[1], &[0, 2, 3]).is_none());
+    }
+}
@@ -0,0 +1,266 @@
+//! Numbering of the GPU pool (`gpu_ordering`, `gpu_uuids`,
+//! `stable_gpu_indices`).
+//!
+//! The pool is first put in server order: servers as configured, each
+//! server's GPUs in its own order, local GPUs last. The ordering strategy
+//! sorts that stably. With stable indices, the daemon then remembers which
+//! GPU got which index in its state directory, and every later numbering,
+//! in this run or after a restart, gives the GPUs it has seen before the
+//! same relative order, with GPUs it hasn't seen after them. Scripts that
+//! pick a GPU by index keep getting the same one, whatever order servers
+//! answer in at startup. CUDA numbers devices without gaps, so while a GPU
+//! is missing the ones after it move up; they move back when it returns.
+//! Changing `gpu_ordering` or `gpu_uuids` starts the numbering afresh.
+//!
+//! GPUs are told apart by UUID.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use tracing::{info, warn};
+
+use rgpu_core::config::GpuOrdering;
+use rgpu_protocol::gpu_info::GpuInfo;
+
+use crate::pool_manager::GpuPoolEntry;
+
+/// Sort the pool by `ordering`. Returns how many GPUs at the front
+/// `gpu_uuids` placed explicitly; stable indices leave those alone.
+pub fn sort(pool: &mut [GpuPoolEntry], ordering: &GpuOrdering, uuids: &[String]) -> usize {
+    pool.sort_by_key(|g| (g.server_index, g.server_device_index));
+    match ordering {
+        GpuOrdering::LocalFirst => pool.sort_by_key(|g| std::cmp::Reverse(g.is_local)),
+        GpuOrdering::RemoteFirst => pool.sort_by_key(|g| g.is_local),
+        GpuOrdering::ServerOrder => {}
+        GpuOrdering::ByVram => pool.sort_by_key(|g| std::cmp::Reverse(g.info.total_memory)),
+        GpuOrdering::ByCapability => pool
+            .sort_by_key(|g| std::cmp::Reverse((g.info.cuda_compute_capability, g.info.total_memory))),
+        GpuOrdering::ByUuid => {
+            let position = |gpu: &GpuInfo| uuids.iter().position(|pattern| uuid_matches(gpu, pattern));
+            pool.sort_by_key(|g| position(&g.info).unwrap_or(usize::MAX));
+            return pool.iter().take_while(|g| position(&g.info).is_some()).count();
+        }
+    }
+    0
+}
+
+/// Whether `gpu`'s UUID is `pattern` or starts with it, regardless of case
+/// and of the `GPU-` prefix.
+pub fn uuid_matches(gpu: &GpuInfo, pattern: &str) -> bool {
+    let Some(uuid) = gpu.uuid_string() else {
+        return false;
+    };
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let pattern = pattern.strip_prefix("gpu-").unwrap_or(&pattern);
+    !pattern.is_empty() && uuid[4..].starts_with(pattern)
+}
+
+/// What tells each GPU of the pool apart, in pool order. A GPU without a
+/// UUID falls back to its server's address, which `address` gives by
+/// server index, and its device index there.
+pub fn keys<'a>(pool: &[GpuPoolEntry], address: impl Fn(usize) -> Option<&'a str>) -> Vec<String> {
+    let mut seen: HashMap<String, u32> = HashMap::new();
+    pool.iter()
+        .map(|g| {
+            let id = match (g.info.uuid_string(), g.is_local) {
+                (Some(uuid), false) => uuid,
+                (Some(uuid), true) => format!("local/{}", uuid),
+                (None, true) => format!("local/{}", g.server_device_index),
+                (None, false) => format!("{}/{}", address(g.server_index).unwrap_or("?"), g.server_device_index),
+            };
+            // Virtual GPUs share their physical GPU's UUID
+            let n = seen.entry(id.clone()).or_default();
+            *n += 1;
+            match *n {
+                1 => id,
+                n => format!("{}#{}", id, n - 1),
+            }
+        })
+        .collect()
+}
+
+/// Put the GPUs of `pool` after the first `pinned` in the order of
+/// `remembered`, adding those it doesn't have yet in their current order.
+/// `keys` tells the GPUs apart. Returns whether `remembered` grew.
+fn stabilize(remembered: &mut Vec<String>, pool: &mut [GpuPoolEntry], keys: &[String], pinned: usize) -> bool {
+    let before = remembered.len();
+    for key in &keys[pinned..] {
+        if !remembered.contains(key) {
+            remembered.push(key.clone());
+        }
+    }
+    let position = |key: &String| remembered.iter().position(|k| k == key).unwrap_or(usize::MAX);
+    let mut order: Vec<usize> = (pinned..pool.len()).collect();
+    order.sort_by_key(|&i| position(&keys[i]));
+    let sorted: Vec<GpuPoolEntry> = order.iter().map(|&i| pool[i].clone()).collect();
+    pool[pinned..].clone_from_slice(&sorted);
+    remembered.len() != before
+}
+
+/// The indices given to GPUs so far, kept in the daemon's state directory.
+pub struct StableIndices {
+    path: PathBuf,
+    /// The ordering settings the indices were given under
+    ordering: String,
+    /// GPU keys by index, including GPUs not in the pool now
+    remembered: Mutex<Vec<String>>,
+}
+
+impl StableIndices {
+    /// The indices remembered for `ordering` and `uuids`; none if they were
+    /// given under other settings.
+    pub fn load(ordering: &GpuOrdering, uuids: &[String]) -> Self {
+        let path = rgpu_common::platform::state_dir().join("gpu-indices");
+        let ordering = describe(ordering, uuids);
+        let remembered = match std::fs::read_to_string(&path).map(|text| parse(&text)) {
+            Ok((previous, keys)) if previous == ordering => keys,
+            Ok((previous, _)) => {
+                info!("GPU ordering changed from {} to {}, numbering GPUs afresh", previous, ordering);
+                Vec::new()
+            }
+            Err(_) => Vec::new(),
+        };
+        Self {
+            path,
+            ordering,
+            remembered: Mutex::new(remembered),
+        }
+    }
+
+    /// Give the GPUs after the first `pinned` of `pool` their remembered
+    /// order, and remember the new ones.
+    pub fn apply(&self, pool: &mut [GpuPoolEntry], keys: &[String], pinned: usize) {
+        let mut remembered = self.remembered.lock().unwrap();
+        if !stabilize(&mut remembered, pool, keys, pinned) {
+            return;
+        }
+        let written = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(&self.path, format(&self.ordering, &remembered)));
+        if let Err(e) = written {
+            warn!("failed to save GPU indices to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+fn describe(ordering: &GpuOrdering, uuids: &[String]) -> String {
+    match ordering {
+        GpuOrdering::ByUuid => format!("{:?}:{}", ordering, uuids.join(",")),
+        _ => format!("{:?}", ordering),
+    }
+}
+
+fn parse(text: &str) -> (String, Vec<String>) {
+    let mut ordering = String::new();
+    let mut keys = Vec::new();
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        match line.strip_prefix("ordering ") {
+            Some(value) => ordering = value.to_string(),
+            None => keys.push(line.to_string()),
+        }
+    }
+    (ordering, keys)
+}
+
+fn format(ordering: &str, keys: &[String]) -> String {
+    let mut text = String::from("# GPUs of the client daemon's pool in index order, kept across restarts\n");
+    text.push_str(&format!("ordering {}\n", ordering));
+    for key in keys {
+        text.push_str(key);
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::gpu_info::GpuDeviceType;
+
+    fn entry(server_index: usize, device: u32, memory_gb: u64, uuid: u8) -> GpuPoolEntry {
+        GpuPoolEntry {
+            pool_index: 0,
+            server_index,
+            server_device_index: device,
+            info: GpuInfo {
+                device_name: format!("GPU {}", uuid),
+                vendor_id: 0x10de,
+                device_id: 0,
+                device_type: GpuDeviceType::DiscreteGpu,
+                total_memory: memory_gb << 30,
+                supports_vulkan: true,
+                supports_cuda: true,
+                vulkan_api_version: None,
+                vulkan_driver_version: None,
+                cuda_compute_capability: Some((8, 6)),
+                queue_family_count: 1,
+                memory_heaps: Vec::new(),
+                server_device_index: device,
+                server_id: server_index as u16,
+                topology: Default::default(),
+                scheduling: Default::default(),
+                uuid: Some([uuid; 16]),
+            },
+            is_local: false,
+        }
+    }
+
+    fn names(pool: &[GpuPoolEntry]) -> Vec<String> {
+        pool.iter().map(|g| g.info.device_name.clone()).collect()
+    }
+
+    #[test]
+    fn strategies_sort_the_pool() {
+        let mut newest = entry(0, 1, 8, 0xbb);
+        newest.info.cuda_compute_capability = Some((9, 0));
+        let mut pool = vec![entry(1, 0, 24, 0xaa), newest, entry(0, 0, 48, 0xcc)];
+        assert_eq!(sort(&mut pool, &GpuOrdering::ServerOrder, &[]), 0);
+        assert_eq!(names(&pool), ["GPU 204", "GPU 187", "GPU 170"]);
+
+        sort(&mut pool, &GpuOrdering::ByVram, &[]);
+        assert_eq!(names(&pool), ["GPU 204", "GPU 170", "GPU 187"]);
+        sort(&mut pool, &GpuOrdering::ByCapability, &[]);
+        assert_eq!(names(&pool), ["GPU 187", "GPU 204", "GPU 170"]);
+
+        let uuids = ["GPU-aaaaaaaa".to_string(), "BBBB".to_string(), "ff".to_string()];
+        assert_eq!(sort(&mut pool, &GpuOrdering::ByUuid, &uuids), 2);
+        assert_eq!(names(&pool), ["GPU 170", "GPU 187", "GPU 204"]);
+        assert_eq!(
+            pool[0].info.uuid_string().unwrap(),
+            "GPU-aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa"
+        );
+    }
+
+    #[test]
+    fn indices_survive_restarts_and_missing_gpus() {
+        let key = |g: &GpuPoolEntry| keys(std::slice::from_ref(g), |_| None).remove(0);
+        let run = |remembered: &mut Vec<String>, mut pool: Vec<GpuPoolEntry>| {
+            let keys: Vec<_> = pool.iter().map(key).collect();
+            stabilize(remembered, &mut pool, &keys, 0);
+            names(&pool)
+        };
+        let (a, b, c, d) = (entry(0, 0, 8, 1), entry(0, 1, 8, 2), entry(1, 0, 8, 3), entry(2, 0, 8, 4));
+
+        let mut remembered = Vec::new();
+        assert_eq!(run(&mut remembered, vec![a.clone(), b.clone(), c.clone()]), ["GPU 1", "GPU 2", "GPU 3"]);
+        // Server 1 answered first, server 0's second GPU is gone
+        assert_eq!(run(&mut remembered, vec![c.clone(), a.clone()]), ["GPU 1", "GPU 3"]);
+        assert_eq!(run(&mut remembered, vec![d, c, b, a]), ["GPU 1", "GPU 2", "GPU 3", "GPU 4"]);
+
+        let text = format("ByVram", &remembered);
+        assert_eq!(parse(&text), ("ByVram".to_string(), remembered));
+    }
+
+    #[test]
+    fn virtual_gpus_and_gpus_without_uuids_have_keys() {
+        let mut anonymous = entry(1, 2, 8, 0);
+        anonymous.info.uuid = None;
+        let pool = [entry(0, 0, 8, 7), entry(0, 1, 8, 7), anonymous];
+        let keys = keys(&pool, |i| (i == 1).then_some("gpu-box:9876"));
+        assert_eq!(keys[1], format!("{}#1", keys[0]));
+        assert_eq!(keys[2], "gpu-box:9876/2");
+    }
+}
@@ -1,93 +1,377 @@
+use std::sync::{Arc, OnceLock};
+
+use bytes::Bytes;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
+use rgpu_common::shm::{Direction, SharedRegion};
+use rgpu_protocol::cuda_commands::CudaResponse;
+use rgpu_protocol::error::ProtocolError;
 use rgpu_protocol::messages::Message;
-use rgpu_protocol::wire;
+use rgpu_protocol::wire::{self, FrameFlags};
+
+use crate::breadcrumbs::{BreadcrumbSettings, Breadcrumbs, DisconnectReason};
+use crate::current_context::Contexts;
+use crate::leaks::HandleLedger;
+use crate::visible_devices::DeviceFilter;
+
+/// Flips to `true` once the local application on an IPC connection has
+/// disconnected, so requests still being forwarded for it can be cancelled.
+pub type PeerGone = tokio::sync::watch::Receiver<bool>;
+
+/// Shared memory an IPC connection may set up for bulk data (see
+/// [`rgpu_common::shm`]).
+#[derive(Debug, Clone, Copy)]
+pub struct SharedMemorySettings {
+    /// Largest region an application is given; 0 turns shared memory off
+    pub max_size: usize,
+    /// The application's uid, which the region is given to
+    pub owner: Option<u32>,
+}
+
+/// Serve one IPC connection until the application disconnects.
+///
+/// Frames are read on a separate task so a disconnect is noticed (and
+/// signalled via [`PeerGone`]) while a request is still being forwarded.
+async fn serve_ipc_connection<R, W, H>(
+    mut reader: R,
+    mut writer: W,
+    handler: std::sync::Arc<H>,
+    mut breadcrumbs: Breadcrumbs,
+    shared_memory: SharedMemorySettings,
+) where
+    R: tokio::io::AsyncRead + Unpin + Send + 'static,
+    W: tokio::io::AsyncWrite + Unpin,
+    H: Fn(Message, PeerGone, Contexts) -> Option<Message> + Send + Sync + 'static,
+{
+    let (gone_tx, gone_rx) = tokio::sync::watch::channel(false);
+    let (msg_tx, mut msg_rx) = tokio::sync::mpsc::channel::<Message>(64);
+    let shared: Arc<OnceLock<SharedRegion>> = Arc::default();
+    let reader_shared = shared.clone();
+
+    let reader_task = tokio::spawn(async move {
+        let mut header_buf = [0u8; wire::HEADER_SIZE];
+
+        let reason = loop {
+            if let Err(e) = reader.read_exact(&mut header_buf).await {
+                break if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                    DisconnectReason::Closed
+                } else {
+                    DisconnectReason::Broken(e.to_string())
+                };
+            }
+            let (flags, _stream_id, payload_len) = match wire::decode_header(&header_buf) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!("IPC decode error: {}", e);
+                    break DisconnectReason::Broken(format!("bad frame header: {}", e));
+                }
+            };
+
+            let mut payload = vec![0u8; payload_len as usize];
+            if let Err(e) = reader.read_exact(&mut payload).await {
+                break DisconnectReason::Broken(format!("stream ended mid-frame: {}", e));
+            }
+            let (flags, payload) = match unshare(flags, payload, reader_shared.get()) {
+                Ok(frame) => frame,
+                Err(reason) => break DisconnectReason::Broken(reason),
+            };
+
+            let msg = match wire::decode_message(&payload, flags) {
+                Ok(m) => m,
+                Err(e) => {
+                    error!("IPC message decode error: {}", e);
+                    continue;
+                }
+            };
+
+            if msg_tx.send(msg).await.is_err() {
+                break DisconnectReason::Closed;
+            }
+        };
+
+        let _ = gone_tx.send(true);
+        reason
+    });
+
+    let mut ledger = HandleLedger::default();
+    let contexts = Contexts::default();
+    let mut attached = false;
+    let mut visible: Option<DeviceFilter> = None;
+    while let Some(msg) = msg_rx.recv().await {
+        let mut msg = match msg {
+            Message::OpenSharedMemory { size } => {
+                let response = open_shared_memory(&shared, shared_memory, size);
+                if write_frame(&mut writer, &response, None).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+            Message::SharedMemoryAttached => {
+                if let Some(region) = shared.get() {
+                    // Mapped on both sides: the name has done its job.
+                    region.unlink();
+                    attached = true;
+                    debug!("IPC connection using {} bytes of shared memory", region.size());
+                }
+                if write_frame(&mut writer, &Message::Pong, None).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+            Message::SetVisibleDevices(devices) => {
+                debug!("IPC connection restricted to devices {:?}", devices);
+                visible = Some(DeviceFilter::new(devices.clone()));
+                if write_frame(&mut writer, &Message::SetVisibleDevices(devices), None).await.is_err() {
+                    break;
+                }
+                continue;
+            }
+            msg => msg,
+        };
+        if let Some(refusal) = visible.as_ref().and_then(|filter| filter.request(&mut msg)) {
+            if write_frame(&mut writer, &refusal, None).await.is_err() {
+                break;
+            }
+            continue;
+        }
+        let region = shared.get().filter(|_| attached);
+        let pending = breadcrumbs.begin(&msg);
+        let creation = ledger.begin(&msg);
+        let chunk_size = match &msg {
+            Message::CudaCommandStreamed { chunk_size, .. } => Some(*chunk_size as usize),
+            _ => None,
+        };
+        let response = match handler(msg, gone_rx.clone(), contexts.clone()) {
+            Some(resp) => resp,
+            None => {
+                // Fallback: send an error response so the app doesn't hang
+                error!("IPC handler returned None, sending error response");
+                Message::CudaResponse {
+                    request_id: rgpu_protocol::messages::RequestId(0),
+                    response: rgpu_protocol::cuda_commands::CudaResponse::Error {
+                        code: 999,
+                        message: "internal daemon error".to_string(),
+                    },
+                }
+            }
+        };
+        let response = match visible.as_mut() {
+            Some(filter) => filter.response(response),
+            None => response,
+        };
+        if let Some(pending) = pending {
+            breadcrumbs.record(pending, &response, *gone_rx.borrow());
+        }
+        if let Some(creation) = creation {
+            ledger.record(creation, &response);
+        }
+
+        if write_response(&mut writer, response, chunk_size, region).await.is_err() {
+            break;
+        }
+    }
+
+    let reason = reader_task.await.unwrap_or(DisconnectReason::Closed);
+    if !ledger.is_empty() {
+        handler(Message::CudaBatch(ledger.free_commands()), gone_rx.clone(), contexts);
+        if breadcrumbs.leak_warnings() {
+            let leaked = ledger.summary();
+            warn!(
+                "{} exited without freeing {} - freed automatically",
+                breadcrumbs.peer(),
+                leaked
+            );
+            breadcrumbs.set_leaked(leaked);
+        }
+    }
+    breadcrumbs.finish(&reason);
+
+    debug!("IPC client disconnected");
+}
+
+/// Write one response. For a `CudaCommandStreamed` request (`chunk_size`
+/// set), `MemoryData` larger than one chunk is sent as `MemoryChunk`s
+/// followed by a `Success` response. The chunks are slices of the payload
+/// the server sent whole; only this hop is split up.
+async fn write_response<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    response: Message,
+    chunk_size: Option<usize>,
+    shared: Option<&SharedRegion>,
+) -> std::io::Result<()> {
+    let (request_id, data, chunk_size) = match (response, chunk_size) {
+        (
+            Message::CudaResponse {
+                request_id,
+                response: CudaResponse::MemoryData(data),
+            },
+            Some(chunk_size),
+        ) if chunk_size > 0 && data.len() > chunk_size => (request_id, Bytes::from(data), chunk_size),
+        (response, _) => return write_frame(writer, &response, shared).await,
+    };
+
+    for offset in (0..data.len()).step_by(chunk_size) {
+        let chunk = Message::MemoryChunk {
+            request_id,
+            offset: offset as u64,
+            data: data.slice(offset..(offset + chunk_size).min(data.len())),
+        };
+        write_frame(writer, &chunk, shared).await?;
+    }
+    write_frame(
+        writer,
+        &Message::CudaResponse {
+            request_id,
+            response: CudaResponse::Success,
+        },
+        shared,
+    )
+    .await
+}
+
+/// Write one frame. With shared memory, nothing is compressed and large
+/// payloads go through the ring, unless it is full.
+async fn write_frame<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    msg: &Message,
+    shared: Option<&SharedRegion>,
+) -> std::io::Result<()> {
+    let encoded = match shared {
+        Some(_) => wire::encode_message_uncompressed(msg, 0),
+        None => wire::encode_message(msg, 0),
+    };
+    match encoded {
+        Ok(frame) => {
+            let position = shared
+                .filter(|_| frame.len() - wire::HEADER_SIZE >= wire::SHARED_MEMORY_THRESHOLD)
+                .and_then(|region| region.ring(Direction::ToApplication).push(&frame[wire::HEADER_SIZE..]));
+            match position {
+                Some(position) => writer.write_all(&wire::shared_frame(&frame, position)).await,
+                None => writer.write_all(&frame).await,
+            }
+        }
+        Err(e) => {
+            error!("IPC encode error: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// The payload of a frame the application sent, taken out of shared memory
+/// if the frame is `SHARED`.
+fn unshare(
+    flags: FrameFlags,
+    payload: Vec<u8>,
+    shared: Option<&SharedRegion>,
+) -> Result<(FrameFlags, Vec<u8>), String> {
+    if !flags.contains(FrameFlags::SHARED) {
+        return Ok((flags, payload));
+    }
+    let region = shared.ok_or("shared-memory frame before shared memory was set up")?;
+    let (position, len) = wire::shared_location(&payload).map_err(|e| e.to_string())?;
+    let payload = region
+        .ring(Direction::ToDaemon)
+        .pop(position, len)
+        .ok_or_else(|| format!("shared-memory frame points outside its ring ({} bytes at {})", len, position))?;
+    Ok((flags - FrameFlags::SHARED, payload))
+}
+
+/// Create the connection's shared-memory region, at most `max_size` bytes.
+fn open_shared_memory(shared: &OnceLock<SharedRegion>, settings: SharedMemorySettings, size: u64) -> Message {
+    if settings.max_size == 0 {
+        return Message::Error(ProtocolError::NotImplemented("shared memory is turned off".to_string()));
+    }
+    if let Some(region) = shared.get() {
+        return Message::SharedMemory {
+            name: region.name().to_string(),
+            size: region.size() as u64,
+        };
+    }
+    let size = (size as usize).min(settings.max_size);
+    match SharedRegion::create(size, settings.owner) {
+        Ok(region) => {
+            let region = shared.get_or_init(|| region);
+            Message::SharedMemory {
+                name: region.name().to_string(),
+                size: region.size() as u64,
+            }
+        }
+        Err(e) => {
+            warn!("can't create IPC shared memory, using the socket: {}", e);
+            Message::Error(ProtocolError::ConnectionFailed(format!("can't create shared memory: {}", e)))
+        }
+    }
+}
 
 /// IPC server that listens for connections from the Vulkan ICD and CUDA
 /// interposition library. Uses named pipes on Windows and Unix domain
 /// sockets on Linux/macOS.
-
 #[cfg(unix)]
 pub async fn start_ipc_listener(
     path: &str,
-    message_handler: impl Fn(Message) -> Option<Message> + Send + Sync + 'static,
+    breadcrumbs: BreadcrumbSettings,
+    shared_memory_mb: u64,
+    message_handler: impl Fn(Message, PeerGone, Contexts) -> Option<Message> + Send + Sync + 'static,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     use tokio::net::UnixListener;
 
-    // Remove stale socket if it exists
-    let _ = std::fs::remove_file(path);
-
-    let listener = UnixListener::bind(path)?;
-    info!("IPC listening on {}", path);
+    let listener = match inherited_listener() {
+        Some(std_listener) => {
+            std_listener.set_nonblocking(true)?;
+            info!("IPC listening on socket-activated descriptor");
+            UnixListener::from_std(std_listener)?
+        }
+        None => {
+            // Remove stale socket if it exists
+            let _ = std::fs::remove_file(path);
+
+            let listener = UnixListener::bind(path)?;
+            info!("IPC listening on {}", path);
+            listener
+        }
+    };
 
     let handler = std::sync::Arc::new(message_handler);
 
     loop {
         let (stream, _) = listener.accept().await?;
         let handler = handler.clone();
+        let peer = stream.peer_cred().ok();
+        let peer_pid = peer.and_then(|cred| cred.pid()).map(|pid| pid as u32);
+        let breadcrumbs = Breadcrumbs::new(breadcrumbs.clone(), peer_pid);
+        let shared_memory = SharedMemorySettings {
+            max_size: (shared_memory_mb as usize) << 20,
+            owner: peer.map(|cred| cred.uid()),
+        };
 
         tokio::spawn(async move {
-            let (mut reader, mut writer) = stream.into_split();
-            let mut header_buf = [0u8; wire::HEADER_SIZE];
-
-            loop {
-                match reader.read_exact(&mut header_buf).await {
-                    Ok(_) => {}
-                    Err(_) => break,
-                }
-
-                let (flags, _stream_id, payload_len) = match wire::decode_header(&header_buf) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!("IPC decode error: {}", e);
-                        break;
-                    }
-                };
-
-                let mut payload = vec![0u8; payload_len as usize];
-                if reader.read_exact(&mut payload).await.is_err() {
-                    break;
-                }
-
-                let msg = match wire::decode_message(&payload, flags) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        error!("IPC message decode error: {}", e);
-                        continue;
-                    }
-                };
+            let (reader, writer) = stream.into_split();
+            serve_ipc_connection(reader, writer, handler, breadcrumbs, shared_memory).await;
+        });
+    }
+}
 
-                let response = match handler(msg) {
-                    Some(resp) => resp,
-                    None => {
-                        // Fallback: send an error response so the app doesn't hang
-                        error!("IPC handler returned None, sending error response");
-                        Message::CudaResponse {
-                            request_id: rgpu_protocol::messages::RequestId(0),
-                            response: rgpu_protocol::cuda_commands::CudaResponse::Error {
-                                code: 999,
-                                message: "internal daemon error".to_string(),
-                            },
-                        }
-                    }
-                };
+/// Take over the listening socket passed by systemd socket activation
+/// (`LISTEN_PID`/`LISTEN_FDS`), if this process was started that way.
+#[cfg(unix)]
+fn inherited_listener() -> Option<std::os::unix::net::UnixListener> {
+    use std::os::unix::io::FromRawFd;
 
-                match wire::encode_message(&response, 0) {
-                    Ok(frame) => {
-                        if writer.write_all(&frame).await.is_err() {
-                            break;
-                        }
-                    }
-                    Err(e) => {
-                        error!("IPC encode error: {}", e);
-                    }
-                }
-            }
+    /// First descriptor passed by the service manager (`SD_LISTEN_FDS_START`).
+    const LISTEN_FDS_START: i32 = 3;
 
-            debug!("IPC client disconnected");
-        });
+    let pid: u32 = std::env::var("LISTEN_PID").ok()?.parse().ok()?;
+    let fds: u32 = std::env::var("LISTEN_FDS").ok()?.parse().ok()?;
+    if pid != std::process::id() || fds == 0 {
+        return None;
     }
+    // Children must not try to adopt the same descriptor.
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    // SAFETY: the service manager guarantees fd 3 is an open listening socket
+    // handed to this process; nothing else in the daemon owns it.
+    Some(unsafe { std::os::unix::net::UnixListener::from_raw_fd(LISTEN_FDS_START) })
 }
 
 #[cfg(windows)]
@@ -163,10 +447,24 @@ fn create_pipe_with_open_access(
     Ok(server)
 }
 
+/// Process ID of the application on the other end of a named pipe.
+#[cfg(windows)]
+fn pipe_client_pid(server: &tokio::net::windows::named_pipe::NamedPipeServer) -> Option<u32> {
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::System::Pipes::GetNamedPipeClientProcessId;
+
+    let mut pid = 0u32;
+    // Safety: the handle belongs to a connected pipe instance owned by `server`.
+    let ok = unsafe { GetNamedPipeClientProcessId(server.as_raw_handle() as _, &mut pid) };
+    (ok != 0).then_some(pid)
+}
+
 #[cfg(windows)]
 pub async fn start_ipc_listener(
     pipe_name: &str,
-    message_handler: impl Fn(Message) -> Option<Message> + Send + Sync + 'static,
+    breadcrumbs: BreadcrumbSettings,
+    shared_memory_mb: u64,
+    message_handler: impl Fn(Message, PeerGone, Contexts) -> Option<Message> + Send + Sync + 'static,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     info!("IPC listening on {}", pipe_name);
 
@@ -177,56 +475,79 @@ pub async fn start_ipc_listener(
 
         server.connect().await?;
         let handler = handler.clone();
+        let breadcrumbs = Breadcrumbs::new(breadcrumbs.clone(), pipe_client_pid(&server));
+        let shared_memory = SharedMemorySettings {
+            max_size: (shared_memory_mb as usize) << 20,
+            owner: None,
+        };
 
         tokio::spawn(async move {
-            let (mut reader, mut writer) = tokio::io::split(server);
-            let mut header_buf = [0u8; wire::HEADER_SIZE];
-
-            loop {
-                match AsyncReadExt::read_exact(&mut reader, &mut header_buf).await {
-                    Ok(_) => {}
-                    Err(_) => break,
-                }
-
-                let (flags, _stream_id, payload_len) = match wire::decode_header(&header_buf) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        error!("IPC decode error: {}", e);
-                        break;
-                    }
-                };
-
-                let mut payload = vec![0u8; payload_len as usize];
-                if AsyncReadExt::read_exact(&mut reader, &mut payload)
-                    .await
-                    .is_err()
-                {
-                    break;
-                }
+            let (reader, writer) = tokio::io::split(server);
+            serve_ipc_connection(reader, writer, handler, breadcrumbs, shared_memory).await;
+        });
+    }
+}
 
-                let msg = match wire::decode_message(&payload, flags) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        error!("IPC message decode error: {}", e);
-                        continue;
-                    }
-                };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::messages::RequestId;
+
+    /// Decode the frames `write_response` wrote to `buf`.
+    fn frames(mut buf: &[u8]) -> Vec<Message> {
+        let mut messages = Vec::new();
+        while !buf.is_empty() {
+            let header: &[u8; wire::HEADER_SIZE] = buf[..wire::HEADER_SIZE].try_into().unwrap();
+            let (flags, _, len) = wire::decode_header(header).unwrap();
+            let end = wire::HEADER_SIZE + len as usize;
+            let payload = buf[wire::HEADER_SIZE..end].to_vec();
+            messages.push(wire::decode_message(&payload, flags).unwrap());
+            buf = &buf[end..];
+        }
+        messages
+    }
 
-                if let Some(response) = handler(msg) {
-                    match wire::encode_message(&response, 0) {
-                        Ok(frame) => {
-                            if AsyncWriteExt::write_all(&mut writer, &frame).await.is_err() {
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            error!("IPC encode error: {}", e);
-                        }
-                    }
+    #[tokio::test]
+    async fn test_streamed_response_comes_in_chunks() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        let response = Message::CudaResponse {
+            request_id: RequestId(3),
+            response: CudaResponse::MemoryData(data.clone()),
+        };
+        let mut buf = Vec::new();
+        write_response(&mut buf, response, Some(4096), None).await.unwrap();
+
+        let messages = frames(&buf);
+        assert_eq!(messages.len(), 4);
+        let mut received = vec![0u8; data.len()];
+        for msg in &messages[..3] {
+            match msg {
+                Message::MemoryChunk { request_id, offset, data } => {
+                    assert_eq!(*request_id, RequestId(3));
+                    assert!(data.len() <= 4096);
+                    received[*offset as usize..*offset as usize + data.len()].copy_from_slice(data);
                 }
+                other => panic!("expected MemoryChunk, got {:?}", other),
             }
+        }
+        assert_eq!(received, data);
+        assert!(matches!(
+            messages[3],
+            Message::CudaResponse { response: CudaResponse::Success, .. }
+        ));
+    }
 
-            debug!("IPC client disconnected");
-        });
+    #[tokio::test]
+    async fn test_small_response_is_sent_whole() {
+        let response = Message::CudaResponse {
+            request_id: RequestId(4),
+            response: CudaResponse::MemoryData(vec![1; 100]),
+        };
+        let mut buf = Vec::new();
+        write_response(&mut buf, response, Some(4096), None).await.unwrap();
+        match &frames(&buf)[..] {
+            [Message::CudaResponse { response: CudaResponse::MemoryData(data), .. }] => assert_eq!(data, &vec![1; 100]),
+            other => panic!("expected MemoryData, got {:?}", other),
+        }
     }
 }
@@ -0,0 +1,293 @@
+//! Handles an application leaves behind.
+//!
+//! Each IPC connection keeps a [`HandleLedger`] of the CUDA objects created
+//! through it and not yet destroyed. When the application disconnects, what
+//! is left is freed on its servers (they would otherwise live as long as the
+//! daemon's server sessions) and summarised, e.g. "3 streams, 1 module,
+//! 2.1 GB device memory", so users can find the frees missing from their
+//! code.
+
+use std::collections::{BTreeMap, HashMap};
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::messages::Message;
+
+/// Kinds of objects tracked, in the order they are freed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Kind {
+    TexObject,
+    SurfObject,
+    GraphExec,
+    Graph,
+    Linker,
+    Event,
+    Stream,
+    Array,
+    IpcMemory,
+    DeviceMemory,
+    HostMemory,
+    Module,
+    MemPool,
+    Context,
+}
+
+impl Kind {
+    fn noun(self, count: usize) -> &'static str {
+        let one = count == 1;
+        match self {
+            Kind::TexObject => if one { "texture object" } else { "texture objects" },
+            Kind::SurfObject => if one { "surface object" } else { "surface objects" },
+            Kind::GraphExec => if one { "graph exec" } else { "graph execs" },
+            Kind::Graph => if one { "graph" } else { "graphs" },
+            Kind::Linker => if one { "linker" } else { "linkers" },
+            Kind::Event => if one { "event" } else { "events" },
+            Kind::Stream => if one { "stream" } else { "streams" },
+            Kind::Array => if one { "array" } else { "arrays" },
+            Kind::IpcMemory => if one { "opened IPC allocation" } else { "opened IPC allocations" },
+            Kind::DeviceMemory => "device memory",
+            Kind::HostMemory => "pinned host memory",
+            Kind::Module => if one { "module" } else { "modules" },
+            Kind::MemPool => if one { "memory pool" } else { "memory pools" },
+            Kind::Context => if one { "context" } else { "contexts" },
+        }
+    }
+
+    /// Command that destroys an object of this kind.
+    fn destroy(self, handle: NetworkHandle) -> CudaCommand {
+        match self {
+            Kind::TexObject => CudaCommand::TexObjectDestroy { tex_object: handle },
+            Kind::SurfObject => CudaCommand::SurfObjectDestroy { surf_object: handle },
+            Kind::GraphExec => CudaCommand::GraphExecDestroy { exec: handle },
+            Kind::Graph => CudaCommand::GraphDestroy { graph: handle },
+            Kind::Linker => CudaCommand::LinkDestroy { link: handle },
+            Kind::Event => CudaCommand::EventDestroy { event: handle },
+            Kind::Stream => CudaCommand::StreamDestroy { stream: handle },
+            Kind::Array => CudaCommand::ArrayDestroy { array: handle },
+            Kind::IpcMemory => CudaCommand::IpcCloseMemHandle { dptr: handle },
+            Kind::DeviceMemory => CudaCommand::MemFree { dptr: handle },
+            Kind::HostMemory => CudaCommand::MemFreeHost { ptr: handle },
+            Kind::Module => CudaCommand::ModuleUnload { module: handle },
+            Kind::MemPool => CudaCommand::MemPoolDestroy { pool: handle },
+            Kind::Context => CudaCommand::CtxDestroy { ctx: handle },
+        }
+    }
+}
+
+/// An object a command creates, known once its response arrives.
+pub struct PendingCreation {
+    kind: Kind,
+    bytes: u64,
+    /// For pitched allocations the size depends on the pitch in the response
+    height: Option<u64>,
+}
+
+/// Live CUDA objects created through one IPC connection.
+#[derive(Default)]
+pub struct HandleLedger {
+    live: HashMap<NetworkHandle, (Kind, u64)>,
+}
+
+impl HandleLedger {
+    /// Note a request as it is handed to the message handler: objects it
+    /// destroys are forgotten right away (they may be queued void commands
+    /// without a response of their own), and an object it creates is
+    /// returned to be recorded with the response.
+    pub fn begin(&mut self, request: &Message) -> Option<PendingCreation> {
+        match request {
+            Message::CudaCommand { command, .. } | Message::CudaCommandStreamed { command, .. } => {
+                self.forget(command);
+                creation(command)
+            }
+            Message::CudaBatch(batch) => {
+                batch.iter().for_each(|c| self.forget(c));
+                None
+            }
+            Message::CudaPipelined { batch, command, .. } => {
+                batch.iter().for_each(|c| self.forget(c));
+                self.forget(command);
+                creation(command)
+            }
+            _ => None,
+        }
+    }
+
+    /// Record the object created by a request started with [`HandleLedger::begin`].
+    pub fn record(&mut self, pending: PendingCreation, response: &Message) {
+        let Message::CudaResponse { response, .. } = response else {
+            return;
+        };
+        let bytes = match (response, pending.height) {
+            (CudaResponse::MemAllocPitch { pitch, .. }, Some(height)) => pitch * height,
+            _ => pending.bytes,
+        };
+        let mut created = None;
+        response.handles(|h| {
+            created.get_or_insert(*h);
+        });
+        if let Some(handle) = created {
+            self.live.insert(handle, (pending.kind, bytes));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    /// Commands that free everything still live, dependents before the
+    /// objects they belong to.
+    pub fn free_commands(&self) -> Vec<CudaCommand> {
+        let mut live: Vec<_> = self.live.iter().map(|(h, (kind, _))| (*kind, *h)).collect();
+        live.sort_by_key(|(kind, _)| *kind);
+        live.into_iter().map(|(kind, handle)| kind.destroy(handle)).collect()
+    }
+
+    /// Per-kind summary, e.g. "3 streams, 1 module, 2.1 GB device memory".
+    pub fn summary(&self) -> String {
+        let mut totals: BTreeMap<Kind, (usize, u64)> = BTreeMap::new();
+        for (kind, bytes) in self.live.values() {
+            let total = totals.entry(*kind).or_default();
+            total.0 += 1;
+            total.1 += bytes;
+        }
+        totals
+            .into_iter()
+            .map(|(kind, (count, bytes))| match kind {
+                Kind::DeviceMemory | Kind::HostMemory => {
+                    format!("{} {}", format_bytes(bytes), kind.noun(count))
+                }
+                _ => format!("{} {}", count, kind.noun(count)),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn forget(&mut self, command: &CudaCommand) {
+        let handle = match command {
+            CudaCommand::TexObjectDestroy { tex_object } => tex_object,
+            CudaCommand::SurfObjectDestroy { surf_object } => surf_object,
+            CudaCommand::GraphExecDestroy { exec } => exec,
+            CudaCommand::GraphDestroy { graph } => graph,
+            CudaCommand::LinkDestroy { link } => link,
+            CudaCommand::EventDestroy { event } => event,
+            CudaCommand::StreamDestroy { stream } => stream,
+            CudaCommand::ArrayDestroy { array } => array,
+            CudaCommand::IpcCloseMemHandle { dptr } => dptr,
+            CudaCommand::MemFree { dptr } | CudaCommand::MemFreeAsync { dptr, .. } => dptr,
+            CudaCommand::MemFreeHost { ptr } | CudaCommand::MemHostUnregister { ptr } => ptr,
+            CudaCommand::ModuleUnload { module } => module,
+            CudaCommand::MemPoolDestroy { pool } => pool,
+            CudaCommand::CtxDestroy { ctx } => ctx,
+            _ => return,
+        };
+        self.live.remove(handle);
+    }
+}
+
+/// The object `command` creates, if any.
+fn creation(command: &CudaCommand) -> Option<PendingCreation> {
+    let (kind, bytes, height) = match command {
+        CudaCommand::CtxCreate { .. } => (Kind::Context, 0, None),
+        CudaCommand::ModuleLoad { .. }
+        | CudaCommand::ModuleLoadData { .. }
+        | CudaCommand::ModuleLoadDataEx { .. }
+        | CudaCommand::ModuleLoadFatBinary { .. } => (Kind::Module, 0, None),
+        CudaCommand::MemAlloc { byte_size }
+        | CudaCommand::MemAllocManaged { byte_size, .. }
+        | CudaCommand::MemAllocAsync { byte_size, .. }
+        | CudaCommand::MemAllocFromPoolAsync { byte_size, .. } => (Kind::DeviceMemory, *byte_size, None),
+        CudaCommand::MemAllocPitch { height, .. } => (Kind::DeviceMemory, 0, Some(*height)),
+        CudaCommand::MemAllocHost { byte_size }
+        | CudaCommand::MemHostAlloc { byte_size, .. }
+        | CudaCommand::MemHostRegister { byte_size, .. } => {
+            (Kind::HostMemory, *byte_size, None)
+        }
+        CudaCommand::StreamCreate { .. } | CudaCommand::StreamCreateWithPriority { .. } => {
+            (Kind::Stream, 0, None)
+        }
+        CudaCommand::EventCreate { .. } => (Kind::Event, 0, None),
+        CudaCommand::MemPoolCreate { .. } => (Kind::MemPool, 0, None),
+        CudaCommand::LinkCreate { .. } => (Kind::Linker, 0, None),
+        CudaCommand::GraphCreate { .. } | CudaCommand::StreamEndCapture { .. } => (Kind::Graph, 0, None),
+        CudaCommand::GraphInstantiate { .. } => (Kind::GraphExec, 0, None),
+        CudaCommand::TexObjectCreate { .. } => (Kind::TexObject, 0, None),
+        CudaCommand::SurfObjectCreate { .. } => (Kind::SurfObject, 0, None),
+        CudaCommand::ArrayCreate { .. } => (Kind::Array, 0, None),
+        CudaCommand::IpcOpenMemHandle { .. } => (Kind::IpcMemory, 0, None),
+        _ => return None,
+    };
+    Some(PendingCreation { kind, bytes, height })
+}
+
+/// `bytes` in the largest binary unit that keeps it at least 1, e.g. "2.1 GB".
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::ResourceType;
+    use rgpu_protocol::messages::RequestId;
+
+    fn command(command: CudaCommand) -> Message {
+        Message::CudaCommand {
+            request_id: RequestId(1),
+            command,
+            deadline_ms: None,
+        }
+    }
+
+    fn respond(ledger: &mut HandleLedger, request: CudaCommand, response: CudaResponse) {
+        let pending = ledger.begin(&command(request)).expect("creating command");
+        ledger.record(
+            pending,
+            &Message::CudaResponse {
+                request_id: RequestId(1),
+                response,
+            },
+        );
+    }
+
+    #[test]
+    fn leftovers_are_summarised_and_freed_in_order() {
+        let handle = |id, resource_type| NetworkHandle {
+            server_id: 0,
+            session_id: 1,
+            resource_id: id,
+            resource_type,
+        };
+        let ctx = handle(1, ResourceType::CuContext);
+        let stream = handle(2, ResourceType::CuStream);
+        let mem = handle(3, ResourceType::CuDevicePtr);
+        let freed = handle(4, ResourceType::CuDevicePtr);
+
+        let mut ledger = HandleLedger::default();
+        let device = handle(0, ResourceType::CuDevice);
+        respond(&mut ledger, CudaCommand::CtxCreate { flags: 0, device }, CudaResponse::Context(ctx));
+        respond(&mut ledger, CudaCommand::StreamCreate { flags: 0 }, CudaResponse::Stream(stream));
+        respond(&mut ledger, CudaCommand::MemAlloc { byte_size: 3 << 29 }, CudaResponse::MemAllocated(mem));
+        respond(&mut ledger, CudaCommand::MemAlloc { byte_size: 1024 }, CudaResponse::MemAllocated(freed));
+        // Frees can arrive as queued void commands.
+        ledger.begin(&Message::CudaBatch(vec![CudaCommand::MemFree { dptr: freed }]));
+
+        assert_eq!(ledger.summary(), "1 stream, 1.5 GB device memory, 1 context");
+        let frees: Vec<String> = ledger
+            .free_commands()
+            .iter()
+            .map(crate::breadcrumbs::variant_name)
+            .collect();
+        assert_eq!(frees, ["StreamDestroy", "MemFree", "CtxDestroy"]);
+    }
+}
@@ -1,5 +1,20 @@
 pub mod daemon;
 pub mod pool_manager;
+pub mod breadcrumbs;
+pub mod chunking;
+pub mod content_cache;
+pub mod current_context;
+pub mod failover;
+pub mod gpu_order;
 pub mod ipc;
+pub mod leaks;
+pub mod mirror;
+pub mod placement;
+pub mod prefetch;
+pub mod readback;
+pub mod relocation;
+pub mod spill;
+pub mod transport_probe;
+pub mod visible_devices;
 
 pub use daemon::ClientDaemon;
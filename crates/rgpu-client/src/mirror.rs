@@ -0,0 +1,261 @@
+//! A/B mirror mode for validating a server before migrating workloads.
+//!
+//! Every CUDA command the daemon forwards to a remote server is replayed, in
+//! order, on a second "mirror" server over its own connection. Handles
+//! returned by the two servers are paired up so later commands can be
+//! rewritten into the mirror's handle space. Key responses are compared:
+//! - result kind (success vs. error, and error codes),
+//! - device-to-host copies, by content hash,
+//! - event timings, within a relative tolerance.
+//!
+//! Mismatches are logged and optionally appended to a report file. The
+//! mirror runs on its own task, so it never adds latency to the application.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Write as _;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use rgpu_core::config::MirrorConfig;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::messages::{Message, RequestId};
+
+use crate::breadcrumbs::variant_name;
+use crate::daemon::{reconnect, ServerConn};
+
+/// Commands queued for the mirror before forwarding starts to wait on it.
+const MIRROR_QUEUE_DEPTH: usize = 256;
+
+/// Log a summary after this many compared responses.
+const SUMMARY_INTERVAL: u64 = 1000;
+
+/// Absolute slack for event timing comparisons, in milliseconds.
+const TIMING_SLACK_MS: f32 = 0.05;
+
+enum MirrorJob {
+    Command {
+        request_id: RequestId,
+        command: CudaCommand,
+        primary: Box<CudaResponse>,
+    },
+    Batch(Vec<CudaCommand>),
+}
+
+/// Handle to the mirror task.
+pub struct Mirror {
+    tx: mpsc::Sender<MirrorJob>,
+}
+
+impl Mirror {
+    /// Spawn the mirror task. Connects lazily on the first command.
+    pub fn start(config: MirrorConfig) -> Self {
+        info!(
+            "A/B mirror mode: replaying CUDA commands on {}",
+            config.server.address
+        );
+        let (tx, rx) = mpsc::channel(MIRROR_QUEUE_DEPTH);
+        tokio::spawn(run_mirror(config, rx));
+        Self { tx }
+    }
+
+    /// Replay `command` on the mirror and compare with the primary's response.
+    pub async fn submit(&self, request_id: RequestId, command: CudaCommand, primary: &Message) {
+        let Message::CudaResponse { response, .. } = primary else {
+            // Daemon-side failures (cancelled, expired) never reached the GPU.
+            return;
+        };
+        let job = MirrorJob::Command {
+            request_id,
+            command,
+            primary: Box::new(response.clone()),
+        };
+        let _ = self.tx.send(job).await;
+    }
+
+    /// Replay a batch of void commands; there is nothing to compare.
+    pub async fn submit_batch(&self, commands: Vec<CudaCommand>) {
+        let _ = self.tx.send(MirrorJob::Batch(commands)).await;
+    }
+}
+
+#[derive(Default)]
+struct MirrorStats {
+    compared: u64,
+    mismatches: u64,
+    failures: u64,
+}
+
+async fn run_mirror(config: MirrorConfig, mut rx: mpsc::Receiver<MirrorJob>) {
+    let mut conn: Option<ServerConn> = None;
+    // Primary handle -> mirror handle
+    let mut handles: HashMap<NetworkHandle, NetworkHandle> = HashMap::new();
+    let mut stats = MirrorStats::default();
+    let mut report = config.report_path.as_ref().and_then(|path| {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| warn!("cannot open mirror report {}: {}", path, e))
+            .ok()
+    });
+
+    while let Some(job) = rx.recv().await {
+        if conn.is_none() {
+            match reconnect(&config.server).await {
+                Ok((new_conn, _sid)) => {
+                    if !handles.is_empty() && new_conn.restored == 0 {
+                        warn!("mirror reconnected with a new session; earlier handles are lost");
+                        handles.clear();
+                    }
+                    conn = Some(new_conn);
+                }
+                Err(e) => {
+                    warn!("mirror server {} unavailable: {}", config.server.address, e);
+                    stats.failures += 1;
+                    continue;
+                }
+            }
+        }
+        let Some(mirror_conn) = conn.as_mut() else {
+            continue;
+        };
+
+        let (msg, expected) = match job {
+            MirrorJob::Command {
+                request_id,
+                mut command,
+                primary,
+            } => {
+                translate(&mut command, &handles);
+                let name = variant_name(&command);
+                let msg = Message::CudaCommand {
+                    request_id,
+                    command,
+                    deadline_ms: None,
+                };
+                (msg, Some((request_id, name, primary)))
+            }
+            MirrorJob::Batch(mut commands) => {
+                for command in &mut commands {
+                    translate(command, &handles);
+                }
+                (Message::CudaBatch(commands), None)
+            }
+        };
+
+        let response = match mirror_conn.send_and_receive(&msg).await {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("mirror request failed: {}", e);
+                stats.failures += 1;
+                conn = None;
+                continue;
+            }
+        };
+
+        let Some((request_id, name, primary)) = expected else {
+            continue;
+        };
+        let Message::CudaResponse { response: mirrored, .. } = response else {
+            stats.failures += 1;
+            continue;
+        };
+
+        pair_handles(&primary, &mirrored, &mut handles);
+        stats.compared += 1;
+        if let Some(detail) = compare(&primary, &mirrored, config.timing_tolerance) {
+            stats.mismatches += 1;
+            warn!("mirror mismatch on {:?} {}: {}", request_id, name, detail);
+            if let Some(file) = report.as_mut() {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let _ = writeln!(file, "{} req={} {} {}", now, request_id.0, name, detail);
+            }
+        }
+
+        if stats.compared.is_multiple_of(SUMMARY_INTERVAL) {
+            info!(
+                "mirror: {} responses compared, {} mismatches, {} failures",
+                stats.compared, stats.mismatches, stats.failures
+            );
+        }
+    }
+}
+
+/// Rewrite primary-server handles into the mirror's handle space. Handles the
+/// mirror hasn't seen (e.g. the null stream) are passed through unchanged.
+fn translate(command: &mut CudaCommand, handles: &HashMap<NetworkHandle, NetworkHandle>) {
+    command.handles_mut(|handle| {
+        if let Some(mirrored) = handles.get(handle) {
+            *handle = *mirrored;
+        }
+    });
+}
+
+/// Both servers saw the same command, so handles in their responses
+/// correspond one-to-one.
+fn pair_handles(
+    primary: &CudaResponse,
+    mirrored: &CudaResponse,
+    handles: &mut HashMap<NetworkHandle, NetworkHandle>,
+) {
+    let mut primary_handles = Vec::new();
+    primary.handles(|h| primary_handles.push(*h));
+    let mut mirrored_handles = Vec::new();
+    mirrored.handles(|h| mirrored_handles.push(*h));
+    for (p, m) in primary_handles.into_iter().zip(mirrored_handles) {
+        handles.insert(p, m);
+    }
+}
+
+/// Describe how the mirror's response differs, if it does in a way that matters.
+fn compare(primary: &CudaResponse, mirrored: &CudaResponse, tolerance: f32) -> Option<String> {
+    match (primary, mirrored) {
+        (CudaResponse::Error { code: a, .. }, CudaResponse::Error { code: b, .. }) => {
+            (a != b).then(|| format!("error code {} vs {}", a, b))
+        }
+        (CudaResponse::MemoryData(a), CudaResponse::MemoryData(b))
+        | (
+            CudaResponse::EncodedMemoryData { data: a, .. },
+            CudaResponse::EncodedMemoryData { data: b, .. },
+        ) => (a != b).then(|| {
+            format!(
+                "DtoH hash {:016x} vs {:016x} ({} vs {} bytes)",
+                hash_bytes(a),
+                hash_bytes(b),
+                a.len(),
+                b.len()
+            )
+        }),
+        (CudaResponse::ElapsedTime(a), CudaResponse::ElapsedTime(b)) => {
+            let allowed = tolerance * a.abs().max(b.abs()) + TIMING_SLACK_MS;
+            ((a - b).abs() > allowed).then(|| format!("elapsed {:.3}ms vs {:.3}ms", a, b))
+        }
+        _ if std::mem::discriminant(primary) != std::mem::discriminant(mirrored) => Some(format!(
+            "{} vs {}",
+            describe_response(primary),
+            describe_response(mirrored)
+        )),
+        _ => None,
+    }
+}
+
+fn describe_response(response: &CudaResponse) -> String {
+    match response {
+        CudaResponse::Error { code, message } => format!("Error({}: {})", code, message),
+        other => variant_name(other),
+    }
+}
+
+fn hash_bytes(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    hasher.finish()
+}
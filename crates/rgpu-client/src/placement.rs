@@ -0,0 +1,231 @@
+//! Load-balancing placement of new CUDA contexts (`[client.placement]`).
+//!
+//! Applications mostly create their context on device 0, which would put
+//! every one of them on the same server. With a placement policy, the first
+//! context created on a remote device handle (cuCtxCreate,
+//! cuDevicePrimaryCtxRetain) goes to the GPU the policy picks among the
+//! remote GPUs of the connected servers, by default only those of the same
+//! model, memory and compute capability as the one asked for. The handle is
+//! swapped for one of the chosen GPU (see [`crate::relocation`]), so later
+//! contexts and commands on it stay there.
+//!
+//! Each decision is logged, and the chosen server is told the contexts
+//! placed on its GPUs in the session label [`PLACEMENT_LABEL`], which
+//! `rgpu stats` and the UI show.
+//!
+//! [`PLACEMENT_LABEL`]: rgpu_protocol::messages::PLACEMENT_LABEL
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use rgpu_core::config::{PlacementConfig, PlacementPolicy};
+
+use crate::pool_manager::GpuPoolEntry;
+
+/// A GPU a context may be placed on.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub gpu: GpuPoolEntry,
+    /// Address of its server, for `weights`
+    pub address: String,
+    /// VRAM allocated on it by all sessions, with `least_vram_used`
+    pub vram_used: u64,
+}
+
+/// Placement policy state.
+pub struct Placement {
+    config: PlacementConfig,
+    /// Next turn with `round_robin`
+    turn: AtomicUsize,
+    /// Smooth weighted round-robin credit with `weighted`, by server index
+    /// and device
+    credit: Mutex<HashMap<(usize, u32), i64>>,
+    /// Contexts placed, by server index and device
+    placed: Mutex<BTreeMap<(usize, u32), u32>>,
+}
+
+impl Placement {
+    pub fn new(config: PlacementConfig) -> Self {
+        Self {
+            config,
+            turn: AtomicUsize::new(0),
+            credit: Mutex::new(HashMap::new()),
+            placed: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn policy(&self) -> PlacementPolicy {
+        self.config.policy
+    }
+
+    /// The GPUs a context asked for on `asked` may go to: the remote CUDA
+    /// GPUs of the `connected` servers, in pool order.
+    pub fn candidates<'a>(
+        &self,
+        pool: &'a [GpuPoolEntry],
+        asked: &GpuPoolEntry,
+        connected: &[usize],
+    ) -> Vec<&'a GpuPoolEntry> {
+        pool.iter()
+            .filter(|g| !g.is_local && g.info.supports_cuda && connected.contains(&g.server_index))
+            .filter(|g| !self.config.match_model || g.info.is_equivalent(&asked.info))
+            .collect()
+    }
+
+    /// The candidate the policy picks for the next context.
+    pub fn choose<'a>(&self, candidates: &'a [Candidate]) -> Option<&'a Candidate> {
+        if candidates.is_empty() {
+            return None;
+        }
+        match self.config.policy {
+            PlacementPolicy::None => None,
+            PlacementPolicy::LeastVramUsed => {
+                let placed = self.placed.lock().unwrap();
+                candidates
+                    .iter()
+                    .min_by_key(|c| (c.vram_used, placed.get(&key(&c.gpu)).copied().unwrap_or(0)))
+            }
+            PlacementPolicy::RoundRobin => {
+                let turn = self.turn.fetch_add(1, Ordering::Relaxed);
+                candidates.get(turn % candidates.len())
+            }
+            PlacementPolicy::Weighted => {
+                let mut credit = self.credit.lock().unwrap();
+                let mut total = 0;
+                let mut best: Option<(&Candidate, i64)> = None;
+                for candidate in candidates {
+                    let weight = self.weight(&candidate.address);
+                    if weight == 0 {
+                        continue;
+                    }
+                    total += weight;
+                    let credit = credit.entry(key(&candidate.gpu)).or_default();
+                    *credit += weight;
+                    if best.is_none_or(|(_, most)| *credit > most) {
+                        best = Some((candidate, *credit));
+                    }
+                }
+                let (chosen, _) = best?;
+                *credit.get_mut(&key(&chosen.gpu)).unwrap() -= total;
+                Some(chosen)
+            }
+        }
+    }
+
+    fn weight(&self, address: &str) -> i64 {
+        self.config.weights.get(address).copied().unwrap_or(1) as i64
+    }
+
+    /// Count a context placed on `gpu`. Returns the contexts placed on its
+    /// server's GPUs so far, for [`PLACEMENT_LABEL`].
+    ///
+    /// [`PLACEMENT_LABEL`]: rgpu_protocol::messages::PLACEMENT_LABEL
+    pub fn note(&self, gpu: &GpuPoolEntry) -> String {
+        let mut placed = self.placed.lock().unwrap();
+        *placed.entry(key(gpu)).or_default() += 1;
+        let counts = placed
+            .iter()
+            .filter(|((server, _), _)| *server == gpu.server_index)
+            .map(|((_, device), count)| format!("GPU {}: {}", device, count))
+            .collect::<Vec<_>>()
+            .join("; ");
+        format!("{} ({})", counts, policy_name(self.config.policy))
+    }
+}
+
+fn key(gpu: &GpuPoolEntry) -> (usize, u32) {
+    (gpu.server_index, gpu.server_device_index)
+}
+
+/// A policy as written in `rgpu.toml`.
+pub fn policy_name(policy: PlacementPolicy) -> &'static str {
+    match policy {
+        PlacementPolicy::None => "none",
+        PlacementPolicy::LeastVramUsed => "least_vram_used",
+        PlacementPolicy::RoundRobin => "round_robin",
+        PlacementPolicy::Weighted => "weighted",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::gpu_info::{GpuDeviceType, GpuInfo};
+
+    fn candidate(server_index: usize, device: u32, name: &str, vram_used: u64) -> Candidate {
+        let gpu = GpuPoolEntry {
+            pool_index: (server_index * 2) as u32 + device,
+            server_index,
+            server_device_index: device,
+            info: GpuInfo {
+                device_name: name.to_string(),
+                vendor_id: 0x10de,
+                device_id: 0,
+                device_type: GpuDeviceType::DiscreteGpu,
+                total_memory: 24 << 30,
+                supports_vulkan: true,
+                supports_cuda: true,
+                vulkan_api_version: None,
+                vulkan_driver_version: None,
+                cuda_compute_capability: Some((8, 9)),
+                queue_family_count: 1,
+                memory_heaps: Vec::new(),
+                server_device_index: device,
+                server_id: server_index as u16,
+                topology: Default::default(),
+                scheduling: Default::default(),
+                uuid: None,
+            },
+            is_local: false,
+        };
+        Candidate { gpu, address: format!("gpu-{}:9876", server_index), vram_used }
+    }
+
+    fn placement(policy: PlacementPolicy, weights: &[(&str, u32)]) -> Placement {
+        Placement::new(PlacementConfig {
+            policy,
+            weights: weights.iter().map(|(a, w)| (a.to_string(), *w)).collect(),
+            match_model: true,
+        })
+    }
+
+    fn picks(placement: &Placement, candidates: &[Candidate], n: usize) -> Vec<usize> {
+        (0..n)
+            .map(|_| {
+                let chosen = placement.choose(candidates).unwrap();
+                placement.note(&chosen.gpu);
+                chosen.gpu.server_index
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_and_weighted_spread_contexts() {
+        let candidates = [candidate(0, 0, "RTX 4090", 0), candidate(1, 0, "RTX 4090", 0), candidate(2, 0, "RTX 4090", 0)];
+        let round_robin = placement(PlacementPolicy::RoundRobin, &[]);
+        assert_eq!(picks(&round_robin, &candidates, 4), [0, 1, 2, 0]);
+
+        let weighted = placement(PlacementPolicy::Weighted, &[("gpu-0:9876", 2), ("gpu-2:9876", 0)]);
+        assert_eq!(picks(&weighted, &candidates, 6), [0, 1, 0, 0, 1, 0]);
+        assert_eq!(weighted.note(&candidates[0].gpu), "GPU 0: 5 (weighted)");
+    }
+
+    #[test]
+    fn least_vram_used_prefers_idle_gpus() {
+        let candidates = [candidate(0, 0, "RTX 4090", 8 << 30), candidate(1, 0, "RTX 4090", 0), candidate(1, 1, "RTX 4090", 0)];
+        let least_used = placement(PlacementPolicy::LeastVramUsed, &[]);
+        let chosen: Vec<_> = (0..2)
+            .map(|_| {
+                let chosen = least_used.choose(&candidates).unwrap();
+                least_used.note(&chosen.gpu);
+                (chosen.gpu.server_index, chosen.gpu.server_device_index)
+            })
+            .collect();
+        assert_eq!(chosen, [(1, 0), (1, 1)]);
+
+        let pool: Vec<_> = candidates.iter().map(|c| c.gpu.clone()).chain([candidate(2, 0, "A100", 0).gpu]).collect();
+        assert_eq!(least_used.candidates(&pool, &pool[0], &[0, 2]).len(), 1);
+        assert!(placement(PlacementPolicy::None, &[]).choose(&candidates).is_none());
+    }
+}
@@ -1,13 +1,15 @@
 use std::collections::HashMap;
 
 use tokio::sync::RwLock;
-use tracing::info;
+use tracing::{info, warn};
 
 use rgpu_protocol::gpu_info::GpuInfo;
 use rgpu_protocol::handle::NetworkHandle;
 
 use rgpu_core::config::{GpuOrdering, ServerEndpoint};
 
+use crate::gpu_order::{self, StableIndices};
+
 /// Special server index for local GPUs (not routed over network).
 pub const LOCAL_SERVER_INDEX: usize = usize::MAX;
 /// Special server ID for local GPUs.
@@ -51,15 +53,22 @@ pub struct GpuPoolManager {
     /// Maps server_id → index in server_conns vec
     server_id_to_index: RwLock<HashMap<u16, usize>>,
     ordering: GpuOrdering,
+    /// UUIDs to number first with `GpuOrdering::ByUuid`
+    uuids: Vec<String>,
+    /// Indices kept across restarts, with `stable_gpu_indices`
+    stable_indices: Option<StableIndices>,
 }
 
 impl GpuPoolManager {
-    pub fn new(ordering: GpuOrdering) -> Self {
+    pub fn new(ordering: GpuOrdering, uuids: Vec<String>, stable_indices: bool) -> Self {
+        let stable_indices = stable_indices.then(|| StableIndices::load(&ordering, &uuids));
         Self {
             servers: RwLock::new(Vec::new()),
             gpu_pool: RwLock::new(Vec::new()),
             server_id_to_index: RwLock::new(HashMap::new()),
             ordering,
+            uuids,
+            stable_indices,
         }
     }
 
@@ -154,6 +163,12 @@ impl GpuPoolManager {
             .collect()
     }
 
+    /// Whether a server is connected, as of the last heartbeat.
+    pub async fn is_connected(&self, server_index: usize) -> bool {
+        let servers = self.servers.read().await;
+        servers.get(server_index).is_some_and(|s| s.status == ConnectionStatus::Connected)
+    }
+
     /// Update the connection status for a server.
     pub async fn set_server_status(&self, server_index: usize, status: ConnectionStatus) {
         let mut servers = self.servers.write().await;
@@ -219,21 +234,67 @@ impl GpuPoolManager {
         );
     }
 
-    /// Apply GPU ordering based on the configured preference.
+    /// Replace a server's GPUs with the ones it now reports, rebuilding the
+    /// pool as at startup; `apply_ordering` numbers it again. Returns whether
+    /// the GPUs changed.
+    pub async fn replace_server_gpus(&self, server_index: usize, gpus: Vec<GpuInfo>) -> bool {
+        let mut servers = self.servers.write().await;
+        let Some(server) = servers.get_mut(server_index) else {
+            return false;
+        };
+        if server.gpus == gpus {
+            return false;
+        }
+        server.gpus = gpus;
+
+        let mut pool = self.gpu_pool.write().await;
+        let local: Vec<_> = pool.iter().filter(|g| g.is_local).cloned().collect();
+        pool.clear();
+        for (server_index, server) in servers.iter().enumerate() {
+            for gpu in &server.gpus {
+                let pool_index = pool.len() as u32;
+                pool.push(GpuPoolEntry {
+                    pool_index,
+                    server_index,
+                    server_device_index: gpu.server_device_index,
+                    info: gpu.clone(),
+                    is_local: false,
+                });
+            }
+        }
+        pool.extend(local);
+        true
+    }
+
+    /// The GPUs of every server in turn, then the local ones.
+    pub async fn listed_gpus(&self) -> Vec<GpuInfo> {
+        let servers = self.servers.read().await;
+        let pool = self.gpu_pool.read().await;
+        servers
+            .iter()
+            .flat_map(|server| server.gpus.iter().cloned())
+            .chain(pool.iter().filter(|g| g.is_local).map(|g| g.info.clone()))
+            .collect()
+    }
+
+    /// Apply GPU ordering based on the configured preference, keeping the
+    /// indices GPUs had before with stable indices (see [`gpu_order`]).
     pub async fn apply_ordering(&self) {
+        let servers = self.servers.read().await;
         let mut pool = self.gpu_pool.write().await;
 
-        match self.ordering {
-            GpuOrdering::LocalFirst => {
-                pool.sort_by(|a, b| b.is_local.cmp(&a.is_local));
-            }
-            GpuOrdering::RemoteFirst => {
-                pool.sort_by(|a, b| a.is_local.cmp(&b.is_local));
-            }
-            GpuOrdering::ByCapability => {
-                pool.sort_by(|a, b| b.info.total_memory.cmp(&a.info.total_memory));
+        let pinned = gpu_order::sort(&mut pool, &self.ordering, &self.uuids);
+        if self.ordering == GpuOrdering::ByUuid {
+            for pattern in &self.uuids {
+                if !pool.iter().any(|g| gpu_order::uuid_matches(&g.info, pattern)) {
+                    warn!("gpu_uuids: no GPU {} in the pool", pattern);
+                }
             }
         }
+        if let Some(stable_indices) = &self.stable_indices {
+            let keys = gpu_order::keys(&pool, |i| servers.get(i).map(|s| s.endpoint.address.as_str()));
+            stable_indices.apply(&mut pool, &keys, pinned);
+        }
 
         // Renumber pool indices after sorting
         for (i, entry) in pool.iter_mut().enumerate() {
@@ -244,7 +305,7 @@ impl GpuPoolManager {
             "GPU pool ordered ({:?}): {}",
             self.ordering,
             pool.iter()
-                .map(|g| format!("{}({})", g.info.device_name, g.server_index))
+                .map(|g| format!("{}: {}({})", g.pool_index, g.info.device_name, g.server_index))
                 .collect::<Vec<_>>()
                 .join(", ")
         );
@@ -0,0 +1,204 @@
+//! Speculative DtoH prefetch.
+//!
+//! Inference and simulation loops typically synchronize and then immediately
+//! read back the same buffers every iteration. The prefetcher records which
+//! reads follow each sync point (context, stream or event synchronize); once
+//! the same reads have followed the same sync [`STABLE_AFTER`] times in a row,
+//! the daemon issues them itself as soon as that sync completes, so the data
+//! is already local when the app asks for it.
+//!
+//! Prefetched data is only served while the app has done nothing but reads
+//! since the sync. Any other command could modify device memory, so it
+//! discards everything still outstanding. Large prefetched responses can be
+//! spilled to disk while they wait (see [`crate::spill`]).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+use tracing::debug;
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
+
+use crate::spill::{Held, Spill};
+
+/// Consecutive identical observations before a pattern is trusted.
+pub const STABLE_AFTER: u32 = 3;
+
+/// Upper bound on bytes prefetched after a single sync.
+const MAX_PREFETCH_BYTES: u64 = 256 * 1024 * 1024;
+
+/// Sync point that a pattern of reads follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Context,
+    Stream(NetworkHandle),
+    Event(NetworkHandle),
+}
+
+/// A device-to-host read: source buffer and length.
+pub type Read = (NetworkHandle, u64);
+
+struct Pattern {
+    reads: Vec<Read>,
+    seen: u32,
+}
+
+/// What the daemon should do with a command it is about to forward.
+pub enum Observation {
+    /// Forward as usual.
+    Forward,
+    /// A sync point; call [`Prefetcher::plan`] once it succeeds.
+    Trigger(Trigger),
+    /// A read that was prefetched; await the response instead of forwarding.
+    Prefetched(oneshot::Receiver<Held>),
+}
+
+#[derive(Default)]
+struct State {
+    /// Sync currently being followed by reads, and the reads seen so far.
+    recording: Option<(Trigger, Vec<Read>)>,
+    patterns: HashMap<Trigger, Pattern>,
+    outstanding: HashMap<Read, oneshot::Receiver<Held>>,
+    hits: u64,
+    wasted: u64,
+}
+
+impl State {
+    /// Close the current recording and fold it into the learned patterns.
+    fn finish_recording(&mut self) {
+        let Some((trigger, reads)) = self.recording.take() else {
+            return;
+        };
+        match self.patterns.get_mut(&trigger) {
+            Some(pattern) if pattern.reads == reads => pattern.seen += 1,
+            _ if reads.is_empty() => {
+                self.patterns.remove(&trigger);
+            }
+            _ => {
+                self.patterns.insert(trigger, Pattern { reads, seen: 1 });
+            }
+        }
+    }
+
+    fn discard_outstanding(&mut self) {
+        if !self.outstanding.is_empty() {
+            self.wasted += self.outstanding.len() as u64;
+            self.outstanding.clear();
+        }
+    }
+}
+
+/// Where the response to one speculative read is delivered.
+pub struct PrefetchSlot {
+    tx: oneshot::Sender<Held>,
+    spill: Option<Arc<Spill>>,
+}
+
+impl PrefetchSlot {
+    /// Hand over the response, spilling it to disk if it is large.
+    pub async fn fill(self, response: CudaResponse) {
+        let held = match self.spill {
+            Some(spill) if spill.exceeds(&response) => {
+                match tokio::task::spawn_blocking(move || spill.hold(response)).await {
+                    Ok(held) => held,
+                    Err(_) => return,
+                }
+            }
+            _ => Held::Memory(response),
+        };
+        let _ = self.tx.send(held);
+    }
+}
+
+/// Learns read patterns after sync points and holds speculative reads.
+pub struct Prefetcher {
+    state: Mutex<State>,
+    spill: Option<Arc<Spill>>,
+}
+
+impl Prefetcher {
+    pub fn new(spill: Option<Spill>) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            spill: spill.map(Arc::new),
+        }
+    }
+
+    /// Note a command the app is sending, before it is forwarded.
+    pub fn observe(&self, command: &CudaCommand) -> Observation {
+        let mut state = self.state.lock().unwrap();
+
+        let read = match command {
+            CudaCommand::MemcpyDtoH { src, byte_count }
+            | CudaCommand::MemcpyDtoHAsync { src, byte_count, .. } => Some((*src, *byte_count)),
+            _ => None,
+        };
+        if let Some(read) = read {
+            if let Some((_, reads)) = state.recording.as_mut() {
+                reads.push(read);
+            }
+            if let Some(rx) = state.outstanding.remove(&read) {
+                state.hits += 1;
+                return Observation::Prefetched(rx);
+            }
+            return Observation::Forward;
+        }
+
+        state.finish_recording();
+        state.discard_outstanding();
+
+        let trigger = match command {
+            CudaCommand::CtxSynchronize => Trigger::Context,
+            CudaCommand::StreamSynchronize { stream } => Trigger::Stream(*stream),
+            CudaCommand::EventSynchronize { event } => Trigger::Event(*event),
+            _ => return Observation::Forward,
+        };
+        state.recording = Some((trigger, Vec::new()));
+        Observation::Trigger(trigger)
+    }
+
+    /// After `trigger` completed successfully: the reads to issue now, each
+    /// with the slot its response should be delivered to. Empty until the
+    /// pattern after this trigger is stable.
+    pub fn plan(&self, trigger: Trigger) -> Vec<(Read, PrefetchSlot)> {
+        let mut state = self.state.lock().unwrap();
+        // Another command may have arrived while the sync was in flight.
+        if state.recording.as_ref().map(|(t, _)| *t) != Some(trigger) {
+            return Vec::new();
+        }
+        let reads = match state.patterns.get(&trigger) {
+            Some(pattern) if pattern.seen >= STABLE_AFTER => pattern.reads.clone(),
+            _ => return Vec::new(),
+        };
+        if reads.iter().map(|(_, len)| *len).sum::<u64>() > MAX_PREFETCH_BYTES {
+            return Vec::new();
+        }
+
+        let mut plan = Vec::with_capacity(reads.len());
+        for read in reads {
+            // A read repeated within the pattern is only prefetched once.
+            if state.outstanding.contains_key(&read) {
+                continue;
+            }
+            let (tx, rx) = oneshot::channel();
+            state.outstanding.insert(read, rx);
+            plan.push((
+                read,
+                PrefetchSlot {
+                    tx,
+                    spill: self.spill.clone(),
+                },
+            ));
+        }
+        debug!(
+            "prefetching {} read(s) after {:?} ({} hits, {} wasted so far)",
+            plan.len(),
+            trigger,
+            state.hits,
+            state.wasted
+        );
+        plan
+    }
+}
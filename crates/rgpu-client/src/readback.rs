@@ -0,0 +1,214 @@
+//! Differential readback cache.
+//!
+//! Apps that repeatedly read back the same device buffer (simulation
+//! previews, progressive renders) mostly transfer bytes the daemon already
+//! has. With `readback_diff` enabled, the daemon keeps the last copy of each
+//! large DtoH read and turns the next read of the same buffer into a
+//! `MemcpyDtoHDiff`, so the server only sends the blocks that changed. The
+//! app still receives the full buffer.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use tracing::{debug, warn};
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::readback::{self, DEFAULT_BLOCK_SIZE};
+
+/// Reads smaller than this are sent in full; hashing isn't worth it.
+const MIN_DIFF_BYTES: u64 = 1024 * 1024;
+
+struct CachedCopy {
+    data: Vec<u8>,
+    hashes: Vec<u64>,
+    last_used: Instant,
+}
+
+/// A readback rewritten into a diff request, waiting for its response.
+pub struct PendingReadback {
+    src: NetworkHandle,
+    byte_count: u64,
+    base: Option<CachedCopy>,
+}
+
+/// Last-read copies of device buffers, keyed by source handle.
+pub struct ReadbackCache {
+    budget: usize,
+    copies: Mutex<HashMap<NetworkHandle, CachedCopy>>,
+}
+
+impl ReadbackCache {
+    pub fn new(budget_mb: u64) -> Self {
+        Self {
+            budget: (budget_mb as usize).saturating_mul(1024 * 1024),
+            copies: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Rewrite a large DtoH read into a diff request. The cached copy is
+    /// checked out until [`ReadbackCache::finish`], so concurrent reads of the
+    /// same buffer fall back to a full transfer instead of racing on it.
+    pub fn begin(&self, command: CudaCommand) -> (CudaCommand, Option<PendingReadback>) {
+        let (src, byte_count) = match &command {
+            CudaCommand::MemcpyDtoH { src, byte_count }
+            | CudaCommand::MemcpyDtoHAsync { src, byte_count, .. } => (*src, *byte_count),
+            CudaCommand::MemFree { dptr } => {
+                self.copies.lock().unwrap().remove(dptr);
+                return (command, None);
+            }
+            _ => return (command, None),
+        };
+        if byte_count < MIN_DIFF_BYTES || byte_count as usize > self.budget {
+            return (command, None);
+        }
+
+        let base = self
+            .copies
+            .lock()
+            .unwrap()
+            .remove(&src)
+            .filter(|copy| copy.data.len() as u64 == byte_count);
+        let known_hashes = base.as_ref().map(|copy| copy.hashes.clone()).unwrap_or_default();
+        let diff = CudaCommand::MemcpyDtoHDiff {
+            src,
+            byte_count,
+            block_size: DEFAULT_BLOCK_SIZE,
+            known_hashes,
+        };
+        (diff, Some(PendingReadback { src, byte_count, base }))
+    }
+
+    /// Rebuild the full buffer from a `MemoryDiff` response and keep it for
+    /// the next read. Other responses (errors) are returned unchanged.
+    pub fn finish(&self, pending: PendingReadback, response: CudaResponse) -> CudaResponse {
+        let CudaResponse::MemoryDiff { changed_blocks, data } = response else {
+            return response;
+        };
+        let (mut buf, mut hashes) = match pending.base {
+            Some(copy) => (copy.data, copy.hashes),
+            None => (Vec::new(), Vec::new()),
+        };
+
+        let byte_count = pending.byte_count as usize;
+        if let Err(e) = readback::apply_diff(
+            &mut buf,
+            byte_count,
+            DEFAULT_BLOCK_SIZE,
+            &changed_blocks,
+            &data,
+        ) {
+            warn!("differential readback of {:?} failed: {}", pending.src, e);
+            return CudaResponse::Error {
+                code: 999,
+                message: format!("differential readback failed: {}", e),
+            };
+        }
+
+        // Only the changed blocks need rehashing.
+        let block_size = DEFAULT_BLOCK_SIZE as usize;
+        hashes.resize(byte_count.div_ceil(block_size), 0);
+        for &index in &changed_blocks {
+            let start = index as usize * block_size;
+            let end = (start + block_size).min(byte_count);
+            hashes[index as usize] = readback::block_hash(&buf[start..end]);
+        }
+        debug!(
+            "differential readback of {:?}: {} of {} block(s) transferred",
+            pending.src,
+            changed_blocks.len(),
+            hashes.len()
+        );
+
+        let response = CudaResponse::MemoryData(buf.clone());
+        self.store(
+            pending.src,
+            CachedCopy {
+                data: buf,
+                hashes,
+                last_used: Instant::now(),
+            },
+        );
+        response
+    }
+
+    /// Insert a copy, evicting the least recently read ones to stay in budget.
+    fn store(&self, src: NetworkHandle, copy: CachedCopy) {
+        let mut copies = self.copies.lock().unwrap();
+        let mut used: usize = copies.values().map(|c| c.data.len()).sum::<usize>() + copy.data.len();
+        while used > self.budget {
+            let Some(oldest) = copies
+                .iter()
+                .min_by_key(|(_, c)| c.last_used)
+                .map(|(handle, _)| *handle)
+            else {
+                break;
+            };
+            if let Some(evicted) = copies.remove(&oldest) {
+                used -= evicted.data.len();
+            }
+        }
+        copies.insert(src, copy);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Read `device` through `cache`, answering the diff request as a server
+    /// would.
+    fn read(cache: &ReadbackCache, src: NetworkHandle, device: &[u8]) -> (usize, CudaResponse) {
+        let command = CudaCommand::MemcpyDtoH { src, byte_count: device.len() as u64 };
+        let (command, pending) = cache.begin(command);
+        let CudaCommand::MemcpyDtoHDiff { block_size, known_hashes, .. } = command else {
+            panic!("expected a diff request, got {:?}", command);
+        };
+        let (changed_blocks, data) = readback::diff_blocks(device, block_size, &known_hashes);
+        let sent = changed_blocks.len();
+        (sent, cache.finish(pending.unwrap(), CudaResponse::MemoryDiff { changed_blocks, data }))
+    }
+
+    #[test]
+    fn test_reads_round_trip() {
+        let cache = ReadbackCache::new(16);
+        let src = NetworkHandle::null();
+        // Big enough to diff, ending in half a block
+        let mut device = vec![7u8; MIN_DIFF_BYTES as usize + DEFAULT_BLOCK_SIZE as usize / 2];
+
+        let (sent, response) = read(&cache, src, &device);
+        assert!(matches!(response, CudaResponse::MemoryData(ref data) if *data == device));
+        assert_eq!(sent, device.len().div_ceil(DEFAULT_BLOCK_SIZE as usize));
+
+        let (sent, response) = read(&cache, src, &device);
+        assert!(matches!(response, CudaResponse::MemoryData(ref data) if *data == device));
+        assert_eq!(sent, 0);
+
+        // Only the changed last, partial block is sent, and rehashed for the
+        // read after
+        *device.last_mut().unwrap() = 8;
+        let (sent, response) = read(&cache, src, &device);
+        assert!(matches!(response, CudaResponse::MemoryData(ref data) if *data == device));
+        assert_eq!(sent, 1);
+        assert_eq!(read(&cache, src, &device).0, 0);
+    }
+
+    #[test]
+    fn test_diff_past_the_buffer_fails_the_read() {
+        let cache = ReadbackCache::new(16);
+        let src = NetworkHandle::null();
+        let command = CudaCommand::MemcpyDtoH { src, byte_count: MIN_DIFF_BYTES };
+        let (_, pending) = cache.begin(command);
+        let blocks = (MIN_DIFF_BYTES / DEFAULT_BLOCK_SIZE as u64) as u32;
+        let response = CudaResponse::MemoryDiff {
+            changed_blocks: vec![blocks],
+            data: vec![0; DEFAULT_BLOCK_SIZE as usize],
+        };
+        assert!(matches!(cache.finish(pending.unwrap(), response), CudaResponse::Error { .. }));
+
+        // Nothing broken was kept: the next read asks for everything
+        let (command, _) = cache.begin(CudaCommand::MemcpyDtoH { src, byte_count: MIN_DIFF_BYTES });
+        assert!(matches!(command, CudaCommand::MemcpyDtoHDiff { ref known_hashes, .. } if known_hashes.is_empty()));
+    }
+}
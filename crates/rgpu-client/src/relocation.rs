@@ -0,0 +1,109 @@
+//! Device handles whose contexts the daemon puts on another GPU.
+//!
+//! Failover and placement both create an application's context on another
+//! GPU than the one its device handle was got for. The daemon gets a device
+//! of that GPU and swaps it in for the application's handle, there and in
+//! every later command naming it.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use rgpu_protocol::cuda_commands::CudaCommand;
+use rgpu_protocol::handle::NetworkHandle;
+
+use crate::placement::Placement;
+
+/// Device handles the daemon handed out, and those it swapped.
+pub struct Relocations {
+    /// Move contexts off servers that are down (`failover`)
+    pub failover: bool,
+    /// Spread new contexts over the remote GPUs (`[client.placement]`)
+    pub placement: Option<Placement>,
+    /// Pool ordinal each device handle was got for
+    devices: Mutex<HashMap<NetworkHandle, u32>>,
+    /// Handle of the GPU used instead, for each swapped device
+    swapped: Mutex<HashMap<NetworkHandle, NetworkHandle>>,
+    /// Devices whose GPU has been decided
+    settled: Mutex<HashSet<NetworkHandle>>,
+}
+
+impl Relocations {
+    pub fn new(failover: bool, placement: Option<Placement>) -> Self {
+        Self {
+            failover,
+            placement,
+            devices: Mutex::new(HashMap::new()),
+            swapped: Mutex::new(HashMap::new()),
+            settled: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Note the pool ordinal a device handle was got for.
+    pub fn note_device(&self, device: NetworkHandle, ordinal: u32) {
+        self.devices.lock().unwrap().insert(device, ordinal);
+    }
+
+    /// The pool ordinal `device` was got for.
+    pub fn ordinal(&self, device: &NetworkHandle) -> Option<u32> {
+        self.devices.lock().unwrap().get(device).copied()
+    }
+
+    /// Use `replacement` wherever the application names `device`.
+    pub fn swap(&self, device: NetworkHandle, replacement: NetworkHandle) {
+        self.swapped.lock().unwrap().insert(device, replacement);
+        self.settle(replacement);
+    }
+
+    /// Decide the GPU of `device` for good. Returns whether it wasn't yet.
+    pub fn settle(&self, device: NetworkHandle) -> bool {
+        self.settled.lock().unwrap().insert(device)
+    }
+
+    /// Replace swapped device handles in a command.
+    pub fn rewrite(&self, command: &mut CudaCommand) {
+        let swapped = self.swapped.lock().unwrap();
+        if swapped.is_empty() {
+            return;
+        }
+        command.handles_mut(|handle| {
+            if let Some(replacement) = swapped.get(handle) {
+                *handle = *replacement;
+            }
+        });
+    }
+}
+
+/// The device a command creates a context on, if it does.
+pub fn context_device(command: &CudaCommand) -> Option<NetworkHandle> {
+    match command {
+        CudaCommand::CtxCreate { device, .. } | CudaCommand::DevicePrimaryCtxRetain { device } => Some(*device),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::ResourceType;
+
+    #[test]
+    fn swapped_devices_are_rewritten() {
+        let relocations = Relocations::new(true, None);
+        let device = |server_id, resource_id| NetworkHandle {
+            server_id,
+            session_id: 1,
+            resource_id,
+            resource_type: ResourceType::CuDevice,
+        };
+        let (failed, standby) = (device(1, 7), device(2, 4));
+        relocations.note_device(failed, 3);
+        assert_eq!(relocations.ordinal(&failed), Some(3));
+
+        relocations.swap(failed, standby);
+        let mut command = CudaCommand::CtxCreate { flags: 0, device: failed };
+        relocations.rewrite(&mut command);
+        assert_eq!(context_device(&command), Some(standby));
+        assert!(!relocations.settle(standby));
+        assert!(relocations.settle(failed));
+    }
+}
@@ -0,0 +1,130 @@
+//! Disk spill for prefetched DtoH data.
+//!
+//! Speculative reads (see [`crate::prefetch`]) complete before the app asks
+//! for the data, so the daemon has to hold the responses in the meantime. On
+//! a memory-constrained thin client a few large buffers are enough to run it
+//! out of RAM. Responses larger than `spill_threshold_mb` are written to a
+//! temp file instead and only read back when the app's read arrives.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tracing::{debug, warn};
+
+use rgpu_core::config::ClientConfig;
+use rgpu_protocol::codec::TransferCodec;
+use rgpu_protocol::cuda_commands::CudaResponse;
+
+/// Where and above which size held responses go to disk.
+pub struct Spill {
+    threshold: usize,
+    dir: PathBuf,
+    next_id: AtomicU64,
+}
+
+impl Spill {
+    /// `None` if spilling is disabled (`spill_threshold_mb = 0`).
+    pub fn from_config(config: &ClientConfig) -> Option<Self> {
+        if config.spill_threshold_mb == 0 {
+            return None;
+        }
+        let dir = match &config.spill_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => std::env::temp_dir().join("rgpu-spill"),
+        };
+        Some(Self {
+            threshold: (config.spill_threshold_mb as usize).saturating_mul(1024 * 1024),
+            dir,
+            next_id: AtomicU64::new(0),
+        })
+    }
+
+    /// Whether `response` is large enough to be spilled.
+    pub fn exceeds(&self, response: &CudaResponse) -> bool {
+        match response {
+            CudaResponse::MemoryData(data) | CudaResponse::EncodedMemoryData { data, .. } => {
+                data.len() > self.threshold
+            }
+            _ => false,
+        }
+    }
+
+    /// Keep a response, on disk if it exceeds the threshold. Blocks on file
+    /// I/O; if the write fails the response is kept in memory.
+    pub fn hold(&self, response: CudaResponse) -> Held {
+        if !self.exceeds(&response) {
+            return Held::Memory(response);
+        }
+        let (data, codec) = match response {
+            CudaResponse::MemoryData(data) => (data, None),
+            CudaResponse::EncodedMemoryData { codec, data } => (data, Some(codec)),
+            other => return Held::Memory(other),
+        };
+
+        let path = self.dir.join(format!(
+            "rgpu-spill-{}-{}.bin",
+            std::process::id(),
+            self.next_id.fetch_add(1, Ordering::Relaxed)
+        ));
+        let written = std::fs::create_dir_all(&self.dir).and_then(|_| std::fs::write(&path, &data));
+        match written {
+            Ok(()) => {
+                debug!("spilled {} byte prefetch to {}", data.len(), path.display());
+                Held::Disk {
+                    file: SpillFile(path),
+                    codec,
+                }
+            }
+            Err(e) => {
+                warn!("failed to spill prefetch to {}: {}", path.display(), e);
+                let _ = std::fs::remove_file(&path);
+                Held::Memory(match codec {
+                    Some(codec) => CudaResponse::EncodedMemoryData { codec, data },
+                    None => CudaResponse::MemoryData(data),
+                })
+            }
+        }
+    }
+}
+
+/// A spill file, removed when dropped.
+pub struct SpillFile(PathBuf);
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// A response waiting for the app to ask for it.
+pub enum Held {
+    Memory(CudaResponse),
+    Disk {
+        file: SpillFile,
+        /// Transfer codec of the spilled data, if it was encoded.
+        codec: Option<TransferCodec>,
+    },
+}
+
+impl Held {
+    /// The response, read back from disk if it was spilled. `None` if the
+    /// spill file could not be read.
+    pub async fn load(self) -> Option<CudaResponse> {
+        let (file, codec) = match self {
+            Held::Memory(response) => return Some(response),
+            Held::Disk { file, codec } => (file, codec),
+        };
+        let read = tokio::task::spawn_blocking(move || std::fs::read(&file.0)).await;
+        match read {
+            Ok(Ok(data)) => Some(match codec {
+                Some(codec) => CudaResponse::EncodedMemoryData { codec, data },
+                None => CudaResponse::MemoryData(data),
+            }),
+            Ok(Err(e)) => {
+                warn!("failed to read back spilled prefetch: {}", e);
+                None
+            }
+            Err(_) => None,
+        }
+    }
+}
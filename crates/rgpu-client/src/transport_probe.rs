@@ -0,0 +1,301 @@
+//! Transport selection for endpoints configured with `transport = "auto"`.
+//!
+//! Whether TCP or QUIC works better depends on the network between client
+//! and server: QUIC copes with loss and changing paths, TCP gets through
+//! middleboxes that throttle or drop UDP. On the first connect from a
+//! network, both are probed — handshake time, round-trip time and loss over
+//! a burst of pings, and throughput over an echoed payload — and the cheaper
+//! one is remembered in the daemon's state directory, keyed by the local
+//! address used to reach the server. Later connects from the same network
+//! use the remembered choice; if it stops connecting, it is forgotten and
+//! the next connect probes again.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info, warn};
+
+use rgpu_core::config::{ServerEndpoint, TransportMode};
+use rgpu_protocol::messages::Message;
+
+use crate::daemon::{reconnect_quic, reconnect_tcp};
+
+/// Pings per probe.
+const PINGS: usize = 16;
+/// A ping not answered within this is counted as lost.
+const PING_TIMEOUT: Duration = Duration::from_millis(500);
+/// Limit on the connect and authenticate step of a probe.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// Size of the echoed payload that measures throughput.
+const ECHO_BYTES: usize = 1 << 20;
+const ECHO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// What a probe measured over one transport.
+#[derive(Debug, Clone, PartialEq)]
+struct Probe {
+    handshake: Duration,
+    /// Median over the answered pings
+    rtt: Duration,
+    /// Fraction of pings lost, below 1
+    loss: f64,
+    /// Bytes per second both ways, if the server echoes
+    throughput: Option<f64>,
+}
+
+impl Probe {
+    /// Expected time for a connection's typical work: connecting, a burst of
+    /// round trips and a megabyte of transfers, repeated in proportion to
+    /// what gets lost.
+    fn cost(&self) -> f64 {
+        let transfer = self.throughput.map_or(0.0, |bytes_per_sec| ECHO_BYTES as f64 / bytes_per_sec);
+        let work = self.handshake.as_secs_f64() + PINGS as f64 * self.rtt.as_secs_f64() + transfer;
+        work / (1.0 - self.loss)
+    }
+}
+
+/// The transport to use for `endpoint`: its configured one, or for `auto`
+/// the remembered or freshly probed choice for the current network. Falls
+/// back to TCP if neither transport answers a probe.
+pub(crate) async fn resolve(endpoint: &ServerEndpoint) -> TransportMode {
+    if endpoint.transport != TransportMode::Auto {
+        return endpoint.transport.clone();
+    }
+    let key = network_key(&endpoint.address).await;
+    if let Some(key) = &key {
+        if let Some(mode) = load_choices().remove(key) {
+            debug!("using remembered transport {:?} for {}", mode, key);
+            return mode;
+        }
+    }
+
+    let tcp = probe(endpoint, TransportMode::Tcp).await;
+    let quic = probe(endpoint, TransportMode::Quic).await;
+    info!("transport probe of {}: tcp {:?}, quic {:?}", endpoint.address, tcp, quic);
+    match choose(tcp.as_ref(), quic.as_ref()) {
+        Some(mode) => {
+            info!("selected {:?} for {}", mode, endpoint.address);
+            if let Some(key) = key {
+                update_choices(|choices| {
+                    choices.insert(key, mode.clone());
+                });
+            }
+            mode
+        }
+        None => {
+            warn!("neither TCP nor QUIC answered a probe of {}, trying TCP", endpoint.address);
+            TransportMode::Tcp
+        }
+    }
+}
+
+/// Drop the remembered choice for `endpoint` on the current network, after
+/// it failed to connect.
+pub(crate) async fn forget(endpoint: &ServerEndpoint) {
+    if endpoint.transport != TransportMode::Auto {
+        return;
+    }
+    if let Some(key) = network_key(&endpoint.address).await {
+        update_choices(|choices| {
+            if choices.remove(&key).is_some() {
+                info!("forgetting transport choice for {}", key);
+            }
+        });
+    }
+}
+
+/// The cheaper of two probes; a transport that didn't answer loses.
+fn choose(tcp: Option<&Probe>, quic: Option<&Probe>) -> Option<TransportMode> {
+    match (tcp, quic) {
+        (Some(tcp), Some(quic)) if quic.cost() < tcp.cost() => Some(TransportMode::Quic),
+        (Some(_), _) => Some(TransportMode::Tcp),
+        (None, Some(_)) => Some(TransportMode::Quic),
+        (None, None) => None,
+    }
+}
+
+/// Connect over `mode` and measure it. `None` if it doesn't connect or
+/// every ping is lost.
+async fn probe(endpoint: &ServerEndpoint, mode: TransportMode) -> Option<Probe> {
+    let start = Instant::now();
+    let connect = async {
+        match mode {
+            TransportMode::Quic => reconnect_quic(endpoint).await,
+            _ => reconnect_tcp(endpoint).await,
+        }
+    };
+    let mut conn = match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+        Ok(Ok((conn, _))) => conn,
+        Ok(Err(e)) => {
+            debug!("{:?} probe of {} failed to connect: {}", mode, endpoint.address, e);
+            return None;
+        }
+        Err(_) => {
+            debug!("{:?} probe of {} timed out connecting", mode, endpoint.address);
+            return None;
+        }
+    };
+    let handshake = start.elapsed();
+
+    let mut rtts = Vec::with_capacity(PINGS);
+    for sent in 0..PINGS {
+        let start = Instant::now();
+        match tokio::time::timeout(PING_TIMEOUT, conn.send_and_receive(&Message::Ping)).await {
+            Ok(Ok(Message::Pong)) => rtts.push(start.elapsed()),
+            Ok(Ok(_)) => {}
+            // A late response would be read as the next one's, so the
+            // connection is done; the rest count as lost.
+            Ok(Err(_)) | Err(_) => {
+                debug!("{:?} probe of {} stopped after {} pings", mode, endpoint.address, sent + 1);
+                break;
+            }
+        }
+    }
+    if rtts.is_empty() {
+        return None;
+    }
+    let loss = (PINGS - rtts.len()) as f64 / PINGS as f64;
+    rtts.sort();
+    let rtt = rtts[rtts.len() / 2];
+
+    // Only on a connection that's still in step, and only if the server
+    // understands echoes; compression would flatter a repetitive payload.
+    let throughput = if rtts.len() == PINGS {
+        let payload = noise(ECHO_BYTES);
+        let start = Instant::now();
+        match tokio::time::timeout(ECHO_TIMEOUT, conn.send_and_receive(&Message::Echo(payload))).await {
+            Ok(Ok(Message::Echo(echoed))) if echoed.len() == ECHO_BYTES => {
+                Some(2.0 * ECHO_BYTES as f64 / start.elapsed().as_secs_f64())
+            }
+            _ => None,
+        }
+    } else {
+        None
+    };
+
+    Some(Probe {
+        handshake,
+        rtt,
+        loss,
+        throughput,
+    })
+}
+
+/// Incompressible bytes (xorshift).
+fn noise(len: usize) -> Vec<u8> {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+/// Identifies the network a server is reached over: the local address the
+/// route to it goes out of, and the server's address. `None` if the server
+/// address doesn't resolve.
+async fn network_key(server: &str) -> Option<String> {
+    let remote = tokio::net::lookup_host(server).await.ok()?.next()?;
+    let bind = if remote.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+    // Connecting a UDP socket sends nothing; it only picks the route.
+    let socket = std::net::UdpSocket::bind(bind).ok()?;
+    socket.connect(remote).ok()?;
+    let local = socket.local_addr().ok()?.ip();
+    Some(format!("{}->{}", local, server))
+}
+
+fn state_path() -> PathBuf {
+    rgpu_common::platform::state_dir().join("transports")
+}
+
+/// Serializes read-modify-write of the state file between endpoints.
+static STATE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+fn load_choices() -> BTreeMap<String, TransportMode> {
+    let _guard = STATE_LOCK.lock().unwrap();
+    std::fs::read_to_string(state_path())
+        .map(|text| parse_choices(&text))
+        .unwrap_or_default()
+}
+
+fn update_choices(f: impl FnOnce(&mut BTreeMap<String, TransportMode>)) {
+    let _guard = STATE_LOCK.lock().unwrap();
+    let path = state_path();
+    let mut choices = std::fs::read_to_string(&path)
+        .map(|text| parse_choices(&text))
+        .unwrap_or_default();
+    f(&mut choices);
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&path, format_choices(&choices)));
+    if let Err(e) = written {
+        warn!("failed to save transport choices to {}: {}", path.display(), e);
+    }
+}
+
+/// One `<network> <tcp|quic>` per line; anything else is skipped.
+fn parse_choices(text: &str) -> BTreeMap<String, TransportMode> {
+    text.lines()
+        .filter_map(|line| {
+            let (key, mode) = line.trim().split_once(' ')?;
+            let mode = match mode.trim() {
+                "tcp" => TransportMode::Tcp,
+                "quic" => TransportMode::Quic,
+                _ => return None,
+            };
+            Some((key.to_string(), mode))
+        })
+        .collect()
+}
+
+fn format_choices(choices: &BTreeMap<String, TransportMode>) -> String {
+    let mut text = String::from("# Transport chosen per network for servers with transport = \"auto\"\n");
+    for (key, mode) in choices {
+        let mode = match mode {
+            TransportMode::Quic => "quic",
+            _ => "tcp",
+        };
+        text.push_str(&format!("{} {}\n", key, mode));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(rtt_ms: u64, loss: f64, throughput: Option<f64>) -> Probe {
+        Probe {
+            handshake: Duration::from_millis(3 * rtt_ms),
+            rtt: Duration::from_millis(rtt_ms),
+            loss,
+            throughput,
+        }
+    }
+
+    #[test]
+    fn cheaper_transport_wins() {
+        let fast = probe(2, 0.0, Some(100e6));
+        let lossy = probe(2, 0.25, Some(100e6));
+        let slow = probe(2, 0.0, Some(1e6));
+        assert_eq!(choose(Some(&fast), Some(&lossy)), Some(TransportMode::Tcp));
+        assert_eq!(choose(Some(&slow), Some(&fast)), Some(TransportMode::Quic));
+        assert_eq!(choose(None, Some(&slow)), Some(TransportMode::Quic));
+        assert_eq!(choose(Some(&slow), None), Some(TransportMode::Tcp));
+        assert_eq!(choose(None, None), None);
+    }
+
+    #[test]
+    fn choices_round_trip() {
+        let mut choices = BTreeMap::new();
+        choices.insert("10.0.0.5->gpu-box:9876".to_string(), TransportMode::Quic);
+        choices.insert("192.168.1.20->gpu-box:9876".to_string(), TransportMode::Tcp);
+        let text = format_choices(&choices);
+        assert_eq!(parse_choices(&text), choices);
+        assert!(parse_choices("garbage\nhost auto\n").is_empty());
+    }
+}
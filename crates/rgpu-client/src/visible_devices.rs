@@ -0,0 +1,194 @@
+//! `RGPU_VISIBLE_DEVICES` for one IPC connection.
+//!
+//! An application started with `RGPU_VISIBLE_DEVICES=2,0` sends
+//! `SetVisibleDevices([2, 0])` when it connects, and from then on sees two
+//! devices: its ordinal 0 is the pool's device 2 and its ordinal 1 the pool's
+//! device 0. Like `CUDA_VISIBLE_DEVICES`, the list ends at the first entry
+//! that doesn't exist. The filter rewrites `cuDeviceGet` ordinals on the way
+//! in and `cuDeviceGetCount` results and `vkEnumeratePhysicalDevices` lists on
+//! the way out; everything else names devices by handle and passes through.
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::messages::Message;
+use rgpu_protocol::vulkan_commands::VulkanResponse;
+
+/// `CUDA_ERROR_INVALID_DEVICE`
+const INVALID_DEVICE: i32 = 101;
+
+/// The devices one application is allowed to see, in its order.
+#[derive(Debug, Clone)]
+pub struct DeviceFilter {
+    devices: Vec<u32>,
+    /// The pool's CUDA device count, once the application has asked for it
+    cuda_count: Option<u32>,
+}
+
+impl DeviceFilter {
+    pub fn new(devices: Vec<u32>) -> Self {
+        Self {
+            devices,
+            cuda_count: None,
+        }
+    }
+
+    /// The visible devices that exist among `count`.
+    fn visible(&self, count: u32) -> &[u32] {
+        let end = self.devices.iter().position(|&d| d >= count).unwrap_or(self.devices.len());
+        &self.devices[..end]
+    }
+
+    /// The pool ordinal behind the application's `ordinal`.
+    fn pool_ordinal(&self, ordinal: i32) -> Option<i32> {
+        let visible = match self.cuda_count {
+            Some(count) => self.visible(count),
+            None => &self.devices,
+        };
+        let ordinal = usize::try_from(ordinal).ok()?;
+        visible.get(ordinal).map(|&d| d as i32)
+    }
+
+    /// Rewrite a request from the application in place. Returns the response
+    /// to send back instead of forwarding it, if it asks for a device the
+    /// application can't see.
+    pub fn request(&self, msg: &mut Message) -> Option<Message> {
+        let (request_id, command) = match msg {
+            Message::CudaCommand { request_id, command, .. }
+            | Message::CudaCommandStreamed { request_id, command, .. }
+            | Message::CudaPipelined { request_id, command, .. } => (*request_id, command),
+            _ => return None,
+        };
+        let CudaCommand::DeviceGet { ordinal } = command else {
+            return None;
+        };
+        match self.pool_ordinal(*ordinal) {
+            Some(pool_ordinal) => {
+                *ordinal = pool_ordinal;
+                None
+            }
+            None => Some(Message::CudaResponse {
+                request_id,
+                response: CudaResponse::Error {
+                    code: INVALID_DEVICE,
+                    message: format!("device {} is not in RGPU_VISIBLE_DEVICES", ordinal),
+                },
+            }),
+        }
+    }
+
+    /// Rewrite a response to the application.
+    pub fn response(&mut self, msg: Message) -> Message {
+        match msg {
+            Message::CudaResponse {
+                request_id,
+                response: CudaResponse::DeviceCount(count),
+            } => {
+                let count = count.max(0) as u32;
+                self.cuda_count = Some(count);
+                Message::CudaResponse {
+                    request_id,
+                    response: CudaResponse::DeviceCount(self.visible(count).len() as i32),
+                }
+            }
+            Message::VulkanResponse {
+                request_id,
+                response: VulkanResponse::PhysicalDevices { handles },
+            } => {
+                let handles = self
+                    .visible(handles.len() as u32)
+                    .iter()
+                    .map(|&d| handles[d as usize])
+                    .collect();
+                Message::VulkanResponse {
+                    request_id,
+                    response: VulkanResponse::PhysicalDevices { handles },
+                }
+            }
+            msg => msg,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::{NetworkHandle, ResourceType};
+    use rgpu_protocol::messages::RequestId;
+
+    fn device_get(ordinal: i32) -> Message {
+        Message::CudaCommand {
+            request_id: RequestId(1),
+            command: CudaCommand::DeviceGet { ordinal },
+            deadline_ms: None,
+        }
+    }
+
+    fn count_response(count: i32) -> Message {
+        Message::CudaResponse {
+            request_id: RequestId(1),
+            response: CudaResponse::DeviceCount(count),
+        }
+    }
+
+    #[test]
+    fn test_ordinals_are_remapped() {
+        let mut filter = DeviceFilter::new(vec![2, 0]);
+        match filter.response(count_response(3)) {
+            Message::CudaResponse {
+                response: CudaResponse::DeviceCount(2),
+                ..
+            } => {}
+            other => panic!("expected 2 devices, got {:?}", other),
+        }
+        let mut request = device_get(0);
+        assert!(filter.request(&mut request).is_none());
+        match request {
+            Message::CudaCommand {
+                command: CudaCommand::DeviceGet { ordinal: 2 },
+                ..
+            } => {}
+            other => panic!("expected device 2, got {:?}", other),
+        }
+        match filter.request(&mut device_get(2)) {
+            Some(Message::CudaResponse {
+                response: CudaResponse::Error { code: INVALID_DEVICE, .. },
+                ..
+            }) => {}
+            other => panic!("expected an invalid device error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_list_ends_at_missing_device() {
+        let mut filter = DeviceFilter::new(vec![1, 5, 0]);
+        match filter.response(count_response(2)) {
+            Message::CudaResponse {
+                response: CudaResponse::DeviceCount(1),
+                ..
+            } => {}
+            other => panic!("expected 1 device, got {:?}", other),
+        }
+        assert!(filter.request(&mut device_get(1)).is_some());
+
+        let handles: Vec<NetworkHandle> = (0..2)
+            .map(|resource_id| NetworkHandle {
+                server_id: 0,
+                session_id: 0,
+                resource_id,
+                resource_type: ResourceType::VkPhysicalDevice,
+            })
+            .collect();
+        let response = filter.response(Message::VulkanResponse {
+            request_id: RequestId(2),
+            response: VulkanResponse::PhysicalDevices {
+                handles: handles.clone(),
+            },
+        });
+        match response {
+            Message::VulkanResponse {
+                response: VulkanResponse::PhysicalDevices { handles: visible },
+                ..
+            } => assert_eq!(visible, vec![handles[1]]),
+            other => panic!("expected physical devices, got {:?}", other),
+        }
+    }
+}
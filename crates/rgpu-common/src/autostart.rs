@@ -0,0 +1,90 @@
+//! On-demand start of the client daemon.
+//!
+//! When the Vulkan ICD or CUDA interposer cannot reach the daemon, it asks
+//! for one to be started before giving up. The per-user service installed by
+//! `rgpu client --user-service install` is preferred; otherwise `rgpu client`
+//! is spawned directly as a detached background process.
+//!
+//! Set `RGPU_NO_AUTOSTART=1` to disable, or `RGPU_BIN` to point at a
+//! specific `rgpu` executable.
+
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Name of the per-user systemd unit / scheduled task.
+pub const USER_SERVICE_NAME: &str = "rgpu-client";
+
+static ATTEMPTED: AtomicBool = AtomicBool::new(false);
+
+/// Try to start the daemon. Only the first call in a process does anything;
+/// returns `true` if that call launched (or asked the service manager to
+/// launch) a daemon, meaning the caller should allow it time to come up.
+pub fn autostart_daemon() -> bool {
+    if ATTEMPTED.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    if std::env::var_os("RGPU_NO_AUTOSTART").is_some() {
+        return false;
+    }
+    start_user_service() || spawn_daemon()
+}
+
+#[cfg(target_os = "linux")]
+fn start_user_service() -> bool {
+    Command::new("systemctl")
+        .args(["--user", "start", &format!("{}.service", USER_SERVICE_NAME)])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn start_user_service() -> bool {
+    Command::new("schtasks")
+        .args(["/Run", "/TN", USER_SERVICE_NAME])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn start_user_service() -> bool {
+    false
+}
+
+fn spawn_daemon() -> bool {
+    let bin = std::env::var("RGPU_BIN").unwrap_or_else(|_| "rgpu".to_string());
+    let mut command = Command::new(bin);
+    command
+        .arg("client")
+        // The daemon must use the real drivers, not the interposer we're running in.
+        .env_remove("LD_PRELOAD")
+        .env_remove("DYLD_INSERT_LIBRARIES")
+        .env_remove("VK_ICD_FILENAMES")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // Detach so the daemon outlives the application that started it and
+    // doesn't receive its terminal signals.
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        command.creation_flags(CREATE_NEW_PROCESS_GROUP | CREATE_NO_WINDOW);
+    }
+
+    command.spawn().is_ok()
+}
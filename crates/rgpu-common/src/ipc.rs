@@ -0,0 +1,334 @@
+//! Synchronous IPC client for communicating with the RGPU client daemon,
+//! shared by the interposers and the Vulkan ICD. It must be synchronous
+//! because the API calls it serves are.
+//!
+//! Each connection introduces itself before carrying commands: it names the
+//! session from the environment, swaps builds with the daemon, and, unless
+//! the client lists every device, passes on `RGPU_VISIBLE_DEVICES`. If no
+//! daemon is listening, one is started (see [`crate::autostart`]) and given
+//! time to come up.
+//!
+//! Daemons that offer it can give a connection a shared-memory region, and
+//! payloads of 64 KB or more then go through its rings instead of the socket.
+
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once};
+
+use rgpu_protocol::messages::{Message, RequestId};
+use rgpu_protocol::version::{self, BuildInfo, DaemonBuilds};
+use rgpu_protocol::wire::{self, FrameFlags};
+use tracing::{debug, info, warn};
+
+use crate::shm::{Direction, SharedRegion};
+
+/// Synchronous IPC client that connects to the RGPU client daemon.
+pub struct IpcClient {
+    path: String,
+    /// Crate name the client reports to the daemon
+    component: &'static str,
+    /// Don't pass on `RGPU_VISIBLE_DEVICES`
+    all_devices: bool,
+    next_request_id: AtomicU64,
+    /// Connection is lazily established and reused.
+    connection: Mutex<Option<IpcConnection>>,
+}
+
+impl IpcClient {
+    pub fn new(path: &str, component: &'static str) -> Self {
+        Self {
+            path: path.to_string(),
+            component,
+            all_devices: false,
+            next_request_id: AtomicU64::new(1),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// See every device whatever `RGPU_VISIBLE_DEVICES` says, for a client
+    /// that lists the whole pool.
+    pub fn with_all_devices(mut self) -> Self {
+        self.all_devices = true;
+        self
+    }
+
+    pub fn next_request_id(&self) -> RequestId {
+        RequestId(self.next_request_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Send `msg` and wait for the daemon's reply. If the write fails, the
+    /// daemon never got `msg`, so it is sent once more on a new connection;
+    /// a connection that broke while reading is replaced by the next call.
+    pub fn send_and_receive(&self, msg: Message) -> Result<Message, String> {
+        let frame = wire::encode_message(&msg, 0).map_err(|e| e.to_string())?;
+
+        let mut conn_guard = self.connection.lock().map_err(|e| e.to_string())?;
+        let mut retried = false;
+        loop {
+            if conn_guard.is_none() {
+                let mut conn = IpcConnection::connect(&self.path, self.component)?;
+                if !self.all_devices {
+                    conn.restrict_devices()?;
+                }
+                *conn_guard = Some(conn);
+            }
+            let conn = conn_guard.as_mut().expect("connection was just set to Some");
+
+            if let Err(e) = conn.write_all(&frame) {
+                *conn_guard = None;
+                if retried {
+                    return Err(e);
+                }
+                retried = true;
+                continue;
+            }
+
+            let response = conn.read_message();
+            if conn.is_broken() {
+                *conn_guard = None;
+            }
+            return response;
+        }
+    }
+}
+
+/// One connection to the daemon.
+pub struct IpcConnection {
+    #[cfg(unix)]
+    stream: std::os::unix::net::UnixStream,
+    #[cfg(windows)]
+    pipe: std::fs::File,
+    /// A read or write failed partway, so the stream is out of step.
+    broken: bool,
+    /// Rings for large payloads, if the daemon set them up
+    shared: Option<SharedRegion>,
+}
+
+impl IpcConnection {
+    /// Connect to the daemon at `path` and introduce `component` to it.
+    pub fn connect(path: &str, component: &str) -> Result<Self, String> {
+        let mut conn = Self::open(path)?;
+        conn.announce_session();
+        conn.check_versions(component)?;
+        Ok(conn)
+    }
+
+    /// Connect to the daemon at `path`, starting one if nobody answers.
+    fn open(path: &str) -> Result<Self, String> {
+        const MAX_RETRIES: u32 = 3;
+        /// Retries allowed once we've just started a daemon ourselves.
+        const AUTOSTART_RETRIES: u32 = 20;
+        const RETRY_DELAY_MS: u64 = 500;
+
+        let mut last_err = String::new();
+        let mut max_retries = MAX_RETRIES;
+        let mut attempt = 0;
+        while attempt < max_retries {
+            if attempt > 0 {
+                std::thread::sleep(std::time::Duration::from_millis(RETRY_DELAY_MS));
+            }
+
+            match Self::try_connect(path) {
+                Ok(conn) => return Ok(conn),
+                Err(e) => {
+                    last_err = e;
+                    if attempt == 0 && crate::autostart::autostart_daemon() {
+                        max_retries = AUTOSTART_RETRIES;
+                    }
+                }
+            }
+            attempt += 1;
+        }
+
+        Err(format!(
+            "failed to connect to RGPU daemon at {} after {} attempts: {}",
+            path, max_retries, last_err
+        ))
+    }
+
+    fn try_connect(path: &str) -> Result<Self, String> {
+        #[cfg(unix)]
+        {
+            let stream = std::os::unix::net::UnixStream::connect(path)
+                .map_err(|e| format!("{}", e))?;
+            stream
+                .set_read_timeout(Some(crate::platform::IPC_READ_TIMEOUT))
+                .ok();
+            Ok(Self { stream, broken: false, shared: None })
+        }
+
+        #[cfg(windows)]
+        {
+            let pipe = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(path)
+                .map_err(|e| format!("{}", e))?;
+            Ok(Self { pipe, broken: false, shared: None })
+        }
+    }
+
+    /// Tell the daemon the session name/labels from `RGPU_SESSION_NAME` and
+    /// `RGPU_SESSION_LABELS`, if set. Failures only cost the tagging.
+    fn announce_session(&mut self) {
+        let Some(tags) = crate::session::session_tags_from_env() else {
+            return;
+        };
+        let msg = Message::SetSessionInfo {
+            name: tags.name,
+            labels: tags.labels,
+        };
+        if self.send(&msg).is_ok() {
+            let _ = self.read_message();
+        }
+    }
+
+    /// Send `RGPU_VISIBLE_DEVICES`, if set, for the daemon to filter devices
+    /// by. A daemon that predates it skips the message, so a Ping follows.
+    pub fn restrict_devices(&mut self) -> Result<(), String> {
+        static UNSUPPORTED: Once = Once::new();
+
+        let Some(devices) = crate::session::visible_devices_from_env() else {
+            return Ok(());
+        };
+        for msg in [Message::SetVisibleDevices(devices), Message::Ping] {
+            self.send(&msg)?;
+        }
+        match self.read_message()? {
+            Message::SetVisibleDevices(_) => self.read_message().map(drop),
+            _ => {
+                UNSUPPORTED.call_once(|| {
+                    warn!("RGPU_VISIBLE_DEVICES is set, but the daemon doesn't support it; update the daemon")
+                });
+                Ok(())
+            }
+        }
+    }
+
+    /// Swap builds with the daemon, log them once per process and warn about
+    /// mismatches. `Err` if the daemon refuses this build.
+    fn check_versions(&mut self, component: &str) -> Result<(), String> {
+        static BANNER: Once = Once::new();
+
+        let ours = BuildInfo::current(component);
+        match version::exchange_with_daemon(self, &ours, Self::send, Self::read_message)? {
+            DaemonBuilds::Known { builds, mismatch } => {
+                BANNER.call_once(|| info!("RGPU: {}, {}", ours, version::banner(&builds)));
+                if let Some(reason) = mismatch {
+                    warn!("RGPU version mismatch: {}", reason);
+                }
+            }
+            DaemonBuilds::Older => BANNER.call_once(|| {
+                warn!(
+                    "RGPU: {} is newer than the daemon (protocol v{} or older); update the daemon",
+                    ours,
+                    ours.protocol_version - 1
+                )
+            }),
+        }
+        Ok(())
+    }
+
+    /// Whether a read or write failed partway; the connection should be
+    /// replaced, since the next reply read from it may not be for the next
+    /// request.
+    pub fn is_broken(&self) -> bool {
+        self.broken
+    }
+
+    /// Ask the daemon for up to `size` bytes of shared memory and map it. A
+    /// daemon that predates shared memory skips the request, and the Ping
+    /// behind it is answered either way; if anything else goes wrong, the
+    /// socket carries it all.
+    pub fn attach_shared_memory(&mut self, size: u64) -> Result<(), String> {
+        for msg in [Message::OpenSharedMemory { size }, Message::Ping] {
+            self.send(&msg)?;
+        }
+        let (name, size) = match self.read_message()? {
+            Message::SharedMemory { name, size } => {
+                self.read_message()?;
+                (name, size)
+            }
+            Message::Pong => return Ok(()),
+            other => {
+                debug!("daemon didn't set up shared memory: {:?}", other);
+                return self.read_message().map(drop);
+            }
+        };
+        match SharedRegion::open(&name, size as usize) {
+            Ok(region) => self.shared = Some(region),
+            Err(e) => {
+                warn!("can't map the RGPU daemon's shared memory {}, using the socket: {}", name, e);
+                return Ok(());
+            }
+        }
+        self.send(&Message::SharedMemoryAttached)?;
+        self.read_message().map(drop)
+    }
+
+    /// Encode `msg`, moving a large payload into shared memory if there is
+    /// room for it there.
+    pub fn frame(&self, msg: &Message) -> Result<Vec<u8>, String> {
+        let Some(region) = &self.shared else {
+            return wire::encode_message(msg, 0).map_err(|e| e.to_string());
+        };
+        let frame = wire::encode_message_uncompressed(msg, 0).map_err(|e| e.to_string())?;
+        if frame.len() - wire::HEADER_SIZE >= wire::SHARED_MEMORY_THRESHOLD {
+            if let Some(position) = region.ring(Direction::ToDaemon).push(&frame[wire::HEADER_SIZE..]) {
+                return Ok(wire::shared_frame(&frame, position));
+            }
+        }
+        Ok(frame)
+    }
+
+    pub fn send(&mut self, msg: &Message) -> Result<(), String> {
+        let frame = self.frame(msg)?;
+        self.write_all(&frame)
+    }
+
+    pub fn write_all(&mut self, data: &[u8]) -> Result<(), String> {
+        #[cfg(unix)]
+        let written = self.stream.write_all(data);
+        #[cfg(windows)]
+        let written = self.pipe.write_all(data);
+
+        self.broken |= written.is_err();
+        written.map_err(|e| format!("IPC write error: {}", e))
+    }
+
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), String> {
+        #[cfg(unix)]
+        let read = self.stream.read_exact(buf);
+        #[cfg(windows)]
+        let read = self.pipe.read_exact(buf);
+
+        self.broken |= read.is_err();
+        read.map_err(|e| format!("IPC read error: {}", e))
+    }
+
+    pub fn read_message(&mut self) -> Result<Message, String> {
+        let mut header_buf = [0u8; wire::HEADER_SIZE];
+        self.read_exact(&mut header_buf)?;
+
+        let (flags, _stream_id, payload_len) = wire::decode_header(&header_buf).map_err(|e| {
+            self.broken = true;
+            e.to_string()
+        })?;
+
+        let mut payload = vec![0u8; payload_len as usize];
+        self.read_exact(&mut payload)?;
+
+        if flags.contains(FrameFlags::SHARED) {
+            let shared = self.shared.as_ref().and_then(|region| {
+                let (position, len) = wire::shared_location(&payload).ok()?;
+                region.ring(Direction::ToApplication).pop(position, len)
+            });
+            let Some(shared) = shared else {
+                self.broken = true;
+                return Err("IPC read error: bad shared-memory frame".to_string());
+            };
+            return wire::decode_message(&shared, flags - FrameFlags::SHARED).map_err(|e| e.to_string());
+        }
+        wire::decode_message(&payload, flags).map_err(|e| e.to_string())
+    }
+}
@@ -1,4 +1,8 @@
+pub mod autostart;
+pub mod ipc;
 pub mod logging;
 pub mod platform;
+pub mod session;
+pub mod shm;
 
 pub use logging::init_logging;
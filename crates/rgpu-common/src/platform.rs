@@ -1,3 +1,7 @@
+/// How long the Vulkan ICD and CUDA interposer wait for a daemon response
+/// before giving up on a request.
+pub const IPC_READ_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
 /// Returns the default IPC socket/pipe path for client daemon communication.
 pub fn default_ipc_path() -> String {
     #[cfg(unix)]
@@ -12,6 +16,27 @@ pub fn default_ipc_path() -> String {
     }
 }
 
+/// Returns the directory for state the daemon or server keeps between runs
+/// (not configuration): `$XDG_STATE_HOME/rgpu` or `~/.local/state/rgpu` on
+/// Unix, `%LOCALAPPDATA%\rgpu` on Windows.
+pub fn state_dir() -> std::path::PathBuf {
+    #[cfg(unix)]
+    {
+        let base = std::env::var_os("XDG_STATE_HOME")
+            .map(std::path::PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".local/state")))
+            .unwrap_or_else(|| std::path::PathBuf::from("/tmp"));
+        base.join("rgpu")
+    }
+    #[cfg(windows)]
+    {
+        let base = std::env::var_os("LOCALAPPDATA")
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(std::env::temp_dir);
+        base.join("rgpu")
+    }
+}
+
 /// Returns the platform name string.
 pub fn platform_name() -> &'static str {
     #[cfg(target_os = "windows")]
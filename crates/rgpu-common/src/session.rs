@@ -0,0 +1,59 @@
+/// Environment variable naming the application's session in server metrics.
+pub const SESSION_NAME_ENV: &str = "RGPU_SESSION_NAME";
+
+/// Environment variable with session labels, as `key=value,key=value`.
+pub const SESSION_LABELS_ENV: &str = "RGPU_SESSION_LABELS";
+
+/// Name and labels an application asks for its session.
+pub struct SessionTags {
+    pub name: Option<String>,
+    pub labels: Vec<(String, String)>,
+}
+
+/// Session name and labels requested through the environment, or `None` if
+/// neither `RGPU_SESSION_NAME` nor `RGPU_SESSION_LABELS` is set.
+pub fn session_tags_from_env() -> Option<SessionTags> {
+    let name = std::env::var(SESSION_NAME_ENV).ok().filter(|n| !n.is_empty());
+    let labels = std::env::var(SESSION_LABELS_ENV)
+        .map(|s| parse_labels(&s))
+        .unwrap_or_default();
+    if name.is_none() && labels.is_empty() {
+        return None;
+    }
+    Some(SessionTags { name, labels })
+}
+
+/// Parse `key=value,key=value`. Entries without `=` or with an empty key are skipped.
+pub fn parse_labels(s: &str) -> Vec<(String, String)> {
+    s.split(',')
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once('=')?;
+            let key = key.trim();
+            (!key.is_empty()).then(|| (key.to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Environment variable restricting the devices an application sees, like
+/// `CUDA_VISIBLE_DEVICES`: `2,0` shows device 2 as device 0 and device 0 as
+/// device 1, and hides the rest.
+pub const VISIBLE_DEVICES_ENV: &str = "RGPU_VISIBLE_DEVICES";
+
+/// Devices requested through `RGPU_VISIBLE_DEVICES`, or `None` if it isn't set.
+pub fn visible_devices_from_env() -> Option<Vec<u32>> {
+    std::env::var(VISIBLE_DEVICES_ENV).ok().map(|s| parse_visible_devices(&s))
+}
+
+/// Parse a comma-separated list of device indices. As with
+/// `CUDA_VISIBLE_DEVICES`, the list ends at the first entry that isn't a
+/// number or repeats one before it, so `""` and `"none"` hide every device.
+pub fn parse_visible_devices(s: &str) -> Vec<u32> {
+    let mut devices = Vec::new();
+    for entry in s.split(',') {
+        match entry.trim().parse::<u32>() {
+            Ok(device) if !devices.contains(&device) => devices.push(device),
+            _ => break,
+        }
+    }
+    devices
+}
@@ -0,0 +1,425 @@
+//! Shared memory between a local application and the client daemon.
+//!
+//! Over the IPC socket or pipe, every byte of a memcpy is copied into the
+//! kernel and out again on each side. An IPC connection can instead set up
+//! a [`SharedRegion`] holding two single-producer rings, one each way: a
+//! large frame payload is copied into the sender's ring, and only its
+//! location goes over the socket. The daemon creates the region (a POSIX
+//! shared memory object on Unix, a named file mapping on Windows) and the
+//! application maps it by name.
+//!
+//! Both processes can write anywhere in the region, so a reader checks
+//! every location it is handed against the ring's bounds before copying.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Bytes of ring header: the write position, then the read position, each
+/// on a cache line of its own.
+const RING_HEADER: usize = 128;
+
+/// Which way a ring carries data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    ToDaemon,
+    ToApplication,
+}
+
+/// A mapped shared-memory region holding a ring in each direction.
+pub struct SharedRegion {
+    name: String,
+    base: *mut u8,
+    size: usize,
+    #[cfg(unix)]
+    owned: std::sync::atomic::AtomicBool,
+    #[cfg(windows)]
+    mapping: windows_sys::Win32::Foundation::HANDLE,
+}
+
+// SAFETY: the region is plain shared memory; the rings only touch it
+// through atomics and bounds-checked copies.
+unsafe impl Send for SharedRegion {}
+unsafe impl Sync for SharedRegion {}
+
+impl SharedRegion {
+    /// Create a region of about `size` bytes under a fresh name. On Unix,
+    /// `owner` is the uid of the application that will map it, which gets
+    /// the object; nobody else can open it.
+    pub fn create(size: usize, owner: Option<u32>) -> std::io::Result<Self> {
+        use std::sync::atomic::AtomicU32;
+        static NEXT: AtomicU32 = AtomicU32::new(1);
+
+        // Two rings of whole pages.
+        let size = size.max(2 * 4096).div_ceil(8192) * 8192;
+        let name = format!("rgpu-{}-{}", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        sys::create(name, size, owner)
+    }
+
+    /// Map a region the daemon created.
+    pub fn open(name: &str, size: usize) -> std::io::Result<Self> {
+        sys::open(name, size)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Remove the region's name once the application has mapped it, so
+    /// nothing is left behind if either side dies. The mapping stays.
+    pub fn unlink(&self) {
+        #[cfg(unix)]
+        if self.owned.swap(false, Ordering::Relaxed) {
+            sys::unlink(&self.name);
+        }
+    }
+
+    pub fn ring(&self, direction: Direction) -> Ring<'_> {
+        let half = self.size / 2;
+        let offset = match direction {
+            Direction::ToDaemon => 0,
+            Direction::ToApplication => half,
+        };
+        // SAFETY: both halves lie within the mapping, which is page-aligned,
+        // so the header atomics are aligned.
+        unsafe {
+            let start = self.base.add(offset);
+            Ring {
+                head: &*(start as *const AtomicU64),
+                tail: &*(start.add(64) as *const AtomicU64),
+                data: start.add(RING_HEADER),
+                capacity: (half - RING_HEADER) as u64,
+                _region: std::marker::PhantomData,
+            }
+        }
+    }
+}
+
+impl Drop for SharedRegion {
+    fn drop(&mut self) {
+        self.unlink();
+        sys::unmap(self);
+    }
+}
+
+/// One direction of a [`SharedRegion`]: one process pushes, the other pops
+/// in the same order.
+pub struct Ring<'a> {
+    /// Bytes ever pushed, including padding skipped at the end of the ring
+    head: &'a AtomicU64,
+    /// Bytes ever popped
+    tail: &'a AtomicU64,
+    data: *mut u8,
+    capacity: u64,
+    _region: std::marker::PhantomData<&'a SharedRegion>,
+}
+
+impl Ring<'_> {
+    /// Copy `bytes` in, returning their position, or `None` if the ring
+    /// hasn't room for them right now (the caller sends them inline).
+    /// Entries never wrap: one that doesn't fit before the end of the ring
+    /// starts over at its beginning.
+    pub fn push(&self, bytes: &[u8]) -> Option<u64> {
+        let len = bytes.len() as u64;
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        let position = if head % self.capacity + len > self.capacity {
+            head.next_multiple_of(self.capacity)
+        } else {
+            head
+        };
+        if len > self.capacity || position + len - tail > self.capacity {
+            return None;
+        }
+        // SAFETY: the entry lies within the data area, which the reader
+        // doesn't touch until the head moves past it.
+        unsafe {
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), self.data.add((position % self.capacity) as usize), bytes.len());
+        }
+        self.head.store(position + len, Ordering::Release);
+        Some(position)
+    }
+
+    /// Copy out the `len` bytes pushed at `position` and free them, along
+    /// with anything before them. `None` if the location isn't one the
+    /// ring holds.
+    pub fn pop(&self, position: u64, len: usize) -> Option<Vec<u8>> {
+        let len = len as u64;
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Relaxed);
+        let offset = position % self.capacity;
+        if position < tail || position + len > head || offset + len > self.capacity {
+            return None;
+        }
+        let mut bytes = vec![0u8; len as usize];
+        // SAFETY: checked to lie within the data area above.
+        unsafe {
+            std::ptr::copy_nonoverlapping(self.data.add(offset as usize), bytes.as_mut_ptr(), bytes.len());
+        }
+        self.tail.store(position + len, Ordering::Release);
+        Some(bytes)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::ffi::CString;
+    use std::io;
+    use std::sync::atomic::AtomicBool;
+
+    use super::SharedRegion;
+
+    fn c_name(name: &str) -> io::Result<CString> {
+        CString::new(format!("/{}", name)).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    }
+
+    pub fn create(name: String, size: usize, owner: Option<u32>) -> io::Result<SharedRegion> {
+        let c = c_name(&name)?;
+        // SAFETY: plain libc calls on a descriptor this function owns.
+        unsafe {
+            let fd = libc::shm_open(c.as_ptr(), libc::O_CREAT | libc::O_EXCL | libc::O_RDWR, 0o600);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let result = (|| {
+                if libc::ftruncate(fd, size as libc::off_t) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+                if let Some(uid) = owner.filter(|uid| *uid != libc::geteuid()) {
+                    if libc::fchown(fd, uid, libc::gid_t::MAX) != 0 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                map(fd, size)
+            })();
+            libc::close(fd);
+            match result {
+                Ok(base) => Ok(SharedRegion {
+                    name,
+                    base,
+                    size,
+                    owned: AtomicBool::new(true),
+                }),
+                Err(e) => {
+                    libc::shm_unlink(c.as_ptr());
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    pub fn open(name: &str, size: usize) -> io::Result<SharedRegion> {
+        let c = c_name(name)?;
+        // SAFETY: as above.
+        unsafe {
+            let fd = libc::shm_open(c.as_ptr(), libc::O_RDWR, 0);
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut stat: libc::stat = std::mem::zeroed();
+            let result = if libc::fstat(fd, &mut stat) != 0 {
+                Err(io::Error::last_os_error())
+            } else if (stat.st_size as usize) < size {
+                Err(io::Error::new(io::ErrorKind::InvalidData, "shared memory smaller than announced"))
+            } else {
+                map(fd, size)
+            };
+            libc::close(fd);
+            Ok(SharedRegion {
+                name: name.to_string(),
+                base: result?,
+                size,
+                owned: AtomicBool::new(false),
+            })
+        }
+    }
+
+    unsafe fn map(fd: libc::c_int, size: usize) -> io::Result<*mut u8> {
+        let base = libc::mmap(
+            std::ptr::null_mut(),
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(base as *mut u8)
+    }
+
+    pub fn unlink(name: &str) {
+        if let Ok(c) = c_name(name) {
+            // SAFETY: removes a name; mappings of the object stay valid.
+            unsafe { libc::shm_unlink(c.as_ptr()) };
+        }
+    }
+
+    pub fn unmap(region: &mut SharedRegion) {
+        // SAFETY: `base` and `size` are the mapping made at creation.
+        unsafe { libc::munmap(region.base as *mut libc::c_void, region.size) };
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    use std::io;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Security::{InitializeSecurityDescriptor, SetSecurityDescriptorDacl, SECURITY_ATTRIBUTES};
+    use windows_sys::Win32::System::Memory::{
+        CreateFileMappingW, MapViewOfFile, OpenFileMappingW, UnmapViewOfFile, FILE_MAP_ALL_ACCESS,
+        MEMORY_MAPPED_VIEW_ADDRESS, PAGE_READWRITE,
+    };
+
+    use super::SharedRegion;
+
+    fn wide(name: &str) -> Vec<u16> {
+        name.encode_utf16().chain(std::iter::once(0)).collect()
+    }
+
+    /// The daemon runs as a service in another session than the
+    /// application, so the name is global when the daemon may create global
+    /// objects, and session-local when it runs as the user.
+    pub fn create(name: String, size: usize, _owner: Option<u32>) -> io::Result<SharedRegion> {
+        const SECURITY_DESCRIPTOR_REVISION: u32 = 1;
+        // A null DACL, like the IPC pipe: any local process may map it.
+        let mut sd_buffer = [0u8; 64];
+        let sd_ptr = sd_buffer.as_mut_ptr() as *mut std::ffi::c_void;
+        // SAFETY: the descriptor buffer outlives the calls that use it.
+        unsafe {
+            if InitializeSecurityDescriptor(sd_ptr, SECURITY_DESCRIPTOR_REVISION) == 0
+                || SetSecurityDescriptorDacl(sd_ptr, 1, std::ptr::null_mut(), 0) == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let sa = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: sd_ptr,
+            bInheritHandle: 0,
+        };
+
+        let mut last_error = io::Error::other("no mapping created");
+        for scope in ["Global", "Local"] {
+            let scoped = format!("{}\\{}", scope, name);
+            let wide_name = wide(&scoped);
+            // SAFETY: creates a page-file backed mapping of `size` bytes.
+            let mapping = unsafe {
+                CreateFileMappingW(
+                    INVALID_HANDLE_VALUE,
+                    &sa,
+                    PAGE_READWRITE,
+                    (size as u64 >> 32) as u32,
+                    size as u32,
+                    wide_name.as_ptr(),
+                )
+            };
+            if mapping.is_null() {
+                last_error = io::Error::last_os_error();
+                continue;
+            }
+            return map(scoped, mapping, size);
+        }
+        Err(last_error)
+    }
+
+    pub fn open(name: &str, size: usize) -> io::Result<SharedRegion> {
+        let wide_name = wide(name);
+        // SAFETY: opens an existing mapping by name.
+        let mapping = unsafe { OpenFileMappingW(FILE_MAP_ALL_ACCESS, 0, wide_name.as_ptr()) };
+        if mapping.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        map(name.to_string(), mapping, size)
+    }
+
+    fn map(name: String, mapping: HANDLE, size: usize) -> io::Result<SharedRegion> {
+        // SAFETY: maps `size` bytes of a mapping at least that large.
+        let view = unsafe { MapViewOfFile(mapping, FILE_MAP_ALL_ACCESS, 0, 0, size) };
+        if view.Value.is_null() {
+            let error = io::Error::last_os_error();
+            // SAFETY: the handle is ours and unused.
+            unsafe { CloseHandle(mapping) };
+            return Err(error);
+        }
+        Ok(SharedRegion {
+            name,
+            base: view.Value as *mut u8,
+            size,
+            mapping,
+        })
+    }
+
+    pub fn unmap(region: &mut SharedRegion) {
+        // SAFETY: the view and handle were made by `map` and are released once.
+        unsafe {
+            UnmapViewOfFile(MEMORY_MAPPED_VIEW_ADDRESS {
+                Value: region.base as *mut std::ffi::c_void,
+            });
+            CloseHandle(region.mapping);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_come_out_as_they_went_in_across_the_wrap() {
+        let region = SharedRegion::create(16 * 1024, None).unwrap();
+        let mapped = SharedRegion::open(region.name(), region.size()).unwrap();
+        let (to_daemon, from_app) = (region.ring(Direction::ToDaemon), mapped.ring(Direction::ToDaemon));
+        let capacity = to_daemon.capacity();
+
+        for round in 0..10u8 {
+            let entry = vec![round; capacity / 3 + round as usize];
+            let position = to_daemon.push(&entry).expect("room after the last pop");
+            assert_eq!(from_app.pop(position, entry.len()), Some(entry));
+        }
+
+        // The other direction is separate.
+        let back = mapped.ring(Direction::ToApplication);
+        let position = back.push(b"answer").unwrap();
+        assert_eq!(region.ring(Direction::ToApplication).pop(position, 6).unwrap(), b"answer");
+    }
+
+    #[test]
+    fn full_ring_and_bad_locations_are_refused() {
+        let region = SharedRegion::create(16 * 1024, None).unwrap();
+        let ring = region.ring(Direction::ToApplication);
+        let capacity = ring.capacity();
+
+        assert_eq!(ring.push(&vec![0; capacity + 1]), None);
+        let first = ring.push(&vec![1; capacity / 2]).unwrap();
+        let second = ring.push(&vec![2; capacity / 4]).unwrap();
+        assert_eq!(ring.push(&vec![3; capacity / 2]), None);
+
+        // Nothing past what was pushed, nor anything already popped.
+        assert!(ring.pop(second, capacity).is_none());
+        assert!(ring.pop(second, capacity / 4).is_some());
+        assert!(ring.pop(first, capacity / 2).is_none());
+
+        // Popping freed the room.
+        assert!(ring.push(&vec![3; capacity / 2]).is_some());
+    }
+
+    #[test]
+    fn name_is_gone_once_unlinked() {
+        let region = SharedRegion::create(16 * 1024, None).unwrap();
+        region.unlink();
+        assert!(SharedRegion::open(region.name(), region.size()).is_err());
+        // The mapping itself still works.
+        let ring = region.ring(Direction::ToDaemon);
+        let position = ring.push(b"still here").unwrap();
+        assert_eq!(ring.pop(position, 10).unwrap(), b"still here");
+    }
+}
@@ -1,7 +1,11 @@
 use serde::{Deserialize, Serialize};
 
+pub use rgpu_protocol::gpu_info::SchedulingMode;
+pub use rgpu_protocol::wire::Codec;
+use rgpu_protocol::wire::{CompressionSettings, COMPRESSION_THRESHOLD};
+
 /// Top-level RGPU configuration, loaded from rgpu.toml.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RgpuConfig {
     #[serde(default)]
     pub server: ServerConfig,
@@ -25,6 +29,10 @@ pub struct ServerConfig {
     /// Transport mode: "tcp" or "quic"
     #[serde(default)]
     pub transport: TransportMode,
+    /// Port to also accept WebSocket connections on, in addition to
+    /// `transport` on `port`, for clients behind a reverse proxy
+    #[serde(default)]
+    pub websocket_port: Option<u16>,
     /// TLS certificate path
     pub cert_path: Option<String>,
     /// TLS private key path
@@ -34,6 +42,281 @@ pub struct ServerConfig {
     /// Maximum clients
     #[serde(default = "default_max_clients")]
     pub max_clients: u32,
+    /// VRAM a session may hold on each GPU, CUDA and Vulkan combined, in
+    /// megabytes (0 = unlimited)
+    #[serde(default)]
+    pub session_vram_quota_mb: u64,
+    /// How long a disconnected session's GPU objects are kept for its client
+    /// to reconnect and resume it, in seconds (0 = free them at once)
+    #[serde(default = "default_session_grace_secs")]
+    pub session_grace_secs: u64,
+    /// How often to look for GPUs added or removed since startup, telling
+    /// connected clients of any change, in seconds (0 = never)
+    #[serde(default = "default_gpu_rescan_secs")]
+    pub gpu_rescan_secs: u64,
+    /// Run each session's GPU commands on a thread pinned to the CPUs of the
+    /// NUMA node its GPU is attached to
+    #[serde(default)]
+    pub numa_affinity: bool,
+    /// Per-GPU CPU placement, overriding the NUMA node the GPU reports
+    #[serde(default)]
+    pub device_affinity: Vec<DeviceAffinity>,
+    /// How sessions share the GPUs
+    #[serde(default)]
+    pub scheduling: SchedulingConfig,
+    /// Compression of the responses sent to clients
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    /// RDMA device for clients that move bulk data onto RDMA, with
+    /// `transport = "rdma"`
+    #[serde(default)]
+    pub rdma: RdmaConfig,
+    /// Protection against clients flooding the server
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    /// Log of the commands sessions run, for attributing GPU use
+    #[serde(default)]
+    pub audit: AuditConfig,
+    /// `[server.device_attributes]`: cuDeviceGetAttribute values to report
+    /// instead of the driver's, by attribute name (`MULTI_GPU_BOARD`) or
+    /// number
+    #[serde(default)]
+    pub device_attributes: std::collections::BTreeMap<String, i32>,
+    /// What individual GPUs report themselves as
+    #[serde(default)]
+    pub device_overrides: Vec<DeviceOverride>,
+    /// GPUs shown to clients as several virtual GPUs
+    #[serde(default)]
+    pub virtual_gpus: Vec<VirtualGpuConfig>,
+}
+
+/// `[server.audit]`: a JSON-lines record of the commands each session runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditConfig {
+    /// File to append to; auditing is off without one
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Categories to record (empty = all)
+    #[serde(default)]
+    pub categories: Vec<AuditCategory>,
+    /// Size at which the file is rotated, in megabytes (0 = never)
+    #[serde(default = "default_audit_max_size_mb")]
+    pub max_size_mb: u64,
+    /// Rotated files kept, as `<path>.1` (newest) to `<path>.<keep>`
+    #[serde(default = "default_audit_keep")]
+    pub keep: u32,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            categories: Vec::new(),
+            max_size_mb: default_audit_max_size_mb(),
+            keep: default_audit_keep(),
+        }
+    }
+}
+
+/// Kinds of commands the audit log can record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditCategory {
+    /// Authentication and session resumption
+    Session,
+    /// Device and host memory allocations and frees
+    Alloc,
+    /// Memory copies, with their sizes
+    Memcpy,
+    Memset,
+    /// Kernel and graph launches
+    Launch,
+    /// Module loads and links, with the SHA-256 of their images
+    Module,
+    Vulkan,
+    /// Every other CUDA command
+    Other,
+}
+
+/// `[server.limits]`: how much a single client address may ask of the
+/// server, so a misbehaving client is turned away instead of exhausting it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// New connections accepted from one IP address per minute (0 = unlimited)
+    #[serde(default = "default_connections_per_minute")]
+    pub connections_per_minute: u32,
+    /// Connections one IP address may have open at once (0 = unlimited)
+    #[serde(default)]
+    pub sessions_per_ip: u32,
+    /// Largest message a client may send, in megabytes, before and after
+    /// decompression
+    #[serde(default = "default_max_message_mb")]
+    pub max_message_mb: u32,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            connections_per_minute: default_connections_per_minute(),
+            sessions_per_ip: 0,
+            max_message_mb: default_max_message_mb(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// `max_message_mb` in bytes.
+    pub fn max_message_size(&self) -> usize {
+        self.max_message_mb as usize * 1024 * 1024
+    }
+}
+
+/// `[server.rdma]`, or `rdma` on a server endpoint: the RDMA device and port
+/// to use. Only read with `transport = "rdma"`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RdmaConfig {
+    /// Device name, e.g. "mlx5_0" (default: the first device found)
+    #[serde(default)]
+    pub device: Option<String>,
+    /// Port number on the device
+    #[serde(default = "default_rdma_port")]
+    pub port: u8,
+    /// GID table index; on RoCE this picks the RoCE version and IP address
+    #[serde(default)]
+    pub gid_index: u8,
+}
+
+impl Default for RdmaConfig {
+    fn default() -> Self {
+        Self {
+            device: None,
+            port: default_rdma_port(),
+            gid_index: 0,
+        }
+    }
+}
+
+/// `[server.scheduling]`: how sessions share each GPU.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchedulingConfig {
+    /// Mode of GPUs without an entry in `devices`
+    #[serde(default)]
+    pub mode: SchedulingMode,
+    /// With `time_slice`, how long a session keeps the GPU while others are
+    /// waiting for it, in milliseconds
+    #[serde(default = "default_time_slice_ms")]
+    pub time_slice_ms: u64,
+    /// With `partitioned`, how many sessions each GPU is split between
+    #[serde(default = "default_partitions")]
+    pub partitions: u32,
+    /// Per-GPU modes
+    #[serde(default)]
+    pub devices: Vec<DeviceScheduling>,
+}
+
+/// Scheduling of one GPU, overriding `[server.scheduling]`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceScheduling {
+    /// Server-side GPU index
+    pub device: u32,
+    pub mode: SchedulingMode,
+    /// With `partitioned`, overrides `partitions`
+    #[serde(default)]
+    pub partitions: Option<u32>,
+}
+
+impl SchedulingConfig {
+    /// Mode and partition count of the GPU with server-side index `device`.
+    pub fn for_device(&self, device: u32) -> (SchedulingMode, u32) {
+        match self.devices.iter().find(|d| d.device == device) {
+            Some(entry) => (entry.mode, entry.partitions.unwrap_or(self.partitions)),
+            None => (self.mode, self.partitions),
+        }
+    }
+}
+
+/// `[server.compression]` / `[client.compression]`: how the frames one side
+/// sends are compressed. Peers that can't decode zstd get LZ4 instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    #[serde(default)]
+    pub codec: Codec,
+    /// zstd level, 1 (fastest) to 22 (smallest); 0 is zstd's default of 3
+    #[serde(default)]
+    pub level: i32,
+    /// Payloads up to this many bytes are sent uncompressed
+    #[serde(default = "default_compression_threshold")]
+    pub threshold: usize,
+}
+
+impl CompressionConfig {
+    pub fn settings(&self) -> CompressionSettings {
+        CompressionSettings {
+            codec: self.codec,
+            level: self.level,
+            threshold: self.threshold,
+        }
+    }
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            codec: Codec::default(),
+            level: 0,
+            threshold: default_compression_threshold(),
+        }
+    }
+}
+
+/// Where the worker threads serving one GPU run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceAffinity {
+    /// Server-side GPU index
+    pub device: u32,
+    /// Pin to the CPUs of this NUMA node
+    #[serde(default)]
+    pub numa_node: Option<u32>,
+    /// Pin to these CPUs, e.g. "0-15,32-47" (takes precedence over `numa_node`)
+    #[serde(default)]
+    pub cpus: Option<String>,
+}
+
+/// `[[server.device_overrides]]`: what one GPU reports to CUDA and Vulkan
+/// applications, for those that gate features on its name, compute
+/// capability or memory size.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceOverride {
+    /// Server-side GPU index
+    pub device: u32,
+    /// Device name
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Total memory, in megabytes
+    #[serde(default)]
+    pub total_memory_mb: Option<u64>,
+    /// Compute capability, e.g. "8.6"
+    #[serde(default)]
+    pub compute_capability: Option<String>,
+    /// cuDeviceGetAttribute values, as in `[server.device_attributes]`
+    #[serde(default)]
+    pub attributes: std::collections::BTreeMap<String, i32>,
+}
+
+/// `[[server.virtual_gpus]]`: one physical GPU shown to clients as `count`
+/// GPUs, each held by one session at a time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualGpuConfig {
+    /// Server-side index of the physical GPU
+    pub device: u32,
+    /// Virtual GPUs it is split into
+    pub count: u32,
+    /// Memory of each, in megabytes (default: an equal share)
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+    /// Share of the multiprocessors each reports, in percent
+    #[serde(default)]
+    pub sm_percent: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -47,6 +330,143 @@ pub struct ClientConfig {
     /// GPU ordering preference
     #[serde(default)]
     pub gpu_ordering: GpuOrdering,
+    /// With `gpu_ordering = "ByUuid"`, the GPUs to number first, in order:
+    /// UUIDs as nvidia-smi writes them, or prefixes of them
+    #[serde(default)]
+    pub gpu_uuids: Vec<String>,
+    /// Give each GPU the index it had before, across daemon restarts and
+    /// GPU list changes, instead of sorting the pool afresh
+    #[serde(default = "default_true")]
+    pub stable_gpu_indices: bool,
+    /// Executables routed through RGPU when the CUDA interpose DLL replaces the
+    /// system `nvcuda.dll`; all other processes use the real driver.
+    /// Empty means every process is interposed.
+    #[serde(default)]
+    pub interpose_allowlist: Vec<String>,
+    /// Let the CUDA interpose library drive local GPUs itself through the
+    /// real driver instead of going through the daemon; remote GPUs in the
+    /// same process still go through RGPU. Needs `include_local_gpus`.
+    #[serde(default)]
+    pub local_passthrough: bool,
+    /// Commands remembered per application for crash breadcrumbs (0 disables)
+    #[serde(default = "default_breadcrumb_depth")]
+    pub breadcrumb_depth: usize,
+    /// Directory for crash breadcrumb files (default: `<temp>/rgpu-crashes`)
+    #[serde(default)]
+    pub breadcrumb_dir: Option<String>,
+    /// Log the CUDA handles an application didn't free before exiting (they
+    /// are freed either way)
+    #[serde(default = "default_true")]
+    pub leak_warnings: bool,
+    /// A/B validation: mirror the CUDA command stream to a second server
+    #[serde(default)]
+    pub mirror: Option<MirrorConfig>,
+    /// Differential readback: keep the last copy of large DtoH reads and
+    /// only transfer the blocks that changed since
+    #[serde(default)]
+    pub readback_diff: bool,
+    /// Memory budget for differential readback copies, in megabytes
+    #[serde(default = "default_readback_diff_cache_mb")]
+    pub readback_diff_cache_mb: u64,
+    /// Learn which DtoH reads follow each sync point and issue them
+    /// speculatively as soon as the sync completes
+    #[serde(default)]
+    pub dtoh_prefetch: bool,
+    /// Prefetched DtoH responses larger than this many megabytes wait on
+    /// disk instead of in RAM until the app reads them (0 disables)
+    #[serde(default)]
+    pub spill_threshold_mb: u64,
+    /// Directory for spilled responses (default: `<temp>/rgpu-spill`)
+    #[serde(default)]
+    pub spill_dir: Option<String>,
+    /// Name shown for this client's sessions in server metrics
+    #[serde(default)]
+    pub session_name: Option<String>,
+    /// Labels attached to this client's sessions in server metrics
+    #[serde(default)]
+    pub session_labels: std::collections::BTreeMap<String, String>,
+    /// What to do when the interposer, ICD, daemon and servers are builds
+    /// that can't talk to each other
+    #[serde(default)]
+    pub version_check: VersionCheck,
+    /// Compression of the commands sent to servers
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    /// Shared memory, in megabytes, each application may use for bulk data
+    /// instead of the IPC socket (0 disables)
+    #[serde(default = "default_ipc_shared_memory_mb")]
+    pub ipc_shared_memory_mb: u64,
+    /// Create new CUDA contexts on an equivalent GPU of another server when
+    /// the server of the GPU asked for is down
+    #[serde(default)]
+    pub failover: bool,
+    /// How often server connections are checked, in seconds: the transport
+    /// probes the server at this interval and gives up on it after three go
+    /// unanswered, and the daemon sends a heartbeat `Ping` as often
+    #[serde(default = "default_health_check_secs")]
+    pub health_check_secs: u64,
+    /// Which remote GPU new CUDA contexts land on
+    #[serde(default)]
+    pub placement: PlacementConfig,
+}
+
+/// `[client.placement]`: spreading new CUDA contexts over the remote GPUs
+/// of several servers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacementConfig {
+    #[serde(default)]
+    pub policy: PlacementPolicy,
+    /// Share of the contexts each server gets with `weighted`, by address
+    /// (servers not listed weigh 1, 0 never gets any)
+    #[serde(default)]
+    pub weights: std::collections::BTreeMap<String, u32>,
+    /// Only place a context on a GPU of the same model, memory and compute
+    /// capability as the one the application asked for
+    #[serde(default = "default_true")]
+    pub match_model: bool,
+}
+
+impl Default for PlacementConfig {
+    fn default() -> Self {
+        Self {
+            policy: PlacementPolicy::default(),
+            weights: std::collections::BTreeMap::new(),
+            match_model: true,
+        }
+    }
+}
+
+/// How the remote GPU for a new CUDA context is chosen.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum PlacementPolicy {
+    /// The GPU the application asked for (default)
+    #[default]
+    #[serde(rename = "none")]
+    None,
+    /// The GPU with the least VRAM allocated by all sessions of its server
+    #[serde(rename = "least_vram_used")]
+    LeastVramUsed,
+    /// Each GPU in turn
+    #[serde(rename = "round_robin")]
+    RoundRobin,
+    /// Servers in proportion to their `weights`
+    #[serde(rename = "weighted")]
+    Weighted,
+}
+
+/// Second server that receives a copy of every remote CUDA command so its
+/// responses can be compared against the primary's.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    /// Mirror server connection details
+    #[serde(flatten)]
+    pub server: ServerEndpoint,
+    /// Allowed relative difference in event timings (0.25 = 25%)
+    #[serde(default = "default_timing_tolerance")]
+    pub timing_tolerance: f32,
+    /// File that mismatches are appended to (optional)
+    #[serde(default)]
+    pub report_path: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -60,6 +480,9 @@ pub struct ServerEndpoint {
     /// Per-server transport override
     #[serde(default)]
     pub transport: TransportMode,
+    /// RDMA device and port, with `transport = "rdma"`
+    #[serde(default)]
+    pub rdma: RdmaConfig,
 }
 
 /// Transport protocol selection.
@@ -72,9 +495,46 @@ pub enum TransportMode {
     /// QUIC (always encrypted, requires cert/key)
     #[serde(rename = "quic")]
     Quic,
+    /// Server: TCP and QUIC on the same port. Client: probe both on first
+    /// connect and remember the faster one per network.
+    #[serde(rename = "auto")]
+    Auto,
+    /// TCP, with bulk data over RDMA (InfiniBand or RoCE) where both sides
+    /// have it
+    #[serde(rename = "rdma")]
+    Rdma,
+    /// The TCP protocol tunnelled over WebSocket, through HTTP(S) proxies
+    /// and firewalls. Client: `address` may be a `ws://` or `wss://` URL.
+    /// Server: WebSocket on `port`, TLS with cert/key
+    #[serde(rename = "websocket")]
+    WebSocket,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl TransportMode {
+    /// The transport for an endpoint given only by address: WebSocket for a
+    /// `ws://` or `wss://` URL, the default otherwise.
+    pub fn for_address(address: &str) -> Self {
+        if address.starts_with("ws://") || address.starts_with("wss://") {
+            Self::WebSocket
+        } else {
+            Self::default()
+        }
+    }
+}
+
+/// Reaction to incompatible component versions.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum VersionCheck {
+    /// Log a warning and carry on (default)
+    #[default]
+    #[serde(rename = "warn")]
+    Warn,
+    /// Refuse the connection
+    #[serde(rename = "refuse")]
+    Refuse,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SecurityConfig {
     /// Accepted authentication tokens
     #[serde(default)]
@@ -91,24 +551,45 @@ pub struct TokenEntry {
     pub allowed_gpus: Option<Vec<u32>>,
     /// Memory limit in bytes (None = unlimited)
     pub max_memory: Option<u64>,
+    /// Whether sessions may run CUDA commands
+    #[serde(default = "default_true")]
+    pub allow_cuda: bool,
+    /// Whether sessions may run Vulkan commands
+    #[serde(default = "default_true")]
+    pub allow_vulkan: bool,
+    /// Whether modules may be JIT-compiled from PTX sent by the client
+    #[serde(default = "default_true")]
+    pub allow_ptx: bool,
+    /// Sessions that may use this token at the same time (None = unlimited)
+    #[serde(default)]
+    pub max_sessions: Option<u32>,
+    /// On out-of-memory, trim the default memory pools of the session's devices and
+    /// retry the allocation once
+    #[serde(default)]
+    pub retry_alloc_after_trim: bool,
+    /// Labels attached to sessions using this token, shown in metrics
+    #[serde(default)]
+    pub labels: std::collections::BTreeMap<String, String>,
 }
 
+/// How the GPUs of the pool are numbered. Each sort is stable: GPUs it
+/// doesn't tell apart keep server order.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GpuOrdering {
+    /// Local GPUs, then the servers' (default)
     #[default]
     LocalFirst,
+    /// The servers' GPUs, then local ones
     RemoteFirst,
+    /// Servers in the order of `[[client.servers]]`, each server's GPUs in
+    /// its own order, then local GPUs
+    ServerOrder,
+    /// Most VRAM first
+    ByVram,
+    /// Highest compute capability first, then most VRAM
     ByCapability,
-}
-
-impl Default for RgpuConfig {
-    fn default() -> Self {
-        Self {
-            server: ServerConfig::default(),
-            client: ClientConfig::default(),
-            security: SecurityConfig::default(),
-        }
-    }
+    /// The GPUs of `gpu_uuids` in that order, then the rest in server order
+    ByUuid,
 }
 
 impl Default for ServerConfig {
@@ -118,28 +599,66 @@ impl Default for ServerConfig {
             port: default_port(),
             bind: default_bind(),
             transport: TransportMode::default(),
+            websocket_port: None,
             cert_path: None,
             key_path: None,
             expose_gpus: None,
             max_clients: default_max_clients(),
+            session_vram_quota_mb: 0,
+            session_grace_secs: default_session_grace_secs(),
+            gpu_rescan_secs: default_gpu_rescan_secs(),
+            numa_affinity: false,
+            device_affinity: Vec::new(),
+            scheduling: SchedulingConfig::default(),
+            compression: CompressionConfig::default(),
+            rdma: RdmaConfig::default(),
+            limits: LimitsConfig::default(),
+            audit: AuditConfig::default(),
+            device_attributes: std::collections::BTreeMap::new(),
+            device_overrides: Vec::new(),
+            virtual_gpus: Vec::new(),
         }
     }
 }
 
-impl Default for ClientConfig {
+impl Default for SchedulingConfig {
     fn default() -> Self {
         Self {
-            servers: Vec::new(),
-            include_local_gpus: true,
-            gpu_ordering: GpuOrdering::default(),
+            mode: SchedulingMode::default(),
+            time_slice_ms: default_time_slice_ms(),
+            partitions: default_partitions(),
+            devices: Vec::new(),
         }
     }
 }
 
-impl Default for SecurityConfig {
+impl Default for ClientConfig {
     fn default() -> Self {
         Self {
-            tokens: Vec::new(),
+            servers: Vec::new(),
+            include_local_gpus: true,
+            gpu_ordering: GpuOrdering::default(),
+            gpu_uuids: Vec::new(),
+            stable_gpu_indices: true,
+            interpose_allowlist: Vec::new(),
+            local_passthrough: false,
+            breadcrumb_depth: default_breadcrumb_depth(),
+            breadcrumb_dir: None,
+            leak_warnings: true,
+            mirror: None,
+            readback_diff: false,
+            readback_diff_cache_mb: default_readback_diff_cache_mb(),
+            dtoh_prefetch: false,
+            spill_threshold_mb: 0,
+            spill_dir: None,
+            session_name: None,
+            session_labels: Default::default(),
+            version_check: VersionCheck::default(),
+            compression: CompressionConfig::default(),
+            ipc_shared_memory_mb: default_ipc_shared_memory_mb(),
+            failover: false,
+            health_check_secs: default_health_check_secs(),
+            placement: PlacementConfig::default(),
         }
     }
 }
@@ -194,6 +713,66 @@ fn default_max_clients() -> u32 {
     16
 }
 
+fn default_session_grace_secs() -> u64 {
+    30
+}
+
+fn default_gpu_rescan_secs() -> u64 {
+    30
+}
+
 fn default_true() -> bool {
     true
 }
+
+fn default_breadcrumb_depth() -> usize {
+    64
+}
+
+fn default_timing_tolerance() -> f32 {
+    0.25
+}
+
+fn default_readback_diff_cache_mb() -> u64 {
+    512
+}
+
+fn default_ipc_shared_memory_mb() -> u64 {
+    64
+}
+
+fn default_health_check_secs() -> u64 {
+    5
+}
+
+fn default_audit_max_size_mb() -> u64 {
+    100
+}
+
+fn default_audit_keep() -> u32 {
+    5
+}
+
+fn default_connections_per_minute() -> u32 {
+    120
+}
+
+fn default_max_message_mb() -> u32 {
+    1024
+}
+
+fn default_rdma_port() -> u8 {
+    1
+}
+
+fn default_time_slice_ms() -> u64 {
+    50
+}
+
+fn default_partitions() -> u32 {
+    2
+}
+
+fn default_compression_threshold() -> usize {
+    COMPRESSION_THRESHOLD
+}
@@ -0,0 +1,243 @@
+//! Generates the loader-stub forwarders and the export table.
+//!
+//! Every `cu*` function exported by this crate gets a twin in
+//! `$OUT_DIR/forwarders.rs` with the same parameters that calls the
+//! identically named export of the real NVIDIA driver when passthrough
+//! routes the call there, and an entry in `$OUT_DIR/exports.rs`, which
+//! `cuGetProcAddress` resolves names against. The signatures are scraped
+//! from the source files so the lists can never drift apart.
+//!
+//! For local passthrough each forwarder also names what the call is routed
+//! by: its handle parameters, or else the thread's current context.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Source files with exports, and the module path of each.
+const SOURCES: &[(&str, &str)] = &[
+    ("src/lib.rs", "crate"),
+    ("src/stubs.rs", "crate::stubs"),
+    ("src/error.rs", "crate::error"),
+    ("src/proc_address.rs", "crate::proc_address"),
+];
+
+/// Handle parameter types and the `Route` variant each one routes by.
+const ROUTES: &[(&str, &str)] = &[
+    ("CUdevice", "Device"),
+    ("CUcontext", "Context"),
+    ("CUmodule", "Module"),
+    ("CUfunction", "Function"),
+    ("CUstream", "Stream"),
+    ("CUevent", "Event"),
+    ("CUdeviceptr", "DevicePtr"),
+    ("CUarray", "Array"),
+    ("CUgraph", "Graph"),
+    ("CUgraphExec", "GraphExec"),
+    ("CUlinkState", "Linker"),
+    ("CUmemoryPool", "MemPool"),
+    ("CUtexObject", "Object"),
+    ("CUsurfObject", "Object"),
+    ("CUtexref", "TexRef"),
+    ("CUsurfref", "TexRef"),
+];
+
+/// Exports never forwarded by local passthrough: the interpose library
+/// answers them for both sides, or decides itself.
+const NEVER_ROUTED: &[&str] = &[
+    "cuInit",
+    "cuDriverGetVersion",
+    "cuDeviceGetCount",
+    "cuDeviceGet",
+    "cuDeviceGetByPCIBusId",
+    "cuGetErrorString",
+    "cuGetErrorName",
+    "cuGetProcAddress",
+    "cuGetProcAddress_v2",
+];
+
+/// Exports taking a page-locked host pointer `p`, routed by who allocated
+/// or registered it.
+const HOST_POINTER_ROUTED: &[&str] = &[
+    "cuMemFreeHost",
+    "cuMemHostGetDevicePointer_v2",
+    "cuMemHostGetFlags",
+    "cuMemHostUnregister",
+];
+
+/// Exports that make a context current, so later context-less calls follow
+/// them to the same side.
+const SWITCHES: &[&str] = &["cuCtxCreate_v2", "cuCtxSetCurrent", "cuCtxPushCurrent_v2"];
+
+struct Export {
+    module: &'static str,
+    name: String,
+    params: Vec<(String, String)>,
+    ret: String,
+}
+
+fn main() {
+    let mut exports = Vec::new();
+    for (source, module) in SOURCES {
+        println!("cargo:rerun-if-changed={}", source);
+        let text = std::fs::read_to_string(source).expect("read interpose source");
+        scan_exports(&text, module, &mut exports);
+    }
+
+    let mut out = String::new();
+    for export in &exports {
+        let params: Vec<String> = export
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect();
+        let args: Vec<&str> = export.params.iter().map(|(name, _)| name.as_str()).collect();
+        writeln!(
+            out,
+            "pub(crate) unsafe fn {name}({params}) -> Option<{ret}> {{\n    \
+             if !routes_to_driver({route}, {switches}) {{\n        \
+             return None;\n    \
+             }}\n    \
+             driver::{name}({args})\n}}\n",
+            name = export.name,
+            params = params.join(", "),
+            ret = export.ret,
+            route = route(export),
+            switches = SWITCHES.contains(&export.name.as_str()),
+            args = args.join(", "),
+        )
+        .unwrap();
+    }
+
+    out.push_str("/// The real driver's exports, called whatever the routing says.\npub(crate) mod driver {\n    use super::*;\n\n");
+    for export in &exports {
+        let params: Vec<String> = export
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect();
+        let types: Vec<&str> = export.params.iter().map(|(_, ty)| ty.as_str()).collect();
+        let args: Vec<&str> = export.params.iter().map(|(name, _)| name.as_str()).collect();
+        writeln!(
+            out,
+            "    pub(crate) unsafe fn {name}({params}) -> Option<{ret}> {{\n        \
+             static SLOT: AtomicPtr<c_void> = AtomicPtr::new(std::ptr::null_mut());\n        \
+             let real = resolve(&SLOT, b\"{name}\\0\")?;\n        \
+             let real = std::mem::transmute::<*mut c_void, unsafe extern \"C\" fn({types}) -> {ret}>(real);\n        \
+             Some(real({args}))\n    }}\n",
+            name = export.name,
+            params = params.join(", "),
+            ret = export.ret,
+            types = types.join(", "),
+            args = args.join(", "),
+        )
+        .unwrap();
+    }
+    out.push_str("}\n");
+
+    let mut table = String::from(
+        "/// Every exported `cu*` function, by name.\n\
+         pub(crate) fn lookup(name: &str) -> Option<*mut c_void> {\n    \
+         let f: *mut c_void = match name {\n",
+    );
+    for export in &exports {
+        writeln!(table, "        \"{0}\" => {1}::{0} as *mut c_void,", export.name, export.module).unwrap();
+    }
+    table.push_str("        _ => return None,\n    };\n    Some(f)\n}\n\n");
+    table.push_str("/// Names of all exports.\n#[cfg(test)]\npub(crate) const NAMES: &[&str] = &[\n");
+    for export in &exports {
+        writeln!(table, "    \"{}\",", export.name).unwrap();
+    }
+    table.push_str("];\n");
+
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR");
+    std::fs::write(Path::new(&out_dir).join("forwarders.rs"), out).expect("write forwarders");
+    std::fs::write(Path::new(&out_dir).join("exports.rs"), table).expect("write export table");
+}
+
+/// The `Route`s a forwarder decides by, as a slice expression.
+fn route(export: &Export) -> String {
+    if NEVER_ROUTED.contains(&export.name.as_str()) {
+        return "&[Route::Never]".to_string();
+    }
+    if HOST_POINTER_ROUTED.contains(&export.name.as_str()) {
+        return "&[Route::HostPtr(p as u64)]".to_string();
+    }
+    let routes: Vec<String> = export
+        .params
+        .iter()
+        .filter_map(|(name, ty)| {
+            let (_, variant) = ROUTES.iter().find(|(handle, _)| handle == ty)?;
+            Some(format!("Route::{}({} as u64)", variant, name))
+        })
+        .collect();
+    if routes.is_empty() {
+        return "&[Route::Current]".to_string();
+    }
+    format!("&[{}]", routes.join(", "))
+}
+
+/// Collect every `extern "C" fn cu*(...) -> T` definition in `text`.
+fn scan_exports(text: &str, module: &'static str, exports: &mut Vec<Export>) {
+    const MARKER: &str = "extern \"C\" fn ";
+    let mut rest = text;
+    while let Some(pos) = rest.find(MARKER) {
+        rest = &rest[pos + MARKER.len()..];
+        let open = rest.find('(').expect("export parameter list");
+        let name = rest[..open].trim().to_string();
+
+        // Parameter types may contain brackets (`*mut [u8; 16]`), so track depth.
+        let mut depth = 0usize;
+        let mut close = open;
+        for (i, c) in rest[open..].char_indices() {
+            match c {
+                '(' | '[' => depth += 1,
+                ')' | ']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        close = open + i;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let params = split_params(&rest[open + 1..close]);
+
+        let body = rest[close..].find('{').expect("export body") + close;
+        let ret = rest[close + 1..body]
+            .trim()
+            .trim_start_matches("->")
+            .trim()
+            .to_string();
+        rest = &rest[body..];
+
+        if name.starts_with("cu") {
+            exports.push(Export { module, name, params, ret });
+        }
+    }
+}
+
+fn split_params(list: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut push = |param: &str| {
+        let param = param.trim();
+        if let Some((name, ty)) = param.split_once(':') {
+            params.push((name.trim().to_string(), ty.trim().to_string()));
+        }
+    };
+    for (i, c) in list.char_indices() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    push(&list[start..]);
+    params
+}
@@ -0,0 +1,77 @@
+//! CUDA array descriptors.
+//!
+//! Arrays live on the server; the application gets an id for each, like the
+//! other opaque handles. Their descriptors are kept in the handle store to
+//! answer `cuArrayGetDescriptor` and `cuArray3DGetDescriptor` locally.
+
+use std::ffi::c_uint;
+
+use rgpu_protocol::cuda_commands::ArrayDescriptor;
+
+/// `CUDA_ARRAY_DESCRIPTOR`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct CUDA_ARRAY_DESCRIPTOR {
+    pub width: usize,
+    pub height: usize,
+    pub format: c_uint,
+    pub num_channels: c_uint,
+}
+
+/// `CUDA_ARRAY3D_DESCRIPTOR`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct CUDA_ARRAY3D_DESCRIPTOR {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub format: c_uint,
+    pub num_channels: c_uint,
+    pub flags: c_uint,
+}
+
+impl CUDA_ARRAY_DESCRIPTOR {
+    pub(crate) fn to_desc(&self) -> ArrayDescriptor {
+        ArrayDescriptor {
+            width: self.width as u64,
+            height: self.height as u64,
+            depth: 0,
+            format: self.format,
+            num_channels: self.num_channels,
+            flags: 0,
+        }
+    }
+
+    pub(crate) fn from_desc(desc: &ArrayDescriptor) -> Self {
+        Self {
+            width: desc.width as usize,
+            height: desc.height as usize,
+            format: desc.format,
+            num_channels: desc.num_channels,
+        }
+    }
+}
+
+impl CUDA_ARRAY3D_DESCRIPTOR {
+    pub(crate) fn to_desc(&self) -> ArrayDescriptor {
+        ArrayDescriptor {
+            width: self.width as u64,
+            height: self.height as u64,
+            depth: self.depth as u64,
+            format: self.format,
+            num_channels: self.num_channels,
+            flags: self.flags,
+        }
+    }
+
+    pub(crate) fn from_desc(desc: &ArrayDescriptor) -> Self {
+        Self {
+            width: desc.width as usize,
+            height: desc.height as usize,
+            depth: desc.depth as usize,
+            format: desc.format,
+            num_channels: desc.num_channels,
+            flags: desc.flags,
+        }
+    }
+}
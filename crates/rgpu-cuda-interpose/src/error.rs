@@ -140,6 +140,7 @@ pub unsafe extern "C" fn cuGetErrorString(
     error: CUresult,
     p_str: *mut *const c_char,
 ) -> CUresult {
+    forward!(cuGetErrorString(error, p_str));
     if p_str.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -152,6 +153,7 @@ pub unsafe extern "C" fn cuGetErrorName(
     error: CUresult,
     p_str: *mut *const c_char,
 ) -> CUresult {
+    forward!(cuGetErrorName(error, p_str));
     if p_str.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -4,22 +4,41 @@
 //! to NetworkHandles used for IPC communication with the RGPU daemon.
 
 use dashmap::DashMap;
+use rgpu_protocol::codec::TransferCodec;
+use rgpu_protocol::cuda_commands::ArrayDescriptor;
 use rgpu_protocol::handle::NetworkHandle;
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::OnceLock;
 
 static NEXT_ID: AtomicU64 = AtomicU64::new(0x1000);
 
+/// Device pointers handed to the application come from their own range,
+/// one `DEVICE_PTR_SPAN`-sized slot per allocation, so a pointer into the
+/// middle of an allocation (`d_buf + i`) can be traced back to it and isn't
+/// mistaken for a small scalar kernel argument.
+const DEVICE_PTR_BASE: u64 = 1 << 48;
+const DEVICE_PTR_SPAN: u64 = 1 << 36;
+static NEXT_DEVICE_PTR: AtomicU64 = AtomicU64::new(DEVICE_PTR_BASE);
+
 static DEVICE_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
 static CTX_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
 static MOD_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
 static FUNC_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
+static FUNC_PARAMS_MAP: OnceLock<DashMap<u64, Vec<u32>>> = OnceLock::new();
 static MEM_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
 static STREAM_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
 static EVENT_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
 static MEMPOOL_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
 static LINKER_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
-static HOST_MEM_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
+static LINKER_CUBIN_MAP: OnceLock<DashMap<u64, Box<[u8]>>> = OnceLock::new();
+static GRAPH_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
+static GRAPH_EXEC_MAP: OnceLock<DashMap<u64, NetworkHandle>> = OnceLock::new();
+static ARRAY_MAP: OnceLock<DashMap<u64, (NetworkHandle, ArrayDescriptor)>> = OnceLock::new();
+static TRANSFER_CODEC_MAP: OnceLock<DashMap<u64, TransferCodec>> = OnceLock::new();
+static HOST_RANGES: OnceLock<parking_lot::Mutex<BTreeMap<u64, HostRange>>> = OnceLock::new();
+static MANAGED_RANGES: OnceLock<parking_lot::Mutex<BTreeMap<u64, u64>>> = OnceLock::new();
+static PENDING_READBACKS: parking_lot::Mutex<Vec<PendingReadback>> = parking_lot::Mutex::new(Vec::new());
 
 fn device_map() -> &'static DashMap<u64, NetworkHandle> {
     DEVICE_MAP.get_or_init(DashMap::new)
@@ -33,6 +52,9 @@ fn mod_map() -> &'static DashMap<u64, NetworkHandle> {
 fn func_map() -> &'static DashMap<u64, NetworkHandle> {
     FUNC_MAP.get_or_init(DashMap::new)
 }
+fn func_params_map() -> &'static DashMap<u64, Vec<u32>> {
+    FUNC_PARAMS_MAP.get_or_init(DashMap::new)
+}
 fn mem_map() -> &'static DashMap<u64, NetworkHandle> {
     MEM_MAP.get_or_init(DashMap::new)
 }
@@ -48,8 +70,26 @@ fn mempool_map() -> &'static DashMap<u64, NetworkHandle> {
 fn linker_map() -> &'static DashMap<u64, NetworkHandle> {
     LINKER_MAP.get_or_init(DashMap::new)
 }
-fn host_mem_map() -> &'static DashMap<u64, NetworkHandle> {
-    HOST_MEM_MAP.get_or_init(DashMap::new)
+fn linker_cubin_map() -> &'static DashMap<u64, Box<[u8]>> {
+    LINKER_CUBIN_MAP.get_or_init(DashMap::new)
+}
+fn transfer_codec_map() -> &'static DashMap<u64, TransferCodec> {
+    TRANSFER_CODEC_MAP.get_or_init(DashMap::new)
+}
+fn graph_map() -> &'static DashMap<u64, NetworkHandle> {
+    GRAPH_MAP.get_or_init(DashMap::new)
+}
+fn host_ranges() -> &'static parking_lot::Mutex<BTreeMap<u64, HostRange>> {
+    HOST_RANGES.get_or_init(Default::default)
+}
+fn managed_ranges() -> &'static parking_lot::Mutex<BTreeMap<u64, u64>> {
+    MANAGED_RANGES.get_or_init(Default::default)
+}
+fn graph_exec_map() -> &'static DashMap<u64, NetworkHandle> {
+    GRAPH_EXEC_MAP.get_or_init(DashMap::new)
+}
+fn array_map() -> &'static DashMap<u64, (NetworkHandle, ArrayDescriptor)> {
+    ARRAY_MAP.get_or_init(DashMap::new)
 }
 
 fn alloc_id() -> u64 {
@@ -93,18 +133,25 @@ pub fn remove_mod(id: u64) {
 }
 
 // ── Function ────────────────────────────────────────────────────
-pub fn store_func(handle: NetworkHandle) -> u64 {
+pub fn store_func(handle: NetworkHandle, param_sizes: Option<Vec<u32>>) -> u64 {
     let id = alloc_id();
     func_map().insert(id, handle);
+    if let Some(sizes) = param_sizes {
+        func_params_map().insert(id, sizes);
+    }
     id
 }
 pub fn get_func(id: u64) -> Option<NetworkHandle> {
     func_map().get(&id).map(|v| *v)
 }
+/// Size of each of the function's parameters, if the server reported them.
+pub fn get_func_param_sizes(id: u64) -> Option<Vec<u32>> {
+    func_params_map().get(&id).map(|v| v.clone())
+}
 
 // ── Memory ──────────────────────────────────────────────────────
 pub fn store_mem(handle: NetworkHandle) -> u64 {
-    let id = alloc_id();
+    let id = NEXT_DEVICE_PTR.fetch_add(DEVICE_PTR_SPAN, Ordering::Relaxed);
     mem_map().insert(id, handle);
     id
 }
@@ -113,10 +160,48 @@ pub fn get_mem(id: u64) -> Option<NetworkHandle> {
 }
 pub fn remove_mem(id: u64) {
     mem_map().remove(&id);
+    transfer_codec_map().remove(&id);
 }
 pub fn get_mem_by_ptr(ptr: u64) -> Option<NetworkHandle> {
     get_mem(ptr)
 }
+/// The allocation a device pointer points into and the offset into it.
+pub fn resolve_device_ptr(ptr: u64) -> Option<(NetworkHandle, u64)> {
+    if ptr < DEVICE_PTR_BASE {
+        let ranges = managed_ranges().lock();
+        let (&base, &size) = ranges.range(..=ptr).next_back()?;
+        return get_mem(base).filter(|_| ptr < base + size).map(|handle| (handle, ptr - base));
+    }
+    let offset = (ptr - DEVICE_PTR_BASE) % DEVICE_PTR_SPAN;
+    get_mem(ptr - offset).map(|handle| (handle, offset))
+}
+
+/// Transfer codec set on an allocation with rgpuMemSetTransferHint.
+pub fn set_transfer_codec(id: u64, codec: Option<TransferCodec>) {
+    match codec {
+        Some(codec) => {
+            transfer_codec_map().insert(id, codec);
+        }
+        None => {
+            transfer_codec_map().remove(&id);
+        }
+    }
+}
+pub fn get_transfer_codec(id: u64) -> Option<TransferCodec> {
+    transfer_codec_map().get(&id).map(|v| *v)
+}
+
+/// Managed allocations are handed out at the address of their region in
+/// this process (see `managed`), which can't collide with the device
+/// pointer range.
+pub fn store_managed_mem(base: u64, size: u64, handle: NetworkHandle) {
+    mem_map().insert(base, handle);
+    managed_ranges().lock().insert(base, size);
+}
+pub fn remove_managed_mem(base: u64) {
+    remove_mem(base);
+    managed_ranges().lock().remove(&base);
+}
 
 // ── Stream ──────────────────────────────────────────────────────
 pub fn store_stream(handle: NetworkHandle) -> u64 {
@@ -166,19 +251,179 @@ pub fn store_linker(handle: NetworkHandle) -> u64 {
 pub fn get_linker(id: u64) -> Option<NetworkHandle> {
     linker_map().get(&id).map(|v| *v)
 }
+/// Remove a linker, freeing the cubin cuLinkComplete returned for it.
 pub fn remove_linker(id: u64) {
     linker_map().remove(&id);
+    linker_cubin_map().remove(&id);
+}
+/// Keep the cubin cuLinkComplete returns until the linker is destroyed, as
+/// the driver does, and return where it lives. A cubin from an earlier
+/// cuLinkComplete on the same linker is freed.
+pub fn store_linker_cubin(id: u64, cubin: Vec<u8>) -> *const u8 {
+    let cubin = cubin.into_boxed_slice();
+    let ptr = cubin.as_ptr();
+    linker_cubin_map().insert(id, cubin);
+    ptr
 }
 
 // ── Host Memory ─────────────────────────────────────────────────
-pub fn store_host_mem(handle: NetworkHandle) -> u64 {
+
+/// Page-locked host memory: a buffer in this process, from cuMemAllocHost /
+/// cuMemHostAlloc (`layout` set, allocated here) or cuMemHostRegister (the
+/// application's own), backed by a pinned allocation on the server.
+#[derive(Clone, Copy)]
+pub struct HostRange {
+    pub size: u64,
+    pub flags: u32,
+    pub handle: NetworkHandle,
+    pub layout: Option<std::alloc::Layout>,
+    /// A device pointer was handed out for it, so the device may read and
+    /// write the server's copy
+    pub mapped: bool,
+    /// Hash of the contents when the two copies last matched
+    pub synced_hash: Option<u64>,
+}
+
+/// Record a range; false if it overlaps one already recorded.
+pub fn insert_host_range(base: u64, range: HostRange) -> bool {
+    let mut ranges = host_ranges().lock();
+    let below = ranges.range(..base + range.size).next_back();
+    if below.is_some_and(|(&start, r)| start + r.size > base) {
+        return false;
+    }
+    ranges.insert(base, range);
+    true
+}
+/// Remove the range starting at `base` if `keep` doesn't object.
+pub fn remove_host_range(base: u64, keep: impl FnOnce(&HostRange) -> bool) -> Option<HostRange> {
+    let mut ranges = host_ranges().lock();
+    if keep(ranges.get(&base)?) {
+        return None;
+    }
+    ranges.remove(&base)
+}
+/// The range containing `ptr` and the offset into it.
+pub fn find_host_range(ptr: u64) -> Option<(HostRange, u64)> {
+    let ranges = host_ranges().lock();
+    let (&start, range) = ranges.range(..=ptr).next_back()?;
+    (ptr < start + range.size).then(|| (*range, ptr - start))
+}
+pub fn set_host_range_mapped(base: u64) {
+    if let Some(range) = host_ranges().lock().get_mut(&base) {
+        range.mapped = true;
+    }
+}
+pub fn set_host_range_synced(base: u64, hash: u64) {
+    if let Some(range) = host_ranges().lock().get_mut(&base) {
+        range.synced_hash = Some(hash);
+    }
+}
+/// Ranges the device may access, by base address.
+pub fn mapped_host_ranges() -> Vec<(u64, HostRange)> {
+    host_ranges()
+        .lock()
+        .iter()
+        .filter(|(_, r)| r.mapped)
+        .map(|(&base, r)| (base, *r))
+        .collect()
+}
+
+// ── Graph ───────────────────────────────────────────────────────
+pub fn store_graph(handle: NetworkHandle) -> u64 {
+    let id = alloc_id();
+    graph_map().insert(id, handle);
+    id
+}
+pub fn get_graph(id: u64) -> Option<NetworkHandle> {
+    graph_map().get(&id).map(|v| *v)
+}
+pub fn remove_graph(id: u64) {
+    graph_map().remove(&id);
+}
+
+// ── Executable Graph ────────────────────────────────────────────
+pub fn store_graph_exec(handle: NetworkHandle) -> u64 {
+    let id = alloc_id();
+    graph_exec_map().insert(id, handle);
+    id
+}
+pub fn get_graph_exec(id: u64) -> Option<NetworkHandle> {
+    graph_exec_map().get(&id).map(|v| *v)
+}
+pub fn remove_graph_exec(id: u64) {
+    graph_exec_map().remove(&id);
+}
+
+// ── CUDA Array ──────────────────────────────────────────────────
+pub fn store_array(handle: NetworkHandle, desc: ArrayDescriptor) -> u64 {
     let id = alloc_id();
-    host_mem_map().insert(id, handle);
+    array_map().insert(id, (handle, desc));
     id
 }
-pub fn get_host_mem(id: u64) -> Option<NetworkHandle> {
-    host_mem_map().get(&id).map(|v| *v)
+pub fn get_array(id: u64) -> Option<NetworkHandle> {
+    array_map().get(&id).map(|v| v.0)
+}
+pub fn get_array_descriptor(id: u64) -> Option<ArrayDescriptor> {
+    array_map().get(&id).map(|v| v.1)
 }
-pub fn remove_host_mem(id: u64) {
-    host_mem_map().remove(&id);
+pub fn remove_array(id: u64) {
+    array_map().remove(&id);
+}
+
+// ── Pending Readbacks ───────────────────────────────────────────
+
+/// A cuMemcpyDtoHAsync that returned before its data was fetched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PendingReadback {
+    /// Host destination in this process
+    pub dst: u64,
+    pub src: NetworkHandle,
+    pub byte_count: u64,
+    pub stream: NetworkHandle,
+}
+
+pub fn push_pending_readback(readback: PendingReadback) {
+    PENDING_READBACKS.lock().push(readback);
+}
+/// Remove the pending readbacks `select` picks and return them, oldest first.
+pub fn take_pending_readbacks(select: impl Fn(&PendingReadback) -> bool) -> Vec<PendingReadback> {
+    let mut pending = PENDING_READBACKS.lock();
+    let (taken, kept) = pending.drain(..).partition(|r| select(r));
+    *pending = kept;
+    taken
+}
+pub fn has_pending_readbacks() -> bool {
+    !PENDING_READBACKS.lock().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::ResourceType;
+
+    fn cubin_bytes() -> usize {
+        linker_cubin_map().iter().map(|cubin| cubin.len()).sum()
+    }
+
+    #[test]
+    fn linker_cubin_freed_on_destroy() {
+        let before = cubin_bytes();
+        for i in 0..1000u64 {
+            let id = store_linker(NetworkHandle {
+                server_id: 0,
+                session_id: 1,
+                resource_id: i,
+                resource_type: ResourceType::CuLinker,
+            });
+            let cubin = vec![0xCB; 4096];
+            let ptr = store_linker_cubin(id, cubin);
+            assert_eq!(unsafe { *ptr }, 0xCB);
+            // A second cuLinkComplete replaces the first cubin
+            store_linker_cubin(id, vec![0xCB; 4096]);
+            assert_eq!(cubin_bytes(), before + 4096);
+            remove_linker(id);
+            assert_eq!(cubin_bytes(), before);
+            assert!(get_linker(id).is_none());
+        }
+    }
 }
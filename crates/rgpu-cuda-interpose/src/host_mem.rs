@@ -0,0 +1,121 @@
+//! Page-locked host memory.
+//!
+//! The server's pinned allocations can't be reached from here, so
+//! cuMemAllocHost / cuMemHostAlloc hand out a buffer allocated in this
+//! process, and cuMemHostRegister keeps the application's own; either way
+//! the server holds a pinned counterpart. Copies read and write the local
+//! buffer directly. Once a device pointer has been handed out for a range,
+//! kernels use the server's copy: local changes are written back before
+//! work that runs on the device, and the server's contents are read back at
+//! synchronization points.
+
+use std::alloc::Layout;
+use std::collections::hash_map::DefaultHasher;
+use std::ffi::c_void;
+use std::hash::Hasher;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+
+use crate::handle_store::{self, HostRange};
+use crate::{send_cuda_command, CUresult, CUDA_ERROR_INVALID_VALUE, CUDA_ERROR_UNKNOWN, CUDA_SUCCESS};
+
+/// Host buffers are page-aligned, like the driver's.
+const PAGE_SIZE: usize = 4096;
+
+/// Set when work that may write mapped memory has been queued since the
+/// last read-back.
+static DEVICE_WRITES_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Allocate `byte_size` bytes on the server with `command` (MemAllocHost or
+/// MemHostAlloc) and a local buffer to go with it.
+pub fn alloc(byte_size: usize, flags: u32, command: CudaCommand) -> Result<*mut c_void, CUresult> {
+    let layout = Layout::from_size_align(byte_size.max(1), PAGE_SIZE).map_err(|_| CUDA_ERROR_INVALID_VALUE)?;
+    let handle = match send_cuda_command(command) {
+        CudaResponse::HostPtr(handle) => handle,
+        CudaResponse::Error { code, .. } => return Err(code),
+        _ => return Err(CUDA_ERROR_UNKNOWN),
+    };
+    // Zeroed, like the server's fresh allocation.
+    let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+    if ptr.is_null() {
+        send_cuda_command(CudaCommand::MemFreeHost { ptr: handle });
+        return Err(crate::CUDA_ERROR_OUT_OF_MEMORY);
+    }
+    let range = HostRange {
+        size: byte_size as u64,
+        flags,
+        handle,
+        layout: Some(layout),
+        mapped: false,
+        synced_hash: None,
+    };
+    handle_store::insert_host_range(ptr as u64, range);
+    Ok(ptr as *mut c_void)
+}
+
+/// Free a buffer from [`alloc`].
+pub fn free(p: *mut c_void) -> CUresult {
+    let range = match handle_store::find_host_range(p as u64) {
+        Some((range, 0)) if range.layout.is_some() => range,
+        _ => return CUDA_ERROR_INVALID_VALUE,
+    };
+    match send_cuda_command(CudaCommand::MemFreeHost { ptr: range.handle }) {
+        CudaResponse::Success => {
+            if let Some(HostRange { layout: Some(layout), .. }) = handle_store::remove_host_range(p as u64, |_| false) {
+                unsafe { std::alloc::dealloc(p as *mut u8, layout) };
+            }
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+fn contents_hash(base: u64, size: u64) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(unsafe { std::slice::from_raw_parts(base as *const u8, size as usize) });
+    hasher.finish()
+}
+
+/// Queue the local contents of mapped ranges that changed since they were
+/// last in sync, ahead of work that runs on the device.
+pub fn write_back_mapped() {
+    let mapped = handle_store::mapped_host_ranges();
+    if mapped.is_empty() {
+        return;
+    }
+    DEVICE_WRITES_PENDING.store(true, Ordering::Relaxed);
+    for (base, range) in mapped {
+        let hash = contents_hash(base, range.size);
+        if range.synced_hash == Some(hash) {
+            continue;
+        }
+        let data = unsafe { std::slice::from_raw_parts(base as *const u8, range.size as usize) }.to_vec();
+        send_cuda_command(CudaCommand::HostMemWrite {
+            ptr: range.handle,
+            offset: 0,
+            data,
+        });
+        handle_store::set_host_range_synced(base, hash);
+    }
+}
+
+/// Pick up what the device wrote to mapped ranges, at a synchronization point.
+pub fn read_back_mapped() {
+    if !DEVICE_WRITES_PENDING.swap(false, Ordering::Relaxed) {
+        return;
+    }
+    for (base, range) in handle_store::mapped_host_ranges() {
+        let response = send_cuda_command(CudaCommand::HostMemRead {
+            ptr: range.handle,
+            offset: 0,
+            byte_count: range.size,
+        });
+        if let CudaResponse::MemoryData(data) = response {
+            let len = data.len().min(range.size as usize);
+            unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), base as *mut u8, len) };
+            handle_store::set_host_range_synced(base, contents_hash(base, range.size));
+        }
+    }
+}
@@ -1,19 +1,55 @@
 //! Synchronous IPC client for communicating with the RGPU client daemon.
 //! This must be synchronous because CUDA API calls are synchronous.
 //!
-//! Supports command pipelining: void CUDA commands (memcpy, memset, free, etc.)
-//! are batched and sent as a single `Message::CudaBatch` at the next sync point.
+//! Supports command pipelining: void CUDA commands (memcpy, memset, launches,
+//! free, etc.) are queued and travel with the next command that needs a
+//! response, as one `Message::CudaPipelined` round trip. Queries whose answer
+//! never changes (device attributes, names, ...) are answered from a cache
+//! after the first time.
+//!
+//! Queued commands fail after the call that queued them returned, as
+//! asynchronous work does in CUDA, and are reported the same way: by the
+//! call they travel with, or, for a full queue sent on its own, by the next
+//! synchronizing call. Errors that leave the context unusable stick, and
+//! every call returns them until the context is destroyed or reset.
+//!
+//! If the daemon goes away (a restart, an upgrade), the connection is dropped
+//! and the next request reconnects, backing off between failed attempts so a
+//! daemon that stays down costs each call little. A new daemon has none of
+//! this process's state: the session is announced again, and the `cuInit`
+//! and device lookups made so far are replayed so the device handles the
+//! application holds keep working. Anything else the application created on
+//! the old daemon (contexts, allocations, modules) is gone; the request that
+//! was in flight when the connection broke fails, and is only resent if it
+//! never reached the daemon. Void commands still queued refer to that state
+//! too, so they are dropped rather than sent to the new daemon, and cached
+//! query answers are forgotten, since the device a handle maps to may be a
+//! different one now.
+//!
+//! Daemons that offer it give each connection a shared-memory region, and
+//! payloads of 64 KB or more go through its rings instead of the socket.
 
-use std::io::{Read, Write};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
+use rgpu_common::ipc::IpcConnection;
 use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
 use rgpu_protocol::messages::{Message, RequestId};
-use rgpu_protocol::wire;
+use tracing::{info, warn};
 
+/// `CUDA_ERROR_CONTEXT_IS_DESTROYED`, for queued commands that were lost with
+/// a daemon that went away.
+const CUDA_ERROR_CONTEXT_IS_DESTROYED: i32 = 709;
 /// Maximum number of void commands to buffer before auto-flushing.
 const PIPELINE_BATCH_SIZE: usize = 32;
+/// Wait after the first failed reconnect; doubles with each further one.
+const RECONNECT_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(8);
+/// Shared memory asked of the daemon, which may give less.
+const SHARED_MEMORY_SIZE: u64 = 64 * 1024 * 1024;
 
 /// Synchronous IPC client that connects to the RGPU client daemon.
 pub struct IpcClient {
@@ -23,15 +59,42 @@ pub struct IpcClient {
     connection: Mutex<Option<IpcConnection>>,
     /// Buffered void CUDA commands waiting to be flushed.
     pipeline_buffer: Mutex<Vec<CudaCommand>>,
+    /// Answers to queries that can't change.
+    query_cache: Mutex<HashMap<QueryKey, CudaResponse>>,
+    /// First error of queued commands that no call has returned yet, for
+    /// the next synchronizing call.
+    deferred_error: Mutex<Option<CudaResponse>>,
+    /// An error that left the context unusable, returned by every call.
+    sticky_error: Mutex<Option<CudaResponse>>,
+    /// Set once connected; later connects are reconnects.
+    connected: AtomicBool,
+    /// Failed reconnects since the last success.
+    reconnect: Mutex<Backoff>,
+    /// `cuInit` and device lookups made so far, with the device handle each
+    /// lookup returned, for replaying on a new daemon.
+    replay: Mutex<Vec<(CudaCommand, Option<NetworkHandle>)>>,
+    /// Device handles from before a reconnect, mapped to the ones their
+    /// lookups returned when replayed.
+    remapped: Mutex<HashMap<NetworkHandle, NetworkHandle>>,
 }
 
-struct IpcConnection {
-    #[cfg(unix)]
-    stream: std::os::unix::net::UnixStream,
-    #[cfg(windows)]
-    pipe: std::fs::File,
+#[derive(Default)]
+struct Backoff {
+    delay: Duration,
+    /// No attempt before this
+    not_before: Option<Instant>,
 }
 
+impl Backoff {
+    fn failed(&mut self) {
+        self.delay = (self.delay * 2).clamp(RECONNECT_BACKOFF, MAX_RECONNECT_BACKOFF);
+        self.not_before = Some(Instant::now() + self.delay);
+    }
+}
+
+/// A cacheable query: which query, the device, and the attribute if any.
+type QueryKey = (&'static str, NetworkHandle, i32);
+
 /// Returns true if this CUDA command is "void" — it always returns Success
 /// and doesn't produce data the caller needs immediately.
 fn is_void_command(cmd: &CudaCommand) -> bool {
@@ -42,6 +105,7 @@ fn is_void_command(cmd: &CudaCommand) -> bool {
         | CudaCommand::MemcpyHtoDAsync { .. }
         | CudaCommand::MemcpyDtoD { .. }
         | CudaCommand::MemcpyDtoDAsync { .. }
+        | CudaCommand::HostMemWrite { .. }
         // Memset operations
         | CudaCommand::MemsetD8 { .. }
         | CudaCommand::MemsetD16 { .. }
@@ -49,10 +113,25 @@ fn is_void_command(cmd: &CudaCommand) -> bool {
         | CudaCommand::MemsetD8Async { .. }
         | CudaCommand::MemsetD16Async { .. }
         | CudaCommand::MemsetD32Async { .. }
+        | CudaCommand::MemsetD2D8 { .. }
+        | CudaCommand::MemsetD2D16 { .. }
+        | CudaCommand::MemsetD2D32 { .. }
+        | CudaCommand::MemsetD2D8Async { .. }
+        | CudaCommand::MemsetD2D16Async { .. }
+        | CudaCommand::MemsetD2D32Async { .. }
+        // Kernel and graph launches (launch errors surface at next sync)
+        | CudaCommand::LaunchKernel { .. }
+        | CudaCommand::LaunchCooperativeKernel { .. }
+        | CudaCommand::GraphLaunch { .. }
+        | CudaCommand::GraphUpload { .. }
         // Free operations
         | CudaCommand::MemFree { .. }
         | CudaCommand::MemFreeHost { .. }
         | CudaCommand::MemFreeAsync { .. }
+        | CudaCommand::TexObjectDestroy { .. }
+        | CudaCommand::SurfObjectDestroy { .. }
+        | CudaCommand::ArrayDestroy { .. }
+        | CudaCommand::IpcCloseMemHandle { .. }
         // Context state changes
         | CudaCommand::CtxSetCurrent { .. }
         | CudaCommand::CtxPushCurrent { .. }
@@ -74,9 +153,80 @@ fn is_void_command(cmd: &CudaCommand) -> bool {
         | CudaCommand::DevicePrimaryCtxSetFlags { .. }
         | CudaCommand::MemPoolTrimTo { .. }
         | CudaCommand::MemPoolSetAttribute { .. }
+        // Texture and surface reference state
+        | CudaCommand::TexRefSet { .. }
+        | CudaCommand::SurfRefSetArray { .. }
+    )
+}
+
+/// Returns true if CUDA reports errors of earlier asynchronous work from
+/// this command: it waits for work to finish, asks whether it has, or
+/// copies its results to the host.
+fn is_sync_point(cmd: &CudaCommand) -> bool {
+    matches!(
+        cmd,
+        CudaCommand::CtxSynchronize
+            | CudaCommand::StreamSynchronize { .. }
+            | CudaCommand::StreamQuery { .. }
+            | CudaCommand::EventSynchronize { .. }
+            | CudaCommand::EventQuery { .. }
+            | CudaCommand::MemcpyDtoH { .. }
+            | CudaCommand::MemcpyDtoHAsync { .. }
+            | CudaCommand::MemcpyDtoHDiff { .. }
+            | CudaCommand::HostMemRead { .. }
+            | CudaCommand::Memcpy3D { .. }
+            | CudaCommand::Memcpy3DAsync { .. }
+    )
+}
+
+/// Returns true for errors after which CUDA fails every call in the context:
+/// illegal address, launch timeout, assert, hardware stack error, misaligned
+/// address, illegal instruction, invalid address space, invalid PC and
+/// launch failure.
+fn is_sticky(response: &CudaResponse) -> bool {
+    matches!(
+        response,
+        CudaResponse::Error { code: 700 | 702 | 710 | 713 | 714 | 715 | 716 | 717 | 719, .. }
     )
 }
 
+/// Cache key for queries whose answer is fixed for the life of the process.
+fn query_key(cmd: &CudaCommand) -> Option<QueryKey> {
+    match cmd {
+        CudaCommand::DriverGetVersion => Some(("DriverGetVersion", NetworkHandle::null(), 0)),
+        CudaCommand::DeviceGetName { device } => Some(("DeviceGetName", *device, 0)),
+        CudaCommand::DeviceGetAttribute { attrib, device } => Some(("DeviceGetAttribute", *device, *attrib)),
+        CudaCommand::DeviceTotalMem { device } => Some(("DeviceTotalMem", *device, 0)),
+        CudaCommand::DeviceComputeCapability { device } => Some(("DeviceComputeCapability", *device, 0)),
+        CudaCommand::DeviceGetUuid { device } => Some(("DeviceGetUuid", *device, 0)),
+        CudaCommand::DeviceGetTexture1DLinearMaxWidth { format, num_channels, device } => {
+            Some(("DeviceGetTexture1DLinearMaxWidth", *device, (*format << 8 | *num_channels) as i32))
+        }
+        CudaCommand::DeviceGetExecAffinitySupport { affinity_type, device } => {
+            Some(("DeviceGetExecAffinitySupport", *device, *affinity_type))
+        }
+        _ => None,
+    }
+}
+
+/// Drop the void commands queued ahead of `msg`'s command, which refer to
+/// what the application had on a daemon that is gone. Returns how many.
+fn drop_queued(msg: &mut Message) -> usize {
+    match msg {
+        Message::CudaPipelined { request_id, batch, command } => {
+            let dropped = batch.len();
+            *msg = Message::CudaCommand {
+                request_id: *request_id,
+                command: command.clone(),
+                deadline_ms: None,
+            };
+            dropped
+        }
+        Message::CudaBatch(batch) => std::mem::take(batch).len(),
+        _ => 0,
+    }
+}
+
 impl IpcClient {
     pub fn new(path: &str) -> Self {
         Self {
@@ -84,12 +234,48 @@ impl IpcClient {
             next_request_id: AtomicU64::new(1),
             connection: Mutex::new(None),
             pipeline_buffer: Mutex::new(Vec::new()),
+            query_cache: Mutex::new(HashMap::new()),
+            deferred_error: Mutex::new(None),
+            sticky_error: Mutex::new(None),
+            connected: AtomicBool::new(false),
+            reconnect: Mutex::new(Backoff::default()),
+            replay: Mutex::new(Vec::new()),
+            remapped: Mutex::new(HashMap::new()),
         }
     }
 
     /// Send a CUDA command to the daemon and wait for the response.
     /// Void commands are batched and sent at the next sync point.
     pub fn send_command(&self, cmd: CudaCommand) -> Result<CudaResponse, String> {
+        let resets = matches!(cmd, CudaCommand::CtxDestroy { .. } | CudaCommand::DevicePrimaryCtxReset { .. });
+        if let Some(sticky) = self.sticky_error.lock().map_err(|e| e.to_string())?.clone() {
+            if !resets {
+                return Ok(sticky);
+            }
+        }
+        if resets {
+            // Errors of the context's work go with it
+            let response = self.send_uncached(cmd)?;
+            if !matches!(response, CudaResponse::Error { .. }) {
+                *self.sticky_error.lock().map_err(|e| e.to_string())? = None;
+                *self.deferred_error.lock().map_err(|e| e.to_string())? = None;
+            }
+            return Ok(response);
+        }
+        let Some(key) = query_key(&cmd) else {
+            return self.send_uncached(cmd);
+        };
+        if let Some(cached) = self.query_cache.lock().map_err(|e| e.to_string())?.get(&key) {
+            return Ok(cached.clone());
+        }
+        let response = self.send_uncached(cmd)?;
+        if !matches!(response, CudaResponse::Error { .. }) {
+            self.query_cache.lock().map_err(|e| e.to_string())?.insert(key, response.clone());
+        }
+        Ok(response)
+    }
+
+    fn send_uncached(&self, cmd: CudaCommand) -> Result<CudaResponse, String> {
         if is_void_command(&cmd) {
             let mut buf = self.pipeline_buffer.lock().map_err(|e| e.to_string())?;
             buf.push(cmd);
@@ -102,24 +288,85 @@ impl IpcClient {
             return Ok(CudaResponse::Success);
         }
 
-        // Sync point: flush any buffered commands first
-        self.flush_pipeline()?;
-
+        // Sync point: buffered commands go along in the same round trip. While
+        // there are any, the buffer stays locked so that commands queued by
+        // other threads can't be flushed ahead of them.
+        let mut buf = self.pipeline_buffer.lock().map_err(|e| e.to_string())?;
+        let batch = std::mem::take(&mut *buf);
         let request_id = RequestId(self.next_request_id.fetch_add(1, Ordering::Relaxed));
-        let msg = Message::CudaCommand {
-            request_id,
-            command: cmd,
+        let replayable = matches!(cmd, CudaCommand::Init { .. } | CudaCommand::DeviceGet { .. })
+            .then(|| cmd.clone());
+        let sync_point = is_sync_point(&cmd);
+        let msg = if batch.is_empty() {
+            drop(buf);
+            Message::CudaCommand {
+                request_id,
+                command: cmd,
+                deadline_ms: None,
+            }
+        } else {
+            Message::CudaPipelined {
+                request_id,
+                batch,
+                command: cmd,
+            }
         };
 
         let response = self.send_and_receive(msg)?;
 
         match response {
-            Message::CudaResponse { response, .. } => Ok(response),
+            Message::CudaResponse { response, .. } => {
+                if let Some(cmd) = replayable {
+                    self.record_replay(cmd, &response)?;
+                }
+                self.note_error(&response)?;
+                if sync_point {
+                    if let Some(deferred) = self.deferred_error.lock().map_err(|e| e.to_string())?.take() {
+                        return Ok(deferred);
+                    }
+                }
+                Ok(response)
+            }
             Message::Error(e) => Err(e.to_string()),
             other => Err(format!("unexpected response: {:?}", other)),
         }
     }
 
+    /// Keep an error that leaves the context unusable for every later call.
+    fn note_error(&self, response: &CudaResponse) -> Result<(), String> {
+        if is_sticky(response) {
+            *self.sticky_error.lock().map_err(|e| e.to_string())? = Some(response.clone());
+        }
+        Ok(())
+    }
+
+    /// Keep the error of queued commands for the next synchronizing call.
+    fn defer_error(&self, response: CudaResponse) -> Result<(), String> {
+        self.note_error(&response)?;
+        self.deferred_error.lock().map_err(|e| e.to_string())?.get_or_insert(response);
+        Ok(())
+    }
+
+    /// Remember a successful `cuInit` or device lookup for replaying after a
+    /// reconnect, once per flags or ordinal.
+    fn record_replay(&self, cmd: CudaCommand, response: &CudaResponse) -> Result<(), String> {
+        let device = match response {
+            CudaResponse::Success => None,
+            CudaResponse::Device(handle) => Some(*handle),
+            _ => return Ok(()),
+        };
+        let mut replay = self.replay.lock().map_err(|e| e.to_string())?;
+        let recorded = replay.iter().any(|(recorded, _)| match (recorded, &cmd) {
+            (CudaCommand::Init { flags: a }, CudaCommand::Init { flags: b }) => a == b,
+            (CudaCommand::DeviceGet { ordinal: a }, CudaCommand::DeviceGet { ordinal: b }) => a == b,
+            _ => false,
+        });
+        if !recorded {
+            replay.push((cmd, device));
+        }
+        Ok(())
+    }
+
     /// Flush any buffered pipeline commands.
     fn flush_pipeline(&self) -> Result<(), String> {
         let mut buf = self.pipeline_buffer.lock().map_err(|e| e.to_string())?;
@@ -135,143 +382,382 @@ impl IpcClient {
             return Ok(());
         }
 
-        let batch = Message::CudaBatch(buf.drain(..).collect());
+        let batch = Message::CudaBatch(std::mem::take(buf));
         let response = self.send_and_receive(batch)?;
 
-        // Check for errors in the batch response
+        // The calls that queued the batch returned long ago
         match response {
             Message::CudaResponse {
-                response: CudaResponse::Error { code, message },
+                response: error @ CudaResponse::Error { .. },
                 ..
-            } => Err(format!("batch error (code {}): {}", code, message)),
+            } => self.defer_error(error),
             _ => Ok(()),
         }
     }
 
-    fn send_and_receive(&self, msg: Message) -> Result<Message, String> {
-        let frame = wire::encode_message(&msg, 0).map_err(|e| e.to_string())?;
-
-        let mut conn_guard = self.connection.lock().map_err(|e| e.to_string())?;
+    /// Send a readback command and copy the returned data into `dst`.
+    /// Large payloads are streamed by the daemon in `chunk_size` pieces and
+    /// written straight into `dst`, in which case `Success` is returned;
+    /// otherwise the response is returned as is for the caller to handle.
+    pub fn send_command_streamed(
+        &self,
+        cmd: CudaCommand,
+        dst: &mut [u8],
+        chunk_size: u32,
+    ) -> Result<CudaResponse, String> {
+        self.flush_pipeline()?;
 
-        // Try to reuse existing connection, or create a new one
-        let conn = if let Some(ref mut c) = *conn_guard {
-            c
-        } else {
-            let new_conn = IpcConnection::connect(&self.path)?;
-            *conn_guard = Some(new_conn);
-            conn_guard.as_mut().expect("connection was just set to Some")
+        let request_id = RequestId(self.next_request_id.fetch_add(1, Ordering::Relaxed));
+        let msg = Message::CudaCommandStreamed {
+            request_id,
+            command: cmd,
+            chunk_size,
         };
 
-        // Send frame
-        if let Err(_e) = conn.write_all(&frame) {
-            // Connection lost, try reconnecting once
-            drop(conn_guard);
-            let mut conn_guard = self.connection.lock().map_err(|e| e.to_string())?;
-            let new_conn = IpcConnection::connect(&self.path)?;
-            *conn_guard = Some(new_conn);
-            let conn = conn_guard.as_mut().expect("connection was just set to Some");
-            conn.write_all(&frame).map_err(|e| e.to_string())?;
+        let response = self.exchange(msg, |conn| loop {
+            match conn.read_message()? {
+                Message::MemoryChunk { offset, data, .. } => {
+                    // Bytes beyond `dst` are dropped, as with an unstreamed copy.
+                    let start = (offset as usize).min(dst.len());
+                    let len = data.len().min(dst.len() - start);
+                    dst[start..start + len].copy_from_slice(&data[..len]);
+                }
+                Message::CudaResponse { response, .. } => return Ok(response),
+                Message::Error(e) => return Err(e.to_string()),
+                other => return Err(format!("unexpected response: {:?}", other)),
+            }
+        })?;
 
-            // Read response
-            let response = conn.read_message()?;
-            return Ok(response);
+        // A copy to the host is a synchronizing call
+        self.note_error(&response)?;
+        if let Some(deferred) = self.deferred_error.lock().map_err(|e| e.to_string())?.take() {
+            return Ok(deferred);
         }
-
-        // Read response
-        let response = conn.read_message()?;
         Ok(response)
     }
-}
 
-impl IpcConnection {
-    fn connect(path: &str) -> Result<Self, String> {
-        const MAX_RETRIES: u32 = 3;
-        const RETRY_DELAY_MS: u64 = 500;
+    fn send_and_receive(&self, msg: Message) -> Result<Message, String> {
+        self.exchange(msg, |conn| conn.read_message())
+    }
 
-        let mut last_err = String::new();
-        for attempt in 0..MAX_RETRIES {
-            if attempt > 0 {
-                std::thread::sleep(std::time::Duration::from_millis(RETRY_DELAY_MS));
+    /// Send `msg` and read its response(s) with `read`. A broken connection
+    /// is dropped for the next request to replace; if the write failed, the
+    /// daemon never got `msg`, so it is sent once more on a new connection.
+    fn exchange<T>(
+        &self,
+        mut msg: Message,
+        mut read: impl FnMut(&mut IpcConnection) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut conn_guard = self.connection.lock().map_err(|e| e.to_string())?;
+        let mut retried = false;
+        loop {
+            if conn_guard.is_none() {
+                let reconnecting = self.connected.load(Ordering::Relaxed);
+                *conn_guard = Some(self.connect()?);
+                let dropped = if reconnecting { drop_queued(&mut msg) } else { 0 };
+                if dropped > 0 {
+                    self.defer_error(CudaResponse::Error {
+                        code: CUDA_ERROR_CONTEXT_IS_DESTROYED,
+                        message: format!("{} queued calls were lost with the daemon", dropped),
+                    })?;
+                }
             }
+            self.remap(&mut msg)?;
+            let conn = conn_guard.as_mut().expect("connection was just set to Some");
+            let frame = conn.frame(&msg)?;
 
-            match Self::try_connect(path) {
-                Ok(conn) => return Ok(conn),
-                Err(e) => {
-                    last_err = e;
+            if let Err(e) = conn.write_all(&frame) {
+                *conn_guard = None;
+                if retried {
+                    return Err(e);
                 }
+                warn!("lost connection to RGPU daemon ({}), reconnecting", e);
+                retried = true;
+                continue;
             }
-        }
 
-        Err(format!(
-            "failed to connect to RGPU daemon at {} after {} attempts: {}",
-            path, MAX_RETRIES, last_err
-        ))
+            // The command may have run, so a failed read isn't retried.
+            let result = read(conn);
+            if conn.is_broken() {
+                warn!("lost connection to RGPU daemon, will reconnect");
+                *conn_guard = None;
+            }
+            return result;
+        }
     }
 
-    fn try_connect(path: &str) -> Result<Self, String> {
-        #[cfg(unix)]
-        {
-            let stream = std::os::unix::net::UnixStream::connect(path)
-                .map_err(|e| format!("{}", e))?;
-            stream
-                .set_read_timeout(Some(std::time::Duration::from_secs(30)))
-                .ok();
-            Ok(Self { stream })
+    /// Connect to the daemon. A reconnect that fails holds off the next
+    /// attempt, failing requests until then; one that succeeds replays the
+    /// recorded state on the new daemon.
+    fn connect(&self) -> Result<IpcConnection, String> {
+        if !self.connected.load(Ordering::Relaxed) {
+            let conn = connect(&self.path)?;
+            self.connected.store(true, Ordering::Relaxed);
+            return Ok(conn);
         }
 
-        #[cfg(windows)]
-        {
-            let pipe = std::fs::OpenOptions::new()
-                .read(true)
-                .write(true)
-                .open(path)
-                .map_err(|e| format!("{}", e))?;
-            Ok(Self { pipe })
+        let mut backoff = self.reconnect.lock().map_err(|e| e.to_string())?;
+        if let Some(not_before) = backoff.not_before {
+            if Instant::now() < not_before {
+                return Err(format!("RGPU daemon at {} unavailable, retrying shortly", self.path));
+            }
+        }
+        match connect(&self.path) {
+            Ok(mut conn) => {
+                *backoff = Backoff::default();
+                self.query_cache.lock().map_err(|e| e.to_string())?.clear();
+                // The contexts an error left unusable are gone with the daemon
+                *self.sticky_error.lock().map_err(|e| e.to_string())? = None;
+                self.replay(&mut conn)?;
+                info!("reconnected to RGPU daemon at {}", self.path);
+                Ok(conn)
+            }
+            Err(e) => {
+                backoff.failed();
+                Err(e)
+            }
         }
     }
 
-    fn write_all(&mut self, data: &[u8]) -> Result<(), String> {
-        #[cfg(unix)]
-        {
-            self.stream
-                .write_all(data)
-                .map_err(|e| format!("IPC write error: {}", e))
+    /// Redo the recorded `cuInit` and device lookups on a new connection,
+    /// mapping each device handle the application holds to its new one.
+    fn replay(&self, conn: &mut IpcConnection) -> Result<(), String> {
+        let replay = self.replay.lock().map_err(|e| e.to_string())?.clone();
+        let mut remapped = self.remapped.lock().map_err(|e| e.to_string())?;
+        for (command, device) in replay {
+            let msg = Message::CudaCommand {
+                request_id: RequestId(self.next_request_id.fetch_add(1, Ordering::Relaxed)),
+                command: command.clone(),
+                deadline_ms: None,
+            };
+            conn.send(&msg)?;
+            match (conn.read_message()?, device) {
+                (
+                    Message::CudaResponse {
+                        response: CudaResponse::Device(new),
+                        ..
+                    },
+                    Some(old),
+                ) => {
+                    remapped.insert(old, new);
+                }
+                (Message::CudaResponse { response: CudaResponse::Success, .. }, None) => {}
+                (other, _) => warn!("replaying {:?} after reconnect failed: {:?}", command, other),
+            }
         }
+        Ok(())
+    }
 
-        #[cfg(windows)]
-        {
-            self.pipe
-                .write_all(data)
-                .map_err(|e| format!("IPC write error: {}", e))
+    /// Rewrite device handles from before a reconnect in `msg`'s commands.
+    fn remap(&self, msg: &mut Message) -> Result<(), String> {
+        let remapped = self.remapped.lock().map_err(|e| e.to_string())?;
+        if remapped.is_empty() {
+            return Ok(());
+        }
+        let mut remap = |cmd: &mut CudaCommand| {
+            cmd.handles_mut(|handle| {
+                if let Some(new) = remapped.get(handle) {
+                    *handle = *new;
+                }
+            })
+        };
+        match msg {
+            Message::CudaCommand { command, .. } | Message::CudaCommandStreamed { command, .. } => {
+                remap(command)
+            }
+            Message::CudaPipelined { batch, command, .. } => {
+                batch.iter_mut().for_each(&mut remap);
+                remap(command);
+            }
+            Message::CudaBatch(batch) => batch.iter_mut().for_each(remap),
+            _ => {}
         }
+        Ok(())
     }
+}
 
-    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), String> {
-        #[cfg(unix)]
-        {
-            self.stream
-                .read_exact(buf)
-                .map_err(|e| format!("IPC read error: {}", e))
-        }
+/// Connect to the daemon at `path` and set the connection up.
+fn connect(path: &str) -> Result<IpcConnection, String> {
+    let mut conn = IpcConnection::connect(path, "rgpu-cuda-interpose")?;
+    conn.restrict_devices()?;
+    conn.attach_shared_memory(SHARED_MEMORY_SIZE)?;
+    Ok(conn)
+}
 
-        #[cfg(windows)]
-        {
-            self.pipe
-                .read_exact(buf)
-                .map_err(|e| format!("IPC read error: {}", e))
-        }
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::ResourceType;
+    use rgpu_protocol::wire;
+    use std::io::{Read, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::mpsc;
+
+    fn device_handle(resource_id: u64) -> NetworkHandle {
+        NetworkHandle { server_id: 0, session_id: 1, resource_id, resource_type: ResourceType::CuDevice }
+    }
+
+    fn read_message(stream: &mut UnixStream) -> Option<Message> {
+        let mut header = [0u8; wire::HEADER_SIZE];
+        stream.read_exact(&mut header).ok()?;
+        let (flags, _, len) = wire::decode_header(&header).ok()?;
+        let mut payload = vec![0u8; len as usize];
+        stream.read_exact(&mut payload).ok()?;
+        wire::decode_message(&payload, flags).ok()
+    }
+
+    /// A memset the daemon fails: with 0xff as if a kernel before it had
+    /// crashed, with 0xfe as if given a bad value.
+    fn failing_memset(value: u8) -> CudaCommand {
+        CudaCommand::MemsetD8 { dst: NetworkHandle::null(), value, count: 1 }
     }
 
-    fn read_message(&mut self) -> Result<Message, String> {
-        let mut header_buf = [0u8; wire::HEADER_SIZE];
-        self.read_exact(&mut header_buf)?;
+    fn failure(batch: &[CudaCommand]) -> Option<CudaResponse> {
+        batch.iter().find_map(|cmd| match cmd {
+            CudaCommand::MemsetD8 { value: 0xff, .. } => {
+                Some(CudaResponse::Error { code: 719, message: "unspecified launch failure".to_string() })
+            }
+            CudaCommand::MemsetD8 { value: 0xfe, .. } => {
+                Some(CudaResponse::Error { code: 1, message: "invalid argument".to_string() })
+            }
+            _ => None,
+        })
+    }
 
-        let (flags, _stream_id, payload_len) =
-            wire::decode_header(&header_buf).map_err(|e| e.to_string())?;
+    /// Answer one connection's CUDA commands as a daemon would, with device
+    /// `device_id` named `name`, and return them. With `hang_up`, the
+    /// connection is closed when that says so, after the first name query.
+    fn serve(mut stream: UnixStream, device_id: u64, name: &str, hang_up: Option<mpsc::Receiver<()>>) -> Vec<CudaCommand> {
+        let mut commands = Vec::new();
+        while let Some(msg) = read_message(&mut stream) {
+            let (request_id, command, batch_failed) = match msg {
+                Message::Ping => {
+                    stream.write_all(&wire::encode_message(&Message::Pong, 0).unwrap()).unwrap();
+                    continue;
+                }
+                Message::CudaCommand { request_id, command, .. } => (request_id, command, None),
+                Message::CudaPipelined { request_id, batch, command } => {
+                    let failed = failure(&batch);
+                    commands.extend(batch);
+                    (request_id, command, failed)
+                }
+                Message::CudaBatch(batch) => {
+                    let response = failure(&batch).unwrap_or(CudaResponse::Success);
+                    commands.extend(batch);
+                    let reply = Message::CudaResponse { request_id: RequestId(0), response };
+                    stream.write_all(&wire::encode_message(&reply, 0).unwrap()).unwrap();
+                    continue;
+                }
+                // Build and shared memory queries go unanswered, as by an
+                // older daemon
+                _ => continue,
+            };
+            let response = match command {
+                _ if batch_failed.is_some() => batch_failed.unwrap(),
+                CudaCommand::DeviceGet { .. } => CudaResponse::Device(device_handle(device_id)),
+                CudaCommand::DeviceGetName { .. } => CudaResponse::DeviceName(name.to_string()),
+                _ => CudaResponse::Success,
+            };
+            let named = matches!(command, CudaCommand::DeviceGetName { .. });
+            commands.push(command);
+            let reply = Message::CudaResponse { request_id, response };
+            stream.write_all(&wire::encode_message(&reply, 0).unwrap()).unwrap();
+            if let (true, Some(hang_up)) = (named, &hang_up) {
+                let _ = hang_up.recv();
+                break;
+            }
+        }
+        commands
+    }
 
-        let mut payload = vec![0u8; payload_len as usize];
-        self.read_exact(&mut payload)?;
+    #[test]
+    fn test_reconnect_forgets_old_daemon_state() {
+        let path = std::env::temp_dir().join(format!("rgpu-ipc-client-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let (hang_up, hung_up) = mpsc::channel();
+        let (closed, wait_closed) = mpsc::channel();
+        let daemon = std::thread::spawn(move || {
+            serve(listener.accept().unwrap().0, 1, "first", Some(hung_up));
+            closed.send(()).unwrap();
+            serve(listener.accept().unwrap().0, 2, "second", None)
+        });
+
+        let client = IpcClient::new(path.to_str().unwrap());
+        let old = match client.send_command(CudaCommand::DeviceGet { ordinal: 0 }) {
+            Ok(CudaResponse::Device(handle)) => handle,
+            other => panic!("unexpected device lookup result: {:?}", other),
+        };
+        let name = |client: &IpcClient| match client.send_command(CudaCommand::DeviceGetName { device: old }) {
+            Ok(CudaResponse::DeviceName(name)) => name,
+            other => panic!("unexpected name query result: {:?}", other),
+        };
+        assert_eq!(name(&client), "first");
+
+        // The daemon restarts while a void command is queued for the old one
+        hang_up.send(()).unwrap();
+        wait_closed.recv().unwrap();
+        let memset = CudaCommand::MemsetD8 { dst: NetworkHandle::null(), value: 0, count: 1 };
+        assert!(matches!(client.send_command(memset), Ok(CudaResponse::Success)));
+        assert!(matches!(
+            client.send_command(CudaCommand::CtxSynchronize),
+            Ok(CudaResponse::Error { code: CUDA_ERROR_CONTEXT_IS_DESTROYED, .. })
+        ));
+
+        // The name is asked again, of the device the lookup now returns
+        assert_eq!(name(&client), "second");
+        drop(client);
+        let commands = daemon.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(
+            matches!(
+                commands.as_slice(),
+                [
+                    CudaCommand::DeviceGet { ordinal: 0 },
+                    CudaCommand::CtxSynchronize,
+                    CudaCommand::DeviceGetName { device },
+                ] if *device == device_handle(2)
+            ),
+            "new daemon got {:?}",
+            commands
+        );
+    }
 
-        wire::decode_message(&payload, flags).map_err(|e| e.to_string())
+    #[test]
+    fn test_queued_errors_are_reported_at_sync_points() {
+        let path = std::env::temp_dir().join(format!("rgpu-ipc-client-errors-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let daemon = std::thread::spawn(move || serve(listener.accept().unwrap().0, 1, "gpu", None));
+        let client = IpcClient::new(path.to_str().unwrap());
+        let sync = || client.send_command(CudaCommand::CtxSynchronize).unwrap();
+
+        // A full queue goes on its own, and its error waits for a sync point
+        // rather than going to the call that happened to fill it or to a
+        // call that doesn't synchronize
+        assert!(matches!(client.send_command(failing_memset(0xfe)), Ok(CudaResponse::Success)));
+        for _ in 1..PIPELINE_BATCH_SIZE {
+            assert!(matches!(client.send_command(failing_memset(0)), Ok(CudaResponse::Success)));
+        }
+        assert!(matches!(
+            client.send_command(CudaCommand::DeviceGetName { device: device_handle(1) }),
+            Ok(CudaResponse::DeviceName(_))
+        ));
+        assert!(matches!(sync(), CudaResponse::Error { code: 1, .. }));
+        assert!(matches!(sync(), CudaResponse::Success));
+
+        // A launch failure sticks until the context is destroyed
+        assert!(matches!(client.send_command(failing_memset(0xff)), Ok(CudaResponse::Success)));
+        assert!(matches!(sync(), CudaResponse::Error { code: 719, .. }));
+        assert!(matches!(sync(), CudaResponse::Error { code: 719, .. }));
+        assert!(matches!(client.send_command(failing_memset(0)), Ok(CudaResponse::Error { code: 719, .. })));
+        let destroy = CudaCommand::CtxDestroy { ctx: NetworkHandle::null() };
+        assert!(matches!(client.send_command(destroy), Ok(CudaResponse::Success)));
+        assert!(matches!(sync(), CudaResponse::Success));
+
+        drop(client);
+        let commands = daemon.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(commands.len(), PIPELINE_BATCH_SIZE + 7, "daemon got {:?}", commands);
     }
 }
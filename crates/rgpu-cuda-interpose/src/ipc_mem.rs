@@ -0,0 +1,46 @@
+//! CUDA IPC memory handles.
+//!
+//! `CUipcMemHandle` is 64 opaque bytes the application hands to another
+//! process. Ours carry the server's token for the export and the allocation
+//! it names, which routes the other process's open to the same server.
+
+use rgpu_protocol::cuda_commands::IpcMemHandle;
+use rgpu_protocol::handle::{NetworkHandle, ResourceType};
+
+const MAGIC: [u8; 8] = *b"RGPUIPC1";
+
+/// `CUipcMemHandle`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+pub struct CUipcMemHandle {
+    pub reserved: [u8; 64],
+}
+
+impl CUipcMemHandle {
+    pub(crate) fn new(handle: &IpcMemHandle) -> Self {
+        let mut reserved = [0; 64];
+        reserved[..8].copy_from_slice(&MAGIC);
+        reserved[8..10].copy_from_slice(&handle.memory.server_id.to_le_bytes());
+        reserved[10..14].copy_from_slice(&handle.memory.session_id.to_le_bytes());
+        reserved[14..22].copy_from_slice(&handle.memory.resource_id.to_le_bytes());
+        reserved[22..30].copy_from_slice(&handle.token.to_le_bytes());
+        Self { reserved }
+    }
+
+    /// The export this handle names, if it is one of ours.
+    pub(crate) fn handle(&self) -> Option<IpcMemHandle> {
+        let bytes = &self.reserved;
+        if bytes[..8] != MAGIC {
+            return None;
+        }
+        let memory = NetworkHandle {
+            server_id: u16::from_le_bytes(bytes[8..10].try_into().ok()?),
+            session_id: u32::from_le_bytes(bytes[10..14].try_into().ok()?),
+            resource_id: u64::from_le_bytes(bytes[14..22].try_into().ok()?),
+            resource_type: ResourceType::CuDevicePtr,
+        };
+        let token = u64::from_le_bytes(bytes[22..30].try_into().ok()?);
+        Some(IpcMemHandle { memory, token })
+    }
+}
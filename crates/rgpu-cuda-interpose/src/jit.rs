@@ -0,0 +1,253 @@
+//! JIT options of cuModuleLoadDataEx and the linker.
+//!
+//! Each option value sits in a pointer-sized slot: a scalar stored in the
+//! slot itself, or for the logs the application's buffer. Scalars go to the
+//! server as they are. The server compiles into log buffers of its own, of
+//! the sizes asked for, and the logs come back to be copied into the
+//! application's buffers along with the values the driver writes back (log
+//! sizes, wall time, threads per block).
+//!
+//! A linker's options stay in use until it is destroyed: the driver writes
+//! its logs through them on every cuLinkAdd* and on cuLinkComplete, so the
+//! application's arrays are kept here for the linker's lifetime.
+//!
+//! Options naming symbol tables in the application's memory can't be
+//! honoured remotely and are left out.
+
+use std::collections::HashMap;
+use std::ffi::{c_int, c_uint, c_void};
+
+use parking_lot::Mutex;
+
+use rgpu_protocol::cuda_commands::{jit_option, JitOutput};
+
+/// CU_JIT_THREADS_PER_BLOCK, in and out
+const THREADS_PER_BLOCK: c_int = 1;
+/// CU_JIT_WALL_TIME, out
+const WALL_TIME: c_int = 2;
+/// CU_JIT_GLOBAL_SYMBOL_NAMES through CU_JIT_GLOBAL_SYMBOL_COUNT
+const GLOBAL_SYMBOLS: std::ops::RangeInclusive<c_int> = 17..=19;
+/// CU_JIT_REFERENCED_KERNEL_NAMES through CU_JIT_REFERENCED_VARIABLE_COUNT
+const REFERENCED_NAMES: std::ops::RangeInclusive<c_int> = 25..=28;
+
+/// Options of the live linkers created with any, by linker id, with the
+/// application's value array
+static LINKERS: Mutex<Option<HashMap<u64, (JitOptions, usize)>>> = Mutex::new(None);
+
+/// Keep a new linker's options to write its logs through later.
+pub(crate) fn keep_linker(linker: u64, jit: JitOptions, values: *mut *mut c_void) {
+    if jit.options.is_empty() {
+        return;
+    }
+    LINKERS.lock().get_or_insert_with(HashMap::new).insert(linker, (jit, values as usize));
+}
+
+/// Write what the server's JIT compiler handed back through a linker's
+/// options.
+///
+/// # Safety
+/// The application's option arrays must still be valid, as the driver
+/// requires for the linker's lifetime.
+pub(crate) unsafe fn write_back_linker(linker: u64, output: &JitOutput) {
+    if let Some((jit, values)) = LINKERS.lock().as_ref().and_then(|linkers| linkers.get(&linker)) {
+        jit.write_back(*values as *mut *mut c_void, output);
+    }
+}
+
+pub(crate) fn forget_linker(linker: u64) {
+    if let Some(linkers) = LINKERS.lock().as_mut() {
+        linkers.remove(&linker);
+    }
+}
+
+/// The options of one call, as sent to the server.
+pub(crate) struct JitOptions {
+    /// Index in the application's arrays of each option sent
+    sent: Vec<usize>,
+    pub options: Vec<i32>,
+    pub values: Vec<u64>,
+}
+
+impl JitOptions {
+    /// Read the application's options.
+    ///
+    /// # Safety
+    /// `options` and `values` must point to `num_options` entries each, or
+    /// `num_options` must be 0.
+    pub(crate) unsafe fn read(num_options: c_uint, options: *const c_int, values: *const *mut c_void) -> Self {
+        let mut jit = Self { sent: Vec::new(), options: Vec::new(), values: Vec::new() };
+        if num_options == 0 || options.is_null() || values.is_null() {
+            return jit;
+        }
+        for i in 0..num_options as usize {
+            let option = *options.add(i);
+            if GLOBAL_SYMBOLS.contains(&option) || REFERENCED_NAMES.contains(&option) {
+                continue;
+            }
+            let value = match option {
+                // Buffers stay here; the server uses its own
+                jit_option::INFO_LOG_BUFFER | jit_option::ERROR_LOG_BUFFER => 0,
+                _ => *values.add(i) as usize as u64,
+            };
+            jit.sent.push(i);
+            jit.options.push(option);
+            jit.values.push(value);
+        }
+        jit
+    }
+
+    /// The requested size of a log, from its size option.
+    fn log_capacity(&self, size_option: i32) -> usize {
+        self.options
+            .iter()
+            .zip(&self.values)
+            .find(|(option, _)| **option == size_option)
+            .map_or(0, |(_, size)| *size as usize)
+    }
+
+    /// Copy the logs into the application's buffers and write back the
+    /// values the driver changed.
+    ///
+    /// # Safety
+    /// As for [`JitOptions::read`], with the same arrays.
+    pub(crate) unsafe fn write_back(&self, values: *mut *mut c_void, output: &JitOutput) {
+        if values.is_null() {
+            return;
+        }
+        for (k, &i) in self.sent.iter().enumerate() {
+            let slot = values.add(i);
+            match self.options[k] {
+                jit_option::INFO_LOG_BUFFER => {
+                    copy_log(*slot, self.log_capacity(jit_option::INFO_LOG_BUFFER_SIZE_BYTES), &output.info_log);
+                }
+                jit_option::ERROR_LOG_BUFFER => {
+                    copy_log(*slot, self.log_capacity(jit_option::ERROR_LOG_BUFFER_SIZE_BYTES), &output.error_log);
+                }
+                THREADS_PER_BLOCK
+                | WALL_TIME
+                | jit_option::INFO_LOG_BUFFER_SIZE_BYTES
+                | jit_option::ERROR_LOG_BUFFER_SIZE_BYTES => {
+                    if let Some(value) = output.option_values.get(k) {
+                        *slot = *value as usize as *mut c_void;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Copy `log` into a buffer of `capacity` bytes, NUL-terminated.
+unsafe fn copy_log(buffer: *mut c_void, capacity: usize, log: &[u8]) {
+    if buffer.is_null() || capacity == 0 {
+        return;
+    }
+    let len = log.len().min(capacity - 1);
+    std::ptr::copy_nonoverlapping(log.as_ptr(), buffer as *mut u8, len);
+    *(buffer as *mut u8).add(len) = 0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CU_JIT_MAX_REGISTERS, in only
+    const MAX_REGISTERS: c_int = 0;
+
+    #[test]
+    fn test_options_are_read_without_buffers_or_symbols() {
+        let mut info_log = [0u8; 16];
+        let options = [
+            MAX_REGISTERS,
+            jit_option::INFO_LOG_BUFFER,
+            jit_option::INFO_LOG_BUFFER_SIZE_BYTES,
+            // CU_JIT_GLOBAL_SYMBOL_NAMES
+            17,
+            WALL_TIME,
+        ];
+        let values = [
+            32usize as *mut c_void,
+            info_log.as_mut_ptr() as *mut c_void,
+            16usize as *mut c_void,
+            0x1234 as *mut c_void,
+            std::ptr::null_mut(),
+        ];
+
+        let jit = unsafe { JitOptions::read(options.len() as c_uint, options.as_ptr(), values.as_ptr()) };
+        // The symbol table is left out, and the log buffer's address stays here.
+        assert_eq!(
+            jit.options,
+            [MAX_REGISTERS, jit_option::INFO_LOG_BUFFER, jit_option::INFO_LOG_BUFFER_SIZE_BYTES, WALL_TIME]
+        );
+        assert_eq!(jit.values, [32, 0, 16, 0]);
+        assert_eq!(jit.sent, [0, 1, 2, 4]);
+
+        let none = unsafe { JitOptions::read(0, std::ptr::null(), std::ptr::null()) };
+        assert!(none.options.is_empty());
+    }
+
+    #[test]
+    fn test_logs_and_values_are_written_back() {
+        let mut info_log = [0xffu8; 8];
+        let mut error_log = [0xffu8; 32];
+        let options = [
+            jit_option::INFO_LOG_BUFFER,
+            jit_option::INFO_LOG_BUFFER_SIZE_BYTES,
+            jit_option::ERROR_LOG_BUFFER,
+            jit_option::ERROR_LOG_BUFFER_SIZE_BYTES,
+            MAX_REGISTERS,
+            WALL_TIME,
+        ];
+        let mut values = [
+            info_log.as_mut_ptr() as *mut c_void,
+            8usize as *mut c_void,
+            error_log.as_mut_ptr() as *mut c_void,
+            32usize as *mut c_void,
+            64usize as *mut c_void,
+            std::ptr::null_mut(),
+        ];
+        let jit = unsafe { JitOptions::read(options.len() as c_uint, options.as_ptr(), values.as_ptr()) };
+
+        let output = JitOutput {
+            option_values: vec![0, 20, 0, 0, 64, 5],
+            info_log: b"12 registers used".to_vec(),
+            error_log: Vec::new(),
+        };
+        unsafe { jit.write_back(values.as_mut_ptr(), &output) };
+
+        // The info log is cut to fit its buffer, NUL included.
+        assert_eq!(&info_log, b"12 regi\0");
+        assert_eq!(error_log[0], 0);
+        assert_eq!(values[1] as usize, 20);
+        assert_eq!(values[3] as usize, 0);
+        // Input-only options are left as the application set them.
+        assert_eq!(values[4] as usize, 64);
+        assert_eq!(values[5] as usize, 5);
+        assert_eq!(values[0], info_log.as_mut_ptr() as *mut c_void);
+    }
+
+    #[test]
+    fn test_linker_logs_go_through_the_kept_options() {
+        const LINKER: u64 = 0x5150;
+        let mut error_log = [0xffu8; 16];
+        let options = [jit_option::ERROR_LOG_BUFFER, jit_option::ERROR_LOG_BUFFER_SIZE_BYTES];
+        let mut values = [error_log.as_mut_ptr() as *mut c_void, 16usize as *mut c_void];
+        let jit = unsafe { JitOptions::read(options.len() as c_uint, options.as_ptr(), values.as_ptr()) };
+        keep_linker(LINKER, jit, values.as_mut_ptr());
+
+        let output = JitOutput {
+            option_values: vec![0, 9],
+            info_log: Vec::new(),
+            error_log: b"undefined".to_vec(),
+        };
+        unsafe { write_back_linker(LINKER, &output) };
+        assert_eq!(&error_log[..10], b"undefined\0");
+        assert_eq!(values[1] as usize, 9);
+
+        // Once the linker is destroyed, nothing is written.
+        forget_linker(LINKER);
+        error_log = [0xff; 16];
+        unsafe { write_back_linker(LINKER, &output) };
+        assert_eq!(error_log, [0xff; 16]);
+    }
+}
@@ -8,8 +8,21 @@
 //! - Linux: LD_PRELOAD=librgpu_cuda_interpose.so <application>
 //! - Windows: Place as nvcuda.dll in the application's directory
 
+// Exported entry points follow the CUDA driver API's own safety contract.
+#![allow(clippy::missing_safety_doc)]
+
+#[macro_use]
+mod passthrough;
 mod ipc_client;
 pub mod handle_store;
+mod memcpy3d;
+mod array;
+mod texture;
+mod texref;
+mod ipc_mem;
+mod host_mem;
+mod managed;
+mod jit;
 pub mod error;
 pub mod proc_address;
 pub mod stubs;
@@ -17,12 +30,17 @@ pub mod stubs;
 use std::ffi::{c_char, c_int, c_uint, c_void};
 use std::sync::OnceLock;
 
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 
-use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse, KernelParam};
+use rgpu_protocol::codec::TransferCodec;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse, KernelParam, TexRefSetting};
 use rgpu_protocol::handle::{NetworkHandle, ResourceType};
 
 use ipc_client::IpcClient;
+use array::{CUDA_ARRAY3D_DESCRIPTOR, CUDA_ARRAY_DESCRIPTOR};
+use memcpy3d::{CUDA_MEMCPY2D, CUDA_MEMCPY3D};
+use texture::{CUDA_RESOURCE_DESC, CUDA_RESOURCE_VIEW_DESC, CUDA_TEXTURE_DESC};
+use ipc_mem::CUipcMemHandle;
 
 // CUDA types
 type CUresult = c_int;
@@ -35,12 +53,23 @@ type CUstream = *mut c_void;
 type CUevent = *mut c_void;
 type CUlinkState = *mut c_void;
 type CUmemoryPool = *mut c_void;
+type CUgraph = *mut c_void;
+type CUgraphExec = *mut c_void;
+type CUtexObject = u64;
+type CUsurfObject = u64;
+type CUarray = *mut c_void;
+type CUtexref = *mut c_void;
+type CUsurfref = *mut c_void;
 
 const CUDA_SUCCESS: CUresult = 0;
 const CUDA_ERROR_INVALID_VALUE: CUresult = 1;
+const CUDA_ERROR_OUT_OF_MEMORY: CUresult = 2;
 const _CUDA_ERROR_NOT_INITIALIZED: CUresult = 3;
 const CUDA_ERROR_NOT_READY: CUresult = 600;
-const _CUDA_ERROR_NOT_SUPPORTED: CUresult = 801;
+const CUDA_ERROR_HOST_MEMORY_ALREADY_REGISTERED: CUresult = 712;
+const CUDA_ERROR_HOST_MEMORY_NOT_REGISTERED: CUresult = 713;
+const CUDA_ERROR_NOT_SUPPORTED: CUresult = 801;
+const CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED: CUresult = 900;
 const CUDA_ERROR_UNKNOWN: CUresult = 999;
 
 static IPC_CLIENT: OnceLock<IpcClient> = OnceLock::new();
@@ -52,7 +81,11 @@ fn get_client() -> &'static IpcClient {
     })
 }
 
-fn send_cuda_command(cmd: CudaCommand) -> CudaResponse {
+fn send_cuda_command(mut cmd: CudaCommand) -> CudaResponse {
+    if handle_store::has_pending_readbacks() {
+        unsafe { complete_readbacks_before(&mut cmd) };
+    }
+    managed::before_command(&mut cmd);
     let client = get_client();
     match client.send_command(cmd) {
         Ok(resp) => resp,
@@ -66,6 +99,75 @@ fn send_cuda_command(cmd: CudaCommand) -> CudaResponse {
     }
 }
 
+/// The `CUdevice` to hand out for a device the daemon returned: under local
+/// passthrough, the real driver's own for a local GPU, else a stored ID.
+fn device_id(handle: NetworkHandle) -> CUdevice {
+    if handle.server_id == passthrough::LOCAL_SERVER_ID && passthrough::local_active() {
+        if let CudaResponse::DevicePCIBusId(bus_id) = send_cuda_command(CudaCommand::DeviceGetPCIBusId { device: handle }) {
+            if let Some(device) = passthrough::local_device(&bus_id) {
+                debug!("local GPU {} driven through the real driver as device {}", bus_id, device);
+                return device;
+            }
+        }
+    }
+    handle_store::store_device(handle) as CUdevice
+}
+
+/// Uploads at least this large are checked for a repeated fill pattern.
+const FILL_DETECT_MIN_BYTES: usize = 64 * 1024;
+
+/// HtoD command for `dst`: a typed fill if the data repeats a short pattern,
+/// otherwise a copy, encoded if the allocation has a transfer codec.
+fn htod_command(dst_id: CUdeviceptr, dst: NetworkHandle, src_data: Vec<u8>) -> CudaCommand {
+    let byte_count = src_data.len() as u64;
+    if src_data.len() >= FILL_DETECT_MIN_BYTES {
+        if let Some(pattern) = rgpu_protocol::fill::detect_pattern(&src_data) {
+            return CudaCommand::FillBufferTyped {
+                dst,
+                count: byte_count / pattern.len() as u64,
+                pattern: pattern.to_vec(),
+            };
+        }
+    }
+    match handle_store::get_transfer_codec(dst_id) {
+        Some(codec) => CudaCommand::MemcpyHtoDEncoded {
+            dst,
+            codec,
+            data: codec.encode(&src_data),
+            byte_count,
+        },
+        None => CudaCommand::MemcpyHtoD {
+            dst,
+            src_data,
+            byte_count,
+        },
+    }
+}
+
+/// DtoH copies at least this large are streamed from the daemon in chunks
+/// written straight into the caller's buffer.
+const STREAM_MIN_BYTES: usize = 16 * 1024 * 1024;
+
+/// Chunk size for streamed DtoH copies.
+const STREAM_CHUNK_BYTES: u32 = 4 * 1024 * 1024;
+
+/// Turn an encoded DtoH response back into plain `MemoryData`.
+fn decode_memory_data(response: CudaResponse, byte_count: usize) -> CudaResponse {
+    match response {
+        CudaResponse::EncodedMemoryData { codec, data } => match codec.decode(&data, byte_count) {
+            Ok(data) => CudaResponse::MemoryData(data),
+            Err(e) => {
+                error!("{:?} transfer decode failed: {}", codec, e);
+                CudaResponse::Error {
+                    code: CUDA_ERROR_UNKNOWN,
+                    message: e.to_string(),
+                }
+            }
+        },
+        other => other,
+    }
+}
+
 /// Create a null/default NetworkHandle for stream references (NULL stream = default).
 fn null_stream_handle() -> NetworkHandle {
     NetworkHandle {
@@ -84,12 +186,103 @@ pub extern "C" fn rgpu_interpose_marker() -> c_int {
     1
 }
 
+// ── RGPU Extensions ─────────────────────────────────────────────────
+
+/// Plain transfers (the default).
+pub const RGPU_TRANSFER_HINT_NONE: c_uint = 0;
+/// fp16 or bf16 tensor.
+pub const RGPU_TRANSFER_HINT_FLOAT16: c_uint = 1;
+/// fp32 tensor.
+pub const RGPU_TRANSFER_HINT_FLOAT32: c_uint = 2;
+/// Mostly-zero tensor of 16-bit elements.
+pub const RGPU_TRANSFER_HINT_SPARSE16: c_uint = 3;
+/// Mostly-zero tensor of 32-bit elements.
+pub const RGPU_TRANSFER_HINT_SPARSE32: c_uint = 4;
+/// Interleaved 8-bit RGB image.
+pub const RGPU_TRANSFER_HINT_IMAGE_RGB8: c_uint = 5;
+/// Interleaved 8-bit RGBA image.
+pub const RGPU_TRANSFER_HINT_IMAGE_RGBA8: c_uint = 6;
+
+/// Tell RGPU what an allocation holds so copies to and from it can use a
+/// domain-specific transfer codec. Look it up with dlsym/GetProcAddress; it
+/// only exists when running under RGPU.
+#[no_mangle]
+pub unsafe extern "C" fn rgpuMemSetTransferHint(dptr: CUdeviceptr, hint: c_uint) -> CUresult {
+    if passthrough::active() {
+        return CUDA_ERROR_NOT_SUPPORTED;
+    }
+    let codec = match hint {
+        RGPU_TRANSFER_HINT_NONE => None,
+        RGPU_TRANSFER_HINT_FLOAT16 => Some(TransferCodec::Shuffle { element_size: 2 }),
+        RGPU_TRANSFER_HINT_FLOAT32 => Some(TransferCodec::Shuffle { element_size: 4 }),
+        RGPU_TRANSFER_HINT_SPARSE16 => Some(TransferCodec::Sparse { element_size: 2 }),
+        RGPU_TRANSFER_HINT_SPARSE32 => Some(TransferCodec::Sparse { element_size: 4 }),
+        RGPU_TRANSFER_HINT_IMAGE_RGB8 => Some(TransferCodec::Delta { stride: 3 }),
+        RGPU_TRANSFER_HINT_IMAGE_RGBA8 => Some(TransferCodec::Delta { stride: 4 }),
+        _ => return CUDA_ERROR_INVALID_VALUE,
+    };
+    let net_handle = match handle_store::get_mem_by_ptr(dptr) {
+        Some(h) => h,
+        None => return CUDA_ERROR_INVALID_VALUE,
+    };
+
+    debug!("rgpuMemSetTransferHint(0x{:x}, {:?})", dptr, codec);
+
+    match send_cuda_command(CudaCommand::MemSetTransferCodec {
+        dptr: net_handle,
+        codec,
+    }) {
+        CudaResponse::Success => {
+            handle_store::set_transfer_codec(dptr, codec);
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+/// Fill `count` elements from `dptr` with a `pattern_size`-byte value (2, 4,
+/// 8 or 16 bytes; e.g. an fp16/bf16 constant or a float4). Only the pattern
+/// crosses the network. Look it up with dlsym/GetProcAddress.
+#[no_mangle]
+pub unsafe extern "C" fn rgpuMemsetTyped(
+    dptr: CUdeviceptr,
+    pattern: *const c_void,
+    pattern_size: usize,
+    count: usize,
+) -> CUresult {
+    if passthrough::active() {
+        return CUDA_ERROR_NOT_SUPPORTED;
+    }
+    if pattern.is_null() || !rgpu_protocol::fill::PATTERN_SIZES.contains(&pattern_size) {
+        return CUDA_ERROR_INVALID_VALUE;
+    }
+    let net_handle = match handle_store::get_mem_by_ptr(dptr) {
+        Some(h) => h,
+        None => return CUDA_ERROR_INVALID_VALUE,
+    };
+    let pattern = std::slice::from_raw_parts(pattern as *const u8, pattern_size).to_vec();
+
+    debug!("rgpuMemsetTyped(0x{:x}, {} bytes x {})", dptr, pattern_size, count);
+
+    match send_cuda_command(CudaCommand::FillBufferTyped {
+        dst: net_handle,
+        pattern,
+        count: count as u64,
+    }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
 // ── Exported CUDA Driver API Functions ──────────────────────────────
 
 // ── Initialization ──────────────────────────────────────────────────
 
 #[no_mangle]
 pub unsafe extern "C" fn cuInit(flags: c_uint) -> CUresult {
+    forward!(cuInit(flags));
     // Initialize logging on first call
     let _ = tracing_subscriber::fmt()
         .with_env_filter(
@@ -101,7 +294,7 @@ pub unsafe extern "C" fn cuInit(flags: c_uint) -> CUresult {
     info!("cuInit(flags={})", flags);
 
     match send_cuda_command(CudaCommand::Init {
-        flags: flags as u32,
+        flags,
     }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
@@ -111,6 +304,7 @@ pub unsafe extern "C" fn cuInit(flags: c_uint) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDriverGetVersion(version: *mut c_int) -> CUresult {
+    forward!(cuDriverGetVersion(version));
     if version.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -129,6 +323,7 @@ pub unsafe extern "C" fn cuDriverGetVersion(version: *mut c_int) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGetCount(count: *mut c_int) -> CUresult {
+    forward!(cuDeviceGetCount(count));
     if count.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -146,14 +341,14 @@ pub unsafe extern "C" fn cuDeviceGetCount(count: *mut c_int) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGet(device: *mut CUdevice, ordinal: c_int) -> CUresult {
+    forward!(cuDeviceGet(device, ordinal));
     if device.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
 
     match send_cuda_command(CudaCommand::DeviceGet { ordinal }) {
         CudaResponse::Device(handle) => {
-            let local_id = handle_store::store_device(handle);
-            *device = local_id as CUdevice;
+            *device = device_id(handle);
             CUDA_SUCCESS
         }
         CudaResponse::Error { code, .. } => code,
@@ -167,6 +362,7 @@ pub unsafe extern "C" fn cuDeviceGetName(
     len: c_int,
     device: CUdevice,
 ) -> CUresult {
+    forward!(cuDeviceGetName(name, len, device));
     if name.is_null() || len <= 0 {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -197,6 +393,7 @@ pub unsafe extern "C" fn cuDeviceGetAttribute(
     attrib: c_int,
     device: CUdevice,
 ) -> CUresult {
+    forward!(cuDeviceGetAttribute(pi, attrib, device));
     if pi.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -221,6 +418,7 @@ pub unsafe extern "C" fn cuDeviceGetAttribute(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceTotalMem_v2(bytes: *mut u64, device: CUdevice) -> CUresult {
+    forward!(cuDeviceTotalMem_v2(bytes, device));
     if bytes.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -248,6 +446,7 @@ pub unsafe extern "C" fn cuDeviceComputeCapability(
     minor: *mut c_int,
     device: CUdevice,
 ) -> CUresult {
+    forward!(cuDeviceComputeCapability(major, minor, device));
     if major.is_null() || minor.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -273,6 +472,61 @@ pub unsafe extern "C" fn cuDeviceComputeCapability(
     }
 }
 
+/// `CUdevprop`, of the deprecated cuDeviceGetProperties
+#[repr(C)]
+#[derive(Default)]
+pub struct CUdevprop {
+    pub max_threads_per_block: c_int,
+    pub max_threads_dim: [c_int; 3],
+    pub max_grid_size: [c_int; 3],
+    pub shared_mem_per_block: c_int,
+    pub total_constant_memory: c_int,
+    pub simd_width: c_int,
+    pub mem_pitch: c_int,
+    pub regs_per_block: c_int,
+    pub clock_rate: c_int,
+    pub texture_align: c_int,
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuDeviceGetProperties(prop: *mut CUdevprop, device: CUdevice) -> CUresult {
+    forward!(cuDeviceGetProperties(prop, device));
+    if prop.is_null() {
+        return CUDA_ERROR_INVALID_VALUE;
+    }
+    let dev_handle = match handle_store::get_device(device as u64) {
+        Some(h) => h,
+        None => return CUDA_ERROR_INVALID_VALUE,
+    };
+    let attribute = |attrib: c_int| match send_cuda_command(CudaCommand::DeviceGetAttribute { attrib, device: dev_handle }) {
+        CudaResponse::DeviceAttribute(val) => Ok(val),
+        CudaResponse::Error { code, .. } => Err(code),
+        _ => Err(CUDA_ERROR_UNKNOWN),
+    };
+    // CU_DEVICE_ATTRIBUTE_* of each field
+    let read = || -> Result<CUdevprop, CUresult> {
+        Ok(CUdevprop {
+            max_threads_per_block: attribute(1)?,
+            max_threads_dim: [attribute(2)?, attribute(3)?, attribute(4)?],
+            max_grid_size: [attribute(5)?, attribute(6)?, attribute(7)?],
+            shared_mem_per_block: attribute(8)?,
+            total_constant_memory: attribute(9)?,
+            simd_width: attribute(10)?,
+            mem_pitch: attribute(11)?,
+            regs_per_block: attribute(12)?,
+            clock_rate: attribute(13)?,
+            texture_align: attribute(14)?,
+        })
+    };
+    match read() {
+        Ok(props) => {
+            *prop = props;
+            CUDA_SUCCESS
+        }
+        Err(code) => code,
+    }
+}
+
 // ── Context Management ──────────────────────────────────────────────
 
 #[no_mangle]
@@ -281,6 +535,7 @@ pub unsafe extern "C" fn cuCtxCreate_v2(
     flags: c_uint,
     dev: CUdevice,
 ) -> CUresult {
+    forward!(cuCtxCreate_v2(pctx, flags, dev));
     if pctx.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -293,7 +548,7 @@ pub unsafe extern "C" fn cuCtxCreate_v2(
     };
 
     match send_cuda_command(CudaCommand::CtxCreate {
-        flags: flags as u32,
+        flags,
         device: dev_handle,
     }) {
         CudaResponse::Context(handle) => {
@@ -308,6 +563,7 @@ pub unsafe extern "C" fn cuCtxCreate_v2(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxDestroy_v2(ctx: CUcontext) -> CUresult {
+    forward!(cuCtxDestroy_v2(ctx));
     let local_id = ctx as u64;
     let net_handle = match handle_store::get_ctx(local_id) {
         Some(h) => h,
@@ -328,6 +584,7 @@ pub unsafe extern "C" fn cuCtxDestroy_v2(ctx: CUcontext) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxSetCurrent(ctx: CUcontext) -> CUresult {
+    forward!(cuCtxSetCurrent(ctx));
     let local_id = ctx as u64;
     let net_handle = match handle_store::get_ctx(local_id) {
         Some(h) => h,
@@ -345,6 +602,7 @@ pub unsafe extern "C" fn cuCtxSetCurrent(ctx: CUcontext) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxGetCurrent(pctx: *mut CUcontext) -> CUresult {
+    forward!(cuCtxGetCurrent(pctx));
     if pctx.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -362,8 +620,13 @@ pub unsafe extern "C" fn cuCtxGetCurrent(pctx: *mut CUcontext) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxSynchronize() -> CUresult {
+    forward!(cuCtxSynchronize());
     match send_cuda_command(CudaCommand::CtxSynchronize) {
-        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Success => {
+            host_mem::read_back_mapped();
+            managed::read_back();
+            complete_readbacks(|_| true)
+        }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
@@ -376,6 +639,7 @@ pub unsafe extern "C" fn cuModuleLoadData(
     module: *mut CUmodule,
     image: *const c_void,
 ) -> CUresult {
+    forward!(cuModuleLoadData(module, image));
     if module.is_null() || image.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -397,6 +661,7 @@ pub unsafe extern "C" fn cuModuleLoadData(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuModuleUnload(hmod: CUmodule) -> CUresult {
+    forward!(cuModuleUnload(hmod));
     let local_id = hmod as u64;
     let net_handle = match handle_store::get_mod(local_id) {
         Some(h) => h,
@@ -408,6 +673,7 @@ pub unsafe extern "C" fn cuModuleUnload(hmod: CUmodule) -> CUresult {
     match send_cuda_command(CudaCommand::ModuleUnload { module: net_handle }) {
         CudaResponse::Success => {
             handle_store::remove_mod(local_id);
+            texref::remove_module(local_id);
             CUDA_SUCCESS
         }
         CudaResponse::Error { code, .. } => code,
@@ -421,6 +687,7 @@ pub unsafe extern "C" fn cuModuleGetFunction(
     hmod: CUmodule,
     name: *const c_char,
 ) -> CUresult {
+    forward!(cuModuleGetFunction(hfunc, hmod, name));
     if hfunc.is_null() || name.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -441,7 +708,12 @@ pub unsafe extern "C" fn cuModuleGetFunction(
         name: func_name,
     }) {
         CudaResponse::Function(handle) => {
-            let local_id = handle_store::store_func(handle);
+            let local_id = handle_store::store_func(handle, None);
+            *hfunc = local_id as CUfunction;
+            CUDA_SUCCESS
+        }
+        CudaResponse::FunctionWithParams { handle, param_sizes } => {
+            let local_id = handle_store::store_func(handle, param_sizes);
             *hfunc = local_id as CUfunction;
             CUDA_SUCCESS
         }
@@ -454,6 +726,7 @@ pub unsafe extern "C" fn cuModuleGetFunction(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemAlloc_v2(dptr: *mut CUdeviceptr, bytesize: usize) -> CUresult {
+    forward!(cuMemAlloc_v2(dptr, bytesize));
     if dptr.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -476,6 +749,7 @@ pub unsafe extern "C" fn cuMemAlloc_v2(dptr: *mut CUdeviceptr, bytesize: usize)
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemFree_v2(dptr: CUdeviceptr) -> CUresult {
+    forward!(cuMemFree_v2(dptr));
     let net_handle = match handle_store::get_mem_by_ptr(dptr) {
         Some(h) => h,
         None => {
@@ -486,6 +760,7 @@ pub unsafe extern "C" fn cuMemFree_v2(dptr: CUdeviceptr) -> CUresult {
     match send_cuda_command(CudaCommand::MemFree { dptr: net_handle }) {
         CudaResponse::Success => {
             handle_store::remove_mem(dptr);
+            managed::free(dptr);
             CUDA_SUCCESS
         }
         CudaResponse::Error { code, .. } => code,
@@ -499,6 +774,7 @@ pub unsafe extern "C" fn cuMemcpyHtoD_v2(
     src_host: *const c_void,
     byte_count: usize,
 ) -> CUresult {
+    forward!(cuMemcpyHtoD_v2(dst_device, src_host, byte_count));
     if src_host.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -514,11 +790,7 @@ pub unsafe extern "C" fn cuMemcpyHtoD_v2(
 
     debug!("cuMemcpyHtoD_v2({} bytes)", byte_count);
 
-    match send_cuda_command(CudaCommand::MemcpyHtoD {
-        dst: net_dst,
-        src_data,
-        byte_count: byte_count as u64,
-    }) {
+    match send_cuda_command(htod_command(dst_device, net_dst, src_data)) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -531,6 +803,7 @@ pub unsafe extern "C" fn cuMemcpyDtoH_v2(
     src_device: CUdeviceptr,
     byte_count: usize,
 ) -> CUresult {
+    forward!(cuMemcpyDtoH_v2(dst_host, src_device, byte_count));
     if dst_host.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -544,10 +817,31 @@ pub unsafe extern "C" fn cuMemcpyDtoH_v2(
 
     debug!("cuMemcpyDtoH_v2({} bytes)", byte_count);
 
-    match send_cuda_command(CudaCommand::MemcpyDtoH {
+    let cmd = CudaCommand::MemcpyDtoH {
         src: net_src,
         byte_count: byte_count as u64,
-    }) {
+    };
+    // Encoded responses have to be decoded as a whole, so only plain copies
+    // are streamed.
+    let response = if byte_count >= STREAM_MIN_BYTES
+        && handle_store::get_transfer_codec(src_device).is_none()
+    {
+        let dst = std::slice::from_raw_parts_mut(dst_host as *mut u8, byte_count);
+        match get_client().send_command_streamed(cmd, dst, STREAM_CHUNK_BYTES) {
+            Ok(resp) => resp,
+            Err(e) => {
+                error!("IPC error: {}", e);
+                CudaResponse::Error {
+                    code: CUDA_ERROR_UNKNOWN,
+                    message: e,
+                }
+            }
+        }
+    } else {
+        send_cuda_command(cmd)
+    };
+    match decode_memory_data(response, byte_count) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::MemoryData(data) => {
             let copy_len = std::cmp::min(data.len(), byte_count);
             std::ptr::copy_nonoverlapping(data.as_ptr(), dst_host as *mut u8, copy_len);
@@ -560,6 +854,69 @@ pub unsafe extern "C" fn cuMemcpyDtoH_v2(
 
 // ── Execution Control ───────────────────────────────────────────────
 
+/// Copy a launch's arguments. With the parameter sizes the server reported
+/// for the function, each argument is copied at its true size; otherwise
+/// (older servers, images the server couldn't parse) every argument is taken
+/// to be 8 bytes and the array to end at a null pointer, which is right for
+/// kernels taking only pointers and 64-bit scalars.
+///
+/// 8-byte arguments that point into an allocation are sent as the
+/// allocation's handle and the offset into it, for the server to replace
+/// with the real address.
+unsafe fn collect_kernel_params(local_func_id: u64, kernel_params: *mut *mut c_void) -> Vec<KernelParam> {
+    let mut params = Vec::new();
+    if kernel_params.is_null() {
+        return params;
+    }
+    if let Some(sizes) = handle_store::get_func_param_sizes(local_func_id) {
+        for (i, &size) in sizes.iter().enumerate() {
+            let param_ptr = *kernel_params.add(i);
+            let data = if param_ptr.is_null() {
+                vec![0; size as usize]
+            } else {
+                std::slice::from_raw_parts(param_ptr as *const u8, size as usize).to_vec()
+            };
+            params.push(kernel_param(data));
+        }
+        return params;
+    }
+    let mut i = 0;
+    loop {
+        let param_ptr = *kernel_params.add(i);
+        if param_ptr.is_null() {
+            break;
+        }
+        let data =
+            std::slice::from_raw_parts(param_ptr as *const u8, std::mem::size_of::<u64>())
+                .to_vec();
+        params.push(kernel_param(data));
+        i += 1;
+        if i >= 256 {
+            break;
+        }
+    }
+    params
+}
+
+fn kernel_param(data: Vec<u8>) -> KernelParam {
+    if let Ok(bytes) = <[u8; 8]>::try_from(data.as_slice()) {
+        let value = u64::from_le_bytes(bytes);
+        if let Some((handle, offset)) = handle_store::resolve_device_ptr(value) {
+            return KernelParam {
+                data: offset.to_le_bytes().to_vec(),
+                device_ptr: Some(handle),
+            };
+        }
+        if let Some(handle) = texture::handle(value) {
+            return KernelParam {
+                data: 0u64.to_le_bytes().to_vec(),
+                device_ptr: Some(handle),
+            };
+        }
+    }
+    KernelParam { data, device_ptr: None }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn cuLaunchKernel(
     f: CUfunction,
@@ -574,6 +931,7 @@ pub unsafe extern "C" fn cuLaunchKernel(
     kernel_params: *mut *mut c_void,
     _extra: *mut *mut c_void,
 ) -> CUresult {
+    forward!(cuLaunchKernel(f, grid_dim_x, grid_dim_y, grid_dim_z, block_dim_x, block_dim_y, block_dim_z, shared_mem_bytes, hstream, kernel_params, _extra));
     let local_func_id = f as u64;
     let net_func = match handle_store::get_func(local_func_id) {
         Some(h) => h,
@@ -593,24 +951,8 @@ pub unsafe extern "C" fn cuLaunchKernel(
         }
     };
 
-    let mut params = Vec::new();
-    if !kernel_params.is_null() {
-        let mut i = 0;
-        loop {
-            let param_ptr = *kernel_params.add(i);
-            if param_ptr.is_null() {
-                break;
-            }
-            let data =
-                std::slice::from_raw_parts(param_ptr as *const u8, std::mem::size_of::<u64>())
-                    .to_vec();
-            params.push(KernelParam { data });
-            i += 1;
-            if i >= 256 {
-                break;
-            }
-        }
-    }
+    let params = collect_kernel_params(local_func_id, kernel_params);
+    host_mem::write_back_mapped();
 
     debug!(
         "cuLaunchKernel(grid=[{}x{}x{}], block=[{}x{}x{}], shared={}, params={})",
@@ -636,12 +978,13 @@ pub unsafe extern "C" fn cuLaunchKernel(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamCreate(phstream: *mut CUstream, flags: c_uint) -> CUresult {
+    forward!(cuStreamCreate(phstream, flags));
     if phstream.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
 
     match send_cuda_command(CudaCommand::StreamCreate {
-        flags: flags as u32,
+        flags,
     }) {
         CudaResponse::Stream(handle) => {
             let local_id = handle_store::store_stream(handle);
@@ -656,6 +999,7 @@ pub unsafe extern "C" fn cuStreamCreate(phstream: *mut CUstream, flags: c_uint)
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamDestroy_v2(hstream: CUstream) -> CUresult {
+    forward!(cuStreamDestroy_v2(hstream));
     let local_id = hstream as u64;
     let net_handle = match handle_store::get_stream(local_id) {
         Some(h) => h,
@@ -678,6 +1022,7 @@ pub unsafe extern "C" fn cuStreamDestroy_v2(hstream: CUstream) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamSynchronize(hstream: CUstream) -> CUresult {
+    forward!(cuStreamSynchronize(hstream));
     let local_id = hstream as u64;
 
     let net_handle = if local_id == 0 {
@@ -694,7 +1039,11 @@ pub unsafe extern "C" fn cuStreamSynchronize(hstream: CUstream) -> CUresult {
     match send_cuda_command(CudaCommand::StreamSynchronize {
         stream: net_handle,
     }) {
-        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Success => {
+            host_mem::read_back_mapped();
+            managed::read_back();
+            complete_readbacks(|r| r.stream == net_handle)
+        }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
@@ -702,6 +1051,7 @@ pub unsafe extern "C" fn cuStreamSynchronize(hstream: CUstream) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamQuery(hstream: CUstream) -> CUresult {
+    forward!(cuStreamQuery(hstream));
     let local_id = hstream as u64;
     let net_handle = if local_id == 0 {
         null_stream_handle()
@@ -717,7 +1067,7 @@ pub unsafe extern "C" fn cuStreamQuery(hstream: CUstream) -> CUresult {
     match send_cuda_command(CudaCommand::StreamQuery {
         stream: net_handle,
     }) {
-        CudaResponse::StreamStatus(true) => CUDA_SUCCESS,
+        CudaResponse::StreamStatus(true) => complete_readbacks(|r| r.stream == net_handle),
         CudaResponse::StreamStatus(false) => CUDA_ERROR_NOT_READY,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -728,12 +1078,13 @@ pub unsafe extern "C" fn cuStreamQuery(hstream: CUstream) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuEventCreate(phevent: *mut CUevent, flags: c_uint) -> CUresult {
+    forward!(cuEventCreate(phevent, flags));
     if phevent.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
 
     match send_cuda_command(CudaCommand::EventCreate {
-        flags: flags as u32,
+        flags,
     }) {
         CudaResponse::Event(handle) => {
             let local_id = handle_store::store_event(handle);
@@ -747,6 +1098,7 @@ pub unsafe extern "C" fn cuEventCreate(phevent: *mut CUevent, flags: c_uint) ->
 
 #[no_mangle]
 pub unsafe extern "C" fn cuEventDestroy_v2(hevent: CUevent) -> CUresult {
+    forward!(cuEventDestroy_v2(hevent));
     let local_id = hevent as u64;
     let net_handle = match handle_store::get_event(local_id) {
         Some(h) => h,
@@ -765,6 +1117,7 @@ pub unsafe extern "C" fn cuEventDestroy_v2(hevent: CUevent) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuEventRecord(hevent: CUevent, hstream: CUstream) -> CUresult {
+    forward!(cuEventRecord(hevent, hstream));
     let local_event_id = hevent as u64;
     let net_event = match handle_store::get_event(local_event_id) {
         Some(h) => h,
@@ -793,6 +1146,7 @@ pub unsafe extern "C" fn cuEventRecord(hevent: CUevent, hstream: CUstream) -> CU
 
 #[no_mangle]
 pub unsafe extern "C" fn cuEventSynchronize(hevent: CUevent) -> CUresult {
+    forward!(cuEventSynchronize(hevent));
     let local_id = hevent as u64;
     let net_handle = match handle_store::get_event(local_id) {
         Some(h) => h,
@@ -800,7 +1154,13 @@ pub unsafe extern "C" fn cuEventSynchronize(hevent: CUevent) -> CUresult {
     };
 
     match send_cuda_command(CudaCommand::EventSynchronize { event: net_handle }) {
-        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Success => {
+            host_mem::read_back_mapped();
+            managed::read_back();
+            // The event's stream isn't known here; each readback is still
+            // read in order on its own stream.
+            complete_readbacks(|_| true)
+        }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
@@ -808,6 +1168,7 @@ pub unsafe extern "C" fn cuEventSynchronize(hevent: CUevent) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuEventQuery(hevent: CUevent) -> CUresult {
+    forward!(cuEventQuery(hevent));
     let local_id = hevent as u64;
     let net_handle = match handle_store::get_event(local_id) {
         Some(h) => h,
@@ -815,7 +1176,7 @@ pub unsafe extern "C" fn cuEventQuery(hevent: CUevent) -> CUresult {
     };
 
     match send_cuda_command(CudaCommand::EventQuery { event: net_handle }) {
-        CudaResponse::EventStatus(true) => CUDA_SUCCESS,
+        CudaResponse::EventStatus(true) => complete_readbacks(|_| true),
         CudaResponse::EventStatus(false) => CUDA_ERROR_NOT_READY,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -828,6 +1189,7 @@ pub unsafe extern "C" fn cuEventElapsedTime(
     hstart: CUevent,
     hend: CUevent,
 ) -> CUresult {
+    forward!(cuEventElapsedTime(ms, hstart, hend));
     if ms.is_null() {
         return CUDA_ERROR_INVALID_VALUE;
     }
@@ -858,6 +1220,7 @@ pub unsafe extern "C" fn cuEventElapsedTime(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGetUuid(uuid: *mut [u8; 16], dev: CUdevice) -> CUresult {
+    forward!(cuDeviceGetUuid(uuid, dev));
     if uuid.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let dev_handle = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DeviceGetUuid { device: dev_handle }) {
@@ -874,6 +1237,7 @@ pub unsafe extern "C" fn cuDeviceGetUuid(uuid: *mut [u8; 16], dev: CUdevice) ->
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGetP2PAttribute(value: *mut c_int, attrib: c_int, src: CUdevice, dst: CUdevice) -> CUresult {
+    forward!(cuDeviceGetP2PAttribute(value, attrib, src, dst));
     if value.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let src_h = match handle_store::get_device(src as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let dst_h = match handle_store::get_device(dst as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
@@ -886,6 +1250,7 @@ pub unsafe extern "C" fn cuDeviceGetP2PAttribute(value: *mut c_int, attrib: c_in
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceCanAccessPeer(can_access: *mut c_int, dev: CUdevice, peer: CUdevice) -> CUresult {
+    forward!(cuDeviceCanAccessPeer(can_access, dev, peer));
     if can_access.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let peer_h = match handle_store::get_device(peer as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
@@ -898,12 +1263,12 @@ pub unsafe extern "C" fn cuDeviceCanAccessPeer(can_access: *mut c_int, dev: CUde
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGetByPCIBusId(dev: *mut CUdevice, pci_bus_id: *const c_char) -> CUresult {
+    forward!(cuDeviceGetByPCIBusId(dev, pci_bus_id));
     if dev.is_null() || pci_bus_id.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let id_str = std::ffi::CStr::from_ptr(pci_bus_id).to_string_lossy().into_owned();
     match send_cuda_command(CudaCommand::DeviceGetByPCIBusId { pci_bus_id: id_str }) {
         CudaResponse::Device(handle) => {
-            let local_id = handle_store::store_device(handle);
-            *dev = local_id as CUdevice;
+            *dev = device_id(handle);
             CUDA_SUCCESS
         }
         CudaResponse::Error { code, .. } => code,
@@ -913,6 +1278,7 @@ pub unsafe extern "C" fn cuDeviceGetByPCIBusId(dev: *mut CUdevice, pci_bus_id: *
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGetPCIBusId(pci_bus_id: *mut c_char, len: c_int, dev: CUdevice) -> CUresult {
+    forward!(cuDeviceGetPCIBusId(pci_bus_id, len, dev));
     if pci_bus_id.is_null() || len <= 0 { return CUDA_ERROR_INVALID_VALUE; }
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DeviceGetPCIBusId { device: dev_h }) {
@@ -928,8 +1294,63 @@ pub unsafe extern "C" fn cuDeviceGetPCIBusId(pci_bus_id: *mut c_char, len: c_int
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn cuDeviceGetTexture1DLinearMaxWidth(max_width: *mut usize, format: c_uint, num_channels: c_uint, dev: CUdevice) -> CUresult {
+    forward!(cuDeviceGetTexture1DLinearMaxWidth(max_width, format, num_channels, dev));
+    if max_width.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::DeviceGetTexture1DLinearMaxWidth { format, num_channels, device: dev_h }) {
+        CudaResponse::Texture1DMaxWidth(w) => { *max_width = w as usize; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuDeviceGetExecAffinitySupport(pi: *mut c_int, affinity_type: c_int, dev: CUdevice) -> CUresult {
+    forward!(cuDeviceGetExecAffinitySupport(pi, affinity_type, dev));
+    if pi.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::DeviceGetExecAffinitySupport { affinity_type, device: dev_h }) {
+        CudaResponse::BoolResult(b) => { *pi = b as c_int; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+/// Graph memory nodes aren't supported, so no device ever has graph memory
+/// in use or reserved: the attributes read 0 and trimming does nothing.
+#[no_mangle]
+pub unsafe extern "C" fn cuDeviceGetGraphMemAttribute(dev: CUdevice, attr: c_int, value: *mut c_void) -> CUresult {
+    forward!(cuDeviceGetGraphMemAttribute(dev, attr, value));
+    if value.is_null() || handle_store::get_device(dev as u64).is_none() { return CUDA_ERROR_INVALID_VALUE; }
+    // CU_GRAPH_MEM_ATTR_{USED,RESERVED}_MEM_{CURRENT,HIGH}
+    if !(0..=3).contains(&attr) { return CUDA_ERROR_INVALID_VALUE; }
+    *(value as *mut u64) = 0;
+    CUDA_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuDeviceSetGraphMemAttribute(dev: CUdevice, attr: c_int, value: *mut c_void) -> CUresult {
+    forward!(cuDeviceSetGraphMemAttribute(dev, attr, value));
+    if value.is_null() || handle_store::get_device(dev as u64).is_none() { return CUDA_ERROR_INVALID_VALUE; }
+    // Only the high watermarks can be set, and only reset to 0.
+    match attr {
+        1 | 3 if *(value as *const u64) == 0 => CUDA_SUCCESS,
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuDeviceGraphMemTrim(dev: CUdevice) -> CUresult {
+    forward!(cuDeviceGraphMemTrim(dev));
+    if handle_store::get_device(dev as u64).is_none() { return CUDA_ERROR_INVALID_VALUE; }
+    CUDA_SUCCESS
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGetDefaultMemPool(pool: *mut CUmemoryPool, dev: CUdevice) -> CUresult {
+    forward!(cuDeviceGetDefaultMemPool(pool, dev));
     if pool.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DeviceGetDefaultMemPool { device: dev_h }) {
@@ -941,6 +1362,7 @@ pub unsafe extern "C" fn cuDeviceGetDefaultMemPool(pool: *mut CUmemoryPool, dev:
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceGetMemPool(pool: *mut CUmemoryPool, dev: CUdevice) -> CUresult {
+    forward!(cuDeviceGetMemPool(pool, dev));
     if pool.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DeviceGetMemPool { device: dev_h }) {
@@ -952,6 +1374,7 @@ pub unsafe extern "C" fn cuDeviceGetMemPool(pool: *mut CUmemoryPool, dev: CUdevi
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDeviceSetMemPool(dev: CUdevice, pool: CUmemoryPool) -> CUresult {
+    forward!(cuDeviceSetMemPool(dev, pool));
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let pool_h = match handle_store::get_mempool(pool as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DeviceSetMemPool { device: dev_h, mem_pool: pool_h }) {
@@ -965,6 +1388,7 @@ pub unsafe extern "C" fn cuDeviceSetMemPool(dev: CUdevice, pool: CUmemoryPool) -
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDevicePrimaryCtxRetain(pctx: *mut CUcontext, dev: CUdevice) -> CUresult {
+    forward!(cuDevicePrimaryCtxRetain(pctx, dev));
     if pctx.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DevicePrimaryCtxRetain { device: dev_h }) {
@@ -976,6 +1400,7 @@ pub unsafe extern "C" fn cuDevicePrimaryCtxRetain(pctx: *mut CUcontext, dev: CUd
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDevicePrimaryCtxRelease_v2(dev: CUdevice) -> CUresult {
+    forward!(cuDevicePrimaryCtxRelease_v2(dev));
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DevicePrimaryCtxRelease { device: dev_h }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -986,6 +1411,7 @@ pub unsafe extern "C" fn cuDevicePrimaryCtxRelease_v2(dev: CUdevice) -> CUresult
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDevicePrimaryCtxReset_v2(dev: CUdevice) -> CUresult {
+    forward!(cuDevicePrimaryCtxReset_v2(dev));
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DevicePrimaryCtxReset { device: dev_h }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -996,6 +1422,7 @@ pub unsafe extern "C" fn cuDevicePrimaryCtxReset_v2(dev: CUdevice) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDevicePrimaryCtxGetState(dev: CUdevice, flags: *mut c_uint, active: *mut c_int) -> CUresult {
+    forward!(cuDevicePrimaryCtxGetState(dev, flags, active));
     if flags.is_null() || active.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::DevicePrimaryCtxGetState { device: dev_h }) {
@@ -1007,8 +1434,9 @@ pub unsafe extern "C" fn cuDevicePrimaryCtxGetState(dev: CUdevice, flags: *mut c
 
 #[no_mangle]
 pub unsafe extern "C" fn cuDevicePrimaryCtxSetFlags_v2(dev: CUdevice, flags: c_uint) -> CUresult {
+    forward!(cuDevicePrimaryCtxSetFlags_v2(dev, flags));
     let dev_h = match handle_store::get_device(dev as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::DevicePrimaryCtxSetFlags { device: dev_h, flags: flags as u32 }) {
+    match send_cuda_command(CudaCommand::DevicePrimaryCtxSetFlags { device: dev_h, flags }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -1019,6 +1447,7 @@ pub unsafe extern "C" fn cuDevicePrimaryCtxSetFlags_v2(dev: CUdevice, flags: c_u
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxPushCurrent_v2(ctx: CUcontext) -> CUresult {
+    forward!(cuCtxPushCurrent_v2(ctx));
     let net_h = match handle_store::get_ctx(ctx as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::CtxPushCurrent { ctx: net_h }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1029,6 +1458,7 @@ pub unsafe extern "C" fn cuCtxPushCurrent_v2(ctx: CUcontext) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxPopCurrent_v2(pctx: *mut CUcontext) -> CUresult {
+    forward!(cuCtxPopCurrent_v2(pctx));
     match send_cuda_command(CudaCommand::CtxPopCurrent) {
         CudaResponse::Context(handle) => {
             let id = handle_store::store_ctx(handle);
@@ -1042,6 +1472,7 @@ pub unsafe extern "C" fn cuCtxPopCurrent_v2(pctx: *mut CUcontext) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxGetDevice(device: *mut CUdevice) -> CUresult {
+    forward!(cuCtxGetDevice(device));
     if device.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     match send_cuda_command(CudaCommand::CtxGetDevice) {
         CudaResponse::ContextDevice(handle) => {
@@ -1056,6 +1487,7 @@ pub unsafe extern "C" fn cuCtxGetDevice(device: *mut CUdevice) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxSetCacheConfig(config: c_int) -> CUresult {
+    forward!(cuCtxSetCacheConfig(config));
     match send_cuda_command(CudaCommand::CtxSetCacheConfig { config }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
@@ -1065,6 +1497,7 @@ pub unsafe extern "C" fn cuCtxSetCacheConfig(config: c_int) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxGetCacheConfig(config: *mut c_int) -> CUresult {
+    forward!(cuCtxGetCacheConfig(config));
     if config.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     match send_cuda_command(CudaCommand::CtxGetCacheConfig) {
         CudaResponse::CacheConfig(c) => { *config = c; CUDA_SUCCESS }
@@ -1075,6 +1508,7 @@ pub unsafe extern "C" fn cuCtxGetCacheConfig(config: *mut c_int) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxSetLimit(limit: c_int, value: usize) -> CUresult {
+    forward!(cuCtxSetLimit(limit, value));
     match send_cuda_command(CudaCommand::CtxSetLimit { limit, value: value as u64 }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
@@ -1084,6 +1518,7 @@ pub unsafe extern "C" fn cuCtxSetLimit(limit: c_int, value: usize) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxGetLimit(pvalue: *mut usize, limit: c_int) -> CUresult {
+    forward!(cuCtxGetLimit(pvalue, limit));
     if pvalue.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     match send_cuda_command(CudaCommand::CtxGetLimit { limit }) {
         CudaResponse::ContextLimit(v) => { *pvalue = v as usize; CUDA_SUCCESS }
@@ -1092,8 +1527,31 @@ pub unsafe extern "C" fn cuCtxGetLimit(pvalue: *mut usize, limit: c_int) -> CUre
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn cuCtxSetSharedMemConfig(config: c_int) -> CUresult {
+    forward!(cuCtxSetSharedMemConfig(config));
+    // Not queued: a bank size change that didn't take should be reported here.
+    match send_cuda_command(CudaCommand::CtxSetSharedMemConfig { config }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuCtxGetSharedMemConfig(config: *mut c_int) -> CUresult {
+    forward!(cuCtxGetSharedMemConfig(config));
+    if config.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match send_cuda_command(CudaCommand::CtxGetSharedMemConfig) {
+        CudaResponse::SharedMemConfig(c) => { *config = c; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxGetStreamPriorityRange(least: *mut c_int, greatest: *mut c_int) -> CUresult {
+    forward!(cuCtxGetStreamPriorityRange(least, greatest));
     match send_cuda_command(CudaCommand::CtxGetStreamPriorityRange) {
         CudaResponse::StreamPriorityRange { least: l, greatest: g } => {
             if !least.is_null() { *least = l; }
@@ -1107,6 +1565,7 @@ pub unsafe extern "C" fn cuCtxGetStreamPriorityRange(least: *mut c_int, greatest
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxGetApiVersion(ctx: CUcontext, version: *mut c_uint) -> CUresult {
+    forward!(cuCtxGetApiVersion(ctx, version));
     if version.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_h = match handle_store::get_ctx(ctx as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::CtxGetApiVersion { ctx: net_h }) {
@@ -1118,6 +1577,7 @@ pub unsafe extern "C" fn cuCtxGetApiVersion(ctx: CUcontext, version: *mut c_uint
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxGetFlags(flags: *mut c_uint) -> CUresult {
+    forward!(cuCtxGetFlags(flags));
     if flags.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     match send_cuda_command(CudaCommand::CtxGetFlags) {
         CudaResponse::ContextFlags(f) => { *flags = f; CUDA_SUCCESS }
@@ -1128,7 +1588,8 @@ pub unsafe extern "C" fn cuCtxGetFlags(flags: *mut c_uint) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxSetFlags(flags: c_uint) -> CUresult {
-    match send_cuda_command(CudaCommand::CtxSetFlags { flags: flags as u32 }) {
+    forward!(cuCtxSetFlags(flags));
+    match send_cuda_command(CudaCommand::CtxSetFlags { flags }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -1137,6 +1598,7 @@ pub unsafe extern "C" fn cuCtxSetFlags(flags: c_uint) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxResetPersistingL2Cache() -> CUresult {
+    forward!(cuCtxResetPersistingL2Cache());
     match send_cuda_command(CudaCommand::CtxResetPersistingL2Cache) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
@@ -1148,8 +1610,9 @@ pub unsafe extern "C" fn cuCtxResetPersistingL2Cache() -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxEnablePeerAccess(peer_ctx: CUcontext, flags: c_uint) -> CUresult {
+    forward!(cuCtxEnablePeerAccess(peer_ctx, flags));
     let net_h = match handle_store::get_ctx(peer_ctx as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::CtxEnablePeerAccess { peer_ctx: net_h, flags: flags as u32 }) {
+    match send_cuda_command(CudaCommand::CtxEnablePeerAccess { peer_ctx: net_h, flags }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -1158,6 +1621,7 @@ pub unsafe extern "C" fn cuCtxEnablePeerAccess(peer_ctx: CUcontext, flags: c_uin
 
 #[no_mangle]
 pub unsafe extern "C" fn cuCtxDisablePeerAccess(peer_ctx: CUcontext) -> CUresult {
+    forward!(cuCtxDisablePeerAccess(peer_ctx));
     let net_h = match handle_store::get_ctx(peer_ctx as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::CtxDisablePeerAccess { peer_ctx: net_h }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1170,6 +1634,7 @@ pub unsafe extern "C" fn cuCtxDisablePeerAccess(peer_ctx: CUcontext) -> CUresult
 
 #[no_mangle]
 pub unsafe extern "C" fn cuModuleLoad(module: *mut CUmodule, fname: *const c_char) -> CUresult {
+    forward!(cuModuleLoad(module, fname));
     if module.is_null() || fname.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let name = std::ffi::CStr::from_ptr(fname).to_string_lossy().into_owned();
     match send_cuda_command(CudaCommand::ModuleLoad { fname: name }) {
@@ -1182,12 +1647,27 @@ pub unsafe extern "C" fn cuModuleLoad(module: *mut CUmodule, fname: *const c_cha
 #[no_mangle]
 pub unsafe extern "C" fn cuModuleLoadDataEx(
     module: *mut CUmodule, image: *const c_void,
-    _num_options: c_uint, _options: *mut c_int, _option_values: *mut *mut c_void,
+    num_options: c_uint, options: *mut c_int, option_values: *mut *mut c_void,
 ) -> CUresult {
+    forward!(cuModuleLoadDataEx(module, image, num_options, options, option_values));
     if module.is_null() || image.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let image_data = detect_and_read_module_image(image);
-    match send_cuda_command(CudaCommand::ModuleLoadDataEx { image: image_data, num_options: 0, options: vec![], option_values: vec![] }) {
+    let jit = jit::JitOptions::read(num_options, options, option_values);
+    let command = CudaCommand::ModuleLoadDataEx {
+        image: image_data,
+        num_options: jit.options.len() as u32,
+        options: jit.options.clone(),
+        option_values: jit.values.clone(),
+    };
+    match send_cuda_command(command) {
         CudaResponse::Module(handle) => { let id = handle_store::store_mod(handle); *module = id as CUmodule; CUDA_SUCCESS }
+        CudaResponse::ModuleJit { module: loaded, code, jit: output } => {
+            jit.write_back(option_values, &output);
+            match loaded {
+                Some(handle) => { let id = handle_store::store_mod(handle); *module = id as CUmodule; CUDA_SUCCESS }
+                None => code,
+            }
+        }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
@@ -1195,6 +1675,7 @@ pub unsafe extern "C" fn cuModuleLoadDataEx(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuModuleLoadFatBinary(module: *mut CUmodule, fat_cubin: *const c_void) -> CUresult {
+    forward!(cuModuleLoadFatBinary(module, fat_cubin));
     if module.is_null() || fat_cubin.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let image_data = detect_and_read_module_image(fat_cubin);
     match send_cuda_command(CudaCommand::ModuleLoadFatBinary { fat_cubin: image_data }) {
@@ -1206,6 +1687,7 @@ pub unsafe extern "C" fn cuModuleLoadFatBinary(module: *mut CUmodule, fat_cubin:
 
 #[no_mangle]
 pub unsafe extern "C" fn cuModuleGetGlobal_v2(dptr: *mut CUdeviceptr, bytes: *mut usize, hmod: CUmodule, name: *const c_char) -> CUresult {
+    forward!(cuModuleGetGlobal_v2(dptr, bytes, hmod, name));
     if name.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_mod = match handle_store::get_mod(hmod as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let func_name = std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned();
@@ -1224,72 +1706,123 @@ pub unsafe extern "C" fn cuModuleGetGlobal_v2(dptr: *mut CUdeviceptr, bytes: *mu
 
 #[no_mangle]
 pub unsafe extern "C" fn cuLinkCreate_v2(
-    _num_options: c_uint, _options: *mut c_int, _option_values: *mut *mut c_void,
+    num_options: c_uint, options: *mut c_int, option_values: *mut *mut c_void,
     state: *mut CUlinkState,
 ) -> CUresult {
+    forward!(cuLinkCreate_v2(num_options, options, option_values, state));
     if state.is_null() { return CUDA_ERROR_INVALID_VALUE; }
-    match send_cuda_command(CudaCommand::LinkCreate { num_options: 0, options: vec![], option_values: vec![] }) {
-        CudaResponse::Linker(handle) => { let id = handle_store::store_linker(handle); *state = id as CUlinkState; CUDA_SUCCESS }
+    let jit = jit::JitOptions::read(num_options, options, option_values);
+    let command = CudaCommand::LinkCreate {
+        num_options: jit.options.len() as u32,
+        options: jit.options.clone(),
+        option_values: jit.values.clone(),
+    };
+    match send_cuda_command(command) {
+        CudaResponse::Linker(handle) => {
+            let id = handle_store::store_linker(handle);
+            jit::keep_linker(id, jit, option_values);
+            *state = id as CUlinkState;
+            CUDA_SUCCESS
+        }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
+/// The result of a linker call, writing what the JIT compiler handed back
+/// through the linker's options.
+unsafe fn link_result(state: CUlinkState, response: CudaResponse) -> Result<Vec<u8>, CUresult> {
+    match response {
+        CudaResponse::Success => Ok(Vec::new()),
+        CudaResponse::LinkCompleted { cubin_data } => Ok(cubin_data),
+        CudaResponse::LinkJit { code, cubin_data, jit: output } => {
+            jit::write_back_linker(state as u64, &output);
+            if code == CUDA_SUCCESS { Ok(cubin_data) } else { Err(code) }
+        }
+        CudaResponse::Error { code, .. } => Err(code),
+        _ => Err(CUDA_ERROR_UNKNOWN),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn cuLinkAddData_v2(
     state: CUlinkState, jit_type: c_int, data: *mut c_void, size: usize,
-    name: *const c_char, _num_options: c_uint, _options: *mut c_int, _option_values: *mut *mut c_void,
+    name: *const c_char, num_options: c_uint, options: *mut c_int, option_values: *mut *mut c_void,
 ) -> CUresult {
+    forward!(cuLinkAddData_v2(state, jit_type, data, size, name, num_options, options, option_values));
     let net_link = match handle_store::get_linker(state as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let data_vec = if !data.is_null() && size > 0 {
         std::slice::from_raw_parts(data as *const u8, size).to_vec()
     } else { vec![] };
     let name_str = if !name.is_null() { std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned() } else { String::new() };
-    match send_cuda_command(CudaCommand::LinkAddData { link: net_link, jit_type, data: data_vec, name: name_str, num_options: 0, options: vec![], option_values: vec![] }) {
-        CudaResponse::Success => CUDA_SUCCESS,
-        CudaResponse::Error { code, .. } => code,
-        _ => CUDA_ERROR_UNKNOWN,
+    // Options for this input alone; its logs go to the linker's buffers
+    let jit = jit::JitOptions::read(num_options, options, option_values);
+    let command = CudaCommand::LinkAddData {
+        link: net_link,
+        jit_type,
+        data: data_vec,
+        name: name_str,
+        num_options: jit.options.len() as u32,
+        options: jit.options,
+        option_values: jit.values,
+    };
+    match link_result(state, send_cuda_command(command)) {
+        Ok(_) => CUDA_SUCCESS,
+        Err(code) => code,
     }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn cuLinkAddFile_v2(
     state: CUlinkState, jit_type: c_int, path: *const c_char,
-    _num_options: c_uint, _options: *mut c_int, _option_values: *mut *mut c_void,
+    num_options: c_uint, options: *mut c_int, option_values: *mut *mut c_void,
 ) -> CUresult {
+    forward!(cuLinkAddFile_v2(state, jit_type, path, num_options, options, option_values));
     if path.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_link = match handle_store::get_linker(state as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let path_str = std::ffi::CStr::from_ptr(path).to_string_lossy().into_owned();
-    match send_cuda_command(CudaCommand::LinkAddFile { link: net_link, jit_type, path: path_str, num_options: 0, options: vec![], option_values: vec![] }) {
-        CudaResponse::Success => CUDA_SUCCESS,
-        CudaResponse::Error { code, .. } => code,
-        _ => CUDA_ERROR_UNKNOWN,
+    let jit = jit::JitOptions::read(num_options, options, option_values);
+    let command = CudaCommand::LinkAddFile {
+        link: net_link,
+        jit_type,
+        path: path_str,
+        num_options: jit.options.len() as u32,
+        options: jit.options,
+        option_values: jit.values,
+    };
+    match link_result(state, send_cuda_command(command)) {
+        Ok(_) => CUDA_SUCCESS,
+        Err(code) => code,
     }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn cuLinkComplete(state: CUlinkState, cubin_out: *mut *mut c_void, size_out: *mut usize) -> CUresult {
+    forward!(cuLinkComplete(state, cubin_out, size_out));
     let net_link = match handle_store::get_linker(state as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::LinkComplete { link: net_link }) {
-        CudaResponse::LinkCompleted { cubin_data } => {
-            // Leak the data so the pointer stays valid
-            let boxed = cubin_data.into_boxed_slice();
-            let len = boxed.len();
-            let ptr = Box::into_raw(boxed) as *mut c_void;
-            if !cubin_out.is_null() { *cubin_out = ptr; }
+    match link_result(state, send_cuda_command(CudaCommand::LinkComplete { link: net_link })) {
+        Ok(cubin_data) => {
+            // The cubin stays valid until cuLinkDestroy frees it
+            let len = cubin_data.len();
+            let ptr = handle_store::store_linker_cubin(state as u64, cubin_data);
+            if !cubin_out.is_null() { *cubin_out = ptr as *mut c_void; }
             if !size_out.is_null() { *size_out = len; }
             CUDA_SUCCESS
         }
-        CudaResponse::Error { code, .. } => code,
-        _ => CUDA_ERROR_UNKNOWN,
+        Err(code) => code,
     }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn cuLinkDestroy(state: CUlinkState) -> CUresult {
+    forward!(cuLinkDestroy(state));
     let net_link = match handle_store::get_linker(state as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::LinkDestroy { link: net_link }) {
-        CudaResponse::Success => { handle_store::remove_linker(state as u64); CUDA_SUCCESS }
+        CudaResponse::Success => {
+            handle_store::remove_linker(state as u64);
+            jit::forget_linker(state as u64);
+            CUDA_SUCCESS
+        }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
@@ -1299,8 +1832,10 @@ pub unsafe extern "C" fn cuLinkDestroy(state: CUlinkState) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemcpyDtoD_v2(dst: CUdeviceptr, src: CUdeviceptr, byte_count: usize) -> CUresult {
+    forward!(cuMemcpyDtoD_v2(dst, src, byte_count));
     let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_src = match handle_store::get_mem_by_ptr(src) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    host_mem::write_back_mapped();
     match send_cuda_command(CudaCommand::MemcpyDtoD { dst: net_dst, src: net_src, byte_count: byte_count as u64 }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
@@ -1310,38 +1845,98 @@ pub unsafe extern "C" fn cuMemcpyDtoD_v2(dst: CUdeviceptr, src: CUdeviceptr, byt
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemcpyHtoDAsync_v2(dst: CUdeviceptr, src: *const c_void, byte_count: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpyHtoDAsync_v2(dst, src, byte_count, hstream));
     if src.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
     let src_data = std::slice::from_raw_parts(src as *const u8, byte_count).to_vec();
-    match send_cuda_command(CudaCommand::MemcpyHtoDAsync { dst: net_dst, src_data, byte_count: byte_count as u64, stream: net_stream }) {
+    // The server executes HtoD copies synchronously, so encoded copies and
+    // fills can drop the stream.
+    let command = match htod_command(dst, net_dst, src_data) {
+        CudaCommand::MemcpyHtoD { dst, src_data, byte_count } => CudaCommand::MemcpyHtoDAsync { dst, src_data, byte_count, stream: net_stream },
+        other => other,
+    };
+    match send_cuda_command(command) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
+/// Returns once the copy is recorded; the data is fetched into `dst` when a
+/// stream, event or context synchronization (or a successful query) shows
+/// the work before it is done, or earlier if a later command could change
+/// or free the source.
 #[no_mangle]
 pub unsafe extern "C" fn cuMemcpyDtoHAsync_v2(dst: *mut c_void, src: CUdeviceptr, byte_count: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpyDtoHAsync_v2(dst, src, byte_count, hstream));
     if dst.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_src = match handle_store::get_mem_by_ptr(src) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
-    match send_cuda_command(CudaCommand::MemcpyDtoHAsync { src: net_src, byte_count: byte_count as u64, stream: net_stream }) {
-        CudaResponse::MemoryData(data) => {
-            let copy_len = std::cmp::min(data.len(), byte_count);
-            std::ptr::copy_nonoverlapping(data.as_ptr(), dst as *mut u8, copy_len);
-            CUDA_SUCCESS
+    handle_store::push_pending_readback(handle_store::PendingReadback {
+        dst: dst as u64,
+        src: net_src,
+        byte_count: byte_count as u64,
+        stream: net_stream,
+    });
+    CUDA_SUCCESS
+}
+
+/// Fetch the pending readbacks `select` picks into their host buffers,
+/// oldest first. Each is read on its own stream, so it sees the work queued
+/// there before the copy. Returns the first failure, if any.
+unsafe fn complete_readbacks(select: impl Fn(&handle_store::PendingReadback) -> bool) -> CUresult {
+    let mut result = CUDA_SUCCESS;
+    for readback in handle_store::take_pending_readbacks(select) {
+        let byte_count = readback.byte_count as usize;
+        let response = send_cuda_command(CudaCommand::MemcpyDtoHAsync {
+            src: readback.src,
+            byte_count: readback.byte_count,
+            stream: readback.stream,
+        });
+        let status = match decode_memory_data(response, byte_count) {
+            CudaResponse::MemoryData(data) => {
+                let copy_len = std::cmp::min(data.len(), byte_count);
+                std::ptr::copy_nonoverlapping(data.as_ptr(), readback.dst as *mut u8, copy_len);
+                CUDA_SUCCESS
+            }
+            CudaResponse::Error { code, .. } => code,
+            _ => CUDA_ERROR_UNKNOWN,
+        };
+        if result == CUDA_SUCCESS {
+            result = status;
+        }
+    }
+    result
+}
+
+/// Pending readbacks must not see writes queued after them: complete the
+/// ones whose source or stream `command` uses, or all of them before a
+/// launch, which can reach any allocation.
+unsafe fn complete_readbacks_before(command: &mut CudaCommand) {
+    let everything = matches!(
+        command,
+        CudaCommand::LaunchKernel { .. } | CudaCommand::LaunchCooperativeKernel { .. } | CudaCommand::GraphLaunch { .. }
+    );
+    let mut touched = Vec::new();
+    if !everything {
+        command.handles_mut(|h| touched.push(*h));
+    }
+    if everything || !touched.is_empty() {
+        let result = complete_readbacks(|r| everything || touched.contains(&r.src) || touched.contains(&r.stream));
+        if result != CUDA_SUCCESS {
+            warn!("deferred device-to-host copy failed: {}", result);
         }
-        CudaResponse::Error { code, .. } => code,
-        _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemcpyDtoDAsync_v2(dst: CUdeviceptr, src: CUdeviceptr, byte_count: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpyDtoDAsync_v2(dst, src, byte_count, hstream));
     let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_src = match handle_store::get_mem_by_ptr(src) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+    host_mem::write_back_mapped();
     match send_cuda_command(CudaCommand::MemcpyDtoDAsync { dst: net_dst, src: net_src, byte_count: byte_count as u64, stream: net_stream }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
@@ -1349,8 +1944,63 @@ pub unsafe extern "C" fn cuMemcpyDtoDAsync_v2(dst: CUdeviceptr, src: CUdeviceptr
     }
 }
 
+/// Which side each end of a cuMemcpy/cuMemcpyAsync is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CopyDirection {
+    HostToHost,
+    HostToDevice,
+    DeviceToHost,
+    DeviceToDevice,
+}
+
+/// cuMemcpy/cuMemcpyAsync infer the direction from the pointers. Device
+/// pointers are recognised by their range in the handle store; anything else
+/// is host memory.
+fn copy_direction(dst: CUdeviceptr, src: CUdeviceptr) -> CopyDirection {
+    let is_device_ptr = |ptr| handle_store::resolve_device_ptr(ptr).is_some();
+    match (is_device_ptr(dst), is_device_ptr(src)) {
+        (true, true) => CopyDirection::DeviceToDevice,
+        (true, false) => CopyDirection::HostToDevice,
+        (false, true) => CopyDirection::DeviceToHost,
+        (false, false) => CopyDirection::HostToHost,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpy(dst: CUdeviceptr, src: CUdeviceptr, byte_count: usize) -> CUresult {
+    forward!(cuMemcpy(dst, src, byte_count));
+    match copy_direction(dst, src) {
+        CopyDirection::DeviceToDevice => cuMemcpyDtoD_v2(dst, src, byte_count),
+        CopyDirection::HostToDevice => cuMemcpyHtoD_v2(dst, src as *const c_void, byte_count),
+        CopyDirection::DeviceToHost => cuMemcpyDtoH_v2(dst as *mut c_void, src, byte_count),
+        CopyDirection::HostToHost => copy_host_to_host(dst, src, byte_count),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyAsync(dst: CUdeviceptr, src: CUdeviceptr, byte_count: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpyAsync(dst, src, byte_count, hstream));
+    match copy_direction(dst, src) {
+        CopyDirection::DeviceToDevice => cuMemcpyDtoDAsync_v2(dst, src, byte_count, hstream),
+        CopyDirection::HostToDevice => cuMemcpyHtoDAsync_v2(dst, src as *const c_void, byte_count, hstream),
+        CopyDirection::DeviceToHost => cuMemcpyDtoHAsync_v2(dst as *mut c_void, src, byte_count, hstream),
+        // Nothing on the stream can touch client host memory, so there is
+        // nothing to order against.
+        CopyDirection::HostToHost => copy_host_to_host(dst, src, byte_count),
+    }
+}
+
+unsafe fn copy_host_to_host(dst: CUdeviceptr, src: CUdeviceptr, byte_count: usize) -> CUresult {
+    if dst == 0 || src == 0 {
+        return CUDA_ERROR_INVALID_VALUE;
+    }
+    std::ptr::copy(src as *const u8, dst as *mut u8, byte_count);
+    CUDA_SUCCESS
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn cuMemsetD8_v2(dst: CUdeviceptr, value: u8, count: usize) -> CUresult {
+    forward!(cuMemsetD8_v2(dst, value, count));
     let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::MemsetD8 { dst: net_dst, value, count: count as u64 }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1361,6 +2011,7 @@ pub unsafe extern "C" fn cuMemsetD8_v2(dst: CUdeviceptr, value: u8, count: usize
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemsetD16_v2(dst: CUdeviceptr, value: u16, count: usize) -> CUresult {
+    forward!(cuMemsetD16_v2(dst, value, count));
     let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::MemsetD16 { dst: net_dst, value, count: count as u64 }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1371,6 +2022,7 @@ pub unsafe extern "C" fn cuMemsetD16_v2(dst: CUdeviceptr, value: u16, count: usi
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemsetD32_v2(dst: CUdeviceptr, value: u32, count: usize) -> CUresult {
+    forward!(cuMemsetD32_v2(dst, value, count));
     let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::MemsetD32 { dst: net_dst, value, count: count as u64 }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1380,104 +2032,215 @@ pub unsafe extern "C" fn cuMemsetD32_v2(dst: CUdeviceptr, value: u32, count: usi
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemGetInfo_v2(free: *mut usize, total: *mut usize) -> CUresult {
-    match send_cuda_command(CudaCommand::MemGetInfo) {
-        CudaResponse::MemInfo { free: f, total: t } => {
-            if !free.is_null() { *free = f as usize; }
-            if !total.is_null() { *total = t as usize; }
-            CUDA_SUCCESS
-        }
+pub unsafe extern "C" fn cuMemsetD2D8_v2(dst: CUdeviceptr, dst_pitch: usize, value: u8, width: usize, height: usize) -> CUresult {
+    forward!(cuMemsetD2D8_v2(dst, dst_pitch, value, width, height));
+    let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::MemsetD2D8 { dst: net_dst, dst_pitch: dst_pitch as u64, value, width: width as u64, height: height as u64 }) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemGetAddressRange_v2(pbase: *mut CUdeviceptr, psize: *mut usize, dptr: CUdeviceptr) -> CUresult {
-    let net_ptr = match handle_store::get_mem_by_ptr(dptr) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::MemGetAddressRange { dptr: net_ptr }) {
-        CudaResponse::MemAddressRange { base, size } => {
-            if !pbase.is_null() { let id = handle_store::store_mem(base); *pbase = id; }
-            if !psize.is_null() { *psize = size as usize; }
-            CUDA_SUCCESS
-        }
+pub unsafe extern "C" fn cuMemsetD2D16_v2(dst: CUdeviceptr, dst_pitch: usize, value: u16, width: usize, height: usize) -> CUresult {
+    forward!(cuMemsetD2D16_v2(dst, dst_pitch, value, width, height));
+    let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::MemsetD2D16 { dst: net_dst, dst_pitch: dst_pitch as u64, value, width: width as u64, height: height as u64 }) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemAllocHost_v2(pp: *mut *mut c_void, bytesize: usize) -> CUresult {
-    if pp.is_null() { return CUDA_ERROR_INVALID_VALUE; }
-    match send_cuda_command(CudaCommand::MemAllocHost { byte_size: bytesize as u64 }) {
-        CudaResponse::HostPtr(handle) => {
-            let id = handle_store::store_host_mem(handle);
-            *pp = id as *mut c_void;
-            CUDA_SUCCESS
-        }
+pub unsafe extern "C" fn cuMemsetD2D32_v2(dst: CUdeviceptr, dst_pitch: usize, value: u32, width: usize, height: usize) -> CUresult {
+    forward!(cuMemsetD2D32_v2(dst, dst_pitch, value, width, height));
+    let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::MemsetD2D32 { dst: net_dst, dst_pitch: dst_pitch as u64, value, width: width as u64, height: height as u64 }) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemFreeHost(p: *mut c_void) -> CUresult {
-    let net_h = match handle_store::get_host_mem(p as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::MemFreeHost { ptr: net_h }) {
-        CudaResponse::Success => { handle_store::remove_host_mem(p as u64); CUDA_SUCCESS }
+pub unsafe extern "C" fn cuMemsetD2D8Async(dst: CUdeviceptr, dst_pitch: usize, value: u8, width: usize, height: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemsetD2D8Async(dst, dst_pitch, value, width, height, hstream));
+    let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+    match send_cuda_command(CudaCommand::MemsetD2D8Async { dst: net_dst, dst_pitch: dst_pitch as u64, value, width: width as u64, height: height as u64, stream: net_stream }) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemHostAlloc(pp: *mut *mut c_void, bytesize: usize, flags: c_uint) -> CUresult {
-    if pp.is_null() { return CUDA_ERROR_INVALID_VALUE; }
-    match send_cuda_command(CudaCommand::MemHostAlloc { byte_size: bytesize as u64, flags: flags as u32 }) {
-        CudaResponse::HostPtr(handle) => {
-            let id = handle_store::store_host_mem(handle);
-            *pp = id as *mut c_void;
-            CUDA_SUCCESS
-        }
+pub unsafe extern "C" fn cuMemsetD2D16Async(dst: CUdeviceptr, dst_pitch: usize, value: u16, width: usize, height: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemsetD2D16Async(dst, dst_pitch, value, width, height, hstream));
+    let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+    match send_cuda_command(CudaCommand::MemsetD2D16Async { dst: net_dst, dst_pitch: dst_pitch as u64, value, width: width as u64, height: height as u64, stream: net_stream }) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemHostGetDevicePointer_v2(pdptr: *mut CUdeviceptr, p: *mut c_void, flags: c_uint) -> CUresult {
-    if pdptr.is_null() { return CUDA_ERROR_INVALID_VALUE; }
-    let net_h = match handle_store::get_host_mem(p as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::MemHostGetDevicePointer { host_ptr: net_h, flags: flags as u32 }) {
-        CudaResponse::HostDevicePtr(handle) => { let id = handle_store::store_mem(handle); *pdptr = id; CUDA_SUCCESS }
+pub unsafe extern "C" fn cuMemsetD2D32Async(dst: CUdeviceptr, dst_pitch: usize, value: u32, width: usize, height: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemsetD2D32Async(dst, dst_pitch, value, width, height, hstream));
+    let net_dst = match handle_store::get_mem_by_ptr(dst) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+    match send_cuda_command(CudaCommand::MemsetD2D32Async { dst: net_dst, dst_pitch: dst_pitch as u64, value, width: width as u64, height: height as u64, stream: net_stream }) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemHostGetFlags(pflags: *mut c_uint, p: *mut c_void) -> CUresult {
-    if pflags.is_null() { return CUDA_ERROR_INVALID_VALUE; }
-    let net_h = match handle_store::get_host_mem(p as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::MemHostGetFlags { host_ptr: net_h }) {
-        CudaResponse::HostFlags(f) => { *pflags = f; CUDA_SUCCESS }
+pub unsafe extern "C" fn cuMemGetInfo_v2(free: *mut usize, total: *mut usize) -> CUresult {
+    forward!(cuMemGetInfo_v2(free, total));
+    match send_cuda_command(CudaCommand::MemGetInfo) {
+        CudaResponse::MemInfo { free: f, total: t } => {
+            if !free.is_null() { *free = f as usize; }
+            if !total.is_null() { *total = t as usize; }
+            CUDA_SUCCESS
+        }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
 #[no_mangle]
-pub unsafe extern "C" fn cuMemAllocManaged(dptr: *mut CUdeviceptr, bytesize: usize, flags: c_uint) -> CUresult {
-    if dptr.is_null() { return CUDA_ERROR_INVALID_VALUE; }
-    match send_cuda_command(CudaCommand::MemAllocManaged { byte_size: bytesize as u64, flags: flags as u32 }) {
-        CudaResponse::MemAllocated(handle) => { let id = handle_store::store_mem(handle); *dptr = id; CUDA_SUCCESS }
+pub unsafe extern "C" fn cuMemGetAddressRange_v2(pbase: *mut CUdeviceptr, psize: *mut usize, dptr: CUdeviceptr) -> CUresult {
+    forward!(cuMemGetAddressRange_v2(pbase, psize, dptr));
+    let net_ptr = match handle_store::get_mem_by_ptr(dptr) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::MemGetAddressRange { dptr: net_ptr }) {
+        CudaResponse::MemAddressRange { base, size } => {
+            if !pbase.is_null() { let id = handle_store::store_mem(base); *pbase = id; }
+            if !psize.is_null() { *psize = size as usize; }
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemAllocHost_v2(pp: *mut *mut c_void, bytesize: usize) -> CUresult {
+    forward!(cuMemAllocHost_v2(pp, bytesize));
+    if pp.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match host_mem::alloc(bytesize, 0, CudaCommand::MemAllocHost { byte_size: bytesize as u64 }) {
+        Ok(p) => { *pp = p; CUDA_SUCCESS }
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemFreeHost(p: *mut c_void) -> CUresult {
+    forward!(cuMemFreeHost(p));
+    host_mem::free(p)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemHostAlloc(pp: *mut *mut c_void, bytesize: usize, flags: c_uint) -> CUresult {
+    forward!(cuMemHostAlloc(pp, bytesize, flags));
+    if pp.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match host_mem::alloc(bytesize, flags, CudaCommand::MemHostAlloc { byte_size: bytesize as u64, flags }) {
+        Ok(p) => { *pp = p; CUDA_SUCCESS }
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemHostGetDevicePointer_v2(pdptr: *mut CUdeviceptr, p: *mut c_void, flags: c_uint) -> CUresult {
+    forward!(cuMemHostGetDevicePointer_v2(pdptr, p, flags));
+    if pdptr.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let (range, offset) = match handle_store::find_host_range(p as u64) { Some(r) => r, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::MemHostGetDevicePointer { host_ptr: range.handle, flags }) {
+        CudaResponse::HostDevicePtr(handle) => {
+            handle_store::set_host_range_mapped(p as u64 - offset);
+            let id = handle_store::store_mem(handle);
+            *pdptr = id + offset;
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemHostGetFlags(pflags: *mut c_uint, p: *mut c_void) -> CUresult {
+    forward!(cuMemHostGetFlags(pflags, p));
+    if pflags.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let (range, _) = match handle_store::find_host_range(p as u64) { Some(r) => r, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::MemHostGetFlags { host_ptr: range.handle }) {
+        CudaResponse::HostFlags(f) => { *pflags = f; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+/// Register an application buffer. Copies to and from it read and write the
+/// buffer itself, synchronous or not; the server keeps a pinned shadow of
+/// the same size for when the range is mapped for the device.
+#[no_mangle]
+pub unsafe extern "C" fn cuMemHostRegister_v2(p: *mut c_void, bytesize: usize, flags: c_uint) -> CUresult {
+    forward!(cuMemHostRegister_v2(p, bytesize, flags));
+    if p.is_null() || bytesize == 0 { return CUDA_ERROR_INVALID_VALUE; }
+    if handle_store::find_host_range(p as u64).is_some() || handle_store::find_host_range(p as u64 + bytesize as u64 - 1).is_some() {
+        return CUDA_ERROR_HOST_MEMORY_ALREADY_REGISTERED;
+    }
+    match send_cuda_command(CudaCommand::MemHostRegister { byte_size: bytesize as u64, flags }) {
+        CudaResponse::HostPtr(handle) => {
+            let range = handle_store::HostRange { size: bytesize as u64, flags, handle, layout: None, mapped: false, synced_hash: None };
+            if handle_store::insert_host_range(p as u64, range) {
+                CUDA_SUCCESS
+            } else {
+                // Overlaps a registration the checks above didn't catch.
+                send_cuda_command(CudaCommand::MemHostUnregister { ptr: handle });
+                CUDA_ERROR_HOST_MEMORY_ALREADY_REGISTERED
+            }
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemHostRegister(p: *mut c_void, bytesize: usize, flags: c_uint) -> CUresult {
+    forward!(cuMemHostRegister(p, bytesize, flags));
+    cuMemHostRegister_v2(p, bytesize, flags)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemHostUnregister(p: *mut c_void) -> CUresult {
+    forward!(cuMemHostUnregister(p));
+    // Allocated rather than registered memory stays until cuMemFreeHost.
+    let registered = match handle_store::remove_host_range(p as u64, |r| r.layout.is_some()) { Some(r) => r, None => return CUDA_ERROR_HOST_MEMORY_NOT_REGISTERED };
+    match send_cuda_command(CudaCommand::MemHostUnregister { ptr: registered.handle }) {
+        CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn cuMemAllocManaged(dptr: *mut CUdeviceptr, bytesize: usize, flags: c_uint) -> CUresult {
+    forward!(cuMemAllocManaged(dptr, bytesize, flags));
+    if dptr.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match managed::alloc(bytesize, flags) {
+        Ok(ptr) => { *dptr = ptr; CUDA_SUCCESS }
+        Err(code) => code,
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn cuMemAllocPitch_v2(dptr: *mut CUdeviceptr, ppitch: *mut usize, width: usize, height: usize, element_size: c_uint) -> CUresult {
+    forward!(cuMemAllocPitch_v2(dptr, ppitch, width, height, element_size));
     if dptr.is_null() || ppitch.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     match send_cuda_command(CudaCommand::MemAllocPitch { width: width as u64, height: height as u64, element_size }) {
         CudaResponse::MemAllocPitch { dptr: handle, pitch } => {
@@ -1491,6 +2254,572 @@ pub unsafe extern "C" fn cuMemAllocPitch_v2(dptr: *mut CUdeviceptr, ppitch: *mut
     }
 }
 
+// ── CUDA Arrays ─────────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cuArrayCreate_v2(p_handle: *mut CUarray, p_allocate_array: *const CUDA_ARRAY_DESCRIPTOR) -> CUresult {
+    forward!(cuArrayCreate_v2(p_handle, p_allocate_array));
+    if p_handle.is_null() || p_allocate_array.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    array_create(p_handle, (*p_allocate_array).to_desc())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuArray3DCreate_v2(p_handle: *mut CUarray, p_allocate_array: *const CUDA_ARRAY3D_DESCRIPTOR) -> CUresult {
+    forward!(cuArray3DCreate_v2(p_handle, p_allocate_array));
+    if p_handle.is_null() || p_allocate_array.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    array_create(p_handle, (*p_allocate_array).to_desc())
+}
+
+unsafe fn array_create(p_handle: *mut CUarray, desc: rgpu_protocol::cuda_commands::ArrayDescriptor) -> CUresult {
+    match send_cuda_command(CudaCommand::ArrayCreate { desc }) {
+        CudaResponse::Array(handle) => {
+            *p_handle = handle_store::store_array(handle, desc) as CUarray;
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuArrayDestroy(h_array: CUarray) -> CUresult {
+    forward!(cuArrayDestroy(h_array));
+    let net_array = match handle_store::get_array(h_array as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    handle_store::remove_array(h_array as u64);
+    match send_cuda_command(CudaCommand::ArrayDestroy { array: net_array }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuArrayGetDescriptor_v2(p_array_descriptor: *mut CUDA_ARRAY_DESCRIPTOR, h_array: CUarray) -> CUresult {
+    forward!(cuArrayGetDescriptor_v2(p_array_descriptor, h_array));
+    match handle_store::get_array_descriptor(h_array as u64) {
+        Some(desc) if !p_array_descriptor.is_null() => { *p_array_descriptor = CUDA_ARRAY_DESCRIPTOR::from_desc(&desc); CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuArray3DGetDescriptor_v2(p_array_descriptor: *mut CUDA_ARRAY3D_DESCRIPTOR, h_array: CUarray) -> CUresult {
+    forward!(cuArray3DGetDescriptor_v2(p_array_descriptor, h_array));
+    match handle_store::get_array_descriptor(h_array as u64) {
+        Some(desc) if !p_array_descriptor.is_null() => { *p_array_descriptor = CUDA_ARRAY3D_DESCRIPTOR::from_desc(&desc); CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+// ── 2D / 3D Copies ──────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpy2D_v2(p_copy: *const CUDA_MEMCPY2D) -> CUresult {
+    forward!(cuMemcpy2D_v2(p_copy));
+    if p_copy.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match (*p_copy).to_copy() {
+        Ok(copy) => memcpy_3d(copy, None),
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpy2DUnaligned_v2(p_copy: *const CUDA_MEMCPY2D) -> CUresult {
+    forward!(cuMemcpy2DUnaligned_v2(p_copy));
+    if p_copy.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match (*p_copy).to_copy() {
+        Ok(copy) => memcpy_3d(copy, None),
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpy2DAsync_v2(p_copy: *const CUDA_MEMCPY2D, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpy2DAsync_v2(p_copy, hstream));
+    if p_copy.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match (*p_copy).to_copy() {
+        Ok(copy) => memcpy_3d(copy, Some(hstream)),
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpy3D_v2(p_copy: *const CUDA_MEMCPY3D) -> CUresult {
+    forward!(cuMemcpy3D_v2(p_copy));
+    if p_copy.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match (*p_copy).to_copy() {
+        Ok(copy) => memcpy_3d(copy, None),
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpy3DAsync_v2(p_copy: *const CUDA_MEMCPY3D, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpy3DAsync_v2(p_copy, hstream));
+    if p_copy.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match (*p_copy).to_copy() {
+        Ok(copy) => memcpy_3d(copy, Some(hstream)),
+        Err(code) => code,
+    }
+}
+
+// 1D copies to and from arrays are one-row 2D copies.
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyHtoA_v2(dst_array: CUarray, dst_offset: usize, src_host: *const c_void, byte_count: usize) -> CUresult {
+    forward!(cuMemcpyHtoA_v2(dst_array, dst_offset, src_host, byte_count));
+    memcpy_2d(&CUDA_MEMCPY2D::row(byte_count).with_src_host(src_host).with_dst_array(dst_array, dst_offset), None)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyHtoAAsync_v2(dst_array: CUarray, dst_offset: usize, src_host: *const c_void, byte_count: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpyHtoAAsync_v2(dst_array, dst_offset, src_host, byte_count, hstream));
+    memcpy_2d(&CUDA_MEMCPY2D::row(byte_count).with_src_host(src_host).with_dst_array(dst_array, dst_offset), Some(hstream))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyAtoH_v2(dst_host: *mut c_void, src_array: CUarray, src_offset: usize, byte_count: usize) -> CUresult {
+    forward!(cuMemcpyAtoH_v2(dst_host, src_array, src_offset, byte_count));
+    memcpy_2d(&CUDA_MEMCPY2D::row(byte_count).with_src_array(src_array, src_offset).with_dst_host(dst_host), None)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyAtoHAsync_v2(dst_host: *mut c_void, src_array: CUarray, src_offset: usize, byte_count: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemcpyAtoHAsync_v2(dst_host, src_array, src_offset, byte_count, hstream));
+    memcpy_2d(&CUDA_MEMCPY2D::row(byte_count).with_src_array(src_array, src_offset).with_dst_host(dst_host), Some(hstream))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyDtoA_v2(dst_array: CUarray, dst_offset: usize, src_device: CUdeviceptr, byte_count: usize) -> CUresult {
+    forward!(cuMemcpyDtoA_v2(dst_array, dst_offset, src_device, byte_count));
+    memcpy_2d(&CUDA_MEMCPY2D::row(byte_count).with_src_device(src_device).with_dst_array(dst_array, dst_offset), None)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyAtoD_v2(dst_device: CUdeviceptr, src_array: CUarray, src_offset: usize, byte_count: usize) -> CUresult {
+    forward!(cuMemcpyAtoD_v2(dst_device, src_array, src_offset, byte_count));
+    memcpy_2d(&CUDA_MEMCPY2D::row(byte_count).with_src_array(src_array, src_offset).with_dst_device(dst_device), None)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuMemcpyAtoA_v2(dst_array: CUarray, dst_offset: usize, src_array: CUarray, src_offset: usize, byte_count: usize) -> CUresult {
+    forward!(cuMemcpyAtoA_v2(dst_array, dst_offset, src_array, src_offset, byte_count));
+    memcpy_2d(&CUDA_MEMCPY2D::row(byte_count).with_src_array(src_array, src_offset).with_dst_array(dst_array, dst_offset), None)
+}
+
+unsafe fn memcpy_2d(copy: &CUDA_MEMCPY2D, hstream: Option<CUstream>) -> CUresult {
+    match copy.to_copy() {
+        Ok(copy) => memcpy_3d(copy, hstream),
+        Err(code) => code,
+    }
+}
+
+/// Run a 2D/3D copy: locally if both sides are host memory, otherwise on
+/// the server with host rows packed into the command or response.
+unsafe fn memcpy_3d(copy: memcpy3d::Copy3D, hstream: Option<CUstream>) -> CUresult {
+    if copy.is_host_only() {
+        copy.unpack_destination(&copy.pack_source());
+        return CUDA_SUCCESS;
+    }
+
+    let src_data = copy.pack_source();
+    let params = Box::new(copy.params.clone());
+    debug!("cuMemcpy3D({} x {} x {} bytes)", params.width_in_bytes, params.height, params.depth);
+    let cmd = match hstream {
+        Some(hstream) => {
+            let stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+            CudaCommand::Memcpy3DAsync { params, src_data, stream }
+        }
+        None => CudaCommand::Memcpy3D { params, src_data },
+    };
+    match send_cuda_command(cmd) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::MemoryData(data) => {
+            copy.unpack_destination(&data);
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+// ── Texture and Surface Objects ─────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexObjectCreate(p_tex_object: *mut CUtexObject, p_res_desc: *const CUDA_RESOURCE_DESC, p_tex_desc: *const CUDA_TEXTURE_DESC, p_res_view_desc: *const CUDA_RESOURCE_VIEW_DESC) -> CUresult {
+    forward!(cuTexObjectCreate(p_tex_object, p_res_desc, p_tex_desc, p_res_view_desc));
+    if p_tex_object.is_null() || p_res_desc.is_null() || p_tex_desc.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let resource = match (*p_res_desc).to_desc() { Ok(r) => Box::new(r), Err(code) => return code };
+    let view = if p_res_view_desc.is_null() { None } else { Some(*p_res_view_desc) };
+    match send_cuda_command(CudaCommand::TexObjectCreate { resource, texture: Box::new((*p_tex_desc).to_desc()), view: view.map(|v| Box::new(v.to_desc())) }) {
+        CudaResponse::TexObject(handle) => {
+            *p_tex_object = texture::store(texture::Object { handle, resource: *p_res_desc, texture: Some(*p_tex_desc), view });
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexObjectDestroy(tex_object: CUtexObject) -> CUresult {
+    forward!(cuTexObjectDestroy(tex_object));
+    let handle = match texture::get(tex_object) { Some(o) if o.texture.is_some() => o.handle, _ => return CUDA_ERROR_INVALID_VALUE };
+    texture::remove(tex_object);
+    match send_cuda_command(CudaCommand::TexObjectDestroy { tex_object: handle }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexObjectGetResourceDesc(p_res_desc: *mut CUDA_RESOURCE_DESC, tex_object: CUtexObject) -> CUresult {
+    forward!(cuTexObjectGetResourceDesc(p_res_desc, tex_object));
+    match texture::get(tex_object) {
+        Some(o) if o.texture.is_some() && !p_res_desc.is_null() => { *p_res_desc = o.resource; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexObjectGetTextureDesc(p_tex_desc: *mut CUDA_TEXTURE_DESC, tex_object: CUtexObject) -> CUresult {
+    forward!(cuTexObjectGetTextureDesc(p_tex_desc, tex_object));
+    match texture::get(tex_object).and_then(|o| o.texture) {
+        Some(desc) if !p_tex_desc.is_null() => { *p_tex_desc = desc; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexObjectGetResourceViewDesc(p_res_view_desc: *mut CUDA_RESOURCE_VIEW_DESC, tex_object: CUtexObject) -> CUresult {
+    forward!(cuTexObjectGetResourceViewDesc(p_res_view_desc, tex_object));
+    // Like the driver, an object created without a view has none to return.
+    match texture::get(tex_object).and_then(|o| o.view) {
+        Some(desc) if !p_res_view_desc.is_null() => { *p_res_view_desc = desc; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuSurfObjectCreate(p_surf_object: *mut CUsurfObject, p_res_desc: *const CUDA_RESOURCE_DESC) -> CUresult {
+    forward!(cuSurfObjectCreate(p_surf_object, p_res_desc));
+    if p_surf_object.is_null() || p_res_desc.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let resource = match (*p_res_desc).to_desc() { Ok(r) => Box::new(r), Err(code) => return code };
+    match send_cuda_command(CudaCommand::SurfObjectCreate { resource }) {
+        CudaResponse::SurfObject(handle) => {
+            *p_surf_object = texture::store(texture::Object { handle, resource: *p_res_desc, texture: None, view: None });
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuSurfObjectDestroy(surf_object: CUsurfObject) -> CUresult {
+    forward!(cuSurfObjectDestroy(surf_object));
+    let handle = match texture::get(surf_object) { Some(o) if o.texture.is_none() => o.handle, _ => return CUDA_ERROR_INVALID_VALUE };
+    texture::remove(surf_object);
+    match send_cuda_command(CudaCommand::SurfObjectDestroy { surf_object: handle }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuSurfObjectGetResourceDesc(p_res_desc: *mut CUDA_RESOURCE_DESC, surf_object: CUsurfObject) -> CUresult {
+    forward!(cuSurfObjectGetResourceDesc(p_res_desc, surf_object));
+    match texture::get(surf_object) {
+        Some(o) if o.texture.is_none() && !p_res_desc.is_null() => { *p_res_desc = o.resource; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+// ── Texture and Surface References ──────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cuModuleGetTexRef(p_tex_ref: *mut CUtexref, hmod: CUmodule, name: *const c_char) -> CUresult {
+    forward!(cuModuleGetTexRef(p_tex_ref, hmod, name));
+    module_get_ref(p_tex_ref, hmod, name, false)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuModuleGetSurfRef(p_surf_ref: *mut CUsurfref, hmod: CUmodule, name: *const c_char) -> CUresult {
+    forward!(cuModuleGetSurfRef(p_surf_ref, hmod, name));
+    module_get_ref(p_surf_ref, hmod, name, true)
+}
+
+unsafe fn module_get_ref(p_ref: *mut *mut c_void, hmod: CUmodule, name: *const c_char, surface: bool) -> CUresult {
+    if p_ref.is_null() || name.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let net_mod = match handle_store::get_mod(hmod as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    let ref_name = std::ffi::CStr::from_ptr(name).to_string_lossy().into_owned();
+    if let Some(id) = texref::lookup(hmod as u64, &ref_name, surface) {
+        *p_ref = id as *mut c_void;
+        return CUDA_SUCCESS;
+    }
+    let cmd = if surface {
+        CudaCommand::ModuleGetSurfRef { module: net_mod, name: ref_name.clone() }
+    } else {
+        CudaCommand::ModuleGetTexRef { module: net_mod, name: ref_name.clone() }
+    };
+    match send_cuda_command(cmd) {
+        CudaResponse::TexRef(handle) | CudaResponse::SurfRef(handle) => {
+            *p_ref = texref::store(hmod as u64, &ref_name, surface, handle) as *mut c_void;
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+/// The texture reference `h_tex_ref` names; surface references aren't accepted.
+fn tex_ref(h_tex_ref: CUtexref) -> Option<texref::TexRef> {
+    texref::get(h_tex_ref as u64).filter(|r| !r.surface)
+}
+
+/// Send a cuTexRefSet* call and record what it set once the server has it.
+unsafe fn tex_ref_set(h_tex_ref: CUtexref, setting: TexRefSetting, apply: impl FnOnce(&mut texref::TexRef)) -> CUresult {
+    let handle = match tex_ref(h_tex_ref) { Some(r) => r.handle, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::TexRefSet { texref: handle, setting }) {
+        CudaResponse::Success => { texref::update(h_tex_ref as u64, apply); CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetAddress_v2(byte_offset: *mut usize, h_tex_ref: CUtexref, dptr: CUdeviceptr, bytes: usize) -> CUresult {
+    forward!(cuTexRefSetAddress_v2(byte_offset, h_tex_ref, dptr, bytes));
+    let handle = match tex_ref(h_tex_ref) { Some(r) => r.handle, None => return CUDA_ERROR_INVALID_VALUE };
+    let (net_mem, offset) = match handle_store::resolve_device_ptr(dptr) { Some(m) => m, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::TexRefSetAddress { texref: handle, dptr: net_mem, offset, bytes: bytes as u64 }) {
+        CudaResponse::TexRefOffset(offset) => {
+            texref::update(h_tex_ref as u64, |r| { r.address = Some(dptr); r.array = None; });
+            if !byte_offset.is_null() { *byte_offset = offset as usize; }
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetAddress2D_v3(h_tex_ref: CUtexref, desc: *const CUDA_ARRAY_DESCRIPTOR, dptr: CUdeviceptr, pitch: usize) -> CUresult {
+    forward!(cuTexRefSetAddress2D_v3(h_tex_ref, desc, dptr, pitch));
+    if desc.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let desc = &*desc;
+    let (net_mem, offset) = match handle_store::resolve_device_ptr(dptr) { Some(m) => m, None => return CUDA_ERROR_INVALID_VALUE };
+    let setting = TexRefSetting::Address2D {
+        dptr: net_mem,
+        offset,
+        format: desc.format,
+        num_channels: desc.num_channels,
+        width: desc.width as u64,
+        height: desc.height as u64,
+        pitch: pitch as u64,
+    };
+    let (format, num_channels) = (desc.format, desc.num_channels as c_int);
+    tex_ref_set(h_tex_ref, setting, |r| {
+        r.address = Some(dptr);
+        r.array = None;
+        r.format = format;
+        r.num_channels = num_channels;
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetArray(h_tex_ref: CUtexref, h_array: CUarray, flags: c_uint) -> CUresult {
+    forward!(cuTexRefSetArray(h_tex_ref, h_array, flags));
+    let net_array = match handle_store::get_array(h_array as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    // Binding an array takes on its format.
+    let desc = handle_store::get_array_descriptor(h_array as u64);
+    tex_ref_set(h_tex_ref, TexRefSetting::Array { array: net_array, flags }, |r| {
+        r.address = None;
+        r.array = Some(h_array as u64);
+        if let Some(desc) = desc {
+            r.format = desc.format;
+            r.num_channels = desc.num_channels as c_int;
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetFormat(h_tex_ref: CUtexref, fmt: c_uint, num_packed_components: c_int) -> CUresult {
+    forward!(cuTexRefSetFormat(h_tex_ref, fmt, num_packed_components));
+    tex_ref_set(h_tex_ref, TexRefSetting::Format { format: fmt, num_packed_components }, |r| {
+        r.format = fmt;
+        r.num_channels = num_packed_components;
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetFlags(h_tex_ref: CUtexref, flags: c_uint) -> CUresult {
+    forward!(cuTexRefSetFlags(h_tex_ref, flags));
+    tex_ref_set(h_tex_ref, TexRefSetting::Flags(flags), |r| r.flags = flags)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetFilterMode(h_tex_ref: CUtexref, fm: c_uint) -> CUresult {
+    forward!(cuTexRefSetFilterMode(h_tex_ref, fm));
+    tex_ref_set(h_tex_ref, TexRefSetting::FilterMode(fm), |r| r.filter_mode = fm)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetAddressMode(h_tex_ref: CUtexref, dim: c_int, am: c_uint) -> CUresult {
+    forward!(cuTexRefSetAddressMode(h_tex_ref, dim, am));
+    if !(0..3).contains(&dim) { return CUDA_ERROR_INVALID_VALUE; }
+    tex_ref_set(h_tex_ref, TexRefSetting::AddressMode { dim, mode: am }, |r| r.address_mode[dim as usize] = am)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetMaxAnisotropy(h_tex_ref: CUtexref, max_aniso: c_uint) -> CUresult {
+    forward!(cuTexRefSetMaxAnisotropy(h_tex_ref, max_aniso));
+    tex_ref_set(h_tex_ref, TexRefSetting::MaxAnisotropy(max_aniso), |r| r.max_anisotropy = max_aniso)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefSetBorderColor(h_tex_ref: CUtexref, p_border_color: *mut f32) -> CUresult {
+    forward!(cuTexRefSetBorderColor(h_tex_ref, p_border_color));
+    if p_border_color.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let color = *(p_border_color as *const [f32; 4]);
+    tex_ref_set(h_tex_ref, TexRefSetting::BorderColor(color), |r| r.border_color = color)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetAddress_v2(pdptr: *mut CUdeviceptr, h_tex_ref: CUtexref) -> CUresult {
+    forward!(cuTexRefGetAddress_v2(pdptr, h_tex_ref));
+    match tex_ref(h_tex_ref).and_then(|r| r.address) {
+        Some(dptr) if !pdptr.is_null() => { *pdptr = dptr; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetArray(ph_array: *mut CUarray, h_tex_ref: CUtexref) -> CUresult {
+    forward!(cuTexRefGetArray(ph_array, h_tex_ref));
+    match tex_ref(h_tex_ref).and_then(|r| r.array) {
+        Some(array) if !ph_array.is_null() => { *ph_array = array as CUarray; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetFormat(p_format: *mut c_uint, p_num_channels: *mut c_int, h_tex_ref: CUtexref) -> CUresult {
+    forward!(cuTexRefGetFormat(p_format, p_num_channels, h_tex_ref));
+    let Some(r) = tex_ref(h_tex_ref) else { return CUDA_ERROR_INVALID_VALUE };
+    if !p_format.is_null() { *p_format = r.format; }
+    if !p_num_channels.is_null() { *p_num_channels = r.num_channels; }
+    CUDA_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetFlags(p_flags: *mut c_uint, h_tex_ref: CUtexref) -> CUresult {
+    forward!(cuTexRefGetFlags(p_flags, h_tex_ref));
+    match tex_ref(h_tex_ref) {
+        Some(r) if !p_flags.is_null() => { *p_flags = r.flags; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetFilterMode(pfm: *mut c_uint, h_tex_ref: CUtexref) -> CUresult {
+    forward!(cuTexRefGetFilterMode(pfm, h_tex_ref));
+    match tex_ref(h_tex_ref) {
+        Some(r) if !pfm.is_null() => { *pfm = r.filter_mode; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetAddressMode(pam: *mut c_uint, h_tex_ref: CUtexref, dim: c_int) -> CUresult {
+    forward!(cuTexRefGetAddressMode(pam, h_tex_ref, dim));
+    match tex_ref(h_tex_ref) {
+        Some(r) if !pam.is_null() && (0..3).contains(&dim) => { *pam = r.address_mode[dim as usize]; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetMaxAnisotropy(pmax_aniso: *mut c_int, h_tex_ref: CUtexref) -> CUresult {
+    forward!(cuTexRefGetMaxAnisotropy(pmax_aniso, h_tex_ref));
+    match tex_ref(h_tex_ref) {
+        Some(r) if !pmax_aniso.is_null() => { *pmax_aniso = r.max_anisotropy as c_int; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuTexRefGetBorderColor(p_border_color: *mut f32, h_tex_ref: CUtexref) -> CUresult {
+    forward!(cuTexRefGetBorderColor(p_border_color, h_tex_ref));
+    match tex_ref(h_tex_ref) {
+        Some(r) if !p_border_color.is_null() => { *(p_border_color as *mut [f32; 4]) = r.border_color; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuSurfRefSetArray(h_surf_ref: CUsurfref, h_array: CUarray, flags: c_uint) -> CUresult {
+    forward!(cuSurfRefSetArray(h_surf_ref, h_array, flags));
+    let handle = match texref::get(h_surf_ref as u64) { Some(r) if r.surface => r.handle, _ => return CUDA_ERROR_INVALID_VALUE };
+    let net_array = match handle_store::get_array(h_array as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::SurfRefSetArray { surfref: handle, array: net_array, flags }) {
+        CudaResponse::Success => {
+            texref::update(h_surf_ref as u64, |r| r.array = Some(h_array as u64));
+            CUDA_SUCCESS
+        }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuSurfRefGetArray(ph_array: *mut CUarray, h_surf_ref: CUsurfref) -> CUresult {
+    forward!(cuSurfRefGetArray(ph_array, h_surf_ref));
+    match texref::get(h_surf_ref as u64).filter(|r| r.surface).and_then(|r| r.array) {
+        Some(array) if !ph_array.is_null() => { *ph_array = array as CUarray; CUDA_SUCCESS }
+        _ => CUDA_ERROR_INVALID_VALUE,
+    }
+}
+
+// ── IPC Memory Handles ──────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cuIpcGetMemHandle(p_handle: *mut CUipcMemHandle, dptr: CUdeviceptr) -> CUresult {
+    forward!(cuIpcGetMemHandle(p_handle, dptr));
+    if p_handle.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let net_mem = match handle_store::get_mem_by_ptr(dptr) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::IpcGetMemHandle { dptr: net_mem }) {
+        CudaResponse::IpcMemHandle(handle) => { *p_handle = CUipcMemHandle::new(&handle); CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuIpcOpenMemHandle_v2(pdptr: *mut CUdeviceptr, handle: CUipcMemHandle, flags: c_uint) -> CUresult {
+    forward!(cuIpcOpenMemHandle_v2(pdptr, handle, flags));
+    if pdptr.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let Some(handle) = handle.handle() else { return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::IpcOpenMemHandle { handle, flags }) {
+        CudaResponse::IpcMemOpened(h) => { *pdptr = handle_store::store_mem(h); CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuIpcCloseMemHandle(dptr: CUdeviceptr) -> CUresult {
+    forward!(cuIpcCloseMemHandle(dptr));
+    let net_mem = match handle_store::get_mem_by_ptr(dptr) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::IpcCloseMemHandle { dptr: net_mem }) {
+        CudaResponse::Success => { handle_store::remove_mem(dptr); CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
 // ── Execution Control Extended ──────────────────────────────────────
 
 #[no_mangle]
@@ -1501,21 +2830,12 @@ pub unsafe extern "C" fn cuLaunchCooperativeKernel(
     shared_mem_bytes: c_uint, hstream: CUstream,
     kernel_params: *mut *mut c_void,
 ) -> CUresult {
+    forward!(cuLaunchCooperativeKernel(f, grid_dim_x, grid_dim_y, grid_dim_z, block_dim_x, block_dim_y, block_dim_z, shared_mem_bytes, hstream, kernel_params));
     let net_func = match handle_store::get_func(f as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
 
-    let mut params = Vec::new();
-    if !kernel_params.is_null() {
-        let mut i = 0;
-        loop {
-            let param_ptr = *kernel_params.add(i);
-            if param_ptr.is_null() { break; }
-            let data = std::slice::from_raw_parts(param_ptr as *const u8, std::mem::size_of::<u64>()).to_vec();
-            params.push(KernelParam { data });
-            i += 1;
-            if i >= 256 { break; }
-        }
-    }
+    let params = collect_kernel_params(f as u64, kernel_params);
+    host_mem::write_back_mapped();
 
     match send_cuda_command(CudaCommand::LaunchCooperativeKernel {
         func: net_func, grid_dim: [grid_dim_x, grid_dim_y, grid_dim_z],
@@ -1530,6 +2850,7 @@ pub unsafe extern "C" fn cuLaunchCooperativeKernel(
 
 #[no_mangle]
 pub unsafe extern "C" fn cuFuncGetAttribute(pi: *mut c_int, attrib: c_int, hfunc: CUfunction) -> CUresult {
+    forward!(cuFuncGetAttribute(pi, attrib, hfunc));
     if pi.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_func = match handle_store::get_func(hfunc as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::FuncGetAttribute { attrib, func: net_func }) {
@@ -1541,6 +2862,7 @@ pub unsafe extern "C" fn cuFuncGetAttribute(pi: *mut c_int, attrib: c_int, hfunc
 
 #[no_mangle]
 pub unsafe extern "C" fn cuFuncSetAttribute(hfunc: CUfunction, attrib: c_int, value: c_int) -> CUresult {
+    forward!(cuFuncSetAttribute(hfunc, attrib, value));
     let net_func = match handle_store::get_func(hfunc as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::FuncSetAttribute { attrib, func: net_func, value }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1551,6 +2873,7 @@ pub unsafe extern "C" fn cuFuncSetAttribute(hfunc: CUfunction, attrib: c_int, va
 
 #[no_mangle]
 pub unsafe extern "C" fn cuFuncSetCacheConfig(hfunc: CUfunction, config: c_int) -> CUresult {
+    forward!(cuFuncSetCacheConfig(hfunc, config));
     let net_func = match handle_store::get_func(hfunc as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::FuncSetCacheConfig { func: net_func, config }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1561,6 +2884,7 @@ pub unsafe extern "C" fn cuFuncSetCacheConfig(hfunc: CUfunction, config: c_int)
 
 #[no_mangle]
 pub unsafe extern "C" fn cuFuncSetSharedMemConfig(hfunc: CUfunction, config: c_int) -> CUresult {
+    forward!(cuFuncSetSharedMemConfig(hfunc, config));
     let net_func = match handle_store::get_func(hfunc as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::FuncSetSharedMemConfig { func: net_func, config }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1571,6 +2895,7 @@ pub unsafe extern "C" fn cuFuncSetSharedMemConfig(hfunc: CUfunction, config: c_i
 
 #[no_mangle]
 pub unsafe extern "C" fn cuFuncGetModule(hmod: *mut CUmodule, hfunc: CUfunction) -> CUresult {
+    forward!(cuFuncGetModule(hmod, hfunc));
     if hmod.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_func = match handle_store::get_func(hfunc as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::FuncGetModule { func: net_func }) {
@@ -1582,6 +2907,7 @@ pub unsafe extern "C" fn cuFuncGetModule(hmod: *mut CUmodule, hfunc: CUfunction)
 
 #[no_mangle]
 pub unsafe extern "C" fn cuFuncGetName(name: *mut *const c_char, hfunc: CUfunction) -> CUresult {
+    forward!(cuFuncGetName(name, hfunc));
     if name.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_func = match handle_store::get_func(hfunc as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::FuncGetName { func: net_func }) {
@@ -1597,6 +2923,7 @@ pub unsafe extern "C" fn cuFuncGetName(name: *mut *const c_char, hfunc: CUfuncti
 
 #[no_mangle]
 pub unsafe extern "C" fn cuOccupancyMaxActiveBlocksPerMultiprocessor(num_blocks: *mut c_int, func: CUfunction, block_size: c_int, dynamic_smem_size: usize) -> CUresult {
+    forward!(cuOccupancyMaxActiveBlocksPerMultiprocessor(num_blocks, func, block_size, dynamic_smem_size));
     if num_blocks.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_func = match handle_store::get_func(func as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::OccupancyMaxActiveBlocksPerMultiprocessor { func: net_func, block_size, dynamic_smem_size: dynamic_smem_size as u64 }) {
@@ -1608,9 +2935,10 @@ pub unsafe extern "C" fn cuOccupancyMaxActiveBlocksPerMultiprocessor(num_blocks:
 
 #[no_mangle]
 pub unsafe extern "C" fn cuOccupancyMaxActiveBlocksPerMultiprocessorWithFlags(num_blocks: *mut c_int, func: CUfunction, block_size: c_int, dynamic_smem_size: usize, flags: c_uint) -> CUresult {
+    forward!(cuOccupancyMaxActiveBlocksPerMultiprocessorWithFlags(num_blocks, func, block_size, dynamic_smem_size, flags));
     if num_blocks.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_func = match handle_store::get_func(func as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::OccupancyMaxActiveBlocksPerMultiprocessorWithFlags { func: net_func, block_size, dynamic_smem_size: dynamic_smem_size as u64, flags: flags as u32 }) {
+    match send_cuda_command(CudaCommand::OccupancyMaxActiveBlocksPerMultiprocessorWithFlags { func: net_func, block_size, dynamic_smem_size: dynamic_smem_size as u64, flags }) {
         CudaResponse::OccupancyBlocks(b) => { *num_blocks = b; CUDA_SUCCESS }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -1619,6 +2947,7 @@ pub unsafe extern "C" fn cuOccupancyMaxActiveBlocksPerMultiprocessorWithFlags(nu
 
 #[no_mangle]
 pub unsafe extern "C" fn cuOccupancyAvailableDynamicSMemPerBlock(dynamic_smem_size: *mut usize, func: CUfunction, num_blocks: c_int, block_size: c_int) -> CUresult {
+    forward!(cuOccupancyAvailableDynamicSMemPerBlock(dynamic_smem_size, func, num_blocks, block_size));
     if dynamic_smem_size.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_func = match handle_store::get_func(func as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::OccupancyAvailableDynamicSMemPerBlock { func: net_func, num_blocks, block_size }) {
@@ -1632,8 +2961,9 @@ pub unsafe extern "C" fn cuOccupancyAvailableDynamicSMemPerBlock(dynamic_smem_si
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamCreateWithPriority(phstream: *mut CUstream, flags: c_uint, priority: c_int) -> CUresult {
+    forward!(cuStreamCreateWithPriority(phstream, flags, priority));
     if phstream.is_null() { return CUDA_ERROR_INVALID_VALUE; }
-    match send_cuda_command(CudaCommand::StreamCreateWithPriority { flags: flags as u32, priority }) {
+    match send_cuda_command(CudaCommand::StreamCreateWithPriority { flags, priority }) {
         CudaResponse::Stream(handle) => { let id = handle_store::store_stream(handle); *phstream = id as CUstream; CUDA_SUCCESS }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -1642,9 +2972,10 @@ pub unsafe extern "C" fn cuStreamCreateWithPriority(phstream: *mut CUstream, fla
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamWaitEvent(hstream: CUstream, hevent: CUevent, flags: c_uint) -> CUresult {
+    forward!(cuStreamWaitEvent(hstream, hevent, flags));
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { match handle_store::get_stream(hstream as u64) { Some(h) => h, None => null_stream_handle() } };
     let net_event = match handle_store::get_event(hevent as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
-    match send_cuda_command(CudaCommand::StreamWaitEvent { stream: net_stream, event: net_event, flags: flags as u32 }) {
+    match send_cuda_command(CudaCommand::StreamWaitEvent { stream: net_stream, event: net_event, flags }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -1653,6 +2984,7 @@ pub unsafe extern "C" fn cuStreamWaitEvent(hstream: CUstream, hevent: CUevent, f
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamGetPriority(hstream: CUstream, priority: *mut c_int) -> CUresult {
+    forward!(cuStreamGetPriority(hstream, priority));
     if priority.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_stream = match handle_store::get_stream(hstream as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::StreamGetPriority { stream: net_stream }) {
@@ -1664,6 +2996,7 @@ pub unsafe extern "C" fn cuStreamGetPriority(hstream: CUstream, priority: *mut c
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamGetFlags(hstream: CUstream, flags: *mut c_uint) -> CUresult {
+    forward!(cuStreamGetFlags(hstream, flags));
     if flags.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_stream = match handle_store::get_stream(hstream as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::StreamGetFlags { stream: net_stream }) {
@@ -1675,6 +3008,7 @@ pub unsafe extern "C" fn cuStreamGetFlags(hstream: CUstream, flags: *mut c_uint)
 
 #[no_mangle]
 pub unsafe extern "C" fn cuStreamGetCtx_v2(hstream: CUstream, pctx: *mut CUcontext) -> CUresult {
+    forward!(cuStreamGetCtx_v2(hstream, pctx));
     if pctx.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_stream = match handle_store::get_stream(hstream as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::StreamGetCtx { stream: net_stream }) {
@@ -1688,9 +3022,10 @@ pub unsafe extern "C" fn cuStreamGetCtx_v2(hstream: CUstream, pctx: *mut CUconte
 
 #[no_mangle]
 pub unsafe extern "C" fn cuEventRecordWithFlags(hevent: CUevent, hstream: CUstream, flags: c_uint) -> CUresult {
+    forward!(cuEventRecordWithFlags(hevent, hstream, flags));
     let net_event = match handle_store::get_event(hevent as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
-    match send_cuda_command(CudaCommand::EventRecordWithFlags { event: net_event, stream: net_stream, flags: flags as u32 }) {
+    match send_cuda_command(CudaCommand::EventRecordWithFlags { event: net_event, stream: net_stream, flags }) {
         CudaResponse::Success => CUDA_SUCCESS,
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
@@ -1701,6 +3036,7 @@ pub unsafe extern "C" fn cuEventRecordWithFlags(hevent: CUevent, hstream: CUstre
 
 #[no_mangle]
 pub unsafe extern "C" fn cuPointerGetAttribute(data: *mut c_void, attribute: c_int, ptr: CUdeviceptr) -> CUresult {
+    forward!(cuPointerGetAttribute(data, attribute, ptr));
     if data.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_ptr = match handle_store::get_mem_by_ptr(ptr) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::PointerGetAttribute { attribute, ptr: net_ptr }) {
@@ -1712,6 +3048,7 @@ pub unsafe extern "C" fn cuPointerGetAttribute(data: *mut c_void, attribute: c_i
 
 #[no_mangle]
 pub unsafe extern "C" fn cuPointerSetAttribute(value: *const c_void, attribute: c_int, ptr: CUdeviceptr) -> CUresult {
+    forward!(cuPointerSetAttribute(value, attribute, ptr));
     let net_ptr = match handle_store::get_mem_by_ptr(ptr) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let val = if !value.is_null() { *(value as *const u64) } else { 0 };
     match send_cuda_command(CudaCommand::PointerSetAttribute { attribute, ptr: net_ptr, value: val }) {
@@ -1725,6 +3062,7 @@ pub unsafe extern "C" fn cuPointerSetAttribute(value: *const c_void, attribute:
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemPoolDestroy(pool: CUmemoryPool) -> CUresult {
+    forward!(cuMemPoolDestroy(pool));
     let net_pool = match handle_store::get_mempool(pool as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::MemPoolDestroy { pool: net_pool }) {
         CudaResponse::Success => { handle_store::remove_mempool(pool as u64); CUDA_SUCCESS }
@@ -1735,6 +3073,7 @@ pub unsafe extern "C" fn cuMemPoolDestroy(pool: CUmemoryPool) -> CUresult {
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemPoolTrimTo(pool: CUmemoryPool, min_bytes_to_keep: usize) -> CUresult {
+    forward!(cuMemPoolTrimTo(pool, min_bytes_to_keep));
     let net_pool = match handle_store::get_mempool(pool as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     match send_cuda_command(CudaCommand::MemPoolTrimTo { pool: net_pool, min_bytes_to_keep: min_bytes_to_keep as u64 }) {
         CudaResponse::Success => CUDA_SUCCESS,
@@ -1745,6 +3084,7 @@ pub unsafe extern "C" fn cuMemPoolTrimTo(pool: CUmemoryPool, min_bytes_to_keep:
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemAllocAsync(dptr: *mut CUdeviceptr, bytesize: usize, hstream: CUstream) -> CUresult {
+    forward!(cuMemAllocAsync(dptr, bytesize, hstream));
     if dptr.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
     match send_cuda_command(CudaCommand::MemAllocAsync { byte_size: bytesize as u64, stream: net_stream }) {
@@ -1756,10 +3096,11 @@ pub unsafe extern "C" fn cuMemAllocAsync(dptr: *mut CUdeviceptr, bytesize: usize
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemFreeAsync(dptr: CUdeviceptr, hstream: CUstream) -> CUresult {
+    forward!(cuMemFreeAsync(dptr, hstream));
     let net_ptr = match handle_store::get_mem_by_ptr(dptr) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
     match send_cuda_command(CudaCommand::MemFreeAsync { dptr: net_ptr, stream: net_stream }) {
-        CudaResponse::Success => { handle_store::remove_mem(dptr); CUDA_SUCCESS }
+        CudaResponse::Success => { handle_store::remove_mem(dptr); managed::free(dptr); CUDA_SUCCESS }
         CudaResponse::Error { code, .. } => code,
         _ => CUDA_ERROR_UNKNOWN,
     }
@@ -1767,6 +3108,7 @@ pub unsafe extern "C" fn cuMemFreeAsync(dptr: CUdeviceptr, hstream: CUstream) ->
 
 #[no_mangle]
 pub unsafe extern "C" fn cuMemAllocFromPoolAsync(dptr: *mut CUdeviceptr, bytesize: usize, pool: CUmemoryPool, hstream: CUstream) -> CUresult {
+    forward!(cuMemAllocFromPoolAsync(dptr, bytesize, pool, hstream));
     if dptr.is_null() { return CUDA_ERROR_INVALID_VALUE; }
     let net_pool = match handle_store::get_mempool(pool as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
     let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
@@ -1777,6 +3119,143 @@ pub unsafe extern "C" fn cuMemAllocFromPoolAsync(dptr: *mut CUdeviceptr, bytesiz
     }
 }
 
+// ── Graphs ──────────────────────────────────────────────────────────
+//
+// Capture happens on the server: kernels, device-to-device copies and
+// memsets queued on a capturing stream are recorded into a graph there, and
+// a launch replays the whole graph with one command. Copies that involve
+// host memory can't be captured, since the server has no access to it at
+// replay time.
+
+/// cuStreamBeginCapture from CUDA 10.0, which had no mode.
+#[no_mangle]
+pub unsafe extern "C" fn cuStreamBeginCapture(hstream: CUstream) -> CUresult {
+    forward!(cuStreamBeginCapture(hstream));
+    cuStreamBeginCapture_v2(hstream, 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuStreamBeginCapture_v2(hstream: CUstream, mode: c_int) -> CUresult {
+    forward!(cuStreamBeginCapture_v2(hstream, mode));
+    // The legacy default stream can't be captured.
+    let net_stream = match handle_store::get_stream(hstream as u64) { Some(h) => h, None => return CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED };
+    match send_cuda_command(CudaCommand::StreamBeginCapture { stream: net_stream, mode: mode as u32 }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuStreamEndCapture(hstream: CUstream, phgraph: *mut CUgraph) -> CUresult {
+    forward!(cuStreamEndCapture(hstream, phgraph));
+    if phgraph.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let net_stream = match handle_store::get_stream(hstream as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::StreamEndCapture { stream: net_stream }) {
+        CudaResponse::Graph(handle) => { *phgraph = handle_store::store_graph(handle) as CUgraph; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuStreamIsCapturing(hstream: CUstream, status: *mut c_int) -> CUresult {
+    forward!(cuStreamIsCapturing(hstream, status));
+    if status.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+    match send_cuda_command(CudaCommand::StreamIsCapturing { stream: net_stream }) {
+        CudaResponse::StreamCaptureStatus(s) => { *status = s as c_int; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphCreate(phgraph: *mut CUgraph, flags: c_uint) -> CUresult {
+    forward!(cuGraphCreate(phgraph, flags));
+    if phgraph.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    match send_cuda_command(CudaCommand::GraphCreate { flags }) {
+        CudaResponse::Graph(handle) => { *phgraph = handle_store::store_graph(handle) as CUgraph; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphDestroy(hgraph: CUgraph) -> CUresult {
+    forward!(cuGraphDestroy(hgraph));
+    let net_graph = match handle_store::get_graph(hgraph as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::GraphDestroy { graph: net_graph }) {
+        CudaResponse::Success => { handle_store::remove_graph(hgraph as u64); CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+/// cuGraphInstantiate before CUDA 12, with an error node and log buffer.
+/// Errors are only reported through the return code.
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphInstantiate_v2(phexec: *mut CUgraphExec, hgraph: CUgraph, error_node: *mut *mut c_void, log_buffer: *mut c_char, buffer_size: usize) -> CUresult {
+    forward!(cuGraphInstantiate_v2(phexec, hgraph, error_node, log_buffer, buffer_size));
+    if !error_node.is_null() { *error_node = std::ptr::null_mut(); }
+    if !log_buffer.is_null() && buffer_size > 0 { *log_buffer = 0; }
+    cuGraphInstantiateWithFlags(phexec, hgraph, 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphInstantiate(phexec: *mut CUgraphExec, hgraph: CUgraph, error_node: *mut *mut c_void, log_buffer: *mut c_char, buffer_size: usize) -> CUresult {
+    forward!(cuGraphInstantiate(phexec, hgraph, error_node, log_buffer, buffer_size));
+    cuGraphInstantiate_v2(phexec, hgraph, error_node, log_buffer, buffer_size)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphInstantiateWithFlags(phexec: *mut CUgraphExec, hgraph: CUgraph, flags: u64) -> CUresult {
+    forward!(cuGraphInstantiateWithFlags(phexec, hgraph, flags));
+    if phexec.is_null() { return CUDA_ERROR_INVALID_VALUE; }
+    let net_graph = match handle_store::get_graph(hgraph as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::GraphInstantiate { graph: net_graph, flags }) {
+        CudaResponse::GraphExec(handle) => { *phexec = handle_store::store_graph_exec(handle) as CUgraphExec; CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphExecDestroy(hexec: CUgraphExec) -> CUresult {
+    forward!(cuGraphExecDestroy(hexec));
+    let net_exec = match handle_store::get_graph_exec(hexec as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    match send_cuda_command(CudaCommand::GraphExecDestroy { exec: net_exec }) {
+        CudaResponse::Success => { handle_store::remove_graph_exec(hexec as u64); CUDA_SUCCESS }
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphLaunch(hexec: CUgraphExec, hstream: CUstream) -> CUresult {
+    forward!(cuGraphLaunch(hexec, hstream));
+    let net_exec = match handle_store::get_graph_exec(hexec as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+    host_mem::write_back_mapped();
+    match send_cuda_command(CudaCommand::GraphLaunch { exec: net_exec, stream: net_stream }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cuGraphUpload(hexec: CUgraphExec, hstream: CUstream) -> CUresult {
+    forward!(cuGraphUpload(hexec, hstream));
+    let net_exec = match handle_store::get_graph_exec(hexec as u64) { Some(h) => h, None => return CUDA_ERROR_INVALID_VALUE };
+    let net_stream = if (hstream as u64) == 0 { null_stream_handle() } else { handle_store::get_stream(hstream as u64).unwrap_or_else(null_stream_handle) };
+    match send_cuda_command(CudaCommand::GraphUpload { exec: net_exec, stream: net_stream }) {
+        CudaResponse::Success => CUDA_SUCCESS,
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
 // ── Helper Functions ────────────────────────────────────────────────
 
 /// Detect whether a module image is PTX (text) or cubin (binary) and return the data.
@@ -1813,3 +3292,72 @@ unsafe fn parse_elf_size(ptr: *const u8) -> Option<usize> {
     let total = e_shoff + (e_shentsize * e_shnum);
     Some(total as usize)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocation(resource_id: u64) -> NetworkHandle {
+        NetworkHandle {
+            server_id: 1,
+            session_id: 1,
+            resource_id,
+            resource_type: ResourceType::CuDevicePtr,
+        }
+    }
+
+    #[test]
+    fn copy_direction_follows_the_handle_store() {
+        let a = handle_store::store_mem(allocation(1));
+        let b = handle_store::store_mem(allocation(2));
+        let host = [0u8; 16];
+        let host = host.as_ptr() as CUdeviceptr;
+
+        assert_eq!(copy_direction(a, host), CopyDirection::HostToDevice);
+        assert_eq!(copy_direction(host, a), CopyDirection::DeviceToHost);
+        assert_eq!(copy_direction(a, b), CopyDirection::DeviceToDevice);
+        // Pointers into an allocation count as well as its base.
+        assert_eq!(copy_direction(a + 256, b + 8), CopyDirection::DeviceToDevice);
+        assert_eq!(copy_direction(host, host + 8), CopyDirection::HostToHost);
+
+        // A freed allocation is no longer the interposer's.
+        handle_store::remove_mem(b);
+        assert_eq!(copy_direction(a, b), CopyDirection::HostToDevice);
+        handle_store::remove_mem(a);
+    }
+
+    #[test]
+    fn unknown_pointers_are_host_memory() {
+        // Pointers the interposer never handed out, including ones in the
+        // range it hands device pointers out from, are taken to be host
+        // memory.
+        for ptr in [0x7f00_0020_0000, (1 << 48) + (1 << 40), u64::MAX - 15] {
+            assert_eq!(copy_direction(ptr, 0x1000), CopyDirection::HostToHost);
+        }
+
+        // Managed memory is recognised by its range, below the device range.
+        let base = 0x7f00_1000_0000;
+        handle_store::store_managed_mem(base, 4096, allocation(3));
+        assert_eq!(copy_direction(base + 4095, 0x1000), CopyDirection::HostToDevice);
+        assert_eq!(copy_direction(base + 4096, 0x1000), CopyDirection::HostToHost);
+        handle_store::remove_managed_mem(base);
+    }
+
+    #[test]
+    fn host_to_host_copies_locally() {
+        let src: Vec<u8> = (0..64).collect();
+        let mut dst = vec![0u8; 64];
+        let result = unsafe { copy_host_to_host(dst.as_mut_ptr() as CUdeviceptr, src.as_ptr() as CUdeviceptr, 64) };
+        assert_eq!(result, CUDA_SUCCESS);
+        assert_eq!(dst, src);
+
+        // Overlapping ranges are copied as memmove would.
+        let mut buf: Vec<u8> = (0..64).collect();
+        let base = buf.as_mut_ptr() as CUdeviceptr;
+        assert_eq!(unsafe { copy_host_to_host(base + 8, base, 32) }, CUDA_SUCCESS);
+        assert_eq!(&buf[8..40], &(0..32).collect::<Vec<u8>>()[..]);
+
+        assert_eq!(unsafe { copy_host_to_host(0, base, 8) }, CUDA_ERROR_INVALID_VALUE);
+        assert_eq!(unsafe { copy_host_to_host(base, 0, 8) }, CUDA_ERROR_INVALID_VALUE);
+    }
+}
@@ -0,0 +1,578 @@
+//! Managed (unified) memory.
+//!
+//! Applications dereference cuMemAllocManaged pointers on the host, so the
+//! pointer handed out is the address of a region in this process, backed by
+//! the managed allocation on the server. The region moves lazily in blocks
+//! of `BLOCK_SIZE`: a block is fetched from the server the first time the
+//! application touches it. Before work that may use an allocation on the
+//! device (kernel and graph launches, and copies and fills naming it), the
+//! blocks the application changed are written back and every block is
+//! dropped, so the next touch fetches the device's version.
+//!
+//! Touches are caught with userfaultfd on Linux, where a handler thread fills
+//! in missing pages, and with no-access pages and a vectored exception
+//! handler on Windows. Where neither is available (userfaultfd can be turned
+//! off with `vm.unprivileged_userfaultfd`) the region stays readable and
+//! dropped blocks are fetched again at synchronization points instead, like
+//! host-mapped memory.
+//!
+//! As on devices without concurrent managed access, the application has to
+//! synchronize before touching memory the device may still be using. Blocks
+//! travel over a connection of their own, so filling in a page never waits
+//! on the connection the faulting thread may be using.
+
+use std::alloc::Layout;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::Hasher;
+use std::sync::OnceLock;
+
+use parking_lot::Mutex;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse, Memcpy3DParams, MemcpyRegion, MemoryType};
+use rgpu_protocol::handle::NetworkHandle;
+use tracing::error;
+
+use crate::handle_store;
+use crate::ipc_client::IpcClient;
+use crate::{send_cuda_command, CUresult, CUDA_ERROR_OUT_OF_MEMORY, CUDA_ERROR_UNKNOWN};
+
+/// Unit of transfer and residency; a multiple of the page size everywhere.
+const BLOCK_SIZE: usize = 64 * 1024;
+
+struct Allocation {
+    handle: NetworkHandle,
+    /// Size requested; the region is rounded up to whole blocks
+    byte_size: usize,
+    /// Content hash of each block when it was last in sync with the server;
+    /// `None` if the block isn't resident (or, in an untrapped region, may be
+    /// stale)
+    blocks: Vec<Option<u64>>,
+    /// Set for regions allocated on the heap because touches can't be
+    /// caught; they are synced at synchronization points instead.
+    layout: Option<Layout>,
+}
+
+impl Allocation {
+    fn region_size(&self) -> usize {
+        self.blocks.len() * BLOCK_SIZE
+    }
+
+    /// Bytes of the allocation in `blocks` (the last block may be partial).
+    fn span(&self, blocks: std::ops::Range<usize>) -> (usize, usize) {
+        let start = blocks.start * BLOCK_SIZE;
+        let end = (blocks.end * BLOCK_SIZE).min(self.byte_size);
+        (start, end.saturating_sub(start))
+    }
+}
+
+fn allocations() -> &'static Mutex<BTreeMap<u64, Allocation>> {
+    static ALLOCATIONS: OnceLock<Mutex<BTreeMap<u64, Allocation>>> = OnceLock::new();
+    ALLOCATIONS.get_or_init(Default::default)
+}
+
+fn client() -> &'static IpcClient {
+    static CLIENT: OnceLock<IpcClient> = OnceLock::new();
+    CLIENT.get_or_init(|| IpcClient::new(&rgpu_common::platform::default_ipc_path()))
+}
+
+fn block_hash(data: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(data);
+    hasher.finish()
+}
+
+/// Maximal runs of consecutive indices, as ranges.
+fn runs(indices: impl IntoIterator<Item = usize>) -> Vec<std::ops::Range<usize>> {
+    let mut runs: Vec<std::ops::Range<usize>> = Vec::new();
+    for index in indices {
+        match runs.last_mut() {
+            Some(run) if run.end == index => run.end += 1,
+            _ => runs.push(index..index + 1),
+        }
+    }
+    runs
+}
+
+/// `len` bytes at `offset` into `handle`, as one side of a 1D copy.
+fn device_region(handle: NetworkHandle, offset: usize, len: usize) -> MemcpyRegion {
+    MemcpyRegion {
+        memory_type: MemoryType::Device,
+        handle: Some(handle),
+        x_in_bytes: offset as u64,
+        y: 0,
+        z: 0,
+        pitch: len as u64,
+        height: 1,
+    }
+}
+
+fn host_region(len: usize) -> MemcpyRegion {
+    MemcpyRegion {
+        memory_type: MemoryType::Host,
+        handle: None,
+        x_in_bytes: 0,
+        y: 0,
+        z: 0,
+        pitch: len as u64,
+        height: 1,
+    }
+}
+
+/// Read `len` bytes at `offset` into the server's allocation.
+fn fetch(handle: NetworkHandle, offset: usize, len: usize) -> Option<Vec<u8>> {
+    let params = Memcpy3DParams {
+        src: device_region(handle, offset, len),
+        dst: host_region(len),
+        width_in_bytes: len as u64,
+        height: 1,
+        depth: 1,
+    };
+    match client().send_command(CudaCommand::Memcpy3D { params: Box::new(params), src_data: Vec::new() }) {
+        Ok(CudaResponse::MemoryData(data)) if data.len() == len => Some(data),
+        other => {
+            error!("managed memory read of {} bytes failed: {:?}", len, other);
+            None
+        }
+    }
+}
+
+/// Write `data` at `offset` into the server's allocation.
+fn store(handle: NetworkHandle, offset: usize, data: Vec<u8>) {
+    let len = data.len();
+    let params = Memcpy3DParams {
+        src: host_region(len),
+        dst: device_region(handle, offset, len),
+        width_in_bytes: len as u64,
+        height: 1,
+        depth: 1,
+    };
+    match client().send_command(CudaCommand::Memcpy3D { params: Box::new(params), src_data: data }) {
+        Ok(CudaResponse::Success) => {}
+        other => error!("managed memory write of {} bytes failed: {:?}", len, other),
+    }
+}
+
+/// Allocate `byte_size` bytes of managed memory; the pointer to hand out.
+pub fn alloc(byte_size: usize, flags: u32) -> Result<u64, CUresult> {
+    let handle = match send_cuda_command(CudaCommand::MemAllocManaged { byte_size: byte_size as u64, flags }) {
+        CudaResponse::MemAllocated(handle) => handle,
+        CudaResponse::Error { code, .. } => return Err(code),
+        _ => return Err(CUDA_ERROR_UNKNOWN),
+    };
+    let block_count = byte_size.max(1).div_ceil(BLOCK_SIZE);
+    let region_size = block_count * BLOCK_SIZE;
+
+    let (base, blocks, layout) = match sys::reserve(region_size) {
+        // Nothing is resident until it's touched.
+        Some(base) => (base, vec![None; block_count], None),
+        None => {
+            let layout = Layout::from_size_align(region_size, BLOCK_SIZE).map_err(|_| CUDA_ERROR_OUT_OF_MEMORY)?;
+            let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+            if ptr.is_null() {
+                send_cuda_command(CudaCommand::MemFree { dptr: handle });
+                return Err(CUDA_ERROR_OUT_OF_MEMORY);
+            }
+            // The contents of a fresh allocation are undefined; zeros will do
+            // until the device writes.
+            let zeros = block_hash(&vec![0u8; BLOCK_SIZE]);
+            (ptr as u64, vec![Some(zeros); block_count], Some(layout))
+        }
+    };
+    handle_store::store_managed_mem(base, region_size as u64, handle);
+    allocations().lock().insert(
+        base,
+        Allocation {
+            handle,
+            byte_size,
+            blocks,
+            layout,
+        },
+    );
+    Ok(base)
+}
+
+/// Release the local region of a managed allocation freed on the server.
+pub fn free(base: u64) {
+    let Some(allocation) = allocations().lock().remove(&base) else {
+        return;
+    };
+    handle_store::remove_managed_mem(base);
+    match allocation.layout {
+        Some(layout) => unsafe { std::alloc::dealloc(base as *mut u8, layout) },
+        None => sys::release(base, allocation.region_size()),
+    }
+}
+
+/// Prepare managed allocations for `command`: write back what the
+/// application changed in those it may use on the device and drop their
+/// local blocks. Takes `&mut` only to visit the command's handles.
+pub fn before_command(command: &mut CudaCommand) {
+    let mut allocations = allocations().lock();
+    if allocations.is_empty() || matches!(command, CudaCommand::MemFree { .. } | CudaCommand::MemFreeAsync { .. }) {
+        return;
+    }
+    // Kernels can reach any allocation through pointers in memory.
+    let everything = matches!(
+        command,
+        CudaCommand::LaunchKernel { .. } | CudaCommand::LaunchCooperativeKernel { .. } | CudaCommand::GraphLaunch { .. }
+    );
+    let mut touched = Vec::new();
+    if !everything {
+        command.handles_mut(|h| touched.push(*h));
+    }
+    for (&base, allocation) in allocations.iter_mut() {
+        if everything || touched.contains(&allocation.handle) {
+            write_back(base, allocation);
+            drop_blocks(base, allocation);
+        }
+    }
+}
+
+/// Send the resident blocks whose contents changed since they were synced.
+fn write_back(base: u64, allocation: &mut Allocation) {
+    let mut changed = Vec::new();
+    for (index, synced) in allocation.blocks.iter_mut().enumerate() {
+        let Some(hash) = synced else {
+            continue;
+        };
+        let block = unsafe { std::slice::from_raw_parts((base as usize + index * BLOCK_SIZE) as *const u8, BLOCK_SIZE) };
+        let current = block_hash(block);
+        if current != *hash {
+            *hash = current;
+            changed.push(index);
+        }
+    }
+    for run in runs(changed) {
+        let (offset, len) = allocation.span(run);
+        let data = unsafe { std::slice::from_raw_parts((base as usize + offset) as *const u8, len) };
+        store(allocation.handle, offset, data.to_vec());
+    }
+}
+
+fn drop_blocks(base: u64, allocation: &mut Allocation) {
+    if allocation.layout.is_none() {
+        let resident = allocation.blocks.iter().enumerate().filter(|(_, b)| b.is_some()).map(|(i, _)| i);
+        for run in runs(resident) {
+            sys::evict(base + (run.start * BLOCK_SIZE) as u64, run.len() * BLOCK_SIZE);
+        }
+    }
+    allocation.blocks.iter_mut().for_each(|b| *b = None);
+}
+
+/// Fetch what the device may have changed in untrapped regions, at a
+/// synchronization point.
+pub fn read_back() {
+    let mut allocations = allocations().lock();
+    for (&base, allocation) in allocations.iter_mut().filter(|(_, a)| a.layout.is_some()) {
+        let stale = allocation.blocks.iter().enumerate().filter(|(_, b)| b.is_none()).map(|(i, _)| i);
+        for run in runs(stale.collect::<Vec<_>>()) {
+            let (offset, len) = allocation.span(run.clone());
+            let Some(data) = fetch(allocation.handle, offset, len) else {
+                continue;
+            };
+            let at = (base as usize + offset) as *mut u8;
+            unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), at, len) };
+            for index in run {
+                let block = unsafe { std::slice::from_raw_parts((base as usize + index * BLOCK_SIZE) as *const u8, BLOCK_SIZE) };
+                allocation.blocks[index] = Some(block_hash(block));
+            }
+        }
+    }
+}
+
+/// Fill in the block containing `addr`, which the application touched.
+/// False if it isn't a missing block of a managed allocation.
+fn fault(addr: u64) -> bool {
+    let mut allocations = allocations().lock();
+    let Some((&base, allocation)) = allocations.range_mut(..=addr).next_back() else {
+        return false;
+    };
+    if allocation.layout.is_some() || addr >= base + allocation.region_size() as u64 {
+        return false;
+    }
+    let index = (addr - base) as usize / BLOCK_SIZE;
+    let block_base = base + (index * BLOCK_SIZE) as u64;
+    if allocation.blocks[index].is_some() {
+        return sys::already_resident(block_base);
+    }
+    let mut block = vec![0u8; BLOCK_SIZE];
+    let (offset, len) = allocation.span(index..index + 1);
+    if len > 0 {
+        // A block that can't be fetched reads as zeros rather than hanging
+        // the thread that touched it.
+        if let Some(data) = fetch(allocation.handle, offset, len) {
+            block[..len].copy_from_slice(&data);
+        }
+    }
+    sys::install(block_base, &block);
+    allocation.blocks[index] = Some(block_hash(&block));
+    true
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    //! userfaultfd: regions are registered for missing-page faults, which a
+    //! handler thread resolves by copying the fetched block in.
+
+    use std::ffi::c_int;
+    use std::sync::OnceLock;
+
+    use tracing::{error, warn};
+
+    use super::BLOCK_SIZE;
+
+    const UFFD_API: u64 = 0xAA;
+    const UFFD_USER_MODE_ONLY: c_int = 1;
+    const UFFD_EVENT_PAGEFAULT: u8 = 0x12;
+    const UFFDIO_REGISTER_MODE_MISSING: u64 = 1;
+    const UFFDIO_API: libc::Ioctl = 0xC018_AA3F;
+    const UFFDIO_REGISTER: libc::Ioctl = 0xC020_AA00;
+    const UFFDIO_WAKE: libc::Ioctl = 0x8010_AA02;
+    const UFFDIO_COPY: libc::Ioctl = 0xC028_AA03;
+
+    #[repr(C)]
+    struct UffdioApi {
+        api: u64,
+        features: u64,
+        ioctls: u64,
+    }
+
+    #[repr(C)]
+    struct UffdioRange {
+        start: u64,
+        len: u64,
+    }
+
+    #[repr(C)]
+    struct UffdioRegister {
+        range: UffdioRange,
+        mode: u64,
+        ioctls: u64,
+    }
+
+    #[repr(C)]
+    struct UffdioCopy {
+        dst: u64,
+        src: u64,
+        len: u64,
+        mode: u64,
+        copy: i64,
+    }
+
+    /// `struct uffd_msg` with the page fault member of its union.
+    #[repr(C)]
+    struct UffdMsg {
+        event: u8,
+        reserved1: u8,
+        reserved2: u16,
+        reserved3: u32,
+        flags: u64,
+        address: u64,
+        ptid: u32,
+        _pad: u32,
+    }
+
+    /// The userfaultfd, with its handler thread started; `None` if the
+    /// kernel won't give us one.
+    fn uffd() -> Option<c_int> {
+        static UFFD: OnceLock<Option<c_int>> = OnceLock::new();
+        *UFFD.get_or_init(|| unsafe {
+            // Unprivileged processes may only handle faults from user mode,
+            // which is all we need.
+            let mut fd = libc::syscall(libc::SYS_userfaultfd, libc::O_CLOEXEC | UFFD_USER_MODE_ONLY) as c_int;
+            if fd < 0 {
+                fd = libc::syscall(libc::SYS_userfaultfd, libc::O_CLOEXEC) as c_int;
+            }
+            if fd < 0 {
+                warn!(
+                    "userfaultfd unavailable ({}); managed memory is synced at synchronization points",
+                    std::io::Error::last_os_error()
+                );
+                return None;
+            }
+            let mut api = UffdioApi { api: UFFD_API, features: 0, ioctls: 0 };
+            if libc::ioctl(fd, UFFDIO_API, &mut api) != 0 {
+                warn!("userfaultfd handshake failed: {}", std::io::Error::last_os_error());
+                libc::close(fd);
+                return None;
+            }
+            let started = std::thread::Builder::new()
+                .name("rgpu-managed-faults".to_string())
+                .spawn(move || serve(fd));
+            if let Err(e) = started {
+                warn!("could not start the managed memory fault handler: {}", e);
+                libc::close(fd);
+                return None;
+            }
+            Some(fd)
+        })
+    }
+
+    fn serve(fd: c_int) {
+        loop {
+            let mut msg = std::mem::MaybeUninit::<UffdMsg>::zeroed();
+            let n = unsafe { libc::read(fd, msg.as_mut_ptr().cast(), std::mem::size_of::<UffdMsg>()) };
+            if n < 0 {
+                let e = std::io::Error::last_os_error();
+                if e.kind() == std::io::ErrorKind::Interrupted {
+                    continue;
+                }
+                error!("managed memory fault handler stopped: {}", e);
+                return;
+            }
+            let msg = unsafe { msg.assume_init() };
+            // Faults in a region freed meanwhile are dropped with it.
+            if n as usize == std::mem::size_of::<UffdMsg>() && msg.event == UFFD_EVENT_PAGEFAULT {
+                super::fault(msg.address);
+            }
+        }
+    }
+
+    pub fn reserve(size: usize) -> Option<u64> {
+        let fd = uffd()?;
+        unsafe {
+            let ptr = libc::mmap(
+                std::ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if ptr == libc::MAP_FAILED {
+                return None;
+            }
+            let mut register = UffdioRegister {
+                range: UffdioRange { start: ptr as u64, len: size as u64 },
+                mode: UFFDIO_REGISTER_MODE_MISSING,
+                ioctls: 0,
+            };
+            if libc::ioctl(fd, UFFDIO_REGISTER, &mut register) != 0 {
+                warn!("userfaultfd registration failed: {}", std::io::Error::last_os_error());
+                libc::munmap(ptr, size);
+                return None;
+            }
+            Some(ptr as u64)
+        }
+    }
+
+    /// Unmapping also unregisters the region.
+    pub fn release(base: u64, size: usize) {
+        unsafe { libc::munmap(base as *mut libc::c_void, size) };
+    }
+
+    /// Place a fetched block, waking the threads waiting for it.
+    pub fn install(at: u64, data: &[u8]) {
+        let Some(fd) = uffd() else {
+            return;
+        };
+        let mut copy = UffdioCopy {
+            dst: at,
+            src: data.as_ptr() as u64,
+            len: data.len() as u64,
+            mode: 0,
+            copy: 0,
+        };
+        if unsafe { libc::ioctl(fd, UFFDIO_COPY, &mut copy) } != 0 {
+            let e = std::io::Error::last_os_error();
+            if e.raw_os_error() != Some(libc::EEXIST) {
+                error!("could not fill in managed memory at {:#x}: {}", at, e);
+            }
+        }
+    }
+
+    /// Make the pages missing again, so the next touch faults.
+    pub fn evict(at: u64, len: usize) {
+        unsafe { libc::madvise(at as *mut libc::c_void, len, libc::MADV_DONTNEED) };
+    }
+
+    /// A fault on a block filled in meanwhile: wake whoever is still waiting.
+    pub fn already_resident(at: u64) -> bool {
+        if let Some(fd) = uffd() {
+            let mut range = UffdioRange { start: at, len: BLOCK_SIZE as u64 };
+            unsafe { libc::ioctl(fd, UFFDIO_WAKE, &mut range) };
+        }
+        true
+    }
+}
+
+#[cfg(windows)]
+mod sys {
+    //! Regions start out inaccessible; the access violation on a touch is
+    //! resolved on the faulting thread by a vectored exception handler.
+
+    use std::ffi::c_void;
+    use std::sync::OnceLock;
+
+    use windows_sys::Win32::Foundation::STATUS_ACCESS_VIOLATION;
+    use windows_sys::Win32::System::Diagnostics::Debug::{AddVectoredExceptionHandler, EXCEPTION_POINTERS};
+    use windows_sys::Win32::System::Memory::{
+        VirtualAlloc, VirtualFree, VirtualProtect, MEM_COMMIT, MEM_RELEASE, MEM_RESERVE, PAGE_NOACCESS,
+        PAGE_PROTECTION_FLAGS, PAGE_READWRITE,
+    };
+
+    const EXCEPTION_CONTINUE_EXECUTION: i32 = -1;
+    const EXCEPTION_CONTINUE_SEARCH: i32 = 0;
+
+    unsafe extern "system" fn on_exception(info: *mut EXCEPTION_POINTERS) -> i32 {
+        let record = &*(*info).ExceptionRecord;
+        // The second parameter of an access violation is the address.
+        if record.ExceptionCode == STATUS_ACCESS_VIOLATION
+            && record.NumberParameters >= 2
+            && super::fault(record.ExceptionInformation[1] as u64)
+        {
+            EXCEPTION_CONTINUE_EXECUTION
+        } else {
+            EXCEPTION_CONTINUE_SEARCH
+        }
+    }
+
+    fn protect(at: u64, len: usize, protection: PAGE_PROTECTION_FLAGS) {
+        let mut old = 0;
+        unsafe { VirtualProtect(at as *const c_void, len, protection, &mut old) };
+    }
+
+    pub fn reserve(size: usize) -> Option<u64> {
+        static HANDLER: OnceLock<bool> = OnceLock::new();
+        let installed = *HANDLER.get_or_init(|| unsafe { !AddVectoredExceptionHandler(1, Some(on_exception)).is_null() });
+        if !installed {
+            return None;
+        }
+        let ptr = unsafe { VirtualAlloc(std::ptr::null(), size, MEM_RESERVE | MEM_COMMIT, PAGE_NOACCESS) };
+        (!ptr.is_null()).then_some(ptr as u64)
+    }
+
+    pub fn release(base: u64, _size: usize) {
+        unsafe { VirtualFree(base as *mut c_void, 0, MEM_RELEASE) };
+    }
+
+    pub fn install(at: u64, data: &[u8]) {
+        protect(at, data.len(), PAGE_READWRITE);
+        unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), at as *mut u8, data.len()) };
+    }
+
+    pub fn evict(at: u64, len: usize) {
+        protect(at, len, PAGE_NOACCESS);
+    }
+
+    /// The block is accessible, so the violation was something else.
+    pub fn already_resident(_at: u64) -> bool {
+        false
+    }
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+mod sys {
+    //! No way to catch touches: every region is synced at synchronization
+    //! points.
+
+    pub fn reserve(_size: usize) -> Option<u64> {
+        None
+    }
+    pub fn release(_base: u64, _size: usize) {}
+    pub fn install(_at: u64, _data: &[u8]) {}
+    pub fn evict(_at: u64, _len: usize) {}
+    pub fn already_resident(_at: u64) -> bool {
+        false
+    }
+}
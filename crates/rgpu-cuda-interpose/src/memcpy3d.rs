@@ -0,0 +1,353 @@
+//! cuMemcpy2D / cuMemcpy3D descriptors.
+//!
+//! Device sides of a copy, allocations or arrays, are sent as handles plus
+//! offsets. Host memory can't be reached from the server, so a host source
+//! has its rows packed into the command, and a host destination comes back
+//! packed and is scattered into the caller's pitched buffer here.
+
+use std::ffi::{c_uint, c_void};
+
+use rgpu_protocol::cuda_commands::{Memcpy3DParams, MemcpyRegion, MemoryType};
+
+use crate::{handle_store, CUdeviceptr, CUresult, CUDA_ERROR_INVALID_VALUE};
+
+/// `CUDA_MEMCPY2D`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct CUDA_MEMCPY2D {
+    pub src_x_in_bytes: usize,
+    pub src_y: usize,
+    pub src_memory_type: c_uint,
+    pub src_host: *const c_void,
+    pub src_device: CUdeviceptr,
+    pub src_array: *mut c_void,
+    pub src_pitch: usize,
+    pub dst_x_in_bytes: usize,
+    pub dst_y: usize,
+    pub dst_memory_type: c_uint,
+    pub dst_host: *mut c_void,
+    pub dst_device: CUdeviceptr,
+    pub dst_array: *mut c_void,
+    pub dst_pitch: usize,
+    pub width_in_bytes: usize,
+    pub height: usize,
+}
+
+impl Default for CUDA_MEMCPY2D {
+    fn default() -> Self {
+        // SAFETY: all-zero is a valid value for every field (integers and null pointers).
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+/// `CUDA_MEMCPY3D`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct CUDA_MEMCPY3D {
+    pub src_x_in_bytes: usize,
+    pub src_y: usize,
+    pub src_z: usize,
+    pub src_lod: usize,
+    pub src_memory_type: c_uint,
+    pub src_host: *const c_void,
+    pub src_device: CUdeviceptr,
+    pub src_array: *mut c_void,
+    pub reserved0: *mut c_void,
+    pub src_pitch: usize,
+    pub src_height: usize,
+    pub dst_x_in_bytes: usize,
+    pub dst_y: usize,
+    pub dst_z: usize,
+    pub dst_lod: usize,
+    pub dst_memory_type: c_uint,
+    pub dst_host: *mut c_void,
+    pub dst_device: CUdeviceptr,
+    pub dst_array: *mut c_void,
+    pub reserved1: *mut c_void,
+    pub dst_pitch: usize,
+    pub dst_height: usize,
+    pub width_in_bytes: usize,
+    pub height: usize,
+    pub depth: usize,
+}
+
+/// Pitched host memory on one side of a copy.
+struct HostRegion {
+    base: *mut u8,
+    x_in_bytes: usize,
+    y: usize,
+    z: usize,
+    pitch: usize,
+    height: usize,
+}
+
+impl HostRegion {
+    /// Start of row `y` of slice `z` of the copied region.
+    fn row(&self, y: usize, z: usize) -> *mut u8 {
+        let offset = (self.z + z) * self.height * self.pitch + (self.y + y) * self.pitch + self.x_in_bytes;
+        self.base.wrapping_add(offset)
+    }
+}
+
+/// A 2D/3D copy with the host sides resolved to local memory.
+pub(crate) struct Copy3D {
+    pub params: Memcpy3DParams,
+    src_host: Option<HostRegion>,
+    dst_host: Option<HostRegion>,
+}
+
+/// Raw fields of one side of a copy.
+struct Side {
+    memory_type: c_uint,
+    host: *mut c_void,
+    device: CUdeviceptr,
+    array: *mut c_void,
+    x_in_bytes: usize,
+    y: usize,
+    z: usize,
+    pitch: usize,
+    height: usize,
+}
+
+impl CUDA_MEMCPY2D {
+    /// A copy of one row of `width_in_bytes`, for the 1D array copies; the
+    /// `with_*` methods set its sides.
+    pub(crate) fn row(width_in_bytes: usize) -> Self {
+        Self {
+            width_in_bytes,
+            height: 1,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn with_src_host(self, host: *const c_void) -> Self {
+        Self {
+            src_memory_type: MemoryType::Host.to_raw(),
+            src_host: host,
+            src_pitch: self.width_in_bytes,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_dst_host(self, host: *mut c_void) -> Self {
+        Self {
+            dst_memory_type: MemoryType::Host.to_raw(),
+            dst_host: host,
+            dst_pitch: self.width_in_bytes,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_src_device(self, device: CUdeviceptr) -> Self {
+        Self {
+            src_memory_type: MemoryType::Device.to_raw(),
+            src_device: device,
+            src_pitch: self.width_in_bytes,
+            ..self
+        }
+    }
+
+    pub(crate) fn with_dst_device(self, device: CUdeviceptr) -> Self {
+        Self {
+            dst_memory_type: MemoryType::Device.to_raw(),
+            dst_device: device,
+            dst_pitch: self.width_in_bytes,
+            ..self
+        }
+    }
+
+    /// `array` from byte `offset` of its first row as the source.
+    pub(crate) fn with_src_array(self, array: *mut c_void, offset: usize) -> Self {
+        Self {
+            src_memory_type: MemoryType::Array.to_raw(),
+            src_array: array,
+            src_x_in_bytes: offset,
+            ..self
+        }
+    }
+
+    /// `array` from byte `offset` of its first row as the destination.
+    pub(crate) fn with_dst_array(self, array: *mut c_void, offset: usize) -> Self {
+        Self {
+            dst_memory_type: MemoryType::Array.to_raw(),
+            dst_array: array,
+            dst_x_in_bytes: offset,
+            ..self
+        }
+    }
+
+    pub(crate) fn to_copy(&self) -> Result<Copy3D, CUresult> {
+        let src = Side {
+            memory_type: self.src_memory_type,
+            host: self.src_host as *mut c_void,
+            device: self.src_device,
+            array: self.src_array,
+            x_in_bytes: self.src_x_in_bytes,
+            y: self.src_y,
+            z: 0,
+            pitch: self.src_pitch,
+            height: 0,
+        };
+        let dst = Side {
+            memory_type: self.dst_memory_type,
+            host: self.dst_host,
+            device: self.dst_device,
+            array: self.dst_array,
+            x_in_bytes: self.dst_x_in_bytes,
+            y: self.dst_y,
+            z: 0,
+            pitch: self.dst_pitch,
+            height: 0,
+        };
+        Copy3D::new(src, dst, self.width_in_bytes, self.height, 1)
+    }
+}
+
+impl CUDA_MEMCPY3D {
+    pub(crate) fn to_copy(&self) -> Result<Copy3D, CUresult> {
+        let src = Side {
+            memory_type: self.src_memory_type,
+            host: self.src_host as *mut c_void,
+            device: self.src_device,
+            array: self.src_array,
+            x_in_bytes: self.src_x_in_bytes,
+            y: self.src_y,
+            z: self.src_z,
+            pitch: self.src_pitch,
+            height: self.src_height,
+        };
+        let dst = Side {
+            memory_type: self.dst_memory_type,
+            host: self.dst_host,
+            device: self.dst_device,
+            array: self.dst_array,
+            x_in_bytes: self.dst_x_in_bytes,
+            y: self.dst_y,
+            z: self.dst_z,
+            pitch: self.dst_pitch,
+            height: self.dst_height,
+        };
+        Copy3D::new(src, dst, self.width_in_bytes, self.height, self.depth)
+    }
+}
+
+impl Copy3D {
+    fn new(src: Side, dst: Side, width_in_bytes: usize, height: usize, depth: usize) -> Result<Self, CUresult> {
+        let (src, src_host) = resolve(src, width_in_bytes)?;
+        let (dst, dst_host) = resolve(dst, width_in_bytes)?;
+        Ok(Self {
+            params: Memcpy3DParams {
+                src,
+                dst,
+                width_in_bytes: width_in_bytes as u64,
+                height: height as u64,
+                depth: depth as u64,
+            },
+            src_host,
+            dst_host,
+        })
+    }
+
+    /// Both sides are host memory, so nothing needs to go to the server.
+    pub(crate) fn is_host_only(&self) -> bool {
+        self.src_host.is_some() && self.dst_host.is_some()
+    }
+
+    /// The source rows packed back to back, or nothing if the source isn't
+    /// host memory.
+    pub(crate) unsafe fn pack_source(&self) -> Vec<u8> {
+        let Some(src) = &self.src_host else {
+            return Vec::new();
+        };
+        let width = self.params.width_in_bytes as usize;
+        let mut data = Vec::with_capacity(self.params.packed_size() as usize);
+        for z in 0..self.params.depth as usize {
+            for y in 0..self.params.height as usize {
+                data.extend_from_slice(std::slice::from_raw_parts(src.row(y, z), width));
+            }
+        }
+        data
+    }
+
+    /// Scatter packed rows into a host destination.
+    pub(crate) unsafe fn unpack_destination(&self, data: &[u8]) {
+        let Some(dst) = &self.dst_host else {
+            return;
+        };
+        let width = self.params.width_in_bytes as usize;
+        if width == 0 {
+            return;
+        }
+        let rows_per_slice = self.params.height as usize;
+        for (i, row) in data.chunks(width).enumerate() {
+            let (z, y) = (i / rows_per_slice.max(1), i % rows_per_slice.max(1));
+            std::ptr::copy_nonoverlapping(row.as_ptr(), dst.row(y, z), row.len());
+        }
+    }
+}
+
+/// Wire description of one side, plus the local memory if it is on the host.
+fn resolve(side: Side, width_in_bytes: usize) -> Result<(MemcpyRegion, Option<HostRegion>), CUresult> {
+    let memory_type = MemoryType::from_raw(side.memory_type).ok_or(CUDA_ERROR_INVALID_VALUE)?;
+    let handle = match memory_type {
+        MemoryType::Host => None,
+        MemoryType::Device => Some(handle_store::get_mem_by_ptr(side.device).ok_or(CUDA_ERROR_INVALID_VALUE)?),
+        // Unified pointers are either one of our allocations or host memory.
+        MemoryType::Unified => handle_store::get_mem_by_ptr(side.device),
+        MemoryType::Array => {
+            let array = handle_store::get_array(side.array as u64).ok_or(CUDA_ERROR_INVALID_VALUE)?;
+            // Arrays have no pitch; the driver lays out their rows.
+            let region = MemcpyRegion {
+                memory_type: MemoryType::Array,
+                handle: Some(array),
+                x_in_bytes: side.x_in_bytes as u64,
+                y: side.y as u64,
+                z: side.z as u64,
+                pitch: 0,
+                height: 0,
+            };
+            return Ok((region, None));
+        }
+    };
+
+    let Some(handle) = handle else {
+        let base = match memory_type {
+            MemoryType::Host => side.host,
+            _ => side.device as *mut c_void,
+        };
+        if base.is_null() || side.pitch < width_in_bytes {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let region = MemcpyRegion {
+            memory_type: MemoryType::Host,
+            handle: None,
+            x_in_bytes: 0,
+            y: 0,
+            z: 0,
+            pitch: width_in_bytes as u64,
+            height: 0,
+        };
+        let host = HostRegion {
+            base: base as *mut u8,
+            x_in_bytes: side.x_in_bytes,
+            y: side.y,
+            z: side.z,
+            pitch: side.pitch,
+            height: side.height,
+        };
+        return Ok((region, Some(host)));
+    };
+
+    Ok((
+        MemcpyRegion {
+            memory_type: MemoryType::Device,
+            handle: Some(handle),
+            x_in_bytes: side.x_in_bytes as u64,
+            y: side.y as u64,
+            z: side.z as u64,
+            pitch: side.pitch as u64,
+            height: side.height as u64,
+        },
+        None,
+    ))
+}
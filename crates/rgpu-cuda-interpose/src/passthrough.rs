@@ -0,0 +1,385 @@
+//! Loader-stub mode for a system-wide `nvcuda.dll`.
+//!
+//! When the interpose DLL is installed over `System32\nvcuda.dll`, every CUDA
+//! application on the machine picks it up — including ones that should keep
+//! using the local GPU. In loader-stub mode only processes named in the
+//! interpose allowlist are routed through RGPU; every other process has each
+//! call forwarded to the original NVIDIA driver, which the installer keeps
+//! next to us as `nvcuda_real.dll`.
+//!
+//! The real driver is loaded lazily on the first forwarded call, and each
+//! export is resolved on first use. The forwarders themselves are generated
+//! by `build.rs` from the signatures of our own exports.
+//!
+//! The allowlist comes from `RGPU_INTERPOSE_ALLOWLIST` (comma-separated
+//! executable names) or `client.interpose_allowlist` in the config file. An
+//! empty allowlist disables loader-stub mode and every process is interposed.
+//!
+//! Local passthrough (`client.local_passthrough` or
+//! `RGPU_LOCAL_PASSTHROUGH=1`) loads the real driver in an interposed
+//! process too, for the local GPUs in the pool: `cuDeviceGet` hands out the
+//! real driver's device for them, and from then on each call goes wherever
+//! the handle it acts on came from. Handles RGPU handed out are in the
+//! handle store; anything else belongs to the real driver. Calls that name
+//! no handle (`cuMemAlloc`, `cuModuleLoadData`, ...) follow the thread's
+//! current context, i.e. the side of the last `cuCtxCreate`,
+//! `cuCtxSetCurrent` or `cuCtxPushCurrent` while the real driver still has
+//! a context current.
+
+use std::cell::Cell;
+use std::ffi::{c_char, c_int, c_uint, c_void, CString};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::OnceLock;
+
+use tracing::{debug, info, warn};
+
+use crate::{handle_store, texref, texture};
+
+/// Filename of the original NVIDIA driver once ours has taken its place.
+const REAL_DRIVER_NAME: &str = "nvcuda_real.dll";
+
+/// Environment override for the allowlist.
+const ALLOWLIST_ENV: &str = "RGPU_INTERPOSE_ALLOWLIST";
+
+/// Environment override for `client.local_passthrough`.
+const LOCAL_PASSTHROUGH_ENV: &str = "RGPU_LOCAL_PASSTHROUGH";
+
+/// Server ID of the daemon's local GPUs — matches
+/// rgpu_client::pool_manager::LOCAL_SERVER_ID.
+pub(crate) const LOCAL_SERVER_ID: u16 = u16::MAX;
+
+/// `CU_STREAM_LEGACY` and `CU_STREAM_PER_THREAD`: like the null stream, they
+/// mean the current context's.
+const SPECIAL_STREAMS: u64 = 2;
+
+/// What a call is routed by (generated for each export by `build.rs`).
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Route {
+    /// Always answered by the interpose library
+    Never,
+    /// The thread's current context
+    Current,
+    Device(u64),
+    Context(u64),
+    Module(u64),
+    Function(u64),
+    Stream(u64),
+    Event(u64),
+    DevicePtr(u64),
+    HostPtr(u64),
+    Array(u64),
+    Graph(u64),
+    GraphExec(u64),
+    Linker(u64),
+    MemPool(u64),
+    /// A texture or surface object
+    Object(u64),
+    /// A texture or surface reference
+    TexRef(u64),
+}
+
+/// The real driver, and whether every call goes to it.
+struct RealDriver {
+    library: libloading::Library,
+    forward_all: bool,
+}
+
+thread_local! {
+    /// The last context this thread made current was the real driver's.
+    static LOCAL_CURRENT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Forward the enclosing export to the real driver when this process is not
+/// allowlisted, or when local passthrough routes it there. Expands to
+/// nothing observable when passthrough is inactive.
+macro_rules! forward {
+    ($name:ident($($arg:expr),* $(,)?)) => {
+        if let Some(result) = $crate::passthrough::forwarders::$name($($arg),*) {
+            return result;
+        }
+    };
+}
+
+/// Typed forwarders for every exported `cu*` function, generated at build time.
+#[allow(non_snake_case, clippy::too_many_arguments, clippy::unnecessary_cast)]
+pub(crate) mod forwarders {
+    use std::ffi::{c_char, c_int, c_uint, c_void};
+    use std::sync::atomic::AtomicPtr;
+
+    use super::{resolve, routes_to_driver, Route};
+    use crate::array::{CUDA_ARRAY3D_DESCRIPTOR, CUDA_ARRAY_DESCRIPTOR};
+    use crate::ipc_mem::CUipcMemHandle;
+    use crate::memcpy3d::{CUDA_MEMCPY2D, CUDA_MEMCPY3D};
+    use crate::texture::{CUDA_RESOURCE_DESC, CUDA_RESOURCE_VIEW_DESC, CUDA_TEXTURE_DESC};
+    use crate::{
+        CUarray, CUcontext, CUdevice, CUdeviceptr, CUdevprop, CUevent, CUfunction, CUgraph, CUgraphExec,
+        CUlinkState, CUmemoryPool, CUmodule, CUresult, CUstream, CUsurfObject, CUsurfref, CUtexObject,
+        CUtexref,
+    };
+
+    include!(concat!(env!("OUT_DIR"), "/forwarders.rs"));
+}
+
+/// The real driver, if passthrough applies to this process.
+static REAL_DRIVER: OnceLock<Option<RealDriver>> = OnceLock::new();
+
+fn real_driver() -> Option<&'static RealDriver> {
+    REAL_DRIVER
+        .get_or_init(|| loader_stub().or_else(local_passthrough))
+        .as_ref()
+}
+
+/// The real driver for a process loader-stub mode doesn't interpose.
+fn loader_stub() -> Option<RealDriver> {
+    if !cfg!(windows) {
+        return None;
+    }
+    let allowlist = load_allowlist();
+    if allowlist.is_empty() {
+        return None;
+    }
+    let exe = current_exe_name()?;
+    if allowlist.contains(&exe) {
+        debug!("{} is allowlisted, interposing CUDA calls", exe);
+        return None;
+    }
+    // SAFETY: loading the vendor driver runs its initialisers, which is
+    // exactly what would have happened had we not replaced it.
+    match unsafe { libloading::Library::new(REAL_DRIVER_NAME) } {
+        Ok(library) => {
+            debug!("{} is not allowlisted, forwarding to {}", exe, REAL_DRIVER_NAME);
+            Some(RealDriver {
+                library,
+                forward_all: true,
+            })
+        }
+        Err(e) => {
+            warn!("loader-stub mode: cannot load {}: {}", REAL_DRIVER_NAME, e);
+            None
+        }
+    }
+}
+
+/// The real driver for the local GPUs, if local passthrough is on and it
+/// initializes.
+fn local_passthrough() -> Option<RealDriver> {
+    let enabled = match std::env::var(LOCAL_PASSTHROUGH_ENV) {
+        Ok(value) => matches!(value.trim(), "1" | "true" | "yes"),
+        Err(_) => {
+            let path = rgpu_core::config::default_config_path();
+            rgpu_core::config::RgpuConfig::load_or_default(&path)
+                .client
+                .local_passthrough
+        }
+    };
+    if !enabled {
+        return None;
+    }
+    let Some((name, library)) = local_driver_candidates().into_iter().find_map(|name| {
+        // SAFETY: as for loader-stub mode; the driver may be loaded already.
+        unsafe { libloading::Library::new(&name) }.ok().map(|lib| (name, lib))
+    }) else {
+        warn!("local passthrough: no CUDA driver found, local GPUs go through the daemon");
+        return None;
+    };
+    // SAFETY: the symbols are only called with their own signatures.
+    unsafe {
+        if library.get::<*mut c_void>(b"rgpuMemsetTyped\0").is_ok() {
+            warn!("local passthrough: {} is the RGPU interpose library, not the driver", name);
+            return None;
+        }
+        let init = library
+            .get::<unsafe extern "C" fn(c_uint) -> c_int>(b"cuInit\0")
+            .ok()?;
+        let result = init(0);
+        if result != 0 {
+            warn!("local passthrough: cuInit failed with {}, local GPUs go through the daemon", result);
+            return None;
+        }
+    }
+    info!("local passthrough: driving local GPUs through {}", name);
+    Some(RealDriver {
+        library,
+        forward_all: false,
+    })
+}
+
+/// Where the real driver may be: next to a system-wide install of ours on
+/// Windows, else the system's.
+fn local_driver_candidates() -> Vec<String> {
+    if cfg!(windows) {
+        let system_root = std::env::var("SystemRoot").unwrap_or_else(|_| r"C:\Windows".to_string());
+        vec![
+            REAL_DRIVER_NAME.to_string(),
+            format!(r"{}\System32\nvcuda.dll", system_root),
+        ]
+    } else {
+        vec!["libcuda.so.1".to_string(), "libcuda.so".to_string()]
+    }
+}
+
+/// Whether this process forwards every call to the real driver.
+pub(crate) fn active() -> bool {
+    real_driver().is_some_and(|driver| driver.forward_all)
+}
+
+/// Whether local GPUs are driven through the real driver in this process.
+pub(crate) fn local_active() -> bool {
+    real_driver().is_some_and(|driver| !driver.forward_all)
+}
+
+/// Whether the export the forwarder belongs to goes to the real driver: not
+/// if any handle it names is RGPU's, else if any is the driver's, else if
+/// the current context is. `switches` marks exports that make a context
+/// current.
+pub(crate) fn routes_to_driver(routes: &[Route], switches: bool) -> bool {
+    let Some(driver) = real_driver() else {
+        return false;
+    };
+    if driver.forward_all {
+        return true;
+    }
+    let mut local = None;
+    for &route in routes {
+        match route {
+            Route::Never => return false,
+            route => match is_rgpu_handle(route) {
+                Some(true) => {
+                    local = Some(false);
+                    break;
+                }
+                Some(false) => local = Some(true),
+                None => {}
+            },
+        }
+    }
+    let local = local.unwrap_or_else(current_is_local);
+    if switches {
+        LOCAL_CURRENT.with(|current| current.set(local));
+    }
+    local
+}
+
+/// Whether RGPU handed out the handle `route` names; `None` for calls that
+/// follow the current context.
+fn is_rgpu_handle(route: Route) -> Option<bool> {
+    let rgpu = match route {
+        Route::Never | Route::Current => return None,
+        Route::Stream(id) if id <= SPECIAL_STREAMS => return None,
+        Route::Context(0) | Route::DevicePtr(0) | Route::HostPtr(0) => return None,
+        Route::Device(id) => handle_store::get_device(id).is_some(),
+        Route::Context(id) => handle_store::get_ctx(id).is_some(),
+        Route::Module(id) => handle_store::get_mod(id).is_some(),
+        Route::Function(id) => handle_store::get_func(id).is_some(),
+        Route::Stream(id) => handle_store::get_stream(id).is_some(),
+        Route::Event(id) => handle_store::get_event(id).is_some(),
+        Route::DevicePtr(ptr) => {
+            handle_store::resolve_device_ptr(ptr).is_some() || handle_store::find_host_range(ptr).is_some()
+        }
+        Route::HostPtr(ptr) => handle_store::find_host_range(ptr).is_some(),
+        Route::Array(id) => handle_store::get_array(id).is_some(),
+        Route::Graph(id) => handle_store::get_graph(id).is_some(),
+        Route::GraphExec(id) => handle_store::get_graph_exec(id).is_some(),
+        Route::Linker(id) => handle_store::get_linker(id).is_some(),
+        Route::MemPool(id) => handle_store::get_mempool(id).is_some(),
+        Route::Object(id) => texture::get(id).is_some(),
+        Route::TexRef(id) => texref::get(id).is_some(),
+    };
+    Some(rgpu)
+}
+
+/// Whether context-less calls on this thread go to the real driver.
+fn current_is_local() -> bool {
+    if !LOCAL_CURRENT.with(Cell::get) {
+        return false;
+    }
+    let mut ctx: *mut c_void = std::ptr::null_mut();
+    // SAFETY: cuCtxGetCurrent only writes the out-pointer.
+    let result = unsafe { forwarders::driver::cuCtxGetCurrent(&mut ctx) };
+    result == Some(0) && !ctx.is_null()
+}
+
+/// The real driver's device for a local GPU with PCI bus ID `pci_bus_id`.
+pub(crate) fn local_device(pci_bus_id: &str) -> Option<c_int> {
+    let bus_id = CString::new(pci_bus_id).ok()?;
+    let mut device: c_int = 0;
+    // SAFETY: the driver reads the string and writes the out-pointer.
+    let result = unsafe { forwarders::driver::cuDeviceGetByPCIBusId(&mut device, bus_id.as_ptr() as *const c_char) };
+    (result == Some(0)).then_some(device)
+}
+
+/// Resolve `symbol` in the real driver, caching the address in `slot`.
+/// Returns `None` when passthrough is inactive or the driver lacks the export.
+pub(crate) fn resolve(slot: &AtomicPtr<c_void>, symbol: &[u8]) -> Option<*mut c_void> {
+    let cached = slot.load(Ordering::Acquire);
+    if !cached.is_null() {
+        return Some(cached);
+    }
+    let lib = &real_driver()?.library;
+    // SAFETY: the pointer is only ever transmuted to the export's own signature.
+    let ptr = unsafe { lib.get::<*mut c_void>(symbol) }.ok().map(|sym| *sym)?;
+    if ptr.is_null() {
+        return None;
+    }
+    slot.store(ptr, Ordering::Release);
+    Some(ptr)
+}
+
+fn load_allowlist() -> Vec<String> {
+    let entries = match std::env::var(ALLOWLIST_ENV) {
+        Ok(list) => list.split(',').map(str::to_string).collect(),
+        Err(_) => {
+            let path = rgpu_core::config::default_config_path();
+            rgpu_core::config::RgpuConfig::load_or_default(&path)
+                .client
+                .interpose_allowlist
+        }
+    };
+    entries
+        .iter()
+        .map(|entry| normalize_exe_name(entry))
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+fn current_exe_name() -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    let name = exe.file_name()?.to_str()?;
+    Some(normalize_exe_name(name))
+}
+
+/// Executable names compare case-insensitively, with or without `.exe`.
+fn normalize_exe_name(name: &str) -> String {
+    let name = name.trim().to_ascii_lowercase();
+    name.strip_suffix(".exe").map(str::to_string).unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::{NetworkHandle, ResourceType};
+
+    #[test]
+    fn handles_route_to_whoever_made_them() {
+        let device = handle_store::store_device(NetworkHandle {
+            server_id: 1,
+            session_id: 1,
+            resource_id: 1,
+            resource_type: ResourceType::CuDevice,
+        });
+        assert_eq!(is_rgpu_handle(Route::Device(device)), Some(true));
+        // The real driver's devices are small ordinals.
+        assert_eq!(is_rgpu_handle(Route::Device(0)), Some(false));
+        assert_eq!(is_rgpu_handle(Route::Context(0x7f00_dead_0000)), Some(false));
+        assert_eq!(is_rgpu_handle(Route::DevicePtr(0x7f00_0020_0000)), Some(false));
+    }
+
+    #[test]
+    fn default_streams_follow_the_current_context() {
+        for stream in 0..=SPECIAL_STREAMS {
+            assert_eq!(is_rgpu_handle(Route::Stream(stream)), None);
+        }
+        assert_eq!(is_rgpu_handle(Route::Current), None);
+        assert_eq!(is_rgpu_handle(Route::Context(0)), None);
+    }
+}
@@ -0,0 +1,95 @@
+//! Legacy texture and surface references.
+//!
+//! A reference is a module global that kernels read by name, so unlike a
+//! texture object it never appears in launch arguments. The application
+//! gets an id for each reference it looks up; the state it sets is kept
+//! here to answer the `cuTexRefGet*` and `cuSurfRefGetArray` queries
+//! locally.
+
+use std::collections::BTreeMap;
+use std::ffi::{c_int, c_uint};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+
+use rgpu_protocol::handle::NetworkHandle;
+
+use crate::CUdeviceptr;
+
+static NEXT_REF: AtomicU64 = AtomicU64::new(1);
+
+/// A texture or surface reference and the state bound to it.
+#[derive(Clone, Copy)]
+pub(crate) struct TexRef {
+    pub handle: NetworkHandle,
+    pub surface: bool,
+    pub module: u64,
+    /// Bound linear memory, as the application's device pointer
+    pub address: Option<CUdeviceptr>,
+    /// Bound array, as the application's array id
+    pub array: Option<u64>,
+    pub format: c_uint,
+    pub num_channels: c_int,
+    pub flags: c_uint,
+    pub filter_mode: c_uint,
+    pub address_mode: [c_uint; 3],
+    pub max_anisotropy: c_uint,
+    pub border_color: [f32; 4],
+}
+
+struct Registry {
+    refs: BTreeMap<u64, TexRef>,
+    /// (module, name, surface) -> id, so looking a reference up again gives
+    /// the same id, as the driver gives the same `CUtexref`.
+    names: BTreeMap<(u64, String, bool), u64>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    refs: BTreeMap::new(),
+    names: BTreeMap::new(),
+});
+
+/// The id of a reference looked up before.
+pub(crate) fn lookup(module: u64, name: &str, surface: bool) -> Option<u64> {
+    REGISTRY.lock().names.get(&(module, name.to_string(), surface)).copied()
+}
+
+/// Record a reference the server looked up; the id is what the application gets.
+pub(crate) fn store(module: u64, name: &str, surface: bool, handle: NetworkHandle) -> u64 {
+    let id = NEXT_REF.fetch_add(1, Ordering::Relaxed);
+    let texref = TexRef {
+        handle,
+        surface,
+        module,
+        address: None,
+        array: None,
+        format: 0,
+        num_channels: 0,
+        flags: 0,
+        filter_mode: 0,
+        address_mode: [0; 3],
+        max_anisotropy: 0,
+        border_color: [0.0; 4],
+    };
+    let mut registry = REGISTRY.lock();
+    registry.refs.insert(id, texref);
+    registry.names.insert((module, name.to_string(), surface), id);
+    id
+}
+
+pub(crate) fn get(id: u64) -> Option<TexRef> {
+    REGISTRY.lock().refs.get(&id).copied()
+}
+
+pub(crate) fn update(id: u64, f: impl FnOnce(&mut TexRef)) {
+    if let Some(texref) = REGISTRY.lock().refs.get_mut(&id) {
+        f(texref);
+    }
+}
+
+/// Forget the references of an unloaded module.
+pub(crate) fn remove_module(module: u64) {
+    let mut registry = REGISTRY.lock();
+    registry.refs.retain(|_, texref| texref.module != module);
+    registry.names.retain(|(m, _, _), _| *m != module);
+}
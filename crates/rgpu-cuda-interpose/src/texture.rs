@@ -0,0 +1,180 @@
+//! Texture and surface objects.
+//!
+//! `CUtexObject` and `CUsurfObject` are 64-bit values that kernels take as
+//! arguments. The application gets ids from their own range, as with device
+//! pointers, so a launch can pick them out of its arguments for the server
+//! to replace with the real objects. The descriptors each object was created
+//! from are kept here to answer the `Get*Desc` queries locally.
+
+use std::collections::BTreeMap;
+use std::ffi::{c_int, c_uint};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+
+use rgpu_protocol::cuda_commands::{ResourceDesc, ResourceViewDesc, TextureDesc, TextureResource};
+use rgpu_protocol::handle::NetworkHandle;
+
+use crate::{handle_store, CUresult, CUDA_ERROR_INVALID_VALUE, CUDA_ERROR_NOT_SUPPORTED};
+
+const CU_RESOURCE_TYPE_ARRAY: c_uint = 0;
+const CU_RESOURCE_TYPE_LINEAR: c_uint = 2;
+const CU_RESOURCE_TYPE_PITCH2D: c_uint = 3;
+
+/// Ids handed to the application, below the device pointer range.
+const OBJECT_BASE: u64 = 1 << 47;
+static NEXT_OBJECT: AtomicU64 = AtomicU64::new(OBJECT_BASE);
+
+/// `CUDA_RESOURCE_DESC`. `res` is the union of the per-type layouts: for an
+/// array `{ hArray }`, for linear memory `{ devPtr, format | numChannels <<
+/// 32, sizeInBytes }`, for pitched 2D memory `{ devPtr, format | numChannels
+/// << 32, width, height, pitchInBytes }`.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+pub struct CUDA_RESOURCE_DESC {
+    pub res_type: c_uint,
+    pub res: [u64; 16],
+    pub flags: c_uint,
+}
+
+/// `CUDA_TEXTURE_DESC`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+pub struct CUDA_TEXTURE_DESC {
+    pub address_mode: [c_uint; 3],
+    pub filter_mode: c_uint,
+    pub flags: c_uint,
+    pub max_anisotropy: c_uint,
+    pub mipmap_filter_mode: c_uint,
+    pub mipmap_level_bias: f32,
+    pub min_mipmap_level_clamp: f32,
+    pub max_mipmap_level_clamp: f32,
+    pub border_color: [f32; 4],
+    pub reserved: [c_int; 12],
+}
+
+/// `CUDA_RESOURCE_VIEW_DESC`
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy)]
+pub struct CUDA_RESOURCE_VIEW_DESC {
+    pub format: c_uint,
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub first_mipmap_level: c_uint,
+    pub last_mipmap_level: c_uint,
+    pub first_layer: c_uint,
+    pub last_layer: c_uint,
+    pub reserved: [c_uint; 16],
+}
+
+impl CUDA_RESOURCE_DESC {
+    /// The resource as sent to the server. Mipmapped arrays aren't supported.
+    pub(crate) fn to_desc(self) -> Result<ResourceDesc, CUresult> {
+        let format = self.res[1] as u32;
+        let num_channels = (self.res[1] >> 32) as u32;
+        let resource = match self.res_type {
+            CU_RESOURCE_TYPE_ARRAY => TextureResource::Array {
+                array: handle_store::get_array(self.res[0]).ok_or(CUDA_ERROR_INVALID_VALUE)?,
+            },
+            CU_RESOURCE_TYPE_LINEAR => {
+                let (dptr, offset) =
+                    handle_store::resolve_device_ptr(self.res[0]).ok_or(CUDA_ERROR_INVALID_VALUE)?;
+                TextureResource::Linear {
+                    dptr,
+                    offset,
+                    format,
+                    num_channels,
+                    size_in_bytes: self.res[2],
+                }
+            }
+            CU_RESOURCE_TYPE_PITCH2D => {
+                let (dptr, offset) =
+                    handle_store::resolve_device_ptr(self.res[0]).ok_or(CUDA_ERROR_INVALID_VALUE)?;
+                TextureResource::Pitch2D {
+                    dptr,
+                    offset,
+                    format,
+                    num_channels,
+                    width: self.res[2],
+                    height: self.res[3],
+                    pitch_in_bytes: self.res[4],
+                }
+            }
+            // Mipmapped arrays
+            _ => return Err(CUDA_ERROR_NOT_SUPPORTED),
+        };
+        Ok(ResourceDesc {
+            resource,
+            flags: self.flags,
+        })
+    }
+}
+
+impl CUDA_TEXTURE_DESC {
+    pub(crate) fn to_desc(self) -> TextureDesc {
+        TextureDesc {
+            address_mode: self.address_mode,
+            filter_mode: self.filter_mode,
+            flags: self.flags,
+            max_anisotropy: self.max_anisotropy,
+            mipmap_filter_mode: self.mipmap_filter_mode,
+            mipmap_level_bias: self.mipmap_level_bias,
+            min_mipmap_level_clamp: self.min_mipmap_level_clamp,
+            max_mipmap_level_clamp: self.max_mipmap_level_clamp,
+            border_color: self.border_color,
+        }
+    }
+}
+
+impl CUDA_RESOURCE_VIEW_DESC {
+    pub(crate) fn to_desc(self) -> ResourceViewDesc {
+        ResourceViewDesc {
+            format: self.format,
+            width: self.width as u64,
+            height: self.height as u64,
+            depth: self.depth as u64,
+            first_mipmap_level: self.first_mipmap_level,
+            last_mipmap_level: self.last_mipmap_level,
+            first_layer: self.first_layer,
+            last_layer: self.last_layer,
+        }
+    }
+}
+
+/// A texture or surface object; surfaces have no texture or view.
+#[derive(Clone, Copy)]
+pub(crate) struct Object {
+    pub handle: NetworkHandle,
+    pub resource: CUDA_RESOURCE_DESC,
+    pub texture: Option<CUDA_TEXTURE_DESC>,
+    pub view: Option<CUDA_RESOURCE_VIEW_DESC>,
+}
+
+static OBJECTS: Mutex<BTreeMap<u64, Object>> = Mutex::new(BTreeMap::new());
+
+/// Record an object; the id is what the application gets.
+pub(crate) fn store(object: Object) -> u64 {
+    let id = NEXT_OBJECT.fetch_add(1, Ordering::Relaxed);
+    OBJECTS.lock().insert(id, object);
+    id
+}
+
+pub(crate) fn get(id: u64) -> Option<Object> {
+    OBJECTS.lock().get(&id).copied()
+}
+
+pub(crate) fn remove(id: u64) {
+    OBJECTS.lock().remove(&id);
+}
+
+/// The object a kernel argument names, if it is one.
+pub(crate) fn handle(id: u64) -> Option<NetworkHandle> {
+    if id < OBJECT_BASE {
+        return None;
+    }
+    OBJECTS.lock().get(&id).map(|object| object.handle)
+}
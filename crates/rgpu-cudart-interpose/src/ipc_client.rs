@@ -0,0 +1,28 @@
+//! IPC client for the runtime API library's CUDA commands, over the shared [`rgpu_common::ipc`] client.
+
+use rgpu_common::ipc;
+use rgpu_protocol::messages::Message;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+
+pub struct IpcClient(ipc::IpcClient);
+
+impl IpcClient {
+    pub fn new(path: &str) -> Self {
+        Self(ipc::IpcClient::new(path, "rgpu-cudart-interpose"))
+    }
+
+    /// Send a CUDA command to the daemon and wait for the response.
+    pub fn send_command(&self, cmd: CudaCommand) -> Result<CudaResponse, String> {
+        let msg = Message::CudaCommand {
+            request_id: self.0.next_request_id(),
+            command: cmd,
+            deadline_ms: None,
+        };
+
+        match self.0.send_and_receive(msg)? {
+            Message::CudaResponse { response, .. } => Ok(response),
+            Message::Error(e) => Err(e.to_string()),
+            other => Err(format!("unexpected response: {:?}", other)),
+        }
+    }
+}
@@ -0,0 +1,1280 @@
+//! CUDA Runtime API interception library.
+//!
+//! The CUDA interposer replaces the driver library, which covers applications
+//! whose runtime loads libcuda dynamically. Some ship a runtime that gets at
+//! the driver in ways that bypass it, or link the runtime statically in a
+//! form that can't be redirected. This cdylib stands in for libcudart
+//! instead: it implements the commonly used runtime calls, including the
+//! registration and launch entry points nvcc generates for `<<<...>>>`, on
+//! top of the same CudaCommand protocol the driver interposer speaks.
+//!
+//! Copies are synchronous, including the `Async` variants, and copies into
+//! or out of the middle of an allocation move the bytes before the offset
+//! too, since the protocol addresses allocations from their start.
+//!
+//! Usage:
+//! - Linux: LD_PRELOAD=librgpu_cudart_interpose.so <application>, or install
+//!   it as libcudart.so.12 ahead of the application's copy
+//! - Windows: Place as cudart64_12.dll in the application's directory
+
+// Exported entry points follow the CUDA runtime API's own safety contract.
+#![allow(clippy::missing_safety_doc)]
+
+mod ipc_client;
+mod registry;
+
+use std::alloc::Layout;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+use parking_lot::Mutex;
+use tracing::{debug, error, info};
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse, KernelParam};
+use rgpu_protocol::handle::{NetworkHandle, ResourceType};
+
+use ipc_client::IpcClient;
+use registry::LoadedFunction;
+
+// CUDA runtime types
+type CudaError = c_int;
+type CudaStream = *mut c_void;
+type CudaEvent = *mut c_void;
+
+/// `dim3`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Dim3 {
+    x: c_uint,
+    y: c_uint,
+    z: c_uint,
+}
+
+// Runtime error codes. The driver's codes that reach the runtime share
+// these numbers, so server errors are passed through as they are.
+const CUDA_SUCCESS: CudaError = 0;
+const CUDA_ERROR_INVALID_VALUE: CudaError = 1;
+const CUDA_ERROR_MEMORY_ALLOCATION: CudaError = 2;
+const CUDA_ERROR_INITIALIZATION_ERROR: CudaError = 3;
+const CUDA_ERROR_INVALID_SYMBOL: CudaError = 13;
+const CUDA_ERROR_INVALID_DEVICE_POINTER: CudaError = 17;
+const CUDA_ERROR_INVALID_MEMCPY_DIRECTION: CudaError = 21;
+const CUDA_ERROR_MISSING_CONFIGURATION: CudaError = 52;
+const CUDA_ERROR_INVALID_DEVICE_FUNCTION: CudaError = 98;
+const CUDA_ERROR_NO_DEVICE: CudaError = 100;
+const CUDA_ERROR_INVALID_DEVICE: CudaError = 101;
+const CUDA_ERROR_INVALID_RESOURCE_HANDLE: CudaError = 400;
+const CUDA_ERROR_NOT_READY: CudaError = 600;
+const CUDA_ERROR_UNKNOWN: CudaError = 999;
+
+// cudaMemcpyKind
+const MEMCPY_HOST_TO_HOST: c_int = 0;
+const MEMCPY_HOST_TO_DEVICE: c_int = 1;
+const MEMCPY_DEVICE_TO_HOST: c_int = 2;
+const MEMCPY_DEVICE_TO_DEVICE: c_int = 3;
+const MEMCPY_DEFAULT: c_int = 4;
+
+/// `cudaStreamLegacy` and `cudaStreamPerThread`, which like 0 name the
+/// default stream.
+const STREAM_LEGACY: u64 = 1;
+const STREAM_PER_THREAD: u64 = 2;
+
+/// Host buffers are page-aligned, like the driver's.
+const PAGE_SIZE: usize = 4096;
+
+/// The leading fields of `cudaDeviceProp`, which every runtime version
+/// since 10.0 lays out the same way. Only these are written.
+#[repr(C)]
+pub struct CudaDevicePropPrefix {
+    name: [c_char; 256],
+    uuid: [u8; 16],
+    luid: [c_char; 8],
+    luid_device_node_mask: c_uint,
+    total_global_mem: usize,
+    shared_mem_per_block: usize,
+    regs_per_block: c_int,
+    warp_size: c_int,
+    mem_pitch: usize,
+    max_threads_per_block: c_int,
+    max_threads_dim: [c_int; 3],
+    max_grid_size: [c_int; 3],
+    clock_rate: c_int,
+    total_const_mem: usize,
+    major: c_int,
+    minor: c_int,
+    texture_alignment: usize,
+    texture_pitch_alignment: usize,
+    device_overlap: c_int,
+    multi_processor_count: c_int,
+}
+
+// CUdevice_attribute values behind the properties above
+const ATTR_MAX_THREADS_PER_BLOCK: i32 = 1;
+const ATTR_MAX_BLOCK_DIM_X: i32 = 2;
+const ATTR_MAX_GRID_DIM_X: i32 = 5;
+const ATTR_MAX_SHARED_MEMORY_PER_BLOCK: i32 = 8;
+const ATTR_TOTAL_CONSTANT_MEMORY: i32 = 9;
+const ATTR_WARP_SIZE: i32 = 10;
+const ATTR_MAX_PITCH: i32 = 11;
+const ATTR_MAX_REGISTERS_PER_BLOCK: i32 = 12;
+const ATTR_CLOCK_RATE: i32 = 13;
+const ATTR_TEXTURE_ALIGNMENT: i32 = 14;
+const ATTR_GPU_OVERLAP: i32 = 15;
+const ATTR_MULTIPROCESSOR_COUNT: i32 = 16;
+const ATTR_TEXTURE_PITCH_ALIGNMENT: i32 = 51;
+
+static IPC_CLIENT: OnceLock<IpcClient> = OnceLock::new();
+
+fn get_client() -> &'static IpcClient {
+    IPC_CLIENT.get_or_init(|| {
+        let path = rgpu_common::platform::default_ipc_path();
+        IpcClient::new(&path)
+    })
+}
+
+fn send(cmd: CudaCommand) -> CudaResponse {
+    match get_client().send_command(cmd) {
+        Ok(resp) => resp,
+        Err(e) => {
+            error!("IPC error: {}", e);
+            CudaResponse::Error {
+                code: CUDA_ERROR_UNKNOWN,
+                message: e,
+            }
+        }
+    }
+}
+
+fn error_of(response: CudaResponse) -> CudaError {
+    match response {
+        CudaResponse::Error { code, .. } => code,
+        _ => CUDA_ERROR_UNKNOWN,
+    }
+}
+
+fn check(response: CudaResponse) -> Result<(), CudaError> {
+    match response {
+        CudaResponse::Success => Ok(()),
+        other => Err(error_of(other)),
+    }
+}
+
+thread_local! {
+    /// Device selected with cudaSetDevice on this thread
+    static CURRENT_DEVICE: Cell<i32> = const { Cell::new(0) };
+    /// What cudaGetLastError returns
+    static LAST_ERROR: Cell<CudaError> = const { Cell::new(CUDA_SUCCESS) };
+    /// Launch configurations pushed by `<<<...>>>`
+    static CALL_CONFIGS: RefCell<Vec<CallConfig>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Record a failure for cudaGetLastError and return the call's result.
+fn ret(result: Result<(), CudaError>) -> CudaError {
+    match result {
+        Ok(()) => CUDA_SUCCESS,
+        Err(code) => {
+            LAST_ERROR.with(|e| e.set(code));
+            code
+        }
+    }
+}
+
+// ── Runtime State ───────────────────────────────────────────────────
+
+/// Devices and their primary contexts, set up on first use like the real
+/// runtime's implicit initialization.
+struct Runtime {
+    devices: Vec<NetworkHandle>,
+    /// Primary context by ordinal, retained on first use
+    contexts: Vec<Option<NetworkHandle>>,
+    /// Device whose context is current on the daemon connection
+    active: Option<i32>,
+}
+
+static RUNTIME: Mutex<Option<Runtime>> = Mutex::new(None);
+
+fn load_runtime() -> Result<Runtime, CudaError> {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_env("RGPU_LOG")
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .try_init();
+
+    info!("initializing CUDA runtime");
+    check(send(CudaCommand::Init { flags: 0 })).map_err(|_| CUDA_ERROR_INITIALIZATION_ERROR)?;
+    let count = match send(CudaCommand::DeviceGetCount) {
+        CudaResponse::DeviceCount(n) => n,
+        other => return Err(error_of(other)),
+    };
+    let mut devices = Vec::new();
+    for ordinal in 0..count {
+        match send(CudaCommand::DeviceGet { ordinal }) {
+            CudaResponse::Device(handle) => devices.push(handle),
+            other => return Err(error_of(other)),
+        }
+    }
+    if devices.is_empty() {
+        return Err(CUDA_ERROR_NO_DEVICE);
+    }
+    debug!("CUDA runtime: {} device(s)", devices.len());
+    Ok(Runtime {
+        contexts: vec![None; devices.len()],
+        devices,
+        active: None,
+    })
+}
+
+fn with_runtime<T>(f: impl FnOnce(&mut Runtime) -> Result<T, CudaError>) -> Result<T, CudaError> {
+    let mut guard = RUNTIME.lock();
+    if guard.is_none() {
+        *guard = Some(load_runtime()?);
+    }
+    f(guard.as_mut().expect("runtime was just loaded"))
+}
+
+fn device_handle(device: i32) -> Result<NetworkHandle, CudaError> {
+    with_runtime(|rt| rt.devices.get(device as usize).copied().ok_or(CUDA_ERROR_INVALID_DEVICE))
+}
+
+/// Make the calling thread's device current on the daemon connection,
+/// retaining its primary context on first use. Returns its ordinal.
+fn ensure_context() -> Result<i32, CudaError> {
+    let device = CURRENT_DEVICE.with(Cell::get);
+    with_runtime(|rt| {
+        let handle = *rt.devices.get(device as usize).ok_or(CUDA_ERROR_INVALID_DEVICE)?;
+        let ctx = match rt.contexts[device as usize] {
+            Some(ctx) => ctx,
+            None => {
+                let ctx = match send(CudaCommand::DevicePrimaryCtxRetain { device: handle }) {
+                    CudaResponse::Context(ctx) => ctx,
+                    other => return Err(error_of(other)),
+                };
+                rt.contexts[device as usize] = Some(ctx);
+                rt.active = None;
+                ctx
+            }
+        };
+        if rt.active != Some(device) {
+            check(send(CudaCommand::CtxSetCurrent { ctx }))?;
+            rt.active = Some(device);
+        }
+        Ok(device)
+    })
+}
+
+// ── Handle Tables ───────────────────────────────────────────────────
+
+/// Device pointers come from their own range, one `DEVICE_PTR_SPAN`-sized
+/// slot per allocation, so a pointer into an allocation (`d_buf + i`) can be
+/// traced back to it. Same scheme as the driver interposer.
+const DEVICE_PTR_BASE: u64 = 1 << 48;
+const DEVICE_PTR_SPAN: u64 = 1 << 36;
+static NEXT_DEVICE_PTR: AtomicU64 = AtomicU64::new(DEVICE_PTR_BASE);
+static ALLOCATIONS: Mutex<BTreeMap<u64, NetworkHandle>> = Mutex::new(BTreeMap::new());
+
+/// Streams and events by the opaque value handed to the application.
+static NEXT_ID: AtomicU64 = AtomicU64::new(0x1000);
+static HANDLES: Mutex<BTreeMap<u64, NetworkHandle>> = Mutex::new(BTreeMap::new());
+
+/// Page-locked host buffers (plain local memory here) and their layouts.
+static HOST_ALLOCATIONS: Mutex<BTreeMap<u64, Layout>> = Mutex::new(BTreeMap::new());
+
+fn store_allocation(handle: NetworkHandle) -> u64 {
+    let ptr = NEXT_DEVICE_PTR.fetch_add(DEVICE_PTR_SPAN, Ordering::Relaxed);
+    ALLOCATIONS.lock().insert(ptr, handle);
+    ptr
+}
+
+/// The allocation a device pointer points into and the offset into it.
+fn resolve_device_ptr(ptr: u64) -> Option<(NetworkHandle, u64)> {
+    if ptr < DEVICE_PTR_BASE {
+        return None;
+    }
+    let offset = (ptr - DEVICE_PTR_BASE) % DEVICE_PTR_SPAN;
+    ALLOCATIONS.lock().get(&(ptr - offset)).map(|handle| (*handle, offset))
+}
+
+fn store_handle(handle: NetworkHandle) -> u64 {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    HANDLES.lock().insert(id, handle);
+    id
+}
+
+fn get_handle(id: *mut c_void) -> Result<NetworkHandle, CudaError> {
+    HANDLES.lock().get(&(id as u64)).copied().ok_or(CUDA_ERROR_INVALID_RESOURCE_HANDLE)
+}
+
+fn stream_handle(stream: CudaStream) -> Result<NetworkHandle, CudaError> {
+    match stream as u64 {
+        0 | STREAM_LEGACY | STREAM_PER_THREAD => Ok(NetworkHandle {
+            server_id: 0,
+            session_id: 0,
+            resource_id: 0,
+            resource_type: ResourceType::CuStream,
+        }),
+        _ => get_handle(stream),
+    }
+}
+
+// ── Data Movement ───────────────────────────────────────────────────
+
+/// Read `len` bytes at `offset` in an allocation. The protocol reads from
+/// the start of an allocation, so the bytes before `offset` come too.
+fn read_device(src: NetworkHandle, offset: u64, len: usize) -> Result<Vec<u8>, CudaError> {
+    match send(CudaCommand::MemcpyDtoH {
+        src,
+        byte_count: offset + len as u64,
+    }) {
+        CudaResponse::MemoryData(mut data) if data.len() >= offset as usize + len => {
+            data.truncate(offset as usize + len);
+            Ok(data.split_off(offset as usize))
+        }
+        CudaResponse::MemoryData(_) => Err(CUDA_ERROR_UNKNOWN),
+        other => Err(error_of(other)),
+    }
+}
+
+/// Write `data` at `offset` in an allocation; past the start, the bytes
+/// before `offset` are read and written back with it.
+fn write_device(dst: NetworkHandle, offset: u64, data: Vec<u8>) -> Result<(), CudaError> {
+    let data = if offset == 0 {
+        data
+    } else {
+        let mut whole = read_device(dst, 0, offset as usize)?;
+        whole.extend_from_slice(&data);
+        whole
+    };
+    check(send(CudaCommand::MemcpyHtoD {
+        dst,
+        byte_count: data.len() as u64,
+        src_data: data,
+    }))
+}
+
+/// The direction `cudaMemcpyDefault` means for these pointers.
+fn memcpy_kind(src_on_device: bool, dst_on_device: bool) -> c_int {
+    match (src_on_device, dst_on_device) {
+        (false, false) => MEMCPY_HOST_TO_HOST,
+        (false, true) => MEMCPY_HOST_TO_DEVICE,
+        (true, false) => MEMCPY_DEVICE_TO_HOST,
+        (true, true) => MEMCPY_DEVICE_TO_DEVICE,
+    }
+}
+
+unsafe fn memcpy(dst: *mut c_void, src: *const c_void, count: usize, kind: c_int) -> Result<(), CudaError> {
+    if count == 0 {
+        return Ok(());
+    }
+    let dst_device = resolve_device_ptr(dst as u64);
+    let src_device = resolve_device_ptr(src as u64);
+    let kind = if kind == MEMCPY_DEFAULT {
+        memcpy_kind(src_device.is_some(), dst_device.is_some())
+    } else {
+        kind
+    };
+    debug!("cudaMemcpy({} bytes, kind={})", count, kind);
+
+    match (kind, src_device, dst_device) {
+        (MEMCPY_HOST_TO_HOST, None, None) => {
+            if dst.is_null() || src.is_null() {
+                return Err(CUDA_ERROR_INVALID_VALUE);
+            }
+            std::ptr::copy(src as *const u8, dst as *mut u8, count);
+            Ok(())
+        }
+        (MEMCPY_HOST_TO_DEVICE, None, Some((dst, offset))) => {
+            if src.is_null() {
+                return Err(CUDA_ERROR_INVALID_VALUE);
+            }
+            ensure_context()?;
+            let data = std::slice::from_raw_parts(src as *const u8, count).to_vec();
+            write_device(dst, offset, data)
+        }
+        (MEMCPY_DEVICE_TO_HOST, Some((src, offset)), None) => {
+            if dst.is_null() {
+                return Err(CUDA_ERROR_INVALID_VALUE);
+            }
+            ensure_context()?;
+            let data = read_device(src, offset, count)?;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), dst as *mut u8, count);
+            Ok(())
+        }
+        (MEMCPY_DEVICE_TO_DEVICE, Some((src, 0)), Some((dst, 0))) => {
+            ensure_context()?;
+            check(send(CudaCommand::MemcpyDtoD {
+                dst,
+                src,
+                byte_count: count as u64,
+            }))
+        }
+        (MEMCPY_DEVICE_TO_DEVICE, Some((src, src_offset)), Some((dst, dst_offset))) => {
+            ensure_context()?;
+            let data = read_device(src, src_offset, count)?;
+            write_device(dst, dst_offset, data)
+        }
+        (MEMCPY_HOST_TO_HOST..=MEMCPY_DEVICE_TO_DEVICE, ..) => Err(CUDA_ERROR_INVALID_DEVICE_POINTER),
+        _ => Err(CUDA_ERROR_INVALID_MEMCPY_DIRECTION),
+    }
+}
+
+unsafe fn memset(dev_ptr: *mut c_void, value: c_int, count: usize) -> Result<(), CudaError> {
+    let (dst, offset) = resolve_device_ptr(dev_ptr as u64).ok_or(CUDA_ERROR_INVALID_DEVICE_POINTER)?;
+    ensure_context()?;
+    if offset == 0 {
+        check(send(CudaCommand::MemsetD8 {
+            dst,
+            value: value as u8,
+            count: count as u64,
+        }))
+    } else {
+        write_device(dst, offset, vec![value as u8; count])
+    }
+}
+
+// ── Kernels and Symbols ─────────────────────────────────────────────
+
+/// Launch configuration pushed by `<<<...>>>` and popped by the stub.
+struct CallConfig {
+    grid: Dim3,
+    block: Dim3,
+    shared_mem: usize,
+    stream: CudaStream,
+}
+
+/// The module a fat binary is loaded as on `device`, loading it first.
+fn load_module(fatbin: u64, device: i32) -> Result<NetworkHandle, CudaError> {
+    if let Some(module) = registry::module(fatbin, device) {
+        return Ok(module);
+    }
+    let image = registry::image(fatbin).ok_or(CUDA_ERROR_INVALID_DEVICE_FUNCTION)?;
+    debug!("loading fat binary {} ({} bytes) on device {}", fatbin, image.len(), device);
+    match send(CudaCommand::ModuleLoadData { image }) {
+        CudaResponse::Module(module) => {
+            registry::set_module(fatbin, device, module);
+            Ok(module)
+        }
+        other => Err(error_of(other)),
+    }
+}
+
+/// The function behind the kernel stub `host_fn` on `device`.
+fn load_function(host_fn: usize, device: i32) -> Result<LoadedFunction, CudaError> {
+    if let Some(function) = registry::function(host_fn, device) {
+        return Ok(function);
+    }
+    let (fatbin, name) = registry::kernel(host_fn).ok_or(CUDA_ERROR_INVALID_DEVICE_FUNCTION)?;
+    let module = load_module(fatbin, device)?;
+    match send(CudaCommand::ModuleGetFunction { module, name }) {
+        CudaResponse::Function(handle) => {
+            let function = (handle, None);
+            registry::set_function(host_fn, device, function.clone());
+            Ok(function)
+        }
+        CudaResponse::FunctionWithParams { handle, param_sizes } => {
+            let function = (handle, param_sizes);
+            registry::set_function(host_fn, device, function.clone());
+            Ok(function)
+        }
+        other => Err(error_of(other)),
+    }
+}
+
+/// The allocation behind the `__device__` variable shadowed at `symbol`,
+/// and its size.
+fn load_symbol(symbol: *const c_void) -> Result<(NetworkHandle, u64), CudaError> {
+    let device = ensure_context()?;
+    let (fatbin, name) = registry::variable(symbol as usize).ok_or(CUDA_ERROR_INVALID_SYMBOL)?;
+    let module = load_module(fatbin, device)?;
+    match send(CudaCommand::ModuleGetGlobal { module, name }) {
+        CudaResponse::GlobalPtr { ptr, size } => Ok((ptr, size)),
+        other => Err(error_of(other)),
+    }
+}
+
+/// Copy a launch's arguments, as the driver interposer does: at the sizes
+/// the server reported for the function, or else as 8-byte values up to a
+/// null pointer. 8-byte arguments that point into an allocation are sent as
+/// the allocation and offset.
+unsafe fn collect_kernel_params(param_sizes: Option<&[u32]>, args: *mut *mut c_void) -> Vec<KernelParam> {
+    let mut params = Vec::new();
+    if args.is_null() {
+        return params;
+    }
+    if let Some(sizes) = param_sizes {
+        for (i, &size) in sizes.iter().enumerate() {
+            let arg = *args.add(i);
+            let data = if arg.is_null() {
+                vec![0; size as usize]
+            } else {
+                std::slice::from_raw_parts(arg as *const u8, size as usize).to_vec()
+            };
+            params.push(kernel_param(data));
+        }
+        return params;
+    }
+    for i in 0..256 {
+        let arg = *args.add(i);
+        if arg.is_null() {
+            break;
+        }
+        params.push(kernel_param(
+            std::slice::from_raw_parts(arg as *const u8, std::mem::size_of::<u64>()).to_vec(),
+        ));
+    }
+    params
+}
+
+fn kernel_param(data: Vec<u8>) -> KernelParam {
+    if let Ok(bytes) = <[u8; 8]>::try_from(data.as_slice()) {
+        if let Some((handle, offset)) = resolve_device_ptr(u64::from_le_bytes(bytes)) {
+            return KernelParam {
+                data: offset.to_le_bytes().to_vec(),
+                device_ptr: Some(handle),
+            };
+        }
+    }
+    KernelParam { data, device_ptr: None }
+}
+
+unsafe fn launch_kernel(
+    func: *const c_void,
+    grid: Dim3,
+    block: Dim3,
+    args: *mut *mut c_void,
+    shared_mem: usize,
+    stream: CudaStream,
+) -> Result<(), CudaError> {
+    let device = ensure_context()?;
+    let (function, param_sizes) = load_function(func as usize, device)?;
+    let stream = stream_handle(stream)?;
+    let kernel_params = collect_kernel_params(param_sizes.as_deref(), args);
+    debug!(
+        "cudaLaunchKernel(grid=[{}x{}x{}], block=[{}x{}x{}], shared={}, params={})",
+        grid.x, grid.y, grid.z, block.x, block.y, block.z, shared_mem, kernel_params.len()
+    );
+    check(send(CudaCommand::LaunchKernel {
+        func: function,
+        grid_dim: [grid.x, grid.y, grid.z],
+        block_dim: [block.x, block.y, block.z],
+        shared_mem_bytes: shared_mem as u32,
+        stream,
+        kernel_params,
+    }))
+}
+
+// ── Exported CUDA Runtime Functions ─────────────────────────────────
+
+// ── Registration (nvcc-generated) ───────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn __cudaRegisterFatBinary(fat_cubin: *mut c_void) -> *mut *mut c_void {
+    match registry::fatbin_image(fat_cubin) {
+        Some(image) => registry::register_fatbin(image) as *mut *mut c_void,
+        None => {
+            error!("__cudaRegisterFatBinary: unrecognized fat binary");
+            std::ptr::null_mut()
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __cudaRegisterFatBinaryEnd(_fat_cubin_handle: *mut *mut c_void) {}
+
+#[no_mangle]
+pub unsafe extern "C" fn __cudaUnregisterFatBinary(fat_cubin_handle: *mut *mut c_void) {
+    for module in registry::unregister_fatbin(fat_cubin_handle as u64) {
+        let _ = send(CudaCommand::ModuleUnload { module });
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __cudaRegisterFunction(
+    fat_cubin_handle: *mut *mut c_void,
+    host_fun: *const c_char,
+    _device_fun: *mut c_char,
+    device_name: *const c_char,
+    _thread_limit: c_int,
+    _tid: *mut c_void,
+    _bid: *mut c_void,
+    _b_dim: *mut Dim3,
+    _g_dim: *mut Dim3,
+    _w_size: *mut c_int,
+) {
+    if device_name.is_null() {
+        return;
+    }
+    let name = CStr::from_ptr(device_name).to_string_lossy().into_owned();
+    registry::register_kernel(fat_cubin_handle as u64, host_fun as usize, name);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __cudaRegisterVar(
+    fat_cubin_handle: *mut *mut c_void,
+    host_var: *mut c_char,
+    _device_address: *mut c_char,
+    device_name: *const c_char,
+    _ext: c_int,
+    _size: usize,
+    _constant: c_int,
+    _global: c_int,
+) {
+    if device_name.is_null() {
+        return;
+    }
+    let name = CStr::from_ptr(device_name).to_string_lossy().into_owned();
+    registry::register_variable(fat_cubin_handle as u64, host_var as usize, name);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __cudaPushCallConfiguration(
+    grid_dim: Dim3,
+    block_dim: Dim3,
+    shared_mem: usize,
+    stream: CudaStream,
+) -> c_uint {
+    CALL_CONFIGS.with(|configs| {
+        configs.borrow_mut().push(CallConfig {
+            grid: grid_dim,
+            block: block_dim,
+            shared_mem,
+            stream,
+        })
+    });
+    0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn __cudaPopCallConfiguration(
+    grid_dim: *mut Dim3,
+    block_dim: *mut Dim3,
+    shared_mem: *mut usize,
+    stream: *mut CudaStream,
+) -> CudaError {
+    let Some(config) = CALL_CONFIGS.with(|configs| configs.borrow_mut().pop()) else {
+        return ret(Err(CUDA_ERROR_MISSING_CONFIGURATION));
+    };
+    if grid_dim.is_null() || block_dim.is_null() || shared_mem.is_null() || stream.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    *grid_dim = config.grid;
+    *block_dim = config.block;
+    *shared_mem = config.shared_mem;
+    *stream = config.stream;
+    CUDA_SUCCESS
+}
+
+// ── Error Handling ──────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetLastError() -> CudaError {
+    LAST_ERROR.with(|e| e.replace(CUDA_SUCCESS))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaPeekAtLastError() -> CudaError {
+    LAST_ERROR.with(Cell::get)
+}
+
+/// Name and description of an error code.
+fn error_text(error: CudaError) -> (&'static CStr, &'static CStr) {
+    match error {
+        0 => (c"cudaSuccess", c"no error"),
+        1 => (c"cudaErrorInvalidValue", c"invalid argument"),
+        2 => (c"cudaErrorMemoryAllocation", c"out of memory"),
+        3 => (c"cudaErrorInitializationError", c"initialization error"),
+        13 => (c"cudaErrorInvalidSymbol", c"invalid device symbol"),
+        17 => (c"cudaErrorInvalidDevicePointer", c"invalid device pointer"),
+        21 => (c"cudaErrorInvalidMemcpyDirection", c"invalid copy direction for memcpy"),
+        52 => (c"cudaErrorMissingConfiguration", c"__global__ function call is not configured"),
+        98 => (c"cudaErrorInvalidDeviceFunction", c"invalid device function"),
+        100 => (c"cudaErrorNoDevice", c"no CUDA-capable device is detected"),
+        101 => (c"cudaErrorInvalidDevice", c"invalid device ordinal"),
+        200 => (c"cudaErrorInvalidKernelImage", c"device kernel image is invalid"),
+        400 => (c"cudaErrorInvalidResourceHandle", c"invalid resource handle"),
+        500 => (c"cudaErrorSymbolNotFound", c"named symbol not found"),
+        600 => (c"cudaErrorNotReady", c"device not ready"),
+        700 => (c"cudaErrorIllegalAddress", c"an illegal memory access was encountered"),
+        701 => (c"cudaErrorLaunchOutOfResources", c"too many resources requested for launch"),
+        719 => (c"cudaErrorLaunchFailure", c"unspecified launch failure"),
+        801 => (c"cudaErrorNotSupported", c"operation not supported"),
+        999 => (c"cudaErrorUnknown", c"unknown error"),
+        _ => (c"cudaErrorUnknown", c"unrecognized error code"),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetErrorName(error: CudaError) -> *const c_char {
+    error_text(error).0.as_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetErrorString(error: CudaError) -> *const c_char {
+    error_text(error).1.as_ptr()
+}
+
+// ── Version ─────────────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaDriverGetVersion(driver_version: *mut c_int) -> CudaError {
+    if driver_version.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(match send(CudaCommand::DriverGetVersion) {
+        CudaResponse::DriverVersion(v) => {
+            *driver_version = v;
+            Ok(())
+        }
+        other => Err(error_of(other)),
+    })
+}
+
+/// Reports the server driver's version: this runtime supports whatever the
+/// driver it forwards to does.
+#[no_mangle]
+pub unsafe extern "C" fn cudaRuntimeGetVersion(runtime_version: *mut c_int) -> CudaError {
+    cudaDriverGetVersion(runtime_version)
+}
+
+// ── Device Management ───────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetDeviceCount(count: *mut c_int) -> CudaError {
+    if count.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(with_runtime(|rt| {
+        *count = rt.devices.len() as c_int;
+        Ok(())
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaSetDevice(device: c_int) -> CudaError {
+    debug!("cudaSetDevice({})", device);
+    ret(device_handle(device).map(|_| CURRENT_DEVICE.with(|d| d.set(device))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetDevice(device: *mut c_int) -> CudaError {
+    if device.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    *device = CURRENT_DEVICE.with(Cell::get);
+    CUDA_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaSetDeviceFlags(flags: c_uint) -> CudaError {
+    let device = CURRENT_DEVICE.with(Cell::get);
+    ret(device_handle(device)
+        .and_then(|device| check(send(CudaCommand::DevicePrimaryCtxSetFlags { device, flags }))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaDeviceSynchronize() -> CudaError {
+    ret(ensure_context().and_then(|_| check(send(CudaCommand::CtxSynchronize))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaThreadSynchronize() -> CudaError {
+    cudaDeviceSynchronize()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaDeviceReset() -> CudaError {
+    let device = CURRENT_DEVICE.with(Cell::get);
+    ret(with_runtime(|rt| {
+        let handle = *rt.devices.get(device as usize).ok_or(CUDA_ERROR_INVALID_DEVICE)?;
+        if rt.contexts[device as usize].take().is_some() {
+            check(send(CudaCommand::DevicePrimaryCtxReset { device: handle }))?;
+            if rt.active == Some(device) {
+                rt.active = None;
+            }
+            registry::forget_device(device);
+        }
+        Ok(())
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaDeviceGetAttribute(value: *mut c_int, attr: c_int, device: c_int) -> CudaError {
+    if value.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    // cudaDeviceAttr shares its values with CUdevice_attribute.
+    ret(device_handle(device).and_then(|device| {
+        match send(CudaCommand::DeviceGetAttribute { attrib: attr, device }) {
+            CudaResponse::DeviceAttribute(v) => {
+                *value = v;
+                Ok(())
+            }
+            other => Err(error_of(other)),
+        }
+    }))
+}
+
+unsafe fn device_properties(prop: *mut CudaDevicePropPrefix, device: c_int) -> Result<(), CudaError> {
+    if prop.is_null() {
+        return Err(CUDA_ERROR_INVALID_VALUE);
+    }
+    let handle = device_handle(device)?;
+    let attribute = |attrib: i32| match send(CudaCommand::DeviceGetAttribute { attrib, device: handle }) {
+        CudaResponse::DeviceAttribute(v) => v,
+        _ => 0,
+    };
+    let name = match send(CudaCommand::DeviceGetName { device: handle }) {
+        CudaResponse::DeviceName(name) => name,
+        other => return Err(error_of(other)),
+    };
+    let total_global_mem = match send(CudaCommand::DeviceTotalMem { device: handle }) {
+        CudaResponse::DeviceTotalMem(bytes) => bytes,
+        other => return Err(error_of(other)),
+    };
+    let (major, minor) = match send(CudaCommand::DeviceComputeCapability { device: handle }) {
+        CudaResponse::ComputeCapability { major, minor } => (major, minor),
+        other => return Err(error_of(other)),
+    };
+    let uuid = match send(CudaCommand::DeviceGetUuid { device: handle }) {
+        CudaResponse::DeviceUuid(bytes) => bytes,
+        _ => Vec::new(),
+    };
+
+    let prop = &mut *prop;
+    let len = name.len().min(prop.name.len() - 1);
+    for (dst, src) in prop.name.iter_mut().zip(&name.as_bytes()[..len]) {
+        *dst = *src as c_char;
+    }
+    prop.name[len] = 0;
+    prop.uuid = [0; 16];
+    let uuid_len = uuid.len().min(16);
+    prop.uuid[..uuid_len].copy_from_slice(&uuid[..uuid_len]);
+    prop.luid = [0; 8];
+    prop.luid_device_node_mask = 0;
+    prop.total_global_mem = total_global_mem as usize;
+    prop.shared_mem_per_block = attribute(ATTR_MAX_SHARED_MEMORY_PER_BLOCK) as usize;
+    prop.regs_per_block = attribute(ATTR_MAX_REGISTERS_PER_BLOCK);
+    prop.warp_size = attribute(ATTR_WARP_SIZE);
+    prop.mem_pitch = attribute(ATTR_MAX_PITCH) as usize;
+    prop.max_threads_per_block = attribute(ATTR_MAX_THREADS_PER_BLOCK);
+    for i in 0..3 {
+        prop.max_threads_dim[i] = attribute(ATTR_MAX_BLOCK_DIM_X + i as i32);
+        prop.max_grid_size[i] = attribute(ATTR_MAX_GRID_DIM_X + i as i32);
+    }
+    prop.clock_rate = attribute(ATTR_CLOCK_RATE);
+    prop.total_const_mem = attribute(ATTR_TOTAL_CONSTANT_MEMORY) as usize;
+    prop.major = major;
+    prop.minor = minor;
+    prop.texture_alignment = attribute(ATTR_TEXTURE_ALIGNMENT) as usize;
+    prop.texture_pitch_alignment = attribute(ATTR_TEXTURE_PITCH_ALIGNMENT) as usize;
+    prop.device_overlap = attribute(ATTR_GPU_OVERLAP);
+    prop.multi_processor_count = attribute(ATTR_MULTIPROCESSOR_COUNT);
+    Ok(())
+}
+
+/// Fills the leading fields of `cudaDeviceProp` (see
+/// [`CudaDevicePropPrefix`]); the rest are left as the caller set them, so
+/// applications that read others should use cudaDeviceGetAttribute.
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetDeviceProperties(prop: *mut CudaDevicePropPrefix, device: c_int) -> CudaError {
+    ret(device_properties(prop, device))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetDeviceProperties_v2(prop: *mut CudaDevicePropPrefix, device: c_int) -> CudaError {
+    ret(device_properties(prop, device))
+}
+
+// ── Memory Management ───────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMalloc(dev_ptr: *mut *mut c_void, size: usize) -> CudaError {
+    if dev_ptr.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    debug!("cudaMalloc({} bytes)", size);
+    ret(ensure_context().and_then(|_| {
+        match send(CudaCommand::MemAlloc { byte_size: size as u64 }) {
+            CudaResponse::MemAllocated(handle) => {
+                *dev_ptr = store_allocation(handle) as *mut c_void;
+                Ok(())
+            }
+            other => Err(error_of(other)),
+        }
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaFree(dev_ptr: *mut c_void) -> CudaError {
+    if dev_ptr.is_null() {
+        return CUDA_SUCCESS;
+    }
+    let Some(handle) = ALLOCATIONS.lock().get(&(dev_ptr as u64)).copied() else {
+        return ret(Err(CUDA_ERROR_INVALID_DEVICE_POINTER));
+    };
+    ret(check(send(CudaCommand::MemFree { dptr: handle })).map(|()| {
+        ALLOCATIONS.lock().remove(&(dev_ptr as u64));
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMemGetInfo(free: *mut usize, total: *mut usize) -> CudaError {
+    if free.is_null() || total.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(ensure_context().and_then(|_| match send(CudaCommand::MemGetInfo) {
+        CudaResponse::MemInfo { free: f, total: t } => {
+            *free = f as usize;
+            *total = t as usize;
+            Ok(())
+        }
+        other => Err(error_of(other)),
+    }))
+}
+
+/// Page-locked memory can't be shared with the server, and copies go
+/// through the daemon either way, so this is ordinary host memory.
+#[no_mangle]
+pub unsafe extern "C" fn cudaHostAlloc(ptr: *mut *mut c_void, size: usize, _flags: c_uint) -> CudaError {
+    if ptr.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    let Ok(layout) = Layout::from_size_align(size.max(1), PAGE_SIZE) else {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    };
+    let p = std::alloc::alloc(layout);
+    if p.is_null() {
+        return ret(Err(CUDA_ERROR_MEMORY_ALLOCATION));
+    }
+    HOST_ALLOCATIONS.lock().insert(p as u64, layout);
+    *ptr = p as *mut c_void;
+    CUDA_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMallocHost(ptr: *mut *mut c_void, size: usize) -> CudaError {
+    cudaHostAlloc(ptr, size, 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaFreeHost(ptr: *mut c_void) -> CudaError {
+    if ptr.is_null() {
+        return CUDA_SUCCESS;
+    }
+    match HOST_ALLOCATIONS.lock().remove(&(ptr as u64)) {
+        Some(layout) => {
+            std::alloc::dealloc(ptr as *mut u8, layout);
+            CUDA_SUCCESS
+        }
+        None => ret(Err(CUDA_ERROR_INVALID_VALUE)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMemcpy(dst: *mut c_void, src: *const c_void, count: usize, kind: c_int) -> CudaError {
+    ret(memcpy(dst, src, count, kind))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMemcpyAsync(
+    dst: *mut c_void,
+    src: *const c_void,
+    count: usize,
+    kind: c_int,
+    stream: CudaStream,
+) -> CudaError {
+    ret(stream_handle(stream).and_then(|_| memcpy(dst, src, count, kind)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMemset(dev_ptr: *mut c_void, value: c_int, count: usize) -> CudaError {
+    ret(memset(dev_ptr, value, count))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMemsetAsync(
+    dev_ptr: *mut c_void,
+    value: c_int,
+    count: usize,
+    stream: CudaStream,
+) -> CudaError {
+    ret(stream_handle(stream).and_then(|_| memset(dev_ptr, value, count)))
+}
+
+// ── Symbols ─────────────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMemcpyToSymbol(
+    symbol: *const c_void,
+    src: *const c_void,
+    count: usize,
+    offset: usize,
+    kind: c_int,
+) -> CudaError {
+    ret((|| {
+        let (global, size) = load_symbol(symbol)?;
+        if (offset + count) as u64 > size || src.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let data = match (kind, resolve_device_ptr(src as u64)) {
+            (MEMCPY_DEVICE_TO_DEVICE | MEMCPY_DEFAULT, Some((src, src_offset))) => read_device(src, src_offset, count)?,
+            (MEMCPY_HOST_TO_DEVICE | MEMCPY_DEFAULT, None) => {
+                std::slice::from_raw_parts(src as *const u8, count).to_vec()
+            }
+            _ => return Err(CUDA_ERROR_INVALID_MEMCPY_DIRECTION),
+        };
+        write_device(global, offset as u64, data)
+    })())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaMemcpyFromSymbol(
+    dst: *mut c_void,
+    symbol: *const c_void,
+    count: usize,
+    offset: usize,
+    kind: c_int,
+) -> CudaError {
+    ret((|| {
+        let (global, size) = load_symbol(symbol)?;
+        if (offset + count) as u64 > size || dst.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let data = read_device(global, offset as u64, count)?;
+        match (kind, resolve_device_ptr(dst as u64)) {
+            (MEMCPY_DEVICE_TO_DEVICE | MEMCPY_DEFAULT, Some((dst, dst_offset))) => write_device(dst, dst_offset, data),
+            (MEMCPY_DEVICE_TO_HOST | MEMCPY_DEFAULT, None) => {
+                std::ptr::copy_nonoverlapping(data.as_ptr(), dst as *mut u8, count);
+                Ok(())
+            }
+            _ => Err(CUDA_ERROR_INVALID_MEMCPY_DIRECTION),
+        }
+    })())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetSymbolAddress(dev_ptr: *mut *mut c_void, symbol: *const c_void) -> CudaError {
+    if dev_ptr.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(load_symbol(symbol).map(|(global, _)| *dev_ptr = store_allocation(global) as *mut c_void))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaGetSymbolSize(size: *mut usize, symbol: *const c_void) -> CudaError {
+    if size.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(load_symbol(symbol).map(|(_, bytes)| *size = bytes as usize))
+}
+
+// ── Execution Control ───────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaLaunchKernel(
+    func: *const c_void,
+    grid_dim: Dim3,
+    block_dim: Dim3,
+    args: *mut *mut c_void,
+    shared_mem: usize,
+    stream: CudaStream,
+) -> CudaError {
+    ret(launch_kernel(func, grid_dim, block_dim, args, shared_mem, stream))
+}
+
+// ── Stream Management ───────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaStreamCreateWithPriority(
+    stream: *mut CudaStream,
+    flags: c_uint,
+    priority: c_int,
+) -> CudaError {
+    if stream.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(ensure_context().and_then(|_| {
+        match send(CudaCommand::StreamCreateWithPriority { flags, priority }) {
+            CudaResponse::Stream(handle) => {
+                *stream = store_handle(handle) as CudaStream;
+                Ok(())
+            }
+            other => Err(error_of(other)),
+        }
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaStreamCreateWithFlags(stream: *mut CudaStream, flags: c_uint) -> CudaError {
+    cudaStreamCreateWithPriority(stream, flags, 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaStreamCreate(stream: *mut CudaStream) -> CudaError {
+    cudaStreamCreateWithPriority(stream, 0, 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaStreamDestroy(stream: CudaStream) -> CudaError {
+    ret(get_handle(stream).and_then(|handle| {
+        check(send(CudaCommand::StreamDestroy { stream: handle }))?;
+        HANDLES.lock().remove(&(stream as u64));
+        Ok(())
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaStreamSynchronize(stream: CudaStream) -> CudaError {
+    ret(ensure_context()
+        .and_then(|_| stream_handle(stream))
+        .and_then(|stream| check(send(CudaCommand::StreamSynchronize { stream }))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaStreamQuery(stream: CudaStream) -> CudaError {
+    let result = ensure_context()
+        .and_then(|_| stream_handle(stream))
+        .and_then(|stream| match send(CudaCommand::StreamQuery { stream }) {
+            CudaResponse::StreamStatus(true) => Ok(()),
+            CudaResponse::StreamStatus(false) => Err(CUDA_ERROR_NOT_READY),
+            other => Err(error_of(other)),
+        });
+    // Not ready isn't an error for cudaGetLastError.
+    match result {
+        Err(CUDA_ERROR_NOT_READY) => CUDA_ERROR_NOT_READY,
+        result => ret(result),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaStreamWaitEvent(stream: CudaStream, event: CudaEvent, flags: c_uint) -> CudaError {
+    ret(stream_handle(stream).and_then(|stream| {
+        let event = get_handle(event)?;
+        check(send(CudaCommand::StreamWaitEvent { stream, event, flags }))
+    }))
+}
+
+// ── Event Management ────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaEventCreateWithFlags(event: *mut CudaEvent, flags: c_uint) -> CudaError {
+    if event.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(ensure_context().and_then(|_| match send(CudaCommand::EventCreate { flags }) {
+        CudaResponse::Event(handle) => {
+            *event = store_handle(handle) as CudaEvent;
+            Ok(())
+        }
+        other => Err(error_of(other)),
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaEventCreate(event: *mut CudaEvent) -> CudaError {
+    cudaEventCreateWithFlags(event, 0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaEventDestroy(event: CudaEvent) -> CudaError {
+    ret(get_handle(event).and_then(|handle| {
+        check(send(CudaCommand::EventDestroy { event: handle }))?;
+        HANDLES.lock().remove(&(event as u64));
+        Ok(())
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaEventRecord(event: CudaEvent, stream: CudaStream) -> CudaError {
+    ret(get_handle(event).and_then(|event| {
+        let stream = stream_handle(stream)?;
+        check(send(CudaCommand::EventRecord { event, stream }))
+    }))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaEventSynchronize(event: CudaEvent) -> CudaError {
+    ret(get_handle(event).and_then(|event| check(send(CudaCommand::EventSynchronize { event }))))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaEventQuery(event: CudaEvent) -> CudaError {
+    let result = get_handle(event).and_then(|event| match send(CudaCommand::EventQuery { event }) {
+        CudaResponse::EventStatus(true) => Ok(()),
+        CudaResponse::EventStatus(false) => Err(CUDA_ERROR_NOT_READY),
+        other => Err(error_of(other)),
+    });
+    match result {
+        Err(CUDA_ERROR_NOT_READY) => CUDA_ERROR_NOT_READY,
+        result => ret(result),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cudaEventElapsedTime(ms: *mut f32, start: CudaEvent, end: CudaEvent) -> CudaError {
+    if ms.is_null() {
+        return ret(Err(CUDA_ERROR_INVALID_VALUE));
+    }
+    ret(get_handle(start).and_then(|start| {
+        let end = get_handle(end)?;
+        match send(CudaCommand::EventElapsedTime { start, end }) {
+            CudaResponse::ElapsedTime(t) => {
+                *ms = t;
+                Ok(())
+            }
+            other => Err(error_of(other)),
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(resource_id: u64) -> NetworkHandle {
+        NetworkHandle {
+            server_id: 1,
+            session_id: 1,
+            resource_id,
+            resource_type: ResourceType::CuDevicePtr,
+        }
+    }
+
+    #[test]
+    fn device_pointers_resolve_with_offset() {
+        let a = store_allocation(handle(1));
+        let b = store_allocation(handle(2));
+        assert_eq!(resolve_device_ptr(a), Some((handle(1), 0)));
+        assert_eq!(resolve_device_ptr(b + 256), Some((handle(2), 256)));
+        assert_eq!(resolve_device_ptr(0x7fff_0000), None);
+        assert!(resolve_device_ptr(NEXT_DEVICE_PTR.load(Ordering::Relaxed)).is_none());
+    }
+
+    #[test]
+    fn default_copy_direction() {
+        assert_eq!(memcpy_kind(false, true), MEMCPY_HOST_TO_DEVICE);
+        assert_eq!(memcpy_kind(true, false), MEMCPY_DEVICE_TO_HOST);
+        assert_eq!(memcpy_kind(true, true), MEMCPY_DEVICE_TO_DEVICE);
+        assert_eq!(memcpy_kind(false, false), MEMCPY_HOST_TO_HOST);
+    }
+
+    #[test]
+    fn launch_configuration_round_trips() {
+        let dims = |x| Dim3 { x, y: 1, z: 1 };
+        unsafe {
+            __cudaPushCallConfiguration(dims(4), dims(256), 128, std::ptr::null_mut());
+            let (mut grid, mut block, mut shared, mut stream) = (dims(0), dims(0), 0, 1 as CudaStream);
+            assert_eq!(__cudaPopCallConfiguration(&mut grid, &mut block, &mut shared, &mut stream), CUDA_SUCCESS);
+            assert_eq!((grid.x, block.x, shared, stream), (4, 256, 128, std::ptr::null_mut()));
+            assert_eq!(
+                __cudaPopCallConfiguration(&mut grid, &mut block, &mut shared, &mut stream),
+                CUDA_ERROR_MISSING_CONFIGURATION
+            );
+            assert_eq!(cudaGetLastError(), CUDA_ERROR_MISSING_CONFIGURATION);
+            assert_eq!(cudaGetLastError(), CUDA_SUCCESS);
+        }
+    }
+}
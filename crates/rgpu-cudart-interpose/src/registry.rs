@@ -0,0 +1,228 @@
+//! Kernels and device variables registered by nvcc-generated code.
+//!
+//! Every translation unit nvcc compiles embeds a fat binary and, from a
+//! static constructor, registers it together with the host stub of each
+//! kernel and the host shadow of each `__device__` variable. The launch and
+//! symbol APIs identify kernels and variables by those host addresses. This
+//! records the registrations, and the module and functions loaded from each
+//! fat binary on each device the first time they are used there.
+
+use std::collections::HashMap;
+use std::ffi::c_void;
+
+use parking_lot::Mutex;
+
+use rgpu_protocol::handle::NetworkHandle;
+
+/// `__fatBinC_Wrapper_t::magic`
+const WRAPPER_MAGIC: u32 = 0x466243b1;
+/// Magic at the start of the fat binary the wrapper points to.
+const FATBIN_MAGIC: u32 = 0xba55ed50;
+
+struct FatBinary {
+    image: Vec<u8>,
+    /// Loaded module by device ordinal
+    modules: HashMap<i32, NetworkHandle>,
+}
+
+/// A kernel's function on one device and its parameter sizes, if known.
+pub type LoadedFunction = (NetworkHandle, Option<Vec<u32>>);
+
+struct Kernel {
+    fatbin: u64,
+    name: String,
+    functions: HashMap<i32, LoadedFunction>,
+}
+
+struct Variable {
+    fatbin: u64,
+    name: String,
+}
+
+#[derive(Default)]
+struct Registry {
+    next_id: u64,
+    fatbins: HashMap<u64, FatBinary>,
+    /// By host stub address
+    kernels: HashMap<usize, Kernel>,
+    /// By host shadow address
+    variables: HashMap<usize, Variable>,
+}
+
+static REGISTRY: Mutex<Option<Registry>> = Mutex::new(None);
+
+fn with_registry<T>(f: impl FnOnce(&mut Registry) -> T) -> T {
+    f(REGISTRY.lock().get_or_insert_with(Registry::default))
+}
+
+/// Copy the fat binary behind an nvcc `__fatBinC_Wrapper_t`. `None` if the
+/// wrapper or the fat binary header isn't recognized.
+pub unsafe fn fatbin_image(wrapper: *const c_void) -> Option<Vec<u8>> {
+    if wrapper.is_null() {
+        return None;
+    }
+    let wrapper = wrapper as *const u8;
+    if std::ptr::read_unaligned(wrapper as *const u32) != WRAPPER_MAGIC {
+        return None;
+    }
+    // { int magic; int version; const unsigned long long *data; void *filename_or_fatbins; }
+    let data = std::ptr::read_unaligned(wrapper.add(8) as *const *const u8);
+    if data.is_null() || std::ptr::read_unaligned(data as *const u32) != FATBIN_MAGIC {
+        return None;
+    }
+    // { unsigned int magic; unsigned short version; unsigned short header_size; unsigned long long fat_size; }
+    let header_size = std::ptr::read_unaligned(data.add(6) as *const u16) as usize;
+    let fat_size = std::ptr::read_unaligned(data.add(8) as *const u64) as usize;
+    Some(std::slice::from_raw_parts(data, header_size + fat_size).to_vec())
+}
+
+/// Record a fat binary; the id is its handle for the other registrations.
+pub fn register_fatbin(image: Vec<u8>) -> u64 {
+    with_registry(|r| {
+        r.next_id += 1;
+        r.fatbins.insert(
+            r.next_id,
+            FatBinary {
+                image,
+                modules: HashMap::new(),
+            },
+        );
+        r.next_id
+    })
+}
+
+/// Forget a fat binary with its kernels and variables. Returns the modules
+/// loaded from it, for the caller to unload.
+pub fn unregister_fatbin(fatbin: u64) -> Vec<NetworkHandle> {
+    with_registry(|r| {
+        r.kernels.retain(|_, kernel| kernel.fatbin != fatbin);
+        r.variables.retain(|_, variable| variable.fatbin != fatbin);
+        r.fatbins
+            .remove(&fatbin)
+            .map(|f| f.modules.into_values().collect())
+            .unwrap_or_default()
+    })
+}
+
+pub fn register_kernel(fatbin: u64, host_fn: usize, name: String) {
+    with_registry(|r| {
+        r.kernels.insert(
+            host_fn,
+            Kernel {
+                fatbin,
+                name,
+                functions: HashMap::new(),
+            },
+        );
+    })
+}
+
+pub fn register_variable(fatbin: u64, host_var: usize, name: String) {
+    with_registry(|r| {
+        r.variables.insert(host_var, Variable { fatbin, name });
+    })
+}
+
+/// Fat binary and device name of the kernel with host stub `host_fn`.
+pub fn kernel(host_fn: usize) -> Option<(u64, String)> {
+    with_registry(|r| r.kernels.get(&host_fn).map(|k| (k.fatbin, k.name.clone())))
+}
+
+/// Fat binary and device name of the variable shadowed at `host_var`.
+pub fn variable(host_var: usize) -> Option<(u64, String)> {
+    with_registry(|r| r.variables.get(&host_var).map(|v| (v.fatbin, v.name.clone())))
+}
+
+pub fn function(host_fn: usize, device: i32) -> Option<LoadedFunction> {
+    with_registry(|r| r.kernels.get(&host_fn)?.functions.get(&device).cloned())
+}
+
+pub fn set_function(host_fn: usize, device: i32, function: LoadedFunction) {
+    with_registry(|r| {
+        if let Some(kernel) = r.kernels.get_mut(&host_fn) {
+            kernel.functions.insert(device, function);
+        }
+    })
+}
+
+pub fn module(fatbin: u64, device: i32) -> Option<NetworkHandle> {
+    with_registry(|r| r.fatbins.get(&fatbin)?.modules.get(&device).copied())
+}
+
+pub fn set_module(fatbin: u64, device: i32, module: NetworkHandle) {
+    with_registry(|r| {
+        if let Some(f) = r.fatbins.get_mut(&fatbin) {
+            f.modules.insert(device, module);
+        }
+    })
+}
+
+pub fn image(fatbin: u64) -> Option<Vec<u8>> {
+    with_registry(|r| r.fatbins.get(&fatbin).map(|f| f.image.clone()))
+}
+
+/// Forget everything loaded on `device`, after a device reset.
+pub fn forget_device(device: i32) {
+    with_registry(|r| {
+        for f in r.fatbins.values_mut() {
+            f.modules.remove(&device);
+        }
+        for kernel in r.kernels.values_mut() {
+            kernel.functions.remove(&device);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_fatbin_through_wrapper() {
+        let mut fatbin = Vec::new();
+        fatbin.extend_from_slice(&FATBIN_MAGIC.to_le_bytes());
+        fatbin.extend_from_slice(&1u16.to_le_bytes());
+        fatbin.extend_from_slice(&16u16.to_le_bytes());
+        fatbin.extend_from_slice(&4u64.to_le_bytes());
+        fatbin.extend_from_slice(&[1, 2, 3, 4, 0xee, 0xee]);
+
+        #[repr(C)]
+        struct Wrapper {
+            magic: u32,
+            version: u32,
+            data: *const u8,
+            filename_or_fatbins: *const c_void,
+        }
+        let wrapper = Wrapper {
+            magic: WRAPPER_MAGIC,
+            version: 1,
+            data: fatbin.as_ptr(),
+            filename_or_fatbins: std::ptr::null(),
+        };
+        let image = unsafe { fatbin_image(&wrapper as *const Wrapper as *const c_void) }.unwrap();
+        assert_eq!(image, fatbin[..20]);
+
+        let bad = Wrapper { magic: 0, ..wrapper };
+        assert!(unsafe { fatbin_image(&bad as *const Wrapper as *const c_void) }.is_none());
+    }
+
+    #[test]
+    fn unregistering_drops_kernels_and_modules() {
+        let loaded = NetworkHandle {
+            server_id: 1,
+            session_id: 1,
+            resource_id: 7,
+            resource_type: rgpu_protocol::handle::ResourceType::CuModule,
+        };
+        let fatbin = register_fatbin(vec![0; 4]);
+        register_kernel(fatbin, 0x1234, "vector_add".to_string());
+        register_variable(fatbin, 0x5678, "scale".to_string());
+        set_module(fatbin, 0, loaded);
+        assert_eq!(kernel(0x1234), Some((fatbin, "vector_add".to_string())));
+        assert_eq!(module(fatbin, 0), Some(loaded));
+
+        assert_eq!(unregister_fatbin(fatbin), vec![loaded]);
+        assert_eq!(kernel(0x1234), None);
+        assert_eq!(variable(0x5678), None);
+    }
+}
@@ -0,0 +1,34 @@
+//! IPC client for the NVML queries, over the shared [`rgpu_common::ipc`] client.
+
+use rgpu_common::ipc;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::messages::Message;
+
+pub struct IpcClient(ipc::IpcClient);
+
+impl IpcClient {
+    pub fn new(path: &str) -> Self {
+        // NVML lists every device, whatever RGPU_VISIBLE_DEVICES says
+        Self(ipc::IpcClient::new(path, "rgpu-nvml-interpose").with_all_devices())
+    }
+
+    /// Send a CUDA command to the daemon and wait for the response.
+    pub fn send_command(&self, cmd: CudaCommand) -> Result<CudaResponse, String> {
+        let msg = Message::CudaCommand {
+            request_id: self.0.next_request_id(),
+            command: cmd,
+            deadline_ms: None,
+        };
+
+        match self.0.send_and_receive(msg)? {
+            Message::CudaResponse { response, .. } => Ok(response),
+            Message::Error(e) => Err(e.to_string()),
+            other => Err(format!("unexpected response: {:?}", other)),
+        }
+    }
+
+    /// Send any message to the daemon and return its reply.
+    pub fn send_message(&self, msg: Message) -> Result<Message, String> {
+        self.0.send_and_receive(msg)
+    }
+}
@@ -0,0 +1,609 @@
+//! NVML interception library.
+//!
+//! Frameworks such as PyTorch, Triton and DCGM exporters enumerate GPUs and
+//! read memory stats through NVML (libnvidia-ml.so / nvml.dll) before, or
+//! instead of, touching CUDA. This cdylib answers the commonly used subset of
+//! the NVML API from the client daemon, so it reports the same devices, in
+//! the same order, as the CUDA interposer: the device table is built with
+//! the CUDA device queries at `nvmlInit`, and memory and utilization are
+//! fetched from the GPU's server on every call.
+//!
+//! Usage:
+//! - Linux: LD_PRELOAD=librgpu_nvml_interpose.so <application>, or install
+//!   it as libnvidia-ml.so.1 on clients without an NVIDIA driver
+//! - Windows: Place as nvml.dll in the application's directory
+
+// Exported entry points follow the NVML API's own safety contract.
+#![allow(clippy::missing_safety_doc)]
+
+mod ipc_client;
+
+use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
+use std::sync::OnceLock;
+
+use parking_lot::Mutex;
+use tracing::{debug, info, warn};
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::messages::Message;
+
+use ipc_client::IpcClient;
+
+// NVML types
+type NvmlReturn = c_int;
+/// Index into the device table plus one, so that no device is null.
+type NvmlDevice = *mut c_void;
+
+const NVML_SUCCESS: NvmlReturn = 0;
+const NVML_ERROR_UNINITIALIZED: NvmlReturn = 1;
+const NVML_ERROR_INVALID_ARGUMENT: NvmlReturn = 2;
+const NVML_ERROR_NOT_SUPPORTED: NvmlReturn = 3;
+const NVML_ERROR_NOT_FOUND: NvmlReturn = 6;
+const NVML_ERROR_INSUFFICIENT_SIZE: NvmlReturn = 7;
+const NVML_ERROR_DRIVER_NOT_LOADED: NvmlReturn = 9;
+const NVML_ERROR_GPU_IS_LOST: NvmlReturn = 15;
+const NVML_ERROR_UNKNOWN: NvmlReturn = 999;
+
+/// `nvmlPciInfo_t` (the layout of `nvmlDeviceGetPciInfo_v2` and `_v3`)
+#[repr(C)]
+pub struct NvmlPciInfo {
+    bus_id_legacy: [c_char; 16],
+    domain: c_uint,
+    bus: c_uint,
+    device: c_uint,
+    pci_device_id: c_uint,
+    pci_sub_system_id: c_uint,
+    bus_id: [c_char; 32],
+}
+
+/// `nvmlMemory_t`
+#[repr(C)]
+pub struct NvmlMemory {
+    total: u64,
+    free: u64,
+    used: u64,
+}
+
+/// `nvmlMemory_v2_t`
+#[repr(C)]
+pub struct NvmlMemoryV2 {
+    version: c_uint,
+    total: u64,
+    reserved: u64,
+    free: u64,
+    used: u64,
+}
+
+/// `nvmlUtilization_t`
+#[repr(C)]
+pub struct NvmlUtilization {
+    gpu: c_uint,
+    memory: c_uint,
+}
+
+/// What NVML reports about one GPU, fetched at `nvmlInit`.
+struct Device {
+    handle: NetworkHandle,
+    name: String,
+    uuid: [u8; 16],
+    /// Lowercase, e.g. "0000:3b:00.0"
+    pci_bus_id: Option<String>,
+    /// `(device id << 16) | vendor id`, as NVML packs it
+    pci_device_id: u32,
+    total_memory: u64,
+    compute_capability: (i32, i32),
+}
+
+/// Devices while initialized; NVML counts `nvmlInit` against `nvmlShutdown`.
+struct State {
+    init_count: u32,
+    devices: Vec<Device>,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    init_count: 0,
+    devices: Vec::new(),
+});
+
+static IPC_CLIENT: OnceLock<IpcClient> = OnceLock::new();
+
+fn get_client() -> &'static IpcClient {
+    IPC_CLIENT.get_or_init(|| {
+        let path = rgpu_common::platform::default_ipc_path();
+        IpcClient::new(&path)
+    })
+}
+
+fn send_cuda_command(cmd: CudaCommand) -> Result<CudaResponse, NvmlReturn> {
+    match get_client().send_command(cmd) {
+        Ok(CudaResponse::Error { code, message }) => {
+            debug!("CUDA error {}: {}", code, message);
+            Err(NVML_ERROR_UNKNOWN)
+        }
+        Ok(resp) => Ok(resp),
+        Err(e) => {
+            warn!("IPC error: {}", e);
+            Err(NVML_ERROR_DRIVER_NOT_LOADED)
+        }
+    }
+}
+
+/// Query everything NVML callers read about the GPUs, in CUDA device order.
+fn load_devices() -> Result<Vec<Device>, NvmlReturn> {
+    send_cuda_command(CudaCommand::Init { flags: 0 })?;
+    let count = match send_cuda_command(CudaCommand::DeviceGetCount)? {
+        CudaResponse::DeviceCount(n) => n,
+        _ => return Err(NVML_ERROR_UNKNOWN),
+    };
+    // PCI IDs aren't part of the CUDA queries; the daemon's GPU list has
+    // them, keyed by bus ID.
+    let gpu_list = match get_client().send_message(Message::QueryGpus) {
+        Ok(Message::GpuList(gpus)) => gpus,
+        _ => Vec::new(),
+    };
+
+    let mut devices = Vec::new();
+    for ordinal in 0..count {
+        let handle = match send_cuda_command(CudaCommand::DeviceGet { ordinal })? {
+            CudaResponse::Device(handle) => handle,
+            _ => return Err(NVML_ERROR_UNKNOWN),
+        };
+        let name = match send_cuda_command(CudaCommand::DeviceGetName { device: handle })? {
+            CudaResponse::DeviceName(name) => name,
+            _ => return Err(NVML_ERROR_UNKNOWN),
+        };
+        let total_memory = match send_cuda_command(CudaCommand::DeviceTotalMem { device: handle })? {
+            CudaResponse::DeviceTotalMem(bytes) => bytes,
+            _ => return Err(NVML_ERROR_UNKNOWN),
+        };
+        let uuid = match send_cuda_command(CudaCommand::DeviceGetUuid { device: handle }) {
+            Ok(CudaResponse::DeviceUuid(bytes)) => {
+                let mut uuid = [0u8; 16];
+                let len = bytes.len().min(16);
+                uuid[..len].copy_from_slice(&bytes[..len]);
+                uuid
+            }
+            _ => [0; 16],
+        };
+        let pci_bus_id = match send_cuda_command(CudaCommand::DeviceGetPCIBusId { device: handle }) {
+            Ok(CudaResponse::DevicePCIBusId(id)) => Some(id.to_ascii_lowercase()),
+            _ => None,
+        };
+        let compute_capability =
+            match send_cuda_command(CudaCommand::DeviceComputeCapability { device: handle }) {
+                Ok(CudaResponse::ComputeCapability { major, minor }) => (major, minor),
+                _ => (0, 0),
+            };
+        let pci_device_id = gpu_list
+            .iter()
+            .find(|gpu| gpu.server_id == handle.server_id && gpu.topology.pci_bus_id == pci_bus_id)
+            .map(|gpu| (gpu.device_id << 16) | (gpu.vendor_id & 0xffff))
+            .unwrap_or(0);
+        devices.push(Device {
+            handle,
+            name,
+            uuid,
+            pci_bus_id,
+            pci_device_id,
+            total_memory,
+            compute_capability,
+        });
+    }
+    Ok(devices)
+}
+
+/// Run `f` on the device behind `device`.
+fn with_device<T>(device: NvmlDevice, f: impl FnOnce(&Device) -> T) -> Result<T, NvmlReturn> {
+    let state = STATE.lock();
+    if state.init_count == 0 {
+        return Err(NVML_ERROR_UNINITIALIZED);
+    }
+    let index = (device as usize).checked_sub(1).ok_or(NVML_ERROR_INVALID_ARGUMENT)?;
+    state.devices.get(index).map(f).ok_or(NVML_ERROR_INVALID_ARGUMENT)
+}
+
+/// The handle of the first device `matches` accepts.
+fn find_device(matches: impl Fn(&Device) -> bool) -> Result<NvmlDevice, NvmlReturn> {
+    let state = STATE.lock();
+    if state.init_count == 0 {
+        return Err(NVML_ERROR_UNINITIALIZED);
+    }
+    state
+        .devices
+        .iter()
+        .position(matches)
+        .map(|i| (i + 1) as NvmlDevice)
+        .ok_or(NVML_ERROR_NOT_FOUND)
+}
+
+/// Current usage of a device: (total, used, gpu %, memory %). A server too
+/// old to report usage gets the total with nothing used.
+fn device_usage(device: NvmlDevice) -> Result<(u64, u64, u32, u32), NvmlReturn> {
+    let (handle, total_memory) = with_device(device, |d| (d.handle, d.total_memory))?;
+    match get_client().send_command(CudaCommand::DeviceGetUsage { device: handle }) {
+        Ok(CudaResponse::DeviceUsage {
+            memory_total,
+            memory_used,
+            gpu_utilization,
+            memory_utilization,
+        }) => Ok((memory_total, memory_used, gpu_utilization, memory_utilization)),
+        Ok(_) => Ok((total_memory, 0, 0, 0)),
+        Err(e) => {
+            warn!("IPC error: {}", e);
+            Err(NVML_ERROR_GPU_IS_LOST)
+        }
+    }
+}
+
+/// `GPU-xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`, as NVML formats UUIDs.
+fn format_uuid(uuid: &[u8; 16]) -> String {
+    let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+    format!(
+        "GPU-{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Domain, bus and device of a bus ID such as "0000:3b:00.0".
+fn parse_bus_id(bus_id: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = bus_id.split(':');
+    let domain = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let bus = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let device = u32::from_str_radix(parts.next()?.split('.').next()?, 16).ok()?;
+    Some((domain, bus, device))
+}
+
+/// Copy `value` into a caller buffer of `length` bytes, NUL-terminated.
+unsafe fn write_string(value: &str, buf: *mut c_char, length: c_uint) -> NvmlReturn {
+    if buf.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    let bytes = value.as_bytes();
+    if bytes.len() >= length as usize {
+        return NVML_ERROR_INSUFFICIENT_SIZE;
+    }
+    std::ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, buf, bytes.len());
+    *buf.add(bytes.len()) = 0;
+    NVML_SUCCESS
+}
+
+/// Copy `value` into a fixed-size C string field, truncating if needed.
+fn fill_c_string(field: &mut [c_char], value: &str) {
+    let len = value.len().min(field.len() - 1);
+    for (dst, src) in field.iter_mut().zip(&value.as_bytes()[..len]) {
+        *dst = *src as c_char;
+    }
+    field[len] = 0;
+}
+
+fn result<T>(r: Result<T, NvmlReturn>, f: impl FnOnce(T)) -> NvmlReturn {
+    match r {
+        Ok(value) => {
+            f(value);
+            NVML_SUCCESS
+        }
+        Err(code) => code,
+    }
+}
+
+// ── Exported NVML Functions ─────────────────────────────────────────
+
+// ── Initialization ──────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlInit_v2() -> NvmlReturn {
+    nvmlInitWithFlags(0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlInit() -> NvmlReturn {
+    nvmlInitWithFlags(0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlInitWithFlags(flags: c_uint) -> NvmlReturn {
+    // Initialize logging on first call
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_env("RGPU_LOG")
+                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+        )
+        .try_init();
+
+    info!("nvmlInitWithFlags(flags={})", flags);
+
+    let mut state = STATE.lock();
+    if state.init_count == 0 {
+        match load_devices() {
+            Ok(devices) => {
+                debug!("NVML: {} device(s)", devices.len());
+                state.devices = devices;
+            }
+            Err(code) => return code,
+        }
+    }
+    state.init_count += 1;
+    NVML_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlShutdown() -> NvmlReturn {
+    let mut state = STATE.lock();
+    if state.init_count == 0 {
+        return NVML_ERROR_UNINITIALIZED;
+    }
+    state.init_count -= 1;
+    if state.init_count == 0 {
+        state.devices.clear();
+    }
+    NVML_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlErrorString(result: NvmlReturn) -> *const c_char {
+    let message: &'static CStr = match result {
+        NVML_SUCCESS => c"Success",
+        NVML_ERROR_UNINITIALIZED => c"Uninitialized",
+        NVML_ERROR_INVALID_ARGUMENT => c"Invalid Argument",
+        NVML_ERROR_NOT_SUPPORTED => c"Not Supported",
+        NVML_ERROR_NOT_FOUND => c"Not Found",
+        NVML_ERROR_INSUFFICIENT_SIZE => c"Insufficient Size",
+        NVML_ERROR_DRIVER_NOT_LOADED => c"Driver Not Loaded",
+        NVML_ERROR_GPU_IS_LOST => c"GPU is lost",
+        _ => c"Unknown Error",
+    };
+    message.as_ptr()
+}
+
+// ── System Queries ──────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlSystemGetCudaDriverVersion(version: *mut c_int) -> NvmlReturn {
+    if version.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    match send_cuda_command(CudaCommand::DriverGetVersion) {
+        Ok(CudaResponse::DriverVersion(v)) => {
+            *version = v;
+            NVML_SUCCESS
+        }
+        Ok(_) => NVML_ERROR_UNKNOWN,
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlSystemGetCudaDriverVersion_v2(version: *mut c_int) -> NvmlReturn {
+    nvmlSystemGetCudaDriverVersion(version)
+}
+
+// ── Device Enumeration ──────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetCount_v2(count: *mut c_uint) -> NvmlReturn {
+    if count.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    let state = STATE.lock();
+    if state.init_count == 0 {
+        return NVML_ERROR_UNINITIALIZED;
+    }
+    *count = state.devices.len() as c_uint;
+    NVML_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetCount(count: *mut c_uint) -> NvmlReturn {
+    nvmlDeviceGetCount_v2(count)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetHandleByIndex_v2(index: c_uint, device: *mut NvmlDevice) -> NvmlReturn {
+    if device.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    let state = STATE.lock();
+    if state.init_count == 0 {
+        return NVML_ERROR_UNINITIALIZED;
+    }
+    if index as usize >= state.devices.len() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    *device = (index as usize + 1) as NvmlDevice;
+    NVML_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetHandleByIndex(index: c_uint, device: *mut NvmlDevice) -> NvmlReturn {
+    nvmlDeviceGetHandleByIndex_v2(index, device)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetHandleByUUID(uuid: *const c_char, device: *mut NvmlDevice) -> NvmlReturn {
+    if uuid.is_null() || device.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    let wanted = CStr::from_ptr(uuid).to_string_lossy().to_ascii_lowercase();
+    result(find_device(|d| format_uuid(&d.uuid).to_ascii_lowercase() == wanted), |h| *device = h)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetHandleByPciBusId_v2(
+    pci_bus_id: *const c_char,
+    device: *mut NvmlDevice,
+) -> NvmlReturn {
+    if pci_bus_id.is_null() || device.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    let Some(wanted) = parse_bus_id(&CStr::from_ptr(pci_bus_id).to_string_lossy()) else {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    };
+    result(
+        find_device(|d| d.pci_bus_id.as_deref().and_then(parse_bus_id) == Some(wanted)),
+        |h| *device = h,
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetHandleByPciBusId(
+    pci_bus_id: *const c_char,
+    device: *mut NvmlDevice,
+) -> NvmlReturn {
+    nvmlDeviceGetHandleByPciBusId_v2(pci_bus_id, device)
+}
+
+// ── Device Queries ──────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetIndex(device: NvmlDevice, index: *mut c_uint) -> NvmlReturn {
+    if index.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    result(with_device(device, |_| ()), |()| *index = device as c_uint - 1)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetName(device: NvmlDevice, name: *mut c_char, length: c_uint) -> NvmlReturn {
+    match with_device(device, |d| d.name.clone()) {
+        Ok(value) => write_string(&value, name, length),
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetUUID(device: NvmlDevice, uuid: *mut c_char, length: c_uint) -> NvmlReturn {
+    match with_device(device, |d| format_uuid(&d.uuid)) {
+        Ok(value) => write_string(&value, uuid, length),
+        Err(code) => code,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetPciInfo_v3(device: NvmlDevice, pci: *mut NvmlPciInfo) -> NvmlReturn {
+    if pci.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    let info = match with_device(device, |d| (d.pci_bus_id.clone(), d.pci_device_id)) {
+        Ok(info) => info,
+        Err(code) => return code,
+    };
+    let (Some(bus_id), pci_device_id) = info else {
+        return NVML_ERROR_NOT_SUPPORTED;
+    };
+    let (domain, bus, dev) = parse_bus_id(&bus_id).unwrap_or_default();
+    let pci = &mut *pci;
+    pci.domain = domain;
+    pci.bus = bus;
+    pci.device = dev;
+    pci.pci_device_id = pci_device_id;
+    pci.pci_sub_system_id = 0;
+    // NVML writes bus IDs in upper case, the legacy field with a 4-digit domain.
+    fill_c_string(&mut pci.bus_id, &format!("{:08X}:{:02X}:{:02X}.0", domain, bus, dev));
+    fill_c_string(&mut pci.bus_id_legacy, &format!("{:04X}:{:02X}:{:02X}.0", domain, bus, dev));
+    NVML_SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetPciInfo_v2(device: NvmlDevice, pci: *mut NvmlPciInfo) -> NvmlReturn {
+    nvmlDeviceGetPciInfo_v3(device, pci)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetCudaComputeCapability(
+    device: NvmlDevice,
+    major: *mut c_int,
+    minor: *mut c_int,
+) -> NvmlReturn {
+    if major.is_null() || minor.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    result(with_device(device, |d| d.compute_capability), |(ma, mi)| {
+        *major = ma;
+        *minor = mi;
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetMemoryInfo(device: NvmlDevice, memory: *mut NvmlMemory) -> NvmlReturn {
+    if memory.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    result(device_usage(device), |(total, used, _, _)| {
+        *memory = NvmlMemory {
+            total,
+            free: total.saturating_sub(used),
+            used,
+        };
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetMemoryInfo_v2(device: NvmlDevice, memory: *mut NvmlMemoryV2) -> NvmlReturn {
+    if memory.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    result(device_usage(device), |(total, used, _, _)| {
+        let memory = &mut *memory;
+        memory.total = total;
+        memory.reserved = 0;
+        memory.free = total.saturating_sub(used);
+        memory.used = used;
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn nvmlDeviceGetUtilizationRates(
+    device: NvmlDevice,
+    utilization: *mut NvmlUtilization,
+) -> NvmlReturn {
+    if utilization.is_null() {
+        return NVML_ERROR_INVALID_ARGUMENT;
+    }
+    result(device_usage(device), |(_, _, gpu, memory)| {
+        *utilization = NvmlUtilization { gpu, memory };
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uuid_format() {
+        let uuid = [
+            0x5c, 0x2a, 0x3e, 0x91, 0x0b, 0x7f, 0x4d, 0x12, 0x9a, 0x01, 0xfe, 0xdc, 0xba, 0x98, 0x76, 0x54,
+        ];
+        assert_eq!(format_uuid(&uuid), "GPU-5c2a3e91-0b7f-4d12-9a01-fedcba987654");
+    }
+
+    #[test]
+    fn bus_ids() {
+        assert_eq!(parse_bus_id("0000:3b:00.0"), Some((0, 0x3b, 0)));
+        assert_eq!(parse_bus_id("00000001:AF:1F.0"), Some((1, 0xaf, 0x1f)));
+        assert_eq!(parse_bus_id("garbage"), None);
+    }
+
+    #[test]
+    fn strings_are_bounded() {
+        let mut buf = [1 as c_char; 8];
+        assert_eq!(unsafe { write_string("RTX", buf.as_mut_ptr(), 8) }, NVML_SUCCESS);
+        assert_eq!(unsafe { CStr::from_ptr(buf.as_ptr()) }, c"RTX");
+        assert_eq!(unsafe { write_string("RTX 4090", buf.as_mut_ptr(), 8) }, NVML_ERROR_INSUFFICIENT_SIZE);
+
+        let mut field = [1 as c_char; 4];
+        fill_c_string(&mut field, "0000:3b");
+        assert_eq!(unsafe { CStr::from_ptr(field.as_ptr()) }, c"000");
+    }
+
+    #[test]
+    fn queries_need_init() {
+        let mut count = 0;
+        assert_eq!(unsafe { nvmlDeviceGetCount_v2(&mut count) }, NVML_ERROR_UNINITIALIZED);
+        assert_eq!(unsafe { nvmlShutdown() }, NVML_ERROR_UNINITIALIZED);
+    }
+}
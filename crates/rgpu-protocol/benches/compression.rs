@@ -0,0 +1,117 @@
+//! Throughput of frame compression for bulk host-to-device copies.
+//!
+//! Encodes and decodes a 16 MB `MemcpyHtoD` with each codec and several zstd
+//! levels, for a few kinds of buffer contents, and prints the ratio and the
+//! encode and decode rates in MB/s of uncompressed payload.
+//!
+//! Run with: cargo bench -p rgpu-protocol --bench compression
+
+use std::time::{Duration, Instant};
+
+use rgpu_protocol::cuda_commands::CudaCommand;
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::messages::{Message, RequestId};
+use rgpu_protocol::wire::{self, Codec, CompressionSettings, CompressionStats, HEADER_SIZE};
+
+const SIZE: usize = 16 * 1024 * 1024;
+
+/// Run each case for at least this long.
+const MIN_TIME: Duration = Duration::from_millis(500);
+
+fn payloads() -> Vec<(&'static str, Vec<u8>)> {
+    // xorshift, so the noise is the same on every run
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    let zeros = vec![0u8; SIZE];
+    let floats: Vec<u8> = (0..SIZE / 4)
+        .flat_map(|i| ((i as f32 * 0.001).sin()).to_le_bytes())
+        .collect();
+    let sparse: Vec<u8> = (0..SIZE)
+        .map(|_| match next() % 16 {
+            0 => next() as u8,
+            _ => 0,
+        })
+        .collect();
+    let random: Vec<u8> = (0..SIZE / 8).flat_map(|_| next().to_le_bytes()).collect();
+    vec![("zeros", zeros), ("f32 signal", floats), ("sparse", sparse), ("random", random)]
+}
+
+fn settings() -> Vec<(String, CompressionSettings)> {
+    let mut settings = vec![
+        ("none".to_string(), Codec::None, 0),
+        ("lz4".to_string(), Codec::Lz4, 0),
+    ];
+    for level in [1, 3, 9, 19] {
+        settings.push((format!("zstd -{}", level), Codec::Zstd, level));
+    }
+    settings
+        .into_iter()
+        .map(|(name, codec, level)| {
+            let settings = CompressionSettings {
+                codec,
+                level,
+                ..Default::default()
+            };
+            (name, settings)
+        })
+        .collect()
+}
+
+/// Average time of `f` over as many runs as fit in `MIN_TIME`.
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    let started = Instant::now();
+    let mut runs = 0;
+    while runs == 0 || started.elapsed() < MIN_TIME {
+        std::hint::black_box(f());
+        runs += 1;
+    }
+    started.elapsed() / runs
+}
+
+fn mb_per_sec(elapsed: Duration) -> f64 {
+    SIZE as f64 / (1024.0 * 1024.0) / elapsed.as_secs_f64()
+}
+
+fn main() {
+    println!(
+        "{:<12} {:<9} {:>8} {:>12} {:>12}",
+        "payload", "codec", "ratio", "encode MB/s", "decode MB/s"
+    );
+    for (payload_name, data) in payloads() {
+        let msg = Message::CudaCommand {
+            request_id: RequestId(1),
+            command: CudaCommand::MemcpyHtoD {
+                dst: NetworkHandle::null(),
+                src_data: data,
+                byte_count: SIZE as u64,
+            },
+            deadline_ms: None,
+        };
+        for (name, settings) in settings() {
+            let stats = CompressionStats::default();
+            stats.configure(settings);
+            let frame = wire::encode_message_tracked(&msg, 1, &stats).expect("encode");
+            let header: [u8; HEADER_SIZE] = frame[..HEADER_SIZE].try_into().expect("header");
+            let (flags, _, _) = wire::decode_header(&header).expect("header");
+            // Readers receive the payload into its own buffer, which rkyv
+            // needs aligned.
+            let payload = frame[HEADER_SIZE..].to_vec();
+
+            let encode = time(|| wire::encode_message_tracked(&msg, 1, &stats).expect("encode"));
+            let decode = time(|| wire::decode_message(&payload, flags).expect("decode"));
+            println!(
+                "{:<12} {:<9} {:>7.2}x {:>12.0} {:>12.0}",
+                payload_name,
+                name,
+                SIZE as f64 / payload.len() as f64,
+                mb_per_sec(encode),
+                mb_per_sec(decode)
+            );
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Records the git commit the crate is built from as `RGPU_GIT_HASH`, for the
+//! build info components exchange at connect time (see `src/version.rs`).
+//! Builds outside a checkout (or without git) get no hash; packagers can set
+//! `RGPU_GIT_HASH` themselves.
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=RGPU_GIT_HASH");
+    if let Ok(hash) = std::env::var("RGPU_GIT_HASH") {
+        println!("cargo:rustc-env=RGPU_GIT_HASH={}", hash);
+        return;
+    }
+
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+            .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    };
+
+    // Rebuild when HEAD moves: a checkout changes HEAD, a commit changes the
+    // branch ref it points at (loose or packed).
+    if let Some(git_dir) = git(&["rev-parse", "--absolute-git-dir"]) {
+        let git_dir = Path::new(&git_dir);
+        let mut watched = vec![git_dir.join("HEAD"), git_dir.join("packed-refs")];
+        if let Some(head_ref) = git(&["symbolic-ref", "-q", "HEAD"]) {
+            watched.push(git_dir.join(head_ref));
+        }
+        // A missing path would rerun the script on every build.
+        for path in watched.iter().filter(|p| p.exists()) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+
+    if let Some(hash) = git(&["rev-parse", "--short=10", "HEAD"]) {
+        println!("cargo:rustc-env=RGPU_GIT_HASH={}", hash);
+    }
+}
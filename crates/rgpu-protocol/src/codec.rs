@@ -0,0 +1,173 @@
+//! Domain-specific transfer codecs.
+//!
+//! Generic LZ4 (applied to every large frame in [`crate::wire`]) does poorly on
+//! raw tensors and images. An application that knows what an allocation holds
+//! can attach a [`TransferCodec`] to it with the `rgpuMemSetTransferHint`
+//! entry point; copies to and from that allocation are then transformed by the
+//! codec before hitting the wire:
+//! - [`TransferCodec::Shuffle`] groups the n-th byte of every element together
+//!   (fp16/bf16/fp32 tensors), which lets LZ4 squeeze sign/exponent bytes.
+//! - [`TransferCodec::Sparse`] sends a bitmap plus the non-zero elements only.
+//! - [`TransferCodec::Delta`] stores each byte as the difference from the same
+//!   channel of the previous pixel (interleaved 8-bit images).
+//!
+//! All codecs are lossless. A new codec implements [`Codec`] and gets a
+//! [`TransferCodec`] variant; both ends must know it.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum CodecError {
+    #[error("encoded data truncated")]
+    Truncated,
+    #[error("encoded data has {0} trailing bytes")]
+    TrailingBytes(usize),
+}
+
+/// A lossless payload transform.
+pub trait Codec {
+    /// Transform `data` for transfer.
+    fn encode(&self, data: &[u8]) -> Vec<u8>;
+    /// Reverse [`Codec::encode`]; `byte_count` is the original length.
+    fn decode(&self, encoded: &[u8], byte_count: usize) -> Result<Vec<u8>, CodecError>;
+}
+
+/// Codec attached to an allocation, as negotiated over the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum TransferCodec {
+    /// Byte-plane shuffle of `element_size`-byte elements.
+    Shuffle { element_size: u8 },
+    /// Zero-element elision for `element_size`-byte elements.
+    Sparse { element_size: u8 },
+    /// Byte delta against the value `stride` bytes earlier.
+    Delta { stride: u8 },
+}
+
+impl TransferCodec {
+    pub fn codec(&self) -> Box<dyn Codec> {
+        match *self {
+            TransferCodec::Shuffle { element_size } => Box::new(Shuffle(element_size.max(1) as usize)),
+            TransferCodec::Sparse { element_size } => Box::new(Sparse(element_size.max(1) as usize)),
+            TransferCodec::Delta { stride } => Box::new(Delta(stride.max(1) as usize)),
+        }
+    }
+
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        self.codec().encode(data)
+    }
+
+    pub fn decode(&self, encoded: &[u8], byte_count: usize) -> Result<Vec<u8>, CodecError> {
+        self.codec().decode(encoded, byte_count)
+    }
+}
+
+struct Shuffle(usize);
+
+impl Codec for Shuffle {
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let size = self.0;
+        let count = data.len() / size;
+        let mut out = vec![0u8; data.len()];
+        for (i, element) in data.chunks_exact(size).enumerate() {
+            for (b, byte) in element.iter().enumerate() {
+                out[b * count + i] = *byte;
+            }
+        }
+        // A partial trailing element is passed through as-is.
+        out[count * size..].copy_from_slice(&data[count * size..]);
+        out
+    }
+
+    fn decode(&self, encoded: &[u8], byte_count: usize) -> Result<Vec<u8>, CodecError> {
+        if encoded.len() < byte_count {
+            return Err(CodecError::Truncated);
+        }
+        if encoded.len() > byte_count {
+            return Err(CodecError::TrailingBytes(encoded.len() - byte_count));
+        }
+        let size = self.0;
+        let count = byte_count / size;
+        let mut out = vec![0u8; byte_count];
+        for (i, element) in out.chunks_exact_mut(size).enumerate() {
+            for (b, byte) in element.iter_mut().enumerate() {
+                *byte = encoded[b * count + i];
+            }
+        }
+        out[count * size..].copy_from_slice(&encoded[count * size..]);
+        Ok(out)
+    }
+}
+
+struct Sparse(usize);
+
+impl Codec for Sparse {
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let size = self.0;
+        let count = data.len() / size;
+        let mut bitmap = vec![0u8; count.div_ceil(8)];
+        let mut values = Vec::new();
+        for (i, element) in data.chunks_exact(size).enumerate() {
+            if element.iter().any(|b| *b != 0) {
+                bitmap[i / 8] |= 1 << (i % 8);
+                values.extend_from_slice(element);
+            }
+        }
+        bitmap.extend_from_slice(&values);
+        bitmap.extend_from_slice(&data[count * size..]);
+        bitmap
+    }
+
+    fn decode(&self, encoded: &[u8], byte_count: usize) -> Result<Vec<u8>, CodecError> {
+        let size = self.0;
+        let count = byte_count / size;
+        let bitmap_len = count.div_ceil(8);
+        let bitmap = encoded.get(..bitmap_len).ok_or(CodecError::Truncated)?;
+
+        let mut out = vec![0u8; byte_count];
+        let mut pos = bitmap_len;
+        for (i, element) in out.chunks_exact_mut(size).enumerate() {
+            if bitmap[i / 8] & (1 << (i % 8)) != 0 {
+                let value = encoded.get(pos..pos + size).ok_or(CodecError::Truncated)?;
+                element.copy_from_slice(value);
+                pos += size;
+            }
+        }
+        let tail = byte_count - count * size;
+        let rest = encoded.get(pos..pos + tail).ok_or(CodecError::Truncated)?;
+        out[count * size..].copy_from_slice(rest);
+        pos += tail;
+        if pos != encoded.len() {
+            return Err(CodecError::TrailingBytes(encoded.len() - pos));
+        }
+        Ok(out)
+    }
+}
+
+struct Delta(usize);
+
+impl Codec for Delta {
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let stride = self.0;
+        let mut out = data.to_vec();
+        for i in stride..data.len() {
+            out[i] = data[i].wrapping_sub(data[i - stride]);
+        }
+        out
+    }
+
+    fn decode(&self, encoded: &[u8], byte_count: usize) -> Result<Vec<u8>, CodecError> {
+        if encoded.len() < byte_count {
+            return Err(CodecError::Truncated);
+        }
+        if encoded.len() > byte_count {
+            return Err(CodecError::TrailingBytes(encoded.len() - byte_count));
+        }
+        let stride = self.0;
+        let mut out = encoded.to_vec();
+        for i in stride..out.len() {
+            out[i] = out[i].wrapping_add(out[i - stride]);
+        }
+        Ok(out)
+    }
+}
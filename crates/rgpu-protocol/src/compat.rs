@@ -0,0 +1,148 @@
+//! Bridging to servers that speak the previous protocol version.
+//!
+//! The daemon pins a version per server connection during the Hello exchange
+//! (the lower of the two peers' versions), so a fleet can be upgraded one
+//! server at a time. Only the version before [`PROTOCOL_VERSION`] is
+//! bridged: what differs between the two is listed in [`Feature`], and each
+//! side leaves out what the other doesn't have.
+//!
+//! Bridging only covers which variants a peer understands, not their
+//! encoding. New variants go at the end of their enum so existing ones keep
+//! their tags, and a change that alters an existing message's encoding has
+//! to raise `MIN_PROTOCOL_VERSION`, since older peers can no longer decode
+//! anything.
+
+use crate::cuda_commands::CudaResponse;
+use crate::error::ProtocolError;
+use crate::messages::{Message, RequestId, PROTOCOL_VERSION};
+use crate::vulkan_commands::VulkanResponse;
+
+/// Oldest protocol version the daemon can still bridge to. Versions before
+/// 55 added fields to existing messages (command deadlines, resume tokens,
+/// session lists in `MetricsData`, device UUIDs) and grew the archived
+/// `Message`, whose root rkyv finds at a fixed distance from the end of the
+/// payload, so none of their frames decode here, or ours there.
+pub const MIN_PROTOCOL_VERSION: u32 = 55;
+
+/// A capability that `MIN_PROTOCOL_VERSION` doesn't have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// `CudaResponse::FunctionWithParams`
+    KernelParamSizes,
+}
+
+impl Feature {
+    /// First protocol version with this feature.
+    pub fn since(self) -> u32 {
+        match self {
+            Feature::KernelParamSizes => 56,
+        }
+    }
+}
+
+/// Whether a peer speaking `version` understands `feature`.
+pub fn supports(version: u32, feature: Feature) -> bool {
+    version >= feature.since()
+}
+
+/// Version to use with a peer that announced `peer_version` in its Hello.
+pub fn negotiate(peer_version: u32) -> Result<u32, String> {
+    if peer_version < MIN_PROTOCOL_VERSION {
+        return Err(format!(
+            "peer speaks protocol v{}, oldest supported is v{}",
+            peer_version, MIN_PROTOCOL_VERSION
+        ));
+    }
+    Ok(peer_version.min(PROTOCOL_VERSION))
+}
+
+/// What the sender of `request` gets when the peer answers it with
+/// `Message::Unsupported`: the error response of the request's own kind, so
+/// the application sees a failed call rather than a broken connection.
+pub fn unsupported_response(request: &Message, reason: String) -> Message {
+    match request {
+        Message::CudaCommand { request_id, .. }
+        | Message::CudaCommandStreamed { request_id, .. }
+        | Message::CudaPipelined { request_id, .. }
+        | Message::CudaCommandChunked { request_id, .. } => Message::CudaResponse {
+            request_id: *request_id,
+            // CUDA_ERROR_NOT_SUPPORTED
+            response: CudaResponse::Error { code: 801, message: reason },
+        },
+        Message::CudaBatch(_) => Message::CudaResponse {
+            request_id: RequestId(0),
+            response: CudaResponse::Error { code: 801, message: reason },
+        },
+        Message::VulkanCommand { request_id, .. } => Message::VulkanResponse {
+            request_id: *request_id,
+            // VK_ERROR_FEATURE_NOT_PRESENT
+            response: VulkanResponse::Error { code: -8, message: reason },
+        },
+        _ => Message::Error(ProtocolError::UnsupportedCommand(reason)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version::{BuildInfo, Compatibility};
+    use crate::wire::{self, FrameFlags};
+
+    /// Payload of `Hello { protocol_version: 3, name: "RGPU Server",
+    /// challenge: Some(vec![7; 4]) }` as a v3 server sends it.
+    const V3_HELLO: &[u8] = include_bytes!("../testdata/v3_hello.bin");
+
+    /// Payload of a v3 server's `AuthResult { success: true, session_id:
+    /// Some(1), server_id: Some(2), available_gpus: vec![], error_message: None }`.
+    const V3_AUTH_RESULT: &[u8] = include_bytes!("../testdata/v3_auth_result.bin");
+
+    /// The Hello of `V3_HELLO`, from a v55 server.
+    const V55_HELLO: &[u8] = include_bytes!("../testdata/v55_hello.bin");
+
+    /// Decode `payload` from a buffer aligned like the ones frames are read into.
+    fn decode(payload: &[u8], flags: FrameFlags) -> Result<Message, wire::WireError> {
+        let mut aligned = rkyv::util::AlignedVec::<16>::new();
+        aligned.extend_from_slice(payload);
+        wire::decode_message(&aligned, flags)
+    }
+
+    #[test]
+    fn test_v3_peers_are_refused() {
+        assert!(decode(V3_HELLO, FrameFlags::empty()).is_err());
+        assert!(decode(V3_AUTH_RESULT, FrameFlags::RESPONSE).is_err());
+        assert!(negotiate(3).is_err());
+
+        let ours = BuildInfo::current("rgpu-client");
+        let server = BuildInfo {
+            protocol_version: 3,
+            ..BuildInfo::current("rgpu-server")
+        };
+        assert!(matches!(ours.check(&server, true), Compatibility::Incompatible(_)));
+    }
+
+    #[test]
+    fn test_oldest_supported_version_decodes() {
+        match decode(V55_HELLO, FrameFlags::empty()) {
+            Ok(Message::Hello {
+                protocol_version,
+                name,
+                challenge,
+            }) => {
+                assert_eq!(protocol_version, MIN_PROTOCOL_VERSION);
+                assert_eq!(name, "RGPU Server");
+                assert_eq!(challenge, Some(vec![7; 4]));
+            }
+            other => panic!("expected a Hello, got {:?}", other),
+        }
+        assert_eq!(negotiate(MIN_PROTOCOL_VERSION), Ok(MIN_PROTOCOL_VERSION));
+    }
+
+    #[test]
+    fn test_the_previous_version_is_bridged() {
+        assert_eq!(negotiate(PROTOCOL_VERSION + 1), Ok(PROTOCOL_VERSION));
+        assert_eq!(negotiate(PROTOCOL_VERSION), Ok(PROTOCOL_VERSION));
+        assert_eq!(MIN_PROTOCOL_VERSION, PROTOCOL_VERSION - 1);
+        assert!(!supports(MIN_PROTOCOL_VERSION, Feature::KernelParamSizes));
+        assert!(supports(PROTOCOL_VERSION, Feature::KernelParamSizes));
+    }
+}
@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::codec::TransferCodec;
 use crate::handle::NetworkHandle;
 
 /// Serialized kernel parameter value.
@@ -8,6 +9,12 @@ use crate::handle::NetworkHandle;
 pub struct KernelParam {
     /// Raw bytes of the parameter value
     pub data: Vec<u8>,
+    /// Set if the parameter is a device pointer: the allocation it points
+    /// into. `data` then holds the offset into the allocation, and the server
+    /// adds the allocation's real address. Texture and surface objects are
+    /// sent the same way, with their handle and a zero offset, for the
+    /// server to replace with the real object.
+    pub device_ptr: Option<NetworkHandle>,
 }
 
 /// CUDA Driver API commands sent from client to server.
@@ -225,6 +232,435 @@ pub enum CudaCommand {
     LinkAddFile { link: NetworkHandle, jit_type: i32, path: String, num_options: u32, options: Vec<i32>, option_values: Vec<u64> },
     LinkComplete { link: NetworkHandle },
     LinkDestroy { link: NetworkHandle },
+
+    // ── Transfer extensions (v4+) ───────────────────────────
+    /// Differential readback: like MemcpyDtoH, but the server only sends
+    /// blocks whose hash differs from `known_hashes` (the caller's copy).
+    MemcpyDtoHDiff {
+        src: NetworkHandle,
+        byte_count: u64,
+        block_size: u32,
+        known_hashes: Vec<u64>,
+    },
+    /// rgpuMemSetTransferHint: copies to and from `dptr` use `codec`
+    /// (`None` reverts to plain transfers).
+    MemSetTransferCodec {
+        dptr: NetworkHandle,
+        codec: Option<TransferCodec>,
+    },
+    /// MemcpyHtoD with `data` encoded by the destination's transfer codec.
+    MemcpyHtoDEncoded {
+        dst: NetworkHandle,
+        codec: TransferCodec,
+        data: Vec<u8>,
+        byte_count: u64,
+    },
+    /// rgpuMemsetTyped: `pattern` (2, 4, 8 or 16 bytes) repeated `count`
+    /// times from `dst`.
+    FillBufferTyped {
+        dst: NetworkHandle,
+        pattern: Vec<u8>,
+        count: u64,
+    },
+
+    // ── 2D / 3D copies (v7+) ────────────────────────────────
+    /// cuMemcpy2D / cuMemcpy3D. If the source is host memory its rows are
+    /// packed into `src_data`; a host destination is returned packed as
+    /// `MemoryData`.
+    Memcpy3D {
+        params: Box<Memcpy3DParams>,
+        src_data: Vec<u8>,
+    },
+    Memcpy3DAsync {
+        params: Box<Memcpy3DParams>,
+        src_data: Vec<u8>,
+        stream: NetworkHandle,
+    },
+
+    // ── Graphs (v9+) ────────────────────────────────────────
+    /// cuStreamBeginCapture. The server always captures in relaxed mode,
+    /// since one server thread serves many sessions; `mode` is what the
+    /// application asked for.
+    StreamBeginCapture {
+        stream: NetworkHandle,
+        mode: u32,
+    },
+    StreamEndCapture {
+        stream: NetworkHandle,
+    },
+    StreamIsCapturing {
+        stream: NetworkHandle,
+    },
+    GraphCreate {
+        flags: u32,
+    },
+    GraphDestroy {
+        graph: NetworkHandle,
+    },
+    GraphInstantiate {
+        graph: NetworkHandle,
+        flags: u64,
+    },
+    GraphExecDestroy {
+        exec: NetworkHandle,
+    },
+    GraphLaunch {
+        exec: NetworkHandle,
+        stream: NetworkHandle,
+    },
+    GraphUpload {
+        exec: NetworkHandle,
+        stream: NetworkHandle,
+    },
+
+    // ── Host-mapped memory (v15+) ───────────────────────────
+    /// Write the client's copy of page-locked host memory into the server's,
+    /// so the device sees what the application wrote.
+    HostMemWrite {
+        ptr: NetworkHandle,
+        offset: u64,
+        data: Vec<u8>,
+    },
+    /// Read the server's copy of page-locked host memory (`MemoryData`),
+    /// to pick up what the device wrote.
+    HostMemRead {
+        ptr: NetworkHandle,
+        offset: u64,
+        byte_count: u64,
+    },
+
+    // ── Shared memory bank configuration (v16+) ─────────────
+    CtxSetSharedMemConfig { config: i32 },
+    CtxGetSharedMemConfig,
+
+    // ── Device usage (v17+) ─────────────────────────────────
+    /// Device-wide memory use and utilization (`DeviceUsage`), for NVML
+    /// queries on the client. Needs no context.
+    DeviceGetUsage { device: NetworkHandle },
+
+    // ── 2D memset (v21+) ────────────────────────────────────
+    /// Set `width` elements in each of `height` rows, `dst_pitch` bytes apart.
+    MemsetD2D8 {
+        dst: NetworkHandle,
+        dst_pitch: u64,
+        value: u8,
+        width: u64,
+        height: u64,
+    },
+    MemsetD2D16 {
+        dst: NetworkHandle,
+        dst_pitch: u64,
+        value: u16,
+        width: u64,
+        height: u64,
+    },
+    MemsetD2D32 {
+        dst: NetworkHandle,
+        dst_pitch: u64,
+        value: u32,
+        width: u64,
+        height: u64,
+    },
+    MemsetD2D8Async {
+        dst: NetworkHandle,
+        dst_pitch: u64,
+        value: u8,
+        width: u64,
+        height: u64,
+        stream: NetworkHandle,
+    },
+    MemsetD2D16Async {
+        dst: NetworkHandle,
+        dst_pitch: u64,
+        value: u16,
+        width: u64,
+        height: u64,
+        stream: NetworkHandle,
+    },
+    MemsetD2D32Async {
+        dst: NetworkHandle,
+        dst_pitch: u64,
+        value: u32,
+        width: u64,
+        height: u64,
+        stream: NetworkHandle,
+    },
+
+    // ── Texture and surface objects (v22+) ──────────────────
+    TexObjectCreate {
+        resource: Box<ResourceDesc>,
+        texture: Box<TextureDesc>,
+        view: Option<Box<ResourceViewDesc>>,
+    },
+    TexObjectDestroy { tex_object: NetworkHandle },
+    SurfObjectCreate { resource: Box<ResourceDesc> },
+    SurfObjectDestroy { surf_object: NetworkHandle },
+
+    // ── CUDA arrays (v23+) ──────────────────────────────────
+    /// cuArrayCreate and cuArray3DCreate; a 2D array has depth 0.
+    ArrayCreate { desc: ArrayDescriptor },
+    ArrayDestroy { array: NetworkHandle },
+
+    // ── Legacy texture and surface references (v24+) ───────
+    ModuleGetTexRef { module: NetworkHandle, name: String },
+    /// cuTexRefSetAddress, answered with the byte offset the binding needs
+    /// for alignment.
+    TexRefSetAddress {
+        texref: NetworkHandle,
+        dptr: NetworkHandle,
+        offset: u64,
+        bytes: u64,
+    },
+    TexRefSet { texref: NetworkHandle, setting: TexRefSetting },
+    ModuleGetSurfRef { module: NetworkHandle, name: String },
+    SurfRefSetArray { surfref: NetworkHandle, array: NetworkHandle, flags: u32 },
+
+    // ── IPC memory handles (v25+) ───────────────────────────
+    IpcGetMemHandle { dptr: NetworkHandle },
+    IpcOpenMemHandle { handle: IpcMemHandle, flags: u32 },
+    IpcCloseMemHandle { dptr: NetworkHandle },
+
+    // ── Module cache (v50+) ─────────────────────────────────
+    /// `ModuleLoadData` with an image the server was sent before, named by
+    /// its SHA-256. Answered with `ModuleImageMissing` if the server doesn't
+    /// have it.
+    ModuleLoadByHash { hash: [u8; 32] },
+}
+
+/// Memory type of one side of a 2D/3D copy (`CUmemorytype`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum MemoryType {
+    Host,
+    Device,
+    Array,
+    Unified,
+}
+
+impl MemoryType {
+    /// From a raw `CUmemorytype` value.
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        match raw {
+            1 => Some(MemoryType::Host),
+            2 => Some(MemoryType::Device),
+            3 => Some(MemoryType::Array),
+            4 => Some(MemoryType::Unified),
+            _ => None,
+        }
+    }
+
+    pub fn to_raw(self) -> u32 {
+        match self {
+            MemoryType::Host => 1,
+            MemoryType::Device => 2,
+            MemoryType::Array => 3,
+            MemoryType::Unified => 4,
+        }
+    }
+}
+
+/// One side of a 2D/3D copy.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct MemcpyRegion {
+    pub memory_type: MemoryType,
+    /// Device allocation or array; `None` for host memory, which travels
+    /// packed instead.
+    pub handle: Option<NetworkHandle>,
+    /// Offset of the copied region within the allocation
+    pub x_in_bytes: u64,
+    pub y: u64,
+    pub z: u64,
+    /// Bytes per row of the allocation
+    pub pitch: u64,
+    /// Rows per slice of the allocation (3D only)
+    pub height: u64,
+}
+
+/// `CUDA_MEMCPY2D` / `CUDA_MEMCPY3D`; 2D copies have a `depth` of 1.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct Memcpy3DParams {
+    pub src: MemcpyRegion,
+    pub dst: MemcpyRegion,
+    pub width_in_bytes: u64,
+    pub height: u64,
+    pub depth: u64,
+}
+
+impl Memcpy3DParams {
+    /// Size of the copied region with the rows packed back to back.
+    pub fn packed_size(&self) -> u64 {
+        self.width_in_bytes
+            .saturating_mul(self.height)
+            .saturating_mul(self.depth)
+    }
+}
+
+/// What a texture or surface object reads (`CUDA_RESOURCE_DESC`). Device
+/// memory is an allocation and an offset into it.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum TextureResource {
+    /// `CU_RESOURCE_TYPE_LINEAR`
+    Linear {
+        dptr: NetworkHandle,
+        offset: u64,
+        /// `CUarray_format`
+        format: u32,
+        num_channels: u32,
+        size_in_bytes: u64,
+    },
+    /// `CU_RESOURCE_TYPE_PITCH2D`
+    Pitch2D {
+        dptr: NetworkHandle,
+        offset: u64,
+        format: u32,
+        num_channels: u32,
+        width: u64,
+        height: u64,
+        pitch_in_bytes: u64,
+    },
+    /// `CU_RESOURCE_TYPE_ARRAY`
+    Array { array: NetworkHandle },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct ResourceDesc {
+    pub resource: TextureResource,
+    pub flags: u32,
+}
+
+/// How a texture object samples (`CUDA_TEXTURE_DESC`).
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct TextureDesc {
+    /// `CUaddress_mode` per dimension
+    pub address_mode: [u32; 3],
+    /// `CUfilter_mode`
+    pub filter_mode: u32,
+    /// `CU_TRSF_*`
+    pub flags: u32,
+    pub max_anisotropy: u32,
+    pub mipmap_filter_mode: u32,
+    pub mipmap_level_bias: f32,
+    pub min_mipmap_level_clamp: f32,
+    pub max_mipmap_level_clamp: f32,
+    pub border_color: [f32; 4],
+}
+
+/// How a texture object views its resource (`CUDA_RESOURCE_VIEW_DESC`).
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct ResourceViewDesc {
+    /// `CUresourceViewFormat`
+    pub format: u32,
+    pub width: u64,
+    pub height: u64,
+    pub depth: u64,
+    pub first_mipmap_level: u32,
+    pub last_mipmap_level: u32,
+    pub first_layer: u32,
+    pub last_layer: u32,
+}
+
+impl TextureResource {
+    /// The allocation or array the resource reads.
+    pub fn handle_mut(&mut self) -> &mut NetworkHandle {
+        match self {
+            TextureResource::Linear { dptr, .. } | TextureResource::Pitch2D { dptr, .. } => dptr,
+            TextureResource::Array { array } => array,
+        }
+    }
+}
+
+/// A cuTexRefSet* call other than cuTexRefSetAddress.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum TexRefSetting {
+    /// cuTexRefSetAddress2D
+    Address2D {
+        dptr: NetworkHandle,
+        offset: u64,
+        format: u32,
+        num_channels: u32,
+        width: u64,
+        height: u64,
+        pitch: u64,
+    },
+    Array { array: NetworkHandle, flags: u32 },
+    Format { format: u32, num_packed_components: i32 },
+    Flags(u32),
+    FilterMode(u32),
+    AddressMode { dim: i32, mode: u32 },
+    MaxAnisotropy(u32),
+    BorderColor([f32; 4]),
+}
+
+/// A device allocation exported with cuIpcGetMemHandle. Other processes
+/// using the same server open it by the server's token; the allocation
+/// routes the open to that server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct IpcMemHandle {
+    pub memory: NetworkHandle,
+    pub token: u64,
+}
+
+/// `CUjit_option`s whose values are the application's log buffers and
+/// their sizes. The server compiles into buffers of its own and sends the
+/// logs back in a `JitOutput`.
+pub mod jit_option {
+    pub const INFO_LOG_BUFFER: i32 = 3;
+    pub const INFO_LOG_BUFFER_SIZE_BYTES: i32 = 4;
+    pub const ERROR_LOG_BUFFER: i32 = 5;
+    pub const ERROR_LOG_BUFFER_SIZE_BYTES: i32 = 6;
+}
+
+/// What the JIT compiler hands back through the options of a
+/// cuModuleLoadDataEx or cuLinkCreate.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct JitOutput {
+    /// Each option's value as the driver left it: log sizes, wall time and
+    /// threads per block are written back. 0 for the log buffers.
+    pub option_values: Vec<u64>,
+    pub info_log: Vec<u8>,
+    pub error_log: Vec<u8>,
+}
+
+/// Shape and element type of a CUDA array (`CUDA_ARRAY3D_DESCRIPTOR`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct ArrayDescriptor {
+    pub width: u64,
+    /// 0 for a 1D array
+    pub height: u64,
+    /// 0 for a 1D or 2D array
+    pub depth: u64,
+    /// `CUarray_format`
+    pub format: u32,
+    pub num_channels: u32,
+    /// `CUDA_ARRAY3D_*`
+    pub flags: u32,
+}
+
+impl ArrayDescriptor {
+    /// Bytes the elements take, before the driver's padding; 0 for a
+    /// format this doesn't know.
+    pub fn byte_size(&self) -> u64 {
+        let channel = match self.format {
+            // UNSIGNED_INT8, SIGNED_INT8
+            0x01 | 0x08 => 1,
+            // UNSIGNED_INT16, SIGNED_INT16, HALF
+            0x02 | 0x09 | 0x10 => 2,
+            // UNSIGNED_INT32, SIGNED_INT32, FLOAT
+            0x03 | 0x0a | 0x20 => 4,
+            _ => return 0,
+        };
+        channel * self.num_channels as u64 * self.width * self.height.max(1) * self.depth.max(1)
+    }
 }
 
 /// CUDA Driver API responses sent from server to client.
@@ -389,4 +825,565 @@ pub enum CudaResponse {
 
     /// cuLinkComplete result.
     LinkCompleted { cubin_data: Vec<u8> },
+
+    /// MemcpyDtoHDiff result: indices of changed blocks and their contents,
+    /// concatenated in the same order.
+    MemoryDiff { changed_blocks: Vec<u32>, data: Vec<u8> },
+
+    /// DtoH result from an allocation with a transfer codec.
+    EncodedMemoryData { codec: TransferCodec, data: Vec<u8> },
+
+    /// cuGraphCreate / cuStreamEndCapture result.
+    Graph(NetworkHandle),
+
+    /// cuGraphInstantiate result.
+    GraphExec(NetworkHandle),
+
+    /// cuStreamIsCapturing result (`CUstreamCaptureStatus`).
+    StreamCaptureStatus(u32),
+
+    /// cuCtxGetSharedMemConfig result.
+    SharedMemConfig(i32),
+
+    /// `DeviceGetUsage` result. Utilization is in percent over the driver's
+    /// last sample period, 0 if the server can't measure it.
+    DeviceUsage {
+        memory_total: u64,
+        memory_used: u64,
+        gpu_utilization: u32,
+        memory_utilization: u32,
+    },
+
+    /// cuTexObjectCreate result.
+    TexObject(NetworkHandle),
+
+    /// cuSurfObjectCreate result.
+    SurfObject(NetworkHandle),
+
+    /// cuArrayCreate/cuArray3DCreate result.
+    Array(NetworkHandle),
+
+    /// cuModuleGetTexRef result.
+    TexRef(NetworkHandle),
+
+    /// cuModuleGetSurfRef result.
+    SurfRef(NetworkHandle),
+
+    /// cuTexRefSetAddress result: the byte offset.
+    TexRefOffset(u64),
+
+    /// cuIpcGetMemHandle result.
+    IpcMemHandle(IpcMemHandle),
+
+    /// cuIpcOpenMemHandle result: a handle of the importing session for the
+    /// exported allocation.
+    IpcMemOpened(NetworkHandle),
+
+    /// The image of a `ModuleLoadByHash` isn't on the server; send it in
+    /// full.
+    ModuleImageMissing,
+
+    /// cuModuleLoadDataEx result when options were given: the module, or
+    /// the error code if it didn't load, and what the JIT compiler handed
+    /// back either way.
+    ModuleJit {
+        module: Option<NetworkHandle>,
+        code: i32,
+        jit: JitOutput,
+    },
+
+    /// cuLinkAddData, cuLinkAddFile and cuLinkComplete result on a linker
+    /// created with options: the error code, CUDA_SUCCESS if it worked, the
+    /// cubin of a completed link, and what the JIT compiler has written
+    /// through the linker's options so far.
+    LinkJit {
+        code: i32,
+        cubin_data: Vec<u8>,
+        jit: JitOutput,
+    },
+
+    /// cuModuleGetFunction result for clients that take kernel parameter
+    /// sizes: the function, with the size in bytes of each parameter if the
+    /// server could work them out.
+    FunctionWithParams {
+        handle: NetworkHandle,
+        param_sizes: Option<Vec<u32>>,
+    },
+}
+
+impl CudaCommand {
+    /// The host data this command uploads, for commands that can take it
+    /// from a chunked upload (`Message::CudaCommandChunked`).
+    pub fn upload_data_mut(&mut self) -> Option<&mut Vec<u8>> {
+        match self {
+            CudaCommand::MemcpyHtoD { src_data, .. } | CudaCommand::MemcpyHtoDAsync { src_data, .. } => {
+                Some(src_data)
+            }
+            _ => None,
+        }
+    }
+
+    /// The stream this command is ordered on, for commands that take one.
+    pub fn stream(&self) -> Option<NetworkHandle> {
+        match self {
+            CudaCommand::MemcpyHtoDAsync { stream, .. }
+            | CudaCommand::MemcpyDtoHAsync { stream, .. }
+            | CudaCommand::MemcpyDtoDAsync { stream, .. }
+            | CudaCommand::MemsetD8Async { stream, .. }
+            | CudaCommand::MemsetD16Async { stream, .. }
+            | CudaCommand::MemsetD32Async { stream, .. }
+            | CudaCommand::MemsetD2D8Async { stream, .. }
+            | CudaCommand::MemsetD2D16Async { stream, .. }
+            | CudaCommand::MemsetD2D32Async { stream, .. }
+            | CudaCommand::Memcpy3DAsync { stream, .. }
+            | CudaCommand::MemPrefetchAsync { stream, .. }
+            | CudaCommand::MemAllocAsync { stream, .. }
+            | CudaCommand::MemFreeAsync { stream, .. }
+            | CudaCommand::MemAllocFromPoolAsync { stream, .. }
+            | CudaCommand::LaunchKernel { stream, .. }
+            | CudaCommand::LaunchCooperativeKernel { stream, .. }
+            | CudaCommand::StreamDestroy { stream }
+            | CudaCommand::StreamSynchronize { stream }
+            | CudaCommand::StreamQuery { stream }
+            | CudaCommand::StreamWaitEvent { stream, .. }
+            | CudaCommand::StreamGetPriority { stream }
+            | CudaCommand::StreamGetFlags { stream }
+            | CudaCommand::StreamGetCtx { stream }
+            | CudaCommand::StreamBeginCapture { stream, .. }
+            | CudaCommand::StreamEndCapture { stream }
+            | CudaCommand::StreamIsCapturing { stream }
+            | CudaCommand::EventRecord { stream, .. }
+            | CudaCommand::EventRecordWithFlags { stream, .. }
+            | CudaCommand::GraphLaunch { stream, .. }
+            | CudaCommand::GraphUpload { stream, .. } => Some(*stream),
+            _ => None,
+        }
+    }
+
+    /// Visit every resource handle carried by this command.
+    pub fn handles_mut(&mut self, mut f: impl FnMut(&mut NetworkHandle)) {
+        match self {
+            CudaCommand::DeviceGetName { device } => f(device),
+            CudaCommand::DeviceGetAttribute { device, .. } => f(device),
+            CudaCommand::DeviceTotalMem { device } => f(device),
+            CudaCommand::DeviceComputeCapability { device } => f(device),
+            CudaCommand::DeviceGetUuid { device } => f(device),
+            CudaCommand::DeviceGetUsage { device } => f(device),
+            CudaCommand::DeviceGetP2PAttribute { src_device, dst_device, .. } => {
+                f(src_device);
+                f(dst_device);
+            }
+            CudaCommand::DeviceCanAccessPeer { device, peer_device } => {
+                f(device);
+                f(peer_device);
+            }
+            CudaCommand::DeviceGetPCIBusId { device } => f(device),
+            CudaCommand::DeviceGetDefaultMemPool { device } => f(device),
+            CudaCommand::DeviceGetMemPool { device } => f(device),
+            CudaCommand::DeviceSetMemPool { device, mem_pool } => {
+                f(device);
+                f(mem_pool);
+            }
+            CudaCommand::DeviceGetTexture1DLinearMaxWidth { device, .. } => f(device),
+            CudaCommand::DeviceGetExecAffinitySupport { device, .. } => f(device),
+            CudaCommand::DevicePrimaryCtxRetain { device } => f(device),
+            CudaCommand::DevicePrimaryCtxRelease { device } => f(device),
+            CudaCommand::DevicePrimaryCtxReset { device } => f(device),
+            CudaCommand::DevicePrimaryCtxGetState { device } => f(device),
+            CudaCommand::DevicePrimaryCtxSetFlags { device, .. } => f(device),
+            CudaCommand::CtxCreate { device, .. } => f(device),
+            CudaCommand::CtxDestroy { ctx } => f(ctx),
+            CudaCommand::CtxSetCurrent { ctx } => f(ctx),
+            CudaCommand::CtxPushCurrent { ctx } => f(ctx),
+            CudaCommand::CtxGetApiVersion { ctx } => f(ctx),
+            CudaCommand::ModuleUnload { module } => f(module),
+            CudaCommand::ModuleGetFunction { module, .. } => f(module),
+            CudaCommand::ModuleGetGlobal { module, .. } => f(module),
+            CudaCommand::MemFree { dptr } => f(dptr),
+            CudaCommand::MemcpyHtoD { dst, .. } => f(dst),
+            CudaCommand::MemcpyDtoH { src, .. } => f(src),
+            CudaCommand::MemcpyDtoD { dst, src, .. } => {
+                f(dst);
+                f(src);
+            }
+            CudaCommand::MemcpyHtoDAsync { dst, stream, .. } => {
+                f(dst);
+                f(stream);
+            }
+            CudaCommand::MemcpyDtoHAsync { src, stream, .. } => {
+                f(src);
+                f(stream);
+            }
+            CudaCommand::MemcpyDtoHDiff { src, .. } => f(src),
+            CudaCommand::MemSetTransferCodec { dptr, .. } => f(dptr),
+            CudaCommand::MemcpyHtoDEncoded { dst, .. } => f(dst),
+            CudaCommand::FillBufferTyped { dst, .. } => f(dst),
+            CudaCommand::Memcpy3D { params, .. } => {
+                params.src.handle.iter_mut().chain(&mut params.dst.handle).for_each(f);
+            }
+            CudaCommand::Memcpy3DAsync { params, stream, .. } => {
+                params.src.handle.iter_mut().chain(&mut params.dst.handle).for_each(&mut f);
+                f(stream);
+            }
+            CudaCommand::MemcpyDtoDAsync { dst, src, stream, .. } => {
+                f(dst);
+                f(src);
+                f(stream);
+            }
+            CudaCommand::MemsetD8 { dst, .. } => f(dst),
+            CudaCommand::MemsetD16 { dst, .. } => f(dst),
+            CudaCommand::MemsetD32 { dst, .. } => f(dst),
+            CudaCommand::MemsetD8Async { dst, stream, .. } => {
+                f(dst);
+                f(stream);
+            }
+            CudaCommand::MemsetD16Async { dst, stream, .. } => {
+                f(dst);
+                f(stream);
+            }
+            CudaCommand::MemsetD32Async { dst, stream, .. } => {
+                f(dst);
+                f(stream);
+            }
+            CudaCommand::MemsetD2D8 { dst, .. }
+            | CudaCommand::MemsetD2D16 { dst, .. }
+            | CudaCommand::MemsetD2D32 { dst, .. } => f(dst),
+            CudaCommand::MemsetD2D8Async { dst, stream, .. }
+            | CudaCommand::MemsetD2D16Async { dst, stream, .. }
+            | CudaCommand::MemsetD2D32Async { dst, stream, .. } => {
+                f(dst);
+                f(stream);
+            }
+            CudaCommand::TexObjectCreate { resource, .. } | CudaCommand::SurfObjectCreate { resource } => {
+                f(resource.resource.handle_mut())
+            }
+            CudaCommand::TexObjectDestroy { tex_object } => f(tex_object),
+            CudaCommand::SurfObjectDestroy { surf_object } => f(surf_object),
+            CudaCommand::ArrayCreate { .. } => {}
+            CudaCommand::ArrayDestroy { array } => f(array),
+            CudaCommand::ModuleGetTexRef { module, .. } | CudaCommand::ModuleGetSurfRef { module, .. } => {
+                f(module)
+            }
+            CudaCommand::TexRefSetAddress { texref, dptr, .. } => {
+                f(texref);
+                f(dptr);
+            }
+            CudaCommand::TexRefSet { texref, setting } => {
+                f(texref);
+                match setting {
+                    TexRefSetting::Address2D { dptr, .. } => f(dptr),
+                    TexRefSetting::Array { array, .. } => f(array),
+                    _ => {}
+                }
+            }
+            CudaCommand::SurfRefSetArray { surfref, array, .. } => {
+                f(surfref);
+                f(array);
+            }
+            CudaCommand::IpcGetMemHandle { dptr } | CudaCommand::IpcCloseMemHandle { dptr } => f(dptr),
+            CudaCommand::IpcOpenMemHandle { handle, .. } => f(&mut handle.memory),
+            CudaCommand::MemGetAddressRange { dptr } => f(dptr),
+            CudaCommand::MemFreeHost { ptr } => f(ptr),
+            CudaCommand::MemHostGetDevicePointer { host_ptr, .. } => f(host_ptr),
+            CudaCommand::MemHostGetFlags { host_ptr } => f(host_ptr),
+            CudaCommand::MemHostUnregister { ptr } => f(ptr),
+            CudaCommand::MemPrefetchAsync { dptr, dst_device, stream, .. } => {
+                f(dptr);
+                f(dst_device);
+                f(stream);
+            }
+            CudaCommand::MemAdvise { dptr, device, .. } => {
+                f(dptr);
+                f(device);
+            }
+            CudaCommand::MemRangeGetAttribute { dptr, .. } => f(dptr),
+            CudaCommand::LaunchKernel {
+                func,
+                stream,
+                kernel_params,
+                ..
+            }
+            | CudaCommand::LaunchCooperativeKernel {
+                func,
+                stream,
+                kernel_params,
+                ..
+            } => {
+                f(func);
+                f(stream);
+                kernel_params
+                    .iter_mut()
+                    .filter_map(|p| p.device_ptr.as_mut())
+                    .for_each(&mut f);
+            }
+            CudaCommand::FuncGetAttribute { func, .. } => f(func),
+            CudaCommand::FuncSetAttribute { func, .. } => f(func),
+            CudaCommand::FuncSetCacheConfig { func, .. } => f(func),
+            CudaCommand::FuncSetSharedMemConfig { func, .. } => f(func),
+            CudaCommand::FuncGetModule { func } => f(func),
+            CudaCommand::FuncGetName { func } => f(func),
+            CudaCommand::OccupancyMaxActiveBlocksPerMultiprocessor { func, .. } => f(func),
+            CudaCommand::OccupancyMaxActiveBlocksPerMultiprocessorWithFlags { func, .. } => f(func),
+            CudaCommand::OccupancyAvailableDynamicSMemPerBlock { func, .. } => f(func),
+            CudaCommand::StreamDestroy { stream } => f(stream),
+            CudaCommand::StreamSynchronize { stream } => f(stream),
+            CudaCommand::StreamQuery { stream } => f(stream),
+            CudaCommand::StreamWaitEvent { stream, event, .. } => {
+                f(stream);
+                f(event);
+            }
+            CudaCommand::StreamGetPriority { stream } => f(stream),
+            CudaCommand::StreamGetFlags { stream } => f(stream),
+            CudaCommand::StreamGetCtx { stream } => f(stream),
+            CudaCommand::EventDestroy { event } => f(event),
+            CudaCommand::EventRecord { event, stream } => {
+                f(event);
+                f(stream);
+            }
+            CudaCommand::EventRecordWithFlags { event, stream, .. } => {
+                f(event);
+                f(stream);
+            }
+            CudaCommand::EventSynchronize { event } => f(event),
+            CudaCommand::EventQuery { event } => f(event),
+            CudaCommand::EventElapsedTime { start, end } => {
+                f(start);
+                f(end);
+            }
+            CudaCommand::PointerGetAttribute { ptr, .. } => f(ptr),
+            CudaCommand::PointerGetAttributes { ptr, .. } => f(ptr),
+            CudaCommand::PointerSetAttribute { ptr, .. } => f(ptr),
+            CudaCommand::CtxEnablePeerAccess { peer_ctx, .. } => f(peer_ctx),
+            CudaCommand::CtxDisablePeerAccess { peer_ctx } => f(peer_ctx),
+            CudaCommand::MemPoolCreate { device, .. } => f(device),
+            CudaCommand::MemPoolDestroy { pool } => f(pool),
+            CudaCommand::MemPoolTrimTo { pool, .. } => f(pool),
+            CudaCommand::MemPoolSetAttribute { pool, .. } => f(pool),
+            CudaCommand::MemPoolGetAttribute { pool, .. } => f(pool),
+            CudaCommand::MemAllocAsync { stream, .. } => f(stream),
+            CudaCommand::MemFreeAsync { dptr, stream } => {
+                f(dptr);
+                f(stream);
+            }
+            CudaCommand::MemAllocFromPoolAsync { pool, stream, .. } => {
+                f(pool);
+                f(stream);
+            }
+            CudaCommand::LinkAddData { link, .. } => f(link),
+            CudaCommand::LinkAddFile { link, .. } => f(link),
+            CudaCommand::LinkComplete { link } => f(link),
+            CudaCommand::LinkDestroy { link } => f(link),
+            CudaCommand::StreamBeginCapture { stream, .. } => f(stream),
+            CudaCommand::StreamEndCapture { stream } => f(stream),
+            CudaCommand::StreamIsCapturing { stream } => f(stream),
+            CudaCommand::GraphDestroy { graph } => f(graph),
+            CudaCommand::GraphInstantiate { graph, .. } => f(graph),
+            CudaCommand::GraphExecDestroy { exec } => f(exec),
+            CudaCommand::GraphLaunch { exec, stream } | CudaCommand::GraphUpload { exec, stream } => {
+                f(exec);
+                f(stream);
+            }
+            CudaCommand::HostMemWrite { ptr, .. } | CudaCommand::HostMemRead { ptr, .. } => f(ptr),
+            CudaCommand::Init { .. }
+            | CudaCommand::DriverGetVersion
+            | CudaCommand::DeviceGetCount
+            | CudaCommand::DeviceGet { .. }
+            | CudaCommand::DeviceGetByPCIBusId { .. }
+            | CudaCommand::CtxGetCurrent
+            | CudaCommand::CtxSynchronize
+            | CudaCommand::CtxPopCurrent
+            | CudaCommand::CtxGetDevice
+            | CudaCommand::CtxSetCacheConfig { .. }
+            | CudaCommand::CtxGetCacheConfig
+            | CudaCommand::CtxSetLimit { .. }
+            | CudaCommand::CtxGetLimit { .. }
+            | CudaCommand::CtxGetStreamPriorityRange
+            | CudaCommand::CtxGetFlags
+            | CudaCommand::CtxSetFlags { .. }
+            | CudaCommand::CtxResetPersistingL2Cache
+            | CudaCommand::CtxSetSharedMemConfig { .. }
+            | CudaCommand::CtxGetSharedMemConfig
+            | CudaCommand::ModuleLoadData { .. }
+            | CudaCommand::ModuleLoadByHash { .. }
+            | CudaCommand::MemAlloc { .. }
+            | CudaCommand::MemGetInfo
+            | CudaCommand::MemAllocHost { .. }
+            | CudaCommand::MemHostAlloc { .. }
+            | CudaCommand::MemAllocManaged { .. }
+            | CudaCommand::MemAllocPitch { .. }
+            | CudaCommand::MemHostRegister { .. }
+            | CudaCommand::StreamCreate { .. }
+            | CudaCommand::StreamCreateWithPriority { .. }
+            | CudaCommand::EventCreate { .. }
+            | CudaCommand::ModuleLoad { .. }
+            | CudaCommand::ModuleLoadDataEx { .. }
+            | CudaCommand::ModuleLoadFatBinary { .. }
+            | CudaCommand::LinkCreate { .. }
+            | CudaCommand::GraphCreate { .. } => {}
+        }
+    }
+}
+
+impl CudaResponse {
+    /// Visit every resource handle carried by this response.
+    pub fn handles(&self, mut f: impl FnMut(&NetworkHandle)) {
+        match self {
+            CudaResponse::Device(h) => f(h),
+            CudaResponse::MemPool(h) => f(h),
+            CudaResponse::Context(h) => f(h),
+            CudaResponse::ContextDevice(h) => f(h),
+            CudaResponse::Module(h) => f(h),
+            CudaResponse::ModuleJit { module: Some(h), .. } => f(h),
+            CudaResponse::Function(h) => f(h),
+            CudaResponse::FunctionWithParams { handle, .. } => f(handle),
+            CudaResponse::GlobalPtr { ptr, .. } => f(ptr),
+            CudaResponse::MemAllocated(h) => f(h),
+            CudaResponse::MemAllocPitch { dptr, .. } => f(dptr),
+            CudaResponse::MemAddressRange { base, .. } => f(base),
+            CudaResponse::HostPtr(h) => f(h),
+            CudaResponse::HostDevicePtr(h) => f(h),
+            CudaResponse::Stream(h) => f(h),
+            CudaResponse::StreamCtx(h) => f(h),
+            CudaResponse::Event(h) => f(h),
+            CudaResponse::FuncModule(h) => f(h),
+            CudaResponse::Linker(h) => f(h),
+            CudaResponse::Graph(h) => f(h),
+            CudaResponse::GraphExec(h) => f(h),
+            CudaResponse::TexObject(h) => f(h),
+            CudaResponse::SurfObject(h) => f(h),
+            CudaResponse::Array(h) => f(h),
+            CudaResponse::TexRef(h) => f(h),
+            CudaResponse::SurfRef(h) => f(h),
+            CudaResponse::IpcMemHandle(handle) => f(&handle.memory),
+            CudaResponse::IpcMemOpened(h) => f(h),
+            CudaResponse::Success
+            | CudaResponse::TexRefOffset(_)
+            | CudaResponse::Error { .. }
+            | CudaResponse::DriverVersion(_)
+            | CudaResponse::DeviceCount(_)
+            | CudaResponse::DeviceName(_)
+            | CudaResponse::DeviceAttribute(_)
+            | CudaResponse::DeviceTotalMem(_)
+            | CudaResponse::ComputeCapability { .. }
+            | CudaResponse::DeviceUuid(_)
+            | CudaResponse::DevicePCIBusId(_)
+            | CudaResponse::P2PAttribute(_)
+            | CudaResponse::BoolResult(_)
+            | CudaResponse::PrimaryCtxState { .. }
+            | CudaResponse::Texture1DMaxWidth(_)
+            | CudaResponse::CacheConfig(_)
+            | CudaResponse::SharedMemConfig(_)
+            | CudaResponse::DeviceUsage { .. }
+            | CudaResponse::ContextLimit(_)
+            | CudaResponse::StreamPriorityRange { .. }
+            | CudaResponse::ContextApiVersion(_)
+            | CudaResponse::ContextFlags(_)
+            | CudaResponse::MemInfo { .. }
+            | CudaResponse::MemoryData(_)
+            | CudaResponse::MemoryDiff { .. }
+            | CudaResponse::EncodedMemoryData { .. }
+            | CudaResponse::StreamCaptureStatus(_)
+            | CudaResponse::HostFlags(_)
+            | CudaResponse::MemRangeAttribute(_)
+            | CudaResponse::StreamStatus(_)
+            | CudaResponse::StreamPriority(_)
+            | CudaResponse::StreamFlags(_)
+            | CudaResponse::EventStatus(_)
+            | CudaResponse::ElapsedTime(_)
+            | CudaResponse::PointerAttribute(_)
+            | CudaResponse::PointerAttributes(_)
+            | CudaResponse::FuncAttribute(_)
+            | CudaResponse::FuncName(_)
+            | CudaResponse::OccupancyBlocks(_)
+            | CudaResponse::OccupancyDynamicSmem(_)
+            | CudaResponse::MemPoolAttribute(_)
+            | CudaResponse::LinkCompleted { .. }
+            | CudaResponse::LinkJit { .. }
+            | CudaResponse::ModuleJit { module: None, .. }
+            | CudaResponse::ModuleImageMissing => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::ResourceType;
+    use crate::messages::{Message, RequestId};
+    use crate::wire;
+
+    /// Send `response` through the wire encoding and return what comes out.
+    fn round_trip(response: CudaResponse) -> CudaResponse {
+        let msg = Message::CudaResponse { request_id: RequestId(1), response };
+        let frame = wire::encode_message(&msg, 0).unwrap();
+        let header: &[u8; wire::HEADER_SIZE] = frame[..wire::HEADER_SIZE].try_into().unwrap();
+        let (flags, _, _) = wire::decode_header(header).unwrap();
+        let mut payload = rkyv::util::AlignedVec::<16>::new();
+        payload.extend_from_slice(&frame[wire::HEADER_SIZE..]);
+        match wire::decode_message(&payload, flags).unwrap() {
+            Message::CudaResponse { response, .. } => response,
+            other => panic!("expected CudaResponse, got {:?}", other),
+        }
+    }
+
+    fn jit_output() -> JitOutput {
+        JitOutput {
+            option_values: vec![0, 42, 0, 7, 128],
+            info_log: b"ptxas info: 12 registers".to_vec(),
+            error_log: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_module_jit_round_trips() {
+        let module = NetworkHandle {
+            server_id: 1,
+            session_id: 2,
+            resource_id: 3,
+            resource_type: ResourceType::CuModule,
+        };
+        match round_trip(CudaResponse::ModuleJit { module: Some(module), code: 0, jit: jit_output() }) {
+            CudaResponse::ModuleJit { module: Some(m), code: 0, jit } => {
+                assert_eq!(m, module);
+                assert_eq!(jit, jit_output());
+            }
+            other => panic!("expected ModuleJit, got {:?}", other),
+        }
+
+        // A module that didn't compile still brings its logs back.
+        let failed = JitOutput { error_log: b"ptxas fatal".to_vec(), ..Default::default() };
+        match round_trip(CudaResponse::ModuleJit { module: None, code: 218, jit: failed.clone() }) {
+            CudaResponse::ModuleJit { module: None, code: 218, jit } => assert_eq!(jit, failed),
+            other => panic!("expected ModuleJit, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_link_jit_round_trips() {
+        let cubin = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0];
+        match round_trip(CudaResponse::LinkJit { code: 0, cubin_data: cubin.clone(), jit: jit_output() }) {
+            CudaResponse::LinkJit { code: 0, cubin_data, jit } => {
+                assert_eq!(cubin_data, cubin);
+                assert_eq!(jit, jit_output());
+            }
+            other => panic!("expected LinkJit, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_module_jit_handle_is_visited() {
+        let module = NetworkHandle {
+            server_id: 1,
+            session_id: 2,
+            resource_id: 3,
+            resource_type: ResourceType::CuModule,
+        };
+        let mut seen = Vec::new();
+        CudaResponse::ModuleJit { module: Some(module), code: 0, jit: jit_output() }.handles(|h| seen.push(*h));
+        assert_eq!(seen, [module]);
+
+        seen.clear();
+        CudaResponse::ModuleJit { module: None, code: 218, jit: jit_output() }.handles(|h| seen.push(*h));
+        assert!(seen.is_empty());
+    }
 }
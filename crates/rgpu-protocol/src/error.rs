@@ -32,4 +32,10 @@ pub enum ProtocolError {
 
     #[error("not implemented: {0}")]
     NotImplemented(String),
+
+    #[error("request cancelled")]
+    Cancelled,
+
+    #[error("incompatible versions: {0}")]
+    VersionMismatch(String),
 }
@@ -0,0 +1,37 @@
+//! Repeated-pattern fills (`CudaCommand::FillBufferTyped`).
+//!
+//! Frameworks often initialize tensors by uploading a host buffer that holds
+//! the same fp16/bf16/fp32/vector value over and over. Sending the pattern
+//! and a repeat count instead of the whole buffer turns a multi-megabyte copy
+//! into a few bytes on the wire.
+
+/// Pattern sizes a fill may use, smallest first.
+pub const PATTERN_SIZES: [usize; 4] = [2, 4, 8, 16];
+
+/// The shortest pattern in [`PATTERN_SIZES`] that `data` repeats, if any.
+pub fn detect_pattern(data: &[u8]) -> Option<&[u8]> {
+    // Anything periodic in a smaller size is periodic in the largest one
+    // dividing the length, so one pass over the data is enough.
+    let &size = PATTERN_SIZES.iter().rev().find(|&&size| data.len().is_multiple_of(size))?;
+    if data.len() < 2 * size {
+        return None;
+    }
+    let pattern = &data[..size];
+    if !data.chunks_exact(size).all(|chunk| chunk == pattern) {
+        return None;
+    }
+    Some(shortest_period(pattern))
+}
+
+/// Reduce a pattern to the shortest prefix in [`PATTERN_SIZES`] it repeats.
+pub fn shortest_period(pattern: &[u8]) -> &[u8] {
+    for size in PATTERN_SIZES {
+        if size >= pattern.len() {
+            break;
+        }
+        if pattern.len().is_multiple_of(size) && pattern.chunks_exact(size).all(|c| c == &pattern[..size]) {
+            return &pattern[..size];
+        }
+    }
+    pattern
+}
@@ -0,0 +1,53 @@
+//! Encoding of presented frames (`VulkanResponse::FramesPresented`).
+//!
+//! A virtual swapchain reads back every presented image and sends it to the
+//! client, which shows it in the application's window. Consecutive frames
+//! mostly repeat each other, so after the first one a frame can be sent as
+//! [`FrameEncoding::Delta`]: the XOR with the previous frame, which is zero
+//! wherever nothing changed, LZ4-compressed. Both ends keep the last frame of
+//! each swapchain; a frame of a different size is always sent raw.
+
+use serde::{Deserialize, Serialize};
+
+/// How the pixels of a presented frame are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum FrameEncoding {
+    /// Tightly packed rows, as read back
+    Raw,
+    /// XOR with the previous frame of the swapchain, LZ4-compressed
+    Delta,
+}
+
+/// Encode `frame`, as a delta against `previous` if there is one of the
+/// same size.
+pub fn encode(frame: &[u8], previous: Option<&[u8]>) -> (FrameEncoding, Vec<u8>) {
+    match previous {
+        Some(previous) if previous.len() == frame.len() => {
+            let delta: Vec<u8> = frame.iter().zip(previous).map(|(a, b)| a ^ b).collect();
+            (FrameEncoding::Delta, lz4_flex::compress_prepend_size(&delta))
+        }
+        _ => (FrameEncoding::Raw, frame.to_vec()),
+    }
+}
+
+/// Reverse [`encode`]. `previous` is the last frame decoded for the same
+/// swapchain.
+pub fn decode(encoding: FrameEncoding, data: Vec<u8>, previous: Option<&[u8]>) -> Result<Vec<u8>, String> {
+    match encoding {
+        FrameEncoding::Raw => Ok(data),
+        FrameEncoding::Delta => {
+            let previous = previous.ok_or("delta frame without a previous frame")?;
+            let mut frame = lz4_flex::decompress_size_prepended(&data).map_err(|e| e.to_string())?;
+            if frame.len() != previous.len() {
+                return Err(format!(
+                    "delta frame is {} bytes, previous frame {}",
+                    frame.len(),
+                    previous.len()
+                ));
+            }
+            frame.iter_mut().zip(previous).for_each(|(a, b)| *a ^= b);
+            Ok(frame)
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 
 /// Comprehensive GPU capability information, shared between server and client.
-#[derive(Debug, Clone, Serialize, Deserialize,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize,
          rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct GpuInfo {
     /// Human-readable device name (e.g., "NVIDIA GeForce RTX 4090")
@@ -33,6 +33,43 @@ pub struct GpuInfo {
     /// Which server this GPU belongs to
     #[serde(default)]
     pub server_id: u16,
+    /// Where the GPU sits on the server's PCIe/NVLink fabric
+    #[serde(default)]
+    pub topology: GpuTopology,
+    /// How the server shares the GPU between sessions, and who is using it
+    #[serde(default)]
+    pub scheduling: GpuScheduling,
+    /// Device UUID, the same as CUDA's and nvidia-smi's (virtual GPUs share
+    /// their physical GPU's)
+    #[serde(default)]
+    pub uuid: Option<[u8; 16]>,
+}
+
+impl GpuInfo {
+    /// Whether work meant for `other` runs the same on this GPU: the same
+    /// model, memory and compute capability, on whichever server.
+    pub fn is_equivalent(&self, other: &GpuInfo) -> bool {
+        self.device_name == other.device_name
+            && self.vendor_id == other.vendor_id
+            && self.device_id == other.device_id
+            && self.total_memory == other.total_memory
+            && self.cuda_compute_capability == other.cuda_compute_capability
+            && self.supports_cuda == other.supports_cuda
+            && self.supports_vulkan == other.supports_vulkan
+    }
+
+    /// The UUID as CUDA and nvidia-smi write it, `GPU-xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`.
+    pub fn uuid_string(&self) -> Option<String> {
+        let hex: String = self.uuid?.iter().map(|b| format!("{:02x}", b)).collect();
+        Some(format!(
+            "GPU-{}-{}-{}-{}-{}",
+            &hex[..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..]
+        ))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize,
@@ -45,9 +82,143 @@ pub enum GpuDeviceType {
     Other,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize,
          rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct MemoryHeapInfo {
     pub size: u64,
     pub is_device_local: bool,
 }
+
+/// Placement of a GPU within its server.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct GpuTopology {
+    /// PCI bus ID, e.g. "0000:3b:00.0"
+    pub pci_bus_id: Option<String>,
+    /// Host NUMA node the GPU is attached to
+    pub numa_node: Option<u32>,
+    /// How this GPU connects to each other GPU on the same server
+    pub links: Vec<PeerLink>,
+}
+
+impl GpuTopology {
+    /// The link to the GPU with server-side index `peer`.
+    pub fn link_to(&self, peer: u32) -> Option<&PeerLink> {
+        self.links.iter().find(|l| l.peer_device_index == peer)
+    }
+}
+
+/// How sessions share a GPU (`[server.scheduling]` in rgpu.toml).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingMode {
+    /// Any number of sessions, no scheduling (default)
+    #[default]
+    Shared,
+    /// Leased to the first session that uses it until that session ends
+    Exclusive,
+    /// Sessions take turns of up to a time slice each
+    TimeSlice,
+    /// Memory split into equal partitions, one per session
+    Partitioned,
+}
+
+/// Scheduling state of a GPU, as of the query.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct GpuScheduling {
+    pub mode: SchedulingMode,
+    /// Sessions that created a CUDA context or Vulkan device on the GPU;
+    /// with `Exclusive`, the one holding the lease
+    pub sessions: Vec<u32>,
+    /// With `TimeSlice`, the session whose turn it is
+    pub current_turn: Option<u32>,
+    /// With `Partitioned`, how many sessions the GPU is split between
+    pub partitions: u32,
+    /// With `Partitioned`, VRAM each session may use
+    pub partition_bytes: u64,
+}
+
+impl GpuScheduling {
+    /// The session holding an exclusive lease, if any.
+    pub fn leased_to(&self) -> Option<u32> {
+        match self.mode {
+            SchedulingMode::Exclusive => self.sessions.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// One-line description, e.g. "exclusive, leased to session 3".
+    pub fn describe(&self) -> String {
+        match self.mode {
+            SchedulingMode::Shared => format!("shared, {} session(s)", self.sessions.len()),
+            SchedulingMode::Exclusive => match self.leased_to() {
+                Some(session) => format!("exclusive, leased to session {}", session),
+                None => "exclusive, available".to_string(),
+            },
+            SchedulingMode::TimeSlice => match self.current_turn {
+                Some(session) => format!("time-sliced, {} session(s), session {} running", self.sessions.len(), session),
+                None => format!("time-sliced, {} session(s)", self.sessions.len()),
+            },
+            SchedulingMode::Partitioned => format!(
+                "partitioned, {} of {} partitions of {} MB in use",
+                self.sessions.len(),
+                self.partitions,
+                self.partition_bytes / (1024 * 1024)
+            ),
+        }
+    }
+}
+
+/// Connection between two GPUs on the same server.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct PeerLink {
+    /// Server-side index of the other GPU
+    pub peer_device_index: u32,
+    /// Closest path between the two
+    pub path: LinkPath,
+    /// Number of NVLink links between the two (0 if not NVLink-connected)
+    pub nvlink_count: u32,
+    /// CUDA peer access is supported
+    pub p2p_access: bool,
+    /// Native atomics over the link are supported
+    pub p2p_atomics: bool,
+    /// Relative performance of the link (lower is better), as reported by
+    /// `CU_DEVICE_P2P_ATTRIBUTE_PERFORMANCE_RANK`
+    pub performance_rank: i32,
+}
+
+/// Closest connection between two GPUs, best first. Mirrors the levels of
+/// `nvidia-smi topo -m`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum LinkPath {
+    /// Direct NVLink
+    NvLink,
+    /// Behind the same PCIe switch (PIX/PXB)
+    PcieSwitch,
+    /// Through a PCIe host bridge (PHB)
+    HostBridge,
+    /// Between host bridges within a NUMA node (NODE)
+    NumaNode,
+    /// Across NUMA nodes (SYS)
+    System,
+    /// Topology couldn't be determined
+    Unknown,
+}
+
+impl LinkPath {
+    /// Short label as used by `nvidia-smi topo -m`.
+    pub fn label(self) -> &'static str {
+        match self {
+            LinkPath::NvLink => "NV",
+            LinkPath::PcieSwitch => "PIX",
+            LinkPath::HostBridge => "PHB",
+            LinkPath::NumaNode => "NODE",
+            LinkPath::System => "SYS",
+            LinkPath::Unknown => "?",
+        }
+    }
+}
@@ -82,4 +82,17 @@ pub enum ResourceType {
     CuHostPtr,
     CuMemPool,
     CuLinker,
+    CuGraph,
+    CuGraphExec,
+    CuTexObject,
+    CuSurfObject,
+    CuArray,
+    CuTexRef,
+    CuSurfRef,
+    /// Another session's allocation opened with cuIpcOpenMemHandle
+    CuIpcMem,
+
+    VkQueryPool,
+    VkPipelineCache,
+    VkBufferView,
 }
@@ -4,7 +4,13 @@ pub mod cuda_commands;
 pub mod vulkan_commands;
 pub mod gpu_info;
 pub mod wire;
+pub mod readback;
+pub mod codec;
+pub mod compat;
+pub mod fill;
+pub mod frame;
 pub mod error;
+pub mod version;
 
 pub use handle::{NetworkHandle, ResourceType};
 pub use messages::{Message, RequestId};
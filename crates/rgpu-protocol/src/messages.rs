@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use crate::cuda_commands::{CudaCommand, CudaResponse};
 use crate::error::ProtocolError;
 use crate::gpu_info::GpuInfo;
+use crate::version::BuildInfo;
 use crate::vulkan_commands::{VulkanCommand, VulkanResponse};
 
 /// A unique identifier for a request, used for matching responses.
@@ -36,6 +37,9 @@ pub enum Message {
         server_id: Option<u16>,
         available_gpus: Vec<GpuInfo>,
         error_message: Option<String>,
+        /// Secret for taking this session over from a later connection (see
+        /// `ResumeSession`)
+        resume_token: Option<u64>,
     },
 
     // ── GPU discovery ───────────────────────────────────────
@@ -49,6 +53,9 @@ pub enum Message {
     CudaCommand {
         request_id: RequestId,
         command: CudaCommand,
+        /// Time budget in milliseconds, counted from when the server receives
+        /// the command. Commands still queued past it are not executed.
+        deadline_ms: Option<u32>,
     },
     CudaResponse {
         request_id: RequestId,
@@ -59,6 +66,8 @@ pub enum Message {
     VulkanCommand {
         request_id: RequestId,
         command: VulkanCommand,
+        /// Time budget in milliseconds (see `CudaCommand::deadline_ms`).
+        deadline_ms: Option<u32>,
     },
     VulkanResponse {
         request_id: RequestId,
@@ -84,6 +93,8 @@ pub enum Message {
         uptime_secs: u64,
         server_id: u16,
         server_address: String,
+        /// Connected sessions with their names and labels.
+        sessions: Vec<SessionSummary>,
     },
 
     // ── Keepalive ───────────────────────────────────────────
@@ -92,7 +103,302 @@ pub enum Message {
 
     // ── Error ───────────────────────────────────────────────
     Error(ProtocolError),
+
+    // ── Cancellation ────────────────────────────────────────
+    /// Abandon a queued or in-flight command (e.g. its local process died).
+    /// No response is sent for the Cancel itself; the cancelled request is
+    /// answered with `Error(ProtocolError::Cancelled)`.
+    Cancel { request_id: RequestId },
+
+    // ── Session tagging ─────────────────────────────────────
+    /// Name and label the sender's session for metrics. Labels are merged
+    /// into the existing ones; labels set by the auth token take precedence.
+    /// Answered with `Pong`.
+    SetSessionInfo {
+        name: Option<String>,
+        labels: Vec<(String, String)>,
+    },
+
+    // ── Streamed readback (IPC only) ────────────────────────
+    /// Like `CudaCommand`, but a `MemoryData` response larger than
+    /// `chunk_size` comes back as `MemoryChunk` messages followed by a
+    /// `CudaResponse` with `Success`, so the application can copy each chunk
+    /// into its destination buffer as it arrives instead of holding the whole
+    /// payload twice. Smaller and non-data responses are sent as usual.
+    CudaCommandStreamed {
+        request_id: RequestId,
+        command: CudaCommand,
+        chunk_size: u32,
+    },
+    /// Part of a streamed `MemoryData` response; `data` belongs at `offset`.
+    /// It is encoded like a `Vec<u8>`, and is a `Bytes` so the daemon can
+    /// send slices of the payload it got without copying them out first.
+    MemoryChunk {
+        request_id: RequestId,
+        offset: u64,
+        data: bytes::Bytes,
+    },
+
+    // ── Pipelining ──────────────────────────────────────────
+    /// The void commands queued since the last sync point, then the command
+    /// that needs a response, in one round trip. The response is the
+    /// command's, or the batch's error (the command isn't run then), which is
+    /// how CUDA reports failed asynchronous work at the next call.
+    CudaPipelined {
+        request_id: RequestId,
+        batch: Vec<CudaCommand>,
+        command: CudaCommand,
+    },
+
+    // ── Build identification ────────────────────────────────
+    /// The sender's build, asking for the receiver's. Sent by the interposer
+    /// and ICD on their first IPC connection and by the daemon after
+    /// authenticating with a server.
+    QueryBuildInfo(BuildInfo),
+    /// The responder's build first, then (from the daemon) each server's.
+    BuildInfo(Vec<BuildInfo>),
+
+    // ── Link probing ────────────────────────────────────────
+    /// Answered with an `Echo` carrying the same payload. The daemon times
+    /// these to measure throughput when choosing a transport.
+    Echo(Vec<u8>),
+
+    // ── Forward compatibility ───────────────────────────────
+    /// The receiver couldn't decode or doesn't handle the request tagged
+    /// `request_id` (see [`crate::wire::request_tag`]), most likely because
+    /// the sender speaks a newer protocol version. Only that request fails;
+    /// the connection stays up.
+    Unsupported {
+        request_id: RequestId,
+        reason: String,
+    },
+
+    // ── Session resumption ──────────────────────────────────
+    /// Sent after authenticating on a reconnect: give this session the GPUs
+    /// the sender's previous session on this server (`session_id`) had, so
+    /// its CUDA ordinals resolve to the same devices. If the server still
+    /// keeps that session's objects and `resume_token` matches the one it
+    /// was given, this session takes them over too. Answered with
+    /// `SessionResumed`.
+    ResumeSession {
+        session_id: u32,
+        resume_token: Option<u64>,
+    },
+    /// `resumed` is false if the server doesn't remember the session.
+    SessionResumed {
+        resumed: bool,
+        /// Handles of the previous session that are still valid (0 if only
+        /// its GPUs were given back)
+        restored: u32,
+        /// Devices the previous session used that couldn't be given back
+        changed: Vec<DeviceChange>,
+    },
+
+    // ── Local shared memory ─────────────────────────────────
+    /// From a local application: set up a shared-memory region of about
+    /// `size` bytes for the bulk data on this IPC connection. Answered with
+    /// `SharedMemory`, or an `Error` if the daemon can't.
+    OpenSharedMemory { size: u64 },
+    /// The region the daemon created for the application to map, of
+    /// `size` bytes: a ring to the daemon, then one to the application.
+    SharedMemory { name: String, size: u64 },
+    /// The application has mapped the region; from now on frames of either
+    /// side may carry their payload in it (`FrameFlags::SHARED`). Answered
+    /// with `Pong`.
+    SharedMemoryAttached,
+
+    // ── RDMA ────────────────────────────────────────────────
+    /// From the client: its queue pair and receive ring, to carry bulk data
+    /// over RDMA next to this TCP connection. Answered with the server's own
+    /// `RdmaConnect`, after which frames of either side may carry their
+    /// payload in the other's ring (`FrameFlags::SHARED`), or an `Error` if
+    /// the server doesn't offer RDMA.
+    RdmaConnect(RdmaEndpoint),
+
+    // ── Device visibility ───────────────────────────────────
+    /// From a local application: show it only these devices, in this order
+    /// (`RGPU_VISIBLE_DEVICES`). The indices are CUDA ordinals and Vulkan
+    /// physical device positions as the daemon enumerates them. Answered
+    /// with the same message.
+    SetVisibleDevices(Vec<u32>),
+
+    // ── GPU topology ────────────────────────────────────────
+    /// From the server, unprompted: its GPUs changed (one was added or
+    /// removed, or a driver restart renumbered them). `gpus` is the new
+    /// list, as `GpuList` would have it. Sent between responses, to
+    /// authenticated clients of v53 on; over QUIC on a stream of its own.
+    /// Not answered.
+    ServerTopologyChanged { gpus: Vec<GpuInfo> },
+
+    // ── Chunked uploads ─────────────────────────────────────
+    /// Part of the data of upload `transfer`, sent ahead of the command that
+    /// uses it so a large copy doesn't travel as one frame: `data` belongs
+    /// at `offset` of its `total` bytes. Answered with `TransferAck`, in
+    /// turn with the responses to requests sent between chunks.
+    TransferChunk {
+        transfer: u64,
+        offset: u64,
+        total: u64,
+        data: Vec<u8>,
+    },
+    /// Bytes of upload `transfer` the server holds so far; 0 if it refused
+    /// the upload.
+    TransferAck { transfer: u64, received: u64 },
+    /// A `CudaCommand` whose data is that of upload `transfer`, sent with
+    /// none (`MemcpyHtoD` and `MemcpyHtoDAsync`). Answered as the command
+    /// would be.
+    CudaCommandChunked {
+        request_id: RequestId,
+        command: CudaCommand,
+        deadline_ms: Option<u32>,
+        transfer: u64,
+    },
+}
+
+/// One side of an RDMA link (see `rgpu_transport::rdma`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct RdmaEndpoint {
+    /// Port LID; 0 on RoCE, which is addressed by `gid`
+    pub lid: u16,
+    pub gid: [u8; 16],
+    pub qp_num: u32,
+    /// First packet sequence number
+    pub psn: u32,
+    /// Address and remote key of the ring the peer writes into
+    pub ring_address: u64,
+    pub ring_key: u32,
+    pub ring_size: u64,
+}
+
+/// A CUDA ordinal of a resumed session that no longer names the GPU it did
+/// before the reconnect.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct DeviceChange {
+    pub ordinal: i32,
+    /// UUID of the GPU the ordinal named before
+    pub previous: [u8; 16],
+    /// UUID of the GPU it names now, if any
+    pub current: Option<[u8; 16]>,
+}
+
+impl Message {
+    /// The request this message is or answers, for those that carry one.
+    pub fn request_id(&self) -> Option<RequestId> {
+        match self {
+            Message::CudaCommand { request_id, .. }
+            | Message::CudaResponse { request_id, .. }
+            | Message::VulkanCommand { request_id, .. }
+            | Message::VulkanResponse { request_id, .. }
+            | Message::Cancel { request_id }
+            | Message::CudaCommandStreamed { request_id, .. }
+            | Message::MemoryChunk { request_id, .. }
+            | Message::CudaPipelined { request_id, .. }
+            | Message::CudaCommandChunked { request_id, .. }
+            | Message::Unsupported { request_id, .. } => Some(*request_id),
+            _ => None,
+        }
+    }
+}
+
+/// Session label a client daemon sets on a server with the contexts its
+/// placement policy put on that server's GPUs.
+pub const PLACEMENT_LABEL: &str = "rgpu.placement";
+
+/// A connected session as reported in `MetricsData`.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SessionSummary {
+    pub session_id: u32,
+    /// Human-readable name, or the client name if none was set.
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub requests: u64,
+    pub connected_secs: u64,
+    /// Wire compression of the server's responses to this session.
+    pub compression: CompressionSummary,
+    /// VRAM held by the session on each device it uses.
+    pub vram: Vec<DeviceVramUsage>,
+    /// VRAM the session may hold on all devices together, from its token's
+    /// `max_memory` (0 = unlimited)
+    pub vram_quota: u64,
+    /// Allocations refused for going over a VRAM quota
+    pub vram_refused: u64,
+}
+
+/// A session's VRAM on one device, split by API.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct DeviceVramUsage {
+    /// Server-side device index (`u32::MAX` if the device couldn't be identified)
+    pub device_index: u32,
+    pub cuda_bytes: u64,
+    pub vulkan_bytes: u64,
+}
+
+impl DeviceVramUsage {
+    pub fn total(&self) -> u64 {
+        self.cuda_bytes + self.vulkan_bytes
+    }
+}
+
+/// Compression counters for one session (see [`crate::wire::CompressionStats`]).
+#[derive(Debug, Clone, Default, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct CompressionSummary {
+    /// Bytes of payloads large enough to try compressing
+    pub bytes_in: u64,
+    /// Bytes sent for those payloads
+    pub bytes_out: u64,
+    /// CPU time spent compressing
+    pub cpu_micros: u64,
+    /// Compression was turned off because the ratio was too low
+    pub disabled: bool,
+}
+
+impl CompressionSummary {
+    /// Uncompressed over compressed size; 1.0 if nothing was compressed yet.
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_out == 0 {
+            1.0
+        } else {
+            self.bytes_in as f64 / self.bytes_out as f64
+        }
+    }
+
+    pub fn bytes_saved(&self) -> u64 {
+        self.bytes_in.saturating_sub(self.bytes_out)
+    }
 }
 
-/// Current protocol version.
-pub const PROTOCOL_VERSION: u32 = 3;
+/// Current protocol version. v4 added cancellation and deadlines, diff
+/// readback, transfer codecs and session info; v5 typed fills (see
+/// [`crate::compat`]); v6 compression counters in `SessionSummary`; v7
+/// 2D/3D copies; v8 per-device VRAM in `SessionSummary`; v9 graphs and
+/// stream capture; v10 GPU topology in `GpuInfo`; v11 `CudaPipelined`; v12
+/// build info exchange; v13 kernel parameter sizes in `CudaResponse::Function`;
+/// v14 device pointers in `KernelParam`; v15 host-mapped memory; v16 shared
+/// memory bank configuration; v17 device usage queries; v18 echo for link
+/// probing; v19 request tags in frame headers and `Unsupported`; v20
+/// session resumption; v21 2D memsets; v22 texture and surface objects; v23
+/// CUDA arrays; v24 legacy texture and surface references; v25 IPC memory
+/// handles; v26 GPU scheduling state in `GpuInfo`; v27 VRAM quota and
+/// refusals in `SessionSummary`; v28 resume tokens and restored objects in
+/// session resumption; v29 zstd frame compression; v30 QUIC streams that
+/// carry a CUDA stream's requests in turn; v31 shared memory between
+/// applications and the daemon; v32 RDMA for bulk data; v33 virtual
+/// swapchains; v34 timeline semaphores; v35 query pools; v36 samplers and
+/// image descriptors; v37 pipeline caches; v38 secondary command buffers;
+/// v39 indirect draws and dispatches; v40 image copies, blits and resolves;
+/// v41 push constants; v42 feature structures in GetPhysicalDeviceFeatures2
+/// and CreateDevice; v43 pNext chains of CreateBuffer, CreateImage and
+/// QueueSubmit; v44 GetPhysicalDeviceImageFormatProperties; v45 buffer
+/// views; v46 events; v47 synchronization2; v48 dynamic rendering; v49
+/// CreateShaderModuleByHash; v50 ModuleLoadByHash; v51 JIT options of
+/// ModuleLoadDataEx; v52 JIT options of the linker; v53 GPU topology
+/// changes pushed by the server; v54 device UUIDs in `GpuInfo`; v55
+/// chunked uploads; v56 kernel parameter sizes in
+/// `CudaResponse::FunctionWithParams`, with `Function` back to the handle
+/// alone.
+pub const PROTOCOL_VERSION: u32 = 56;
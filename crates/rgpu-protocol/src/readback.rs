@@ -0,0 +1,134 @@
+//! Block hashing for differential readback (`CudaCommand::MemcpyDtoHDiff`).
+//!
+//! The buffer is split into fixed-size blocks (the last one may be short).
+//! The requester sends the hashes of the copy it already holds, the server
+//! answers with only the blocks whose hash differs, and the requester patches
+//! them into its copy.
+
+/// Default block size: small enough that a localized update only resends a
+/// little, large enough that the hash list stays tiny (8 bytes per 64 KB).
+pub const DEFAULT_BLOCK_SIZE: u32 = 64 * 1024;
+
+/// Hash of a single block.
+pub fn block_hash(block: &[u8]) -> u64 {
+    twox_hash::XxHash3_64::oneshot(block)
+}
+
+/// Blocks of `data` that are missing from or differ from `known_hashes`.
+/// Returns the block indices and their contents concatenated.
+pub fn diff_blocks(data: &[u8], block_size: u32, known_hashes: &[u64]) -> (Vec<u32>, Vec<u8>) {
+    let mut changed_blocks = Vec::new();
+    let mut changed_data = Vec::new();
+    for (index, block) in data.chunks(block_size.max(1) as usize).enumerate() {
+        let unchanged = known_hashes
+            .get(index)
+            .is_some_and(|known| *known == block_hash(block));
+        if !unchanged {
+            changed_blocks.push(index as u32);
+            changed_data.extend_from_slice(block);
+        }
+    }
+    (changed_blocks, changed_data)
+}
+
+/// Patch the changed blocks from a `MemoryDiff` response into `base`, which
+/// is resized to `byte_count` first. Fails if the diff doesn't fit the buffer.
+pub fn apply_diff(
+    base: &mut Vec<u8>,
+    byte_count: usize,
+    block_size: u32,
+    changed_blocks: &[u32],
+    data: &[u8],
+) -> Result<(), String> {
+    let block_size = block_size.max(1) as usize;
+    base.resize(byte_count, 0);
+
+    let mut offset = 0;
+    for &index in changed_blocks {
+        let start = index as usize * block_size;
+        if start >= byte_count {
+            return Err(format!("block {} is past the end of the buffer", index));
+        }
+        let len = block_size.min(byte_count - start);
+        let Some(block) = data.get(offset..offset + len) else {
+            return Err(format!("diff data truncated at block {}", index));
+        };
+        base[start..start + len].copy_from_slice(block);
+        offset += len;
+    }
+    if offset != data.len() {
+        return Err(format!(
+            "diff data has {} trailing bytes",
+            data.len() - offset
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: u32 = 16;
+
+    fn hashes(data: &[u8]) -> Vec<u64> {
+        data.chunks(BLOCK as usize).map(block_hash).collect()
+    }
+
+    /// Diff `new` against a copy of `old`, patch the copy, and return the
+    /// blocks that were sent.
+    fn round_trip(old: &[u8], new: &[u8]) -> Vec<u32> {
+        let (changed_blocks, data) = diff_blocks(new, BLOCK, &hashes(old));
+        let mut copy = old.to_vec();
+        apply_diff(&mut copy, new.len(), BLOCK, &changed_blocks, &data).unwrap();
+        assert_eq!(copy, new);
+        changed_blocks
+    }
+
+    #[test]
+    fn test_identical_buffers_diff_empty() {
+        let data: Vec<u8> = (0..100).collect();
+        let (changed_blocks, changed_data) = diff_blocks(&data, BLOCK, &hashes(&data));
+        assert!(changed_blocks.is_empty());
+        assert!(changed_data.is_empty());
+        assert!(round_trip(&data, &data).is_empty());
+    }
+
+    #[test]
+    fn test_change_in_last_partial_block() {
+        // Six full blocks and four bytes
+        let old: Vec<u8> = (0..100).collect();
+        let mut new = old.clone();
+        new[99] ^= 0xff;
+        assert_eq!(round_trip(&old, &new), vec![6]);
+    }
+
+    #[test]
+    fn test_different_lengths() {
+        let old: Vec<u8> = (0..100).collect();
+
+        // Growing sends the blocks past the old end, and the old last block
+        // now that it is longer
+        let mut longer = old.clone();
+        longer.extend(100..140);
+        assert_eq!(round_trip(&old, &longer), vec![6, 7, 8]);
+
+        // Shrinking to a block boundary sends nothing
+        assert!(round_trip(&old, &old[..64]).is_empty());
+        // Shrinking into a block sends that block, now shorter
+        assert_eq!(round_trip(&old, &old[..70]), vec![4]);
+    }
+
+    #[test]
+    fn test_out_of_range_diff_is_refused() {
+        let mut base = vec![0u8; 100];
+
+        // A block past the end
+        assert!(apply_diff(&mut base, 100, BLOCK, &[7], &[1; 16]).is_err());
+        // Less data than the blocks need; the last block is four bytes
+        assert!(apply_diff(&mut base, 100, BLOCK, &[0, 6], &[1; 19]).is_err());
+        // More data than the blocks need
+        assert!(apply_diff(&mut base, 100, BLOCK, &[6], &[1; 16]).is_err());
+        assert!(apply_diff(&mut base, 100, BLOCK, &[6], &[1; 4]).is_ok());
+    }
+}
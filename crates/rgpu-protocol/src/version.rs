@@ -0,0 +1,199 @@
+//! Build identification exchanged between components at connect time.
+//!
+//! The interposer and ICD send their [`BuildInfo`] to the daemon on their
+//! first IPC connection and get back the daemon's and every server's, so a
+//! mismatched install shows up as a clear warning (or a refused connection)
+//! instead of decode errors deep inside an application's first GPU call.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::compat::MIN_PROTOCOL_VERSION;
+use crate::messages::{Message, PROTOCOL_VERSION};
+
+/// Git commit of this build, if it was built from a checkout.
+pub const GIT_HASH: Option<&str> = option_env!("RGPU_GIT_HASH");
+
+/// Version and build of one component.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct BuildInfo {
+    /// Crate name, e.g. `rgpu-cuda-interpose`
+    pub component: String,
+    /// Semantic version of the release
+    pub version: String,
+    pub git_hash: Option<String>,
+    pub protocol_version: u32,
+    /// Where the component runs, for servers as seen from the daemon
+    pub address: Option<String>,
+}
+
+impl BuildInfo {
+    /// Info of the running build of `component`.
+    pub fn current(component: &str) -> Self {
+        Self {
+            component: component.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: GIT_HASH.map(str::to_string),
+            protocol_version: PROTOCOL_VERSION,
+            address: None,
+        }
+    }
+
+    pub fn with_address(mut self, address: impl Into<String>) -> Self {
+        self.address = Some(address.into());
+        self
+    }
+
+    /// How well a peer with `other`'s build works with this one. `bridged`
+    /// peers (servers behind the daemon) may be older as long as the daemon
+    /// can still translate for them; local components must speak the same
+    /// protocol version, since nothing translates IPC messages.
+    pub fn check(&self, other: &BuildInfo, bridged: bool) -> Compatibility {
+        let versions = self.protocol_version != other.protocol_version;
+        if versions && (!bridged || other.protocol_version < MIN_PROTOCOL_VERSION) {
+            return Compatibility::Incompatible(format!(
+                "{} speaks protocol v{}, {} speaks v{}",
+                self, self.protocol_version, other, other.protocol_version
+            ));
+        }
+        if versions || self.version != other.version {
+            return Compatibility::Differs(format!("{} is running next to {}", other, self));
+        }
+        match (&self.git_hash, &other.git_hash) {
+            (Some(ours), Some(theirs)) if ours != theirs => {
+                Compatibility::Differs(format!("{} is running next to {}", other, self))
+            }
+            _ => Compatibility::Same,
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.component, self.version)?;
+        if let Some(hash) = &self.git_hash {
+            write!(f, " ({})", hash)?;
+        }
+        if let Some(address) = &self.address {
+            write!(f, " at {}", address)?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of [`BuildInfo::check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Compatibility {
+    Same,
+    /// Different builds that still understand each other
+    Differs(String),
+    /// Messages between the two would fail to decode
+    Incompatible(String),
+}
+
+/// One-line listing of `builds`, e.g. for a startup banner.
+pub fn banner(builds: &[BuildInfo]) -> String {
+    builds
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// What a daemon told a local component about its build.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DaemonBuilds {
+    /// The daemon's build, then every server's, with how the daemon's
+    /// differs from ours if it does
+    Known {
+        builds: Vec<BuildInfo>,
+        mismatch: Option<String>,
+    },
+    /// The daemon predates the exchange
+    Older,
+}
+
+/// Swap builds with the daemon on a new local connection, where `send`
+/// writes a message and `read` reads the next reply. A daemon that predates
+/// the exchange can't decode the query and skips it, so a Ping goes behind
+/// it to get an answer either way. `Err` if the daemon refuses `ours`.
+pub fn exchange_with_daemon<C, E: From<String>>(
+    conn: &mut C,
+    ours: &BuildInfo,
+    send: impl Fn(&mut C, &Message) -> Result<(), E>,
+    read: impl Fn(&mut C) -> Result<Message, E>,
+) -> Result<DaemonBuilds, E> {
+    for msg in [Message::QueryBuildInfo(ours.clone()), Message::Ping] {
+        send(conn, &msg)?;
+    }
+    match read(conn)? {
+        Message::BuildInfo(builds) => {
+            read(conn)?;
+            let mismatch = builds.first().and_then(|daemon| match ours.check(daemon, false) {
+                Compatibility::Same => None,
+                Compatibility::Differs(reason) | Compatibility::Incompatible(reason) => Some(reason),
+            });
+            Ok(DaemonBuilds::Known { builds, mismatch })
+        }
+        Message::Pong => Ok(DaemonBuilds::Older),
+        Message::Error(e) => Err(format!("RGPU daemon refused connection: {}", e).into()),
+        other => Err(format!("unexpected response to build query: {:?}", other).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ProtocolError;
+    use std::collections::VecDeque;
+
+    /// Run the exchange against a daemon that answers with `replies`,
+    /// returning the outcome and what was sent.
+    fn exchange(replies: Vec<Message>) -> (Result<DaemonBuilds, String>, Vec<Message>) {
+        let mut conn = (Vec::new(), VecDeque::from(replies));
+        let result = exchange_with_daemon(
+            &mut conn,
+            &BuildInfo::current("rgpu-cuda-interpose"),
+            |(sent, _), msg| {
+                sent.push(msg.clone());
+                Ok(())
+            },
+            |(_, replies)| replies.pop_front().ok_or_else(|| "connection closed".to_string()),
+        );
+        (result, conn.0)
+    }
+
+    #[test]
+    fn test_exchange_with_current_daemon() {
+        let daemon = BuildInfo::current("rgpu-client");
+        let (result, sent) = exchange(vec![Message::BuildInfo(vec![daemon.clone()]), Message::Pong]);
+        assert_eq!(result, Ok(DaemonBuilds::Known { builds: vec![daemon], mismatch: None }));
+        assert!(matches!(sent.as_slice(), [Message::QueryBuildInfo(_), Message::Ping]));
+    }
+
+    #[test]
+    fn test_exchange_reports_mismatch() {
+        let daemon = BuildInfo {
+            version: "0.0.1".to_string(),
+            ..BuildInfo::current("rgpu-client")
+        };
+        let (result, _) = exchange(vec![Message::BuildInfo(vec![daemon]), Message::Pong]);
+        assert!(matches!(result, Ok(DaemonBuilds::Known { mismatch: Some(_), .. })));
+    }
+
+    #[test]
+    fn test_exchange_with_older_daemon() {
+        // Only the Ping is answered
+        let (result, _) = exchange(vec![Message::Pong]);
+        assert_eq!(result, Ok(DaemonBuilds::Older));
+    }
+
+    #[test]
+    fn test_exchange_refused() {
+        let refusal = ProtocolError::VersionMismatch("rgpu-client speaks protocol v1".to_string());
+        let (result, _) = exchange(vec![Message::Error(refusal)]);
+        assert!(result.is_err());
+    }
+}
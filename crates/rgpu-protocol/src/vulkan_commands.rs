@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use crate::frame::FrameEncoding;
 use crate::handle::NetworkHandle;
 
 // ============================================================================
@@ -28,6 +29,8 @@ pub struct SerializedDescriptorSetLayoutBinding {
     pub descriptor_type: i32,
     pub descriptor_count: u32,
     pub stage_flags: u32,
+    /// Samplers baked into the layout; empty when the binding has none
+    pub immutable_samplers: Vec<NetworkHandle>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize,
@@ -69,6 +72,10 @@ pub struct SerializedWriteDescriptorSet {
     pub dst_array_element: u32,
     pub descriptor_type: i32,
     pub buffer_infos: Vec<SerializedDescriptorBufferInfo>,
+    /// For sampler, image and input attachment descriptors
+    pub image_infos: Vec<SerializedDescriptorImageInfo>,
+    /// For uniform and storage texel buffer descriptors
+    pub texel_buffer_views: Vec<NetworkHandle>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize,
@@ -79,6 +86,72 @@ pub struct SerializedDescriptorBufferInfo {
     pub range: u64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedDescriptorImageInfo {
+    /// Ignored for image-only descriptors and immutable samplers
+    pub sampler: Option<NetworkHandle>,
+    /// Ignored for sampler-only descriptors
+    pub image_view: Option<NetworkHandle>,
+    pub image_layout: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedSamplerCreateInfo {
+    pub flags: u32,
+    pub mag_filter: i32,
+    pub min_filter: i32,
+    pub mipmap_mode: i32,
+    pub address_mode_u: i32,
+    pub address_mode_v: i32,
+    pub address_mode_w: i32,
+    pub mip_lod_bias: f32,
+    pub anisotropy_enable: bool,
+    pub max_anisotropy: f32,
+    pub compare_enable: bool,
+    pub compare_op: i32,
+    pub min_lod: f32,
+    pub max_lod: f32,
+    pub border_color: i32,
+    pub unnormalized_coordinates: bool,
+}
+
+/// A feature structure of a pNext chain: its VkStructureType and the
+/// VkBool32 members after sType and pNext, as raw bytes.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedFeatureStruct {
+    pub s_type: i32,
+    pub data: Vec<u8>,
+}
+
+/// A structure of the pNext chain of a create or submit info. Structures
+/// that hold pointers have a variant of their own; the rest travel as `Raw`.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub enum SerializedPNext {
+    /// A structure without pointers: its VkStructureType and the bytes
+    /// after sType and pNext, passed through as they are
+    Raw { s_type: i32, data: Vec<u8> },
+    /// VkImageFormatListCreateInfo
+    ImageFormatList { view_formats: Vec<i32> },
+}
+
+/// How a secondary command buffer is begun: its usage flags and
+/// VkCommandBufferInheritanceInfo.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedSecondaryBeginInfo {
+    pub usage_flags: u32,
+    pub render_pass: Option<NetworkHandle>,
+    pub subpass: u32,
+    pub framebuffer: Option<NetworkHandle>,
+    pub occlusion_query_enable: bool,
+    pub query_flags: u32,
+    pub pipeline_statistics: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize,
          rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
 pub struct SerializedSubmitInfo {
@@ -86,6 +159,13 @@ pub struct SerializedSubmitInfo {
     pub wait_dst_stage_masks: Vec<u32>,
     pub command_buffers: Vec<NetworkHandle>,
     pub signal_semaphores: Vec<NetworkHandle>,
+    /// Timeline values for `wait_semaphores` (VkTimelineSemaphoreSubmitInfo);
+    /// empty when only binary semaphores are involved
+    pub wait_semaphore_values: Vec<u64>,
+    /// Timeline values for `signal_semaphores`; empty like `wait_semaphore_values`
+    pub signal_semaphore_values: Vec<u64>,
+    /// Other structures chained to VkSubmitInfo
+    pub p_next: Vec<SerializedPNext>,
 }
 
 /// Recorded command buffer commands, batched client-side and sent at submit time.
@@ -185,6 +265,142 @@ pub enum RecordedCommand {
         dst_buffer: NetworkHandle,
         regions: Vec<SerializedBufferImageCopy>,
     },
+
+    // ── Queries ─────────────────────────────────────────────
+    ResetQueryPool {
+        query_pool: NetworkHandle,
+        first_query: u32,
+        query_count: u32,
+    },
+    BeginQuery {
+        query_pool: NetworkHandle,
+        query: u32,
+        flags: u32,
+    },
+    EndQuery {
+        query_pool: NetworkHandle,
+        query: u32,
+    },
+    WriteTimestamp {
+        pipeline_stage: u32,
+        query_pool: NetworkHandle,
+        query: u32,
+    },
+    CopyQueryPoolResults {
+        query_pool: NetworkHandle,
+        first_query: u32,
+        query_count: u32,
+        dst_buffer: NetworkHandle,
+        dst_offset: u64,
+        stride: u64,
+        flags: u32,
+    },
+    ExecuteCommands {
+        command_buffers: Vec<NetworkHandle>,
+    },
+    DrawIndirect {
+        buffer: NetworkHandle,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    },
+    DrawIndexedIndirect {
+        buffer: NetworkHandle,
+        offset: u64,
+        draw_count: u32,
+        stride: u32,
+    },
+    DispatchIndirect {
+        buffer: NetworkHandle,
+        offset: u64,
+    },
+    DrawIndirectCount {
+        buffer: NetworkHandle,
+        offset: u64,
+        count_buffer: NetworkHandle,
+        count_buffer_offset: u64,
+        max_draw_count: u32,
+        stride: u32,
+    },
+    DrawIndexedIndirectCount {
+        buffer: NetworkHandle,
+        offset: u64,
+        count_buffer: NetworkHandle,
+        count_buffer_offset: u64,
+        max_draw_count: u32,
+        stride: u32,
+    },
+    CopyImage {
+        src_image: NetworkHandle,
+        src_image_layout: i32,
+        dst_image: NetworkHandle,
+        dst_image_layout: i32,
+        regions: Vec<SerializedImageCopy>,
+    },
+    BlitImage {
+        src_image: NetworkHandle,
+        src_image_layout: i32,
+        dst_image: NetworkHandle,
+        dst_image_layout: i32,
+        regions: Vec<SerializedImageBlit>,
+        filter: i32,
+    },
+    ResolveImage {
+        src_image: NetworkHandle,
+        src_image_layout: i32,
+        dst_image: NetworkHandle,
+        dst_image_layout: i32,
+        regions: Vec<SerializedImageCopy>,
+    },
+    PushConstants {
+        layout: NetworkHandle,
+        stage_flags: u32,
+        offset: u32,
+        data: Vec<u8>,
+    },
+    SetEvent {
+        event: NetworkHandle,
+        stage_mask: u32,
+    },
+    ResetEvent {
+        event: NetworkHandle,
+        stage_mask: u32,
+    },
+    WaitEvents {
+        events: Vec<NetworkHandle>,
+        src_stage_mask: u32,
+        dst_stage_mask: u32,
+        memory_barriers: Vec<SerializedMemoryBarrier>,
+        buffer_memory_barriers: Vec<SerializedBufferMemoryBarrier>,
+        image_memory_barriers: Vec<SerializedImageMemoryBarrier>,
+    },
+    PipelineBarrier2 {
+        dependency_info: SerializedDependencyInfo,
+    },
+    SetEvent2 {
+        event: NetworkHandle,
+        dependency_info: SerializedDependencyInfo,
+    },
+    ResetEvent2 {
+        event: NetworkHandle,
+        stage_mask: u64,
+    },
+    WaitEvents2 {
+        events: Vec<NetworkHandle>,
+        /// One per event
+        dependency_infos: Vec<SerializedDependencyInfo>,
+    },
+    WriteTimestamp2 {
+        stage: u64,
+        query_pool: NetworkHandle,
+        query: u32,
+    },
+
+    // ── Dynamic rendering ───────────────────────────────────
+    BeginRendering {
+        rendering_info: SerializedRenderingInfo,
+    },
+    EndRendering,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize,
@@ -233,6 +449,8 @@ pub struct SerializedImageCreateInfo {
     pub sharing_mode: i32,
     pub queue_family_indices: Vec<u32>,
     pub initial_layout: i32,
+    /// Structures chained to VkImageCreateInfo
+    pub p_next: Vec<SerializedPNext>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize,
@@ -448,8 +666,20 @@ pub struct SerializedGraphicsPipelineCreateInfo {
     pub color_blend_state: Option<SerializedPipelineColorBlendStateCreateInfo>,
     pub dynamic_state: Option<SerializedPipelineDynamicStateCreateInfo>,
     pub layout: NetworkHandle,
-    pub render_pass: NetworkHandle,
+    /// None for pipelines used with dynamic rendering
+    pub render_pass: Option<NetworkHandle>,
     pub subpass: u32,
+    /// VkPipelineRenderingCreateInfo, for dynamic rendering
+    pub rendering: Option<SerializedPipelineRenderingCreateInfo>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedPipelineRenderingCreateInfo {
+    pub view_mask: u32,
+    pub color_attachment_formats: Vec<i32>,
+    pub depth_attachment_format: i32,
+    pub stencil_attachment_format: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize,
@@ -491,6 +721,163 @@ pub struct SerializedBufferImageCopy {
     pub image_extent: [u32; 3],
 }
 
+/// A region of vkCmdCopyImage, or of vkCmdResolveImage (VkImageResolve has
+/// the same fields).
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedImageCopy {
+    pub src_subresource: SerializedImageSubresourceLayers,
+    pub src_offset: [i32; 3],
+    pub dst_subresource: SerializedImageSubresourceLayers,
+    pub dst_offset: [i32; 3],
+    pub extent: [u32; 3],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedImageBlit {
+    pub src_subresource: SerializedImageSubresourceLayers,
+    pub src_offsets: [[i32; 3]; 2],
+    pub dst_subresource: SerializedImageSubresourceLayers,
+    pub dst_offsets: [[i32; 3]; 2],
+}
+
+// ============================================================================
+// Synchronization2 serialization types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedMemoryBarrier2 {
+    pub src_stage_mask: u64,
+    pub src_access_mask: u64,
+    pub dst_stage_mask: u64,
+    pub dst_access_mask: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedBufferMemoryBarrier2 {
+    pub src_stage_mask: u64,
+    pub src_access_mask: u64,
+    pub dst_stage_mask: u64,
+    pub dst_access_mask: u64,
+    pub src_queue_family_index: u32,
+    pub dst_queue_family_index: u32,
+    pub buffer: NetworkHandle,
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedImageMemoryBarrier2 {
+    pub src_stage_mask: u64,
+    pub src_access_mask: u64,
+    pub dst_stage_mask: u64,
+    pub dst_access_mask: u64,
+    pub old_layout: i32,
+    pub new_layout: i32,
+    pub src_queue_family_index: u32,
+    pub dst_queue_family_index: u32,
+    pub image: NetworkHandle,
+    pub subresource_range: SerializedImageSubresourceRange,
+}
+
+/// VkDependencyInfo: the barriers of a synchronization2 barrier or event.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedDependencyInfo {
+    pub dependency_flags: u32,
+    pub memory_barriers: Vec<SerializedMemoryBarrier2>,
+    pub buffer_memory_barriers: Vec<SerializedBufferMemoryBarrier2>,
+    pub image_memory_barriers: Vec<SerializedImageMemoryBarrier2>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedSemaphoreSubmitInfo {
+    pub semaphore: NetworkHandle,
+    /// Ignored for binary semaphores
+    pub value: u64,
+    pub stage_mask: u64,
+    pub device_index: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedSubmitInfo2 {
+    pub flags: u32,
+    pub wait_semaphore_infos: Vec<SerializedSemaphoreSubmitInfo>,
+    pub command_buffers: Vec<NetworkHandle>,
+    pub signal_semaphore_infos: Vec<SerializedSemaphoreSubmitInfo>,
+}
+
+// ============================================================================
+// Dynamic rendering serialization types
+// ============================================================================
+
+/// VkRenderingAttachmentInfo
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedRenderingAttachmentInfo {
+    /// None when the attachment is unused
+    pub image_view: Option<NetworkHandle>,
+    pub image_layout: i32,
+    pub resolve_mode: u32,
+    pub resolve_image_view: Option<NetworkHandle>,
+    pub resolve_image_layout: i32,
+    pub load_op: i32,
+    pub store_op: i32,
+    pub clear_value: SerializedClearValue,
+}
+
+/// VkRenderingInfo: the attachments a vkCmdBeginRendering renders to.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedRenderingInfo {
+    pub flags: u32,
+    pub render_area: SerializedRect2D,
+    pub layer_count: u32,
+    pub view_mask: u32,
+    pub color_attachments: Vec<SerializedRenderingAttachmentInfo>,
+    pub depth_attachment: Option<SerializedRenderingAttachmentInfo>,
+    pub stencil_attachment: Option<SerializedRenderingAttachmentInfo>,
+}
+
+// ============================================================================
+// Virtual swapchain serialization types
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedSwapchainCreateInfo {
+    pub min_image_count: u32,
+    pub format: i32,
+    pub extent: [u32; 2],
+    pub image_usage: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct SerializedPresent {
+    pub swapchain: NetworkHandle,
+    pub image_index: u32,
+}
+
+/// A presented image, read back for the client to show.
+#[derive(Debug, Clone, Serialize, Deserialize,
+         rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)]
+pub struct PresentedFrame {
+    pub swapchain: NetworkHandle,
+    pub width: u32,
+    pub height: u32,
+    pub format: i32,
+    pub encoding: FrameEncoding,
+    /// Tightly packed rows, encoded as `encoding` says
+    pub data: Vec<u8>,
+}
+
 // ============================================================================
 // Response serialized types
 // ============================================================================
@@ -581,6 +968,9 @@ pub enum VulkanCommand {
     },
     GetPhysicalDeviceFeatures2 {
         physical_device: NetworkHandle,
+        /// Feature structures the application chained, zeroed, to be
+        /// filled in
+        chain: Vec<SerializedFeatureStruct>,
     },
     GetPhysicalDeviceMemoryProperties {
         physical_device: NetworkHandle,
@@ -606,6 +996,9 @@ pub enum VulkanCommand {
         enabled_extensions: Vec<String>,
         /// Physical device features serialized as raw bytes (VkPhysicalDeviceFeatures)
         enabled_features: Option<Vec<u8>>,
+        /// Structures chained to VkDeviceCreateInfo, such as
+        /// VkPhysicalDeviceDescriptorIndexingFeatures
+        p_next: Vec<SerializedPNext>,
     },
     DestroyDevice {
         device: NetworkHandle,
@@ -671,6 +1064,8 @@ pub enum VulkanCommand {
         usage: u32,
         sharing_mode: u32,
         queue_family_indices: Vec<u32>,
+        /// Structures chained to VkBufferCreateInfo
+        p_next: Vec<SerializedPNext>,
     },
     DestroyBuffer {
         device: NetworkHandle,
@@ -721,6 +1116,8 @@ pub enum VulkanCommand {
     // ── Compute Pipeline ────────────────────────────────────
     CreateComputePipelines {
         device: NetworkHandle,
+        /// The application's pipeline cache; the server's own when `None`
+        pipeline_cache: Option<NetworkHandle>,
         create_infos: Vec<SerializedComputePipelineCreateInfo>,
     },
     DestroyPipeline {
@@ -786,8 +1183,12 @@ pub enum VulkanCommand {
     },
 
     // ── Command Buffer Recording (Batched) ──────────────────
+    /// Sent at submit time for a primary command buffer, and when it ends
+    /// for a secondary one
     SubmitRecordedCommands {
         command_buffer: NetworkHandle,
+        /// Begin info of a secondary command buffer; `None` for a primary one
+        secondary: Option<SerializedSecondaryBeginInfo>,
         commands: Vec<RecordedCommand>,
     },
 
@@ -878,17 +1279,179 @@ pub enum VulkanCommand {
     // ── Graphics Pipeline ──────────────────────────────────
     CreateGraphicsPipelines {
         device: NetworkHandle,
+        /// The application's pipeline cache; the server's own when `None`
+        pipeline_cache: Option<NetworkHandle>,
         create_infos: Vec<SerializedGraphicsPipelineCreateInfo>,
     },
 
     // ── Semaphore ──────────────────────────────────────────
     CreateSemaphore {
         device: NetworkHandle,
+        /// Initial value of a timeline semaphore; `None` for a binary one
+        timeline: Option<u64>,
     },
     DestroySemaphore {
         device: NetworkHandle,
         semaphore: NetworkHandle,
     },
+
+    // ── Virtual Swapchain ──────────────────────────────────
+    CreateSwapchain {
+        device: NetworkHandle,
+        create_info: SerializedSwapchainCreateInfo,
+    },
+    DestroySwapchain {
+        device: NetworkHandle,
+        swapchain: NetworkHandle,
+    },
+    AcquireNextImage {
+        device: NetworkHandle,
+        swapchain: NetworkHandle,
+        semaphore: Option<NetworkHandle>,
+        fence: Option<NetworkHandle>,
+    },
+    /// Read back the presented images once `wait_semaphores` are signaled.
+    QueuePresent {
+        queue: NetworkHandle,
+        wait_semaphores: Vec<NetworkHandle>,
+        presents: Vec<SerializedPresent>,
+        /// The client keeps the previous frame of each swapchain, so frames
+        /// may be sent as deltas against it
+        delta: bool,
+    },
+
+    // ── Timeline Semaphores ────────────────────────────────
+    WaitSemaphores {
+        device: NetworkHandle,
+        semaphores: Vec<NetworkHandle>,
+        values: Vec<u64>,
+        /// `VK_SEMAPHORE_WAIT_ANY_BIT`: return when any semaphore reaches its value
+        wait_any: bool,
+        timeout_ns: u64,
+    },
+    SignalSemaphore {
+        device: NetworkHandle,
+        semaphore: NetworkHandle,
+        value: u64,
+    },
+    GetSemaphoreCounterValue {
+        device: NetworkHandle,
+        semaphore: NetworkHandle,
+    },
+
+    // ── Query Pool ─────────────────────────────────────────
+    CreateQueryPool {
+        device: NetworkHandle,
+        query_type: i32,
+        query_count: u32,
+        /// VkQueryPipelineStatisticFlags, for pipeline statistics pools
+        pipeline_statistics: u32,
+    },
+    DestroyQueryPool {
+        device: NetworkHandle,
+        query_pool: NetworkHandle,
+    },
+    GetQueryPoolResults {
+        device: NetworkHandle,
+        query_pool: NetworkHandle,
+        first_query: u32,
+        query_count: u32,
+        data_size: u64,
+        stride: u64,
+        flags: u32,
+    },
+
+    // ── Sampler ────────────────────────────────────────────
+    CreateSampler {
+        device: NetworkHandle,
+        create_info: SerializedSamplerCreateInfo,
+    },
+    DestroySampler {
+        device: NetworkHandle,
+        sampler: NetworkHandle,
+    },
+
+    // ── Pipeline Cache ─────────────────────────────────────
+    CreatePipelineCache {
+        device: NetworkHandle,
+        initial_data: Vec<u8>,
+    },
+    DestroyPipelineCache {
+        device: NetworkHandle,
+        pipeline_cache: NetworkHandle,
+    },
+    GetPipelineCacheData {
+        device: NetworkHandle,
+        pipeline_cache: NetworkHandle,
+    },
+    MergePipelineCaches {
+        device: NetworkHandle,
+        dst_cache: NetworkHandle,
+        src_caches: Vec<NetworkHandle>,
+    },
+
+    // ── Image Format Properties ────────────────────────────
+    GetPhysicalDeviceImageFormatProperties {
+        physical_device: NetworkHandle,
+        format: i32,
+        image_type: i32,
+        tiling: i32,
+        usage: u32,
+        flags: u32,
+        /// Structures chained to VkPhysicalDeviceImageFormatInfo2
+        p_next: Vec<SerializedPNext>,
+    },
+
+    // ── Buffer View ────────────────────────────────────────
+    CreateBufferView {
+        device: NetworkHandle,
+        buffer: NetworkHandle,
+        format: i32,
+        offset: u64,
+        range: u64,
+    },
+    DestroyBufferView {
+        device: NetworkHandle,
+        buffer_view: NetworkHandle,
+    },
+
+    // ── Event ──────────────────────────────────────────────
+    CreateEvent {
+        device: NetworkHandle,
+        flags: u32,
+    },
+    DestroyEvent {
+        device: NetworkHandle,
+        event: NetworkHandle,
+    },
+    GetEventStatus {
+        device: NetworkHandle,
+        event: NetworkHandle,
+    },
+    SetEvent {
+        device: NetworkHandle,
+        event: NetworkHandle,
+    },
+    ResetEvent {
+        device: NetworkHandle,
+        event: NetworkHandle,
+    },
+
+    // ── Synchronization2 ───────────────────────────────────
+    QueueSubmit2 {
+        queue: NetworkHandle,
+        submits: Vec<SerializedSubmitInfo2>,
+        fence: Option<NetworkHandle>,
+    },
+
+    // ── Shader Cache ───────────────────────────────────────
+    /// `CreateShaderModule` with code the server was sent before, named by
+    /// its SHA-256. Answered with `ShaderCodeMissing` if the server doesn't
+    /// have it.
+    CreateShaderModuleByHash {
+        device: NetworkHandle,
+        hash: [u8; 32],
+    },
 }
 
 // ============================================================================
@@ -991,4 +1554,674 @@ pub enum VulkanResponse {
 
     // ── Semaphore ───────────────────────────────────────────
     SemaphoreCreated { handle: NetworkHandle },
+
+    // ── Virtual Swapchain ───────────────────────────────────
+    SwapchainCreated {
+        handle: NetworkHandle,
+        images: Vec<NetworkHandle>,
+    },
+    ImageAcquired { index: u32 },
+    FramesPresented { frames: Vec<PresentedFrame> },
+
+    // ── Timeline Semaphores ─────────────────────────────────
+    SemaphoreWaitResult { result: i32 },
+    SemaphoreCounterValue { value: u64 },
+
+    // ── Query Pool ──────────────────────────────────────────
+    QueryPoolCreated { handle: NetworkHandle },
+    /// `result` is VK_SUCCESS or VK_NOT_READY; `data` is `data_size` bytes
+    /// either way
+    QueryPoolResults { result: i32, data: Vec<u8> },
+
+    // ── Sampler ─────────────────────────────────────────────
+    SamplerCreated { handle: NetworkHandle },
+
+    // ── Pipeline Cache ──────────────────────────────────────
+    PipelineCacheCreated { handle: NetworkHandle },
+    PipelineCacheData { data: Vec<u8> },
+
+    // ── Physical Device Features 2 ─────────────────────────
+    PhysicalDeviceFeatures2 {
+        /// VkPhysicalDeviceFeatures serialized as raw bytes
+        features_raw: Vec<u8>,
+        /// The requested chain, filled in; structures the driver doesn't
+        /// know stay zeroed
+        chain: Vec<SerializedFeatureStruct>,
+    },
+
+    // ── Image Format Properties ────────────────────────────
+    ImageFormatProperties {
+        max_extent: [u32; 3],
+        max_mip_levels: u32,
+        max_array_layers: u32,
+        sample_counts: u32,
+        max_resource_size: u64,
+    },
+
+    // ── Buffer View ─────────────────────────────────────────
+    BufferViewCreated { handle: NetworkHandle },
+
+    // ── Event ───────────────────────────────────────────────
+    EventCreated { handle: NetworkHandle },
+    EventStatus { set: bool },
+
+    // ── Shader Cache ────────────────────────────────────────
+    /// The code of a `CreateShaderModuleByHash` isn't on the server; send it
+    /// in full
+    ShaderCodeMissing,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handle::ResourceType;
+    use crate::messages::{Message, RequestId};
+    use crate::wire;
+
+    fn handle(resource_id: u64, resource_type: ResourceType) -> NetworkHandle {
+        NetworkHandle {
+            server_id: 1,
+            session_id: 2,
+            resource_id,
+            resource_type,
+        }
+    }
+
+    /// Send `msg` through the wire encoding and check it comes out the same.
+    fn round_trip(msg: Message) {
+        let frame = wire::encode_message(&msg, 0).unwrap();
+        let header: &[u8; wire::HEADER_SIZE] = frame[..wire::HEADER_SIZE].try_into().unwrap();
+        let (flags, _, _) = wire::decode_header(header).unwrap();
+        let mut payload = rkyv::util::AlignedVec::<16>::new();
+        payload.extend_from_slice(&frame[wire::HEADER_SIZE..]);
+        let decoded = wire::decode_message(&payload, flags).unwrap();
+        assert_eq!(format!("{:?}", decoded), format!("{:?}", msg));
+    }
+
+    fn commands(commands: Vec<VulkanCommand>) {
+        for (id, command) in commands.into_iter().enumerate() {
+            round_trip(Message::VulkanCommand {
+                request_id: RequestId(id as u64),
+                command,
+                deadline_ms: Some(1000),
+            });
+        }
+    }
+
+    fn responses(responses: Vec<VulkanResponse>) {
+        for (id, response) in responses.into_iter().enumerate() {
+            round_trip(Message::VulkanResponse {
+                request_id: RequestId(id as u64),
+                response,
+            });
+        }
+    }
+
+    /// Record `recorded` into a command buffer, as sent at submit time.
+    fn recorded(recorded: Vec<RecordedCommand>) {
+        commands(vec![VulkanCommand::SubmitRecordedCommands {
+            command_buffer: handle(1, ResourceType::VkCommandBuffer),
+            secondary: None,
+            commands: recorded,
+        }]);
+    }
+
+    fn subresource_layers() -> SerializedImageSubresourceLayers {
+        SerializedImageSubresourceLayers {
+            aspect_mask: 1,
+            mip_level: 2,
+            base_array_layer: 3,
+            layer_count: 4,
+        }
+    }
+
+    fn dependency_info() -> SerializedDependencyInfo {
+        SerializedDependencyInfo {
+            dependency_flags: 1,
+            memory_barriers: vec![SerializedMemoryBarrier2 {
+                src_stage_mask: 1 << 40,
+                src_access_mask: 2,
+                dst_stage_mask: 3,
+                dst_access_mask: 1 << 33,
+            }],
+            buffer_memory_barriers: vec![SerializedBufferMemoryBarrier2 {
+                src_stage_mask: 1,
+                src_access_mask: 2,
+                dst_stage_mask: 3,
+                dst_access_mask: 4,
+                src_queue_family_index: u32::MAX,
+                dst_queue_family_index: 0,
+                buffer: handle(5, ResourceType::VkBuffer),
+                offset: 64,
+                size: u64::MAX,
+            }],
+            image_memory_barriers: vec![SerializedImageMemoryBarrier2 {
+                src_stage_mask: 1,
+                src_access_mask: 2,
+                dst_stage_mask: 3,
+                dst_access_mask: 4,
+                old_layout: 0,
+                new_layout: 1000314000,
+                src_queue_family_index: 0,
+                dst_queue_family_index: 1,
+                image: handle(6, ResourceType::VkImage),
+                subresource_range: SerializedImageSubresourceRange {
+                    aspect_mask: 1,
+                    base_mip_level: 0,
+                    level_count: 3,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                },
+            }],
+        }
+    }
+
+    fn attachment(view: u64) -> SerializedRenderingAttachmentInfo {
+        SerializedRenderingAttachmentInfo {
+            image_view: Some(handle(view, ResourceType::VkImageView)),
+            image_layout: 2,
+            resolve_mode: 1,
+            resolve_image_view: None,
+            resolve_image_layout: 0,
+            load_op: 1,
+            store_op: 0,
+            clear_value: SerializedClearValue { data: [0x3f; 16] },
+        }
+    }
+
+    #[test]
+    fn test_timeline_semaphores_round_trip() {
+        let device = handle(1, ResourceType::VkDevice);
+        let semaphore = handle(2, ResourceType::VkSemaphore);
+        commands(vec![
+            VulkanCommand::CreateSemaphore { device, timeline: Some(7) },
+            VulkanCommand::CreateSemaphore { device, timeline: None },
+            VulkanCommand::WaitSemaphores {
+                device,
+                semaphores: vec![semaphore, handle(3, ResourceType::VkSemaphore)],
+                values: vec![1, u64::MAX],
+                wait_any: true,
+                timeout_ns: 5_000_000,
+            },
+            VulkanCommand::SignalSemaphore { device, semaphore, value: 9 },
+            VulkanCommand::GetSemaphoreCounterValue { device, semaphore },
+            VulkanCommand::QueueSubmit {
+                queue: handle(4, ResourceType::VkQueue),
+                submits: vec![SerializedSubmitInfo {
+                    wait_semaphores: vec![semaphore],
+                    wait_dst_stage_masks: vec![1],
+                    command_buffers: vec![handle(5, ResourceType::VkCommandBuffer)],
+                    signal_semaphores: vec![semaphore],
+                    wait_semaphore_values: vec![3],
+                    signal_semaphore_values: vec![4],
+                    p_next: vec![SerializedPNext::Raw { s_type: 1000145000, data: vec![1, 0, 0, 0] }],
+                }],
+                fence: None,
+            },
+        ]);
+        responses(vec![
+            VulkanResponse::SemaphoreWaitResult { result: 2 },
+            VulkanResponse::SemaphoreCounterValue { value: u64::MAX - 1 },
+        ]);
+    }
+
+    #[test]
+    fn test_query_pools_round_trip() {
+        let device = handle(1, ResourceType::VkDevice);
+        let query_pool = handle(2, ResourceType::VkQueryPool);
+        commands(vec![
+            VulkanCommand::CreateQueryPool { device, query_type: 2, query_count: 16, pipeline_statistics: 0x7ff },
+            VulkanCommand::DestroyQueryPool { device, query_pool },
+            VulkanCommand::GetQueryPoolResults {
+                device,
+                query_pool,
+                first_query: 4,
+                query_count: 8,
+                data_size: 128,
+                stride: 16,
+                flags: 3,
+            },
+        ]);
+        recorded(vec![
+            RecordedCommand::ResetQueryPool { query_pool, first_query: 0, query_count: 16 },
+            RecordedCommand::BeginQuery { query_pool, query: 1, flags: 1 },
+            RecordedCommand::EndQuery { query_pool, query: 1 },
+            RecordedCommand::WriteTimestamp { pipeline_stage: 0x2000, query_pool, query: 2 },
+            RecordedCommand::CopyQueryPoolResults {
+                query_pool,
+                first_query: 0,
+                query_count: 3,
+                dst_buffer: handle(3, ResourceType::VkBuffer),
+                dst_offset: 256,
+                stride: 8,
+                flags: 1,
+            },
+        ]);
+        responses(vec![
+            VulkanResponse::QueryPoolCreated { handle: query_pool },
+            VulkanResponse::QueryPoolResults { result: 1, data: (0..128).collect() },
+        ]);
+    }
+
+    #[test]
+    fn test_samplers_and_image_descriptors_round_trip() {
+        let device = handle(1, ResourceType::VkDevice);
+        let sampler = handle(2, ResourceType::VkSampler);
+        commands(vec![
+            VulkanCommand::CreateSampler {
+                device,
+                create_info: SerializedSamplerCreateInfo {
+                    flags: 0,
+                    mag_filter: 1,
+                    min_filter: 1,
+                    mipmap_mode: 1,
+                    address_mode_u: 0,
+                    address_mode_v: 2,
+                    address_mode_w: 3,
+                    mip_lod_bias: -0.5,
+                    anisotropy_enable: true,
+                    max_anisotropy: 16.0,
+                    compare_enable: false,
+                    compare_op: 7,
+                    min_lod: 0.0,
+                    max_lod: 1000.0,
+                    border_color: 3,
+                    unnormalized_coordinates: false,
+                },
+            },
+            VulkanCommand::DestroySampler { device, sampler },
+            VulkanCommand::CreateDescriptorSetLayout {
+                device,
+                bindings: vec![SerializedDescriptorSetLayoutBinding {
+                    binding: 0,
+                    descriptor_type: 1,
+                    descriptor_count: 2,
+                    stage_flags: 0x10,
+                    immutable_samplers: vec![sampler, sampler],
+                }],
+            },
+            VulkanCommand::UpdateDescriptorSets {
+                device,
+                writes: vec![SerializedWriteDescriptorSet {
+                    dst_set: handle(3, ResourceType::VkDescriptorSet),
+                    dst_binding: 0,
+                    dst_array_element: 1,
+                    descriptor_type: 1,
+                    buffer_infos: Vec::new(),
+                    image_infos: vec![
+                        SerializedDescriptorImageInfo {
+                            sampler: Some(sampler),
+                            image_view: Some(handle(4, ResourceType::VkImageView)),
+                            image_layout: 5,
+                        },
+                        SerializedDescriptorImageInfo { sampler: None, image_view: None, image_layout: 0 },
+                    ],
+                    texel_buffer_views: Vec::new(),
+                }],
+            },
+        ]);
+        responses(vec![VulkanResponse::SamplerCreated { handle: sampler }]);
+    }
+
+    #[test]
+    fn test_pipeline_caches_round_trip() {
+        let device = handle(1, ResourceType::VkDevice);
+        let pipeline_cache = handle(2, ResourceType::VkPipelineCache);
+        commands(vec![
+            VulkanCommand::CreatePipelineCache { device, initial_data: vec![0xca; 300] },
+            VulkanCommand::DestroyPipelineCache { device, pipeline_cache },
+            VulkanCommand::GetPipelineCacheData { device, pipeline_cache },
+            VulkanCommand::MergePipelineCaches {
+                device,
+                dst_cache: pipeline_cache,
+                src_caches: vec![handle(3, ResourceType::VkPipelineCache), handle(4, ResourceType::VkPipelineCache)],
+            },
+            VulkanCommand::CreateComputePipelines {
+                device,
+                pipeline_cache: Some(pipeline_cache),
+                create_infos: Vec::new(),
+            },
+            VulkanCommand::CreateGraphicsPipelines { device, pipeline_cache: None, create_infos: Vec::new() },
+        ]);
+        responses(vec![
+            VulkanResponse::PipelineCacheCreated { handle: pipeline_cache },
+            VulkanResponse::PipelineCacheData { data: Vec::new() },
+            VulkanResponse::PipelineCacheData { data: vec![1; 4096] },
+        ]);
+    }
+
+    #[test]
+    fn test_secondary_command_buffers_round_trip() {
+        commands(vec![VulkanCommand::SubmitRecordedCommands {
+            command_buffer: handle(1, ResourceType::VkCommandBuffer),
+            secondary: Some(SerializedSecondaryBeginInfo {
+                usage_flags: 2,
+                render_pass: Some(handle(2, ResourceType::VkRenderPass)),
+                subpass: 1,
+                framebuffer: None,
+                occlusion_query_enable: true,
+                query_flags: 1,
+                pipeline_statistics: 0,
+            }),
+            commands: vec![RecordedCommand::EndRendering],
+        }]);
+        recorded(vec![RecordedCommand::ExecuteCommands {
+            command_buffers: vec![handle(3, ResourceType::VkCommandBuffer), handle(4, ResourceType::VkCommandBuffer)],
+        }]);
+    }
+
+    #[test]
+    fn test_indirect_commands_round_trip() {
+        let buffer = handle(1, ResourceType::VkBuffer);
+        let count_buffer = handle(2, ResourceType::VkBuffer);
+        recorded(vec![
+            RecordedCommand::DrawIndirect { buffer, offset: 16, draw_count: 3, stride: 16 },
+            RecordedCommand::DrawIndexedIndirect { buffer, offset: 0, draw_count: 1, stride: 20 },
+            RecordedCommand::DispatchIndirect { buffer, offset: 12 },
+            RecordedCommand::DrawIndirectCount {
+                buffer,
+                offset: 0,
+                count_buffer,
+                count_buffer_offset: 4,
+                max_draw_count: 64,
+                stride: 16,
+            },
+            RecordedCommand::DrawIndexedIndirectCount {
+                buffer,
+                offset: 32,
+                count_buffer,
+                count_buffer_offset: 8,
+                max_draw_count: 128,
+                stride: 20,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_image_copies_round_trip() {
+        let src_image = handle(1, ResourceType::VkImage);
+        let dst_image = handle(2, ResourceType::VkImage);
+        let copy = SerializedImageCopy {
+            src_subresource: subresource_layers(),
+            src_offset: [-1, 2, 0],
+            dst_subresource: subresource_layers(),
+            dst_offset: [4, 5, 6],
+            extent: [64, 32, 1],
+        };
+        recorded(vec![
+            RecordedCommand::CopyImage {
+                src_image,
+                src_image_layout: 6,
+                dst_image,
+                dst_image_layout: 7,
+                regions: vec![copy.clone(), copy.clone()],
+            },
+            RecordedCommand::BlitImage {
+                src_image,
+                src_image_layout: 6,
+                dst_image,
+                dst_image_layout: 7,
+                regions: vec![SerializedImageBlit {
+                    src_subresource: subresource_layers(),
+                    src_offsets: [[0, 0, 0], [64, 64, 1]],
+                    dst_subresource: subresource_layers(),
+                    dst_offsets: [[0, 0, 0], [32, -32, 1]],
+                }],
+                filter: 1,
+            },
+            RecordedCommand::ResolveImage {
+                src_image,
+                src_image_layout: 6,
+                dst_image,
+                dst_image_layout: 7,
+                regions: vec![copy],
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_push_constants_round_trip() {
+        recorded(vec![RecordedCommand::PushConstants {
+            layout: handle(1, ResourceType::VkPipelineLayout),
+            stage_flags: 0x11,
+            offset: 16,
+            data: (0..128).collect(),
+        }]);
+    }
+
+    #[test]
+    fn test_feature_chains_round_trip() {
+        let physical_device = handle(1, ResourceType::VkPhysicalDevice);
+        let chain = vec![
+            SerializedFeatureStruct { s_type: 1000161000, data: vec![1; 80] },
+            SerializedFeatureStruct { s_type: 49, data: Vec::new() },
+        ];
+        commands(vec![
+            VulkanCommand::GetPhysicalDeviceFeatures2 { physical_device, chain: chain.clone() },
+            VulkanCommand::CreateDevice {
+                physical_device,
+                queue_create_infos: Vec::new(),
+                enabled_extensions: vec!["VK_KHR_timeline_semaphore".to_string()],
+                enabled_features: Some(vec![0; 220]),
+                p_next: vec![SerializedPNext::Raw { s_type: 1000161000, data: vec![1; 80] }],
+            },
+        ]);
+        responses(vec![VulkanResponse::PhysicalDeviceFeatures2 { features_raw: vec![1; 220], chain }]);
+    }
+
+    #[test]
+    fn test_p_next_chains_round_trip() {
+        let device = handle(1, ResourceType::VkDevice);
+        let p_next = vec![
+            SerializedPNext::Raw { s_type: 1000072000, data: vec![2, 0, 0, 0] },
+            SerializedPNext::ImageFormatList { view_formats: vec![37, 43] },
+            SerializedPNext::ImageFormatList { view_formats: Vec::new() },
+        ];
+        commands(vec![
+            VulkanCommand::CreateBuffer {
+                device,
+                size: 1 << 20,
+                usage: 0x82,
+                sharing_mode: 0,
+                queue_family_indices: Vec::new(),
+                p_next: p_next.clone(),
+            },
+            VulkanCommand::CreateImage {
+                device,
+                create_info: SerializedImageCreateInfo {
+                    flags: 8,
+                    image_type: 1,
+                    format: 37,
+                    extent: [256, 256, 1],
+                    mip_levels: 9,
+                    array_layers: 1,
+                    samples: 1,
+                    tiling: 0,
+                    usage: 0x17,
+                    sharing_mode: 1,
+                    queue_family_indices: vec![0, 2],
+                    initial_layout: 0,
+                    p_next,
+                },
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_image_format_properties_round_trip() {
+        commands(vec![VulkanCommand::GetPhysicalDeviceImageFormatProperties {
+            physical_device: handle(1, ResourceType::VkPhysicalDevice),
+            format: 44,
+            image_type: 1,
+            tiling: 1,
+            usage: 0x6,
+            flags: 0x10,
+            p_next: vec![SerializedPNext::Raw { s_type: 1000071000, data: vec![0x80, 0, 0, 0] }],
+        }]);
+        responses(vec![VulkanResponse::ImageFormatProperties {
+            max_extent: [16384, 16384, 1],
+            max_mip_levels: 15,
+            max_array_layers: 2048,
+            sample_counts: 0x7f,
+            max_resource_size: 1 << 40,
+        }]);
+    }
+
+    #[test]
+    fn test_buffer_views_round_trip() {
+        let device = handle(1, ResourceType::VkDevice);
+        let buffer_view = handle(2, ResourceType::VkBufferView);
+        commands(vec![
+            VulkanCommand::CreateBufferView {
+                device,
+                buffer: handle(3, ResourceType::VkBuffer),
+                format: 100,
+                offset: 256,
+                range: u64::MAX,
+            },
+            VulkanCommand::DestroyBufferView { device, buffer_view },
+            VulkanCommand::UpdateDescriptorSets {
+                device,
+                writes: vec![SerializedWriteDescriptorSet {
+                    dst_set: handle(4, ResourceType::VkDescriptorSet),
+                    dst_binding: 2,
+                    dst_array_element: 0,
+                    descriptor_type: 4,
+                    buffer_infos: Vec::new(),
+                    image_infos: Vec::new(),
+                    texel_buffer_views: vec![buffer_view, buffer_view],
+                }],
+            },
+        ]);
+        responses(vec![VulkanResponse::BufferViewCreated { handle: buffer_view }]);
+    }
+
+    #[test]
+    fn test_events_round_trip() {
+        let device = handle(1, ResourceType::VkDevice);
+        let event = handle(2, ResourceType::VkEvent);
+        commands(vec![
+            VulkanCommand::CreateEvent { device, flags: 1 },
+            VulkanCommand::DestroyEvent { device, event },
+            VulkanCommand::GetEventStatus { device, event },
+            VulkanCommand::SetEvent { device, event },
+            VulkanCommand::ResetEvent { device, event },
+        ]);
+        recorded(vec![
+            RecordedCommand::SetEvent { event, stage_mask: 0x800 },
+            RecordedCommand::ResetEvent { event, stage_mask: 1 },
+            RecordedCommand::WaitEvents {
+                events: vec![event, handle(3, ResourceType::VkEvent)],
+                src_stage_mask: 0x800,
+                dst_stage_mask: 0x1,
+                memory_barriers: vec![SerializedMemoryBarrier { src_access_mask: 0x40, dst_access_mask: 0x20 }],
+                buffer_memory_barriers: Vec::new(),
+                image_memory_barriers: Vec::new(),
+            },
+        ]);
+        responses(vec![
+            VulkanResponse::EventCreated { handle: event },
+            VulkanResponse::EventStatus { set: true },
+            VulkanResponse::EventStatus { set: false },
+        ]);
+    }
+
+    #[test]
+    fn test_synchronization2_round_trip() {
+        let event = handle(1, ResourceType::VkEvent);
+        let semaphore = handle(2, ResourceType::VkSemaphore);
+        commands(vec![VulkanCommand::QueueSubmit2 {
+            queue: handle(3, ResourceType::VkQueue),
+            submits: vec![SerializedSubmitInfo2 {
+                flags: 1,
+                wait_semaphore_infos: vec![SerializedSemaphoreSubmitInfo {
+                    semaphore,
+                    value: 5,
+                    stage_mask: 1 << 35,
+                    device_index: 0,
+                }],
+                command_buffers: vec![handle(4, ResourceType::VkCommandBuffer)],
+                signal_semaphore_infos: Vec::new(),
+            }],
+            fence: Some(handle(5, ResourceType::VkFence)),
+        }]);
+        recorded(vec![
+            RecordedCommand::PipelineBarrier2 { dependency_info: dependency_info() },
+            RecordedCommand::SetEvent2 { event, dependency_info: dependency_info() },
+            RecordedCommand::ResetEvent2 { event, stage_mask: 1 << 38 },
+            RecordedCommand::WaitEvents2 {
+                events: vec![event, event],
+                dependency_infos: vec![dependency_info(), dependency_info()],
+            },
+            RecordedCommand::WriteTimestamp2 {
+                stage: 1 << 32,
+                query_pool: handle(6, ResourceType::VkQueryPool),
+                query: 3,
+            },
+        ]);
+    }
+
+    #[test]
+    fn test_dynamic_rendering_round_trip() {
+        commands(vec![VulkanCommand::CreateGraphicsPipelines {
+            device: handle(1, ResourceType::VkDevice),
+            pipeline_cache: None,
+            create_infos: vec![SerializedGraphicsPipelineCreateInfo {
+                flags: 0,
+                stages: vec![SerializedPipelineShaderStageCreateInfo {
+                    module: handle(2, ResourceType::VkShaderModule),
+                    entry_point: "main".to_string(),
+                    stage: 0x1,
+                }],
+                vertex_input_state: SerializedPipelineVertexInputStateCreateInfo {
+                    vertex_binding_descriptions: Vec::new(),
+                    vertex_attribute_descriptions: Vec::new(),
+                },
+                input_assembly_state: SerializedPipelineInputAssemblyStateCreateInfo {
+                    topology: 3,
+                    primitive_restart_enable: false,
+                },
+                viewport_state: None,
+                rasterization_state: SerializedPipelineRasterizationStateCreateInfo {
+                    depth_clamp_enable: false,
+                    rasterizer_discard_enable: false,
+                    polygon_mode: 0,
+                    cull_mode: 2,
+                    front_face: 0,
+                    depth_bias_enable: false,
+                    depth_bias_constant_factor: 0.0,
+                    depth_bias_clamp: 0.0,
+                    depth_bias_slope_factor: 0.0,
+                    line_width: 1.0,
+                },
+                multisample_state: None,
+                depth_stencil_state: None,
+                color_blend_state: None,
+                dynamic_state: None,
+                layout: handle(3, ResourceType::VkPipelineLayout),
+                render_pass: None,
+                subpass: 0,
+                rendering: Some(SerializedPipelineRenderingCreateInfo {
+                    view_mask: 0,
+                    color_attachment_formats: vec![44, 37],
+                    depth_attachment_format: 126,
+                    stencil_attachment_format: 0,
+                }),
+            }],
+        }]);
+        recorded(vec![
+            RecordedCommand::BeginRendering {
+                rendering_info: SerializedRenderingInfo {
+                    flags: 0,
+                    render_area: SerializedRect2D { offset: [0, -8], extent: [1920, 1080] },
+                    layer_count: 1,
+                    view_mask: 0,
+                    color_attachments: vec![attachment(1), attachment(2)],
+                    depth_attachment: Some(attachment(3)),
+                    stencil_attachment: None,
+                },
+            },
+            RecordedCommand::EndRendering,
+        ]);
+    }
 }
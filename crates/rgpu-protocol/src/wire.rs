@@ -1,6 +1,10 @@
 use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::time::Instant;
 
-use crate::messages::Message;
+use serde::{Deserialize, Serialize};
+
+use crate::messages::{CompressionSummary, Message, RequestId};
 
 /// Wire protocol magic bytes: "RG"
 pub const MAGIC: [u8; 2] = [0x52, 0x47];
@@ -8,12 +12,16 @@ pub const MAGIC: [u8; 2] = [0x52, 0x47];
 /// Maximum frame payload size: 256 MB
 pub const MAX_FRAME_SIZE: u32 = 256 * 1024 * 1024;
 
+/// Largest size a frame payload may decompress to, unless the receiver sets
+/// a lower limit (see [`decode_message_limited`]).
+pub const MAX_MESSAGE_SIZE: usize = 1024 * 1024 * 1024;
+
 /// Frame header size in bytes: magic(2) + flags(1) + stream_id(4) + length(4) = 11
 pub const HEADER_SIZE: usize = 11;
 
-/// Minimum payload size to attempt LZ4 compression (bytes).
+/// Default minimum payload size to attempt compression (bytes).
 /// Payloads smaller than this are sent uncompressed to avoid overhead.
-const COMPRESSION_THRESHOLD: usize = 512;
+pub const COMPRESSION_THRESHOLD: usize = 512;
 
 bitflags::bitflags! {
     /// Frame flags byte.
@@ -24,30 +32,253 @@ bitflags::bitflags! {
         const RESPONSE    = 0b0000_0100;
         const ERROR       = 0b0000_1000;
         const BATCH       = 0b0001_0000;
+        /// With `COMPRESSED`: the payload is zstd rather than LZ4
+        const ZSTD        = 0b0010_0000;
+        /// The payload is in the receiver's ring, in shared memory on local
+        /// IPC connections or written there by RDMA; the frame carries only
+        /// its location (see [`shared_frame`])
+        const SHARED      = 0b0100_0000;
+    }
+}
+
+/// Payloads at least this large go through shared memory on local IPC
+/// connections that have set it up, and through RDMA on server connections
+/// that have.
+pub const SHARED_MEMORY_THRESHOLD: usize = 64 * 1024;
+
+/// Payload size of a [`FrameFlags::SHARED`] frame: the position of the real
+/// payload in the shared-memory ring and its length, as two LE u64s.
+pub const SHARED_LOCATION_SIZE: usize = 16;
+
+/// How frame payloads are compressed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Codec {
+    /// Fast, with a modest ratio
+    #[default]
+    Lz4,
+    /// Slower, with a better ratio that grows with the level
+    Zstd,
+    None,
+}
+
+/// What a connection does with the frames it sends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionSettings {
+    pub codec: Codec,
+    /// zstd level, 1 (fastest) to 22 (smallest); 0 is zstd's default of 3
+    pub level: i32,
+    /// Payloads up to this many bytes are sent as they are
+    pub threshold: usize,
+}
+
+impl Default for CompressionSettings {
+    fn default() -> Self {
+        Self {
+            codec: Codec::Lz4,
+            level: 0,
+            threshold: COMPRESSION_THRESHOLD,
+        }
+    }
+}
+
+/// Compression ratio below which [`CompressionStats::auto_tune`] turns
+/// compression off.
+pub const AUTO_DISABLE_RATIO: f64 = 1.05;
+
+/// Payload bytes to see before judging the ratio.
+const AUTO_DISABLE_MIN_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Compression accounting for one session's outgoing frames, fed by
+/// [`encode_message_tracked`].
+#[derive(Debug, Default)]
+pub struct CompressionStats {
+    /// Bytes of payloads large enough to try compressing
+    bytes_in: AtomicU64,
+    /// Bytes sent for those payloads
+    bytes_out: AtomicU64,
+    cpu_nanos: AtomicU64,
+    disabled: AtomicBool,
+    /// [`Codec`] in use, as its index
+    codec: AtomicU8,
+    level: AtomicI32,
+    /// Threshold plus one, so that 0 means not configured and
+    /// [`COMPRESSION_THRESHOLD`]
+    threshold: AtomicUsize,
+}
+
+impl CompressionStats {
+    pub fn is_enabled(&self) -> bool {
+        !self.disabled.load(Ordering::Relaxed)
+    }
+
+    /// Compress with `settings` from now on.
+    pub fn configure(&self, settings: CompressionSettings) {
+        let codec = match settings.codec {
+            Codec::Lz4 => 0,
+            Codec::Zstd => 1,
+            Codec::None => 2,
+        };
+        self.codec.store(codec, Ordering::Relaxed);
+        self.level.store(settings.level, Ordering::Relaxed);
+        self.threshold.store(settings.threshold.saturating_add(1), Ordering::Relaxed);
+    }
+
+    pub fn settings(&self) -> CompressionSettings {
+        let codec = match self.codec.load(Ordering::Relaxed) {
+            0 => Codec::Lz4,
+            1 => Codec::Zstd,
+            _ => Codec::None,
+        };
+        let threshold = match self.threshold.load(Ordering::Relaxed) {
+            0 => COMPRESSION_THRESHOLD,
+            threshold => threshold - 1,
+        };
+        CompressionSettings {
+            codec,
+            level: self.level.load(Ordering::Relaxed),
+            threshold,
+        }
+    }
+
+    pub fn summary(&self) -> CompressionSummary {
+        CompressionSummary {
+            bytes_in: self.bytes_in.load(Ordering::Relaxed),
+            bytes_out: self.bytes_out.load(Ordering::Relaxed),
+            cpu_micros: self.cpu_nanos.load(Ordering::Relaxed) / 1000,
+            disabled: !self.is_enabled(),
+        }
+    }
+
+    /// Turn compression off once enough data has been seen to tell it isn't
+    /// reaching [`AUTO_DISABLE_RATIO`]. Returns true if this call turned it off.
+    pub fn auto_tune(&self) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+        let summary = self.summary();
+        if summary.bytes_in < AUTO_DISABLE_MIN_BYTES || summary.ratio() >= AUTO_DISABLE_RATIO {
+            return false;
+        }
+        !self.disabled.swap(true, Ordering::Relaxed)
+    }
+
+    fn record(&self, bytes_in: usize, bytes_out: usize, started: Instant) {
+        self.bytes_in.fetch_add(bytes_in as u64, Ordering::Relaxed);
+        self.bytes_out.fetch_add(bytes_out as u64, Ordering::Relaxed);
+        self.cpu_nanos
+            .fetch_add(started.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Frame `stream_id` for `msg`: the low 32 bits of its request id, or 0 if
+/// it has none. A receiver that can't decode the payload still knows which
+/// request to answer with `Message::Unsupported`.
+pub fn request_tag(msg: &Message) -> u32 {
+    msg.request_id().map_or(0, |id| id.0 as u32)
+}
+
+/// Stand-in for a frame whose payload didn't decode, so the reader can pass
+/// it on and the request it carried is answered instead of lost. `tag` is
+/// the frame's `stream_id`.
+pub fn undecodable(tag: u32, error: &WireError) -> Message {
+    Message::Unsupported {
+        request_id: RequestId(tag as u64),
+        reason: format!("can't decode message: {}", error),
     }
 }
 
-/// Encode a Message into bytes (header + payload), with optional LZ4 compression.
+/// Encode a Message into bytes (header + payload), with LZ4 compression of
+/// payloads over [`COMPRESSION_THRESHOLD`].
 pub fn encode_message(msg: &Message, stream_id: u32) -> Result<Vec<u8>, WireError> {
+    encode_frame(msg, stream_id, None)
+}
+
+/// Like [`encode_message`], but never compressed, for local hops where
+/// compressing costs more than copying.
+pub fn encode_message_uncompressed(msg: &Message, stream_id: u32) -> Result<Vec<u8>, WireError> {
+    let stats = CompressionStats::default();
+    stats.configure(CompressionSettings {
+        codec: Codec::None,
+        ..Default::default()
+    });
+    encode_frame(msg, stream_id, Some(&stats))
+}
+
+/// The frame that stands in for `frame` once its payload has been copied
+/// into shared memory at `position`.
+pub fn shared_frame(frame: &[u8], position: u64) -> Vec<u8> {
+    let payload_len = (frame.len() - HEADER_SIZE) as u64;
+    let mut shared = Vec::with_capacity(HEADER_SIZE + SHARED_LOCATION_SIZE);
+    shared.extend_from_slice(&frame[..2]);
+    shared.push(frame[2] | FrameFlags::SHARED.bits());
+    shared.extend_from_slice(&frame[3..7]);
+    shared.extend_from_slice(&(SHARED_LOCATION_SIZE as u32).to_le_bytes());
+    shared.extend_from_slice(&position.to_le_bytes());
+    shared.extend_from_slice(&payload_len.to_le_bytes());
+    shared
+}
+
+/// Position and length of the payload a [`FrameFlags::SHARED`] frame points to.
+pub fn shared_location(payload: &[u8]) -> Result<(u64, usize), WireError> {
+    let location: [u8; SHARED_LOCATION_SIZE] = payload
+        .try_into()
+        .map_err(|_| WireError::Serialization(format!("bad shared frame of {} bytes", payload.len())))?;
+    let position = u64::from_le_bytes(location[..8].try_into().expect("8 bytes"));
+    let len = u64::from_le_bytes(location[8..].try_into().expect("8 bytes"));
+    if len > MAX_FRAME_SIZE as u64 {
+        return Err(WireError::FrameTooLarge(len as u32));
+    }
+    Ok((position, len as usize))
+}
+
+/// Like [`encode_message`], but compresses with the settings of `stats`,
+/// records compression there, and sends uncompressed if `stats` has
+/// compression turned off.
+pub fn encode_message_tracked(
+    msg: &Message,
+    stream_id: u32,
+    stats: &CompressionStats,
+) -> Result<Vec<u8>, WireError> {
+    encode_frame(msg, stream_id, Some(stats))
+}
+
+fn encode_frame(
+    msg: &Message,
+    stream_id: u32,
+    stats: Option<&CompressionStats>,
+) -> Result<Vec<u8>, WireError> {
     let payload = rkyv::to_bytes::<rkyv::rancor::Error>(msg)
         .map_err(|e| WireError::Serialization(e.to_string()))?;
 
-    // Attempt LZ4 compression for payloads above threshold
-    let (final_payload, compression_flag) = if payload.len() > COMPRESSION_THRESHOLD {
-        let compressed = lz4_flex::compress_prepend_size(&payload);
-        if compressed.len() < payload.len() {
-            (Cow::Owned(compressed), FrameFlags::COMPRESSED)
+    // Attempt compression for payloads above threshold
+    let settings = stats.map_or_else(CompressionSettings::default, |s| s.settings());
+    let compress = settings.codec != Codec::None
+        && payload.len() > settings.threshold
+        && stats.is_none_or(|s| s.is_enabled());
+    let (final_payload, compression_flag) = if compress {
+        let started = Instant::now();
+        let (compressed, flags) = match settings.codec {
+            Codec::Zstd => (zstd_compress(&payload, settings.level)?, FrameFlags::COMPRESSED | FrameFlags::ZSTD),
+            _ => (lz4_flex::compress_prepend_size(&payload), FrameFlags::COMPRESSED),
+        };
+        let result = if compressed.len() < payload.len() {
+            (Cow::Owned(compressed), flags)
         } else {
             // Compression didn't help, send uncompressed
             (Cow::Borrowed(payload.as_slice()), FrameFlags::empty())
+        };
+        if let Some(stats) = stats {
+            stats.record(payload.len(), result.0.len(), started);
         }
+        result
     } else {
         (Cow::Borrowed(payload.as_slice()), FrameFlags::empty())
     };
 
     let msg_flags = match msg {
         Message::Error(_) => FrameFlags::ERROR,
-        Message::CudaBatch(_) => FrameFlags::BATCH,
+        Message::CudaBatch(_) | Message::CudaPipelined { .. } => FrameFlags::BATCH,
         Message::CudaResponse { .. }
         | Message::VulkanResponse { .. }
         | Message::AuthResult { .. }
@@ -69,6 +300,26 @@ pub fn encode_message(msg: &Message, stream_id: u32) -> Result<Vec<u8>, WireErro
     Ok(frame)
 }
 
+/// zstd-compress `payload`, prefixed with its size like LZ4 frames.
+fn zstd_compress(payload: &[u8], level: i32) -> Result<Vec<u8>, WireError> {
+    let mut compressed = (payload.len() as u32).to_le_bytes().to_vec();
+    compressed.extend(zstd::bulk::compress(payload, level)?);
+    Ok(compressed)
+}
+
+/// The decompressed size a compressed payload claims in its prefix.
+fn decompressed_size(payload: &[u8]) -> Result<usize, WireError> {
+    let (size, _) = payload
+        .split_first_chunk::<4>()
+        .ok_or_else(|| WireError::DecompressionError("missing size prefix".to_string()))?;
+    Ok(u32::from_le_bytes(*size) as usize)
+}
+
+fn zstd_decompress(payload: &[u8]) -> Result<Vec<u8>, WireError> {
+    zstd::bulk::decompress(&payload[4..], decompressed_size(payload)?)
+        .map_err(|e| WireError::DecompressionError(e.to_string()))
+}
+
 /// Decode a frame header. Returns (flags, stream_id, payload_length).
 pub fn decode_header(header: &[u8; HEADER_SIZE]) -> Result<(FrameFlags, u32, u32), WireError> {
     if header[0] != MAGIC[0] || header[1] != MAGIC[1] {
@@ -88,7 +339,24 @@ pub fn decode_header(header: &[u8; HEADER_SIZE]) -> Result<(FrameFlags, u32, u32
 
 /// Decode a message from payload bytes, decompressing if the COMPRESSED flag is set.
 pub fn decode_message(payload: &[u8], flags: FrameFlags) -> Result<Message, WireError> {
-    let data: Cow<'_, [u8]> = if flags.contains(FrameFlags::COMPRESSED) {
+    decode_message_limited(payload, flags, MAX_MESSAGE_SIZE)
+}
+
+/// Like [`decode_message`], but refuses payloads larger than `max_size`, or
+/// claiming to decompress to more, before allocating anything for them.
+pub fn decode_message_limited(payload: &[u8], flags: FrameFlags, max_size: usize) -> Result<Message, WireError> {
+    if payload.len() > max_size {
+        return Err(WireError::FrameTooLarge(payload.len() as u32));
+    }
+    if flags.contains(FrameFlags::COMPRESSED) {
+        let size = decompressed_size(payload)?;
+        if size > max_size {
+            return Err(WireError::FrameTooLarge(size as u32));
+        }
+    }
+    let data: Cow<'_, [u8]> = if flags.contains(FrameFlags::COMPRESSED | FrameFlags::ZSTD) {
+        Cow::Owned(zstd_decompress(payload)?)
+    } else if flags.contains(FrameFlags::COMPRESSED) {
         Cow::Owned(
             lz4_flex::decompress_size_prepended(payload)
                 .map_err(|e| WireError::DecompressionError(e.to_string()))?,
@@ -0,0 +1,188 @@
+//! NUMA-aware placement of the threads that execute GPU commands.
+//!
+//! With affinity configured, each session gets a worker thread of its own.
+//! Once the session has created a CUDA context or Vulkan device, the thread
+//! is pinned to the CPUs local to that GPU, so the driver's work and the
+//! staging buffers it fills (which the kernel places on the node of the
+//! thread that first touches them) stay on the GPU's socket. Pinning is only
+//! supported on Linux; elsewhere sessions still get their worker thread but
+//! it is left unpinned.
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+
+use tracing::{debug, info, warn};
+
+use rgpu_core::config::ServerConfig;
+use rgpu_protocol::gpu_info::GpuInfo;
+
+use crate::session::Session;
+use crate::vram::DeviceUuid;
+
+/// CPUs the workers serving each GPU are pinned to.
+#[derive(Default)]
+pub struct CpuAffinity {
+    cpus: HashMap<DeviceUuid, Vec<usize>>,
+}
+
+impl CpuAffinity {
+    /// Resolve the configured placement for each GPU. A `device_affinity`
+    /// entry wins over the GPU's reported NUMA node, which is only used when
+    /// `numa_affinity` is on.
+    pub fn from_config(config: &ServerConfig, gpus: &[(GpuInfo, DeviceUuid)]) -> Self {
+        let mut cpus = HashMap::new();
+        for (gpu, uuid) in gpus {
+            let entry = config
+                .device_affinity
+                .iter()
+                .find(|a| a.device == gpu.server_device_index);
+            let set = match entry {
+                Some(entry) => match (&entry.cpus, entry.numa_node) {
+                    (Some(list), _) => parse_cpu_list(list).or_else(|| {
+                        warn!("GPU {}: invalid CPU list {:?}", gpu.server_device_index, list);
+                        None
+                    }),
+                    (None, Some(node)) => node_cpus(node),
+                    (None, None) => None,
+                },
+                None if config.numa_affinity => gpu.topology.numa_node.and_then(node_cpus),
+                None => None,
+            };
+            if let Some(set) = set.filter(|s| !s.is_empty()) {
+                info!("GPU {}: workers pinned to CPUs {}", gpu.server_device_index, format_cpu_list(&set));
+                cpus.insert(*uuid, set);
+            }
+        }
+        if !cpus.is_empty() && !cfg!(target_os = "linux") {
+            warn!("CPU affinity is only supported on Linux; workers will not be pinned");
+        }
+        Self { cpus }
+    }
+
+    /// Whether any GPU has a placement, i.e. sessions need worker threads.
+    pub fn is_enabled(&self) -> bool {
+        !self.cpus.is_empty()
+    }
+
+    fn cpus_for(&self, device: &DeviceUuid) -> Option<&[usize]> {
+        self.cpus.get(device).map(Vec::as_slice)
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A session's dedicated thread for GPU commands. Commands run one at a time
+/// in arrival order, as they would inline, and always on the same thread, so
+/// a CUDA context made current by one command is still current for the next.
+pub struct SessionWorker {
+    tx: mpsc::Sender<Job>,
+}
+
+impl SessionWorker {
+    /// Start the worker thread. It follows `session` to whichever GPU it
+    /// works on, re-pinning itself between commands.
+    pub fn spawn(session: std::sync::Arc<Session>, affinity: std::sync::Arc<CpuAffinity>) -> std::io::Result<Self> {
+        let (tx, rx) = mpsc::channel::<Job>();
+        let session_id = session.session_id;
+        std::thread::Builder::new()
+            .name(format!("rgpu-session-{}", session_id))
+            .spawn(move || {
+                let mut pinned: Option<DeviceUuid> = None;
+                for job in rx {
+                    job();
+                    let device = session.device();
+                    if device == pinned {
+                        continue;
+                    }
+                    pinned = device;
+                    if let Some(cpus) = device.as_ref().and_then(|d| affinity.cpus_for(d)) {
+                        if pin_current_thread(cpus) {
+                            debug!(session_id, "session worker pinned to CPUs {}", format_cpu_list(cpus));
+                        }
+                    }
+                }
+            })?;
+        Ok(Self { tx })
+    }
+
+    /// Run `f` on the worker and wait for its result.
+    pub async fn run<R: Send + 'static>(&self, f: impl FnOnce() -> R + Send + 'static) -> Option<R> {
+        let (result_tx, result_rx) = tokio::sync::oneshot::channel();
+        let job: Job = Box::new(move || {
+            let _ = result_tx.send(f());
+        });
+        self.tx.send(job).ok()?;
+        result_rx.await.ok()
+    }
+}
+
+/// Parse a Linux CPU list such as "0-3,8,10-11".
+pub fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+    for part in list.trim().split(',').filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end): (usize, usize) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+                if end < start {
+                    return None;
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(part.trim().parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+fn format_cpu_list(cpus: &[usize]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut i = 0;
+    while i < cpus.len() {
+        let start = cpus[i];
+        while i + 1 < cpus.len() && cpus[i + 1] == cpus[i] + 1 {
+            i += 1;
+        }
+        ranges.push(if cpus[i] == start {
+            start.to_string()
+        } else {
+            format!("{}-{}", start, cpus[i])
+        });
+        i += 1;
+    }
+    ranges.join(",")
+}
+
+/// CPUs of a NUMA node, from sysfs.
+fn node_cpus(node: u32) -> Option<Vec<usize>> {
+    let list = std::fs::read_to_string(format!("/sys/devices/system/node/node{}/cpulist", node));
+    match list {
+        Ok(list) => parse_cpu_list(&list),
+        Err(e) => {
+            warn!("can't read CPUs of NUMA node {}: {}", node, e);
+            None
+        }
+    }
+}
+
+/// Restrict the calling thread to `cpus`. Returns whether it took effect.
+#[cfg(target_os = "linux")]
+fn pin_current_thread(cpus: &[usize]) -> bool {
+    // SAFETY: cpu_set_t is plain data; CPU_SET only writes within the set
+    // for indices below CPU_SETSIZE, which are the only ones passed.
+    unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        for &cpu in cpus.iter().filter(|&&c| c < libc::CPU_SETSIZE as usize) {
+            libc::CPU_SET(cpu, &mut set);
+        }
+        let res = libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set);
+        if res != 0 {
+            warn!("sched_setaffinity failed: {}", std::io::Error::last_os_error());
+        }
+        res == 0
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_current_thread(_cpus: &[usize]) -> bool {
+    false
+}
@@ -0,0 +1,463 @@
+//! Audit log of the commands sessions run.
+//!
+//! On a shared server, admins need to know who used the GPUs for what. With
+//! `[server.audit]` set, every command a session sends is appended to a
+//! JSON-lines file as it's answered: the session, its token and name, the
+//! command and its category, the bytes it allocated, copied or set, the grid
+//! of a kernel launch and the SHA-256 of a loaded module image, and whether
+//! it succeeded. The file is rotated when it reaches `max_size_mb`.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use rgpu_core::config::{AuditCategory, AuditConfig};
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::messages::Message;
+use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
+
+use crate::session::Session;
+
+/// One line of the log.
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    /// Unix time in milliseconds
+    pub time_ms: u64,
+    pub session: u32,
+    /// Name of the token the session authenticated with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Session name, as shown in metrics
+    pub client: String,
+    pub category: AuditCategory,
+    pub command: String,
+    /// Bytes allocated, copied or set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// Kernel launches: grid and block dimensions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<[u32; 3]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<[u32; 3]>,
+    /// Function a launch runs, or a function looked up: its resource id
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<u64>,
+    /// Kernel, file or link input name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// SHA-256 of a loaded module image, in hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// "ok", "error <code>", or why the command wasn't run
+    pub result: String,
+}
+
+/// What a command does, worked out before it runs.
+#[derive(Debug)]
+pub struct Pending {
+    category: AuditCategory,
+    command: String,
+    bytes: Option<u64>,
+    grid: Option<[u32; 3]>,
+    block: Option<[u32; 3]>,
+    function: Option<u64>,
+    name: Option<String>,
+    sha256: Option<String>,
+}
+
+impl Pending {
+    fn new(category: AuditCategory, command: String) -> Self {
+        Self {
+            category,
+            command,
+            bytes: None,
+            grid: None,
+            block: None,
+            function: None,
+            name: None,
+            sha256: None,
+        }
+    }
+}
+
+struct Output {
+    file: File,
+    size: u64,
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+    /// Categories recorded; empty records all
+    categories: HashSet<AuditCategory>,
+    max_size: u64,
+    keep: u32,
+    output: parking_lot::Mutex<Output>,
+}
+
+impl AuditLog {
+    /// Open the log configured in `[server.audit]`, if any.
+    pub fn from_config(config: &AuditConfig) -> Option<Self> {
+        let path = PathBuf::from(config.path.as_ref()?);
+        match open(&path) {
+            Ok(output) => Some(Self {
+                path,
+                categories: config.categories.iter().copied().collect(),
+                max_size: config.max_size_mb * 1024 * 1024,
+                keep: config.keep,
+                output: parking_lot::Mutex::new(output),
+            }),
+            Err(e) => {
+                warn!("can't open audit log {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// The commands in `msg` this log records. Module images are hashed
+    /// here, before the message is handed on.
+    pub fn describe(&self, msg: &Message) -> Vec<Pending> {
+        let entries = match msg {
+            Message::CudaCommand { command, .. } | Message::CudaCommandStreamed { command, .. } => {
+                vec![describe_cuda(command)]
+            }
+            Message::CudaBatch(commands) => commands.iter().map(describe_cuda).collect(),
+            Message::CudaPipelined { batch, command, .. } => {
+                batch.iter().chain([command]).map(describe_cuda).collect()
+            }
+            Message::VulkanCommand { command, .. } => vec![describe_vulkan(command)],
+            Message::Authenticate { .. } => {
+                vec![Pending::new(AuditCategory::Session, "Authenticate".to_string())]
+            }
+            Message::ResumeSession { session_id, .. } => {
+                let mut entry = Pending::new(AuditCategory::Session, "ResumeSession".to_string());
+                entry.name = Some(session_id.to_string());
+                vec![entry]
+            }
+            _ => Vec::new(),
+        };
+        entries
+            .into_iter()
+            .filter(|entry| self.categories.is_empty() || self.categories.contains(&entry.category))
+            .collect()
+    }
+
+    /// Append `entries` with the result they got.
+    pub fn record(&self, session: &Session, entries: Vec<Pending>, result: &str) {
+        if entries.is_empty() {
+            return;
+        }
+        let time_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let (token, client) = (session.token(), session.name());
+        let mut lines = Vec::new();
+        for pending in entries {
+            let entry = AuditEntry {
+                time_ms,
+                session: session.session_id,
+                token: token.clone(),
+                client: client.clone(),
+                category: pending.category,
+                command: pending.command,
+                bytes: pending.bytes,
+                grid: pending.grid,
+                block: pending.block,
+                function: pending.function,
+                name: pending.name,
+                sha256: pending.sha256,
+                result: result.to_string(),
+            };
+            if serde_json::to_writer(&mut lines, &entry).is_ok() {
+                lines.push(b'\n');
+            }
+        }
+        self.write(&lines);
+    }
+
+    fn write(&self, lines: &[u8]) {
+        let mut output = self.output.lock();
+        if self.max_size > 0 && output.size > 0 && output.size + lines.len() as u64 > self.max_size {
+            match self.rotate() {
+                Ok(fresh) => *output = fresh,
+                Err(e) => warn!("can't rotate audit log {}: {}", self.path.display(), e),
+            }
+        }
+        match output.file.write_all(lines) {
+            Ok(()) => output.size += lines.len() as u64,
+            Err(e) => warn!("can't write audit log {}: {}", self.path.display(), e),
+        }
+    }
+
+    /// Shift `<path>.N` to `<path>.N+1`, dropping the oldest, move the
+    /// current file to `<path>.1` and start a new one.
+    fn rotate(&self) -> std::io::Result<Output> {
+        let rotated = |n: u32| PathBuf::from(format!("{}.{}", self.path.display(), n));
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for n in (1..self.keep).rev() {
+                if rotated(n).exists() {
+                    std::fs::rename(rotated(n), rotated(n + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated(1))?;
+        }
+        open(&self.path)
+    }
+}
+
+/// The result of a command as recorded: "ok", or the error it got.
+pub fn outcome(response: Option<&Message>) -> String {
+    match response {
+        Some(Message::CudaResponse {
+            response: CudaResponse::Error { code, .. },
+            ..
+        })
+        | Some(Message::VulkanResponse {
+            response: VulkanResponse::Error { code, .. },
+            ..
+        }) => format!("error {}", code),
+        Some(Message::AuthResult { success: false, .. }) => "refused".to_string(),
+        Some(Message::Unsupported { reason, .. }) => reason.clone(),
+        Some(Message::Error(e)) => e.to_string(),
+        _ => "ok".to_string(),
+    }
+}
+
+fn open(path: &Path) -> std::io::Result<Output> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok(Output { file, size })
+}
+
+fn sha256(data: &[u8]) -> String {
+    hex(&Sha256::digest(data))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn describe_cuda(command: &CudaCommand) -> Pending {
+    use AuditCategory::*;
+
+    let name = format!("Cuda::{}", variant_name(command));
+    let (category, bytes) = match command {
+        CudaCommand::MemAlloc { byte_size }
+        | CudaCommand::MemAllocHost { byte_size }
+        | CudaCommand::MemHostAlloc { byte_size, .. }
+        | CudaCommand::MemAllocManaged { byte_size, .. }
+        | CudaCommand::MemAllocAsync { byte_size, .. }
+        | CudaCommand::MemAllocFromPoolAsync { byte_size, .. }
+        | CudaCommand::MemHostRegister { byte_size, .. } => (Alloc, Some(*byte_size)),
+        CudaCommand::MemAllocPitch { width, height, .. } => (Alloc, Some(width * height)),
+        CudaCommand::MemFree { .. }
+        | CudaCommand::MemFreeHost { .. }
+        | CudaCommand::MemFreeAsync { .. }
+        | CudaCommand::MemHostUnregister { .. } => (Alloc, None),
+        CudaCommand::MemcpyHtoD { byte_count, .. }
+        | CudaCommand::MemcpyDtoH { byte_count, .. }
+        | CudaCommand::MemcpyDtoD { byte_count, .. }
+        | CudaCommand::MemcpyHtoDAsync { byte_count, .. }
+        | CudaCommand::MemcpyDtoHAsync { byte_count, .. }
+        | CudaCommand::MemcpyDtoDAsync { byte_count, .. }
+        | CudaCommand::MemcpyDtoHDiff { byte_count, .. }
+        | CudaCommand::MemcpyHtoDEncoded { byte_count, .. }
+        | CudaCommand::HostMemRead { byte_count, .. } => (Memcpy, Some(*byte_count)),
+        CudaCommand::HostMemWrite { data, .. } => (Memcpy, Some(data.len() as u64)),
+        CudaCommand::Memcpy3D { params, .. } | CudaCommand::Memcpy3DAsync { params, .. } => {
+            (Memcpy, Some(params.packed_size()))
+        }
+        CudaCommand::MemsetD8 { count, .. } | CudaCommand::MemsetD8Async { count, .. } => (Memset, Some(*count)),
+        CudaCommand::MemsetD16 { count, .. } | CudaCommand::MemsetD16Async { count, .. } => {
+            (Memset, Some(count * 2))
+        }
+        CudaCommand::MemsetD32 { count, .. } | CudaCommand::MemsetD32Async { count, .. } => {
+            (Memset, Some(count * 4))
+        }
+        CudaCommand::MemsetD2D8 { width, height, .. } | CudaCommand::MemsetD2D8Async { width, height, .. } => {
+            (Memset, Some(width * height))
+        }
+        CudaCommand::MemsetD2D16 { width, height, .. } | CudaCommand::MemsetD2D16Async { width, height, .. } => {
+            (Memset, Some(width * height * 2))
+        }
+        CudaCommand::MemsetD2D32 { width, height, .. } | CudaCommand::MemsetD2D32Async { width, height, .. } => {
+            (Memset, Some(width * height * 4))
+        }
+        CudaCommand::FillBufferTyped { pattern, count, .. } => (Memset, Some(pattern.len() as u64 * count)),
+        CudaCommand::LaunchKernel { .. }
+        | CudaCommand::LaunchCooperativeKernel { .. }
+        | CudaCommand::GraphLaunch { .. } => (Launch, None),
+        CudaCommand::ModuleLoadData { image }
+        | CudaCommand::ModuleLoadDataEx { image, .. }
+        | CudaCommand::ModuleLoadFatBinary { fat_cubin: image }
+        | CudaCommand::LinkAddData { data: image, .. } => (Module, Some(image.len() as u64)),
+        CudaCommand::ModuleLoad { .. }
+        | CudaCommand::ModuleLoadByHash { .. }
+        | CudaCommand::ModuleUnload { .. }
+        | CudaCommand::ModuleGetFunction { .. }
+        | CudaCommand::LinkCreate { .. }
+        | CudaCommand::LinkAddFile { .. }
+        | CudaCommand::LinkComplete { .. }
+        | CudaCommand::LinkDestroy { .. } => (Module, None),
+        _ => (Other, None),
+    };
+
+    let mut entry = Pending::new(category, name);
+    entry.bytes = bytes;
+    match command {
+        CudaCommand::LaunchKernel { func, grid_dim, block_dim, .. }
+        | CudaCommand::LaunchCooperativeKernel { func, grid_dim, block_dim, .. } => {
+            entry.grid = Some(*grid_dim);
+            entry.block = Some(*block_dim);
+            entry.function = Some(func.resource_id);
+        }
+        CudaCommand::ModuleLoadData { image }
+        | CudaCommand::ModuleLoadDataEx { image, .. }
+        | CudaCommand::ModuleLoadFatBinary { fat_cubin: image } => entry.sha256 = Some(sha256(image)),
+        CudaCommand::ModuleLoadByHash { hash } => entry.sha256 = Some(hex(hash)),
+        CudaCommand::LinkAddData { data, name, .. } => {
+            entry.sha256 = Some(sha256(data));
+            entry.name = Some(name.clone());
+        }
+        CudaCommand::ModuleLoad { fname } => entry.name = Some(fname.clone()),
+        CudaCommand::LinkAddFile { path, .. } => entry.name = Some(path.clone()),
+        CudaCommand::ModuleGetFunction { name, .. } => entry.name = Some(name.clone()),
+        _ => {}
+    }
+    entry
+}
+
+fn describe_vulkan(command: &VulkanCommand) -> Pending {
+    let mut entry = Pending::new(AuditCategory::Vulkan, format!("Vulkan::{}", variant_name(command)));
+    match command {
+        VulkanCommand::AllocateMemory { alloc_size, .. } => entry.bytes = Some(*alloc_size),
+        VulkanCommand::CreateShaderModule { code, .. } => {
+            entry.bytes = Some(code.len() as u64);
+            entry.sha256 = Some(sha256(code));
+        }
+        VulkanCommand::CreateShaderModuleByHash { hash, .. } => {
+            entry.sha256 = Some(hex(hash));
+        }
+        _ => {}
+    }
+    entry
+}
+
+/// Enum variant name from the derived `Debug` output, without formatting
+/// the fields (which may hold megabytes of buffer data).
+fn variant_name(value: &impl std::fmt::Debug) -> String {
+    struct NameOnly(String);
+
+    impl std::fmt::Write for NameOnly {
+        fn write_str(&mut self, s: &str) -> std::fmt::Result {
+            match s.find(|c: char| !(c.is_alphanumeric() || c == '_')) {
+                Some(end) => {
+                    self.0.push_str(&s[..end]);
+                    Err(std::fmt::Error)
+                }
+                None => {
+                    self.0.push_str(s);
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    let mut name = NameOnly(String::new());
+    let _ = std::fmt::Write::write_fmt(&mut name, format_args!("{:?}", value));
+    name.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::messages::RequestId;
+
+    fn log_at(path: &Path, config: AuditConfig) -> AuditLog {
+        AuditLog::from_config(&AuditConfig {
+            path: Some(path.to_string_lossy().into_owned()),
+            ..config
+        })
+        .expect("audit log")
+    }
+
+    fn cuda(command: CudaCommand) -> Message {
+        Message::CudaCommand {
+            request_id: RequestId(1),
+            command,
+            deadline_ms: None,
+        }
+    }
+
+    #[test]
+    fn entries_are_json_lines() {
+        let dir = std::env::temp_dir().join(format!("rgpu-audit-{}-lines", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let log = log_at(&dir.join("audit.jsonl"), AuditConfig::default());
+        let session = Session::new(3, 0, "test".to_string());
+
+        let load = cuda(CudaCommand::ModuleLoadData { image: b"abc".to_vec() });
+        log.record(&session, log.describe(&load), "ok");
+        let copy = cuda(CudaCommand::MemcpyDtoH {
+            src: session.alloc_handle(rgpu_protocol::handle::ResourceType::CuDevicePtr),
+            byte_count: 4096,
+        });
+        log.record(&session, log.describe(&copy), "error 700");
+        // Nothing to record for messages that aren't commands.
+        assert!(log.describe(&Message::Ping).is_empty());
+
+        let text = std::fs::read_to_string(dir.join("audit.jsonl")).unwrap();
+        let lines: Vec<serde_json::Value> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["category"], "module");
+        assert_eq!(
+            lines[0]["sha256"],
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(lines[1]["command"], "Cuda::MemcpyDtoH");
+        assert_eq!(lines[1]["bytes"], 4096);
+        assert_eq!(lines[1]["result"], "error 700");
+        assert_eq!(lines[1]["session"], 3);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn categories_filter_and_rotation() {
+        let dir = std::env::temp_dir().join(format!("rgpu-audit-{}-rotate", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("audit.jsonl");
+        let log = log_at(
+            &path,
+            AuditConfig {
+                categories: vec![AuditCategory::Alloc],
+                keep: 2,
+                ..Default::default()
+            },
+        );
+        assert!(log.describe(&cuda(CudaCommand::MemGetInfo)).is_empty());
+        assert_eq!(log.describe(&cuda(CudaCommand::MemAlloc { byte_size: 1 })).len(), 1);
+
+        // Rotate on every write from here on.
+        let log = AuditLog { max_size: 1, ..log };
+        let session = Session::new(4, 0, "test".to_string());
+        for _ in 0..4 {
+            log.record(&session, log.describe(&cuda(CudaCommand::MemAlloc { byte_size: 1 })), "ok");
+        }
+        assert!(dir.join("audit.jsonl.1").exists());
+        assert!(dir.join("audit.jsonl.2").exists());
+        assert!(!dir.join("audit.jsonl.3").exists());
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,219 @@
+//! Fault injection for failure testing (the `chaos` feature).
+//!
+//! A [`Chaos`] attached to the server with [`RgpuServer::with_chaos`] drops,
+//! delays or fails a fraction of each session's GPU commands, so tests can
+//! check how the daemon and applications cope with a flaky server. Other
+//! messages (Hello, Ping, metrics) are never touched.
+//!
+//! A failed command gets an error response with the configured CUDA code
+//! (Vulkan commands get `VK_ERROR_DEVICE_LOST`). With `sticky` set, the first
+//! injected error poisons the session: every later command fails the same
+//! way, as CUDA does after e.g. an illegal address, until the session ends
+//! or [`Chaos::clear_session`] is called.
+//!
+//! A server built with the feature also reads `RGPU_CHAOS` at startup, e.g.
+//! `RGPU_CHAOS="drop=0.01,delay=0.1,delay_ms=250,error=0.02,code=700,sticky,seed=7"`.
+//!
+//! [`RgpuServer::with_chaos`]: crate::server::RgpuServer::with_chaos
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use rgpu_protocol::cuda_commands::CudaResponse;
+use rgpu_protocol::messages::{Message, RequestId};
+use rgpu_protocol::vulkan_commands::VulkanResponse;
+
+/// `VK_ERROR_DEVICE_LOST`
+const VK_ERROR_DEVICE_LOST: i32 = -4;
+
+/// Fractions of commands to fault, each in `0.0..=1.0`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    /// Commands executed but never answered
+    pub drop_rate: f64,
+    /// Commands answered with an error instead of being executed
+    pub error_rate: f64,
+    /// Commands held back for `delay` before being executed
+    pub delay_rate: f64,
+    pub delay: Duration,
+    /// CUDA error code of injected errors
+    pub error_code: i32,
+    /// An injected error fails every later command of the session
+    pub sticky: bool,
+    /// Seed of each session's fault sequence (mixed with the session ID)
+    pub seed: u64,
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            drop_rate: 0.0,
+            error_rate: 0.0,
+            delay_rate: 0.0,
+            delay: Duration::from_millis(100),
+            error_code: 999,
+            sticky: false,
+            seed: 0,
+        }
+    }
+}
+
+impl ChaosConfig {
+    /// Read `RGPU_CHAOS`, if set.
+    pub fn from_env() -> Option<Result<Self, String>> {
+        std::env::var("RGPU_CHAOS").ok().map(|spec| spec.parse())
+    }
+}
+
+impl std::str::FromStr for ChaosConfig {
+    type Err = String;
+
+    /// Comma-separated `key=value` pairs: `drop`, `error`, `delay` (rates),
+    /// `delay_ms`, `code`, `seed`, and the flag `sticky`.
+    fn from_str(spec: &str) -> Result<Self, String> {
+        let mut config = Self::default();
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            if part == "sticky" {
+                config.sticky = true;
+                continue;
+            }
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value, got {:?}", part))?;
+            let rate = || match value.parse::<f64>() {
+                Ok(r) if (0.0..=1.0).contains(&r) => Ok(r),
+                _ => Err(format!("{}: rate must be between 0 and 1, got {:?}", key, value)),
+            };
+            let int = || value.parse::<u64>().map_err(|_| format!("{}: invalid number {:?}", key, value));
+            match key {
+                "drop" => config.drop_rate = rate()?,
+                "error" => config.error_rate = rate()?,
+                "delay" => config.delay_rate = rate()?,
+                "delay_ms" => config.delay = Duration::from_millis(int()?),
+                "code" => config.error_code = value.parse().map_err(|_| format!("{}: invalid number {:?}", key, value))?,
+                "seed" => config.seed = int()?,
+                _ => return Err(format!("unknown chaos setting {:?}", key)),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// What to do to a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    Drop,
+    Delay(Duration),
+    Error(i32),
+}
+
+struct SessionChaos {
+    /// Overrides the default config for this session
+    config: Option<ChaosConfig>,
+    rng: StdRng,
+    /// Code of the sticky error the session is stuck on
+    poisoned: Option<i32>,
+}
+
+/// Fault injection state for all sessions.
+pub struct Chaos {
+    default: parking_lot::RwLock<ChaosConfig>,
+    sessions: parking_lot::Mutex<HashMap<u32, SessionChaos>>,
+}
+
+impl Chaos {
+    pub fn new(default: ChaosConfig) -> Self {
+        Self {
+            default: parking_lot::RwLock::new(default),
+            sessions: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Change the config of sessions without their own.
+    pub fn set_default(&self, config: ChaosConfig) {
+        *self.default.write() = config;
+    }
+
+    /// Give one session its own config.
+    pub fn set_session(&self, session_id: u32, config: ChaosConfig) {
+        let mut sessions = self.sessions.lock();
+        let default = self.default.read().clone();
+        let state = Self::state(&mut sessions, session_id, &default);
+        state.rng = StdRng::seed_from_u64(Self::session_seed(&config, session_id));
+        state.config = Some(config);
+    }
+
+    /// Forget a session's config, fault sequence and sticky error.
+    pub fn clear_session(&self, session_id: u32) {
+        self.sessions.lock().remove(&session_id);
+    }
+
+    /// Decide the fault, if any, for the session's next command.
+    pub fn roll(&self, session_id: u32) -> Option<Fault> {
+        let mut sessions = self.sessions.lock();
+        let default = self.default.read().clone();
+        let state = Self::state(&mut sessions, session_id, &default);
+        if let Some(code) = state.poisoned {
+            return Some(Fault::Error(code));
+        }
+        let config = state.config.as_ref().unwrap_or(&default);
+        if state.rng.gen_bool(config.drop_rate) {
+            return Some(Fault::Drop);
+        }
+        if state.rng.gen_bool(config.error_rate) {
+            let code = config.error_code;
+            if config.sticky {
+                state.poisoned = Some(code);
+            }
+            return Some(Fault::Error(code));
+        }
+        if state.rng.gen_bool(config.delay_rate) {
+            return Some(Fault::Delay(config.delay));
+        }
+        None
+    }
+
+    /// Error response to a GPU command failed with CUDA error `code`.
+    pub fn error_response(msg: &Message, code: i32) -> Option<Message> {
+        let cuda = |request_id: RequestId| Message::CudaResponse {
+            request_id,
+            response: CudaResponse::Error {
+                code,
+                message: "injected fault".to_string(),
+            },
+        };
+        match msg {
+            Message::CudaCommand { request_id, .. } | Message::CudaPipelined { request_id, .. } => {
+                Some(cuda(*request_id))
+            }
+            Message::CudaBatch(_) => Some(cuda(RequestId(0))),
+            Message::VulkanCommand { request_id, .. } => Some(Message::VulkanResponse {
+                request_id: *request_id,
+                response: VulkanResponse::Error {
+                    code: VK_ERROR_DEVICE_LOST,
+                    message: "injected fault".to_string(),
+                },
+            }),
+            _ => None,
+        }
+    }
+
+    fn state<'a>(
+        sessions: &'a mut HashMap<u32, SessionChaos>,
+        session_id: u32,
+        default: &ChaosConfig,
+    ) -> &'a mut SessionChaos {
+        sessions.entry(session_id).or_insert_with(|| SessionChaos {
+            config: None,
+            rng: StdRng::seed_from_u64(Self::session_seed(default, session_id)),
+            poisoned: None,
+        })
+    }
+
+    fn session_seed(config: &ChaosConfig, session_id: u32) -> u64 {
+        config.seed ^ (session_id as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+    }
+}
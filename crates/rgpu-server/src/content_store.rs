@@ -0,0 +1,83 @@
+//! Uploads kept by content hash.
+//!
+//! Clients send the same SPIR-V shaders and CUDA module images on every
+//! run. The executors keep what they were sent here, by SHA-256 and shared
+//! by all sessions, so a client that has sent some code before can name it
+//! by hash instead of sending it again. The oldest uploads are dropped once
+//! a store is over its budget; clients then send them in full.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use sha2::{Digest, Sha256};
+
+#[derive(Default)]
+struct Contents {
+    data: HashMap<[u8; 32], Arc<Vec<u8>>>,
+    /// Hashes oldest first
+    order: VecDeque<[u8; 32]>,
+    bytes: usize,
+}
+
+/// Uploads by SHA-256, up to `budget` bytes.
+pub struct ContentStore {
+    budget: usize,
+    contents: Mutex<Contents>,
+}
+
+impl ContentStore {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            contents: Mutex::new(Contents::default()),
+        }
+    }
+
+    pub fn get(&self, hash: &[u8; 32]) -> Option<Arc<Vec<u8>>> {
+        self.contents.lock().unwrap().data.get(hash).cloned()
+    }
+
+    /// Keep `data`, dropping the oldest uploads past the budget.
+    pub fn insert(&self, data: &[u8]) {
+        if data.len() > self.budget {
+            return;
+        }
+        let hash: [u8; 32] = Sha256::digest(data).into();
+        let mut contents = self.contents.lock().unwrap();
+        if contents.data.contains_key(&hash) {
+            return;
+        }
+        while contents.bytes + data.len() > self.budget {
+            let Some(oldest) = contents.order.pop_front() else { break };
+            if let Some(old) = contents.data.remove(&oldest) {
+                contents.bytes -= old.len();
+            }
+        }
+        contents.data.insert(hash, Arc::new(data.to_vec()));
+        contents.order.push_back(hash);
+        contents.bytes += data.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(data: &[u8]) -> [u8; 32] {
+        Sha256::digest(data).into()
+    }
+
+    #[test]
+    fn oldest_dropped_past_budget() {
+        let store = ContentStore::new(8);
+        store.insert(b"aaaa");
+        store.insert(b"bbbb");
+        assert!(store.get(&hash(b"aaaa")).is_some());
+        store.insert(b"cccc");
+        assert!(store.get(&hash(b"aaaa")).is_none());
+        assert_eq!(store.get(&hash(b"bbbb")).unwrap().as_slice(), b"bbbb");
+        assert_eq!(store.get(&hash(b"cccc")).unwrap().as_slice(), b"cccc");
+        store.insert(b"too large to keep");
+        assert!(store.get(&hash(b"too large to keep")).is_none());
+    }
+}
@@ -3,12 +3,17 @@
 //! Uses `libloading` to load `nvcuda.dll` (Windows) or `libcuda.so.1` (Linux)
 //! and provides safe Rust wrappers around the raw CUDA driver API functions.
 
+// Opaque CUDA handles are passed straight through to the driver, never dereferenced here.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 use std::ffi::{c_char, c_int, c_uint, c_void, CStr};
 use std::sync::Arc;
 
 use libloading::{Library, Symbol};
 use tracing::{debug, info};
 
+use rgpu_protocol::cuda_commands::{jit_option, JitOutput};
+
 /// CUDA result type (CUresult).
 pub type CUresult = c_int;
 
@@ -24,9 +29,34 @@ pub type CUstream = *mut c_void;
 pub type CUevent = *mut c_void;
 pub type CUlinkState = *mut c_void;
 pub type CUmemoryPool = *mut c_void;
+pub type CUgraph = *mut c_void;
+pub type CUgraphExec = *mut c_void;
+pub type CUtexObject = u64;
+pub type CUsurfObject = u64;
+pub type CUarray = *mut c_void;
+pub type CUtexref = *mut c_void;
+pub type CUsurfref = *mut c_void;
 
 pub const CUDA_SUCCESS: CUresult = 0;
+pub const CUDA_ERROR_INVALID_VALUE: CUresult = 1;
+pub const CUDA_ERROR_OUT_OF_MEMORY: CUresult = 2;
+pub const CUDA_ERROR_DEVICE_UNAVAILABLE: CUresult = 46;
+pub const CUDA_ERROR_INVALID_DEVICE: CUresult = 101;
+pub const CUDA_ERROR_HOST_MEMORY_NOT_REGISTERED: CUresult = 713;
 pub const CUDA_ERROR_NOT_SUPPORTED: CUresult = 801;
+pub const CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED: CUresult = 900;
+
+/// `cuMemHostRegister` flags.
+pub const CU_MEMHOSTREGISTER_PORTABLE: u32 = 0x01;
+pub const CU_MEMHOSTREGISTER_DEVICEMAP: u32 = 0x02;
+
+/// `CU_STREAM_CAPTURE_MODE_RELAXED`
+pub const CU_STREAM_CAPTURE_MODE_RELAXED: c_int = 2;
+
+/// `CUdevice_P2PAttribute` values.
+pub const CU_DEVICE_P2P_ATTRIBUTE_PERFORMANCE_RANK: i32 = 1;
+pub const CU_DEVICE_P2P_ATTRIBUTE_ACCESS_SUPPORTED: i32 = 2;
+pub const CU_DEVICE_P2P_ATTRIBUTE_NATIVE_ATOMIC_SUPPORTED: i32 = 3;
 
 /// UUID structure (16 bytes).
 #[repr(C)]
@@ -34,6 +64,189 @@ pub struct CUuuid {
     pub bytes: [u8; 16],
 }
 
+/// Copy descriptor for cuMemcpy3D (`CUDA_MEMCPY3D`).
+#[repr(C)]
+#[allow(non_camel_case_types)]
+pub struct CUDA_MEMCPY3D {
+    pub src_x_in_bytes: usize,
+    pub src_y: usize,
+    pub src_z: usize,
+    pub src_lod: usize,
+    pub src_memory_type: c_uint,
+    pub src_host: *const c_void,
+    pub src_device: CUdeviceptr,
+    pub src_array: *mut c_void,
+    pub reserved0: *mut c_void,
+    pub src_pitch: usize,
+    pub src_height: usize,
+    pub dst_x_in_bytes: usize,
+    pub dst_y: usize,
+    pub dst_z: usize,
+    pub dst_lod: usize,
+    pub dst_memory_type: c_uint,
+    pub dst_host: *mut c_void,
+    pub dst_device: CUdeviceptr,
+    pub dst_array: *mut c_void,
+    pub reserved1: *mut c_void,
+    pub dst_pitch: usize,
+    pub dst_height: usize,
+    pub width_in_bytes: usize,
+    pub height: usize,
+    pub depth: usize,
+}
+
+impl Default for CUDA_MEMCPY3D {
+    fn default() -> Self {
+        // SAFETY: all-zero is a valid value for every field (integers and null pointers).
+        unsafe { std::mem::zeroed() }
+    }
+}
+
+pub const CU_RESOURCE_TYPE_ARRAY: c_uint = 0;
+pub const CU_RESOURCE_TYPE_LINEAR: c_uint = 2;
+pub const CU_RESOURCE_TYPE_PITCH2D: c_uint = 3;
+
+/// Resource descriptor for texture and surface objects
+/// (`CUDA_RESOURCE_DESC`). `res` is the union of the per-type layouts: for
+/// an array `{ hArray }`, for linear memory `{ devPtr, format | numChannels
+/// << 32, sizeInBytes }`, for pitched 2D memory `{ devPtr, format |
+/// numChannels << 32, width, height, pitchInBytes }`.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Default)]
+pub struct CUDA_RESOURCE_DESC {
+    pub res_type: c_uint,
+    pub res: [u64; 16],
+    pub flags: c_uint,
+}
+
+/// Opaque IPC handle for a device allocation (`CUipcMemHandle`).
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CUipcMemHandle {
+    pub reserved: [u8; 64],
+}
+
+/// 2D array descriptor (`CUDA_ARRAY_DESCRIPTOR`), used by
+/// cuTexRefSetAddress2D to describe pitched memory.
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Default)]
+pub struct CUDA_ARRAY_DESCRIPTOR {
+    pub width: usize,
+    pub height: usize,
+    pub format: c_uint,
+    pub num_channels: c_uint,
+}
+
+/// CUDA array descriptor (`CUDA_ARRAY3D_DESCRIPTOR`).
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Default)]
+pub struct CUDA_ARRAY3D_DESCRIPTOR {
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub format: c_uint,
+    pub num_channels: c_uint,
+    pub flags: c_uint,
+}
+
+/// Texture descriptor (`CUDA_TEXTURE_DESC`).
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Default)]
+pub struct CUDA_TEXTURE_DESC {
+    pub address_mode: [c_uint; 3],
+    pub filter_mode: c_uint,
+    pub flags: c_uint,
+    pub max_anisotropy: c_uint,
+    pub mipmap_filter_mode: c_uint,
+    pub mipmap_level_bias: f32,
+    pub min_mipmap_level_clamp: f32,
+    pub max_mipmap_level_clamp: f32,
+    pub border_color: [f32; 4],
+    pub reserved: [c_int; 12],
+}
+
+/// Resource view descriptor (`CUDA_RESOURCE_VIEW_DESC`).
+#[repr(C)]
+#[allow(non_camel_case_types)]
+#[derive(Default)]
+pub struct CUDA_RESOURCE_VIEW_DESC {
+    pub format: c_uint,
+    pub width: usize,
+    pub height: usize,
+    pub depth: usize,
+    pub first_mipmap_level: c_uint,
+    pub last_mipmap_level: c_uint,
+    pub first_layer: c_uint,
+    pub last_layer: c_uint,
+    pub reserved: [c_uint; 16],
+}
+
+/// Largest JIT log kept for a client.
+const MAX_JIT_LOG: usize = 1024 * 1024;
+
+/// A client's JIT options made ready for the driver: scalar values as they
+/// came, and buffers of this process in place of the client's log buffers,
+/// of the sizes it asked for.
+pub struct JitOptions {
+    options: Vec<c_int>,
+    values: Vec<*mut c_void>,
+    info_log: Vec<u8>,
+    error_log: Vec<u8>,
+}
+
+impl JitOptions {
+    pub fn new(options: &[i32], option_values: &[u64]) -> Self {
+        let requested = |size_option: i32| {
+            options
+                .iter()
+                .zip(option_values)
+                .find(|(option, _)| **option == size_option)
+                .map_or(0, |(_, size)| (*size as usize).min(MAX_JIT_LOG))
+        };
+        let mut info_log = vec![0u8; requested(jit_option::INFO_LOG_BUFFER_SIZE_BYTES)];
+        let mut error_log = vec![0u8; requested(jit_option::ERROR_LOG_BUFFER_SIZE_BYTES)];
+        let values = options
+            .iter()
+            .zip(option_values)
+            .map(|(option, value)| match *option {
+                jit_option::INFO_LOG_BUFFER => info_log.as_mut_ptr() as *mut c_void,
+                jit_option::ERROR_LOG_BUFFER => error_log.as_mut_ptr() as *mut c_void,
+                jit_option::INFO_LOG_BUFFER_SIZE_BYTES => info_log.len() as *mut c_void,
+                jit_option::ERROR_LOG_BUFFER_SIZE_BYTES => error_log.len() as *mut c_void,
+                _ => *value as usize as *mut c_void,
+            })
+            .collect();
+        Self {
+            options: options[..option_values.len().min(options.len())].to_vec(),
+            values,
+            info_log,
+            error_log,
+        }
+    }
+
+    /// The option values and logs the driver left, for the client.
+    pub fn output(&self) -> JitOutput {
+        let log = |log: &[u8]| log[..log.iter().position(|&b| b == 0).unwrap_or(log.len())].to_vec();
+        JitOutput {
+            option_values: self
+                .options
+                .iter()
+                .zip(&self.values)
+                .map(|(option, value)| match *option {
+                    jit_option::INFO_LOG_BUFFER | jit_option::ERROR_LOG_BUFFER => 0,
+                    _ => *value as usize as u64,
+                })
+                .collect(),
+            info_log: log(&self.info_log),
+            error_log: log(&self.error_log),
+        }
+    }
+}
+
 /// Function pointer type definitions for the CUDA driver API.
 type FnCuInit = unsafe extern "C" fn(flags: c_uint) -> CUresult;
 type FnCuDriverGetVersion = unsafe extern "C" fn(version: *mut c_int) -> CUresult;
@@ -52,6 +265,8 @@ type FnCuDeviceGetPCIBusId = unsafe extern "C" fn(pci_bus_id: *mut c_char, len:
 type FnCuDeviceGetByPCIBusId = unsafe extern "C" fn(dev: *mut CUdevice, pci_bus_id: *const c_char) -> CUresult;
 type FnCuDeviceCanAccessPeer = unsafe extern "C" fn(can_access: *mut c_int, dev: CUdevice, peer_dev: CUdevice) -> CUresult;
 type FnCuDeviceGetP2PAttribute = unsafe extern "C" fn(value: *mut c_int, attrib: c_int, src: CUdevice, dst: CUdevice) -> CUresult;
+type FnCuDeviceGetTexture1DLinearMaxWidth = unsafe extern "C" fn(max_width: *mut usize, format: c_uint, num_channels: c_uint, dev: CUdevice) -> CUresult;
+type FnCuDeviceGetExecAffinitySupport = unsafe extern "C" fn(pi: *mut c_int, affinity_type: c_int, dev: CUdevice) -> CUresult;
 
 // Primary context
 type FnCuDevicePrimaryCtxRetain = unsafe extern "C" fn(pctx: *mut CUcontext, dev: CUdevice) -> CUresult;
@@ -79,6 +294,8 @@ type FnCuCtxGetApiVersion = unsafe extern "C" fn(ctx: CUcontext, version: *mut c
 type FnCuCtxGetFlags = unsafe extern "C" fn(flags: *mut c_uint) -> CUresult;
 type FnCuCtxSetFlags = unsafe extern "C" fn(flags: c_uint) -> CUresult;
 type FnCuCtxResetPersistingL2Cache = unsafe extern "C" fn() -> CUresult;
+type FnCuCtxSetSharedMemConfig = unsafe extern "C" fn(config: c_int) -> CUresult;
+type FnCuCtxGetSharedMemConfig = unsafe extern "C" fn(config: *mut c_int) -> CUresult;
 
 // Module management
 type FnCuModuleLoadData =
@@ -139,6 +356,15 @@ type FnCuMemsetD16 =
     unsafe extern "C" fn(dst: CUdeviceptr, value: u16, count: usize) -> CUresult;
 type FnCuMemsetD32 =
     unsafe extern "C" fn(dst: CUdeviceptr, value: u32, count: usize) -> CUresult;
+type FnCuMemsetD8Async = unsafe extern "C" fn(dst: CUdeviceptr, value: u8, count: usize, hstream: CUstream) -> CUresult;
+type FnCuMemsetD16Async = unsafe extern "C" fn(dst: CUdeviceptr, value: u16, count: usize, hstream: CUstream) -> CUresult;
+type FnCuMemsetD32Async = unsafe extern "C" fn(dst: CUdeviceptr, value: u32, count: usize, hstream: CUstream) -> CUresult;
+type FnCuMemsetD2D8 = unsafe extern "C" fn(dst: CUdeviceptr, dst_pitch: usize, value: u8, width: usize, height: usize) -> CUresult;
+type FnCuMemsetD2D16 = unsafe extern "C" fn(dst: CUdeviceptr, dst_pitch: usize, value: u16, width: usize, height: usize) -> CUresult;
+type FnCuMemsetD2D32 = unsafe extern "C" fn(dst: CUdeviceptr, dst_pitch: usize, value: u32, width: usize, height: usize) -> CUresult;
+type FnCuMemsetD2D8Async = unsafe extern "C" fn(dst: CUdeviceptr, dst_pitch: usize, value: u8, width: usize, height: usize, hstream: CUstream) -> CUresult;
+type FnCuMemsetD2D16Async = unsafe extern "C" fn(dst: CUdeviceptr, dst_pitch: usize, value: u16, width: usize, height: usize, hstream: CUstream) -> CUresult;
+type FnCuMemsetD2D32Async = unsafe extern "C" fn(dst: CUdeviceptr, dst_pitch: usize, value: u32, width: usize, height: usize, hstream: CUstream) -> CUresult;
 type FnCuMemGetInfo = unsafe extern "C" fn(free: *mut usize, total: *mut usize) -> CUresult;
 type FnCuMemGetAddressRange = unsafe extern "C" fn(pbase: *mut CUdeviceptr, psize: *mut usize, dptr: CUdeviceptr) -> CUresult;
 type FnCuMemAllocHost = unsafe extern "C" fn(pp: *mut *mut c_void, bytesize: usize) -> CUresult;
@@ -147,6 +373,7 @@ type FnCuMemHostAlloc = unsafe extern "C" fn(pp: *mut *mut c_void, bytesize: usi
 type FnCuMemHostGetDevicePointer = unsafe extern "C" fn(pdptr: *mut CUdeviceptr, p: *mut c_void, flags: c_uint) -> CUresult;
 type FnCuMemHostGetFlags = unsafe extern "C" fn(pflags: *mut c_uint, p: *mut c_void) -> CUresult;
 type FnCuMemAllocManaged = unsafe extern "C" fn(dptr: *mut CUdeviceptr, bytesize: usize, flags: c_uint) -> CUresult;
+type FnCuMemcpy3D = unsafe extern "C" fn(copy: *const CUDA_MEMCPY3D) -> CUresult;
 type FnCuMemAllocPitch = unsafe extern "C" fn(dptr: *mut CUdeviceptr, ppitch: *mut usize, width: usize, height: usize, element_size: c_uint) -> CUresult;
 
 // Memory pool
@@ -177,6 +404,7 @@ type FnCuLaunchKernel = unsafe extern "C" fn(
     extra: *mut *mut c_void,
 ) -> CUresult;
 type FnCuFuncGetAttribute = unsafe extern "C" fn(pi: *mut c_int, attrib: c_int, hfunc: CUfunction) -> CUresult;
+type FnCuFuncGetParamInfo = unsafe extern "C" fn(func: CUfunction, param_index: usize, param_offset: *mut usize, param_size: *mut usize) -> CUresult;
 type FnCuFuncSetAttribute = unsafe extern "C" fn(hfunc: CUfunction, attrib: c_int, value: c_int) -> CUresult;
 type FnCuFuncSetCacheConfig = unsafe extern "C" fn(hfunc: CUfunction, config: c_int) -> CUresult;
 type FnCuFuncSetSharedMemConfig = unsafe extern "C" fn(hfunc: CUfunction, config: c_int) -> CUresult;
@@ -197,6 +425,59 @@ type FnCuStreamGetPriority = unsafe extern "C" fn(hstream: CUstream, priority: *
 type FnCuStreamGetFlags = unsafe extern "C" fn(hstream: CUstream, flags: *mut c_uint) -> CUresult;
 type FnCuStreamGetCtx = unsafe extern "C" fn(hstream: CUstream, pctx: *mut CUcontext) -> CUresult;
 
+// Graphs
+type FnCuStreamBeginCapture = unsafe extern "C" fn(hstream: CUstream, mode: c_int) -> CUresult;
+type FnCuStreamEndCapture = unsafe extern "C" fn(hstream: CUstream, phgraph: *mut CUgraph) -> CUresult;
+type FnCuStreamIsCapturing = unsafe extern "C" fn(hstream: CUstream, status: *mut c_int) -> CUresult;
+type FnCuGraphCreate = unsafe extern "C" fn(phgraph: *mut CUgraph, flags: c_uint) -> CUresult;
+type FnCuGraphDestroy = unsafe extern "C" fn(hgraph: CUgraph) -> CUresult;
+type FnCuGraphInstantiateWithFlags = unsafe extern "C" fn(phexec: *mut CUgraphExec, hgraph: CUgraph, flags: u64) -> CUresult;
+type FnCuGraphExecDestroy = unsafe extern "C" fn(hexec: CUgraphExec) -> CUresult;
+type FnCuGraphLaunch = unsafe extern "C" fn(hexec: CUgraphExec, hstream: CUstream) -> CUresult;
+type FnCuGraphUpload = unsafe extern "C" fn(hexec: CUgraphExec, hstream: CUstream) -> CUresult;
+
+// Texture and surface objects
+type FnCuTexObjectCreate = unsafe extern "C" fn(
+    ptex_object: *mut CUtexObject,
+    res_desc: *const CUDA_RESOURCE_DESC,
+    tex_desc: *const CUDA_TEXTURE_DESC,
+    view_desc: *const CUDA_RESOURCE_VIEW_DESC,
+) -> CUresult;
+type FnCuTexObjectDestroy = unsafe extern "C" fn(tex_object: CUtexObject) -> CUresult;
+type FnCuSurfObjectCreate = unsafe extern "C" fn(psurf_object: *mut CUsurfObject, res_desc: *const CUDA_RESOURCE_DESC) -> CUresult;
+type FnCuSurfObjectDestroy = unsafe extern "C" fn(surf_object: CUsurfObject) -> CUresult;
+
+// CUDA arrays
+type FnCuArray3DCreate = unsafe extern "C" fn(parray: *mut CUarray, desc: *const CUDA_ARRAY3D_DESCRIPTOR) -> CUresult;
+type FnCuArrayDestroy = unsafe extern "C" fn(array: CUarray) -> CUresult;
+
+// Legacy texture and surface references
+type FnCuModuleGetTexRef = unsafe extern "C" fn(ptexref: *mut CUtexref, hmod: CUmodule, name: *const c_char) -> CUresult;
+type FnCuModuleGetSurfRef = unsafe extern "C" fn(psurfref: *mut CUsurfref, hmod: CUmodule, name: *const c_char) -> CUresult;
+type FnCuTexRefSetAddress = unsafe extern "C" fn(
+    byte_offset: *mut usize,
+    htexref: CUtexref,
+    dptr: CUdeviceptr,
+    bytes: usize,
+) -> CUresult;
+type FnCuTexRefSetAddress2D = unsafe extern "C" fn(
+    htexref: CUtexref,
+    desc: *const CUDA_ARRAY_DESCRIPTOR,
+    dptr: CUdeviceptr,
+    pitch: usize,
+) -> CUresult;
+type FnCuTexRefSetArray = unsafe extern "C" fn(htexref: CUtexref, harray: CUarray, flags: c_uint) -> CUresult;
+type FnCuTexRefSetFormat = unsafe extern "C" fn(htexref: CUtexref, fmt: c_uint, num_packed_components: c_int) -> CUresult;
+type FnCuTexRefSetFlags = unsafe extern "C" fn(htexref: CUtexref, flags: c_uint) -> CUresult;
+type FnCuTexRefSetFilterMode = unsafe extern "C" fn(htexref: CUtexref, fm: c_uint) -> CUresult;
+type FnCuTexRefSetAddressMode = unsafe extern "C" fn(htexref: CUtexref, dim: c_int, am: c_uint) -> CUresult;
+type FnCuTexRefSetMaxAnisotropy = unsafe extern "C" fn(htexref: CUtexref, max_aniso: c_uint) -> CUresult;
+type FnCuTexRefSetBorderColor = unsafe extern "C" fn(htexref: CUtexref, border_color: *const f32) -> CUresult;
+type FnCuSurfRefSetArray = unsafe extern "C" fn(hsurfref: CUsurfref, harray: CUarray, flags: c_uint) -> CUresult;
+
+// IPC
+type FnCuIpcGetMemHandle = unsafe extern "C" fn(phandle: *mut CUipcMemHandle, dptr: CUdeviceptr) -> CUresult;
+
 // Event management
 type FnCuEventCreate = unsafe extern "C" fn(phevent: *mut CUevent, flags: c_uint) -> CUresult;
 type FnCuEventDestroy = unsafe extern "C" fn(hevent: CUevent) -> CUresult;
@@ -233,6 +514,8 @@ pub struct CudaDriver {
     cu_device_get_by_pci_bus_id: Option<FnCuDeviceGetByPCIBusId>,
     cu_device_can_access_peer: Option<FnCuDeviceCanAccessPeer>,
     cu_device_get_p2p_attribute: Option<FnCuDeviceGetP2PAttribute>,
+    cu_device_get_texture_1d_linear_max_width: Option<FnCuDeviceGetTexture1DLinearMaxWidth>,
+    cu_device_get_exec_affinity_support: Option<FnCuDeviceGetExecAffinitySupport>,
     cu_device_get_default_mem_pool: Option<FnCuDeviceGetDefaultMemPool>,
     cu_device_get_mem_pool: Option<FnCuDeviceGetMemPool>,
     cu_device_set_mem_pool: Option<FnCuDeviceSetMemPool>,
@@ -260,6 +543,8 @@ pub struct CudaDriver {
     cu_ctx_get_flags: Option<FnCuCtxGetFlags>,
     cu_ctx_set_flags: Option<FnCuCtxSetFlags>,
     cu_ctx_reset_persisting_l2_cache: Option<FnCuCtxResetPersistingL2Cache>,
+    cu_ctx_set_shared_mem_config: Option<FnCuCtxSetSharedMemConfig>,
+    cu_ctx_get_shared_mem_config: Option<FnCuCtxGetSharedMemConfig>,
     cu_ctx_enable_peer_access: Option<FnCuCtxEnablePeerAccess>,
     cu_ctx_disable_peer_access: Option<FnCuCtxDisablePeerAccess>,
     // Module management
@@ -268,7 +553,7 @@ pub struct CudaDriver {
     cu_module_get_function: FnCuModuleGetFunction,
     cu_module_get_global: FnCuModuleGetGlobal,
     cu_module_load: Option<FnCuModuleLoad>,
-    _cu_module_load_data_ex: Option<FnCuModuleLoadDataEx>,
+    cu_module_load_data_ex: Option<FnCuModuleLoadDataEx>,
     cu_module_load_fat_binary: Option<FnCuModuleLoadFatBinary>,
     // Linker
     cu_link_create: Option<FnCuLinkCreate>,
@@ -285,9 +570,19 @@ pub struct CudaDriver {
     cu_memcpy_htod_async: Option<FnCuMemcpyHtoDAsync>,
     cu_memcpy_dtoh_async: Option<FnCuMemcpyDtoHAsync>,
     cu_memcpy_dtod_async: Option<FnCuMemcpyDtoDAsync>,
+    cu_memcpy_3d: Option<FnCuMemcpy3D>,
     cu_memset_d8: FnCuMemsetD8,
     cu_memset_d16: Option<FnCuMemsetD16>,
     cu_memset_d32: FnCuMemsetD32,
+    cu_memset_d8_async: Option<FnCuMemsetD8Async>,
+    cu_memset_d16_async: Option<FnCuMemsetD16Async>,
+    cu_memset_d32_async: Option<FnCuMemsetD32Async>,
+    cu_memset_d2d8: Option<FnCuMemsetD2D8>,
+    cu_memset_d2d16: Option<FnCuMemsetD2D16>,
+    cu_memset_d2d32: Option<FnCuMemsetD2D32>,
+    cu_memset_d2d8_async: Option<FnCuMemsetD2D8Async>,
+    cu_memset_d2d16_async: Option<FnCuMemsetD2D16Async>,
+    cu_memset_d2d32_async: Option<FnCuMemsetD2D32Async>,
     cu_mem_get_info: Option<FnCuMemGetInfo>,
     cu_mem_get_address_range: Option<FnCuMemGetAddressRange>,
     cu_mem_alloc_host: Option<FnCuMemAllocHost>,
@@ -309,6 +604,7 @@ pub struct CudaDriver {
     // Execution
     cu_launch_kernel: FnCuLaunchKernel,
     cu_func_get_attribute: Option<FnCuFuncGetAttribute>,
+    cu_func_get_param_info: Option<FnCuFuncGetParamInfo>,
     cu_func_set_attribute: Option<FnCuFuncSetAttribute>,
     cu_func_set_cache_config: Option<FnCuFuncSetCacheConfig>,
     cu_func_set_shared_mem_config: Option<FnCuFuncSetSharedMemConfig>,
@@ -327,6 +623,39 @@ pub struct CudaDriver {
     cu_stream_get_priority: Option<FnCuStreamGetPriority>,
     cu_stream_get_flags: Option<FnCuStreamGetFlags>,
     cu_stream_get_ctx: Option<FnCuStreamGetCtx>,
+    // Graphs
+    cu_stream_begin_capture: Option<FnCuStreamBeginCapture>,
+    cu_stream_end_capture: Option<FnCuStreamEndCapture>,
+    cu_stream_is_capturing: Option<FnCuStreamIsCapturing>,
+    cu_graph_create: Option<FnCuGraphCreate>,
+    cu_graph_destroy: Option<FnCuGraphDestroy>,
+    cu_graph_instantiate_with_flags: Option<FnCuGraphInstantiateWithFlags>,
+    cu_graph_exec_destroy: Option<FnCuGraphExecDestroy>,
+    cu_graph_launch: Option<FnCuGraphLaunch>,
+    cu_graph_upload: Option<FnCuGraphUpload>,
+    // Texture and surface objects
+    cu_tex_object_create: Option<FnCuTexObjectCreate>,
+    cu_tex_object_destroy: Option<FnCuTexObjectDestroy>,
+    cu_surf_object_create: Option<FnCuSurfObjectCreate>,
+    cu_surf_object_destroy: Option<FnCuSurfObjectDestroy>,
+    // CUDA arrays
+    cu_array_3d_create: Option<FnCuArray3DCreate>,
+    cu_array_destroy: Option<FnCuArrayDestroy>,
+    // Legacy texture and surface references
+    cu_module_get_tex_ref: Option<FnCuModuleGetTexRef>,
+    cu_module_get_surf_ref: Option<FnCuModuleGetSurfRef>,
+    cu_tex_ref_set_address: Option<FnCuTexRefSetAddress>,
+    cu_tex_ref_set_address_2d: Option<FnCuTexRefSetAddress2D>,
+    cu_tex_ref_set_array: Option<FnCuTexRefSetArray>,
+    cu_tex_ref_set_format: Option<FnCuTexRefSetFormat>,
+    cu_tex_ref_set_flags: Option<FnCuTexRefSetFlags>,
+    cu_tex_ref_set_filter_mode: Option<FnCuTexRefSetFilterMode>,
+    cu_tex_ref_set_address_mode: Option<FnCuTexRefSetAddressMode>,
+    cu_tex_ref_set_max_anisotropy: Option<FnCuTexRefSetMaxAnisotropy>,
+    cu_tex_ref_set_border_color: Option<FnCuTexRefSetBorderColor>,
+    cu_surf_ref_set_array: Option<FnCuSurfRefSetArray>,
+    // IPC
+    cu_ipc_get_mem_handle: Option<FnCuIpcGetMemHandle>,
     // Event management
     cu_event_create: FnCuEventCreate,
     cu_event_destroy: FnCuEventDestroy,
@@ -366,6 +695,8 @@ impl CudaDriver {
                 cu_device_get_by_pci_bus_id: Self::load_fn_opt(&lib, "cuDeviceGetByPCIBusId"),
                 cu_device_can_access_peer: Self::load_fn_opt(&lib, "cuDeviceCanAccessPeer"),
                 cu_device_get_p2p_attribute: Self::load_fn_opt(&lib, "cuDeviceGetP2PAttribute"),
+                cu_device_get_texture_1d_linear_max_width: Self::load_fn_opt(&lib, "cuDeviceGetTexture1DLinearMaxWidth"),
+                cu_device_get_exec_affinity_support: Self::load_fn_opt(&lib, "cuDeviceGetExecAffinitySupport"),
                 cu_device_get_default_mem_pool: Self::load_fn_opt(&lib, "cuDeviceGetDefaultMemPool"),
                 cu_device_get_mem_pool: Self::load_fn_opt(&lib, "cuDeviceGetMemPool"),
                 cu_device_set_mem_pool: Self::load_fn_opt(&lib, "cuDeviceSetMemPool"),
@@ -400,6 +731,8 @@ impl CudaDriver {
                 cu_ctx_get_flags: Self::load_fn_opt(&lib, "cuCtxGetFlags"),
                 cu_ctx_set_flags: Self::load_fn_opt(&lib, "cuCtxSetFlags"),
                 cu_ctx_reset_persisting_l2_cache: Self::load_fn_opt(&lib, "cuCtxResetPersistingL2Cache"),
+                cu_ctx_set_shared_mem_config: Self::load_fn_opt(&lib, "cuCtxSetSharedMemConfig"),
+                cu_ctx_get_shared_mem_config: Self::load_fn_opt(&lib, "cuCtxGetSharedMemConfig"),
                 cu_ctx_enable_peer_access: Self::load_fn_opt(&lib, "cuCtxEnablePeerAccess"),
                 cu_ctx_disable_peer_access: Self::load_fn_opt(&lib, "cuCtxDisablePeerAccess"),
                 // Module
@@ -409,7 +742,7 @@ impl CudaDriver {
                 cu_module_get_global: Self::load_fn(&lib, "cuModuleGetGlobal_v2")
                     .or_else(|_| Self::load_fn(&lib, "cuModuleGetGlobal"))?,
                 cu_module_load: Self::load_fn_opt(&lib, "cuModuleLoad"),
-                _cu_module_load_data_ex: Self::load_fn_opt(&lib, "cuModuleLoadDataEx"),
+                cu_module_load_data_ex: Self::load_fn_opt(&lib, "cuModuleLoadDataEx"),
                 cu_module_load_fat_binary: Self::load_fn_opt(&lib, "cuModuleLoadFatBinary"),
                 // Linker
                 cu_link_create: Self::load_fn_opt::<FnCuLinkCreate>(&lib, "cuLinkCreate_v2")
@@ -437,12 +770,26 @@ impl CudaDriver {
                     .or(Self::load_fn_opt(&lib, "cuMemcpyDtoHAsync")),
                 cu_memcpy_dtod_async: Self::load_fn_opt::<FnCuMemcpyDtoDAsync>(&lib, "cuMemcpyDtoDAsync_v2")
                     .or(Self::load_fn_opt(&lib, "cuMemcpyDtoDAsync")),
+                cu_memcpy_3d: Self::load_fn_opt::<FnCuMemcpy3D>(&lib, "cuMemcpy3D_v2")
+                    .or(Self::load_fn_opt(&lib, "cuMemcpy3D")),
                 cu_memset_d8: Self::load_fn(&lib, "cuMemsetD8_v2")
                     .or_else(|_| Self::load_fn(&lib, "cuMemsetD8"))?,
                 cu_memset_d16: Self::load_fn_opt::<FnCuMemsetD16>(&lib, "cuMemsetD16_v2")
                     .or(Self::load_fn_opt(&lib, "cuMemsetD16")),
                 cu_memset_d32: Self::load_fn(&lib, "cuMemsetD32_v2")
                     .or_else(|_| Self::load_fn(&lib, "cuMemsetD32"))?,
+                cu_memset_d8_async: Self::load_fn_opt(&lib, "cuMemsetD8Async"),
+                cu_memset_d16_async: Self::load_fn_opt(&lib, "cuMemsetD16Async"),
+                cu_memset_d32_async: Self::load_fn_opt(&lib, "cuMemsetD32Async"),
+                cu_memset_d2d8: Self::load_fn_opt::<FnCuMemsetD2D8>(&lib, "cuMemsetD2D8_v2")
+                    .or(Self::load_fn_opt(&lib, "cuMemsetD2D8")),
+                cu_memset_d2d16: Self::load_fn_opt::<FnCuMemsetD2D16>(&lib, "cuMemsetD2D16_v2")
+                    .or(Self::load_fn_opt(&lib, "cuMemsetD2D16")),
+                cu_memset_d2d32: Self::load_fn_opt::<FnCuMemsetD2D32>(&lib, "cuMemsetD2D32_v2")
+                    .or(Self::load_fn_opt(&lib, "cuMemsetD2D32")),
+                cu_memset_d2d8_async: Self::load_fn_opt(&lib, "cuMemsetD2D8Async"),
+                cu_memset_d2d16_async: Self::load_fn_opt(&lib, "cuMemsetD2D16Async"),
+                cu_memset_d2d32_async: Self::load_fn_opt(&lib, "cuMemsetD2D32Async"),
                 cu_mem_get_info: Self::load_fn_opt::<FnCuMemGetInfo>(&lib, "cuMemGetInfo_v2")
                     .or(Self::load_fn_opt(&lib, "cuMemGetInfo")),
                 cu_mem_get_address_range: Self::load_fn_opt::<FnCuMemGetAddressRange>(&lib, "cuMemGetAddressRange_v2")
@@ -472,6 +819,7 @@ impl CudaDriver {
                 // Execution
                 cu_launch_kernel: Self::load_fn(&lib, "cuLaunchKernel")?,
                 cu_func_get_attribute: Self::load_fn_opt(&lib, "cuFuncGetAttribute"),
+                cu_func_get_param_info: Self::load_fn_opt(&lib, "cuFuncGetParamInfo"),
                 cu_func_set_attribute: Self::load_fn_opt(&lib, "cuFuncSetAttribute"),
                 cu_func_set_cache_config: Self::load_fn_opt(&lib, "cuFuncSetCacheConfig"),
                 cu_func_set_shared_mem_config: Self::load_fn_opt(&lib, "cuFuncSetSharedMemConfig"),
@@ -492,6 +840,40 @@ impl CudaDriver {
                 cu_stream_get_flags: Self::load_fn_opt(&lib, "cuStreamGetFlags"),
                 cu_stream_get_ctx: Self::load_fn_opt::<FnCuStreamGetCtx>(&lib, "cuStreamGetCtx_v2")
                     .or(Self::load_fn_opt(&lib, "cuStreamGetCtx")),
+                // Graphs
+                cu_stream_begin_capture: Self::load_fn_opt::<FnCuStreamBeginCapture>(&lib, "cuStreamBeginCapture_v2")
+                    .or(Self::load_fn_opt(&lib, "cuStreamBeginCapture")),
+                cu_stream_end_capture: Self::load_fn_opt(&lib, "cuStreamEndCapture"),
+                cu_stream_is_capturing: Self::load_fn_opt(&lib, "cuStreamIsCapturing"),
+                cu_graph_create: Self::load_fn_opt(&lib, "cuGraphCreate"),
+                cu_graph_destroy: Self::load_fn_opt(&lib, "cuGraphDestroy"),
+                cu_graph_instantiate_with_flags: Self::load_fn_opt(&lib, "cuGraphInstantiateWithFlags"),
+                cu_graph_exec_destroy: Self::load_fn_opt(&lib, "cuGraphExecDestroy"),
+                cu_graph_launch: Self::load_fn_opt(&lib, "cuGraphLaunch"),
+                cu_graph_upload: Self::load_fn_opt(&lib, "cuGraphUpload"),
+                // Texture and surface objects
+                cu_tex_object_create: Self::load_fn_opt(&lib, "cuTexObjectCreate"),
+                cu_tex_object_destroy: Self::load_fn_opt(&lib, "cuTexObjectDestroy"),
+                cu_surf_object_create: Self::load_fn_opt(&lib, "cuSurfObjectCreate"),
+                cu_surf_object_destroy: Self::load_fn_opt(&lib, "cuSurfObjectDestroy"),
+                // CUDA arrays
+                cu_array_3d_create: Self::load_fn_opt(&lib, "cuArray3DCreate_v2"),
+                cu_array_destroy: Self::load_fn_opt(&lib, "cuArrayDestroy"),
+                // Legacy texture and surface references
+                cu_module_get_tex_ref: Self::load_fn_opt(&lib, "cuModuleGetTexRef"),
+                cu_module_get_surf_ref: Self::load_fn_opt(&lib, "cuModuleGetSurfRef"),
+                cu_tex_ref_set_address: Self::load_fn_opt(&lib, "cuTexRefSetAddress_v2"),
+                cu_tex_ref_set_address_2d: Self::load_fn_opt(&lib, "cuTexRefSetAddress2D_v3"),
+                cu_tex_ref_set_array: Self::load_fn_opt(&lib, "cuTexRefSetArray"),
+                cu_tex_ref_set_format: Self::load_fn_opt(&lib, "cuTexRefSetFormat"),
+                cu_tex_ref_set_flags: Self::load_fn_opt(&lib, "cuTexRefSetFlags"),
+                cu_tex_ref_set_filter_mode: Self::load_fn_opt(&lib, "cuTexRefSetFilterMode"),
+                cu_tex_ref_set_address_mode: Self::load_fn_opt(&lib, "cuTexRefSetAddressMode"),
+                cu_tex_ref_set_max_anisotropy: Self::load_fn_opt(&lib, "cuTexRefSetMaxAnisotropy"),
+                cu_tex_ref_set_border_color: Self::load_fn_opt(&lib, "cuTexRefSetBorderColor"),
+                cu_surf_ref_set_array: Self::load_fn_opt(&lib, "cuSurfRefSetArray"),
+                // IPC
+                cu_ipc_get_mem_handle: Self::load_fn_opt(&lib, "cuIpcGetMemHandle"),
                 // Event
                 cu_event_create: Self::load_fn(&lib, "cuEventCreate")?,
                 cu_event_destroy: Self::load_fn(&lib, "cuEventDestroy_v2")
@@ -650,6 +1032,28 @@ impl CudaDriver {
         }
     }
 
+    /// Largest 1D linear texture for a format (CUDA 11.1+).
+    pub fn device_get_texture_1d_linear_max_width(&self, format: u32, num_channels: u32, device: CUdevice) -> Result<usize, CUresult> {
+        if let Some(func) = self.cu_device_get_texture_1d_linear_max_width {
+            let mut width: usize = 0;
+            let res = unsafe { func(&mut width, format, num_channels, device) };
+            if res == CUDA_SUCCESS { Ok(width) } else { Err(res) }
+        } else {
+            Err(CUDA_ERROR_NOT_SUPPORTED)
+        }
+    }
+
+    /// Whether the device supports an execution affinity type (CUDA 11.4+).
+    pub fn device_get_exec_affinity_support(&self, affinity_type: i32, device: CUdevice) -> Result<bool, CUresult> {
+        if let Some(func) = self.cu_device_get_exec_affinity_support {
+            let mut supported: c_int = 0;
+            let res = unsafe { func(&mut supported, affinity_type, device) };
+            if res == CUDA_SUCCESS { Ok(supported != 0) } else { Err(res) }
+        } else {
+            Err(CUDA_ERROR_NOT_SUPPORTED)
+        }
+    }
+
     pub fn device_can_access_peer(&self, dev: CUdevice, peer_dev: CUdevice) -> Result<bool, CUresult> {
         if let Some(func) = self.cu_device_can_access_peer {
             let mut can: c_int = 0;
@@ -817,6 +1221,26 @@ impl CudaDriver {
         }
     }
 
+    /// Shared memory bank size for the current context (deprecated in CUDA 12
+    /// and a no-op on devices with fixed banks, but still exported).
+    pub fn ctx_set_shared_mem_config(&self, config: i32) -> CUresult {
+        if let Some(func) = self.cu_ctx_set_shared_mem_config {
+            unsafe { func(config) }
+        } else {
+            CUDA_ERROR_NOT_SUPPORTED
+        }
+    }
+
+    pub fn ctx_get_shared_mem_config(&self) -> Result<i32, CUresult> {
+        if let Some(func) = self.cu_ctx_get_shared_mem_config {
+            let mut config: c_int = 0;
+            let res = unsafe { func(&mut config) };
+            if res == CUDA_SUCCESS { Ok(config) } else { Err(res) }
+        } else {
+            Err(CUDA_ERROR_NOT_SUPPORTED)
+        }
+    }
+
     pub fn ctx_set_limit(&self, limit: i32, value: u64) -> CUresult {
         if let Some(func) = self.cu_ctx_set_limit {
             unsafe { func(limit, value as usize) }
@@ -938,9 +1362,22 @@ impl CudaDriver {
         }
     }
 
-    pub fn module_load_data_ex(&self, image: &[u8]) -> Result<CUmodule, CUresult> {
-        // Simplified: ignore JIT options, forward to ModuleLoadData
-        self.module_load_data(image)
+    pub fn module_load_data_ex(&self, image: &[u8], jit: &mut JitOptions) -> Result<CUmodule, CUresult> {
+        let Some(func) = self.cu_module_load_data_ex else {
+            // No options to apply without it
+            return self.module_load_data(image);
+        };
+        let mut module: CUmodule = std::ptr::null_mut();
+        let res = unsafe {
+            func(
+                &mut module,
+                image.as_ptr() as *const c_void,
+                jit.options.len() as c_uint,
+                jit.options.as_mut_ptr(),
+                jit.values.as_mut_ptr(),
+            )
+        };
+        if res == CUDA_SUCCESS { Ok(module) } else { Err(res) }
     }
 
     pub fn module_load_fat_binary(&self, fat_cubin: &[u8]) -> Result<CUmodule, CUresult> {
@@ -956,29 +1393,53 @@ impl CudaDriver {
 
     // ── Linker ────────────────────────────────────────────────────
 
-    pub fn link_create(&self) -> Result<CUlinkState, CUresult> {
+    /// Create a linker. The driver writes through `jit` until the linker is
+    /// destroyed.
+    pub fn link_create(&self, jit: &mut JitOptions) -> Result<CUlinkState, CUresult> {
         if let Some(func) = self.cu_link_create {
             let mut state: CUlinkState = std::ptr::null_mut();
-            let res = unsafe { func(0, std::ptr::null_mut(), std::ptr::null_mut(), &mut state) };
+            let res = unsafe {
+                func(jit.options.len() as c_uint, jit.options.as_mut_ptr(), jit.values.as_mut_ptr(), &mut state)
+            };
             if res == CUDA_SUCCESS { Ok(state) } else { Err(res) }
         } else {
             Err(CUDA_ERROR_NOT_SUPPORTED)
         }
     }
 
-    pub fn link_add_data(&self, state: CUlinkState, jit_type: i32, data: &[u8], name: &str) -> CUresult {
+    pub fn link_add_data(&self, state: CUlinkState, jit_type: i32, data: &[u8], name: &str, jit: &mut JitOptions) -> CUresult {
         if let Some(func) = self.cu_link_add_data {
             let c_name = match std::ffi::CString::new(name) { Ok(s) => s, Err(_) => return 1 };
-            unsafe { func(state, jit_type, data.as_ptr() as *mut c_void, data.len(), c_name.as_ptr(), 0, std::ptr::null_mut(), std::ptr::null_mut()) }
+            unsafe {
+                func(
+                    state,
+                    jit_type,
+                    data.as_ptr() as *mut c_void,
+                    data.len(),
+                    c_name.as_ptr(),
+                    jit.options.len() as c_uint,
+                    jit.options.as_mut_ptr(),
+                    jit.values.as_mut_ptr(),
+                )
+            }
         } else {
             CUDA_ERROR_NOT_SUPPORTED
         }
     }
 
-    pub fn link_add_file(&self, state: CUlinkState, jit_type: i32, path: &str) -> CUresult {
+    pub fn link_add_file(&self, state: CUlinkState, jit_type: i32, path: &str, jit: &mut JitOptions) -> CUresult {
         if let Some(func) = self.cu_link_add_file {
             let c_path = match std::ffi::CString::new(path) { Ok(s) => s, Err(_) => return 1 };
-            unsafe { func(state, jit_type, c_path.as_ptr(), 0, std::ptr::null_mut(), std::ptr::null_mut()) }
+            unsafe {
+                func(
+                    state,
+                    jit_type,
+                    c_path.as_ptr(),
+                    jit.options.len() as c_uint,
+                    jit.options.as_mut_ptr(),
+                    jit.values.as_mut_ptr(),
+                )
+            }
         } else {
             CUDA_ERROR_NOT_SUPPORTED
         }
@@ -1056,6 +1517,13 @@ impl CudaDriver {
         }
     }
 
+    pub fn memcpy_3d(&self, copy: &CUDA_MEMCPY3D) -> CUresult {
+        match self.cu_memcpy_3d {
+            Some(func) => unsafe { func(copy) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
     pub fn memset_d8(&self, dst: CUdeviceptr, value: u8, count: usize) -> CUresult {
         unsafe { (self.cu_memset_d8)(dst, value, count) }
     }
@@ -1072,6 +1540,93 @@ impl CudaDriver {
         unsafe { (self.cu_memset_d32)(dst, value, count) }
     }
 
+    pub fn memset_d8_async(&self, dst: CUdeviceptr, value: u8, count: usize, stream: CUstream) -> CUresult {
+        match self.cu_memset_d8_async {
+            Some(func) => unsafe { func(dst, value, count, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d16_async(&self, dst: CUdeviceptr, value: u16, count: usize, stream: CUstream) -> CUresult {
+        match self.cu_memset_d16_async {
+            Some(func) => unsafe { func(dst, value, count, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d32_async(&self, dst: CUdeviceptr, value: u32, count: usize, stream: CUstream) -> CUresult {
+        match self.cu_memset_d32_async {
+            Some(func) => unsafe { func(dst, value, count, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d2d8(&self, dst: CUdeviceptr, dst_pitch: usize, value: u8, width: usize, height: usize) -> CUresult {
+        match self.cu_memset_d2d8 {
+            Some(func) => unsafe { func(dst, dst_pitch, value, width, height) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d2d16(&self, dst: CUdeviceptr, dst_pitch: usize, value: u16, width: usize, height: usize) -> CUresult {
+        match self.cu_memset_d2d16 {
+            Some(func) => unsafe { func(dst, dst_pitch, value, width, height) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d2d32(&self, dst: CUdeviceptr, dst_pitch: usize, value: u32, width: usize, height: usize) -> CUresult {
+        match self.cu_memset_d2d32 {
+            Some(func) => unsafe { func(dst, dst_pitch, value, width, height) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d2d8_async(
+        &self,
+        dst: CUdeviceptr,
+        dst_pitch: usize,
+        value: u8,
+        width: usize,
+        height: usize,
+        stream: CUstream,
+    ) -> CUresult {
+        match self.cu_memset_d2d8_async {
+            Some(func) => unsafe { func(dst, dst_pitch, value, width, height, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d2d16_async(
+        &self,
+        dst: CUdeviceptr,
+        dst_pitch: usize,
+        value: u16,
+        width: usize,
+        height: usize,
+        stream: CUstream,
+    ) -> CUresult {
+        match self.cu_memset_d2d16_async {
+            Some(func) => unsafe { func(dst, dst_pitch, value, width, height, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn memset_d2d32_async(
+        &self,
+        dst: CUdeviceptr,
+        dst_pitch: usize,
+        value: u32,
+        width: usize,
+        height: usize,
+        stream: CUstream,
+    ) -> CUresult {
+        match self.cu_memset_d2d32_async {
+            Some(func) => unsafe { func(dst, dst_pitch, value, width, height, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
     pub fn mem_get_info(&self) -> Result<(usize, usize), CUresult> {
         if let Some(func) = self.cu_mem_get_info {
             let mut free: usize = 0;
@@ -1212,6 +1767,10 @@ impl CudaDriver {
 
     // ── Execution ─────────────────────────────────────────────────
 
+    /// Launch a kernel.
+    ///
+    /// # Safety
+    /// `kernel_params` must point at argument buffers matching the kernel's signature.
     pub unsafe fn launch_kernel(
         &self,
         func: CUfunction,
@@ -1242,6 +1801,28 @@ impl CudaDriver {
         }
     }
 
+    pub fn has_func_param_info(&self) -> bool {
+        self.cu_func_get_param_info.is_some()
+    }
+
+    /// Size of each kernel parameter, in order (CUDA 12.4+). `None` if the
+    /// driver can't tell.
+    pub fn func_param_sizes(&self, func: CUfunction) -> Option<Vec<u32>> {
+        let f = self.cu_func_get_param_info?;
+        let mut sizes = Vec::new();
+        loop {
+            let mut offset: usize = 0;
+            let mut size: usize = 0;
+            let res = unsafe { f(func, sizes.len(), &mut offset, &mut size) };
+            match res {
+                CUDA_SUCCESS => sizes.push(size as u32),
+                // Past the last parameter
+                CUDA_ERROR_INVALID_VALUE => return Some(sizes),
+                _ => return None,
+            }
+        }
+    }
+
     pub fn func_set_attribute(&self, func: CUfunction, attrib: i32, value: i32) -> CUresult {
         if let Some(f) = self.cu_func_set_attribute {
             unsafe { f(func, attrib, value) }
@@ -1391,6 +1972,228 @@ impl CudaDriver {
         }
     }
 
+    // ── Graphs ────────────────────────────────────────────────────
+
+    pub fn stream_begin_capture(&self, stream: CUstream, mode: c_int) -> CUresult {
+        match self.cu_stream_begin_capture {
+            Some(func) => unsafe { func(stream, mode) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn stream_end_capture(&self, stream: CUstream) -> Result<CUgraph, CUresult> {
+        let func = self.cu_stream_end_capture.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut graph: CUgraph = std::ptr::null_mut();
+        let res = unsafe { func(stream, &mut graph) };
+        if res == CUDA_SUCCESS { Ok(graph) } else { Err(res) }
+    }
+
+    /// `CUstreamCaptureStatus` of a stream.
+    pub fn stream_is_capturing(&self, stream: CUstream) -> Result<u32, CUresult> {
+        let func = self.cu_stream_is_capturing.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut status: c_int = 0;
+        let res = unsafe { func(stream, &mut status) };
+        if res == CUDA_SUCCESS { Ok(status as u32) } else { Err(res) }
+    }
+
+    pub fn graph_create(&self, flags: u32) -> Result<CUgraph, CUresult> {
+        let func = self.cu_graph_create.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut graph: CUgraph = std::ptr::null_mut();
+        let res = unsafe { func(&mut graph, flags as c_uint) };
+        if res == CUDA_SUCCESS { Ok(graph) } else { Err(res) }
+    }
+
+    pub fn graph_destroy(&self, graph: CUgraph) -> CUresult {
+        match self.cu_graph_destroy {
+            Some(func) => unsafe { func(graph) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn graph_instantiate(&self, graph: CUgraph, flags: u64) -> Result<CUgraphExec, CUresult> {
+        let func = self.cu_graph_instantiate_with_flags.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut exec: CUgraphExec = std::ptr::null_mut();
+        let res = unsafe { func(&mut exec, graph, flags) };
+        if res == CUDA_SUCCESS { Ok(exec) } else { Err(res) }
+    }
+
+    pub fn graph_exec_destroy(&self, exec: CUgraphExec) -> CUresult {
+        match self.cu_graph_exec_destroy {
+            Some(func) => unsafe { func(exec) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn graph_launch(&self, exec: CUgraphExec, stream: CUstream) -> CUresult {
+        match self.cu_graph_launch {
+            Some(func) => unsafe { func(exec, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn graph_upload(&self, exec: CUgraphExec, stream: CUstream) -> CUresult {
+        match self.cu_graph_upload {
+            Some(func) => unsafe { func(exec, stream) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    // ── Texture and Surface Objects ───────────────────────────────
+
+    pub fn tex_object_create(
+        &self,
+        res_desc: &CUDA_RESOURCE_DESC,
+        tex_desc: &CUDA_TEXTURE_DESC,
+        view_desc: Option<&CUDA_RESOURCE_VIEW_DESC>,
+    ) -> Result<CUtexObject, CUresult> {
+        let func = self.cu_tex_object_create.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let view = view_desc.map_or(std::ptr::null(), |v| v as *const CUDA_RESOURCE_VIEW_DESC);
+        let mut object: CUtexObject = 0;
+        let res = unsafe { func(&mut object, res_desc, tex_desc, view) };
+        if res == CUDA_SUCCESS { Ok(object) } else { Err(res) }
+    }
+
+    pub fn tex_object_destroy(&self, object: CUtexObject) -> CUresult {
+        match self.cu_tex_object_destroy {
+            Some(func) => unsafe { func(object) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn surf_object_create(&self, res_desc: &CUDA_RESOURCE_DESC) -> Result<CUsurfObject, CUresult> {
+        let func = self.cu_surf_object_create.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut object: CUsurfObject = 0;
+        let res = unsafe { func(&mut object, res_desc) };
+        if res == CUDA_SUCCESS { Ok(object) } else { Err(res) }
+    }
+
+    pub fn surf_object_destroy(&self, object: CUsurfObject) -> CUresult {
+        match self.cu_surf_object_destroy {
+            Some(func) => unsafe { func(object) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    // ── CUDA Arrays ───────────────────────────────────────────────
+
+    pub fn array_3d_create(&self, desc: &CUDA_ARRAY3D_DESCRIPTOR) -> Result<CUarray, CUresult> {
+        let func = self.cu_array_3d_create.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut array: CUarray = std::ptr::null_mut();
+        let res = unsafe { func(&mut array, desc) };
+        if res == CUDA_SUCCESS { Ok(array) } else { Err(res) }
+    }
+
+    pub fn array_destroy(&self, array: CUarray) -> CUresult {
+        match self.cu_array_destroy {
+            Some(func) => unsafe { func(array) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    // ── Legacy Texture and Surface References ─────────────────────
+
+    pub fn module_get_tex_ref(&self, module: CUmodule, name: &str) -> Result<CUtexref, CUresult> {
+        let func = self.cu_module_get_tex_ref.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let c_name = std::ffi::CString::new(name).map_err(|_| CUDA_ERROR_INVALID_VALUE)?;
+        let mut texref: CUtexref = std::ptr::null_mut();
+        let res = unsafe { func(&mut texref, module, c_name.as_ptr()) };
+        if res == CUDA_SUCCESS { Ok(texref) } else { Err(res) }
+    }
+
+    pub fn module_get_surf_ref(&self, module: CUmodule, name: &str) -> Result<CUsurfref, CUresult> {
+        let func = self.cu_module_get_surf_ref.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let c_name = std::ffi::CString::new(name).map_err(|_| CUDA_ERROR_INVALID_VALUE)?;
+        let mut surfref: CUsurfref = std::ptr::null_mut();
+        let res = unsafe { func(&mut surfref, module, c_name.as_ptr()) };
+        if res == CUDA_SUCCESS { Ok(surfref) } else { Err(res) }
+    }
+
+    /// Bind linear memory; returns the offset texture fetches must add.
+    pub fn tex_ref_set_address(&self, texref: CUtexref, dptr: CUdeviceptr, bytes: usize) -> Result<usize, CUresult> {
+        let func = self.cu_tex_ref_set_address.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut offset: usize = 0;
+        let res = unsafe { func(&mut offset, texref, dptr, bytes) };
+        if res == CUDA_SUCCESS { Ok(offset) } else { Err(res) }
+    }
+
+    pub fn tex_ref_set_address_2d(
+        &self,
+        texref: CUtexref,
+        desc: &CUDA_ARRAY_DESCRIPTOR,
+        dptr: CUdeviceptr,
+        pitch: usize,
+    ) -> CUresult {
+        match self.cu_tex_ref_set_address_2d {
+            Some(func) => unsafe { func(texref, desc, dptr, pitch) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn tex_ref_set_array(&self, texref: CUtexref, array: CUarray, flags: u32) -> CUresult {
+        match self.cu_tex_ref_set_array {
+            Some(func) => unsafe { func(texref, array, flags) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn tex_ref_set_format(&self, texref: CUtexref, format: u32, num_packed_components: i32) -> CUresult {
+        match self.cu_tex_ref_set_format {
+            Some(func) => unsafe { func(texref, format, num_packed_components) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn tex_ref_set_flags(&self, texref: CUtexref, flags: u32) -> CUresult {
+        match self.cu_tex_ref_set_flags {
+            Some(func) => unsafe { func(texref, flags) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn tex_ref_set_filter_mode(&self, texref: CUtexref, mode: u32) -> CUresult {
+        match self.cu_tex_ref_set_filter_mode {
+            Some(func) => unsafe { func(texref, mode) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn tex_ref_set_address_mode(&self, texref: CUtexref, dim: i32, mode: u32) -> CUresult {
+        match self.cu_tex_ref_set_address_mode {
+            Some(func) => unsafe { func(texref, dim, mode) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn tex_ref_set_max_anisotropy(&self, texref: CUtexref, max_anisotropy: u32) -> CUresult {
+        match self.cu_tex_ref_set_max_anisotropy {
+            Some(func) => unsafe { func(texref, max_anisotropy) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn tex_ref_set_border_color(&self, texref: CUtexref, color: &[f32; 4]) -> CUresult {
+        match self.cu_tex_ref_set_border_color {
+            Some(func) => unsafe { func(texref, color.as_ptr()) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    pub fn surf_ref_set_array(&self, surfref: CUsurfref, array: CUarray, flags: u32) -> CUresult {
+        match self.cu_surf_ref_set_array {
+            Some(func) => unsafe { func(surfref, array, flags) },
+            None => CUDA_ERROR_NOT_SUPPORTED,
+        }
+    }
+
+    // ── IPC ───────────────────────────────────────────────────────
+
+    pub fn ipc_get_mem_handle(&self, dptr: CUdeviceptr) -> Result<CUipcMemHandle, CUresult> {
+        let func = self.cu_ipc_get_mem_handle.ok_or(CUDA_ERROR_NOT_SUPPORTED)?;
+        let mut handle = CUipcMemHandle { reserved: [0; 64] };
+        let res = unsafe { func(&mut handle, dptr) };
+        if res == CUDA_SUCCESS { Ok(handle) } else { Err(res) }
+    }
+
     // ── Event Management ──────────────────────────────────────────
 
     pub fn event_create(&self, flags: u32) -> Result<CUevent, CUresult> {
@@ -1471,6 +2274,8 @@ pub fn cuda_error_name(result: CUresult) -> &'static str {
         702 => "CUDA_ERROR_LAUNCH_TIMEOUT",
         719 => "CUDA_ERROR_LAUNCH_FAILED",
         801 => "CUDA_ERROR_NOT_SUPPORTED",
+        900 => "CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED",
+        901 => "CUDA_ERROR_STREAM_CAPTURE_INVALIDATED",
         _ => "CUDA_ERROR_UNKNOWN",
     }
 }
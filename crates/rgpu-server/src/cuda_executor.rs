@@ -1,14 +1,44 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
-use dashmap::DashMap;
+use dashmap::{DashMap, DashSet};
 use tracing::{debug, error, info, warn};
 
-use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::codec::TransferCodec;
+use rgpu_protocol::compat::Feature;
+use rgpu_protocol::cuda_commands::{
+    ArrayDescriptor, CudaCommand, CudaResponse, KernelParam, Memcpy3DParams, MemcpyRegion,
+    IpcMemHandle, MemoryType, ResourceDesc, ResourceViewDesc, TexRefSetting, TextureDesc, TextureResource,
+};
 use rgpu_protocol::handle::{NetworkHandle, ResourceType};
-
-use crate::cuda_driver::{self, CudaDriver, CUDA_ERROR_NOT_SUPPORTED, CUDA_SUCCESS};
+use rgpu_protocol::messages::DeviceChange;
+
+use crate::cuda_driver::{
+    self, CudaDriver, CUDA_ERROR_DEVICE_UNAVAILABLE, CUDA_ERROR_HOST_MEMORY_NOT_REGISTERED, CUDA_ERROR_NOT_SUPPORTED,
+    CUDA_ERROR_OUT_OF_MEMORY, CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED, CUDA_MEMCPY3D,
+    CUDA_ARRAY3D_DESCRIPTOR, CUDA_ARRAY_DESCRIPTOR, CUDA_RESOURCE_DESC, CUDA_RESOURCE_VIEW_DESC, CUDA_SUCCESS,
+    CUDA_TEXTURE_DESC, CU_MEMHOSTREGISTER_DEVICEMAP, CU_MEMHOSTREGISTER_PORTABLE,
+    CU_RESOURCE_TYPE_ARRAY, CU_RESOURCE_TYPE_LINEAR, CU_RESOURCE_TYPE_PITCH2D,
+    CU_STREAM_CAPTURE_MODE_RELAXED, JitOptions,
+};
+use crate::content_store::ContentStore;
+use crate::device_attributes;
+use crate::device_overrides::DeviceOverrides;
+use crate::kernel_params::{self, ParamTable};
+use crate::scheduling::Scheduler;
 use crate::session::Session;
+use crate::usage;
+use crate::virtual_gpus::{Slice, VirtualGpus};
+use crate::vram::{Api, Charge, DeviceUuid, VramLedger};
+
+/// Device-to-host copies are split into chunks of this size so a cancelled
+/// request stops between chunks instead of finishing a huge transfer.
+const DTOH_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+/// Host buffer size used to expand fill patterns that no memset covers.
+const FILL_CHUNK_BYTES: usize = 4 * 1024 * 1024;
 
 /// Server-side CUDA command executor.
 /// Executes CUDA driver API commands on real GPU hardware via dynamically loaded CUDA driver.
@@ -23,28 +53,82 @@ pub struct CudaExecutor {
     context_handles: DashMap<NetworkHandle, cuda_driver::CUcontext>,
     /// Maps NetworkHandle -> real CUmodule pointer
     module_handles: DashMap<NetworkHandle, cuda_driver::CUmodule>,
+    /// Kernel parameter sizes read from each module's image, kept only when
+    /// the driver can't report them itself
+    module_params: DashMap<NetworkHandle, Arc<ParamTable>>,
+    /// Images of the modules loaded, for `ModuleLoadByHash`
+    module_images: ContentStore,
     /// Maps NetworkHandle -> real CUfunction pointer
     function_handles: DashMap<NetworkHandle, cuda_driver::CUfunction>,
     /// Maps NetworkHandle -> real CUdeviceptr (GPU memory address)
     memory_handles: DashMap<NetworkHandle, cuda_driver::CUdeviceptr>,
     /// Maps NetworkHandle -> allocated byte size for memory
     memory_sizes: DashMap<NetworkHandle, u64>,
+    /// Maps NetworkHandle -> transfer codec set by rgpuMemSetTransferHint
+    transfer_codecs: DashMap<NetworkHandle, TransferCodec>,
     /// Maps NetworkHandle -> real CUstream pointer
     stream_handles: DashMap<NetworkHandle, cuda_driver::CUstream>,
     /// Maps NetworkHandle -> real CUevent pointer
     event_handles: DashMap<NetworkHandle, cuda_driver::CUevent>,
     /// Maps NetworkHandle -> real host memory pointer (cuMemAllocHost / cuMemHostAlloc)
     host_memory_handles: DashMap<NetworkHandle, *mut c_void>,
+    /// Maps host memory NetworkHandle -> allocation size in bytes
+    host_memory_sizes: DashMap<NetworkHandle, u64>,
     /// Maps NetworkHandle -> real CUmemoryPool pointer
     mempool_handles: DashMap<NetworkHandle, cuda_driver::CUmemoryPool>,
     /// Maps NetworkHandle -> real CUlinkState pointer
     linker_handles: DashMap<NetworkHandle, cuda_driver::CUlinkState>,
+    /// JIT options of the linkers created with any, which the driver writes
+    /// through until the linker is destroyed
+    linker_jit: DashMap<NetworkHandle, JitOptions>,
+    /// Maps NetworkHandle -> real CUgraph pointer
+    graph_handles: DashMap<NetworkHandle, cuda_driver::CUgraph>,
+    /// Maps NetworkHandle -> real CUgraphExec pointer
+    graph_exec_handles: DashMap<NetworkHandle, cuda_driver::CUgraphExec>,
+    /// Maps NetworkHandle -> real CUtexObject / CUsurfObject
+    texture_objects: DashMap<NetworkHandle, u64>,
+    /// Maps NetworkHandle -> real CUarray pointer
+    array_handles: DashMap<NetworkHandle, cuda_driver::CUarray>,
+    /// Maps NetworkHandle -> real CUtexref / CUsurfref pointer
+    texture_refs: DashMap<NetworkHandle, *mut c_void>,
+    /// Allocations exported with cuIpcGetMemHandle, by token. Every session
+    /// lives in this process, so opening one aliases the allocation rather
+    /// than going through cuIpcOpenMemHandle, which refuses handles from
+    /// its own process.
+    ipc_exports: DashMap<u64, NetworkHandle>,
+    next_ipc_token: AtomicU64,
+    /// Streams between cuStreamBeginCapture and cuStreamEndCapture
+    capturing_streams: DashSet<NetworkHandle>,
+    /// Per-device VRAM accounting, shared with the Vulkan executor
+    vram: Arc<VramLedger>,
+    /// Which sessions may use each GPU, shared with the Vulkan executor
+    scheduler: Arc<Scheduler>,
+    /// What the GPUs report in place of the driver's answers
+    overrides: Arc<DeviceOverrides>,
+    /// GPUs shown to clients as several, shared with the Vulkan executor
+    virtual_gpus: Arc<VirtualGpus>,
+    /// Ordinal and slice of the device handles naming virtual GPUs
+    virtual_devices: DashMap<NetworkHandle, (u32, Slice)>,
 }
 
+/// Bytes of module images kept for `ModuleLoadByHash`.
+const MODULE_IMAGE_BUDGET: usize = 256 * 1024 * 1024;
+
+/// CU_DEVICE_ATTRIBUTE_MULTIPROCESSOR_COUNT
+const MULTIPROCESSOR_COUNT: i32 = 16;
+
 // SAFETY: CUDA driver pointers are valid across threads when used with proper context management
 unsafe impl Send for CudaExecutor {}
 unsafe impl Sync for CudaExecutor {}
 
+/// Where one side of a 2D/3D copy is on the server.
+enum CopyLocation {
+    /// Packed rows in the command or response
+    Host,
+    Device(cuda_driver::CUdeviceptr),
+    Array(cuda_driver::CUarray),
+}
+
 impl CudaExecutor {
     pub fn new(gpu_infos: Vec<rgpu_protocol::gpu_info::GpuInfo>) -> Self {
         // Try to load the CUDA driver
@@ -76,15 +160,92 @@ impl CudaExecutor {
             device_handles: DashMap::new(),
             context_handles: DashMap::new(),
             module_handles: DashMap::new(),
+            module_params: DashMap::new(),
+            module_images: ContentStore::new(MODULE_IMAGE_BUDGET),
             function_handles: DashMap::new(),
             memory_handles: DashMap::new(),
             memory_sizes: DashMap::new(),
+            transfer_codecs: DashMap::new(),
             stream_handles: DashMap::new(),
             event_handles: DashMap::new(),
             host_memory_handles: DashMap::new(),
+            host_memory_sizes: DashMap::new(),
             mempool_handles: DashMap::new(),
             linker_handles: DashMap::new(),
+            linker_jit: DashMap::new(),
+            graph_handles: DashMap::new(),
+            graph_exec_handles: DashMap::new(),
+            texture_objects: DashMap::new(),
+            array_handles: DashMap::new(),
+            texture_refs: DashMap::new(),
+            ipc_exports: DashMap::new(),
+            next_ipc_token: AtomicU64::new(1),
+            capturing_streams: DashSet::new(),
+            vram: Arc::new(VramLedger::unlimited()),
+            scheduler: Arc::new(Scheduler::default()),
+            overrides: Arc::new(DeviceOverrides::default()),
+            virtual_gpus: Arc::new(VirtualGpus::default()),
+            virtual_devices: DashMap::new(),
+        }
+    }
+
+    /// Account allocations in `ledger` instead of a private one.
+    pub fn with_vram_ledger(mut self, ledger: Arc<VramLedger>) -> Self {
+        self.vram = ledger;
+        self
+    }
+
+    /// Admit sessions to GPUs through `scheduler`.
+    pub fn with_scheduler(mut self, scheduler: Arc<Scheduler>) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Report device properties as `overrides` says, whatever the driver
+    /// says.
+    pub fn with_device_overrides(mut self, overrides: Arc<DeviceOverrides>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Show clients the GPUs `virtual_gpus` splits as several.
+    pub fn with_virtual_gpus(mut self, virtual_gpus: Arc<VirtualGpus>) -> Self {
+        self.virtual_gpus = virtual_gpus;
+        self
+    }
+
+    /// UUID of the GPU a device handle names, for its overrides.
+    fn device_uuid(&self, device: &NetworkHandle) -> Option<DeviceUuid> {
+        let real_dev = *self.device_handles.get(device)?;
+        self.driver.as_deref()?.device_get_uuid(real_dev).ok()
+    }
+
+    /// Refuse a context on a GPU the scheduler won't give the session, or
+    /// a virtual GPU another session holds.
+    fn admit(
+        &self,
+        d: &CudaDriver,
+        session: &Session,
+        device: &NetworkHandle,
+        real_dev: cuda_driver::CUdevice,
+    ) -> Result<(), CudaResponse> {
+        let Ok(uuid) = d.device_get_uuid(real_dev) else {
+            return Ok(());
+        };
+        let refuse = |reason: String| {
+            warn!(session_id = session.session_id, "context refused: {}", reason);
+            CudaResponse::Error {
+                code: CUDA_ERROR_DEVICE_UNAVAILABLE,
+                message: reason,
+            }
+        };
+        self.scheduler.admit(session.session_id, &uuid).map_err(|refused| refuse(refused.to_string()))?;
+        if let Some((ordinal, slice)) = self.virtual_devices.get(device).map(|v| *v) {
+            if self.virtual_gpus.claim(session.session_id, ordinal).map_err(|taken| refuse(taken.to_string()))? {
+                self.vram.add_session_device_quota(session.session_id, uuid, slice.memory);
+            }
         }
+        Ok(())
     }
 
     /// Check if the real CUDA driver is available.
@@ -95,6 +256,166 @@ impl CudaExecutor {
         })
     }
 
+    /// Charge an allocation on the current context's device to the session.
+    /// `None` if the device can't be identified, in which case it isn't
+    /// accounted.
+    fn charge_vram(
+        &self,
+        d: &CudaDriver,
+        session: &Session,
+        bytes: u64,
+    ) -> Result<Option<Charge>, CudaResponse> {
+        let Ok(device) = d.ctx_get_device().and_then(|dev| d.device_get_uuid(dev)) else {
+            return Ok(None);
+        };
+        let charge = Charge {
+            session_id: session.session_id,
+            device,
+            api: Api::Cuda,
+            bytes,
+        };
+        match self.vram.try_charge(&charge) {
+            Ok(()) => Ok(Some(charge)),
+            Err(e) => {
+                warn!(
+                    session_id = session.session_id,
+                    "allocation of {} bytes refused: session VRAM quota {} bytes, {} in use",
+                    bytes, e.quota, e.used
+                );
+                Err(CudaResponse::Error {
+                    code: CUDA_ERROR_OUT_OF_MEMORY,
+                    message: "session VRAM quota exceeded".to_string(),
+                })
+            }
+        }
+    }
+
+    /// Settle a charge from [`Self::charge_vram`] once the allocation is done.
+    fn settle_vram(&self, charge: Option<Charge>, allocated: Option<NetworkHandle>) {
+        let Some(charge) = charge else { return };
+        match allocated {
+            Some(handle) => self.vram.track(handle, charge),
+            None => self.vram.uncharge(&charge),
+        }
+    }
+
+    /// The real stream if `stream` is being captured. Work queued on such a
+    /// stream has to go through the driver's async calls so it ends up in
+    /// the graph, rather than running synchronously as it otherwise does.
+    fn capturing_stream(&self, stream: &NetworkHandle) -> Option<cuda_driver::CUstream> {
+        if !self.capturing_streams.contains(stream) {
+            return None;
+        }
+        self.stream_handles.get(stream).map(|s| *s)
+    }
+
+    /// Real executable graph and stream (NULL for the default stream) for
+    /// cuGraphLaunch / cuGraphUpload.
+    fn graph_exec_and_stream(
+        &self,
+        exec: &NetworkHandle,
+        stream: &NetworkHandle,
+    ) -> Result<(cuda_driver::CUgraphExec, cuda_driver::CUstream), CudaResponse> {
+        let real_exec = self.graph_exec_handles.get(exec).map(|e| *e).ok_or(CudaResponse::Error {
+            code: 400,
+            message: "invalid graph exec handle".to_string(),
+        })?;
+        let real_stream = self
+            .stream_handles
+            .get(stream)
+            .map(|s| *s)
+            .unwrap_or(std::ptr::null_mut());
+        Ok((real_exec, real_stream))
+    }
+
+    /// A stream destroyed mid-capture: end the capture and drop the partial
+    /// graph so the stream can be destroyed.
+    fn end_abandoned_capture(&self, d: &CudaDriver, stream: &NetworkHandle, real_stream: cuda_driver::CUstream) {
+        if self.capturing_streams.remove(stream).is_some() {
+            if let Ok(graph) = d.stream_end_capture(real_stream) {
+                d.graph_destroy(graph);
+            }
+        }
+    }
+
+    /// The answer to a linker call that returned `res`: with what the JIT
+    /// compiler wrote through the linker's options, if it was created with
+    /// any.
+    fn link_response(&self, link: &NetworkHandle, res: cuda_driver::CUresult, cubin_data: Vec<u8>) -> CudaResponse {
+        match self.linker_jit.get(link) {
+            Some(jit) => CudaResponse::LinkJit {
+                code: res,
+                cubin_data,
+                jit: jit.output(),
+            },
+            None if res == CUDA_SUCCESS => CudaResponse::Success,
+            None => Self::cuda_err(res),
+        }
+    }
+
+    /// cuModuleLoadData of `image`.
+    fn module_load_data(&self, session: &Session, image: &[u8]) -> CudaResponse {
+        let d = match self.driver() {
+            Ok(d) => d,
+            Err(e) => return e,
+        };
+
+        match d.module_load_data(image) {
+            Ok(module) => {
+                let handle = session.alloc_handle(ResourceType::CuModule);
+                self.module_handles.insert(handle, module);
+                self.record_param_table(d, handle, image);
+                debug!(
+                    session_id = session.session_id,
+                    "ModuleLoadData ({} bytes) -> {:?}", image.len(), handle
+                );
+                CudaResponse::Module(handle)
+            }
+            Err(e) => Self::cuda_err(e),
+        }
+    }
+
+    /// Keep the kernel parameter sizes in a module's image for
+    /// `ModuleGetFunction`, unless the driver can report them itself.
+    fn record_param_table(&self, d: &CudaDriver, module: NetworkHandle, image: &[u8]) {
+        if !d.has_func_param_info() {
+            self.module_params.insert(module, Arc::new(kernel_params::param_table(image)));
+        }
+    }
+
+    /// Argument buffers for a launch, with device pointer arguments (sent as
+    /// an allocation handle and an offset into it) replaced by real addresses.
+    fn kernel_arguments(&self, kernel_params: &[KernelParam]) -> Result<Vec<Vec<u8>>, CudaResponse> {
+        kernel_params
+            .iter()
+            .map(|p| {
+                let Some(handle) = p.device_ptr else {
+                    return Ok(p.data.clone());
+                };
+                if matches!(handle.resource_type, ResourceType::CuTexObject | ResourceType::CuSurfObject) {
+                    let object = self.texture_objects.get(&handle).map(|o| *o).ok_or_else(|| {
+                        CudaResponse::Error {
+                            code: cuda_driver::CUDA_ERROR_INVALID_VALUE,
+                            message: "kernel argument is an unknown texture or surface object".to_string(),
+                        }
+                    })?;
+                    return Ok(object.to_le_bytes().to_vec());
+                }
+                let base = self.memory_handles.get(&handle).map(|b| *b).ok_or_else(|| {
+                    CudaResponse::Error {
+                        code: cuda_driver::CUDA_ERROR_INVALID_VALUE,
+                        message: "kernel argument points to unknown device memory".to_string(),
+                    }
+                })?;
+                let offset = match <[u8; 8]>::try_from(p.data.as_slice()) {
+                    Ok(bytes) => u64::from_le_bytes(bytes),
+                    Err(_) => 0,
+                };
+                Ok(base.wrapping_add(offset).to_le_bytes().to_vec())
+            })
+            .collect()
+    }
+
     /// Convert a CUresult to a CudaResponse::Error.
     fn cuda_err(code: cuda_driver::CUresult) -> CudaResponse {
         CudaResponse::Error {
@@ -103,8 +424,392 @@ impl CudaExecutor {
         }
     }
 
+    /// Fill with a pattern no memset covers by copying a host buffer of
+    /// repetitions, at most [`FILL_CHUNK_BYTES`] at a time.
+    fn fill_by_copy(
+        d: &CudaDriver,
+        dst: cuda_driver::CUdeviceptr,
+        pattern: &[u8],
+        count: u64,
+    ) -> cuda_driver::CUresult {
+        let total = pattern.len() as u64 * count;
+        let per_chunk = (FILL_CHUNK_BYTES / pattern.len()).max(1);
+        let chunk = pattern.repeat(per_chunk.min(count as usize));
+        let mut offset = 0u64;
+        while offset < total {
+            let len = (chunk.len() as u64).min(total - offset) as usize;
+            let res = d.memcpy_htod(dst + offset, &chunk[..len]);
+            if res != CUDA_SUCCESS {
+                return res;
+            }
+            offset += len as u64;
+        }
+        CUDA_SUCCESS
+    }
+
+    /// The driver's descriptor for `desc`, with the allocation it reads
+    /// resolved to its real address.
+    fn resource_desc(&self, desc: &ResourceDesc) -> Result<CUDA_RESOURCE_DESC, CudaResponse> {
+        let base = |dptr: &NetworkHandle| {
+            self.memory_handles.get(dptr).map(|b| *b).ok_or(CudaResponse::Error {
+                code: 400,
+                message: "invalid memory handle".to_string(),
+            })
+        };
+        let mut raw = CUDA_RESOURCE_DESC {
+            flags: desc.flags,
+            ..Default::default()
+        };
+        match &desc.resource {
+            TextureResource::Linear {
+                dptr,
+                offset,
+                format,
+                num_channels,
+                size_in_bytes,
+            } => {
+                raw.res_type = CU_RESOURCE_TYPE_LINEAR;
+                raw.res[0] = base(dptr)? + offset;
+                raw.res[1] = *format as u64 | (*num_channels as u64) << 32;
+                raw.res[2] = *size_in_bytes;
+            }
+            TextureResource::Pitch2D {
+                dptr,
+                offset,
+                format,
+                num_channels,
+                width,
+                height,
+                pitch_in_bytes,
+            } => {
+                raw.res_type = CU_RESOURCE_TYPE_PITCH2D;
+                raw.res[0] = base(dptr)? + offset;
+                raw.res[1] = *format as u64 | (*num_channels as u64) << 32;
+                raw.res[2] = *width;
+                raw.res[3] = *height;
+                raw.res[4] = *pitch_in_bytes;
+            }
+            TextureResource::Array { array } => {
+                raw.res_type = CU_RESOURCE_TYPE_ARRAY;
+                raw.res[0] = self.array(array)? as u64;
+            }
+        }
+        Ok(raw)
+    }
+
+    fn array(&self, array: &NetworkHandle) -> Result<cuda_driver::CUarray, CudaResponse> {
+        self.array_handles.get(array).map(|a| *a).ok_or(CudaResponse::Error {
+            code: 400,
+            message: "invalid array handle".to_string(),
+        })
+    }
+
+    /// The real texture or surface reference behind `handle`.
+    fn texture_ref(&self, handle: &NetworkHandle) -> Result<*mut c_void, CudaResponse> {
+        self.texture_refs.get(handle).map(|r| *r).ok_or(CudaResponse::Error {
+            code: 400,
+            message: "invalid texture reference handle".to_string(),
+        })
+    }
+
+    /// Apply one cuTexRefSet* call to `texref`.
+    fn set_texture_ref(&self, d: &CudaDriver, texref: *mut c_void, setting: &TexRefSetting) -> Result<(), CudaResponse> {
+        let res = match setting {
+            TexRefSetting::Address2D {
+                dptr,
+                offset,
+                format,
+                num_channels,
+                width,
+                height,
+                pitch,
+            } => {
+                let base = self.memory_handles.get(dptr).map(|b| *b).ok_or(CudaResponse::Error {
+                    code: 400,
+                    message: "invalid memory handle".to_string(),
+                })?;
+                let desc = CUDA_ARRAY_DESCRIPTOR {
+                    width: *width as usize,
+                    height: *height as usize,
+                    format: *format,
+                    num_channels: *num_channels,
+                };
+                d.tex_ref_set_address_2d(texref, &desc, base + offset, *pitch as usize)
+            }
+            TexRefSetting::Array { array, flags } => d.tex_ref_set_array(texref, self.array(array)?, *flags),
+            TexRefSetting::Format {
+                format,
+                num_packed_components,
+            } => d.tex_ref_set_format(texref, *format, *num_packed_components),
+            TexRefSetting::Flags(flags) => d.tex_ref_set_flags(texref, *flags),
+            TexRefSetting::FilterMode(mode) => d.tex_ref_set_filter_mode(texref, *mode),
+            TexRefSetting::AddressMode { dim, mode } => d.tex_ref_set_address_mode(texref, *dim, *mode),
+            TexRefSetting::MaxAnisotropy(max) => d.tex_ref_set_max_anisotropy(texref, *max),
+            TexRefSetting::BorderColor(color) => d.tex_ref_set_border_color(texref, color),
+        };
+        if res == CUDA_SUCCESS {
+            Ok(())
+        } else {
+            Err(Self::cuda_err(res))
+        }
+    }
+
+    /// Exports of `dptr` can no longer be opened once it is freed; mappings
+    /// already opened dangle, as with the driver.
+    fn forget_ipc_exports(&self, dptr: &NetworkHandle) {
+        self.ipc_exports.retain(|_, exported| exported != dptr);
+    }
+
+    fn ipc_mem_not_freeable() -> CudaResponse {
+        CudaResponse::Error {
+            code: 1,
+            message: "memory opened with cuIpcOpenMemHandle is released with cuIpcCloseMemHandle".to_string(),
+        }
+    }
+
+    fn texture_desc(desc: &TextureDesc) -> CUDA_TEXTURE_DESC {
+        CUDA_TEXTURE_DESC {
+            address_mode: desc.address_mode,
+            filter_mode: desc.filter_mode,
+            flags: desc.flags,
+            max_anisotropy: desc.max_anisotropy,
+            mipmap_filter_mode: desc.mipmap_filter_mode,
+            mipmap_level_bias: desc.mipmap_level_bias,
+            min_mipmap_level_clamp: desc.min_mipmap_level_clamp,
+            max_mipmap_level_clamp: desc.max_mipmap_level_clamp,
+            border_color: desc.border_color,
+            ..Default::default()
+        }
+    }
+
+    fn view_desc(desc: &ResourceViewDesc) -> CUDA_RESOURCE_VIEW_DESC {
+        CUDA_RESOURCE_VIEW_DESC {
+            format: desc.format,
+            width: desc.width as usize,
+            height: desc.height as usize,
+            depth: desc.depth as usize,
+            first_mipmap_level: desc.first_mipmap_level,
+            last_mipmap_level: desc.last_mipmap_level,
+            first_layer: desc.first_layer,
+            last_layer: desc.last_layer,
+            ..Default::default()
+        }
+    }
+
+    fn array_descriptor(desc: &ArrayDescriptor) -> CUDA_ARRAY3D_DESCRIPTOR {
+        CUDA_ARRAY3D_DESCRIPTOR {
+            width: desc.width as usize,
+            height: desc.height as usize,
+            depth: desc.depth as usize,
+            format: desc.format,
+            num_channels: desc.num_channels,
+            flags: desc.flags,
+        }
+    }
+
+    /// cuMemsetD2D*: `set` runs on the allocation `dst`, with the real
+    /// stream if `stream` is being captured. Otherwise it runs synchronously,
+    /// like the 1D async memsets, since the network is the bottleneck.
+    fn memset_2d(
+        &self,
+        dst: &NetworkHandle,
+        stream: Option<&NetworkHandle>,
+        set: impl FnOnce(&CudaDriver, cuda_driver::CUdeviceptr, Option<cuda_driver::CUstream>) -> cuda_driver::CUresult,
+    ) -> CudaResponse {
+        let d = match self.driver() {
+            Ok(d) => d,
+            Err(e) => return e,
+        };
+        let Some(real_ptr) = self.memory_handles.get(dst).map(|p| *p) else {
+            return CudaResponse::Error {
+                code: 400,
+                message: "invalid memory handle".to_string(),
+            };
+        };
+        let res = set(d, real_ptr, stream.and_then(|s| self.capturing_stream(s)));
+        if res == CUDA_SUCCESS {
+            CudaResponse::Success
+        } else {
+            Self::cuda_err(res)
+        }
+    }
+
+    /// cuMemcpy2D/3D. Host sides are the packed rows carried in the command
+    /// or returned in the response, so they have no offset or padding.
+    fn memcpy_3d(&self, params: &Memcpy3DParams, src_data: &[u8]) -> CudaResponse {
+        let d = match self.driver() {
+            Ok(d) => d,
+            Err(e) => return e,
+        };
+        let packed_size = params.packed_size() as usize;
+
+        let mut copy = CUDA_MEMCPY3D {
+            width_in_bytes: params.width_in_bytes as usize,
+            height: params.height as usize,
+            depth: params.depth as usize,
+            ..Default::default()
+        };
+        match self.memcpy_location(&params.src) {
+            Ok(CopyLocation::Device(ptr)) => {
+                copy.src_memory_type = MemoryType::Device.to_raw();
+                copy.src_device = ptr;
+                copy.src_x_in_bytes = params.src.x_in_bytes as usize;
+                copy.src_y = params.src.y as usize;
+                copy.src_z = params.src.z as usize;
+                copy.src_pitch = params.src.pitch as usize;
+                copy.src_height = params.src.height as usize;
+            }
+            Ok(CopyLocation::Array(array)) => {
+                copy.src_memory_type = MemoryType::Array.to_raw();
+                copy.src_array = array;
+                copy.src_x_in_bytes = params.src.x_in_bytes as usize;
+                copy.src_y = params.src.y as usize;
+                copy.src_z = params.src.z as usize;
+            }
+            Ok(CopyLocation::Host) => {
+                if src_data.len() != packed_size {
+                    return CudaResponse::Error {
+                        code: 1,
+                        message: format!("expected {} bytes of source rows, got {}", packed_size, src_data.len()),
+                    };
+                }
+                copy.src_memory_type = MemoryType::Host.to_raw();
+                copy.src_host = src_data.as_ptr() as *const c_void;
+                copy.src_pitch = copy.width_in_bytes;
+                copy.src_height = copy.height;
+            }
+            Err(e) => return e,
+        }
+
+        let mut dst_data = Vec::new();
+        match self.memcpy_location(&params.dst) {
+            Ok(CopyLocation::Device(ptr)) => {
+                copy.dst_memory_type = MemoryType::Device.to_raw();
+                copy.dst_device = ptr;
+                copy.dst_x_in_bytes = params.dst.x_in_bytes as usize;
+                copy.dst_y = params.dst.y as usize;
+                copy.dst_z = params.dst.z as usize;
+                copy.dst_pitch = params.dst.pitch as usize;
+                copy.dst_height = params.dst.height as usize;
+            }
+            Ok(CopyLocation::Array(array)) => {
+                copy.dst_memory_type = MemoryType::Array.to_raw();
+                copy.dst_array = array;
+                copy.dst_x_in_bytes = params.dst.x_in_bytes as usize;
+                copy.dst_y = params.dst.y as usize;
+                copy.dst_z = params.dst.z as usize;
+            }
+            Ok(CopyLocation::Host) => {
+                dst_data = vec![0u8; packed_size];
+                copy.dst_memory_type = MemoryType::Host.to_raw();
+                copy.dst_host = dst_data.as_mut_ptr() as *mut c_void;
+                copy.dst_pitch = copy.width_in_bytes;
+                copy.dst_height = copy.height;
+            }
+            Err(e) => return e,
+        }
+
+        let res = d.memcpy_3d(&copy);
+        if res != CUDA_SUCCESS {
+            Self::cuda_err(res)
+        } else if params.dst.memory_type == MemoryType::Host {
+            CudaResponse::MemoryData(dst_data)
+        } else {
+            CudaResponse::Success
+        }
+    }
+
+    /// Where one side of a 2D/3D copy is on the server.
+    fn memcpy_location(&self, region: &MemcpyRegion) -> Result<CopyLocation, CudaResponse> {
+        match (region.memory_type, region.handle) {
+            (MemoryType::Host, _) => Ok(CopyLocation::Host),
+            (MemoryType::Device | MemoryType::Unified, Some(handle)) => {
+                match self.memory_handles.get(&handle) {
+                    Some(p) => Ok(CopyLocation::Device(*p)),
+                    None => Err(CudaResponse::Error {
+                        code: 400,
+                        message: "invalid memory handle".to_string(),
+                    }),
+                }
+            }
+            (MemoryType::Array, Some(handle)) => self.array(&handle).map(CopyLocation::Array),
+            (_, None) => Err(CudaResponse::Error {
+                code: 1,
+                message: "device side of copy has no handle".to_string(),
+            }),
+        }
+    }
+
+    /// DtoH result for `src`, encoded if the allocation has a transfer codec.
+    fn memory_data(&self, src: &NetworkHandle, data: Vec<u8>) -> CudaResponse {
+        match self.transfer_codecs.get(src).map(|c| *c) {
+            Some(codec) => CudaResponse::EncodedMemoryData {
+                data: codec.encode(&data),
+                codec,
+            },
+            None => CudaResponse::MemoryData(data),
+        }
+    }
+
+    /// Devices `session_id` has looked up, as the driver numbers them.
+    fn session_devices(&self, session_id: u32) -> BTreeSet<cuda_driver::CUdevice> {
+        self.device_handles
+            .iter()
+            .filter(|entry| entry.key().session_id == session_id)
+            .map(|entry| *entry.value())
+            .collect()
+    }
+
+    /// Release cached-but-unused memory held by the default pools of the
+    /// devices `session_id` uses. Other devices' pools are left to the
+    /// sessions on them.
+    fn trim_default_mem_pools(&self, d: &CudaDriver, session_id: u32) {
+        for device in self.session_devices(session_id) {
+            if let Ok(pool) = d.device_get_default_mem_pool(device) {
+                let res = d.mem_pool_trim_to(pool, 0);
+                debug!("trimmed default mem pool of device {} (result={})", device, res);
+            }
+        }
+    }
+
+    /// Copy device memory into a new host buffer chunk by chunk, giving up
+    /// (and dropping the staging buffer) as soon as `cancel` is set.
+    fn memcpy_dtoh_chunked(
+        d: &CudaDriver,
+        src: cuda_driver::CUdeviceptr,
+        byte_count: usize,
+        cancel: &AtomicBool,
+    ) -> Result<Vec<u8>, CudaResponse> {
+        let mut buf = vec![0u8; byte_count];
+        let mut offset = 0u64;
+        for chunk in buf.chunks_mut(DTOH_CHUNK_SIZE) {
+            if cancel.load(Ordering::Relaxed) {
+                return Err(CudaResponse::Error {
+                    code: 999, // CUDA_ERROR_UNKNOWN
+                    message: "request cancelled".to_string(),
+                });
+            }
+            let res = d.memcpy_dtoh(chunk, src + offset);
+            if res != CUDA_SUCCESS {
+                return Err(Self::cuda_err(res));
+            }
+            offset += chunk.len() as u64;
+        }
+        Ok(buf)
+    }
+
     /// Execute a CUDA command and return the response.
     pub fn execute(&self, session: &Session, cmd: CudaCommand) -> CudaResponse {
+        self.execute_cancellable(session, cmd, &AtomicBool::new(false))
+    }
+
+    /// Execute a CUDA command, checking `cancel` during long-running transfers.
+    pub fn execute_cancellable(
+        &self,
+        session: &Session,
+        cmd: CudaCommand,
+        cancel: &AtomicBool,
+    ) -> CudaResponse {
         match cmd {
             CudaCommand::Init { flags } => {
                 info!(
@@ -129,6 +834,9 @@ impl CudaExecutor {
             }
 
             CudaCommand::DeviceGetCount => {
+                if let Some(count) = self.virtual_gpus.count() {
+                    return CudaResponse::DeviceCount(count);
+                }
                 match self.driver() {
                     Ok(d) => match d.device_get_count() {
                         Ok(count) => {
@@ -147,10 +855,16 @@ impl CudaExecutor {
             }
 
             CudaCommand::DeviceGet { ordinal } => {
+                let Some((physical, slice)) = self.virtual_gpus.resolve(ordinal as u32) else {
+                    return Self::cuda_err(cuda_driver::CUDA_ERROR_INVALID_DEVICE);
+                };
                 let handle = session.alloc_handle(ResourceType::CuDevice);
+                if let Some(slice) = slice {
+                    self.virtual_devices.insert(handle, (ordinal as u32, slice));
+                }
 
                 if let Ok(d) = self.driver() {
-                    match d.device_get(ordinal) {
+                    match Self::resolve_ordinal(d, session, physical as i32) {
                         Ok(real_device) => {
                             self.device_handles.insert(handle, real_device);
                             debug!(
@@ -161,17 +875,21 @@ impl CudaExecutor {
                         }
                         Err(e) => {
                             session.remove_handle(&handle);
+                            self.virtual_devices.remove(&handle);
                             Self::cuda_err(e)
                         }
                     }
                 } else {
                     // Fallback
-                    self.device_handles.insert(handle, ordinal);
+                    self.device_handles.insert(handle, physical as i32);
                     CudaResponse::Device(handle)
                 }
             }
 
             CudaCommand::DeviceGetName { device } => {
+                if let Some(name) = self.overrides.name(self.device_uuid(&device).as_ref()) {
+                    return CudaResponse::DeviceName(name.to_string());
+                }
                 if let Some(real_dev) = self.device_handles.get(&device) {
                     if let Ok(d) = self.driver() {
                         match d.device_get_name(*real_dev) {
@@ -191,19 +909,40 @@ impl CudaExecutor {
             }
 
             CudaCommand::DeviceGetAttribute { attrib, device } => {
+                if let Some(value) = self.overrides.attribute(self.device_uuid(&device).as_ref(), attrib) {
+                    return CudaResponse::DeviceAttribute(value);
+                }
+                let fallback = device_attributes::fallback(attrib);
                 if let Some(real_dev) = self.device_handles.get(&device) {
                     if let Ok(d) = self.driver() {
                         match d.device_get_attribute(attrib, *real_dev) {
+                            Ok(val) if attrib == MULTIPROCESSOR_COUNT => {
+                                let val = match self.virtual_devices.get(&device) {
+                                    Some(v) => v.1.multiprocessors(val),
+                                    None => val,
+                                };
+                                return CudaResponse::DeviceAttribute(val);
+                            }
                             Ok(val) => return CudaResponse::DeviceAttribute(val),
+                            // Newer than this driver
+                            Err(cuda_driver::CUDA_ERROR_INVALID_VALUE) if fallback.is_some() => {}
                             Err(e) => return Self::cuda_err(e),
                         }
                     }
                 }
-                // Fallback
-                CudaResponse::DeviceAttribute(self.get_device_attribute_fallback(attrib))
+                match fallback {
+                    Some(value) => CudaResponse::DeviceAttribute(value),
+                    None => Self::cuda_err(cuda_driver::CUDA_ERROR_INVALID_VALUE),
+                }
             }
 
             CudaCommand::DeviceTotalMem { device } => {
+                if let Some(v) = self.virtual_devices.get(&device) {
+                    return CudaResponse::DeviceTotalMem(v.1.memory);
+                }
+                if let Some(bytes) = self.overrides.total_memory(self.device_uuid(&device).as_ref()) {
+                    return CudaResponse::DeviceTotalMem(bytes);
+                }
                 if let Some(real_dev) = self.device_handles.get(&device) {
                     if let Ok(d) = self.driver() {
                         match d.device_total_mem(*real_dev) {
@@ -222,6 +961,9 @@ impl CudaExecutor {
             }
 
             CudaCommand::DeviceComputeCapability { device } => {
+                if let Some((major, minor)) = self.overrides.compute_capability(self.device_uuid(&device).as_ref()) {
+                    return CudaResponse::ComputeCapability { major, minor };
+                }
                 if let Some(real_dev) = self.device_handles.get(&device) {
                     if let Ok(d) = self.driver() {
                         match d.device_compute_capability(*real_dev) {
@@ -415,15 +1157,45 @@ impl CudaExecutor {
                 }
             }
 
-            CudaCommand::DeviceGetTexture1DLinearMaxWidth { format: _, num_channels: _, device: _ } => {
-                // This API is rarely used and requires cuDeviceGetTexture1DLinearMaxWidth
-                // which is not commonly loaded. Return a reasonable default.
-                CudaResponse::Texture1DMaxWidth(1 << 27) // 128M texels
+            CudaCommand::DeviceGetTexture1DLinearMaxWidth { format, num_channels, device } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_dev = match self.device_handles.get(&device) {
+                    Some(dev) => *dev,
+                    None => return CudaResponse::Error {
+                        code: 101,
+                        message: "invalid device handle".to_string(),
+                    },
+                };
+                match d.device_get_texture_1d_linear_max_width(format, num_channels, real_dev) {
+                    Ok(width) => CudaResponse::Texture1DMaxWidth(width as u64),
+                    // Drivers before 11.1: the 1D linear texture limit every
+                    // architecture since Maxwell has.
+                    Err(CUDA_ERROR_NOT_SUPPORTED) => CudaResponse::Texture1DMaxWidth(1 << 27),
+                    Err(e) => Self::cuda_err(e),
+                }
             }
 
-            CudaCommand::DeviceGetExecAffinitySupport { affinity_type: _, device: _ } => {
-                // cuDeviceGetExecAffinitySupport - not commonly available, return false
-                CudaResponse::BoolResult(false)
+            CudaCommand::DeviceGetExecAffinitySupport { affinity_type, device } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_dev = match self.device_handles.get(&device) {
+                    Some(dev) => *dev,
+                    None => return CudaResponse::Error {
+                        code: 101,
+                        message: "invalid device handle".to_string(),
+                    },
+                };
+                match d.device_get_exec_affinity_support(affinity_type, real_dev) {
+                    Ok(supported) => CudaResponse::BoolResult(supported),
+                    // Drivers before 11.4 have no execution affinity.
+                    Err(CUDA_ERROR_NOT_SUPPORTED) => CudaResponse::BoolResult(false),
+                    Err(e) => Self::cuda_err(e),
+                }
             }
 
             // ── Primary Context ────────────────────────────────────
@@ -440,8 +1212,14 @@ impl CudaExecutor {
                         message: "invalid device handle".to_string(),
                     },
                 };
+                if let Err(refused) = self.admit(d, session, &device, real_dev) {
+                    return refused;
+                }
                 match d.device_primary_ctx_retain(real_dev) {
                     Ok(ctx) => {
+                        if let Ok(uuid) = d.device_get_uuid(real_dev) {
+                            session.set_device(uuid);
+                        }
                         let handle = session.alloc_handle(ResourceType::CuContext);
                         self.context_handles.insert(handle, ctx);
                         debug!(
@@ -550,8 +1328,14 @@ impl CudaExecutor {
                     }
                 };
 
+                if let Err(refused) = self.admit(d, session, &device, real_dev) {
+                    return refused;
+                }
                 match d.ctx_create(flags, real_dev) {
                     Ok(ctx) => {
+                        if let Ok(uuid) = d.device_get_uuid(real_dev) {
+                            session.set_device(uuid);
+                        }
                         let handle = session.alloc_handle(ResourceType::CuContext);
                         self.context_handles.insert(handle, ctx);
                         debug!(
@@ -827,25 +1611,24 @@ impl CudaExecutor {
             // ── Module Management ───────────────────────────────────
 
             CudaCommand::ModuleLoadData { image } => {
-                let d = match self.driver() {
-                    Ok(d) => d,
-                    Err(e) => return e,
-                };
-
-                match d.module_load_data(&image) {
-                    Ok(module) => {
-                        let handle = session.alloc_handle(ResourceType::CuModule);
-                        self.module_handles.insert(handle, module);
-                        debug!(
-                            session_id = session.session_id,
-                            "ModuleLoadData ({} bytes) -> {:?}", image.len(), handle
-                        );
-                        CudaResponse::Module(handle)
-                    }
-                    Err(e) => Self::cuda_err(e),
+                let response = self.module_load_data(session, &image);
+                if matches!(response, CudaResponse::Module(_)) {
+                    self.module_images.insert(&image);
                 }
+                response
             }
 
+            CudaCommand::ModuleLoadByHash { hash } => match self.module_images.get(&hash) {
+                // The token's scope was checked against the hash, not the image
+                Some(image) if !session.allows_ptx() && kernel_params::is_ptx(&image) => CudaResponse::Error {
+                    // CUDA_ERROR_NOT_PERMITTED
+                    code: 800,
+                    message: "loading PTX is not allowed for this token".to_string(),
+                },
+                Some(image) => self.module_load_data(session, &image),
+                None => CudaResponse::ModuleImageMissing,
+            },
+
             CudaCommand::ModuleUnload { module } => {
                 let d = match self.driver() {
                     Ok(d) => d,
@@ -854,6 +1637,7 @@ impl CudaExecutor {
 
                 match self.module_handles.remove(&module) {
                     Some((_, real_mod)) => {
+                        self.module_params.remove(&module);
                         let res = d.module_unload(real_mod);
                         session.remove_handle(&module);
                         if res == CUDA_SUCCESS {
@@ -889,11 +1673,18 @@ impl CudaExecutor {
                     Ok(func) => {
                         let handle = session.alloc_handle(ResourceType::CuFunction);
                         self.function_handles.insert(handle, func);
+                        let param_sizes = d.func_param_sizes(func).or_else(|| {
+                            self.module_params.get(&module).and_then(|t| t.get(&name).cloned())
+                        });
                         debug!(
                             session_id = session.session_id,
-                            "ModuleGetFunction('{}') -> {:?}", name, handle
+                            "ModuleGetFunction('{}') -> {:?}, param sizes {:?}", name, handle, param_sizes
                         );
-                        CudaResponse::Function(handle)
+                        if session.supports(Feature::KernelParamSizes) {
+                            CudaResponse::FunctionWithParams { handle, param_sizes }
+                        } else {
+                            CudaResponse::Function(handle)
+                        }
                     }
                     Err(e) => Self::cuda_err(e),
                 }
@@ -938,6 +1729,11 @@ impl CudaExecutor {
                     Ok(module) => {
                         let handle = session.alloc_handle(ResourceType::CuModule);
                         self.module_handles.insert(handle, module);
+                        if !d.has_func_param_info() {
+                            if let Ok(image) = std::fs::read(&fname) {
+                                self.record_param_table(d, handle, &image);
+                            }
+                        }
                         debug!(
                             session_id = session.session_id,
                             "ModuleLoad('{}') -> {:?}", fname, handle
@@ -948,23 +1744,36 @@ impl CudaExecutor {
                 }
             }
 
-            CudaCommand::ModuleLoadDataEx { image, num_options: _, options: _, option_values: _ } => {
+            CudaCommand::ModuleLoadDataEx { image, num_options: _, options, option_values } => {
                 let d = match self.driver() {
                     Ok(d) => d,
                     Err(e) => return e,
                 };
-                // Simplified: forward to module_load_data_ex which ignores JIT options
-                match d.module_load_data_ex(&image) {
-                    Ok(module) => {
-                        let handle = session.alloc_handle(ResourceType::CuModule);
-                        self.module_handles.insert(handle, module);
-                        debug!(
-                            session_id = session.session_id,
-                            "ModuleLoadDataEx ({} bytes) -> {:?}", image.len(), handle
-                        );
-                        CudaResponse::Module(handle)
-                    }
-                    Err(e) => Self::cuda_err(e),
+                let mut jit = JitOptions::new(&options, &option_values);
+                let loaded = d.module_load_data_ex(&image, &mut jit).map(|module| {
+                    let handle = session.alloc_handle(ResourceType::CuModule);
+                    self.module_handles.insert(handle, module);
+                    self.record_param_table(d, handle, &image);
+                    debug!(
+                        session_id = session.session_id,
+                        "ModuleLoadDataEx ({} bytes, {} options) -> {:?}", image.len(), options.len(), handle
+                    );
+                    handle
+                });
+                match loaded {
+                    // Clients that pass no options expect the plain answer
+                    Ok(handle) if options.is_empty() => CudaResponse::Module(handle),
+                    Err(e) if options.is_empty() => Self::cuda_err(e),
+                    Ok(handle) => CudaResponse::ModuleJit {
+                        module: Some(handle),
+                        code: CUDA_SUCCESS,
+                        jit: jit.output(),
+                    },
+                    Err(e) => CudaResponse::ModuleJit {
+                        module: None,
+                        code: e,
+                        jit: jit.output(),
+                    },
                 }
             }
 
@@ -977,6 +1786,7 @@ impl CudaExecutor {
                     Ok(module) => {
                         let handle = session.alloc_handle(ResourceType::CuModule);
                         self.module_handles.insert(handle, module);
+                        self.record_param_table(d, handle, &fat_cubin);
                         debug!(
                             session_id = session.session_id,
                             "ModuleLoadFatBinary ({} bytes) -> {:?}", fat_cubin.len(), handle
@@ -987,18 +1797,22 @@ impl CudaExecutor {
                 }
             }
 
-            CudaCommand::LinkCreate { num_options: _, options: _, option_values: _ } => {
+            CudaCommand::LinkCreate { num_options: _, options, option_values } => {
                 let d = match self.driver() {
                     Ok(d) => d,
                     Err(e) => return e,
                 };
-                match d.link_create() {
+                let mut jit = JitOptions::new(&options, &option_values);
+                match d.link_create(&mut jit) {
                     Ok(state) => {
                         let handle = session.alloc_handle(ResourceType::CuLinker);
                         self.linker_handles.insert(handle, state);
+                        if !options.is_empty() {
+                            self.linker_jit.insert(handle, jit);
+                        }
                         debug!(
                             session_id = session.session_id,
-                            "LinkCreate -> {:?}", handle
+                            "LinkCreate ({} options) -> {:?}", options.len(), handle
                         );
                         CudaResponse::Linker(handle)
                     }
@@ -1006,7 +1820,7 @@ impl CudaExecutor {
                 }
             }
 
-            CudaCommand::LinkAddData { link, jit_type, data, name, num_options: _, options: _, option_values: _ } => {
+            CudaCommand::LinkAddData { link, jit_type, data, name, num_options: _, options, option_values } => {
                 let d = match self.driver() {
                     Ok(d) => d,
                     Err(e) => return e,
@@ -1018,15 +1832,11 @@ impl CudaExecutor {
                         message: "invalid linker handle".to_string(),
                     },
                 };
-                let res = d.link_add_data(real_state, jit_type, &data, &name);
-                if res == CUDA_SUCCESS {
-                    CudaResponse::Success
-                } else {
-                    Self::cuda_err(res)
-                }
+                let res = d.link_add_data(real_state, jit_type, &data, &name, &mut JitOptions::new(&options, &option_values));
+                self.link_response(&link, res, Vec::new())
             }
 
-            CudaCommand::LinkAddFile { link, jit_type, path, num_options: _, options: _, option_values: _ } => {
+            CudaCommand::LinkAddFile { link, jit_type, path, num_options: _, options, option_values } => {
                 let d = match self.driver() {
                     Ok(d) => d,
                     Err(e) => return e,
@@ -1038,12 +1848,8 @@ impl CudaExecutor {
                         message: "invalid linker handle".to_string(),
                     },
                 };
-                let res = d.link_add_file(real_state, jit_type, &path);
-                if res == CUDA_SUCCESS {
-                    CudaResponse::Success
-                } else {
-                    Self::cuda_err(res)
-                }
+                let res = d.link_add_file(real_state, jit_type, &path, &mut JitOptions::new(&options, &option_values));
+                self.link_response(&link, res, Vec::new())
             }
 
             CudaCommand::LinkComplete { link } => {
@@ -1059,8 +1865,11 @@ impl CudaExecutor {
                     },
                 };
                 match d.link_complete(real_state) {
+                    Ok(cubin_data) if self.linker_jit.contains_key(&link) => {
+                        self.link_response(&link, CUDA_SUCCESS, cubin_data)
+                    }
                     Ok(cubin_data) => CudaResponse::LinkCompleted { cubin_data },
-                    Err(e) => Self::cuda_err(e),
+                    Err(e) => self.link_response(&link, e, Vec::new()),
                 }
             }
 
@@ -1072,6 +1881,7 @@ impl CudaExecutor {
                 match self.linker_handles.remove(&link) {
                     Some((_, real_state)) => {
                         let res = d.link_destroy(real_state);
+                        self.linker_jit.remove(&link);
                         session.remove_handle(&link);
                         if res == CUDA_SUCCESS {
                             CudaResponse::Success
@@ -1094,18 +1904,39 @@ impl CudaExecutor {
                     Err(e) => return e,
                 };
 
-                match d.mem_alloc(byte_size as usize) {
+                let charge = match self.charge_vram(d, session, byte_size) {
+                    Ok(c) => c,
+                    Err(e) => return e,
+                };
+
+                let result = match d.mem_alloc(byte_size as usize) {
+                    Err(CUDA_ERROR_OUT_OF_MEMORY) if session.retry_alloc_after_trim() => {
+                        info!(
+                            session_id = session.session_id,
+                            "MemAlloc({} bytes) out of memory, trimming pools and retrying", byte_size
+                        );
+                        self.trim_default_mem_pools(d, session.session_id);
+                        d.mem_alloc(byte_size as usize)
+                    }
+                    other => other,
+                };
+
+                match result {
                     Ok(dptr) => {
                         let handle = session.alloc_handle(ResourceType::CuDevicePtr);
                         self.memory_handles.insert(handle, dptr);
                         self.memory_sizes.insert(handle, byte_size);
+                        self.settle_vram(charge, Some(handle));
                         debug!(
                             session_id = session.session_id,
                             "MemAlloc({} bytes) -> {:?} (dptr=0x{:x})", byte_size, handle, dptr
                         );
                         CudaResponse::MemAllocated(handle)
                     }
-                    Err(e) => Self::cuda_err(e),
+                    Err(e) => {
+                        self.settle_vram(charge, None);
+                        Self::cuda_err(e)
+                    }
                 }
             }
 
@@ -1115,10 +1946,16 @@ impl CudaExecutor {
                     Err(e) => return e,
                 };
 
+                if dptr.resource_type == ResourceType::CuIpcMem {
+                    return Self::ipc_mem_not_freeable();
+                }
                 match self.memory_handles.remove(&dptr) {
                     Some((_, real_ptr)) => {
+                        self.forget_ipc_exports(&dptr);
                         let res = d.mem_free(real_ptr);
                         self.memory_sizes.remove(&dptr);
+                        self.vram.free(&dptr);
+                        self.transfer_codecs.remove(&dptr);
                         session.remove_handle(&dptr);
                         if res == CUDA_SUCCESS {
                             debug!(
@@ -1186,16 +2023,15 @@ impl CudaExecutor {
                     }
                 };
 
-                let mut buf = vec![0u8; byte_count as usize];
-                let res = d.memcpy_dtoh(&mut buf, real_ptr);
-                if res == CUDA_SUCCESS {
-                    debug!(
-                        session_id = session.session_id,
-                        "MemcpyDtoH({:?}, {} bytes)", src, byte_count
-                    );
-                    CudaResponse::MemoryData(buf)
-                } else {
-                    Self::cuda_err(res)
+                match Self::memcpy_dtoh_chunked(d, real_ptr, byte_count as usize, cancel) {
+                    Ok(buf) => {
+                        debug!(
+                            session_id = session.session_id,
+                            "MemcpyDtoH({:?}, {} bytes)", src, byte_count
+                        );
+                        self.memory_data(&src, buf)
+                    }
+                    Err(e) => e,
                 }
             }
 
@@ -1236,6 +2072,13 @@ impl CudaExecutor {
                 }
             }
 
+            CudaCommand::MemcpyHtoDAsync { stream, .. } | CudaCommand::MemcpyDtoHAsync { stream, .. }
+                if self.capturing_streams.contains(&stream) =>
+            {
+                // The client's host memory isn't there when the graph replays.
+                Self::cuda_err(CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED)
+            }
+
             CudaCommand::MemcpyHtoDAsync {
                 dst,
                 src_data,
@@ -1293,24 +2136,188 @@ impl CudaExecutor {
                     }
                 };
 
-                let mut buf = vec![0u8; byte_count as usize];
-                let res = d.memcpy_dtoh(&mut buf, real_ptr);
-                if res == CUDA_SUCCESS {
-                    debug!(
-                        session_id = session.session_id,
-                        "MemcpyDtoHAsync({:?}, {} bytes) [sync]", src, byte_count
-                    );
-                    CudaResponse::MemoryData(buf)
-                } else {
+                match Self::memcpy_dtoh_chunked(d, real_ptr, byte_count as usize, cancel) {
+                    Ok(buf) => {
+                        debug!(
+                            session_id = session.session_id,
+                            "MemcpyDtoHAsync({:?}, {} bytes) [sync]", src, byte_count
+                        );
+                        self.memory_data(&src, buf)
+                    }
+                    Err(e) => e,
+                }
+            }
+
+            CudaCommand::MemcpyDtoHDiff {
+                src,
+                byte_count,
+                block_size,
+                known_hashes,
+            } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+
+                let real_ptr = match self.memory_handles.get(&src) {
+                    Some(p) => *p,
+                    None => {
+                        return CudaResponse::Error {
+                            code: 400,
+                            message: "invalid source memory handle".to_string(),
+                        }
+                    }
+                };
+
+                match Self::memcpy_dtoh_chunked(d, real_ptr, byte_count as usize, cancel) {
+                    Ok(buf) => {
+                        let (changed_blocks, data) =
+                            rgpu_protocol::readback::diff_blocks(&buf, block_size, &known_hashes);
+                        debug!(
+                            session_id = session.session_id,
+                            "MemcpyDtoHDiff({:?}, {} bytes) -> {} changed block(s), {} bytes",
+                            src,
+                            byte_count,
+                            changed_blocks.len(),
+                            data.len()
+                        );
+                        CudaResponse::MemoryDiff { changed_blocks, data }
+                    }
+                    Err(e) => e,
+                }
+            }
+
+            CudaCommand::MemSetTransferCodec { dptr, codec } => {
+                if !self.memory_handles.contains_key(&dptr) {
+                    return CudaResponse::Error {
+                        code: 400,
+                        message: "invalid memory handle".to_string(),
+                    };
+                }
+                match codec {
+                    Some(codec) => {
+                        self.transfer_codecs.insert(dptr, codec);
+                    }
+                    None => {
+                        self.transfer_codecs.remove(&dptr);
+                    }
+                }
+                debug!(
+                    session_id = session.session_id,
+                    "MemSetTransferCodec({:?}, {:?})", dptr, codec
+                );
+                CudaResponse::Success
+            }
+
+            CudaCommand::MemcpyHtoDEncoded {
+                dst,
+                codec,
+                data,
+                byte_count,
+            } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+
+                let real_ptr = match self.memory_handles.get(&dst) {
+                    Some(p) => *p,
+                    None => {
+                        return CudaResponse::Error {
+                            code: 400,
+                            message: "invalid destination memory handle".to_string(),
+                        }
+                    }
+                };
+
+                let decoded = match codec.decode(&data, byte_count as usize) {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        return CudaResponse::Error {
+                            code: 1,
+                            message: format!("{:?} decode failed: {}", codec, e),
+                        }
+                    }
+                };
+                let res = d.memcpy_htod(real_ptr, &decoded);
+                if res == CUDA_SUCCESS {
+                    debug!(
+                        session_id = session.session_id,
+                        "MemcpyHtoDEncoded({:?}, {} bytes, {} on the wire)", dst, byte_count, data.len()
+                    );
+                    CudaResponse::Success
+                } else {
+                    Self::cuda_err(res)
+                }
+            }
+
+            CudaCommand::FillBufferTyped {
+                dst,
+                pattern,
+                count,
+            } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+
+                let real_ptr = match self.memory_handles.get(&dst) {
+                    Some(p) => *p,
+                    None => {
+                        return CudaResponse::Error {
+                            code: 400,
+                            message: "invalid destination memory handle".to_string(),
+                        }
+                    }
+                };
+
+                let pattern = rgpu_protocol::fill::shortest_period(&pattern);
+                let res = match pattern.len() {
+                    2 => d.memset_d16(real_ptr, u16::from_le_bytes([pattern[0], pattern[1]]), count as usize),
+                    4 => d.memset_d32(
+                        real_ptr,
+                        u32::from_le_bytes([pattern[0], pattern[1], pattern[2], pattern[3]]),
+                        count as usize,
+                    ),
+                    8 | 16 => Self::fill_by_copy(d, real_ptr, pattern, count),
+                    len => {
+                        return CudaResponse::Error {
+                            code: 1,
+                            message: format!("unsupported fill pattern size {}", len),
+                        }
+                    }
+                };
+                if res == CUDA_SUCCESS {
+                    debug!(
+                        session_id = session.session_id,
+                        "FillBufferTyped({:?}, {}-byte pattern x {})", dst, pattern.len(), count
+                    );
+                    CudaResponse::Success
+                } else {
                     Self::cuda_err(res)
                 }
             }
 
+            CudaCommand::Memcpy3DAsync { stream, .. } if self.capturing_streams.contains(&stream) => {
+                Self::cuda_err(CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED)
+            }
+
+            CudaCommand::Memcpy3D { params, src_data }
+            | CudaCommand::Memcpy3DAsync { params, src_data, .. } => {
+                // Async copies run synchronously too - network is the bottleneck
+                let response = self.memcpy_3d(&params, &src_data);
+                debug!(
+                    session_id = session.session_id,
+                    "Memcpy3D({} x {} x {} bytes)", params.width_in_bytes, params.height, params.depth
+                );
+                response
+            }
+
             CudaCommand::MemcpyDtoDAsync {
                 dst,
                 src,
                 byte_count,
-                stream: _,
+                stream,
             } => {
                 // Use sync version - network is the bottleneck
                 let d = match self.driver() {
@@ -1337,7 +2344,10 @@ impl CudaExecutor {
                     }
                 };
 
-                let res = d.memcpy_dtod(real_dst, real_src, byte_count as usize);
+                let res = match self.capturing_stream(&stream) {
+                    Some(real_stream) => d.memcpy_dtod_async(real_dst, real_src, byte_count as usize, real_stream),
+                    None => d.memcpy_dtod(real_dst, real_src, byte_count as usize),
+                };
                 if res == CUDA_SUCCESS {
                     CudaResponse::Success
                 } else {
@@ -1433,7 +2443,7 @@ impl CudaExecutor {
                 dst,
                 value,
                 count,
-                stream: _,
+                stream,
             } => {
                 // Use sync version - network is the bottleneck
                 let d = match self.driver() {
@@ -1451,7 +2461,10 @@ impl CudaExecutor {
                     }
                 };
 
-                let res = d.memset_d8(real_ptr, value, count as usize);
+                let res = match self.capturing_stream(&stream) {
+                    Some(real_stream) => d.memset_d8_async(real_ptr, value, count as usize, real_stream),
+                    None => d.memset_d8(real_ptr, value, count as usize),
+                };
                 if res == CUDA_SUCCESS {
                     CudaResponse::Success
                 } else {
@@ -1463,7 +2476,7 @@ impl CudaExecutor {
                 dst,
                 value,
                 count,
-                stream: _,
+                stream,
             } => {
                 // Use sync version - network is the bottleneck
                 let d = match self.driver() {
@@ -1481,7 +2494,10 @@ impl CudaExecutor {
                     }
                 };
 
-                let res = d.memset_d16(real_ptr, value, count as usize);
+                let res = match self.capturing_stream(&stream) {
+                    Some(real_stream) => d.memset_d16_async(real_ptr, value, count as usize, real_stream),
+                    None => d.memset_d16(real_ptr, value, count as usize),
+                };
                 if res == CUDA_SUCCESS {
                     CudaResponse::Success
                 } else {
@@ -1493,7 +2509,7 @@ impl CudaExecutor {
                 dst,
                 value,
                 count,
-                stream: _,
+                stream,
             } => {
                 // Use sync version - network is the bottleneck
                 let d = match self.driver() {
@@ -1511,7 +2527,10 @@ impl CudaExecutor {
                     }
                 };
 
-                let res = d.memset_d32(real_ptr, value, count as usize);
+                let res = match self.capturing_stream(&stream) {
+                    Some(real_stream) => d.memset_d32_async(real_ptr, value, count as usize, real_stream),
+                    None => d.memset_d32(real_ptr, value, count as usize),
+                };
                 if res == CUDA_SUCCESS {
                     CudaResponse::Success
                 } else {
@@ -1519,15 +2538,91 @@ impl CudaExecutor {
                 }
             }
 
+            CudaCommand::MemsetD2D8 {
+                dst,
+                dst_pitch,
+                value,
+                width,
+                height,
+            } => self.memset_2d(&dst, None, |d, ptr, _| {
+                d.memset_d2d8(ptr, dst_pitch as usize, value, width as usize, height as usize)
+            }),
+
+            CudaCommand::MemsetD2D16 {
+                dst,
+                dst_pitch,
+                value,
+                width,
+                height,
+            } => self.memset_2d(&dst, None, |d, ptr, _| {
+                d.memset_d2d16(ptr, dst_pitch as usize, value, width as usize, height as usize)
+            }),
+
+            CudaCommand::MemsetD2D32 {
+                dst,
+                dst_pitch,
+                value,
+                width,
+                height,
+            } => self.memset_2d(&dst, None, |d, ptr, _| {
+                d.memset_d2d32(ptr, dst_pitch as usize, value, width as usize, height as usize)
+            }),
+
+            CudaCommand::MemsetD2D8Async {
+                dst,
+                dst_pitch,
+                value,
+                width,
+                height,
+                stream,
+            } => self.memset_2d(&dst, Some(&stream), |d, ptr, stream| match stream {
+                Some(s) => d.memset_d2d8_async(ptr, dst_pitch as usize, value, width as usize, height as usize, s),
+                None => d.memset_d2d8(ptr, dst_pitch as usize, value, width as usize, height as usize),
+            }),
+
+            CudaCommand::MemsetD2D16Async {
+                dst,
+                dst_pitch,
+                value,
+                width,
+                height,
+                stream,
+            } => self.memset_2d(&dst, Some(&stream), |d, ptr, stream| match stream {
+                Some(s) => d.memset_d2d16_async(ptr, dst_pitch as usize, value, width as usize, height as usize, s),
+                None => d.memset_d2d16(ptr, dst_pitch as usize, value, width as usize, height as usize),
+            }),
+
+            CudaCommand::MemsetD2D32Async {
+                dst,
+                dst_pitch,
+                value,
+                width,
+                height,
+                stream,
+            } => self.memset_2d(&dst, Some(&stream), |d, ptr, stream| match stream {
+                Some(s) => d.memset_d2d32_async(ptr, dst_pitch as usize, value, width as usize, height as usize, s),
+                None => d.memset_d2d32(ptr, dst_pitch as usize, value, width as usize, height as usize),
+            }),
+
             CudaCommand::MemGetInfo => {
                 let d = match self.driver() {
                     Ok(d) => d,
                     Err(e) => return e,
                 };
+                // On a virtual GPU, what is left of the session's share
+                let share = session
+                    .device()
+                    .and_then(|uuid| self.vram.session_device_quota(session.session_id, &uuid));
                 match d.mem_get_info() {
-                    Ok((free, total)) => CudaResponse::MemInfo {
-                        free: free as u64,
-                        total: total as u64,
+                    Ok((free, total)) => match share {
+                        Some((used, quota)) => CudaResponse::MemInfo {
+                            free: (free as u64).min(quota.saturating_sub(used)),
+                            total: quota,
+                        },
+                        None => CudaResponse::MemInfo {
+                            free: free as u64,
+                            total: total as u64,
+                        },
                     },
                     Err(e) => Self::cuda_err(e),
                 }
@@ -1578,6 +2673,7 @@ impl CudaExecutor {
                     Ok(ptr) => {
                         let handle = session.alloc_handle(ResourceType::CuHostPtr);
                         self.host_memory_handles.insert(handle, ptr);
+                        self.host_memory_sizes.insert(handle, byte_size);
                         debug!(
                             session_id = session.session_id,
                             "MemAllocHost({} bytes) -> {:?}", byte_size, handle
@@ -1595,6 +2691,7 @@ impl CudaExecutor {
                 };
                 match self.host_memory_handles.remove(&ptr) {
                     Some((_, real_ptr)) => {
+                        self.host_memory_sizes.remove(&ptr);
                         let res = d.mem_free_host(real_ptr);
                         session.remove_handle(&ptr);
                         if res == CUDA_SUCCESS {
@@ -1619,6 +2716,7 @@ impl CudaExecutor {
                     Ok(ptr) => {
                         let handle = session.alloc_handle(ResourceType::CuHostPtr);
                         self.host_memory_handles.insert(handle, ptr);
+                        self.host_memory_sizes.insert(handle, byte_size);
                         debug!(
                             session_id = session.session_id,
                             "MemHostAlloc({} bytes, flags={}) -> {:?}", byte_size, flags, handle
@@ -1678,18 +2776,26 @@ impl CudaExecutor {
                     Ok(d) => d,
                     Err(e) => return e,
                 };
+                let charge = match self.charge_vram(d, session, byte_size) {
+                    Ok(c) => c,
+                    Err(e) => return e,
+                };
                 match d.mem_alloc_managed(byte_size as usize, flags) {
                     Ok(dptr) => {
                         let handle = session.alloc_handle(ResourceType::CuDevicePtr);
                         self.memory_handles.insert(handle, dptr);
                         self.memory_sizes.insert(handle, byte_size);
+                        self.settle_vram(charge, Some(handle));
                         debug!(
                             session_id = session.session_id,
                             "MemAllocManaged({} bytes) -> {:?}", byte_size, handle
                         );
                         CudaResponse::MemAllocated(handle)
                     }
-                    Err(e) => Self::cuda_err(e),
+                    Err(e) => {
+                        self.settle_vram(charge, None);
+                        Self::cuda_err(e)
+                    }
                 }
             }
 
@@ -1698,28 +2804,74 @@ impl CudaExecutor {
                     Ok(d) => d,
                     Err(e) => return e,
                 };
+                // The padded size isn't known until the driver picks a pitch.
+                let charge = match self.charge_vram(d, session, width * height) {
+                    Ok(c) => c,
+                    Err(e) => return e,
+                };
                 match d.mem_alloc_pitch(width as usize, height as usize, element_size) {
                     Ok((dptr, pitch)) => {
                         let handle = session.alloc_handle(ResourceType::CuDevicePtr);
                         self.memory_handles.insert(handle, dptr);
                         self.memory_sizes.insert(handle, pitch as u64 * height);
+                        self.settle_vram(charge, Some(handle));
                         CudaResponse::MemAllocPitch {
                             dptr: handle,
                             pitch: pitch as u64,
                         }
                     }
-                    Err(e) => Self::cuda_err(e),
+                    Err(e) => {
+                        self.settle_vram(charge, None);
+                        Self::cuda_err(e)
+                    }
                 }
             }
 
-            CudaCommand::MemHostRegister { byte_size: _, flags: _ } => {
-                // Cannot register client host memory on the server - not supported over network
-                Self::cuda_err(CUDA_ERROR_NOT_SUPPORTED)
+            CudaCommand::MemHostRegister { byte_size, flags } => {
+                // The client's buffer can't be pinned from here, so it gets a
+                // pinned shadow of the same size: registered memory then has a
+                // server-side counterpart for cuMemHostGetDevicePointer.
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                // PORTABLE and DEVICEMAP mean the same for both calls; the
+                // IOMEMORY and READ_ONLY registration flags have no
+                // cuMemHostAlloc equivalent.
+                let alloc_flags = flags & (CU_MEMHOSTREGISTER_PORTABLE | CU_MEMHOSTREGISTER_DEVICEMAP);
+                match d.mem_host_alloc(byte_size as usize, alloc_flags) {
+                    Ok(ptr) => {
+                        let handle = session.alloc_handle(ResourceType::CuHostPtr);
+                        self.host_memory_handles.insert(handle, ptr);
+                        self.host_memory_sizes.insert(handle, byte_size);
+                        debug!(
+                            session_id = session.session_id,
+                            "MemHostRegister({} bytes, flags={}) -> {:?}", byte_size, flags, handle
+                        );
+                        CudaResponse::HostPtr(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
             }
 
-            CudaCommand::MemHostUnregister { ptr: _ } => {
-                // Cannot unregister client host memory on the server - not supported over network
-                Self::cuda_err(CUDA_ERROR_NOT_SUPPORTED)
+            CudaCommand::MemHostUnregister { ptr } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                match self.host_memory_handles.remove(&ptr) {
+                    Some((_, shadow)) => {
+                        self.host_memory_sizes.remove(&ptr);
+                        let res = d.mem_free_host(shadow);
+                        session.remove_handle(&ptr);
+                        if res == CUDA_SUCCESS {
+                            CudaResponse::Success
+                        } else {
+                            Self::cuda_err(res)
+                        }
+                    }
+                    None => Self::cuda_err(CUDA_ERROR_HOST_MEMORY_NOT_REGISTERED),
+                }
             }
 
             CudaCommand::MemPrefetchAsync { dptr: _, count: _, dst_device: _, stream: _ } => {
@@ -1771,10 +2923,10 @@ impl CudaExecutor {
                 // Deserialize kernel parameters.
                 // Each KernelParam contains the raw bytes of a parameter value.
                 // We need to create a pointer to each parameter's data buffer.
-                let mut param_buffers: Vec<Vec<u8>> = kernel_params
-                    .iter()
-                    .map(|p| p.data.clone())
-                    .collect();
+                let mut param_buffers = match self.kernel_arguments(&kernel_params) {
+                    Ok(buffers) => buffers,
+                    Err(e) => return e,
+                };
                 let mut param_ptrs: Vec<*mut c_void> = param_buffers
                     .iter_mut()
                     .map(|buf| buf.as_mut_ptr() as *mut c_void)
@@ -1845,10 +2997,10 @@ impl CudaExecutor {
                     .map(|s| *s)
                     .unwrap_or(std::ptr::null_mut());
 
-                let mut param_buffers: Vec<Vec<u8>> = kernel_params
-                    .iter()
-                    .map(|p| p.data.clone())
-                    .collect();
+                let mut param_buffers = match self.kernel_arguments(&kernel_params) {
+                    Ok(buffers) => buffers,
+                    Err(e) => return e,
+                };
                 let mut param_ptrs: Vec<*mut c_void> = param_buffers
                     .iter_mut()
                     .map(|buf| buf.as_mut_ptr() as *mut c_void)
@@ -2115,6 +3267,7 @@ impl CudaExecutor {
 
                 match self.stream_handles.remove(&stream) {
                     Some((_, real_stream)) => {
+                        self.end_abandoned_capture(d, &stream, real_stream);
                         let res = d.stream_destroy(real_stream);
                         session.remove_handle(&stream);
                         if res == CUDA_SUCCESS {
@@ -2664,18 +3817,26 @@ impl CudaExecutor {
                     .get(&stream)
                     .map(|s| *s)
                     .unwrap_or(std::ptr::null_mut());
+                let charge = match self.charge_vram(d, session, byte_size) {
+                    Ok(c) => c,
+                    Err(e) => return e,
+                };
                 match d.mem_alloc_async(byte_size as usize, real_stream) {
                     Ok(dptr) => {
                         let handle = session.alloc_handle(ResourceType::CuDevicePtr);
                         self.memory_handles.insert(handle, dptr);
                         self.memory_sizes.insert(handle, byte_size);
+                        self.settle_vram(charge, Some(handle));
                         debug!(
                             session_id = session.session_id,
                             "MemAllocAsync({} bytes) -> {:?}", byte_size, handle
                         );
                         CudaResponse::MemAllocated(handle)
                     }
-                    Err(e) => Self::cuda_err(e),
+                    Err(e) => {
+                        self.settle_vram(charge, None);
+                        Self::cuda_err(e)
+                    }
                 }
             }
 
@@ -2689,10 +3850,16 @@ impl CudaExecutor {
                     .get(&stream)
                     .map(|s| *s)
                     .unwrap_or(std::ptr::null_mut());
+                if dptr.resource_type == ResourceType::CuIpcMem {
+                    return Self::ipc_mem_not_freeable();
+                }
                 match self.memory_handles.remove(&dptr) {
                     Some((_, real_ptr)) => {
+                        self.forget_ipc_exports(&dptr);
                         let res = d.mem_free_async(real_ptr, real_stream);
                         self.memory_sizes.remove(&dptr);
+                        self.vram.free(&dptr);
+                        self.transfer_codecs.remove(&dptr);
                         session.remove_handle(&dptr);
                         if res == CUDA_SUCCESS {
                             CudaResponse::Success
@@ -2724,116 +3891,792 @@ impl CudaExecutor {
                     .get(&stream)
                     .map(|s| *s)
                     .unwrap_or(std::ptr::null_mut());
+                let charge = match self.charge_vram(d, session, byte_size) {
+                    Ok(c) => c,
+                    Err(e) => return e,
+                };
                 match d.mem_alloc_from_pool_async(byte_size as usize, real_pool, real_stream) {
                     Ok(dptr) => {
                         let handle = session.alloc_handle(ResourceType::CuDevicePtr);
                         self.memory_handles.insert(handle, dptr);
                         self.memory_sizes.insert(handle, byte_size);
+                        self.settle_vram(charge, Some(handle));
                         debug!(
                             session_id = session.session_id,
                             "MemAllocFromPoolAsync({} bytes) -> {:?}", byte_size, handle
                         );
                         CudaResponse::MemAllocated(handle)
                     }
-                    Err(e) => Self::cuda_err(e),
+                    Err(e) => {
+                        self.settle_vram(charge, None);
+                        Self::cuda_err(e)
+                    }
                 }
             }
-        }
-    }
-
-    /// Fallback device attribute values when no real CUDA driver is available.
-    fn get_device_attribute_fallback(&self, attrib: i32) -> i32 {
-        match attrib {
-            1 => 1024,       // CU_DEVICE_ATTRIBUTE_MAX_THREADS_PER_BLOCK
-            2 => 1024,       // CU_DEVICE_ATTRIBUTE_MAX_BLOCK_DIM_X
-            3 => 1024,       // CU_DEVICE_ATTRIBUTE_MAX_BLOCK_DIM_Y
-            4 => 64,         // CU_DEVICE_ATTRIBUTE_MAX_BLOCK_DIM_Z
-            5 => 2147483647, // CU_DEVICE_ATTRIBUTE_MAX_GRID_DIM_X
-            6 => 65535,      // CU_DEVICE_ATTRIBUTE_MAX_GRID_DIM_Y
-            7 => 65535,      // CU_DEVICE_ATTRIBUTE_MAX_GRID_DIM_Z
-            8 => 49152,      // CU_DEVICE_ATTRIBUTE_MAX_SHARED_MEMORY_PER_BLOCK
-            13 => 32,        // CU_DEVICE_ATTRIBUTE_WARP_SIZE
-            16 => 65536,     // CU_DEVICE_ATTRIBUTE_MAX_REGISTERS_PER_BLOCK
-            21 => 1,         // CU_DEVICE_ATTRIBUTE_CLOCK_RATE (dummy)
-            29 => 128,       // CU_DEVICE_ATTRIBUTE_MULTIPROCESSOR_COUNT
-            75 => 8,         // CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MAJOR
-            76 => 6,         // CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MINOR
-            _ => 0,
-        }
-    }
-
-    /// Clean up all GPU resources owned by a disconnecting session.
-    /// Destroys resources in reverse-dependency order to avoid dangling references.
-    pub fn cleanup_session(&self, session: &Session) {
-        let handles = session.all_handles();
-        if handles.is_empty() {
-            return;
-        }
-
-        let driver = match &self.driver {
-            Some(d) => d,
-            None => return,
-        };
 
-        let mut cleaned = 0u32;
-
-        // Pass 1: Events
-        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuEvent) {
-            if let Some((_, evt)) = self.event_handles.remove(h) {
-                driver.event_destroy(evt);
-                cleaned += 1;
-            }
-        }
+            // ── Graphs ──────────────────────────────────────────────
 
-        // Pass 2: Streams
-        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuStream) {
-            if let Some((_, stream)) = self.stream_handles.remove(h) {
-                driver.stream_destroy(stream);
-                cleaned += 1;
+            CudaCommand::StreamBeginCapture { stream, mode } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_stream = match self.stream_handles.get(&stream) {
+                    Some(s) => *s,
+                    None => {
+                        return CudaResponse::Error {
+                            code: 400,
+                            message: "invalid stream handle".to_string(),
+                        }
+                    }
+                };
+                // Global and thread-local modes would tie the capture to
+                // whichever server thread runs the next command, and global
+                // mode would fail other sessions' allocations.
+                let res = d.stream_begin_capture(real_stream, CU_STREAM_CAPTURE_MODE_RELAXED);
+                if res == CUDA_SUCCESS {
+                    self.capturing_streams.insert(stream);
+                    debug!(
+                        session_id = session.session_id,
+                        "StreamBeginCapture({:?}, requested mode {})", stream, mode
+                    );
+                    CudaResponse::Success
+                } else {
+                    Self::cuda_err(res)
+                }
             }
-        }
 
-        // Pass 3: Device memory
-        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuDevicePtr) {
+            CudaCommand::StreamEndCapture { stream } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_stream = match self.stream_handles.get(&stream) {
+                    Some(s) => *s,
+                    None => {
+                        return CudaResponse::Error {
+                            code: 400,
+                            message: "invalid stream handle".to_string(),
+                        }
+                    }
+                };
+                self.capturing_streams.remove(&stream);
+                match d.stream_end_capture(real_stream) {
+                    Ok(graph) => {
+                        let handle = session.alloc_handle(ResourceType::CuGraph);
+                        self.graph_handles.insert(handle, graph);
+                        debug!(
+                            session_id = session.session_id,
+                            "StreamEndCapture({:?}) -> {:?}", stream, handle
+                        );
+                        CudaResponse::Graph(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::StreamIsCapturing { stream } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_stream = self
+                    .stream_handles
+                    .get(&stream)
+                    .map(|s| *s)
+                    .unwrap_or(std::ptr::null_mut());
+                match d.stream_is_capturing(real_stream) {
+                    Ok(status) => CudaResponse::StreamCaptureStatus(status),
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::GraphCreate { flags } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                match d.graph_create(flags) {
+                    Ok(graph) => {
+                        let handle = session.alloc_handle(ResourceType::CuGraph);
+                        self.graph_handles.insert(handle, graph);
+                        CudaResponse::Graph(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::GraphDestroy { graph } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                match self.graph_handles.remove(&graph) {
+                    Some((_, real_graph)) => {
+                        let res = d.graph_destroy(real_graph);
+                        session.remove_handle(&graph);
+                        if res == CUDA_SUCCESS {
+                            CudaResponse::Success
+                        } else {
+                            Self::cuda_err(res)
+                        }
+                    }
+                    None => CudaResponse::Error {
+                        code: 400,
+                        message: "invalid graph handle".to_string(),
+                    },
+                }
+            }
+
+            CudaCommand::GraphInstantiate { graph, flags } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_graph = match self.graph_handles.get(&graph) {
+                    Some(g) => *g,
+                    None => {
+                        return CudaResponse::Error {
+                            code: 400,
+                            message: "invalid graph handle".to_string(),
+                        }
+                    }
+                };
+                match d.graph_instantiate(real_graph, flags) {
+                    Ok(exec) => {
+                        let handle = session.alloc_handle(ResourceType::CuGraphExec);
+                        self.graph_exec_handles.insert(handle, exec);
+                        debug!(
+                            session_id = session.session_id,
+                            "GraphInstantiate({:?}) -> {:?}", graph, handle
+                        );
+                        CudaResponse::GraphExec(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::GraphExecDestroy { exec } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                match self.graph_exec_handles.remove(&exec) {
+                    Some((_, real_exec)) => {
+                        let res = d.graph_exec_destroy(real_exec);
+                        session.remove_handle(&exec);
+                        if res == CUDA_SUCCESS {
+                            CudaResponse::Success
+                        } else {
+                            Self::cuda_err(res)
+                        }
+                    }
+                    None => CudaResponse::Error {
+                        code: 400,
+                        message: "invalid graph exec handle".to_string(),
+                    },
+                }
+            }
+
+            CudaCommand::GraphLaunch { exec, stream } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let (real_exec, real_stream) = match self.graph_exec_and_stream(&exec, &stream) {
+                    Ok(v) => v,
+                    Err(e) => return e,
+                };
+                let res = d.graph_launch(real_exec, real_stream);
+                if res == CUDA_SUCCESS {
+                    CudaResponse::Success
+                } else {
+                    Self::cuda_err(res)
+                }
+            }
+
+            CudaCommand::GraphUpload { exec, stream } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let (real_exec, real_stream) = match self.graph_exec_and_stream(&exec, &stream) {
+                    Ok(v) => v,
+                    Err(e) => return e,
+                };
+                let res = d.graph_upload(real_exec, real_stream);
+                if res == CUDA_SUCCESS {
+                    CudaResponse::Success
+                } else {
+                    Self::cuda_err(res)
+                }
+            }
+
+            CudaCommand::TexObjectCreate { resource, texture, view } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let res_desc = match self.resource_desc(&resource) {
+                    Ok(r) => r,
+                    Err(e) => return e,
+                };
+                let view = view.as_deref().map(Self::view_desc);
+                match d.tex_object_create(&res_desc, &Self::texture_desc(&texture), view.as_ref()) {
+                    Ok(object) => {
+                        let handle = session.alloc_handle(ResourceType::CuTexObject);
+                        self.texture_objects.insert(handle, object);
+                        CudaResponse::TexObject(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::SurfObjectCreate { resource } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let res_desc = match self.resource_desc(&resource) {
+                    Ok(r) => r,
+                    Err(e) => return e,
+                };
+                match d.surf_object_create(&res_desc) {
+                    Ok(object) => {
+                        let handle = session.alloc_handle(ResourceType::CuSurfObject);
+                        self.texture_objects.insert(handle, object);
+                        CudaResponse::SurfObject(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::TexObjectDestroy { tex_object: object }
+            | CudaCommand::SurfObjectDestroy { surf_object: object } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                match self.texture_objects.remove(&object) {
+                    Some((_, real_object)) => {
+                        let res = if object.resource_type == ResourceType::CuSurfObject {
+                            d.surf_object_destroy(real_object)
+                        } else {
+                            d.tex_object_destroy(real_object)
+                        };
+                        session.remove_handle(&object);
+                        if res == CUDA_SUCCESS {
+                            CudaResponse::Success
+                        } else {
+                            Self::cuda_err(res)
+                        }
+                    }
+                    None => CudaResponse::Error {
+                        code: 400,
+                        message: "invalid texture or surface object".to_string(),
+                    },
+                }
+            }
+
+            CudaCommand::ArrayCreate { desc } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let charge = match self.charge_vram(d, session, desc.byte_size()) {
+                    Ok(c) => c,
+                    Err(e) => return e,
+                };
+                match d.array_3d_create(&Self::array_descriptor(&desc)) {
+                    Ok(array) => {
+                        let handle = session.alloc_handle(ResourceType::CuArray);
+                        self.array_handles.insert(handle, array);
+                        self.settle_vram(charge, Some(handle));
+                        debug!(
+                            session_id = session.session_id,
+                            "ArrayCreate({} x {} x {}) -> {:?}", desc.width, desc.height, desc.depth, handle
+                        );
+                        CudaResponse::Array(handle)
+                    }
+                    Err(e) => {
+                        self.settle_vram(charge, None);
+                        Self::cuda_err(e)
+                    }
+                }
+            }
+
+            CudaCommand::ArrayDestroy { array } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                match self.array_handles.remove(&array) {
+                    Some((_, real_array)) => {
+                        let res = d.array_destroy(real_array);
+                        self.vram.free(&array);
+                        session.remove_handle(&array);
+                        if res == CUDA_SUCCESS {
+                            CudaResponse::Success
+                        } else {
+                            Self::cuda_err(res)
+                        }
+                    }
+                    None => CudaResponse::Error {
+                        code: 400,
+                        message: "invalid array handle".to_string(),
+                    },
+                }
+            }
+
+            CudaCommand::ModuleGetTexRef { module, name } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let Some(real_mod) = self.module_handles.get(&module).map(|m| *m) else {
+                    return CudaResponse::Error {
+                        code: 400,
+                        message: "invalid module handle".to_string(),
+                    };
+                };
+                match d.module_get_tex_ref(real_mod, &name) {
+                    Ok(texref) => {
+                        let handle = session.alloc_handle(ResourceType::CuTexRef);
+                        self.texture_refs.insert(handle, texref);
+                        debug!(session_id = session.session_id, "ModuleGetTexRef('{}') -> {:?}", name, handle);
+                        CudaResponse::TexRef(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::ModuleGetSurfRef { module, name } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let Some(real_mod) = self.module_handles.get(&module).map(|m| *m) else {
+                    return CudaResponse::Error {
+                        code: 400,
+                        message: "invalid module handle".to_string(),
+                    };
+                };
+                match d.module_get_surf_ref(real_mod, &name) {
+                    Ok(surfref) => {
+                        let handle = session.alloc_handle(ResourceType::CuSurfRef);
+                        self.texture_refs.insert(handle, surfref);
+                        debug!(session_id = session.session_id, "ModuleGetSurfRef('{}') -> {:?}", name, handle);
+                        CudaResponse::SurfRef(handle)
+                    }
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::TexRefSetAddress {
+                texref,
+                dptr,
+                offset,
+                bytes,
+            } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_ref = match self.texture_ref(&texref) {
+                    Ok(r) => r,
+                    Err(e) => return e,
+                };
+                let Some(base) = self.memory_handles.get(&dptr).map(|b| *b) else {
+                    return CudaResponse::Error {
+                        code: 400,
+                        message: "invalid memory handle".to_string(),
+                    };
+                };
+                match d.tex_ref_set_address(real_ref, base + offset, bytes as usize) {
+                    Ok(byte_offset) => CudaResponse::TexRefOffset(byte_offset as u64),
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::TexRefSet { texref, setting } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let result = self
+                    .texture_ref(&texref)
+                    .and_then(|real_ref| self.set_texture_ref(d, real_ref, &setting));
+                match result {
+                    Ok(()) => CudaResponse::Success,
+                    Err(e) => e,
+                }
+            }
+
+            CudaCommand::SurfRefSetArray { surfref, array, flags } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let (real_ref, real_array) = match (self.texture_ref(&surfref), self.array(&array)) {
+                    (Ok(r), Ok(a)) => (r, a),
+                    (Err(e), _) | (_, Err(e)) => return e,
+                };
+                let res = d.surf_ref_set_array(real_ref, real_array, flags);
+                if res == CUDA_SUCCESS {
+                    CudaResponse::Success
+                } else {
+                    Self::cuda_err(res)
+                }
+            }
+
+            CudaCommand::IpcGetMemHandle { dptr } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let Some(real_ptr) = self.memory_handles.get(&dptr).map(|p| *p) else {
+                    return CudaResponse::Error {
+                        code: 400,
+                        message: "invalid memory handle".to_string(),
+                    };
+                };
+                // The driver's own export checks the allocation can be shared.
+                if let Err(e) = d.ipc_get_mem_handle(real_ptr) {
+                    return Self::cuda_err(e);
+                }
+                let existing = self.ipc_exports.iter().find(|e| *e.value() == dptr).map(|e| *e.key());
+                let token = existing.unwrap_or_else(|| {
+                    let token = self.next_ipc_token.fetch_add(1, Ordering::Relaxed);
+                    self.ipc_exports.insert(token, dptr);
+                    token
+                });
+                debug!(session_id = session.session_id, "IpcGetMemHandle({:?}) -> token {}", dptr, token);
+                CudaResponse::IpcMemHandle(IpcMemHandle { memory: dptr, token })
+            }
+
+            CudaCommand::IpcOpenMemHandle { handle, flags: _ } => {
+                let exported = self.ipc_exports.get(&handle.token).map(|e| *e);
+                let real_ptr = exported
+                    .filter(|m| m.session_id == handle.memory.session_id && m.resource_id == handle.memory.resource_id)
+                    .and_then(|m| self.memory_handles.get(&m).map(|p| (m, *p)));
+                let Some((memory, real_ptr)) = real_ptr else {
+                    return CudaResponse::Error {
+                        code: 400,
+                        message: "invalid IPC memory handle".to_string(),
+                    };
+                };
+                let alias = session.alloc_handle(ResourceType::CuIpcMem);
+                self.memory_handles.insert(alias, real_ptr);
+                if let Some(size) = self.memory_sizes.get(&memory).map(|s| *s) {
+                    self.memory_sizes.insert(alias, size);
+                }
+                debug!(session_id = session.session_id, "IpcOpenMemHandle({:?}) -> {:?}", memory, alias);
+                CudaResponse::IpcMemOpened(alias)
+            }
+
+            CudaCommand::IpcCloseMemHandle { dptr } => {
+                if dptr.resource_type != ResourceType::CuIpcMem || self.memory_handles.remove(&dptr).is_none() {
+                    return CudaResponse::Error {
+                        code: 400,
+                        message: "invalid IPC memory handle".to_string(),
+                    };
+                }
+                self.memory_sizes.remove(&dptr);
+                self.transfer_codecs.remove(&dptr);
+                session.remove_handle(&dptr);
+                CudaResponse::Success
+            }
+
+            CudaCommand::HostMemWrite { ptr, offset, data } => {
+                let host = match self.host_memory_range(&ptr, offset, data.len() as u64) {
+                    Ok(p) => p,
+                    Err(e) => return e,
+                };
+                unsafe { std::ptr::copy_nonoverlapping(data.as_ptr(), host, data.len()) };
+                CudaResponse::Success
+            }
+
+            CudaCommand::HostMemRead { ptr, offset, byte_count } => {
+                let host = match self.host_memory_range(&ptr, offset, byte_count) {
+                    Ok(p) => p,
+                    Err(e) => return e,
+                };
+                let data = unsafe { std::slice::from_raw_parts(host, byte_count as usize) };
+                CudaResponse::MemoryData(data.to_vec())
+            }
+
+            CudaCommand::CtxSetSharedMemConfig { config } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let res = d.ctx_set_shared_mem_config(config);
+                if res == CUDA_SUCCESS {
+                    CudaResponse::Success
+                } else {
+                    Self::cuda_err(res)
+                }
+            }
+
+            CudaCommand::CtxGetSharedMemConfig => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                match d.ctx_get_shared_mem_config() {
+                    Ok(config) => CudaResponse::SharedMemConfig(config),
+                    Err(e) => Self::cuda_err(e),
+                }
+            }
+
+            CudaCommand::DeviceGetUsage { device } => {
+                let d = match self.driver() {
+                    Ok(d) => d,
+                    Err(e) => return e,
+                };
+                let real_dev = match self.device_handles.get(&device) {
+                    Some(dev) => *dev,
+                    None => return CudaResponse::Error {
+                        code: 101,
+                        message: "invalid device handle".to_string(),
+                    },
+                };
+                let memory_total = match d.device_total_mem(real_dev) {
+                    Ok(total) => total as u64,
+                    Err(e) => return Self::cuda_err(e),
+                };
+                let nvml = d
+                    .device_get_pci_bus_id(real_dev)
+                    .ok()
+                    .and_then(|bus_id| usage::nvml_usage(&bus_id));
+                match nvml {
+                    Some(usage) => CudaResponse::DeviceUsage {
+                        memory_total,
+                        memory_used: usage.memory_used,
+                        gpu_utilization: usage.gpu_utilization,
+                        memory_utilization: usage.memory_utilization,
+                    },
+                    // Only what this server's sessions hold is known.
+                    None => CudaResponse::DeviceUsage {
+                        memory_total,
+                        memory_used: d
+                            .device_get_uuid(real_dev)
+                            .map(|uuid| self.vram.device_usage(&uuid))
+                            .unwrap_or(0),
+                        gpu_utilization: 0,
+                        memory_utilization: 0,
+                    },
+                }
+            }
+        }
+    }
+
+    /// Address of `len` bytes at `offset` into a host allocation, checked to
+    /// lie within it.
+    fn host_memory_range(&self, handle: &NetworkHandle, offset: u64, len: u64) -> Result<*mut u8, CudaResponse> {
+        let invalid = |message: &str| CudaResponse::Error {
+            code: cuda_driver::CUDA_ERROR_INVALID_VALUE,
+            message: message.to_string(),
+        };
+        let base = self
+            .host_memory_handles
+            .get(handle)
+            .map(|p| *p as *mut u8)
+            .ok_or_else(|| invalid("invalid host memory handle"))?;
+        let size = self.host_memory_sizes.get(handle).map(|s| *s).unwrap_or(0);
+        match offset.checked_add(len) {
+            Some(end) if end <= size => Ok(unsafe { base.add(offset as usize) }),
+            _ => Err(invalid("host memory range out of bounds")),
+        }
+    }
+
+    /// The device `ordinal` names for this session: the GPU it was bound to
+    /// if that is still present, otherwise the driver's device at `ordinal`,
+    /// which the ordinal is then bound to.
+    fn resolve_ordinal(
+        d: &CudaDriver,
+        session: &Session,
+        ordinal: i32,
+    ) -> Result<cuda_driver::CUdevice, cuda_driver::CUresult> {
+        if let Some(device) = session.device_binding(ordinal).and_then(|uuid| Self::find_device(d, &uuid)) {
+            return Ok(device);
+        }
+        let device = d.device_get(ordinal)?;
+        if let Ok(uuid) = d.device_get_uuid(device) {
+            session.bind_device(ordinal, uuid);
+        }
+        Ok(device)
+    }
+
+    fn find_device(d: &CudaDriver, uuid: &DeviceUuid) -> Option<cuda_driver::CUdevice> {
+        (0..d.device_get_count().ok()?)
+            .filter_map(|ordinal| d.device_get(ordinal).ok())
+            .find(|&device| d.device_get_uuid(device).ok().as_ref() == Some(uuid))
+    }
+
+    /// Give `session` the device bindings of the session it resumes. Returns
+    /// the ordinals whose GPU is gone; those are dropped, so they resolve
+    /// afresh. Without a driver nothing can be checked and all are kept.
+    pub fn resume_device_bindings(
+        &self,
+        session: &Session,
+        mut bindings: BTreeMap<i32, DeviceUuid>,
+    ) -> Vec<DeviceChange> {
+        let mut changed = Vec::new();
+        if let Some(d) = self.driver.as_deref() {
+            bindings.retain(|&ordinal, previous| {
+                if Self::find_device(d, previous).is_some() {
+                    return true;
+                }
+                let current = d.device_get(ordinal).ok().and_then(|device| d.device_get_uuid(device).ok());
+                changed.push(DeviceChange {
+                    ordinal,
+                    previous: *previous,
+                    current,
+                });
+                false
+            });
+        }
+        session.set_device_bindings(bindings);
+        changed
+    }
+
+    /// Fallback device attribute values when no real CUDA driver is available.
+    /// Clean up all GPU resources owned by a disconnecting session.
+    /// Destroys resources in reverse-dependency order to avoid dangling references.
+    pub fn cleanup_session(&self, session: &Session) {
+        let handles = session.all_handles();
+        if handles.is_empty() {
+            return;
+        }
+
+        let driver = match &self.driver {
+            Some(d) => d,
+            None => return,
+        };
+
+        let mut cleaned = 0u32;
+
+        // Pass 1: Events
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuEvent) {
+            if let Some((_, evt)) = self.event_handles.remove(h) {
+                driver.event_destroy(evt);
+                cleaned += 1;
+            }
+        }
+
+        // Pass 2: Executable graphs, then graphs
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuGraphExec) {
+            if let Some((_, exec)) = self.graph_exec_handles.remove(h) {
+                driver.graph_exec_destroy(exec);
+                cleaned += 1;
+            }
+        }
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuGraph) {
+            if let Some((_, graph)) = self.graph_handles.remove(h) {
+                driver.graph_destroy(graph);
+                cleaned += 1;
+            }
+        }
+
+        // Pass 3: Streams
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuStream) {
+            if let Some((_, stream)) = self.stream_handles.remove(h) {
+                self.end_abandoned_capture(driver, h, stream);
+                driver.stream_destroy(stream);
+                cleaned += 1;
+            }
+        }
+
+        // Pass 3b: Texture and surface objects, before the memory they read
+        for h in handles.iter().filter(|h| {
+            matches!(h.resource_type, ResourceType::CuTexObject | ResourceType::CuSurfObject)
+        }) {
+            if let Some((_, object)) = self.texture_objects.remove(h) {
+                if h.resource_type == ResourceType::CuSurfObject {
+                    driver.surf_object_destroy(object);
+                } else {
+                    driver.tex_object_destroy(object);
+                }
+                cleaned += 1;
+            }
+        }
+
+        // Pass 3c: Arrays, after the objects that read them
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuArray) {
+            if let Some((_, array)) = self.array_handles.remove(h) {
+                driver.array_destroy(array);
+                self.vram.free(h);
+                cleaned += 1;
+            }
+        }
+
+        // Pass 4: Device memory
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuDevicePtr) {
             if let Some((_, ptr)) = self.memory_handles.remove(h) {
                 driver.mem_free(ptr);
+                self.forget_ipc_exports(h);
+                self.memory_sizes.remove(h);
+                self.vram.free(h);
+                self.transfer_codecs.remove(h);
+                cleaned += 1;
+            }
+        }
+
+        // Pass 4b: IPC mappings; the memory belongs to the exporting session
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuIpcMem) {
+            if self.memory_handles.remove(h).is_some() {
                 self.memory_sizes.remove(h);
+                self.transfer_codecs.remove(h);
                 cleaned += 1;
             }
         }
 
-        // Pass 4: Host memory
+        // Pass 5: Host memory
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuHostPtr) {
             if let Some((_, ptr)) = self.host_memory_handles.remove(h) {
                 driver.mem_free_host(ptr);
+                self.host_memory_sizes.remove(h);
                 cleaned += 1;
             }
         }
 
-        // Pass 5: Linkers
+        // Pass 6: Linkers
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuLinker) {
             if let Some((_, link)) = self.linker_handles.remove(h) {
                 driver.link_destroy(link);
+                self.linker_jit.remove(h);
                 cleaned += 1;
             }
         }
 
-        // Pass 6: Functions (no driver call, just remove tracking)
+        // Pass 7: Functions (no driver call, just remove tracking)
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuFunction) {
             if self.function_handles.remove(h).is_some() {
                 cleaned += 1;
             }
         }
 
-        // Pass 7: Modules
+        // Pass 7b: Texture and surface references belong to their modules
+        for h in handles
+            .iter()
+            .filter(|h| matches!(h.resource_type, ResourceType::CuTexRef | ResourceType::CuSurfRef))
+        {
+            if self.texture_refs.remove(h).is_some() {
+                cleaned += 1;
+            }
+        }
+
+        // Pass 8: Modules
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuModule) {
             if let Some((_, module)) = self.module_handles.remove(h) {
+                self.module_params.remove(h);
                 driver.module_unload(module);
                 cleaned += 1;
             }
         }
 
-        // Pass 8: Contexts
+        // Pass 9: Contexts
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuContext) {
             if let Some((_, ctx)) = self.context_handles.remove(h) {
                 driver.ctx_destroy(ctx);
@@ -2841,7 +4684,7 @@ impl CudaExecutor {
             }
         }
 
-        // Pass 9: Memory pools (no destroy for default pool)
+        // Pass 10: Memory pools (no destroy for default pool)
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::CuMemPool) {
             self.mempool_handles.remove(h);
         }
@@ -2856,3 +4699,31 @@ impl CudaExecutor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(session_id: u32, resource_id: u64) -> NetworkHandle {
+        NetworkHandle {
+            server_id: 0,
+            session_id,
+            resource_id,
+            resource_type: ResourceType::CuDevice,
+        }
+    }
+
+    #[test]
+    fn test_session_devices_are_its_own() {
+        let executor = CudaExecutor::new(Vec::new());
+        executor.device_handles.insert(device(1, 1), 0);
+        executor.device_handles.insert(device(1, 2), 2);
+        // Looked up twice
+        executor.device_handles.insert(device(1, 3), 2);
+        executor.device_handles.insert(device(2, 1), 1);
+
+        assert_eq!(executor.session_devices(1), BTreeSet::from([0, 2]));
+        assert_eq!(executor.session_devices(2), BTreeSet::from([1]));
+        assert!(executor.session_devices(3).is_empty());
+    }
+}
@@ -0,0 +1,200 @@
+//! The CUdevice_attribute table.
+//!
+//! cuDeviceGetAttribute answers from the server's driver. An older driver
+//! than the application was built for refuses the attributes added since,
+//! and without a driver there is nothing to ask; both get the value here,
+//! which for the capabilities added in later releases is "not supported".
+//! `[server.device_attributes]` overrides any of them, by name or number,
+//! for applications that gate features on a value the GPU doesn't report
+//! (see [`crate::device_overrides`]).
+
+use std::collections::{BTreeMap, HashMap};
+
+use tracing::warn;
+
+/// Each attribute by number, with its name after `CU_DEVICE_ATTRIBUTE_` and
+/// the value reported when the driver can't say.
+pub const ATTRIBUTES: &[(i32, &str, i32)] = &[
+    (1, "MAX_THREADS_PER_BLOCK", 1024),
+    (2, "MAX_BLOCK_DIM_X", 1024),
+    (3, "MAX_BLOCK_DIM_Y", 1024),
+    (4, "MAX_BLOCK_DIM_Z", 64),
+    (5, "MAX_GRID_DIM_X", 2147483647),
+    (6, "MAX_GRID_DIM_Y", 65535),
+    (7, "MAX_GRID_DIM_Z", 65535),
+    (8, "MAX_SHARED_MEMORY_PER_BLOCK", 49152),
+    (9, "TOTAL_CONSTANT_MEMORY", 65536),
+    (10, "WARP_SIZE", 32),
+    (11, "MAX_PITCH", 2147483647),
+    (12, "MAX_REGISTERS_PER_BLOCK", 65536),
+    (13, "CLOCK_RATE", 1),
+    (14, "TEXTURE_ALIGNMENT", 512),
+    (15, "GPU_OVERLAP", 1),
+    (16, "MULTIPROCESSOR_COUNT", 128),
+    (17, "KERNEL_EXEC_TIMEOUT", 0),
+    (18, "INTEGRATED", 0),
+    (19, "CAN_MAP_HOST_MEMORY", 1),
+    (20, "COMPUTE_MODE", 0),
+    (21, "MAXIMUM_TEXTURE1D_WIDTH", 131072),
+    (22, "MAXIMUM_TEXTURE2D_WIDTH", 131072),
+    (23, "MAXIMUM_TEXTURE2D_HEIGHT", 65536),
+    (24, "MAXIMUM_TEXTURE3D_WIDTH", 16384),
+    (25, "MAXIMUM_TEXTURE3D_HEIGHT", 16384),
+    (26, "MAXIMUM_TEXTURE3D_DEPTH", 16384),
+    (27, "MAXIMUM_TEXTURE2D_LAYERED_WIDTH", 32768),
+    (28, "MAXIMUM_TEXTURE2D_LAYERED_HEIGHT", 32768),
+    (29, "MAXIMUM_TEXTURE2D_LAYERED_LAYERS", 2048),
+    (30, "SURFACE_ALIGNMENT", 512),
+    (31, "CONCURRENT_KERNELS", 1),
+    (32, "ECC_ENABLED", 0),
+    (33, "PCI_BUS_ID", 0),
+    (34, "PCI_DEVICE_ID", 0),
+    (35, "TCC_DRIVER", 0),
+    (36, "MEMORY_CLOCK_RATE", 1),
+    (37, "GLOBAL_MEMORY_BUS_WIDTH", 256),
+    (38, "L2_CACHE_SIZE", 4194304),
+    (39, "MAX_THREADS_PER_MULTIPROCESSOR", 1536),
+    (40, "ASYNC_ENGINE_COUNT", 2),
+    (41, "UNIFIED_ADDRESSING", 1),
+    (42, "MAXIMUM_TEXTURE1D_LAYERED_WIDTH", 32768),
+    (43, "MAXIMUM_TEXTURE1D_LAYERED_LAYERS", 2048),
+    (44, "CAN_TEX2D_GATHER", 0),
+    (45, "MAXIMUM_TEXTURE2D_GATHER_WIDTH", 32768),
+    (46, "MAXIMUM_TEXTURE2D_GATHER_HEIGHT", 32768),
+    (47, "MAXIMUM_TEXTURE3D_WIDTH_ALTERNATE", 8192),
+    (48, "MAXIMUM_TEXTURE3D_HEIGHT_ALTERNATE", 8192),
+    (49, "MAXIMUM_TEXTURE3D_DEPTH_ALTERNATE", 32768),
+    (50, "PCI_DOMAIN_ID", 0),
+    (51, "TEXTURE_PITCH_ALIGNMENT", 32),
+    (52, "MAXIMUM_TEXTURECUBEMAP_WIDTH", 32768),
+    (53, "MAXIMUM_TEXTURECUBEMAP_LAYERED_WIDTH", 32768),
+    (54, "MAXIMUM_TEXTURECUBEMAP_LAYERED_LAYERS", 2046),
+    (55, "MAXIMUM_SURFACE1D_WIDTH", 32768),
+    (56, "MAXIMUM_SURFACE2D_WIDTH", 131072),
+    (57, "MAXIMUM_SURFACE2D_HEIGHT", 65536),
+    (58, "MAXIMUM_SURFACE3D_WIDTH", 16384),
+    (59, "MAXIMUM_SURFACE3D_HEIGHT", 16384),
+    (60, "MAXIMUM_SURFACE3D_DEPTH", 16384),
+    (61, "MAXIMUM_SURFACE1D_LAYERED_WIDTH", 32768),
+    (62, "MAXIMUM_SURFACE1D_LAYERED_LAYERS", 2048),
+    (63, "MAXIMUM_SURFACE2D_LAYERED_WIDTH", 32768),
+    (64, "MAXIMUM_SURFACE2D_LAYERED_HEIGHT", 32768),
+    (65, "MAXIMUM_SURFACE2D_LAYERED_LAYERS", 2048),
+    (66, "MAXIMUM_SURFACECUBEMAP_WIDTH", 32768),
+    (67, "MAXIMUM_SURFACECUBEMAP_LAYERED_WIDTH", 32768),
+    (68, "MAXIMUM_SURFACECUBEMAP_LAYERED_LAYERS", 2046),
+    (69, "MAXIMUM_TEXTURE1D_LINEAR_WIDTH", 268435456),
+    (70, "MAXIMUM_TEXTURE2D_LINEAR_WIDTH", 131072),
+    (71, "MAXIMUM_TEXTURE2D_LINEAR_HEIGHT", 65000),
+    (72, "MAXIMUM_TEXTURE2D_LINEAR_PITCH", 2097120),
+    (73, "MAXIMUM_TEXTURE2D_MIPMAPPED_WIDTH", 32768),
+    (74, "MAXIMUM_TEXTURE2D_MIPMAPPED_HEIGHT", 32768),
+    (75, "COMPUTE_CAPABILITY_MAJOR", 8),
+    (76, "COMPUTE_CAPABILITY_MINOR", 6),
+    (77, "MAXIMUM_TEXTURE1D_MIPMAPPED_WIDTH", 32768),
+    (78, "STREAM_PRIORITIES_SUPPORTED", 1),
+    (79, "GLOBAL_L1_CACHE_SUPPORTED", 1),
+    (80, "LOCAL_L1_CACHE_SUPPORTED", 1),
+    (81, "MAX_SHARED_MEMORY_PER_MULTIPROCESSOR", 102400),
+    (82, "MAX_REGISTERS_PER_MULTIPROCESSOR", 65536),
+    (83, "MANAGED_MEMORY", 1),
+    (84, "MULTI_GPU_BOARD", 0),
+    (85, "MULTI_GPU_BOARD_GROUP_ID", 0),
+    (86, "HOST_NATIVE_ATOMIC_SUPPORTED", 0),
+    (87, "SINGLE_TO_DOUBLE_PRECISION_PERF_RATIO", 32),
+    (88, "PAGEABLE_MEMORY_ACCESS", 0),
+    (89, "CONCURRENT_MANAGED_ACCESS", 0),
+    (90, "COMPUTE_PREEMPTION_SUPPORTED", 0),
+    (91, "CAN_USE_HOST_POINTER_FOR_REGISTERED_MEM", 0),
+    (92, "CAN_USE_STREAM_MEM_OPS_V1", 0),
+    (93, "CAN_USE_64_BIT_STREAM_MEM_OPS_V1", 0),
+    (94, "CAN_USE_STREAM_WAIT_VALUE_NOR_V1", 0),
+    (95, "COOPERATIVE_LAUNCH", 0),
+    (96, "COOPERATIVE_MULTI_DEVICE_LAUNCH", 0),
+    (97, "MAX_SHARED_MEMORY_PER_BLOCK_OPTIN", 49152),
+    (98, "CAN_FLUSH_REMOTE_WRITES", 0),
+    (99, "HOST_REGISTER_SUPPORTED", 0),
+    (100, "PAGEABLE_MEMORY_ACCESS_USES_HOST_PAGE_TABLES", 0),
+    (101, "DIRECT_MANAGED_MEM_ACCESS_FROM_HOST", 0),
+    (102, "VIRTUAL_MEMORY_MANAGEMENT_SUPPORTED", 0),
+    (103, "HANDLE_TYPE_POSIX_FILE_DESCRIPTOR_SUPPORTED", 0),
+    (104, "HANDLE_TYPE_WIN32_HANDLE_SUPPORTED", 0),
+    (105, "HANDLE_TYPE_WIN32_KMT_HANDLE_SUPPORTED", 0),
+    (106, "MAX_BLOCKS_PER_MULTIPROCESSOR", 16),
+    (107, "GENERIC_COMPRESSION_SUPPORTED", 0),
+    (108, "MAX_PERSISTING_L2_CACHE_SIZE", 0),
+    (109, "MAX_ACCESS_POLICY_WINDOW_SIZE", 0),
+    (110, "GPU_DIRECT_RDMA_WITH_CUDA_VMM_SUPPORTED", 0),
+    (111, "RESERVED_SHARED_MEMORY_PER_BLOCK", 0),
+    (112, "SPARSE_CUDA_ARRAY_SUPPORTED", 0),
+    (113, "READ_ONLY_HOST_REGISTER_SUPPORTED", 0),
+    (114, "TIMELINE_SEMAPHORE_INTEROP_SUPPORTED", 0),
+    (115, "MEMORY_POOLS_SUPPORTED", 0),
+    (116, "GPU_DIRECT_RDMA_SUPPORTED", 0),
+    (117, "GPU_DIRECT_RDMA_FLUSH_WRITES_OPTIONS", 0),
+    (118, "GPU_DIRECT_RDMA_WRITES_ORDERING", 0),
+    (119, "MEMPOOL_SUPPORTED_HANDLE_TYPES", 0),
+    (120, "CLUSTER_LAUNCH", 0),
+    (121, "DEFERRED_MAPPING_CUDA_ARRAY_SUPPORTED", 0),
+    (122, "CAN_USE_64_BIT_STREAM_MEM_OPS", 0),
+    (123, "CAN_USE_STREAM_WAIT_VALUE_NOR", 0),
+    (124, "DMA_BUF_SUPPORTED", 0),
+    (125, "IPC_EVENT_SUPPORTED", 0),
+    (126, "MEM_SYNC_DOMAIN_COUNT", 1),
+    (127, "TENSOR_MAP_ACCESS_SUPPORTED", 0),
+    (128, "HANDLE_TYPE_FABRIC_SUPPORTED", 0),
+    (129, "UNIFIED_FUNCTION_POINTERS", 0),
+    (130, "NUMA_CONFIG", 0),
+    (131, "NUMA_ID", -1),
+    (132, "MULTICAST_SUPPORTED", 0),
+    (133, "MPS_ENABLED", 0),
+    (134, "HOST_NUMA_ID", -1),
+];
+
+/// The value reported for `attrib` when the driver can't say, if it is one.
+pub fn fallback(attrib: i32) -> Option<i32> {
+    ATTRIBUTES.iter().find(|(number, _, _)| *number == attrib).map(|(_, _, value)| *value)
+}
+
+/// The attribute `key` names: its number, or its name with or without the
+/// `CU_DEVICE_ATTRIBUTE_` prefix, in any case.
+pub fn parse(key: &str) -> Option<i32> {
+    if let Ok(number) = key.parse() {
+        return Some(number);
+    }
+    let key = key.to_ascii_uppercase();
+    let name = key.strip_prefix("CU_DEVICE_ATTRIBUTE_").unwrap_or(&key);
+    ATTRIBUTES.iter().find(|(_, n, _)| *n == name).map(|(number, _, _)| *number)
+}
+
+/// Resolve configured overrides to attribute numbers, skipping unknown names.
+pub fn overrides(config: &BTreeMap<String, i32>) -> HashMap<i32, i32> {
+    config
+        .iter()
+        .filter_map(|(key, value)| match parse(key) {
+            Some(attrib) => Some((attrib, *value)),
+            None => {
+                warn!("unknown device attribute {:?} in the server config", key);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_and_numbers() {
+        assert_eq!(parse("MULTI_GPU_BOARD"), Some(84));
+        assert_eq!(parse("cu_device_attribute_memory_pools_supported"), Some(115));
+        assert_eq!(parse("90"), Some(90));
+        assert_eq!(parse("NOT_AN_ATTRIBUTE"), None);
+        assert_eq!(fallback(10), Some(32));
+        assert_eq!(fallback(10_000), None);
+        for (i, (number, _, _)) in ATTRIBUTES.iter().enumerate() {
+            assert_eq!(*number, i as i32 + 1);
+        }
+    }
+}
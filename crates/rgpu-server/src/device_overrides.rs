@@ -0,0 +1,200 @@
+//! What GPUs report themselves as.
+//!
+//! Some applications refuse to run, or turn features off, by the device
+//! name, compute capability or memory size they see. `[[server.device_overrides]]`
+//! sets those per GPU, and `[server.device_attributes]` sets attributes on
+//! all of them. Both executors answer their device queries through here:
+//! cuDeviceGetName, cuDeviceTotalMem, cuDeviceComputeCapability and
+//! cuDeviceGetAttribute, and vkGetPhysicalDeviceProperties and the memory
+//! heaps. The GPU list clients are sent says the same.
+//!
+//! Only what the GPU reports changes; allocations are still limited by its
+//! real memory.
+
+use std::collections::HashMap;
+
+use tracing::{info, warn};
+
+use rgpu_core::config::ServerConfig;
+use rgpu_protocol::gpu_info::GpuInfo;
+
+use crate::device_attributes;
+use crate::vram::DeviceUuid;
+
+/// CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MAJOR
+const COMPUTE_CAPABILITY_MAJOR: i32 = 75;
+/// CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MINOR
+const COMPUTE_CAPABILITY_MINOR: i32 = 76;
+
+/// What one GPU reports.
+#[derive(Debug, Default)]
+struct Reported {
+    name: Option<String>,
+    total_memory: Option<u64>,
+    compute_capability: Option<(i32, i32)>,
+    attributes: HashMap<i32, i32>,
+}
+
+/// The configured overrides, by GPU UUID.
+#[derive(Debug, Default)]
+pub struct DeviceOverrides {
+    /// Attributes of every GPU
+    all: HashMap<i32, i32>,
+    devices: HashMap<DeviceUuid, Reported>,
+}
+
+impl DeviceOverrides {
+    pub fn from_config(config: &ServerConfig, gpus: &[(GpuInfo, DeviceUuid)]) -> Self {
+        let mut devices = HashMap::new();
+        for entry in &config.device_overrides {
+            let Some((_, uuid)) = gpus.iter().find(|(gpu, _)| gpu.server_device_index == entry.device) else {
+                warn!("device_overrides: no GPU {}", entry.device);
+                continue;
+            };
+            let compute_capability = entry.compute_capability.as_deref().and_then(|cc| {
+                let parsed = parse_compute_capability(cc);
+                if parsed.is_none() {
+                    warn!("device_overrides: compute capability {:?} isn't major.minor", cc);
+                }
+                parsed
+            });
+            info!("GPU {}: reported properties overridden", entry.device);
+            devices.insert(
+                *uuid,
+                Reported {
+                    name: entry.name.clone(),
+                    total_memory: entry.total_memory_mb.map(|mb| mb * 1024 * 1024),
+                    compute_capability,
+                    attributes: device_attributes::overrides(&entry.attributes),
+                },
+            );
+        }
+        Self {
+            all: device_attributes::overrides(&config.device_attributes),
+            devices,
+        }
+    }
+
+    fn device(&self, uuid: Option<&DeviceUuid>) -> Option<&Reported> {
+        self.devices.get(uuid?)
+    }
+
+    pub fn name(&self, uuid: Option<&DeviceUuid>) -> Option<&str> {
+        self.device(uuid)?.name.as_deref()
+    }
+
+    /// Total memory in bytes.
+    pub fn total_memory(&self, uuid: Option<&DeviceUuid>) -> Option<u64> {
+        self.device(uuid)?.total_memory
+    }
+
+    pub fn compute_capability(&self, uuid: Option<&DeviceUuid>) -> Option<(i32, i32)> {
+        self.device(uuid)?.compute_capability
+    }
+
+    /// A cuDeviceGetAttribute value: the GPU's own override, then its
+    /// compute capability, then the one for all GPUs.
+    pub fn attribute(&self, uuid: Option<&DeviceUuid>, attrib: i32) -> Option<i32> {
+        let device = self.device(uuid);
+        device
+            .and_then(|d| d.attributes.get(&attrib).copied())
+            .or_else(|| {
+                let (major, minor) = device?.compute_capability?;
+                match attrib {
+                    COMPUTE_CAPABILITY_MAJOR => Some(major),
+                    COMPUTE_CAPABILITY_MINOR => Some(minor),
+                    _ => None,
+                }
+            })
+            .or_else(|| self.all.get(&attrib).copied())
+    }
+
+    /// Make the GPU list clients are sent say the same.
+    pub fn apply(&self, gpus: &mut [(GpuInfo, DeviceUuid)]) {
+        for (gpu, uuid) in gpus {
+            let Some(device) = self.devices.get(uuid) else { continue };
+            if let Some(name) = &device.name {
+                gpu.device_name = name.clone();
+            }
+            if let Some(bytes) = device.total_memory {
+                gpu.total_memory = bytes;
+                if let Some(heap) = gpu.memory_heaps.iter_mut().filter(|h| h.is_device_local).max_by_key(|h| h.size) {
+                    heap.size = bytes;
+                }
+            }
+            if let Some(cc) = device.compute_capability {
+                gpu.cuda_compute_capability = Some(cc);
+            }
+        }
+    }
+}
+
+/// "8.6" as (8, 6).
+fn parse_compute_capability(cc: &str) -> Option<(i32, i32)> {
+    let (major, minor) = cc.trim().split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_core::config::DeviceOverride;
+    use rgpu_protocol::gpu_info::{GpuDeviceType, MemoryHeapInfo};
+
+    fn gpu(index: u32) -> GpuInfo {
+        GpuInfo {
+            device_name: "Real GPU".to_string(),
+            vendor_id: 0x10de,
+            device_id: 0,
+            device_type: GpuDeviceType::DiscreteGpu,
+            total_memory: 8 << 30,
+            supports_vulkan: true,
+            supports_cuda: true,
+            vulkan_api_version: None,
+            vulkan_driver_version: None,
+            cuda_compute_capability: Some((7, 5)),
+            queue_family_count: 1,
+            memory_heaps: vec![
+                MemoryHeapInfo { size: 8 << 30, is_device_local: true },
+                MemoryHeapInfo { size: 16 << 30, is_device_local: false },
+            ],
+            server_device_index: index,
+            server_id: 0,
+            topology: Default::default(),
+            scheduling: Default::default(),
+            uuid: None,
+        }
+    }
+
+    #[test]
+    fn per_device_over_all() {
+        let mut config = ServerConfig::default();
+        config.device_attributes.insert("MULTI_GPU_BOARD".to_string(), 1);
+        config.device_attributes.insert("COMPUTE_PREEMPTION_SUPPORTED".to_string(), 1);
+        config.device_overrides.push(DeviceOverride {
+            device: 1,
+            name: Some("Spoofed GPU".to_string()),
+            total_memory_mb: Some(24 * 1024),
+            compute_capability: Some("8.9".to_string()),
+            attributes: [("MULTI_GPU_BOARD".to_string(), 0)].into(),
+        });
+        let mut gpus = vec![(gpu(0), [0; 16]), (gpu(1), [1; 16])];
+        let overrides = DeviceOverrides::from_config(&config, &gpus);
+
+        assert_eq!(overrides.name(Some(&[0; 16])), None);
+        assert_eq!(overrides.name(Some(&[1; 16])), Some("Spoofed GPU"));
+        assert_eq!(overrides.total_memory(Some(&[1; 16])), Some(24 << 30));
+        assert_eq!(overrides.attribute(Some(&[1; 16]), 84), Some(0));
+        assert_eq!(overrides.attribute(Some(&[1; 16]), 90), Some(1));
+        assert_eq!(overrides.attribute(Some(&[1; 16]), COMPUTE_CAPABILITY_MAJOR), Some(8));
+        assert_eq!(overrides.attribute(Some(&[0; 16]), COMPUTE_CAPABILITY_MAJOR), None);
+        assert_eq!(overrides.attribute(None, 84), Some(1));
+
+        overrides.apply(&mut gpus);
+        assert_eq!(gpus[0].0.device_name, "Real GPU");
+        assert_eq!(gpus[1].0.device_name, "Spoofed GPU");
+        assert_eq!(gpus[1].0.cuda_compute_capability, Some((8, 9)));
+        assert_eq!(gpus[1].0.memory_heaps[0].size, 24 << 30);
+        assert_eq!(gpus[1].0.memory_heaps[1].size, 16 << 30);
+    }
+}
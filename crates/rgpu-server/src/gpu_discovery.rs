@@ -1,10 +1,22 @@
 use tracing::{info, warn};
 
-use rgpu_protocol::gpu_info::{GpuDeviceType, GpuInfo, MemoryHeapInfo};
+use rgpu_protocol::gpu_info::{GpuDeviceType, GpuInfo, GpuTopology, MemoryHeapInfo};
+
+use crate::topology;
+use crate::vram::DeviceUuid;
 
 /// Discover all available GPUs on this machine.
 /// Uses Vulkan (via ash) for device enumeration.
 pub fn discover_gpus(server_id: u16) -> Vec<GpuInfo> {
+    discover_gpus_with_uuids(server_id)
+        .into_iter()
+        .map(|(gpu, _)| gpu)
+        .collect()
+}
+
+/// Like [`discover_gpus`], with each GPU's UUID (all zeroes if the driver
+/// doesn't report one).
+pub fn discover_gpus_with_uuids(server_id: u16) -> Vec<(GpuInfo, DeviceUuid)> {
     let mut gpus = Vec::new();
 
     // Try Vulkan discovery
@@ -21,11 +33,14 @@ pub fn discover_gpus(server_id: u16) -> Vec<GpuInfo> {
     if gpus.is_empty() {
         warn!("no GPUs discovered on this machine");
     }
+    topology::resolve(&mut gpus);
 
     gpus
 }
 
-fn discover_vulkan_gpus(server_id: u16) -> Result<Vec<GpuInfo>, Box<dyn std::error::Error>> {
+fn discover_vulkan_gpus(
+    server_id: u16,
+) -> Result<Vec<(GpuInfo, DeviceUuid)>, Box<dyn std::error::Error>> {
     let entry = unsafe { ash::Entry::load()? };
 
     let app_info = ash::vk::ApplicationInfo::default()
@@ -45,6 +60,24 @@ fn discover_vulkan_gpus(server_id: u16) -> Result<Vec<GpuInfo>, Box<dyn std::err
 
     for (idx, &pd) in physical_devices.iter().enumerate() {
         let props = unsafe { instance.get_physical_device_properties(pd) };
+        let has_pci_bus_info = unsafe { instance.enumerate_device_extension_properties(pd) }
+            .unwrap_or_default()
+            .iter()
+            .any(|ext| ext.extension_name_as_c_str() == Ok(ash::vk::EXT_PCI_BUS_INFO_NAME));
+        let mut id_props = ash::vk::PhysicalDeviceIDProperties::default();
+        let mut pci_props = ash::vk::PhysicalDevicePCIBusInfoPropertiesEXT::default();
+        let mut props2 = ash::vk::PhysicalDeviceProperties2::default().push_next(&mut id_props);
+        if has_pci_bus_info {
+            props2 = props2.push_next(&mut pci_props);
+        }
+        unsafe { instance.get_physical_device_properties2(pd, &mut props2) };
+        let uuid = id_props.device_uuid;
+        let pci_bus_id = has_pci_bus_info.then(|| {
+            format!(
+                "{:04x}:{:02x}:{:02x}.{:x}",
+                pci_props.pci_domain, pci_props.pci_bus, pci_props.pci_device, pci_props.pci_function
+            )
+        });
         let mem_props = unsafe { instance.get_physical_device_memory_properties(pd) };
         let queue_families =
             unsafe { instance.get_physical_device_queue_family_properties(pd) };
@@ -99,6 +132,12 @@ fn discover_vulkan_gpus(server_id: u16) -> Result<Vec<GpuInfo>, Box<dyn std::err
             memory_heaps,
             server_device_index: idx as u32,
             server_id,
+            topology: GpuTopology {
+                pci_bus_id,
+                ..Default::default()
+            },
+            scheduling: Default::default(),
+            uuid: Some(uuid),
         };
 
         info!(
@@ -110,7 +149,7 @@ fn discover_vulkan_gpus(server_id: u16) -> Result<Vec<GpuInfo>, Box<dyn std::err
             gpu.supports_cuda,
         );
 
-        gpus.push(gpu);
+        gpus.push((gpu, uuid));
     }
 
     unsafe {
@@ -0,0 +1,159 @@
+//! GPUs added and removed while the server runs.
+//!
+//! A driver restart, a MIG reconfiguration or a hot-plugged card changes
+//! the GPUs Vulkan enumerates. Every `gpu_rescan_secs` the server discovers
+//! them again, and if the list clients would see has changed, it becomes
+//! the one `QueryGpus` answers with and every connection tells its client
+//! (`ServerTopologyChanged`). The per-GPU settings (overrides, virtual
+//! GPUs) are applied to the new list by UUID and index as at startup;
+//! scheduling and CPU placement stay with the GPUs there were at startup.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use rgpu_protocol::gpu_info::GpuInfo;
+
+use crate::device_overrides::DeviceOverrides;
+use crate::gpu_discovery;
+use crate::virtual_gpus::VirtualGpus;
+use crate::vram::DeviceUuid;
+
+/// The server's GPUs, as discovered and as clients see them.
+pub struct GpuWatch {
+    server_id: u16,
+    overrides: Arc<DeviceOverrides>,
+    virtual_gpus: Arc<VirtualGpus>,
+    /// GPUs as last discovered, before overrides
+    discovered: Mutex<Vec<(GpuInfo, DeviceUuid)>>,
+    /// GPUs clients see
+    listed: watch::Sender<Arc<Vec<GpuInfo>>>,
+}
+
+impl GpuWatch {
+    pub fn new(
+        server_id: u16,
+        overrides: Arc<DeviceOverrides>,
+        virtual_gpus: Arc<VirtualGpus>,
+        discovered: Vec<(GpuInfo, DeviceUuid)>,
+    ) -> Self {
+        let listed = Self::listed(&overrides, &virtual_gpus, discovered.clone());
+        Self {
+            server_id,
+            overrides,
+            virtual_gpus,
+            discovered: Mutex::new(discovered),
+            listed: watch::Sender::new(Arc::new(listed)),
+        }
+    }
+
+    /// The GPUs clients see.
+    pub fn gpus(&self) -> Arc<Vec<GpuInfo>> {
+        self.listed.borrow().clone()
+    }
+
+    /// Be told each time the GPUs clients see change.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<Vec<GpuInfo>>> {
+        self.listed.subscribe()
+    }
+
+    fn listed(
+        overrides: &DeviceOverrides,
+        virtual_gpus: &VirtualGpus,
+        mut discovered: Vec<(GpuInfo, DeviceUuid)>,
+    ) -> Vec<GpuInfo> {
+        overrides.apply(&mut discovered);
+        virtual_gpus.expand(discovered).into_iter().map(|(gpu, _)| gpu).collect()
+    }
+
+    /// Discover the GPUs again. Returns whether the ones clients see
+    /// changed.
+    pub fn rescan(&self) -> bool {
+        self.replace(gpu_discovery::discover_gpus_with_uuids(self.server_id))
+    }
+
+    fn replace(&self, found: Vec<(GpuInfo, DeviceUuid)>) -> bool {
+        {
+            let mut discovered = self.discovered.lock();
+            for (gpu, _) in found.iter().filter(|(_, uuid)| !discovered.iter().any(|(_, u)| u == uuid)) {
+                info!("GPU {} added: {}", gpu.server_device_index, gpu.device_name);
+            }
+            for (gpu, _) in discovered.iter().filter(|(_, uuid)| !found.iter().any(|(_, u)| u == uuid)) {
+                warn!("GPU {} removed: {}", gpu.server_device_index, gpu.device_name);
+            }
+            *discovered = found.clone();
+        }
+        let listed = Self::listed(&self.overrides, &self.virtual_gpus, found);
+        self.listed.send_if_modified(|current| {
+            if **current == listed {
+                return false;
+            }
+            info!("GPU list changed, now {} GPU(s)", listed.len());
+            *current = Arc::new(listed);
+            true
+        })
+    }
+
+    /// Rescan every `every` until `shutdown` is set.
+    pub async fn run(self: Arc<Self>, every: Duration, mut shutdown: watch::Receiver<bool>) {
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(every) => {
+                    let watch = self.clone();
+                    if let Err(e) = tokio::task::spawn_blocking(move || watch.rescan()).await {
+                        warn!("GPU rescan failed: {}", e);
+                    }
+                }
+                _ = shutdown.changed() => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::gpu_info::{GpuDeviceType, MemoryHeapInfo};
+
+    fn gpu(index: u32, name: &str) -> (GpuInfo, DeviceUuid) {
+        let info = GpuInfo {
+            device_name: name.to_string(),
+            vendor_id: 0x10de,
+            device_id: 0,
+            device_type: GpuDeviceType::DiscreteGpu,
+            total_memory: 8 << 30,
+            supports_vulkan: true,
+            supports_cuda: true,
+            vulkan_api_version: None,
+            vulkan_driver_version: None,
+            cuda_compute_capability: Some((8, 6)),
+            queue_family_count: 1,
+            memory_heaps: vec![MemoryHeapInfo { size: 8 << 30, is_device_local: true }],
+            server_device_index: index,
+            server_id: 0,
+            topology: Default::default(),
+            scheduling: Default::default(),
+            uuid: None,
+        };
+        (info, [name.len() as u8; 16])
+    }
+
+    #[test]
+    fn subscribers_see_changes_only() {
+        let watch = GpuWatch::new(0, Default::default(), Default::default(), vec![gpu(0, "a")]);
+        let mut changes = watch.subscribe();
+        assert!(!watch.replace(vec![gpu(0, "a")]));
+        assert!(!changes.has_changed().unwrap());
+
+        assert!(watch.replace(vec![gpu(0, "a"), gpu(1, "bb")]));
+        assert!(changes.has_changed().unwrap());
+        let names: Vec<_> = changes.borrow_and_update().iter().map(|gpu| gpu.device_name.clone()).collect();
+        assert_eq!(names, ["a", "bb"]);
+
+        assert!(watch.replace(vec![gpu(0, "bb")]));
+        assert_eq!(watch.gpus().len(), 1);
+    }
+}
@@ -0,0 +1,280 @@
+//! Kernel parameter sizes from module images.
+//!
+//! `cuLaunchKernel` only gets an array of pointers to the arguments, so the
+//! interposer needs each parameter's size to copy them. Drivers from CUDA
+//! 12.4 report it through `cuFuncGetParamInfo`; for older ones the sizes are
+//! read from the image the module was loaded from when it's loaded: the
+//! `.entry` signatures of PTX, or the `EIATTR_KPARAM_INFO` records in a
+//! cubin's `.nv.info.<kernel>` sections. Fat binaries are searched for an
+//! uncompressed cubin or PTX entry.
+
+use std::collections::HashMap;
+
+/// Parameter sizes of each kernel in a module, by (mangled) kernel name.
+pub type ParamTable = HashMap<String, Vec<u32>>;
+
+const FATBIN_MAGIC: u32 = 0xBA55_ED50;
+const FATBIN_KIND_PTX: u16 = 1;
+const FATBIN_KIND_ELF: u16 = 2;
+/// Entry payload is compressed
+const FATBIN_FLAG_COMPRESSED: u64 = 0x2000;
+
+const EIFMT_SVAL: u8 = 0x04;
+const EIATTR_KPARAM_INFO: u8 = 0x17;
+
+/// Parameter sizes of the kernels in a module image (PTX, cubin or fat
+/// binary). Empty if the format isn't recognised.
+pub fn param_table(image: &[u8]) -> ParamTable {
+    if image.starts_with(b"\x7fELF") {
+        return from_cubin(image).unwrap_or_default();
+    }
+    if image.len() >= 4 && u32::from_le_bytes(image[..4].try_into().unwrap()) == FATBIN_MAGIC {
+        return from_fatbin(image).unwrap_or_default();
+    }
+    let text = image.split(|&b| b == 0).next().unwrap_or_default();
+    match std::str::from_utf8(text) {
+        Ok(ptx) => from_ptx(ptx),
+        Err(_) => ParamTable::new(),
+    }
+}
+
+/// Whether a module image is PTX source, which the driver JIT-compiles,
+/// rather than a cubin or fat binary.
+pub fn is_ptx(image: &[u8]) -> bool {
+    let fatbin = image.len() >= 4 && u32::from_le_bytes(image[..4].try_into().unwrap()) == FATBIN_MAGIC;
+    !image.starts_with(b"\x7fELF") && !fatbin
+}
+
+fn read_u16(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+// ── Fat binaries ────────────────────────────────────────────────────
+
+fn from_fatbin(image: &[u8]) -> Option<ParamTable> {
+    let header_size = read_u16(image, 6)? as usize;
+    let fat_size = read_u64(image, 8)? as usize;
+    let end = (header_size + fat_size).min(image.len());
+
+    let mut table = ParamTable::new();
+    let mut at = header_size;
+    while at + 64 <= end {
+        let kind = read_u16(image, at)?;
+        let entry_header = read_u32(image, at + 4)? as usize;
+        let payload_size = read_u64(image, at + 8)? as usize;
+        let flags = read_u64(image, at + 40)?;
+        let payload = image.get(at + entry_header..at + entry_header + payload_size)?;
+        if flags & FATBIN_FLAG_COMPRESSED == 0 {
+            let kernels = match kind {
+                FATBIN_KIND_ELF => from_cubin(payload).unwrap_or_default(),
+                FATBIN_KIND_PTX => param_table(payload),
+                _ => ParamTable::new(),
+            };
+            // Every entry describes the same kernels; the first one wins.
+            for (name, sizes) in kernels {
+                table.entry(name).or_insert(sizes);
+            }
+        }
+        at += entry_header + payload_size;
+    }
+    Some(table)
+}
+
+// ── Cubins ──────────────────────────────────────────────────────────
+
+fn from_cubin(elf: &[u8]) -> Option<ParamTable> {
+    // 64-bit little-endian only, which is all nvcc has produced for years.
+    if elf.get(4) != Some(&2) || elf.get(5) != Some(&1) {
+        return None;
+    }
+    let shoff = read_u64(elf, 0x28)? as usize;
+    let shentsize = read_u16(elf, 0x3a)? as usize;
+    let shnum = read_u16(elf, 0x3c)? as usize;
+    let shstrndx = read_u16(elf, 0x3e)? as usize;
+
+    let section = |index: usize| -> Option<(u32, &[u8])> {
+        let header = shoff + index * shentsize;
+        let name = read_u32(elf, header)?;
+        let offset = read_u64(elf, header + 0x18)? as usize;
+        let size = read_u64(elf, header + 0x20)? as usize;
+        Some((name, elf.get(offset..offset + size)?))
+    };
+    let (_, names) = section(shstrndx)?;
+
+    let mut table = ParamTable::new();
+    for index in 0..shnum {
+        let Some((name, data)) = section(index) else {
+            continue;
+        };
+        let name = names.get(name as usize..).unwrap_or_default();
+        let name = name.split(|&b| b == 0).next().unwrap_or_default();
+        let Some(kernel) = name.strip_prefix(b".nv.info.") else {
+            continue;
+        };
+        if let Ok(kernel) = std::str::from_utf8(kernel) {
+            table.insert(kernel.to_string(), kparam_sizes(data));
+        }
+    }
+    Some(table)
+}
+
+/// Parameter sizes from the attribute records of a `.nv.info.<kernel>`
+/// section, ordered by ordinal.
+fn kparam_sizes(info: &[u8]) -> Vec<u32> {
+    let mut params = Vec::new();
+    let mut at = 0;
+    // Each record is a format byte, an attribute byte and a 16-bit value;
+    // for `EIFMT_SVAL` the value is the length of the payload that follows.
+    while at + 4 <= info.len() {
+        let format = info[at];
+        let attribute = info[at + 1];
+        let value = u16::from_le_bytes([info[at + 2], info[at + 3]]) as usize;
+        at += 4;
+        if format != EIFMT_SVAL {
+            continue;
+        }
+        let Some(payload) = info.get(at..at + value) else {
+            break;
+        };
+        at += value;
+        if attribute == EIATTR_KPARAM_INFO && payload.len() >= 12 {
+            let ordinal = u16::from_le_bytes([payload[4], payload[5]]);
+            let packed = u32::from_le_bytes(payload[8..12].try_into().unwrap());
+            params.push((ordinal, (packed >> 18) & 0x3fff));
+        }
+    }
+    params.sort_unstable();
+    params.into_iter().map(|(_, size)| size).collect()
+}
+
+// ── PTX ─────────────────────────────────────────────────────────────
+
+fn from_ptx(ptx: &str) -> ParamTable {
+    let mut table = ParamTable::new();
+    let mut rest = ptx;
+    while let Some(pos) = rest.find(".entry") {
+        rest = &rest[pos + ".entry".len()..];
+        // `.entry name (params) { ... }`; a kernel without parameters may
+        // have no parentheses at all.
+        rest = rest.trim_start();
+        let end = rest
+            .find(|c: char| c.is_whitespace() || c == '(' || c == '{')
+            .unwrap_or(rest.len());
+        let name = &rest[..end];
+        let trimmed = rest[end..].trim_start();
+        let sizes = if trimmed.starts_with('(') {
+            let close = trimmed.find(')').unwrap_or(trimmed.len());
+            trimmed[1..close]
+                .split(',')
+                .filter_map(ptx_param_size)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        table.insert(name.to_string(), sizes);
+    }
+    table
+}
+
+/// Size of one `.param` declaration, e.g. `.param .u64 .ptr .global p` or
+/// `.param .align 8 .b8 s[24]`.
+fn ptx_param_size(decl: &str) -> Option<u32> {
+    let decl = decl.trim();
+    if !decl.starts_with(".param") {
+        return None;
+    }
+    let element = decl.split_whitespace().find_map(|word| match word {
+        ".b8" | ".u8" | ".s8" => Some(1),
+        ".b16" | ".u16" | ".s16" | ".f16" | ".bf16" => Some(2),
+        ".b32" | ".u32" | ".s32" | ".f32" | ".f16x2" | ".bf16x2" => Some(4),
+        ".b64" | ".u64" | ".s64" | ".f64" => Some(8),
+        _ => None,
+    })?;
+    let count = match (decl.find('['), decl.find(']')) {
+        (Some(open), Some(close)) if open < close => decl[open + 1..close].trim().parse().ok()?,
+        _ => 1,
+    };
+    Some(element * count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ptx_signatures() {
+        let ptx = r#"
+.version 8.0
+.target sm_80
+.address_size 64
+
+.visible .entry _Z6vecAddPKfS0_Pfi(
+	.param .u64 .ptr .global .align 1 _Z6vecAddPKfS0_Pfi_param_0,
+	.param .u64 _Z6vecAddPKfS0_Pfi_param_1,
+	.param .u64 _Z6vecAddPKfS0_Pfi_param_2,
+	.param .u32 _Z6vecAddPKfS0_Pfi_param_3
+)
+{
+	ret;
+}
+
+.visible .entry scale(
+	.param .align 4 .b8 scale_param_0[12],
+	.param .f32 scale_param_1,
+	.param .u8 scale_param_2
+)
+{
+	ret;
+}
+
+.entry noargs
+{
+	ret;
+}
+"#;
+        let table = param_table(ptx.as_bytes());
+        assert_eq!(table["_Z6vecAddPKfS0_Pfi"], vec![8, 8, 8, 4]);
+        assert_eq!(table["scale"], vec![12, 4, 1]);
+        assert_eq!(table["noargs"], Vec::<u32>::new());
+    }
+
+    #[test]
+    fn kparam_info_records() {
+        fn kparam(ordinal: u16, offset: u16, size: u32) -> Vec<u8> {
+            let mut record = vec![EIFMT_SVAL, EIATTR_KPARAM_INFO, 12, 0];
+            record.extend_from_slice(&0u32.to_le_bytes());
+            record.extend_from_slice(&ordinal.to_le_bytes());
+            record.extend_from_slice(&offset.to_le_bytes());
+            record.extend_from_slice(&((size << 18) | 0x1f000).to_le_bytes());
+            record
+        }
+        // Records come last parameter first; other attributes are mixed in.
+        let mut info = vec![0x03, 0x1b, 0xff, 0x00];
+        info.extend(kparam(2, 16, 4));
+        info.extend(kparam(1, 8, 12));
+        info.extend([0x01, 0x2f, 0x00, 0x00]);
+        info.extend(kparam(0, 0, 8));
+        assert_eq!(kparam_sizes(&info), vec![8, 12, 4]);
+    }
+
+    #[test]
+    fn unknown_images() {
+        assert!(param_table(&[0xde, 0xad, 0xbe, 0xef, 0xff]).is_empty());
+        assert!(param_table(b"\x7fELF\x01\x01").is_empty());
+    }
+
+    #[test]
+    fn ptx_is_told_apart() {
+        assert!(is_ptx(b".version 8.0\n.target sm_80\n"));
+        assert!(!is_ptx(b"\x7fELF\x02\x01"));
+        assert!(!is_ptx(&FATBIN_MAGIC.to_le_bytes()));
+    }
+}
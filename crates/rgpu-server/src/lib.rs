@@ -1,8 +1,26 @@
 pub mod gpu_discovery;
+pub mod hotplug;
 pub mod cuda_driver;
 pub mod cuda_executor;
+pub mod device_attributes;
+pub mod device_overrides;
+pub mod kernel_params;
+pub mod content_store;
 pub mod vulkan_executor;
 pub mod session;
+pub mod session_devices;
+pub mod parked_sessions;
+pub mod scheduling;
+pub mod vram;
+pub mod virtual_gpus;
+pub mod topology;
+pub mod usage;
+pub mod affinity;
+pub mod limits;
+pub mod audit;
+pub mod uploads;
+#[cfg(feature = "chaos")]
+pub mod chaos;
 pub mod server;
 
 pub use server::RgpuServer;
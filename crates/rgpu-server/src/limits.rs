@@ -0,0 +1,143 @@
+//! Per-address limits on the listeners.
+//!
+//! A client that opens connections in a loop, or keeps dozens open, would
+//! otherwise take every session slot and a thread and queue for each. Every
+//! accepted connection is admitted here first: an address may open
+//! `connections_per_minute` connections in any minute and hold
+//! `sessions_per_ip` at once. Refused connections are told why and closed
+//! before a session is set up for them.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rgpu_core::config::LimitsConfig;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Addresses tracked before idle ones are swept out.
+const SWEEP_AT: usize = 4096;
+
+#[derive(Default)]
+struct Address {
+    /// When its recent connections were accepted, oldest first
+    accepted: VecDeque<Instant>,
+    /// Connections currently open
+    open: u32,
+}
+
+/// Why a connection was turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+    RateLimited(u32),
+    TooManySessions(u32),
+}
+
+impl std::fmt::Display for Refusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Refusal::RateLimited(limit) => write!(f, "more than {} connections per minute from this address", limit),
+            Refusal::TooManySessions(limit) => write!(f, "more than {} open connections from this address", limit),
+        }
+    }
+}
+
+pub struct ConnectionLimiter {
+    per_minute: u32,
+    per_address: u32,
+    addresses: parking_lot::Mutex<HashMap<IpAddr, Address>>,
+}
+
+/// An admitted connection; frees its slot when dropped.
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    ip: IpAddr,
+}
+
+impl ConnectionLimiter {
+    pub fn new(config: &LimitsConfig) -> Self {
+        Self {
+            per_minute: config.connections_per_minute,
+            per_address: config.sessions_per_ip,
+            addresses: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Admit a connection from `ip`, or say why not.
+    pub fn admit(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionPermit, Refusal> {
+        self.admit_at(ip, Instant::now())
+    }
+
+    fn admit_at(self: &Arc<Self>, ip: IpAddr, now: Instant) -> Result<ConnectionPermit, Refusal> {
+        let mut addresses = self.addresses.lock();
+        if addresses.len() >= SWEEP_AT {
+            addresses.retain(|_, address| {
+                address.open > 0 || address.accepted.back().is_some_and(|&at| now.duration_since(at) < WINDOW)
+            });
+        }
+
+        let address = addresses.entry(ip).or_default();
+        while address.accepted.front().is_some_and(|&at| now.duration_since(at) >= WINDOW) {
+            address.accepted.pop_front();
+        }
+        if self.per_minute > 0 && address.accepted.len() >= self.per_minute as usize {
+            return Err(Refusal::RateLimited(self.per_minute));
+        }
+        if self.per_address > 0 && address.open >= self.per_address {
+            return Err(Refusal::TooManySessions(self.per_address));
+        }
+        address.accepted.push_back(now);
+        address.open += 1;
+        Ok(ConnectionPermit {
+            limiter: self.clone(),
+            ip,
+        })
+    }
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        if let Some(address) = self.limiter.addresses.lock().get_mut(&self.ip) {
+            address.open = address.open.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(connections_per_minute: u32, sessions_per_ip: u32) -> Arc<ConnectionLimiter> {
+        Arc::new(ConnectionLimiter::new(&LimitsConfig {
+            connections_per_minute,
+            sessions_per_ip,
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn connections_per_minute() {
+        let limiter = limiter(2, 0);
+        let (a, b): (IpAddr, IpAddr) = ("10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap());
+        let start = Instant::now();
+
+        let _first = limiter.admit_at(a, start).unwrap();
+        drop(limiter.admit_at(a, start).unwrap());
+        assert_eq!(limiter.admit_at(a, start).err(), Some(Refusal::RateLimited(2)));
+        // Other addresses have their own budget, and it refills.
+        assert!(limiter.admit_at(b, start).is_ok());
+        assert!(limiter.admit_at(a, start + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn open_connections_per_address() {
+        let limiter = limiter(0, 1);
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+
+        let first = limiter.admit(ip).unwrap();
+        assert_eq!(limiter.admit(ip).err(), Some(Refusal::TooManySessions(1)));
+        drop(first);
+        assert!(limiter.admit(ip).is_ok());
+    }
+}
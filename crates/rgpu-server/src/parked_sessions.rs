@@ -0,0 +1,110 @@
+//! Sessions whose connection dropped, kept alive for their client to resume.
+//!
+//! A network blip shouldn't cost a running application its contexts,
+//! allocations and modules. When a connection closes, its session is parked
+//! here with everything it holds for the configured grace period. A client
+//! that reconnects in time and sends `ResumeSession` with the session id and
+//! the resume token it got at authentication takes the session over, handles
+//! and all. Sessions nobody claims are torn down when the period is up.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::session::Session;
+
+pub struct ParkedSessions {
+    grace: Duration,
+    sessions: parking_lot::Mutex<HashMap<u32, Arc<Session>>>,
+}
+
+impl ParkedSessions {
+    pub fn new(grace: Duration) -> Self {
+        Self {
+            grace,
+            sessions: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How long a parked session waits for its client.
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    /// Keep `session` for its client. Returns false if there is nothing to
+    /// keep, or no grace period, and the session should be torn down now.
+    pub fn park(&self, session: Arc<Session>) -> bool {
+        if self.grace.is_zero() || session.all_handles().is_empty() {
+            return false;
+        }
+        self.sessions.lock().insert(session.session_id, session);
+        true
+    }
+
+    /// Hand a parked session to the client that presents its token.
+    pub fn claim(&self, session_id: u32, resume_token: u64) -> Option<Arc<Session>> {
+        let mut sessions = self.sessions.lock();
+        match sessions.get(&session_id) {
+            Some(session) if session.resume_token == resume_token => sessions.remove(&session_id),
+            _ => None,
+        }
+    }
+
+    /// Remove a session whose grace period is up, unless it was claimed.
+    pub fn expire(&self, session_id: u32) -> Option<Arc<Session>> {
+        self.sessions.lock().remove(&session_id)
+    }
+
+    /// Number of sessions waiting for their client.
+    pub fn len(&self) -> usize {
+        self.sessions.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::ResourceType;
+
+    fn session_with_memory(session_id: u32) -> Arc<Session> {
+        let session = Arc::new(Session::new(session_id, 0, "test".to_string()));
+        session.alloc_handle(ResourceType::CuContext);
+        session.alloc_handle(ResourceType::CuDevicePtr);
+        session
+    }
+
+    #[test]
+    fn only_the_token_holder_can_claim() {
+        let parked = ParkedSessions::new(Duration::from_secs(30));
+        let session = session_with_memory(5);
+        assert!(parked.park(session.clone()));
+
+        assert!(parked.claim(5, session.resume_token.wrapping_add(1)).is_none());
+        assert!(parked.claim(6, session.resume_token).is_none());
+        let claimed = parked.claim(5, session.resume_token).expect("claimed");
+        assert!(parked.is_empty());
+
+        // The new connection's session takes the handles over.
+        let resumed = Session::new(9, 0, "test".to_string());
+        let handles = claimed.all_handles();
+        assert_eq!(resumed.adopt(&claimed), 2);
+        assert!(handles.iter().all(|h| resumed.validate_handle(h)));
+        assert!(claimed.all_handles().is_empty());
+        assert_eq!(parked.expire(5).map(|s| s.session_id), None);
+    }
+
+    #[test]
+    fn nothing_is_parked_without_a_grace_period_or_resources() {
+        let parked = ParkedSessions::new(Duration::ZERO);
+        assert!(!parked.park(session_with_memory(1)));
+
+        let parked = ParkedSessions::new(Duration::from_secs(30));
+        assert!(!parked.park(Arc::new(Session::new(2, 0, "test".to_string()))));
+        assert!(parked.park(session_with_memory(3)));
+        assert_eq!(parked.expire(3).map(|s| s.session_id), Some(3));
+    }
+}
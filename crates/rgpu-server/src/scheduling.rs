@@ -0,0 +1,454 @@
+//! How sessions share each GPU (`[server.scheduling]`).
+//!
+//! A session starts using a GPU when it creates a CUDA context or Vulkan
+//! device on it, and stops when it disconnects. What happens then depends on
+//! the GPU's mode:
+//!
+//! - `shared`: nothing; sessions run side by side as the driver schedules
+//!   them.
+//! - `exclusive`: the first session leases the GPU until it disconnects.
+//!   Other sessions can't create contexts or devices on it meanwhile.
+//! - `time_slice`: sessions take turns. A session keeps the GPU for up to
+//!   `time_slice_ms` of commands while others are waiting, then queues
+//!   behind them; the others' commands wait for their turn.
+//! - `partitioned`: the GPU's memory is split into `partitions` equal parts.
+//!   Each session may allocate one part, and once all are taken further
+//!   sessions are refused like with `exclusive`.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info};
+
+use rgpu_core::config::SchedulingConfig;
+use rgpu_protocol::gpu_info::{GpuInfo, GpuScheduling, SchedulingMode};
+
+use crate::session::Session;
+use crate::vram::DeviceUuid;
+
+/// Why a session can't use a GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refused {
+    /// Leased to another session
+    Leased { device: u32, session_id: u32 },
+    /// Every partition is taken
+    Full { device: u32, partitions: u32 },
+}
+
+impl fmt::Display for Refused {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refused::Leased { device, session_id } => {
+                write!(f, "GPU {} is leased exclusively to session {}", device, session_id)
+            }
+            Refused::Full { device, partitions } => {
+                write!(f, "all {} partitions of GPU {} are in use", partitions, device)
+            }
+        }
+    }
+}
+
+struct Device {
+    index: u32,
+    mode: SchedulingMode,
+    partitions: u32,
+    partition_bytes: u64,
+    /// Sessions using the GPU, in the order they started
+    sessions: parking_lot::Mutex<Vec<u32>>,
+    /// With `TimeSlice`, whose turn it is
+    turns: Option<Arc<Turns>>,
+}
+
+/// Scheduling of all GPUs on the server.
+#[derive(Default)]
+pub struct Scheduler {
+    devices: HashMap<DeviceUuid, Device>,
+}
+
+impl Scheduler {
+    pub fn from_config(config: &SchedulingConfig, gpus: &[(GpuInfo, DeviceUuid)]) -> Self {
+        let slice = Duration::from_millis(config.time_slice_ms.max(1));
+        let devices = gpus
+            .iter()
+            .map(|(gpu, uuid)| {
+                let (mode, partitions) = config.for_device(gpu.server_device_index);
+                let partitions = partitions.max(1);
+                if mode != SchedulingMode::Shared {
+                    info!("GPU {}: {:?} scheduling", gpu.server_device_index, mode);
+                }
+                let device = Device {
+                    index: gpu.server_device_index,
+                    mode,
+                    partitions,
+                    partition_bytes: gpu.total_memory / partitions as u64,
+                    sessions: parking_lot::Mutex::new(Vec::new()),
+                    turns: (mode == SchedulingMode::TimeSlice).then(|| Arc::new(Turns::new(slice))),
+                };
+                (*uuid, device)
+            })
+            .collect();
+        Self { devices }
+    }
+
+    /// VRAM quotas of memory-partitioned GPUs, for the `VramLedger`.
+    pub fn partition_quotas(&self) -> HashMap<DeviceUuid, u64> {
+        self.devices
+            .iter()
+            .filter(|(_, device)| device.mode == SchedulingMode::Partitioned)
+            .map(|(uuid, device)| (*uuid, device.partition_bytes))
+            .collect()
+    }
+
+    /// Let `session_id` use `device`, or say why it can't. Called before a
+    /// CUDA context or Vulkan device is created on it.
+    pub fn admit(&self, session_id: u32, device: &DeviceUuid) -> Result<(), Refused> {
+        let Some(device) = self.devices.get(device) else {
+            return Ok(());
+        };
+        let mut sessions = device.sessions.lock();
+        if sessions.contains(&session_id) {
+            return Ok(());
+        }
+        match device.mode {
+            SchedulingMode::Exclusive if !sessions.is_empty() => {
+                return Err(Refused::Leased {
+                    device: device.index,
+                    session_id: sessions[0],
+                });
+            }
+            SchedulingMode::Partitioned if sessions.len() >= device.partitions as usize => {
+                return Err(Refused::Full {
+                    device: device.index,
+                    partitions: device.partitions,
+                });
+            }
+            SchedulingMode::Exclusive => info!(session_id, "GPU {} leased", device.index),
+            _ => {}
+        }
+        sessions.push(session_id);
+        Ok(())
+    }
+
+    /// Wait for the session's turn on its GPU if that is time-sliced. The
+    /// turn lasts until the returned guard is dropped.
+    pub async fn turn(&self, session: &Session) -> Option<Turn> {
+        let turns = self.devices.get(&session.device()?)?.turns.clone()?;
+        turns.acquire(session.session_id).await;
+        Some(Turn { turns })
+    }
+
+    /// Release everything a disconnected session held.
+    pub fn end_session(&self, session_id: u32) {
+        for device in self.devices.values() {
+            let mut sessions = device.sessions.lock();
+            let before = sessions.len();
+            sessions.retain(|&s| s != session_id);
+            if sessions.len() < before && device.mode == SchedulingMode::Exclusive {
+                info!(session_id, "GPU {} lease released", device.index);
+            }
+            if let Some(turns) = &device.turns {
+                turns.remove(session_id);
+            }
+        }
+    }
+
+    /// Hand a resumed session's leases and partitions to the session that
+    /// took it over.
+    pub fn transfer_session(&self, from: u32, to: u32) {
+        for device in self.devices.values() {
+            for session in device.sessions.lock().iter_mut() {
+                if *session == from {
+                    *session = to;
+                }
+            }
+            if let Some(turns) = &device.turns {
+                turns.remove(from);
+            }
+        }
+    }
+
+    /// Fill in the scheduling state of each GPU in `gpus`.
+    pub fn annotate(&self, gpus: &mut [GpuInfo]) {
+        for gpu in gpus {
+            let Some(device) = self.devices.values().find(|d| d.index == gpu.server_device_index) else {
+                continue;
+            };
+            gpu.scheduling = GpuScheduling {
+                mode: device.mode,
+                sessions: device.sessions.lock().clone(),
+                current_turn: device.turns.as_ref().and_then(|t| t.current()),
+                partitions: match device.mode {
+                    SchedulingMode::Partitioned => device.partitions,
+                    _ => 0,
+                },
+                partition_bytes: match device.mode {
+                    SchedulingMode::Partitioned => device.partition_bytes,
+                    _ => 0,
+                },
+            };
+        }
+    }
+}
+
+/// A session's turn on a time-sliced GPU.
+pub struct Turn {
+    turns: Arc<Turns>,
+}
+
+impl Drop for Turn {
+    fn drop(&mut self) {
+        self.turns.release();
+    }
+}
+
+/// Round-robin turns on one GPU.
+struct Turns {
+    slice: Duration,
+    state: parking_lot::Mutex<TurnState>,
+    changed: tokio::sync::Notify,
+}
+
+#[derive(Default)]
+struct TurnState {
+    holder: Option<u32>,
+    /// Commands of the holder executing now
+    running: u32,
+    slice_ends: Option<Instant>,
+    /// Sessions waiting for a turn, in order
+    waiting: VecDeque<u32>,
+}
+
+/// Outcome of trying to take a turn.
+enum Take {
+    Taken,
+    /// Wait for a change, or until the holder's slice ends
+    Wait(Option<Instant>),
+}
+
+impl TurnState {
+    fn try_take(&mut self, session_id: u32, slice: Duration, now: Instant) -> Take {
+        let slice_over = self.slice_ends.is_none_or(|end| now >= end);
+        if let Some(holder) = self.holder {
+            if holder == session_id {
+                if !slice_over || self.waiting.is_empty() {
+                    if slice_over {
+                        self.slice_ends = Some(now + slice);
+                    }
+                    self.running += 1;
+                    return Take::Taken;
+                }
+                if self.running > 0 {
+                    // Concurrent commands of the holder (QUIC streams)
+                    // finish its turn first.
+                    self.enqueue(session_id);
+                    return Take::Wait(None);
+                }
+                // Slice used up while others wait: to the back of the queue.
+                self.holder = None;
+            } else if self.running > 0 {
+                self.enqueue(session_id);
+                return Take::Wait(None);
+            } else if !slice_over {
+                self.enqueue(session_id);
+                return Take::Wait(self.slice_ends);
+            } else {
+                // Idle past its slice; the holder queues again when it
+                // next has work.
+                self.holder = None;
+            }
+        }
+        match self.waiting.front() {
+            Some(&first) if first != session_id => {
+                self.enqueue(session_id);
+                Take::Wait(None)
+            }
+            _ => {
+                self.waiting.pop_front();
+                self.holder = Some(session_id);
+                self.running = 1;
+                self.slice_ends = Some(now + slice);
+                Take::Taken
+            }
+        }
+    }
+
+    fn enqueue(&mut self, session_id: u32) {
+        if !self.waiting.contains(&session_id) {
+            self.waiting.push_back(session_id);
+        }
+    }
+}
+
+impl Turns {
+    fn new(slice: Duration) -> Self {
+        Self {
+            slice,
+            state: parking_lot::Mutex::new(TurnState::default()),
+            changed: tokio::sync::Notify::new(),
+        }
+    }
+
+    async fn acquire(&self, session_id: u32) {
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            // Registered before looking at the state so a release in
+            // between isn't missed.
+            notified.as_mut().enable();
+            let (take, holder_changed) = {
+                let mut state = self.state.lock();
+                let holder = state.holder;
+                let take = state.try_take(session_id, self.slice, Instant::now());
+                (take, state.holder != holder)
+            };
+            if holder_changed {
+                self.changed.notify_waiters();
+            }
+            match take {
+                Take::Taken => return,
+                Take::Wait(Some(until)) => {
+                    debug!(session_id, "waiting for a GPU turn");
+                    let _ = tokio::time::timeout_at(until.into(), notified).await;
+                }
+                Take::Wait(None) => {
+                    debug!(session_id, "waiting for a GPU turn");
+                    notified.await;
+                }
+            }
+        }
+    }
+
+    fn release(&self) {
+        let mut state = self.state.lock();
+        state.running = state.running.saturating_sub(1);
+        drop(state);
+        self.changed.notify_waiters();
+    }
+
+    fn remove(&self, session_id: u32) {
+        let mut state = self.state.lock();
+        state.waiting.retain(|&s| s != session_id);
+        if state.holder == Some(session_id) {
+            state.holder = None;
+            state.running = 0;
+        }
+        drop(state);
+        self.changed.notify_waiters();
+    }
+
+    fn current(&self) -> Option<u32> {
+        self.state.lock().holder
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpu(index: u32, total_memory: u64) -> (GpuInfo, DeviceUuid) {
+        let info = GpuInfo {
+            device_name: format!("GPU {}", index),
+            vendor_id: 0x10DE,
+            device_id: 0,
+            device_type: rgpu_protocol::gpu_info::GpuDeviceType::DiscreteGpu,
+            total_memory,
+            supports_vulkan: true,
+            supports_cuda: true,
+            vulkan_api_version: None,
+            vulkan_driver_version: None,
+            cuda_compute_capability: None,
+            queue_family_count: 1,
+            memory_heaps: Vec::new(),
+            server_device_index: index,
+            server_id: 0,
+            topology: Default::default(),
+            scheduling: Default::default(),
+            uuid: None,
+        };
+        (info, [index as u8; 16])
+    }
+
+    fn scheduler(mode: SchedulingMode) -> Scheduler {
+        let config = SchedulingConfig {
+            mode,
+            partitions: 2,
+            ..Default::default()
+        };
+        Scheduler::from_config(&config, &[gpu(0, 8 << 30)])
+    }
+
+    #[test]
+    fn exclusive_lease_lasts_until_the_session_ends() {
+        let scheduler = scheduler(SchedulingMode::Exclusive);
+        let uuid = [0; 16];
+        assert_eq!(scheduler.admit(1, &uuid), Ok(()));
+        assert_eq!(scheduler.admit(1, &uuid), Ok(()));
+        assert_eq!(scheduler.admit(2, &uuid), Err(Refused::Leased { device: 0, session_id: 1 }));
+
+        let mut gpus = vec![gpu(0, 8 << 30).0];
+        scheduler.annotate(&mut gpus);
+        assert_eq!(gpus[0].scheduling.leased_to(), Some(1));
+
+        scheduler.end_session(1);
+        assert_eq!(scheduler.admit(2, &uuid), Ok(()));
+        // GPUs the scheduler doesn't know aren't scheduled.
+        assert_eq!(scheduler.admit(3, &[9; 16]), Ok(()));
+    }
+
+    #[test]
+    fn partitions_split_memory_between_sessions() {
+        let scheduler = scheduler(SchedulingMode::Partitioned);
+        let uuid = [0; 16];
+        assert_eq!(scheduler.partition_quotas()[&uuid], 4 << 30);
+        assert_eq!(scheduler.admit(1, &uuid), Ok(()));
+        assert_eq!(scheduler.admit(2, &uuid), Ok(()));
+        assert_eq!(scheduler.admit(3, &uuid), Err(Refused::Full { device: 0, partitions: 2 }));
+        scheduler.end_session(1);
+        assert_eq!(scheduler.admit(3, &uuid), Ok(()));
+        assert!(self::scheduler(SchedulingMode::Shared).partition_quotas().is_empty());
+    }
+
+    #[test]
+    fn turns_rotate_when_the_slice_is_used_up() {
+        let slice = Duration::from_millis(50);
+        let mut state = TurnState::default();
+        let start = Instant::now();
+        assert!(matches!(state.try_take(1, slice, start), Take::Taken));
+        // Session 2 waits while session 1 runs.
+        assert!(matches!(state.try_take(2, slice, start), Take::Wait(None)));
+        state.running = 0;
+        // Session 1 keeps its turn within the slice...
+        assert!(matches!(state.try_take(1, slice, start), Take::Taken));
+        state.running = 0;
+        // ...and 2 waits for the slice to end while 1 is idle.
+        assert!(matches!(state.try_take(2, slice, start), Take::Wait(Some(_))));
+
+        // Past the slice, 1 goes behind 2.
+        let later = start + slice;
+        assert!(matches!(state.try_take(1, slice, later), Take::Wait(None)));
+        assert_eq!(state.holder, None);
+        assert!(matches!(state.try_take(2, slice, later), Take::Taken));
+        state.running = 0;
+        assert!(matches!(state.try_take(1, slice, later), Take::Wait(_)));
+        assert_eq!(state.waiting, [1]);
+    }
+
+    #[tokio::test]
+    async fn turns_are_handed_over_on_release() {
+        let turns = Arc::new(Turns::new(Duration::from_millis(1)));
+        turns.acquire(1).await;
+        let waiter = {
+            let turns = turns.clone();
+            tokio::spawn(async move { turns.acquire(2).await })
+        };
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert_eq!(turns.current(), Some(1));
+        turns.release();
+        tokio::time::timeout(Duration::from_secs(5), waiter).await.unwrap().unwrap();
+        assert_eq!(turns.current(), Some(2));
+        turns.remove(2);
+        assert_eq!(turns.current(), None);
+    }
+}
@@ -1,23 +1,44 @@
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 use tokio::net::TcpListener;
 use tokio::sync::watch;
 use tracing::{debug, error, info, warn};
 
+use rgpu_protocol::compat;
 use rgpu_protocol::gpu_info::GpuInfo;
-use rgpu_protocol::messages::{Message, PROTOCOL_VERSION};
+use rgpu_protocol::error::ProtocolError;
+use rgpu_protocol::messages::{Message, RdmaEndpoint, SessionSummary, PROTOCOL_VERSION};
+use rgpu_protocol::version::{BuildInfo, Compatibility};
+use rgpu_protocol::wire::{self, CompressionSettings};
 
-use rgpu_core::config::{ServerConfig, TransportMode};
+use rgpu_core::config::{RdmaConfig, ServerConfig, TransportMode};
 use rgpu_transport::auth;
-use rgpu_transport::connection::RgpuConnection;
+use rgpu_transport::connection::{skip_payload, RgpuConnection};
+use rgpu_transport::rdma::{self, RdmaLink};
 use rgpu_transport::tls;
+use rgpu_transport::websocket;
+use rgpu_transport::TransportError;
 
+use crate::audit::{self, AuditLog};
+use crate::affinity::{CpuAffinity, SessionWorker};
 use crate::cuda_executor::CudaExecutor;
+use crate::device_overrides::DeviceOverrides;
+use crate::hotplug::GpuWatch;
+use crate::virtual_gpus::VirtualGpus;
 use crate::vulkan_executor::VulkanExecutor;
 use crate::gpu_discovery;
-use crate::session::Session;
+use crate::limits::ConnectionLimiter;
+use crate::parked_sessions::ParkedSessions;
+use crate::scheduling::Scheduler;
+use crate::session::{InFlightRequest, Session, TokenScope};
+use crate::session_devices::SessionDevices;
+use crate::vram::VramLedger;
+
+/// Messages a connection's reader may queue ahead of the executing command.
+const INBOUND_QUEUE_DEPTH: usize = 64;
 
 /// Server-wide metrics tracked via atomic counters.
 pub struct ServerMetrics {
@@ -29,10 +50,14 @@ pub struct ServerMetrics {
     pub vulkan_commands: AtomicU64,
     pub start_time: std::time::Instant,
     pub bind_address: parking_lot::RwLock<String>,
+    /// Connected sessions, for the per-session part of `MetricsData`
+    pub sessions: parking_lot::RwLock<HashMap<u32, Arc<Session>>>,
+    /// VRAM held by each session, shared with the executors
+    pub vram: Arc<VramLedger>,
 }
 
 impl ServerMetrics {
-    fn new() -> Self {
+    fn new(vram: Arc<VramLedger>) -> Self {
         Self {
             connections_total: AtomicU64::new(0),
             connections_active: AtomicU32::new(0),
@@ -42,20 +67,127 @@ impl ServerMetrics {
             vulkan_commands: AtomicU64::new(0),
             start_time: std::time::Instant::now(),
             bind_address: parking_lot::RwLock::new(String::new()),
+            sessions: parking_lot::RwLock::new(HashMap::new()),
+            vram,
+        }
+    }
+
+    fn register_session(&self, session: &Arc<Session>) {
+        self.sessions.write().insert(session.session_id, session.clone());
+    }
+
+    /// Restrict `session` to the scope of its token, unless `max_sessions`
+    /// other connected sessions already use that token.
+    fn scope_session(&self, session: &Session, scope: TokenScope, max_sessions: Option<u32>) -> bool {
+        let sessions = self.sessions.write();
+        if let Some(max) = max_sessions {
+            let using = sessions
+                .values()
+                .filter(|s| s.session_id != session.session_id && s.token().as_deref() == Some(scope.token.as_str()))
+                .count();
+            if using >= max as usize {
+                return false;
+            }
         }
+        session.set_scope(scope);
+        true
+    }
+
+    fn unregister_session(&self, session: &Session) {
+        self.sessions.write().remove(&session.session_id);
+        self.vram.end_session(session.session_id);
+    }
+
+    /// Summaries of all connected sessions, ordered by session ID.
+    pub fn session_summaries(&self) -> Vec<SessionSummary> {
+        let mut summaries: Vec<_> = self.sessions.read().values().map(|s| s.summary(&self.vram)).collect();
+        summaries.sort_by_key(|s| s.session_id);
+        summaries
     }
 }
 
 /// The main RGPU server. Listens for incoming connections and serves GPU commands.
 pub struct RgpuServer {
     config: ServerConfig,
-    gpu_infos: Vec<GpuInfo>,
     cuda_executor: Arc<CudaExecutor>,
     vulkan_executor: Arc<VulkanExecutor>,
     next_session_id: AtomicU32,
     /// Accepted authentication tokens (empty = no auth required)
     accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
     metrics: Arc<ServerMetrics>,
+    execution: Arc<Execution>,
+    /// Connection rate and count per client address
+    limiter: Arc<ConnectionLimiter>,
+}
+
+/// How sessions' GPU commands are run, shared by all connections.
+#[derive(Clone)]
+struct Execution {
+    /// CPU placement of session worker threads
+    affinity: Arc<CpuAffinity>,
+    /// GPUs of ended sessions, for clients that resume them
+    session_devices: Arc<SessionDevices>,
+    /// Sessions of dropped connections, kept for their clients to resume
+    parked: Arc<ParkedSessions>,
+    /// Leases and turns on the GPUs
+    scheduler: Arc<Scheduler>,
+    /// The GPUs clients see, as of the last rescan
+    gpus: Arc<GpuWatch>,
+    /// Which session holds each virtual GPU
+    virtual_gpus: Arc<VirtualGpus>,
+    /// Compression of responses, before fitting it to each client's version
+    compression: CompressionSettings,
+    /// Largest message a client may send, compressed or not
+    max_message_size: usize,
+    /// Record of the commands sessions run, if `[server.audit]` is set
+    audit: Option<Arc<AuditLog>>,
+    #[cfg(feature = "chaos")]
+    chaos: Option<Arc<crate::chaos::Chaos>>,
+}
+
+impl Execution {
+    /// Forget per-session state when a session ends, keeping what a
+    /// resuming client needs.
+    fn end_session(&self, session: &Session) {
+        self.session_devices.save(session);
+        self.scheduler.end_session(session.session_id);
+        self.virtual_gpus.end_session(session.session_id);
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = &self.chaos {
+            chaos.clear_session(session.session_id);
+        }
+    }
+
+    /// Free the session of a closed connection, or park it with everything
+    /// it holds for the grace period in case its client reconnects.
+    fn close_session(
+        self: &Arc<Self>,
+        session: Arc<Session>,
+        cuda_executor: &Arc<CudaExecutor>,
+        vulkan_executor: &Arc<VulkanExecutor>,
+        metrics: &Arc<ServerMetrics>,
+    ) {
+        metrics.unregister_session(&session);
+        let session_id = session.session_id;
+        if !self.parked.park(session.clone()) {
+            self.end_session(&session);
+            session.teardown(cuda_executor, vulkan_executor, &metrics.vram);
+            return;
+        }
+
+        let grace = self.parked.grace();
+        info!(session_id, "keeping session for {}s for its client to resume", grace.as_secs());
+        let (execution, cuda_executor, vulkan_executor, metrics) =
+            (self.clone(), cuda_executor.clone(), vulkan_executor.clone(), metrics.clone());
+        tokio::spawn(async move {
+            tokio::time::sleep(grace).await;
+            if let Some(session) = execution.parked.expire(session_id) {
+                info!(session_id, "session not resumed in time");
+                execution.end_session(&session);
+                session.teardown(&cuda_executor, &vulkan_executor, &metrics.vram);
+            }
+        });
+    }
 }
 
 impl RgpuServer {
@@ -64,24 +196,95 @@ impl RgpuServer {
         config: ServerConfig,
         accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
     ) -> Self {
-        let gpu_infos = gpu_discovery::discover_gpus(config.server_id);
-        let cuda_executor = Arc::new(CudaExecutor::new(gpu_infos.clone()));
-        let vulkan_executor = Arc::new(VulkanExecutor::new());
+        let mut gpus = gpu_discovery::discover_gpus_with_uuids(config.server_id);
+        let discovered = gpus.clone();
+        let affinity = Arc::new(CpuAffinity::from_config(&config, &gpus));
+        let scheduler = Arc::new(Scheduler::from_config(&config.scheduling, &gpus));
+        // Clients see the overridden properties; quotas stay on the real ones
+        let overrides = Arc::new(DeviceOverrides::from_config(&config, &gpus));
+        overrides.apply(&mut gpus);
+        // Clients see split GPUs as their virtual GPUs; the executors and
+        // the ledger work on the physical ones
+        let virtual_gpus = Arc::new(VirtualGpus::from_config(&config.virtual_gpus, &gpus));
+        let gpu_watch = Arc::new(GpuWatch::new(config.server_id, overrides.clone(), virtual_gpus.clone(), discovered));
+        let (physical_gpus, uuids): (Vec<GpuInfo>, Vec<_>) = gpus.into_iter().unzip();
+        let vram = Arc::new(
+            VramLedger::new(uuids, config.session_vram_quota_mb * 1024 * 1024)
+                .with_device_quotas(scheduler.partition_quotas()),
+        );
+        let cuda_executor = Arc::new(
+            CudaExecutor::new(physical_gpus)
+                .with_vram_ledger(vram.clone())
+                .with_scheduler(scheduler.clone())
+                .with_device_overrides(overrides.clone())
+                .with_virtual_gpus(virtual_gpus.clone()),
+        );
+        let vulkan_executor = Arc::new(
+            VulkanExecutor::new()
+                .with_vram_ledger(vram.clone())
+                .with_scheduler(scheduler.clone())
+                .with_device_overrides(overrides.clone())
+                .with_virtual_gpus(virtual_gpus.clone())
+                .with_pipeline_cache_dir(rgpu_common::platform::state_dir().join("pipeline-cache")),
+        );
+        let session_devices = Arc::new(SessionDevices::in_state_dir());
+        let parked = Arc::new(ParkedSessions::new(Duration::from_secs(config.session_grace_secs)));
+        let compression = config.compression.settings();
+        let limiter = Arc::new(ConnectionLimiter::new(&config.limits));
+        let max_message_size = config.limits.max_message_size();
+        let audit = AuditLog::from_config(&config.audit).map(Arc::new);
 
         Self {
             config,
-            gpu_infos,
             cuda_executor,
             vulkan_executor,
-            next_session_id: AtomicU32::new(1),
+            // Ids of sessions that can still be resumed stay theirs.
+            next_session_id: AtomicU32::new(session_devices.max_session_id() + 1),
             accepted_tokens,
-            metrics: Arc::new(ServerMetrics::new()),
+            metrics: Arc::new(ServerMetrics::new(vram)),
+            execution: Arc::new(Execution {
+                affinity,
+                session_devices,
+                parked,
+                scheduler,
+                gpus: gpu_watch,
+                virtual_gpus,
+                compression,
+                max_message_size,
+                audit,
+                #[cfg(feature = "chaos")]
+                chaos: crate::chaos::ChaosConfig::from_env().and_then(|config| match config {
+                    Ok(config) => {
+                        warn!("RGPU_CHAOS set, injecting faults: {:?}", config);
+                        Some(Arc::new(crate::chaos::Chaos::new(config)))
+                    }
+                    Err(e) => {
+                        error!("ignoring RGPU_CHAOS: {}", e);
+                        None
+                    }
+                }),
+            }),
+            limiter,
         }
     }
 
-    /// Returns a reference to the discovered GPU information.
-    pub fn gpu_infos(&self) -> &[GpuInfo] {
-        &self.gpu_infos
+    /// Inject faults into sessions' GPU commands.
+    #[cfg(feature = "chaos")]
+    pub fn with_chaos(mut self, chaos: Arc<crate::chaos::Chaos>) -> Self {
+        Arc::make_mut(&mut self.execution).chaos = Some(chaos);
+        self
+    }
+
+    /// The GPUs clients see, as of the last rescan.
+    pub fn gpu_infos(&self) -> Arc<Vec<GpuInfo>> {
+        self.execution.gpus.gpus()
+    }
+
+    /// The discovered GPUs with their current leases and turns.
+    pub fn gpu_status(&self) -> Vec<GpuInfo> {
+        let mut gpus = self.gpu_infos().to_vec();
+        self.execution.scheduler.annotate(&mut gpus);
+        gpus
     }
 
     /// Returns a reference to the server metrics.
@@ -97,10 +300,11 @@ impl RgpuServer {
         &self,
         shutdown_rx: watch::Receiver<bool>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let gpus = self.gpu_infos();
         info!(
             "serving {} GPU(s): {}",
-            self.gpu_infos.len(),
-            self.gpu_infos
+            gpus.len(),
+            gpus
                 .iter()
                 .map(|g| g.device_name.as_str())
                 .collect::<Vec<_>>()
@@ -133,18 +337,16 @@ impl RgpuServer {
         *self.metrics.bind_address.write() =
             format!("{}:{}", self.config.bind, self.config.port);
 
-        match self.config.transport {
-            TransportMode::Quic => self.run_quic(shutdown_rx).await,
-            TransportMode::Tcp => self.run_tcp(shutdown_rx).await,
-        }
+        self.run_listeners(shutdown_rx).await
     }
 
     /// Start listening for connections.
     pub async fn run(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let gpus = self.gpu_infos();
         info!(
             "serving {} GPU(s): {}",
-            self.gpu_infos.len(),
-            self.gpu_infos
+            gpus.len(),
+            gpus
                 .iter()
                 .map(|g| g.device_name.as_str())
                 .collect::<Vec<_>>()
@@ -184,21 +386,67 @@ impl RgpuServer {
         *self.metrics.bind_address.write() =
             format!("{}:{}", self.config.bind, self.config.port);
 
-        match self.config.transport {
-            TransportMode::Quic => self.run_quic(shutdown_rx).await,
-            TransportMode::Tcp => self.run_tcp(shutdown_rx).await,
+        self.run_listeners(shutdown_rx).await
+    }
+
+    /// Listen on the configured transport. `auto` serves TCP and QUIC on the
+    /// same port, so each client can pick whichever works better on its
+    /// network; without a certificate there is no QUIC and it serves TCP.
+    /// `rdma` serves TCP, with bulk data over RDMA for clients that ask.
+    /// `websocket_port` serves WebSocket there besides.
+    async fn run_listeners(
+        &self,
+        shutdown_rx: watch::Receiver<bool>,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.config.gpu_rescan_secs > 0 {
+            let every = Duration::from_secs(self.config.gpu_rescan_secs);
+            tokio::spawn(self.execution.gpus.clone().run(every, shutdown_rx.clone()));
+        }
+        let port = self.config.port;
+        let main = async {
+            match self.config.transport {
+                TransportMode::Quic => self.run_quic(shutdown_rx.clone()).await,
+                TransportMode::Tcp | TransportMode::Rdma => self.run_tcp(port, false, shutdown_rx.clone()).await,
+                TransportMode::WebSocket => self.run_tcp(port, true, shutdown_rx.clone()).await,
+                TransportMode::Auto if self.config.cert_path.is_none() || self.config.key_path.is_none() => {
+                    info!("auto transport without cert_path and key_path: serving TCP only");
+                    self.run_tcp(port, false, shutdown_rx.clone()).await
+                }
+                TransportMode::Auto => {
+                    tokio::try_join!(self.run_tcp(port, false, shutdown_rx.clone()), self.run_quic(shutdown_rx.clone()))?;
+                    Ok(())
+                }
+            }
+        };
+        match self.config.websocket_port {
+            // QUIC is on UDP, the TCP port is free
+            Some(ws_port) if ws_port == port && self.config.transport != TransportMode::Quic => {
+                if self.config.transport != TransportMode::WebSocket {
+                    warn!("websocket_port {} is the {:?} port, not serving WebSocket", ws_port, self.config.transport);
+                }
+                main.await
+            }
+            Some(ws_port) => {
+                tokio::try_join!(main, self.run_tcp(ws_port, true, shutdown_rx.clone()))?;
+                Ok(())
+            }
+            None => main.await,
         }
     }
 
-    /// Run with TCP transport (plain or TLS).
+    /// Run with TCP transport (plain or TLS), or with WebSocket over it if
+    /// `websocket`.
     async fn run_tcp(
         &self,
+        port: u16,
+        websocket: bool,
         mut shutdown_rx: watch::Receiver<bool>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let bind_addr = format!("{}:{}", self.config.bind, self.config.port);
+        let bind_addr = format!("{}:{}", self.config.bind, port);
         let listener = TcpListener::bind(&bind_addr).await?;
+        let kind = if websocket { "WebSocket" } else { "TCP" };
 
-        info!("RGPU server listening on {} (TCP)", bind_addr);
+        info!("RGPU server listening on {} ({}), build {}", bind_addr, kind, BuildInfo::current("rgpu-server"));
 
         // Build TLS acceptor if cert/key are provided
         let tls_acceptor = if let (Some(cert), Some(key)) =
@@ -212,6 +460,12 @@ impl RgpuServer {
 
         let active_sessions = Arc::new(AtomicU32::new(0));
         let max_clients = self.config.max_clients;
+        let rdma = (self.config.transport == TransportMode::Rdma && !websocket).then(|| self.config.rdma.clone());
+        if rdma.is_some() && tls_acceptor.is_some() {
+            warn!("RDMA is only offered on plain TCP connections; TLS clients keep bulk data on TCP");
+        }
+        // Refusals can be written straight to the socket
+        let raw = tls_acceptor.is_none() && !websocket;
 
         loop {
             let tcp_accept = listener.accept();
@@ -226,63 +480,76 @@ impl RgpuServer {
                     let current = active_sessions.load(Ordering::Relaxed);
                     if current >= max_clients {
                         warn!("connection from {} rejected: max_clients ({}) reached", peer_addr, max_clients);
-                        drop(tcp_stream);
+                        if raw {
+                            Self::refuse_plain(tcp_stream, "the server is full".to_string());
+                        }
                         continue;
                     }
+                    let permit = match self.limiter.admit(peer_addr.ip()) {
+                        Ok(permit) => permit,
+                        Err(refusal) => {
+                            warn!("connection from {} rejected: {}", peer_addr, refusal);
+                            if raw {
+                                Self::refuse_plain(tcp_stream, refusal.to_string());
+                            }
+                            continue;
+                        }
+                    };
 
                     let cuda_executor = self.cuda_executor.clone();
                     let vulkan_executor = self.vulkan_executor.clone();
-                    let gpu_infos = self.gpu_infos.clone();
                     let session_id = self.next_session_id.fetch_add(1, Ordering::Relaxed);
                     let server_id = self.config.server_id;
                     let accepted_tokens = self.accepted_tokens.clone();
                     let active = active_sessions.clone();
                     let metrics = self.metrics.clone();
+                    let execution = self.execution.clone();
 
                     active.fetch_add(1, Ordering::Relaxed);
                     metrics.connections_total.fetch_add(1, Ordering::Relaxed);
                     metrics.connections_active.fetch_add(1, Ordering::Relaxed);
 
-                    if let Some(ref acceptor) = tls_acceptor {
-                        let acceptor = acceptor.clone();
+                    if !raw {
+                        let acceptor = tls_acceptor.clone();
                         tokio::spawn(async move {
-                            match acceptor.accept(tcp_stream).await {
-                                Ok(tls_stream) => {
-                                    match RgpuConnection::from_server_stream(tls_stream).await {
-                                        Ok(conn) => {
-                                            Self::handle_client(
-                                                conn,
-                                                session_id,
-                                                server_id,
-                                                gpu_infos,
-                                                cuda_executor,
-                                                vulkan_executor,
-                                                accepted_tokens,
-                                                metrics.clone(),
-                                            )
-                                            .await;
-                                        }
-                                        Err(e) => error!("connection setup failed: {}", e),
-                                    }
+                            let _permit = permit;
+                            let max_message_size = execution.max_message_size;
+                            match Self::open_connection(tcp_stream, acceptor, websocket, max_message_size).await {
+                                Ok(conn) => {
+                                    let client = if websocket { "WebSocket client" } else { "TLS client" };
+                                    Self::handle_client(
+                                        conn,
+                                        client,
+                                        session_id,
+                                        server_id,
+                                        cuda_executor,
+                                        vulkan_executor,
+                                        accepted_tokens,
+                                        metrics.clone(),
+                                        execution,
+                                    )
+                                    .await;
                                 }
-                                Err(e) => error!("TLS handshake failed from {}: {}", peer_addr, e),
+                                Err(e) => error!("connection setup from {} failed: {}", peer_addr, e),
                             }
                             active.fetch_sub(1, Ordering::Relaxed);
                             metrics.connections_active.fetch_sub(1, Ordering::Relaxed);
                         });
                     } else {
                         // No TLS - for development/testing only
-                        let gpu_infos_clone = gpu_infos;
+                        let rdma = rdma.clone();
                         tokio::spawn(async move {
+                            let _permit = permit;
                             Self::handle_plain_client(
                                 tcp_stream,
                                 session_id,
                                 server_id,
-                                gpu_infos_clone,
                                 cuda_executor,
                                 vulkan_executor,
                                 accepted_tokens,
                                 metrics.clone(),
+                                execution,
+                                rdma,
                             )
                             .await;
                             active.fetch_sub(1, Ordering::Relaxed);
@@ -291,7 +558,7 @@ impl RgpuServer {
                     }
                 }
                 _ = shutdown => {
-                    info!("shutdown signal received, stopping TCP accept loop");
+                    info!("shutdown signal received, stopping {} accept loop", kind);
                     break;
                 }
             }
@@ -331,7 +598,7 @@ impl RgpuServer {
             format!("{}:{}", self.config.bind, self.config.port).parse()?;
 
         let endpoint = rgpu_transport::quic::build_quic_server(bind_addr, cert_path, key_path)?;
-        info!("RGPU server listening on {} (QUIC)", bind_addr);
+        info!("RGPU server listening on {} (QUIC), build {}", bind_addr, BuildInfo::current("rgpu-server"));
 
         let active_sessions = Arc::new(AtomicU32::new(0));
         let max_clients = self.config.max_clients;
@@ -351,21 +618,30 @@ impl RgpuServer {
                         incoming.refuse();
                         continue;
                     }
+                    let permit = match self.limiter.admit(incoming.remote_address().ip()) {
+                        Ok(permit) => permit,
+                        Err(refusal) => {
+                            warn!("QUIC connection from {} rejected: {}", incoming.remote_address(), refusal);
+                            incoming.refuse();
+                            continue;
+                        }
+                    };
 
                     let cuda_executor = self.cuda_executor.clone();
                     let vulkan_executor = self.vulkan_executor.clone();
-                    let gpu_infos = self.gpu_infos.clone();
                     let session_id = self.next_session_id.fetch_add(1, Ordering::Relaxed);
                     let server_id = self.config.server_id;
                     let accepted_tokens = self.accepted_tokens.clone();
                     let active = active_sessions.clone();
                     let metrics = self.metrics.clone();
+                    let execution = self.execution.clone();
 
                     active.fetch_add(1, Ordering::Relaxed);
                     metrics.connections_total.fetch_add(1, Ordering::Relaxed);
                     metrics.connections_active.fetch_add(1, Ordering::Relaxed);
 
                     tokio::spawn(async move {
+                        let _permit = permit;
                         match incoming.await {
                             Ok(connection) => {
                                 let remote = connection.remote_address();
@@ -375,11 +651,11 @@ impl RgpuServer {
                                     connection,
                                     session_id,
                                     server_id,
-                                    gpu_infos,
                                     cuda_executor,
                                     vulkan_executor,
                                     accepted_tokens,
                                     metrics.clone(),
+                                    execution,
                                 )
                                 .await;
 
@@ -424,75 +700,133 @@ impl RgpuServer {
     }
 
     /// Handle a plain TCP client (no TLS) - development only.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_plain_client(
         stream: tokio::net::TcpStream,
         session_id: u32,
         server_id: u16,
-        gpu_infos: Vec<GpuInfo>,
         cuda_executor: Arc<CudaExecutor>,
         vulkan_executor: Arc<VulkanExecutor>,
-        _accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
+        accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
         metrics: Arc<ServerMetrics>,
+        execution: Arc<Execution>,
+        rdma: Option<RdmaConfig>,
     ) {
-        use rgpu_protocol::wire;
         use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-        let session = Session::new(session_id, server_id, "unknown".to_string());
+        let session = Arc::new(Session::new(session_id, server_id, "unknown".to_string()));
+        metrics.register_session(&session);
+        let worker = Self::spawn_worker(&session, &execution.affinity);
         let (mut reader, mut writer) = stream.into_split();
 
         info!(session_id, "plain TCP client connected");
 
-        let mut header_buf = [0u8; rgpu_protocol::wire::HEADER_SIZE];
+        // Read frames on a separate task so a Cancel is seen while an
+        // earlier command is still executing.
+        let (msg_tx, mut msg_rx) = tokio::sync::mpsc::channel::<Message>(INBOUND_QUEUE_DEPTH);
+        let reader_session = session.clone();
+        let link: Arc<OnceLock<RdmaLink>> = Arc::default();
+        let reader_link = link.clone();
+        let max_message_size = execution.max_message_size;
+        let reader_task = tokio::spawn(async move {
+            let mut header_buf = [0u8; rgpu_protocol::wire::HEADER_SIZE];
 
-        loop {
-            // Read frame header with idle timeout
-            match tokio::time::timeout(
-                Duration::from_secs(120),
-                reader.read_exact(&mut header_buf),
-            )
-            .await
-            {
-                Ok(Ok(_)) => {}
-                Ok(Err(e)) => {
+            loop {
+                if let Err(e) = reader.read_exact(&mut header_buf).await {
                     info!(session_id, "client disconnected: {}", e);
                     break;
                 }
-                Err(_) => {
-                    warn!(session_id, "client idle timeout (120s), disconnecting");
-                    break;
+
+                let (flags, tag, payload_len) = match wire::decode_header(&header_buf) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        error!(session_id, "invalid frame: {}", e);
+                        break;
+                    }
+                };
+
+                if payload_len as usize > max_message_size {
+                    // Skipped without allocating it; only its request fails.
+                    warn!(session_id, "refusing a {}-byte message", payload_len);
+                    if let Err(e) = skip_payload(&mut reader, payload_len).await {
+                        error!(session_id, "payload read error: {}", e);
+                        break;
+                    }
+                    let msg = wire::undecodable(tag, &wire::WireError::FrameTooLarge(payload_len));
+                    if msg_tx.send(msg).await.is_err() {
+                        break;
+                    }
+                    continue;
                 }
-            }
 
-            let (flags, _stream_id, payload_len) = match wire::decode_header(&header_buf) {
-                Ok(v) => v,
-                Err(e) => {
-                    error!(session_id, "invalid frame: {}", e);
+                // Read payload
+                let mut payload = vec![0u8; payload_len as usize];
+                if let Err(e) = reader.read_exact(&mut payload).await {
+                    error!(session_id, "payload read error: {}", e);
                     break;
                 }
-            };
+                let (flags, payload) = match rdma::unshare(reader_link.get(), flags, payload) {
+                    Ok(frame) => frame,
+                    Err(e) => {
+                        error!(session_id, "{}", e);
+                        break;
+                    }
+                };
 
-            // Read payload
-            let mut payload = vec![0u8; payload_len as usize];
-            if let Err(e) = reader.read_exact(&mut payload).await {
-                error!(session_id, "payload read error: {}", e);
-                break;
+                let msg = match wire::decode_message_limited(&payload, flags, max_message_size) {
+                    Ok(m) => m,
+                    Err(e) => {
+                        error!(session_id, "decode error: {}", e);
+                        wire::undecodable(tag, &e)
+                    }
+                };
+
+                if let Some(msg) = Self::admit_message(&reader_session, msg, max_message_size) {
+                    if msg_tx.send(msg).await.is_err() {
+                        break;
+                    }
+                }
             }
+        });
 
-            // Decode and handle message
-            let msg = match wire::decode_message(&payload, flags) {
-                Ok(m) => m,
-                Err(e) => {
-                    error!(session_id, "decode error: {}", e);
-                    continue;
+        let mut gpus = execution.gpus.subscribe();
+        loop {
+            let response = tokio::select! {
+                msg = tokio::time::timeout(Duration::from_secs(120), msg_rx.recv()) => {
+                    let msg = match msg {
+                        Ok(Some(msg)) => msg,
+                        Ok(None) => break,
+                        Err(_) => {
+                            warn!(session_id, "client idle timeout (120s), disconnecting");
+                            break;
+                        }
+                    };
+                    match msg {
+                        Message::RdmaConnect(remote) => Some(Self::accept_rdma(session_id, rdma.as_ref(), &link, &remote)),
+                        msg => {
+                            Self::dispatch_message(
+                                &session, msg, &execution.gpus.gpus(), &cuda_executor, &vulkan_executor,
+                                &accepted_tokens, &metrics, &execution, worker.as_ref(),
+                            )
+                            .await
+                        }
+                    }
+                }
+                // Pushed between responses, never in the middle of one
+                Ok(()) = gpus.changed() => {
+                    let listed = gpus.borrow_and_update().clone();
+                    Self::topology_update(&session, &listed)
                 }
             };
 
-            let response = Self::handle_message(&session, msg, &gpu_infos, &cuda_executor, &vulkan_executor, &metrics);
-
             // Send response
             if let Some(resp) = response {
-                match wire::encode_message(&resp, 0) {
+                match Self::encode_response(&session, &resp) {
                     Ok(frame) => {
+                        let frame = match link.get() {
+                            Some(link) => link.offload(frame).await,
+                            None => frame,
+                        };
                         if let Err(e) = writer.write_all(&frame).await {
                             error!(session_id, "write error: {}", e);
                             break;
@@ -504,134 +838,288 @@ impl RgpuServer {
                 }
             }
         }
+        reader_task.abort();
 
-        // Clean up leaked resources
-        let leaked = session.all_handles().len();
-        if leaked > 0 {
-            warn!(session_id, "{} handle(s) leaked at disconnect, cleaning up", leaked);
-        }
-        cuda_executor.cleanup_session(&session);
-        vulkan_executor.cleanup_session(&session);
+        execution.close_session(session, &cuda_executor, &vulkan_executor, &metrics);
         info!(session_id, "client session ended");
     }
 
-    /// Handle a TLS client connection.
+    /// Open this side of the RDMA link a plain TCP client asked for.
+    fn accept_rdma(
+        session_id: u32,
+        config: Option<&RdmaConfig>,
+        link: &OnceLock<RdmaLink>,
+        remote: &RdmaEndpoint,
+    ) -> Message {
+        let Some(config) = config else {
+            return Message::Error(ProtocolError::NotImplemented("this server doesn't offer RDMA".to_string()));
+        };
+        if link.get().is_some() {
+            return Message::Error(ProtocolError::ConnectionFailed("RDMA is already set up".to_string()));
+        }
+        let opened = RdmaLink::open(config.device.as_deref(), config.port, config.gid_index).and_then(|mut opened| {
+            opened.connect(remote)?;
+            Ok(opened)
+        });
+        match opened {
+            Ok(opened) => {
+                info!(session_id, "bulk data over RDMA on {}", opened.device());
+                let local = opened.local();
+                let _ = link.set(opened);
+                Message::RdmaConnect(local)
+            }
+            Err(e) => {
+                warn!(session_id, "can't set up RDMA, keeping bulk data on TCP: {}", e);
+                Message::Error(ProtocolError::ConnectionFailed(e.to_string()))
+            }
+        }
+    }
+
+    /// TLS handshake and WebSocket upgrade of a new connection, as far as
+    /// each applies.
+    async fn open_connection(
+        tcp_stream: tokio::net::TcpStream,
+        acceptor: Option<tokio_rustls::TlsAcceptor>,
+        websocket: bool,
+        max_message_size: usize,
+    ) -> Result<RgpuConnection, TransportError> {
+        match (acceptor, websocket) {
+            (Some(acceptor), false) => {
+                let tls_stream = acceptor.accept(tcp_stream).await?;
+                RgpuConnection::from_server_stream(tls_stream, max_message_size).await
+            }
+            (Some(acceptor), true) => {
+                let tls_stream = acceptor.accept(tcp_stream).await?;
+                RgpuConnection::from_server_websocket(websocket::accept(tls_stream).await?, max_message_size).await
+            }
+            (None, _) => {
+                RgpuConnection::from_server_websocket(websocket::accept(tcp_stream).await?, max_message_size).await
+            }
+        }
+    }
+
+    /// Handle a TLS or WebSocket client connection.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_client(
         conn: RgpuConnection,
+        client: &'static str,
         session_id: u32,
         server_id: u16,
-        gpu_infos: Vec<GpuInfo>,
         cuda_executor: Arc<CudaExecutor>,
         vulkan_executor: Arc<VulkanExecutor>,
-        _accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
+        accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
         metrics: Arc<ServerMetrics>,
+        execution: Arc<Execution>,
     ) {
-        let session = Session::new(session_id, server_id, "tls-client".to_string());
-        info!(session_id, "TLS client connected");
-
-        loop {
-            match tokio::time::timeout(Duration::from_secs(120), conn.recv()).await {
-                Ok(Ok(msg)) => {
-                    let response =
-                        Self::handle_message(&session, msg, &gpu_infos, &cuda_executor, &vulkan_executor, &metrics);
-                    if let Some(resp) = response {
-                        if let Err(e) = conn.send(resp).await {
-                            error!(session_id, "send error: {}", e);
-                            break;
+        let session = Arc::new(Session::new(session_id, server_id, client.to_lowercase().replace(' ', "-")));
+        metrics.register_session(&session);
+        let worker = Self::spawn_worker(&session, &execution.affinity);
+        let conn = Arc::new(conn);
+        info!(session_id, "{} connected", client);
+
+        // Receive on a separate task so a Cancel is seen while an earlier
+        // command is still executing.
+        let (msg_tx, mut msg_rx) = tokio::sync::mpsc::channel::<Message>(INBOUND_QUEUE_DEPTH);
+        let reader_session = session.clone();
+        let reader_conn = conn.clone();
+        let max_message_size = execution.max_message_size;
+        let reader_task = tokio::spawn(async move {
+            loop {
+                match reader_conn.recv().await {
+                    Ok(msg) => {
+                        if let Some(msg) = Self::admit_message(&reader_session, msg, max_message_size) {
+                            if msg_tx.send(msg).await.is_err() {
+                                break;
+                            }
                         }
                     }
+                    Err(e) => {
+                        info!(session_id, "client disconnected: {}", e);
+                        break;
+                    }
                 }
-                Ok(Err(e)) => {
-                    info!(session_id, "client disconnected: {}", e);
-                    break;
+            }
+        });
+
+        let mut gpus = execution.gpus.subscribe();
+        loop {
+            let response = tokio::select! {
+                msg = tokio::time::timeout(Duration::from_secs(120), msg_rx.recv()) => match msg {
+                    Ok(Some(msg)) => {
+                        Self::dispatch_message(
+                            &session, msg, &execution.gpus.gpus(), &cuda_executor, &vulkan_executor,
+                            &accepted_tokens, &metrics, &execution, worker.as_ref(),
+                        )
+                        .await
+                    }
+                    Ok(None) => break,
+                    Err(_) => {
+                        warn!(session_id, "client idle timeout (120s), disconnecting");
+                        break;
+                    }
+                },
+                // Pushed between responses, never in the middle of one
+                Ok(()) = gpus.changed() => {
+                    let listed = gpus.borrow_and_update().clone();
+                    Self::topology_update(&session, &listed)
                 }
-                Err(_) => {
-                    warn!(session_id, "client idle timeout (120s), disconnecting");
+            };
+            if let Some(resp) = response {
+                if let Err(e) = conn.send(resp).await {
+                    error!(session_id, "send error: {}", e);
                     break;
                 }
             }
         }
+        reader_task.abort();
 
-        let leaked = session.all_handles().len();
-        if leaked > 0 {
-            warn!(session_id, "{} handle(s) leaked at disconnect, cleaning up", leaked);
-        }
-        cuda_executor.cleanup_session(&session);
-        vulkan_executor.cleanup_session(&session);
+        execution.close_session(session, &cuda_executor, &vulkan_executor, &metrics);
         info!(session_id, "client session ended");
     }
 
+    /// The GPU list to push to a session whose server's GPUs changed, if its
+    /// client takes such pushes.
+    fn topology_update(session: &Session, gpus: &[GpuInfo]) -> Option<Message> {
+        session
+            .wants_topology_updates()
+            .then(|| Message::ServerTopologyChanged { gpus: Self::visible_gpus(session, gpus) })
+    }
+
+    /// Push a message to a QUIC client on a stream of its own.
+    async fn push_quic(connection: &quinn::Connection, session: &Session, msg: &Message) -> Result<(), String> {
+        let frame = Self::encode_response(session, msg).map_err(|e| e.to_string())?;
+        let mut send = connection.open_uni().await.map_err(|e| e.to_string())?;
+        send.write_all(&frame).await.map_err(|e| e.to_string())?;
+        send.finish().map_err(|e| e.to_string())
+    }
+
+    /// Encode a response to `session`, tracking compression and turning it
+    /// off for the session if it doesn't pay off.
+    fn encode_response(session: &Session, resp: &Message) -> Result<Vec<u8>, wire::WireError> {
+        let frame = wire::encode_message_tracked(resp, 0, &session.compression)?;
+        if session.compression.auto_tune() {
+            let summary = session.compression.summary();
+            info!(
+                session_id = session.session_id,
+                "compression ratio {:.2} below {}, sending uncompressed",
+                summary.ratio(),
+                wire::AUTO_DISABLE_RATIO
+            );
+        }
+        Ok(frame)
+    }
+
     /// Handle a QUIC client connection.
-    /// Each bidirectional stream carries one request-response pair.
+    /// Each bidirectional stream carries requests answered in turn: one for a
+    /// plain request, or the requests of one CUDA stream on a lane. Streams
+    /// are served side by side.
+    #[allow(clippy::too_many_arguments)]
     async fn handle_quic_client(
         connection: quinn::Connection,
         session_id: u32,
         server_id: u16,
-        gpu_infos: Vec<GpuInfo>,
         cuda_executor: Arc<CudaExecutor>,
         vulkan_executor: Arc<VulkanExecutor>,
-        _accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
+        accepted_tokens: Vec<rgpu_core::config::TokenEntry>,
         metrics: Arc<ServerMetrics>,
+        execution: Arc<Execution>,
     ) {
-        use rgpu_protocol::wire;
 
         let session = Arc::new(Session::new(session_id, server_id, "quic-client".to_string()));
+        metrics.register_session(&session);
+        let worker = Self::spawn_worker(&session, &execution.affinity).map(Arc::new);
+        let accepted_tokens = Arc::new(accepted_tokens);
+        let mut gpus = execution.gpus.subscribe();
 
         loop {
-            match connection.accept_bi().await {
+            let accepted = tokio::select! {
+                accepted = connection.accept_bi() => accepted,
+                Ok(()) = gpus.changed() => {
+                    let listed = gpus.borrow_and_update().clone();
+                    if let Some(update) = Self::topology_update(&session, &listed) {
+                        if let Err(e) = Self::push_quic(&connection, &session, &update).await {
+                            debug!(session_id, "QUIC push error: {}", e);
+                        }
+                    }
+                    continue;
+                }
+            };
+            match accepted {
                 Ok((mut send, mut recv)) => {
                     let cuda_exec = cuda_executor.clone();
                     let vulkan_exec = vulkan_executor.clone();
-                    let gpu_infos = gpu_infos.clone();
                     let session = session.clone();
+                    let accepted_tokens = accepted_tokens.clone();
                     let metrics = metrics.clone();
+                    let execution = execution.clone();
+                    let worker = worker.clone();
 
                     tokio::spawn(async move {
-                        // Read request
-                        let mut header_buf = [0u8; wire::HEADER_SIZE];
-                        if let Err(e) = recv.read_exact(&mut header_buf).await {
-                            debug!("QUIC stream read header error: {}", e);
-                            return;
-                        }
-
-                        let (flags, _, payload_len) = match wire::decode_header(&header_buf) {
-                            Ok(v) => v,
-                            Err(e) => {
-                                error!(session_id, "QUIC invalid frame: {}", e);
-                                return;
-                            }
-                        };
+                        loop {
+                            // Read request
+                            let header_buf = match rgpu_transport::quic::read_next_header(&mut recv).await {
+                                Ok(Some(header_buf)) => header_buf,
+                                Ok(None) => break,
+                                Err(e) => {
+                                    debug!(session_id, "QUIC stream error: {}", e);
+                                    return;
+                                }
+                            };
 
-                        let mut payload = vec![0u8; payload_len as usize];
-                        if let Err(e) = recv.read_exact(&mut payload).await {
-                            error!(session_id, "QUIC payload read error: {}", e);
-                            return;
-                        }
+                            let (flags, tag, payload_len) = match wire::decode_header(&header_buf) {
+                                Ok(v) => v,
+                                Err(e) => {
+                                    error!(session_id, "QUIC invalid frame: {}", e);
+                                    return;
+                                }
+                            };
+
+                            let max_message_size = execution.max_message_size;
+                            let msg = if payload_len as usize > max_message_size {
+                                warn!(session_id, "refusing a {}-byte message", payload_len);
+                                if let Err(e) = skip_payload(&mut recv, payload_len).await {
+                                    error!(session_id, "QUIC payload read error: {}", e);
+                                    return;
+                                }
+                                Err(wire::WireError::FrameTooLarge(payload_len))
+                            } else {
+                                let mut payload = vec![0u8; payload_len as usize];
+                                if let Err(e) = recv.read_exact(&mut payload).await {
+                                    error!(session_id, "QUIC payload read error: {}", e);
+                                    return;
+                                }
+                                wire::decode_message_limited(&payload, flags, max_message_size)
+                            };
 
-                        let msg = match wire::decode_message(&payload, flags) {
-                            Ok(m) => m,
-                            Err(e) => {
-                                error!(session_id, "QUIC decode error: {}", e);
-                                return;
-                            }
-                        };
+                            let msg = match msg {
+                                Ok(m) => m,
+                                Err(e) => {
+                                    error!(session_id, "QUIC decode error: {}", e);
+                                    wire::undecodable(tag, &e)
+                                }
+                            };
 
-                        // Handle and respond
-                        if let Some(resp) = Self::handle_message(
-                            &session, msg, &gpu_infos, &cuda_exec, &vulkan_exec, &metrics,
-                        ) {
-                            match wire::encode_message(&resp, 0) {
-                                Ok(frame) => {
-                                    if let Err(e) = send.write_all(&frame).await {
-                                        debug!("QUIC write error: {}", e);
+                            // Handle and respond
+                            let response = Self::dispatch_message(
+                                &session, msg, &execution.gpus.gpus(), &cuda_exec, &vulkan_exec, &accepted_tokens,
+                                &metrics, &execution, worker.as_deref(),
+                            )
+                            .await;
+                            if let Some(resp) = response {
+                                match Self::encode_response(&session, &resp) {
+                                    Ok(frame) => {
+                                        if let Err(e) = send.write_all(&frame).await {
+                                            debug!("QUIC write error: {}", e);
+                                            return;
+                                        }
+                                    }
+                                    Err(e) => {
+                                        error!(session_id, "QUIC encode error: {}", e);
                                     }
-                                    let _ = send.finish();
-                                }
-                                Err(e) => {
-                                    error!(session_id, "QUIC encode error: {}", e);
                                 }
                             }
                         }
+                        let _ = send.finish();
                     });
                 }
                 Err(quinn::ConnectionError::ApplicationClosed(_)) => break,
@@ -642,16 +1130,219 @@ impl RgpuServer {
             }
         }
 
-        // Clean up leaked resources
-        let leaked = session.all_handles().len();
-        if leaked > 0 {
-            warn!(session_id, "{} handle(s) leaked at disconnect, cleaning up", leaked);
-        }
-        cuda_executor.cleanup_session(&session);
-        vulkan_executor.cleanup_session(&session);
+        execution.close_session(session, &cuda_executor, &vulkan_executor, &metrics);
         info!(session_id, "QUIC client session ended");
     }
 
+    /// Called by a connection's reader as each message arrives, before it is
+    /// queued for execution. A `Cancel` is applied immediately (it must not
+    /// wait behind the command it targets) and consumed; commands are
+    /// registered as in-flight so they can be cancelled while still queued.
+    /// Upload chunks are filed as they arrive and queued as their
+    /// acknowledgements, and a command on an upload gets its data here.
+    fn admit_message(session: &Session, msg: Message, max_upload: usize) -> Option<Message> {
+        match msg {
+            Message::Cancel { request_id } => {
+                if session.cancel_request(request_id) {
+                    debug!(session_id = session.session_id, "cancel requested for {:?}", request_id);
+                }
+                None
+            }
+            Message::CudaCommand { request_id, deadline_ms, .. }
+            | Message::VulkanCommand { request_id, deadline_ms, .. } => {
+                session.begin_request(request_id, deadline_ms);
+                Some(msg)
+            }
+            Message::CudaPipelined { request_id, .. } => {
+                session.begin_request(request_id, None);
+                Some(msg)
+            }
+            Message::TransferChunk { transfer, offset, total, data } if session.is_authenticated() => {
+                let received = session.uploads.receive(transfer, offset, total, data, max_upload);
+                Some(Message::TransferAck { transfer, received })
+            }
+            Message::CudaCommandChunked {
+                request_id,
+                mut command,
+                deadline_ms,
+                transfer,
+            } => match (command.upload_data_mut(), session.uploads.take(transfer)) {
+                (Some(src_data), Some(data)) => {
+                    *src_data = data;
+                    session.begin_request(request_id, deadline_ms);
+                    Some(Message::CudaCommand { request_id, command, deadline_ms })
+                }
+                _ => Some(Message::CudaCommandChunked { request_id, command, deadline_ms, transfer }),
+            },
+            other => Some(other),
+        }
+    }
+
+    /// The session's GPU worker thread, if CPU affinity is configured.
+    fn spawn_worker(session: &Arc<Session>, affinity: &Arc<CpuAffinity>) -> Option<SessionWorker> {
+        if !affinity.is_enabled() {
+            return None;
+        }
+        match SessionWorker::spawn(session.clone(), affinity.clone()) {
+            Ok(worker) => Some(worker),
+            Err(e) => {
+                warn!(session_id = session.session_id, "can't start worker thread, running inline: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Process a message, running GPU commands on the session's worker
+    /// thread when it has one.
+    #[allow(clippy::too_many_arguments)]
+    async fn dispatch_message(
+        session: &Arc<Session>,
+        msg: Message,
+        gpu_infos: &[GpuInfo],
+        cuda_executor: &Arc<CudaExecutor>,
+        vulkan_executor: &Arc<VulkanExecutor>,
+        accepted_tokens: &[rgpu_core::config::TokenEntry],
+        metrics: &Arc<ServerMetrics>,
+        execution: &Execution,
+        worker: Option<&SessionWorker>,
+    ) -> Option<Message> {
+        let audit = execution.audit.as_ref().map(|log| (log, log.describe(&msg)));
+
+        if let Message::ResumeSession { session_id, resume_token } = msg {
+            let response = Self::resume_session(session, session_id, resume_token, cuda_executor, execution, metrics);
+            if let Some((log, entries)) = audit {
+                log.record(session, entries, &audit::outcome(Some(&response)));
+            }
+            return Some(response);
+        }
+
+        if let Message::Hello { protocol_version, .. } = &msg {
+            session.compression.configure(execution.compression);
+            session.set_protocol_version(*protocol_version);
+        }
+
+        if let Err(reason) = session.permits(&msg) {
+            warn!(session_id = session.session_id, "refused: {}", reason);
+            if let Some((log, entries)) = audit {
+                log.record(session, entries, &format!("refused: {}", reason));
+            }
+            return Some(Self::denied_response(&msg, reason));
+        }
+
+        let is_gpu_command = matches!(
+            msg,
+            Message::CudaCommand { .. }
+                | Message::CudaBatch(_)
+                | Message::CudaPipelined { .. }
+                | Message::VulkanCommand { .. }
+        );
+
+        #[cfg(feature = "chaos")]
+        let mut drop_response = false;
+        #[cfg(feature = "chaos")]
+        if let Some(chaos) = execution.chaos.as_ref().filter(|_| is_gpu_command) {
+            use crate::chaos::{Chaos, Fault};
+            match chaos.roll(session.session_id) {
+                Some(Fault::Drop) => drop_response = true,
+                Some(Fault::Delay(delay)) => tokio::time::sleep(delay).await,
+                Some(Fault::Error(code)) => {
+                    debug!(session_id = session.session_id, "injecting error {}", code);
+                    if let Message::CudaCommand { request_id, .. }
+                    | Message::VulkanCommand { request_id, .. }
+                    | Message::CudaPipelined { request_id, .. } = &msg
+                    {
+                        session.end_request(*request_id);
+                    }
+                    return Chaos::error_response(&msg, code);
+                }
+                None => {}
+            }
+        }
+
+        // On a time-sliced GPU, wait for the session's turn.
+        let _turn = match is_gpu_command {
+            true => execution.scheduler.turn(session).await,
+            false => None,
+        };
+
+        let mut response = match worker.filter(|_| is_gpu_command) {
+            None => Self::handle_message(
+                session, msg, gpu_infos, cuda_executor, vulkan_executor, accepted_tokens, metrics,
+            ),
+            Some(worker) => {
+                let (session, cuda_executor, vulkan_executor, metrics) =
+                    (session.clone(), cuda_executor.clone(), vulkan_executor.clone(), metrics.clone());
+                worker
+                    .run(move || {
+                        // GPU commands don't look at the GPU list or tokens.
+                        Self::handle_message(&session, msg, &[], &cuda_executor, &vulkan_executor, &[], &metrics)
+                    })
+                    .await
+                    .flatten()
+            }
+        };
+
+        if let Some(Message::AuthResult { available_gpus: gpus, .. } | Message::GpuList(gpus)) = &mut response {
+            execution.scheduler.annotate(gpus);
+        }
+        if let Some((log, entries)) = audit {
+            log.record(session, entries, &audit::outcome(response.as_ref()));
+        }
+
+        #[cfg(feature = "chaos")]
+        if drop_response {
+            debug!(session_id = session.session_id, "dropping response");
+            return None;
+        }
+        response
+    }
+
+    /// Hand the parked session `previous` over to `session` if the token
+    /// matches, or else carry the device bindings of the ended session over.
+    fn resume_session(
+        session: &Session,
+        previous: u32,
+        resume_token: Option<u64>,
+        cuda_executor: &CudaExecutor,
+        execution: &Execution,
+        metrics: &ServerMetrics,
+    ) -> Message {
+        if let Some(parked) = resume_token.and_then(|token| execution.parked.claim(previous, token)) {
+            let restored = session.adopt(&parked);
+            metrics.vram.transfer_session(previous, session.session_id);
+            execution.scheduler.transfer_session(previous, session.session_id);
+            execution.virtual_gpus.transfer_session(previous, session.session_id);
+            info!(
+                session_id = session.session_id,
+                "resumed session {} with {} object(s)", previous, restored
+            );
+            return Message::SessionResumed {
+                resumed: true,
+                restored: restored as u32,
+                changed: Vec::new(),
+            };
+        }
+
+        let Some(bindings) = execution.session_devices.take(previous) else {
+            debug!(session_id = session.session_id, "session {} can't be resumed", previous);
+            return Message::SessionResumed {
+                resumed: false,
+                restored: 0,
+                changed: Vec::new(),
+            };
+        };
+        let changed = cuda_executor.resume_device_bindings(session, bindings);
+        info!(
+            session_id = session.session_id,
+            "resumed session {}, {} device(s) changed", previous, changed.len()
+        );
+        Message::SessionResumed {
+            resumed: true,
+            restored: 0,
+            changed,
+        }
+    }
+
     /// Process a single message and return the response.
     fn handle_message(
         session: &Session,
@@ -659,12 +1350,14 @@ impl RgpuServer {
         gpu_infos: &[GpuInfo],
         cuda_executor: &CudaExecutor,
         vulkan_executor: &VulkanExecutor,
+        accepted_tokens: &[rgpu_core::config::TokenEntry],
         metrics: &ServerMetrics,
     ) -> Option<Message> {
         metrics.requests_total.fetch_add(1, Ordering::Relaxed);
+        session.record_request();
 
         match &msg {
-            Message::CudaCommand { .. } | Message::CudaBatch(_) => {
+            Message::CudaCommand { .. } | Message::CudaBatch(_) | Message::CudaPipelined { .. } => {
                 metrics.cuda_commands.fetch_add(1, Ordering::Relaxed);
             }
             Message::VulkanCommand { .. } => {
@@ -683,6 +1376,10 @@ impl RgpuServer {
                     session_id = session.session_id,
                     "Hello from '{}' (protocol v{})", name, protocol_version
                 );
+                if let Err(reason) = compat::negotiate(protocol_version) {
+                    warn!(session_id = session.session_id, "refused: {}", reason);
+                    return Some(Message::Error(ProtocolError::VersionMismatch(reason)));
+                }
                 let challenge = auth::generate_challenge(32);
                 Some(Message::Hello {
                     protocol_version: PROTOCOL_VERSION,
@@ -691,22 +1388,46 @@ impl RgpuServer {
                 })
             }
 
-            Message::Authenticate { .. } => {
+            Message::Authenticate { token, .. } => {
                 // For now, accept any auth in Phase 1
+                if let Some(entry) = accepted_tokens.iter().find(|t| t.token == token) {
+                    if !metrics.scope_session(session, TokenScope::from_token(entry), entry.max_sessions) {
+                        warn!(
+                            session_id = session.session_id,
+                            "token '{}' is at its limit of {} session(s)", entry.name, entry.max_sessions.unwrap_or(0)
+                        );
+                        session.set_scope(TokenScope::refused());
+                        return Some(Message::AuthResult {
+                            success: false,
+                            session_id: None,
+                            server_id: Some(session.server_id()),
+                            available_gpus: Vec::new(),
+                            error_message: Some(format!("token '{}' has too many sessions", entry.name)),
+                            resume_token: None,
+                        });
+                    }
+                    session.set_retry_alloc_after_trim(entry.retry_alloc_after_trim);
+                    session.set_token_tags(&entry.name, &entry.labels);
+                    if let Some(max_memory) = entry.max_memory {
+                        metrics.vram.set_session_quota(session.session_id, max_memory);
+                    }
+                }
                 info!(
                     session_id = session.session_id,
                     "client authenticated"
                 );
+                session.set_authenticated();
                 Some(Message::AuthResult {
                     success: true,
                     session_id: Some(session.session_id),
                     server_id: Some(session.server_id()),
-                    available_gpus: gpu_infos.to_vec(),
+                    available_gpus: Self::visible_gpus(session, gpu_infos),
                     error_message: None,
+                    resume_token: Some(session.resume_token),
                 })
             }
 
-            Message::QueryGpus => Some(Message::GpuList(gpu_infos.to_vec())),
+            Message::QueryGpus => Some(Message::GpuList(Self::visible_gpus(session, gpu_infos))),
 
             Message::QueryMetrics => Some(Message::MetricsData {
                 connections_total: metrics.connections_total.load(Ordering::Relaxed),
@@ -718,59 +1439,242 @@ impl RgpuServer {
                 uptime_secs: metrics.start_time.elapsed().as_secs(),
                 server_id: session.server_id(),
                 server_address: metrics.bind_address.read().clone(),
+                sessions: metrics.session_summaries(),
             }),
 
+            Message::SetSessionInfo { name, labels } => {
+                session.set_client_tags(name, labels);
+                Some(Message::Pong)
+            }
+
             Message::CudaCommand {
                 request_id,
                 command,
+                deadline_ms,
             } => {
-                let response = cuda_executor.execute(session, command);
-                Some(Message::CudaResponse {
-                    request_id,
-                    response,
-                })
+                let request = session.begin_request(request_id, deadline_ms);
+                if let Some(skipped) = Self::skip_if_abandoned(session, request_id, &request) {
+                    return Some(skipped);
+                }
+                let response = cuda_executor.execute_cancellable(session, command, &request.cancelled);
+                session.end_request(request_id);
+                Some(Self::finished_response(session, request_id, &request, response))
             }
 
             Message::VulkanCommand {
                 request_id,
                 command,
+                deadline_ms,
             } => {
+                let request = session.begin_request(request_id, deadline_ms);
+                if let Some(skipped) = Self::skip_if_abandoned(session, request_id, &request) {
+                    return Some(skipped);
+                }
                 let response = vulkan_executor.execute(session, command);
+                session.end_request(request_id);
                 Some(Message::VulkanResponse {
                     request_id,
                     response,
                 })
             }
 
+            Message::Cancel { request_id } => {
+                session.cancel_request(request_id);
+                None
+            }
+
             Message::CudaBatch(commands) => {
-                let mut last_error = None;
-                for cmd in commands {
-                    let response = cuda_executor.execute(session, cmd);
-                    if let rgpu_protocol::cuda_commands::CudaResponse::Error { .. } = &response {
-                        last_error = Some(Message::CudaResponse {
-                            request_id: rgpu_protocol::messages::RequestId(0),
-                            response,
-                        });
-                    }
-                }
                 // Return last error if any, otherwise Success
-                Some(last_error.unwrap_or_else(|| Message::CudaResponse {
+                Some(Message::CudaResponse {
                     request_id: rgpu_protocol::messages::RequestId(0),
-                    response: rgpu_protocol::cuda_commands::CudaResponse::Success,
-                }))
+                    response: Self::run_cuda_batch(session, cuda_executor, commands)
+                        .unwrap_or(rgpu_protocol::cuda_commands::CudaResponse::Success),
+                })
+            }
+
+            Message::CudaPipelined {
+                request_id,
+                batch,
+                command,
+            } => {
+                let request = session.begin_request(request_id, None);
+                if let Some(skipped) = Self::skip_if_abandoned(session, request_id, &request) {
+                    return Some(skipped);
+                }
+                if let Some(error) = Self::run_cuda_batch(session, cuda_executor, batch) {
+                    session.end_request(request_id);
+                    return Some(Message::CudaResponse {
+                        request_id,
+                        response: error,
+                    });
+                }
+                let response = cuda_executor.execute_cancellable(session, command, &request.cancelled);
+                session.end_request(request_id);
+                Some(Self::finished_response(session, request_id, &request, response))
+            }
+
+            Message::QueryBuildInfo(client) => {
+                let ours = BuildInfo::current("rgpu-server");
+                match ours.check(&client, true) {
+                    Compatibility::Same => info!(session_id = session.session_id, "client build: {}", client),
+                    Compatibility::Differs(reason) | Compatibility::Incompatible(reason) => warn!(
+                        session_id = session.session_id,
+                        "version difference: {}", reason
+                    ),
+                }
+                Some(Message::BuildInfo(vec![ours]))
             }
 
             Message::Ping => Some(Message::Pong),
 
-            _ => {
+            Message::Echo(payload) => Some(Message::Echo(payload)),
+
+            // admit_message filed the chunk; anything else that reaches here
+            // wasn't taken (unauthenticated, or on a connection that doesn't
+            // reassemble uploads).
+            ack @ Message::TransferAck { .. } => Some(ack),
+            Message::TransferChunk { transfer, .. } => Some(Message::TransferAck { transfer, received: 0 }),
+            Message::CudaCommandChunked { request_id, transfer, .. } => Some(Message::CudaResponse {
+                request_id,
+                response: rgpu_protocol::cuda_commands::CudaResponse::Error {
+                    code: 1, // CUDA_ERROR_INVALID_VALUE
+                    message: format!("upload {} is incomplete or was refused", transfer),
+                },
+            }),
+
+            // A frame the reader couldn't decode; answered in turn like any
+            // other request so the session carries on.
+            Message::Unsupported { request_id, reason } => {
+                warn!(session_id = session.session_id, "request {:?}: {}", request_id, reason);
+                Some(Self::unsupported_response(request_id, reason))
+            }
+
+            other => {
                 warn!(
                     session_id = session.session_id,
                     "unhandled message type"
                 );
-                None
+                let request_id = other.request_id().unwrap_or(rgpu_protocol::messages::RequestId(0));
+                Some(Self::unsupported_response(request_id, "unhandled message type".to_string()))
             }
         }
     }
+
+    /// Response to a request this server can't decode or handle. Without a
+    /// request id (a peer older than tagged frames) it can only be a plain
+    /// error.
+    fn unsupported_response(request_id: rgpu_protocol::messages::RequestId, reason: String) -> Message {
+        if request_id.0 == 0 {
+            Message::Error(rgpu_protocol::error::ProtocolError::UnsupportedCommand(reason))
+        } else {
+            Message::Unsupported { request_id, reason }
+        }
+    }
+
+    /// Run batched void commands in order. Returns the last error, if any.
+    fn run_cuda_batch(
+        session: &Session,
+        cuda_executor: &CudaExecutor,
+        commands: Vec<rgpu_protocol::cuda_commands::CudaCommand>,
+    ) -> Option<rgpu_protocol::cuda_commands::CudaResponse> {
+        let mut last_error = None;
+        for cmd in commands {
+            let response = cuda_executor.execute(session, cmd);
+            if let rgpu_protocol::cuda_commands::CudaResponse::Error { .. } = &response {
+                last_error = Some(response);
+            }
+        }
+        last_error
+    }
+
+    /// If the client has already cancelled or given up on a request, stop
+    /// tracking it and return the response to send instead of executing it.
+    fn skip_if_abandoned(
+        session: &Session,
+        request_id: rgpu_protocol::messages::RequestId,
+        request: &InFlightRequest,
+    ) -> Option<Message> {
+        if request.is_cancelled() {
+            session.end_request(request_id);
+            return Some(Self::cancelled_response(session, request_id));
+        }
+        if request.is_expired() {
+            session.end_request(request_id);
+            debug!(
+                session_id = session.session_id,
+                "request {:?} expired while queued, skipping", request_id
+            );
+            return Some(Message::Error(rgpu_protocol::error::ProtocolError::Timeout));
+        }
+        None
+    }
+
+    /// Tell a plain TCP client why it's being turned away. Best effort: the
+    /// socket is closed right after, without waiting for a slow reader.
+    fn refuse_plain(stream: tokio::net::TcpStream, reason: String) {
+        let refusal = Message::Error(ProtocolError::ConnectionFailed(reason));
+        // Written straight to the non-blocking socket: tokio's `try_write`
+        // would wait for a readiness event a fresh socket hasn't had yet.
+        if let (Ok(frame), Ok(mut stream)) = (wire::encode_message(&refusal, 0), stream.into_std()) {
+            let _ = std::io::Write::write(&mut stream, &frame);
+        }
+    }
+
+    /// The GPUs the session's token lets it see.
+    fn visible_gpus(session: &Session, gpu_infos: &[GpuInfo]) -> Vec<GpuInfo> {
+        gpu_infos
+            .iter()
+            .filter(|gpu| session.allows_gpu(gpu.server_device_index))
+            .cloned()
+            .collect()
+    }
+
+    /// The answer to a request the session's token doesn't allow, as an
+    /// error of the request's kind.
+    fn denied_response(request: &Message, reason: String) -> Message {
+        // CUDA_ERROR_NOT_PERMITTED
+        let cuda = |request_id| Message::CudaResponse {
+            request_id,
+            response: rgpu_protocol::cuda_commands::CudaResponse::Error { code: 800, message: reason.clone() },
+        };
+        match request {
+            Message::CudaCommand { request_id, .. }
+            | Message::CudaCommandStreamed { request_id, .. }
+            | Message::CudaPipelined { request_id, .. } => cuda(*request_id),
+            Message::CudaBatch(_) => cuda(rgpu_protocol::messages::RequestId(0)),
+            Message::VulkanCommand { request_id, .. } => Message::VulkanResponse {
+                request_id: *request_id,
+                // VK_ERROR_INITIALIZATION_FAILED
+                response: rgpu_protocol::vulkan_commands::VulkanResponse::Error { code: -3, message: reason },
+            },
+            _ => Message::Error(ProtocolError::AuthenticationFailed(reason)),
+        }
+    }
+
+    /// The answer to a CUDA command that has run: its own response, unless
+    /// it failed because the request was cancelled while it ran. A command
+    /// that got to the end keeps its response, or the client would never
+    /// learn of, and free, whatever it created.
+    fn finished_response(
+        session: &Session,
+        request_id: rgpu_protocol::messages::RequestId,
+        request: &InFlightRequest,
+        response: rgpu_protocol::cuda_commands::CudaResponse,
+    ) -> Message {
+        if request.is_cancelled() && matches!(response, rgpu_protocol::cuda_commands::CudaResponse::Error { .. }) {
+            return Self::cancelled_response(session, request_id);
+        }
+        Message::CudaResponse {
+            request_id,
+            response,
+        }
+    }
+
+    /// Response sent in place of a command that was cancelled before or during execution.
+    fn cancelled_response(session: &Session, request_id: rgpu_protocol::messages::RequestId) -> Message {
+        debug!(session_id = session.session_id, "request {:?} cancelled", request_id);
+        Message::Error(rgpu_protocol::error::ProtocolError::Cancelled)
+    }
 }
 
 /// Wait for a shutdown signal (Ctrl+C or SIGTERM on Unix).
@@ -1,7 +1,106 @@
-use std::collections::HashSet;
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use rgpu_core::config::TokenEntry;
+use rgpu_protocol::compat::{self, Feature};
+use rgpu_protocol::cuda_commands::CudaCommand;
 use rgpu_protocol::handle::{NetworkHandle, ResourceType};
+use rgpu_protocol::messages::{Message, RequestId, SessionSummary};
+use rgpu_protocol::wire::CompressionStats;
+
+use tracing::warn;
+
+use crate::cuda_executor::CudaExecutor;
+use crate::kernel_params;
+use crate::uploads::Uploads;
+use crate::vram::{DeviceUuid, VramLedger};
+use crate::vulkan_executor::VulkanExecutor;
+
+/// A command that has been received but not yet answered.
+pub struct InFlightRequest {
+    /// Set when the client sends `Cancel` for this request
+    pub cancelled: AtomicBool,
+    /// Point after which the client no longer waits for the result
+    deadline: Option<Instant>,
+}
+
+impl InFlightRequest {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Whether the client's deadline for this request has already passed.
+    pub fn is_expired(&self) -> bool {
+        self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+}
+
+/// What a session may do, from the token it authenticated with.
+#[derive(Debug, Clone)]
+pub struct TokenScope {
+    /// Name of the token, to count the sessions using it
+    pub token: String,
+    /// GPUs (server device indices) the session may see; None = all
+    pub gpus: Option<Vec<u32>>,
+    pub cuda: bool,
+    pub vulkan: bool,
+    /// Whether modules may be JIT-compiled from PTX
+    pub ptx: bool,
+}
+
+impl TokenScope {
+    pub fn from_token(entry: &TokenEntry) -> Self {
+        Self {
+            token: entry.name.clone(),
+            gpus: entry.allowed_gpus.clone(),
+            cuda: entry.allow_cuda,
+            vulkan: entry.allow_vulkan,
+            ptx: entry.allow_ptx,
+        }
+    }
+
+    /// A scope that allows nothing, for a session whose authentication was
+    /// refused.
+    pub fn refused() -> Self {
+        Self {
+            token: String::new(),
+            gpus: Some(Vec::new()),
+            cuda: false,
+            vulkan: false,
+            ptx: false,
+        }
+    }
+
+    pub fn allows_gpu(&self, index: u32) -> bool {
+        self.gpus.as_ref().is_none_or(|gpus| gpus.contains(&index))
+    }
+
+    /// Why the token doesn't allow `command`, if it doesn't.
+    fn check_cuda(&self, command: &CudaCommand) -> Result<(), String> {
+        const CU_JIT_INPUT_PTX: i32 = 1;
+        match command {
+            CudaCommand::DeviceGet { ordinal } if !self.allows_gpu(*ordinal as u32) => {
+                Err(format!("GPU {} is not available to this token", ordinal))
+            }
+            CudaCommand::ModuleLoadData { image } | CudaCommand::ModuleLoadDataEx { image, .. }
+                if !self.ptx && kernel_params::is_ptx(image) =>
+            {
+                Err("loading PTX is not allowed for this token".to_string())
+            }
+            CudaCommand::LinkAddData { jit_type, .. } | CudaCommand::LinkAddFile { jit_type, .. }
+                if !self.ptx && *jit_type == CU_JIT_INPUT_PTX =>
+            {
+                Err("linking PTX is not allowed for this token".to_string())
+            }
+            CudaCommand::ModuleLoad { fname } if !self.ptx && fname.ends_with(".ptx") => {
+                Err("loading PTX is not allowed for this token".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
 
 /// Per-client session state on the server side.
 /// Tracks all resources allocated by a client for cleanup on disconnect.
@@ -14,6 +113,40 @@ pub struct Session {
     next_resource_id: AtomicU64,
     /// Server ID (for handle generation)
     server_id: u16,
+    /// Trim default memory pools and retry once when an allocation hits OOM
+    retry_alloc_after_trim: AtomicBool,
+    /// Requests that are queued or executing
+    in_flight: parking_lot::Mutex<HashMap<RequestId, Arc<InFlightRequest>>>,
+    /// Name and labels shown in metrics
+    tags: parking_lot::RwLock<SessionTags>,
+    /// Messages handled for this session
+    requests: AtomicU64,
+    connected_at: Instant,
+    /// Compression of the responses sent to this session
+    pub compression: CompressionStats,
+    /// GPU the session last created a CUDA context or Vulkan device on
+    device: parking_lot::Mutex<Option<DeviceUuid>>,
+    /// GPU each CUDA ordinal resolved to, kept across reconnects (see
+    /// `device_affinity`)
+    device_bindings: parking_lot::Mutex<BTreeMap<i32, DeviceUuid>>,
+    /// Secret a later connection of the same client presents to take this
+    /// session over (see `ParkedSessions`)
+    pub resume_token: u64,
+    /// Restrictions of the auth token; None when it has none
+    scope: parking_lot::RwLock<Option<TokenScope>>,
+    /// Protocol version from the client's Hello (0 before it)
+    protocol_version: AtomicU32,
+    authenticated: AtomicBool,
+    /// Chunked uploads still arriving or not yet used
+    pub uploads: Uploads,
+}
+
+#[derive(Default)]
+struct SessionTags {
+    name: Option<String>,
+    labels: BTreeMap<String, String>,
+    /// Labels that came from the auth token; the client can't override these
+    token_labels: BTreeMap<String, String>,
 }
 
 impl Session {
@@ -24,6 +157,21 @@ impl Session {
             allocated_handles: parking_lot::RwLock::new(HashSet::new()),
             next_resource_id: AtomicU64::new(1),
             server_id,
+            retry_alloc_after_trim: AtomicBool::new(false),
+            in_flight: parking_lot::Mutex::new(HashMap::new()),
+            tags: parking_lot::RwLock::new(SessionTags::default()),
+            requests: AtomicU64::new(0),
+            connected_at: Instant::now(),
+            compression: CompressionStats::default(),
+            device: parking_lot::Mutex::new(None),
+            device_bindings: parking_lot::Mutex::new(BTreeMap::new()),
+            resume_token: u64::from_le_bytes(
+                rgpu_transport::auth::generate_challenge(8).try_into().expect("8 bytes"),
+            ),
+            scope: parking_lot::RwLock::new(None),
+            protocol_version: AtomicU32::new(0),
+            authenticated: AtomicBool::new(false),
+            uploads: Uploads::default(),
         }
     }
 
@@ -39,10 +187,10 @@ impl Session {
         handle
     }
 
-    /// Validate that a handle belongs to this session.
+    /// Validate that a handle belongs to this session, including handles it
+    /// took over from the session it resumed.
     pub fn validate_handle(&self, handle: &NetworkHandle) -> bool {
-        handle.session_id == self.session_id
-            && self.allocated_handles.read().contains(handle)
+        self.allocated_handles.read().contains(handle)
     }
 
     /// Get all allocated handles (for cleanup).
@@ -55,8 +203,235 @@ impl Session {
         self.allocated_handles.write().remove(handle);
     }
 
+    /// Free everything the session still holds once its connection is gone,
+    /// whether the client disconnected cleanly or not. Requests still queued
+    /// are cancelled, the executors destroy the session's CUDA and Vulkan
+    /// objects in dependency order, and whatever VRAM is still charged to
+    /// the session is released. Returns the number of handles that were
+    /// still allocated.
+    pub fn teardown(&self, cuda: &CudaExecutor, vulkan: &VulkanExecutor, vram: &VramLedger) -> usize {
+        for request in self.in_flight.lock().values() {
+            request.cancelled.store(true, Ordering::Relaxed);
+        }
+        let leaked = self.allocated_handles.read().len();
+        if leaked > 0 {
+            warn!(session_id = self.session_id, "{} handle(s) leaked at disconnect, cleaning up", leaked);
+        }
+        cuda.cleanup_session(self);
+        vulkan.cleanup_session(self);
+        vram.release_session(self.session_id);
+        self.allocated_handles.write().clear();
+        leaked
+    }
+
+    /// Note the protocol version the client announced in its Hello.
+    pub fn set_protocol_version(&self, version: u32) {
+        self.protocol_version.store(version, Ordering::Relaxed);
+    }
+
+    pub fn set_authenticated(&self) {
+        self.authenticated.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_authenticated(&self) -> bool {
+        self.authenticated.load(Ordering::Relaxed)
+    }
+
+    /// Whether the protocol version the client announced has `feature`.
+    pub fn supports(&self, feature: Feature) -> bool {
+        compat::supports(self.protocol_version.load(Ordering::Relaxed), feature)
+    }
+
+    /// Whether the client is to be told of changes to the server's GPUs:
+    /// it said Hello and authenticated.
+    pub fn wants_topology_updates(&self) -> bool {
+        self.authenticated.load(Ordering::Relaxed) && self.protocol_version.load(Ordering::Relaxed) != 0
+    }
+
     /// Get this session's server ID.
     pub fn server_id(&self) -> u16 {
         self.server_id
     }
+
+    /// Whether allocations should be retried after trimming memory pools.
+    pub fn retry_alloc_after_trim(&self) -> bool {
+        self.retry_alloc_after_trim.load(Ordering::Relaxed)
+    }
+
+    /// Enable or disable the trim-and-retry allocation policy (set from the token).
+    pub fn set_retry_alloc_after_trim(&self, enabled: bool) {
+        self.retry_alloc_after_trim.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Register a request as queued or executing, starting its deadline clock
+    /// (`deadline_ms` from the command) now. Returns the existing entry if the
+    /// request is already registered, so the clock starts at first arrival.
+    pub fn begin_request(&self, request_id: RequestId, deadline_ms: Option<u32>) -> Arc<InFlightRequest> {
+        self.in_flight
+            .lock()
+            .entry(request_id)
+            .or_insert_with(|| {
+                Arc::new(InFlightRequest {
+                    cancelled: AtomicBool::new(false),
+                    deadline: deadline_ms
+                        .map(|ms| Instant::now() + Duration::from_millis(ms as u64)),
+                })
+            })
+            .clone()
+    }
+
+    /// Stop tracking a finished request.
+    pub fn end_request(&self, request_id: RequestId) {
+        self.in_flight.lock().remove(&request_id);
+    }
+
+    /// Flag a queued or executing request as cancelled.
+    /// Returns false if the request is unknown (e.g. it already finished).
+    pub fn cancel_request(&self, request_id: RequestId) -> bool {
+        match self.in_flight.lock().get(&request_id) {
+            Some(request) => {
+                request.cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Restrict the session to what its auth token allows.
+    pub fn set_scope(&self, scope: TokenScope) {
+        *self.scope.write() = Some(scope);
+    }
+
+    /// Name of the token the session authenticated with, if it has a scope.
+    pub fn token(&self) -> Option<String> {
+        self.scope.read().as_ref().map(|s| s.token.clone())
+    }
+
+    /// Whether the session may see the GPU with server device index `index`.
+    pub fn allows_gpu(&self, index: u32) -> bool {
+        self.scope.read().as_ref().is_none_or(|s| s.allows_gpu(index))
+    }
+
+    /// Whether the session may have PTX JIT-compiled.
+    pub fn allows_ptx(&self) -> bool {
+        self.scope.read().as_ref().is_none_or(|s| s.ptx)
+    }
+
+    /// Check a request against the token's scope: CUDA and Vulkan each have
+    /// to be allowed, CUDA devices must be among the token's GPUs and PTX is
+    /// only JIT-compiled if the token permits it. Returns why not otherwise.
+    pub fn permits(&self, msg: &Message) -> Result<(), String> {
+        let scope = self.scope.read();
+        let Some(scope) = scope.as_ref() else {
+            return Ok(());
+        };
+        let commands: Vec<&CudaCommand> = match msg {
+            Message::CudaCommand { command, .. } | Message::CudaCommandStreamed { command, .. } => vec![command],
+            Message::CudaBatch(commands) => commands.iter().collect(),
+            Message::CudaPipelined { batch, command, .. } => batch.iter().chain([command]).collect(),
+            Message::VulkanCommand { .. } if !scope.vulkan => {
+                return Err("Vulkan is not allowed for this token".to_string());
+            }
+            _ => return Ok(()),
+        };
+        if !scope.cuda {
+            return Err("CUDA is not allowed for this token".to_string());
+        }
+        commands.into_iter().try_for_each(|command| scope.check_cuda(command))
+    }
+
+    /// Apply the name and labels configured on the auth token.
+    pub fn set_token_tags(&self, name: &str, labels: &BTreeMap<String, String>) {
+        let mut tags = self.tags.write();
+        if tags.name.is_none() && !name.is_empty() {
+            tags.name = Some(name.to_string());
+        }
+        tags.labels.extend(labels.iter().map(|(k, v)| (k.clone(), v.clone())));
+        tags.token_labels = labels.clone();
+    }
+
+    /// Apply a client's `SetSessionInfo`. The name replaces any previous one;
+    /// labels are merged, except those fixed by the token.
+    pub fn set_client_tags(&self, name: Option<String>, labels: Vec<(String, String)>) {
+        let mut tags = self.tags.write();
+        if let Some(name) = name {
+            tags.name = Some(name);
+        }
+        for (key, value) in labels {
+            if !tags.token_labels.contains_key(&key) {
+                tags.labels.insert(key, value);
+            }
+        }
+    }
+
+    /// Record the GPU the session is working on.
+    pub fn set_device(&self, device: DeviceUuid) {
+        *self.device.lock() = Some(device);
+    }
+
+    /// The GPU the session is working on, once it has created a context or
+    /// device.
+    pub fn device(&self) -> Option<DeviceUuid> {
+        *self.device.lock()
+    }
+
+    /// The GPU `ordinal` is bound to, if the session resolved it before.
+    pub fn device_binding(&self, ordinal: i32) -> Option<DeviceUuid> {
+        self.device_bindings.lock().get(&ordinal).copied()
+    }
+
+    pub fn bind_device(&self, ordinal: i32, device: DeviceUuid) {
+        self.device_bindings.lock().insert(ordinal, device);
+    }
+
+    pub fn device_bindings(&self) -> BTreeMap<i32, DeviceUuid> {
+        self.device_bindings.lock().clone()
+    }
+
+    /// Take over the objects and GPUs of `previous`, a session whose client
+    /// reconnected within the grace period. Its handles keep their session
+    /// id, so the client can go on using them. Returns how many there were.
+    pub fn adopt(&self, previous: &Session) -> usize {
+        let handles = std::mem::take(&mut *previous.allocated_handles.write());
+        let count = handles.len();
+        self.allocated_handles.write().extend(handles);
+        self.set_device_bindings(previous.device_bindings());
+        if let Some(device) = previous.device() {
+            self.set_device(device);
+        }
+        count
+    }
+
+    /// Adopt the bindings of a resumed session.
+    pub fn set_device_bindings(&self, bindings: BTreeMap<i32, DeviceUuid>) {
+        *self.device_bindings.lock() = bindings;
+    }
+
+    /// Count a message handled for this session.
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// The session's name: what the client or its token called it, or the
+    /// client's name from the handshake.
+    pub fn name(&self) -> String {
+        self.tags.read().name.clone().unwrap_or_else(|| self.client_name.clone())
+    }
+
+    /// Snapshot for `MetricsData`.
+    pub fn summary(&self, vram: &VramLedger) -> SessionSummary {
+        let tags = self.tags.read();
+        let (vram_quota, vram_refused) = vram.session_limit(self.session_id);
+        SessionSummary {
+            session_id: self.session_id,
+            name: tags.name.clone().unwrap_or_else(|| self.client_name.clone()),
+            labels: tags.labels.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            requests: self.requests.load(Ordering::Relaxed),
+            connected_secs: self.connected_at.elapsed().as_secs(),
+            compression: self.compression.summary(),
+            vram: vram.session_usage(self.session_id),
+            vram_quota,
+            vram_refused,
+        }
+    }
 }
@@ -0,0 +1,200 @@
+//! GPUs of ended sessions, for clients that reconnect and resume them.
+//!
+//! A client that reconnects after a network blip gets a new session, and the
+//! CUDA ordinals it resolves could name other GPUs than before if the
+//! server's devices were enumerated differently in between (a restart, a GPU
+//! dropping out), invalidating state the application tuned for a device.
+//! When a session ends, the GPU each of its ordinals resolved to is kept
+//! here, in memory and in the server's state directory. A client that sends
+//! `ResumeSession` with the old session id gets those bindings back, so its
+//! ordinals resolve to the same GPUs wherever they are still present; the
+//! ones that aren't are reported as changed.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::{debug, warn};
+
+use crate::session::Session;
+use crate::vram::DeviceUuid;
+
+/// How long an ended session can still be resumed.
+const KEEP: Duration = Duration::from_secs(24 * 60 * 60);
+
+struct Ended {
+    /// Unix time the session ended
+    ended_at: u64,
+    bindings: BTreeMap<i32, DeviceUuid>,
+}
+
+pub struct SessionDevices {
+    sessions: parking_lot::Mutex<HashMap<u32, Ended>>,
+    /// Where they are saved; `None` keeps them in memory only
+    path: Option<PathBuf>,
+}
+
+impl SessionDevices {
+    /// Load the sessions saved at `path`, dropping those too old to resume.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let sessions = path
+            .as_ref()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+            .map(|text| parse(&text))
+            .unwrap_or_default();
+        let devices = Self {
+            sessions: parking_lot::Mutex::new(sessions),
+            path,
+        };
+        devices.expire(now());
+        devices
+    }
+
+    /// In the server's state directory.
+    pub fn in_state_dir() -> Self {
+        Self::load(Some(rgpu_common::platform::state_dir().join("session-devices")))
+    }
+
+    /// Highest session id that can still be resumed, so a restarted server
+    /// doesn't hand the id to a new session.
+    pub fn max_session_id(&self) -> u32 {
+        self.sessions.lock().keys().copied().max().unwrap_or(0)
+    }
+
+    /// Keep an ending session's bindings.
+    pub fn save(&self, session: &Session) {
+        let bindings = session.device_bindings();
+        if bindings.is_empty() {
+            return;
+        }
+        let ended_at = now();
+        self.sessions
+            .lock()
+            .insert(session.session_id, Ended { ended_at, bindings });
+        self.expire(ended_at);
+        self.persist();
+    }
+
+    /// Remove and return the bindings of the ended session `session_id`.
+    pub fn take(&self, session_id: u32) -> Option<BTreeMap<i32, DeviceUuid>> {
+        let ended = self.sessions.lock().remove(&session_id)?;
+        self.persist();
+        Some(ended.bindings)
+    }
+
+    fn expire(&self, now: u64) {
+        self.sessions
+            .lock()
+            .retain(|_, ended| now.saturating_sub(ended.ended_at) < KEEP.as_secs());
+    }
+
+    fn persist(&self) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let text = format(&self.sessions.lock());
+        let written = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|()| std::fs::write(path, text));
+        match written {
+            Ok(()) => debug!("saved session devices to {}", path.display()),
+            Err(e) => warn!("failed to save session devices to {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// One `<session id> <ended at> <ordinal>=<uuid>,...` per line, UUIDs in
+/// hex; anything else is skipped.
+fn parse(text: &str) -> HashMap<u32, Ended> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let session_id = fields.next()?.parse().ok()?;
+            let ended_at = fields.next()?.parse().ok()?;
+            let bindings = fields
+                .next()?
+                .split(',')
+                .map(|binding| {
+                    let (ordinal, uuid) = binding.split_once('=')?;
+                    Some((ordinal.parse().ok()?, parse_uuid(uuid)?))
+                })
+                .collect::<Option<_>>()?;
+            Some((session_id, Ended { ended_at, bindings }))
+        })
+        .collect()
+}
+
+fn parse_uuid(hex: &str) -> Option<DeviceUuid> {
+    if hex.len() != 32 {
+        return None;
+    }
+    let mut uuid = [0u8; 16];
+    for (i, byte) in uuid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(uuid)
+}
+
+fn format(sessions: &HashMap<u32, Ended>) -> String {
+    let mut text = String::from("# GPUs of ended sessions, for clients that resume them\n");
+    let mut ids: Vec<_> = sessions.keys().collect();
+    ids.sort();
+    for id in ids {
+        let ended = &sessions[id];
+        let bindings: Vec<String> = ended
+            .bindings
+            .iter()
+            .map(|(ordinal, uuid)| {
+                let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+                format!("{}={}", ordinal, hex)
+            })
+            .collect();
+        text.push_str(&format!("{} {} {}\n", id, ended.ended_at, bindings.join(",")));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sessions_round_trip() {
+        let mut sessions = HashMap::new();
+        sessions.insert(
+            3,
+            Ended {
+                ended_at: 1_700_000_000,
+                bindings: BTreeMap::from([(0, [0xab; 16]), (1, [0x01; 16])]),
+            },
+        );
+        let parsed = parse(&format(&sessions));
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[&3].ended_at, 1_700_000_000);
+        assert_eq!(parsed[&3].bindings, sessions[&3].bindings);
+        assert!(parse("garbage\n4 5 0=zz\n").is_empty());
+    }
+
+    #[test]
+    fn take_and_expiry() {
+        let devices = SessionDevices::load(None);
+        let session = Session::new(7, 0, "test".to_string());
+        session.bind_device(0, [0x42; 16]);
+        devices.save(&session);
+        assert_eq!(devices.max_session_id(), 7);
+
+        assert_eq!(devices.take(7), Some(BTreeMap::from([(0, [0x42; 16])])));
+        assert_eq!(devices.take(7), None);
+
+        devices.save(&session);
+        devices.expire(now() + KEEP.as_secs());
+        assert_eq!(devices.take(7), None);
+    }
+}
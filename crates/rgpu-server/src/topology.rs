@@ -0,0 +1,252 @@
+//! PCIe, NVLink and NUMA placement of the server's GPUs.
+//!
+//! Every source is optional: sysfs gives the PCIe path and NUMA node (Linux
+//! only), NVML gives NVLink connections, and the CUDA driver gives peer
+//! access. Whatever can't be determined is left empty or `Unknown`.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_uint, c_void, CString};
+use std::path::PathBuf;
+
+use libloading::{Library, Symbol};
+use tracing::{debug, info};
+
+use rgpu_protocol::gpu_info::{GpuInfo, LinkPath, PeerLink};
+
+use crate::cuda_driver::{
+    CUdevice, CudaDriver, CUDA_SUCCESS, CU_DEVICE_P2P_ATTRIBUTE_ACCESS_SUPPORTED,
+    CU_DEVICE_P2P_ATTRIBUTE_NATIVE_ATOMIC_SUPPORTED, CU_DEVICE_P2P_ATTRIBUTE_PERFORMANCE_RANK,
+};
+use crate::vram::DeviceUuid;
+
+/// PCI domain, bus and device; the function is ignored, GPUs are function 0.
+type PciAddress = (u32, u32, u32);
+
+/// Fill in `topology` for each GPU. GPUs are matched to CUDA devices by UUID.
+pub fn resolve(gpus: &mut [(GpuInfo, DeviceUuid)]) {
+    let cuda = cuda_devices();
+
+    for (gpu, uuid) in gpus.iter_mut() {
+        if gpu.topology.pci_bus_id.is_none() {
+            gpu.topology.pci_bus_id = cuda
+                .as_ref()
+                .and_then(|(d, devices)| devices.get(uuid).and_then(|dev| d.device_get_pci_bus_id(*dev).ok()))
+                .map(|id| id.to_ascii_lowercase());
+        }
+        gpu.topology.numa_node = gpu.topology.pci_bus_id.as_deref().and_then(numa_node);
+    }
+
+    let nvlinks = Nvml::load().map(|nvml| nvml.links(gpus)).unwrap_or_default();
+    let paths: Vec<Option<Vec<String>>> = gpus
+        .iter()
+        .map(|(gpu, _)| gpu.topology.pci_bus_id.as_deref().and_then(sysfs_path))
+        .collect();
+
+    for i in 0..gpus.len() {
+        let mut links = Vec::new();
+        for j in 0..gpus.len() {
+            if i == j {
+                continue;
+            }
+            let nvlink_count = nvlinks.get(&(i, j)).copied().unwrap_or(0);
+            let path = if nvlink_count > 0 {
+                LinkPath::NvLink
+            } else {
+                match (&paths[i], &paths[j]) {
+                    (Some(a), Some(b)) => {
+                        pcie_path(a, b, gpus[i].0.topology.numa_node, gpus[j].0.topology.numa_node)
+                    }
+                    _ => LinkPath::Unknown,
+                }
+            };
+            let (p2p_access, p2p_atomics, performance_rank) = cuda
+                .as_ref()
+                .and_then(|(d, devices)| {
+                    let src = *devices.get(&gpus[i].1)?;
+                    let dst = *devices.get(&gpus[j].1)?;
+                    let attr = |a| d.device_get_p2p_attribute(a, src, dst).unwrap_or(0);
+                    Some((
+                        attr(CU_DEVICE_P2P_ATTRIBUTE_ACCESS_SUPPORTED) != 0,
+                        attr(CU_DEVICE_P2P_ATTRIBUTE_NATIVE_ATOMIC_SUPPORTED) != 0,
+                        attr(CU_DEVICE_P2P_ATTRIBUTE_PERFORMANCE_RANK),
+                    ))
+                })
+                .unwrap_or((false, false, 0));
+            links.push(PeerLink {
+                peer_device_index: gpus[j].0.server_device_index,
+                path,
+                nvlink_count,
+                p2p_access,
+                p2p_atomics,
+                performance_rank,
+            });
+        }
+        gpus[i].0.topology.links = links;
+    }
+}
+
+/// The CUDA driver and its devices by UUID, if CUDA is available.
+fn cuda_devices() -> Option<(std::sync::Arc<CudaDriver>, HashMap<DeviceUuid, CUdevice>)> {
+    let driver = CudaDriver::load().ok()?;
+    if driver.init(0) != CUDA_SUCCESS {
+        return None;
+    }
+    let count = driver.device_get_count().ok()?;
+    let devices = (0..count)
+        .filter_map(|ordinal| {
+            let dev = driver.device_get(ordinal).ok()?;
+            Some((driver.device_get_uuid(dev).ok()?, dev))
+        })
+        .collect();
+    Some((driver, devices))
+}
+
+/// Parse "dddd:bb:dd.f" (any domain width, either case).
+fn parse_bus_id(id: &str) -> Option<PciAddress> {
+    let mut parts = id.trim().split(':');
+    let domain = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let bus = u32::from_str_radix(parts.next()?, 16).ok()?;
+    let device = u32::from_str_radix(parts.next()?.split('.').next()?, 16).ok()?;
+    Some((domain, bus, device))
+}
+
+/// Host NUMA node of a PCI device, from sysfs.
+fn numa_node(bus_id: &str) -> Option<u32> {
+    let node = std::fs::read_to_string(format!("/sys/bus/pci/devices/{}/numa_node", bus_id)).ok()?;
+    // -1 when the platform has no NUMA information.
+    node.trim().parse::<i32>().ok().and_then(|n| u32::try_from(n).ok())
+}
+
+/// The chain of PCI devices from the root complex down to the device, e.g.
+/// `["pci0000:00", "0000:00:01.0", "0000:01:00.0"]`.
+fn sysfs_path(bus_id: &str) -> Option<Vec<String>> {
+    let path: PathBuf = std::fs::canonicalize(format!("/sys/bus/pci/devices/{}", bus_id)).ok()?;
+    let components: Vec<String> = path
+        .strip_prefix("/sys/devices")
+        .ok()?
+        .iter()
+        .map(|c| c.to_string_lossy().into_owned())
+        .collect();
+    (!components.is_empty()).then_some(components)
+}
+
+/// Classify the PCIe path between two devices from their sysfs paths.
+fn pcie_path(a: &[String], b: &[String], numa_a: Option<u32>, numa_b: Option<u32>) -> LinkPath {
+    let common = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    match common {
+        // Different root complexes.
+        0 if numa_a.is_some() && numa_a == numa_b => LinkPath::NumaNode,
+        0 => LinkPath::System,
+        // Same root complex, different root ports.
+        1 => LinkPath::HostBridge,
+        // A shared root port has a switch below it.
+        _ => LinkPath::PcieSwitch,
+    }
+}
+
+// ── NVML ────────────────────────────────────────────────────────────
+
+type NvmlReturn = c_int;
+type NvmlDevice = *mut c_void;
+
+const NVML_SUCCESS: NvmlReturn = 0;
+const NVML_NVLINK_MAX_LINKS: c_uint = 18;
+
+/// `nvmlPciInfo_t`
+#[repr(C)]
+struct NvmlPciInfo {
+    bus_id_legacy: [c_char; 16],
+    domain: c_uint,
+    bus: c_uint,
+    device: c_uint,
+    pci_device_id: c_uint,
+    pci_sub_system_id: c_uint,
+    bus_id: [c_char; 32],
+}
+
+type FnNvmlInit = unsafe extern "C" fn() -> NvmlReturn;
+type FnNvmlShutdown = unsafe extern "C" fn() -> NvmlReturn;
+type FnNvmlDeviceGetHandleByPciBusId = unsafe extern "C" fn(bus_id: *const c_char, device: *mut NvmlDevice) -> NvmlReturn;
+type FnNvmlDeviceGetNvLinkState = unsafe extern "C" fn(device: NvmlDevice, link: c_uint, is_active: *mut c_uint) -> NvmlReturn;
+type FnNvmlDeviceGetNvLinkRemotePciInfo = unsafe extern "C" fn(device: NvmlDevice, link: c_uint, pci: *mut NvmlPciInfo) -> NvmlReturn;
+
+struct Nvml {
+    _lib: Library,
+    shutdown: FnNvmlShutdown,
+    get_handle_by_pci_bus_id: FnNvmlDeviceGetHandleByPciBusId,
+    get_nvlink_state: FnNvmlDeviceGetNvLinkState,
+    get_nvlink_remote_pci_info: FnNvmlDeviceGetNvLinkRemotePciInfo,
+}
+
+impl Nvml {
+    fn load() -> Option<Self> {
+        #[cfg(target_os = "windows")]
+        let lib_names = &["nvml.dll"];
+        #[cfg(not(target_os = "windows"))]
+        let lib_names = &["libnvidia-ml.so.1", "libnvidia-ml.so"];
+
+        let lib = lib_names.iter().find_map(|name| unsafe { Library::new(name).ok() })?;
+        unsafe {
+            let init: FnNvmlInit = Self::sym(&lib, "nvmlInit_v2")?;
+            let nvml = Self {
+                shutdown: Self::sym(&lib, "nvmlShutdown")?,
+                get_handle_by_pci_bus_id: Self::sym(&lib, "nvmlDeviceGetHandleByPciBusId_v2")?,
+                get_nvlink_state: Self::sym(&lib, "nvmlDeviceGetNvLinkState")?,
+                get_nvlink_remote_pci_info: Self::sym(&lib, "nvmlDeviceGetNvLinkRemotePciInfo_v2")?,
+                _lib: lib,
+            };
+            if init() != NVML_SUCCESS {
+                debug!("NVML failed to initialize");
+                return None;
+            }
+            info!("loaded NVML for NVLink topology");
+            Some(nvml)
+        }
+    }
+
+    unsafe fn sym<F: Copy>(lib: &Library, name: &str) -> Option<F> {
+        lib.get(name.as_bytes()).ok().map(|s: Symbol<F>| *s)
+    }
+
+    /// Active NVLink links between each ordered pair of GPUs, by position in
+    /// `gpus`. Links to NVSwitches and non-GPU peers are left out.
+    fn links(&self, gpus: &[(GpuInfo, DeviceUuid)]) -> HashMap<(usize, usize), u32> {
+        let addresses: Vec<Option<PciAddress>> = gpus
+            .iter()
+            .map(|(gpu, _)| gpu.topology.pci_bus_id.as_deref().and_then(parse_bus_id))
+            .collect();
+        let mut counts = HashMap::new();
+        for (i, (gpu, _)) in gpus.iter().enumerate() {
+            let Some(bus_id) = gpu.topology.pci_bus_id.as_deref().and_then(|id| CString::new(id).ok()) else {
+                continue;
+            };
+            let mut device: NvmlDevice = std::ptr::null_mut();
+            if unsafe { (self.get_handle_by_pci_bus_id)(bus_id.as_ptr(), &mut device) } != NVML_SUCCESS {
+                continue;
+            }
+            for link in 0..NVML_NVLINK_MAX_LINKS {
+                let mut active: c_uint = 0;
+                if unsafe { (self.get_nvlink_state)(device, link, &mut active) } != NVML_SUCCESS || active == 0 {
+                    continue;
+                }
+                let mut pci: NvmlPciInfo = unsafe { std::mem::zeroed() };
+                if unsafe { (self.get_nvlink_remote_pci_info)(device, link, &mut pci) } != NVML_SUCCESS {
+                    continue;
+                }
+                let remote = Some((pci.domain, pci.bus, pci.device));
+                if let Some(j) = addresses.iter().position(|a| *a == remote) {
+                    *counts.entry((i, j)).or_insert(0) += 1;
+                }
+            }
+        }
+        counts
+    }
+}
+
+impl Drop for Nvml {
+    fn drop(&mut self) {
+        unsafe {
+            (self.shutdown)();
+        }
+    }
+}
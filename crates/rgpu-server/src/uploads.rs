@@ -0,0 +1,109 @@
+//! Reassembly of chunked uploads.
+//!
+//! Clients send the data of a large host-to-device copy as
+//! `TransferChunk`s ahead of the command, so it doesn't travel as one
+//! frame that holds up the connection and both ends' memory. The reader
+//! task files each chunk here as it arrives; `CudaCommandChunked` then
+//! takes the whole upload. Chunks come in order on a byte stream, so an
+//! upload only ever grows at its end.
+
+use std::collections::VecDeque;
+
+/// Uploads a session may have open at once. Starting another drops the
+/// oldest, which its client has abandoned.
+const MAX_OPEN: usize = 4;
+
+struct Upload {
+    transfer: u64,
+    total: u64,
+    data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct Uploads {
+    open: parking_lot::Mutex<VecDeque<Upload>>,
+}
+
+impl Uploads {
+    /// File the chunk of `transfer` at `offset`. Returns the bytes of the
+    /// upload held, or 0 if it is refused: larger than `limit`, or the
+    /// chunk doesn't continue it. A refused upload is dropped.
+    pub fn receive(&self, transfer: u64, offset: u64, total: u64, data: Vec<u8>, limit: usize) -> u64 {
+        let mut open = self.open.lock();
+        let existing = open.iter().position(|upload| upload.transfer == transfer);
+        if offset == 0 {
+            if let Some(index) = existing {
+                open.remove(index);
+            }
+            if total > limit as u64 || data.len() as u64 > total {
+                return 0;
+            }
+            if open.len() >= MAX_OPEN {
+                open.pop_front();
+            }
+            let mut buffer = Vec::with_capacity(total as usize);
+            buffer.extend_from_slice(&data);
+            open.push_back(Upload { transfer, total, data: buffer });
+            return data.len() as u64;
+        }
+
+        let Some(index) = existing else {
+            return 0;
+        };
+        let upload = &mut open[index];
+        let held = upload.data.len() as u64;
+        if offset != held || total != upload.total || held + data.len() as u64 > total {
+            open.remove(index);
+            return 0;
+        }
+        upload.data.extend_from_slice(&data);
+        upload.data.len() as u64
+    }
+
+    /// Take the data of `transfer` if all of it has arrived.
+    pub fn take(&self, transfer: u64) -> Option<Vec<u8>> {
+        let mut open = self.open.lock();
+        let index = open
+            .iter()
+            .position(|upload| upload.transfer == transfer && upload.data.len() as u64 == upload.total)?;
+        open.remove(index).map(|upload| upload.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_reassemble_in_order() {
+        let uploads = Uploads::default();
+        assert_eq!(uploads.receive(1, 0, 6, vec![1, 2], 1024), 2);
+        assert!(uploads.take(1).is_none());
+        assert_eq!(uploads.receive(1, 2, 6, vec![3, 4], 1024), 4);
+        assert_eq!(uploads.receive(1, 4, 6, vec![5, 6], 1024), 6);
+        assert_eq!(uploads.take(1), Some(vec![1, 2, 3, 4, 5, 6]));
+        assert!(uploads.take(1).is_none());
+    }
+
+    #[test]
+    fn test_gaps_and_oversized_uploads_are_refused() {
+        let uploads = Uploads::default();
+        assert_eq!(uploads.receive(1, 0, 2048, vec![0; 8], 1024), 0);
+        assert_eq!(uploads.receive(2, 8, 16, vec![0; 8], 1024), 0);
+
+        assert_eq!(uploads.receive(3, 0, 16, vec![0; 4], 1024), 4);
+        assert_eq!(uploads.receive(3, 8, 16, vec![0; 8], 1024), 0);
+        // Dropped with the gap
+        assert_eq!(uploads.receive(3, 4, 16, vec![0; 4], 1024), 0);
+    }
+
+    #[test]
+    fn test_oldest_upload_makes_way() {
+        let uploads = Uploads::default();
+        for transfer in 0..=MAX_OPEN as u64 {
+            assert_eq!(uploads.receive(transfer, 0, 1, vec![7], 1024), 1);
+        }
+        assert!(uploads.take(0).is_none());
+        assert_eq!(uploads.take(MAX_OPEN as u64), Some(vec![7]));
+    }
+}
@@ -0,0 +1,116 @@
+//! Device-wide memory use and utilization, for `DeviceGetUsage`.
+//!
+//! NVML reports what the whole GPU is doing, including other processes on
+//! the server. Without it the server can only count what its own sessions
+//! allocated (see [`crate::vram::VramLedger`]) and reports no utilization.
+
+use std::ffi::{c_char, c_int, c_uint, c_void, CString};
+use std::sync::OnceLock;
+
+use libloading::{Library, Symbol};
+use tracing::{debug, info};
+
+type NvmlReturn = c_int;
+type NvmlDevice = *mut c_void;
+
+const NVML_SUCCESS: NvmlReturn = 0;
+
+/// `nvmlMemory_t`
+#[repr(C)]
+#[derive(Default)]
+struct NvmlMemory {
+    total: u64,
+    free: u64,
+    used: u64,
+}
+
+/// `nvmlUtilization_t`
+#[repr(C)]
+#[derive(Default)]
+struct NvmlUtilization {
+    gpu: c_uint,
+    memory: c_uint,
+}
+
+type FnNvmlInit = unsafe extern "C" fn() -> NvmlReturn;
+type FnNvmlDeviceGetHandleByPciBusId = unsafe extern "C" fn(bus_id: *const c_char, device: *mut NvmlDevice) -> NvmlReturn;
+type FnNvmlDeviceGetMemoryInfo = unsafe extern "C" fn(device: NvmlDevice, memory: *mut NvmlMemory) -> NvmlReturn;
+type FnNvmlDeviceGetUtilizationRates = unsafe extern "C" fn(device: NvmlDevice, utilization: *mut NvmlUtilization) -> NvmlReturn;
+
+/// Usage of one GPU as NVML sees it.
+pub struct NvmlUsage {
+    pub memory_used: u64,
+    /// 0 if the GPU doesn't report utilization (e.g. some vGPUs)
+    pub gpu_utilization: u32,
+    pub memory_utilization: u32,
+}
+
+/// NVML, loaded on first use and kept for the life of the server.
+struct Nvml {
+    _lib: Library,
+    get_handle_by_pci_bus_id: FnNvmlDeviceGetHandleByPciBusId,
+    get_memory_info: FnNvmlDeviceGetMemoryInfo,
+    get_utilization_rates: FnNvmlDeviceGetUtilizationRates,
+}
+
+// SAFETY: NVML is thread-safe.
+unsafe impl Send for Nvml {}
+unsafe impl Sync for Nvml {}
+
+impl Nvml {
+    fn get() -> Option<&'static Nvml> {
+        static NVML: OnceLock<Option<Nvml>> = OnceLock::new();
+        NVML.get_or_init(Self::load).as_ref()
+    }
+
+    fn load() -> Option<Self> {
+        #[cfg(target_os = "windows")]
+        let lib_names = &["nvml.dll"];
+        #[cfg(not(target_os = "windows"))]
+        let lib_names = &["libnvidia-ml.so.1", "libnvidia-ml.so"];
+
+        let lib = lib_names.iter().find_map(|name| unsafe { Library::new(name).ok() })?;
+        unsafe {
+            let init: FnNvmlInit = Self::sym(&lib, "nvmlInit_v2")?;
+            let nvml = Self {
+                get_handle_by_pci_bus_id: Self::sym(&lib, "nvmlDeviceGetHandleByPciBusId_v2")?,
+                get_memory_info: Self::sym(&lib, "nvmlDeviceGetMemoryInfo")?,
+                get_utilization_rates: Self::sym(&lib, "nvmlDeviceGetUtilizationRates")?,
+                _lib: lib,
+            };
+            if init() != NVML_SUCCESS {
+                debug!("NVML failed to initialize");
+                return None;
+            }
+            info!("loaded NVML for device usage");
+            Some(nvml)
+        }
+    }
+
+    unsafe fn sym<F: Copy>(lib: &Library, name: &str) -> Option<F> {
+        lib.get(name.as_bytes()).ok().map(|s: Symbol<F>| *s)
+    }
+}
+
+/// Usage of the GPU at `pci_bus_id`, or `None` without NVML.
+pub fn nvml_usage(pci_bus_id: &str) -> Option<NvmlUsage> {
+    let nvml = Nvml::get()?;
+    let bus_id = CString::new(pci_bus_id).ok()?;
+    let mut device: NvmlDevice = std::ptr::null_mut();
+    if unsafe { (nvml.get_handle_by_pci_bus_id)(bus_id.as_ptr(), &mut device) } != NVML_SUCCESS {
+        return None;
+    }
+    let mut memory = NvmlMemory::default();
+    if unsafe { (nvml.get_memory_info)(device, &mut memory) } != NVML_SUCCESS {
+        return None;
+    }
+    let mut utilization = NvmlUtilization::default();
+    if unsafe { (nvml.get_utilization_rates)(device, &mut utilization) } != NVML_SUCCESS {
+        utilization = NvmlUtilization::default();
+    }
+    Some(NvmlUsage {
+        memory_used: memory.used,
+        gpu_utilization: utilization.gpu,
+        memory_utilization: utilization.memory,
+    })
+}
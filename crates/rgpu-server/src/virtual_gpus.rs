@@ -0,0 +1,273 @@
+//! Physical GPUs shown to clients as several virtual ones
+//! (`[[server.virtual_gpus]]`).
+//!
+//! A split GPU appears `count` times in the GPU list, in cuDeviceGetCount and
+//! in vkEnumeratePhysicalDevices, each time with its share of the memory.
+//! The first session to create a context or Vulkan device on a virtual GPU
+//! holds it until the session ends; other sessions are refused it, like a
+//! leased GPU. The holder's allocations on the physical GPU are limited to
+//! the share, which cuMemGetInfo, cuDeviceTotalMem and the Vulkan heap sizes
+//! report, and a compute share scales the multiprocessor count it reports.
+//! The share of compute is a hint for sizing launches; kernels still run
+//! on the whole GPU.
+//!
+//! Clients number GPUs by what they see: ordinals, and the GPU indices of
+//! tokens' `allowed_gpus`, count virtual GPUs. The other server settings
+//! name physical GPUs.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use tracing::{info, warn};
+
+use rgpu_core::config::VirtualGpuConfig;
+use rgpu_protocol::gpu_info::{GpuDeviceType, GpuInfo};
+
+use crate::vram::DeviceUuid;
+
+/// A virtual GPU's share of its physical GPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slice {
+    /// Which of the physical GPU's slices, from 0
+    pub index: u32,
+    /// Memory in bytes
+    pub memory: u64,
+    /// Share of the multiprocessors, in percent
+    pub sm_percent: Option<u32>,
+}
+
+impl Slice {
+    /// A multiprocessor count scaled to the slice's share.
+    pub fn multiprocessors(&self, count: i32) -> i32 {
+        match self.sm_percent {
+            Some(percent) => (count as i64 * percent as i64 / 100).max(1) as i32,
+            None => count,
+        }
+    }
+}
+
+/// What a client-visible ordinal is.
+#[derive(Debug, Clone, Copy)]
+struct Ordinal {
+    /// Server-side index of the physical GPU
+    physical: u32,
+    slice: Option<Slice>,
+}
+
+/// A virtual GPU held by another session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Taken {
+    pub ordinal: u32,
+    pub session_id: u32,
+}
+
+impl fmt::Display for Taken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "virtual GPU {} is in use by session {}", self.ordinal, self.session_id)
+    }
+}
+
+/// The GPUs clients see, by ordinal. Without any split GPUs they are the
+/// physical GPUs and nothing here applies.
+#[derive(Debug, Default)]
+pub struct VirtualGpus {
+    ordinals: Vec<Ordinal>,
+    /// Session holding each virtual GPU, by ordinal
+    holders: parking_lot::Mutex<HashMap<u32, u32>>,
+}
+
+impl VirtualGpus {
+    pub fn from_config(config: &[VirtualGpuConfig], gpus: &[(GpuInfo, DeviceUuid)]) -> Self {
+        for entry in config {
+            if !gpus.iter().any(|(gpu, _)| gpu.server_device_index == entry.device) {
+                warn!("virtual_gpus: no GPU {}", entry.device);
+            }
+        }
+        if !config.iter().any(|entry| entry.count > 1) {
+            return Self::default();
+        }
+        let mut ordinals = Vec::new();
+        for (gpu, _) in gpus {
+            let entry = config.iter().find(|entry| entry.device == gpu.server_device_index);
+            let Some(entry) = entry.filter(|entry| entry.count > 1) else {
+                ordinals.push(Ordinal { physical: gpu.server_device_index, slice: None });
+                continue;
+            };
+            let share = gpu.total_memory / entry.count as u64;
+            let memory = match entry.memory_mb.map(|mb| mb * 1024 * 1024) {
+                Some(bytes) if bytes <= share => bytes,
+                Some(bytes) => {
+                    warn!("virtual_gpus: {} MB each doesn't fit GPU {} {} times", bytes >> 20, gpu.server_device_index, entry.count);
+                    share
+                }
+                None => share,
+            };
+            let sm_percent = entry.sm_percent.map(|percent| percent.clamp(1, 100));
+            info!(
+                "GPU {}: {} virtual GPUs of {} MB",
+                gpu.server_device_index,
+                entry.count,
+                memory >> 20
+            );
+            for index in 0..entry.count {
+                ordinals.push(Ordinal {
+                    physical: gpu.server_device_index,
+                    slice: Some(Slice { index, memory, sm_percent }),
+                });
+            }
+        }
+        Self {
+            ordinals,
+            holders: parking_lot::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// How many GPUs clients see, if any GPU is split.
+    pub fn count(&self) -> Option<i32> {
+        (!self.ordinals.is_empty()).then_some(self.ordinals.len() as i32)
+    }
+
+    /// The physical GPU a client-visible ordinal is on, and its slice of it
+    /// if it is a virtual GPU.
+    pub fn resolve(&self, ordinal: u32) -> Option<(u32, Option<Slice>)> {
+        if self.ordinals.is_empty() {
+            return Some((ordinal, None));
+        }
+        self.ordinals.get(ordinal as usize).map(|o| (o.physical, o.slice))
+    }
+
+    /// The GPU list clients see: each split GPU once for every slice, with
+    /// its share of the memory.
+    pub fn expand(&self, gpus: Vec<(GpuInfo, DeviceUuid)>) -> Vec<(GpuInfo, DeviceUuid)> {
+        if self.ordinals.is_empty() {
+            return gpus;
+        }
+        let mut expanded = Vec::with_capacity(self.ordinals.len());
+        for (ordinal, o) in self.ordinals.iter().enumerate() {
+            let Some((gpu, uuid)) = gpus.iter().find(|(gpu, _)| gpu.server_device_index == o.physical) else {
+                continue;
+            };
+            let mut gpu = gpu.clone();
+            gpu.server_device_index = ordinal as u32;
+            if let Some(slice) = o.slice {
+                gpu.device_type = GpuDeviceType::VirtualGpu;
+                gpu.total_memory = slice.memory;
+                if let Some(heap) = gpu.memory_heaps.iter_mut().filter(|h| h.is_device_local).max_by_key(|h| h.size) {
+                    heap.size = slice.memory;
+                }
+            }
+            expanded.push((gpu, *uuid));
+        }
+        expanded
+    }
+
+    /// Let `session_id` hold the virtual GPU at `ordinal`. Returns whether
+    /// the session has only now taken it.
+    pub fn claim(&self, session_id: u32, ordinal: u32) -> Result<bool, Taken> {
+        let mut holders = self.holders.lock();
+        match holders.get(&ordinal) {
+            Some(&holder) if holder != session_id => Err(Taken { ordinal, session_id: holder }),
+            Some(_) => Ok(false),
+            None => {
+                info!(session_id, "virtual GPU {} taken", ordinal);
+                holders.insert(ordinal, session_id);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Release the virtual GPUs a session held.
+    pub fn end_session(&self, session_id: u32) {
+        self.holders.lock().retain(|ordinal, holder| {
+            if *holder == session_id {
+                info!(session_id, "virtual GPU {} released", ordinal);
+            }
+            *holder != session_id
+        });
+    }
+
+    /// Hand a resumed session's virtual GPUs to the session that took it
+    /// over.
+    pub fn transfer_session(&self, from: u32, to: u32) {
+        for holder in self.holders.lock().values_mut() {
+            if *holder == from {
+                *holder = to;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::gpu_info::MemoryHeapInfo;
+
+    fn gpu(index: u32, memory: u64) -> (GpuInfo, DeviceUuid) {
+        let info = GpuInfo {
+            device_name: format!("GPU {}", index),
+            vendor_id: 0x10de,
+            device_id: 0,
+            device_type: GpuDeviceType::DiscreteGpu,
+            total_memory: memory,
+            supports_vulkan: true,
+            supports_cuda: true,
+            vulkan_api_version: None,
+            vulkan_driver_version: None,
+            cuda_compute_capability: Some((8, 6)),
+            queue_family_count: 1,
+            memory_heaps: vec![MemoryHeapInfo { size: memory, is_device_local: true }],
+            server_device_index: index,
+            server_id: 0,
+            topology: Default::default(),
+            scheduling: Default::default(),
+            uuid: None,
+        };
+        (info, [index as u8; 16])
+    }
+
+    #[test]
+    fn split_gpu_listed_per_slice() {
+        let config = [VirtualGpuConfig { device: 0, count: 3, memory_mb: None, sm_percent: Some(33) }];
+        let gpus = vec![gpu(0, 24 << 30), gpu(1, 8 << 30)];
+        let virtual_gpus = VirtualGpus::from_config(&config, &gpus);
+        assert_eq!(virtual_gpus.count(), Some(4));
+
+        let listed = virtual_gpus.expand(gpus);
+        let indices: Vec<_> = listed.iter().map(|(gpu, _)| gpu.server_device_index).collect();
+        assert_eq!(indices, [0, 1, 2, 3]);
+        assert_eq!(listed[2].0.total_memory, 8 << 30);
+        assert_eq!(listed[2].0.memory_heaps[0].size, 8 << 30);
+        assert_eq!(listed[2].1, [0; 16]);
+        assert_eq!(listed[3].0.device_name, "GPU 1");
+        assert_eq!(listed[3].0.device_type, GpuDeviceType::DiscreteGpu);
+
+        let (physical, slice) = virtual_gpus.resolve(2).unwrap();
+        assert_eq!(physical, 0);
+        assert_eq!(slice.unwrap().index, 2);
+        assert_eq!(slice.unwrap().multiprocessors(128), 42);
+        assert_eq!(virtual_gpus.resolve(3), Some((1, None)));
+        assert_eq!(virtual_gpus.resolve(4), None);
+    }
+
+    #[test]
+    fn one_session_per_virtual_gpu() {
+        let config = [VirtualGpuConfig { device: 0, count: 2, memory_mb: Some(1024), sm_percent: None }];
+        let virtual_gpus = VirtualGpus::from_config(&config, &[gpu(0, 8 << 30)]);
+        assert_eq!(virtual_gpus.claim(1, 0), Ok(true));
+        assert_eq!(virtual_gpus.claim(1, 0), Ok(false));
+        assert_eq!(virtual_gpus.claim(2, 0), Err(Taken { ordinal: 0, session_id: 1 }));
+        virtual_gpus.claim(2, 1).unwrap();
+
+        virtual_gpus.transfer_session(1, 3);
+        assert_eq!(virtual_gpus.claim(2, 0), Err(Taken { ordinal: 0, session_id: 3 }));
+        virtual_gpus.end_session(3);
+        virtual_gpus.claim(2, 0).unwrap();
+    }
+
+    #[test]
+    fn nothing_split_is_identity() {
+        let virtual_gpus = VirtualGpus::from_config(&[], &[gpu(0, 8 << 30)]);
+        assert_eq!(virtual_gpus.count(), None);
+        assert_eq!(virtual_gpus.resolve(5), Some((5, None)));
+    }
+}
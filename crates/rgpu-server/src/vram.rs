@@ -0,0 +1,338 @@
+//! Per-device VRAM accounting shared by the CUDA and Vulkan executors.
+//!
+//! A session that uses both APIs on the same GPU draws from a single budget:
+//! allocations are charged per session and physical device (identified by
+//! its UUID, which CUDA and Vulkan report identically), and the session's
+//! quota applies to the CUDA and Vulkan totals combined. A session can also
+//! have a limit across all GPUs, from the `max_memory` of its token.
+
+use std::collections::HashMap;
+
+use dashmap::DashMap;
+
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::messages::DeviceVramUsage;
+
+/// UUID of a physical GPU.
+pub type DeviceUuid = [u8; 16];
+
+/// Which executor an allocation came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Api {
+    Cuda,
+    Vulkan,
+}
+
+/// VRAM charged to a session for one allocation.
+#[derive(Debug, Clone, Copy)]
+pub struct Charge {
+    pub session_id: u32,
+    pub device: DeviceUuid,
+    pub api: Api,
+    pub bytes: u64,
+}
+
+/// Allocation would take the session past its quota on the device or its
+/// limit across devices.
+#[derive(Debug, Clone, Copy)]
+pub struct QuotaExceeded {
+    pub used: u64,
+    pub quota: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Usage {
+    cuda: u64,
+    vulkan: u64,
+}
+
+impl Usage {
+    fn total(&self) -> u64 {
+        self.cuda + self.vulkan
+    }
+
+    fn slot(&mut self, api: Api) -> &mut u64 {
+        match api {
+            Api::Cuda => &mut self.cuda,
+            Api::Vulkan => &mut self.vulkan,
+        }
+    }
+}
+
+/// A session's limit across devices and how often it was hit.
+#[derive(Debug, Default, Clone, Copy)]
+struct SessionLimit {
+    /// Bytes on all devices together (0 = unlimited)
+    quota: u64,
+    /// Allocations refused for going over a quota
+    refused: u64,
+}
+
+pub struct VramLedger {
+    /// Server-side index of each GPU, for reporting
+    device_indices: HashMap<DeviceUuid, u32>,
+    /// Per-session, per-device bytes (0 = unlimited)
+    quota: u64,
+    /// Tighter quotas of memory-partitioned GPUs
+    device_quotas: HashMap<DeviceUuid, u64>,
+    /// Quotas of sessions holding virtual GPUs, by session and device
+    session_device_quotas: parking_lot::Mutex<HashMap<(u32, DeviceUuid), u64>>,
+    usage: parking_lot::Mutex<HashMap<(u32, DeviceUuid), Usage>>,
+    limits: parking_lot::Mutex<HashMap<u32, SessionLimit>>,
+    /// Charges of live allocations, released when they are freed
+    allocations: DashMap<NetworkHandle, Charge>,
+}
+
+impl VramLedger {
+    /// `devices` are the GPU UUIDs in server device index order.
+    pub fn new(devices: Vec<DeviceUuid>, quota: u64) -> Self {
+        Self {
+            device_indices: devices
+                .into_iter()
+                .enumerate()
+                .map(|(i, uuid)| (uuid, i as u32))
+                .collect(),
+            quota,
+            device_quotas: HashMap::new(),
+            session_device_quotas: parking_lot::Mutex::new(HashMap::new()),
+            usage: parking_lot::Mutex::new(HashMap::new()),
+            limits: parking_lot::Mutex::new(HashMap::new()),
+            allocations: DashMap::new(),
+        }
+    }
+
+    /// A ledger that only counts.
+    pub fn unlimited() -> Self {
+        Self::new(Vec::new(), 0)
+    }
+
+    /// Per-session quotas for some devices; the lower of these and the
+    /// server-wide quota applies.
+    pub fn with_device_quotas(mut self, quotas: HashMap<DeviceUuid, u64>) -> Self {
+        self.device_quotas = quotas;
+        self
+    }
+
+    /// Bytes the session may have on `device` (0 = unlimited).
+    fn quota_for(&self, session_id: u32, device: &DeviceUuid) -> u64 {
+        let session_quota = self.session_device_quotas.lock().get(&(session_id, *device)).copied();
+        [self.device_quotas.get(device).copied(), session_quota, Some(self.quota)]
+            .into_iter()
+            .flatten()
+            .filter(|&quota| quota > 0)
+            .min()
+            .unwrap_or(0)
+    }
+
+    /// Limit a session to `bytes` on `device`, for the virtual GPU it holds
+    /// there. A session holding several virtual GPUs of one device gets
+    /// their memory together.
+    pub fn add_session_device_quota(&self, session_id: u32, device: DeviceUuid, bytes: u64) {
+        *self.session_device_quotas.lock().entry((session_id, device)).or_default() += bytes;
+    }
+
+    /// The session's bytes in use on `device` and its own quota there, if
+    /// it holds a virtual GPU of it.
+    pub fn session_device_quota(&self, session_id: u32, device: &DeviceUuid) -> Option<(u64, u64)> {
+        let quota = self.session_device_quotas.lock().get(&(session_id, *device)).copied()?;
+        let used = self.usage.lock().get(&(session_id, *device)).map_or(0, Usage::total);
+        Some((used, quota))
+    }
+
+    /// Limit a session to `bytes` on all devices together (0 = unlimited).
+    pub fn set_session_quota(&self, session_id: u32, bytes: u64) {
+        self.limits.lock().entry(session_id).or_default().quota = bytes;
+    }
+
+    /// Forget a disconnected session's limit.
+    pub fn end_session(&self, session_id: u32) {
+        self.limits.lock().remove(&session_id);
+    }
+
+    /// The session's quota across devices (0 = unlimited) and how many of
+    /// its allocations were refused.
+    pub fn session_limit(&self, session_id: u32) -> (u64, u64) {
+        let limit = self.limits.lock().get(&session_id).copied().unwrap_or_default();
+        (limit.quota, limit.refused)
+    }
+
+    /// Charge an allocation that is about to be made.
+    pub fn try_charge(&self, charge: &Charge) -> Result<(), QuotaExceeded> {
+        let quota = self.quota_for(charge.session_id, &charge.device);
+        let mut usage = self.usage.lock();
+        let session_used: u64 = usage
+            .iter()
+            .filter(|((session, _), _)| *session == charge.session_id)
+            .map(|(_, usage)| usage.total())
+            .sum();
+        let mut limits = self.limits.lock();
+        let limit = limits.entry(charge.session_id).or_default();
+        let entry = usage.entry((charge.session_id, charge.device)).or_default();
+        let exceeded = if quota > 0 && entry.total().saturating_add(charge.bytes) > quota {
+            Some(QuotaExceeded {
+                used: entry.total(),
+                quota,
+            })
+        } else if limit.quota > 0 && session_used.saturating_add(charge.bytes) > limit.quota {
+            Some(QuotaExceeded {
+                used: session_used,
+                quota: limit.quota,
+            })
+        } else {
+            None
+        };
+        if let Some(exceeded) = exceeded {
+            limit.refused += 1;
+            return Err(exceeded);
+        }
+        *entry.slot(charge.api) += charge.bytes;
+        Ok(())
+    }
+
+    /// Undo a charge whose allocation failed.
+    pub fn uncharge(&self, charge: &Charge) {
+        let mut usage = self.usage.lock();
+        if let Some(entry) = usage.get_mut(&(charge.session_id, charge.device)) {
+            let slot = entry.slot(charge.api);
+            *slot = slot.saturating_sub(charge.bytes);
+            if entry.total() == 0 {
+                usage.remove(&(charge.session_id, charge.device));
+            }
+        }
+    }
+
+    /// Remember the charge of a successful allocation until it is freed.
+    pub fn track(&self, handle: NetworkHandle, charge: Charge) {
+        self.allocations.insert(handle, charge);
+    }
+
+    /// Release the charge of a freed allocation.
+    pub fn free(&self, handle: &NetworkHandle) {
+        if let Some((_, charge)) = self.allocations.remove(handle) {
+            self.uncharge(&charge);
+        }
+    }
+
+    /// Drop every charge a session still has, for allocations whose objects
+    /// were torn down without going through [`Self::free`].
+    pub fn release_session(&self, session_id: u32) {
+        self.allocations.retain(|_, charge| charge.session_id != session_id);
+        self.usage.lock().retain(|(session, _), _| *session != session_id);
+        self.session_device_quotas.lock().retain(|(session, _), _| *session != session_id);
+    }
+
+    /// Move a resumed session's charges to the session that took it over.
+    pub fn transfer_session(&self, from: u32, to: u32) {
+        for mut allocation in self.allocations.iter_mut() {
+            if allocation.session_id == from {
+                allocation.session_id = to;
+            }
+        }
+        let mut quotas = self.session_device_quotas.lock();
+        let moved: Vec<_> = quotas.keys().filter(|(session, _)| *session == from).copied().collect();
+        for key in moved {
+            if let Some(quota) = quotas.remove(&key) {
+                quotas.insert((to, key.1), quota);
+            }
+        }
+        drop(quotas);
+        let mut usage = self.usage.lock();
+        let moved: Vec<_> = usage.keys().filter(|(session, _)| *session == from).copied().collect();
+        for key in moved {
+            if let Some(charged) = usage.remove(&key) {
+                let entry = usage.entry((to, key.1)).or_default();
+                entry.cuda += charged.cuda;
+                entry.vulkan += charged.vulkan;
+            }
+        }
+    }
+
+    /// VRAM in use by all sessions on a device.
+    pub fn device_usage(&self, device: &DeviceUuid) -> u64 {
+        let usage = self.usage.lock();
+        usage
+            .iter()
+            .filter(|((_, d), _)| d == device)
+            .map(|(_, usage)| usage.total())
+            .sum()
+    }
+
+    /// VRAM in use by a session, per device.
+    pub fn session_usage(&self, session_id: u32) -> Vec<DeviceVramUsage> {
+        let usage = self.usage.lock();
+        let mut devices: Vec<DeviceVramUsage> = usage
+            .iter()
+            .filter(|((session, _), _)| *session == session_id)
+            .map(|((_, device), usage)| DeviceVramUsage {
+                device_index: self.device_indices.get(device).copied().unwrap_or(u32::MAX),
+                cuda_bytes: usage.cuda,
+                vulkan_bytes: usage.vulkan,
+            })
+            .collect();
+        devices.sort_by_key(|d| d.device_index);
+        devices
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn charge(session_id: u32, device: u8, api: Api, bytes: u64) -> Charge {
+        Charge {
+            session_id,
+            device: [device; 16],
+            api,
+            bytes,
+        }
+    }
+
+    #[test]
+    fn session_quota_spans_devices_and_counts_refusals() {
+        let ledger = VramLedger::new(vec![[1; 16], [2; 16]], 0);
+        ledger.set_session_quota(7, 1000);
+
+        ledger.try_charge(&charge(7, 1, Api::Cuda, 600)).unwrap();
+        let refused = ledger.try_charge(&charge(7, 2, Api::Vulkan, 500)).unwrap_err();
+        assert_eq!((refused.used, refused.quota), (600, 1000));
+        ledger.try_charge(&charge(7, 2, Api::Vulkan, 400)).unwrap();
+        assert_eq!(ledger.session_limit(7), (1000, 1));
+
+        // Other sessions aren't limited, and freeing makes room again.
+        ledger.try_charge(&charge(8, 1, Api::Cuda, 5000)).unwrap();
+        ledger.uncharge(&charge(7, 1, Api::Cuda, 600));
+        ledger.try_charge(&charge(7, 1, Api::Cuda, 600)).unwrap();
+
+        ledger.end_session(7);
+        assert_eq!(ledger.session_limit(7), (0, 0));
+    }
+
+    #[test]
+    fn released_session_returns_to_baseline() {
+        let ledger = VramLedger::unlimited();
+        let handle = |session_id, resource_id| NetworkHandle {
+            server_id: 0,
+            session_id,
+            resource_id,
+            resource_type: rgpu_protocol::handle::ResourceType::CuDevicePtr,
+        };
+        let other = charge(2, 1, Api::Cuda, 300);
+        ledger.try_charge(&other).unwrap();
+        ledger.track(handle(2, 1), other);
+        let baseline = ledger.device_usage(&[1; 16]);
+
+        for (id, api) in [(1, Api::Cuda), (2, Api::Vulkan)] {
+            let leaked = charge(1, 1, api, 1000);
+            ledger.try_charge(&leaked).unwrap();
+            ledger.track(handle(1, id), leaked);
+        }
+        assert_eq!(ledger.device_usage(&[1; 16]), baseline + 2000);
+
+        ledger.release_session(1);
+        assert_eq!(ledger.device_usage(&[1; 16]), baseline);
+        assert!(ledger.session_usage(1).is_empty());
+        // A late free of a released allocation changes nothing.
+        ledger.free(&handle(1, 1));
+        assert_eq!(ledger.device_usage(&[1; 16]), baseline);
+    }
+}
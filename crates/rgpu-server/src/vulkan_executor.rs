@@ -1,14 +1,21 @@
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use ash::vk;
 use dashmap::DashMap;
 use tracing::{debug, info, warn};
 
+use rgpu_protocol::frame::{self, FrameEncoding};
 use rgpu_protocol::handle::{NetworkHandle, ResourceType};
 use rgpu_protocol::vulkan_commands::*;
 
+use crate::content_store::ContentStore;
+use crate::device_overrides::DeviceOverrides;
+use crate::virtual_gpus::{Slice, VirtualGpus};
+use crate::scheduling::Scheduler;
 use crate::session::Session;
+use crate::vram::{Api, Charge, DeviceUuid, VramLedger};
 
 /// Server-side Vulkan command executor.
 /// Executes Vulkan commands on real GPU hardware via `ash`.
@@ -25,11 +32,14 @@ pub struct VulkanExecutor {
     device_wrappers: DashMap<NetworkHandle, ash::Device>,
     device_to_instance: DashMap<NetworkHandle, NetworkHandle>,
     queue_handles: DashMap<NetworkHandle, vk::Queue>,
+    queue_to_device: DashMap<NetworkHandle, NetworkHandle>,
     memory_handles: DashMap<NetworkHandle, vk::DeviceMemory>,
     memory_to_device: DashMap<NetworkHandle, NetworkHandle>,
     memory_info: DashMap<NetworkHandle, MappedMemoryInfo>,
     buffer_handles: DashMap<NetworkHandle, vk::Buffer>,
     buffer_to_device: DashMap<NetworkHandle, NetworkHandle>,
+    buffer_view_handles: DashMap<NetworkHandle, vk::BufferView>,
+    buffer_view_to_device: DashMap<NetworkHandle, NetworkHandle>,
     shader_module_handles: DashMap<NetworkHandle, vk::ShaderModule>,
     shader_to_device: DashMap<NetworkHandle, NetworkHandle>,
     desc_set_layout_handles: DashMap<NetworkHandle, vk::DescriptorSetLayout>,
@@ -41,10 +51,14 @@ pub struct VulkanExecutor {
     desc_pool_handles: DashMap<NetworkHandle, vk::DescriptorPool>,
     desc_pool_to_device: DashMap<NetworkHandle, NetworkHandle>,
     desc_set_handles: DashMap<NetworkHandle, vk::DescriptorSet>,
+    /// Pool each descriptor set came from, so destroying the pool forgets them
+    desc_set_to_pool: DashMap<NetworkHandle, NetworkHandle>,
     command_pool_handles: DashMap<NetworkHandle, vk::CommandPool>,
     command_pool_to_device: DashMap<NetworkHandle, NetworkHandle>,
     command_buffer_handles: DashMap<NetworkHandle, vk::CommandBuffer>,
     command_buffer_to_device: DashMap<NetworkHandle, NetworkHandle>,
+    /// Pool each command buffer came from, like `desc_set_to_pool`
+    command_buffer_to_pool: DashMap<NetworkHandle, NetworkHandle>,
     fence_handles: DashMap<NetworkHandle, vk::Fence>,
     fence_to_device: DashMap<NetworkHandle, NetworkHandle>,
     image_handles: DashMap<NetworkHandle, vk::Image>,
@@ -57,6 +71,485 @@ pub struct VulkanExecutor {
     framebuffer_to_device: DashMap<NetworkHandle, NetworkHandle>,
     semaphore_handles: DashMap<NetworkHandle, vk::Semaphore>,
     semaphore_to_device: DashMap<NetworkHandle, NetworkHandle>,
+    query_pool_handles: DashMap<NetworkHandle, vk::QueryPool>,
+    query_pool_to_device: DashMap<NetworkHandle, NetworkHandle>,
+    event_handles: DashMap<NetworkHandle, vk::Event>,
+    event_to_device: DashMap<NetworkHandle, NetworkHandle>,
+    sampler_handles: DashMap<NetworkHandle, vk::Sampler>,
+    sampler_to_device: DashMap<NetworkHandle, NetworkHandle>,
+    pipeline_cache_handles: DashMap<NetworkHandle, vk::PipelineCache>,
+    pipeline_cache_to_device: DashMap<NetworkHandle, NetworkHandle>,
+    /// The server's own pipeline cache of each logical device, kept on disk
+    /// across runs
+    device_pipeline_caches: DashMap<NetworkHandle, DevicePipelineCache>,
+    /// Where device pipeline caches are saved, as `<device UUID>.bin`; they
+    /// only last as long as their device without one
+    pipeline_cache_dir: Option<PathBuf>,
+    /// SPIR-V of the shader modules created, for `CreateShaderModuleByHash`
+    shader_code: ContentStore,
+    /// Which GPU each logical device is on, for VRAM accounting
+    device_vram: DashMap<NetworkHandle, DeviceVram>,
+    /// Physical device and first queue family of each logical device, for
+    /// what the executor allocates itself
+    device_physical: DashMap<NetworkHandle, (vk::PhysicalDevice, u32)>,
+    swapchains: DashMap<NetworkHandle, VirtualSwapchain>,
+    /// Timeline semaphore entry points of the devices that have them
+    timeline_semaphores: DashMap<NetworkHandle, ash::khr::timeline_semaphore::Device>,
+    /// Synchronization2 entry points of the devices that have them
+    synchronization2: DashMap<NetworkHandle, ash::khr::synchronization2::Device>,
+    /// Dynamic rendering entry points of the devices that have them
+    dynamic_rendering: DashMap<NetworkHandle, ash::khr::dynamic_rendering::Device>,
+    /// Per-device VRAM accounting, shared with the CUDA executor
+    vram: Arc<VramLedger>,
+    /// Which sessions may use each GPU, shared with the CUDA executor
+    scheduler: Arc<Scheduler>,
+    /// What the GPUs report in place of the driver's answers, shared with
+    /// the CUDA executor
+    overrides: Arc<DeviceOverrides>,
+    /// GPUs shown to clients as several, shared with the CUDA executor
+    virtual_gpus: Arc<VirtualGpus>,
+    /// Ordinal and slice of the physical device handles naming virtual GPUs
+    virtual_physical_devices: DashMap<NetworkHandle, (u32, Slice)>,
+}
+
+/// What VRAM accounting needs to know about a logical device.
+#[derive(Clone, Copy)]
+struct DeviceVram {
+    uuid: DeviceUuid,
+    /// Bit per memory type index that is device-local
+    local_types: u32,
+}
+
+/// A device's server-side pipeline cache, and the file it is saved to.
+struct DevicePipelineCache {
+    cache: vk::PipelineCache,
+    path: Option<PathBuf>,
+}
+
+/// sType and pNext, which come before the members of every structure in a
+/// pNext chain.
+const CHAIN_HEADER: usize = std::mem::size_of::<vk::BaseOutStructure<'static>>();
+
+/// A pNext chain rebuilt from the wire, each structure in an allocation of
+/// its own so the links stay valid.
+#[derive(Default)]
+struct PNextChain {
+    structs: Vec<Vec<u64>>,
+    /// Bytes after the header in each structure
+    lens: Vec<usize>,
+    /// Arrays the structures point to
+    formats: Vec<Vec<vk::Format>>,
+}
+
+impl PNextChain {
+    fn new(chain: &[SerializedPNext]) -> Self {
+        let mut this = Self::default();
+        for s in chain {
+            match s {
+                SerializedPNext::Raw { s_type, data } => this.push_raw(*s_type, data),
+                SerializedPNext::ImageFormatList { view_formats } => {
+                    let formats: Vec<vk::Format> = view_formats.iter().map(|&f| vk::Format::from_raw(f)).collect();
+                    this.push(&vk::ImageFormatListCreateInfo::default().view_formats(&formats));
+                    // The heap buffer stays put when the Vec moves
+                    this.formats.push(formats);
+                }
+            }
+        }
+        this.link();
+        this
+    }
+
+    fn from_features(chain: &[SerializedFeatureStruct]) -> Self {
+        let mut this = Self::default();
+        for s in chain {
+            this.push_raw(s.s_type, &s.data);
+        }
+        this.link();
+        this
+    }
+
+    fn push_raw(&mut self, s_type: i32, data: &[u8]) {
+        let mut buf = vec![0u64; (CHAIN_HEADER + data.len()).div_ceil(8)];
+        unsafe {
+            let base = buf.as_mut_ptr() as *mut u8;
+            (*(base as *mut vk::BaseOutStructure)).s_type = vk::StructureType::from_raw(s_type);
+            std::ptr::copy_nonoverlapping(data.as_ptr(), base.add(CHAIN_HEADER), data.len());
+        }
+        self.structs.push(buf);
+        self.lens.push(data.len());
+    }
+
+    /// Adds a copy of `value`, whose sType is already set.
+    fn push<T>(&mut self, value: &T) {
+        let size = std::mem::size_of::<T>();
+        let mut buf = vec![0u64; size.div_ceil(8)];
+        unsafe {
+            std::ptr::copy_nonoverlapping(value as *const T as *const u8, buf.as_mut_ptr() as *mut u8, size);
+        }
+        self.structs.push(buf);
+        self.lens.push(size - CHAIN_HEADER);
+    }
+
+    fn link(&mut self) {
+        let ptrs: Vec<*mut vk::BaseOutStructure> =
+            self.structs.iter_mut().map(|buf| buf.as_mut_ptr() as *mut vk::BaseOutStructure).collect();
+        for link in ptrs.windows(2) {
+            unsafe { (*link[0]).p_next = link[1] };
+        }
+    }
+
+    /// First structure, for a pNext member; null when empty.
+    fn head(&mut self) -> *mut std::ffi::c_void {
+        self.structs
+            .first_mut()
+            .map_or(std::ptr::null_mut(), |buf| buf.as_mut_ptr() as *mut std::ffi::c_void)
+    }
+
+    fn s_type(buf: &[u64]) -> vk::StructureType {
+        unsafe { (*(buf.as_ptr() as *const vk::BaseOutStructure)).s_type }
+    }
+
+    /// The structure of type `s_type`, if it is chained and big enough to
+    /// be a `T`.
+    fn find<T>(&mut self, s_type: vk::StructureType) -> Option<&mut T> {
+        self.structs
+            .iter_mut()
+            .zip(&self.lens)
+            .find(|(buf, len)| Self::s_type(buf) == s_type && CHAIN_HEADER + **len >= std::mem::size_of::<T>())
+            .map(|(buf, _)| unsafe { &mut *(buf.as_mut_ptr() as *mut T) })
+    }
+
+    fn serialize(&self) -> Vec<SerializedFeatureStruct> {
+        self.structs
+            .iter()
+            .zip(&self.lens)
+            .map(|(buf, len)| SerializedFeatureStruct {
+                s_type: Self::s_type(buf).as_raw(),
+                data: unsafe { std::slice::from_raw_parts((buf.as_ptr() as *const u8).add(CHAIN_HEADER), *len) }
+                    .to_vec(),
+            })
+            .collect()
+    }
+}
+
+/// Formats a virtual swapchain can have: four bytes per pixel, which the
+/// client converts to what its window system takes.
+const SWAPCHAIN_FORMATS: [vk::Format; 4] = [
+    vk::Format::B8G8R8A8_UNORM,
+    vk::Format::B8G8R8A8_SRGB,
+    vk::Format::R8G8B8A8_UNORM,
+    vk::Format::R8G8B8A8_SRGB,
+];
+const MAX_SWAPCHAIN_IMAGES: u32 = 8;
+
+/// Bytes of SPIR-V kept for `CreateShaderModuleByHash`.
+const SHADER_CODE_BUDGET: usize = 64 * 1024 * 1024;
+
+/// Extensions clients implement themselves: window system surfaces, and
+/// swapchains, which are virtual here. They are never enabled on the driver.
+const CLIENT_EXTENSIONS: [&CStr; 6] = [
+    ash::khr::surface::NAME,
+    ash::khr::xcb_surface::NAME,
+    ash::khr::xlib_surface::NAME,
+    ash::khr::wayland_surface::NAME,
+    ash::khr::win32_surface::NAME,
+    ash::khr::swapchain::NAME,
+];
+
+/// The extensions of `requested` to enable on the driver, leaving out the
+/// clients' own. The first the driver doesn't have is the error.
+fn driver_extensions(requested: &[String], available: &[vk::ExtensionProperties]) -> Result<Vec<CString>, String> {
+    let mut extensions = Vec::new();
+    for name in requested {
+        let Ok(c_name) = CString::new(name.as_str()) else {
+            return Err(name.clone());
+        };
+        if CLIENT_EXTENSIONS.contains(&c_name.as_c_str()) {
+            continue;
+        }
+        if !available.iter().any(|e| e.extension_name_as_c_str() == Ok(c_name.as_c_str())) {
+            return Err(name.clone());
+        }
+        if !extensions.contains(&c_name) {
+            extensions.push(c_name);
+        }
+    }
+    Ok(extensions)
+}
+
+/// A swapchain kept in place of a window system's: images the application
+/// renders into, and a host-visible buffer each presented image is copied to
+/// for sending to the client.
+struct VirtualSwapchain {
+    device: NetworkHandle,
+    handles: Vec<NetworkHandle>,
+    images: Vec<vk::Image>,
+    image_memory: Vec<vk::DeviceMemory>,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    queue: vk::Queue,
+    command_pool: vk::CommandPool,
+    command_buffer: vk::CommandBuffer,
+    fence: vk::Fence,
+    readback: vk::Buffer,
+    readback_memory: vk::DeviceMemory,
+    readback_ptr: *const u8,
+    readback_coherent: bool,
+    /// Image the next acquire hands out
+    next_image: u32,
+    /// Last frame sent, what the next delta is taken against
+    previous: Option<Vec<u8>>,
+}
+
+impl VirtualSwapchain {
+    fn frame_size(&self) -> u64 {
+        self.extent.width as u64 * self.extent.height as u64 * 4
+    }
+
+    /// Create the images, the readback buffer and what copying between them
+    /// takes. Returns the bytes of image memory allocated. On error, what was
+    /// created so far is left for [`VirtualSwapchain::destroy`].
+    unsafe fn build(
+        &mut self,
+        dev: &ash::Device,
+        mem_props: &vk::PhysicalDeviceMemoryProperties,
+        image_count: u32,
+        usage: vk::ImageUsageFlags,
+        queue_family: u32,
+    ) -> Result<u64, vk::Result> {
+        let mut image_bytes = 0;
+        for _ in 0..image_count {
+            let image_ci = vk::ImageCreateInfo::default()
+                .image_type(vk::ImageType::TYPE_2D)
+                .format(self.format)
+                .extent(vk::Extent3D {
+                    width: self.extent.width,
+                    height: self.extent.height,
+                    depth: 1,
+                })
+                .mip_levels(1)
+                .array_layers(1)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .tiling(vk::ImageTiling::OPTIMAL)
+                .usage(usage | vk::ImageUsageFlags::TRANSFER_SRC)
+                .sharing_mode(vk::SharingMode::EXCLUSIVE)
+                .initial_layout(vk::ImageLayout::UNDEFINED);
+            let image = dev.create_image(&image_ci, None)?;
+            self.images.push(image);
+            let reqs = dev.get_image_memory_requirements(image);
+            let type_index = memory_type(mem_props, reqs.memory_type_bits, vk::MemoryPropertyFlags::DEVICE_LOCAL)
+                .or_else(|| memory_type(mem_props, reqs.memory_type_bits, vk::MemoryPropertyFlags::empty()))
+                .ok_or(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY)?;
+            let alloc_info = vk::MemoryAllocateInfo::default()
+                .allocation_size(reqs.size)
+                .memory_type_index(type_index);
+            let memory = dev.allocate_memory(&alloc_info, None)?;
+            self.image_memory.push(memory);
+            dev.bind_image_memory(image, memory, 0)?;
+            image_bytes += reqs.size;
+        }
+
+        let buffer_ci = vk::BufferCreateInfo::default()
+            .size(self.frame_size())
+            .usage(vk::BufferUsageFlags::TRANSFER_DST)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        self.readback = dev.create_buffer(&buffer_ci, None)?;
+        let reqs = dev.get_buffer_memory_requirements(self.readback);
+        let visible = vk::MemoryPropertyFlags::HOST_VISIBLE;
+        let coherent = visible | vk::MemoryPropertyFlags::HOST_COHERENT;
+        let type_index = memory_type(mem_props, reqs.memory_type_bits, coherent | vk::MemoryPropertyFlags::HOST_CACHED)
+            .or_else(|| memory_type(mem_props, reqs.memory_type_bits, coherent))
+            .or_else(|| memory_type(mem_props, reqs.memory_type_bits, visible))
+            .ok_or(vk::Result::ERROR_OUT_OF_HOST_MEMORY)?;
+        self.readback_coherent = mem_props.memory_types[type_index as usize]
+            .property_flags
+            .contains(vk::MemoryPropertyFlags::HOST_COHERENT);
+        let alloc_info = vk::MemoryAllocateInfo::default()
+            .allocation_size(reqs.size)
+            .memory_type_index(type_index);
+        self.readback_memory = dev.allocate_memory(&alloc_info, None)?;
+        dev.bind_buffer_memory(self.readback, self.readback_memory, 0)?;
+        self.readback_ptr = dev.map_memory(self.readback_memory, 0, vk::WHOLE_SIZE, vk::MemoryMapFlags::empty())? as *const u8;
+
+        let pool_ci = vk::CommandPoolCreateInfo::default()
+            .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
+            .queue_family_index(queue_family);
+        self.command_pool = dev.create_command_pool(&pool_ci, None)?;
+        let alloc_info = vk::CommandBufferAllocateInfo::default()
+            .command_pool(self.command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY)
+            .command_buffer_count(1);
+        self.command_buffer = dev.allocate_command_buffers(&alloc_info)?[0];
+        self.fence = dev.create_fence(&vk::FenceCreateInfo::default(), None)?;
+        self.queue = dev.get_device_queue(queue_family, 0);
+        Ok(image_bytes)
+    }
+
+    /// Copy `image` to the readback buffer once `waits` are signaled, and
+    /// return its pixels. The image is back in the present layout after.
+    unsafe fn read_back(&self, dev: &ash::Device, image: vk::Image, waits: &[vk::Semaphore]) -> Result<Vec<u8>, vk::Result> {
+        let cb = self.command_buffer;
+        let range = vk::ImageSubresourceRange::default()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .level_count(1)
+            .layer_count(1);
+        dev.reset_command_buffer(cb, vk::CommandBufferResetFlags::empty())?;
+        let begin = vk::CommandBufferBeginInfo::default().flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+        dev.begin_command_buffer(cb, &begin)?;
+
+        let to_transfer = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::MEMORY_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .old_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(range);
+        dev.cmd_pipeline_barrier(
+            cb,
+            vk::PipelineStageFlags::ALL_COMMANDS,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[],
+            &[to_transfer],
+        );
+        let copy = vk::BufferImageCopy::default()
+            .image_subresource(
+                vk::ImageSubresourceLayers::default()
+                    .aspect_mask(vk::ImageAspectFlags::COLOR)
+                    .layer_count(1),
+            )
+            .image_extent(vk::Extent3D {
+                width: self.extent.width,
+                height: self.extent.height,
+                depth: 1,
+            });
+        dev.cmd_copy_image_to_buffer(cb, image, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, self.readback, &[copy]);
+        let to_present = vk::ImageMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(range);
+        let to_host = vk::BufferMemoryBarrier::default()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(self.readback)
+            .size(vk::WHOLE_SIZE);
+        dev.cmd_pipeline_barrier(
+            cb,
+            vk::PipelineStageFlags::TRANSFER,
+            vk::PipelineStageFlags::HOST | vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+            vk::DependencyFlags::empty(),
+            &[],
+            &[to_host],
+            &[to_present],
+        );
+        dev.end_command_buffer(cb)?;
+
+        let stages = vec![vk::PipelineStageFlags::ALL_COMMANDS; waits.len()];
+        let command_buffers = [cb];
+        let submit = vk::SubmitInfo::default()
+            .wait_semaphores(waits)
+            .wait_dst_stage_mask(&stages)
+            .command_buffers(&command_buffers);
+        dev.queue_submit(self.queue, &[submit], self.fence)?;
+        dev.wait_for_fences(&[self.fence], true, u64::MAX)?;
+        dev.reset_fences(&[self.fence])?;
+
+        if !self.readback_coherent {
+            let range = vk::MappedMemoryRange::default()
+                .memory(self.readback_memory)
+                .size(vk::WHOLE_SIZE);
+            dev.invalidate_mapped_memory_ranges(&[range])?;
+        }
+        Ok(std::slice::from_raw_parts(self.readback_ptr, self.frame_size() as usize).to_vec())
+    }
+
+    /// Destroy everything [`VirtualSwapchain::build`] created.
+    unsafe fn destroy(&self, dev: &ash::Device) {
+        dev.destroy_fence(self.fence, None);
+        dev.destroy_command_pool(self.command_pool, None);
+        dev.destroy_buffer(self.readback, None);
+        dev.free_memory(self.readback_memory, None);
+        for image in &self.images {
+            dev.destroy_image(*image, None);
+        }
+        for memory in &self.image_memory {
+            dev.free_memory(*memory, None);
+        }
+    }
+}
+
+fn subresource_layers(s: &SerializedImageSubresourceLayers) -> vk::ImageSubresourceLayers {
+    vk::ImageSubresourceLayers {
+        aspect_mask: vk::ImageAspectFlags::from_raw(s.aspect_mask),
+        mip_level: s.mip_level,
+        base_array_layer: s.base_array_layer,
+        layer_count: s.layer_count,
+    }
+}
+
+/// The barriers of a VkDependencyInfo, kept alive while one points to them.
+struct Barriers2 {
+    dependency_flags: vk::DependencyFlags,
+    memory: Vec<vk::MemoryBarrier2<'static>>,
+    buffer: Vec<vk::BufferMemoryBarrier2<'static>>,
+    image: Vec<vk::ImageMemoryBarrier2<'static>>,
+}
+
+impl Barriers2 {
+    fn dependency_info(&self) -> vk::DependencyInfo<'_> {
+        vk::DependencyInfo::default()
+            .dependency_flags(self.dependency_flags)
+            .memory_barriers(&self.memory)
+            .buffer_memory_barriers(&self.buffer)
+            .image_memory_barriers(&self.image)
+    }
+}
+
+fn memory_barriers_of(barriers: &[SerializedMemoryBarrier]) -> Vec<vk::MemoryBarrier<'static>> {
+    barriers
+        .iter()
+        .map(|mb| {
+            vk::MemoryBarrier::default()
+                .src_access_mask(vk::AccessFlags::from_raw(mb.src_access_mask))
+                .dst_access_mask(vk::AccessFlags::from_raw(mb.dst_access_mask))
+        })
+        .collect()
+}
+
+fn offset_3d(o: &[i32; 3]) -> vk::Offset3D {
+    vk::Offset3D { x: o[0], y: o[1], z: o[2] }
+}
+
+fn extent_3d(e: &[u32; 3]) -> vk::Extent3D {
+    vk::Extent3D {
+        width: e[0],
+        height: e[1],
+        depth: e[2],
+    }
+}
+
+/// Forget the children of a destroyed pool: the handles `to_pool` maps to it.
+fn pool_children(to_pool: &DashMap<NetworkHandle, NetworkHandle>, pool: &NetworkHandle) -> Vec<NetworkHandle> {
+    let children: Vec<NetworkHandle> = to_pool.iter().filter(|e| e.value() == pool).map(|e| *e.key()).collect();
+    for child in &children {
+        to_pool.remove(child);
+    }
+    children
+}
+
+/// First memory type among `type_bits` with all of `flags`.
+fn memory_type(props: &vk::PhysicalDeviceMemoryProperties, type_bits: u32, flags: vk::MemoryPropertyFlags) -> Option<u32> {
+    (0..props.memory_type_count)
+        .find(|&i| type_bits & (1 << i) != 0 && props.memory_types[i as usize].property_flags.contains(flags))
 }
 
 struct MappedMemoryInfo {
@@ -69,6 +562,12 @@ struct MappedMemoryInfo {
 unsafe impl Send for VulkanExecutor {}
 unsafe impl Sync for VulkanExecutor {}
 
+impl Default for VulkanExecutor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl VulkanExecutor {
     pub fn new() -> Self {
         let entry = match unsafe { ash::Entry::load() } {
@@ -91,11 +590,14 @@ impl VulkanExecutor {
             device_wrappers: DashMap::new(),
             device_to_instance: DashMap::new(),
             queue_handles: DashMap::new(),
+            queue_to_device: DashMap::new(),
             memory_handles: DashMap::new(),
             memory_to_device: DashMap::new(),
             memory_info: DashMap::new(),
             buffer_handles: DashMap::new(),
             buffer_to_device: DashMap::new(),
+            buffer_view_handles: DashMap::new(),
+            buffer_view_to_device: DashMap::new(),
             shader_module_handles: DashMap::new(),
             shader_to_device: DashMap::new(),
             desc_set_layout_handles: DashMap::new(),
@@ -107,10 +609,12 @@ impl VulkanExecutor {
             desc_pool_handles: DashMap::new(),
             desc_pool_to_device: DashMap::new(),
             desc_set_handles: DashMap::new(),
+            desc_set_to_pool: DashMap::new(),
             command_pool_handles: DashMap::new(),
             command_pool_to_device: DashMap::new(),
             command_buffer_handles: DashMap::new(),
             command_buffer_to_device: DashMap::new(),
+            command_buffer_to_pool: DashMap::new(),
             fence_handles: DashMap::new(),
             fence_to_device: DashMap::new(),
             image_handles: DashMap::new(),
@@ -123,6 +627,208 @@ impl VulkanExecutor {
             framebuffer_to_device: DashMap::new(),
             semaphore_handles: DashMap::new(),
             semaphore_to_device: DashMap::new(),
+            query_pool_handles: DashMap::new(),
+            query_pool_to_device: DashMap::new(),
+            event_handles: DashMap::new(),
+            event_to_device: DashMap::new(),
+            sampler_handles: DashMap::new(),
+            sampler_to_device: DashMap::new(),
+            pipeline_cache_handles: DashMap::new(),
+            pipeline_cache_to_device: DashMap::new(),
+            device_pipeline_caches: DashMap::new(),
+            pipeline_cache_dir: None,
+            shader_code: ContentStore::new(SHADER_CODE_BUDGET),
+            device_vram: DashMap::new(),
+            device_physical: DashMap::new(),
+            timeline_semaphores: DashMap::new(),
+            synchronization2: DashMap::new(),
+            dynamic_rendering: DashMap::new(),
+            swapchains: DashMap::new(),
+            vram: Arc::new(VramLedger::unlimited()),
+            scheduler: Arc::new(Scheduler::default()),
+            overrides: Arc::new(DeviceOverrides::default()),
+            virtual_gpus: Arc::new(VirtualGpus::default()),
+            virtual_physical_devices: DashMap::new(),
+        }
+    }
+
+    /// Account allocations in `ledger` instead of a private one.
+    pub fn with_vram_ledger(mut self, ledger: Arc<VramLedger>) -> Self {
+        self.vram = ledger;
+        self
+    }
+
+    /// Admit sessions to GPUs through `scheduler`.
+    pub fn with_scheduler(mut self, scheduler: Arc<Scheduler>) -> Self {
+        self.scheduler = scheduler;
+        self
+    }
+
+    /// Report device properties as `overrides` says.
+    pub fn with_device_overrides(mut self, overrides: Arc<DeviceOverrides>) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Show clients the GPUs `virtual_gpus` splits as several.
+    pub fn with_virtual_gpus(mut self, virtual_gpus: Arc<VirtualGpus>) -> Self {
+        self.virtual_gpus = virtual_gpus;
+        self
+    }
+
+    /// Save each GPU's pipeline cache in `dir`, so later runs reuse the
+    /// pipelines compiled in earlier ones.
+    pub fn with_pipeline_cache_dir(mut self, dir: PathBuf) -> Self {
+        self.pipeline_cache_dir = Some(dir);
+        self
+    }
+
+    /// Create the server's pipeline cache for a new device, from what was
+    /// saved for its GPU.
+    fn open_pipeline_cache(&self, device: NetworkHandle, dev: &ash::Device, uuid: Option<&DeviceUuid>) {
+        let path = self.pipeline_cache_dir.as_ref().zip(uuid).map(|(dir, uuid)| {
+            let hex: String = uuid.iter().map(|b| format!("{:02x}", b)).collect();
+            dir.join(format!("{}.bin", hex))
+        });
+        let data = path.as_ref().and_then(|p| std::fs::read(p).ok()).unwrap_or_default();
+
+        // The driver ignores data from another driver version, but a damaged
+        // file can still fail creation: start empty then.
+        let created = unsafe { dev.create_pipeline_cache(&vk::PipelineCacheCreateInfo::default().initial_data(&data), None) }
+            .or_else(|_| unsafe { dev.create_pipeline_cache(&vk::PipelineCacheCreateInfo::default(), None) });
+        match created {
+            Ok(cache) => {
+                if !data.is_empty() {
+                    debug!("loaded {} bytes of pipeline cache for {:?}", data.len(), device);
+                }
+                self.device_pipeline_caches.insert(device, DevicePipelineCache { cache, path });
+            }
+            Err(e) => warn!("no pipeline cache for {:?}: {:?}", device, e),
+        }
+    }
+
+    /// Save and destroy a device's server-side pipeline cache, before the
+    /// device is destroyed.
+    fn close_pipeline_cache(&self, device: &NetworkHandle, dev: &ash::Device) {
+        let Some((_, entry)) = self.device_pipeline_caches.remove(device) else {
+            return;
+        };
+        if let Some(path) = &entry.path {
+            match unsafe { dev.get_pipeline_cache_data(entry.cache) } {
+                Ok(data) if !data.is_empty() => {
+                    // Through a temporary file, so a crash can't leave half a cache
+                    let tmp = path.with_extension("tmp");
+                    let saved = path
+                        .parent()
+                        .map_or(Ok(()), std::fs::create_dir_all)
+                        .and_then(|_| std::fs::write(&tmp, &data))
+                        .and_then(|_| std::fs::rename(&tmp, path));
+                    match saved {
+                        Ok(()) => debug!("saved {} bytes of pipeline cache to {}", data.len(), path.display()),
+                        Err(e) => warn!("can't save pipeline cache to {}: {}", path.display(), e),
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("can't read pipeline cache of {:?}: {:?}", device, e),
+            }
+        }
+        unsafe { dev.destroy_pipeline_cache(entry.cache, None) };
+    }
+
+    /// Cache to compile a device's pipelines with: the application's, or
+    /// the server's own when it passed none.
+    fn pipeline_cache_for(&self, device: &NetworkHandle, app_cache: Option<NetworkHandle>) -> vk::PipelineCache {
+        match app_cache {
+            Some(h) => self.pipeline_cache_handles.get(&h).map(|v| *v.value()),
+            None => self.device_pipeline_caches.get(device).map(|v| v.cache),
+        }
+        .unwrap_or(vk::PipelineCache::null())
+    }
+
+    /// Destroy an application's pipeline cache, keeping what it holds in
+    /// the device's own.
+    fn retire_pipeline_cache(&self, device: &NetworkHandle, dev: &ash::Device, cache: vk::PipelineCache) {
+        if let Some(own) = self.device_pipeline_caches.get(device) {
+            if let Err(e) = unsafe { dev.merge_pipeline_caches(own.cache, &[cache]) } {
+                debug!("can't merge pipeline cache into {:?}'s: {:?}", device, e);
+            }
+        }
+        unsafe { dev.destroy_pipeline_cache(cache, None) };
+    }
+
+    /// UUID and device-local memory types of a physical device. `None` if the
+    /// instance can't query the UUID (it needs Vulkan 1.1).
+    fn device_vram_info(&self, wrapper: &ash::Instance, pd: vk::PhysicalDevice) -> Option<DeviceVram> {
+        let entry = self.entry.as_ref()?;
+        unsafe { entry.get_instance_proc_addr(wrapper.handle(), c"vkGetPhysicalDeviceProperties2".as_ptr()) }?;
+
+        let mut id_props = vk::PhysicalDeviceIDProperties::default();
+        let mut props2 = vk::PhysicalDeviceProperties2::default().push_next(&mut id_props);
+        unsafe { wrapper.get_physical_device_properties2(pd, &mut props2) };
+        let uuid = id_props.device_uuid;
+
+        let mem_props = unsafe { wrapper.get_physical_device_memory_properties(pd) };
+        let local_types = mem_props.memory_types[..mem_props.memory_type_count as usize]
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| t.property_flags.contains(vk::MemoryPropertyFlags::DEVICE_LOCAL))
+            .fold(0u32, |mask, (i, _)| mask | (1 << i));
+        Some(DeviceVram { uuid, local_types })
+    }
+
+    /// Whether the driver of `device` has the entry point `name`; ash panics
+    /// on calls to the ones it couldn't load.
+    fn has_device_fn(&self, device: &NetworkHandle, dev: &ash::Device, name: &CStr) -> bool {
+        self.device_to_instance
+            .get(device)
+            .and_then(|inst| self.instance_wrappers.get(inst.value()))
+            .and_then(|w| unsafe { (w.fp_v1_0().get_device_proc_addr)(dev.handle(), name.as_ptr()) })
+            .is_some()
+    }
+
+    /// The device a queue was retrieved from.
+    fn queue_device(&self, queue: &NetworkHandle) -> Option<dashmap::mapref::one::Ref<'_, NetworkHandle, ash::Device>> {
+        let device = *self.queue_to_device.get(queue)?.value();
+        self.device_wrappers.get(&device)
+    }
+
+    /// Create a shader module from SPIR-V `code`.
+    fn create_shader_module(&self, session: &Session, device: NetworkHandle, code: &[u8]) -> VulkanResponse {
+        let dev = match self.device_wrappers.get(&device) {
+            Some(d) => d,
+            None => {
+                return VulkanResponse::Error {
+                    code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                    message: "invalid device handle".to_string(),
+                }
+            }
+        };
+
+        // SPIR-V code must be aligned to 4 bytes and size must be multiple of 4
+        if !code.len().is_multiple_of(4) {
+            return VulkanResponse::Error {
+                code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
+                message: "SPIR-V code size must be multiple of 4".to_string(),
+            };
+        }
+
+        let code_u32: &[u32] = unsafe {
+            std::slice::from_raw_parts(
+                code.as_ptr() as *const u32,
+                code.len() / 4,
+            )
+        };
+
+        let create_info = vk::ShaderModuleCreateInfo::default().code(code_u32);
+        match unsafe { dev.create_shader_module(&create_info, None) } {
+            Ok(module) => {
+                let handle = session.alloc_handle(ResourceType::VkShaderModule);
+                self.shader_module_handles.insert(handle, module);
+                self.shader_to_device.insert(handle, device);
+                debug!("created shader module: {:?}", handle);
+                VulkanResponse::ShaderModuleCreated { handle }
+            }
+            Err(e) => Self::vk_err(e),
         }
     }
 
@@ -133,6 +839,141 @@ impl VulkanExecutor {
         }
     }
 
+    /// A VkRenderingAttachmentInfo; unknown image views read as unused.
+    fn rendering_attachment_of(&self, a: &SerializedRenderingAttachmentInfo) -> vk::RenderingAttachmentInfo<'static> {
+        let view_of = |h: Option<NetworkHandle>| {
+            h.and_then(|h| self.image_view_handles.get(&h).map(|v| *v.value()))
+                .unwrap_or(vk::ImageView::null())
+        };
+        vk::RenderingAttachmentInfo::default()
+            .image_view(view_of(a.image_view))
+            .image_layout(vk::ImageLayout::from_raw(a.image_layout))
+            .resolve_mode(vk::ResolveModeFlags::from_raw(a.resolve_mode))
+            .resolve_image_view(view_of(a.resolve_image_view))
+            .resolve_image_layout(vk::ImageLayout::from_raw(a.resolve_image_layout))
+            .load_op(vk::AttachmentLoadOp::from_raw(a.load_op))
+            .store_op(vk::AttachmentStoreOp::from_raw(a.store_op))
+            .clear_value(unsafe { std::mem::transmute::<[u8; 16], vk::ClearValue>(a.clear_value.data) })
+    }
+
+    fn barriers2_of(&self, info: &SerializedDependencyInfo) -> Barriers2 {
+        let memory = info
+            .memory_barriers
+            .iter()
+            .map(|mb| {
+                vk::MemoryBarrier2::default()
+                    .src_stage_mask(vk::PipelineStageFlags2::from_raw(mb.src_stage_mask))
+                    .src_access_mask(vk::AccessFlags2::from_raw(mb.src_access_mask))
+                    .dst_stage_mask(vk::PipelineStageFlags2::from_raw(mb.dst_stage_mask))
+                    .dst_access_mask(vk::AccessFlags2::from_raw(mb.dst_access_mask))
+            })
+            .collect();
+        let buffer = info
+            .buffer_memory_barriers
+            .iter()
+            .map(|bmb| {
+                let buffer = self
+                    .buffer_handles
+                    .get(&bmb.buffer)
+                    .map(|v| *v.value())
+                    .unwrap_or(vk::Buffer::null());
+                vk::BufferMemoryBarrier2::default()
+                    .src_stage_mask(vk::PipelineStageFlags2::from_raw(bmb.src_stage_mask))
+                    .src_access_mask(vk::AccessFlags2::from_raw(bmb.src_access_mask))
+                    .dst_stage_mask(vk::PipelineStageFlags2::from_raw(bmb.dst_stage_mask))
+                    .dst_access_mask(vk::AccessFlags2::from_raw(bmb.dst_access_mask))
+                    .src_queue_family_index(bmb.src_queue_family_index)
+                    .dst_queue_family_index(bmb.dst_queue_family_index)
+                    .buffer(buffer)
+                    .offset(bmb.offset)
+                    .size(bmb.size)
+            })
+            .collect();
+        let image = info
+            .image_memory_barriers
+            .iter()
+            .map(|imb| {
+                let image = self
+                    .image_handles
+                    .get(&imb.image)
+                    .map(|v| *v.value())
+                    .unwrap_or(vk::Image::null());
+                vk::ImageMemoryBarrier2::default()
+                    .src_stage_mask(vk::PipelineStageFlags2::from_raw(imb.src_stage_mask))
+                    .src_access_mask(vk::AccessFlags2::from_raw(imb.src_access_mask))
+                    .dst_stage_mask(vk::PipelineStageFlags2::from_raw(imb.dst_stage_mask))
+                    .dst_access_mask(vk::AccessFlags2::from_raw(imb.dst_access_mask))
+                    .old_layout(vk::ImageLayout::from_raw(imb.old_layout))
+                    .new_layout(vk::ImageLayout::from_raw(imb.new_layout))
+                    .src_queue_family_index(imb.src_queue_family_index)
+                    .dst_queue_family_index(imb.dst_queue_family_index)
+                    .image(image)
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::from_raw(imb.subresource_range.aspect_mask),
+                        base_mip_level: imb.subresource_range.base_mip_level,
+                        level_count: imb.subresource_range.level_count,
+                        base_array_layer: imb.subresource_range.base_array_layer,
+                        layer_count: imb.subresource_range.layer_count,
+                    })
+            })
+            .collect();
+        Barriers2 {
+            dependency_flags: vk::DependencyFlags::from_raw(info.dependency_flags),
+            memory,
+            buffer,
+            image,
+        }
+    }
+
+    fn buffer_barriers_of(&self, barriers: &[SerializedBufferMemoryBarrier]) -> Vec<vk::BufferMemoryBarrier<'static>> {
+        barriers
+            .iter()
+            .map(|bmb| {
+                let buffer = self
+                    .buffer_handles
+                    .get(&bmb.buffer)
+                    .map(|v| *v.value())
+                    .unwrap_or(vk::Buffer::null());
+                vk::BufferMemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::from_raw(bmb.src_access_mask))
+                    .dst_access_mask(vk::AccessFlags::from_raw(bmb.dst_access_mask))
+                    .src_queue_family_index(bmb.src_queue_family_index)
+                    .dst_queue_family_index(bmb.dst_queue_family_index)
+                    .buffer(buffer)
+                    .offset(bmb.offset)
+                    .size(bmb.size)
+            })
+            .collect()
+    }
+
+    fn image_barriers_of(&self, barriers: &[SerializedImageMemoryBarrier]) -> Vec<vk::ImageMemoryBarrier<'static>> {
+        barriers
+            .iter()
+            .map(|imb| {
+                let image = self
+                    .image_handles
+                    .get(&imb.image)
+                    .map(|v| *v.value())
+                    .unwrap_or(vk::Image::null());
+                vk::ImageMemoryBarrier::default()
+                    .src_access_mask(vk::AccessFlags::from_raw(imb.src_access_mask))
+                    .dst_access_mask(vk::AccessFlags::from_raw(imb.dst_access_mask))
+                    .old_layout(vk::ImageLayout::from_raw(imb.old_layout))
+                    .new_layout(vk::ImageLayout::from_raw(imb.new_layout))
+                    .src_queue_family_index(imb.src_queue_family_index)
+                    .dst_queue_family_index(imb.dst_queue_family_index)
+                    .image(image)
+                    .subresource_range(vk::ImageSubresourceRange {
+                        aspect_mask: vk::ImageAspectFlags::from_raw(imb.subresource_range.aspect_mask),
+                        base_mip_level: imb.subresource_range.base_mip_level,
+                        level_count: imb.subresource_range.level_count,
+                        base_array_layer: imb.subresource_range.base_array_layer,
+                        layer_count: imb.subresource_range.layer_count,
+                    })
+            })
+            .collect()
+    }
+
 
     /// Check if Vulkan is available on this system.
     pub fn is_available(&self) -> bool {
@@ -159,8 +1000,8 @@ impl VulkanExecutor {
                 engine_name,
                 engine_version,
                 api_version,
-                enabled_extensions: _,
-                enabled_layers: _,
+                enabled_extensions,
+                enabled_layers,
             } => {
                 let app_name_c = app_name
                     .as_deref()
@@ -185,7 +1026,39 @@ impl VulkanExecutor {
                     app_info = app_info.engine_name(name.as_c_str());
                 }
 
-                let create_info = vk::InstanceCreateInfo::default().application_info(&app_info);
+                let available = unsafe { entry.enumerate_instance_extension_properties(None) }.unwrap_or_default();
+                let extensions = match driver_extensions(&enabled_extensions, &available) {
+                    Ok(e) => e,
+                    Err(name) => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_EXTENSION_NOT_PRESENT.as_raw(),
+                            message: format!("instance extension {} not present", name),
+                        }
+                    }
+                };
+                // The ICD lists no layers, so the ones asked for are a
+                // convenience: enabled where the server has them.
+                let available_layers = unsafe { entry.enumerate_instance_layer_properties() }.unwrap_or_default();
+                let mut layers = Vec::new();
+                for name in &enabled_layers {
+                    match CString::new(name.as_str()) {
+                        Ok(c_name)
+                            if available_layers
+                                .iter()
+                                .any(|l| l.layer_name_as_c_str() == Ok(c_name.as_c_str())) =>
+                        {
+                            layers.push(c_name)
+                        }
+                        _ => warn!("layer {} not present, skipped", name),
+                    }
+                }
+                let extension_ptrs: Vec<*const std::ffi::c_char> = extensions.iter().map(|e| e.as_ptr()).collect();
+                let layer_ptrs: Vec<*const std::ffi::c_char> = layers.iter().map(|l| l.as_ptr()).collect();
+
+                let create_info = vk::InstanceCreateInfo::default()
+                    .application_info(&app_info)
+                    .enabled_extension_names(&extension_ptrs)
+                    .enabled_layer_names(&layer_ptrs);
 
                 match unsafe { entry.create_instance(&create_info, None) } {
                     Ok(instance) => {
@@ -214,6 +1087,8 @@ impl VulkanExecutor {
                         .collect();
                     for key in pd_keys {
                         self.physical_device_handles.remove(&key);
+                        self.virtual_physical_devices.remove(&key);
+                        session.remove_handle(&key);
                     }
                     let _ = inst;
                     session.remove_handle(&instance);
@@ -237,10 +1112,25 @@ impl VulkanExecutor {
                 match unsafe { wrapper.enumerate_physical_devices() } {
                     Ok(physical_devices) => {
                         let mut handles = Vec::new();
-                        for pd in physical_devices {
+                        // Enumerated in the order GPU discovery numbered them,
+                        // split GPUs once per virtual GPU.
+                        let count = self.virtual_gpus.count().unwrap_or(physical_devices.len() as i32);
+                        for ordinal in 0..count as u32 {
+                            let Some((physical, slice)) = self.virtual_gpus.resolve(ordinal) else {
+                                continue;
+                            };
+                            let Some(&pd) = physical_devices.get(physical as usize) else {
+                                continue;
+                            };
+                            if !session.allows_gpu(ordinal) {
+                                continue;
+                            }
                             let handle = session.alloc_handle(ResourceType::VkPhysicalDevice);
                             self.physical_device_handles
                                 .insert(handle, (pd, instance));
+                            if let Some(slice) = slice {
+                                self.virtual_physical_devices.insert(handle, (ordinal, slice));
+                            }
                             handles.push(handle);
                         }
                         debug!("enumerated {} physical devices", handles.len());
@@ -298,11 +1188,15 @@ impl VulkanExecutor {
                 };
 
                 let props = unsafe { wrapper.get_physical_device_properties(pd) };
-
-                let device_name = unsafe {
-                    CStr::from_ptr(props.device_name.as_ptr())
-                        .to_string_lossy()
-                        .into_owned()
+                let uuid = self.device_vram_info(&wrapper, pd).map(|info| info.uuid);
+
+                let device_name = match self.overrides.name(uuid.as_ref()) {
+                    Some(name) => name.to_string(),
+                    None => unsafe {
+                        CStr::from_ptr(props.device_name.as_ptr())
+                            .to_string_lossy()
+                            .into_owned()
+                    },
                 };
 
                 // Serialize limits as raw bytes
@@ -339,8 +1233,57 @@ impl VulkanExecutor {
                 }
             }
 
-            VulkanCommand::GetPhysicalDeviceFeatures { physical_device }
-            | VulkanCommand::GetPhysicalDeviceFeatures2 { physical_device } => {
+            VulkanCommand::GetPhysicalDeviceFeatures2 { physical_device, chain } => {
+                let (pd, inst_handle) = match self.physical_device_handles.get(&physical_device) {
+                    Some(e) => *e.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid physical device handle".to_string(),
+                        }
+                    }
+                };
+                let wrapper = match self.instance_wrappers.get(&inst_handle) {
+                    Some(w) => w,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
+                            message: "instance wrapper not found".to_string(),
+                        }
+                    }
+                };
+
+                let mut chain = PNextChain::from_features(&chain);
+                let mut features2 = vk::PhysicalDeviceFeatures2 {
+                    p_next: chain.head(),
+                    ..Default::default()
+                };
+                // Drivers without Vulkan 1.1 only have the core features
+                let has_features2 = self.entry.as_ref().is_some_and(|entry| {
+                    unsafe { entry.get_instance_proc_addr(wrapper.handle(), c"vkGetPhysicalDeviceFeatures2".as_ptr()) }
+                        .is_some()
+                });
+                if has_features2 {
+                    unsafe { wrapper.get_physical_device_features2(pd, &mut features2) };
+                } else {
+                    features2.features = unsafe { wrapper.get_physical_device_features(pd) };
+                }
+                let features_raw = unsafe {
+                    let ptr = &features2.features as *const vk::PhysicalDeviceFeatures as *const u8;
+                    std::slice::from_raw_parts(
+                        ptr,
+                        std::mem::size_of::<vk::PhysicalDeviceFeatures>(),
+                    )
+                    .to_vec()
+                };
+
+                VulkanResponse::PhysicalDeviceFeatures2 {
+                    features_raw,
+                    chain: chain.serialize(),
+                }
+            }
+
+            VulkanCommand::GetPhysicalDeviceFeatures { physical_device } => {
                 let (pd, inst_handle) = match self.physical_device_handles.get(&physical_device) {
                     Some(e) => *e.value(),
                     None => {
@@ -405,13 +1348,29 @@ impl VulkanExecutor {
                         })
                         .collect();
 
-                let memory_heaps: Vec<SerializedMemoryHeap> =
+                let mut memory_heaps: Vec<SerializedMemoryHeap> =
                     (0..mem_props.memory_heap_count as usize)
                         .map(|i| SerializedMemoryHeap {
                             size: mem_props.memory_heaps[i].size,
                             flags: mem_props.memory_heaps[i].flags.as_raw(),
                         })
                         .collect();
+                // An overridden memory size is the size of the VRAM heap
+                let uuid = self.device_vram_info(&wrapper, pd).map(|info| info.uuid);
+                let slice = self.virtual_physical_devices.get(&physical_device).map(|v| v.1);
+                let memory = match slice {
+                    Some(slice) => Some(slice.memory),
+                    None => self.overrides.total_memory(uuid.as_ref()),
+                };
+                if let Some(bytes) = memory {
+                    let vram = memory_heaps
+                        .iter_mut()
+                        .filter(|heap| heap.flags & vk::MemoryHeapFlags::DEVICE_LOCAL.as_raw() != 0)
+                        .max_by_key(|heap| heap.size);
+                    if let Some(heap) = vram {
+                        heap.size = bytes;
+                    }
+                }
 
                 VulkanResponse::PhysicalDeviceMemoryProperties {
                     memory_type_count: mem_props.memory_type_count,
@@ -496,12 +1455,91 @@ impl VulkanExecutor {
                 }
             }
 
+            VulkanCommand::GetPhysicalDeviceImageFormatProperties {
+                physical_device,
+                format,
+                image_type,
+                tiling,
+                usage,
+                flags,
+                p_next,
+            } => {
+                let (pd, inst_handle) = match self.physical_device_handles.get(&physical_device) {
+                    Some(e) => *e.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid physical device handle".to_string(),
+                        }
+                    }
+                };
+                let wrapper = match self.instance_wrappers.get(&inst_handle) {
+                    Some(w) => w,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
+                            message: "instance wrapper not found".to_string(),
+                        }
+                    }
+                };
+
+                let format = vk::Format::from_raw(format);
+                let image_type = vk::ImageType::from_raw(image_type);
+                let tiling = vk::ImageTiling::from_raw(tiling);
+                let usage = vk::ImageUsageFlags::from_raw(usage);
+                let flags = vk::ImageCreateFlags::from_raw(flags);
+                // Chained structures need the Vulkan 1.1 query
+                let has_properties2 = !p_next.is_empty()
+                    && self.entry.as_ref().is_some_and(|entry| {
+                        unsafe {
+                            entry.get_instance_proc_addr(
+                                wrapper.handle(),
+                                c"vkGetPhysicalDeviceImageFormatProperties2".as_ptr(),
+                            )
+                        }
+                        .is_some()
+                    });
+                let result = if has_properties2 {
+                    let mut chain = PNextChain::new(&p_next);
+                    let mut info = vk::PhysicalDeviceImageFormatInfo2::default()
+                        .format(format)
+                        .ty(image_type)
+                        .tiling(tiling)
+                        .usage(usage)
+                        .flags(flags);
+                    info.p_next = chain.head();
+                    let mut properties = vk::ImageFormatProperties2::default();
+                    unsafe { wrapper.get_physical_device_image_format_properties2(pd, &info, &mut properties) }
+                        .map(|()| properties.image_format_properties)
+                } else {
+                    unsafe {
+                        wrapper.get_physical_device_image_format_properties(pd, format, image_type, tiling, usage, flags)
+                    }
+                };
+
+                match result {
+                    Ok(properties) => VulkanResponse::ImageFormatProperties {
+                        max_extent: [
+                            properties.max_extent.width,
+                            properties.max_extent.height,
+                            properties.max_extent.depth,
+                        ],
+                        max_mip_levels: properties.max_mip_levels,
+                        max_array_layers: properties.max_array_layers,
+                        sample_counts: properties.sample_counts.as_raw(),
+                        max_resource_size: properties.max_resource_size,
+                    },
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
             // ── Logical Device ──────────────────────────────────
             VulkanCommand::CreateDevice {
                 physical_device,
                 queue_create_infos,
-                enabled_extensions: _,
+                enabled_extensions,
                 enabled_features,
+                p_next,
             } => {
                 let (pd, inst_handle) = match self.physical_device_handles.get(&physical_device) {
                     Some(e) => *e.value(),
@@ -552,29 +1590,192 @@ impl VulkanExecutor {
                 let mut device_create_info = vk::DeviceCreateInfo::default()
                     .queue_create_infos(&vk_queue_create_infos);
 
+                let vram_info = self.device_vram_info(&wrapper, pd);
+                if let Some(info) = &vram_info {
+                    let refuse = |reason: String| {
+                        warn!(session_id = session.session_id, "device refused: {}", reason);
+                        VulkanResponse::Error {
+                            code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
+                            message: reason,
+                        }
+                    };
+                    if let Err(refused) = self.scheduler.admit(session.session_id, &info.uuid) {
+                        return refuse(refused.to_string());
+                    }
+                    if let Some((ordinal, slice)) = self.virtual_physical_devices.get(&physical_device).map(|v| *v) {
+                        match self.virtual_gpus.claim(session.session_id, ordinal) {
+                            Ok(true) => self.vram.add_session_device_quota(session.session_id, info.uuid, slice.memory),
+                            Ok(false) => {}
+                            Err(taken) => return refuse(taken.to_string()),
+                        }
+                    }
+                }
+
                 if let Some(ref f) = features {
                     device_create_info = device_create_info.enabled_features(f);
                 }
 
-                match unsafe { wrapper.create_device(pd, &device_create_info, None) } {
-                    Ok(device) => {
-                        let handle = session.alloc_handle(ResourceType::VkDevice);
-                        let raw = device.handle();
-                        self.device_handles.insert(handle, raw);
-                        self.device_wrappers.insert(handle, device);
-                        self.device_to_instance.insert(handle, inst_handle);
-                        info!("created Vulkan device: {:?}", handle);
-                        VulkanResponse::DeviceCreated { handle }
+                let device_extensions = unsafe { wrapper.enumerate_device_extension_properties(pd) }.unwrap_or_default();
+                let has_extension =
+                    |name: &CStr| device_extensions.iter().any(|e| e.extension_name_as_c_str() == Ok(name));
+                let app_extensions = match driver_extensions(&enabled_extensions, &device_extensions) {
+                    Ok(e) => e,
+                    Err(name) => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_EXTENSION_NOT_PRESENT.as_raw(),
+                            message: format!("device extension {} not present", name),
+                        }
                     }
-                    Err(e) => Self::vk_err(e),
+                };
+                let mut feature_chain = PNextChain::new(&p_next);
+                let mut extensions = Vec::new();
+
+                // Descriptor indexing features need the extension before
+                // Vulkan 1.2.
+                if feature_chain
+                    .find::<vk::PhysicalDeviceDescriptorIndexingFeatures>(
+                        vk::StructureType::PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES,
+                    )
+                    .is_some()
+                    && has_extension(ash::ext::descriptor_indexing::NAME)
+                {
+                    extensions.push(ash::ext::descriptor_indexing::NAME.as_ptr());
                 }
-            }
 
-            VulkanCommand::DestroyDevice { device } => {
-                if let Some((_, dev)) = self.device_wrappers.remove(&device) {
-                    unsafe { dev.destroy_device(None) };
+                // Timeline semaphores are always on where the driver has them
+                // (every Vulkan 1.2 driver), whatever the application enabled.
+                // They go in the application's own Vulkan 1.2 or timeline
+                // structure if it chained one, as both can't be in the chain.
+                let timeline = has_extension(ash::khr::timeline_semaphore::NAME);
+                let mut timeline_features =
+                    vk::PhysicalDeviceTimelineSemaphoreFeatures::default().timeline_semaphore(true);
+                let mut chain_timeline = false;
+                if timeline {
+                    extensions.push(ash::khr::timeline_semaphore::NAME.as_ptr());
+                    if let Some(v12) = feature_chain
+                        .find::<vk::PhysicalDeviceVulkan12Features>(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES)
+                    {
+                        v12.timeline_semaphore = vk::TRUE;
+                        chain_timeline = true;
+                    } else if let Some(t) = feature_chain.find::<vk::PhysicalDeviceTimelineSemaphoreFeatures>(
+                        vk::StructureType::PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES,
+                    ) {
+                        t.timeline_semaphore = vk::TRUE;
+                        chain_timeline = true;
+                    }
+                }
+                // Synchronization2 is turned on the same way, in the
+                // application's Vulkan 1.3 structure if it chained one.
+                let sync2 = has_extension(ash::khr::synchronization2::NAME);
+                let mut sync2_features =
+                    vk::PhysicalDeviceSynchronization2Features::default().synchronization2(true);
+                let mut chain_sync2 = false;
+                if sync2 {
+                    extensions.push(ash::khr::synchronization2::NAME.as_ptr());
+                    if let Some(v13) = feature_chain
+                        .find::<vk::PhysicalDeviceVulkan13Features>(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_FEATURES)
+                    {
+                        v13.synchronization2 = vk::TRUE;
+                        chain_sync2 = true;
+                    } else if let Some(s) = feature_chain.find::<vk::PhysicalDeviceSynchronization2Features>(
+                        vk::StructureType::PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES,
+                    ) {
+                        s.synchronization2 = vk::TRUE;
+                        chain_sync2 = true;
+                    }
+                }
+                let dynamic_rendering = has_extension(ash::khr::dynamic_rendering::NAME);
+                let mut dynamic_rendering_features =
+                    vk::PhysicalDeviceDynamicRenderingFeatures::default().dynamic_rendering(true);
+                let mut chain_dynamic_rendering = false;
+                if dynamic_rendering {
+                    extensions.push(ash::khr::dynamic_rendering::NAME.as_ptr());
+                    if let Some(v13) = feature_chain
+                        .find::<vk::PhysicalDeviceVulkan13Features>(vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_FEATURES)
+                    {
+                        v13.dynamic_rendering = vk::TRUE;
+                        chain_dynamic_rendering = true;
+                    } else if let Some(d) = feature_chain.find::<vk::PhysicalDeviceDynamicRenderingFeatures>(
+                        vk::StructureType::PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES,
+                    ) {
+                        d.dynamic_rendering = vk::TRUE;
+                        chain_dynamic_rendering = true;
+                    }
+                }
+                // The application's own, past the ones turned on above
+                for ext in &app_extensions {
+                    if !extensions.iter().any(|&e| unsafe { CStr::from_ptr(e) } == ext.as_c_str()) {
+                        extensions.push(ext.as_ptr());
+                    }
+                }
+                device_create_info = device_create_info.enabled_extension_names(&extensions);
+                device_create_info.p_next = feature_chain.head();
+                if timeline && !chain_timeline {
+                    device_create_info = device_create_info.push_next(&mut timeline_features);
+                }
+                if sync2 && !chain_sync2 {
+                    device_create_info = device_create_info.push_next(&mut sync2_features);
+                }
+                if dynamic_rendering && !chain_dynamic_rendering {
+                    device_create_info = device_create_info.push_next(&mut dynamic_rendering_features);
+                }
+
+                match unsafe { wrapper.create_device(pd, &device_create_info, None) } {
+                    Ok(device) => {
+                        let handle = session.alloc_handle(ResourceType::VkDevice);
+                        let raw = device.handle();
+                        self.open_pipeline_cache(handle, &device, vram_info.as_ref().map(|info| &info.uuid));
+                        if timeline {
+                            self.timeline_semaphores
+                                .insert(handle, ash::khr::timeline_semaphore::Device::new(&wrapper, &device));
+                        }
+                        if sync2 {
+                            self.synchronization2
+                                .insert(handle, ash::khr::synchronization2::Device::new(&wrapper, &device));
+                        }
+                        if dynamic_rendering {
+                            self.dynamic_rendering
+                                .insert(handle, ash::khr::dynamic_rendering::Device::new(&wrapper, &device));
+                        }
+                        self.device_handles.insert(handle, raw);
+                        self.device_wrappers.insert(handle, device);
+                        self.device_to_instance.insert(handle, inst_handle);
+                        let queue_family = queue_create_infos.first().map_or(0, |qi| qi.queue_family_index);
+                        self.device_physical.insert(handle, (pd, queue_family));
+                        if let Some(info) = vram_info {
+                            session.set_device(info.uuid);
+                            self.device_vram.insert(handle, info);
+                        }
+                        info!("created Vulkan device: {:?}", handle);
+                        VulkanResponse::DeviceCreated { handle }
+                    }
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::DestroyDevice { device } => {
+                if let Some((_, dev)) = self.device_wrappers.remove(&device) {
+                    self.close_pipeline_cache(&device, &dev);
+                    unsafe { dev.destroy_device(None) };
                     self.device_handles.remove(&device);
                     self.device_to_instance.remove(&device);
+                    self.device_vram.remove(&device);
+                    self.device_physical.remove(&device);
+                    self.timeline_semaphores.remove(&device);
+                    self.synchronization2.remove(&device);
+                    self.dynamic_rendering.remove(&device);
+                    // Its queues go with it
+                    let queues: Vec<NetworkHandle> = self
+                        .queue_to_device
+                        .iter()
+                        .filter(|e| *e.value() == device)
+                        .map(|e| *e.key())
+                        .collect();
+                    for q in queues {
+                        self.queue_handles.remove(&q);
+                        self.queue_to_device.remove(&q);
+                        session.remove_handle(&q);
+                    }
                     session.remove_handle(&device);
                     debug!("destroyed Vulkan device: {:?}", device);
                 }
@@ -616,6 +1817,7 @@ impl VulkanExecutor {
                     unsafe { dev.get_device_queue(queue_family_index, queue_index) };
                 let handle = session.alloc_handle(ResourceType::VkQueue);
                 self.queue_handles.insert(handle, queue);
+                self.queue_to_device.insert(handle, device);
                 debug!(
                     "got queue family={} index={}: {:?}",
                     queue_family_index, queue_index, handle
@@ -638,14 +1840,12 @@ impl VulkanExecutor {
                     }
                 };
 
-                // We need to find the device wrapper for this queue.
-                // Use any device wrapper we have (in Phase 3, single device assumption).
-                let dev = match self.device_wrappers.iter().next() {
+                let dev = match self.queue_device(&queue) {
                     Some(d) => d,
                     None => {
                         return VulkanResponse::Error {
                             code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
-                            message: "no device available".to_string(),
+                            message: "queue's device is gone".to_string(),
                         }
                     }
                 };
@@ -656,6 +1856,15 @@ impl VulkanExecutor {
                 let mut wait_sem_vecs: Vec<Vec<vk::Semaphore>> = Vec::new();
                 let mut sig_sem_vecs: Vec<Vec<vk::Semaphore>> = Vec::new();
                 let mut stage_mask_vecs: Vec<Vec<vk::PipelineStageFlags>> = Vec::new();
+                let mut timeline_infos: Vec<vk::TimelineSemaphoreSubmitInfo> = submits
+                    .iter()
+                    .map(|submit| {
+                        vk::TimelineSemaphoreSubmitInfo::default()
+                            .wait_semaphore_values(&submit.wait_semaphore_values)
+                            .signal_semaphore_values(&submit.signal_semaphore_values)
+                    })
+                    .collect();
+                let mut chains: Vec<PNextChain> = submits.iter().map(|s| PNextChain::new(&s.p_next)).collect();
 
                 for submit in &submits {
                     let cmd_bufs: Vec<vk::CommandBuffer> = submit
@@ -687,9 +1896,14 @@ impl VulkanExecutor {
                     stage_mask_vecs.push(stage_masks);
                 }
 
-                for i in 0..submits.len() {
+                for (i, (timeline_info, chain)) in timeline_infos.iter_mut().zip(&mut chains).enumerate() {
                     let mut submit_info = vk::SubmitInfo::default()
                         .command_buffers(&cmd_buf_vecs[i]);
+                    submit_info.p_next = chain.head();
+                    let submit = &submits[i];
+                    if !submit.wait_semaphore_values.is_empty() || !submit.signal_semaphore_values.is_empty() {
+                        submit_info = submit_info.push_next(timeline_info);
+                    }
                     if !wait_sem_vecs[i].is_empty() {
                         submit_info = submit_info
                             .wait_semaphores(&wait_sem_vecs[i])
@@ -711,6 +1925,76 @@ impl VulkanExecutor {
                 }
             }
 
+            VulkanCommand::QueueSubmit2 { queue, submits, fence } => {
+                let q = match self.queue_handles.get(&queue) {
+                    Some(q) => *q.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid queue handle".to_string(),
+                        }
+                    }
+                };
+                let sync2 = match self.queue_to_device.get(&queue).and_then(|d| self.synchronization2.get(d.value())) {
+                    Some(s) => s,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_FEATURE_NOT_PRESENT.as_raw(),
+                            message: "device has no synchronization2".to_string(),
+                        }
+                    }
+                };
+
+                let semaphore_infos = |infos: &[SerializedSemaphoreSubmitInfo]| -> Vec<vk::SemaphoreSubmitInfo<'static>> {
+                    infos
+                        .iter()
+                        .filter_map(|info| {
+                            let sem = *self.semaphore_handles.get(&info.semaphore)?.value();
+                            Some(
+                                vk::SemaphoreSubmitInfo::default()
+                                    .semaphore(sem)
+                                    .value(info.value)
+                                    .stage_mask(vk::PipelineStageFlags2::from_raw(info.stage_mask))
+                                    .device_index(info.device_index),
+                            )
+                        })
+                        .collect()
+                };
+                let wait_vecs: Vec<_> = submits.iter().map(|s| semaphore_infos(&s.wait_semaphore_infos)).collect();
+                let signal_vecs: Vec<_> = submits.iter().map(|s| semaphore_infos(&s.signal_semaphore_infos)).collect();
+                let cmd_buf_vecs: Vec<Vec<vk::CommandBufferSubmitInfo>> = submits
+                    .iter()
+                    .map(|s| {
+                        s.command_buffers
+                            .iter()
+                            .filter_map(|h| self.command_buffer_handles.get(h).map(|v| *v.value()))
+                            .map(|cb| vk::CommandBufferSubmitInfo::default().command_buffer(cb))
+                            .collect()
+                    })
+                    .collect();
+
+                let vk_submits: Vec<vk::SubmitInfo2> = submits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| {
+                        vk::SubmitInfo2::default()
+                            .flags(vk::SubmitFlags::from_raw(s.flags))
+                            .wait_semaphore_infos(&wait_vecs[i])
+                            .command_buffer_infos(&cmd_buf_vecs[i])
+                            .signal_semaphore_infos(&signal_vecs[i])
+                    })
+                    .collect();
+
+                let vk_fence = fence
+                    .and_then(|fh| self.fence_handles.get(&fh).map(|v| *v.value()))
+                    .unwrap_or(vk::Fence::null());
+
+                match unsafe { sync2.queue_submit2(q, &vk_submits, vk_fence) } {
+                    Ok(()) => VulkanResponse::Success,
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
             VulkanCommand::QueueWaitIdle { queue } => {
                 let q = match self.queue_handles.get(&queue) {
                     Some(q) => *q.value(),
@@ -721,12 +2005,12 @@ impl VulkanExecutor {
                         }
                     }
                 };
-                let dev = match self.device_wrappers.iter().next() {
+                let dev = match self.queue_device(&queue) {
                     Some(d) => d,
                     None => {
                         return VulkanResponse::Error {
                             code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
-                            message: "no device available".to_string(),
+                            message: "queue's device is gone".to_string(),
                         }
                     }
                 };
@@ -751,6 +2035,28 @@ impl VulkanExecutor {
                         }
                     }
                 };
+                // Only device-local memory counts against the session's VRAM.
+                let charge = self
+                    .device_vram
+                    .get(&device)
+                    .filter(|info| memory_type_index < 32 && info.local_types & (1 << memory_type_index) != 0)
+                    .map(|info| Charge {
+                        session_id: session.session_id,
+                        device: info.uuid,
+                        api: Api::Vulkan,
+                        bytes: alloc_size,
+                    });
+                if let Some(charge) = &charge {
+                    if let Err(e) = self.vram.try_charge(charge) {
+                        warn!(
+                            session_id = session.session_id,
+                            "allocation of {} bytes refused: session VRAM quota {} bytes, {} in use",
+                            alloc_size, e.quota, e.used
+                        );
+                        return Self::vk_err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY);
+                    }
+                }
+
                 let alloc_info = vk::MemoryAllocateInfo::default()
                     .allocation_size(alloc_size)
                     .memory_type_index(memory_type_index);
@@ -759,10 +2065,18 @@ impl VulkanExecutor {
                         let handle = session.alloc_handle(ResourceType::VkDeviceMemory);
                         self.memory_handles.insert(handle, memory);
                         self.memory_to_device.insert(handle, device);
+                        if let Some(charge) = charge {
+                            self.vram.track(handle, charge);
+                        }
                         debug!("allocated {} bytes of device memory: {:?}", alloc_size, handle);
                         VulkanResponse::MemoryAllocated { handle }
                     }
-                    Err(e) => Self::vk_err(e),
+                    Err(e) => {
+                        if let Some(charge) = &charge {
+                            self.vram.uncharge(charge);
+                        }
+                        Self::vk_err(e)
+                    }
                 }
             }
 
@@ -779,6 +2093,7 @@ impl VulkanExecutor {
                     }
                     unsafe { dev.free_memory(mem, None) };
                     self.memory_to_device.remove(&memory);
+                    self.vram.free(&memory);
                     session.remove_handle(&memory);
                 }
                 VulkanResponse::Success
@@ -979,6 +2294,7 @@ impl VulkanExecutor {
                 usage,
                 sharing_mode,
                 queue_family_indices,
+                p_next,
             } => {
                 let dev = match self.device_wrappers.get(&device) {
                     Some(d) => d,
@@ -999,6 +2315,8 @@ impl VulkanExecutor {
                     create_info =
                         create_info.queue_family_indices(&queue_family_indices);
                 }
+                let mut chain = PNextChain::new(&p_next);
+                create_info.p_next = chain.head();
 
                 match unsafe { dev.create_buffer(&create_info, None) } {
                     Ok(buffer) => {
@@ -1025,6 +2343,62 @@ impl VulkanExecutor {
                 VulkanResponse::Success
             }
 
+            // ── Buffer View ─────────────────────────────────────
+            VulkanCommand::CreateBufferView {
+                device,
+                buffer,
+                format,
+                offset,
+                range,
+            } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let buf = match self.buffer_handles.get(&buffer) {
+                    Some(b) => *b.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
+                            message: "invalid buffer handle".to_string(),
+                        }
+                    }
+                };
+
+                let ci = vk::BufferViewCreateInfo::default()
+                    .buffer(buf)
+                    .format(vk::Format::from_raw(format))
+                    .offset(offset)
+                    .range(range);
+                match unsafe { dev.create_buffer_view(&ci, None) } {
+                    Ok(view) => {
+                        let handle = session.alloc_handle(ResourceType::VkBufferView);
+                        self.buffer_view_handles.insert(handle, view);
+                        self.buffer_view_to_device.insert(handle, device);
+                        VulkanResponse::BufferViewCreated { handle }
+                    }
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::DestroyBufferView { device, buffer_view } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => return VulkanResponse::Success,
+                };
+                if let Some((_, view)) = self.buffer_view_handles.remove(&buffer_view) {
+                    unsafe { dev.destroy_buffer_view(view, None) };
+                    self.buffer_view_to_device.remove(&buffer_view);
+                    session.remove_handle(&buffer_view);
+                }
+                VulkanResponse::Success
+            }
+
             VulkanCommand::BindBufferMemory {
                 device,
                 buffer,
@@ -1093,41 +2467,17 @@ impl VulkanExecutor {
 
             // ── Shader Module ───────────────────────────────────
             VulkanCommand::CreateShaderModule { device, code } => {
-                let dev = match self.device_wrappers.get(&device) {
-                    Some(d) => d,
-                    None => {
-                        return VulkanResponse::Error {
-                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
-                            message: "invalid device handle".to_string(),
-                        }
-                    }
-                };
-
-                // SPIR-V code must be aligned to 4 bytes and size must be multiple of 4
-                if code.len() % 4 != 0 {
-                    return VulkanResponse::Error {
-                        code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
-                        message: "SPIR-V code size must be multiple of 4".to_string(),
-                    };
+                let response = self.create_shader_module(session, device, &code);
+                if matches!(response, VulkanResponse::ShaderModuleCreated { .. }) {
+                    self.shader_code.insert(&code);
                 }
+                response
+            }
 
-                let code_u32: &[u32] = unsafe {
-                    std::slice::from_raw_parts(
-                        code.as_ptr() as *const u32,
-                        code.len() / 4,
-                    )
-                };
-
-                let create_info = vk::ShaderModuleCreateInfo::default().code(code_u32);
-                match unsafe { dev.create_shader_module(&create_info, None) } {
-                    Ok(module) => {
-                        let handle = session.alloc_handle(ResourceType::VkShaderModule);
-                        self.shader_module_handles.insert(handle, module);
-                        self.shader_to_device.insert(handle, device);
-                        debug!("created shader module: {:?}", handle);
-                        VulkanResponse::ShaderModuleCreated { handle }
-                    }
-                    Err(e) => Self::vk_err(e),
+            VulkanCommand::CreateShaderModuleByHash { device, hash } => {
+                match self.shader_code.get(&hash) {
+                    Some(code) => self.create_shader_module(session, device, &code),
+                    None => VulkanResponse::ShaderCodeMissing,
                 }
             }
 
@@ -1159,14 +2509,30 @@ impl VulkanExecutor {
                     }
                 };
 
-                let vk_bindings: Vec<vk::DescriptorSetLayoutBinding> = bindings
+                let immutable_sampler_vecs: Vec<Vec<vk::Sampler>> = bindings
                     .iter()
                     .map(|b| {
-                        vk::DescriptorSetLayoutBinding::default()
+                        b.immutable_samplers
+                            .iter()
+                            .map(|h| self.sampler_handles.get(h).map(|v| *v.value()).unwrap_or(vk::Sampler::null()))
+                            .collect()
+                    })
+                    .collect();
+
+                let vk_bindings: Vec<vk::DescriptorSetLayoutBinding> = bindings
+                    .iter()
+                    .enumerate()
+                    .map(|(i, b)| {
+                        let binding = vk::DescriptorSetLayoutBinding::default()
                             .binding(b.binding)
                             .descriptor_type(vk::DescriptorType::from_raw(b.descriptor_type))
                             .descriptor_count(b.descriptor_count)
-                            .stage_flags(vk::ShaderStageFlags::from_raw(b.stage_flags))
+                            .stage_flags(vk::ShaderStageFlags::from_raw(b.stage_flags));
+                        if immutable_sampler_vecs[i].is_empty() {
+                            binding
+                        } else {
+                            binding.immutable_samplers(&immutable_sampler_vecs[i])
+                        }
                     })
                     .collect();
 
@@ -1263,6 +2629,7 @@ impl VulkanExecutor {
             // ── Compute Pipeline ────────────────────────────────
             VulkanCommand::CreateComputePipelines {
                 device,
+                pipeline_cache,
                 create_infos,
             } => {
                 let dev = match self.device_wrappers.get(&device) {
@@ -1311,7 +2678,7 @@ impl VulkanExecutor {
 
                 match unsafe {
                     dev.create_compute_pipelines(
-                        vk::PipelineCache::null(),
+                        self.pipeline_cache_for(&device, pipeline_cache),
                         &vk_create_infos,
                         None,
                     )
@@ -1393,6 +2760,11 @@ impl VulkanExecutor {
                 if let Some((_, p)) = self.desc_pool_handles.remove(&pool) {
                     unsafe { dev.destroy_descriptor_pool(p, None) };
                     self.desc_pool_to_device.remove(&pool);
+                    // The pool's sets went with it
+                    for set in pool_children(&self.desc_set_to_pool, &pool) {
+                        self.desc_set_handles.remove(&set);
+                        session.remove_handle(&set);
+                    }
                     session.remove_handle(&pool);
                 }
                 VulkanResponse::Success
@@ -1442,6 +2814,7 @@ impl VulkanExecutor {
                         for set in sets {
                             let handle = session.alloc_handle(ResourceType::VkDescriptorSet);
                             self.desc_set_handles.insert(handle, set);
+                            self.desc_set_to_pool.insert(handle, descriptor_pool);
                             handles.push(handle);
                         }
                         VulkanResponse::DescriptorSetsAllocated { handles }
@@ -1470,6 +2843,7 @@ impl VulkanExecutor {
                         self.desc_set_handles
                             .remove(h)
                             .map(|(_, s)| {
+                                self.desc_set_to_pool.remove(h);
                                 session.remove_handle(h);
                                 s
                             })
@@ -1514,6 +2888,39 @@ impl VulkanExecutor {
                     })
                     .collect();
 
+                let image_info_vecs: Vec<Vec<vk::DescriptorImageInfo>> = writes
+                    .iter()
+                    .map(|w| {
+                        w.image_infos
+                            .iter()
+                            .map(|ii| {
+                                let sampler = ii
+                                    .sampler
+                                    .and_then(|h| self.sampler_handles.get(&h).map(|v| *v.value()))
+                                    .unwrap_or(vk::Sampler::null());
+                                let image_view = ii
+                                    .image_view
+                                    .and_then(|h| self.image_view_handles.get(&h).map(|v| *v.value()))
+                                    .unwrap_or(vk::ImageView::null());
+                                vk::DescriptorImageInfo::default()
+                                    .sampler(sampler)
+                                    .image_view(image_view)
+                                    .image_layout(vk::ImageLayout::from_raw(ii.image_layout))
+                            })
+                            .collect()
+                    })
+                    .collect();
+
+                let texel_view_vecs: Vec<Vec<vk::BufferView>> = writes
+                    .iter()
+                    .map(|w| {
+                        w.texel_buffer_views
+                            .iter()
+                            .map(|h| self.buffer_view_handles.get(h).map(|v| *v.value()).unwrap_or(vk::BufferView::null()))
+                            .collect()
+                    })
+                    .collect();
+
                 let vk_writes: Vec<vk::WriteDescriptorSet> = writes
                     .iter()
                     .enumerate()
@@ -1524,12 +2931,18 @@ impl VulkanExecutor {
                             .map(|v| *v.value())
                             .unwrap_or(vk::DescriptorSet::null());
 
-                        vk::WriteDescriptorSet::default()
+                        let write = vk::WriteDescriptorSet::default()
                             .dst_set(dst_set)
                             .dst_binding(w.dst_binding)
                             .dst_array_element(w.dst_array_element)
-                            .descriptor_type(vk::DescriptorType::from_raw(w.descriptor_type))
-                            .buffer_info(&buffer_info_vecs[i])
+                            .descriptor_type(vk::DescriptorType::from_raw(w.descriptor_type));
+                        if !texel_view_vecs[i].is_empty() {
+                            write.texel_buffer_view(&texel_view_vecs[i])
+                        } else if image_info_vecs[i].is_empty() {
+                            write.buffer_info(&buffer_info_vecs[i])
+                        } else {
+                            write.image_info(&image_info_vecs[i])
+                        }
                     })
                     .collect();
 
@@ -1579,6 +2992,12 @@ impl VulkanExecutor {
                 if let Some((_, pool)) = self.command_pool_handles.remove(&command_pool) {
                     unsafe { dev.destroy_command_pool(pool, None) };
                     self.command_pool_to_device.remove(&command_pool);
+                    // The pool's command buffers went with it
+                    for cb in pool_children(&self.command_buffer_to_pool, &command_pool) {
+                        self.command_buffer_handles.remove(&cb);
+                        self.command_buffer_to_device.remove(&cb);
+                        session.remove_handle(&cb);
+                    }
                     session.remove_handle(&command_pool);
                 }
                 VulkanResponse::Success
@@ -1653,6 +3072,7 @@ impl VulkanExecutor {
                             let handle = session.alloc_handle(ResourceType::VkCommandBuffer);
                             self.command_buffer_handles.insert(handle, cb);
                             self.command_buffer_to_device.insert(handle, device);
+                            self.command_buffer_to_pool.insert(handle, command_pool);
                             handles.push(handle);
                         }
                         VulkanResponse::CommandBuffersAllocated { handles }
@@ -1682,6 +3102,7 @@ impl VulkanExecutor {
                             .remove(h)
                             .map(|(_, cb)| {
                                 self.command_buffer_to_device.remove(h);
+                                self.command_buffer_to_pool.remove(h);
                                 session.remove_handle(h);
                                 cb
                             })
@@ -1697,6 +3118,7 @@ impl VulkanExecutor {
             // ── Command Buffer Recording (Batched) ──────────────
             VulkanCommand::SubmitRecordedCommands {
                 command_buffer,
+                secondary,
                 commands,
             } => {
                 let cb = match self.command_buffer_handles.get(&command_buffer) {
@@ -1728,10 +3150,34 @@ impl VulkanExecutor {
                         }
                     }
                 };
+                let sync2 = self.synchronization2.get(&dev_handle);
+                let dynamic_rendering = self.dynamic_rendering.get(&dev_handle);
 
-                // Begin command buffer
-                let begin_info = vk::CommandBufferBeginInfo::default()
+                // Begin command buffer. Primaries are recorded again for each
+                // submit; secondaries once, with the application's flags.
+                let mut inheritance = vk::CommandBufferInheritanceInfo::default();
+                let mut begin_info = vk::CommandBufferBeginInfo::default()
                     .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
+                if let Some(s) = &secondary {
+                    inheritance = inheritance
+                        .render_pass(
+                            s.render_pass
+                                .and_then(|h| self.render_pass_handles.get(&h).map(|v| *v.value()))
+                                .unwrap_or(vk::RenderPass::null()),
+                        )
+                        .subpass(s.subpass)
+                        .framebuffer(
+                            s.framebuffer
+                                .and_then(|h| self.framebuffer_handles.get(&h).map(|v| *v.value()))
+                                .unwrap_or(vk::Framebuffer::null()),
+                        )
+                        .occlusion_query_enable(s.occlusion_query_enable)
+                        .query_flags(vk::QueryControlFlags::from_raw(s.query_flags))
+                        .pipeline_statistics(vk::QueryPipelineStatisticFlags::from_raw(s.pipeline_statistics));
+                    begin_info = vk::CommandBufferBeginInfo::default()
+                        .flags(vk::CommandBufferUsageFlags::from_raw(s.usage_flags))
+                        .inheritance_info(&inheritance);
+                }
 
                 if let Err(e) = unsafe { dev.begin_command_buffer(cb, &begin_info) } {
                     return Self::vk_err(e);
@@ -1809,77 +3255,9 @@ impl VulkanExecutor {
                             buffer_memory_barriers,
                             image_memory_barriers,
                         } => {
-                            let vk_mem_barriers: Vec<vk::MemoryBarrier> = memory_barriers
-                                .iter()
-                                .map(|mb| {
-                                    vk::MemoryBarrier::default()
-                                        .src_access_mask(vk::AccessFlags::from_raw(
-                                            mb.src_access_mask,
-                                        ))
-                                        .dst_access_mask(vk::AccessFlags::from_raw(
-                                            mb.dst_access_mask,
-                                        ))
-                                })
-                                .collect();
-
-                            let vk_buf_barriers: Vec<vk::BufferMemoryBarrier> =
-                                buffer_memory_barriers
-                                    .iter()
-                                    .map(|bmb| {
-                                        let buffer = self
-                                            .buffer_handles
-                                            .get(&bmb.buffer)
-                                            .map(|v| *v.value())
-                                            .unwrap_or(vk::Buffer::null());
-                                        vk::BufferMemoryBarrier::default()
-                                            .src_access_mask(vk::AccessFlags::from_raw(
-                                                bmb.src_access_mask,
-                                            ))
-                                            .dst_access_mask(vk::AccessFlags::from_raw(
-                                                bmb.dst_access_mask,
-                                            ))
-                                            .src_queue_family_index(bmb.src_queue_family_index)
-                                            .dst_queue_family_index(bmb.dst_queue_family_index)
-                                            .buffer(buffer)
-                                            .offset(bmb.offset)
-                                            .size(bmb.size)
-                                    })
-                                    .collect();
-
-                            let vk_img_barriers: Vec<vk::ImageMemoryBarrier> =
-                                image_memory_barriers
-                                    .iter()
-                                    .map(|imb| {
-                                        let image = self
-                                            .image_handles
-                                            .get(&imb.image)
-                                            .map(|v| *v.value())
-                                            .unwrap_or(vk::Image::null());
-                                        vk::ImageMemoryBarrier::default()
-                                            .src_access_mask(vk::AccessFlags::from_raw(
-                                                imb.src_access_mask,
-                                            ))
-                                            .dst_access_mask(vk::AccessFlags::from_raw(
-                                                imb.dst_access_mask,
-                                            ))
-                                            .old_layout(vk::ImageLayout::from_raw(imb.old_layout))
-                                            .new_layout(vk::ImageLayout::from_raw(imb.new_layout))
-                                            .src_queue_family_index(imb.src_queue_family_index)
-                                            .dst_queue_family_index(imb.dst_queue_family_index)
-                                            .image(image)
-                                            .subresource_range(vk::ImageSubresourceRange {
-                                                aspect_mask: vk::ImageAspectFlags::from_raw(
-                                                    imb.subresource_range.aspect_mask,
-                                                ),
-                                                base_mip_level: imb.subresource_range.base_mip_level,
-                                                level_count: imb.subresource_range.level_count,
-                                                base_array_layer: imb
-                                                    .subresource_range
-                                                    .base_array_layer,
-                                                layer_count: imb.subresource_range.layer_count,
-                                            })
-                                    })
-                                    .collect();
+                            let vk_mem_barriers = memory_barriers_of(memory_barriers);
+                            let vk_buf_barriers = self.buffer_barriers_of(buffer_memory_barriers);
+                            let vk_img_barriers = self.image_barriers_of(image_memory_barriers);
 
                             unsafe {
                                 dev.cmd_pipeline_barrier(
@@ -2196,46 +3574,530 @@ impl VulkanExecutor {
                                 );
                             }
                         }
-                    }
-                }
 
-                // End command buffer
-                if let Err(e) = unsafe { dev.end_command_buffer(cb) } {
-                    return Self::vk_err(e);
-                }
+                        RecordedCommand::ResetQueryPool {
+                            query_pool,
+                            first_query,
+                            query_count,
+                        } => {
+                            let pool = match self.query_pool_handles.get(query_pool) {
+                                Some(p) => *p.value(),
+                                None => continue,
+                            };
+                            unsafe { dev.cmd_reset_query_pool(cb, pool, *first_query, *query_count) };
+                        }
 
-                VulkanResponse::Success
-            }
+                        RecordedCommand::BeginQuery {
+                            query_pool,
+                            query,
+                            flags,
+                        } => {
+                            let pool = match self.query_pool_handles.get(query_pool) {
+                                Some(p) => *p.value(),
+                                None => continue,
+                            };
+                            unsafe {
+                                dev.cmd_begin_query(cb, pool, *query, vk::QueryControlFlags::from_raw(*flags))
+                            };
+                        }
 
-            // ── Fence ───────────────────────────────────────────
-            VulkanCommand::CreateFence { device, signaled } => {
-                let dev = match self.device_wrappers.get(&device) {
-                    Some(d) => d,
-                    None => {
-                        return VulkanResponse::Error {
-                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
-                            message: "invalid device handle".to_string(),
+                        RecordedCommand::EndQuery { query_pool, query } => {
+                            let pool = match self.query_pool_handles.get(query_pool) {
+                                Some(p) => *p.value(),
+                                None => continue,
+                            };
+                            unsafe { dev.cmd_end_query(cb, pool, *query) };
                         }
-                    }
-                };
 
-                let mut create_info = vk::FenceCreateInfo::default();
-                if signaled {
-                    create_info = create_info.flags(vk::FenceCreateFlags::SIGNALED);
-                }
+                        RecordedCommand::WriteTimestamp {
+                            pipeline_stage,
+                            query_pool,
+                            query,
+                        } => {
+                            let pool = match self.query_pool_handles.get(query_pool) {
+                                Some(p) => *p.value(),
+                                None => continue,
+                            };
+                            unsafe {
+                                dev.cmd_write_timestamp(
+                                    cb,
+                                    vk::PipelineStageFlags::from_raw(*pipeline_stage),
+                                    pool,
+                                    *query,
+                                )
+                            };
+                        }
 
-                match unsafe { dev.create_fence(&create_info, None) } {
-                    Ok(fence) => {
-                        let handle = session.alloc_handle(ResourceType::VkFence);
-                        self.fence_handles.insert(handle, fence);
-                        self.fence_to_device.insert(handle, device);
-                        VulkanResponse::FenceCreated { handle }
-                    }
-                    Err(e) => Self::vk_err(e),
-                }
-            }
+                        RecordedCommand::CopyQueryPoolResults {
+                            query_pool,
+                            first_query,
+                            query_count,
+                            dst_buffer,
+                            dst_offset,
+                            stride,
+                            flags,
+                        } => {
+                            let pool = match self.query_pool_handles.get(query_pool) {
+                                Some(p) => *p.value(),
+                                None => continue,
+                            };
+                            let buf = match self.buffer_handles.get(dst_buffer) {
+                                Some(b) => *b.value(),
+                                None => continue,
+                            };
+                            unsafe {
+                                dev.cmd_copy_query_pool_results(
+                                    cb,
+                                    pool,
+                                    *first_query,
+                                    *query_count,
+                                    buf,
+                                    *dst_offset,
+                                    *stride,
+                                    vk::QueryResultFlags::from_raw(*flags),
+                                )
+                            };
+                        }
 
-            VulkanCommand::DestroyFence { device, fence } => {
+                        RecordedCommand::ExecuteCommands { command_buffers } => {
+                            let secondaries: Vec<vk::CommandBuffer> = command_buffers
+                                .iter()
+                                .filter_map(|h| self.command_buffer_handles.get(h).map(|v| *v.value()))
+                                .collect();
+                            if !secondaries.is_empty() {
+                                unsafe { dev.cmd_execute_commands(cb, &secondaries) };
+                            }
+                        }
+
+                        RecordedCommand::DrawIndirect {
+                            buffer,
+                            offset,
+                            draw_count,
+                            stride,
+                        } => {
+                            let buf = match self.buffer_handles.get(buffer) {
+                                Some(b) => *b.value(),
+                                None => continue,
+                            };
+                            unsafe { dev.cmd_draw_indirect(cb, buf, *offset, *draw_count, *stride) };
+                        }
+
+                        RecordedCommand::DrawIndexedIndirect {
+                            buffer,
+                            offset,
+                            draw_count,
+                            stride,
+                        } => {
+                            let buf = match self.buffer_handles.get(buffer) {
+                                Some(b) => *b.value(),
+                                None => continue,
+                            };
+                            unsafe { dev.cmd_draw_indexed_indirect(cb, buf, *offset, *draw_count, *stride) };
+                        }
+
+                        RecordedCommand::DispatchIndirect { buffer, offset } => {
+                            let buf = match self.buffer_handles.get(buffer) {
+                                Some(b) => *b.value(),
+                                None => continue,
+                            };
+                            unsafe { dev.cmd_dispatch_indirect(cb, buf, *offset) };
+                        }
+
+                        RecordedCommand::DrawIndirectCount {
+                            buffer,
+                            offset,
+                            count_buffer,
+                            count_buffer_offset,
+                            max_draw_count,
+                            stride,
+                        } => {
+                            let (Some(buf), Some(count_buf)) = (
+                                self.buffer_handles.get(buffer).map(|b| *b.value()),
+                                self.buffer_handles.get(count_buffer).map(|b| *b.value()),
+                            ) else {
+                                continue;
+                            };
+                            if !self.has_device_fn(&dev_handle, &dev, c"vkCmdDrawIndirectCount") {
+                                warn!("vkCmdDrawIndirectCount needs a Vulkan 1.2 device, skipped");
+                                continue;
+                            }
+                            unsafe {
+                                dev.cmd_draw_indirect_count(
+                                    cb,
+                                    buf,
+                                    *offset,
+                                    count_buf,
+                                    *count_buffer_offset,
+                                    *max_draw_count,
+                                    *stride,
+                                )
+                            };
+                        }
+
+                        RecordedCommand::DrawIndexedIndirectCount {
+                            buffer,
+                            offset,
+                            count_buffer,
+                            count_buffer_offset,
+                            max_draw_count,
+                            stride,
+                        } => {
+                            let (Some(buf), Some(count_buf)) = (
+                                self.buffer_handles.get(buffer).map(|b| *b.value()),
+                                self.buffer_handles.get(count_buffer).map(|b| *b.value()),
+                            ) else {
+                                continue;
+                            };
+                            if !self.has_device_fn(&dev_handle, &dev, c"vkCmdDrawIndexedIndirectCount") {
+                                warn!("vkCmdDrawIndexedIndirectCount needs a Vulkan 1.2 device, skipped");
+                                continue;
+                            }
+                            unsafe {
+                                dev.cmd_draw_indexed_indirect_count(
+                                    cb,
+                                    buf,
+                                    *offset,
+                                    count_buf,
+                                    *count_buffer_offset,
+                                    *max_draw_count,
+                                    *stride,
+                                )
+                            };
+                        }
+
+                        RecordedCommand::CopyImage {
+                            src_image,
+                            src_image_layout,
+                            dst_image,
+                            dst_image_layout,
+                            regions,
+                        } => {
+                            let (Some(src), Some(dst)) = (
+                                self.image_handles.get(src_image).map(|i| *i.value()),
+                                self.image_handles.get(dst_image).map(|i| *i.value()),
+                            ) else {
+                                continue;
+                            };
+                            let vk_regions: Vec<vk::ImageCopy> = regions
+                                .iter()
+                                .map(|r| vk::ImageCopy {
+                                    src_subresource: subresource_layers(&r.src_subresource),
+                                    src_offset: offset_3d(&r.src_offset),
+                                    dst_subresource: subresource_layers(&r.dst_subresource),
+                                    dst_offset: offset_3d(&r.dst_offset),
+                                    extent: extent_3d(&r.extent),
+                                })
+                                .collect();
+                            unsafe {
+                                dev.cmd_copy_image(
+                                    cb,
+                                    src,
+                                    vk::ImageLayout::from_raw(*src_image_layout),
+                                    dst,
+                                    vk::ImageLayout::from_raw(*dst_image_layout),
+                                    &vk_regions,
+                                )
+                            };
+                        }
+
+                        RecordedCommand::BlitImage {
+                            src_image,
+                            src_image_layout,
+                            dst_image,
+                            dst_image_layout,
+                            regions,
+                            filter,
+                        } => {
+                            let (Some(src), Some(dst)) = (
+                                self.image_handles.get(src_image).map(|i| *i.value()),
+                                self.image_handles.get(dst_image).map(|i| *i.value()),
+                            ) else {
+                                continue;
+                            };
+                            let vk_regions: Vec<vk::ImageBlit> = regions
+                                .iter()
+                                .map(|r| vk::ImageBlit {
+                                    src_subresource: subresource_layers(&r.src_subresource),
+                                    src_offsets: [offset_3d(&r.src_offsets[0]), offset_3d(&r.src_offsets[1])],
+                                    dst_subresource: subresource_layers(&r.dst_subresource),
+                                    dst_offsets: [offset_3d(&r.dst_offsets[0]), offset_3d(&r.dst_offsets[1])],
+                                })
+                                .collect();
+                            unsafe {
+                                dev.cmd_blit_image(
+                                    cb,
+                                    src,
+                                    vk::ImageLayout::from_raw(*src_image_layout),
+                                    dst,
+                                    vk::ImageLayout::from_raw(*dst_image_layout),
+                                    &vk_regions,
+                                    vk::Filter::from_raw(*filter),
+                                )
+                            };
+                        }
+
+                        RecordedCommand::ResolveImage {
+                            src_image,
+                            src_image_layout,
+                            dst_image,
+                            dst_image_layout,
+                            regions,
+                        } => {
+                            let (Some(src), Some(dst)) = (
+                                self.image_handles.get(src_image).map(|i| *i.value()),
+                                self.image_handles.get(dst_image).map(|i| *i.value()),
+                            ) else {
+                                continue;
+                            };
+                            let vk_regions: Vec<vk::ImageResolve> = regions
+                                .iter()
+                                .map(|r| vk::ImageResolve {
+                                    src_subresource: subresource_layers(&r.src_subresource),
+                                    src_offset: offset_3d(&r.src_offset),
+                                    dst_subresource: subresource_layers(&r.dst_subresource),
+                                    dst_offset: offset_3d(&r.dst_offset),
+                                    extent: extent_3d(&r.extent),
+                                })
+                                .collect();
+                            unsafe {
+                                dev.cmd_resolve_image(
+                                    cb,
+                                    src,
+                                    vk::ImageLayout::from_raw(*src_image_layout),
+                                    dst,
+                                    vk::ImageLayout::from_raw(*dst_image_layout),
+                                    &vk_regions,
+                                )
+                            };
+                        }
+
+                        RecordedCommand::PushConstants {
+                            layout,
+                            stage_flags,
+                            offset,
+                            data,
+                        } => {
+                            let pl = match self.pipeline_layout_handles.get(layout) {
+                                Some(l) => *l.value(),
+                                None => continue,
+                            };
+                            unsafe {
+                                dev.cmd_push_constants(
+                                    cb,
+                                    pl,
+                                    vk::ShaderStageFlags::from_raw(*stage_flags),
+                                    *offset,
+                                    data,
+                                )
+                            };
+                        }
+
+                        RecordedCommand::SetEvent { event, stage_mask } => {
+                            let ev = match self.event_handles.get(event) {
+                                Some(e) => *e.value(),
+                                None => continue,
+                            };
+                            unsafe { dev.cmd_set_event(cb, ev, vk::PipelineStageFlags::from_raw(*stage_mask)) };
+                        }
+
+                        RecordedCommand::ResetEvent { event, stage_mask } => {
+                            let ev = match self.event_handles.get(event) {
+                                Some(e) => *e.value(),
+                                None => continue,
+                            };
+                            unsafe { dev.cmd_reset_event(cb, ev, vk::PipelineStageFlags::from_raw(*stage_mask)) };
+                        }
+
+                        RecordedCommand::WaitEvents {
+                            events,
+                            src_stage_mask,
+                            dst_stage_mask,
+                            memory_barriers,
+                            buffer_memory_barriers,
+                            image_memory_barriers,
+                        } => {
+                            let vk_events: Vec<vk::Event> = events
+                                .iter()
+                                .filter_map(|h| self.event_handles.get(h).map(|v| *v.value()))
+                                .collect();
+                            if vk_events.is_empty() {
+                                continue;
+                            }
+                            let vk_mem_barriers = memory_barriers_of(memory_barriers);
+                            let vk_buf_barriers = self.buffer_barriers_of(buffer_memory_barriers);
+                            let vk_img_barriers = self.image_barriers_of(image_memory_barriers);
+                            unsafe {
+                                dev.cmd_wait_events(
+                                    cb,
+                                    &vk_events,
+                                    vk::PipelineStageFlags::from_raw(*src_stage_mask),
+                                    vk::PipelineStageFlags::from_raw(*dst_stage_mask),
+                                    &vk_mem_barriers,
+                                    &vk_buf_barriers,
+                                    &vk_img_barriers,
+                                )
+                            };
+                        }
+
+                        RecordedCommand::PipelineBarrier2 { dependency_info } => {
+                            let Some(sync2) = &sync2 else {
+                                warn!("vkCmdPipelineBarrier2 needs synchronization2, skipped");
+                                continue;
+                            };
+                            let barriers = self.barriers2_of(dependency_info);
+                            unsafe { sync2.cmd_pipeline_barrier2(cb, &barriers.dependency_info()) };
+                        }
+
+                        RecordedCommand::SetEvent2 { event, dependency_info } => {
+                            let Some(sync2) = &sync2 else {
+                                warn!("vkCmdSetEvent2 needs synchronization2, skipped");
+                                continue;
+                            };
+                            let ev = match self.event_handles.get(event) {
+                                Some(e) => *e.value(),
+                                None => continue,
+                            };
+                            let barriers = self.barriers2_of(dependency_info);
+                            unsafe { sync2.cmd_set_event2(cb, ev, &barriers.dependency_info()) };
+                        }
+
+                        RecordedCommand::ResetEvent2 { event, stage_mask } => {
+                            let Some(sync2) = &sync2 else {
+                                warn!("vkCmdResetEvent2 needs synchronization2, skipped");
+                                continue;
+                            };
+                            let ev = match self.event_handles.get(event) {
+                                Some(e) => *e.value(),
+                                None => continue,
+                            };
+                            unsafe { sync2.cmd_reset_event2(cb, ev, vk::PipelineStageFlags2::from_raw(*stage_mask)) };
+                        }
+
+                        RecordedCommand::WaitEvents2 { events, dependency_infos } => {
+                            let Some(sync2) = &sync2 else {
+                                warn!("vkCmdWaitEvents2 needs synchronization2, skipped");
+                                continue;
+                            };
+                            // Each event comes with its own dependency info,
+                            // so unknown events drop theirs too
+                            let (vk_events, barriers): (Vec<vk::Event>, Vec<Barriers2>) = events
+                                .iter()
+                                .zip(dependency_infos)
+                                .filter_map(|(h, info)| {
+                                    let ev = *self.event_handles.get(h)?.value();
+                                    Some((ev, self.barriers2_of(info)))
+                                })
+                                .unzip();
+                            if vk_events.is_empty() {
+                                continue;
+                            }
+                            let infos: Vec<vk::DependencyInfo> = barriers.iter().map(Barriers2::dependency_info).collect();
+                            unsafe { sync2.cmd_wait_events2(cb, &vk_events, &infos) };
+                        }
+
+                        RecordedCommand::WriteTimestamp2 {
+                            stage,
+                            query_pool,
+                            query,
+                        } => {
+                            let Some(sync2) = &sync2 else {
+                                warn!("vkCmdWriteTimestamp2 needs synchronization2, skipped");
+                                continue;
+                            };
+                            let pool = match self.query_pool_handles.get(query_pool) {
+                                Some(p) => *p.value(),
+                                None => continue,
+                            };
+                            unsafe {
+                                sync2.cmd_write_timestamp2(cb, vk::PipelineStageFlags2::from_raw(*stage), pool, *query)
+                            };
+                        }
+
+                        RecordedCommand::BeginRendering { rendering_info } => {
+                            let Some(dynamic_rendering) = &dynamic_rendering else {
+                                warn!("vkCmdBeginRendering needs dynamic rendering, skipped");
+                                continue;
+                            };
+                            let color_attachments: Vec<vk::RenderingAttachmentInfo> = rendering_info
+                                .color_attachments
+                                .iter()
+                                .map(|a| self.rendering_attachment_of(a))
+                                .collect();
+                            let depth_attachment =
+                                rendering_info.depth_attachment.as_ref().map(|a| self.rendering_attachment_of(a));
+                            let stencil_attachment =
+                                rendering_info.stencil_attachment.as_ref().map(|a| self.rendering_attachment_of(a));
+                            let mut info = vk::RenderingInfo::default()
+                                .flags(vk::RenderingFlags::from_raw(rendering_info.flags))
+                                .render_area(vk::Rect2D {
+                                    offset: vk::Offset2D {
+                                        x: rendering_info.render_area.offset[0],
+                                        y: rendering_info.render_area.offset[1],
+                                    },
+                                    extent: vk::Extent2D {
+                                        width: rendering_info.render_area.extent[0],
+                                        height: rendering_info.render_area.extent[1],
+                                    },
+                                })
+                                .layer_count(rendering_info.layer_count)
+                                .view_mask(rendering_info.view_mask)
+                                .color_attachments(&color_attachments);
+                            if let Some(depth) = &depth_attachment {
+                                info = info.depth_attachment(depth);
+                            }
+                            if let Some(stencil) = &stencil_attachment {
+                                info = info.stencil_attachment(stencil);
+                            }
+                            unsafe { dynamic_rendering.cmd_begin_rendering(cb, &info) };
+                        }
+
+                        RecordedCommand::EndRendering => {
+                            let Some(dynamic_rendering) = &dynamic_rendering else {
+                                warn!("vkCmdEndRendering needs dynamic rendering, skipped");
+                                continue;
+                            };
+                            unsafe { dynamic_rendering.cmd_end_rendering(cb) };
+                        }
+                    }
+                }
+
+                // End command buffer
+                if let Err(e) = unsafe { dev.end_command_buffer(cb) } {
+                    return Self::vk_err(e);
+                }
+
+                VulkanResponse::Success
+            }
+
+            // ── Fence ───────────────────────────────────────────
+            VulkanCommand::CreateFence { device, signaled } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+
+                let mut create_info = vk::FenceCreateInfo::default();
+                if signaled {
+                    create_info = create_info.flags(vk::FenceCreateFlags::SIGNALED);
+                }
+
+                match unsafe { dev.create_fence(&create_info, None) } {
+                    Ok(fence) => {
+                        let handle = session.alloc_handle(ResourceType::VkFence);
+                        self.fence_handles.insert(handle, fence);
+                        self.fence_to_device.insert(handle, device);
+                        VulkanResponse::FenceCreated { handle }
+                    }
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::DestroyFence { device, fence } => {
                 let dev = match self.device_wrappers.get(&device) {
                     Some(d) => d,
                     None => return VulkanResponse::Success,
@@ -2361,6 +4223,8 @@ impl VulkanExecutor {
                 if !qfi.is_empty() {
                     image_ci = image_ci.queue_family_indices(qfi);
                 }
+                let mut chain = PNextChain::new(&ci.p_next);
+                image_ci.p_next = chain.head();
 
                 match unsafe { dev.create_image(&image_ci, None) } {
                     Ok(image) => {
@@ -2732,6 +4596,7 @@ impl VulkanExecutor {
             // ── Graphics Pipeline ──────────────────────────────────
             VulkanCommand::CreateGraphicsPipelines {
                 device,
+                pipeline_cache,
                 create_infos,
             } => {
                 let dev = match self.device_wrappers.get(&device) {
@@ -2765,6 +4630,8 @@ impl VulkanExecutor {
                 let mut all_cb_states: Vec<vk::PipelineColorBlendStateCreateInfo> = Vec::new();
                 let mut all_dyn_states_raw: Vec<Vec<vk::DynamicState>> = Vec::new();
                 let mut all_dyn_states: Vec<vk::PipelineDynamicStateCreateInfo> = Vec::new();
+                let mut all_color_formats: Vec<Vec<vk::Format>> = Vec::new();
+                let mut all_rendering_infos: Vec<vk::PipelineRenderingCreateInfo> = Vec::new();
 
                 // Phase 1: Collect all raw data into Vecs (no references created yet)
                 for ci in &create_infos {
@@ -2886,6 +4753,11 @@ impl VulkanExecutor {
                     } else {
                         all_dyn_states_raw.push(Vec::new());
                     }
+
+                    // Attachment formats for dynamic rendering
+                    all_color_formats.push(ci.rendering.as_ref().map_or_else(Vec::new, |r| {
+                        r.color_attachment_formats.iter().map(|&f| vk::Format::from_raw(f)).collect()
+                    }));
                 }
 
                 // Phase 2: Build all CreateInfo structs (now all Vecs are stable, no more pushes)
@@ -3021,10 +4893,24 @@ impl VulkanExecutor {
                     } else {
                         all_dyn_states.push(vk::PipelineDynamicStateCreateInfo::default());
                     }
+
+                    // Pipeline rendering info
+                    let rendering = ci.rendering.as_ref();
+                    all_rendering_infos.push(
+                        vk::PipelineRenderingCreateInfo::default()
+                            .view_mask(rendering.map_or(0, |r| r.view_mask))
+                            .color_attachment_formats(&all_color_formats[i])
+                            .depth_attachment_format(vk::Format::from_raw(
+                                rendering.map_or(0, |r| r.depth_attachment_format),
+                            ))
+                            .stencil_attachment_format(vk::Format::from_raw(
+                                rendering.map_or(0, |r| r.stencil_attachment_format),
+                            )),
+                    );
                 }
 
                 // Phase 3: Assemble final pipeline create infos
-                for (i, ci) in create_infos.iter().enumerate() {
+                for ((i, ci), rendering_info) in create_infos.iter().enumerate().zip(&mut all_rendering_infos) {
                     let layout = match self.pipeline_layout_handles.get(&ci.layout) {
                         Some(l) => *l.value(),
                         None => {
@@ -3034,14 +4920,18 @@ impl VulkanExecutor {
                             }
                         }
                     };
-                    let rp = match self.render_pass_handles.get(&ci.render_pass) {
-                        Some(r) => *r.value(),
-                        None => {
-                            return VulkanResponse::Error {
-                                code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
-                                message: "invalid render pass handle".to_string(),
+                    // Pipelines for dynamic rendering have no render pass
+                    let rp = match ci.render_pass {
+                        Some(h) => match self.render_pass_handles.get(&h) {
+                            Some(r) => *r.value(),
+                            None => {
+                                return VulkanResponse::Error {
+                                    code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                                    message: "invalid render pass handle".to_string(),
+                                }
                             }
-                        }
+                        },
+                        None => vk::RenderPass::null(),
                     };
 
                     let mut pipeline_ci = vk::GraphicsPipelineCreateInfo::default()
@@ -3064,12 +4954,16 @@ impl VulkanExecutor {
                         pipeline_ci = pipeline_ci.dynamic_state(&all_dyn_states[i]);
                     }
 
+                    if ci.rendering.is_some() {
+                        pipeline_ci = pipeline_ci.push_next(rendering_info);
+                    }
+
                     vk_create_infos.push(pipeline_ci);
                 }
 
                 match unsafe {
                     dev.create_graphics_pipelines(
-                        vk::PipelineCache::null(),
+                        self.pipeline_cache_for(&device, pipeline_cache),
                         &vk_create_infos,
                         None,
                     )
@@ -3098,7 +4992,7 @@ impl VulkanExecutor {
             }
 
             // ── Semaphore ──────────────────────────────────────────
-            VulkanCommand::CreateSemaphore { device } => {
+            VulkanCommand::CreateSemaphore { device, timeline } => {
                 let dev = match self.device_wrappers.get(&device) {
                     Some(d) => d,
                     None => {
@@ -3109,7 +5003,16 @@ impl VulkanExecutor {
                     }
                 };
 
-                let ci = vk::SemaphoreCreateInfo::default();
+                let mut type_info = vk::SemaphoreTypeCreateInfo::default()
+                    .semaphore_type(vk::SemaphoreType::TIMELINE)
+                    .initial_value(timeline.unwrap_or(0));
+                let mut ci = vk::SemaphoreCreateInfo::default();
+                if timeline.is_some() {
+                    if !self.timeline_semaphores.contains_key(&device) {
+                        return Self::vk_err(vk::Result::ERROR_FEATURE_NOT_PRESENT);
+                    }
+                    ci = ci.push_next(&mut type_info);
+                }
                 match unsafe { dev.create_semaphore(&ci, None) } {
                     Ok(sem) => {
                         let handle = session.alloc_handle(ResourceType::VkSemaphore);
@@ -3133,11 +5036,682 @@ impl VulkanExecutor {
                 }
                 VulkanResponse::Success
             }
-        }
-    }
 
-    /// Clean up all Vulkan resources owned by a disconnecting session.
-    /// Destroys in reverse-dependency order to avoid use-after-free.
+            // ── Event ──────────────────────────────────────────────
+            VulkanCommand::CreateEvent { device, flags } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+
+                let ci = vk::EventCreateInfo::default().flags(vk::EventCreateFlags::from_raw(flags));
+                match unsafe { dev.create_event(&ci, None) } {
+                    Ok(event) => {
+                        let handle = session.alloc_handle(ResourceType::VkEvent);
+                        self.event_handles.insert(handle, event);
+                        self.event_to_device.insert(handle, device);
+                        VulkanResponse::EventCreated { handle }
+                    }
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::DestroyEvent { device, event } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => return VulkanResponse::Success,
+                };
+                if let Some((_, ev)) = self.event_handles.remove(&event) {
+                    unsafe { dev.destroy_event(ev, None) };
+                    self.event_to_device.remove(&event);
+                    session.remove_handle(&event);
+                }
+                VulkanResponse::Success
+            }
+
+            VulkanCommand::GetEventStatus { device, event } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let ev = match self.event_handles.get(&event) {
+                    Some(e) => *e.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid event handle".to_string(),
+                        }
+                    }
+                };
+                match unsafe { dev.get_event_status(ev) } {
+                    Ok(set) => VulkanResponse::EventStatus { set },
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::SetEvent { device, event } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let ev = match self.event_handles.get(&event) {
+                    Some(e) => *e.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid event handle".to_string(),
+                        }
+                    }
+                };
+                match unsafe { dev.set_event(ev) } {
+                    Ok(()) => VulkanResponse::Success,
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::ResetEvent { device, event } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let ev = match self.event_handles.get(&event) {
+                    Some(e) => *e.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid event handle".to_string(),
+                        }
+                    }
+                };
+                match unsafe { dev.reset_event(ev) } {
+                    Ok(()) => VulkanResponse::Success,
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            // ── Sampler ────────────────────────────────────────────
+            VulkanCommand::CreateSampler { device, create_info } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+
+                let ci = vk::SamplerCreateInfo::default()
+                    .flags(vk::SamplerCreateFlags::from_raw(create_info.flags))
+                    .mag_filter(vk::Filter::from_raw(create_info.mag_filter))
+                    .min_filter(vk::Filter::from_raw(create_info.min_filter))
+                    .mipmap_mode(vk::SamplerMipmapMode::from_raw(create_info.mipmap_mode))
+                    .address_mode_u(vk::SamplerAddressMode::from_raw(create_info.address_mode_u))
+                    .address_mode_v(vk::SamplerAddressMode::from_raw(create_info.address_mode_v))
+                    .address_mode_w(vk::SamplerAddressMode::from_raw(create_info.address_mode_w))
+                    .mip_lod_bias(create_info.mip_lod_bias)
+                    .anisotropy_enable(create_info.anisotropy_enable)
+                    .max_anisotropy(create_info.max_anisotropy)
+                    .compare_enable(create_info.compare_enable)
+                    .compare_op(vk::CompareOp::from_raw(create_info.compare_op))
+                    .min_lod(create_info.min_lod)
+                    .max_lod(create_info.max_lod)
+                    .border_color(vk::BorderColor::from_raw(create_info.border_color))
+                    .unnormalized_coordinates(create_info.unnormalized_coordinates);
+                match unsafe { dev.create_sampler(&ci, None) } {
+                    Ok(sampler) => {
+                        let handle = session.alloc_handle(ResourceType::VkSampler);
+                        self.sampler_handles.insert(handle, sampler);
+                        self.sampler_to_device.insert(handle, device);
+                        VulkanResponse::SamplerCreated { handle }
+                    }
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::DestroySampler { device, sampler } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => return VulkanResponse::Success,
+                };
+                if let Some((_, s)) = self.sampler_handles.remove(&sampler) {
+                    unsafe { dev.destroy_sampler(s, None) };
+                    self.sampler_to_device.remove(&sampler);
+                    session.remove_handle(&sampler);
+                }
+                VulkanResponse::Success
+            }
+
+            // ── Pipeline Cache ─────────────────────────────────────
+            VulkanCommand::CreatePipelineCache { device, initial_data } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+
+                let ci = vk::PipelineCacheCreateInfo::default().initial_data(&initial_data);
+                match unsafe { dev.create_pipeline_cache(&ci, None) } {
+                    Ok(cache) => {
+                        // An empty cache starts from what the server has
+                        // compiled on this GPU before.
+                        if initial_data.is_empty() {
+                            if let Some(own) = self.device_pipeline_caches.get(&device) {
+                                let _ = unsafe { dev.merge_pipeline_caches(cache, &[own.cache]) };
+                            }
+                        }
+                        let handle = session.alloc_handle(ResourceType::VkPipelineCache);
+                        self.pipeline_cache_handles.insert(handle, cache);
+                        self.pipeline_cache_to_device.insert(handle, device);
+                        VulkanResponse::PipelineCacheCreated { handle }
+                    }
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::DestroyPipelineCache { device, pipeline_cache } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => return VulkanResponse::Success,
+                };
+                if let Some((_, cache)) = self.pipeline_cache_handles.remove(&pipeline_cache) {
+                    self.retire_pipeline_cache(&device, &dev, cache);
+                    self.pipeline_cache_to_device.remove(&pipeline_cache);
+                    session.remove_handle(&pipeline_cache);
+                }
+                VulkanResponse::Success
+            }
+
+            VulkanCommand::GetPipelineCacheData { device, pipeline_cache } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let cache = match self.pipeline_cache_handles.get(&pipeline_cache) {
+                    Some(c) => *c.value(),
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
+                            message: "invalid pipeline cache handle".to_string(),
+                        }
+                    }
+                };
+                match unsafe { dev.get_pipeline_cache_data(cache) } {
+                    Ok(data) => VulkanResponse::PipelineCacheData { data },
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::MergePipelineCaches {
+                device,
+                dst_cache,
+                src_caches,
+            } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let Some(dst) = self.pipeline_cache_handles.get(&dst_cache).map(|c| *c.value()) else {
+                    return VulkanResponse::Error {
+                        code: vk::Result::ERROR_INITIALIZATION_FAILED.as_raw(),
+                        message: "invalid pipeline cache handle".to_string(),
+                    };
+                };
+                let srcs: Vec<vk::PipelineCache> = src_caches
+                    .iter()
+                    .filter_map(|h| self.pipeline_cache_handles.get(h).map(|c| *c.value()))
+                    .collect();
+                match unsafe { dev.merge_pipeline_caches(dst, &srcs) } {
+                    Ok(()) => VulkanResponse::Success,
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            // ── Query Pool ─────────────────────────────────────────
+            VulkanCommand::CreateQueryPool {
+                device,
+                query_type,
+                query_count,
+                pipeline_statistics,
+            } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+
+                let ci = vk::QueryPoolCreateInfo::default()
+                    .query_type(vk::QueryType::from_raw(query_type))
+                    .query_count(query_count)
+                    .pipeline_statistics(vk::QueryPipelineStatisticFlags::from_raw(pipeline_statistics));
+                match unsafe { dev.create_query_pool(&ci, None) } {
+                    Ok(pool) => {
+                        let handle = session.alloc_handle(ResourceType::VkQueryPool);
+                        self.query_pool_handles.insert(handle, pool);
+                        self.query_pool_to_device.insert(handle, device);
+                        VulkanResponse::QueryPoolCreated { handle }
+                    }
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::DestroyQueryPool { device, query_pool } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => return VulkanResponse::Success,
+                };
+                if let Some((_, pool)) = self.query_pool_handles.remove(&query_pool) {
+                    unsafe { dev.destroy_query_pool(pool, None) };
+                    self.query_pool_to_device.remove(&query_pool);
+                    session.remove_handle(&query_pool);
+                }
+                VulkanResponse::Success
+            }
+
+            VulkanCommand::GetQueryPoolResults {
+                device,
+                query_pool,
+                first_query,
+                query_count,
+                data_size,
+                stride,
+                flags,
+            } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let pool = match self.query_pool_handles.get(&query_pool) {
+                    Some(p) => *p.value(),
+                    None => return Self::vk_err(vk::Result::ERROR_UNKNOWN),
+                };
+
+                // Any stride the application asks for, so the raw entry point.
+                let mut data = vec![0u8; data_size as usize];
+                let result = unsafe {
+                    (dev.fp_v1_0().get_query_pool_results)(
+                        dev.handle(),
+                        pool,
+                        first_query,
+                        query_count,
+                        data.len(),
+                        data.as_mut_ptr() as *mut std::ffi::c_void,
+                        stride,
+                        vk::QueryResultFlags::from_raw(flags),
+                    )
+                };
+                match result {
+                    vk::Result::SUCCESS | vk::Result::NOT_READY => VulkanResponse::QueryPoolResults {
+                        result: result.as_raw(),
+                        data,
+                    },
+                    e => Self::vk_err(e),
+                }
+            }
+
+            // ── Virtual Swapchain ──────────────────────────────────
+            VulkanCommand::CreateSwapchain { device, create_info } => {
+                self.create_swapchain(session, device, &create_info)
+            }
+
+            VulkanCommand::DestroySwapchain { device: _, swapchain } => {
+                if let Some((_, sc)) = self.swapchains.remove(&swapchain) {
+                    self.release_swapchain(&swapchain, &sc);
+                    for image in &sc.handles {
+                        session.remove_handle(image);
+                    }
+                    session.remove_handle(&swapchain);
+                }
+                VulkanResponse::Success
+            }
+
+            VulkanCommand::AcquireNextImage {
+                device,
+                swapchain,
+                semaphore,
+                fence,
+            } => {
+                let dev = match self.device_wrappers.get(&device) {
+                    Some(d) => d,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                            message: "invalid device handle".to_string(),
+                        }
+                    }
+                };
+                let mut sc = match self.swapchains.get_mut(&swapchain) {
+                    Some(sc) => sc,
+                    None => {
+                        return VulkanResponse::Error {
+                            code: vk::Result::ERROR_SURFACE_LOST_KHR.as_raw(),
+                            message: "invalid swapchain handle".to_string(),
+                        }
+                    }
+                };
+                let index = sc.next_image;
+                sc.next_image = (index + 1) % sc.images.len() as u32;
+
+                // Presenting reads the image back before it returns, so every
+                // image is free by the time it's acquired: signal right away.
+                let semaphores: Vec<vk::Semaphore> = semaphore
+                    .and_then(|h| self.semaphore_handles.get(&h).map(|v| *v.value()))
+                    .into_iter()
+                    .collect();
+                let vk_fence = fence
+                    .and_then(|h| self.fence_handles.get(&h).map(|v| *v.value()))
+                    .unwrap_or(vk::Fence::null());
+                if !semaphores.is_empty() || vk_fence != vk::Fence::null() {
+                    let submit = vk::SubmitInfo::default().signal_semaphores(&semaphores);
+                    if let Err(e) = unsafe { dev.queue_submit(sc.queue, &[submit], vk_fence) } {
+                        return Self::vk_err(e);
+                    }
+                }
+                VulkanResponse::ImageAcquired { index }
+            }
+
+            VulkanCommand::QueuePresent {
+                queue: _,
+                wait_semaphores,
+                presents,
+                delta,
+            } => self.present(&wait_semaphores, &presents, delta),
+
+            // ── Timeline Semaphores ────────────────────────────────
+            VulkanCommand::WaitSemaphores {
+                device,
+                semaphores,
+                values,
+                wait_any,
+                timeout_ns,
+            } => {
+                let timeline = match self.timeline_semaphores.get(&device) {
+                    Some(t) => t,
+                    None => return Self::vk_err(vk::Result::ERROR_FEATURE_NOT_PRESENT),
+                };
+                let vk_semaphores: Vec<vk::Semaphore> = semaphores
+                    .iter()
+                    .filter_map(|h| self.semaphore_handles.get(h).map(|v| *v.value()))
+                    .collect();
+                if vk_semaphores.len() != values.len() {
+                    return VulkanResponse::Error {
+                        code: vk::Result::ERROR_UNKNOWN.as_raw(),
+                        message: "invalid semaphore handle or value count".to_string(),
+                    };
+                }
+                let flags = if wait_any {
+                    vk::SemaphoreWaitFlags::ANY
+                } else {
+                    vk::SemaphoreWaitFlags::empty()
+                };
+                let wait_info = vk::SemaphoreWaitInfo::default()
+                    .flags(flags)
+                    .semaphores(&vk_semaphores)
+                    .values(&values);
+                match unsafe { timeline.wait_semaphores(&wait_info, timeout_ns) } {
+                    Ok(()) => VulkanResponse::SemaphoreWaitResult {
+                        result: vk::Result::SUCCESS.as_raw(),
+                    },
+                    Err(vk::Result::TIMEOUT) => VulkanResponse::SemaphoreWaitResult {
+                        result: vk::Result::TIMEOUT.as_raw(),
+                    },
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::SignalSemaphore {
+                device,
+                semaphore,
+                value,
+            } => {
+                let timeline = match self.timeline_semaphores.get(&device) {
+                    Some(t) => t,
+                    None => return Self::vk_err(vk::Result::ERROR_FEATURE_NOT_PRESENT),
+                };
+                let sem = match self.semaphore_handles.get(&semaphore) {
+                    Some(s) => *s.value(),
+                    None => return Self::vk_err(vk::Result::ERROR_UNKNOWN),
+                };
+                let signal_info = vk::SemaphoreSignalInfo::default().semaphore(sem).value(value);
+                match unsafe { timeline.signal_semaphore(&signal_info) } {
+                    Ok(()) => VulkanResponse::Success,
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+
+            VulkanCommand::GetSemaphoreCounterValue { device, semaphore } => {
+                let timeline = match self.timeline_semaphores.get(&device) {
+                    Some(t) => t,
+                    None => return Self::vk_err(vk::Result::ERROR_FEATURE_NOT_PRESENT),
+                };
+                let sem = match self.semaphore_handles.get(&semaphore) {
+                    Some(s) => *s.value(),
+                    None => return Self::vk_err(vk::Result::ERROR_UNKNOWN),
+                };
+                match unsafe { timeline.get_semaphore_counter_value(sem) } {
+                    Ok(value) => VulkanResponse::SemaphoreCounterValue { value },
+                    Err(e) => Self::vk_err(e),
+                }
+            }
+        }
+    }
+
+    fn create_swapchain(
+        &self,
+        session: &Session,
+        device: NetworkHandle,
+        info: &SerializedSwapchainCreateInfo,
+    ) -> VulkanResponse {
+        let format = vk::Format::from_raw(info.format);
+        if !SWAPCHAIN_FORMATS.contains(&format) {
+            return Self::vk_err(vk::Result::ERROR_FORMAT_NOT_SUPPORTED);
+        }
+        let dev = match self.device_wrappers.get(&device) {
+            Some(d) => d,
+            None => {
+                return VulkanResponse::Error {
+                    code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                    message: "invalid device handle".to_string(),
+                }
+            }
+        };
+        let mem_props = self
+            .device_physical
+            .get(&device)
+            .map(|e| *e.value())
+            .zip(self.device_to_instance.get(&device).map(|e| *e.value()))
+            .and_then(|((pd, queue_family), instance)| {
+                let wrapper = self.instance_wrappers.get(&instance)?;
+                Some((unsafe { wrapper.get_physical_device_memory_properties(pd) }, queue_family))
+            });
+        let Some((mem_props, queue_family)) = mem_props else {
+            return VulkanResponse::Error {
+                code: vk::Result::ERROR_DEVICE_LOST.as_raw(),
+                message: "device has no physical device".to_string(),
+            };
+        };
+
+        let mut sc = VirtualSwapchain {
+            device,
+            handles: Vec::new(),
+            images: Vec::new(),
+            image_memory: Vec::new(),
+            extent: vk::Extent2D {
+                width: info.extent[0].max(1),
+                height: info.extent[1].max(1),
+            },
+            format,
+            queue: vk::Queue::null(),
+            command_pool: vk::CommandPool::null(),
+            command_buffer: vk::CommandBuffer::null(),
+            fence: vk::Fence::null(),
+            readback: vk::Buffer::null(),
+            readback_memory: vk::DeviceMemory::null(),
+            readback_ptr: std::ptr::null(),
+            readback_coherent: false,
+            next_image: 0,
+            previous: None,
+        };
+        let image_count = info.min_image_count.clamp(1, MAX_SWAPCHAIN_IMAGES);
+        let usage = vk::ImageUsageFlags::from_raw(info.image_usage);
+        let image_bytes = match unsafe { sc.build(&dev, &mem_props, image_count, usage, queue_family) } {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                unsafe { sc.destroy(&dev) };
+                return Self::vk_err(e);
+            }
+        };
+
+        let handle = session.alloc_handle(ResourceType::VkSwapchain);
+        if let Some(info) = self.device_vram.get(&device) {
+            let charge = Charge {
+                session_id: session.session_id,
+                device: info.uuid,
+                api: Api::Vulkan,
+                bytes: image_bytes,
+            };
+            if let Err(e) = self.vram.try_charge(&charge) {
+                warn!(
+                    session_id = session.session_id,
+                    "swapchain of {} bytes refused: session VRAM quota {} bytes, {} in use",
+                    image_bytes, e.quota, e.used
+                );
+                unsafe { sc.destroy(&dev) };
+                session.remove_handle(&handle);
+                return Self::vk_err(vk::Result::ERROR_OUT_OF_DEVICE_MEMORY);
+            }
+            self.vram.track(handle, charge);
+        }
+
+        for image in &sc.images {
+            let image_handle = session.alloc_handle(ResourceType::VkImage);
+            self.image_handles.insert(image_handle, *image);
+            self.image_to_device.insert(image_handle, device);
+            sc.handles.push(image_handle);
+        }
+        let images = sc.handles.clone();
+        debug!(
+            "created {}x{} swapchain with {} images: {:?}",
+            sc.extent.width, sc.extent.height, image_count, handle
+        );
+        self.swapchains.insert(handle, sc);
+        VulkanResponse::SwapchainCreated { handle, images }
+    }
+
+    /// Destroy a swapchain that was taken out of `swapchains`.
+    fn release_swapchain(&self, handle: &NetworkHandle, sc: &VirtualSwapchain) {
+        for image in &sc.handles {
+            self.image_handles.remove(image);
+            self.image_to_device.remove(image);
+        }
+        if let Some(dev) = self.device_wrappers.get(&sc.device) {
+            unsafe { sc.destroy(&dev) };
+        }
+        self.vram.free(handle);
+    }
+
+    /// Read back the presented images, in order, once `wait_semaphores` are
+    /// signaled.
+    fn present(&self, wait_semaphores: &[NetworkHandle], presents: &[SerializedPresent], delta: bool) -> VulkanResponse {
+        let mut waits: Vec<vk::Semaphore> = wait_semaphores
+            .iter()
+            .filter_map(|h| self.semaphore_handles.get(h).map(|v| *v.value()))
+            .collect();
+        let mut frames = Vec::with_capacity(presents.len());
+        for present in presents {
+            let mut sc = match self.swapchains.get_mut(&present.swapchain) {
+                Some(sc) => sc,
+                None => {
+                    return VulkanResponse::Error {
+                        code: vk::Result::ERROR_SURFACE_LOST_KHR.as_raw(),
+                        message: "invalid swapchain handle".to_string(),
+                    }
+                }
+            };
+            let dev = match self.device_wrappers.get(&sc.device) {
+                Some(d) => d,
+                None => return Self::vk_err(vk::Result::ERROR_DEVICE_LOST),
+            };
+            let image = match sc.images.get(present.image_index as usize) {
+                Some(image) => *image,
+                None => {
+                    return VulkanResponse::Error {
+                        code: vk::Result::ERROR_OUT_OF_DATE_KHR.as_raw(),
+                        message: format!("swapchain has no image {}", present.image_index),
+                    }
+                }
+            };
+            let pixels = match unsafe { sc.read_back(&dev, image, &waits) } {
+                Ok(pixels) => pixels,
+                Err(e) => return Self::vk_err(e),
+            };
+            // The first copy waited for the semaphores, which unsignals them.
+            waits.clear();
+
+            let (encoding, data) = if delta {
+                let encoded = frame::encode(&pixels, sc.previous.as_deref());
+                sc.previous = Some(pixels);
+                encoded
+            } else {
+                (FrameEncoding::Raw, pixels)
+            };
+            frames.push(PresentedFrame {
+                swapchain: present.swapchain,
+                width: sc.extent.width,
+                height: sc.extent.height,
+                format: sc.format.as_raw(),
+                encoding,
+                data,
+            });
+        }
+        VulkanResponse::FramesPresented { frames }
+    }
+
+    /// Clean up all Vulkan resources owned by a disconnecting session.
+    /// Destroys in reverse-dependency order to avoid use-after-free.
     pub fn cleanup_session(&self, session: &Session) {
         let handles = session.all_handles();
         if handles.is_empty() {
@@ -3146,6 +5720,16 @@ impl VulkanExecutor {
 
         let mut cleaned = 0u32;
 
+        // Nothing may be destroyed while the GPU still uses it: let the
+        // session's devices finish what it submitted first.
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkDevice) {
+            if let Some(dev) = self.device_wrappers.get(h) {
+                if let Err(e) = unsafe { dev.device_wait_idle() } {
+                    warn!(session_id = session.session_id, "device {:?} didn't go idle: {:?}", h, e);
+                }
+            }
+        }
+
         // Helper: get ash::Device for a handle via *_to_device mapping
         macro_rules! cleanup_vk {
             ($handles:expr, $to_device:expr, $resource_type:expr, $destroy_fn:ident) => {
@@ -3165,8 +5749,9 @@ impl VulkanExecutor {
         // Pass 1: Framebuffers
         cleanup_vk!(self.framebuffer_handles, self.framebuffer_to_device, ResourceType::VkFramebuffer, destroy_framebuffer);
 
-        // Pass 2: ImageViews
+        // Pass 2: ImageViews, BufferViews
         cleanup_vk!(self.image_view_handles, self.image_view_to_device, ResourceType::VkImageView, destroy_image_view);
+        cleanup_vk!(self.buffer_view_handles, self.buffer_view_to_device, ResourceType::VkBufferView, destroy_buffer_view);
 
         // Pass 3: RenderPasses
         cleanup_vk!(self.render_pass_handles, self.render_pass_to_device, ResourceType::VkRenderPass, destroy_render_pass);
@@ -3182,24 +5767,46 @@ impl VulkanExecutor {
         // Remove any remaining descriptor sets and layouts
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkDescriptorSet) {
             self.desc_set_handles.remove(h);
+            self.desc_set_to_pool.remove(h);
         }
         cleanup_vk!(self.desc_set_layout_handles, self.desc_set_layout_to_device, ResourceType::VkDescriptorSetLayout, destroy_descriptor_set_layout);
 
-        // Pass 7: ShaderModules
+        // Pass 7: ShaderModules, Samplers, PipelineCaches (kept in the
+        // device's own cache first)
         cleanup_vk!(self.shader_module_handles, self.shader_to_device, ResourceType::VkShaderModule, destroy_shader_module);
+        cleanup_vk!(self.sampler_handles, self.sampler_to_device, ResourceType::VkSampler, destroy_sampler);
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkPipelineCache) {
+            if let Some((_, cache)) = self.pipeline_cache_handles.remove(h) {
+                if let Some((_, dev_handle)) = self.pipeline_cache_to_device.remove(h) {
+                    if let Some(dev) = self.device_wrappers.get(&dev_handle) {
+                        self.retire_pipeline_cache(&dev_handle, &dev, cache);
+                    }
+                }
+                cleaned += 1;
+            }
+        }
 
         // Pass 8: CommandPools (implicitly frees command buffers)
         cleanup_vk!(self.command_pool_handles, self.command_pool_to_device, ResourceType::VkCommandPool, destroy_command_pool);
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkCommandBuffer) {
             self.command_buffer_handles.remove(h);
             self.command_buffer_to_device.remove(h);
+            self.command_buffer_to_pool.remove(h);
         }
 
-        // Pass 9: Fences, Semaphores, Events
+        // Pass 9: Fences, Semaphores, Events, QueryPools
         cleanup_vk!(self.fence_handles, self.fence_to_device, ResourceType::VkFence, destroy_fence);
         cleanup_vk!(self.semaphore_handles, self.semaphore_to_device, ResourceType::VkSemaphore, destroy_semaphore);
+        cleanup_vk!(self.event_handles, self.event_to_device, ResourceType::VkEvent, destroy_event);
+        cleanup_vk!(self.query_pool_handles, self.query_pool_to_device, ResourceType::VkQueryPool, destroy_query_pool);
 
-        // Pass 10: Images
+        // Pass 10: Swapchains (with their images), then images
+        for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkSwapchain) {
+            if let Some((_, swapchain)) = self.swapchains.remove(h) {
+                self.release_swapchain(h, &swapchain);
+                cleaned += 1;
+            }
+        }
         cleanup_vk!(self.image_handles, self.image_to_device, ResourceType::VkImage, destroy_image);
 
         // Pass 11: Buffers
@@ -3214,6 +5821,7 @@ impl VulkanExecutor {
                     }
                 }
                 self.memory_info.remove(h);
+                self.vram.free(h);
                 cleaned += 1;
             }
         }
@@ -3221,15 +5829,22 @@ impl VulkanExecutor {
         // Pass 13: Queues (no destroy, just remove tracking)
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkQueue) {
             self.queue_handles.remove(h);
+            self.queue_to_device.remove(h);
         }
 
         // Pass 14: Devices
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkDevice) {
             if let Some((_, _raw)) = self.device_handles.remove(h) {
                 if let Some((_, dev_wrapper)) = self.device_wrappers.remove(h) {
+                    self.close_pipeline_cache(h, &dev_wrapper);
                     unsafe { dev_wrapper.destroy_device(None); }
                 }
                 self.device_to_instance.remove(h);
+                self.device_vram.remove(h);
+                self.device_physical.remove(h);
+                self.timeline_semaphores.remove(h);
+                self.synchronization2.remove(h);
+                self.dynamic_rendering.remove(h);
                 cleaned += 1;
             }
         }
@@ -3237,6 +5852,7 @@ impl VulkanExecutor {
         // Pass 15: PhysicalDevices (no destroy, just remove)
         for h in handles.iter().filter(|h| h.resource_type == ResourceType::VkPhysicalDevice) {
             self.physical_device_handles.remove(h);
+            self.virtual_physical_devices.remove(h);
         }
 
         // Pass 16: Instances
@@ -3257,3 +5873,37 @@ impl VulkanExecutor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle(resource_id: u64, resource_type: ResourceType) -> NetworkHandle {
+        NetworkHandle {
+            server_id: 0,
+            session_id: 1,
+            resource_id,
+            resource_type,
+        }
+    }
+
+    #[test]
+    fn test_destroyed_pool_forgets_its_children() {
+        let pool = handle(1, ResourceType::VkDescriptorPool);
+        let other_pool = handle(2, ResourceType::VkDescriptorPool);
+        let to_pool = DashMap::new();
+        for id in 10..13 {
+            to_pool.insert(handle(id, ResourceType::VkDescriptorSet), pool);
+        }
+        to_pool.insert(handle(20, ResourceType::VkDescriptorSet), other_pool);
+
+        let mut children = pool_children(&to_pool, &pool);
+        children.sort_by_key(|h| h.resource_id);
+        assert_eq!(children.iter().map(|h| h.resource_id).collect::<Vec<_>>(), [10, 11, 12]);
+        // Only the other pool's set is left.
+        assert_eq!(to_pool.len(), 1);
+        assert!(to_pool.contains_key(&handle(20, ResourceType::VkDescriptorSet)));
+
+        assert!(pool_children(&to_pool, &pool).is_empty());
+    }
+}
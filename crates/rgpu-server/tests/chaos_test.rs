@@ -0,0 +1,250 @@
+//! Integration test: fault injection (`chaos` feature)
+//!
+//! Runs a plain-TCP server with a `Chaos` attached and checks that commands
+//! are failed, dropped and delayed as configured, that sticky errors stick,
+//! and that non-GPU messages are left alone. One test puts the client
+//! daemon in front of the server and checks what an application sees
+//! through it. No GPU is needed: faults are decided before a command
+//! reaches the executors.
+//!
+//! Run with: cargo test -p rgpu-server --features chaos --test chaos_test
+
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::net::TcpStream;
+
+use rgpu_client::ClientDaemon;
+use rgpu_common::ipc::IpcClient;
+use rgpu_core::config::{ClientConfig, ServerConfig, ServerEndpoint};
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::messages::{Message, RequestId};
+use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
+use rgpu_server::chaos::{Chaos, ChaosConfig};
+use rgpu_server::RgpuServer;
+
+use common::{recv, send, WAIT};
+
+/// Start a server with `chaos` on a free local port.
+fn start_server(chaos: Arc<Chaos>) -> (u16, tokio::sync::watch::Sender<bool>) {
+    common::start(ServerConfig::default(), |config| {
+        RgpuServer::new(config, Vec::new()).with_chaos(chaos)
+    })
+}
+
+/// Start a server with `chaos` on a free local port and connect to it.
+async fn start(chaos: Arc<Chaos>) -> (TcpStream, tokio::sync::watch::Sender<bool>) {
+    let (port, shutdown) = start_server(chaos);
+    (common::connect(port).await, shutdown)
+}
+
+fn cuda_command(id: u64) -> Message {
+    Message::CudaCommand {
+        request_id: RequestId(id),
+        command: CudaCommand::DeviceGetCount,
+        deadline_ms: None,
+    }
+}
+
+fn injected_code(msg: Message) -> Option<i32> {
+    match msg {
+        Message::CudaResponse {
+            response: CudaResponse::Error { code, message },
+            ..
+        } if message == "injected fault" => Some(code),
+        _ => None,
+    }
+}
+
+#[tokio::test]
+async fn test_injected_errors() {
+    let chaos = Arc::new(Chaos::new(ChaosConfig {
+        error_rate: 1.0,
+        error_code: 700,
+        ..Default::default()
+    }));
+    let (mut stream, _shutdown) = start(chaos).await;
+
+    send(&mut stream, &cuda_command(1)).await;
+    match recv(&mut stream).await {
+        msg @ Message::CudaResponse { request_id, .. } => {
+            assert_eq!(request_id, RequestId(1));
+            assert_eq!(injected_code(msg), Some(700));
+        }
+        other => panic!("expected CudaResponse, got {:?}", other),
+    }
+
+    send(
+        &mut stream,
+        &Message::VulkanCommand {
+            request_id: RequestId(2),
+            command: VulkanCommand::EnumerateInstanceLayerProperties,
+            deadline_ms: None,
+        },
+    )
+    .await;
+    match recv(&mut stream).await {
+        Message::VulkanResponse {
+            response: VulkanResponse::Error { code, .. },
+            ..
+        } => assert_eq!(code, -4),
+        other => panic!("expected VulkanResponse error, got {:?}", other),
+    }
+
+    // Non-GPU messages are never faulted.
+    send(&mut stream, &Message::Ping).await;
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+}
+
+#[tokio::test]
+async fn test_sticky_error_persists() {
+    let chaos = Arc::new(Chaos::new(ChaosConfig {
+        error_rate: 1.0,
+        error_code: 700,
+        sticky: true,
+        ..Default::default()
+    }));
+    let (mut stream, _shutdown) = start(chaos.clone()).await;
+
+    send(&mut stream, &cuda_command(1)).await;
+    assert_eq!(injected_code(recv(&mut stream).await), Some(700));
+
+    // Turning injection off doesn't clear the sticky error.
+    chaos.set_default(ChaosConfig::default());
+    for id in 2..5 {
+        send(&mut stream, &cuda_command(id)).await;
+        assert_eq!(injected_code(recv(&mut stream).await), Some(700));
+    }
+
+    // Session IDs start at 1.
+    chaos.clear_session(1);
+    send(&mut stream, &cuda_command(5)).await;
+    assert_eq!(injected_code(recv(&mut stream).await), None);
+}
+
+#[tokio::test]
+async fn test_dropped_responses() {
+    let chaos = Arc::new(Chaos::new(ChaosConfig {
+        drop_rate: 1.0,
+        ..Default::default()
+    }));
+    let (mut stream, _shutdown) = start(chaos).await;
+
+    send(&mut stream, &cuda_command(1)).await;
+    send(&mut stream, &Message::Ping).await;
+    // The command's response never comes; the Ping's does.
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+    assert!(common::recv_within(&mut stream, Duration::from_millis(500)).await.is_none());
+}
+
+#[tokio::test]
+async fn test_delayed_commands() {
+    let chaos = Arc::new(Chaos::new(ChaosConfig {
+        delay_rate: 1.0,
+        delay: Duration::from_millis(300),
+        ..Default::default()
+    }));
+    let (mut stream, _shutdown) = start(chaos).await;
+
+    let start = Instant::now();
+    send(&mut stream, &cuda_command(1)).await;
+    match recv(&mut stream).await {
+        Message::CudaResponse { request_id, .. } => assert_eq!(request_id, RequestId(1)),
+        other => panic!("expected CudaResponse, got {:?}", other),
+    }
+    assert!(start.elapsed() >= Duration::from_millis(300));
+}
+
+/// Ask the daemon at `path` for the driver version, as an application would.
+async fn driver_version(path: &str) -> CudaResponse {
+    let path = path.to_string();
+    let request = tokio::task::spawn_blocking(move || {
+        let client = IpcClient::new(&path, "chaos-test");
+        client.send_and_receive(Message::CudaCommand {
+            request_id: client.next_request_id(),
+            command: CudaCommand::DriverGetVersion,
+            deadline_ms: None,
+        })
+    });
+    match tokio::time::timeout(WAIT, request).await {
+        Ok(Ok(Ok(Message::CudaResponse { response, .. }))) => response,
+        other => panic!("expected CudaResponse, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_daemon_passes_on_injected_errors() {
+    let chaos = Arc::new(Chaos::new(ChaosConfig {
+        error_rate: 1.0,
+        error_code: 700,
+        sticky: true,
+        ..Default::default()
+    }));
+    let (port, _shutdown) = start_server(chaos.clone());
+
+    // The daemon listens in $XDG_RUNTIME_DIR; nothing else in this binary
+    // reads the environment.
+    let runtime_dir = std::env::temp_dir().join(format!("rgpu-chaos-test-{}", std::process::id()));
+    std::fs::create_dir_all(&runtime_dir).unwrap();
+    std::env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+    std::env::set_var("RGPU_NO_AUTOSTART", "1");
+    let path = rgpu_common::platform::default_ipc_path();
+
+    let daemon = ClientDaemon::new(ClientConfig {
+        servers: vec![ServerEndpoint {
+            address: format!("127.0.0.1:{}", port),
+            token: "chaos-test".to_string(),
+            ca_cert: None,
+            transport: Default::default(),
+            rdma: Default::default(),
+        }],
+        include_local_gpus: false,
+        stable_gpu_indices: false,
+        ..Default::default()
+    });
+    tokio::spawn(async move { daemon.run().await.map_err(|e| e.to_string()) });
+    let deadline = Instant::now() + WAIT;
+    while !std::path::Path::new(&path).exists() {
+        assert!(Instant::now() < deadline, "the daemon didn't start listening");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    // The application sees the injected error as the call's result, and the
+    // sticky error outlives the fault that caused it.
+    let injected = |response| matches!(response, CudaResponse::Error { code: 700, .. });
+    assert!(injected(driver_version(&path).await));
+    chaos.set_default(ChaosConfig::default());
+    assert!(injected(driver_version(&path).await));
+
+    // Once cleared, the same connection works again.
+    chaos.clear_session(1);
+    match driver_version(&path).await {
+        CudaResponse::DriverVersion(_) => {}
+        other => panic!("expected DriverVersion, got {:?}", other),
+    }
+
+    let _ = std::fs::remove_dir_all(&runtime_dir);
+}
+
+#[test]
+fn test_parse_spec() {
+    let config: ChaosConfig = "drop=0.01, delay=0.5,delay_ms=250,error=0.02,code=700,sticky,seed=7"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        config,
+        ChaosConfig {
+            drop_rate: 0.01,
+            error_rate: 0.02,
+            delay_rate: 0.5,
+            delay: Duration::from_millis(250),
+            error_code: 700,
+            sticky: true,
+            seed: 7,
+        }
+    );
+    assert!("drop=2".parse::<ChaosConfig>().is_err());
+    assert!("bogus=1".parse::<ChaosConfig>().is_err());
+}
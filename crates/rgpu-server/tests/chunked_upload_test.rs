@@ -0,0 +1,121 @@
+//! Integration test: chunked uploads
+//!
+//! An authenticated session's upload chunks are acknowledged with the bytes
+//! held so far, and a `CudaCommandChunked` runs with the reassembled data.
+//! Chunks the server can't take are acknowledged with 0, and a command on
+//! an upload it doesn't hold gets an error. No GPU is needed: the one
+//! command that reaches the executor fails there on its unknown handle.
+//!
+//! Run with: cargo test -p rgpu-server --test chunked_upload_test
+
+mod common;
+
+use tokio::net::TcpStream;
+
+use rgpu_core::config::ServerConfig;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::handle::{NetworkHandle, ResourceType};
+use rgpu_protocol::messages::{Message, RequestId};
+use rgpu_server::RgpuServer;
+
+use common::{recv, send};
+
+const TOKEN: &str = "upload-secret";
+
+/// Start a server on a free local port and connect to it.
+async fn start() -> (TcpStream, tokio::sync::watch::Sender<bool>) {
+    let tokens = vec![common::token("upload")];
+    let (port, shutdown) = common::start(ServerConfig::default(), |config| RgpuServer::new(config, tokens));
+    (common::connect(port).await, shutdown)
+}
+
+async fn authenticate(stream: &mut TcpStream) {
+    let auth = Message::Authenticate {
+        token: TOKEN.to_string(),
+        challenge_response: Vec::new(),
+    };
+    send(stream, &auth).await;
+    match recv(stream).await {
+        Message::AuthResult { success: true, .. } => {}
+        other => panic!("expected successful AuthResult, got {:?}", other),
+    }
+}
+
+/// Send a chunk and return the bytes the server acknowledges.
+async fn chunk(stream: &mut TcpStream, transfer: u64, offset: u64, total: u64, data: Vec<u8>) -> u64 {
+    send(stream, &Message::TransferChunk { transfer, offset, total, data }).await;
+    match recv(stream).await {
+        Message::TransferAck { transfer: acked, received } if acked == transfer => received,
+        other => panic!("expected TransferAck for {}, got {:?}", transfer, other),
+    }
+}
+
+fn memcpy(transfer: u64, byte_count: u64) -> Message {
+    Message::CudaCommandChunked {
+        request_id: RequestId(7),
+        command: CudaCommand::MemcpyHtoD {
+            dst: NetworkHandle {
+                server_id: 0,
+                session_id: 0,
+                resource_id: 12345,
+                resource_type: ResourceType::CuDevicePtr,
+            },
+            src_data: Vec::new(),
+            byte_count,
+        },
+        deadline_ms: None,
+        transfer,
+    }
+}
+
+async fn error_message(stream: &mut TcpStream) -> String {
+    match recv(stream).await {
+        Message::CudaResponse {
+            request_id: RequestId(7),
+            response: CudaResponse::Error { message, .. },
+        } => message,
+        other => panic!("expected an error CudaResponse, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_chunked_uploads() {
+    let (mut stream, _shutdown) = start().await;
+
+    // Not before authentication
+    assert_eq!(chunk(&mut stream, 1, 0, 8, vec![1; 4]).await, 0);
+    authenticate(&mut stream).await;
+
+    // Pipelined chunks are acknowledged in turn
+    for offset in [0, 4, 8] {
+        send(
+            &mut stream,
+            &Message::TransferChunk {
+                transfer: 1,
+                offset,
+                total: 12,
+                data: vec![offset as u8; 4],
+            },
+        )
+        .await;
+    }
+    for held in [4, 8, 12] {
+        match recv(&mut stream).await {
+            Message::TransferAck { transfer: 1, received } => assert_eq!(received, held),
+            other => panic!("expected TransferAck, got {:?}", other),
+        }
+    }
+
+    // The command takes the upload; it fails in the executor, not for
+    // want of data, and the upload is gone afterwards.
+    send(&mut stream, &memcpy(1, 12)).await;
+    assert!(!error_message(&mut stream).await.contains("upload"));
+    send(&mut stream, &memcpy(1, 12)).await;
+    assert!(error_message(&mut stream).await.contains("upload 1"));
+
+    // A gap drops the upload
+    assert_eq!(chunk(&mut stream, 2, 0, 8, vec![2; 4]).await, 4);
+    assert_eq!(chunk(&mut stream, 2, 6, 8, vec![2; 2]).await, 0);
+    send(&mut stream, &memcpy(2, 8)).await;
+    assert!(error_message(&mut stream).await.contains("upload 2"));
+}
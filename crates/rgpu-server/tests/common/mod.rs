@@ -0,0 +1,106 @@
+//! Fixtures shared by the server's integration tests. Each test binary uses
+//! some of them.
+
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+
+use rgpu_core::config::{ServerConfig, TokenEntry};
+use rgpu_protocol::messages::Message;
+use rgpu_protocol::wire;
+use rgpu_server::RgpuServer;
+
+/// How long a test waits for an answer.
+pub const WAIT: Duration = Duration::from_secs(5);
+
+/// A local TCP port nothing listens on.
+pub fn free_port() -> u16 {
+    std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port()
+}
+
+/// A token named `name` that allows everything, with `<name>-secret` as the
+/// secret.
+pub fn token(name: &str) -> TokenEntry {
+    TokenEntry {
+        token: format!("{}-secret", name),
+        name: name.to_string(),
+        allowed_gpus: None,
+        max_memory: None,
+        allow_cuda: true,
+        allow_vulkan: true,
+        allow_ptx: true,
+        max_sessions: None,
+        retry_alloc_after_trim: false,
+        labels: Default::default(),
+    }
+}
+
+/// Run `server` in the background until the returned sender is set.
+pub fn spawn(server: RgpuServer) -> watch::Sender<bool> {
+    let server = Arc::new(server);
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        let _ = server.run_with_shutdown(shutdown_rx).await;
+    });
+    shutdown_tx
+}
+
+/// Start the server `build` makes from `config` on a free local port, and
+/// return the port. [`connect`] waits for the server to come up; nothing
+/// connects before, so the first connection gets the first session.
+pub fn start(
+    config: ServerConfig,
+    build: impl FnOnce(ServerConfig) -> RgpuServer,
+) -> (u16, watch::Sender<bool>) {
+    let port = free_port();
+    let config = ServerConfig {
+        bind: "127.0.0.1".to_string(),
+        port,
+        ..config
+    };
+    (port, spawn(build(config)))
+}
+
+/// Connect to `port` on localhost, retrying while the server starts.
+pub async fn connect(port: u16) -> TcpStream {
+    for _ in 0..50 {
+        if let Ok(stream) = TcpStream::connect(("127.0.0.1", port)).await {
+            return stream;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("server did not start on port {}", port);
+}
+
+pub async fn send(stream: &mut TcpStream, msg: &Message) {
+    let frame = wire::encode_message(msg, wire::request_tag(msg)).unwrap();
+    stream.write_all(&frame).await.unwrap();
+}
+
+pub async fn recv(stream: &mut TcpStream) -> Message {
+    recv_with_flags(stream).await.0
+}
+
+pub async fn recv_with_flags(stream: &mut TcpStream) -> (Message, wire::FrameFlags) {
+    recv_within(stream, WAIT).await.expect("no response")
+}
+
+/// The next message from the server and its flags, or `None` if none comes
+/// within `wait`.
+pub async fn recv_within(stream: &mut TcpStream, wait: Duration) -> Option<(Message, wire::FrameFlags)> {
+    let mut header = [0u8; wire::HEADER_SIZE];
+    tokio::time::timeout(wait, stream.read_exact(&mut header)).await.ok()?.unwrap();
+    let (flags, _, len) = wire::decode_header(&header).unwrap();
+    let mut payload = vec![0u8; len as usize];
+    stream.read_exact(&mut payload).await.unwrap();
+    Some((wire::decode_message(&payload, flags).unwrap(), flags))
+}
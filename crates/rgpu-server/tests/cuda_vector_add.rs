@@ -12,7 +12,9 @@
 //!
 //! Run with: cargo test --test cuda_vector_add -- --nocapture
 
-use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::compat::Feature;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse, KernelParam};
+use rgpu_protocol::messages::PROTOCOL_VERSION;
 use rgpu_server::cuda_executor::CudaExecutor;
 use rgpu_server::gpu_discovery;
 use rgpu_server::session::Session;
@@ -105,7 +107,7 @@ fn test_cuda_driver_loads() {
 
 #[test]
 fn test_cuda_executor_device_enumeration() {
-    let gpu_infos = gpu_discovery::discover_gpus();
+    let gpu_infos = gpu_discovery::discover_gpus(0);
     let executor = CudaExecutor::new(gpu_infos.clone());
     let session = Session::new(1, 0, "test".to_string());
 
@@ -169,9 +171,10 @@ fn test_cuda_vector_add() {
         return;
     }
 
-    let gpu_infos = gpu_discovery::discover_gpus();
+    let gpu_infos = gpu_discovery::discover_gpus(0);
     let executor = CudaExecutor::new(gpu_infos);
     let session = Session::new(1, 0, "test".to_string());
+    session.set_protocol_version(PROTOCOL_VERSION);
 
     // Init
     let resp = executor.execute(&session, CudaCommand::Init { flags: 0 });
@@ -223,13 +226,26 @@ fn test_cuda_vector_add() {
         },
     );
     let func_handle = match resp {
-        CudaResponse::Function(h) => {
-            println!("function obtained: {:?}", h);
-            h
+        CudaResponse::FunctionWithParams { handle, param_sizes } => {
+            println!("function obtained: {:?}", handle);
+            assert_eq!(param_sizes, Some(vec![8, 8, 8, 4]));
+            handle
         }
         other => panic!("ModuleGetFunction failed: {:?}", other),
     };
 
+    // Clients from before parameter sizes get the handle alone
+    session.set_protocol_version(Feature::KernelParamSizes.since() - 1);
+    let resp = executor.execute(
+        &session,
+        CudaCommand::ModuleGetFunction {
+            module: module_handle,
+            name: "vector_add".to_string(),
+        },
+    );
+    assert!(matches!(resp, CudaResponse::Function(_)), "ModuleGetFunction failed: {:?}", resp);
+    session.set_protocol_version(PROTOCOL_VERSION);
+
     // Prepare test data
     let n: u32 = 1024;
     let size = (n as u64) * 4; // 4 bytes per float32
@@ -301,35 +317,66 @@ fn test_cuda_vector_add() {
 
     // Launch kernel
     // Kernel params: float* a, float* b, float* c, int n
-    // We need to pass the actual device pointers as kernel parameters.
-    // The executor maps our NetworkHandles to real device pointers internally.
-    // For kernel params, we serialize the device pointer values.
-    // Since we're calling the executor directly (not via network), we need to
-    // construct KernelParam entries that the executor can interpret.
-
-    // The kernel expects: (CUdeviceptr a, CUdeviceptr b, CUdeviceptr c, int n)
-    // When going through the network, kernel params are opaque bytes.
-    // The server-side executor already has the real device pointers in its handle maps.
-    // We need to pass the raw handle resource_ids which the executor can look up.
-
-    // Actually, the kernel params should contain the VALUES that will be passed to the kernel.
-    // In the CUDA driver API, kernel_params[i] is a pointer to the i-th parameter's value.
-    // On the server side, the executor constructs param pointers from the KernelParam data.
-    // So we need to send the actual device pointer values as they exist on the server.
-
-    // For this direct test, we'll use a workaround: pass the NetworkHandle data
-    // which the executor will resolve to actual device pointers before launch.
-
-    // Actually, looking at the executor code, it directly uses the KernelParam.data
-    // as the parameter bytes. So for device pointers, we need to send the actual
-    // GPU addresses. But we don't know those on the client side!
-
-    // This is a fundamental design challenge. For the direct test, let's test
-    // the simpler operations (alloc, memcpy, free) and verify those work.
-    // The kernel launch with proper parameter passing will need the device pointers
-    // to be resolved on the server side.
+    // Device pointers are sent as the allocation's handle plus an offset;
+    // the executor substitutes the real address.
+    let device_ptr = |handle| KernelParam {
+        data: 0u64.to_le_bytes().to_vec(),
+        device_ptr: Some(handle),
+    };
+    let resp = executor.execute(
+        &session,
+        CudaCommand::LaunchKernel {
+            func: func_handle,
+            grid_dim: [n.div_ceil(256), 1, 1],
+            block_dim: [256, 1, 1],
+            shared_mem_bytes: 0,
+            stream: stream_handle,
+            kernel_params: vec![
+                device_ptr(d_a),
+                device_ptr(d_b),
+                device_ptr(d_c),
+                KernelParam {
+                    data: n.to_le_bytes().to_vec(),
+                    device_ptr: None,
+                },
+            ],
+        },
+    );
+    assert!(
+        matches!(resp, CudaResponse::Success),
+        "LaunchKernel failed: {:?}",
+        resp
+    );
+    let resp = executor.execute(
+        &session,
+        CudaCommand::StreamSynchronize {
+            stream: stream_handle,
+        },
+    );
+    assert!(
+        matches!(resp, CudaResponse::Success),
+        "StreamSynchronize failed: {:?}",
+        resp
+    );
 
-    // For now, let's verify memory operations work correctly.
+    let resp = executor.execute(
+        &session,
+        CudaCommand::MemcpyDtoH {
+            src: d_c,
+            byte_count: size,
+        },
+    );
+    match resp {
+        CudaResponse::MemoryData(data) => {
+            let result: Vec<f32> = data
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+            assert_eq!(result, expected, "vector_add produced wrong results");
+            println!("vector_add verified for {} elements", n);
+        }
+        other => panic!("MemcpyDtoH c failed: {:?}", other),
+    }
 
     // Copy data back from device to verify memcpy works
     let resp = executor.execute(
@@ -399,6 +446,7 @@ fn test_cuda_vector_add() {
     println!("Memory allocation: PASS");
     println!("Memory copy HtoD: PASS");
     println!("Memory copy DtoH: PASS");
+    println!("Kernel launch: PASS");
     println!("Memory roundtrip verification: PASS");
     println!("Stream management: PASS");
     println!("Resource cleanup: PASS");
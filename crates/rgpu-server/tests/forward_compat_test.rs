@@ -0,0 +1,244 @@
+//! Integration test: messages the server can't decode or doesn't handle
+//!
+//! A peer on a newer protocol version may send message or command variants
+//! this server doesn't know. Each must fail on its own, answered in turn
+//! with an error tied to its request, while the connection and every other
+//! request on it carry on. No GPU is needed: nothing here reaches the
+//! executors.
+//!
+//! Run with: cargo test -p rgpu-server --test forward_compat_test
+
+mod common;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use rgpu_core::config::{Codec, CompressionConfig, ServerConfig};
+use rgpu_protocol::compat;
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::error::ProtocolError;
+use rgpu_protocol::messages::{self, ArchivedMessage, Message, RequestId};
+use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
+use rgpu_protocol::wire;
+use rgpu_server::RgpuServer;
+
+use common::{recv, recv_with_flags, send};
+
+/// Start a server on a free local port and connect to it.
+async fn start() -> (TcpStream, tokio::sync::watch::Sender<bool>) {
+    start_with(ServerConfig::default()).await
+}
+
+async fn start_with(config: ServerConfig) -> (TcpStream, tokio::sync::watch::Sender<bool>) {
+    let (port, shutdown) = common::start(config, |config| RgpuServer::new(config, Vec::new()));
+    (common::connect(port).await, shutdown)
+}
+
+/// `payload` in a frame with no flags.
+fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&wire::MAGIC);
+    frame.push(0);
+    frame.extend_from_slice(&0u32.to_le_bytes());
+    frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// `msg` framed as a peer with a newer protocol would send it if `msg` were
+/// a variant this build doesn't have: its tag rewritten to one past the end.
+fn from_the_future(msg: &Message) -> Vec<u8> {
+    let mut frame = wire::encode_message(msg, wire::request_tag(msg)).unwrap();
+    // Small payloads aren't compressed; the archived root is at the end and
+    // starts with the variant tag.
+    let root = frame.len() - std::mem::size_of::<ArchivedMessage>();
+    frame[root] = 0xff;
+    frame
+}
+
+#[tokio::test]
+async fn test_unknown_variant_fails_only_its_request() {
+    let (mut stream, _shutdown) = start().await;
+
+    let command = Message::CudaCommand {
+        request_id: RequestId(7),
+        command: CudaCommand::DeviceGetCount,
+        deadline_ms: None,
+    };
+    stream.write_all(&from_the_future(&command)).await.unwrap();
+    send(&mut stream, &Message::Ping).await;
+
+    match recv(&mut stream).await {
+        Message::Unsupported { request_id, reason } => {
+            assert_eq!(request_id, RequestId(7));
+            assert!(reason.contains("decode"), "{}", reason);
+        }
+        other => panic!("expected Unsupported, got {:?}", other),
+    }
+    // Answered in order, on the same connection.
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+}
+
+#[tokio::test]
+async fn test_untagged_garbage_gets_plain_error() {
+    let (mut stream, _shutdown) = start().await;
+
+    stream.write_all(&frame(&[0xab; 64])).await.unwrap();
+
+    assert!(matches!(
+        recv(&mut stream).await,
+        Message::Error(ProtocolError::UnsupportedCommand(_))
+    ));
+    send(&mut stream, &Message::Ping).await;
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+}
+
+#[tokio::test]
+async fn test_unhandled_message_is_answered() {
+    let (mut stream, _shutdown) = start().await;
+
+    // A response type is never a request a server handles.
+    send(
+        &mut stream,
+        &Message::CudaResponse {
+            request_id: RequestId(9),
+            response: CudaResponse::Success,
+        },
+    )
+    .await;
+    match recv(&mut stream).await {
+        Message::Unsupported { request_id, .. } => assert_eq!(request_id, RequestId(9)),
+        other => panic!("expected Unsupported, got {:?}", other),
+    }
+    send(&mut stream, &Message::Ping).await;
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+}
+
+#[tokio::test]
+async fn test_zstd_for_peers_that_announce_it() {
+    let config = ServerConfig {
+        compression: CompressionConfig {
+            codec: Codec::Zstd,
+            level: 3,
+            threshold: 1024,
+        },
+        ..Default::default()
+    };
+    let (mut stream, _shutdown) = start_with(config).await;
+
+    let hello = Message::Hello {
+        protocol_version: messages::PROTOCOL_VERSION,
+        name: "test".to_string(),
+        challenge: None,
+    };
+    send(&mut stream, &hello).await;
+    assert!(matches!(recv(&mut stream).await, Message::Hello { .. }));
+
+    let payload = vec![7u8; 64 * 1024];
+    send(&mut stream, &Message::Echo(payload.clone())).await;
+    let (echo, flags) = recv_with_flags(&mut stream).await;
+    assert!(matches!(echo, Message::Echo(data) if data == payload));
+    assert!(flags.contains(wire::FrameFlags::COMPRESSED | wire::FrameFlags::ZSTD));
+}
+
+#[tokio::test]
+async fn test_peers_older_than_the_minimum_are_refused() {
+    let (mut stream, _shutdown) = start().await;
+
+    let hello = Message::Hello {
+        protocol_version: compat::MIN_PROTOCOL_VERSION - 1,
+        name: "test".to_string(),
+        challenge: None,
+    };
+    send(&mut stream, &hello).await;
+    match recv(&mut stream).await {
+        Message::Error(ProtocolError::VersionMismatch(reason)) => {
+            assert!(reason.contains(&format!("v{}", compat::MIN_PROTOCOL_VERSION)))
+        }
+        other => panic!("expected VersionMismatch, got {:?}", other),
+    }
+}
+
+/// Hello payloads as captured from older builds; see rgpu-protocol's compat
+/// tests for what they hold.
+const V3_HELLO: &[u8] = include_bytes!("../../rgpu-protocol/testdata/v3_hello.bin");
+const V55_HELLO: &[u8] = include_bytes!("../../rgpu-protocol/testdata/v55_hello.bin");
+
+#[tokio::test]
+async fn test_hello_bytes_of_older_peers() {
+    // The oldest version the server still bridges to is understood
+    let (mut stream, _shutdown) = start().await;
+    stream.write_all(&frame(V55_HELLO)).await.unwrap();
+    match recv(&mut stream).await {
+        Message::Hello { protocol_version, .. } => assert_eq!(protocol_version, messages::PROTOCOL_VERSION),
+        other => panic!("expected Hello, got {:?}", other),
+    }
+
+    // Messages from before the layout changes don't decode, so a v3 peer is
+    // refused rather than misread
+    let (mut stream, _shutdown) = start().await;
+    stream.write_all(&frame(V3_HELLO)).await.unwrap();
+    assert!(matches!(recv(&mut stream).await, Message::Error(_)));
+    send(&mut stream, &Message::Ping).await;
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+}
+
+#[tokio::test]
+async fn test_rdma_refused_without_rdma_transport() {
+    let (mut stream, _shutdown) = start().await;
+
+    let endpoint = messages::RdmaEndpoint {
+        lid: 1,
+        gid: [0; 16],
+        qp_num: 42,
+        psn: 7,
+        ring_address: 0x1000,
+        ring_key: 3,
+        ring_size: 64 * 1024 * 1024,
+    };
+    send(&mut stream, &Message::RdmaConnect(endpoint)).await;
+    assert!(matches!(
+        recv(&mut stream).await,
+        Message::Error(ProtocolError::NotImplemented(_))
+    ));
+    // The client stays on TCP.
+    send(&mut stream, &Message::Ping).await;
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+}
+
+#[test]
+fn test_unsupported_becomes_error_of_request_kind() {
+    let cuda = Message::CudaCommand {
+        request_id: RequestId(3),
+        command: CudaCommand::DeviceGetCount,
+        deadline_ms: None,
+    };
+    match compat::unsupported_response(&cuda, "new".to_string()) {
+        Message::CudaResponse {
+            request_id,
+            response: CudaResponse::Error { code, .. },
+        } => {
+            assert_eq!(request_id, RequestId(3));
+            assert_eq!(code, 801);
+        }
+        other => panic!("expected CudaResponse, got {:?}", other),
+    }
+
+    let vulkan = Message::VulkanCommand {
+        request_id: RequestId(4),
+        command: VulkanCommand::EnumerateInstanceLayerProperties,
+        deadline_ms: None,
+    };
+    assert!(matches!(
+        compat::unsupported_response(&vulkan, "new".to_string()),
+        Message::VulkanResponse {
+            request_id: RequestId(4),
+            response: VulkanResponse::Error { code: -8, .. },
+        }
+    ));
+
+    assert!(matches!(
+        compat::unsupported_response(&Message::Ping, "new".to_string()),
+        Message::Error(ProtocolError::UnsupportedCommand(_))
+    ));
+}
@@ -0,0 +1,83 @@
+//! Integration test: flood protection on the listener
+//!
+//! Clients that open connections too fast or send messages over
+//! `max_message_mb` are answered with protocol errors, and the server goes
+//! on serving everyone else. No GPU is needed.
+//!
+//! Run with: cargo test -p rgpu-server --test limits_test
+
+mod common;
+
+use tokio::net::TcpStream;
+
+use rgpu_core::config::{LimitsConfig, ServerConfig};
+use rgpu_protocol::error::ProtocolError;
+use rgpu_protocol::messages::{Message, RequestId};
+use rgpu_server::RgpuServer;
+
+use common::{recv, send};
+
+/// Start a server with `limits` on a free local port.
+fn start(limits: LimitsConfig) -> (u16, tokio::sync::watch::Sender<bool>) {
+    let config = ServerConfig {
+        limits,
+        ..Default::default()
+    };
+    common::start(config, |config| RgpuServer::new(config, Vec::new()))
+}
+
+#[tokio::test]
+async fn test_oversized_message_fails_alone() {
+    let (port, _shutdown) = start(LimitsConfig {
+        max_message_mb: 1,
+        ..Default::default()
+    });
+    let mut stream = common::connect(port).await;
+
+    // Random bytes, so compression can't bring it under the limit.
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let payload: Vec<u8> = (0..2 * 1024 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let command = Message::CudaCommand {
+        request_id: RequestId(11),
+        command: rgpu_protocol::cuda_commands::CudaCommand::ModuleLoadData { image: payload },
+        deadline_ms: None,
+    };
+    send(&mut stream, &command).await;
+    match recv(&mut stream).await {
+        Message::Unsupported { request_id, reason } => {
+            assert_eq!(request_id, RequestId(11));
+            assert!(reason.contains("too large"), "{}", reason);
+        }
+        other => panic!("expected Unsupported, got {:?}", other),
+    }
+    send(&mut stream, &Message::Ping).await;
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+}
+
+#[tokio::test]
+async fn test_connection_flood_is_refused() {
+    let (port, _shutdown) = start(LimitsConfig {
+        connections_per_minute: 3,
+        ..Default::default()
+    });
+
+    let mut kept = common::connect(port).await;
+    let _second = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let _third = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let mut refused = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    match recv(&mut refused).await {
+        Message::Error(ProtocolError::ConnectionFailed(reason)) => assert!(reason.contains("per minute"), "{}", reason),
+        other => panic!("expected a refusal, got {:?}", other),
+    }
+
+    // Connections already admitted are unaffected.
+    send(&mut kept, &Message::Ping).await;
+    assert!(matches!(recv(&mut kept).await, Message::Pong));
+}
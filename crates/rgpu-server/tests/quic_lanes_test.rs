@@ -0,0 +1,119 @@
+//! Integration test: requests on QUIC lanes
+//!
+//! A lane is a QUIC stream the client keeps open for the requests of one
+//! CUDA stream. The server must answer every request on it, in order, while
+//! other streams are served alongside. No GPU is needed: the requests here
+//! never reach the executors.
+//!
+//! Run with: cargo test -p rgpu-server --test quic_lanes_test
+
+mod common;
+
+use std::time::Duration;
+
+use rgpu_core::config::{ServerConfig, TransportMode};
+use rgpu_protocol::messages::{Message, PROTOCOL_VERSION};
+use rgpu_protocol::wire::CompressionStats;
+use rgpu_server::RgpuServer;
+use rgpu_transport::quic::{connect_quic_client, QuicConnection};
+
+use common::WAIT;
+
+/// Start a QUIC server with a fresh self-signed certificate on a free port.
+fn start() -> (String, tokio::sync::watch::Sender<bool>) {
+    let config = ServerConfig {
+        transport: TransportMode::Quic,
+        ..Default::default()
+    };
+    let (port, shutdown) = common::start(config, |config| {
+        let dir = std::env::temp_dir().join(format!("rgpu-quic-lanes-{}-{}", std::process::id(), config.port));
+        std::fs::create_dir_all(&dir).unwrap();
+        let certified = rcgen::generate_simple_self_signed(vec!["rgpu-server".to_string()]).unwrap();
+        let cert_path = dir.join("cert.pem");
+        let key_path = dir.join("key.pem");
+        std::fs::write(&cert_path, certified.cert.pem()).unwrap();
+        std::fs::write(&key_path, certified.key_pair.serialize_pem()).unwrap();
+
+        let config = ServerConfig {
+            cert_path: Some(cert_path.to_string_lossy().into_owned()),
+            key_path: Some(key_path.to_string_lossy().into_owned()),
+            ..config
+        };
+        RgpuServer::new(config, Vec::new())
+    });
+    (format!("127.0.0.1:{}", port), shutdown)
+}
+
+/// Connect and say Hello, retrying while the server starts.
+async fn connect(address: &str) -> QuicConnection {
+    for _ in 0..50 {
+        if let Ok(conn) = connect_quic_client(address).await {
+            let hello = Message::Hello {
+                protocol_version: PROTOCOL_VERSION,
+                name: "test".to_string(),
+                challenge: None,
+            };
+            let answer = tokio::time::timeout(WAIT, conn.send_and_receive(&hello)).await;
+            if let Ok(Ok(Message::Hello { .. })) = answer {
+                return conn;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("QUIC server did not start on {}", address);
+}
+
+async fn echo(conn: &QuicConnection, lane: Option<u64>, data: Vec<u8>) -> Vec<u8> {
+    let stats = CompressionStats::default();
+    let response = tokio::time::timeout(WAIT, conn.send_and_receive_tracked(&Message::Echo(data), lane, &stats))
+        .await
+        .expect("no response")
+        .unwrap();
+    match response {
+        Message::Echo(data) => data,
+        other => panic!("expected Echo, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_lane_answers_each_request_in_order() {
+    let (address, _shutdown) = start();
+    let conn = connect(&address).await;
+
+    // Every request on the lane's one stream is answered.
+    for i in 0..16u8 {
+        let data = vec![i; 1024 + i as usize];
+        assert_eq!(echo(&conn, Some(1), data.clone()).await, data);
+    }
+    assert_eq!(conn.lane_count(), 1);
+
+    // A large transfer on one lane doesn't hold up the others.
+    let big = vec![3u8; 16 * 1024 * 1024];
+    let (big_answer, small, plain) = tokio::join!(
+        echo(&conn, Some(1), big.clone()),
+        echo(&conn, Some(2), vec![2]),
+        echo(&conn, None, vec![0]),
+    );
+    assert_eq!(big_answer, big);
+    assert_eq!((small, plain), (vec![2], vec![0]));
+    assert_eq!(conn.lane_count(), 2);
+
+    // A closed lane is opened again by its next request.
+    conn.close_lane(1);
+    assert_eq!(conn.lane_count(), 1);
+    assert_eq!(echo(&conn, Some(1), vec![9]).await, vec![9]);
+    assert_eq!(conn.lane_count(), 2);
+}
+
+#[tokio::test]
+async fn test_reconnect_resumes_tls_session() {
+    let (address, _shutdown) = start();
+    let first = connect(&address).await;
+    assert_eq!(echo(&first, None, vec![1]).await, vec![1]);
+    drop(first);
+
+    // The second connection sends its Hello as 0-RTT data with the ticket
+    // from the first; it must be answered all the same.
+    let second = connect(&address).await;
+    assert_eq!(echo(&second, Some(5), vec![2]).await, vec![2]);
+}
@@ -0,0 +1,154 @@
+//! Integration test: per-token restrictions
+//!
+//! A token in `[security.tokens]` can take CUDA, Vulkan or PTX away from the
+//! sessions using it and limit how many there may be at once. Refused
+//! requests get an error of their own kind and the session carries on. No
+//! GPU is needed: refused requests never reach the executors.
+//!
+//! Run with: cargo test -p rgpu-server --test token_scope_test
+
+mod common;
+
+use std::time::Duration;
+
+use tokio::net::TcpStream;
+
+use rgpu_core::config::{ServerConfig, TokenEntry};
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse};
+use rgpu_protocol::messages::{Message, RequestId};
+use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
+use rgpu_server::RgpuServer;
+
+use common::{recv, send, token};
+
+/// Start a server accepting `tokens` on a free local port.
+fn start(tokens: Vec<TokenEntry>) -> (u16, tokio::sync::watch::Sender<bool>) {
+    common::start(ServerConfig::default(), |config| RgpuServer::new(config, tokens))
+}
+
+/// Connect and authenticate with `token`, returning the stream and whether
+/// the server accepted it.
+async fn connect(port: u16, token: &str) -> (TcpStream, bool) {
+    let mut stream = common::connect(port).await;
+    let auth = Message::Authenticate {
+        token: token.to_string(),
+        challenge_response: Vec::new(),
+    };
+    send(&mut stream, &auth).await;
+    match recv(&mut stream).await {
+        Message::AuthResult { success, .. } => (stream, success),
+        other => panic!("expected AuthResult, got {:?}", other),
+    }
+}
+
+async fn cuda(stream: &mut TcpStream, command: CudaCommand) -> CudaResponse {
+    send(
+        stream,
+        &Message::CudaCommand {
+            request_id: RequestId(1),
+            command,
+            deadline_ms: None,
+        },
+    )
+    .await;
+    match recv(stream).await {
+        Message::CudaResponse { response, .. } => response,
+        other => panic!("expected CudaResponse, got {:?}", other),
+    }
+}
+
+fn not_permitted(response: &CudaResponse) -> bool {
+    matches!(response, CudaResponse::Error { code: 800, .. })
+}
+
+#[tokio::test]
+async fn test_api_restrictions() {
+    let vulkan_only = TokenEntry {
+        allow_cuda: false,
+        ..token("vulkan-only")
+    };
+    let cuda_only = TokenEntry {
+        allow_vulkan: false,
+        ..token("cuda-only")
+    };
+    let (port, _shutdown) = start(vec![vulkan_only, cuda_only]);
+
+    let (mut stream, accepted) = connect(port, "vulkan-only-secret").await;
+    assert!(accepted);
+    assert!(not_permitted(&cuda(&mut stream, CudaCommand::DeviceGetCount).await));
+    send(&mut stream, &Message::Ping).await;
+    assert!(matches!(recv(&mut stream).await, Message::Pong));
+
+    let (mut stream, accepted) = connect(port, "cuda-only-secret").await;
+    assert!(accepted);
+    let create = Message::VulkanCommand {
+        request_id: RequestId(2),
+        command: VulkanCommand::CreateInstance {
+            app_name: None,
+            app_version: 0,
+            engine_name: None,
+            engine_version: 0,
+            api_version: 0,
+            enabled_extensions: Vec::new(),
+            enabled_layers: Vec::new(),
+        },
+        deadline_ms: None,
+    };
+    send(&mut stream, &create).await;
+    match recv(&mut stream).await {
+        Message::VulkanResponse { request_id, response } => {
+            assert_eq!(request_id, RequestId(2));
+            assert!(matches!(response, VulkanResponse::Error { code: -3, .. }), "{:?}", response);
+        }
+        other => panic!("expected VulkanResponse, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_ptx_and_gpu_restrictions() {
+    let restricted = TokenEntry {
+        allow_ptx: false,
+        allowed_gpus: Some(vec![1]),
+        ..token("restricted")
+    };
+    let (port, _shutdown) = start(vec![restricted]);
+    let (mut stream, accepted) = connect(port, "restricted-secret").await;
+    assert!(accepted);
+
+    let ptx = b".version 8.0\n.target sm_80\n.address_size 64\n\0".to_vec();
+    assert!(not_permitted(&cuda(&mut stream, CudaCommand::ModuleLoadData { image: ptx }).await));
+    assert!(not_permitted(&cuda(&mut stream, CudaCommand::DeviceGet { ordinal: 0 }).await));
+
+    send(&mut stream, &Message::QueryGpus).await;
+    match recv(&mut stream).await {
+        Message::GpuList(gpus) => assert!(gpus.iter().all(|g| g.server_device_index == 1)),
+        other => panic!("expected GpuList, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_session_limit() {
+    let limited = TokenEntry {
+        max_sessions: Some(1),
+        ..token("limited")
+    };
+    let (port, _shutdown) = start(vec![limited]);
+
+    let (first, accepted) = connect(port, "limited-secret").await;
+    assert!(accepted);
+    let (mut second, accepted) = connect(port, "limited-secret").await;
+    assert!(!accepted);
+    // A refused session can't run anything.
+    assert!(not_permitted(&cuda(&mut second, CudaCommand::DeviceGetCount).await));
+
+    // The slot is free again once the first session has gone.
+    drop(first);
+    for _ in 0..50 {
+        let (_third, accepted) = connect(port, "limited-secret").await;
+        if accepted {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("session slot was not freed");
+}
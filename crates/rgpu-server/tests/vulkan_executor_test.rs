@@ -6,8 +6,12 @@
 //!
 //! Run with: cargo test --test vulkan_executor_test -- --nocapture
 
+use std::sync::Arc;
+
 use rgpu_protocol::vulkan_commands::*;
+use rgpu_server::cuda_executor::CudaExecutor;
 use rgpu_server::session::Session;
+use rgpu_server::vram::VramLedger;
 use rgpu_server::vulkan_executor::VulkanExecutor;
 
 fn make_session() -> Session {
@@ -54,6 +58,34 @@ fn test_create_and_destroy_instance() {
     }
 }
 
+#[test]
+fn test_create_instance_with_missing_extension() {
+    let executor = VulkanExecutor::new();
+    let session = make_session();
+
+    // The surface extension is the client's and is passed over; the made-up
+    // one fails the creation
+    let resp = executor.execute(
+        &session,
+        VulkanCommand::CreateInstance {
+            app_name: Some("ExtensionTest".to_string()),
+            app_version: 1,
+            engine_name: None,
+            engine_version: 0,
+            api_version: ash::vk::make_api_version(0, 1, 0, 0),
+            enabled_extensions: vec!["VK_KHR_surface".to_string(), "VK_RGPU_no_such_extension".to_string()],
+            enabled_layers: Vec::new(),
+        },
+    );
+    match resp {
+        VulkanResponse::Error { code, message } => {
+            assert_eq!(code, ash::vk::Result::ERROR_EXTENSION_NOT_PRESENT.as_raw());
+            assert!(message.contains("VK_RGPU_no_such_extension"), "{}", message);
+        }
+        other => panic!("expected Error, got {:?}", other),
+    }
+}
+
 #[test]
 fn test_enumerate_physical_devices() {
     let executor = VulkanExecutor::new();
@@ -236,6 +268,7 @@ fn test_create_device_and_buffer() {
             }],
             enabled_extensions: Vec::new(),
             enabled_features: None,
+            p_next: Vec::new(),
         },
     ) {
         VulkanResponse::DeviceCreated { handle } => {
@@ -254,6 +287,7 @@ fn test_create_device_and_buffer() {
             usage: 0x00000080 | 0x00000001, // STORAGE_BUFFER | TRANSFER_SRC
             sharing_mode: 0,                // EXCLUSIVE
             queue_family_indices: Vec::new(),
+            p_next: Vec::new(),
         },
     ) {
         VulkanResponse::BufferCreated { handle } => {
@@ -442,3 +476,189 @@ fn test_enumerate_extensions() {
         other => println!("Unexpected response: {:?}", other),
     }
 }
+
+#[test]
+fn test_dropped_session_memory_returns_to_baseline() {
+    let ledger = Arc::new(VramLedger::unlimited());
+    let executor = VulkanExecutor::new().with_vram_ledger(ledger.clone());
+    let cuda = CudaExecutor::new(Vec::new());
+    let session = make_session();
+
+    let instance = match executor.execute(
+        &session,
+        VulkanCommand::CreateInstance {
+            app_name: Some("TeardownTest".to_string()),
+            app_version: 1,
+            engine_name: None,
+            engine_version: 0,
+            api_version: ash::vk::make_api_version(0, 1, 1, 0),
+            enabled_extensions: Vec::new(),
+            enabled_layers: Vec::new(),
+        },
+    ) {
+        VulkanResponse::InstanceCreated { handle } => handle,
+        other => panic!("expected InstanceCreated, got {:?}", other),
+    };
+    let physical_device = match executor.execute(&session, VulkanCommand::EnumeratePhysicalDevices { instance }) {
+        VulkanResponse::PhysicalDevices { handles } => handles[0],
+        other => panic!("expected PhysicalDevices, got {:?}", other),
+    };
+    let device = match executor.execute(
+        &session,
+        VulkanCommand::CreateDevice {
+            physical_device,
+            queue_create_infos: vec![DeviceQueueCreateInfo {
+                queue_family_index: 0,
+                queue_priorities: vec![1.0],
+            }],
+            enabled_extensions: Vec::new(),
+            enabled_features: None,
+            p_next: Vec::new(),
+        },
+    ) {
+        VulkanResponse::DeviceCreated { handle } => handle,
+        other => panic!("expected DeviceCreated, got {:?}", other),
+    };
+    let device_local = match executor.execute(
+        &session,
+        VulkanCommand::GetPhysicalDeviceMemoryProperties { physical_device },
+    ) {
+        VulkanResponse::PhysicalDeviceMemoryProperties { memory_types, .. } => memory_types
+            .iter()
+            .position(|mt| mt.property_flags & 0x01 != 0) // DEVICE_LOCAL
+            .expect("no device-local memory type") as u32,
+        other => panic!("expected PhysicalDeviceMemoryProperties, got {:?}", other),
+    };
+
+    // Leave everything allocated, as a client that crashed would.
+    for _ in 0..4 {
+        match executor.execute(
+            &session,
+            VulkanCommand::AllocateMemory {
+                device,
+                alloc_size: 1 << 20,
+                memory_type_index: device_local,
+            },
+        ) {
+            VulkanResponse::MemoryAllocated { .. } => {}
+            other => panic!("expected MemoryAllocated, got {:?}", other),
+        }
+    }
+    let charged: u64 = ledger.session_usage(session.session_id).iter().map(|d| d.total()).sum();
+    assert_eq!(charged, 4 << 20);
+
+    let leaked = session.teardown(&cuda, &executor, &ledger);
+    assert!(leaked >= 7, "instance, device and memory should be leaked, got {}", leaked);
+    assert!(session.all_handles().is_empty());
+    assert!(ledger.session_usage(session.session_id).is_empty());
+
+    // The session's objects are gone from the executor too.
+    match executor.execute(
+        &session,
+        VulkanCommand::AllocateMemory {
+            device,
+            alloc_size: 1 << 20,
+            memory_type_index: device_local,
+        },
+    ) {
+        VulkanResponse::Error { .. } => {}
+        other => panic!("expected Error for a torn-down device, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_queues_of_two_devices() {
+    let executor = VulkanExecutor::new();
+    let session = make_session();
+
+    let instance = match executor.execute(
+        &session,
+        VulkanCommand::CreateInstance {
+            app_name: Some("TwoDeviceTest".to_string()),
+            app_version: 1,
+            engine_name: None,
+            engine_version: 0,
+            api_version: ash::vk::make_api_version(0, 1, 0, 0),
+            enabled_extensions: Vec::new(),
+            enabled_layers: Vec::new(),
+        },
+    ) {
+        VulkanResponse::InstanceCreated { handle } => handle,
+        other => panic!("expected InstanceCreated, got {:?}", other),
+    };
+    let physical_device = match executor.execute(&session, VulkanCommand::EnumeratePhysicalDevices { instance }) {
+        VulkanResponse::PhysicalDevices { handles } => handles[0],
+        other => panic!("expected PhysicalDevices, got {:?}", other),
+    };
+
+    // Two logical devices, each with its own queue
+    let mut devices = Vec::new();
+    let mut queues = Vec::new();
+    for _ in 0..2 {
+        let device = match executor.execute(
+            &session,
+            VulkanCommand::CreateDevice {
+                physical_device,
+                queue_create_infos: vec![DeviceQueueCreateInfo {
+                    queue_family_index: 0,
+                    queue_priorities: vec![1.0],
+                }],
+                enabled_extensions: Vec::new(),
+                enabled_features: None,
+                p_next: Vec::new(),
+            },
+        ) {
+            VulkanResponse::DeviceCreated { handle } => handle,
+            other => panic!("expected DeviceCreated, got {:?}", other),
+        };
+        let queue = match executor.execute(
+            &session,
+            VulkanCommand::GetDeviceQueue {
+                device,
+                queue_family_index: 0,
+                queue_index: 0,
+            },
+        ) {
+            VulkanResponse::QueueRetrieved { handle } => handle,
+            other => panic!("expected QueueRetrieved, got {:?}", other),
+        };
+        devices.push(device);
+        queues.push(queue);
+    }
+
+    // Each queue goes to its own device
+    for &queue in &queues {
+        match executor.execute(
+            &session,
+            VulkanCommand::QueueSubmit {
+                queue,
+                submits: Vec::new(),
+                fence: None,
+            },
+        ) {
+            VulkanResponse::Success => {}
+            other => panic!("expected Success, got {:?}", other),
+        }
+        match executor.execute(&session, VulkanCommand::QueueWaitIdle { queue }) {
+            VulkanResponse::Success => {}
+            other => panic!("expected Success, got {:?}", other),
+        }
+    }
+
+    // With the first device gone its queue is too, and the second's still works
+    match executor.execute(&session, VulkanCommand::DestroyDevice { device: devices[0] }) {
+        VulkanResponse::Success => {}
+        other => panic!("expected Success, got {:?}", other),
+    }
+    match executor.execute(&session, VulkanCommand::QueueWaitIdle { queue: queues[0] }) {
+        VulkanResponse::Error { .. } => {}
+        other => panic!("expected Error for a destroyed device's queue, got {:?}", other),
+    }
+    match executor.execute(&session, VulkanCommand::QueueWaitIdle { queue: queues[1] }) {
+        VulkanResponse::Success => {}
+        other => panic!("expected Success, got {:?}", other),
+    }
+
+    executor.execute(&session, VulkanCommand::DestroyDevice { device: devices[1] });
+    executor.execute(&session, VulkanCommand::DestroyInstance { instance });
+}
@@ -155,6 +155,7 @@ fn setup_device() -> (
             }],
             enabled_extensions: Vec::new(),
             enabled_features: None,
+            p_next: Vec::new(),
         },
     ) {
         VulkanResponse::DeviceCreated { handle } => handle,
@@ -199,10 +200,12 @@ fn find_memory_type(
                 (type_bits & (1 << i)) != 0 && (mt.property_flags & properties) == properties
             })
             .map(|(i, _)| i as u32)
-            .expect(&format!(
-                "no memory type matching bits=0x{:x} props=0x{:x}",
-                type_bits, properties
-            )),
+            .unwrap_or_else(|| {
+                panic!(
+                    "no memory type matching bits=0x{:x} props=0x{:x}",
+                    type_bits, properties
+                )
+            }),
         other => panic!("expected PhysicalDeviceMemoryProperties, got {:?}", other),
     }
 }
@@ -229,6 +232,7 @@ fn test_create_image_and_image_view() {
                 sharing_mode: 0,
                 queue_family_indices: Vec::new(),
                 initial_layout: 0, // UNDEFINED
+                p_next: Vec::new(),
             },
         },
     ) {
@@ -353,6 +357,7 @@ fn test_render_pass_and_framebuffer() {
                 sharing_mode: 0,
                 queue_family_indices: Vec::new(),
                 initial_layout: 0,
+                p_next: Vec::new(),
             },
         },
     ) {
@@ -529,6 +534,7 @@ fn test_triangle_render() {
                 sharing_mode: 0,
                 queue_family_indices: Vec::new(),
                 initial_layout: 0,
+                p_next: Vec::new(),
             },
         },
     ) {
@@ -612,6 +618,7 @@ fn test_triangle_render() {
             usage: 0x00000002, // TRANSFER_DST
             sharing_mode: 0,
             queue_family_indices: Vec::new(),
+            p_next: Vec::new(),
         },
     ) {
         VulkanResponse::BufferCreated { handle } => handle,
@@ -756,6 +763,7 @@ fn test_triangle_render() {
         &session,
         VulkanCommand::CreateGraphicsPipelines {
             device,
+            pipeline_cache: None,
             create_infos: vec![SerializedGraphicsPipelineCreateInfo {
                 flags: 0,
                 stages: vec![
@@ -829,8 +837,9 @@ fn test_triangle_render() {
                 }),
                 dynamic_state: None,
                 layout: pipeline_layout,
-                render_pass,
+                render_pass: Some(render_pass),
                 subpass: 0,
+                rendering: None,
             }],
         },
     ) {
@@ -941,6 +950,7 @@ fn test_triangle_render() {
         &session,
         VulkanCommand::SubmitRecordedCommands {
             command_buffer: cmd_buf,
+            secondary: None,
             commands: recorded_commands,
         },
     ) {
@@ -958,6 +968,9 @@ fn test_triangle_render() {
                 wait_dst_stage_masks: Vec::new(),
                 command_buffers: vec![cmd_buf],
                 signal_semaphores: Vec::new(),
+                wait_semaphore_values: Vec::new(),
+                signal_semaphore_values: Vec::new(),
+                p_next: Vec::new(),
             }],
             fence: Some(fence),
         },
@@ -1127,3 +1140,79 @@ fn test_triangle_render() {
 
     println!("=== test_triangle_render PASSED ===");
 }
+
+#[test]
+fn test_virtual_swapchain_present() {
+    let (executor, session, instance, _phys_dev, device, queue, _qf) = setup_device();
+
+    let (swapchain, images) = match executor.execute(
+        &session,
+        VulkanCommand::CreateSwapchain {
+            device,
+            create_info: SerializedSwapchainCreateInfo {
+                min_image_count: 2,
+                format: 44, // VK_FORMAT_B8G8R8A8_UNORM
+                extent: [64, 64],
+                image_usage: 0x00000010, // COLOR_ATTACHMENT
+            },
+        },
+    ) {
+        VulkanResponse::SwapchainCreated { handle, images } => (handle, images),
+        other => panic!("expected SwapchainCreated, got {:?}", other),
+    };
+    assert_eq!(images.len(), 2);
+
+    let mut previous: Option<Vec<u8>> = None;
+    for expected in [0u32, 1] {
+        let index = match executor.execute(
+            &session,
+            VulkanCommand::AcquireNextImage {
+                device,
+                swapchain,
+                semaphore: None,
+                fence: None,
+            },
+        ) {
+            VulkanResponse::ImageAcquired { index } => index,
+            other => panic!("expected ImageAcquired, got {:?}", other),
+        };
+        assert_eq!(index, expected);
+
+        let mut frames = match executor.execute(
+            &session,
+            VulkanCommand::QueuePresent {
+                queue,
+                wait_semaphores: Vec::new(),
+                presents: vec![SerializedPresent {
+                    swapchain,
+                    image_index: index,
+                }],
+                delta: true,
+            },
+        ) {
+            VulkanResponse::FramesPresented { frames } => frames,
+            other => panic!("expected FramesPresented, got {:?}", other),
+        };
+        assert_eq!(frames.len(), 1);
+        let frame = frames.remove(0);
+        assert_eq!((frame.width, frame.height), (64, 64));
+        // Only the first frame has nothing to be a delta against.
+        let expected_encoding = if previous.is_none() {
+            rgpu_protocol::frame::FrameEncoding::Raw
+        } else {
+            rgpu_protocol::frame::FrameEncoding::Delta
+        };
+        assert_eq!(frame.encoding, expected_encoding);
+        let pixels = rgpu_protocol::frame::decode(frame.encoding, frame.data, previous.as_deref())
+            .expect("frame should decode");
+        assert_eq!(pixels.len(), 64 * 64 * 4);
+        previous = Some(pixels);
+    }
+
+    executor.execute(
+        &session,
+        VulkanCommand::DestroySwapchain { device, swapchain },
+    );
+    executor.execute(&session, VulkanCommand::DestroyDevice { device });
+    executor.execute(&session, VulkanCommand::DestroyInstance { instance });
+}
@@ -0,0 +1,87 @@
+//! Integration test: the framed protocol over WebSocket
+//!
+//! A server with `websocket_port` answers on it as it does on its TCP port,
+//! with frames split across WebSocket messages both ways. No GPU is needed:
+//! the requests here never reach the executors.
+//!
+//! Run with: cargo test -p rgpu-server --test websocket_test
+
+mod common;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use rgpu_core::config::ServerConfig;
+use rgpu_protocol::messages::{Message, PROTOCOL_VERSION};
+use rgpu_protocol::wire;
+use rgpu_server::RgpuServer;
+use rgpu_transport::websocket;
+
+use common::WAIT;
+
+/// Start a plain server with a WebSocket port. Returns both ports once the
+/// WebSocket port accepts connections.
+async fn start() -> (u16, u16, tokio::sync::watch::Sender<bool>) {
+    let ws_port = common::free_port();
+    let config = ServerConfig {
+        websocket_port: Some(ws_port),
+        ..Default::default()
+    };
+    let (port, shutdown) = common::start(config, |config| RgpuServer::new(config, Vec::new()));
+    common::connect(ws_port).await;
+    (port, ws_port, shutdown)
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(stream: &mut S, msg: &Message) -> Message {
+    let frame = wire::encode_message(msg, wire::request_tag(msg)).unwrap();
+    stream.write_all(&frame).await.unwrap();
+    stream.flush().await.unwrap();
+
+    let mut header = [0u8; wire::HEADER_SIZE];
+    tokio::time::timeout(WAIT, stream.read_exact(&mut header))
+        .await
+        .expect("no response")
+        .unwrap();
+    let (flags, _, len) = wire::decode_header(&header).unwrap();
+    let mut payload = vec![0u8; len as usize];
+    tokio::time::timeout(WAIT, stream.read_exact(&mut payload))
+        .await
+        .expect("response cut short")
+        .unwrap();
+    wire::decode_message(&payload, flags).unwrap()
+}
+
+fn hello() -> Message {
+    Message::Hello {
+        protocol_version: PROTOCOL_VERSION,
+        name: "test".to_string(),
+        challenge: None,
+    }
+}
+
+#[tokio::test]
+async fn test_websocket_port_serves_the_protocol() {
+    let (port, ws_port, _shutdown) = start().await;
+
+    let mut ws = websocket::connect(&format!("ws://127.0.0.1:{}/rgpu", ws_port), None, None)
+        .await
+        .unwrap();
+    assert!(matches!(exchange(&mut ws, &hello()).await, Message::Hello { .. }));
+
+    // Several WebSocket messages each way
+    let data: Vec<u8> = (0..3 * websocket::MAX_CHUNK + 17).map(|i| (i * 7 % 251) as u8).collect();
+    match exchange(&mut ws, &Message::Echo(data.clone())).await {
+        Message::Echo(echoed) => assert_eq!(echoed, data),
+        other => panic!("expected Echo, got {:?}", other),
+    }
+
+    // The TCP port still speaks the protocol directly
+    let mut tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    assert!(matches!(exchange(&mut tcp, &hello()).await, Message::Hello { .. }));
+}
+
+#[test]
+fn test_bare_addresses_become_urls() {
+    assert_eq!(websocket::url("gpu-box:9877"), "ws://gpu-box:9877/");
+    assert_eq!(websocket::url("wss://proxy.example.com/rgpu"), "wss://proxy.example.com/rgpu");
+}
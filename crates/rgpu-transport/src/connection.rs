@@ -12,6 +12,21 @@ use rgpu_protocol::messages::{Message, RequestId};
 use rgpu_protocol::wire::{self, HEADER_SIZE};
 
 use crate::error::TransportError;
+use crate::websocket::WsStream;
+
+/// Read past a frame payload of `len` bytes without buffering it, for
+/// payloads too large to accept.
+pub async fn skip_payload<R>(reader: &mut R, len: u32) -> std::io::Result<()>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let mut payload = reader.take(len as u64);
+    let skipped = tokio::io::copy(&mut payload, &mut tokio::io::sink()).await?;
+    if skipped < len as u64 {
+        return Err(std::io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
 
 /// Whether this side of the connection is the server or client.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -35,12 +50,28 @@ pub struct RgpuConnection {
 }
 
 impl RgpuConnection {
-    /// Create a connection from a raw TLS stream (server-side).
+    /// Create a connection from a raw TLS stream (server-side). Incoming
+    /// messages over `max_message_size` bytes are skipped and answered as
+    /// undecodable.
     pub async fn from_server_stream(
         stream: ServerTlsStream<TcpStream>,
+        max_message_size: usize,
     ) -> Result<Self, TransportError> {
         let (read_half, write_half) = tokio::io::split(stream);
-        Self::setup(ConnectionRole::Server, read_half, write_half).await
+        Self::setup(ConnectionRole::Server, read_half, write_half, max_message_size).await
+    }
+
+    /// Create a connection from a WebSocket tunnel (server-side), over TLS
+    /// or not.
+    pub async fn from_server_websocket<S>(
+        stream: WsStream<S>,
+        max_message_size: usize,
+    ) -> Result<Self, TransportError>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+    {
+        let (read_half, write_half) = tokio::io::split(stream);
+        Self::setup(ConnectionRole::Server, read_half, write_half, max_message_size).await
     }
 
     /// Create a connection from a raw TLS stream (client-side).
@@ -48,10 +79,15 @@ impl RgpuConnection {
         stream: ClientTlsStream<TcpStream>,
     ) -> Result<Self, TransportError> {
         let (read_half, write_half) = tokio::io::split(stream);
-        Self::setup(ConnectionRole::Client, read_half, write_half).await
+        Self::setup(ConnectionRole::Client, read_half, write_half, wire::MAX_MESSAGE_SIZE).await
     }
 
-    async fn setup<R, W>(role: ConnectionRole, read_half: R, write_half: W) -> Result<Self, TransportError>
+    async fn setup<R, W>(
+        role: ConnectionRole,
+        read_half: R,
+        write_half: W,
+        max_message_size: usize,
+    ) -> Result<Self, TransportError>
     where
         R: tokio::io::AsyncRead + Unpin + Send + 'static,
         W: tokio::io::AsyncWrite + Unpin + Send + 'static,
@@ -72,6 +108,13 @@ impl RgpuConnection {
                     error!("write error: {}", e);
                     break;
                 }
+                // Sends what a WebSocket tunnel buffered
+                if out_rx.is_empty() {
+                    if let Err(e) = AsyncWriteExt::flush(&mut write_half).await {
+                        error!("write error: {}", e);
+                        break;
+                    }
+                }
             }
         });
 
@@ -90,7 +133,7 @@ impl RgpuConnection {
                     }
                 }
 
-                let (flags, _stream_id, payload_len) = match wire::decode_header(&header_buf) {
+                let (flags, tag, payload_len) = match wire::decode_header(&header_buf) {
                     Ok(v) => v,
                     Err(e) => {
                         error!("invalid frame header: {}", e);
@@ -98,19 +141,28 @@ impl RgpuConnection {
                     }
                 };
 
-                // Read payload
-                let mut payload = vec![0u8; payload_len as usize];
-                if let Err(e) = AsyncReadExt::read_exact(&mut read_half, &mut payload).await {
-                    error!("payload read error: {}", e);
-                    break;
-                }
+                let msg = if payload_len as usize > max_message_size {
+                    // Skipped without allocating it; only its request fails.
+                    if let Err(e) = skip_payload(&mut read_half, payload_len).await {
+                        error!("payload read error: {}", e);
+                        break;
+                    }
+                    wire::undecodable(tag, &wire::WireError::FrameTooLarge(payload_len))
+                } else {
+                    // Read payload
+                    let mut payload = vec![0u8; payload_len as usize];
+                    if let Err(e) = AsyncReadExt::read_exact(&mut read_half, &mut payload).await {
+                        error!("payload read error: {}", e);
+                        break;
+                    }
 
-                // Decode message
-                let msg = match wire::decode_message(&payload, flags) {
-                    Ok(m) => m,
-                    Err(e) => {
-                        error!("message decode error: {}", e);
-                        continue;
+                    // Decode message
+                    match wire::decode_message_limited(&payload, flags, max_message_size) {
+                        Ok(m) => m,
+                        Err(e) => {
+                            error!("message decode error: {}", e);
+                            wire::undecodable(tag, &e)
+                        }
                     }
                 };
 
@@ -118,6 +170,7 @@ impl RgpuConnection {
                 let is_response = match &msg {
                     Message::CudaResponse { request_id, .. } => Some(request_id.0),
                     Message::VulkanResponse { request_id, .. } => Some(request_id.0),
+                    Message::Unsupported { request_id, .. } => Some(request_id.0),
                     Message::AuthResult { .. } => None, // handled differently
                     _ => None,
                 };
@@ -148,7 +201,7 @@ impl RgpuConnection {
 
     /// Send a message without waiting for a response.
     pub async fn send(&self, msg: Message) -> Result<(), TransportError> {
-        let frame = wire::encode_message(&msg, 0)?;
+        let frame = wire::encode_message(&msg, wire::request_tag(&msg))?;
         self.tx
             .send(frame)
             .await
@@ -168,6 +221,7 @@ impl RgpuConnection {
         let msg = Message::CudaCommand {
             request_id,
             command,
+            deadline_ms: None,
         };
         self.send(msg).await?;
 
@@ -175,6 +229,11 @@ impl RgpuConnection {
 
         match response {
             Message::CudaResponse { response, .. } => Ok(response),
+            Message::Unsupported { reason, .. } => Ok(rgpu_protocol::cuda_commands::CudaResponse::Error {
+                // CUDA_ERROR_NOT_SUPPORTED
+                code: 801,
+                message: reason,
+            }),
             Message::Error(e) => Err(TransportError::AuthFailed(e.to_string())),
             _ => Err(TransportError::ConnectionClosed),
         }
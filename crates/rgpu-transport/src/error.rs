@@ -15,6 +15,12 @@ pub enum TransportError {
     #[error("QUIC error: {0}")]
     Quic(String),
 
+    #[error("RDMA error: {0}")]
+    Rdma(String),
+
+    #[error("WebSocket error: {0}")]
+    WebSocket(String),
+
     #[error("connection closed")]
     ConnectionClosed,
 
@@ -0,0 +1,33 @@
+//! Health checks below the protocol.
+//!
+//! Without them a server that dies or drops off the network is only noticed
+//! when a request to it times out, which can take minutes. The transport
+//! probes the connection itself every interval instead, busy or idle: TCP
+//! keepalive probes, QUIC PING frames. After `MISSED_PROBES` intervals
+//! without an answer the connection fails, and so does any request waiting
+//! on it.
+
+use std::time::Duration;
+
+/// Intervals without an answer before a connection is given up.
+pub const MISSED_PROBES: u32 = 3;
+
+/// Send keepalive probes on a TCP connection idle for `interval`, and every
+/// `interval` after that until it answers.
+pub fn tcp_keepalive(stream: &tokio::net::TcpStream, interval: Duration) -> std::io::Result<()> {
+    let keepalive = socket2::TcpKeepalive::new()
+        .with_time(interval)
+        .with_interval(interval)
+        .with_retries(MISSED_PROBES);
+    socket2::SockRef::from(stream).set_tcp_keepalive(&keepalive)
+}
+
+/// QUIC transport settings that ping the peer every `interval` and give up
+/// on it after `MISSED_PROBES` intervals of silence.
+pub fn quic_transport(interval: Duration) -> quinn::TransportConfig {
+    let mut transport = quinn::TransportConfig::default();
+    transport.keep_alive_interval(Some(interval));
+    transport.max_idle_timeout((interval * MISSED_PROBES).try_into().ok());
+    transport
+}
+
@@ -2,7 +2,10 @@ pub mod connection;
 pub mod tls;
 pub mod auth;
 pub mod error;
+pub mod health;
 pub mod quic;
+pub mod rdma;
+pub mod websocket;
 
 pub use connection::{RgpuConnection, ConnectionRole};
 pub use error::TransportError;
@@ -1,11 +1,21 @@
 //! QUIC transport for RGPU, using Quinn.
 //!
 //! QUIC always provides encryption (TLS 1.3), so there is no "plain" mode.
-//! Each request-response pair uses a bidirectional QUIC stream.
+//! Requests go on bidirectional QUIC streams, which the server answers
+//! independently of each other. A request opens a stream of its own, unless
+//! it is sent on a lane: a stream kept open for the requests of one CUDA
+//! stream, answered in the order they were sent. Work on different CUDA
+//! streams so never waits behind each other, as it would on one TCP
+//! connection, while work on one CUDA stream keeps its order.
+//!
+//! Clients keep the TLS session tickets servers hand out, so reconnecting to
+//! a server it has talked to before sends the first request as 0-RTT data
+//! instead of waiting out the handshake.
 
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
+use dashmap::DashMap;
 use quinn::{Endpoint, ServerConfig as QuinnServerConfig};
 use tracing::{debug, error, info};
 
@@ -23,12 +33,16 @@ pub fn build_quic_server(
     let (certs, key) = crate::tls::load_certs_and_key(cert_path, key_path)
         .map_err(|e| TransportError::Quic(format!("failed to load certs: {}", e)))?;
 
+    crate::tls::install_crypto_provider();
     let mut server_crypto = rustls::ServerConfig::builder()
         .with_no_client_auth()
         .with_single_cert(certs, key)
-        .map_err(|e| TransportError::Tls(e))?;
+        .map_err(TransportError::Tls)?;
 
     server_crypto.alpn_protocols = vec![b"rgpu/1".to_vec()];
+    // Accept 0-RTT data from resuming clients. Their first request is the
+    // Hello, which is safe to replay.
+    server_crypto.max_early_data_size = u32::MAX;
 
     let mut server_config = QuinnServerConfig::with_crypto(Arc::new(
         quinn::crypto::rustls::QuicServerConfig::try_from(server_crypto)
@@ -53,8 +67,17 @@ pub fn build_quic_server(
 
 /// Opaque wrapper around a QUIC connection.
 /// Allows the client daemon to hold a QUIC connection without depending on quinn directly.
+/// Clones share the connection and its lanes.
+#[derive(Clone)]
 pub struct QuicConnection {
     connection: quinn::Connection,
+    lanes: Arc<DashMap<u64, Arc<tokio::sync::Mutex<Lane>>>>,
+}
+
+/// A stream kept open for the requests of one CUDA stream.
+struct Lane {
+    send: quinn::SendStream,
+    recv: quinn::RecvStream,
 }
 
 impl QuicConnection {
@@ -67,24 +90,119 @@ impl QuicConnection {
         quic_send_and_receive(&self.connection, msg).await
     }
 
+    /// Like `send_and_receive`, recording compression of the request in `stats`.
+    /// With a `lane`, the request goes on that lane's stream after the
+    /// requests sent on it before.
+    pub async fn send_and_receive_tracked(
+        &self,
+        msg: &Message,
+        lane: Option<u64>,
+        stats: &wire::CompressionStats,
+    ) -> Result<Message, TransportError> {
+        let frame = wire::encode_message_tracked(msg, wire::request_tag(msg), stats).map_err(TransportError::Wire)?;
+        match lane {
+            Some(lane) => self.send_on_lane(lane, &frame).await,
+            None => send_frame_and_receive(&self.connection, &frame).await,
+        }
+    }
+
+    async fn send_on_lane(&self, id: u64, frame: &[u8]) -> Result<Message, TransportError> {
+        let lane = match self.lanes.get(&id) {
+            Some(lane) => lane.clone(),
+            None => {
+                let (send, recv) = self
+                    .connection
+                    .open_bi()
+                    .await
+                    .map_err(|e| TransportError::Quic(format!("open stream error: {}", e)))?;
+                let lane = Arc::new(tokio::sync::Mutex::new(Lane { send, recv }));
+                self.lanes.entry(id).or_insert(lane).clone()
+            }
+        };
+        let mut lane = lane.lock().await;
+        let result = match lane.send.write_all(frame).await {
+            Ok(()) => read_quic_message(&mut lane.recv).await,
+            Err(e) => Err(TransportError::Quic(format!("write error: {}", e))),
+        };
+        if result.is_err() {
+            // A broken lane takes no more requests; the next one opens a new stream.
+            self.lanes.remove(&id);
+        }
+        result
+    }
+
+    /// Finish a lane's stream, once its CUDA stream is gone.
+    pub fn close_lane(&self, id: u64) {
+        // Dropping the last reference to the send half finishes it.
+        self.lanes.remove(&id);
+    }
+
+    /// Number of lanes open on this connection.
+    pub fn lane_count(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// Whether `other` is a clone of this connection.
+    pub fn same_connection(&self, other: &QuicConnection) -> bool {
+        self.connection.stable_id() == other.connection.stable_id()
+    }
+
+    /// Send a message without waiting for a response (control messages such as `Cancel`).
+    pub async fn send_oneway(&self, msg: &Message) -> Result<(), TransportError> {
+        let (mut send, _recv) = self
+            .connection
+            .open_bi()
+            .await
+            .map_err(|e| TransportError::Quic(format!("open stream error: {}", e)))?;
+
+        let frame = wire::encode_message(msg, 0)
+            .map_err(TransportError::Wire)?;
+
+        send.write_all(&frame)
+            .await
+            .map_err(|e| TransportError::Quic(format!("write error: {}", e)))?;
+
+        send.finish()
+            .map_err(|e| TransportError::Quic(format!("finish error: {}", e)))?;
+
+        Ok(())
+    }
+
+    /// Wait for the next message the server pushes on a stream of its own
+    /// (`ServerTopologyChanged`). Fails once the connection is closed.
+    pub async fn accept_pushed(&self) -> Result<Message, TransportError> {
+        let mut recv = self
+            .connection
+            .accept_uni()
+            .await
+            .map_err(|e| TransportError::Quic(format!("accept stream error: {}", e)))?;
+        read_quic_message(&mut recv).await
+    }
+
     /// Get the remote address of this connection.
     pub fn remote_address(&self) -> SocketAddr {
         self.connection.remote_address()
     }
 }
 
-/// Build a QUIC client endpoint and connect to the server.
-pub async fn connect_quic_client(
-    server_addr: &str,
-) -> Result<QuicConnection, TransportError> {
+/// Client TLS configuration, shared by all connections so the session
+/// tickets of one are there for the next.
+static CLIENT_CONFIG: OnceLock<quinn::ClientConfig> = OnceLock::new();
+
+fn client_config() -> Result<quinn::ClientConfig, TransportError> {
+    if let Some(config) = CLIENT_CONFIG.get() {
+        return Ok(config.clone());
+    }
     let mut roots = rustls::RootCertStore::empty();
     roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
 
+    crate::tls::install_crypto_provider();
     let mut client_crypto = rustls::ClientConfig::builder()
         .with_root_certificates(roots)
         .with_no_client_auth();
 
     client_crypto.alpn_protocols = vec![b"rgpu/1".to_vec()];
+    client_crypto.enable_early_data = true;
     // Allow self-signed certs for development
     client_crypto.dangerous().set_certificate_verifier(Arc::new(SkipServerVerification));
 
@@ -92,6 +210,26 @@ pub async fn connect_quic_client(
         quinn::crypto::rustls::QuicClientConfig::try_from(client_crypto)
             .map_err(|e| TransportError::Quic(e.to_string()))?,
     ));
+    Ok(CLIENT_CONFIG.get_or_init(|| client_config).clone())
+}
+
+/// Build a QUIC client endpoint and connect to the server.
+pub async fn connect_quic_client(
+    server_addr: &str,
+) -> Result<QuicConnection, TransportError> {
+    connect_quic_client_checked(server_addr, None).await
+}
+
+/// Like `connect_quic_client`, pinging the server every `health_check` and
+/// giving up on it when it stops answering (see [`crate::health`]).
+pub async fn connect_quic_client_checked(
+    server_addr: &str,
+    health_check: Option<std::time::Duration>,
+) -> Result<QuicConnection, TransportError> {
+    let mut client_config = client_config()?;
+    if let Some(interval) = health_check {
+        client_config.transport_config(Arc::new(crate::health::quic_transport(interval)));
+    }
 
     let bind_addr: SocketAddr = "0.0.0.0:0".parse()
         .map_err(|e| TransportError::Quic(format!("invalid bind address: {}", e)))?;
@@ -103,14 +241,24 @@ pub async fn connect_quic_client(
         .parse()
         .map_err(|e| TransportError::Quic(format!("invalid server address: {}", e)))?;
 
-    let connection = endpoint
+    let connecting = endpoint
         .connect(addr, "rgpu-server")
-        .map_err(|e| TransportError::Quic(format!("QUIC connect error: {}", e)))?
-        .await
-        .map_err(|e| TransportError::Quic(format!("QUIC connection error: {}", e)))?;
+        .map_err(|e| TransportError::Quic(format!("QUIC connect error: {}", e)))?;
+    let connection = match connecting.into_0rtt() {
+        Ok((connection, _accepted)) => {
+            debug!("QUIC connection to {} resumed with 0-RTT", server_addr);
+            connection
+        }
+        Err(connecting) => connecting
+            .await
+            .map_err(|e| TransportError::Quic(format!("QUIC connection error: {}", e)))?,
+    };
 
     debug!("QUIC connection established to {}", server_addr);
-    Ok(QuicConnection { connection })
+    Ok(QuicConnection {
+        connection,
+        lanes: Arc::new(DashMap::new()),
+    })
 }
 
 /// Send a message and receive a response over a QUIC connection.
@@ -119,68 +267,135 @@ pub async fn quic_send_and_receive(
     connection: &quinn::Connection,
     msg: &Message,
 ) -> Result<Message, TransportError> {
+    let frame = wire::encode_message(msg, wire::request_tag(msg))
+        .map_err(TransportError::Wire)?;
+    send_frame_and_receive(connection, &frame).await
+}
+
+/// Send an encoded frame on a new bidirectional stream and read the response.
+async fn send_frame_and_receive(
+    connection: &quinn::Connection,
+    frame: &[u8],
+) -> Result<Message, TransportError> {
+    match exchange(connection, frame).await {
+        // Sent as 0-RTT data the server wouldn't take (it lost the session
+        // ticket, say); the handshake is done now, so send it again.
+        Err(StreamError::ZeroRttRejected) => {
+            debug!("0-RTT data rejected by {}, resending", connection.remote_address());
+            exchange(connection, frame).await.map_err(StreamError::into_transport)
+        }
+        result => result.map_err(StreamError::into_transport),
+    }
+}
+
+/// Why a request on a stream failed.
+enum StreamError {
+    /// The stream was opened in 0-RTT data that the server rejected
+    ZeroRttRejected,
+    Failed(TransportError),
+}
+
+impl StreamError {
+    fn into_transport(self) -> TransportError {
+        match self {
+            StreamError::ZeroRttRejected => TransportError::Quic("0-RTT data rejected".to_string()),
+            StreamError::Failed(e) => e,
+        }
+    }
+
+    fn read(context: &str, e: quinn::ReadExactError) -> Self {
+        match e {
+            quinn::ReadExactError::ReadError(quinn::ReadError::ZeroRttRejected) => StreamError::ZeroRttRejected,
+            e => StreamError::Failed(TransportError::Quic(format!("{} error: {}", context, e))),
+        }
+    }
+}
+
+async fn exchange(connection: &quinn::Connection, frame: &[u8]) -> Result<Message, StreamError> {
     let (mut send, mut recv) = connection
         .open_bi()
         .await
-        .map_err(|e| TransportError::Quic(format!("open stream error: {}", e)))?;
+        .map_err(|e| StreamError::Failed(TransportError::Quic(format!("open stream error: {}", e))))?;
 
-    // Encode and send the message
-    let frame = wire::encode_message(msg, 0)
-        .map_err(|e| TransportError::Wire(e))?;
-
-    send.write_all(&frame)
-        .await
-        .map_err(|e| TransportError::Quic(format!("write error: {}", e)))?;
+    send.write_all(frame).await.map_err(|e| match e {
+        quinn::WriteError::ZeroRttRejected => StreamError::ZeroRttRejected,
+        e => StreamError::Failed(TransportError::Quic(format!("write error: {}", e))),
+    })?;
 
     send.finish()
-        .map_err(|e| TransportError::Quic(format!("finish error: {}", e)))?;
+        .map_err(|e| StreamError::Failed(TransportError::Quic(format!("finish error: {}", e))))?;
 
     // Read response
-    let response = read_quic_message(&mut recv).await?;
-    Ok(response)
+    read_frame(&mut recv).await
 }
 
 /// Read a single framed message from a QUIC receive stream.
 pub async fn read_quic_message(
     recv: &mut quinn::RecvStream,
 ) -> Result<Message, TransportError> {
+    read_frame(recv).await.map_err(StreamError::into_transport)
+}
+
+async fn read_frame(recv: &mut quinn::RecvStream) -> Result<Message, StreamError> {
     let mut header_buf = [0u8; wire::HEADER_SIZE];
     recv.read_exact(&mut header_buf)
         .await
-        .map_err(|e| TransportError::Quic(format!("read header error: {}", e)))?;
+        .map_err(|e| StreamError::read("read header", e))?;
 
-    let (flags, _, payload_len) = wire::decode_header(&header_buf)?;
+    let (flags, _, payload_len) =
+        wire::decode_header(&header_buf).map_err(|e| StreamError::Failed(e.into()))?;
 
     let mut payload = vec![0u8; payload_len as usize];
     recv.read_exact(&mut payload)
         .await
-        .map_err(|e| TransportError::Quic(format!("read payload error: {}", e)))?;
+        .map_err(|e| StreamError::read("read payload", e))?;
 
-    let msg = wire::decode_message(&payload, flags)?;
-    Ok(msg)
+    wire::decode_message(&payload, flags).map_err(|e| StreamError::Failed(e.into()))
+}
+
+/// Read the header of the next request on a stream (server side), or `None`
+/// once the client has finished the stream. Clients finish a stream after
+/// one request, or keep it open as a lane for the requests of a CUDA stream.
+pub async fn read_next_header(
+    recv: &mut quinn::RecvStream,
+) -> Result<Option<[u8; wire::HEADER_SIZE]>, TransportError> {
+    let mut header_buf = [0u8; wire::HEADER_SIZE];
+    match recv.read_exact(&mut header_buf).await {
+        Ok(()) => Ok(Some(header_buf)),
+        Err(quinn::ReadExactError::FinishedEarly(0)) => Ok(None),
+        Err(e) => Err(TransportError::Quic(format!("read header error: {}", e))),
+    }
 }
 
 /// Handle an incoming QUIC bidirectional stream (server side).
-/// Reads one request, calls the handler, and writes the response.
+/// Reads requests until the client finishes the stream, calls the handler
+/// on each, and writes the responses in order.
 pub async fn handle_quic_stream(
     send: &mut quinn::SendStream,
     recv: &mut quinn::RecvStream,
     handler: &(dyn Fn(Message) -> Option<Message> + Send + Sync),
 ) -> Result<(), TransportError> {
-    let msg = read_quic_message(recv).await?;
-
-    if let Some(response) = handler(msg) {
-        let frame = wire::encode_message(&response, 0)
-            .map_err(|e| TransportError::Wire(e))?;
-
-        send.write_all(&frame)
+    while let Some(header_buf) = read_next_header(recv).await? {
+        let (flags, _, payload_len) = wire::decode_header(&header_buf)?;
+        let mut payload = vec![0u8; payload_len as usize];
+        recv.read_exact(&mut payload)
             .await
-            .map_err(|e| TransportError::Quic(format!("write response error: {}", e)))?;
+            .map_err(|e| TransportError::Quic(format!("read payload error: {}", e)))?;
+        let msg = wire::decode_message(&payload, flags)?;
 
-        send.finish()
-            .map_err(|e| TransportError::Quic(format!("finish response error: {}", e)))?;
+        if let Some(response) = handler(msg) {
+            let frame = wire::encode_message(&response, 0)
+                .map_err(TransportError::Wire)?;
+
+            send.write_all(&frame)
+                .await
+                .map_err(|e| TransportError::Quic(format!("write response error: {}", e)))?;
+        }
     }
 
+    send.finish()
+        .map_err(|e| TransportError::Quic(format!("finish response error: {}", e)))?;
+
     Ok(())
 }
 
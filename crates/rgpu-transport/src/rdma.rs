@@ -0,0 +1,905 @@
+//! RDMA for bulk data over InfiniBand and RoCE.
+//!
+//! The control connection stays on TCP. Once it is authenticated, each side
+//! opens a reliable-connected queue pair on its RDMA device and registers a
+//! ring for what the other side sends it, and the two swap queue pairs,
+//! ring addresses and keys in `Message::RdmaConnect`. From then on a payload
+//! of 64 KB or more is written straight into the peer's ring with an RDMA
+//! WRITE, and the TCP frame carries only its location
+//! (`FrameFlags::SHARED`), as on local IPC connections with shared memory.
+//! Neither kernel copies the data, and the receiver's CPU first touches it
+//! when it copies it out of the ring.
+//!
+//! libibverbs is loaded at run time: without it, or without an active port,
+//! opening a link fails and the connection keeps everything on TCP. Linux
+//! only.
+
+use tracing::warn;
+
+use rgpu_protocol::messages::RdmaEndpoint;
+use rgpu_protocol::wire::{self, FrameFlags};
+
+use crate::error::TransportError;
+
+/// Bytes each side registers for its peer to write into.
+pub const RDMA_RING_SIZE: usize = 64 * 1024 * 1024;
+
+/// A queue pair to one peer and the ring the peer writes into.
+pub struct RdmaLink {
+    link: sys::Link,
+}
+
+impl RdmaLink {
+    /// Open a queue pair on `device` (the first device if `None`) and
+    /// `port`, addressed by GID `gid_index` on RoCE.
+    pub fn open(device: Option<&str>, port: u8, gid_index: u8) -> Result<Self, TransportError> {
+        Ok(Self {
+            link: sys::Link::open(device, port, gid_index)?,
+        })
+    }
+
+    /// What the peer needs to reach this side, for `Message::RdmaConnect`.
+    pub fn local(&self) -> RdmaEndpoint {
+        self.link.local()
+    }
+
+    /// Connect the queue pair to the peer's. Nothing is written to the peer
+    /// before this.
+    pub fn connect(&mut self, remote: &RdmaEndpoint) -> Result<(), TransportError> {
+        self.link.connect(remote)
+    }
+
+    /// Name of the device the link is on.
+    pub fn device(&self) -> &str {
+        self.link.device()
+    }
+
+    /// Move a large payload of `frame` into the peer's ring, returning the
+    /// frame to send in its place. Small payloads, and payloads the ring has
+    /// no room for, stay in the frame.
+    pub async fn offload(&self, frame: Vec<u8>) -> Vec<u8> {
+        if frame.len() - wire::HEADER_SIZE < wire::SHARED_MEMORY_THRESHOLD {
+            return frame;
+        }
+        match self.link.write(&frame[wire::HEADER_SIZE..]).await {
+            Ok(Some(position)) => wire::shared_frame(&frame, position),
+            Ok(None) => frame,
+            Err(e) => {
+                warn!("RDMA write failed, sending over TCP: {}", e);
+                frame
+            }
+        }
+    }
+}
+
+/// The payload of a received frame, taken out of the RDMA ring if the frame
+/// is `SHARED`.
+pub fn unshare(
+    link: Option<&RdmaLink>,
+    flags: FrameFlags,
+    payload: Vec<u8>,
+) -> Result<(FrameFlags, Vec<u8>), TransportError> {
+    if !flags.contains(FrameFlags::SHARED) {
+        return Ok((flags, payload));
+    }
+    let link = link.ok_or_else(|| TransportError::Rdma("RDMA frame before RDMA was set up".to_string()))?;
+    let (position, len) = wire::shared_location(&payload)?;
+    let payload = link
+        .link
+        .take(position, len)
+        .ok_or_else(|| TransportError::Rdma(format!("RDMA frame points outside its ring ({} bytes at {})", len, position)))?;
+    Ok((flags - FrameFlags::SHARED, payload))
+}
+
+#[cfg(target_os = "linux")]
+mod sys {
+    //! libibverbs bindings. `ibv_post_send` and `ibv_poll_cq` are inline in
+    //! `verbs.h`, calling through the context's ops table, so the structs
+    //! they reach into are laid out here as rdma-core defines them.
+
+    use std::ffi::{c_int, c_uint, c_void, CStr, CString};
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::OnceLock;
+    use std::time::{Duration, Instant};
+
+    use libloading::{Library, Symbol};
+    use tracing::{debug, info};
+
+    use rgpu_protocol::messages::RdmaEndpoint;
+
+    use super::RDMA_RING_SIZE;
+    use crate::error::TransportError;
+
+    /// Ring header: the tail (bytes the receiver has copied out) at 0, then
+    /// the data from here on.
+    const RING_HEADER: usize = 64;
+    /// Largest ring a peer may ask this side to stage writes for.
+    const MAX_PEER_RING: u64 = 1 << 30;
+    const COMPLETION_TIMEOUT: Duration = Duration::from_secs(10);
+
+    const IBV_PORT_ACTIVE: c_uint = 4;
+    const IBV_QPT_RC: c_uint = 2;
+    const IBV_QPS_INIT: c_uint = 1;
+    const IBV_QPS_RTR: c_uint = 2;
+    const IBV_QPS_RTS: c_uint = 3;
+    const IBV_ACCESS_LOCAL_WRITE: c_int = 1;
+    const IBV_ACCESS_REMOTE_WRITE: c_int = 2;
+    const IBV_ACCESS_REMOTE_READ: c_int = 4;
+    const IBV_WR_RDMA_WRITE: c_uint = 0;
+    const IBV_WR_RDMA_READ: c_uint = 4;
+    const IBV_SEND_SIGNALED: c_uint = 2;
+    const IBV_WC_SUCCESS: c_uint = 0;
+
+    const IBV_QP_STATE: c_int = 1 << 0;
+    const IBV_QP_ACCESS_FLAGS: c_int = 1 << 3;
+    const IBV_QP_PKEY_INDEX: c_int = 1 << 4;
+    const IBV_QP_PORT: c_int = 1 << 5;
+    const IBV_QP_AV: c_int = 1 << 7;
+    const IBV_QP_PATH_MTU: c_int = 1 << 8;
+    const IBV_QP_TIMEOUT: c_int = 1 << 9;
+    const IBV_QP_RETRY_CNT: c_int = 1 << 10;
+    const IBV_QP_RNR_RETRY: c_int = 1 << 11;
+    const IBV_QP_RQ_PSN: c_int = 1 << 12;
+    const IBV_QP_MAX_QP_RD_ATOMIC: c_int = 1 << 13;
+    const IBV_QP_MIN_RNR_TIMER: c_int = 1 << 15;
+    const IBV_QP_SQ_PSN: c_int = 1 << 16;
+    const IBV_QP_MAX_DEST_RD_ATOMIC: c_int = 1 << 17;
+    const IBV_QP_DEST_QPN: c_int = 1 << 20;
+
+    type Opaque = c_void;
+    type PollCq = unsafe extern "C" fn(cq: *mut IbvCq, num_entries: c_int, wc: *mut IbvWc) -> c_int;
+    type PostSend = unsafe extern "C" fn(qp: *mut IbvQp, wr: *mut IbvSendWr, bad_wr: *mut *mut IbvSendWr) -> c_int;
+
+    /// `struct ibv_context_ops`, up to `post_send`
+    #[repr(C)]
+    struct IbvContextOps {
+        _before_poll_cq: [*mut Opaque; 11],
+        poll_cq: PollCq,
+        _before_post_send: [*mut Opaque; 13],
+        post_send: PostSend,
+    }
+
+    /// `struct ibv_context`, up to its ops
+    #[repr(C)]
+    struct IbvContext {
+        device: *mut Opaque,
+        ops: IbvContextOps,
+    }
+
+    /// `struct ibv_cq`, up to its context
+    #[repr(C)]
+    struct IbvCq {
+        context: *mut IbvContext,
+    }
+
+    /// `struct ibv_qp`, up to `qp_num`
+    #[repr(C)]
+    struct IbvQp {
+        context: *mut IbvContext,
+        qp_context: *mut Opaque,
+        pd: *mut Opaque,
+        send_cq: *mut IbvCq,
+        recv_cq: *mut IbvCq,
+        srq: *mut Opaque,
+        handle: u32,
+        qp_num: u32,
+    }
+
+    /// `struct ibv_mr`
+    #[repr(C)]
+    struct IbvMr {
+        context: *mut IbvContext,
+        pd: *mut Opaque,
+        addr: *mut c_void,
+        length: usize,
+        handle: u32,
+        lkey: u32,
+        rkey: u32,
+    }
+
+    /// `struct ibv_port_attr`, with room for fields newer libraries add
+    #[repr(C)]
+    struct IbvPortAttr {
+        state: c_uint,
+        max_mtu: c_uint,
+        active_mtu: c_uint,
+        gid_tbl_len: c_int,
+        port_cap_flags: u32,
+        max_msg_sz: u32,
+        bad_pkey_cntr: u32,
+        qkey_viol_cntr: u32,
+        pkey_tbl_len: u16,
+        lid: u16,
+        _rest: [u8; 128],
+    }
+
+    #[repr(C)]
+    struct IbvQpCap {
+        max_send_wr: u32,
+        max_recv_wr: u32,
+        max_send_sge: u32,
+        max_recv_sge: u32,
+        max_inline_data: u32,
+    }
+
+    #[repr(C)]
+    struct IbvQpInitAttr {
+        qp_context: *mut Opaque,
+        send_cq: *mut IbvCq,
+        recv_cq: *mut IbvCq,
+        srq: *mut Opaque,
+        cap: IbvQpCap,
+        qp_type: c_uint,
+        sq_sig_all: c_int,
+    }
+
+    #[repr(C)]
+    struct IbvGlobalRoute {
+        dgid: [u8; 16],
+        flow_label: u32,
+        sgid_index: u8,
+        hop_limit: u8,
+        traffic_class: u8,
+    }
+
+    #[repr(C)]
+    struct IbvAhAttr {
+        grh: IbvGlobalRoute,
+        dlid: u16,
+        sl: u8,
+        src_path_bits: u8,
+        static_rate: u8,
+        is_global: u8,
+        port_num: u8,
+    }
+
+    #[repr(C)]
+    struct IbvQpAttr {
+        qp_state: c_uint,
+        cur_qp_state: c_uint,
+        path_mtu: c_uint,
+        path_mig_state: c_uint,
+        qkey: u32,
+        rq_psn: u32,
+        sq_psn: u32,
+        dest_qp_num: u32,
+        qp_access_flags: c_uint,
+        cap: IbvQpCap,
+        ah_attr: IbvAhAttr,
+        alt_ah_attr: IbvAhAttr,
+        pkey_index: u16,
+        alt_pkey_index: u16,
+        en_sqd_async_notify: u8,
+        sq_draining: u8,
+        max_rd_atomic: u8,
+        max_dest_rd_atomic: u8,
+        min_rnr_timer: u8,
+        port_num: u8,
+        timeout: u8,
+        retry_cnt: u8,
+        rnr_retry: u8,
+        alt_port_num: u8,
+        alt_timeout: u8,
+        rate_limit: u32,
+    }
+
+    #[repr(C)]
+    struct IbvSge {
+        addr: u64,
+        length: u32,
+        lkey: u32,
+    }
+
+    /// `struct ibv_send_wr`, with the `rdma` member of its `wr` union
+    #[repr(C)]
+    struct IbvSendWr {
+        wr_id: u64,
+        next: *mut IbvSendWr,
+        sg_list: *mut IbvSge,
+        num_sge: c_int,
+        opcode: c_uint,
+        send_flags: c_uint,
+        imm_data: u32,
+        remote_addr: u64,
+        rkey: u32,
+        _wr: [u32; 5],
+        _qp_type: u32,
+        _bind_mw: [u64; 6],
+    }
+
+    #[repr(C)]
+    struct IbvWc {
+        wr_id: u64,
+        status: c_uint,
+        opcode: c_uint,
+        vendor_err: u32,
+        byte_len: u32,
+        imm_data: u32,
+        qp_num: u32,
+        src_qp: u32,
+        wc_flags: c_uint,
+        pkey_index: u16,
+        slid: u16,
+        sl: u8,
+        dlid_path_bits: u8,
+    }
+
+    type FnGetDeviceList = unsafe extern "C" fn(num_devices: *mut c_int) -> *mut *mut Opaque;
+    type FnFreeDeviceList = unsafe extern "C" fn(list: *mut *mut Opaque);
+    type FnGetDeviceName = unsafe extern "C" fn(device: *mut Opaque) -> *const std::ffi::c_char;
+    type FnOpenDevice = unsafe extern "C" fn(device: *mut Opaque) -> *mut IbvContext;
+    type FnCloseDevice = unsafe extern "C" fn(context: *mut IbvContext) -> c_int;
+    type FnQueryPort = unsafe extern "C" fn(context: *mut IbvContext, port: u8, attr: *mut IbvPortAttr) -> c_int;
+    type FnQueryGid = unsafe extern "C" fn(context: *mut IbvContext, port: u8, index: c_int, gid: *mut [u8; 16]) -> c_int;
+    type FnAllocPd = unsafe extern "C" fn(context: *mut IbvContext) -> *mut Opaque;
+    type FnDeallocPd = unsafe extern "C" fn(pd: *mut Opaque) -> c_int;
+    type FnRegMr = unsafe extern "C" fn(pd: *mut Opaque, addr: *mut c_void, length: usize, access: c_int) -> *mut IbvMr;
+    type FnDeregMr = unsafe extern "C" fn(mr: *mut IbvMr) -> c_int;
+    type FnCreateCq = unsafe extern "C" fn(
+        context: *mut IbvContext,
+        cqe: c_int,
+        cq_context: *mut c_void,
+        channel: *mut Opaque,
+        comp_vector: c_int,
+    ) -> *mut IbvCq;
+    type FnDestroyCq = unsafe extern "C" fn(cq: *mut IbvCq) -> c_int;
+    type FnCreateQp = unsafe extern "C" fn(pd: *mut Opaque, attr: *mut IbvQpInitAttr) -> *mut IbvQp;
+    type FnDestroyQp = unsafe extern "C" fn(qp: *mut IbvQp) -> c_int;
+    type FnModifyQp = unsafe extern "C" fn(qp: *mut IbvQp, attr: *mut IbvQpAttr, mask: c_int) -> c_int;
+
+    struct Verbs {
+        _lib: Library,
+        get_device_list: FnGetDeviceList,
+        free_device_list: FnFreeDeviceList,
+        get_device_name: FnGetDeviceName,
+        open_device: FnOpenDevice,
+        close_device: FnCloseDevice,
+        query_port: FnQueryPort,
+        query_gid: FnQueryGid,
+        alloc_pd: FnAllocPd,
+        dealloc_pd: FnDeallocPd,
+        reg_mr: FnRegMr,
+        dereg_mr: FnDeregMr,
+        create_cq: FnCreateCq,
+        destroy_cq: FnDestroyCq,
+        create_qp: FnCreateQp,
+        destroy_qp: FnDestroyQp,
+        modify_qp: FnModifyQp,
+    }
+
+    impl Verbs {
+        fn load() -> Option<Self> {
+            let lib = ["libibverbs.so.1", "libibverbs.so"]
+                .iter()
+                .find_map(|name| unsafe { Library::new(name).ok() })?;
+            unsafe {
+                let verbs = Self {
+                    get_device_list: Self::sym(&lib, "ibv_get_device_list")?,
+                    free_device_list: Self::sym(&lib, "ibv_free_device_list")?,
+                    get_device_name: Self::sym(&lib, "ibv_get_device_name")?,
+                    open_device: Self::sym(&lib, "ibv_open_device")?,
+                    close_device: Self::sym(&lib, "ibv_close_device")?,
+                    query_port: Self::sym(&lib, "ibv_query_port")?,
+                    query_gid: Self::sym(&lib, "ibv_query_gid")?,
+                    alloc_pd: Self::sym(&lib, "ibv_alloc_pd")?,
+                    dealloc_pd: Self::sym(&lib, "ibv_dealloc_pd")?,
+                    reg_mr: Self::sym(&lib, "ibv_reg_mr")?,
+                    dereg_mr: Self::sym(&lib, "ibv_dereg_mr")?,
+                    create_cq: Self::sym(&lib, "ibv_create_cq")?,
+                    destroy_cq: Self::sym(&lib, "ibv_destroy_cq")?,
+                    create_qp: Self::sym(&lib, "ibv_create_qp")?,
+                    destroy_qp: Self::sym(&lib, "ibv_destroy_qp")?,
+                    modify_qp: Self::sym(&lib, "ibv_modify_qp")?,
+                    _lib: lib,
+                };
+                info!("loaded libibverbs for RDMA");
+                Some(verbs)
+            }
+        }
+
+        unsafe fn sym<F: Copy>(lib: &Library, name: &str) -> Option<F> {
+            lib.get(name.as_bytes()).ok().map(|s: Symbol<F>| *s)
+        }
+    }
+
+    fn verbs() -> Result<&'static Verbs, TransportError> {
+        static VERBS: OnceLock<Option<Verbs>> = OnceLock::new();
+        VERBS
+            .get_or_init(Verbs::load)
+            .as_ref()
+            .ok_or_else(|| TransportError::Rdma("libibverbs not found".to_string()))
+    }
+
+    fn error(what: &str) -> TransportError {
+        TransportError::Rdma(format!("{}: {}", what, std::io::Error::last_os_error()))
+    }
+
+    /// Page-aligned, zeroed memory to register.
+    struct Buffer {
+        ptr: *mut u8,
+        layout: std::alloc::Layout,
+    }
+
+    impl Buffer {
+        fn new(size: usize) -> Self {
+            let layout = std::alloc::Layout::from_size_align(size, 4096).expect("valid RDMA buffer layout");
+            // SAFETY: the layout has a non-zero size.
+            let ptr = unsafe { std::alloc::alloc_zeroed(layout) };
+            if ptr.is_null() {
+                std::alloc::handle_alloc_error(layout);
+            }
+            Self { ptr, layout }
+        }
+    }
+
+    impl Drop for Buffer {
+        fn drop(&mut self) {
+            // SAFETY: allocated in `new` with this layout.
+            unsafe { std::alloc::dealloc(self.ptr, self.layout) }
+        }
+    }
+
+    /// The sending half, once connected: a staging copy of the peer's ring
+    /// and where this side is in it.
+    struct Sender {
+        staging: Buffer,
+        staging_mr: *mut IbvMr,
+        remote_address: u64,
+        remote_key: u32,
+        capacity: u64,
+        /// Bytes ever written, including padding skipped at the end of the ring
+        head: u64,
+        /// The peer's tail when last read
+        tail: u64,
+    }
+
+    pub struct Link {
+        verbs: &'static Verbs,
+        device: String,
+        context: *mut IbvContext,
+        pd: *mut Opaque,
+        cq: *mut IbvCq,
+        qp: *mut IbvQp,
+        port: u8,
+        gid_index: u8,
+        lid: u16,
+        gid: [u8; 16],
+        mtu: c_uint,
+        psn: u32,
+        ring: Buffer,
+        ring_mr: *mut IbvMr,
+        sender: Option<tokio::sync::Mutex<Sender>>,
+    }
+
+    // SAFETY: the verbs objects may be used from any thread; the ring's tail
+    // is only moved by `take` and the sending half is behind a mutex.
+    unsafe impl Send for Link {}
+    unsafe impl Sync for Link {}
+    unsafe impl Send for Sender {}
+    unsafe impl Sync for Sender {}
+
+    impl Link {
+        pub fn open(device: Option<&str>, port: u8, gid_index: u8) -> Result<Self, TransportError> {
+            let verbs = verbs()?;
+            let (context, name) = open_device(verbs, device)?;
+            let mut link = Self {
+                verbs,
+                device: name,
+                context,
+                pd: std::ptr::null_mut(),
+                cq: std::ptr::null_mut(),
+                qp: std::ptr::null_mut(),
+                port,
+                gid_index,
+                lid: 0,
+                gid: [0; 16],
+                mtu: 0,
+                psn: rand::random::<u32>() & 0xff_ffff,
+                ring: Buffer::new(RING_HEADER + RDMA_RING_SIZE),
+                ring_mr: std::ptr::null_mut(),
+                sender: None,
+            };
+            // SAFETY: each call gets objects created by the ones before it;
+            // `Drop` releases whatever was created if a later step fails.
+            unsafe {
+                let mut attr: IbvPortAttr = std::mem::zeroed();
+                if (verbs.query_port)(context, port, &mut attr) != 0 {
+                    return Err(error("can't query RDMA port"));
+                }
+                if attr.state != IBV_PORT_ACTIVE {
+                    return Err(TransportError::Rdma(format!("port {} of {} is not active", port, link.device)));
+                }
+                link.lid = attr.lid;
+                link.mtu = attr.active_mtu;
+                if (verbs.query_gid)(context, port, gid_index as c_int, &mut link.gid) != 0 {
+                    return Err(error("can't query RDMA GID"));
+                }
+
+                link.pd = (verbs.alloc_pd)(context);
+                if link.pd.is_null() {
+                    return Err(error("can't allocate an RDMA protection domain"));
+                }
+                link.cq = (verbs.create_cq)(context, 16, std::ptr::null_mut(), std::ptr::null_mut(), 0);
+                if link.cq.is_null() {
+                    return Err(error("can't create an RDMA completion queue"));
+                }
+                let mut init = IbvQpInitAttr {
+                    qp_context: std::ptr::null_mut(),
+                    send_cq: link.cq,
+                    recv_cq: link.cq,
+                    srq: std::ptr::null_mut(),
+                    cap: IbvQpCap {
+                        max_send_wr: 16,
+                        max_recv_wr: 1,
+                        max_send_sge: 1,
+                        max_recv_sge: 1,
+                        max_inline_data: 0,
+                    },
+                    qp_type: IBV_QPT_RC,
+                    sq_sig_all: 0,
+                };
+                link.qp = (verbs.create_qp)(link.pd, &mut init);
+                if link.qp.is_null() {
+                    return Err(error("can't create an RDMA queue pair"));
+                }
+                link.ring_mr = (verbs.reg_mr)(
+                    link.pd,
+                    link.ring.ptr.cast(),
+                    link.ring.layout.size(),
+                    IBV_ACCESS_LOCAL_WRITE | IBV_ACCESS_REMOTE_WRITE | IBV_ACCESS_REMOTE_READ,
+                );
+                if link.ring_mr.is_null() {
+                    return Err(error("can't register the RDMA ring"));
+                }
+
+                let mut attr: IbvQpAttr = std::mem::zeroed();
+                attr.qp_state = IBV_QPS_INIT;
+                attr.port_num = port;
+                attr.qp_access_flags = (IBV_ACCESS_REMOTE_WRITE | IBV_ACCESS_REMOTE_READ) as c_uint;
+                let mask = IBV_QP_STATE | IBV_QP_PKEY_INDEX | IBV_QP_PORT | IBV_QP_ACCESS_FLAGS;
+                if (verbs.modify_qp)(link.qp, &mut attr, mask) != 0 {
+                    return Err(error("can't initialize the RDMA queue pair"));
+                }
+            }
+            debug!("RDMA queue pair {} on {} port {}", link.qp_num(), link.device, port);
+            Ok(link)
+        }
+
+        pub fn device(&self) -> &str {
+            &self.device
+        }
+
+        fn qp_num(&self) -> u32 {
+            // SAFETY: `qp` is a live queue pair.
+            unsafe { (*self.qp).qp_num }
+        }
+
+        pub fn local(&self) -> RdmaEndpoint {
+            RdmaEndpoint {
+                lid: self.lid,
+                gid: self.gid,
+                qp_num: self.qp_num(),
+                psn: self.psn,
+                ring_address: self.ring.ptr as u64,
+                ring_key: unsafe { (*self.ring_mr).rkey },
+                ring_size: RDMA_RING_SIZE as u64,
+            }
+        }
+
+        pub fn connect(&mut self, remote: &RdmaEndpoint) -> Result<(), TransportError> {
+            if remote.ring_size == 0 || remote.ring_size > MAX_PEER_RING {
+                return Err(TransportError::Rdma(format!("peer ring of {} bytes", remote.ring_size)));
+            }
+            let staging = Buffer::new(RING_HEADER + remote.ring_size as usize);
+            // SAFETY: as in `open`; the staging buffer outlives its
+            // registration, which `Drop` removes first.
+            unsafe {
+                let staging_mr =
+                    (self.verbs.reg_mr)(self.pd, staging.ptr.cast(), staging.layout.size(), IBV_ACCESS_LOCAL_WRITE);
+                if staging_mr.is_null() {
+                    return Err(error("can't register the RDMA staging buffer"));
+                }
+                self.sender = Some(tokio::sync::Mutex::new(Sender {
+                    staging,
+                    staging_mr,
+                    remote_address: remote.ring_address,
+                    remote_key: remote.ring_key,
+                    capacity: remote.ring_size,
+                    head: 0,
+                    tail: 0,
+                }));
+
+                let mut attr: IbvQpAttr = std::mem::zeroed();
+                attr.qp_state = IBV_QPS_RTR;
+                attr.path_mtu = self.mtu;
+                attr.dest_qp_num = remote.qp_num;
+                attr.rq_psn = remote.psn;
+                attr.max_dest_rd_atomic = 1;
+                attr.min_rnr_timer = 12;
+                attr.ah_attr.dlid = remote.lid;
+                attr.ah_attr.port_num = self.port;
+                // RoCE has no LIDs; its packets are routed by GID.
+                if remote.lid == 0 {
+                    attr.ah_attr.is_global = 1;
+                    attr.ah_attr.grh.dgid = remote.gid;
+                    attr.ah_attr.grh.sgid_index = self.gid_index;
+                    attr.ah_attr.grh.hop_limit = 1;
+                }
+                let mask = IBV_QP_STATE
+                    | IBV_QP_AV
+                    | IBV_QP_PATH_MTU
+                    | IBV_QP_DEST_QPN
+                    | IBV_QP_RQ_PSN
+                    | IBV_QP_MAX_DEST_RD_ATOMIC
+                    | IBV_QP_MIN_RNR_TIMER;
+                if (self.verbs.modify_qp)(self.qp, &mut attr, mask) != 0 {
+                    return Err(error("can't connect the RDMA queue pair"));
+                }
+
+                let mut attr: IbvQpAttr = std::mem::zeroed();
+                attr.qp_state = IBV_QPS_RTS;
+                attr.timeout = 14;
+                attr.retry_cnt = 7;
+                attr.rnr_retry = 7;
+                attr.sq_psn = self.psn;
+                attr.max_rd_atomic = 1;
+                let mask = IBV_QP_STATE
+                    | IBV_QP_TIMEOUT
+                    | IBV_QP_RETRY_CNT
+                    | IBV_QP_RNR_RETRY
+                    | IBV_QP_SQ_PSN
+                    | IBV_QP_MAX_QP_RD_ATOMIC;
+                if (self.verbs.modify_qp)(self.qp, &mut attr, mask) != 0 {
+                    return Err(error("can't make the RDMA queue pair ready to send"));
+                }
+            }
+            Ok(())
+        }
+
+        /// Write `bytes` into the peer's ring, returning their position, or
+        /// `None` if the ring hasn't room for them right now. Entries never
+        /// wrap, as in [`rgpu_common::shm`].
+        pub async fn write(&self, bytes: &[u8]) -> Result<Option<u64>, TransportError> {
+            let sender = self
+                .sender
+                .as_ref()
+                .ok_or_else(|| TransportError::Rdma("RDMA link not connected".to_string()))?;
+            let mut sender = sender.lock().await;
+            let len = bytes.len() as u64;
+            let capacity = sender.capacity;
+            if len > capacity {
+                return Ok(None);
+            }
+            let position = if sender.head % capacity + len > capacity {
+                sender.head.next_multiple_of(capacity)
+            } else {
+                sender.head
+            };
+            if position + len - sender.tail > capacity {
+                sender.tail = self.read_remote_tail(&sender).await?;
+                if position + len - sender.tail > capacity {
+                    return Ok(None);
+                }
+            }
+            let offset = RING_HEADER + (position % capacity) as usize;
+            // SAFETY: the entry lies within the staging buffer's data area,
+            // which mirrors the peer's ring.
+            unsafe {
+                let local = sender.staging.ptr.add(offset);
+                std::ptr::copy_nonoverlapping(bytes.as_ptr(), local, bytes.len());
+                self.post(
+                    IBV_WR_RDMA_WRITE,
+                    local,
+                    bytes.len() as u32,
+                    (*sender.staging_mr).lkey,
+                    sender.remote_address + offset as u64,
+                    sender.remote_key,
+                )?;
+            }
+            // Once the write completes, the data is in the peer's memory,
+            // ahead of the frame that points to it.
+            self.complete().await?;
+            sender.head = position + len;
+            Ok(Some(position))
+        }
+
+        /// Read how far the peer has got through its ring.
+        async fn read_remote_tail(&self, sender: &Sender) -> Result<u64, TransportError> {
+            self.post(
+                IBV_WR_RDMA_READ,
+                sender.staging.ptr,
+                8,
+                unsafe { (*sender.staging_mr).lkey },
+                sender.remote_address,
+                sender.remote_key,
+            )?;
+            self.complete().await?;
+            // SAFETY: the read has landed in the first 8 bytes of the header.
+            Ok(unsafe { std::ptr::read_volatile(sender.staging.ptr.cast::<u64>()) })
+        }
+
+        fn post(
+            &self,
+            opcode: c_uint,
+            local: *mut u8,
+            len: u32,
+            lkey: u32,
+            remote_addr: u64,
+            rkey: u32,
+        ) -> Result<(), TransportError> {
+            let mut sge = IbvSge {
+                addr: local as u64,
+                length: len,
+                lkey,
+            };
+            // SAFETY: a zeroed work request is valid; the fields used are set
+            // below, and `sge` outlives the call.
+            unsafe {
+                let mut wr: IbvSendWr = std::mem::zeroed();
+                wr.sg_list = &mut sge;
+                wr.num_sge = 1;
+                wr.opcode = opcode;
+                wr.send_flags = IBV_SEND_SIGNALED;
+                wr.remote_addr = remote_addr;
+                wr.rkey = rkey;
+                let mut bad = std::ptr::null_mut();
+                let post_send = (*(*self.qp).context).ops.post_send;
+                match post_send(self.qp, &mut wr, &mut bad) {
+                    0 => Ok(()),
+                    err => Err(TransportError::Rdma(format!("posting an RDMA request failed ({})", err))),
+                }
+            }
+        }
+
+        /// Wait for the completion of the request just posted. Transfers
+        /// take microseconds, so the queue is polled rather than armed for
+        /// an event.
+        async fn complete(&self) -> Result<(), TransportError> {
+            let deadline = Instant::now() + COMPLETION_TIMEOUT;
+            // SAFETY: `cq` is a live completion queue.
+            let poll_cq = unsafe { (*(*self.cq).context).ops.poll_cq };
+            let mut spins = 0u32;
+            loop {
+                let mut wc: IbvWc = unsafe { std::mem::zeroed() };
+                match unsafe { poll_cq(self.cq, 1, &mut wc) } {
+                    0 => {}
+                    1 if wc.status == IBV_WC_SUCCESS => return Ok(()),
+                    1 => {
+                        return Err(TransportError::Rdma(format!(
+                            "RDMA request failed (status {}, vendor error {})",
+                            wc.status, wc.vendor_err
+                        )))
+                    }
+                    err => return Err(TransportError::Rdma(format!("polling RDMA completions failed ({})", err))),
+                }
+                spins += 1;
+                if spins.is_multiple_of(1024) {
+                    if Instant::now() > deadline {
+                        return Err(TransportError::Timeout);
+                    }
+                    tokio::task::yield_now().await;
+                } else {
+                    std::hint::spin_loop();
+                }
+            }
+        }
+
+        /// Copy out the `len` bytes the peer wrote at `position` and free
+        /// them, along with anything before them. `None` if the location
+        /// isn't one the ring can hold.
+        pub fn take(&self, position: u64, len: usize) -> Option<Vec<u8>> {
+            // SAFETY: the tail is the first 8 bytes of the page-aligned ring.
+            let tail = unsafe { &*self.ring.ptr.cast::<AtomicU64>() };
+            let capacity = RDMA_RING_SIZE as u64;
+            let len = len as u64;
+            let offset = position % capacity;
+            let start = tail.load(Ordering::Acquire);
+            if position < start || position + len - start > capacity || offset + len > capacity {
+                return None;
+            }
+            let mut bytes = vec![0u8; len as usize];
+            // SAFETY: checked to lie within the data area above.
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    self.ring.ptr.add(RING_HEADER + offset as usize),
+                    bytes.as_mut_ptr(),
+                    bytes.len(),
+                );
+            }
+            tail.store(position + len, Ordering::Release);
+            Some(bytes)
+        }
+    }
+
+    impl Drop for Link {
+        fn drop(&mut self) {
+            // SAFETY: each object is released once, the queue pair before
+            // the memory it could reach and everything before its context.
+            unsafe {
+                if !self.qp.is_null() {
+                    (self.verbs.destroy_qp)(self.qp);
+                }
+                if let Some(sender) = self.sender.take() {
+                    (self.verbs.dereg_mr)(sender.into_inner().staging_mr);
+                }
+                if !self.ring_mr.is_null() {
+                    (self.verbs.dereg_mr)(self.ring_mr);
+                }
+                if !self.cq.is_null() {
+                    (self.verbs.destroy_cq)(self.cq);
+                }
+                if !self.pd.is_null() {
+                    (self.verbs.dealloc_pd)(self.pd);
+                }
+                (self.verbs.close_device)(self.context);
+            }
+        }
+    }
+
+    /// Open `name`, or the first RDMA device if `None`.
+    fn open_device(verbs: &Verbs, name: Option<&str>) -> Result<(*mut IbvContext, String), TransportError> {
+        let wanted = name.map(|name| CString::new(name).unwrap_or_default());
+        // SAFETY: the list is freed once a device is opened (which keeps its
+        // own reference) or none is found.
+        unsafe {
+            let mut count: c_int = 0;
+            let list = (verbs.get_device_list)(&mut count);
+            if list.is_null() {
+                return Err(error("can't list RDMA devices"));
+            }
+            let devices = std::slice::from_raw_parts(list, count.max(0) as usize);
+            let found = devices.iter().copied().find_map(|device| {
+                let device_name = CStr::from_ptr((verbs.get_device_name)(device));
+                match &wanted {
+                    Some(wanted) if wanted.as_c_str() != device_name => None,
+                    _ => Some((device, device_name.to_string_lossy().into_owned())),
+                }
+            });
+            let opened = found.map(|(device, name)| ((verbs.open_device)(device), name));
+            (verbs.free_device_list)(list);
+            match opened {
+                Some((context, name)) if !context.is_null() => Ok((context, name)),
+                Some((_, name)) => Err(error(&format!("can't open RDMA device {}", name))),
+                None => Err(TransportError::Rdma(match name {
+                    Some(name) => format!("no RDMA device named {}", name),
+                    None => "no RDMA devices".to_string(),
+                })),
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod sys {
+    use rgpu_protocol::messages::RdmaEndpoint;
+
+    use crate::error::TransportError;
+
+    pub enum Link {}
+
+    impl Link {
+        pub fn open(_device: Option<&str>, _port: u8, _gid_index: u8) -> Result<Self, TransportError> {
+            Err(TransportError::Rdma("RDMA is only supported on Linux".to_string()))
+        }
+
+        pub fn device(&self) -> &str {
+            match *self {}
+        }
+
+        pub fn local(&self) -> RdmaEndpoint {
+            match *self {}
+        }
+
+        pub fn connect(&mut self, _remote: &RdmaEndpoint) -> Result<(), TransportError> {
+            match *self {}
+        }
+
+        pub async fn write(&self, _bytes: &[u8]) -> Result<Option<u64>, TransportError> {
+            match *self {}
+        }
+
+        pub fn take(&self, _position: u64, _len: usize) -> Option<Vec<u8>> {
+            match *self {}
+        }
+    }
+}
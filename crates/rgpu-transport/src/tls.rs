@@ -3,6 +3,13 @@ use std::sync::Arc;
 use rustls::pki_types::{CertificateDer, PrivateKeyDer};
 use tokio_rustls::{TlsAcceptor, TlsConnector};
 
+/// Make aws-lc-rs the process's rustls crypto provider. Both backends are
+/// compiled in (quinn brings in ring), so rustls won't pick one by itself.
+pub(crate) fn install_crypto_provider() {
+    // Fails only if a provider is already installed, which is as good.
+    let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+}
+
 /// Load certificate chain and private key from PEM files.
 pub fn load_certs_and_key(
     cert_path: &str,
@@ -34,6 +41,7 @@ pub fn build_server_tls(
     let key: PrivateKeyDer<'static> = rustls_pemfile::private_key(&mut &key_pem[..])?
         .ok_or("no private key found in key file")?;
 
+    install_crypto_provider();
     let config = rustls::ServerConfig::builder()
         .with_no_client_auth()
         .with_single_cert(certs, key)?;
@@ -58,6 +66,7 @@ pub fn build_client_tls(
         root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
     }
 
+    install_crypto_provider();
     let config = rustls::ClientConfig::builder()
         .with_root_certificates(root_store)
         .with_no_client_auth();
@@ -68,6 +77,7 @@ pub fn build_client_tls(
 /// Build a TLS connector that accepts any certificate (for development only).
 pub fn build_insecure_client_tls() -> Result<TlsConnector, Box<dyn std::error::Error + Send + Sync>>
 {
+    install_crypto_provider();
     let config = rustls::ClientConfig::builder()
         .dangerous()
         .with_custom_certificate_verifier(Arc::new(InsecureVerifier))
@@ -0,0 +1,161 @@
+//! The framed protocol tunnelled over WebSocket.
+//!
+//! For networks where the server is only reachable through an HTTP(S)
+//! reverse proxy or a firewall that lets nothing else through. The byte
+//! stream a TCP connection would carry goes in binary WebSocket messages
+//! instead; message boundaries mean nothing, so frames are split across
+//! them and read back as one stream. The client connects to a `ws://` or
+//! `wss://` URL, a proxy in between may terminate TLS and forward to the
+//! server's plain WebSocket port.
+
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_util::{Sink, Stream};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::{Bytes, Error as WsError, Message as WsMessage};
+use tokio_tungstenite::WebSocketStream;
+use tracing::debug;
+
+use crate::error::TransportError;
+
+/// Most bytes put in one WebSocket message. Proxies and the peer limit
+/// message size; large frames are split instead.
+pub const MAX_CHUNK: usize = 1 << 20;
+
+/// A byte stream that can carry a WebSocket connection.
+pub trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+/// A WebSocket connection read and written as a byte stream. Writes are
+/// buffered until flushed.
+pub struct WsStream<S> {
+    inner: WebSocketStream<S>,
+    /// What is left of the last message read
+    unread: Bytes,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> WsStream<S> {
+    fn new(inner: WebSocketStream<S>) -> Self {
+        Self { inner, unread: Bytes::new() }
+    }
+}
+
+fn io_error(e: WsError) -> std::io::Error {
+    match e {
+        WsError::Io(e) => e,
+        WsError::ConnectionClosed | WsError::AlreadyClosed => std::io::ErrorKind::BrokenPipe.into(),
+        e => std::io::Error::other(e),
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for WsStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        while self.unread.is_empty() {
+            match ready!(Pin::new(&mut self.inner).poll_next(cx)) {
+                Some(Ok(WsMessage::Binary(data))) => self.unread = data,
+                // Pongs are queued by tungstenite and sent with the next write
+                Some(Ok(WsMessage::Ping(_) | WsMessage::Pong(_) | WsMessage::Frame(_))) => {}
+                Some(Ok(WsMessage::Text(_))) => {
+                    return Poll::Ready(Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "text message on an RGPU WebSocket",
+                    )));
+                }
+                Some(Ok(WsMessage::Close(_))) | None => return Poll::Ready(Ok(())),
+                Some(Err(e)) => return Poll::Ready(Err(io_error(e))),
+            }
+        }
+        let n = self.unread.len().min(buf.remaining());
+        let chunk = self.unread.split_to(n);
+        buf.put_slice(&chunk);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for WsStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<std::io::Result<usize>> {
+        ready!(Pin::new(&mut self.inner).poll_ready(cx)).map_err(io_error)?;
+        let n = buf.len().min(MAX_CHUNK);
+        Pin::new(&mut self.inner)
+            .start_send(WsMessage::Binary(Bytes::copy_from_slice(&buf[..n])))
+            .map_err(io_error)?;
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx).map_err(io_error)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx).map_err(io_error)
+    }
+}
+
+/// The URL to connect to for an endpoint address: the address itself if it
+/// is a `ws://` or `wss://` URL, `ws://<address>/` for a bare `host:port`.
+pub fn url(address: &str) -> String {
+    if address.starts_with("ws://") || address.starts_with("wss://") {
+        address.to_string()
+    } else {
+        format!("ws://{}/", address)
+    }
+}
+
+/// Connect to the server at `address` (see [`url`]). `wss://` checks the
+/// server's certificate against `ca_cert`, or the usual web roots without
+/// one, as a reverse proxy is likely to have a public certificate. With
+/// `health_check`, the TCP connection to the server or proxy gets
+/// keepalive probes (see [`crate::health`]).
+pub async fn connect(
+    address: &str,
+    ca_cert: Option<&str>,
+    health_check: Option<std::time::Duration>,
+) -> Result<WsStream<Box<dyn Io>>, TransportError> {
+    let request = url(address)
+        .into_client_request()
+        .map_err(|e| TransportError::WebSocket(e.to_string()))?;
+    let uri = request.uri();
+    let secure = uri.scheme_str() == Some("wss");
+    let host = uri
+        .host()
+        .ok_or_else(|| TransportError::WebSocket(format!("no host in {}", uri)))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = uri.port_u16().unwrap_or(if secure { 443 } else { 80 });
+
+    let tcp = TcpStream::connect((host.as_str(), port)).await?;
+    tcp.set_nodelay(true)?;
+    if let Some(interval) = health_check {
+        if let Err(e) = crate::health::tcp_keepalive(&tcp, interval) {
+            debug!("no TCP keepalive for {}: {}", address, e);
+        }
+    }
+    let stream: Box<dyn Io> = if secure {
+        let connector =
+            crate::tls::build_client_tls(ca_cert).map_err(|e| TransportError::WebSocket(e.to_string()))?;
+        let name = ServerName::try_from(host).map_err(|e| TransportError::WebSocket(e.to_string()))?;
+        Box::new(connector.connect(name, tcp).await?)
+    } else {
+        Box::new(tcp)
+    };
+    let (inner, _) = tokio_tungstenite::client_async(request, stream)
+        .await
+        .map_err(|e| TransportError::WebSocket(e.to_string()))?;
+    debug!("WebSocket connection established to {}", address);
+    Ok(WsStream::new(inner))
+}
+
+/// Take the WebSocket upgrade of a connection to the server's WebSocket
+/// port, whatever the path: proxies may forward any path to it.
+pub async fn accept<S: AsyncRead + AsyncWrite + Unpin>(stream: S) -> Result<WsStream<S>, TransportError> {
+    let inner = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(|e| TransportError::WebSocket(e.to_string()))?;
+    Ok(WsStream::new(inner))
+}
@@ -44,18 +44,23 @@ struct ServerConnection {
 }
 
 impl ServerConnection {
-    /// Send a message and read the response.
+    /// Send a message and read the response. GPU lists the server pushes
+    /// in between are skipped; the UI polls `QueryGpus` anyway.
     async fn request(&mut self, msg: &Message) -> anyhow::Result<Message> {
         let frame = wire::encode_message(msg, 0)?;
         self.writer.write_all(&frame).await?;
 
-        let mut header_buf = [0u8; wire::HEADER_SIZE];
-        self.reader.read_exact(&mut header_buf).await?;
-        let (flags, _, payload_len) = wire::decode_header(&header_buf)?;
-        let mut payload = vec![0u8; payload_len as usize];
-        self.reader.read_exact(&mut payload).await?;
-        let response = wire::decode_message(&payload, flags)?;
-        Ok(response)
+        loop {
+            let mut header_buf = [0u8; wire::HEADER_SIZE];
+            self.reader.read_exact(&mut header_buf).await?;
+            let (flags, _, payload_len) = wire::decode_header(&header_buf)?;
+            let mut payload = vec![0u8; payload_len as usize];
+            self.reader.read_exact(&mut payload).await?;
+            match wire::decode_message(&payload, flags)? {
+                Message::ServerTopologyChanged { .. } => continue,
+                response => return Ok(response),
+            }
+        }
     }
 }
 
@@ -182,8 +187,9 @@ async fn fetcher_loop(state: Arc<Mutex<UiState>>, ctx: egui::Context) {
                 cuda_commands: metrics_ref.cuda_commands.load(std::sync::atomic::Ordering::Relaxed),
                 vulkan_commands: metrics_ref.vulkan_commands.load(std::sync::atomic::Ordering::Relaxed),
                 uptime_secs: uptime,
+                sessions: metrics_ref.session_summaries(),
             };
-            let gpu_infos = srv.server_ref.gpu_infos().to_vec();
+            let gpu_infos = srv.server_ref.gpu_status();
 
             let mut st = state.lock().unwrap();
             st.embedded_server_gpus = gpu_infos;
@@ -261,6 +267,7 @@ async fn fetcher_loop(state: Arc<Mutex<UiState>>, ctx: egui::Context) {
                                 st.servers[i].connection_state =
                                     ServerConnectionState::Error(e.to_string());
                                 st.push_error(format!("query gpus {}: {}", address, e));
+                                st.note_failover(i);
                             }
                             ctx.request_repaint();
                             continue;
@@ -279,6 +286,7 @@ async fn fetcher_loop(state: Arc<Mutex<UiState>>, ctx: egui::Context) {
                                 vulkan_commands,
                                 uptime_secs,
                                 server_id,
+                                sessions,
                                 ..
                             }) => {
                                 let snapshot = MetricsSnapshot {
@@ -290,6 +298,7 @@ async fn fetcher_loop(state: Arc<Mutex<UiState>>, ctx: egui::Context) {
                                     cuda_commands,
                                     vulkan_commands,
                                     uptime_secs,
+                                    sessions,
                                 };
                                 let mut st = state.lock().unwrap();
                                 if i < st.servers.len() {
@@ -307,6 +316,7 @@ async fn fetcher_loop(state: Arc<Mutex<UiState>>, ctx: egui::Context) {
                                     st.servers[i].connection_state =
                                         ServerConnectionState::Error(e.to_string());
                                     st.push_error(format!("query metrics {}: {}", address, e));
+                                    st.note_failover(i);
                                 }
                                 ctx.request_repaint();
                                 continue;
@@ -361,6 +371,7 @@ async fn handle_server_lifecycle(
                 port: cfg.port,
                 bind: cfg.bind.clone(),
                 transport: cfg.transport.clone(),
+                websocket_port: None,
                 cert_path: if cfg.cert_path.is_empty() {
                     None
                 } else {
@@ -373,6 +384,19 @@ async fn handle_server_lifecycle(
                 },
                 expose_gpus: None,
                 max_clients: cfg.max_clients,
+                session_vram_quota_mb: 0,
+                session_grace_secs: 0,
+                gpu_rescan_secs: 30,
+                numa_affinity: false,
+                device_affinity: Vec::new(),
+                scheduling: Default::default(),
+                compression: Default::default(),
+                rdma: Default::default(),
+                limits: Default::default(),
+                audit: Default::default(),
+                device_attributes: Default::default(),
+                device_overrides: Vec::new(),
+                virtual_gpus: Vec::new(),
             };
             let tokens = cfg.tokens.clone();
             let address = format!("127.0.0.1:{}", cfg.port);
@@ -395,7 +419,7 @@ async fn handle_server_lifecycle(
 
         // Set status to running and populate initial GPU info directly (no TCP self-connect)
         {
-            let gpu_infos = server.gpu_infos().to_vec();
+            let gpu_infos = server.gpu_status();
             let mut st = state.lock().unwrap();
             st.local_server_status = LocalServerStatus::Running;
             st.embedded_server_gpus = gpu_infos;
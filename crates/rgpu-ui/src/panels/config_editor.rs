@@ -1,7 +1,7 @@
 use egui::{Color32, RichText, Ui};
 
 use rgpu_core::config::{
-    GpuOrdering, RgpuConfig, ServerEndpoint, TokenEntry, TransportMode,
+    GpuOrdering, PlacementPolicy, RgpuConfig, ServerEndpoint, TokenEntry, TransportMode,
 };
 
 use crate::state::{ConfigEditorState, UiState};
@@ -122,17 +122,33 @@ pub fn show(ui: &mut Ui, state: &mut UiState) {
                     ui.end_row();
 
                     ui.label("Transport:");
-                    let mut is_quic =
-                        matches!(editor.config.server.transport, TransportMode::Quic);
+                    egui::ComboBox::from_id_salt("server_transport")
+                        .selected_text(format!("{:?}", editor.config.server.transport))
+                        .show_ui(ui, |ui| {
+                            for (mode, label) in [
+                                (TransportMode::Tcp, "Tcp"),
+                                (TransportMode::Quic, "Quic"),
+                                (TransportMode::Auto, "Auto (TCP and QUIC)"),
+                                (TransportMode::Rdma, "Rdma (TCP and RDMA)"),
+                                (TransportMode::WebSocket, "WebSocket"),
+                            ] {
+                                if ui
+                                    .selectable_value(&mut editor.config.server.transport, mode, label)
+                                    .changed()
+                                {
+                                    editor.dirty = true;
+                                }
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("WebSocket Port (0 = off):");
+                    let mut ws_port = editor.config.server.websocket_port.unwrap_or(0) as i32;
                     if ui
-                        .checkbox(&mut is_quic, "Use QUIC (instead of TCP)")
+                        .add(egui::DragValue::new(&mut ws_port).range(0..=65535))
                         .changed()
                     {
-                        editor.config.server.transport = if is_quic {
-                            TransportMode::Quic
-                        } else {
-                            TransportMode::Tcp
-                        };
+                        editor.config.server.websocket_port = (ws_port != 0).then_some(ws_port as u16);
                         editor.dirty = true;
                     }
                     ui.end_row();
@@ -206,42 +222,113 @@ pub fn show(ui: &mut Ui, state: &mut UiState) {
                     }
                     ui.end_row();
 
+                    ui.label("Local Passthrough:");
+                    if ui
+                        .checkbox(
+                            &mut editor.config.client.local_passthrough,
+                            "",
+                        )
+                        .changed()
+                    {
+                        editor.dirty = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Failover:");
+                    if ui
+                        .checkbox(
+                            &mut editor.config.client.failover,
+                            "",
+                        )
+                        .changed()
+                    {
+                        editor.dirty = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Health Check (s):");
+                    if ui
+                        .add(egui::DragValue::new(&mut editor.config.client.health_check_secs).range(1..=60))
+                        .changed()
+                    {
+                        editor.dirty = true;
+                    }
+                    ui.end_row();
+
+                    ui.label("Placement:");
+                    egui::ComboBox::from_id_salt("placement_policy")
+                        .selected_text(format!("{:?}", editor.config.client.placement.policy))
+                        .show_ui(ui, |ui| {
+                            for policy in [
+                                PlacementPolicy::None,
+                                PlacementPolicy::LeastVramUsed,
+                                PlacementPolicy::RoundRobin,
+                                PlacementPolicy::Weighted,
+                            ] {
+                                if ui
+                                    .selectable_value(
+                                        &mut editor.config.client.placement.policy,
+                                        policy,
+                                        format!("{:?}", policy),
+                                    )
+                                    .changed()
+                                {
+                                    editor.dirty = true;
+                                }
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Same Model Only:");
+                    if ui
+                        .checkbox(
+                            &mut editor.config.client.placement.match_model,
+                            "",
+                        )
+                        .changed()
+                    {
+                        editor.dirty = true;
+                    }
+                    ui.end_row();
+
                     ui.label("GPU Ordering:");
                     egui::ComboBox::from_id_salt("gpu_ordering")
                         .selected_text(format!("{:?}", editor.config.client.gpu_ordering))
                         .show_ui(ui, |ui| {
-                            if ui
-                                .selectable_value(
-                                    &mut editor.config.client.gpu_ordering,
-                                    GpuOrdering::LocalFirst,
-                                    "LocalFirst",
-                                )
-                                .changed()
-                            {
-                                editor.dirty = true;
-                            }
-                            if ui
-                                .selectable_value(
-                                    &mut editor.config.client.gpu_ordering,
-                                    GpuOrdering::RemoteFirst,
-                                    "RemoteFirst",
-                                )
-                                .changed()
-                            {
-                                editor.dirty = true;
-                            }
-                            if ui
-                                .selectable_value(
-                                    &mut editor.config.client.gpu_ordering,
-                                    GpuOrdering::ByCapability,
-                                    "ByCapability",
-                                )
-                                .changed()
-                            {
-                                editor.dirty = true;
+                            for ordering in [
+                                GpuOrdering::LocalFirst,
+                                GpuOrdering::RemoteFirst,
+                                GpuOrdering::ServerOrder,
+                                GpuOrdering::ByVram,
+                                GpuOrdering::ByCapability,
+                                GpuOrdering::ByUuid,
+                            ] {
+                                let label = format!("{:?}", ordering);
+                                if ui
+                                    .selectable_value(
+                                        &mut editor.config.client.gpu_ordering,
+                                        ordering,
+                                        label,
+                                    )
+                                    .changed()
+                                {
+                                    editor.dirty = true;
+                                }
                             }
                         });
                     ui.end_row();
+
+                    ui.label("Stable GPU Indices:");
+                    if ui
+                        .checkbox(
+                            &mut editor.config.client.stable_gpu_indices,
+                            "",
+                        )
+                        .changed()
+                    {
+                        editor.dirty = true;
+                    }
+                    ui.end_row();
                 });
 
             ui.add_space(8.0);
@@ -276,7 +363,8 @@ pub fn show(ui: &mut Ui, state: &mut UiState) {
                         address: editor.new_server_address.clone(),
                         token: editor.new_server_token.clone(),
                         ca_cert: None,
-                        transport: TransportMode::default(),
+                        transport: TransportMode::for_address(&editor.new_server_address),
+                        rdma: Default::default(),
                     });
                     editor.new_server_address.clear();
                     editor.new_server_token.clear();
@@ -284,6 +372,37 @@ pub fn show(ui: &mut Ui, state: &mut UiState) {
                 }
             });
 
+            // GPUs numbered first with ByUuid ordering
+            if editor.config.client.gpu_ordering == GpuOrdering::ByUuid {
+                ui.add_space(8.0);
+                ui.label(RichText::new("GPU UUIDs (numbered first, in order):").strong());
+                ui.add_space(4.0);
+
+                let mut to_remove = None;
+                for (idx, uuid) in editor.config.client.gpu_uuids.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("  {}: {}", idx, uuid));
+                        if ui.small_button("Remove").clicked() {
+                            to_remove = Some(idx);
+                        }
+                    });
+                }
+                if let Some(idx) = to_remove {
+                    editor.config.client.gpu_uuids.remove(idx);
+                    editor.dirty = true;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("UUID:");
+                    ui.text_edit_singleline(&mut editor.new_gpu_uuid);
+                    if ui.button("Add GPU").clicked() && !editor.new_gpu_uuid.trim().is_empty() {
+                        editor.config.client.gpu_uuids.push(editor.new_gpu_uuid.trim().to_string());
+                        editor.new_gpu_uuid.clear();
+                        editor.dirty = true;
+                    }
+                });
+            }
+
             ui.add_space(12.0);
 
             // --- Security Config ---
@@ -327,6 +446,12 @@ pub fn show(ui: &mut Ui, state: &mut UiState) {
                         name: editor.new_token_name.clone(),
                         allowed_gpus: None,
                         max_memory: None,
+                        allow_cuda: true,
+                        allow_vulkan: true,
+                        allow_ptx: true,
+                        max_sessions: None,
+                        retry_alloc_after_trim: false,
+                        labels: Default::default(),
                     });
                     editor.new_token_name.clear();
                     editor.new_token_value.clear();
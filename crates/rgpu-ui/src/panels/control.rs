@@ -158,20 +158,19 @@ fn show_server_config_form(ui: &mut Ui, state: &mut UiState, disabled: bool) {
                 ui.end_row();
 
                 ui.label("Transport:");
-                let mut is_quic = matches!(
-                    state.local_server_config.transport,
-                    TransportMode::Quic
-                );
-                if ui
-                    .checkbox(&mut is_quic, "Use QUIC (instead of TCP)")
-                    .changed()
-                {
-                    state.local_server_config.transport = if is_quic {
-                        TransportMode::Quic
-                    } else {
-                        TransportMode::Tcp
-                    };
-                }
+                egui::ComboBox::from_id_salt("local_server_transport")
+                    .selected_text(format!("{:?}", state.local_server_config.transport))
+                    .show_ui(ui, |ui| {
+                        for (mode, label) in [
+                            (TransportMode::Tcp, "Tcp"),
+                            (TransportMode::Quic, "Quic"),
+                            (TransportMode::Auto, "Auto (TCP and QUIC)"),
+                            (TransportMode::Rdma, "Rdma (TCP and RDMA)"),
+                            (TransportMode::WebSocket, "WebSocket"),
+                        ] {
+                            ui.selectable_value(&mut state.local_server_config.transport, mode, label);
+                        }
+                    });
                 ui.end_row();
 
                 ui.label("TLS Certificate:");
@@ -233,6 +232,12 @@ fn show_server_config_form(ui: &mut Ui, state: &mut UiState, disabled: bool) {
                     name: state.local_server_config.new_token_name.clone(),
                     allowed_gpus: None,
                     max_memory: None,
+                    allow_cuda: true,
+                    allow_vulkan: true,
+                    allow_ptx: true,
+                    max_sessions: None,
+                    retry_alloc_after_trim: false,
+                    labels: Default::default(),
                 });
                 state.local_server_config.new_token_name.clear();
                 state.local_server_config.new_token_value.clear();
@@ -1,5 +1,7 @@
 use egui::{Color32, RichText, Ui, Vec2};
 
+use rgpu_protocol::messages::{SessionSummary, PLACEMENT_LABEL};
+
 use crate::state::{LocalServerStatus, UiState};
 use crate::widgets::metric_chart;
 
@@ -81,6 +83,7 @@ pub fn show(ui: &mut Ui, state: &UiState) {
                     );
                 });
 
+                sessions_table(ui, "sessions_local_server", &latest.sessions);
                 ui.add_space(8.0);
 
                 // Charts - 2x2 grid
@@ -198,6 +201,8 @@ pub fn show(ui: &mut Ui, state: &UiState) {
                             Color32::from_rgb(180, 180, 180),
                         );
                     });
+
+                    sessions_table(ui, &format!("sessions_{}", server.address), &latest.sessions);
                 }
 
                 ui.add_space(8.0);
@@ -259,6 +264,100 @@ pub fn show(ui: &mut Ui, state: &UiState) {
         });
 }
 
+/// Connected sessions with their names and labels, and the contexts client
+/// daemons placed on the server's GPUs.
+fn sessions_table(ui: &mut Ui, id: &str, sessions: &[SessionSummary]) {
+    if sessions.is_empty() {
+        return;
+    }
+    ui.add_space(4.0);
+    egui::CollapsingHeader::new(format!("Sessions ({})", sessions.len()))
+        .id_salt(id)
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid::new(id)
+                .num_columns(8)
+                .spacing([16.0, 4.0])
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("ID");
+                    ui.strong("Name");
+                    ui.strong("Labels");
+                    ui.strong("Placed Contexts");
+                    ui.strong("Requests");
+                    ui.strong("Connected");
+                    ui.strong("VRAM");
+                    ui.strong("Compression");
+                    ui.end_row();
+
+                    for session in sessions {
+                        let labels = session
+                            .labels
+                            .iter()
+                            .filter(|(k, _)| k != PLACEMENT_LABEL)
+                            .map(|(k, v)| format!("{}={}", k, v))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let placed = session
+                            .labels
+                            .iter()
+                            .find(|(k, _)| k == PLACEMENT_LABEL)
+                            .map(|(_, v)| v.as_str())
+                            .unwrap_or("-");
+                        ui.label(session.session_id.to_string());
+                        ui.label(RichText::new(&session.name).strong());
+                        ui.label(RichText::new(labels).color(Color32::GRAY));
+                        ui.label(placed);
+                        ui.label(session.requests.to_string());
+                        ui.label(format_uptime(session.connected_secs));
+                        let vram_total: u64 = session.vram.iter().map(|d| d.total()).sum();
+                        let per_device = session
+                            .vram
+                            .iter()
+                            .map(|d| {
+                                format!(
+                                    "GPU {}: {} MB CUDA, {} MB Vulkan",
+                                    d.device_index,
+                                    d.cuda_bytes / (1024 * 1024),
+                                    d.vulkan_bytes / (1024 * 1024)
+                                )
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        let vram = if session.vram_quota > 0 {
+                            format!(
+                                "{} / {} MB",
+                                vram_total / (1024 * 1024),
+                                session.vram_quota / (1024 * 1024)
+                            )
+                        } else {
+                            format!("{} MB", vram_total / (1024 * 1024))
+                        };
+                        let vram = if session.vram_refused > 0 {
+                            RichText::new(format!("{vram} ({} refused)", session.vram_refused))
+                                .color(Color32::from_rgb(230, 150, 50))
+                        } else {
+                            RichText::new(vram)
+                        };
+                        ui.label(vram).on_hover_text(per_device);
+                        let compression = &session.compression;
+                        let ratio = if compression.disabled {
+                            RichText::new(format!("off ({:.2}x)", compression.ratio()))
+                                .color(Color32::GRAY)
+                        } else {
+                            RichText::new(format!("{:.2}x", compression.ratio()))
+                        };
+                        ui.label(ratio).on_hover_text(format!(
+                            "{} bytes saved, {} ms CPU",
+                            compression.bytes_saved(),
+                            compression.cpu_micros / 1000
+                        ));
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
 fn summary_card(ui: &mut Ui, label: &str, value: &str, color: Color32) {
     egui::Frame::group(ui.style())
         .inner_margin(egui::Margin::same(6))
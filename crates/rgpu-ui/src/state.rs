@@ -21,6 +21,8 @@ pub struct MetricsSnapshot {
     pub cuda_commands: u64,
     pub vulkan_commands: u64,
     pub uptime_secs: u64,
+    /// Connected sessions with their names and labels
+    pub sessions: Vec<rgpu_protocol::messages::SessionSummary>,
 }
 
 /// Derived per-second rates computed between successive snapshots.
@@ -175,6 +177,8 @@ pub struct ConfigEditorState {
     /// New server endpoint fields being edited
     pub new_server_address: String,
     pub new_server_token: String,
+    /// New GPU UUID being edited, for `ByUuid` ordering
+    pub new_gpu_uuid: String,
     /// New token fields being edited
     pub new_token_value: String,
     pub new_token_name: String,
@@ -187,6 +191,7 @@ impl ConfigEditorState {
             dirty: false,
             new_server_address: String::new(),
             new_server_token: String::new(),
+            new_gpu_uuid: String::new(),
             new_token_value: String::new(),
             new_token_name: String::new(),
         }
@@ -301,6 +306,27 @@ impl UiState {
         self.error_log.push_back(msg);
     }
 
+    /// Log where client daemons with `failover` now create contexts meant
+    /// for server `index`, which went down: the next connected server whose
+    /// GPUs match its own.
+    pub fn note_failover(&mut self, index: usize) {
+        let Some(down) = self.servers.get(index) else {
+            return;
+        };
+        if down.gpus.is_empty() {
+            return;
+        }
+        let count = self.servers.len();
+        let standby = (1..count).map(|k| &self.servers[(index + k) % count]).find(|s| {
+            s.connection_state.is_connected()
+                && down.gpus.iter().all(|gpu| s.gpus.iter().any(|g| g.is_equivalent(gpu)))
+        });
+        if let Some(standby) = standby {
+            let msg = format!("{} is down; clients with failover move new contexts to {}", down.address, standby.address);
+            self.push_error(msg);
+        }
+    }
+
     pub fn total_gpus(&self) -> usize {
         let remote: usize = self.servers.iter().map(|s| s.gpus.len()).sum();
         remote + self.embedded_server_gpus.len()
@@ -62,12 +62,27 @@ pub fn gpu_card(ui: &mut Ui, gpu: &GpuInfo, server_address: &str) {
                     ui.label(format!("{}", gpu.queue_family_count));
                     ui.end_row();
 
+                    ui.label("Sharing:");
+                    let sharing = RichText::new(gpu.scheduling.describe());
+                    if gpu.scheduling.leased_to().is_some() {
+                        ui.label(sharing.color(Color32::from_rgb(255, 180, 100)));
+                    } else {
+                        ui.label(sharing);
+                    }
+                    ui.end_row();
+
                     ui.label("Server:");
                     ui.label(format!(
                         "{} (ID: {})",
                         server_address, gpu.server_id
                     ));
                     ui.end_row();
+
+                    if let Some(uuid) = gpu.uuid_string() {
+                        ui.label("UUID:");
+                        ui.label(RichText::new(uuid).monospace());
+                        ui.end_row();
+                    }
                 });
         });
 }
@@ -34,7 +34,7 @@ pub fn metric_line_chart(
     painter.rect_filled(rect, 4.0, Color32::from_gray(30));
 
     // Collect values
-    let values: Vec<f64> = history.iter().map(|s| value_fn(s)).collect();
+    let values: Vec<f64> = history.iter().map(value_fn).collect();
     let max_val = values
         .iter()
         .cloned()
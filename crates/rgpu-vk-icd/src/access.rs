@@ -0,0 +1,313 @@
+//! Which memory a queue submission uses.
+//!
+//! Host-coherent mapped memory has no flush for the ICD to hook, so what
+//! the application wrote is sent at the submissions that may read it, and
+//! what the GPU wrote is copied back once a wait shows it finished (see
+//! `memory`). Doing that for every mapping at every submit would cost time
+//! in proportion to all mapped memory, so the memory bound to each buffer
+//! and image is noted here, along with what views, framebuffers and
+//! descriptor sets refer to, and a command buffer's recorded commands give
+//! the memory it may read and write.
+//!
+//! Everything is in server handles, since those are what recorded commands
+//! hold.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::vulkan_commands::RecordedCommand;
+
+/// The memory objects commands may use.
+#[derive(Debug, Default, Clone)]
+pub struct Access {
+    /// Memory the commands may read or write
+    pub used: HashSet<NetworkHandle>,
+    /// Memory the commands may write
+    pub written: HashSet<NetworkHandle>,
+    /// The commands may use and write any memory: they execute a secondary
+    /// command buffer whose commands aren't known
+    pub everything: bool,
+}
+
+impl Access {
+    pub fn uses(&self, memory: &NetworkHandle) -> bool {
+        self.everything || self.used.contains(memory)
+    }
+
+    pub fn writes(&self, memory: &NetworkHandle) -> bool {
+        self.everything || self.written.contains(memory)
+    }
+
+    pub fn merge(&mut self, other: &Access) {
+        self.used.extend(other.used.iter().copied());
+        self.written.extend(other.written.iter().copied());
+        self.everything |= other.everything;
+    }
+}
+
+/// (binding, array element) → the resource or view written there, and
+/// whether shaders may write it.
+type Descriptors = HashMap<(u32, u32), (NetworkHandle, bool)>;
+
+/// What the ICD knows about the resources commands refer to.
+#[derive(Default)]
+pub struct Resources {
+    /// Buffer or image → the memory bound to it
+    memory: HashMap<NetworkHandle, NetworkHandle>,
+    /// Buffer view or image view → its buffer or image
+    views: HashMap<NetworkHandle, NetworkHandle>,
+    /// Framebuffer → its attachments
+    framebuffers: HashMap<NetworkHandle, Vec<NetworkHandle>>,
+    /// Descriptor set → its descriptors
+    descriptor_sets: HashMap<NetworkHandle, Descriptors>,
+    /// Command buffer → what its commands, as last recorded, use
+    command_buffers: HashMap<NetworkHandle, Access>,
+}
+
+impl Resources {
+    pub fn bind_memory(&mut self, resource: NetworkHandle, memory: NetworkHandle) {
+        self.memory.insert(resource, memory);
+    }
+
+    pub fn add_view(&mut self, view: NetworkHandle, resource: NetworkHandle) {
+        self.views.insert(view, resource);
+    }
+
+    pub fn add_framebuffer(&mut self, framebuffer: NetworkHandle, attachments: Vec<NetworkHandle>) {
+        self.framebuffers.insert(framebuffer, attachments);
+    }
+
+    /// Note the descriptors written from `first_element` of `binding` on,
+    /// each a buffer, buffer view or image view.
+    pub fn write_descriptors(
+        &mut self,
+        set: NetworkHandle,
+        binding: u32,
+        first_element: u32,
+        resources: impl IntoIterator<Item = NetworkHandle>,
+        writable: bool,
+    ) {
+        let entries = self.descriptor_sets.entry(set).or_default();
+        for (element, resource) in (first_element..).zip(resources) {
+            entries.insert((binding, element), (resource, writable));
+        }
+    }
+
+    /// Forget a destroyed object of any kind.
+    pub fn forget(&mut self, handle: &NetworkHandle) {
+        self.memory.remove(handle);
+        self.views.remove(handle);
+        self.framebuffers.remove(handle);
+        self.descriptor_sets.remove(handle);
+        self.command_buffers.remove(handle);
+    }
+
+    /// Note what a command buffer's newly recorded commands use.
+    pub fn record(&mut self, command_buffer: NetworkHandle, commands: &[RecordedCommand]) {
+        let access = self.of_commands(commands);
+        self.command_buffers.insert(command_buffer, access);
+    }
+
+    /// What a submitted command buffer uses, as last recorded.
+    pub fn command_buffer(&self, command_buffer: &NetworkHandle) -> Access {
+        self.command_buffers.get(command_buffer).cloned().unwrap_or_default()
+    }
+
+    /// The memory a buffer, image or view of either is bound to.
+    fn memory_of(&self, handle: &NetworkHandle) -> Option<NetworkHandle> {
+        let resource = self.views.get(handle).unwrap_or(handle);
+        self.memory.get(resource).copied()
+    }
+
+    fn note(&self, access: &mut Access, handle: &NetworkHandle, written: bool) {
+        if let Some(memory) = self.memory_of(handle) {
+            access.used.insert(memory);
+            if written {
+                access.written.insert(memory);
+            }
+        }
+    }
+
+    /// The memory `commands` may read and write. Descriptor sets count
+    /// from when they are bound, whether or not a pipeline uses them.
+    pub fn of_commands(&self, commands: &[RecordedCommand]) -> Access {
+        let mut access = Access::default();
+        for command in commands {
+            match command {
+                RecordedCommand::BindDescriptorSets { descriptor_sets, .. } => {
+                    for set in descriptor_sets {
+                        for (resource, writable) in self.descriptor_sets.get(set).into_iter().flat_map(|e| e.values()) {
+                            self.note(&mut access, resource, *writable);
+                        }
+                    }
+                }
+                RecordedCommand::CopyBuffer { src, dst, .. } => {
+                    self.note(&mut access, src, false);
+                    self.note(&mut access, dst, true);
+                }
+                RecordedCommand::FillBuffer { buffer, .. } | RecordedCommand::UpdateBuffer { buffer, .. } => {
+                    self.note(&mut access, buffer, true);
+                }
+                RecordedCommand::BeginRenderPass { framebuffer, .. } => {
+                    for view in self.framebuffers.get(framebuffer).into_iter().flatten() {
+                        self.note(&mut access, view, true);
+                    }
+                }
+                RecordedCommand::BindVertexBuffers { buffers, .. } => {
+                    for buffer in buffers {
+                        self.note(&mut access, buffer, false);
+                    }
+                }
+                RecordedCommand::BindIndexBuffer { buffer, .. }
+                | RecordedCommand::DrawIndirect { buffer, .. }
+                | RecordedCommand::DrawIndexedIndirect { buffer, .. }
+                | RecordedCommand::DispatchIndirect { buffer, .. } => {
+                    self.note(&mut access, buffer, false);
+                }
+                RecordedCommand::DrawIndirectCount { buffer, count_buffer, .. }
+                | RecordedCommand::DrawIndexedIndirectCount { buffer, count_buffer, .. } => {
+                    self.note(&mut access, buffer, false);
+                    self.note(&mut access, count_buffer, false);
+                }
+                RecordedCommand::CopyBufferToImage { src_buffer, dst_image, .. } => {
+                    self.note(&mut access, src_buffer, false);
+                    self.note(&mut access, dst_image, true);
+                }
+                RecordedCommand::CopyImageToBuffer { src_image, dst_buffer, .. } => {
+                    self.note(&mut access, src_image, false);
+                    self.note(&mut access, dst_buffer, true);
+                }
+                RecordedCommand::CopyQueryPoolResults { dst_buffer, .. } => {
+                    self.note(&mut access, dst_buffer, true);
+                }
+                RecordedCommand::CopyImage { src_image, dst_image, .. }
+                | RecordedCommand::BlitImage { src_image, dst_image, .. }
+                | RecordedCommand::ResolveImage { src_image, dst_image, .. } => {
+                    self.note(&mut access, src_image, false);
+                    self.note(&mut access, dst_image, true);
+                }
+                RecordedCommand::ExecuteCommands { command_buffers } => {
+                    for command_buffer in command_buffers {
+                        match self.command_buffers.get(command_buffer) {
+                            Some(secondary) => access.merge(secondary),
+                            None => access.everything = true,
+                        }
+                    }
+                }
+                RecordedCommand::BeginRendering { rendering_info } => {
+                    let attachments = rendering_info
+                        .color_attachments
+                        .iter()
+                        .chain(&rendering_info.depth_attachment)
+                        .chain(&rendering_info.stencil_attachment);
+                    for attachment in attachments {
+                        for view in attachment.image_view.iter().chain(&attachment.resolve_image_view) {
+                            self.note(&mut access, view, true);
+                        }
+                    }
+                }
+                RecordedCommand::BindPipeline { .. }
+                | RecordedCommand::Dispatch { .. }
+                | RecordedCommand::PipelineBarrier { .. }
+                | RecordedCommand::EndRenderPass
+                | RecordedCommand::Draw { .. }
+                | RecordedCommand::DrawIndexed { .. }
+                | RecordedCommand::SetViewport { .. }
+                | RecordedCommand::SetScissor { .. }
+                | RecordedCommand::ResetQueryPool { .. }
+                | RecordedCommand::BeginQuery { .. }
+                | RecordedCommand::EndQuery { .. }
+                | RecordedCommand::WriteTimestamp { .. }
+                | RecordedCommand::PushConstants { .. }
+                | RecordedCommand::SetEvent { .. }
+                | RecordedCommand::ResetEvent { .. }
+                | RecordedCommand::WaitEvents { .. }
+                | RecordedCommand::PipelineBarrier2 { .. }
+                | RecordedCommand::SetEvent2 { .. }
+                | RecordedCommand::ResetEvent2 { .. }
+                | RecordedCommand::WaitEvents2 { .. }
+                | RecordedCommand::WriteTimestamp2 { .. }
+                | RecordedCommand::EndRendering => {}
+            }
+        }
+        access
+    }
+}
+
+static RESOURCES: OnceLock<Mutex<Resources>> = OnceLock::new();
+
+/// Run `f` on what is known of the resources of every device of the
+/// process.
+pub fn resources<R>(f: impl FnOnce(&mut Resources) -> R) -> R {
+    let mut resources = RESOURCES.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    f(&mut resources)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rgpu_protocol::handle::ResourceType;
+
+    fn handle(resource_id: u64, resource_type: ResourceType) -> NetworkHandle {
+        NetworkHandle { server_id: 0, session_id: 1, resource_id, resource_type }
+    }
+
+    #[test]
+    fn test_copy_reads_source_and_writes_destination() {
+        let (src, dst) = (handle(1, ResourceType::VkBuffer), handle(2, ResourceType::VkBuffer));
+        let (src_memory, dst_memory) = (handle(3, ResourceType::VkDeviceMemory), handle(4, ResourceType::VkDeviceMemory));
+        let mut resources = Resources::default();
+        resources.bind_memory(src, src_memory);
+        resources.bind_memory(dst, dst_memory);
+
+        let access = resources.of_commands(&[RecordedCommand::CopyBuffer { src, dst, regions: Vec::new() }]);
+        assert!(access.uses(&src_memory) && !access.writes(&src_memory));
+        assert!(access.writes(&dst_memory));
+        assert!(!access.uses(&handle(5, ResourceType::VkDeviceMemory)));
+    }
+
+    #[test]
+    fn test_storage_descriptors_are_written() {
+        let (set, uniform, storage, view) = (
+            handle(1, ResourceType::VkDescriptorSet),
+            handle(2, ResourceType::VkBuffer),
+            handle(3, ResourceType::VkBuffer),
+            handle(4, ResourceType::VkBufferView),
+        );
+        let memory: Vec<_> = (5..7).map(|id| handle(id, ResourceType::VkDeviceMemory)).collect();
+        let mut resources = Resources::default();
+        resources.bind_memory(uniform, memory[0]);
+        resources.bind_memory(storage, memory[1]);
+        resources.add_view(view, storage);
+        resources.write_descriptors(set, 0, 0, [uniform], false);
+        resources.write_descriptors(set, 1, 0, [view], true);
+
+        let bind = RecordedCommand::BindDescriptorSets {
+            pipeline_bind_point: 1,
+            layout: handle(7, ResourceType::VkPipelineLayout),
+            first_set: 0,
+            descriptor_sets: vec![set],
+            dynamic_offsets: Vec::new(),
+        };
+        let access = resources.of_commands(&[bind]);
+        assert!(access.uses(&memory[0]) && !access.writes(&memory[0]));
+        assert!(access.writes(&memory[1]));
+    }
+
+    #[test]
+    fn test_secondary_command_buffers() {
+        let (buffer, memory) = (handle(1, ResourceType::VkBuffer), handle(2, ResourceType::VkDeviceMemory));
+        let (secondary, unknown) = (handle(3, ResourceType::VkCommandBuffer), handle(4, ResourceType::VkCommandBuffer));
+        let mut resources = Resources::default();
+        resources.bind_memory(buffer, memory);
+        resources.record(secondary, &[RecordedCommand::FillBuffer { buffer, offset: 0, size: 4, data: 0 }]);
+
+        let access = resources.of_commands(&[RecordedCommand::ExecuteCommands { command_buffers: vec![secondary] }]);
+        assert!(access.writes(&memory) && !access.everything);
+
+        // Commands that aren't known may use anything
+        let access = resources.of_commands(&[RecordedCommand::ExecuteCommands { command_buffers: vec![unknown] }]);
+        assert!(access.everything);
+    }
+}
@@ -13,15 +13,22 @@ use crate::send_vulkan_command;
 
 use rgpu_protocol::vulkan_commands::{
     RecordedCommand, SerializedBufferCopy, SerializedBufferImageCopy, SerializedBufferMemoryBarrier,
-    SerializedClearValue, SerializedImageMemoryBarrier, SerializedImageSubresourceLayers,
-    SerializedImageSubresourceRange, SerializedMemoryBarrier, SerializedRect2D,
-    SerializedViewport, VulkanCommand, VulkanResponse,
+    SerializedBufferMemoryBarrier2, SerializedClearValue, SerializedDependencyInfo, SerializedImageBlit,
+    SerializedImageCopy, SerializedImageMemoryBarrier, SerializedImageMemoryBarrier2,
+    SerializedImageSubresourceLayers, SerializedImageSubresourceRange, SerializedMemoryBarrier,
+    SerializedMemoryBarrier2, SerializedRect2D, SerializedRenderingAttachmentInfo,
+    SerializedRenderingInfo, SerializedSecondaryBeginInfo, SerializedViewport,
+    VulkanCommand, VulkanResponse,
 };
 
 /// Per-command-buffer recording state.
 struct CommandBufferState {
     recording: bool,
     commands: Vec<RecordedCommand>,
+    /// Begin info of a secondary command buffer, which is sent to the server
+    /// when it ends rather than at submit time; `None` for a primary one
+    secondary: Option<SerializedSecondaryBeginInfo>,
+    level: vk::CommandBufferLevel,
 }
 
 /// Map from local command buffer ID to its recording state.
@@ -188,6 +195,8 @@ pub unsafe extern "C" fn vkAllocateCommandBuffers(
                         CommandBufferState {
                             recording: false,
                             commands: Vec::new(),
+                            secondary: None,
+                            level: ai.level,
                         },
                     );
                 }
@@ -238,6 +247,7 @@ pub unsafe extern "C" fn vkFreeCommandBuffers(
         }
 
         if let Some(h) = handle_store::remove_cmd_buffer(local_id) {
+            crate::access::resources(|r| r.forget(&h));
             net_handles.push(h);
         }
         DispatchableHandle::destroy(cb_disp);
@@ -257,7 +267,7 @@ pub unsafe extern "C" fn vkFreeCommandBuffers(
 #[no_mangle]
 pub unsafe extern "C" fn vkBeginCommandBuffer(
     command_buffer: vk::CommandBuffer,
-    _p_begin_info: *const vk::CommandBufferBeginInfo<'_>,
+    p_begin_info: *const vk::CommandBufferBeginInfo<'_>,
 ) -> vk::Result {
     let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
     let local_id = DispatchableHandle::get_id(cb_disp);
@@ -266,6 +276,28 @@ pub unsafe extern "C" fn vkBeginCommandBuffer(
         if let Some(state) = states.get_mut(&local_id) {
             state.recording = true;
             state.commands.clear();
+            if state.level == vk::CommandBufferLevel::SECONDARY && !p_begin_info.is_null() {
+                let bi = &*p_begin_info;
+                let mut secondary = SerializedSecondaryBeginInfo {
+                    usage_flags: bi.flags.as_raw(),
+                    render_pass: None,
+                    subpass: 0,
+                    framebuffer: None,
+                    occlusion_query_enable: false,
+                    query_flags: 0,
+                    pipeline_statistics: 0,
+                };
+                if !bi.p_inheritance_info.is_null() {
+                    let ii = &*bi.p_inheritance_info;
+                    secondary.render_pass = handle_store::get_render_pass(ii.render_pass.as_raw());
+                    secondary.subpass = ii.subpass;
+                    secondary.framebuffer = handle_store::get_framebuffer(ii.framebuffer.as_raw());
+                    secondary.occlusion_query_enable = ii.occlusion_query_enable != 0;
+                    secondary.query_flags = ii.query_flags.as_raw();
+                    secondary.pipeline_statistics = ii.pipeline_statistics.as_raw();
+                }
+                state.secondary = Some(secondary);
+            }
         }
     }
 
@@ -279,12 +311,36 @@ pub unsafe extern "C" fn vkEndCommandBuffer(
     let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
     let local_id = DispatchableHandle::get_id(cb_disp);
 
+    // A secondary command buffer is recorded on the server now, so that it
+    // is ready when a primary that executes it is submitted.
+    let mut secondary = None;
     if let Ok(mut states) = cmd_buf_states().lock() {
         if let Some(state) = states.get_mut(&local_id) {
             state.recording = false;
+            if let Some(begin) = state.secondary.take() {
+                secondary = Some((begin, std::mem::take(&mut state.commands)));
+            }
         }
     }
 
+    if let Some((begin, commands)) = secondary {
+        let cb_handle = match handle_store::get_cmd_buffer(local_id) {
+            Some(h) => h,
+            None => return vk::Result::ERROR_UNKNOWN,
+        };
+        crate::access::resources(|r| r.record(cb_handle, &commands));
+        let cmd = VulkanCommand::SubmitRecordedCommands {
+            command_buffer: cb_handle,
+            secondary: Some(begin),
+            commands,
+        };
+        return match send_vulkan_command(cmd) {
+            Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+            Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+            _ => vk::Result::ERROR_UNKNOWN,
+        };
+    }
+
     vk::Result::SUCCESS
 }
 
@@ -300,6 +356,7 @@ pub unsafe extern "C" fn vkResetCommandBuffer(
         if let Some(state) = states.get_mut(&local_id) {
             state.recording = false;
             state.commands.clear();
+            state.secondary = None;
         }
     }
 
@@ -418,6 +475,43 @@ pub unsafe extern "C" fn vkCmdPipelineBarrier(
     let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
     let local_id = DispatchableHandle::get_id(cb_disp);
 
+    let (memory_barriers, buffer_memory_barriers, image_memory_barriers) = read_barriers(
+        memory_barrier_count,
+        p_memory_barriers,
+        buffer_memory_barrier_count,
+        p_buffer_memory_barriers,
+        image_memory_barrier_count,
+        p_image_memory_barriers,
+    );
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::PipelineBarrier {
+                src_stage_mask: src_stage_mask.as_raw(),
+                dst_stage_mask: dst_stage_mask.as_raw(),
+                dependency_flags: dependency_flags.as_raw(),
+                memory_barriers,
+                buffer_memory_barriers,
+                image_memory_barriers,
+            });
+        }
+    }
+}
+
+/// The barriers of vkCmdPipelineBarrier and vkCmdWaitEvents; barriers on
+/// buffers or images the ICD doesn't know are dropped.
+unsafe fn read_barriers(
+    memory_barrier_count: u32,
+    p_memory_barriers: *const vk::MemoryBarrier<'_>,
+    buffer_memory_barrier_count: u32,
+    p_buffer_memory_barriers: *const vk::BufferMemoryBarrier<'_>,
+    image_memory_barrier_count: u32,
+    p_image_memory_barriers: *const vk::ImageMemoryBarrier<'_>,
+) -> (
+    Vec<SerializedMemoryBarrier>,
+    Vec<SerializedBufferMemoryBarrier>,
+    Vec<SerializedImageMemoryBarrier>,
+) {
     let mut memory_barriers = Vec::new();
     if !p_memory_barriers.is_null() {
         for i in 0..memory_barrier_count as usize {
@@ -429,7 +523,7 @@ pub unsafe extern "C" fn vkCmdPipelineBarrier(
         }
     }
 
-    let mut buffer_memory_barriers_vec = Vec::new();
+    let mut buffer_memory_barriers = Vec::new();
     if !p_buffer_memory_barriers.is_null() {
         for i in 0..buffer_memory_barrier_count as usize {
             let bmb = &*p_buffer_memory_barriers.add(i);
@@ -437,7 +531,7 @@ pub unsafe extern "C" fn vkCmdPipelineBarrier(
                 Some(h) => h,
                 None => continue,
             };
-            buffer_memory_barriers_vec.push(SerializedBufferMemoryBarrier {
+            buffer_memory_barriers.push(SerializedBufferMemoryBarrier {
                 src_access_mask: bmb.src_access_mask.as_raw(),
                 dst_access_mask: bmb.dst_access_mask.as_raw(),
                 src_queue_family_index: bmb.src_queue_family_index,
@@ -449,7 +543,7 @@ pub unsafe extern "C" fn vkCmdPipelineBarrier(
         }
     }
 
-    let mut image_memory_barriers_vec = Vec::new();
+    let mut image_memory_barriers = Vec::new();
     if !p_image_memory_barriers.is_null() {
         for i in 0..image_memory_barrier_count as usize {
             let imb = &*p_image_memory_barriers.add(i);
@@ -457,7 +551,7 @@ pub unsafe extern "C" fn vkCmdPipelineBarrier(
                 Some(h) => h,
                 None => continue,
             };
-            image_memory_barriers_vec.push(SerializedImageMemoryBarrier {
+            image_memory_barriers.push(SerializedImageMemoryBarrier {
                 src_access_mask: imb.src_access_mask.as_raw(),
                 dst_access_mask: imb.dst_access_mask.as_raw(),
                 old_layout: imb.old_layout.as_raw(),
@@ -476,18 +570,7 @@ pub unsafe extern "C" fn vkCmdPipelineBarrier(
         }
     }
 
-    if let Ok(mut states) = cmd_buf_states().lock() {
-        if let Some(state) = states.get_mut(&local_id) {
-            state.commands.push(RecordedCommand::PipelineBarrier {
-                src_stage_mask: src_stage_mask.as_raw(),
-                dst_stage_mask: dst_stage_mask.as_raw(),
-                dependency_flags: dependency_flags.as_raw(),
-                memory_barriers,
-                buffer_memory_barriers: buffer_memory_barriers_vec,
-                image_memory_barriers: image_memory_barriers_vec,
-            });
-        }
-    }
+    (memory_barriers, buffer_memory_barriers, image_memory_barriers)
 }
 
 #[no_mangle]
@@ -960,3 +1043,925 @@ pub unsafe extern "C" fn vkCmdCopyImageToBuffer(
         }
     }
 }
+
+// ── Query recording functions ───────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdResetQueryPool(
+    command_buffer: vk::CommandBuffer,
+    query_pool: vk::QueryPool,
+    first_query: u32,
+    query_count: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let pool_handle = match handle_store::get_query_pool(query_pool.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::ResetQueryPool {
+                query_pool: pool_handle,
+                first_query,
+                query_count,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdBeginQuery(
+    command_buffer: vk::CommandBuffer,
+    query_pool: vk::QueryPool,
+    query: u32,
+    flags: vk::QueryControlFlags,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let pool_handle = match handle_store::get_query_pool(query_pool.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::BeginQuery {
+                query_pool: pool_handle,
+                query,
+                flags: flags.as_raw(),
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdEndQuery(
+    command_buffer: vk::CommandBuffer,
+    query_pool: vk::QueryPool,
+    query: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let pool_handle = match handle_store::get_query_pool(query_pool.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::EndQuery {
+                query_pool: pool_handle,
+                query,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdWriteTimestamp(
+    command_buffer: vk::CommandBuffer,
+    pipeline_stage: vk::PipelineStageFlags,
+    query_pool: vk::QueryPool,
+    query: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let pool_handle = match handle_store::get_query_pool(query_pool.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::WriteTimestamp {
+                pipeline_stage: pipeline_stage.as_raw(),
+                query_pool: pool_handle,
+                query,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdCopyQueryPoolResults(
+    command_buffer: vk::CommandBuffer,
+    query_pool: vk::QueryPool,
+    first_query: u32,
+    query_count: u32,
+    dst_buffer: vk::Buffer,
+    dst_offset: vk::DeviceSize,
+    stride: vk::DeviceSize,
+    flags: vk::QueryResultFlags,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let pool_handle = match handle_store::get_query_pool(query_pool.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+    let buf_handle = match handle_store::get_buffer(dst_buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::CopyQueryPoolResults {
+                query_pool: pool_handle,
+                first_query,
+                query_count,
+                dst_buffer: buf_handle,
+                dst_offset,
+                stride,
+                flags: flags.as_raw(),
+            });
+        }
+    }
+}
+
+// ── Secondary command buffers ───────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdExecuteCommands(
+    command_buffer: vk::CommandBuffer,
+    command_buffer_count: u32,
+    p_command_buffers: *const vk::CommandBuffer,
+) {
+    if p_command_buffers.is_null() || command_buffer_count == 0 {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let mut command_buffers = Vec::new();
+    for i in 0..command_buffer_count as usize {
+        let secondary = *p_command_buffers.add(i);
+        let secondary_disp = secondary.as_raw() as *const DispatchableHandle;
+        match handle_store::get_cmd_buffer(DispatchableHandle::get_id(secondary_disp)) {
+            Some(h) => command_buffers.push(h),
+            None => return,
+        }
+    }
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::ExecuteCommands { command_buffers });
+        }
+    }
+}
+
+// ── Indirect draws and dispatches ───────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdDrawIndirect(
+    command_buffer: vk::CommandBuffer,
+    buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+    draw_count: u32,
+    stride: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let buf_handle = match handle_store::get_buffer(buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::DrawIndirect {
+                buffer: buf_handle,
+                offset,
+                draw_count,
+                stride,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdDrawIndexedIndirect(
+    command_buffer: vk::CommandBuffer,
+    buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+    draw_count: u32,
+    stride: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let buf_handle = match handle_store::get_buffer(buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::DrawIndexedIndirect {
+                buffer: buf_handle,
+                offset,
+                draw_count,
+                stride,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdDispatchIndirect(
+    command_buffer: vk::CommandBuffer,
+    buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let buf_handle = match handle_store::get_buffer(buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::DispatchIndirect {
+                buffer: buf_handle,
+                offset,
+            });
+        }
+    }
+}
+
+/// Also `vkCmdDrawIndirectCountKHR`.
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdDrawIndirectCount(
+    command_buffer: vk::CommandBuffer,
+    buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+    count_buffer: vk::Buffer,
+    count_buffer_offset: vk::DeviceSize,
+    max_draw_count: u32,
+    stride: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let buf_handle = match handle_store::get_buffer(buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+    let count_handle = match handle_store::get_buffer(count_buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::DrawIndirectCount {
+                buffer: buf_handle,
+                offset,
+                count_buffer: count_handle,
+                count_buffer_offset,
+                max_draw_count,
+                stride,
+            });
+        }
+    }
+}
+
+/// Also `vkCmdDrawIndexedIndirectCountKHR`.
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdDrawIndexedIndirectCount(
+    command_buffer: vk::CommandBuffer,
+    buffer: vk::Buffer,
+    offset: vk::DeviceSize,
+    count_buffer: vk::Buffer,
+    count_buffer_offset: vk::DeviceSize,
+    max_draw_count: u32,
+    stride: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let buf_handle = match handle_store::get_buffer(buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+    let count_handle = match handle_store::get_buffer(count_buffer.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::DrawIndexedIndirectCount {
+                buffer: buf_handle,
+                offset,
+                count_buffer: count_handle,
+                count_buffer_offset,
+                max_draw_count,
+                stride,
+            });
+        }
+    }
+}
+
+// ── Image to image copies ───────────────────────────────────
+
+fn serialize_subresource_layers(s: &vk::ImageSubresourceLayers) -> SerializedImageSubresourceLayers {
+    SerializedImageSubresourceLayers {
+        aspect_mask: s.aspect_mask.as_raw(),
+        mip_level: s.mip_level,
+        base_array_layer: s.base_array_layer,
+        layer_count: s.layer_count,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdCopyImage(
+    command_buffer: vk::CommandBuffer,
+    src_image: vk::Image,
+    src_image_layout: vk::ImageLayout,
+    dst_image: vk::Image,
+    dst_image_layout: vk::ImageLayout,
+    region_count: u32,
+    p_regions: *const vk::ImageCopy,
+) {
+    if p_regions.is_null() || region_count == 0 {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let src_handle = match handle_store::get_image(src_image.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+    let dst_handle = match handle_store::get_image(dst_image.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    let regions: Vec<SerializedImageCopy> = std::slice::from_raw_parts(p_regions, region_count as usize)
+        .iter()
+        .map(|r| SerializedImageCopy {
+            src_subresource: serialize_subresource_layers(&r.src_subresource),
+            src_offset: [r.src_offset.x, r.src_offset.y, r.src_offset.z],
+            dst_subresource: serialize_subresource_layers(&r.dst_subresource),
+            dst_offset: [r.dst_offset.x, r.dst_offset.y, r.dst_offset.z],
+            extent: [r.extent.width, r.extent.height, r.extent.depth],
+        })
+        .collect();
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::CopyImage {
+                src_image: src_handle,
+                src_image_layout: src_image_layout.as_raw(),
+                dst_image: dst_handle,
+                dst_image_layout: dst_image_layout.as_raw(),
+                regions,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdBlitImage(
+    command_buffer: vk::CommandBuffer,
+    src_image: vk::Image,
+    src_image_layout: vk::ImageLayout,
+    dst_image: vk::Image,
+    dst_image_layout: vk::ImageLayout,
+    region_count: u32,
+    p_regions: *const vk::ImageBlit,
+    filter: vk::Filter,
+) {
+    if p_regions.is_null() || region_count == 0 {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let src_handle = match handle_store::get_image(src_image.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+    let dst_handle = match handle_store::get_image(dst_image.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    let offsets = |o: &[vk::Offset3D; 2]| [[o[0].x, o[0].y, o[0].z], [o[1].x, o[1].y, o[1].z]];
+    let regions: Vec<SerializedImageBlit> = std::slice::from_raw_parts(p_regions, region_count as usize)
+        .iter()
+        .map(|r| SerializedImageBlit {
+            src_subresource: serialize_subresource_layers(&r.src_subresource),
+            src_offsets: offsets(&r.src_offsets),
+            dst_subresource: serialize_subresource_layers(&r.dst_subresource),
+            dst_offsets: offsets(&r.dst_offsets),
+        })
+        .collect();
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::BlitImage {
+                src_image: src_handle,
+                src_image_layout: src_image_layout.as_raw(),
+                dst_image: dst_handle,
+                dst_image_layout: dst_image_layout.as_raw(),
+                regions,
+                filter: filter.as_raw(),
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdResolveImage(
+    command_buffer: vk::CommandBuffer,
+    src_image: vk::Image,
+    src_image_layout: vk::ImageLayout,
+    dst_image: vk::Image,
+    dst_image_layout: vk::ImageLayout,
+    region_count: u32,
+    p_regions: *const vk::ImageResolve,
+) {
+    if p_regions.is_null() || region_count == 0 {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let src_handle = match handle_store::get_image(src_image.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+    let dst_handle = match handle_store::get_image(dst_image.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    let regions: Vec<SerializedImageCopy> = std::slice::from_raw_parts(p_regions, region_count as usize)
+        .iter()
+        .map(|r| SerializedImageCopy {
+            src_subresource: serialize_subresource_layers(&r.src_subresource),
+            src_offset: [r.src_offset.x, r.src_offset.y, r.src_offset.z],
+            dst_subresource: serialize_subresource_layers(&r.dst_subresource),
+            dst_offset: [r.dst_offset.x, r.dst_offset.y, r.dst_offset.z],
+            extent: [r.extent.width, r.extent.height, r.extent.depth],
+        })
+        .collect();
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::ResolveImage {
+                src_image: src_handle,
+                src_image_layout: src_image_layout.as_raw(),
+                dst_image: dst_handle,
+                dst_image_layout: dst_image_layout.as_raw(),
+                regions,
+            });
+        }
+    }
+}
+
+// ── Push constants ──────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdPushConstants(
+    command_buffer: vk::CommandBuffer,
+    layout: vk::PipelineLayout,
+    stage_flags: vk::ShaderStageFlags,
+    offset: u32,
+    size: u32,
+    p_values: *const std::ffi::c_void,
+) {
+    if p_values.is_null() || size == 0 {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let layout_handle = match handle_store::get_pipeline_layout(layout.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    let data = std::slice::from_raw_parts(p_values as *const u8, size as usize).to_vec();
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::PushConstants {
+                layout: layout_handle,
+                stage_flags: stage_flags.as_raw(),
+                offset,
+                data,
+            });
+        }
+    }
+}
+
+// ── Events ──────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdSetEvent(
+    command_buffer: vk::CommandBuffer,
+    event: vk::Event,
+    stage_mask: vk::PipelineStageFlags,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let event_handle = match handle_store::get_event(event.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::SetEvent {
+                event: event_handle,
+                stage_mask: stage_mask.as_raw(),
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdResetEvent(
+    command_buffer: vk::CommandBuffer,
+    event: vk::Event,
+    stage_mask: vk::PipelineStageFlags,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let event_handle = match handle_store::get_event(event.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::ResetEvent {
+                event: event_handle,
+                stage_mask: stage_mask.as_raw(),
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdWaitEvents(
+    command_buffer: vk::CommandBuffer,
+    event_count: u32,
+    p_events: *const vk::Event,
+    src_stage_mask: vk::PipelineStageFlags,
+    dst_stage_mask: vk::PipelineStageFlags,
+    memory_barrier_count: u32,
+    p_memory_barriers: *const vk::MemoryBarrier<'_>,
+    buffer_memory_barrier_count: u32,
+    p_buffer_memory_barriers: *const vk::BufferMemoryBarrier<'_>,
+    image_memory_barrier_count: u32,
+    p_image_memory_barriers: *const vk::ImageMemoryBarrier<'_>,
+) {
+    if p_events.is_null() || event_count == 0 {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let events: Vec<_> = std::slice::from_raw_parts(p_events, event_count as usize)
+        .iter()
+        .filter_map(|e| handle_store::get_event(e.as_raw()))
+        .collect();
+
+    let (memory_barriers, buffer_memory_barriers, image_memory_barriers) = read_barriers(
+        memory_barrier_count,
+        p_memory_barriers,
+        buffer_memory_barrier_count,
+        p_buffer_memory_barriers,
+        image_memory_barrier_count,
+        p_image_memory_barriers,
+    );
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::WaitEvents {
+                events,
+                src_stage_mask: src_stage_mask.as_raw(),
+                dst_stage_mask: dst_stage_mask.as_raw(),
+                memory_barriers,
+                buffer_memory_barriers,
+                image_memory_barriers,
+            });
+        }
+    }
+}
+
+// ── Synchronization2 ────────────────────────────────────────
+
+/// A VkDependencyInfo; barriers on buffers or images the ICD doesn't know
+/// are dropped.
+unsafe fn read_dependency_info(info: &vk::DependencyInfo<'_>) -> SerializedDependencyInfo {
+    let mut memory_barriers = Vec::new();
+    if !info.p_memory_barriers.is_null() {
+        for i in 0..info.memory_barrier_count as usize {
+            let mb = &*info.p_memory_barriers.add(i);
+            memory_barriers.push(SerializedMemoryBarrier2 {
+                src_stage_mask: mb.src_stage_mask.as_raw(),
+                src_access_mask: mb.src_access_mask.as_raw(),
+                dst_stage_mask: mb.dst_stage_mask.as_raw(),
+                dst_access_mask: mb.dst_access_mask.as_raw(),
+            });
+        }
+    }
+
+    let mut buffer_memory_barriers = Vec::new();
+    if !info.p_buffer_memory_barriers.is_null() {
+        for i in 0..info.buffer_memory_barrier_count as usize {
+            let bmb = &*info.p_buffer_memory_barriers.add(i);
+            let buf_handle = match handle_store::get_buffer(bmb.buffer.as_raw()) {
+                Some(h) => h,
+                None => continue,
+            };
+            buffer_memory_barriers.push(SerializedBufferMemoryBarrier2 {
+                src_stage_mask: bmb.src_stage_mask.as_raw(),
+                src_access_mask: bmb.src_access_mask.as_raw(),
+                dst_stage_mask: bmb.dst_stage_mask.as_raw(),
+                dst_access_mask: bmb.dst_access_mask.as_raw(),
+                src_queue_family_index: bmb.src_queue_family_index,
+                dst_queue_family_index: bmb.dst_queue_family_index,
+                buffer: buf_handle,
+                offset: bmb.offset,
+                size: bmb.size,
+            });
+        }
+    }
+
+    let mut image_memory_barriers = Vec::new();
+    if !info.p_image_memory_barriers.is_null() {
+        for i in 0..info.image_memory_barrier_count as usize {
+            let imb = &*info.p_image_memory_barriers.add(i);
+            let img_handle = match handle_store::get_image(imb.image.as_raw()) {
+                Some(h) => h,
+                None => continue,
+            };
+            image_memory_barriers.push(SerializedImageMemoryBarrier2 {
+                src_stage_mask: imb.src_stage_mask.as_raw(),
+                src_access_mask: imb.src_access_mask.as_raw(),
+                dst_stage_mask: imb.dst_stage_mask.as_raw(),
+                dst_access_mask: imb.dst_access_mask.as_raw(),
+                old_layout: imb.old_layout.as_raw(),
+                new_layout: imb.new_layout.as_raw(),
+                src_queue_family_index: imb.src_queue_family_index,
+                dst_queue_family_index: imb.dst_queue_family_index,
+                image: img_handle,
+                subresource_range: SerializedImageSubresourceRange {
+                    aspect_mask: imb.subresource_range.aspect_mask.as_raw(),
+                    base_mip_level: imb.subresource_range.base_mip_level,
+                    level_count: imb.subresource_range.level_count,
+                    base_array_layer: imb.subresource_range.base_array_layer,
+                    layer_count: imb.subresource_range.layer_count,
+                },
+            });
+        }
+    }
+
+    SerializedDependencyInfo {
+        dependency_flags: info.dependency_flags.as_raw(),
+        memory_barriers,
+        buffer_memory_barriers,
+        image_memory_barriers,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdPipelineBarrier2(
+    command_buffer: vk::CommandBuffer,
+    p_dependency_info: *const vk::DependencyInfo<'_>,
+) {
+    if p_dependency_info.is_null() {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let dependency_info = read_dependency_info(&*p_dependency_info);
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::PipelineBarrier2 { dependency_info });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdSetEvent2(
+    command_buffer: vk::CommandBuffer,
+    event: vk::Event,
+    p_dependency_info: *const vk::DependencyInfo<'_>,
+) {
+    if p_dependency_info.is_null() {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let event_handle = match handle_store::get_event(event.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+    let dependency_info = read_dependency_info(&*p_dependency_info);
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::SetEvent2 {
+                event: event_handle,
+                dependency_info,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdResetEvent2(
+    command_buffer: vk::CommandBuffer,
+    event: vk::Event,
+    stage_mask: vk::PipelineStageFlags2,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let event_handle = match handle_store::get_event(event.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::ResetEvent2 {
+                event: event_handle,
+                stage_mask: stage_mask.as_raw(),
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdWaitEvents2(
+    command_buffer: vk::CommandBuffer,
+    event_count: u32,
+    p_events: *const vk::Event,
+    p_dependency_infos: *const vk::DependencyInfo<'_>,
+) {
+    if p_events.is_null() || p_dependency_infos.is_null() || event_count == 0 {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    // Events and dependency infos pair up, so both are dropped for unknown
+    // events
+    let mut events = Vec::new();
+    let mut dependency_infos = Vec::new();
+    for i in 0..event_count as usize {
+        if let Some(h) = handle_store::get_event((*p_events.add(i)).as_raw()) {
+            events.push(h);
+            dependency_infos.push(read_dependency_info(&*p_dependency_infos.add(i)));
+        }
+    }
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::WaitEvents2 {
+                events,
+                dependency_infos,
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdWriteTimestamp2(
+    command_buffer: vk::CommandBuffer,
+    stage: vk::PipelineStageFlags2,
+    query_pool: vk::QueryPool,
+    query: u32,
+) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let pool_handle = match handle_store::get_query_pool(query_pool.as_raw()) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::WriteTimestamp2 {
+                stage: stage.as_raw(),
+                query_pool: pool_handle,
+                query,
+            });
+        }
+    }
+}
+
+// ── Dynamic rendering ───────────────────────────────────────
+
+unsafe fn read_rendering_attachment(a: &vk::RenderingAttachmentInfo<'_>) -> SerializedRenderingAttachmentInfo {
+    let view_of = |v: vk::ImageView| {
+        if v == vk::ImageView::null() {
+            None
+        } else {
+            handle_store::get_image_view(v.as_raw())
+        }
+    };
+    SerializedRenderingAttachmentInfo {
+        image_view: view_of(a.image_view),
+        image_layout: a.image_layout.as_raw(),
+        resolve_mode: a.resolve_mode.as_raw(),
+        resolve_image_view: view_of(a.resolve_image_view),
+        resolve_image_layout: a.resolve_image_layout.as_raw(),
+        load_op: a.load_op.as_raw(),
+        store_op: a.store_op.as_raw(),
+        clear_value: SerializedClearValue {
+            data: std::mem::transmute::<vk::ClearValue, [u8; 16]>(a.clear_value),
+        },
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdBeginRendering(
+    command_buffer: vk::CommandBuffer,
+    p_rendering_info: *const vk::RenderingInfo<'_>,
+) {
+    if p_rendering_info.is_null() {
+        return;
+    }
+
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    let ri = &*p_rendering_info;
+
+    let color_attachments = if !ri.p_color_attachments.is_null() && ri.color_attachment_count > 0 {
+        std::slice::from_raw_parts(ri.p_color_attachments, ri.color_attachment_count as usize)
+            .iter()
+            .map(|a| read_rendering_attachment(a))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    let depth_attachment = ri.p_depth_attachment.as_ref().map(|a| read_rendering_attachment(a));
+    let stencil_attachment = ri.p_stencil_attachment.as_ref().map(|a| read_rendering_attachment(a));
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::BeginRendering {
+                rendering_info: SerializedRenderingInfo {
+                    flags: ri.flags.as_raw(),
+                    render_area: SerializedRect2D {
+                        offset: [ri.render_area.offset.x, ri.render_area.offset.y],
+                        extent: [ri.render_area.extent.width, ri.render_area.extent.height],
+                    },
+                    layer_count: ri.layer_count,
+                    view_mask: ri.view_mask,
+                    color_attachments,
+                    depth_attachment,
+                    stencil_attachment,
+                },
+            });
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCmdEndRendering(command_buffer: vk::CommandBuffer) {
+    let cb_disp = command_buffer.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(cb_disp);
+
+    if let Ok(mut states) = cmd_buf_states().lock() {
+        if let Some(state) = states.get_mut(&local_id) {
+            state.commands.push(RecordedCommand::EndRendering);
+        }
+    }
+}
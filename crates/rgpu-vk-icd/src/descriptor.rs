@@ -8,8 +8,8 @@ use crate::handle_store;
 use crate::send_vulkan_command;
 
 use rgpu_protocol::vulkan_commands::{
-    SerializedDescriptorBufferInfo, SerializedDescriptorPoolSize, SerializedWriteDescriptorSet,
-    VulkanCommand, VulkanResponse,
+    SerializedDescriptorBufferInfo, SerializedDescriptorImageInfo, SerializedDescriptorPoolSize,
+    SerializedWriteDescriptorSet, VulkanCommand, VulkanResponse,
 };
 
 // ── Descriptor Pool ─────────────────────────────────────────
@@ -178,6 +178,7 @@ pub unsafe extern "C" fn vkFreeDescriptorSets(
     for i in 0..descriptor_set_count as usize {
         let ds = *p_descriptor_sets.add(i);
         if let Some(h) = handle_store::remove_desc_set(ds.as_raw()) {
+            crate::access::resources(|r| r.forget(&h));
             net_handles.push(h);
         }
     }
@@ -227,9 +228,43 @@ pub unsafe extern "C" fn vkUpdateDescriptorSets(
             None => continue,
         };
 
+        // Which info array is valid depends on the descriptor type
+        let uses_image_info = matches!(
+            w.descriptor_type,
+            vk::DescriptorType::SAMPLER
+                | vk::DescriptorType::COMBINED_IMAGE_SAMPLER
+                | vk::DescriptorType::SAMPLED_IMAGE
+                | vk::DescriptorType::STORAGE_IMAGE
+                | vk::DescriptorType::INPUT_ATTACHMENT
+        );
+        let uses_buffer_info = matches!(
+            w.descriptor_type,
+            vk::DescriptorType::UNIFORM_BUFFER
+                | vk::DescriptorType::STORAGE_BUFFER
+                | vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC
+                | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC
+        );
+        let uses_texel_buffer_view = matches!(
+            w.descriptor_type,
+            vk::DescriptorType::UNIFORM_TEXEL_BUFFER | vk::DescriptorType::STORAGE_TEXEL_BUFFER
+        );
+
+        // Read image infos; sampler and view are optional depending on the type
+        let mut image_infos = Vec::new();
+        if uses_image_info && !w.p_image_info.is_null() {
+            for j in 0..w.descriptor_count as usize {
+                let ii = &*w.p_image_info.add(j);
+                image_infos.push(SerializedDescriptorImageInfo {
+                    sampler: handle_store::get_sampler(ii.sampler.as_raw()),
+                    image_view: handle_store::get_image_view(ii.image_view.as_raw()),
+                    image_layout: ii.image_layout.as_raw(),
+                });
+            }
+        }
+
         // Read buffer infos
         let mut buffer_infos = Vec::new();
-        if !w.p_buffer_info.is_null() {
+        if uses_buffer_info && !w.p_buffer_info.is_null() {
             for j in 0..w.descriptor_count as usize {
                 let bi = &*w.p_buffer_info.add(j);
                 let buf_handle = match handle_store::get_buffer(bi.buffer.as_raw()) {
@@ -244,12 +279,40 @@ pub unsafe extern "C" fn vkUpdateDescriptorSets(
             }
         }
 
+        // Read texel buffer views
+        let mut texel_buffer_views = Vec::new();
+        if uses_texel_buffer_view && !w.p_texel_buffer_view.is_null() {
+            for j in 0..w.descriptor_count as usize {
+                let view = *w.p_texel_buffer_view.add(j);
+                if let Some(h) = handle_store::get_buffer_view(view.as_raw()) {
+                    texel_buffer_views.push(h);
+                }
+            }
+        }
+
+        // Shaders may write storage descriptors, and the memory behind them
+        let writable = matches!(
+            w.descriptor_type,
+            vk::DescriptorType::STORAGE_BUFFER
+                | vk::DescriptorType::STORAGE_BUFFER_DYNAMIC
+                | vk::DescriptorType::STORAGE_IMAGE
+                | vk::DescriptorType::STORAGE_TEXEL_BUFFER
+        );
+        let resources = buffer_infos
+            .iter()
+            .map(|bi| bi.buffer)
+            .chain(image_infos.iter().filter_map(|ii| ii.image_view))
+            .chain(texel_buffer_views.iter().copied());
+        crate::access::resources(|r| r.write_descriptors(dst_set, w.dst_binding, w.dst_array_element, resources, writable));
+
         writes.push(SerializedWriteDescriptorSet {
             dst_set,
             dst_binding: w.dst_binding,
             dst_array_element: w.dst_array_element,
             descriptor_type: w.descriptor_type.as_raw(),
             buffer_infos,
+            image_infos,
+            texel_buffer_views,
         });
     }
 
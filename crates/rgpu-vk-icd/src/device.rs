@@ -5,6 +5,7 @@ use ash::vk::Handle;
 
 use crate::dispatch::DispatchableHandle;
 use crate::handle_store;
+use crate::memory::Finished;
 use crate::send_vulkan_command;
 
 use rgpu_protocol::vulkan_commands::{DeviceQueueCreateInfo, VulkanCommand, VulkanResponse};
@@ -54,9 +55,18 @@ pub unsafe extern "C" fn vkCreateDevice(
         ci.enabled_extension_count,
     );
 
-    // Read enabled features (raw bytes)
-    let enabled_features = if !ci.p_enabled_features.is_null() {
-        let features = &*ci.p_enabled_features;
+    // Read enabled features (raw bytes), which may also come as a
+    // VkPhysicalDeviceFeatures2 in the chain
+    let mut p_enabled_features = ci.p_enabled_features;
+    let mut p_next = ci.p_next as *const vk::BaseInStructure<'_>;
+    while !p_next.is_null() {
+        if (*p_next).s_type == vk::StructureType::PHYSICAL_DEVICE_FEATURES_2 {
+            p_enabled_features = &(*(p_next as *const vk::PhysicalDeviceFeatures2<'_>)).features;
+        }
+        p_next = (*p_next).p_next;
+    }
+    let enabled_features = if !p_enabled_features.is_null() {
+        let features = &*p_enabled_features;
         let bytes = std::slice::from_raw_parts(
             features as *const vk::PhysicalDeviceFeatures as *const u8,
             std::mem::size_of::<vk::PhysicalDeviceFeatures>(),
@@ -71,11 +81,13 @@ pub unsafe extern "C" fn vkCreateDevice(
         queue_create_infos,
         enabled_extensions,
         enabled_features,
+        p_next: crate::pnext::read_p_next(ci.p_next),
     };
 
     match send_vulkan_command(cmd) {
         Ok(VulkanResponse::DeviceCreated { handle }) => {
             let dev_local_id = handle_store::store_device(handle);
+            crate::memory::note_memory_types(dev_local_id, pd_handle);
             let dev_disp = DispatchableHandle::new(dev_local_id);
             *p_device = std::mem::transmute(dev_disp);
             vk::Result::SUCCESS
@@ -99,6 +111,8 @@ pub unsafe extern "C" fn vkDestroyDevice(
     if let Some(handle) = handle_store::remove_device(local_id) {
         let _ = send_vulkan_command(VulkanCommand::DestroyDevice { device: handle });
     }
+    crate::memory::forget_memory_types(local_id);
+    crate::dispatch::destroy_queues(local_id);
     DispatchableHandle::destroy(disp);
 }
 
@@ -121,15 +135,18 @@ pub unsafe extern "C" fn vkGetDeviceQueue(
         None => return,
     };
 
-    let cmd = VulkanCommand::GetDeviceQueue {
-        device: dev_handle,
-        queue_family_index,
-        queue_index,
-    };
-
-    if let Ok(VulkanResponse::QueueRetrieved { handle }) = send_vulkan_command(cmd) {
-        let q_local_id = handle_store::store_queue(handle);
-        let q_disp = DispatchableHandle::new(q_local_id);
+    let q_disp = crate::dispatch::queue(local_id, queue_family_index, queue_index, || {
+        let cmd = VulkanCommand::GetDeviceQueue {
+            device: dev_handle,
+            queue_family_index,
+            queue_index,
+        };
+        match send_vulkan_command(cmd) {
+            Ok(VulkanResponse::QueueRetrieved { handle }) => Some(handle_store::store_queue(handle)),
+            _ => None,
+        }
+    });
+    if let Some(q_disp) = q_disp {
         *p_queue = std::mem::transmute(q_disp);
     }
 }
@@ -149,7 +166,7 @@ pub unsafe extern "C" fn vkDeviceWaitIdle(device: vk::Device) -> vk::Result {
     };
 
     match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+        Ok(VulkanResponse::Success) => crate::memory::after_wait(vk::Result::SUCCESS, Finished::Device(dev_handle)),
         Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
         _ => vk::Result::ERROR_DEVICE_LOST,
     }
@@ -0,0 +1,189 @@
+//! Dirty tracking for mapped memory.
+//!
+//! The application writes mapped memory in a shadow buffer, and
+//! `vkFlushMappedMemoryRanges` and `vkUnmapMemory` send it to the server.
+//! Streaming buffers are flushed whole every frame with only a little of
+//! them changed, so each region keeps a hash of every block as the server
+//! last saw it, and only the blocks whose hash changed are sent. Hashing
+//! rather than write-protecting pages needs no fault handler in the
+//! application's process and works the same on every platform.
+//!
+//! A hash only says what the server had when it last saw the block. Once
+//! the GPU may have written the memory, the application writing the same
+//! bytes again (resetting a counter to 0, say) would look unchanged, so
+//! the caller either forgets the hashes of memory a submission may write
+//! or refreshes the mirror from the server once the submission finished.
+//!
+//! Blocks are aligned to `BLOCK_SIZE` within the memory object, so the
+//! sub-ranges sent stay multiples of `nonCoherentAtomSize` where the
+//! application's ranges were.
+
+use std::ops::Range;
+
+use rgpu_protocol::readback::block_hash;
+
+/// Bytes covered by one hash: a page.
+pub const BLOCK_SIZE: usize = 4096;
+
+/// Block hashes of a mapped region.
+pub struct DirtyTracker {
+    /// Offset of the mapping in its memory object
+    offset: u64,
+    /// `None` for blocks the server's copy of isn't known
+    hashes: Vec<Option<u64>>,
+}
+
+impl DirtyTracker {
+    /// Track `data`, mapped at `offset` of its memory object and as the
+    /// server has it.
+    pub fn new(offset: u64, data: &[u8]) -> Self {
+        let mut tracker = Self { offset, hashes: Vec::new() };
+        tracker.hashes = tracker.blocks(data.len(), 0, data.len()).map(|block| Some(block_hash(&data[block]))).collect();
+        tracker
+    }
+
+    /// The blocks of a mapping `len` bytes long that overlap `start..end`,
+    /// as ranges of the mapping, first block first.
+    fn blocks(&self, len: usize, start: usize, end: usize) -> impl Iterator<Item = Range<usize>> {
+        let skew = (self.offset % BLOCK_SIZE as u64) as usize;
+        let first = (start + skew) / BLOCK_SIZE;
+        let last = (end.min(len) + skew).div_ceil(BLOCK_SIZE);
+        (first..last).map(move |index| {
+            (index * BLOCK_SIZE).saturating_sub(skew)..((index + 1) * BLOCK_SIZE - skew).min(len)
+        })
+    }
+
+    fn index(&self, block: &Range<usize>) -> usize {
+        (block.start + (self.offset % BLOCK_SIZE as u64) as usize) / BLOCK_SIZE
+    }
+
+    /// The parts of `start..end` of `data` in blocks that changed since the
+    /// server last saw them, adjacent ones merged, which the caller is
+    /// about to send. Blocks wholly inside the range count as seen from
+    /// now on.
+    pub fn take_dirty(&mut self, data: &[u8], start: usize, end: usize) -> Vec<Range<usize>> {
+        let end = end.min(data.len());
+        let mut dirty: Vec<Range<usize>> = Vec::new();
+        for block in self.blocks(data.len(), start, end).collect::<Vec<_>>() {
+            let index = self.index(&block);
+            let hash = block_hash(&data[block.clone()]);
+            if self.hashes[index] == Some(hash) {
+                continue;
+            }
+            if block.start >= start && block.end <= end {
+                self.hashes[index] = Some(hash);
+            }
+            let part = block.start.max(start)..block.end.min(end);
+            match dirty.last_mut() {
+                Some(run) if run.end == part.start => run.end = part.end,
+                _ => dirty.push(part),
+            }
+        }
+        dirty
+    }
+
+    /// Note that the server's copy of `start..end` now matches `data`.
+    pub fn sync(&mut self, data: &[u8], start: usize, end: usize) {
+        let end = end.min(data.len());
+        for block in self.blocks(data.len(), start, end).collect::<Vec<_>>() {
+            if block.start >= start && block.end <= end {
+                let index = self.index(&block);
+                self.hashes[index] = Some(block_hash(&data[block]));
+            }
+        }
+    }
+
+    /// Forget what the server has: the GPU may have changed it, so every
+    /// block counts as changed until it is sent or synced again.
+    pub fn forget(&mut self) {
+        self.hashes.fill(None);
+    }
+
+    /// Copy the server's copy of the mapping, which the GPU may have
+    /// written, into `data`: only the blocks the application hasn't
+    /// changed since the server last saw them, so that none of its writes
+    /// are lost. Those blocks count as seen.
+    pub fn refresh(&mut self, data: &mut [u8], server: &[u8]) {
+        let len = data.len().min(server.len());
+        for block in self.blocks(len, 0, len).collect::<Vec<_>>() {
+            let index = self.index(&block);
+            if self.hashes[index] == Some(block_hash(&data[block.clone()])) {
+                data[block.clone()].copy_from_slice(&server[block.clone()]);
+                self.hashes[index] = Some(block_hash(&server[block]));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_flush_sends_every_changed_block() {
+        let mut data = vec![0u8; 3 * BLOCK_SIZE];
+        let mut tracker = DirtyTracker::new(0, &data);
+        data.fill(1);
+        assert_eq!(tracker.take_dirty(&data, 0, data.len()), vec![0..3 * BLOCK_SIZE]);
+        assert!(tracker.take_dirty(&data, 0, data.len()).is_empty());
+    }
+
+    #[test]
+    fn test_unchanged_blocks_are_skipped() {
+        let mut data = vec![0u8; 4 * BLOCK_SIZE];
+        let mut tracker = DirtyTracker::new(0, &data);
+        data[BLOCK_SIZE + 1] = 1;
+        data[3 * BLOCK_SIZE] = 1;
+        assert_eq!(
+            tracker.take_dirty(&data, 0, data.len()),
+            vec![BLOCK_SIZE..2 * BLOCK_SIZE, 3 * BLOCK_SIZE..4 * BLOCK_SIZE]
+        );
+    }
+
+    #[test]
+    fn test_partial_last_block() {
+        let len = 2 * BLOCK_SIZE + 100;
+        let mut data = vec![0u8; len];
+        let mut tracker = DirtyTracker::new(0, &data);
+        data[len - 1] = 1;
+        assert_eq!(tracker.take_dirty(&data, 0, len), vec![2 * BLOCK_SIZE..len]);
+        assert!(tracker.take_dirty(&data, 0, len).is_empty());
+
+        // A mapping at an unaligned offset has partial blocks at both ends
+        let mut tracker = DirtyTracker::new(BLOCK_SIZE as u64 - 10, &data);
+        data[0] = 2;
+        data[len - 1] = 2;
+        assert_eq!(tracker.take_dirty(&data, 0, len), vec![0..10, len - 90..len]);
+    }
+
+    #[test]
+    fn test_rewrite_after_gpu_write_is_sent() {
+        let mut data = vec![0u8; BLOCK_SIZE];
+        let mut tracker = DirtyTracker::new(0, &data);
+
+        // The GPU changes the server's copy; the application then resets
+        // it to what the server last saw from it.
+        tracker.forget();
+        data.fill(0);
+        assert_eq!(tracker.take_dirty(&data, 0, data.len()), vec![0..BLOCK_SIZE]);
+        assert!(tracker.take_dirty(&data, 0, data.len()).is_empty());
+    }
+
+    #[test]
+    fn test_refresh_keeps_application_writes() {
+        let mut data = vec![0u8; 2 * BLOCK_SIZE + 100];
+        let mut tracker = DirtyTracker::new(0, &data);
+
+        // The GPU wrote every block; the application meanwhile the first
+        data[0] = 1;
+        let server = vec![2u8; data.len()];
+        tracker.refresh(&mut data, &server);
+        assert_eq!(data[0], 1);
+        assert!(data[1..BLOCK_SIZE].iter().all(|&b| b == 0));
+        assert!(data[BLOCK_SIZE..].iter().all(|&b| b == 2));
+
+        // Refreshed blocks match the server; the application's block is
+        // still to be sent
+        assert_eq!(tracker.take_dirty(&data, 0, data.len()), vec![0..BLOCK_SIZE]);
+    }
+}
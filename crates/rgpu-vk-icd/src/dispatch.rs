@@ -3,6 +3,19 @@
 //! The Vulkan loader requires that dispatchable handles (VkInstance, VkDevice,
 //! VkQueue, VkCommandBuffer) have their first `sizeof(void*)` bytes point to
 //! a dispatch table. The loader writes this after the ICD returns the handle.
+//! Handles the loader has already seen are handed out again rather than
+//! allocated anew, and device-level functions are resolved here for
+//! vkGetDeviceProcAddr.
+
+use std::sync::OnceLock;
+
+use dashmap::DashMap;
+
+use crate::handle_store;
+use crate::{
+    command, descriptor, device, graphics_pipeline, image, memory, pipeline, query, renderpass,
+    swapchain, sync,
+};
 
 /// The ICD loader magic value. The loader expects this in new dispatchable handles.
 pub const ICD_LOADER_MAGIC: usize = 0x01CDC0DE;
@@ -42,3 +55,780 @@ impl DispatchableHandle {
         drop(Box::from_raw(ptr));
     }
 }
+
+// ── Handles the loader has seen ─────────────────────────────
+
+// The loader keeps its dispatch table pointer in a dispatchable handle, and
+// matches physical devices by handle between enumerations, so a physical
+// device or queue asked for again must be the same object. Pointers are
+// kept as usize to be Send.
+
+/// Physical devices of each instance, by instance local id, in the order
+/// they were enumerated.
+static PHYSICAL_DEVICES: OnceLock<DashMap<u64, Vec<usize>>> = OnceLock::new();
+/// Queues by device local id, family and index.
+static QUEUES: OnceLock<DashMap<(u64, u32, u32), usize>> = OnceLock::new();
+
+fn physical_device_map() -> &'static DashMap<u64, Vec<usize>> {
+    PHYSICAL_DEVICES.get_or_init(DashMap::new)
+}
+
+fn queue_map() -> &'static DashMap<(u64, u32, u32), usize> {
+    QUEUES.get_or_init(DashMap::new)
+}
+
+/// The physical devices of an instance, enumerated by `enumerate` the first
+/// time and the same objects after that.
+pub fn physical_devices<E>(
+    instance_id: u64,
+    enumerate: impl FnOnce() -> Result<Vec<u64>, E>,
+) -> Result<Vec<*mut DispatchableHandle>, E> {
+    let ptrs = match physical_device_map().entry(instance_id) {
+        dashmap::Entry::Occupied(e) => e.get().clone(),
+        dashmap::Entry::Vacant(e) => {
+            let ptrs: Vec<usize> = enumerate()?
+                .into_iter()
+                .map(|local_id| DispatchableHandle::new(local_id) as usize)
+                .collect();
+            e.insert(ptrs).clone()
+        }
+    };
+    Ok(ptrs.into_iter().map(|p| p as *mut DispatchableHandle).collect())
+}
+
+/// Free the physical devices of a destroyed instance.
+///
+/// # Safety
+/// Nothing may use the instance's physical devices any more.
+pub unsafe fn destroy_physical_devices(instance_id: u64) {
+    if let Some((_, ptrs)) = physical_device_map().remove(&instance_id) {
+        for p in ptrs {
+            let p = p as *mut DispatchableHandle;
+            handle_store::remove_physical_device(DispatchableHandle::get_id(p));
+            DispatchableHandle::destroy(p);
+        }
+    }
+}
+
+/// A queue of a device, retrieved by `get` the first time and the same
+/// object after that. `None` if `get` fails.
+pub fn queue(
+    device_id: u64,
+    family: u32,
+    index: u32,
+    get: impl FnOnce() -> Option<u64>,
+) -> Option<*mut DispatchableHandle> {
+    let ptr = match queue_map().entry((device_id, family, index)) {
+        dashmap::Entry::Occupied(e) => *e.get(),
+        dashmap::Entry::Vacant(e) => *e.insert(DispatchableHandle::new(get()?) as usize),
+    };
+    Some(ptr as *mut DispatchableHandle)
+}
+
+/// The local ID of the device a queue belongs to.
+pub fn queue_device(queue_id: u64) -> Option<u64> {
+    let is_queue = |p: usize| unsafe { DispatchableHandle::get_id(p as *const DispatchableHandle) } == queue_id;
+    queue_map().iter().find(|e| is_queue(*e.value())).map(|e| e.key().0)
+}
+
+/// Free the queues of a destroyed device.
+///
+/// # Safety
+/// Nothing may use the device's queues any more.
+pub unsafe fn destroy_queues(device_id: u64) {
+    let keys: Vec<(u64, u32, u32)> = queue_map()
+        .iter()
+        .filter(|e| e.key().0 == device_id)
+        .map(|e| *e.key())
+        .collect();
+    for key in keys {
+        if let Some((_, p)) = queue_map().remove(&key) {
+            let p = p as *mut DispatchableHandle;
+            handle_store::remove_queue(DispatchableHandle::get_id(p));
+            DispatchableHandle::destroy(p);
+        }
+    }
+}
+
+// ── Device-level functions ──────────────────────────────────
+
+/// Device-level functions: those whose first parameter is a VkDevice,
+/// VkQueue or VkCommandBuffer. vkGetDeviceProcAddr resolves only these;
+/// vk_icdGetInstanceProcAddr falls back to them.
+pub unsafe fn device_proc_addr(name: &str) -> Option<unsafe extern "C" fn()> {
+    match name {
+        "vkGetDeviceProcAddr" => {
+            Some(std::mem::transmute(
+                crate::vkGetDeviceProcAddr as *const (),
+            ))
+        }
+
+        // ── Logical Device ──────────────────────────────────
+        "vkDestroyDevice" => {
+            Some(std::mem::transmute(
+                device::vkDestroyDevice as *const (),
+            ))
+        }
+        "vkGetDeviceQueue" => {
+            Some(std::mem::transmute(
+                device::vkGetDeviceQueue as *const (),
+            ))
+        }
+        "vkDeviceWaitIdle" => {
+            Some(std::mem::transmute(
+                device::vkDeviceWaitIdle as *const (),
+            ))
+        }
+
+        // ── Memory ──────────────────────────────────────────
+        "vkAllocateMemory" => {
+            Some(std::mem::transmute(
+                memory::vkAllocateMemory as *const (),
+            ))
+        }
+        "vkFreeMemory" => {
+            Some(std::mem::transmute(
+                memory::vkFreeMemory as *const (),
+            ))
+        }
+        "vkMapMemory" => {
+            Some(std::mem::transmute(
+                memory::vkMapMemory as *const (),
+            ))
+        }
+        "vkUnmapMemory" => {
+            Some(std::mem::transmute(
+                memory::vkUnmapMemory as *const (),
+            ))
+        }
+        "vkFlushMappedMemoryRanges" => {
+            Some(std::mem::transmute(
+                memory::vkFlushMappedMemoryRanges as *const (),
+            ))
+        }
+        "vkInvalidateMappedMemoryRanges" => {
+            Some(std::mem::transmute(
+                memory::vkInvalidateMappedMemoryRanges as *const (),
+            ))
+        }
+
+        // ── Buffer ──────────────────────────────────────────
+        "vkCreateBuffer" => {
+            Some(std::mem::transmute(
+                memory::vkCreateBuffer as *const (),
+            ))
+        }
+        "vkDestroyBuffer" => {
+            Some(std::mem::transmute(
+                memory::vkDestroyBuffer as *const (),
+            ))
+        }
+        "vkBindBufferMemory" => {
+            Some(std::mem::transmute(
+                memory::vkBindBufferMemory as *const (),
+            ))
+        }
+        "vkGetBufferMemoryRequirements" => {
+            Some(std::mem::transmute(
+                memory::vkGetBufferMemoryRequirements as *const (),
+            ))
+        }
+        "vkCreateBufferView" => {
+            Some(std::mem::transmute(
+                memory::vkCreateBufferView as *const (),
+            ))
+        }
+        "vkDestroyBufferView" => {
+            Some(std::mem::transmute(
+                memory::vkDestroyBufferView as *const (),
+            ))
+        }
+
+        // ── Shader Module ───────────────────────────────────
+        "vkCreateShaderModule" => {
+            Some(std::mem::transmute(
+                pipeline::vkCreateShaderModule as *const (),
+            ))
+        }
+        "vkDestroyShaderModule" => {
+            Some(std::mem::transmute(
+                pipeline::vkDestroyShaderModule as *const (),
+            ))
+        }
+
+        // ── Descriptor Set Layout ───────────────────────────
+        "vkCreateDescriptorSetLayout" => {
+            Some(std::mem::transmute(
+                pipeline::vkCreateDescriptorSetLayout as *const (),
+            ))
+        }
+        "vkDestroyDescriptorSetLayout" => {
+            Some(std::mem::transmute(
+                pipeline::vkDestroyDescriptorSetLayout as *const (),
+            ))
+        }
+
+        // ── Pipeline Layout ─────────────────────────────────
+        "vkCreatePipelineLayout" => {
+            Some(std::mem::transmute(
+                pipeline::vkCreatePipelineLayout as *const (),
+            ))
+        }
+        "vkDestroyPipelineLayout" => {
+            Some(std::mem::transmute(
+                pipeline::vkDestroyPipelineLayout as *const (),
+            ))
+        }
+
+        // ── Compute Pipeline ────────────────────────────────
+        "vkCreateComputePipelines" => {
+            Some(std::mem::transmute(
+                pipeline::vkCreateComputePipelines as *const (),
+            ))
+        }
+        "vkDestroyPipeline" => {
+            Some(std::mem::transmute(
+                pipeline::vkDestroyPipeline as *const (),
+            ))
+        }
+        "vkCreatePipelineCache" => {
+            Some(std::mem::transmute(
+                pipeline::vkCreatePipelineCache as *const (),
+            ))
+        }
+        "vkDestroyPipelineCache" => {
+            Some(std::mem::transmute(
+                pipeline::vkDestroyPipelineCache as *const (),
+            ))
+        }
+        "vkGetPipelineCacheData" => {
+            Some(std::mem::transmute(
+                pipeline::vkGetPipelineCacheData as *const (),
+            ))
+        }
+        "vkMergePipelineCaches" => {
+            Some(std::mem::transmute(
+                pipeline::vkMergePipelineCaches as *const (),
+            ))
+        }
+
+        // ── Image ────────────────────────────────────────────
+        "vkCreateImage" => {
+            Some(std::mem::transmute(
+                image::vkCreateImage as *const (),
+            ))
+        }
+        "vkDestroyImage" => {
+            Some(std::mem::transmute(
+                image::vkDestroyImage as *const (),
+            ))
+        }
+        "vkGetImageMemoryRequirements" => {
+            Some(std::mem::transmute(
+                image::vkGetImageMemoryRequirements as *const (),
+            ))
+        }
+        "vkBindImageMemory" => {
+            Some(std::mem::transmute(
+                image::vkBindImageMemory as *const (),
+            ))
+        }
+
+        // ── Image View ───────────────────────────────────────
+        "vkCreateImageView" => {
+            Some(std::mem::transmute(
+                image::vkCreateImageView as *const (),
+            ))
+        }
+        "vkDestroyImageView" => {
+            Some(std::mem::transmute(
+                image::vkDestroyImageView as *const (),
+            ))
+        }
+        "vkCreateSampler" => {
+            Some(std::mem::transmute(
+                image::vkCreateSampler as *const (),
+            ))
+        }
+        "vkDestroySampler" => {
+            Some(std::mem::transmute(
+                image::vkDestroySampler as *const (),
+            ))
+        }
+
+        // ── Render Pass ──────────────────────────────────────
+        "vkCreateRenderPass" => {
+            Some(std::mem::transmute(
+                renderpass::vkCreateRenderPass as *const (),
+            ))
+        }
+        "vkDestroyRenderPass" => {
+            Some(std::mem::transmute(
+                renderpass::vkDestroyRenderPass as *const (),
+            ))
+        }
+
+        // ── Framebuffer ──────────────────────────────────────
+        "vkCreateFramebuffer" => {
+            Some(std::mem::transmute(
+                renderpass::vkCreateFramebuffer as *const (),
+            ))
+        }
+        "vkDestroyFramebuffer" => {
+            Some(std::mem::transmute(
+                renderpass::vkDestroyFramebuffer as *const (),
+            ))
+        }
+
+        // ── Graphics Pipeline ────────────────────────────────
+        "vkCreateGraphicsPipelines" => {
+            Some(std::mem::transmute(
+                graphics_pipeline::vkCreateGraphicsPipelines as *const (),
+            ))
+        }
+
+        // ── Semaphore ────────────────────────────────────────
+        "vkCreateSemaphore" => {
+            Some(std::mem::transmute(
+                sync::vkCreateSemaphore as *const (),
+            ))
+        }
+        "vkDestroySemaphore" => {
+            Some(std::mem::transmute(
+                sync::vkDestroySemaphore as *const (),
+            ))
+        }
+        "vkWaitSemaphores" => {
+            Some(std::mem::transmute(
+                sync::vkWaitSemaphores as *const (),
+            ))
+        }
+        "vkWaitSemaphoresKHR" => {
+            Some(std::mem::transmute(
+                sync::vkWaitSemaphoresKHR as *const (),
+            ))
+        }
+        "vkSignalSemaphore" => {
+            Some(std::mem::transmute(
+                sync::vkSignalSemaphore as *const (),
+            ))
+        }
+        "vkSignalSemaphoreKHR" => {
+            Some(std::mem::transmute(
+                sync::vkSignalSemaphoreKHR as *const (),
+            ))
+        }
+        "vkGetSemaphoreCounterValue" => {
+            Some(std::mem::transmute(
+                sync::vkGetSemaphoreCounterValue as *const (),
+            ))
+        }
+        "vkGetSemaphoreCounterValueKHR" => {
+            Some(std::mem::transmute(
+                sync::vkGetSemaphoreCounterValueKHR as *const (),
+            ))
+        }
+
+        // ── Event ───────────────────────────────────────────
+        "vkCreateEvent" => {
+            Some(std::mem::transmute(
+                sync::vkCreateEvent as *const (),
+            ))
+        }
+        "vkDestroyEvent" => {
+            Some(std::mem::transmute(
+                sync::vkDestroyEvent as *const (),
+            ))
+        }
+        "vkGetEventStatus" => {
+            Some(std::mem::transmute(
+                sync::vkGetEventStatus as *const (),
+            ))
+        }
+        "vkSetEvent" => {
+            Some(std::mem::transmute(
+                sync::vkSetEvent as *const (),
+            ))
+        }
+        "vkResetEvent" => {
+            Some(std::mem::transmute(
+                sync::vkResetEvent as *const (),
+            ))
+        }
+
+        // ── Descriptor Pool ─────────────────────────────────
+        "vkCreateDescriptorPool" => {
+            Some(std::mem::transmute(
+                descriptor::vkCreateDescriptorPool as *const (),
+            ))
+        }
+        "vkDestroyDescriptorPool" => {
+            Some(std::mem::transmute(
+                descriptor::vkDestroyDescriptorPool as *const (),
+            ))
+        }
+
+        // ── Descriptor Set ──────────────────────────────────
+        "vkAllocateDescriptorSets" => {
+            Some(std::mem::transmute(
+                descriptor::vkAllocateDescriptorSets as *const (),
+            ))
+        }
+        "vkFreeDescriptorSets" => {
+            Some(std::mem::transmute(
+                descriptor::vkFreeDescriptorSets as *const (),
+            ))
+        }
+        "vkUpdateDescriptorSets" => {
+            Some(std::mem::transmute(
+                descriptor::vkUpdateDescriptorSets as *const (),
+            ))
+        }
+
+        // ── Command Pool ────────────────────────────────────
+        "vkCreateCommandPool" => {
+            Some(std::mem::transmute(
+                command::vkCreateCommandPool as *const (),
+            ))
+        }
+        "vkDestroyCommandPool" => {
+            Some(std::mem::transmute(
+                command::vkDestroyCommandPool as *const (),
+            ))
+        }
+        "vkResetCommandPool" => {
+            Some(std::mem::transmute(
+                command::vkResetCommandPool as *const (),
+            ))
+        }
+
+        // ── Command Buffer ──────────────────────────────────
+        "vkAllocateCommandBuffers" => {
+            Some(std::mem::transmute(
+                command::vkAllocateCommandBuffers as *const (),
+            ))
+        }
+        "vkFreeCommandBuffers" => {
+            Some(std::mem::transmute(
+                command::vkFreeCommandBuffers as *const (),
+            ))
+        }
+        "vkBeginCommandBuffer" => {
+            Some(std::mem::transmute(
+                command::vkBeginCommandBuffer as *const (),
+            ))
+        }
+        "vkEndCommandBuffer" => {
+            Some(std::mem::transmute(
+                command::vkEndCommandBuffer as *const (),
+            ))
+        }
+        "vkResetCommandBuffer" => {
+            Some(std::mem::transmute(
+                command::vkResetCommandBuffer as *const (),
+            ))
+        }
+
+        // ── vkCmd* Recording ────────────────────────────────
+        "vkCmdBindPipeline" => {
+            Some(std::mem::transmute(
+                command::vkCmdBindPipeline as *const (),
+            ))
+        }
+        "vkCmdBindDescriptorSets" => {
+            Some(std::mem::transmute(
+                command::vkCmdBindDescriptorSets as *const (),
+            ))
+        }
+        "vkCmdPushConstants" => {
+            Some(std::mem::transmute(
+                command::vkCmdPushConstants as *const (),
+            ))
+        }
+        "vkCmdDispatch" => {
+            Some(std::mem::transmute(
+                command::vkCmdDispatch as *const (),
+            ))
+        }
+        "vkCmdPipelineBarrier" => {
+            Some(std::mem::transmute(
+                command::vkCmdPipelineBarrier as *const (),
+            ))
+        }
+        "vkCmdSetEvent" => {
+            Some(std::mem::transmute(
+                command::vkCmdSetEvent as *const (),
+            ))
+        }
+        "vkCmdResetEvent" => {
+            Some(std::mem::transmute(
+                command::vkCmdResetEvent as *const (),
+            ))
+        }
+        "vkCmdWaitEvents" => {
+            Some(std::mem::transmute(
+                command::vkCmdWaitEvents as *const (),
+            ))
+        }
+        "vkCmdCopyBuffer" => {
+            Some(std::mem::transmute(
+                command::vkCmdCopyBuffer as *const (),
+            ))
+        }
+        "vkCmdFillBuffer" => {
+            Some(std::mem::transmute(
+                command::vkCmdFillBuffer as *const (),
+            ))
+        }
+        "vkCmdUpdateBuffer" => {
+            Some(std::mem::transmute(
+                command::vkCmdUpdateBuffer as *const (),
+            ))
+        }
+        "vkCmdBeginRenderPass" => {
+            Some(std::mem::transmute(
+                command::vkCmdBeginRenderPass as *const (),
+            ))
+        }
+        "vkCmdEndRenderPass" => {
+            Some(std::mem::transmute(
+                command::vkCmdEndRenderPass as *const (),
+            ))
+        }
+        "vkCmdBeginRendering" | "vkCmdBeginRenderingKHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdBeginRendering as *const (),
+            ))
+        }
+        "vkCmdEndRendering" | "vkCmdEndRenderingKHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdEndRendering as *const (),
+            ))
+        }
+        "vkCmdDraw" => {
+            Some(std::mem::transmute(
+                command::vkCmdDraw as *const (),
+            ))
+        }
+        "vkCmdDrawIndexed" => {
+            Some(std::mem::transmute(
+                command::vkCmdDrawIndexed as *const (),
+            ))
+        }
+        "vkCmdDrawIndirect" => {
+            Some(std::mem::transmute(
+                command::vkCmdDrawIndirect as *const (),
+            ))
+        }
+        "vkCmdDrawIndexedIndirect" => {
+            Some(std::mem::transmute(
+                command::vkCmdDrawIndexedIndirect as *const (),
+            ))
+        }
+        "vkCmdPipelineBarrier2" | "vkCmdPipelineBarrier2KHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdPipelineBarrier2 as *const (),
+            ))
+        }
+        "vkCmdSetEvent2" | "vkCmdSetEvent2KHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdSetEvent2 as *const (),
+            ))
+        }
+        "vkCmdResetEvent2" | "vkCmdResetEvent2KHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdResetEvent2 as *const (),
+            ))
+        }
+        "vkCmdWaitEvents2" | "vkCmdWaitEvents2KHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdWaitEvents2 as *const (),
+            ))
+        }
+        "vkCmdWriteTimestamp2" | "vkCmdWriteTimestamp2KHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdWriteTimestamp2 as *const (),
+            ))
+        }
+        "vkCmdDispatchIndirect" => {
+            Some(std::mem::transmute(
+                command::vkCmdDispatchIndirect as *const (),
+            ))
+        }
+        "vkCmdDrawIndirectCount" | "vkCmdDrawIndirectCountKHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdDrawIndirectCount as *const (),
+            ))
+        }
+        "vkCmdDrawIndexedIndirectCount" | "vkCmdDrawIndexedIndirectCountKHR" => {
+            Some(std::mem::transmute(
+                command::vkCmdDrawIndexedIndirectCount as *const (),
+            ))
+        }
+        "vkCmdBindVertexBuffers" => {
+            Some(std::mem::transmute(
+                command::vkCmdBindVertexBuffers as *const (),
+            ))
+        }
+        "vkCmdBindIndexBuffer" => {
+            Some(std::mem::transmute(
+                command::vkCmdBindIndexBuffer as *const (),
+            ))
+        }
+        "vkCmdSetViewport" => {
+            Some(std::mem::transmute(
+                command::vkCmdSetViewport as *const (),
+            ))
+        }
+        "vkCmdSetScissor" => {
+            Some(std::mem::transmute(
+                command::vkCmdSetScissor as *const (),
+            ))
+        }
+        "vkCmdCopyBufferToImage" => {
+            Some(std::mem::transmute(
+                command::vkCmdCopyBufferToImage as *const (),
+            ))
+        }
+        "vkCmdCopyImageToBuffer" => {
+            Some(std::mem::transmute(
+                command::vkCmdCopyImageToBuffer as *const (),
+            ))
+        }
+        "vkCmdCopyImage" => {
+            Some(std::mem::transmute(
+                command::vkCmdCopyImage as *const (),
+            ))
+        }
+        "vkCmdBlitImage" => {
+            Some(std::mem::transmute(
+                command::vkCmdBlitImage as *const (),
+            ))
+        }
+        "vkCmdResolveImage" => {
+            Some(std::mem::transmute(
+                command::vkCmdResolveImage as *const (),
+            ))
+        }
+        "vkCmdResetQueryPool" => {
+            Some(std::mem::transmute(
+                command::vkCmdResetQueryPool as *const (),
+            ))
+        }
+        "vkCmdBeginQuery" => {
+            Some(std::mem::transmute(
+                command::vkCmdBeginQuery as *const (),
+            ))
+        }
+        "vkCmdEndQuery" => {
+            Some(std::mem::transmute(
+                command::vkCmdEndQuery as *const (),
+            ))
+        }
+        "vkCmdWriteTimestamp" => {
+            Some(std::mem::transmute(
+                command::vkCmdWriteTimestamp as *const (),
+            ))
+        }
+        "vkCmdCopyQueryPoolResults" => {
+            Some(std::mem::transmute(
+                command::vkCmdCopyQueryPoolResults as *const (),
+            ))
+        }
+        "vkCmdExecuteCommands" => {
+            Some(std::mem::transmute(
+                command::vkCmdExecuteCommands as *const (),
+            ))
+        }
+
+        // ── Query Pool ──────────────────────────────────────
+        "vkCreateQueryPool" => {
+            Some(std::mem::transmute(
+                query::vkCreateQueryPool as *const (),
+            ))
+        }
+        "vkDestroyQueryPool" => {
+            Some(std::mem::transmute(
+                query::vkDestroyQueryPool as *const (),
+            ))
+        }
+        "vkGetQueryPoolResults" => {
+            Some(std::mem::transmute(
+                query::vkGetQueryPoolResults as *const (),
+            ))
+        }
+
+        // ── Fence ───────────────────────────────────────────
+        "vkCreateFence" => {
+            Some(std::mem::transmute(
+                sync::vkCreateFence as *const (),
+            ))
+        }
+        "vkDestroyFence" => {
+            Some(std::mem::transmute(
+                sync::vkDestroyFence as *const (),
+            ))
+        }
+        "vkWaitForFences" => {
+            Some(std::mem::transmute(
+                sync::vkWaitForFences as *const (),
+            ))
+        }
+        "vkResetFences" => {
+            Some(std::mem::transmute(
+                sync::vkResetFences as *const (),
+            ))
+        }
+        "vkGetFenceStatus" => {
+            Some(std::mem::transmute(
+                sync::vkGetFenceStatus as *const (),
+            ))
+        }
+
+        // ── Queue ───────────────────────────────────────────
+        "vkQueueSubmit" => {
+            Some(std::mem::transmute(
+                sync::vkQueueSubmit as *const (),
+            ))
+        }
+        "vkQueueSubmit2" | "vkQueueSubmit2KHR" => {
+            Some(std::mem::transmute(
+                sync::vkQueueSubmit2 as *const (),
+            ))
+        }
+        "vkQueueWaitIdle" => {
+            Some(std::mem::transmute(
+                sync::vkQueueWaitIdle as *const (),
+            ))
+        }
+
+        // ── Swapchains ──────────────────────────────────────
+        "vkCreateSwapchainKHR" => {
+            Some(std::mem::transmute(
+                swapchain::vkCreateSwapchainKHR as *const (),
+            ))
+        }
+        "vkDestroySwapchainKHR" => {
+            Some(std::mem::transmute(
+                swapchain::vkDestroySwapchainKHR as *const (),
+            ))
+        }
+        "vkGetSwapchainImagesKHR" => {
+            Some(std::mem::transmute(
+                swapchain::vkGetSwapchainImagesKHR as *const (),
+            ))
+        }
+        "vkAcquireNextImageKHR" => {
+            Some(std::mem::transmute(
+                swapchain::vkAcquireNextImageKHR as *const (),
+            ))
+        }
+        "vkQueuePresentKHR" => {
+            Some(std::mem::transmute(
+                swapchain::vkQueuePresentKHR as *const (),
+            ))
+        }
+
+        _ => None,
+    }
+}
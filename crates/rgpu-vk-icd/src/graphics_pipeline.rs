@@ -7,6 +7,7 @@ use ash::vk::Handle;
 
 use crate::dispatch::DispatchableHandle;
 use crate::handle_store;
+use crate::pnext::find_in_chain;
 use crate::send_vulkan_command;
 
 use rgpu_protocol::vulkan_commands::{
@@ -14,7 +15,8 @@ use rgpu_protocol::vulkan_commands::{
     SerializedPipelineColorBlendStateCreateInfo, SerializedPipelineDepthStencilStateCreateInfo,
     SerializedPipelineDynamicStateCreateInfo, SerializedPipelineInputAssemblyStateCreateInfo,
     SerializedPipelineMultisampleStateCreateInfo,
-    SerializedPipelineRasterizationStateCreateInfo, SerializedPipelineShaderStageCreateInfo,
+    SerializedPipelineRasterizationStateCreateInfo, SerializedPipelineRenderingCreateInfo,
+    SerializedPipelineShaderStageCreateInfo,
     SerializedPipelineVertexInputStateCreateInfo, SerializedPipelineViewportStateCreateInfo,
     SerializedRect2D, SerializedStencilOpState, SerializedVertexInputAttributeDescription,
     SerializedVertexInputBindingDescription, SerializedViewport, VulkanCommand, VulkanResponse,
@@ -25,7 +27,7 @@ use rgpu_protocol::vulkan_commands::{
 #[no_mangle]
 pub unsafe extern "C" fn vkCreateGraphicsPipelines(
     device: vk::Device,
-    _pipeline_cache: vk::PipelineCache,
+    pipeline_cache: vk::PipelineCache,
     create_info_count: u32,
     p_create_infos: *const vk::GraphicsPipelineCreateInfo<'_>,
     _p_allocator: *const vk::AllocationCallbacks<'_>,
@@ -307,11 +309,34 @@ pub unsafe extern "C" fn vkCreateGraphicsPipelines(
             Some(h) => h,
             None => return vk::Result::ERROR_UNKNOWN,
         };
-        let rp_handle = match handle_store::get_render_pass(ci.render_pass.as_raw()) {
-            Some(h) => h,
-            None => return vk::Result::ERROR_UNKNOWN,
+        // No render pass with dynamic rendering
+        let rp_handle = if ci.render_pass == vk::RenderPass::null() {
+            None
+        } else {
+            match handle_store::get_render_pass(ci.render_pass.as_raw()) {
+                Some(h) => Some(h),
+                None => return vk::Result::ERROR_UNKNOWN,
+            }
         };
 
+        let rendering = find_in_chain::<vk::PipelineRenderingCreateInfo>(
+            ci.p_next,
+            vk::StructureType::PIPELINE_RENDERING_CREATE_INFO,
+        )
+        .map(|ri| SerializedPipelineRenderingCreateInfo {
+            view_mask: ri.view_mask,
+            color_attachment_formats: if ri.p_color_attachment_formats.is_null() {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts(ri.p_color_attachment_formats, ri.color_attachment_count as usize)
+                    .iter()
+                    .map(|f| f.as_raw())
+                    .collect()
+            },
+            depth_attachment_format: ri.depth_attachment_format.as_raw(),
+            stencil_attachment_format: ri.stencil_attachment_format.as_raw(),
+        });
+
         serialized_cis.push(SerializedGraphicsPipelineCreateInfo {
             flags: ci.flags.as_raw(),
             stages,
@@ -326,11 +351,13 @@ pub unsafe extern "C" fn vkCreateGraphicsPipelines(
             layout: layout_handle,
             render_pass: rp_handle,
             subpass: ci.subpass,
+            rendering,
         });
     }
 
     let cmd = VulkanCommand::CreateGraphicsPipelines {
         device: dev_handle,
+        pipeline_cache: handle_store::get_pipeline_cache(pipeline_cache.as_raw()),
         create_infos: serialized_cis,
     };
 
@@ -9,7 +9,7 @@ use rgpu_protocol::handle::NetworkHandle;
 
 static NEXT_ID: AtomicU64 = AtomicU64::new(0x2000);
 
-fn alloc_id() -> u64 {
+pub(crate) fn alloc_id() -> u64 {
     NEXT_ID.fetch_add(1, Ordering::Relaxed)
 }
 
@@ -57,3 +57,8 @@ handle_map!(IMAGE_VIEW_MAP, image_view_map, store_image_view, get_image_view, re
 handle_map!(RENDER_PASS_MAP, render_pass_map, store_render_pass, get_render_pass, remove_render_pass);
 handle_map!(FRAMEBUFFER_MAP, framebuffer_map, store_framebuffer, get_framebuffer, remove_framebuffer);
 handle_map!(SEMAPHORE_MAP, semaphore_map, store_semaphore, get_semaphore, remove_semaphore);
+handle_map!(QUERY_POOL_MAP, query_pool_map, store_query_pool, get_query_pool, remove_query_pool);
+handle_map!(SAMPLER_MAP, sampler_map, store_sampler, get_sampler, remove_sampler);
+handle_map!(BUFFER_VIEW_MAP, buffer_view_map, store_buffer_view, get_buffer_view, remove_buffer_view);
+handle_map!(PIPELINE_CACHE_MAP, pipeline_cache_map, store_pipeline_cache, get_pipeline_cache, remove_pipeline_cache);
+handle_map!(EVENT_MAP, event_map, store_event, get_event, remove_event);
@@ -1,4 +1,4 @@
-//! Image and ImageView functions for the Vulkan ICD.
+//! Image, ImageView and Sampler functions for the Vulkan ICD.
 
 use ash::vk;
 use ash::vk::Handle;
@@ -9,7 +9,7 @@ use crate::send_vulkan_command;
 
 use rgpu_protocol::vulkan_commands::{
     SerializedComponentMapping, SerializedImageCreateInfo, SerializedImageSubresourceRange,
-    VulkanCommand, VulkanResponse,
+    SerializedSamplerCreateInfo, VulkanCommand, VulkanResponse,
 };
 
 // ── vkCreateImage ────────────────────────────────────────────
@@ -58,6 +58,7 @@ pub unsafe extern "C" fn vkCreateImage(
             sharing_mode: ci.sharing_mode.as_raw(),
             queue_family_indices,
             initial_layout: ci.initial_layout.as_raw(),
+            p_next: crate::pnext::read_p_next(ci.p_next),
         },
     };
 
@@ -94,6 +95,7 @@ pub unsafe extern "C" fn vkDestroyImage(
 
     let local_id = image.as_raw();
     if let Some(handle) = handle_store::remove_image(local_id) {
+        crate::access::resources(|r| r.forget(&handle));
         let _ = send_vulkan_command(VulkanCommand::DestroyImage {
             device: dev_handle,
             image: handle,
@@ -179,7 +181,10 @@ pub unsafe extern "C" fn vkBindImageMemory(
     };
 
     match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+        Ok(VulkanResponse::Success) => {
+            crate::access::resources(|r| r.bind_memory(img_handle, mem_handle));
+            vk::Result::SUCCESS
+        }
         Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
         _ => vk::Result::ERROR_UNKNOWN,
     }
@@ -234,6 +239,7 @@ pub unsafe extern "C" fn vkCreateImageView(
 
     match send_vulkan_command(cmd) {
         Ok(VulkanResponse::ImageViewCreated { handle }) => {
+            crate::access::resources(|r| r.add_view(handle, img_handle));
             let local_id = handle_store::store_image_view(handle);
             *p_view = vk::ImageView::from_raw(local_id);
             vk::Result::SUCCESS
@@ -265,9 +271,93 @@ pub unsafe extern "C" fn vkDestroyImageView(
 
     let local_id = image_view.as_raw();
     if let Some(handle) = handle_store::remove_image_view(local_id) {
+        crate::access::resources(|r| r.forget(&handle));
         let _ = send_vulkan_command(VulkanCommand::DestroyImageView {
             device: dev_handle,
             image_view: handle,
         });
     }
 }
+
+// ── vkCreateSampler ──────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateSampler(
+    device: vk::Device,
+    p_create_info: *const vk::SamplerCreateInfo<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_sampler: *mut vk::Sampler,
+) -> vk::Result {
+    if p_create_info.is_null() || p_sampler.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let ci = &*p_create_info;
+    let cmd = VulkanCommand::CreateSampler {
+        device: dev_handle,
+        create_info: SerializedSamplerCreateInfo {
+            flags: ci.flags.as_raw(),
+            mag_filter: ci.mag_filter.as_raw(),
+            min_filter: ci.min_filter.as_raw(),
+            mipmap_mode: ci.mipmap_mode.as_raw(),
+            address_mode_u: ci.address_mode_u.as_raw(),
+            address_mode_v: ci.address_mode_v.as_raw(),
+            address_mode_w: ci.address_mode_w.as_raw(),
+            mip_lod_bias: ci.mip_lod_bias,
+            anisotropy_enable: ci.anisotropy_enable != 0,
+            max_anisotropy: ci.max_anisotropy,
+            compare_enable: ci.compare_enable != 0,
+            compare_op: ci.compare_op.as_raw(),
+            min_lod: ci.min_lod,
+            max_lod: ci.max_lod,
+            border_color: ci.border_color.as_raw(),
+            unnormalized_coordinates: ci.unnormalized_coordinates != 0,
+        },
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::SamplerCreated { handle }) => {
+            let local_id = handle_store::store_sampler(handle);
+            *p_sampler = vk::Sampler::from_raw(local_id);
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
+    }
+}
+
+// ── vkDestroySampler ─────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkDestroySampler(
+    device: vk::Device,
+    sampler: vk::Sampler,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    if sampler == vk::Sampler::null() {
+        return;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Some(handle) = handle_store::remove_sampler(sampler.as_raw()) {
+        let _ = send_vulkan_command(VulkanCommand::DestroySampler {
+            device: dev_handle,
+            sampler: handle,
+        });
+    }
+}
@@ -9,7 +9,7 @@ use crate::dispatch::DispatchableHandle;
 use crate::handle_store;
 use crate::send_vulkan_command;
 
-use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
+use rgpu_protocol::vulkan_commands::{SerializedExtensionProperties, VulkanCommand, VulkanResponse};
 
 #[no_mangle]
 pub unsafe extern "C" fn vkCreateInstance(
@@ -80,6 +80,7 @@ pub unsafe extern "C" fn vkDestroyInstance(
     if let Some(handle) = handle_store::remove_instance(local_id) {
         let _ = send_vulkan_command(VulkanCommand::DestroyInstance { instance: handle });
     }
+    crate::dispatch::destroy_physical_devices(local_id);
     DispatchableHandle::destroy(disp);
 }
 
@@ -101,35 +102,40 @@ pub unsafe extern "C" fn vkEnumeratePhysicalDevices(
         None => return vk::Result::ERROR_INITIALIZATION_FAILED,
     };
 
-    let cmd = VulkanCommand::EnumeratePhysicalDevices { instance: inst_handle };
-
-    match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::PhysicalDevices { handles }) => {
-            if p_physical_devices.is_null() {
-                *p_physical_device_count = handles.len() as u32;
-                return vk::Result::SUCCESS;
+    // Physical devices are dispatchable handles, enumerated once per instance
+    let devices = crate::dispatch::physical_devices(local_id, || {
+        let cmd = VulkanCommand::EnumeratePhysicalDevices { instance: inst_handle };
+        match send_vulkan_command(cmd) {
+            Ok(VulkanResponse::PhysicalDevices { handles }) => {
+                Ok(handles.into_iter().map(handle_store::store_physical_device).collect())
             }
+            Ok(VulkanResponse::Error { code, .. }) => Err(vk::Result::from_raw(code)),
+            _ => Err(vk::Result::ERROR_INITIALIZATION_FAILED),
+        }
+    });
+    let devices = match devices {
+        Ok(d) => d,
+        Err(e) => return e,
+    };
 
-            let requested = *p_physical_device_count as usize;
-            let available = handles.len();
-            let count = std::cmp::min(requested, available);
+    if p_physical_devices.is_null() {
+        *p_physical_device_count = devices.len() as u32;
+        return vk::Result::SUCCESS;
+    }
 
-            for i in 0..count {
-                let pd_local_id = handle_store::store_physical_device(handles[i]);
-                // Physical devices are dispatchable handles
-                let pd_disp = DispatchableHandle::new(pd_local_id);
-                *p_physical_devices.add(i) = std::mem::transmute(pd_disp);
-            }
-            *p_physical_device_count = count as u32;
+    let requested = *p_physical_device_count as usize;
+    let available = devices.len();
+    let count = std::cmp::min(requested, available);
 
-            if count < available {
-                vk::Result::INCOMPLETE
-            } else {
-                vk::Result::SUCCESS
-            }
-        }
-        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
-        _ => vk::Result::ERROR_INITIALIZATION_FAILED,
+    for (i, &pd_disp) in devices.iter().enumerate().take(count) {
+        *p_physical_devices.add(i) = std::mem::transmute(pd_disp);
+    }
+    *p_physical_device_count = count as u32;
+
+    if count < available {
+        vk::Result::INCOMPLETE
+    } else {
+        vk::Result::SUCCESS
     }
 }
 
@@ -149,10 +155,15 @@ pub unsafe extern "C" fn vkEnumerateInstanceExtensionProperties(
         None
     };
 
+    let own_extensions = layer_name.is_none();
     let cmd = VulkanCommand::EnumerateInstanceExtensionProperties { layer_name };
 
     match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::ExtensionProperties { extensions }) => {
+        Ok(VulkanResponse::ExtensionProperties { mut extensions }) => {
+            // Surfaces are implemented here, not by the server's driver.
+            if own_extensions {
+                add_extensions(&mut extensions, crate::swapchain::instance_extensions());
+            }
             if p_properties.is_null() {
                 *p_property_count = extensions.len() as u32;
                 return vk::Result::SUCCESS;
@@ -161,10 +172,10 @@ pub unsafe extern "C" fn vkEnumerateInstanceExtensionProperties(
             let requested = *p_property_count as usize;
             let count = std::cmp::min(requested, extensions.len());
 
-            for i in 0..count {
+            for (i, ext) in extensions.iter().enumerate().take(count) {
                 let prop = &mut *p_properties.add(i);
-                write_c_string(&extensions[i].extension_name, &mut prop.extension_name);
-                prop.spec_version = extensions[i].spec_version;
+                write_c_string(&ext.extension_name, &mut prop.extension_name);
+                prop.spec_version = ext.spec_version;
             }
             *p_property_count = count as u32;
 
@@ -218,13 +229,17 @@ pub unsafe extern "C" fn vkEnumerateDeviceExtensionProperties(
         None
     };
 
+    let own_extensions = layer_name.is_none();
     let cmd = VulkanCommand::EnumerateDeviceExtensionProperties {
         physical_device: pd_handle,
         layer_name,
     };
 
     match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::ExtensionProperties { extensions }) => {
+        Ok(VulkanResponse::ExtensionProperties { mut extensions }) => {
+            if own_extensions {
+                add_extensions(&mut extensions, crate::swapchain::device_extensions());
+            }
             if p_properties.is_null() {
                 *p_property_count = extensions.len() as u32;
                 return vk::Result::SUCCESS;
@@ -233,10 +248,10 @@ pub unsafe extern "C" fn vkEnumerateDeviceExtensionProperties(
             let requested = *p_property_count as usize;
             let count = std::cmp::min(requested, extensions.len());
 
-            for i in 0..count {
+            for (i, ext) in extensions.iter().enumerate().take(count) {
                 let prop = &mut *p_properties.add(i);
-                write_c_string(&extensions[i].extension_name, &mut prop.extension_name);
-                prop.spec_version = extensions[i].spec_version;
+                write_c_string(&ext.extension_name, &mut prop.extension_name);
+                prop.spec_version = ext.spec_version;
             }
             *p_property_count = count as u32;
 
@@ -255,6 +270,19 @@ pub unsafe extern "C" fn vkEnumerateDeviceExtensionProperties(
 
 // ── Helpers ─────────────────────────────────────────────────
 
+/// Append the ICD's own extensions to the server's list.
+fn add_extensions(extensions: &mut Vec<SerializedExtensionProperties>, own: Vec<(&'static CStr, u32)>) {
+    for (name, spec_version) in own {
+        let name = name.to_string_lossy();
+        if !extensions.iter().any(|e| e.extension_name == name) {
+            extensions.push(SerializedExtensionProperties {
+                extension_name: name.into_owned(),
+                spec_version,
+            });
+        }
+    }
+}
+
 unsafe fn read_string_array(ptrs: *const *const c_char, count: u32) -> Vec<String> {
     if ptrs.is_null() || count == 0 {
         return Vec::new();
@@ -4,14 +4,20 @@
 //! physical devices. Applications using Vulkan will automatically see remote
 //! GPUs when this ICD is registered with the Vulkan loader.
 
+// Entry points follow the Vulkan spec's valid-usage rules; function pointers
+// are transmuted to loader PFN types throughout.
+#![allow(clippy::missing_safety_doc, clippy::missing_transmute_annotations)]
+
 use std::ffi::{c_char, CStr};
 use std::sync::OnceLock;
 
 use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
 
+pub mod access;
 pub mod command;
 pub mod descriptor;
 pub mod device;
+pub mod dirty;
 pub mod dispatch;
 pub mod graphics_pipeline;
 pub mod handle_store;
@@ -21,7 +27,11 @@ pub mod ipc_client;
 pub mod memory;
 pub mod physical_device;
 pub mod pipeline;
+pub mod pnext;
+pub mod present;
+pub mod query;
 pub mod renderpass;
+pub mod swapchain;
 pub mod sync;
 
 // ── IPC Client singleton ────────────────────────────────────
@@ -196,431 +206,213 @@ pub unsafe extern "C" fn vk_icdGetInstanceProcAddr(
                 physical_device::vkGetPhysicalDeviceFormatProperties2KHR as *const (),
             ))
         }
-        "vkGetPhysicalDeviceSparseImageFormatProperties" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties as *const (),
-            ))
-        }
-        "vkGetPhysicalDeviceSparseImageFormatProperties2" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties2 as *const (),
-            ))
-        }
-        "vkGetPhysicalDeviceSparseImageFormatProperties2KHR" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties2KHR as *const (),
-            ))
-        }
-
-        // ── Logical Device ──────────────────────────────────
-        "vkCreateDevice" => {
-            Some(std::mem::transmute(
-                device::vkCreateDevice as *const (),
-            ))
-        }
-        "vkDestroyDevice" => {
-            Some(std::mem::transmute(
-                device::vkDestroyDevice as *const (),
-            ))
-        }
-        "vkGetDeviceQueue" => {
-            Some(std::mem::transmute(
-                device::vkGetDeviceQueue as *const (),
-            ))
-        }
-        "vkDeviceWaitIdle" => {
-            Some(std::mem::transmute(
-                device::vkDeviceWaitIdle as *const (),
-            ))
-        }
-
-        // ── Memory ──────────────────────────────────────────
-        "vkAllocateMemory" => {
-            Some(std::mem::transmute(
-                memory::vkAllocateMemory as *const (),
-            ))
-        }
-        "vkFreeMemory" => {
-            Some(std::mem::transmute(
-                memory::vkFreeMemory as *const (),
-            ))
-        }
-        "vkMapMemory" => {
-            Some(std::mem::transmute(
-                memory::vkMapMemory as *const (),
-            ))
-        }
-        "vkUnmapMemory" => {
-            Some(std::mem::transmute(
-                memory::vkUnmapMemory as *const (),
-            ))
-        }
-        "vkFlushMappedMemoryRanges" => {
-            Some(std::mem::transmute(
-                memory::vkFlushMappedMemoryRanges as *const (),
-            ))
-        }
-        "vkInvalidateMappedMemoryRanges" => {
-            Some(std::mem::transmute(
-                memory::vkInvalidateMappedMemoryRanges as *const (),
-            ))
-        }
-
-        // ── Buffer ──────────────────────────────────────────
-        "vkCreateBuffer" => {
-            Some(std::mem::transmute(
-                memory::vkCreateBuffer as *const (),
-            ))
-        }
-        "vkDestroyBuffer" => {
-            Some(std::mem::transmute(
-                memory::vkDestroyBuffer as *const (),
-            ))
-        }
-        "vkBindBufferMemory" => {
-            Some(std::mem::transmute(
-                memory::vkBindBufferMemory as *const (),
-            ))
-        }
-        "vkGetBufferMemoryRequirements" => {
+        "vkGetPhysicalDeviceImageFormatProperties" => {
             Some(std::mem::transmute(
-                memory::vkGetBufferMemoryRequirements as *const (),
+                physical_device::vkGetPhysicalDeviceImageFormatProperties as *const (),
             ))
         }
-
-        // ── Shader Module ───────────────────────────────────
-        "vkCreateShaderModule" => {
+        "vkGetPhysicalDeviceImageFormatProperties2" => {
             Some(std::mem::transmute(
-                pipeline::vkCreateShaderModule as *const (),
+                physical_device::vkGetPhysicalDeviceImageFormatProperties2 as *const (),
             ))
         }
-        "vkDestroyShaderModule" => {
+        "vkGetPhysicalDeviceImageFormatProperties2KHR" => {
             Some(std::mem::transmute(
-                pipeline::vkDestroyShaderModule as *const (),
+                physical_device::vkGetPhysicalDeviceImageFormatProperties2KHR as *const (),
             ))
         }
-
-        // ── Descriptor Set Layout ───────────────────────────
-        "vkCreateDescriptorSetLayout" => {
-            Some(std::mem::transmute(
-                pipeline::vkCreateDescriptorSetLayout as *const (),
-            ))
-        }
-        "vkDestroyDescriptorSetLayout" => {
+        "vkGetPhysicalDeviceSparseImageFormatProperties" => {
             Some(std::mem::transmute(
-                pipeline::vkDestroyDescriptorSetLayout as *const (),
+                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties as *const (),
             ))
         }
-
-        // ── Pipeline Layout ─────────────────────────────────
-        "vkCreatePipelineLayout" => {
+        "vkGetPhysicalDeviceSparseImageFormatProperties2" => {
             Some(std::mem::transmute(
-                pipeline::vkCreatePipelineLayout as *const (),
+                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties2 as *const (),
             ))
         }
-        "vkDestroyPipelineLayout" => {
+        "vkGetPhysicalDeviceSparseImageFormatProperties2KHR" => {
             Some(std::mem::transmute(
-                pipeline::vkDestroyPipelineLayout as *const (),
+                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties2KHR as *const (),
             ))
         }
 
-        // ── Compute Pipeline ────────────────────────────────
-        "vkCreateComputePipelines" => {
-            Some(std::mem::transmute(
-                pipeline::vkCreateComputePipelines as *const (),
-            ))
-        }
-        "vkDestroyPipeline" => {
+        // ── Logical Device ──────────────────────────────────
+        "vkCreateDevice" => {
             Some(std::mem::transmute(
-                pipeline::vkDestroyPipeline as *const (),
+                device::vkCreateDevice as *const (),
             ))
         }
 
-        // ── Image ────────────────────────────────────────────
-        "vkCreateImage" => {
-            Some(std::mem::transmute(
-                image::vkCreateImage as *const (),
-            ))
-        }
-        "vkDestroyImage" => {
+        // ── Surfaces ────────────────────────────────────────
+        "vkCreateXcbSurfaceKHR" => {
             Some(std::mem::transmute(
-                image::vkDestroyImage as *const (),
+                swapchain::vkCreateXcbSurfaceKHR as *const (),
             ))
         }
-        "vkGetImageMemoryRequirements" => {
+        "vkCreateXlibSurfaceKHR" => {
             Some(std::mem::transmute(
-                image::vkGetImageMemoryRequirements as *const (),
+                swapchain::vkCreateXlibSurfaceKHR as *const (),
             ))
         }
-        "vkBindImageMemory" => {
+        "vkCreateWaylandSurfaceKHR" => {
             Some(std::mem::transmute(
-                image::vkBindImageMemory as *const (),
+                swapchain::vkCreateWaylandSurfaceKHR as *const (),
             ))
         }
-
-        // ── Image View ───────────────────────────────────────
-        "vkCreateImageView" => {
+        "vkCreateWin32SurfaceKHR" => {
             Some(std::mem::transmute(
-                image::vkCreateImageView as *const (),
+                swapchain::vkCreateWin32SurfaceKHR as *const (),
             ))
         }
-        "vkDestroyImageView" => {
+        "vkDestroySurfaceKHR" => {
             Some(std::mem::transmute(
-                image::vkDestroyImageView as *const (),
+                swapchain::vkDestroySurfaceKHR as *const (),
             ))
         }
-
-        // ── Render Pass ──────────────────────────────────────
-        "vkCreateRenderPass" => {
+        "vkGetPhysicalDeviceSurfaceSupportKHR" => {
             Some(std::mem::transmute(
-                renderpass::vkCreateRenderPass as *const (),
+                swapchain::vkGetPhysicalDeviceSurfaceSupportKHR as *const (),
             ))
         }
-        "vkDestroyRenderPass" => {
-            Some(std::mem::transmute(
-                renderpass::vkDestroyRenderPass as *const (),
-            ))
-        }
-
-        // ── Framebuffer ──────────────────────────────────────
-        "vkCreateFramebuffer" => {
+        "vkGetPhysicalDeviceSurfaceCapabilitiesKHR" => {
             Some(std::mem::transmute(
-                renderpass::vkCreateFramebuffer as *const (),
+                swapchain::vkGetPhysicalDeviceSurfaceCapabilitiesKHR as *const (),
             ))
         }
-        "vkDestroyFramebuffer" => {
+        "vkGetPhysicalDeviceSurfaceFormatsKHR" => {
             Some(std::mem::transmute(
-                renderpass::vkDestroyFramebuffer as *const (),
+                swapchain::vkGetPhysicalDeviceSurfaceFormatsKHR as *const (),
             ))
         }
-
-        // ── Graphics Pipeline ────────────────────────────────
-        "vkCreateGraphicsPipelines" => {
+        "vkGetPhysicalDeviceSurfacePresentModesKHR" => {
             Some(std::mem::transmute(
-                graphics_pipeline::vkCreateGraphicsPipelines as *const (),
+                swapchain::vkGetPhysicalDeviceSurfacePresentModesKHR as *const (),
             ))
         }
-
-        // ── Semaphore ────────────────────────────────────────
-        "vkCreateSemaphore" => {
+        "vkGetPhysicalDeviceXcbPresentationSupportKHR" => {
             Some(std::mem::transmute(
-                sync::vkCreateSemaphore as *const (),
+                swapchain::vkGetPhysicalDeviceXcbPresentationSupportKHR as *const (),
             ))
         }
-        "vkDestroySemaphore" => {
+        "vkGetPhysicalDeviceXlibPresentationSupportKHR" => {
             Some(std::mem::transmute(
-                sync::vkDestroySemaphore as *const (),
+                swapchain::vkGetPhysicalDeviceXlibPresentationSupportKHR as *const (),
             ))
         }
-
-        // ── Descriptor Pool ─────────────────────────────────
-        "vkCreateDescriptorPool" => {
+        "vkGetPhysicalDeviceWaylandPresentationSupportKHR" => {
             Some(std::mem::transmute(
-                descriptor::vkCreateDescriptorPool as *const (),
+                swapchain::vkGetPhysicalDeviceWaylandPresentationSupportKHR as *const (),
             ))
         }
-        "vkDestroyDescriptorPool" => {
+        "vkGetPhysicalDeviceWin32PresentationSupportKHR" => {
             Some(std::mem::transmute(
-                descriptor::vkDestroyDescriptorPool as *const (),
+                swapchain::vkGetPhysicalDeviceWin32PresentationSupportKHR as *const (),
             ))
         }
 
-        // ── Descriptor Set ──────────────────────────────────
-        "vkAllocateDescriptorSets" => {
-            Some(std::mem::transmute(
-                descriptor::vkAllocateDescriptorSets as *const (),
-            ))
-        }
-        "vkFreeDescriptorSets" => {
-            Some(std::mem::transmute(
-                descriptor::vkFreeDescriptorSets as *const (),
-            ))
-        }
-        "vkUpdateDescriptorSets" => {
-            Some(std::mem::transmute(
-                descriptor::vkUpdateDescriptorSets as *const (),
-            ))
-        }
+        // ── Device level ────────────────────────────────────
+        _ => dispatch::device_proc_addr(name),
+    }
+}
 
-        // ── Command Pool ────────────────────────────────────
-        "vkCreateCommandPool" => {
-            Some(std::mem::transmute(
-                command::vkCreateCommandPool as *const (),
-            ))
-        }
-        "vkDestroyCommandPool" => {
-            Some(std::mem::transmute(
-                command::vkDestroyCommandPool as *const (),
-            ))
-        }
-        "vkResetCommandPool" => {
-            Some(std::mem::transmute(
-                command::vkResetCommandPool as *const (),
-            ))
-        }
+/// Returns function pointers for physical device extension functions.
+#[no_mangle]
+pub unsafe extern "C" fn vk_icdGetPhysicalDeviceProcAddr(
+    _instance: usize,
+    p_name: *const c_char,
+) -> Option<unsafe extern "C" fn()> {
+    if p_name.is_null() {
+        return None;
+    }
 
-        // ── Command Buffer ──────────────────────────────────
-        "vkAllocateCommandBuffers" => {
-            Some(std::mem::transmute(
-                command::vkAllocateCommandBuffers as *const (),
-            ))
-        }
-        "vkFreeCommandBuffers" => {
-            Some(std::mem::transmute(
-                command::vkFreeCommandBuffers as *const (),
-            ))
-        }
-        "vkBeginCommandBuffer" => {
-            Some(std::mem::transmute(
-                command::vkBeginCommandBuffer as *const (),
-            ))
-        }
-        "vkEndCommandBuffer" => {
-            Some(std::mem::transmute(
-                command::vkEndCommandBuffer as *const (),
-            ))
-        }
-        "vkResetCommandBuffer" => {
-            Some(std::mem::transmute(
-                command::vkResetCommandBuffer as *const (),
-            ))
-        }
+    let name = CStr::from_ptr(p_name).to_str().ok()?;
 
-        // ── vkCmd* Recording ────────────────────────────────
-        "vkCmdBindPipeline" => {
-            Some(std::mem::transmute(
-                command::vkCmdBindPipeline as *const (),
-            ))
-        }
-        "vkCmdBindDescriptorSets" => {
-            Some(std::mem::transmute(
-                command::vkCmdBindDescriptorSets as *const (),
-            ))
-        }
-        "vkCmdDispatch" => {
-            Some(std::mem::transmute(
-                command::vkCmdDispatch as *const (),
-            ))
-        }
-        "vkCmdPipelineBarrier" => {
-            Some(std::mem::transmute(
-                command::vkCmdPipelineBarrier as *const (),
-            ))
-        }
-        "vkCmdCopyBuffer" => {
-            Some(std::mem::transmute(
-                command::vkCmdCopyBuffer as *const (),
-            ))
-        }
-        "vkCmdFillBuffer" => {
-            Some(std::mem::transmute(
-                command::vkCmdFillBuffer as *const (),
-            ))
-        }
-        "vkCmdUpdateBuffer" => {
-            Some(std::mem::transmute(
-                command::vkCmdUpdateBuffer as *const (),
-            ))
-        }
-        "vkCmdBeginRenderPass" => {
-            Some(std::mem::transmute(
-                command::vkCmdBeginRenderPass as *const (),
-            ))
-        }
-        "vkCmdEndRenderPass" => {
-            Some(std::mem::transmute(
-                command::vkCmdEndRenderPass as *const (),
-            ))
-        }
-        "vkCmdDraw" => {
+    match name {
+        "vkGetPhysicalDeviceProperties2KHR" => {
             Some(std::mem::transmute(
-                command::vkCmdDraw as *const (),
+                physical_device::vkGetPhysicalDeviceProperties2KHR as *const (),
             ))
         }
-        "vkCmdDrawIndexed" => {
+        "vkGetPhysicalDeviceFeatures2KHR" => {
             Some(std::mem::transmute(
-                command::vkCmdDrawIndexed as *const (),
+                physical_device::vkGetPhysicalDeviceFeatures2KHR as *const (),
             ))
         }
-        "vkCmdBindVertexBuffers" => {
+        "vkGetPhysicalDeviceMemoryProperties2KHR" => {
             Some(std::mem::transmute(
-                command::vkCmdBindVertexBuffers as *const (),
+                physical_device::vkGetPhysicalDeviceMemoryProperties2KHR as *const (),
             ))
         }
-        "vkCmdBindIndexBuffer" => {
+        "vkGetPhysicalDeviceQueueFamilyProperties2KHR" => {
             Some(std::mem::transmute(
-                command::vkCmdBindIndexBuffer as *const (),
+                physical_device::vkGetPhysicalDeviceQueueFamilyProperties2KHR as *const (),
             ))
         }
-        "vkCmdSetViewport" => {
+        "vkGetPhysicalDeviceFormatProperties2KHR" => {
             Some(std::mem::transmute(
-                command::vkCmdSetViewport as *const (),
+                physical_device::vkGetPhysicalDeviceFormatProperties2KHR as *const (),
             ))
         }
-        "vkCmdSetScissor" => {
+        "vkGetPhysicalDeviceImageFormatProperties2KHR" => {
             Some(std::mem::transmute(
-                command::vkCmdSetScissor as *const (),
+                physical_device::vkGetPhysicalDeviceImageFormatProperties2KHR as *const (),
             ))
         }
-        "vkCmdCopyBufferToImage" => {
+        "vkGetPhysicalDeviceSparseImageFormatProperties2KHR" => {
             Some(std::mem::transmute(
-                command::vkCmdCopyBufferToImage as *const (),
+                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties2KHR as *const (),
             ))
         }
-        "vkCmdCopyImageToBuffer" => {
+        "vkGetPhysicalDeviceSurfaceSupportKHR" => {
             Some(std::mem::transmute(
-                command::vkCmdCopyImageToBuffer as *const (),
+                swapchain::vkGetPhysicalDeviceSurfaceSupportKHR as *const (),
             ))
         }
-
-        // ── Fence ───────────────────────────────────────────
-        "vkCreateFence" => {
+        "vkGetPhysicalDeviceSurfaceCapabilitiesKHR" => {
             Some(std::mem::transmute(
-                sync::vkCreateFence as *const (),
+                swapchain::vkGetPhysicalDeviceSurfaceCapabilitiesKHR as *const (),
             ))
         }
-        "vkDestroyFence" => {
+        "vkGetPhysicalDeviceSurfaceFormatsKHR" => {
             Some(std::mem::transmute(
-                sync::vkDestroyFence as *const (),
+                swapchain::vkGetPhysicalDeviceSurfaceFormatsKHR as *const (),
             ))
         }
-        "vkWaitForFences" => {
+        "vkGetPhysicalDeviceSurfacePresentModesKHR" => {
             Some(std::mem::transmute(
-                sync::vkWaitForFences as *const (),
+                swapchain::vkGetPhysicalDeviceSurfacePresentModesKHR as *const (),
             ))
         }
-        "vkResetFences" => {
+        "vkGetPhysicalDeviceXcbPresentationSupportKHR" => {
             Some(std::mem::transmute(
-                sync::vkResetFences as *const (),
+                swapchain::vkGetPhysicalDeviceXcbPresentationSupportKHR as *const (),
             ))
         }
-        "vkGetFenceStatus" => {
+        "vkGetPhysicalDeviceXlibPresentationSupportKHR" => {
             Some(std::mem::transmute(
-                sync::vkGetFenceStatus as *const (),
+                swapchain::vkGetPhysicalDeviceXlibPresentationSupportKHR as *const (),
             ))
         }
-
-        // ── Queue ───────────────────────────────────────────
-        "vkQueueSubmit" => {
+        "vkGetPhysicalDeviceWaylandPresentationSupportKHR" => {
             Some(std::mem::transmute(
-                sync::vkQueueSubmit as *const (),
+                swapchain::vkGetPhysicalDeviceWaylandPresentationSupportKHR as *const (),
             ))
         }
-        "vkQueueWaitIdle" => {
+        "vkGetPhysicalDeviceWin32PresentationSupportKHR" => {
             Some(std::mem::transmute(
-                sync::vkQueueWaitIdle as *const (),
+                swapchain::vkGetPhysicalDeviceWin32PresentationSupportKHR as *const (),
             ))
         }
-
-        // ── Not implemented (return None) ───────────────────
         _ => None,
     }
 }
 
-/// Returns function pointers for physical device extension functions.
+/// Returns function pointers for device-level functions. The loader and
+/// layers resolve these per device after vkCreateDevice.
 #[no_mangle]
-pub unsafe extern "C" fn vk_icdGetPhysicalDeviceProcAddr(
-    _instance: usize,
+pub unsafe extern "C" fn vkGetDeviceProcAddr(
+    _device: usize,
     p_name: *const c_char,
 ) -> Option<unsafe extern "C" fn()> {
     if p_name.is_null() {
@@ -628,38 +420,5 @@ pub unsafe extern "C" fn vk_icdGetPhysicalDeviceProcAddr(
     }
 
     let name = CStr::from_ptr(p_name).to_str().ok()?;
-
-    match name {
-        "vkGetPhysicalDeviceProperties2KHR" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceProperties2KHR as *const (),
-            ))
-        }
-        "vkGetPhysicalDeviceFeatures2KHR" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceFeatures2KHR as *const (),
-            ))
-        }
-        "vkGetPhysicalDeviceMemoryProperties2KHR" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceMemoryProperties2KHR as *const (),
-            ))
-        }
-        "vkGetPhysicalDeviceQueueFamilyProperties2KHR" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceQueueFamilyProperties2KHR as *const (),
-            ))
-        }
-        "vkGetPhysicalDeviceFormatProperties2KHR" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceFormatProperties2KHR as *const (),
-            ))
-        }
-        "vkGetPhysicalDeviceSparseImageFormatProperties2KHR" => {
-            Some(std::mem::transmute(
-                physical_device::vkGetPhysicalDeviceSparseImageFormatProperties2KHR as *const (),
-            ))
-        }
-        _ => None,
-    }
+    dispatch::device_proc_addr(name)
 }
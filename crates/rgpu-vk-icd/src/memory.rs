@@ -7,29 +7,267 @@ use std::os::raw::c_void;
 use std::sync::Mutex;
 use std::sync::OnceLock;
 
+use crate::access::Access;
+use crate::dirty::DirtyTracker;
 use crate::dispatch::DispatchableHandle;
 use crate::handle_store;
 use crate::send_vulkan_command;
 
+use rgpu_protocol::handle::NetworkHandle;
 use rgpu_protocol::vulkan_commands::{MappedMemoryRange, VulkanCommand, VulkanResponse};
 
-/// Shadow buffer info for a mapped memory region.
+/// Shadow buffer info for a mapped memory region: the local mirror the
+/// application's pointer points into, for as long as the memory stays
+/// mapped.
 struct ShadowBuffer {
     ptr: *mut u8,
     size: usize,
     offset: u64,
     layout: std::alloc::Layout,
+    device: NetworkHandle,
+    memory: NetworkHandle,
+    /// Host-coherent memory is synced at submits and waits, and other
+    /// memory only when the application flushes or invalidates it
+    coherent: bool,
+    /// Which blocks the application changed since the server last saw them
+    dirty: DirtyTracker,
 }
 
 // Shadow buffers are only accessed under Mutex, so this is safe.
 unsafe impl Send for ShadowBuffer {}
 
+impl ShadowBuffer {
+    /// The changed blocks of `start..end` of the mirror as flush ranges,
+    /// with their data. With `to_end`, a range reaching `end` is sent as
+    /// reaching the end of the mapping, which needn't be a multiple of
+    /// `nonCoherentAtomSize`.
+    unsafe fn take_changes(&mut self, start: usize, end: usize, to_end: bool) -> (Vec<MappedMemoryRange>, Vec<Vec<u8>>) {
+        let shadow = std::slice::from_raw_parts(self.ptr, self.size);
+        let runs = self.dirty.take_dirty(shadow, start, end);
+        let ranges = runs
+            .iter()
+            .map(|run| MappedMemoryRange {
+                memory: self.memory,
+                offset: self.offset + run.start as u64,
+                size: if to_end && run.end >= end { vk::WHOLE_SIZE } else { run.len() as u64 },
+            })
+            .collect();
+        (ranges, runs.into_iter().map(|run| shadow[run].to_vec()).collect())
+    }
+}
+
 static SHADOW_BUFFERS: OnceLock<Mutex<HashMap<u64, ShadowBuffer>>> = OnceLock::new();
 
 fn shadow_buffers() -> &'static Mutex<HashMap<u64, ShadowBuffer>> {
     SHADOW_BUFFERS.get_or_init(|| Mutex::new(HashMap::new()))
 }
 
+/// Size of an allocation, so a `VK_WHOLE_SIZE` mapping gets a mirror of
+/// the right size, and whether it is host-coherent.
+struct Allocation {
+    size: u64,
+    coherent: bool,
+}
+
+static ALLOCATIONS: OnceLock<Mutex<HashMap<u64, Allocation>>> = OnceLock::new();
+
+fn allocations() -> &'static Mutex<HashMap<u64, Allocation>> {
+    ALLOCATIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Property flags of each memory type of a device, by device local ID.
+static MEMORY_TYPES: OnceLock<Mutex<HashMap<u64, Vec<u32>>>> = OnceLock::new();
+
+fn memory_types() -> &'static Mutex<HashMap<u64, Vec<u32>>> {
+    MEMORY_TYPES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Look up the memory types of a new device's physical device, which say
+/// which of its allocations are host-coherent.
+pub(crate) fn note_memory_types(dev_local_id: u64, physical_device: NetworkHandle) {
+    let cmd = VulkanCommand::GetPhysicalDeviceMemoryProperties { physical_device };
+    if let Ok(VulkanResponse::PhysicalDeviceMemoryProperties { memory_types: types, .. }) = send_vulkan_command(cmd) {
+        if let Ok(mut devices) = memory_types().lock() {
+            devices.insert(dev_local_id, types.iter().map(|mt| mt.property_flags).collect());
+        }
+    }
+}
+
+pub(crate) fn forget_memory_types(dev_local_id: u64) {
+    if let Ok(mut devices) = memory_types().lock() {
+        devices.remove(&dev_local_id);
+    }
+}
+
+/// Send what the application wrote to the host-coherent mapped memory a
+/// queue submission uses since the server last saw it. Memory that stays
+/// mapped, like a uniform buffer mapped once at startup, is never
+/// unmapped, and host-coherent memory is never flushed, so this is the
+/// only point where those writes can go to the server. Memory that isn't
+/// coherent is the application's to flush.
+///
+/// Once the GPU may have written memory that isn't coherent, its hashes
+/// are forgotten: the next flush sends whatever the application writes,
+/// even bytes equal to what it sent before. Coherent memory the GPU writes
+/// is copied back once a wait shows it finished, see `after_wait`.
+pub(crate) fn sync_mapped_memory(access: &Access) -> Result<(), vk::Result> {
+    let mut flushes: HashMap<NetworkHandle, (Vec<MappedMemoryRange>, Vec<Vec<u8>>)> = HashMap::new();
+    if let Ok(mut bufs) = shadow_buffers().lock() {
+        for sb in bufs.values_mut() {
+            if !sb.coherent {
+                if access.writes(&sb.memory) {
+                    sb.dirty.forget();
+                }
+                continue;
+            }
+            if !access.uses(&sb.memory) {
+                continue;
+            }
+            let (ranges, data) = unsafe { sb.take_changes(0, sb.size, true) };
+            if !ranges.is_empty() {
+                let flush = flushes.entry(sb.device).or_default();
+                flush.0.extend(ranges);
+                flush.1.extend(data);
+            }
+        }
+    }
+    for (device, (ranges, data)) in flushes {
+        match send_vulkan_command(VulkanCommand::FlushMappedMemoryRanges { device, ranges, data }) {
+            Ok(VulkanResponse::Success) => {}
+            Ok(VulkanResponse::Error { code, .. }) => return Err(vk::Result::from_raw(code)),
+            _ => return Err(vk::Result::ERROR_DEVICE_LOST),
+        }
+    }
+    Ok(())
+}
+
+/// Memory a submission may write, until a wait shows it finished.
+struct PendingWrites {
+    device: NetworkHandle,
+    queue: NetworkHandle,
+    fence: Option<NetworkHandle>,
+    access: Access,
+}
+
+/// Submissions that may write memory, oldest first.
+static PENDING_WRITES: OnceLock<Mutex<Vec<PendingWrites>>> = OnceLock::new();
+
+fn pending_writes() -> &'static Mutex<Vec<PendingWrites>> {
+    PENDING_WRITES.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Note a queue submission, whose writes to coherent mapped memory are
+/// copied back once it finishes.
+pub(crate) fn submitted(device: NetworkHandle, queue: NetworkHandle, fence: Option<NetworkHandle>, access: Access) {
+    if !access.everything && access.written.is_empty() {
+        return;
+    }
+    if let Ok(mut pending) = pending_writes().lock() {
+        pending.push(PendingWrites { device, queue, fence, access });
+    }
+}
+
+/// What a successful wait shows finished.
+pub(crate) enum Finished<'a> {
+    /// Fences were signaled, and with them every submission before theirs
+    /// on the same queue
+    Fences(&'a [NetworkHandle]),
+    /// At least one of these fences was signaled
+    AnyFence(&'a [NetworkHandle]),
+    /// vkQueueWaitIdle
+    Queue(NetworkHandle),
+    /// vkDeviceWaitIdle
+    Device(NetworkHandle),
+    /// Timeline semaphores reached their values, which may or may not have
+    /// been signaled by any submission of the device
+    Semaphores(NetworkHandle),
+}
+
+/// The result of a wait, once the writes it shows finished are copied
+/// back if it succeeded.
+pub(crate) fn after_wait(result: vk::Result, finished: Finished<'_>) -> vk::Result {
+    if result != vk::Result::SUCCESS {
+        return result;
+    }
+    match read_back_gpu_writes(finished) {
+        Ok(()) => result,
+        Err(e) => e,
+    }
+}
+
+/// Copy what finished submissions may have written to host-coherent
+/// memory into the mirrors, in the blocks the application hasn't changed
+/// since. Submissions a wait shows finished are done with; those it may
+/// not have finished are copied back again at later waits.
+fn read_back_gpu_writes(finished: Finished<'_>) -> Result<(), vk::Result> {
+    let mut written = Access::default();
+    if let Ok(mut pending) = pending_writes().lock() {
+        let mut done = vec![false; pending.len()];
+        let mut keep = false;
+        match finished {
+            Finished::Fences(fences) | Finished::AnyFence(fences) => {
+                keep = matches!(finished, Finished::AnyFence(_));
+                for (i, signaled) in pending.iter().enumerate() {
+                    if signaled.fence.is_some_and(|f| fences.contains(&f)) {
+                        for (j, earlier) in pending[..=i].iter().enumerate() {
+                            done[j] |= earlier.queue == signaled.queue;
+                        }
+                    }
+                }
+            }
+            Finished::Queue(queue) => {
+                for (i, p) in pending.iter().enumerate() {
+                    done[i] = p.queue == queue;
+                }
+            }
+            Finished::Device(device) | Finished::Semaphores(device) => {
+                keep = matches!(finished, Finished::Semaphores(_));
+                for (i, p) in pending.iter().enumerate() {
+                    done[i] = p.device == device;
+                }
+            }
+        }
+        for (p, _) in pending.iter().zip(&done).filter(|(_, done)| **done) {
+            written.merge(&p.access);
+        }
+        if !keep {
+            let mut done = done.into_iter();
+            pending.retain(|_| !done.next().unwrap_or(false));
+        }
+    }
+    if !written.everything && written.written.is_empty() {
+        return Ok(());
+    }
+
+    let mut reads: HashMap<NetworkHandle, (Vec<u64>, Vec<MappedMemoryRange>)> = HashMap::new();
+    if let Ok(bufs) = shadow_buffers().lock() {
+        for (local_id, sb) in bufs.iter() {
+            if sb.coherent && written.writes(&sb.memory) {
+                let read = reads.entry(sb.device).or_default();
+                read.0.push(*local_id);
+                read.1.push(MappedMemoryRange { memory: sb.memory, offset: sb.offset, size: sb.size as u64 });
+            }
+        }
+    }
+    for (device, (local_ids, ranges)) in reads {
+        let range_data = match send_vulkan_command(VulkanCommand::InvalidateMappedMemoryRanges { device, ranges }) {
+            Ok(VulkanResponse::InvalidatedData { range_data }) => range_data,
+            Ok(VulkanResponse::Error { code, .. }) => return Err(vk::Result::from_raw(code)),
+            _ => return Err(vk::Result::ERROR_DEVICE_LOST),
+        };
+        if let Ok(mut bufs) = shadow_buffers().lock() {
+            for (local_id, data) in local_ids.iter().zip(&range_data) {
+                // Unmapped or remapped since
+                if let Some(sb) = bufs.get_mut(local_id).filter(|sb| sb.size == data.len()) {
+                    let shadow = unsafe { std::slice::from_raw_parts_mut(sb.ptr, sb.size) };
+                    sb.dirty.refresh(shadow, data);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
 // ── vkAllocateMemory ────────────────────────────────────────
 
 #[no_mangle]
@@ -61,6 +299,17 @@ pub unsafe extern "C" fn vkAllocateMemory(
     match send_vulkan_command(cmd) {
         Ok(VulkanResponse::MemoryAllocated { handle }) => {
             let local_id = handle_store::store_memory(handle);
+            // Memory of a type that isn't known counts as coherent, which
+            // only costs syncing it when it needn't be
+            let coherent = match memory_types().lock().ok().and_then(|d| d.get(&dev_local_id).cloned()) {
+                Some(types) => types.get(ai.memory_type_index as usize).is_none_or(|flags| {
+                    vk::MemoryPropertyFlags::from_raw(*flags).contains(vk::MemoryPropertyFlags::HOST_COHERENT)
+                }),
+                None => true,
+            };
+            if let Ok(mut allocs) = allocations().lock() {
+                allocs.insert(local_id, Allocation { size: ai.allocation_size, coherent });
+            }
             *p_memory = vk::DeviceMemory::from_raw(local_id);
             vk::Result::SUCCESS
         }
@@ -95,6 +344,9 @@ pub unsafe extern "C" fn vkFreeMemory(
             std::alloc::dealloc(sb.ptr, sb.layout);
         }
     }
+    if let Ok(mut allocs) = allocations().lock() {
+        allocs.remove(&local_id);
+    }
 
     if let Some(handle) = handle_store::remove_memory(local_id) {
         let _ = send_vulkan_command(VulkanCommand::FreeMemory {
@@ -133,6 +385,15 @@ pub unsafe extern "C" fn vkMapMemory(
         None => return vk::Result::ERROR_MEMORY_MAP_FAILED,
     };
 
+    // The server sends the mapped contents for the mirror, which it can
+    // only size if told how much of the allocation is mapped.
+    let allocation = allocations().lock().ok().and_then(|allocs| allocs.get(&mem_local_id).map(|a| (a.size, a.coherent)));
+    let size = match allocation {
+        Some((allocation_size, _)) if size == vk::WHOLE_SIZE => allocation_size.saturating_sub(offset),
+        _ => size,
+    };
+    let coherent = allocation.is_none_or(|(_, coherent)| coherent);
+
     let cmd = VulkanCommand::MapMemory {
         device: dev_handle,
         memory: mem_handle,
@@ -168,6 +429,10 @@ pub unsafe extern "C" fn vkMapMemory(
                         size: buf_size,
                         offset,
                         layout,
+                        device: dev_handle,
+                        memory: mem_handle,
+                        coherent,
+                        dirty: DirtyTracker::new(offset, &data),
                     },
                 );
             }
@@ -198,13 +463,25 @@ pub unsafe extern "C" fn vkUnmapMemory(device: vk::Device, memory: vk::DeviceMem
         None => return,
     };
 
-    // Read shadow buffer data and send to server
+    // Send what the application changed in the shadow buffer: one run
+    // with the unmap, several as a flush ahead of it.
     let (written_data, offset) = if let Ok(mut bufs) = shadow_buffers().lock() {
-        if let Some(sb) = bufs.remove(&mem_local_id) {
-            let data = std::slice::from_raw_parts(sb.ptr, sb.size).to_vec();
-            let offset = sb.offset;
+        if let Some(mut sb) = bufs.remove(&mem_local_id) {
+            let (ranges, mut data) = sb.take_changes(0, sb.size, true);
+            let written = match ranges.as_slice() {
+                [] => (None, sb.offset),
+                [range] => (data.pop(), range.offset),
+                _ => {
+                    let _ = send_vulkan_command(VulkanCommand::FlushMappedMemoryRanges {
+                        device: dev_handle,
+                        ranges,
+                        data,
+                    });
+                    (None, sb.offset)
+                }
+            };
             std::alloc::dealloc(sb.ptr, sb.layout);
-            (Some(data), offset)
+            written
         } else {
             (None, 0)
         }
@@ -252,9 +529,9 @@ pub unsafe extern "C" fn vkFlushMappedMemoryRanges(
             None => continue,
         };
 
-        // Read the flushed data from shadow buffer
-        let range_data = if let Ok(bufs) = shadow_buffers().lock() {
-            if let Some(sb) = bufs.get(&mem_local_id) {
+        // Send only the blocks of the range the application changed
+        if let Ok(mut bufs) = shadow_buffers().lock() {
+            if let Some(sb) = bufs.get_mut(&mem_local_id) {
                 let flush_offset = (mr.offset - sb.offset) as usize;
                 let flush_size = if mr.size == vk::WHOLE_SIZE {
                     sb.size - flush_offset
@@ -262,21 +539,24 @@ pub unsafe extern "C" fn vkFlushMappedMemoryRanges(
                     mr.size as usize
                 };
                 let end = std::cmp::min(flush_offset + flush_size, sb.size);
-                std::slice::from_raw_parts(sb.ptr.add(flush_offset), end - flush_offset)
-                    .to_vec()
-            } else {
-                Vec::new()
+                let (changed, data) = sb.take_changes(flush_offset, end, mr.size == vk::WHOLE_SIZE);
+                ranges.extend(changed);
+                data_vec.extend(data);
+                continue;
             }
-        } else {
-            Vec::new()
-        };
+        }
 
         ranges.push(MappedMemoryRange {
             memory: mem_handle,
             offset: mr.offset,
             size: mr.size,
         });
-        data_vec.push(range_data);
+        data_vec.push(Vec::new());
+    }
+
+    // Nothing changed since the server last saw these ranges
+    if ranges.is_empty() {
+        return vk::Result::SUCCESS;
     }
 
     let cmd = VulkanCommand::FlushMappedMemoryRanges {
@@ -342,8 +622,8 @@ pub unsafe extern "C" fn vkInvalidateMappedMemoryRanges(
                 let mr = &*p_memory_ranges.add(i);
                 let mem_local_id = mr.memory.as_raw();
 
-                if let Ok(bufs) = shadow_buffers().lock() {
-                    if let Some(sb) = bufs.get(&mem_local_id) {
+                if let Ok(mut bufs) = shadow_buffers().lock() {
+                    if let Some(sb) = bufs.get_mut(&mem_local_id) {
                         let inv_offset = (mr.offset - sb.offset) as usize;
                         let copy_len = std::cmp::min(data.len(), sb.size - inv_offset);
                         std::ptr::copy_nonoverlapping(
@@ -351,6 +631,8 @@ pub unsafe extern "C" fn vkInvalidateMappedMemoryRanges(
                             sb.ptr.add(inv_offset),
                             copy_len,
                         );
+                        let shadow = std::slice::from_raw_parts(sb.ptr, sb.size);
+                        sb.dirty.sync(shadow, inv_offset, inv_offset + copy_len);
                     }
                 }
             }
@@ -398,6 +680,7 @@ pub unsafe extern "C" fn vkCreateBuffer(
         usage: ci.usage.as_raw(),
         sharing_mode: ci.sharing_mode.as_raw() as u32,
         queue_family_indices,
+        p_next: crate::pnext::read_p_next(ci.p_next),
     };
 
     match send_vulkan_command(cmd) {
@@ -431,6 +714,7 @@ pub unsafe extern "C" fn vkDestroyBuffer(
 
     let local_id = buffer.as_raw();
     if let Some(handle) = handle_store::remove_buffer(local_id) {
+        crate::access::resources(|r| r.forget(&handle));
         let _ = send_vulkan_command(VulkanCommand::DestroyBuffer {
             device: dev_handle,
             buffer: handle,
@@ -471,7 +755,10 @@ pub unsafe extern "C" fn vkBindBufferMemory(
     };
 
     match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+        Ok(VulkanResponse::Success) => {
+            crate::access::resources(|r| r.bind_memory(buf_handle, mem_handle));
+            vk::Result::SUCCESS
+        }
         Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
         _ => vk::Result::ERROR_UNKNOWN,
     }
@@ -517,3 +804,77 @@ pub unsafe extern "C" fn vkGetBufferMemoryRequirements(
         mr.memory_type_bits = memory_type_bits;
     }
 }
+
+// ── Buffer View ─────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateBufferView(
+    device: vk::Device,
+    p_create_info: *const vk::BufferViewCreateInfo<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_view: *mut vk::BufferView,
+) -> vk::Result {
+    if p_create_info.is_null() || p_view.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let ci = &*p_create_info;
+    let buf_handle = match handle_store::get_buffer(ci.buffer.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+    };
+
+    let cmd = VulkanCommand::CreateBufferView {
+        device: dev_handle,
+        buffer: buf_handle,
+        format: ci.format.as_raw(),
+        offset: ci.offset,
+        range: ci.range,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::BufferViewCreated { handle }) => {
+            crate::access::resources(|r| r.add_view(handle, buf_handle));
+            let local_id = handle_store::store_buffer_view(handle);
+            *p_view = vk::BufferView::from_raw(local_id);
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkDestroyBufferView(
+    device: vk::Device,
+    buffer_view: vk::BufferView,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    if buffer_view == vk::BufferView::null() {
+        return;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Some(handle) = handle_store::remove_buffer_view(buffer_view.as_raw()) {
+        crate::access::resources(|r| r.forget(&handle));
+        let _ = send_vulkan_command(VulkanCommand::DestroyBufferView {
+            device: dev_handle,
+            buffer_view: handle,
+        });
+    }
+}
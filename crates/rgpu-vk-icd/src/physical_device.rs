@@ -6,9 +6,12 @@ use std::os::raw::c_void;
 
 use crate::dispatch::DispatchableHandle;
 use crate::handle_store;
+use crate::pnext::{chained_feature_size, read_feature_chain, CHAIN_HEADER};
 use crate::send_vulkan_command;
 
-use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
+use rgpu_protocol::vulkan_commands::{
+    SerializedFeatureStruct, SerializedPNext, VulkanCommand, VulkanResponse,
+};
 
 // ── vkGetPhysicalDeviceProperties ───────────────────────────
 
@@ -55,8 +58,8 @@ pub unsafe extern "C" fn vkGetPhysicalDeviceProperties(
         // Write device name
         let name_bytes = device_name.as_bytes();
         let len = std::cmp::min(name_bytes.len(), props.device_name.len() - 1);
-        for i in 0..len {
-            props.device_name[i] = name_bytes[i] as std::os::raw::c_char;
+        for (dst, &byte) in props.device_name.iter_mut().zip(&name_bytes[..len]) {
+            *dst = byte as std::os::raw::c_char;
         }
         props.device_name[len] = 0;
 
@@ -151,7 +154,65 @@ pub unsafe extern "C" fn vkGetPhysicalDeviceFeatures2(
     if p_features.is_null() {
         return;
     }
-    vkGetPhysicalDeviceFeatures(physical_device, &mut (*p_features).features);
+
+    let disp = physical_device.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(disp);
+
+    let pd_handle = match handle_store::get_physical_device(local_id) {
+        Some(h) => h,
+        None => return,
+    };
+
+    // The server fills in the known structures of the chain, zeroed here
+    let chain = read_feature_chain((*p_features).p_next)
+        .into_iter()
+        .map(|s| SerializedFeatureStruct {
+            s_type: s.s_type,
+            data: vec![0; s.data.len()],
+        })
+        .collect();
+    let cmd = VulkanCommand::GetPhysicalDeviceFeatures2 {
+        physical_device: pd_handle,
+        chain,
+    };
+
+    let (features_raw, chain) = match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::PhysicalDeviceFeatures2 { features_raw, chain }) => (features_raw, chain),
+        // From a server that only knows the core features
+        Ok(VulkanResponse::PhysicalDeviceFeatures { features_raw }) => (features_raw, Vec::new()),
+        _ => return,
+    };
+    if features_raw.len() == std::mem::size_of::<vk::PhysicalDeviceFeatures>() {
+        std::ptr::copy_nonoverlapping(
+            features_raw.as_ptr(),
+            &mut (*p_features).features as *mut vk::PhysicalDeviceFeatures as *mut u8,
+            features_raw.len(),
+        );
+    }
+    let mut p_next = (*p_features).p_next as *mut vk::BaseOutStructure<'_>;
+    while !p_next.is_null() {
+        let filled = chain.iter().find(|s| s.s_type == (*p_next).s_type.as_raw());
+        if let (Some(filled), Some(size)) = (filled, chained_feature_size((*p_next).s_type)) {
+            let len = filled.data.len().min(size - CHAIN_HEADER);
+            std::ptr::copy_nonoverlapping(filled.data.as_ptr(), (p_next as *mut u8).add(CHAIN_HEADER), len);
+        }
+        p_next = (*p_next).p_next;
+    }
+
+    // Timeline semaphores are enabled on every server device that has them.
+    let mut p_next = (*p_features).p_next as *mut vk::BaseOutStructure<'_>;
+    while !p_next.is_null() {
+        match (*p_next).s_type {
+            vk::StructureType::PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES => {
+                (*(p_next as *mut vk::PhysicalDeviceTimelineSemaphoreFeatures<'_>)).timeline_semaphore = vk::TRUE;
+            }
+            vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES => {
+                (*(p_next as *mut vk::PhysicalDeviceVulkan12Features<'_>)).timeline_semaphore = vk::TRUE;
+            }
+            _ => {}
+        }
+        p_next = (*p_next).p_next;
+    }
 }
 
 #[no_mangle]
@@ -273,8 +334,7 @@ pub unsafe extern "C" fn vkGetPhysicalDeviceQueueFamilyProperties(
         let requested = *p_queue_family_property_count as usize;
         let count = std::cmp::min(requested, families.len());
 
-        for i in 0..count {
-            let qf = &families[i];
+        for (i, qf) in families.iter().enumerate().take(count) {
             let dst = &mut *p_queue_family_properties.add(i);
             dst.queue_flags = vk::QueueFlags::from_raw(qf.queue_flags);
             dst.queue_count = qf.queue_count;
@@ -319,8 +379,8 @@ pub unsafe extern "C" fn vkGetPhysicalDeviceQueueFamilyProperties2(
     );
 
     let fill = std::cmp::min(count as usize, *p_queue_family_property_count as usize);
-    for i in 0..fill {
-        (*p_queue_family_properties.add(i)).queue_family_properties = core_props[i];
+    for (i, props) in core_props.iter().enumerate().take(fill) {
+        (*p_queue_family_properties.add(i)).queue_family_properties = *props;
     }
     *p_queue_family_property_count = count;
 }
@@ -404,6 +464,131 @@ pub unsafe extern "C" fn vkGetPhysicalDeviceFormatProperties2KHR(
     vkGetPhysicalDeviceFormatProperties2(physical_device, format, p_format_properties);
 }
 
+// ── vkGetPhysicalDeviceImageFormatProperties ───────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceImageFormatProperties(
+    physical_device: vk::PhysicalDevice,
+    format: vk::Format,
+    type_: vk::ImageType,
+    tiling: vk::ImageTiling,
+    usage: vk::ImageUsageFlags,
+    flags: vk::ImageCreateFlags,
+    p_image_format_properties: *mut vk::ImageFormatProperties,
+) -> vk::Result {
+    query_image_format_properties(
+        physical_device,
+        format,
+        type_,
+        tiling,
+        usage,
+        flags,
+        Vec::new(),
+        p_image_format_properties,
+    )
+}
+
+// ── vkGetPhysicalDeviceImageFormatProperties2 ──────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceImageFormatProperties2(
+    physical_device: vk::PhysicalDevice,
+    p_image_format_info: *const vk::PhysicalDeviceImageFormatInfo2<'_>,
+    p_image_format_properties: *mut vk::ImageFormatProperties2<'_>,
+) -> vk::Result {
+    if p_image_format_info.is_null() || p_image_format_properties.is_null() {
+        return vk::Result::ERROR_INITIALIZATION_FAILED;
+    }
+    let info = &*p_image_format_info;
+    query_image_format_properties(
+        physical_device,
+        info.format,
+        info.ty,
+        info.tiling,
+        info.usage,
+        info.flags,
+        crate::pnext::read_p_next(info.p_next),
+        &mut (*p_image_format_properties).image_format_properties,
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceImageFormatProperties2KHR(
+    physical_device: vk::PhysicalDevice,
+    p_image_format_info: *const vk::PhysicalDeviceImageFormatInfo2<'_>,
+    p_image_format_properties: *mut vk::ImageFormatProperties2<'_>,
+) -> vk::Result {
+    vkGetPhysicalDeviceImageFormatProperties2(
+        physical_device,
+        p_image_format_info,
+        p_image_format_properties,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn query_image_format_properties(
+    physical_device: vk::PhysicalDevice,
+    format: vk::Format,
+    image_type: vk::ImageType,
+    tiling: vk::ImageTiling,
+    usage: vk::ImageUsageFlags,
+    flags: vk::ImageCreateFlags,
+    p_next: Vec<SerializedPNext>,
+    p_image_format_properties: *mut vk::ImageFormatProperties,
+) -> vk::Result {
+    if p_image_format_properties.is_null() {
+        return vk::Result::ERROR_INITIALIZATION_FAILED;
+    }
+
+    let disp = physical_device.as_raw() as *const DispatchableHandle;
+    let local_id = DispatchableHandle::get_id(disp);
+
+    let pd_handle = match handle_store::get_physical_device(local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+    };
+
+    let cmd = VulkanCommand::GetPhysicalDeviceImageFormatProperties {
+        physical_device: pd_handle,
+        format: format.as_raw(),
+        image_type: image_type.as_raw(),
+        tiling: tiling.as_raw(),
+        usage: usage.as_raw(),
+        flags: flags.as_raw(),
+        p_next,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::ImageFormatProperties {
+            max_extent,
+            max_mip_levels,
+            max_array_layers,
+            sample_counts,
+            max_resource_size,
+        }) => {
+            *p_image_format_properties = vk::ImageFormatProperties {
+                max_extent: vk::Extent3D {
+                    width: max_extent[0],
+                    height: max_extent[1],
+                    depth: max_extent[2],
+                },
+                max_mip_levels,
+                max_array_layers,
+                sample_counts: vk::SampleCountFlags::from_raw(sample_counts),
+                max_resource_size,
+            };
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => {
+            // The spec wants the properties zeroed when the combination
+            // isn't supported
+            *p_image_format_properties = vk::ImageFormatProperties::default();
+            vk::Result::from_raw(code)
+        }
+        _ => vk::Result::ERROR_FORMAT_NOT_SUPPORTED,
+    }
+}
+
 // ── Sparse image support stubs ─────────────────────────────
 
 #[no_mangle]
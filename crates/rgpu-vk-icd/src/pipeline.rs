@@ -110,11 +110,26 @@ pub unsafe extern "C" fn vkCreateDescriptorSetLayout(
     if !ci.p_bindings.is_null() {
         for i in 0..ci.binding_count as usize {
             let b = &*ci.p_bindings.add(i);
+            // pImmutableSamplers is only read for sampler bindings
+            let mut immutable_samplers = Vec::new();
+            if !b.p_immutable_samplers.is_null()
+                && (b.descriptor_type == vk::DescriptorType::SAMPLER
+                    || b.descriptor_type == vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
+            {
+                for j in 0..b.descriptor_count as usize {
+                    let s = *b.p_immutable_samplers.add(j);
+                    match handle_store::get_sampler(s.as_raw()) {
+                        Some(h) => immutable_samplers.push(h),
+                        None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+                    }
+                }
+            }
             bindings.push(SerializedDescriptorSetLayoutBinding {
                 binding: b.binding,
                 descriptor_type: b.descriptor_type.as_raw(),
                 descriptor_count: b.descriptor_count,
                 stage_flags: b.stage_flags.as_raw(),
+                immutable_samplers,
             });
         }
     }
@@ -260,7 +275,7 @@ pub unsafe extern "C" fn vkDestroyPipelineLayout(
 #[no_mangle]
 pub unsafe extern "C" fn vkCreateComputePipelines(
     device: vk::Device,
-    _pipeline_cache: vk::PipelineCache,
+    pipeline_cache: vk::PipelineCache,
     create_info_count: u32,
     p_create_infos: *const vk::ComputePipelineCreateInfo<'_>,
     _p_allocator: *const vk::AllocationCallbacks<'_>,
@@ -318,6 +333,7 @@ pub unsafe extern "C" fn vkCreateComputePipelines(
 
     let cmd = VulkanCommand::CreateComputePipelines {
         device: dev_handle,
+        pipeline_cache: handle_store::get_pipeline_cache(pipeline_cache.as_raw()),
         create_infos,
     };
 
@@ -360,3 +376,164 @@ pub unsafe extern "C" fn vkDestroyPipeline(
         });
     }
 }
+
+// ── Pipeline Cache ──────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreatePipelineCache(
+    device: vk::Device,
+    p_create_info: *const vk::PipelineCacheCreateInfo<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_pipeline_cache: *mut vk::PipelineCache,
+) -> vk::Result {
+    if p_create_info.is_null() || p_pipeline_cache.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let ci = &*p_create_info;
+    let initial_data = if !ci.p_initial_data.is_null() && ci.initial_data_size > 0 {
+        std::slice::from_raw_parts(ci.p_initial_data as *const u8, ci.initial_data_size).to_vec()
+    } else {
+        Vec::new()
+    };
+
+    let cmd = VulkanCommand::CreatePipelineCache {
+        device: dev_handle,
+        initial_data,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::PipelineCacheCreated { handle }) => {
+            let local_id = handle_store::store_pipeline_cache(handle);
+            *p_pipeline_cache = vk::PipelineCache::from_raw(local_id);
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkDestroyPipelineCache(
+    device: vk::Device,
+    pipeline_cache: vk::PipelineCache,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    if pipeline_cache == vk::PipelineCache::null() {
+        return;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Some(handle) = handle_store::remove_pipeline_cache(pipeline_cache.as_raw()) {
+        let _ = send_vulkan_command(VulkanCommand::DestroyPipelineCache {
+            device: dev_handle,
+            pipeline_cache: handle,
+        });
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPipelineCacheData(
+    device: vk::Device,
+    pipeline_cache: vk::PipelineCache,
+    p_data_size: *mut usize,
+    p_data: *mut std::ffi::c_void,
+) -> vk::Result {
+    if p_data_size.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+    let cache_handle = match handle_store::get_pipeline_cache(pipeline_cache.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+    };
+
+    let cmd = VulkanCommand::GetPipelineCacheData {
+        device: dev_handle,
+        pipeline_cache: cache_handle,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::PipelineCacheData { data }) => {
+            if p_data.is_null() {
+                *p_data_size = data.len();
+                return vk::Result::SUCCESS;
+            }
+            // Cache data is only usable whole, so a short buffer gets none
+            if *p_data_size < data.len() {
+                *p_data_size = 0;
+                return vk::Result::INCOMPLETE;
+            }
+            std::ptr::copy_nonoverlapping(data.as_ptr(), p_data as *mut u8, data.len());
+            *p_data_size = data.len();
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkMergePipelineCaches(
+    device: vk::Device,
+    dst_cache: vk::PipelineCache,
+    src_cache_count: u32,
+    p_src_caches: *const vk::PipelineCache,
+) -> vk::Result {
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+    let dst_handle = match handle_store::get_pipeline_cache(dst_cache.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+    };
+
+    let mut src_caches = Vec::new();
+    if !p_src_caches.is_null() {
+        for i in 0..src_cache_count as usize {
+            let src = *p_src_caches.add(i);
+            match handle_store::get_pipeline_cache(src.as_raw()) {
+                Some(h) => src_caches.push(h),
+                None => return vk::Result::ERROR_INITIALIZATION_FAILED,
+            }
+        }
+    }
+
+    let cmd = VulkanCommand::MergePipelineCaches {
+        device: dev_handle,
+        dst_cache: dst_handle,
+        src_caches,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_OUT_OF_HOST_MEMORY,
+    }
+}
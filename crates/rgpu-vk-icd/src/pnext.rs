@@ -0,0 +1,235 @@
+//! pNext chains of the structures passed to the ICD, read into the form
+//! they travel in.
+
+use ash::vk;
+use std::os::raw::c_void;
+
+use rgpu_protocol::vulkan_commands::{SerializedFeatureStruct, SerializedPNext};
+
+/// sType and pNext, which come before the members of every structure in a
+/// pNext chain.
+pub(crate) const CHAIN_HEADER: usize = std::mem::size_of::<vk::BaseOutStructure<'static>>();
+
+/// Size of the feature structures passed through to the server in
+/// vkGetPhysicalDeviceFeatures2 and vkCreateDevice. Only features the ICD
+/// can carry out are listed; others read as unsupported.
+pub(crate) fn chained_feature_size(s_type: vk::StructureType) -> Option<usize> {
+    use std::mem::size_of;
+    Some(match s_type {
+        vk::StructureType::PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES => {
+            size_of::<vk::PhysicalDeviceDescriptorIndexingFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_1_FEATURES => {
+            size_of::<vk::PhysicalDeviceVulkan11Features<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_2_FEATURES => {
+            size_of::<vk::PhysicalDeviceVulkan12Features<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_FEATURES => {
+            size_of::<vk::PhysicalDeviceVulkan13Features<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES => {
+            size_of::<vk::PhysicalDeviceSynchronization2Features<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_DYNAMIC_RENDERING_FEATURES => {
+            size_of::<vk::PhysicalDeviceDynamicRenderingFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_TIMELINE_SEMAPHORE_FEATURES => {
+            size_of::<vk::PhysicalDeviceTimelineSemaphoreFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_8BIT_STORAGE_FEATURES => {
+            size_of::<vk::PhysicalDevice8BitStorageFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_16BIT_STORAGE_FEATURES => {
+            size_of::<vk::PhysicalDevice16BitStorageFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_SHADER_FLOAT16_INT8_FEATURES => {
+            size_of::<vk::PhysicalDeviceShaderFloat16Int8Features<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_SCALAR_BLOCK_LAYOUT_FEATURES => {
+            size_of::<vk::PhysicalDeviceScalarBlockLayoutFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_UNIFORM_BUFFER_STANDARD_LAYOUT_FEATURES => {
+            size_of::<vk::PhysicalDeviceUniformBufferStandardLayoutFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_VULKAN_MEMORY_MODEL_FEATURES => {
+            size_of::<vk::PhysicalDeviceVulkanMemoryModelFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_SHADER_DRAW_PARAMETERS_FEATURES => {
+            size_of::<vk::PhysicalDeviceShaderDrawParametersFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_SHADER_SUBGROUP_EXTENDED_TYPES_FEATURES => {
+            size_of::<vk::PhysicalDeviceShaderSubgroupExtendedTypesFeatures<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_VARIABLE_POINTERS_FEATURES => {
+            size_of::<vk::PhysicalDeviceVariablePointersFeatures<'static>>()
+        }
+        _ => return None,
+    })
+}
+
+/// The known feature structures of a pNext chain, with their members.
+pub(crate) unsafe fn read_feature_chain(p_next: *const c_void) -> Vec<SerializedFeatureStruct> {
+    let mut chain = Vec::new();
+    let mut p_next = p_next as *const vk::BaseInStructure<'_>;
+    while !p_next.is_null() {
+        if let Some(size) = chained_feature_size((*p_next).s_type) {
+            chain.push(SerializedFeatureStruct {
+                s_type: (*p_next).s_type.as_raw(),
+                data: std::slice::from_raw_parts((p_next as *const u8).add(CHAIN_HEADER), size - CHAIN_HEADER)
+                    .to_vec(),
+            });
+        }
+        p_next = (*p_next).p_next;
+    }
+    chain
+}
+
+/// The structure of type `s_type` in a pNext chain, if there is one.
+pub(crate) unsafe fn find_in_chain<'a, T>(mut p_next: *const c_void, s_type: vk::StructureType) -> Option<&'a T> {
+    while !p_next.is_null() {
+        let base = &*(p_next as *const vk::BaseInStructure<'_>);
+        if base.s_type == s_type {
+            return Some(&*(p_next as *const T));
+        }
+        p_next = base.p_next as *const c_void;
+    }
+    None
+}
+
+/// Size of the structures without pointers that are passed through to the
+/// server as they are: the feature structures above and the extension
+/// structures of VkBufferCreateInfo, VkImageCreateInfo, VkSubmitInfo and
+/// VkPhysicalDeviceImageFormatInfo2.
+fn raw_struct_size(s_type: vk::StructureType) -> Option<usize> {
+    use std::mem::size_of;
+    Some(match s_type {
+        vk::StructureType::EXTERNAL_MEMORY_BUFFER_CREATE_INFO => {
+            size_of::<vk::ExternalMemoryBufferCreateInfo<'static>>()
+        }
+        vk::StructureType::BUFFER_OPAQUE_CAPTURE_ADDRESS_CREATE_INFO => {
+            size_of::<vk::BufferOpaqueCaptureAddressCreateInfo<'static>>()
+        }
+        vk::StructureType::EXTERNAL_MEMORY_IMAGE_CREATE_INFO => {
+            size_of::<vk::ExternalMemoryImageCreateInfo<'static>>()
+        }
+        vk::StructureType::PHYSICAL_DEVICE_EXTERNAL_IMAGE_FORMAT_INFO => {
+            size_of::<vk::PhysicalDeviceExternalImageFormatInfo<'static>>()
+        }
+        vk::StructureType::IMAGE_STENCIL_USAGE_CREATE_INFO => {
+            size_of::<vk::ImageStencilUsageCreateInfo<'static>>()
+        }
+        vk::StructureType::DEDICATED_ALLOCATION_IMAGE_CREATE_INFO_NV => {
+            size_of::<vk::DedicatedAllocationImageCreateInfoNV<'static>>()
+        }
+        vk::StructureType::DEDICATED_ALLOCATION_BUFFER_CREATE_INFO_NV => {
+            size_of::<vk::DedicatedAllocationBufferCreateInfoNV<'static>>()
+        }
+        vk::StructureType::PROTECTED_SUBMIT_INFO => size_of::<vk::ProtectedSubmitInfo<'static>>(),
+        vk::StructureType::PERFORMANCE_QUERY_SUBMIT_INFO_KHR => {
+            size_of::<vk::PerformanceQuerySubmitInfoKHR<'static>>()
+        }
+        s_type => return chained_feature_size(s_type),
+    })
+}
+
+/// The structures of a pNext chain that can be sent to the server. Those
+/// the ICD handles itself, like VkPhysicalDeviceFeatures2 or
+/// VkTimelineSemaphoreSubmitInfo, are skipped, and so are unknown ones:
+/// their size, and what their pointers lead to, can't be known.
+pub(crate) unsafe fn read_p_next(p_next: *const c_void) -> Vec<SerializedPNext> {
+    let mut chain = Vec::new();
+    let mut p_next = p_next as *const vk::BaseInStructure<'_>;
+    while !p_next.is_null() {
+        let s_type = (*p_next).s_type;
+        if s_type == vk::StructureType::IMAGE_FORMAT_LIST_CREATE_INFO {
+            let info = &*(p_next as *const vk::ImageFormatListCreateInfo<'_>);
+            let view_formats = if info.view_format_count == 0 || info.p_view_formats.is_null() {
+                Vec::new()
+            } else {
+                std::slice::from_raw_parts(info.p_view_formats, info.view_format_count as usize)
+                    .iter()
+                    .map(|f| f.as_raw())
+                    .collect()
+            };
+            chain.push(SerializedPNext::ImageFormatList { view_formats });
+        } else if let Some(size) = raw_struct_size(s_type) {
+            chain.push(SerializedPNext::Raw {
+                s_type: s_type.as_raw(),
+                data: std::slice::from_raw_parts((p_next as *const u8).add(CHAIN_HEADER), size - CHAIN_HEADER)
+                    .to_vec(),
+            });
+        }
+        p_next = (*p_next).p_next;
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_structures_are_read_and_others_skipped() {
+        let view_formats = [vk::Format::R8G8B8A8_UNORM, vk::Format::R8G8B8A8_SRGB];
+        let mut format_list = vk::ImageFormatListCreateInfo::default().view_formats(&view_formats);
+        let mut external = vk::ExternalMemoryImageCreateInfo::default()
+            .handle_types(vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD);
+        // Unknown, and it holds a pointer, so it is left out
+        let mut compression = vk::ImageCompressionControlEXT::default();
+        let info = vk::ImageCreateInfo::default()
+            .push_next(&mut format_list)
+            .push_next(&mut compression)
+            .push_next(&mut external);
+
+        let chain = unsafe { read_p_next(info.p_next) };
+        assert_eq!(chain.len(), 2);
+        match &chain[0] {
+            SerializedPNext::Raw { s_type, data } => {
+                assert_eq!(*s_type, vk::StructureType::EXTERNAL_MEMORY_IMAGE_CREATE_INFO.as_raw());
+                assert_eq!(data[..4], vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD.as_raw().to_ne_bytes());
+            }
+            other => panic!("expected the external memory info, got {:?}", other),
+        }
+        match &chain[1] {
+            SerializedPNext::ImageFormatList { view_formats } => assert_eq!(view_formats, &[37, 43]),
+            other => panic!("expected the format list, got {:?}", other),
+        }
+
+        assert!(unsafe { read_p_next(std::ptr::null()) }.is_empty());
+    }
+
+    #[test]
+    fn test_feature_chain_keeps_the_members() {
+        let mut indexing = vk::PhysicalDeviceDescriptorIndexingFeatures::default().runtime_descriptor_array(true);
+        let mut sync2 = vk::PhysicalDeviceSynchronization2Features::default().synchronization2(true);
+        // Not one of the features passed through
+        let mut robustness = vk::PhysicalDeviceRobustness2FeaturesEXT::default().null_descriptor(true);
+        let features = vk::PhysicalDeviceFeatures2::default()
+            .push_next(&mut indexing)
+            .push_next(&mut robustness)
+            .push_next(&mut sync2);
+
+        let chain = unsafe { read_feature_chain(features.p_next) };
+        let s_types: Vec<i32> = chain.iter().map(|s| s.s_type).collect();
+        assert_eq!(
+            s_types,
+            [
+                vk::StructureType::PHYSICAL_DEVICE_SYNCHRONIZATION_2_FEATURES.as_raw(),
+                vk::StructureType::PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES.as_raw(),
+            ]
+        );
+        // synchronization2, set, then padding
+        assert_eq!(chain[0].data[..4], vk::TRUE.to_ne_bytes());
+        let indexing_size = std::mem::size_of::<vk::PhysicalDeviceDescriptorIndexingFeatures<'static>>();
+        assert_eq!(chain[1].data.len(), indexing_size - CHAIN_HEADER);
+
+        let found: Option<&vk::PhysicalDeviceDescriptorIndexingFeatures<'_>> = unsafe {
+            find_in_chain(features.p_next, vk::StructureType::PHYSICAL_DEVICE_DESCRIPTOR_INDEXING_FEATURES)
+        };
+        assert_eq!(found.map(|f| f.runtime_descriptor_array), Some(vk::TRUE));
+        let missing: Option<&vk::PhysicalDeviceVulkan13Features<'_>> =
+            unsafe { find_in_chain(features.p_next, vk::StructureType::PHYSICAL_DEVICE_VULKAN_1_3_FEATURES) };
+        assert!(missing.is_none());
+    }
+}
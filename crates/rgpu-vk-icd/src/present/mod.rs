@@ -0,0 +1,71 @@
+//! Showing presented frames in the application's window.
+//!
+//! The server renders into a virtual swapchain and sends every presented
+//! image back; this module puts it on screen with the local window system:
+//! X11 through libxcb, Wayland through wl_shm buffers, and Win32 through GDI.
+//! The libraries are loaded when the first swapchain is created, so the ICD
+//! has no link-time dependency on any of them.
+
+use std::ffi::{c_ulong, c_void};
+
+#[cfg(target_os = "linux")]
+mod wayland;
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+/// A window surface, as the application described it.
+#[derive(Debug, Clone, Copy)]
+pub enum Window {
+    Xcb { connection: *mut c_void, window: u32 },
+    Xlib { display: *mut c_void, window: c_ulong },
+    Wayland { display: *mut c_void, surface: *mut c_void },
+    Win32 { hwnd: *mut c_void },
+}
+
+// SAFETY: the window system handles are only used through libraries that
+// allow calls from any thread.
+unsafe impl Send for Window {}
+unsafe impl Sync for Window {}
+
+/// Shows frames in one window.
+pub trait Present: Send {
+    /// Show a `width` x `height` frame of BGRA pixels, rows tightly packed.
+    fn show(&mut self, width: u32, height: u32, pixels: &[u8]) -> Result<(), String>;
+}
+
+impl Window {
+    /// Size of the window's drawable area, if the window system has one
+    /// before anything is shown (Wayland takes its size from the buffers).
+    pub fn extent(&self) -> Option<(u32, u32)> {
+        match *self {
+            #[cfg(target_os = "linux")]
+            Window::Xcb { connection, window } => x11::extent(x11::Connection::Xcb(connection), window),
+            #[cfg(target_os = "linux")]
+            Window::Xlib { display, window } => x11::extent(x11::Connection::Xlib(display), window as u32),
+            #[cfg(windows)]
+            Window::Win32 { hwnd } => win32::extent(hwnd),
+            _ => None,
+        }
+    }
+
+    /// A presenter for this window.
+    pub fn presenter(&self) -> Result<Box<dyn Present>, String> {
+        match *self {
+            #[cfg(target_os = "linux")]
+            Window::Xcb { connection, window } => {
+                Ok(Box::new(x11::Presenter::new(x11::Connection::Xcb(connection), window)?))
+            }
+            #[cfg(target_os = "linux")]
+            Window::Xlib { display, window } => {
+                Ok(Box::new(x11::Presenter::new(x11::Connection::Xlib(display), window as u32)?))
+            }
+            #[cfg(target_os = "linux")]
+            Window::Wayland { display, surface } => Ok(Box::new(wayland::Presenter::new(display, surface)?)),
+            #[cfg(windows)]
+            Window::Win32 { hwnd } => Ok(Box::new(win32::Presenter::new(hwnd))),
+            other => Err(format!("{:?} windows aren't supported on this platform", other)),
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Wayland: frames are copied into wl_shm buffers that are attached to the
+//! application's surface. The registry and buffer releases are handled on a
+//! private event queue, so the application's own event loop never sees them.
+
+use std::ffi::{c_char, c_int, c_void, CStr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+
+use libloading::{Library, Symbol};
+use tracing::info;
+
+use super::Present;
+
+const DISPLAY_GET_REGISTRY: u32 = 1;
+const REGISTRY_BIND: u32 = 0;
+const SHM_CREATE_POOL: u32 = 0;
+const SHM_POOL_CREATE_BUFFER: u32 = 0;
+const SHM_POOL_DESTROY: u32 = 1;
+const BUFFER_DESTROY: u32 = 0;
+const SURFACE_ATTACH: u32 = 1;
+const SURFACE_DAMAGE: u32 = 2;
+const SURFACE_COMMIT: u32 = 6;
+/// `WL_MARSHAL_FLAG_DESTROY`
+const MARSHAL_DESTROY: u32 = 1;
+/// `WL_SHM_FORMAT_XRGB8888`: BGRX in memory
+const FORMAT_XRGB8888: u32 = 1;
+/// Buffers per surface before waiting for the compositor to release one
+const MAX_BUFFERS: usize = 3;
+
+type FnMarshalFlags =
+    unsafe extern "C" fn(proxy: *mut c_void, opcode: u32, interface: *const c_void, version: u32, flags: u32, ...) -> *mut c_void;
+type FnAddListener =
+    unsafe extern "C" fn(proxy: *mut c_void, implementation: *const c_void, data: *mut c_void) -> c_int;
+type FnGetVersion = unsafe extern "C" fn(proxy: *mut c_void) -> u32;
+type FnCreateWrapper = unsafe extern "C" fn(proxy: *mut c_void) -> *mut c_void;
+type FnSetQueue = unsafe extern "C" fn(proxy: *mut c_void, queue: *mut c_void);
+type FnDestroy = unsafe extern "C" fn(object: *mut c_void);
+type FnCreateQueue = unsafe extern "C" fn(display: *mut c_void) -> *mut c_void;
+type FnQueueCall = unsafe extern "C" fn(display: *mut c_void, queue: *mut c_void) -> c_int;
+type FnFlush = unsafe extern "C" fn(display: *mut c_void) -> c_int;
+
+struct Client {
+    _lib: Library,
+    marshal_flags: FnMarshalFlags,
+    add_listener: FnAddListener,
+    get_version: FnGetVersion,
+    create_wrapper: FnCreateWrapper,
+    wrapper_destroy: FnDestroy,
+    set_queue: FnSetQueue,
+    proxy_destroy: FnDestroy,
+    create_queue: FnCreateQueue,
+    queue_destroy: FnDestroy,
+    roundtrip_queue: FnQueueCall,
+    dispatch_queue_pending: FnQueueCall,
+    flush: FnFlush,
+    registry_interface: *const c_void,
+    shm_interface: *const c_void,
+    shm_pool_interface: *const c_void,
+    buffer_interface: *const c_void,
+}
+
+// SAFETY: the interface pointers point to immutable data in libwayland-client.
+unsafe impl Send for Client {}
+unsafe impl Sync for Client {}
+
+impl Client {
+    fn load() -> Option<Self> {
+        let lib = ["libwayland-client.so.0", "libwayland-client.so"]
+            .iter()
+            .find_map(|name| unsafe { Library::new(name).ok() })?;
+        unsafe {
+            let client = Self {
+                // Since libwayland 1.20
+                marshal_flags: Self::sym(&lib, "wl_proxy_marshal_flags")?,
+                add_listener: Self::sym(&lib, "wl_proxy_add_listener")?,
+                get_version: Self::sym(&lib, "wl_proxy_get_version")?,
+                create_wrapper: Self::sym(&lib, "wl_proxy_create_wrapper")?,
+                wrapper_destroy: Self::sym(&lib, "wl_proxy_wrapper_destroy")?,
+                set_queue: Self::sym(&lib, "wl_proxy_set_queue")?,
+                proxy_destroy: Self::sym(&lib, "wl_proxy_destroy")?,
+                create_queue: Self::sym(&lib, "wl_display_create_queue")?,
+                queue_destroy: Self::sym(&lib, "wl_event_queue_destroy")?,
+                roundtrip_queue: Self::sym(&lib, "wl_display_roundtrip_queue")?,
+                dispatch_queue_pending: Self::sym(&lib, "wl_display_dispatch_queue_pending")?,
+                flush: Self::sym(&lib, "wl_display_flush")?,
+                registry_interface: Self::sym(&lib, "wl_registry_interface")?,
+                shm_interface: Self::sym(&lib, "wl_shm_interface")?,
+                shm_pool_interface: Self::sym(&lib, "wl_shm_pool_interface")?,
+                buffer_interface: Self::sym(&lib, "wl_buffer_interface")?,
+                _lib: lib,
+            };
+            info!("loaded libwayland-client for presentation");
+            Some(client)
+        }
+    }
+
+    unsafe fn sym<F: Copy>(lib: &Library, name: &str) -> Option<F> {
+        lib.get(name.as_bytes()).ok().map(|s: Symbol<F>| *s)
+    }
+}
+
+fn client() -> Result<&'static Client, String> {
+    static CLIENT: OnceLock<Option<Client>> = OnceLock::new();
+    CLIENT
+        .get_or_init(Client::load)
+        .as_ref()
+        .ok_or_else(|| "libwayland-client 1.20 or later not found".to_string())
+}
+
+#[repr(C)]
+struct RegistryListener {
+    global: unsafe extern "C" fn(*mut c_void, *mut c_void, u32, *const c_char, u32),
+    global_remove: unsafe extern "C" fn(*mut c_void, *mut c_void, u32),
+}
+
+/// Records the name and version of the `wl_shm` global in `data`.
+unsafe extern "C" fn registry_global(data: *mut c_void, _: *mut c_void, name: u32, interface: *const c_char, version: u32) {
+    if CStr::from_ptr(interface).to_bytes() == b"wl_shm" {
+        *(data as *mut Option<(u32, u32)>) = Some((name, version));
+    }
+}
+
+unsafe extern "C" fn registry_global_remove(_: *mut c_void, _: *mut c_void, _: u32) {}
+
+static REGISTRY_LISTENER: RegistryListener = RegistryListener {
+    global: registry_global,
+    global_remove: registry_global_remove,
+};
+
+#[repr(C)]
+struct BufferListener {
+    release: unsafe extern "C" fn(*mut c_void, *mut c_void),
+}
+
+/// Marks the buffer whose busy flag is `data` free.
+unsafe extern "C" fn buffer_release(data: *mut c_void, _: *mut c_void) {
+    (*(data as *const AtomicBool)).store(false, Ordering::Release);
+}
+
+static BUFFER_LISTENER: BufferListener = BufferListener { release: buffer_release };
+
+/// A wl_buffer and the shared memory behind it.
+struct Buffer {
+    client: &'static Client,
+    pool: *mut c_void,
+    buffer: *mut c_void,
+    data: *mut u8,
+    size: usize,
+    width: u32,
+    height: u32,
+    /// Attached and not released by the compositor yet
+    busy: Box<AtomicBool>,
+}
+
+impl Buffer {
+    unsafe fn new(client: &'static Client, shm: *mut c_void, width: u32, height: u32) -> Result<Self, String> {
+        let stride = width as usize * 4;
+        let size = stride * height as usize;
+        let fd = libc::memfd_create(c"rgpu-frame".as_ptr(), libc::MFD_CLOEXEC);
+        if fd < 0 {
+            return Err(format!("memfd_create: {}", std::io::Error::last_os_error()));
+        }
+        if libc::ftruncate(fd, size as libc::off_t) < 0 {
+            let error = std::io::Error::last_os_error();
+            libc::close(fd);
+            return Err(format!("ftruncate: {}", error));
+        }
+        let data = libc::mmap(
+            std::ptr::null_mut(),
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            fd,
+            0,
+        );
+        if data == libc::MAP_FAILED {
+            let error = std::io::Error::last_os_error();
+            libc::close(fd);
+            return Err(format!("mmap: {}", error));
+        }
+
+        // The fd is duplicated when the request is queued.
+        let pool = (client.marshal_flags)(
+            shm,
+            SHM_CREATE_POOL,
+            client.shm_pool_interface,
+            (client.get_version)(shm),
+            0,
+            std::ptr::null_mut::<c_void>(),
+            fd,
+            size as i32,
+        );
+        libc::close(fd);
+        let buffer = (client.marshal_flags)(
+            pool,
+            SHM_POOL_CREATE_BUFFER,
+            client.buffer_interface,
+            (client.get_version)(pool),
+            0,
+            std::ptr::null_mut::<c_void>(),
+            0i32,
+            width as i32,
+            height as i32,
+            stride as i32,
+            FORMAT_XRGB8888,
+        );
+        let busy = Box::new(AtomicBool::new(false));
+        (client.add_listener)(
+            buffer,
+            &BUFFER_LISTENER as *const BufferListener as *const c_void,
+            &*busy as *const AtomicBool as *mut c_void,
+        );
+        Ok(Self {
+            client,
+            pool,
+            buffer,
+            data: data as *mut u8,
+            size,
+            width,
+            height,
+            busy,
+        })
+    }
+}
+
+impl Drop for Buffer {
+    fn drop(&mut self) {
+        let client = self.client;
+        unsafe {
+            (client.marshal_flags)(self.buffer, BUFFER_DESTROY, std::ptr::null(), (client.get_version)(self.buffer), MARSHAL_DESTROY);
+            (client.marshal_flags)(self.pool, SHM_POOL_DESTROY, std::ptr::null(), (client.get_version)(self.pool), MARSHAL_DESTROY);
+            libc::munmap(self.data as *mut c_void, self.size);
+        }
+    }
+}
+
+pub struct Presenter {
+    client: &'static Client,
+    display: *mut c_void,
+    surface: *mut c_void,
+    queue: *mut c_void,
+    shm: *mut c_void,
+    buffers: Vec<Buffer>,
+}
+
+// SAFETY: libwayland-client proxies may be used from any thread.
+unsafe impl Send for Presenter {}
+
+impl Presenter {
+    pub fn new(display: *mut c_void, surface: *mut c_void) -> Result<Self, String> {
+        let client = client()?;
+        unsafe {
+            let queue = (client.create_queue)(display);
+            if queue.is_null() {
+                return Err("can't create a Wayland event queue".to_string());
+            }
+            // Objects made through the wrapper deliver their events to `queue`.
+            let wrapper = (client.create_wrapper)(display);
+            if wrapper.is_null() {
+                (client.queue_destroy)(queue);
+                return Err("can't wrap the Wayland display".to_string());
+            }
+            (client.set_queue)(wrapper, queue);
+            let registry = (client.marshal_flags)(
+                wrapper,
+                DISPLAY_GET_REGISTRY,
+                client.registry_interface,
+                (client.get_version)(wrapper),
+                0,
+                std::ptr::null_mut::<c_void>(),
+            );
+            (client.wrapper_destroy)(wrapper);
+
+            let mut shm_global: Option<(u32, u32)> = None;
+            (client.add_listener)(
+                registry,
+                &REGISTRY_LISTENER as *const RegistryListener as *const c_void,
+                &mut shm_global as *mut Option<(u32, u32)> as *mut c_void,
+            );
+            (client.roundtrip_queue)(display, queue);
+            let shm = shm_global.map(|(name, _)| {
+                // The interface's name is its first field.
+                let interface_name = *(client.shm_interface as *const *const c_char);
+                (client.marshal_flags)(
+                    registry,
+                    REGISTRY_BIND,
+                    client.shm_interface,
+                    1,
+                    0,
+                    name,
+                    interface_name,
+                    1u32,
+                    std::ptr::null_mut::<c_void>(),
+                )
+            });
+            (client.proxy_destroy)(registry);
+
+            match shm {
+                Some(shm) if !shm.is_null() => Ok(Self {
+                    client,
+                    display,
+                    surface,
+                    queue,
+                    shm,
+                    buffers: Vec::new(),
+                }),
+                _ => {
+                    (client.queue_destroy)(queue);
+                    Err("the Wayland compositor has no wl_shm".to_string())
+                }
+            }
+        }
+    }
+
+    fn free_buffer(&self, width: u32, height: u32) -> Option<usize> {
+        self.buffers
+            .iter()
+            .position(|b| (b.width, b.height) == (width, height) && !b.busy.load(Ordering::Acquire))
+    }
+}
+
+impl Present for Presenter {
+    fn show(&mut self, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+        let size = width as usize * height as usize * 4;
+        if size == 0 || pixels.len() < size {
+            return Err(format!("{} bytes is not a {}x{} frame", pixels.len(), width, height));
+        }
+        let client = self.client;
+        unsafe {
+            (client.dispatch_queue_pending)(self.display, self.queue);
+            // Buffers of another size go once the compositor is done with them.
+            self.buffers
+                .retain(|b| (b.width, b.height) == (width, height) || b.busy.load(Ordering::Acquire));
+
+            let mut index = self.free_buffer(width, height);
+            if index.is_none() && self.buffers.len() >= MAX_BUFFERS {
+                (client.roundtrip_queue)(self.display, self.queue);
+                index = self.free_buffer(width, height);
+            }
+            let index = match index {
+                Some(index) => index,
+                None => {
+                    self.buffers.push(Buffer::new(client, self.shm, width, height)?);
+                    self.buffers.len() - 1
+                }
+            };
+
+            let buffer = &self.buffers[index];
+            std::ptr::copy_nonoverlapping(pixels.as_ptr(), buffer.data, size);
+            buffer.busy.store(true, Ordering::Release);
+            let version = (client.get_version)(self.surface);
+            (client.marshal_flags)(self.surface, SURFACE_ATTACH, std::ptr::null(), version, 0, buffer.buffer, 0i32, 0i32);
+            (client.marshal_flags)(self.surface, SURFACE_DAMAGE, std::ptr::null(), version, 0, 0i32, 0i32, i32::MAX, i32::MAX);
+            (client.marshal_flags)(self.surface, SURFACE_COMMIT, std::ptr::null(), version, 0);
+            if (client.flush)(self.display) < 0 {
+                return Err(format!("Wayland flush: {}", std::io::Error::last_os_error()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Presenter {
+    fn drop(&mut self) {
+        self.buffers.clear();
+        unsafe {
+            (self.client.proxy_destroy)(self.shm);
+            (self.client.flush)(self.display);
+            (self.client.queue_destroy)(self.queue);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Win32: frames are drawn into the window's client area with GDI.
+
+use std::ffi::c_void;
+
+use windows_sys::Win32::Foundation::RECT;
+use windows_sys::Win32::Graphics::Gdi::{
+    GetDC, ReleaseDC, SetDIBitsToDevice, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::GetClientRect;
+
+use super::Present;
+
+pub fn extent(hwnd: *mut c_void) -> Option<(u32, u32)> {
+    let mut rect = RECT { left: 0, top: 0, right: 0, bottom: 0 };
+    if unsafe { GetClientRect(hwnd, &mut rect) } == 0 {
+        return None;
+    }
+    Some(((rect.right - rect.left) as u32, (rect.bottom - rect.top) as u32))
+}
+
+pub struct Presenter {
+    hwnd: *mut c_void,
+}
+
+// SAFETY: GDI calls on another thread's window are allowed.
+unsafe impl Send for Presenter {}
+
+impl Presenter {
+    pub fn new(hwnd: *mut c_void) -> Self {
+        Self { hwnd }
+    }
+}
+
+impl Present for Presenter {
+    fn show(&mut self, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+        if width == 0 || pixels.len() < width as usize * height as usize * 4 {
+            return Err(format!("{} bytes is not a {}x{} frame", pixels.len(), width, height));
+        }
+        let mut info: BITMAPINFO = unsafe { std::mem::zeroed() };
+        info.bmiHeader = BITMAPINFOHEADER {
+            biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+            biWidth: width as i32,
+            // Negative: rows run top to bottom
+            biHeight: -(height as i32),
+            biPlanes: 1,
+            biBitCount: 32,
+            biCompression: BI_RGB,
+            ..unsafe { std::mem::zeroed() }
+        };
+        unsafe {
+            let dc = GetDC(self.hwnd);
+            if dc.is_null() {
+                return Err("GetDC failed".to_string());
+            }
+            let lines = SetDIBitsToDevice(
+                dc,
+                0,
+                0,
+                width,
+                height,
+                0,
+                0,
+                0,
+                height,
+                pixels.as_ptr() as *const c_void,
+                &info,
+                DIB_RGB_COLORS,
+            );
+            ReleaseDC(self.hwnd, dc);
+            if lines == 0 {
+                return Err("SetDIBitsToDevice failed".to_string());
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! X11: frames go to the window with `xcb_put_image`, in strips that fit the
+//! server's maximum request length. Xlib windows are drawn through the XCB
+//! connection underneath the display.
+
+use std::ffi::c_void;
+use std::sync::OnceLock;
+
+use libloading::{Library, Symbol};
+use tracing::info;
+
+use super::Present;
+
+/// `XCB_IMAGE_FORMAT_Z_PIXMAP`
+const Z_PIXMAP: u8 = 2;
+/// Size of a PutImage request without its data
+const PUT_IMAGE_HEADER: usize = 24;
+
+/// How the application reached the X server.
+#[derive(Clone, Copy)]
+pub enum Connection {
+    /// `xcb_connection_t *`
+    Xcb(*mut c_void),
+    /// Xlib `Display *`
+    Xlib(*mut c_void),
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Cookie {
+    _sequence: u32,
+}
+
+#[repr(C)]
+struct GeometryReply {
+    _response_type: u8,
+    depth: u8,
+    _sequence: u16,
+    _length: u32,
+    _root: u32,
+    _x: i16,
+    _y: i16,
+    width: u16,
+    height: u16,
+    _border_width: u16,
+    _pad0: [u8; 2],
+}
+
+type FnGenerateId = unsafe extern "C" fn(conn: *mut c_void) -> u32;
+type FnCreateGc =
+    unsafe extern "C" fn(conn: *mut c_void, gc: u32, drawable: u32, value_mask: u32, values: *const u32) -> Cookie;
+type FnFreeGc = unsafe extern "C" fn(conn: *mut c_void, gc: u32) -> Cookie;
+type FnPutImage = unsafe extern "C" fn(
+    conn: *mut c_void,
+    format: u8,
+    drawable: u32,
+    gc: u32,
+    width: u16,
+    height: u16,
+    dst_x: i16,
+    dst_y: i16,
+    left_pad: u8,
+    depth: u8,
+    data_len: u32,
+    data: *const u8,
+) -> Cookie;
+type FnGetGeometry = unsafe extern "C" fn(conn: *mut c_void, drawable: u32) -> Cookie;
+type FnGetGeometryReply =
+    unsafe extern "C" fn(conn: *mut c_void, cookie: Cookie, error: *mut *mut c_void) -> *mut GeometryReply;
+type FnGetMaximumRequestLength = unsafe extern "C" fn(conn: *mut c_void) -> u32;
+type FnFlush = unsafe extern "C" fn(conn: *mut c_void) -> i32;
+type FnGetXcbConnection = unsafe extern "C" fn(display: *mut c_void) -> *mut c_void;
+
+struct Xcb {
+    _lib: Library,
+    _x11_xcb: Option<Library>,
+    generate_id: FnGenerateId,
+    create_gc: FnCreateGc,
+    free_gc: FnFreeGc,
+    put_image: FnPutImage,
+    get_geometry: FnGetGeometry,
+    get_geometry_reply: FnGetGeometryReply,
+    get_maximum_request_length: FnGetMaximumRequestLength,
+    flush: FnFlush,
+    /// From libX11-xcb, for Xlib surfaces
+    get_xcb_connection: Option<FnGetXcbConnection>,
+}
+
+impl Xcb {
+    fn load() -> Option<Self> {
+        let lib = ["libxcb.so.1", "libxcb.so"]
+            .iter()
+            .find_map(|name| unsafe { Library::new(name).ok() })?;
+        let x11_xcb = ["libX11-xcb.so.1", "libX11-xcb.so"]
+            .iter()
+            .find_map(|name| unsafe { Library::new(name).ok() });
+        unsafe {
+            let xcb = Self {
+                generate_id: Self::sym(&lib, "xcb_generate_id")?,
+                create_gc: Self::sym(&lib, "xcb_create_gc")?,
+                free_gc: Self::sym(&lib, "xcb_free_gc")?,
+                put_image: Self::sym(&lib, "xcb_put_image")?,
+                get_geometry: Self::sym(&lib, "xcb_get_geometry")?,
+                get_geometry_reply: Self::sym(&lib, "xcb_get_geometry_reply")?,
+                get_maximum_request_length: Self::sym(&lib, "xcb_get_maximum_request_length")?,
+                flush: Self::sym(&lib, "xcb_flush")?,
+                get_xcb_connection: x11_xcb.as_ref().and_then(|l| Self::sym(l, "XGetXCBConnection")),
+                _x11_xcb: x11_xcb,
+                _lib: lib,
+            };
+            info!("loaded libxcb for presentation");
+            Some(xcb)
+        }
+    }
+
+    unsafe fn sym<F: Copy>(lib: &Library, name: &str) -> Option<F> {
+        lib.get(name.as_bytes()).ok().map(|s: Symbol<F>| *s)
+    }
+
+    fn connection(&self, connection: Connection) -> Option<*mut c_void> {
+        match connection {
+            Connection::Xcb(conn) => Some(conn),
+            Connection::Xlib(display) => self.get_xcb_connection.map(|get| unsafe { get(display) }),
+        }
+        .filter(|conn| !conn.is_null())
+    }
+
+    /// Width, height and depth of `window`.
+    fn geometry(&self, conn: *mut c_void, window: u32) -> Option<(u32, u32, u8)> {
+        unsafe {
+            let cookie = (self.get_geometry)(conn, window);
+            let mut error = std::ptr::null_mut();
+            let reply = (self.get_geometry_reply)(conn, cookie, &mut error);
+            if !error.is_null() {
+                libc::free(error);
+            }
+            if reply.is_null() {
+                return None;
+            }
+            let geometry = ((*reply).width as u32, (*reply).height as u32, (*reply).depth);
+            libc::free(reply as *mut c_void);
+            Some(geometry)
+        }
+    }
+}
+
+fn xcb() -> Result<&'static Xcb, String> {
+    static XCB: OnceLock<Option<Xcb>> = OnceLock::new();
+    XCB.get_or_init(Xcb::load).as_ref().ok_or_else(|| "libxcb not found".to_string())
+}
+
+pub fn extent(connection: Connection, window: u32) -> Option<(u32, u32)> {
+    let xcb = xcb().ok()?;
+    let conn = xcb.connection(connection)?;
+    xcb.geometry(conn, window).map(|(width, height, _)| (width, height))
+}
+
+pub struct Presenter {
+    xcb: &'static Xcb,
+    conn: *mut c_void,
+    window: u32,
+    gc: u32,
+    depth: u8,
+    /// Largest request the server takes, in bytes
+    max_request: usize,
+}
+
+// SAFETY: XCB connections are thread-safe.
+unsafe impl Send for Presenter {}
+
+impl Presenter {
+    pub fn new(connection: Connection, window: u32) -> Result<Self, String> {
+        let xcb = xcb()?;
+        let conn = xcb
+            .connection(connection)
+            .ok_or("can't get the XCB connection of the Xlib display")?;
+        let (_, _, depth) = xcb
+            .geometry(conn, window)
+            .ok_or_else(|| format!("X11 window 0x{:x} not found", window))?;
+        unsafe {
+            let gc = (xcb.generate_id)(conn);
+            (xcb.create_gc)(conn, gc, window, 0, std::ptr::null());
+            let max_request = (xcb.get_maximum_request_length)(conn) as usize * 4;
+            Ok(Self {
+                xcb,
+                conn,
+                window,
+                gc,
+                depth,
+                max_request,
+            })
+        }
+    }
+}
+
+impl Present for Presenter {
+    fn show(&mut self, width: u32, height: u32, pixels: &[u8]) -> Result<(), String> {
+        let stride = width as usize * 4;
+        if stride == 0 || pixels.len() < stride * height as usize {
+            return Err(format!("{} bytes is not a {}x{} frame", pixels.len(), width, height));
+        }
+        // Depth 24 and 32 windows both take 32-bit BGRX pixels in Z format.
+        let rows = (self.max_request.saturating_sub(PUT_IMAGE_HEADER) / stride).max(1);
+        for (i, strip) in pixels[..stride * height as usize].chunks(rows * stride).enumerate() {
+            unsafe {
+                (self.xcb.put_image)(
+                    self.conn,
+                    Z_PIXMAP,
+                    self.window,
+                    self.gc,
+                    width as u16,
+                    (strip.len() / stride) as u16,
+                    0,
+                    (i * rows) as i16,
+                    0,
+                    self.depth,
+                    strip.len() as u32,
+                    strip.as_ptr(),
+                );
+            }
+        }
+        if unsafe { (self.xcb.flush)(self.conn) } <= 0 {
+            return Err("X11 connection lost".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Presenter {
+    fn drop(&mut self) {
+        unsafe {
+            (self.xcb.free_gc)(self.conn, self.gc);
+            (self.xcb.flush)(self.conn);
+        }
+    }
+}
@@ -0,0 +1,124 @@
+//! Query pool functions for the Vulkan ICD.
+//! Queries themselves are recorded into command buffers (see `command`).
+
+use ash::vk;
+use ash::vk::Handle;
+
+use crate::dispatch::DispatchableHandle;
+use crate::handle_store;
+use crate::send_vulkan_command;
+
+use rgpu_protocol::vulkan_commands::{VulkanCommand, VulkanResponse};
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateQueryPool(
+    device: vk::Device,
+    p_create_info: *const vk::QueryPoolCreateInfo<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_query_pool: *mut vk::QueryPool,
+) -> vk::Result {
+    if p_create_info.is_null() || p_query_pool.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let ci = &*p_create_info;
+    let cmd = VulkanCommand::CreateQueryPool {
+        device: dev_handle,
+        query_type: ci.query_type.as_raw(),
+        query_count: ci.query_count,
+        pipeline_statistics: ci.pipeline_statistics.as_raw(),
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::QueryPoolCreated { handle }) => {
+            let local_id = handle_store::store_query_pool(handle);
+            *p_query_pool = vk::QueryPool::from_raw(local_id);
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkDestroyQueryPool(
+    device: vk::Device,
+    query_pool: vk::QueryPool,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    if query_pool == vk::QueryPool::null() {
+        return;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Some(handle) = handle_store::remove_query_pool(query_pool.as_raw()) {
+        let _ = send_vulkan_command(VulkanCommand::DestroyQueryPool {
+            device: dev_handle,
+            query_pool: handle,
+        });
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetQueryPoolResults(
+    device: vk::Device,
+    query_pool: vk::QueryPool,
+    first_query: u32,
+    query_count: u32,
+    data_size: usize,
+    p_data: *mut std::ffi::c_void,
+    stride: vk::DeviceSize,
+    flags: vk::QueryResultFlags,
+) -> vk::Result {
+    if p_data.is_null() || data_size == 0 {
+        return vk::Result::SUCCESS;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let pool_handle = match handle_store::get_query_pool(query_pool.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_UNKNOWN,
+    };
+
+    let cmd = VulkanCommand::GetQueryPoolResults {
+        device: dev_handle,
+        query_pool: pool_handle,
+        first_query,
+        query_count,
+        data_size: data_size as u64,
+        stride,
+        flags: flags.as_raw(),
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::QueryPoolResults { result, data }) => {
+            let len = std::cmp::min(data.len(), data_size);
+            std::ptr::copy_nonoverlapping(data.as_ptr(), p_data as *mut u8, len);
+            vk::Result::from_raw(result)
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_DEVICE_LOST,
+    }
+}
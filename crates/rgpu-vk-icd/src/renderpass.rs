@@ -261,7 +261,7 @@ pub unsafe extern "C" fn vkCreateFramebuffer(
     let cmd = VulkanCommand::CreateFramebuffer {
         device: dev_handle,
         render_pass: rp_handle,
-        attachments: attachment_handles,
+        attachments: attachment_handles.clone(),
         width: ci.width,
         height: ci.height,
         layers: ci.layers,
@@ -269,6 +269,7 @@ pub unsafe extern "C" fn vkCreateFramebuffer(
 
     match send_vulkan_command(cmd) {
         Ok(VulkanResponse::FramebufferCreated { handle }) => {
+            crate::access::resources(|r| r.add_framebuffer(handle, attachment_handles));
             let local_id = handle_store::store_framebuffer(handle);
             *p_framebuffer = vk::Framebuffer::from_raw(local_id);
             vk::Result::SUCCESS
@@ -300,6 +301,7 @@ pub unsafe extern "C" fn vkDestroyFramebuffer(
 
     let local_id = framebuffer.as_raw();
     if let Some(handle) = handle_store::remove_framebuffer(local_id) {
+        crate::access::resources(|r| r.forget(&handle));
         let _ = send_vulkan_command(VulkanCommand::DestroyFramebuffer {
             device: dev_handle,
             framebuffer: handle,
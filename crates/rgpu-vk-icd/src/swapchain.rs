@@ -0,0 +1,571 @@
+//! Window surfaces and swapchains for the Vulkan ICD.
+//!
+//! Surfaces never leave the client: they only remember the window. A
+//! swapchain is created on the server as a set of ordinary images; each
+//! `vkQueuePresentKHR` returns the presented images, which are shown in the
+//! window by [`crate::present`].
+
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+use ash::vk;
+use ash::vk::Handle;
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use tracing::warn;
+
+use crate::dispatch::DispatchableHandle;
+use crate::handle_store;
+use crate::present::{Present, Window};
+use crate::send_vulkan_command;
+
+use rgpu_protocol::frame;
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::vulkan_commands::{
+    SerializedPresent, SerializedSwapchainCreateInfo, VulkanCommand, VulkanResponse,
+};
+
+/// Formats the server's virtual swapchains support.
+const SURFACE_FORMATS: [vk::Format; 4] = [
+    vk::Format::B8G8R8A8_UNORM,
+    vk::Format::B8G8R8A8_SRGB,
+    vk::Format::R8G8B8A8_UNORM,
+    vk::Format::R8G8B8A8_SRGB,
+];
+
+const PRESENT_MODES: [vk::PresentModeKHR; 2] = [vk::PresentModeKHR::FIFO, vk::PresentModeKHR::IMMEDIATE];
+
+struct Swapchain {
+    handle: NetworkHandle,
+    device: NetworkHandle,
+    /// Local ids of the images, in swapchain order
+    images: Vec<u64>,
+    format: vk::Format,
+    window: Window,
+    display: Mutex<Display>,
+}
+
+/// What's on screen for one swapchain.
+struct Display {
+    presenter: Option<Box<dyn Present>>,
+    /// The last frame, for decoding deltas
+    previous: Option<Vec<u8>>,
+}
+
+fn surfaces() -> &'static DashMap<u64, Window> {
+    static SURFACES: OnceLock<DashMap<u64, Window>> = OnceLock::new();
+    SURFACES.get_or_init(DashMap::new)
+}
+
+fn swapchains() -> &'static DashMap<u64, Arc<Swapchain>> {
+    static SWAPCHAINS: OnceLock<DashMap<u64, Arc<Swapchain>>> = OnceLock::new();
+    SWAPCHAINS.get_or_init(DashMap::new)
+}
+
+/// The instance extensions this module implements, to add to the server's.
+pub fn instance_extensions() -> Vec<(&'static std::ffi::CStr, u32)> {
+    let mut extensions = vec![(ash::khr::surface::NAME, ash::khr::surface::SPEC_VERSION)];
+    #[cfg(target_os = "linux")]
+    extensions.extend([
+        (ash::khr::xcb_surface::NAME, ash::khr::xcb_surface::SPEC_VERSION),
+        (ash::khr::xlib_surface::NAME, ash::khr::xlib_surface::SPEC_VERSION),
+        (ash::khr::wayland_surface::NAME, ash::khr::wayland_surface::SPEC_VERSION),
+    ]);
+    #[cfg(windows)]
+    extensions.push((ash::khr::win32_surface::NAME, ash::khr::win32_surface::SPEC_VERSION));
+    extensions
+}
+
+/// The device extensions this module implements.
+pub fn device_extensions() -> Vec<(&'static std::ffi::CStr, u32)> {
+    vec![(ash::khr::swapchain::NAME, ash::khr::swapchain::SPEC_VERSION)]
+}
+
+// ── Surfaces ─────────────────────────────────────────────────
+
+unsafe fn create_surface(window: Window, p_surface: *mut vk::SurfaceKHR) -> vk::Result {
+    if p_surface.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    let id = handle_store::alloc_id();
+    surfaces().insert(id, window);
+    *p_surface = vk::SurfaceKHR::from_raw(id);
+    vk::Result::SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateXcbSurfaceKHR(
+    _instance: vk::Instance,
+    p_create_info: *const vk::XcbSurfaceCreateInfoKHR<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_surface: *mut vk::SurfaceKHR,
+) -> vk::Result {
+    if p_create_info.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    let ci = &*p_create_info;
+    create_surface(
+        Window::Xcb {
+            connection: ci.connection,
+            window: ci.window,
+        },
+        p_surface,
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateXlibSurfaceKHR(
+    _instance: vk::Instance,
+    p_create_info: *const vk::XlibSurfaceCreateInfoKHR<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_surface: *mut vk::SurfaceKHR,
+) -> vk::Result {
+    if p_create_info.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    let ci = &*p_create_info;
+    create_surface(
+        Window::Xlib {
+            display: ci.dpy,
+            window: ci.window,
+        },
+        p_surface,
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateWaylandSurfaceKHR(
+    _instance: vk::Instance,
+    p_create_info: *const vk::WaylandSurfaceCreateInfoKHR<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_surface: *mut vk::SurfaceKHR,
+) -> vk::Result {
+    if p_create_info.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    let ci = &*p_create_info;
+    create_surface(
+        Window::Wayland {
+            display: ci.display,
+            surface: ci.surface,
+        },
+        p_surface,
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateWin32SurfaceKHR(
+    _instance: vk::Instance,
+    p_create_info: *const vk::Win32SurfaceCreateInfoKHR<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_surface: *mut vk::SurfaceKHR,
+) -> vk::Result {
+    if p_create_info.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    let ci = &*p_create_info;
+    create_surface(Window::Win32 { hwnd: ci.hwnd as *mut c_void }, p_surface)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkDestroySurfaceKHR(
+    _instance: vk::Instance,
+    surface: vk::SurfaceKHR,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    surfaces().remove(&surface.as_raw());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceSurfaceSupportKHR(
+    _physical_device: vk::PhysicalDevice,
+    _queue_family_index: u32,
+    surface: vk::SurfaceKHR,
+    p_supported: *mut vk::Bool32,
+) -> vk::Result {
+    if p_supported.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    if !surfaces().contains_key(&surface.as_raw()) {
+        return vk::Result::ERROR_SURFACE_LOST_KHR;
+    }
+    // Frames are copied on the server's own queue, so any family presents.
+    *p_supported = vk::TRUE;
+    vk::Result::SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceSurfaceCapabilitiesKHR(
+    _physical_device: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    p_surface_capabilities: *mut vk::SurfaceCapabilitiesKHR,
+) -> vk::Result {
+    if p_surface_capabilities.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    let window = match surfaces().get(&surface.as_raw()) {
+        Some(w) => *w,
+        None => return vk::Result::ERROR_SURFACE_LOST_KHR,
+    };
+    // Without a window size (Wayland) the application picks the extent.
+    let current_extent = match window.extent() {
+        Some((width, height)) => vk::Extent2D { width, height },
+        None => vk::Extent2D {
+            width: u32::MAX,
+            height: u32::MAX,
+        },
+    };
+    *p_surface_capabilities = vk::SurfaceCapabilitiesKHR {
+        min_image_count: 2,
+        max_image_count: 8,
+        current_extent,
+        min_image_extent: vk::Extent2D { width: 1, height: 1 },
+        max_image_extent: vk::Extent2D {
+            width: 16384,
+            height: 16384,
+        },
+        max_image_array_layers: 1,
+        supported_transforms: vk::SurfaceTransformFlagsKHR::IDENTITY,
+        current_transform: vk::SurfaceTransformFlagsKHR::IDENTITY,
+        supported_composite_alpha: vk::CompositeAlphaFlagsKHR::OPAQUE,
+        supported_usage_flags: vk::ImageUsageFlags::COLOR_ATTACHMENT
+            | vk::ImageUsageFlags::TRANSFER_SRC
+            | vk::ImageUsageFlags::TRANSFER_DST
+            | vk::ImageUsageFlags::SAMPLED,
+    };
+    vk::Result::SUCCESS
+}
+
+/// Standard two-call enumeration of `items` into `p_items`.
+unsafe fn write_array<T: Copy>(items: &[T], p_count: *mut u32, p_items: *mut T) -> vk::Result {
+    if p_count.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    if p_items.is_null() {
+        *p_count = items.len() as u32;
+        return vk::Result::SUCCESS;
+    }
+    let count = std::cmp::min(*p_count as usize, items.len());
+    std::ptr::copy_nonoverlapping(items.as_ptr(), p_items, count);
+    *p_count = count as u32;
+    if count < items.len() {
+        vk::Result::INCOMPLETE
+    } else {
+        vk::Result::SUCCESS
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceSurfaceFormatsKHR(
+    _physical_device: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    p_surface_format_count: *mut u32,
+    p_surface_formats: *mut vk::SurfaceFormatKHR,
+) -> vk::Result {
+    if !surfaces().contains_key(&surface.as_raw()) {
+        return vk::Result::ERROR_SURFACE_LOST_KHR;
+    }
+    let formats: Vec<vk::SurfaceFormatKHR> = SURFACE_FORMATS
+        .iter()
+        .map(|&format| vk::SurfaceFormatKHR {
+            format,
+            color_space: vk::ColorSpaceKHR::SRGB_NONLINEAR,
+        })
+        .collect();
+    write_array(&formats, p_surface_format_count, p_surface_formats)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceSurfacePresentModesKHR(
+    _physical_device: vk::PhysicalDevice,
+    surface: vk::SurfaceKHR,
+    p_present_mode_count: *mut u32,
+    p_present_modes: *mut vk::PresentModeKHR,
+) -> vk::Result {
+    if !surfaces().contains_key(&surface.as_raw()) {
+        return vk::Result::ERROR_SURFACE_LOST_KHR;
+    }
+    write_array(&PRESENT_MODES, p_present_mode_count, p_present_modes)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceXcbPresentationSupportKHR(
+    _physical_device: vk::PhysicalDevice,
+    _queue_family_index: u32,
+    _connection: *mut c_void,
+    _visual_id: u32,
+) -> vk::Bool32 {
+    vk::TRUE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceXlibPresentationSupportKHR(
+    _physical_device: vk::PhysicalDevice,
+    _queue_family_index: u32,
+    _dpy: *mut c_void,
+    _visual_id: std::ffi::c_ulong,
+) -> vk::Bool32 {
+    vk::TRUE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceWaylandPresentationSupportKHR(
+    _physical_device: vk::PhysicalDevice,
+    _queue_family_index: u32,
+    _display: *mut c_void,
+) -> vk::Bool32 {
+    vk::TRUE
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetPhysicalDeviceWin32PresentationSupportKHR(
+    _physical_device: vk::PhysicalDevice,
+    _queue_family_index: u32,
+) -> vk::Bool32 {
+    vk::TRUE
+}
+
+// ── Swapchains ───────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateSwapchainKHR(
+    device: vk::Device,
+    p_create_info: *const vk::SwapchainCreateInfoKHR<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_swapchain: *mut vk::SwapchainKHR,
+) -> vk::Result {
+    if p_create_info.is_null() || p_swapchain.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let ci = &*p_create_info;
+    let window = match surfaces().get(&ci.surface.as_raw()) {
+        Some(w) => *w,
+        None => return vk::Result::ERROR_SURFACE_LOST_KHR,
+    };
+
+    let cmd = VulkanCommand::CreateSwapchain {
+        device: dev_handle,
+        create_info: SerializedSwapchainCreateInfo {
+            min_image_count: ci.min_image_count,
+            format: ci.image_format.as_raw(),
+            extent: [ci.image_extent.width, ci.image_extent.height],
+            image_usage: ci.image_usage.as_raw(),
+        },
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::SwapchainCreated { handle, images }) => {
+            let images = images.into_iter().map(handle_store::store_image).collect();
+            let local_id = handle_store::alloc_id();
+            swapchains().insert(
+                local_id,
+                Arc::new(Swapchain {
+                    handle,
+                    device: dev_handle,
+                    images,
+                    format: ci.image_format,
+                    window,
+                    display: Mutex::new(Display {
+                        presenter: None,
+                        previous: None,
+                    }),
+                }),
+            );
+            *p_swapchain = vk::SwapchainKHR::from_raw(local_id);
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_OUT_OF_DEVICE_MEMORY,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkDestroySwapchainKHR(
+    _device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    if swapchain == vk::SwapchainKHR::null() {
+        return;
+    }
+    if let Some((_, sc)) = swapchains().remove(&swapchain.as_raw()) {
+        for &image in &sc.images {
+            handle_store::remove_image(image);
+        }
+        let _ = send_vulkan_command(VulkanCommand::DestroySwapchain {
+            device: sc.device,
+            swapchain: sc.handle,
+        });
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetSwapchainImagesKHR(
+    _device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+    p_swapchain_image_count: *mut u32,
+    p_swapchain_images: *mut vk::Image,
+) -> vk::Result {
+    let sc = match swapchains().get(&swapchain.as_raw()) {
+        Some(sc) => Arc::clone(&sc),
+        None => return vk::Result::ERROR_SURFACE_LOST_KHR,
+    };
+    let images: Vec<vk::Image> = sc.images.iter().map(|&id| vk::Image::from_raw(id)).collect();
+    write_array(&images, p_swapchain_image_count, p_swapchain_images)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkAcquireNextImageKHR(
+    _device: vk::Device,
+    swapchain: vk::SwapchainKHR,
+    _timeout: u64,
+    semaphore: vk::Semaphore,
+    fence: vk::Fence,
+    p_image_index: *mut u32,
+) -> vk::Result {
+    if p_image_index.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+    let sc = match swapchains().get(&swapchain.as_raw()) {
+        Some(sc) => Arc::clone(&sc),
+        None => return vk::Result::ERROR_SURFACE_LOST_KHR,
+    };
+
+    let cmd = VulkanCommand::AcquireNextImage {
+        device: sc.device,
+        swapchain: sc.handle,
+        semaphore: handle_store::get_semaphore(semaphore.as_raw()),
+        fence: handle_store::get_fence(fence.as_raw()),
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::ImageAcquired { index }) => {
+            *p_image_index = index;
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_DEVICE_LOST,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkQueuePresentKHR(
+    queue: vk::Queue,
+    p_present_info: *const vk::PresentInfoKHR<'_>,
+) -> vk::Result {
+    if p_present_info.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let q_disp = queue.as_raw() as *const DispatchableHandle;
+    let q_local_id = DispatchableHandle::get_id(q_disp);
+
+    let queue_handle = match handle_store::get_queue(q_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let pi = &*p_present_info;
+    let wait_semaphores = if !pi.p_wait_semaphores.is_null() {
+        std::slice::from_raw_parts(pi.p_wait_semaphores, pi.wait_semaphore_count as usize)
+            .iter()
+            .filter_map(|s| handle_store::get_semaphore(s.as_raw()))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let count = pi.swapchain_count as usize;
+    let mut targets = Vec::with_capacity(count);
+    let mut presents = Vec::with_capacity(count);
+    for i in 0..count {
+        let sc = match swapchains().get(&(*pi.p_swapchains.add(i)).as_raw()) {
+            Some(sc) => Arc::clone(&sc),
+            None => return vk::Result::ERROR_SURFACE_LOST_KHR,
+        };
+        presents.push(SerializedPresent {
+            swapchain: sc.handle,
+            image_index: *pi.p_image_indices.add(i),
+        });
+        targets.push(sc);
+    }
+
+    let frames = match send_vulkan_command(VulkanCommand::QueuePresent {
+        queue: queue_handle,
+        wait_semaphores,
+        presents,
+        delta: true,
+    }) {
+        Ok(VulkanResponse::FramesPresented { frames }) => frames,
+        Ok(VulkanResponse::Error { code, .. }) => return vk::Result::from_raw(code),
+        _ => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let mut result = vk::Result::SUCCESS;
+    for (i, (sc, frame)) in targets.iter().zip(frames).enumerate() {
+        let (width, height) = (frame.width, frame.height);
+        let status = show(sc, frame);
+        if !pi.p_results.is_null() {
+            *pi.p_results.add(i) = status;
+        }
+        if status != vk::Result::SUCCESS {
+            result = status;
+        } else if sc.window.extent().is_some_and(|extent| extent != (width, height)) {
+            // The window was resized: ask for a new swapchain.
+            result = vk::Result::SUBOPTIMAL_KHR;
+        }
+    }
+    result
+}
+
+/// Decode a presented frame and put it in the swapchain's window.
+fn show(sc: &Swapchain, frame: rgpu_protocol::vulkan_commands::PresentedFrame) -> vk::Result {
+    // Presentation failures are reported once; the application keeps running.
+    static WARNED: AtomicBool = AtomicBool::new(false);
+
+    let mut display = sc.display.lock();
+    let mut pixels = match frame::decode(frame.encoding, frame.data, display.previous.as_deref()) {
+        Ok(pixels) => pixels,
+        Err(e) => {
+            warn!("bad frame for swapchain {:?}: {}", sc.handle, e);
+            display.previous = None;
+            return vk::Result::ERROR_OUT_OF_DATE_KHR;
+        }
+    };
+    display.previous = Some(pixels.clone());
+
+    // Window systems take BGRA.
+    if matches!(sc.format, vk::Format::R8G8B8A8_UNORM | vk::Format::R8G8B8A8_SRGB) {
+        pixels.chunks_exact_mut(4).for_each(|px| px.swap(0, 2));
+    }
+
+    if display.presenter.is_none() {
+        match sc.window.presenter() {
+            Ok(presenter) => display.presenter = Some(presenter),
+            Err(e) => {
+                if !WARNED.swap(true, Ordering::Relaxed) {
+                    warn!("can't show frames: {}", e);
+                }
+                return vk::Result::SUCCESS;
+            }
+        }
+    }
+    if let Some(presenter) = display.presenter.as_mut() {
+        if let Err(e) = presenter.show(frame.width, frame.height, &pixels) {
+            if !WARNED.swap(true, Ordering::Relaxed) {
+                warn!("can't show frames: {}", e);
+            }
+        }
+    }
+    vk::Result::SUCCESS
+}
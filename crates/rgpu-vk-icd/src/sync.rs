@@ -3,12 +3,19 @@
 use ash::vk;
 use ash::vk::Handle;
 
+use crate::access::Access;
 use crate::command;
 use crate::dispatch::DispatchableHandle;
 use crate::handle_store;
+use crate::memory::{after_wait, Finished};
+use crate::pnext::find_in_chain;
 use crate::send_vulkan_command;
 
-use rgpu_protocol::vulkan_commands::{SerializedSubmitInfo, VulkanCommand, VulkanResponse};
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::vulkan_commands::{
+    SerializedSemaphoreSubmitInfo, SerializedSubmitInfo, SerializedSubmitInfo2, VulkanCommand,
+    VulkanResponse,
+};
 
 // ── Fence ───────────────────────────────────────────────────
 
@@ -108,13 +115,18 @@ pub unsafe extern "C" fn vkWaitForFences(
 
     let cmd = VulkanCommand::WaitForFences {
         device: dev_handle,
-        fences: fence_handles,
+        fences: fence_handles.clone(),
         wait_all: wait_all != 0,
         timeout_ns: timeout,
     };
 
+    let finished = if wait_all != 0 || fence_handles.len() == 1 {
+        Finished::Fences(&fence_handles)
+    } else {
+        Finished::AnyFence(&fence_handles)
+    };
     match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::FenceWaitResult { result }) => vk::Result::from_raw(result),
+        Ok(VulkanResponse::FenceWaitResult { result }) => after_wait(vk::Result::from_raw(result), finished),
         Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
         _ => vk::Result::ERROR_UNKNOWN,
     }
@@ -185,7 +197,7 @@ pub unsafe extern "C" fn vkGetFenceStatus(
     match send_vulkan_command(cmd) {
         Ok(VulkanResponse::FenceStatus { signaled }) => {
             if signaled {
-                vk::Result::SUCCESS
+                after_wait(vk::Result::SUCCESS, Finished::Fences(&[fence_handle]))
             } else {
                 vk::Result::NOT_READY
             }
@@ -200,7 +212,7 @@ pub unsafe extern "C" fn vkGetFenceStatus(
 #[no_mangle]
 pub unsafe extern "C" fn vkCreateSemaphore(
     device: vk::Device,
-    _p_create_info: *const vk::SemaphoreCreateInfo<'_>,
+    p_create_info: *const vk::SemaphoreCreateInfo<'_>,
     _p_allocator: *const vk::AllocationCallbacks<'_>,
     p_semaphore: *mut vk::Semaphore,
 ) -> vk::Result {
@@ -216,8 +228,21 @@ pub unsafe extern "C" fn vkCreateSemaphore(
         None => return vk::Result::ERROR_DEVICE_LOST,
     };
 
+    let type_info = if p_create_info.is_null() {
+        None
+    } else {
+        find_in_chain::<vk::SemaphoreTypeCreateInfo>(
+            (*p_create_info).p_next,
+            vk::StructureType::SEMAPHORE_TYPE_CREATE_INFO,
+        )
+    };
+    let timeline = type_info
+        .filter(|ti| ti.semaphore_type == vk::SemaphoreType::TIMELINE)
+        .map(|ti| ti.initial_value);
+
     let cmd = VulkanCommand::CreateSemaphore {
         device: dev_handle,
+        timeline,
     };
 
     match send_vulkan_command(cmd) {
@@ -258,8 +283,193 @@ pub unsafe extern "C" fn vkDestroySemaphore(
     }
 }
 
+// ── Event ───────────────────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkCreateEvent(
+    device: vk::Device,
+    p_create_info: *const vk::EventCreateInfo<'_>,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+    p_event: *mut vk::Event,
+) -> vk::Result {
+    if p_create_info.is_null() || p_event.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let cmd = VulkanCommand::CreateEvent {
+        device: dev_handle,
+        flags: (*p_create_info).flags.as_raw(),
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::EventCreated { handle }) => {
+            let local_id = handle_store::store_event(handle);
+            *p_event = vk::Event::from_raw(local_id);
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkDestroyEvent(
+    device: vk::Device,
+    event: vk::Event,
+    _p_allocator: *const vk::AllocationCallbacks<'_>,
+) {
+    if event == vk::Event::null() {
+        return;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return,
+    };
+
+    if let Some(handle) = handle_store::remove_event(event.as_raw()) {
+        let _ = send_vulkan_command(VulkanCommand::DestroyEvent {
+            device: dev_handle,
+            event: handle,
+        });
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetEventStatus(device: vk::Device, event: vk::Event) -> vk::Result {
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let event_handle = match handle_store::get_event(event.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_UNKNOWN,
+    };
+
+    let cmd = VulkanCommand::GetEventStatus {
+        device: dev_handle,
+        event: event_handle,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::EventStatus { set }) => {
+            if set {
+                vk::Result::EVENT_SET
+            } else {
+                vk::Result::EVENT_RESET
+            }
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkSetEvent(device: vk::Device, event: vk::Event) -> vk::Result {
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let event_handle = match handle_store::get_event(event.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_UNKNOWN,
+    };
+
+    match send_vulkan_command(VulkanCommand::SetEvent {
+        device: dev_handle,
+        event: event_handle,
+    }) {
+        Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkResetEvent(device: vk::Device, event: vk::Event) -> vk::Result {
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let event_handle = match handle_store::get_event(event.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_UNKNOWN,
+    };
+
+    match send_vulkan_command(VulkanCommand::ResetEvent {
+        device: dev_handle,
+        event: event_handle,
+    }) {
+        Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_UNKNOWN,
+    }
+}
+
 // ── Queue Submit ────────────────────────────────────────────
 
+/// Send the commands recorded into a command buffer ahead of its submission.
+/// Returns the command buffer's network handle.
+unsafe fn flush_recorded_commands(cb: vk::CommandBuffer, access: &mut Access) -> Result<NetworkHandle, vk::Result> {
+    let cb_disp = cb.as_raw() as *const DispatchableHandle;
+    let cb_local_id = DispatchableHandle::get_id(cb_disp);
+
+    let cb_handle = match handle_store::get_cmd_buffer(cb_local_id) {
+        Some(h) => h,
+        None => return Err(vk::Result::ERROR_UNKNOWN),
+    };
+
+    // Take recorded commands and send them, noting the memory they use;
+    // a command buffer submitted again uses what it did before
+    let commands = command::take_recorded_commands(cb_local_id);
+    if !commands.is_empty() {
+        crate::access::resources(|r| r.record(cb_handle, &commands));
+        let cmd = VulkanCommand::SubmitRecordedCommands {
+            command_buffer: cb_handle,
+            secondary: None,
+            commands,
+        };
+
+        match send_vulkan_command(cmd) {
+            Ok(VulkanResponse::Success) => {}
+            Ok(VulkanResponse::Error { code, .. }) => return Err(vk::Result::from_raw(code)),
+            _ => return Err(vk::Result::ERROR_UNKNOWN),
+        }
+    }
+    crate::access::resources(|r| access.merge(&r.command_buffer(&cb_handle)));
+
+    Ok(cb_handle)
+}
+
+/// Note a submission's writes to memory, to copy them back once it finishes.
+fn submitted(q_local_id: u64, queue: NetworkHandle, fence: Option<NetworkHandle>, access: Access) {
+    let device = crate::dispatch::queue_device(q_local_id).and_then(handle_store::get_device);
+    crate::memory::submitted(device.unwrap_or_else(NetworkHandle::null), queue, fence, access);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn vkQueueSubmit(
     queue: vk::Queue,
@@ -276,41 +486,25 @@ pub unsafe extern "C" fn vkQueueSubmit(
     };
 
     // First, send recorded commands for all command buffers in all submits
+    let mut access = Access::default();
     if !p_submits.is_null() {
         for i in 0..submit_count as usize {
             let si = &*p_submits.add(i);
             if !si.p_command_buffers.is_null() {
                 for j in 0..si.command_buffer_count as usize {
-                    let cb = *si.p_command_buffers.add(j);
-                    let cb_disp = cb.as_raw() as *const DispatchableHandle;
-                    let cb_local_id = DispatchableHandle::get_id(cb_disp);
-
-                    let cb_handle = match handle_store::get_cmd_buffer(cb_local_id) {
-                        Some(h) => h,
-                        None => return vk::Result::ERROR_UNKNOWN,
-                    };
-
-                    // Take recorded commands and send them
-                    let commands = command::take_recorded_commands(cb_local_id);
-                    if !commands.is_empty() {
-                        let cmd = VulkanCommand::SubmitRecordedCommands {
-                            command_buffer: cb_handle,
-                            commands,
-                        };
-
-                        match send_vulkan_command(cmd) {
-                            Ok(VulkanResponse::Success) => {}
-                            Ok(VulkanResponse::Error { code, .. }) => {
-                                return vk::Result::from_raw(code);
-                            }
-                            _ => return vk::Result::ERROR_UNKNOWN,
-                        }
+                    if let Err(e) = flush_recorded_commands(*si.p_command_buffers.add(j), &mut access) {
+                        return e;
                     }
                 }
             }
         }
     }
 
+    // The submitted work sees what the application wrote to mapped memory
+    if let Err(e) = crate::memory::sync_mapped_memory(&access) {
+        return e;
+    }
+
     // Now do the actual queue submit
     let mut submits = Vec::new();
     if !p_submits.is_null() {
@@ -361,11 +555,27 @@ pub unsafe extern "C" fn vkQueueSubmit(
                 }
             }
 
+            // Timeline values, if any
+            let (wait_semaphore_values, signal_semaphore_values) = match find_in_chain::<
+                vk::TimelineSemaphoreSubmitInfo,
+            >(
+                si.p_next, vk::StructureType::TIMELINE_SEMAPHORE_SUBMIT_INFO
+            ) {
+                Some(ti) => (
+                    read_values(ti.p_wait_semaphore_values, ti.wait_semaphore_value_count),
+                    read_values(ti.p_signal_semaphore_values, ti.signal_semaphore_value_count),
+                ),
+                None => (Vec::new(), Vec::new()),
+            };
+
             submits.push(SerializedSubmitInfo {
                 wait_semaphores,
                 wait_dst_stage_masks,
                 command_buffers,
                 signal_semaphores,
+                wait_semaphore_values,
+                signal_semaphore_values,
+                p_next: crate::pnext::read_p_next(si.p_next),
             });
         }
     }
@@ -383,12 +593,118 @@ pub unsafe extern "C" fn vkQueueSubmit(
     };
 
     match send_vulkan_command(cmd) {
-        Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
+        Ok(VulkanResponse::Success) => {
+            submitted(q_local_id, queue_handle, fence_handle, access);
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_UNKNOWN,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkQueueSubmit2(
+    queue: vk::Queue,
+    submit_count: u32,
+    p_submits: *const vk::SubmitInfo2<'_>,
+    fence: vk::Fence,
+) -> vk::Result {
+    let q_disp = queue.as_raw() as *const DispatchableHandle;
+    let q_local_id = DispatchableHandle::get_id(q_disp);
+
+    let queue_handle = match handle_store::get_queue(q_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let mut access = Access::default();
+    let mut submits = Vec::new();
+    if !p_submits.is_null() {
+        for i in 0..submit_count as usize {
+            let si = &*p_submits.add(i);
+
+            // Recorded commands go out before the submit that references them
+            let mut command_buffers = Vec::new();
+            if !si.p_command_buffer_infos.is_null() {
+                for j in 0..si.command_buffer_info_count as usize {
+                    let cbi = &*si.p_command_buffer_infos.add(j);
+                    match flush_recorded_commands(cbi.command_buffer, &mut access) {
+                        Ok(h) => command_buffers.push(h),
+                        Err(e) => return e,
+                    }
+                }
+            }
+
+            let wait_semaphore_infos =
+                match read_semaphore_infos(si.p_wait_semaphore_infos, si.wait_semaphore_info_count) {
+                    Some(v) => v,
+                    None => return vk::Result::ERROR_UNKNOWN,
+                };
+            let signal_semaphore_infos = match read_semaphore_infos(
+                si.p_signal_semaphore_infos,
+                si.signal_semaphore_info_count,
+            ) {
+                Some(v) => v,
+                None => return vk::Result::ERROR_UNKNOWN,
+            };
+
+            submits.push(SerializedSubmitInfo2 {
+                flags: si.flags.as_raw(),
+                wait_semaphore_infos,
+                command_buffers,
+                signal_semaphore_infos,
+            });
+        }
+    }
+
+    let fence_handle = if fence != vk::Fence::null() {
+        handle_store::get_fence(fence.as_raw())
+    } else {
+        None
+    };
+
+    // The submitted work sees what the application wrote to mapped memory
+    if let Err(e) = crate::memory::sync_mapped_memory(&access) {
+        return e;
+    }
+
+    let cmd = VulkanCommand::QueueSubmit2 {
+        queue: queue_handle,
+        submits,
+        fence: fence_handle,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::Success) => {
+            submitted(q_local_id, queue_handle, fence_handle, access);
+            vk::Result::SUCCESS
+        }
         Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
         _ => vk::Result::ERROR_UNKNOWN,
     }
 }
 
+/// Resolve an array of VkSemaphoreSubmitInfo; `None` if a semaphore is unknown.
+unsafe fn read_semaphore_infos(
+    p_infos: *const vk::SemaphoreSubmitInfo<'_>,
+    count: u32,
+) -> Option<Vec<SerializedSemaphoreSubmitInfo>> {
+    let mut infos = Vec::new();
+    if p_infos.is_null() {
+        return Some(infos);
+    }
+    for k in 0..count as usize {
+        let info = &*p_infos.add(k);
+        infos.push(SerializedSemaphoreSubmitInfo {
+            semaphore: handle_store::get_semaphore(info.semaphore.as_raw())?,
+            value: info.value,
+            stage_mask: info.stage_mask.as_raw(),
+            device_index: info.device_index,
+        });
+    }
+    Some(infos)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn vkQueueWaitIdle(queue: vk::Queue) -> vk::Result {
     let q_disp = queue.as_raw() as *const DispatchableHandle;
@@ -403,9 +719,168 @@ pub unsafe extern "C" fn vkQueueWaitIdle(queue: vk::Queue) -> vk::Result {
         queue: queue_handle,
     };
 
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::Success) => after_wait(vk::Result::SUCCESS, Finished::Queue(queue_handle)),
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_DEVICE_LOST,
+    }
+}
+
+// ── Timeline Semaphores ─────────────────────────────────────
+
+#[no_mangle]
+pub unsafe extern "C" fn vkWaitSemaphores(
+    device: vk::Device,
+    p_wait_info: *const vk::SemaphoreWaitInfo<'_>,
+    timeout: u64,
+) -> vk::Result {
+    if p_wait_info.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let wi = &*p_wait_info;
+    if wi.semaphore_count == 0 || wi.p_semaphores.is_null() || wi.p_values.is_null() {
+        return vk::Result::SUCCESS;
+    }
+    let mut semaphores = Vec::new();
+    for i in 0..wi.semaphore_count as usize {
+        let sem = *wi.p_semaphores.add(i);
+        match handle_store::get_semaphore(sem.as_raw()) {
+            Some(h) => semaphores.push(h),
+            None => return vk::Result::ERROR_UNKNOWN,
+        }
+    }
+
+    let cmd = VulkanCommand::WaitSemaphores {
+        device: dev_handle,
+        semaphores,
+        values: read_values(wi.p_values, wi.semaphore_count),
+        wait_any: wi.flags.contains(vk::SemaphoreWaitFlags::ANY),
+        timeout_ns: timeout,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::SemaphoreWaitResult { result }) => {
+            after_wait(vk::Result::from_raw(result), Finished::Semaphores(dev_handle))
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_DEVICE_LOST,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkWaitSemaphoresKHR(
+    device: vk::Device,
+    p_wait_info: *const vk::SemaphoreWaitInfo<'_>,
+    timeout: u64,
+) -> vk::Result {
+    vkWaitSemaphores(device, p_wait_info, timeout)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkSignalSemaphore(
+    device: vk::Device,
+    p_signal_info: *const vk::SemaphoreSignalInfo<'_>,
+) -> vk::Result {
+    if p_signal_info.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let si = &*p_signal_info;
+    let sem_handle = match handle_store::get_semaphore(si.semaphore.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_UNKNOWN,
+    };
+
+    let cmd = VulkanCommand::SignalSemaphore {
+        device: dev_handle,
+        semaphore: sem_handle,
+        value: si.value,
+    };
+
     match send_vulkan_command(cmd) {
         Ok(VulkanResponse::Success) => vk::Result::SUCCESS,
         Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
         _ => vk::Result::ERROR_DEVICE_LOST,
     }
 }
+
+#[no_mangle]
+pub unsafe extern "C" fn vkSignalSemaphoreKHR(
+    device: vk::Device,
+    p_signal_info: *const vk::SemaphoreSignalInfo<'_>,
+) -> vk::Result {
+    vkSignalSemaphore(device, p_signal_info)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetSemaphoreCounterValue(
+    device: vk::Device,
+    semaphore: vk::Semaphore,
+    p_value: *mut u64,
+) -> vk::Result {
+    if p_value.is_null() {
+        return vk::Result::ERROR_OUT_OF_HOST_MEMORY;
+    }
+
+    let disp = device.as_raw() as *const DispatchableHandle;
+    let dev_local_id = DispatchableHandle::get_id(disp);
+
+    let dev_handle = match handle_store::get_device(dev_local_id) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_DEVICE_LOST,
+    };
+
+    let sem_handle = match handle_store::get_semaphore(semaphore.as_raw()) {
+        Some(h) => h,
+        None => return vk::Result::ERROR_UNKNOWN,
+    };
+
+    let cmd = VulkanCommand::GetSemaphoreCounterValue {
+        device: dev_handle,
+        semaphore: sem_handle,
+    };
+
+    match send_vulkan_command(cmd) {
+        Ok(VulkanResponse::SemaphoreCounterValue { value }) => {
+            *p_value = value;
+            vk::Result::SUCCESS
+        }
+        Ok(VulkanResponse::Error { code, .. }) => vk::Result::from_raw(code),
+        _ => vk::Result::ERROR_DEVICE_LOST,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn vkGetSemaphoreCounterValueKHR(
+    device: vk::Device,
+    semaphore: vk::Semaphore,
+    p_value: *mut u64,
+) -> vk::Result {
+    vkGetSemaphoreCounterValue(device, semaphore, p_value)
+}
+
+// ── Helpers ─────────────────────────────────────────────────
+
+unsafe fn read_values(ptr: *const u64, count: u32) -> Vec<u64> {
+    if ptr.is_null() || count == 0 {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(ptr, count as usize).to_vec()
+}
@@ -0,0 +1,6 @@
+#!/usr/bin/env bash
+# Build and run the vector-add sample on a remote GPU.
+source "$(dirname "$0")/../scripts/local-stack.sh"
+
+make -C "$EXAMPLES_DIR/cuda-vector-add" LIB_DIR="$TARGET_DIR"
+"$RGPU" shell --run "$EXAMPLES_DIR/cuda-vector-add/vector_add"
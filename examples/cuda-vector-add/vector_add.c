@@ -0,0 +1,132 @@
+/*
+ * Vector addition through the CUDA driver API, run on a remote GPU by the
+ * RGPU interpose library. The few driver declarations needed are below, so
+ * no CUDA toolkit is required to build it.
+ */
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+typedef int CUresult;
+typedef int CUdevice;
+typedef struct CUctx_st *CUcontext;
+typedef struct CUmod_st *CUmodule;
+typedef struct CUfunc_st *CUfunction;
+typedef struct CUstream_st *CUstream;
+typedef unsigned long long CUdeviceptr;
+
+CUresult cuInit(unsigned int flags);
+CUresult cuDeviceGet(CUdevice *device, int ordinal);
+CUresult cuDeviceGetName(char *name, int len, CUdevice dev);
+CUresult cuCtxCreate_v2(CUcontext *pctx, unsigned int flags, CUdevice dev);
+CUresult cuCtxDestroy_v2(CUcontext ctx);
+CUresult cuCtxSynchronize(void);
+CUresult cuModuleLoadData(CUmodule *module, const void *image);
+CUresult cuModuleUnload(CUmodule hmod);
+CUresult cuModuleGetFunction(CUfunction *hfunc, CUmodule hmod, const char *name);
+CUresult cuMemAlloc_v2(CUdeviceptr *dptr, size_t bytesize);
+CUresult cuMemFree_v2(CUdeviceptr dptr);
+CUresult cuMemcpyHtoD_v2(CUdeviceptr dst, const void *src, size_t bytes);
+CUresult cuMemcpyDtoH_v2(void *dst, CUdeviceptr src, size_t bytes);
+CUresult cuLaunchKernel(CUfunction f, unsigned int gx, unsigned int gy, unsigned int gz,
+                        unsigned int bx, unsigned int by, unsigned int bz,
+                        unsigned int shared_mem, CUstream stream, void **params, void **extra);
+CUresult cuGetErrorName(CUresult error, const char **name);
+
+/* c[i] = a[i] + b[i] */
+static const char PTX[] =
+    ".version 7.0\n"
+    ".target sm_50\n"
+    ".address_size 64\n"
+    ".visible .entry vector_add(.param .u64 a, .param .u64 b, .param .u64 c, .param .u32 n)\n"
+    "{\n"
+    "    .reg .pred %p<2>;\n"
+    "    .reg .f32 %f<4>;\n"
+    "    .reg .b32 %r<6>;\n"
+    "    .reg .b64 %rd<8>;\n"
+    "    ld.param.u64 %rd1, [a];\n"
+    "    ld.param.u64 %rd2, [b];\n"
+    "    ld.param.u64 %rd3, [c];\n"
+    "    ld.param.u32 %r1, [n];\n"
+    "    mov.u32 %r2, %ctaid.x;\n"
+    "    mov.u32 %r3, %ntid.x;\n"
+    "    mov.u32 %r4, %tid.x;\n"
+    "    mad.lo.s32 %r5, %r2, %r3, %r4;\n"
+    "    setp.ge.s32 %p1, %r5, %r1;\n"
+    "    @%p1 bra $done;\n"
+    "    mul.wide.u32 %rd4, %r5, 4;\n"
+    "    add.s64 %rd5, %rd1, %rd4;\n"
+    "    add.s64 %rd6, %rd2, %rd4;\n"
+    "    add.s64 %rd7, %rd3, %rd4;\n"
+    "    ld.global.f32 %f1, [%rd5];\n"
+    "    ld.global.f32 %f2, [%rd6];\n"
+    "    add.f32 %f3, %f1, %f2;\n"
+    "    st.global.f32 [%rd7], %f3;\n"
+    "$done:\n"
+    "    ret;\n"
+    "}\n";
+
+#define CHECK(call)                                                       \
+    do {                                                                  \
+        CUresult res_ = (call);                                           \
+        if (res_ != 0) {                                                  \
+            const char *name_ = "unknown";                                \
+            cuGetErrorName(res_, &name_);                                 \
+            fprintf(stderr, "%s failed: %s (%d)\n", #call, name_, res_);  \
+            return 1;                                                     \
+        }                                                                 \
+    } while (0)
+
+int main(void) {
+    enum { N = 1 << 20, BLOCK = 256 };
+    size_t bytes = N * sizeof(float);
+    float *a = malloc(bytes), *b = malloc(bytes), *c = malloc(bytes);
+    for (int i = 0; i < N; i++) {
+        a[i] = (float)i;
+        b[i] = 2.0f * (float)i;
+    }
+
+    CUdevice dev;
+    CUcontext ctx;
+    CUmodule module;
+    CUfunction kernel;
+    char name[256];
+    CHECK(cuInit(0));
+    CHECK(cuDeviceGet(&dev, 0));
+    CHECK(cuDeviceGetName(name, sizeof name, dev));
+    printf("device 0: %s\n", name);
+    CHECK(cuCtxCreate_v2(&ctx, 0, dev));
+    CHECK(cuModuleLoadData(&module, PTX));
+    CHECK(cuModuleGetFunction(&kernel, module, "vector_add"));
+
+    CUdeviceptr da, db, dc;
+    CHECK(cuMemAlloc_v2(&da, bytes));
+    CHECK(cuMemAlloc_v2(&db, bytes));
+    CHECK(cuMemAlloc_v2(&dc, bytes));
+    CHECK(cuMemcpyHtoD_v2(da, a, bytes));
+    CHECK(cuMemcpyHtoD_v2(db, b, bytes));
+
+    int n = N;
+    void *params[] = {&da, &db, &dc, &n};
+    CHECK(cuLaunchKernel(kernel, (N + BLOCK - 1) / BLOCK, 1, 1, BLOCK, 1, 1, 0, NULL, params, NULL));
+    CHECK(cuCtxSynchronize());
+    CHECK(cuMemcpyDtoH_v2(c, dc, bytes));
+
+    int errors = 0;
+    for (int i = 0; i < N; i++) {
+        if (fabsf(c[i] - 3.0f * (float)i) > 1e-3f * (float)i) {
+            errors++;
+        }
+    }
+    printf("vector_add of %d elements: %s\n", N, errors ? "FAILED" : "ok");
+
+    cuMemFree_v2(da);
+    cuMemFree_v2(db);
+    cuMemFree_v2(dc);
+    cuModuleUnload(module);
+    cuCtxDestroy_v2(ctx);
+    free(a);
+    free(b);
+    free(c);
+    return errors ? 1 : 0;
+}
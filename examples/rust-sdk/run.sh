@@ -0,0 +1,5 @@
+#!/usr/bin/env bash
+# Run the Rust SDK sample against a server (a local one unless RGPU_SERVER is set).
+source "$(dirname "$0")/../scripts/local-stack.sh"
+
+(cd "$EXAMPLES_DIR" && cargo run --release -p rust-sdk)
@@ -0,0 +1,208 @@
+//! Talking to an RGPU server from Rust, without the daemon or interposer.
+//!
+//! Connects over plain TCP, authenticates, lists the server's GPUs and runs
+//! a vector addition with `CudaCommand`s, the same messages the daemon sends
+//! on behalf of CUDA applications.
+//!
+//! Run with: RGPU_SERVER=host:port RGPU_TOKEN=... cargo run -p rust-sdk
+
+use anyhow::{bail, Context};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use rgpu_protocol::cuda_commands::{CudaCommand, CudaResponse, KernelParam};
+use rgpu_protocol::handle::NetworkHandle;
+use rgpu_protocol::messages::{Message, RequestId, PROTOCOL_VERSION};
+use rgpu_protocol::wire;
+
+/// c[i] = a[i] + b[i]
+const VECTOR_ADD_PTX: &str = r#"
+.version 7.0
+.target sm_50
+.address_size 64
+.visible .entry vector_add(.param .u64 a, .param .u64 b, .param .u64 c, .param .u32 n)
+{
+    .reg .pred %p<2>;
+    .reg .f32 %f<4>;
+    .reg .b32 %r<6>;
+    .reg .b64 %rd<8>;
+    ld.param.u64 %rd1, [a];
+    ld.param.u64 %rd2, [b];
+    ld.param.u64 %rd3, [c];
+    ld.param.u32 %r1, [n];
+    mov.u32 %r2, %ctaid.x;
+    mov.u32 %r3, %ntid.x;
+    mov.u32 %r4, %tid.x;
+    mad.lo.s32 %r5, %r2, %r3, %r4;
+    setp.ge.s32 %p1, %r5, %r1;
+    @%p1 bra $done;
+    mul.wide.u32 %rd4, %r5, 4;
+    add.s64 %rd5, %rd1, %rd4;
+    add.s64 %rd6, %rd2, %rd4;
+    add.s64 %rd7, %rd3, %rd4;
+    ld.global.f32 %f1, [%rd5];
+    ld.global.f32 %f2, [%rd6];
+    add.f32 %f3, %f1, %f2;
+    st.global.f32 [%rd7], %f3;
+$done:
+    ret;
+}
+"#;
+
+struct Connection {
+    stream: TcpStream,
+    next_request: u64,
+}
+
+impl Connection {
+    async fn send(&mut self, msg: &Message) -> anyhow::Result<()> {
+        self.stream.write_all(&wire::encode_message(msg, 0)?).await?;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> anyhow::Result<Message> {
+        let mut header = [0u8; wire::HEADER_SIZE];
+        self.stream.read_exact(&mut header).await?;
+        let (flags, _, len) = wire::decode_header(&header)?;
+        let mut payload = vec![0u8; len as usize];
+        self.stream.read_exact(&mut payload).await?;
+        Ok(wire::decode_message(&payload, flags)?)
+    }
+
+    /// Connect and authenticate with a pre-shared token.
+    async fn open(address: &str, token: &str) -> anyhow::Result<(Self, Vec<rgpu_protocol::gpu_info::GpuInfo>)> {
+        let stream = TcpStream::connect(address).await.with_context(|| format!("connecting to {}", address))?;
+        let mut conn = Connection { stream, next_request: 1 };
+        conn.send(&Message::Hello {
+            protocol_version: PROTOCOL_VERSION,
+            name: "rgpu-sdk-example".to_string(),
+            challenge: None,
+        })
+        .await?;
+        let challenge = match conn.recv().await? {
+            Message::Hello { challenge, .. } => challenge.unwrap_or_default(),
+            other => bail!("expected Hello, got {:?}", other),
+        };
+        conn.send(&Message::Authenticate {
+            token: token.to_string(),
+            challenge_response: rgpu_transport::auth::compute_challenge_response(token, &challenge),
+        })
+        .await?;
+        match conn.recv().await? {
+            Message::AuthResult { success: true, available_gpus, .. } => Ok((conn, available_gpus)),
+            Message::AuthResult { error_message, .. } => {
+                bail!("authentication failed: {}", error_message.unwrap_or_default())
+            }
+            other => bail!("expected AuthResult, got {:?}", other),
+        }
+    }
+
+    /// Run one CUDA command; driver errors become `Err`.
+    async fn cuda(&mut self, command: CudaCommand) -> anyhow::Result<CudaResponse> {
+        let request_id = RequestId(self.next_request);
+        self.next_request += 1;
+        self.send(&Message::CudaCommand { request_id, command, deadline_ms: None }).await?;
+        loop {
+            match self.recv().await? {
+                Message::CudaResponse { request_id: id, response } if id == request_id => {
+                    if let CudaResponse::Error { code, message } = response {
+                        bail!("CUDA error {}: {}", code, message);
+                    }
+                    return Ok(response);
+                }
+                // Server notices and other traffic aren't ours to handle here.
+                _ => continue,
+            }
+        }
+    }
+}
+
+fn device_ptr(handle: NetworkHandle) -> KernelParam {
+    KernelParam { data: 0u64.to_le_bytes().to_vec(), device_ptr: Some(handle) }
+}
+
+fn floats_to_bytes(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let address = std::env::var("RGPU_SERVER").unwrap_or_else(|_| "127.0.0.1:9876".to_string());
+    let token = std::env::var("RGPU_TOKEN").context("set RGPU_TOKEN to a token the server accepts")?;
+
+    let (mut conn, gpus) = Connection::open(&address, &token).await?;
+    println!("connected to {}", address);
+    for (i, gpu) in gpus.iter().enumerate() {
+        println!("  GPU {}: {} ({} MB)", i, gpu.device_name, gpu.total_memory / (1024 * 1024));
+    }
+
+    conn.cuda(CudaCommand::Init { flags: 0 }).await?;
+    let CudaResponse::Device(device) = conn.cuda(CudaCommand::DeviceGet { ordinal: 0 }).await? else {
+        bail!("unexpected DeviceGet response");
+    };
+    let CudaResponse::Context(ctx) = conn.cuda(CudaCommand::CtxCreate { flags: 0, device }).await? else {
+        bail!("unexpected CtxCreate response");
+    };
+    let mut image = VECTOR_ADD_PTX.as_bytes().to_vec();
+    image.push(0);
+    let CudaResponse::Module(module) = conn.cuda(CudaCommand::ModuleLoadData { image }).await? else {
+        bail!("unexpected ModuleLoadData response");
+    };
+    let CudaResponse::Function { handle: func, .. } = conn
+        .cuda(CudaCommand::ModuleGetFunction { module, name: "vector_add".to_string() })
+        .await?
+    else {
+        bail!("unexpected ModuleGetFunction response");
+    };
+
+    const N: usize = 1 << 16;
+    let bytes = (N * 4) as u64;
+    let a: Vec<f32> = (0..N).map(|i| i as f32).collect();
+    let b: Vec<f32> = (0..N).map(|i| 2.0 * i as f32).collect();
+    let mut buffers = Vec::new();
+    for _ in 0..3 {
+        let CudaResponse::MemAllocated(handle) = conn.cuda(CudaCommand::MemAlloc { byte_size: bytes }).await? else {
+            bail!("unexpected MemAlloc response");
+        };
+        buffers.push(handle);
+    }
+    let (da, db, dc) = (buffers[0], buffers[1], buffers[2]);
+    conn.cuda(CudaCommand::MemcpyHtoD { dst: da, src_data: floats_to_bytes(&a), byte_count: bytes }).await?;
+    conn.cuda(CudaCommand::MemcpyHtoD { dst: db, src_data: floats_to_bytes(&b), byte_count: bytes }).await?;
+
+    conn.cuda(CudaCommand::LaunchKernel {
+        func,
+        grid_dim: [(N as u32).div_ceil(256), 1, 1],
+        block_dim: [256, 1, 1],
+        shared_mem_bytes: 0,
+        stream: NetworkHandle::null_stream(),
+        kernel_params: vec![
+            device_ptr(da),
+            device_ptr(db),
+            device_ptr(dc),
+            KernelParam { data: (N as u32).to_le_bytes().to_vec(), device_ptr: None },
+        ],
+    })
+    .await?;
+    conn.cuda(CudaCommand::CtxSynchronize).await?;
+    let CudaResponse::MemoryData(data) = conn.cuda(CudaCommand::MemcpyDtoH { src: dc, byte_count: bytes }).await? else {
+        bail!("unexpected MemcpyDtoH response");
+    };
+
+    let wrong = data
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(i, c)| f32::from_le_bytes((*c).try_into().unwrap()) != a[*i] + b[*i])
+        .count();
+    println!("vector_add of {} elements: {}", N, if wrong == 0 { "ok" } else { "FAILED" });
+
+    for dptr in buffers {
+        conn.cuda(CudaCommand::MemFree { dptr }).await?;
+    }
+    conn.cuda(CudaCommand::ModuleUnload { module }).await?;
+    conn.cuda(CudaCommand::CtxDestroy { ctx }).await?;
+    if wrong != 0 {
+        bail!("{} wrong results", wrong);
+    }
+    Ok(())
+}
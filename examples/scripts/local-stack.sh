@@ -0,0 +1,65 @@
+# Sourced by the example run scripts: builds RGPU and, unless RGPU_SERVER is
+# set, starts a server and client daemon on this machine for the duration of
+# the script. Needs a machine with a GPU either way; there is no mock backend
+# yet.
+#
+#   RGPU_SERVER=host:port RGPU_TOKEN=... ./run.sh   # use an existing server
+#   RGPU_PORT=19876 ./run.sh                        # local stack on another port
+
+set -euo pipefail
+
+EXAMPLES_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")/.." && pwd)"
+REPO_DIR="$(cd "$EXAMPLES_DIR/.." && pwd)"
+TARGET_DIR="${CARGO_TARGET_DIR:-$REPO_DIR/target}/release"
+RGPU="$TARGET_DIR/rgpu"
+
+(cd "$REPO_DIR" && cargo build --release -p rgpu-cli -p rgpu-cuda-interpose -p rgpu-vk-icd)
+
+# A private runtime directory keeps the daemon socket away from any daemon
+# already running for this user.
+STACK_DIR="$(mktemp -d)"
+export XDG_RUNTIME_DIR="$STACK_DIR"
+export RGPU_NO_AUTOSTART=1
+STACK_PIDS=()
+
+stop_stack() {
+    for pid in "${STACK_PIDS[@]}"; do
+        kill "$pid" 2>/dev/null || true
+    done
+    wait 2>/dev/null || true
+    rm -rf "$STACK_DIR"
+}
+trap stop_stack EXIT
+
+if [ -z "${RGPU_SERVER:-}" ]; then
+    RGPU_PORT="${RGPU_PORT:-19876}"
+    RGPU_SERVER="127.0.0.1:$RGPU_PORT"
+    RGPU_TOKEN="$(od -An -tx1 -N32 /dev/urandom | tr -d ' \n')"
+    cat > "$STACK_DIR/server.toml" <<TOML
+[server]
+bind = "127.0.0.1"
+port = $RGPU_PORT
+
+[[security.tokens]]
+token = "$RGPU_TOKEN"
+name = "examples"
+TOML
+    "$RGPU" server --config "$STACK_DIR/server.toml" > "$STACK_DIR/server.log" 2>&1 &
+    STACK_PIDS+=($!)
+    sleep 1
+fi
+
+"$RGPU" client --server "$RGPU_SERVER" --token "${RGPU_TOKEN:?set RGPU_TOKEN for RGPU_SERVER}" \
+    > "$STACK_DIR/client.log" 2>&1 &
+STACK_PIDS+=($!)
+
+for _ in $(seq 50); do
+    [ -S "$XDG_RUNTIME_DIR/rgpu.sock" ] && break
+    sleep 0.2
+done
+if [ ! -S "$XDG_RUNTIME_DIR/rgpu.sock" ]; then
+    echo "client daemon did not start:" >&2
+    cat "$STACK_DIR"/*.log >&2
+    exit 1
+fi
+export RGPU_SERVER RGPU_TOKEN
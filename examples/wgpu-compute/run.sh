@@ -0,0 +1,6 @@
+#!/usr/bin/env bash
+# Build and run the wgpu compute sample on a remote GPU.
+source "$(dirname "$0")/../scripts/local-stack.sh"
+
+(cd "$EXAMPLES_DIR" && cargo build --release -p wgpu-compute)
+"$RGPU" shell --run "$EXAMPLES_DIR/target/release/wgpu-compute"
@@ -0,0 +1,118 @@
+//! A wgpu compute pass on a remote GPU through the RGPU Vulkan ICD.
+//!
+//! Doubles a buffer of integers in a compute shader and checks the result.
+//! Only the Vulkan backend is enabled, so with `VK_ICD_FILENAMES` pointing at
+//! the RGPU manifest (`rgpu shell` sets it) the adapter is a remote GPU.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use wgpu::util::DeviceExt;
+
+const SHADER: &str = r#"
+@group(0) @binding(0) var<storage, read_write> data: array<u32>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x < arrayLength(&data)) {
+        data[id.x] = data[id.x] * 2u;
+    }
+}
+"#;
+
+const COUNT: u32 = 1 << 16;
+
+/// Wakes the thread blocked in [`block_on`].
+struct ThreadWaker(std::thread::Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run a future to completion on this thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::VULKAN,
+        ..Default::default()
+    });
+    let adapter = block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+        .ok_or("no Vulkan adapter; is VK_ICD_FILENAMES set?")?;
+    println!("adapter: {}", adapter.get_info().name);
+    let (device, queue) = block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))?;
+
+    let input: Vec<u32> = (0..COUNT).collect();
+    let bytes: Vec<u8> = input.iter().flat_map(|v| v.to_le_bytes()).collect();
+    let storage = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("data"),
+        contents: &bytes,
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+    });
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback"),
+        size: bytes.len() as u64,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("double"),
+        source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+    });
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("double"),
+        layout: None,
+        module: &module,
+        entry_point: Some("main"),
+        compilation_options: Default::default(),
+        cache: None,
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.get_bind_group_layout(0),
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: storage.as_entire_binding(),
+        }],
+    });
+
+    let mut encoder = device.create_command_encoder(&Default::default());
+    {
+        let mut pass = encoder.begin_compute_pass(&Default::default());
+        pass.set_pipeline(&pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.dispatch_workgroups(COUNT.div_ceil(64), 1, 1);
+    }
+    encoder.copy_buffer_to_buffer(&storage, 0, &readback, 0, bytes.len() as u64);
+    queue.submit([encoder.finish()]);
+
+    readback.slice(..).map_async(wgpu::MapMode::Read, |result| result.expect("map readback buffer"));
+    device.poll(wgpu::Maintain::Wait);
+    let output: Vec<u32> = readback
+        .slice(..)
+        .get_mapped_range()
+        .chunks_exact(4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .collect();
+
+    let wrong = input.iter().zip(&output).filter(|(i, o)| **i * 2 != **o).count();
+    println!("doubled {} values: {}", COUNT, if wrong == 0 { "ok" } else { "FAILED" });
+    if wrong != 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -42,7 +42,7 @@ fi
 # Step 2: Verify artifacts
 echo "[2/4] Verifying build artifacts..."
 MISSING=false
-for artifact in rgpu librgpu_cuda_interpose.so librgpu_vk_icd.so; do
+for artifact in rgpu librgpu_cuda_interpose.so librgpu_nvml_interpose.so librgpu_cudart_interpose.so librgpu_vk_icd.so; do
     if [ -f "$PROJECT_ROOT/target/release/${artifact}" ]; then
         SIZE=$(du -h "$PROJECT_ROOT/target/release/${artifact}" | cut -f1)
         echo "  Found: ${artifact} (${SIZE})"